@@ -0,0 +1,233 @@
+//! An in-memory queue broker built on the listener API
+//!
+//! Messages are routed by the target address of the producer links into per-address queues and
+//! delivered to the consumer links attached with the same source address.
+
+use std::sync::Arc;
+
+use fe2o3_amqp::{
+    acceptor::{
+        link::{LinkAcceptor, LinkEndpoint},
+        session::{ListenerSessionHandle, SessionAcceptor},
+        ConnectionAcceptor, ListenerConnectionHandle, SaslAcceptor, SaslAnonymousMechanism,
+        SaslPlainMechanism,
+    },
+    link::{receiver::CreditMode, RecvError},
+    types::{
+        definitions::{self, AmqpError},
+        messaging::Body,
+        primitives::Value,
+    },
+    Receiver, SendReceipt, Sender,
+};
+use tokio::net::TcpListener;
+
+mod queue;
+
+use queue::{Queue, Queues};
+
+/// Max number of messages held by each queue
+const QUEUE_CAPACITY: usize = 100;
+
+#[tokio::main]
+async fn main() {
+    let tcp_listener = TcpListener::bind("localhost:5672").await.unwrap();
+    let queues = Arc::new(Queues::new(QUEUE_CAPACITY));
+
+    let username = std::env::var("BROKER_USERNAME").ok();
+    let password = std::env::var("BROKER_PASSWORD").ok();
+    match (username, password) {
+        (Some(username), Some(password)) => {
+            let sasl_acceptor = SaslPlainMechanism::new(username, password);
+            serve(tcp_listener, sasl_acceptor, queues).await
+        }
+        _ => serve(tcp_listener, SaslAnonymousMechanism::new(), queues).await,
+    }
+}
+
+async fn serve<S>(tcp_listener: TcpListener, sasl_acceptor: S, queues: Arc<Queues>)
+where
+    S: SaslAcceptor + Send + Sync + 'static,
+{
+    let connection_acceptor = ConnectionAcceptor::builder()
+        .container_id("broker")
+        .sasl_acceptor(sasl_acceptor)
+        .build();
+
+    while let Ok((stream, addr)) = tcp_listener.accept().await {
+        println!("Incoming connection from {:?}", addr);
+        match connection_acceptor.accept(stream).await {
+            Ok(connection) => {
+                tokio::spawn(connection_main(connection, queues.clone()));
+            }
+            Err(error) => println!("Failed to open connection: {:?}", error),
+        }
+    }
+}
+
+async fn connection_main(mut connection: ListenerConnectionHandle, queues: Arc<Queues>) {
+    let session_acceptor = SessionAcceptor::new();
+    while let Ok(session) = session_acceptor.accept(&mut connection).await {
+        tokio::spawn(session_main(session, queues.clone()));
+    }
+
+    // The close from the client is answered by the connection
+    if let Err(error) = connection.on_close().await {
+        println!("Connection closed with error: {:?}", error);
+    }
+}
+
+async fn session_main(mut session: ListenerSessionHandle, queues: Arc<Queues>) {
+    // Credit is issued by `producer_main` according to the room left in the queue
+    let link_acceptor = LinkAcceptor::builder()
+        .credit_mode(CreditMode::Manual)
+        .build();
+
+    while let Ok(link) = link_acceptor.accept(&mut session).await {
+        match link {
+            LinkEndpoint::Receiver(receiver) => {
+                tokio::spawn(producer_main(receiver, queues.clone()));
+            }
+            LinkEndpoint::Sender(sender) => {
+                tokio::spawn(consumer_main(sender, queues.clone()));
+            }
+        }
+    }
+
+    if let Err(error) = session.on_end().await {
+        println!("Session ended with error: {:?}", error);
+    }
+}
+
+/// Moves the messages sent by a producer into the queue at the target address
+async fn producer_main(mut receiver: Receiver, queues: Arc<Queues>) {
+    let address = receiver
+        .target()
+        .as_ref()
+        .and_then(|target| target.address.clone());
+    let queue = match address {
+        Some(address) => queues.get_or_create(&address),
+        None => {
+            let error = definitions::Error::new(
+                AmqpError::InvalidField,
+                Some("A target address is required".to_string()),
+                None,
+            );
+            let _ = receiver.close_with_error(error).await;
+            return;
+        }
+    };
+
+    loop {
+        // The link has no credit while waiting, so `recv` only returns if the client detaches
+        let room = tokio::select! {
+            room = queue.wait_for_room() => room,
+            result = receiver.recv::<Body<Value>>() => match result {
+                Ok(delivery) => {
+                    queue.push(delivery.message().clone());
+                    if receiver.accept(&delivery).await.is_err() {
+                        break;
+                    }
+                    continue;
+                }
+                Err(error) => {
+                    on_recv_error(receiver, error).await;
+                    return;
+                }
+            },
+        };
+
+        if receiver.set_credit(room as u32).await.is_err() {
+            break;
+        }
+        for _ in 0..room {
+            let delivery = match receiver.recv::<Body<Value>>().await {
+                Ok(delivery) => delivery,
+                Err(error) => {
+                    on_recv_error(receiver, error).await;
+                    return;
+                }
+            };
+            queue.push(delivery.message().clone());
+            if receiver.accept(&delivery).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    let _ = receiver.close().await;
+}
+
+async fn on_recv_error(mut receiver: Receiver, error: RecvError) {
+    match error {
+        // The detach from the client is already answered by the receiver
+        RecvError::LinkStateError(_) => {}
+        error => {
+            println!("Closing producer link: {:?}", error);
+            let _ = receiver.close().await;
+        }
+    }
+}
+
+/// Delivers the messages in the queue at the source address to a consumer
+async fn consumer_main(mut sender: Sender, queues: Arc<Queues>) {
+    let address = sender
+        .source()
+        .as_ref()
+        .and_then(|source| source.address.clone());
+    let queue = match address {
+        Some(address) => queues.get_or_create(&address),
+        None => {
+            let error = definitions::Error::new(
+                AmqpError::InvalidField,
+                Some("A source address is required".to_string()),
+                None,
+            );
+            let _ = sender.close_with_error(error).await;
+            return;
+        }
+    };
+
+    loop {
+        let message = tokio::select! {
+            message = queue.pop() => message,
+            // The detach from the client is answered by closing the link below
+            _ = sender.on_detach() => break,
+        };
+
+        if !deliver(&mut sender, &queue, message).await {
+            break;
+        }
+    }
+
+    let _ = sender.close().await;
+}
+
+/// Sends a message and waits for its outcome. Returns `false` if the link cannot be used anymore
+async fn deliver(sender: &mut Sender, queue: &Queue, mut message: queue::AnyMessage) -> bool {
+    match sender.send(message.clone()).await {
+        Ok(SendReceipt::Settled) | Ok(SendReceipt::Accepted(_)) => true,
+        Ok(SendReceipt::Rejected(rejected)) => {
+            // A broker would usually move the message to a dead letter queue
+            println!("Message rejected: {:?}", rejected.error);
+            true
+        }
+        Ok(SendReceipt::Released(_)) => {
+            queue.push_front(message);
+            true
+        }
+        Ok(SendReceipt::Modified(modified)) => {
+            // The annotations of the outcome, such as a retry count, are carried by the next
+            // delivery of the message
+            message.apply_modified(&modified);
+            queue.push_front(message);
+            true
+        }
+        Err(error) => {
+            // The outcome is unknown, so the message is delivered again
+            println!("Failed to deliver message: {:?}", error);
+            queue.push_front(message);
+            false
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use fe2o3_amqp::types::{
+    messaging::{Body, Message},
+    primitives::Value,
+};
+use tokio::sync::Notify;
+
+/// A message with any body section
+pub type AnyMessage = Message<Body<Value>>;
+
+/// A bounded in-memory queue
+#[derive(Debug)]
+pub struct Queue {
+    capacity: usize,
+    messages: Mutex<VecDeque<AnyMessage>>,
+    pushed: Notify,
+    popped: Notify,
+}
+
+impl Queue {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            messages: Mutex::new(VecDeque::new()),
+            pushed: Notify::new(),
+            popped: Notify::new(),
+        }
+    }
+
+    /// Number of messages that can be pushed before the queue is full
+    pub fn room(&self) -> usize {
+        let depth = self.messages.lock().unwrap().len();
+        self.capacity.saturating_sub(depth)
+    }
+
+    /// Waits until the queue is not full and returns the room left
+    pub async fn wait_for_room(&self) -> usize {
+        loop {
+            // The future is created before checking so that a pop in between is not missed
+            let popped = self.popped.notified();
+            match self.room() {
+                0 => popped.await,
+                room => return room,
+            }
+        }
+    }
+
+    /// Pushes a message to the back of the queue
+    pub fn push(&self, message: AnyMessage) {
+        self.messages.lock().unwrap().push_back(message);
+        self.pushed.notify_waiters();
+    }
+
+    /// Puts back a message that was not taken by a consumer so that it is delivered first
+    pub fn push_front(&self, message: AnyMessage) {
+        self.messages.lock().unwrap().push_front(message);
+        self.pushed.notify_waiters();
+    }
+
+    /// Waits for a message.
+    ///
+    /// This is cancel safe. A message is only taken from the queue when it is returned
+    pub async fn pop(&self) -> AnyMessage {
+        loop {
+            let pushed = self.pushed.notified();
+            let message = self.messages.lock().unwrap().pop_front();
+            match message {
+                Some(message) => {
+                    self.popped.notify_waiters();
+                    return message;
+                }
+                None => pushed.await,
+            }
+        }
+    }
+}
+
+/// Queues by address. A queue is created when a link is attached to a new address
+#[derive(Debug)]
+pub struct Queues {
+    capacity: usize,
+    queues: Mutex<HashMap<String, Arc<Queue>>>,
+}
+
+impl Queues {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            queues: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get_or_create(&self, address: &str) -> Arc<Queue> {
+        let mut queues = self.queues.lock().unwrap();
+        queues
+            .entry(address.to_string())
+            .or_insert_with(|| Arc::new(Queue::new(self.capacity)))
+            .clone()
+    }
+}
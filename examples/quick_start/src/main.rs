@@ -1,5 +1,5 @@
 use fe2o3_amqp::{Connection, Session, Sender, Receiver};
-use fe2o3_amqp::types::messaging::Outcome;
+use fe2o3_amqp::SendReceipt;
 
 #[tokio::main]
 async fn main() {
@@ -25,13 +25,13 @@ async fn main() {
     ).await.unwrap();
 
     // Send a message to the broker and wait for outcome (Disposition)
-    let outcome: Outcome = sender.send("hello AMQP").await.unwrap();
-    outcome.accepted_or_else(|state| state).unwrap(); // Handle delivery outcome
+    let receipt: SendReceipt = sender.send("hello AMQP").await.unwrap();
+    receipt.accepted_or_else(|receipt| receipt).unwrap(); // Handle delivery outcome
 
     // Send a message with batchable field set to true
     let fut = sender.send_batchable("hello batchable AMQP").await.unwrap();
-    let outcome: Outcome = fut.await.unwrap(); // Wait for outcome (Disposition)
-    outcome.accepted_or_else(|state| state).unwrap(); // Handle delivery outcome
+    let receipt: SendReceipt = fut.await.unwrap(); // Wait for outcome (Disposition)
+    receipt.accepted_or_else(|receipt| receipt).unwrap(); // Handle delivery outcome
 
     // Receive the message from the broker
     let delivery = receiver.recv::<String>().await.unwrap();
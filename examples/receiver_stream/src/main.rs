@@ -0,0 +1,64 @@
+//! Consumes two receivers as one stream and processes up to `MAX_IN_FLIGHT` deliveries
+//! concurrently
+
+use std::time::Duration;
+
+use fe2o3_amqp::{
+    connection::Connection, link::Receiver, session::Session, types::primitives::Value, Delivery,
+};
+use futures::{stream, StreamExt};
+
+const MAX_IN_FLIGHT: usize = 8;
+
+async fn process(delivery: Delivery<Value>) {
+    // Simulate some work
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    println!("Processed: {:?}", delivery.body());
+}
+
+#[tokio::main]
+async fn main() {
+    let mut connection = Connection::open("connection-1", "amqp://localhost:5672")
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+
+    // The deliveries are accepted as they arrive because the streams own the receivers
+    let receiver1 = Receiver::builder()
+        .name("rust-recver-1")
+        .source("q1")
+        .auto_accept(true)
+        .attach(&mut session)
+        .await
+        .unwrap();
+    let receiver2 = Receiver::builder()
+        .name("rust-recver-2")
+        .source("q2")
+        .auto_accept(true)
+        .attach(&mut session)
+        .await
+        .unwrap();
+
+    // Both streams end when their links are detached by the broker
+    stream::select(
+        receiver1.into_stream::<Value>(),
+        receiver2.into_stream::<Value>(),
+    )
+    .filter_map(|result| async move {
+        match result {
+            Ok(delivery) => Some(delivery),
+            Err(err) => {
+                eprintln!("Receive error: {:?}", err);
+                None
+            }
+        }
+    })
+    // No more deliveries are taken from the streams while `MAX_IN_FLIGHT` are being processed
+    .map(process)
+    .buffer_unordered(MAX_IN_FLIGHT)
+    .collect::<()>()
+    .await;
+
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
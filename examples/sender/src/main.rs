@@ -1,4 +1,4 @@
-use fe2o3_amqp::{connection::Connection, session::Session, Sender};
+use fe2o3_amqp::{connection::Connection, session::Session, SendReceipt, Sender};
 
 #[tokio::main]
 async fn main() {
@@ -10,8 +10,14 @@ async fn main() {
         .await
         .unwrap();
 
-    let outcome = sender.send("hello AMQP").await.unwrap();
-    outcome.accepted_or_else(|outcome| outcome).unwrap();
+    let receipt = sender.send("hello AMQP").await.unwrap();
+    match receipt {
+        SendReceipt::Accepted(_) => println!("Message accepted"),
+        SendReceipt::Settled => println!("Message settled by the sender"),
+        SendReceipt::Rejected(rejected) => println!("Message rejected: {:?}", rejected.error),
+        SendReceipt::Released(_) => println!("Message released"),
+        SendReceipt::Modified(modified) => println!("Message modified: {:?}", modified),
+    }
 
     sender.close().await.unwrap();
     session.end().await.unwrap();
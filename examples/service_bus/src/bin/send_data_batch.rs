@@ -1,27 +1,25 @@
 use std::env;
 
 use dotenv::dotenv;
-use fe2o3_amqp::{Connection, sasl_profile::SaslProfile, Session, Sender, types::{primitives::Binary, messaging::{Message, Data}}};
+use fe2o3_amqp::{Connection, connection::azure::{self, AzureCredential}, Session, Sender, types::{primitives::Binary, messaging::{Message, Data}}};
 
 #[tokio::main]
 async fn main() {
     dotenv().ok();
 
     let hostname = env::var("HOST_NAME").unwrap();
-    let port = 5671;
     let sa_key_name = env::var("SHARED_ACCESS_KEY_NAME").unwrap();
     let sa_key_value = env::var("SHARED_ACCESS_KEY_VALUE").unwrap();
     let queue_name = "q1";
 
-    let url = format!("amqps://{}:{}", hostname, port);
+    let credential = AzureCredential::SharedAccessKey {
+        key_name: sa_key_name,
+        key: sa_key_value,
+    };
+    // The preset uses the alternative TLS establishment that ServiceBus requires
     let mut connection = Connection::builder()
-        .container_id("rust-connection-1")
-        .alt_tls_establishment(true) // ServiceBus uses alternative TLS establishment
-        .sasl_profile(SaslProfile::Plain {
-            username: sa_key_name,
-            password: sa_key_value,
-        })
-        .open(&url[..])
+        .service_bus(&hostname, credential)
+        .open(azure::url(&hostname).unwrap())
         .await
         .unwrap();
     let mut session = Session::begin(&mut connection).await.unwrap();
@@ -0,0 +1,245 @@
+//! A listener that emulates how a broker handles topic subscriptions
+//!
+//! - A consumer link whose source has the `topic` capability subscribes to the topic at the source
+//!   address, and the subscription is identified by the link name
+//! - A durable subscription is kept when the link is detached, and attaching with the same link
+//!   name resumes it
+//! - A closing detach deletes the subscription
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use fe2o3_amqp::{
+    acceptor::{
+        link::{LinkAcceptor, LinkEndpoint},
+        session::{ListenerSessionHandle, SessionAcceptor},
+        ConnectionAcceptor, ListenerConnectionHandle,
+    },
+    link::{
+        subscription::{GLOBAL, SHARED, TOPIC},
+        DetachError, LinkStateError, SendError,
+    },
+    types::{
+        definitions::{self, AmqpError},
+        messaging::{Source, TerminusDurability},
+        primitives::Symbol,
+    },
+    Receiver, Sender,
+};
+use tokio::{net::TcpListener, sync::Notify};
+
+/// The messages published to a topic since the subscription was created
+#[derive(Debug)]
+struct Subscription {
+    topic: String,
+    messages: Mutex<VecDeque<String>>,
+    published: Notify,
+}
+
+impl Subscription {
+    fn push(&self, message: String) {
+        self.messages.lock().unwrap().push_back(message);
+        self.published.notify_one();
+    }
+
+    fn push_front(&self, message: String) {
+        self.messages.lock().unwrap().push_front(message);
+        self.published.notify_one();
+    }
+
+    /// Waits for the next message. This is cancel safe
+    async fn pop(&self) -> String {
+        loop {
+            let published = self.published.notified();
+            if let Some(message) = self.messages.lock().unwrap().pop_front() {
+                return message;
+            }
+            published.await;
+        }
+    }
+}
+
+/// The subscriptions by link name
+#[derive(Debug, Default)]
+pub struct Topics {
+    subscriptions: Mutex<HashMap<String, Arc<Subscription>>>,
+}
+
+impl Topics {
+    /// Creates the subscription or resumes the existing one with the same name
+    fn subscribe(&self, name: &str, topic: &str) -> Arc<Subscription> {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        match subscriptions.get(name) {
+            Some(subscription) => {
+                println!("[broker] Resumed subscription {:?}", name);
+                subscription.clone()
+            }
+            None => {
+                println!("[broker] Created subscription {:?} to {:?}", name, topic);
+                let subscription = Arc::new(Subscription {
+                    topic: topic.to_string(),
+                    messages: Mutex::new(VecDeque::new()),
+                    published: Notify::new(),
+                });
+                subscriptions.insert(name.to_string(), subscription.clone());
+                subscription
+            }
+        }
+    }
+
+    fn unsubscribe(&self, name: &str) {
+        if self.subscriptions.lock().unwrap().remove(name).is_some() {
+            println!("[broker] Deleted subscription {:?}", name);
+        }
+    }
+
+    /// Copies the message into every subscription to the topic
+    fn publish(&self, topic: &str, message: String) {
+        for subscription in self.subscriptions.lock().unwrap().values() {
+            if subscription.topic == topic {
+                subscription.push(message.clone());
+            }
+        }
+    }
+}
+
+pub async fn serve(tcp_listener: TcpListener, topics: Arc<Topics>) {
+    let connection_acceptor = ConnectionAcceptor::new("topic-broker");
+
+    while let Ok((stream, _)) = tcp_listener.accept().await {
+        match connection_acceptor.accept(stream).await {
+            Ok(connection) => {
+                tokio::spawn(connection_main(connection, topics.clone()));
+            }
+            Err(error) => println!("[broker] Failed to open connection: {:?}", error),
+        }
+    }
+}
+
+async fn connection_main(mut connection: ListenerConnectionHandle, topics: Arc<Topics>) {
+    let session_acceptor = SessionAcceptor::new();
+    while let Ok(session) = session_acceptor.accept(&mut connection).await {
+        tokio::spawn(session_main(session, topics.clone()));
+    }
+    let _ = connection.on_close().await;
+}
+
+async fn session_main(mut session: ListenerSessionHandle, topics: Arc<Topics>) {
+    let link_acceptor = LinkAcceptor::builder()
+        .source_capabilities(vec![
+            Symbol::from(TOPIC),
+            Symbol::from(SHARED),
+            Symbol::from(GLOBAL),
+        ])
+        .build();
+
+    // The acceptor replaces the capabilities of the source with its own, so the topic is taken
+    // from the source requested in the incoming Attach
+    while let Some(attach) = session.next_incoming_attach().await {
+        let requested = topic_of(&attach.source);
+        match link_acceptor
+            .accept_incoming_attach(attach, &mut session)
+            .await
+        {
+            Ok(LinkEndpoint::Receiver(receiver)) => {
+                tokio::spawn(publisher_main(receiver, topics.clone()));
+            }
+            Ok(LinkEndpoint::Sender(sender)) => {
+                tokio::spawn(subscriber_main(sender, requested, topics.clone()));
+            }
+            Err(error) => println!("[broker] Failed to accept link: {:?}", error),
+        }
+    }
+
+    let _ = session.on_end().await;
+}
+
+/// Publishes the messages sent to the target address to the subscriptions of the topic
+async fn publisher_main(mut receiver: Receiver, topics: Arc<Topics>) {
+    let topic = receiver
+        .target()
+        .as_ref()
+        .and_then(|target| target.address.clone())
+        .unwrap_or_default();
+
+    while let Ok(delivery) = receiver.recv::<String>().await {
+        topics.publish(&topic, delivery.body().clone());
+        if receiver.accept(&delivery).await.is_err() {
+            break;
+        }
+    }
+    let _ = receiver.close().await;
+}
+
+/// Returns the topic if the source has the `topic` capability
+fn topic_of(source: &Option<Box<Source>>) -> Option<(String, bool)> {
+    let source = source.as_ref()?;
+    let capabilities = source.capabilities.as_ref()?;
+    if !capabilities
+        .0
+        .iter()
+        .any(|capability| capability.as_str() == TOPIC)
+    {
+        return None;
+    }
+    let durable = source.durable != TerminusDurability::None;
+    Some((source.address.clone()?, durable))
+}
+
+/// Delivers the messages of the subscription identified by the link name
+async fn subscriber_main(
+    mut sender: Sender,
+    requested: Option<(String, bool)>,
+    topics: Arc<Topics>,
+) {
+    let (topic, durable) = match requested {
+        Some(topic) => topic,
+        None => {
+            let error = definitions::Error::new(
+                AmqpError::NotImplemented,
+                Some("Only topic subscriptions are supported".to_string()),
+                None,
+            );
+            let _ = sender.close_with_error(error).await;
+            return;
+        }
+    };
+    let name = sender.name().to_string();
+    let subscription = topics.subscribe(&name, &topic);
+
+    loop {
+        let message = tokio::select! {
+            message = subscription.pop() => message,
+            error = sender.on_detach() => {
+                match error {
+                    DetachError::DetachedByRemote => {
+                        let _ = sender.detach().await;
+                        if !durable {
+                            topics.unsubscribe(&name);
+                        }
+                    }
+                    _ => {
+                        let _ = sender.close().await;
+                        topics.unsubscribe(&name);
+                    }
+                }
+                return;
+            }
+        };
+
+        // The detach that arrives while waiting for credit is answered by `send`
+        match sender.send(message.clone()).await {
+            Ok(_) => {}
+            Err(SendError::LinkStateError(LinkStateError::RemoteDetached)) if durable => {
+                subscription.push_front(message);
+                return;
+            }
+            Err(_) => {
+                topics.unsubscribe(&name);
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! Subscribes to a topic with a durable shared subscription
+//!
+//! The subscription runs against a listener in the same process that emulates how a broker
+//! handles topic subscriptions (see `broker.rs`).
+
+use std::sync::Arc;
+
+use fe2o3_amqp::{link::Subscription, Connection, Receiver, Sender, Session};
+use tokio::net::TcpListener;
+
+mod broker;
+
+use broker::Topics;
+
+#[tokio::main]
+async fn main() {
+    let tcp_listener = TcpListener::bind("localhost:0").await.unwrap();
+    let addr = tcp_listener.local_addr().unwrap();
+    tokio::spawn(broker::serve(tcp_listener, Arc::new(Topics::default())));
+
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("topic-subscription-example", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut publisher = Sender::attach(&mut session, "publisher", "prices")
+        .await
+        .unwrap();
+
+    let subscription = Subscription::builder()
+        .topic("prices")
+        .shared(true)
+        .durable(true)
+        .subscription_name("worker-group")
+        .build()
+        .unwrap();
+
+    let mut receiver = subscription.attach(&mut session).await.unwrap();
+    println!("Attached link {:?}", receiver.name());
+    publisher.send("price-1").await.unwrap();
+    receive(&mut receiver).await;
+
+    // The durable subscription keeps the messages published while the link is detached
+    receiver.detach().await.unwrap();
+    publisher.send("price-2").await.unwrap();
+    publisher.send("price-3").await.unwrap();
+
+    // Attaching with the same link name resumes the subscription
+    let mut receiver = subscription.attach(&mut session).await.unwrap();
+    receive(&mut receiver).await;
+    receive(&mut receiver).await;
+    receiver.detach().await.unwrap();
+
+    // Deletes the subscription, so the message below is not kept for it
+    subscription.unsubscribe(&mut session).await.unwrap();
+    publisher.send("price-4").await.unwrap();
+
+    publisher.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
+
+async fn receive(receiver: &mut Receiver) {
+    let delivery = receiver.recv::<String>().await.unwrap();
+    receiver.accept(&delivery).await.unwrap();
+    println!("Received {:?}", delivery.body());
+}
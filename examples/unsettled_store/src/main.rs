@@ -0,0 +1,98 @@
+//! Persists the unsettled map of a receiver to the file system so that the deliveries left
+//! unsettled by a previous run can be resumed after the process restarts
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use fe2o3_amqp::{
+    connection::Connection,
+    link::{
+        unsettled_store::{StoredUnsettledMap, UnsettledStore},
+        Receiver,
+    },
+    session::Session,
+    types::primitives::Value,
+    Delivery,
+};
+
+/// Stores the unsettled map of each link in its own file under `dir`
+#[derive(Debug)]
+struct FileUnsettledStore {
+    dir: PathBuf,
+}
+
+impl FileUnsettledStore {
+    fn new(dir: impl AsRef<Path>) -> std::io::Result<Self> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+        })
+    }
+
+    fn path(&self, link_name: &str) -> PathBuf {
+        let file_name: String = link_name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        self.dir.join(file_name)
+    }
+}
+
+impl UnsettledStore for FileUnsettledStore {
+    fn load(&self, link_name: &str) -> StoredUnsettledMap {
+        let buf = match fs::read(self.path(link_name)) {
+            Ok(buf) => buf,
+            Err(_) => return StoredUnsettledMap::new(),
+        };
+        serde_amqp::from_slice(&buf).unwrap_or_else(|err| {
+            eprintln!("Failed to decode unsettled map of {link_name}: {err}");
+            StoredUnsettledMap::new()
+        })
+    }
+
+    fn save(&self, link_name: &str, map: &StoredUnsettledMap) {
+        let path = self.path(link_name);
+        let result = match map.is_empty() {
+            true => fs::remove_file(&path).or_else(|err| match err.kind() {
+                std::io::ErrorKind::NotFound => Ok(()),
+                _ => Err(err),
+            }),
+            false => serde_amqp::to_vec(map)
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+                .and_then(|buf| fs::write(&path, buf)),
+        };
+        if let Err(err) = result {
+            eprintln!("Failed to save unsettled map of {link_name}: {err}");
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let store = FileUnsettledStore::new("unsettled").unwrap();
+
+    let mut connection = Connection::open("connection-1", "amqp://localhost:5672")
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+
+    // Deliveries that were left unsettled by a previous run are offered to the broker in the
+    // Attach frame
+    let mut receiver = Receiver::builder()
+        .name("rust-recver-1")
+        .source("q1")
+        .unsettled_store(store)
+        .attach(&mut session)
+        .await
+        .unwrap();
+
+    let delivery: Delivery<Value> = receiver.recv().await.unwrap();
+    println!("Received: {:?}", delivery.body());
+    receiver.accept(&delivery).await.unwrap();
+
+    receiver.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
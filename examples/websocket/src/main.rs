@@ -1,6 +1,5 @@
 use fe2o3_amqp::{
-    types::{messaging::Outcome, primitives::Value},
-    Connection, Delivery, Receiver, Sender, Session,
+    types::primitives::Value, Connection, Delivery, Receiver, SendReceipt, Sender, Session,
 };
 use fe2o3_amqp_ws::WebSocketStream;
 
@@ -29,8 +28,8 @@ async fn main() {
     println!("{:?}", delivery.body());
     receiver.accept(&delivery).await.unwrap();
 
-    let outcome: Outcome = fut.await.unwrap();
-    outcome.accepted_or_else(|state| state).unwrap(); // Handle delivery outcome
+    let receipt: SendReceipt = fut.await.unwrap();
+    receipt.accepted_or_else(|receipt| receipt).unwrap(); // Handle delivery outcome
 
     sender.close().await.unwrap();
     receiver.close().await.unwrap();
@@ -3,3 +3,4 @@
 //! Extensions to `fe2o3-amqp`
 
 pub mod filters;
+pub mod service_bus;
@@ -0,0 +1,266 @@
+//! Typed access to the message annotations used by Azure Service Bus
+//!
+//! The accessors are implemented purely over [`Message::message_annotations`] and do not require
+//! any broker specific type. A received `Delivery` exposes its message with `delivery.message()`.
+//!
+//! # Example
+//!
+//! ```rust
+//! use fe2o3_amqp_ext::service_bus::{ServiceBusMessageBuilderExt, ServiceBusMessageExt};
+//! use fe2o3_amqp_types::{messaging::Message, primitives::Timestamp};
+//!
+//! let message = Message::builder()
+//!     .scheduled_enqueue_time(Timestamp::from_milliseconds(1_697_123_456_789))
+//!     .partition_key("session-1")
+//!     .value("hello")
+//!     .build();
+//!
+//! assert_eq!(
+//!     message.scheduled_enqueue_time(),
+//!     Some(Timestamp::from_milliseconds(1_697_123_456_789))
+//! );
+//! assert_eq!(message.partition_key(), Some("session-1"));
+//! assert_eq!(message.sequence_number(), None);
+//! ```
+
+use fe2o3_amqp_types::{
+    messaging::{
+        annotations::{AnnotationKey, OwnedKey},
+        message::Builder,
+        Message, MessageAnnotations,
+    },
+    primitives::{Timestamp, Value},
+};
+
+/// The UTC time at which the message was accepted and stored by the broker
+pub const ENQUEUED_TIME: &str = "x-opt-enqueued-time";
+
+/// The unique number assigned to the message by the broker
+pub const SEQUENCE_NUMBER: &str = "x-opt-sequence-number";
+
+/// The original sequence number of a message that was auto-forwarded
+pub const ENQUEUE_SEQUENCE_NUMBER: &str = "x-opt-enqueue-sequence-number";
+
+/// The UTC time until which the message is locked in the queue or subscription
+pub const LOCKED_UNTIL: &str = "x-opt-locked-until";
+
+/// The key used to route the message to a partition
+pub const PARTITION_KEY: &str = "x-opt-partition-key";
+
+/// The UTC time at which a scheduled message is made available to receivers
+pub const SCHEDULED_ENQUEUE_TIME: &str = "x-opt-scheduled-enqueue-time";
+
+/// The name of the queue or subscription that the message was dead-lettered from
+pub const DEADLETTER_SOURCE: &str = "x-opt-deadletter-source";
+
+fn annotation<'a>(
+    message_annotations: Option<&'a MessageAnnotations>,
+    key: &str,
+) -> Option<&'a Value> {
+    message_annotations?.get(&key as &dyn AnnotationKey)
+}
+
+fn timestamp(value: Option<&Value>) -> Option<Timestamp> {
+    match value? {
+        Value::Timestamp(timestamp) => Some(timestamp.clone()),
+        _ => None,
+    }
+}
+
+fn long(value: Option<&Value>) -> Option<i64> {
+    match value? {
+        Value::Long(value) => Some(*value),
+        _ => None,
+    }
+}
+
+fn string(value: Option<&Value>) -> Option<&str> {
+    match value? {
+        Value::String(value) => Some(value),
+        _ => None,
+    }
+}
+
+/// Typed getters for the message annotations set by Azure Service Bus
+///
+/// A getter returns `None` if the annotation is absent or is not of the expected type.
+pub trait ServiceBusMessageExt {
+    /// Get the message annotations
+    fn service_bus_annotations(&self) -> Option<&MessageAnnotations>;
+
+    /// Get `x-opt-enqueued-time`
+    fn enqueued_time(&self) -> Option<Timestamp> {
+        timestamp(annotation(self.service_bus_annotations(), ENQUEUED_TIME))
+    }
+
+    /// Get `x-opt-sequence-number`
+    fn sequence_number(&self) -> Option<i64> {
+        long(annotation(self.service_bus_annotations(), SEQUENCE_NUMBER))
+    }
+
+    /// Get `x-opt-enqueue-sequence-number`
+    fn enqueue_sequence_number(&self) -> Option<i64> {
+        long(annotation(
+            self.service_bus_annotations(),
+            ENQUEUE_SEQUENCE_NUMBER,
+        ))
+    }
+
+    /// Get `x-opt-locked-until`
+    fn locked_until(&self) -> Option<Timestamp> {
+        timestamp(annotation(self.service_bus_annotations(), LOCKED_UNTIL))
+    }
+
+    /// Get `x-opt-partition-key`
+    fn partition_key(&self) -> Option<&str> {
+        string(annotation(self.service_bus_annotations(), PARTITION_KEY))
+    }
+
+    /// Get `x-opt-scheduled-enqueue-time`
+    fn scheduled_enqueue_time(&self) -> Option<Timestamp> {
+        timestamp(annotation(
+            self.service_bus_annotations(),
+            SCHEDULED_ENQUEUE_TIME,
+        ))
+    }
+
+    /// Get `x-opt-deadletter-source`
+    fn deadletter_source(&self) -> Option<&str> {
+        string(annotation(
+            self.service_bus_annotations(),
+            DEADLETTER_SOURCE,
+        ))
+    }
+}
+
+impl<B> ServiceBusMessageExt for Message<B> {
+    fn service_bus_annotations(&self) -> Option<&MessageAnnotations> {
+        self.message_annotations.as_ref()
+    }
+}
+
+impl ServiceBusMessageExt for MessageAnnotations {
+    fn service_bus_annotations(&self) -> Option<&MessageAnnotations> {
+        Some(self)
+    }
+}
+
+/// Setters for the message annotations that are understood by Azure Service Bus
+///
+/// The annotations are inserted into the message annotations that are already set on the builder.
+pub trait ServiceBusMessageBuilderExt: Sized {
+    /// Insert a message annotation
+    fn insert_message_annotation(self, key: &str, value: Value) -> Self;
+
+    /// Set `x-opt-scheduled-enqueue-time`, which delays the message until the given time
+    fn scheduled_enqueue_time(self, time: impl Into<Timestamp>) -> Self {
+        self.insert_message_annotation(SCHEDULED_ENQUEUE_TIME, Value::Timestamp(time.into()))
+    }
+
+    /// Set `x-opt-partition-key`
+    fn partition_key(self, key: impl Into<String>) -> Self {
+        self.insert_message_annotation(PARTITION_KEY, Value::String(key.into()))
+    }
+}
+
+impl<T> ServiceBusMessageBuilderExt for Builder<T> {
+    fn insert_message_annotation(mut self, key: &str, value: Value) -> Self {
+        self.message_annotations
+            .get_or_insert_with(MessageAnnotations::default)
+            .insert(OwnedKey::from(key), value);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fe2o3_amqp_types::{
+        messaging::{Message, MessageAnnotations},
+        primitives::{Timestamp, Value},
+    };
+    use serde_amqp::{from_slice, to_vec};
+
+    use super::{ServiceBusMessageBuilderExt, ServiceBusMessageExt, PARTITION_KEY};
+
+    /// Message annotations section laid out as Service Bus sends it on a partitioned queue
+    const RECEIVED_ANNOTATIONS: [u8; 169] = [
+        0x00, 0x53, 0x72, 0xc1, 0xa4, 0x0a, 0xa3, 0x13, 0x78, 0x2d, 0x6f, 0x70, 0x74, 0x2d, 0x65,
+        0x6e, 0x71, 0x75, 0x65, 0x75, 0x65, 0x64, 0x2d, 0x74, 0x69, 0x6d, 0x65, 0x83, 0x00, 0x00,
+        0x01, 0x8b, 0x24, 0x70, 0xd7, 0x15, 0xa3, 0x15, 0x78, 0x2d, 0x6f, 0x70, 0x74, 0x2d, 0x73,
+        0x65, 0x71, 0x75, 0x65, 0x6e, 0x63, 0x65, 0x2d, 0x6e, 0x75, 0x6d, 0x62, 0x65, 0x72, 0x81,
+        0x00, 0x13, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0xa3, 0x1d, 0x78, 0x2d, 0x6f, 0x70, 0x74,
+        0x2d, 0x65, 0x6e, 0x71, 0x75, 0x65, 0x75, 0x65, 0x2d, 0x73, 0x65, 0x71, 0x75, 0x65, 0x6e,
+        0x63, 0x65, 0x2d, 0x6e, 0x75, 0x6d, 0x62, 0x65, 0x72, 0x81, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0xa3, 0x13, 0x78, 0x2d, 0x6f, 0x70, 0x74, 0x2d, 0x70, 0x61, 0x72, 0x74,
+        0x69, 0x74, 0x69, 0x6f, 0x6e, 0x2d, 0x6b, 0x65, 0x79, 0xa1, 0x09, 0x73, 0x65, 0x73, 0x73,
+        0x69, 0x6f, 0x6e, 0x2d, 0x31, 0xa3, 0x12, 0x78, 0x2d, 0x6f, 0x70, 0x74, 0x2d, 0x6c, 0x6f,
+        0x63, 0x6b, 0x65, 0x64, 0x2d, 0x75, 0x6e, 0x74, 0x69, 0x6c, 0x83, 0x00, 0x00, 0x01, 0x8b,
+        0x24, 0x71, 0xc1, 0x75,
+    ];
+
+    #[test]
+    fn typed_getters_on_received_annotations() {
+        let annotations: MessageAnnotations = from_slice(&RECEIVED_ANNOTATIONS).unwrap();
+        let message = Message::builder()
+            .message_annotations(annotations)
+            .value(())
+            .build();
+
+        assert_eq!(
+            message.enqueued_time(),
+            Some(Timestamp::from_milliseconds(1_697_123_456_789))
+        );
+        assert_eq!(message.sequence_number(), Some(5_348_024_557_502_465));
+        assert_eq!(message.enqueue_sequence_number(), Some(0));
+        assert_eq!(message.partition_key(), Some("session-1"));
+        assert_eq!(
+            message.locked_until(),
+            Some(Timestamp::from_milliseconds(1_697_123_516_789))
+        );
+        assert_eq!(message.scheduled_enqueue_time(), None);
+        assert_eq!(message.deadletter_source(), None);
+    }
+
+    #[test]
+    fn getters_return_none_without_annotations() {
+        let message = Message::builder().value(()).build();
+        assert_eq!(message.enqueued_time(), None);
+        assert_eq!(message.sequence_number(), None);
+        assert_eq!(message.partition_key(), None);
+    }
+
+    #[test]
+    fn getter_returns_none_for_unexpected_type() {
+        let annotations = MessageAnnotations::builder()
+            .insert(PARTITION_KEY, Value::Long(1))
+            .build();
+        assert_eq!(annotations.partition_key(), None);
+    }
+
+    #[test]
+    fn builder_setters_keep_existing_annotations() {
+        let message = Message::builder()
+            .message_annotations(
+                MessageAnnotations::builder()
+                    .insert("x-opt-custom", Value::Int(1))
+                    .build(),
+            )
+            .scheduled_enqueue_time(Timestamp::from_milliseconds(1_697_123_456_789))
+            .partition_key("session-1")
+            .value(())
+            .build();
+
+        let annotations = message.message_annotations.as_ref().unwrap();
+        assert_eq!(annotations.len(), 3);
+        assert_eq!(
+            message.scheduled_enqueue_time(),
+            Some(Timestamp::from_milliseconds(1_697_123_456_789))
+        );
+        assert_eq!(message.partition_key(), Some("session-1"));
+
+        // The annotations round trip through the encoded section
+        let buf = to_vec(annotations).unwrap();
+        let decoded: MessageAnnotations = from_slice(&buf).unwrap();
+        assert_eq!(&decoded, annotations);
+    }
+}
@@ -2,15 +2,15 @@
 
 use fe2o3_amqp::{
     link::{
-        DetachError, DetachThenResumeReceiverError, ReceiverAttachExchange,
-        ReceiverResumeErrorKind, SendError,
+        DetachError, DetachThenResumeReceiverError, DispositionError, IllegalLinkStateError,
+        ReceiverAttachExchange, ReceiverResumeErrorKind, SendError,
     },
     session::SessionHandle,
-    Delivery, Receiver, Sender,
+    Delivery, Receiver, SendReceipt, Sender,
 };
 use fe2o3_amqp_types::{
     definitions::Fields,
-    messaging::{Body, FromBody, IntoBody, MessageId, Outcome, Properties},
+    messaging::{Body, FromBody, IntoBody, MessageId, Properties},
     primitives::Value,
 };
 
@@ -59,6 +59,14 @@ impl MgmtClient {
             match self.receiver.recv::<Body<Value>>().await {
                 Ok(delivery) => {
                     self.receiver.reject(&delivery, None).await.map_err(|e| {
+                        // The client receiver settles in `ReceiverSettleMode::First` and never
+                        // waits for the remote settlement
+                        let e = match e {
+                            DispositionError::IllegalSessionState => {
+                                IllegalLinkStateError::IllegalSessionState
+                            }
+                            _ => IllegalLinkStateError::IllegalState,
+                        };
                         let err = ReceiverResumeErrorKind::FlowError(e);
                         let err = DetachThenResumeReceiverError::Resume(err);
                         DetachThenResumeError::Receiver(err)
@@ -85,7 +93,7 @@ impl MgmtClient {
     /// Send a request and wait for the outcome.
     ///
     /// This currently takes ownership of the request because it needs to set the request id if the field is not set.
-    pub async fn send_request(&mut self, request: impl Request) -> Result<Outcome, SendError> {
+    pub async fn send_request(&mut self, request: impl Request) -> Result<SendReceipt, SendError> {
         let mut message = request.into_message().map_body(IntoBody::into_body);
 
         // Only insert the request-id if it's not already set
@@ -123,8 +131,10 @@ impl MgmtClient {
         Res::Error: Into<Error>,
         for<'de> Res::Body: FromBody<'de> + std::fmt::Debug + Send,
     {
-        let outcome = self.send_request(request).await?;
-        let _accepted = outcome.accepted_or_else(Error::NotAccepted)?;
+        match self.send_request(request).await? {
+            SendReceipt::Accepted(_) | SendReceipt::Settled => {}
+            receipt => return Err(Error::NotAccepted(receipt)),
+        }
         self.recv_response().await
     }
 }
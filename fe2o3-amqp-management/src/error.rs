@@ -4,7 +4,13 @@ use fe2o3_amqp::link::{
     DetachThenResumeReceiverError, DetachThenResumeSenderError, DispositionError,
     ReceiverAttachError, RecvError, SendError, SenderAttachError,
 };
-use fe2o3_amqp_types::messaging::Outcome;
+use fe2o3_amqp::SendReceipt;
+
+#[cfg(feature = "server")]
+use fe2o3_amqp::{
+    acceptor::{error::AcceptorAttachError, LinkEndpoint},
+    link::DetachError,
+};
 
 use crate::status::StatusCode;
 
@@ -29,6 +35,28 @@ pub struct StatusError {
     pub description: Option<String>,
 }
 
+impl StatusError {
+    /// Creates a new status error with a description
+    pub fn new(code: StatusCode, description: impl Into<String>) -> Self {
+        Self {
+            code,
+            description: Some(description.into()),
+        }
+    }
+}
+
+impl From<InvalidType> for StatusError {
+    fn from(invalid_type: InvalidType) -> Self {
+        Self::new(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Invalid type, expected {}, found {}",
+                invalid_type.expected, invalid_type.actual
+            ),
+        )
+    }
+}
+
 impl std::fmt::Display for StatusError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(
@@ -92,7 +120,7 @@ pub enum Error {
 
     /// Request is not accepted
     #[error("Request is not accepted: {:?}", .0)]
-    NotAccepted(Outcome),
+    NotAccepted(SendReceipt),
 
     /// Error with receiving the response
     #[error(transparent)]
@@ -103,9 +131,9 @@ pub enum Error {
     Disposition(#[from] DispositionError),
 }
 
-impl From<Outcome> for Error {
-    fn from(outcome: Outcome) -> Self {
-        Self::NotAccepted(outcome)
+impl From<SendReceipt> for Error {
+    fn from(receipt: SendReceipt) -> Self {
+        Self::NotAccepted(receipt)
     }
 }
 
@@ -132,3 +160,38 @@ pub enum DetachThenResumeError {
     #[error(transparent)]
     Receiver(#[from] DetachThenResumeReceiverError),
 }
+
+/// An error that can occur when accepting the links of a management node.
+#[cfg(feature = "server")]
+#[derive(Debug, thiserror::Error)]
+pub enum AcceptError {
+    /// An error occurred when accepting a link.
+    #[error(transparent)]
+    Attach(#[from] AcceptorAttachError),
+
+    /// A link that is not attached to the address of the management node, or that
+    /// duplicates a link that is already accepted.
+    #[error("Unexpected link")]
+    UnexpectedLink(LinkEndpoint),
+}
+
+/// An error with the links of a management node.
+#[cfg(feature = "server")]
+#[derive(Debug, thiserror::Error)]
+pub enum ServerError {
+    /// Error with receiving the request
+    #[error(transparent)]
+    Recv(#[from] RecvError),
+
+    /// Error with accepting the request
+    #[error(transparent)]
+    Disposition(#[from] DispositionError),
+
+    /// Error with sending the response
+    #[error(transparent)]
+    Send(#[from] SendError),
+
+    /// Error with closing the links
+    #[error(transparent)]
+    Detach(#[from] DetachError),
+}
@@ -16,6 +16,9 @@ pub mod response;
 
 pub mod mgmt_ext;
 
+#[cfg(feature = "server")]
+pub mod server;
+
 /// The default address of the management node.
 pub const MANAGEMENT_NODE_ADDRESS: &str = "$management";
 
@@ -26,6 +29,9 @@ pub use client::MgmtClient;
 pub use request::Request;
 pub use response::Response;
 
+#[cfg(feature = "server")]
+pub use server::ManagementNode;
+
 // pub trait ManageableEntityAttributes {
 //     /// A case-sensitive string identifying the entity. It MUST be unique within the Management Node
 //     /// through which it is accessed. It MAY change during its lifetime. When a new Manageable
@@ -1,11 +1,16 @@
 use std::borrow::Cow;
 
 use fe2o3_amqp_types::{
-    messaging::{ApplicationProperties, Message},
+    messaging::{ApplicationProperties, Body, Message},
     primitives::Value,
 };
 
-use crate::{constants::REGISTER, error::Error, request::Request, response::Response};
+use crate::{
+    constants::{LOCALES, REGISTER, TYPE},
+    error::{Error, StatusError},
+    request::{remove_required_string_property, remove_string_property, DecodeRequest, Request},
+    response::{EncodeResponse, Response},
+};
 
 /// A trait for handling Register request on a Manageable Node.
 pub trait Register {
@@ -72,6 +77,21 @@ impl<'a> Request for RegisterRequest<'a> {
     fn encode_body(self) -> Self::Body {}
 }
 
+impl DecodeRequest for RegisterRequest<'static> {
+    const OPERATION: &'static str = REGISTER;
+
+    // Any message body is valid and ignored
+    type Body = Body<Value>;
+
+    fn decode_message(mut message: Message<Self::Body>) -> Result<Self, StatusError> {
+        Ok(Self {
+            address: remove_required_string_property(&mut message, "address")?.into(),
+            r#type: remove_required_string_property(&mut message, TYPE)?.into(),
+            locales: remove_string_property(&mut message, LOCALES)?.map(Into::into),
+        })
+    }
+}
+
 /// No information is carried in the message body therefore any message body is valid and MUST be
 /// ignored.
 ///
@@ -94,3 +114,11 @@ impl Response for RegisterResponse {
         Ok(Self {})
     }
 }
+
+impl EncodeResponse for RegisterResponse {
+    const STATUS_CODE: u16 = 200;
+
+    type Body = ();
+
+    fn encode_body(self) -> Self::Body {}
+}
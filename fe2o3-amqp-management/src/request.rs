@@ -1,8 +1,12 @@
 //! Defines the Request trait for AMQP 1.0 management requests.
 
-use fe2o3_amqp_types::messaging::{IntoBody, Message};
+use fe2o3_amqp_types::messaging::{FromBody, IntoBody, Message};
 
-use crate::response::Response;
+use crate::{
+    error::{InvalidType, StatusError},
+    response::Response,
+    status::StatusCode,
+};
 
 use fe2o3_amqp_types::{
     messaging::{
@@ -114,3 +118,52 @@ pub trait Request: Sized {
             .build()
     }
 }
+
+/// A trait for decoding AMQP 1.0 management requests on a management node.
+pub trait DecodeRequest: Sized {
+    /// Management operation
+    const OPERATION: &'static str;
+
+    /// The body type of the request.
+    type Body: for<'de> FromBody<'de>;
+
+    /// Decodes the request from the message.
+    ///
+    /// The returned error is sent back to the client as the response. A request that cannot be
+    /// decoded should be answered with a status code of 400 (Bad Request), which is what the
+    /// conversion from [`InvalidType`](crate::error::InvalidType) gives.
+    fn decode_message(message: Message<Self::Body>) -> Result<Self, StatusError>;
+}
+
+/// Removes a string value from the application properties of a request message
+pub(crate) fn remove_string_property<T>(
+    message: &mut Message<T>,
+    key: &str,
+) -> Result<Option<String>, StatusError> {
+    match message
+        .application_properties
+        .as_mut()
+        .and_then(|ap| ap.swap_remove(key))
+    {
+        Some(SimpleValue::String(s)) => Ok(Some(s)),
+        Some(value) => Err(InvalidType {
+            expected: "String".to_string(),
+            actual: format!("{:?}", value),
+        }
+        .into()),
+        None => Ok(None),
+    }
+}
+
+/// Removes a mandatory string value from the application properties of a request message
+pub(crate) fn remove_required_string_property<T>(
+    message: &mut Message<T>,
+    key: &str,
+) -> Result<String, StatusError> {
+    remove_string_property(message, key)?.ok_or_else(|| {
+        StatusError::new(
+            StatusCode::BAD_REQUEST,
+            format!("Missing application property {:?}", key),
+        )
+    })
+}
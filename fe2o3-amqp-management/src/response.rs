@@ -1,8 +1,12 @@
 //! Defines the Response trait for AMQP 1.0 management responses.
 
-use fe2o3_amqp_types::messaging::{FromBody, Message};
+use fe2o3_amqp_types::{
+    messaging::{ApplicationProperties, FromBody, IntoBody, Message},
+    primitives::SimpleValue,
+};
 
 use crate::{
+    constants::lower_camel_case,
     error::{InvalidType, StatusCodeNotFound, StatusError},
     mgmt_ext::AmqpMessageManagementExt,
     status::StatusCode,
@@ -76,3 +80,39 @@ pub trait Response: Sized {
         Self::decode_message(message)
     }
 }
+
+/// A trait for encoding AMQP 1.0 management responses on a management node.
+pub trait EncodeResponse: Sized {
+    /// The status code of the response.
+    const STATUS_CODE: u16;
+
+    /// The body type of the response.
+    type Body: IntoBody;
+
+    /// Encode the ApplicationProperties section of the message.
+    fn encode_application_properties(&mut self) -> Option<ApplicationProperties> {
+        None
+    }
+
+    /// Encode the body of the message.
+    fn encode_body(self) -> Self::Body;
+
+    /// Encode this response into a message.
+    ///
+    /// The status code is inserted into the application properties unless it is already set.
+    fn into_message(mut self) -> Message<Self::Body> {
+        let mut application_properties = self.encode_application_properties().unwrap_or_default();
+        application_properties
+            .as_inner_mut()
+            .entry(lower_camel_case::STATUS_CODE.to_string())
+            .or_insert(SimpleValue::Int(Self::STATUS_CODE as i32));
+
+        // `encode_body` will consume self, so we need to call it last.
+        let body = self.encode_body();
+
+        Message::builder()
+            .application_properties(application_properties)
+            .body(body)
+            .build()
+    }
+}
@@ -0,0 +1,369 @@
+//! Implements a management node for the AMQP 1.0 management working draft on top of the
+//! listener acceptors of `fe2o3-amqp`.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use fe2o3_amqp::acceptor::{LinkAcceptor, ListenerSessionHandle};
+//! use fe2o3_amqp_management::{
+//!     operations::node::{RegisterRequest, RegisterResponse},
+//!     server::ManagementNode,
+//! };
+//!
+//! async fn session_main(mut session: ListenerSessionHandle) {
+//!     let link_acceptor = LinkAcceptor::new();
+//!     let node = ManagementNode::builder()
+//!         .handler("org.example:node", |_: RegisterRequest<'static>| Ok(RegisterResponse {}))
+//!         .accept(&link_acceptor, &mut session)
+//!         .await
+//!         .unwrap();
+//!     node.run().await.unwrap();
+//! }
+//! ```
+
+use std::{collections::HashMap, future::Future, marker::PhantomData, pin::Pin};
+
+use fe2o3_amqp::{
+    acceptor::{LinkAcceptor, LinkEndpoint, ListenerSessionHandle},
+    link::{delivery::RawDelivery, LinkStateError, RecvError, SendError},
+    Receiver, SendReceipt, Sender,
+};
+use fe2o3_amqp_types::{
+    messaging::{AmqpValue, ApplicationProperties, IntoBody, Message, Properties, Source, Target},
+    primitives::SimpleValue,
+};
+
+use crate::{
+    constants::{self, lower_camel_case},
+    error::{AcceptError, ServerError, StatusError},
+    request::DecodeRequest,
+    response::EncodeResponse,
+    status::StatusCode,
+    MANAGEMENT_NODE_ADDRESS,
+};
+
+type ResponseFuture<'a> = Pin<Box<dyn Future<Output = Result<SendReceipt, SendError>> + Send + 'a>>;
+
+/// A handler of one (operation, type) pair with the request and response types erased
+trait Handler: Send {
+    /// Decodes the request, calls the handler and sends the response with the given properties
+    fn handle<'a>(
+        &mut self,
+        delivery: &RawDelivery,
+        properties: Properties,
+        sender: &'a mut Sender,
+    ) -> ResponseFuture<'a>;
+}
+
+struct OperationHandler<Req, Res, F> {
+    f: F,
+    marker: PhantomData<fn(Req) -> Res>,
+}
+
+impl<Req, Res, F> Handler for OperationHandler<Req, Res, F>
+where
+    Req: DecodeRequest,
+    Res: EncodeResponse,
+    <Res::Body as IntoBody>::Body: Send + 'static,
+    F: FnMut(Req) -> Result<Res, StatusError> + Send,
+{
+    fn handle<'a>(
+        &mut self,
+        delivery: &RawDelivery,
+        properties: Properties,
+        sender: &'a mut Sender,
+    ) -> ResponseFuture<'a> {
+        let request = delivery
+            .decode::<Req::Body>()
+            .map_err(|err| StatusError::new(StatusCode::BAD_REQUEST, err.to_string()))
+            .and_then(Req::decode_message);
+        match request.and_then(&mut self.f) {
+            Ok(response) => {
+                let mut message = response.into_message().map_body(IntoBody::into_body);
+                message.properties = Some(properties);
+                Box::pin(sender.send(message))
+            }
+            Err(status) => Box::pin(sender.send(status_message(status, properties))),
+        }
+    }
+}
+
+/// A response that carries nothing but the status code and description
+fn status_message(status: StatusError, properties: Properties) -> Message<AmqpValue<()>> {
+    let mut application_properties = ApplicationProperties::builder().insert(
+        lower_camel_case::STATUS_CODE,
+        SimpleValue::Int(status.code.0.get() as i32),
+    );
+    if let Some(description) = status.description {
+        application_properties =
+            application_properties.insert(lower_camel_case::STATUS_DESCRIPTION, description);
+    }
+    Message::builder()
+        .properties(properties)
+        .application_properties(application_properties.build())
+        .value(())
+        .build()
+}
+
+/// The properties of the response to a request with the given properties
+///
+/// The correlation-id of the response is the correlation-id of the request if it is set and the
+/// message-id of the request otherwise. The response is addressed to the reply-to address of the
+/// request.
+fn response_properties(request: Option<Properties>) -> Properties {
+    let request = request.unwrap_or_default();
+    Properties {
+        to: request.reply_to,
+        correlation_id: request.correlation_id.or(request.message_id),
+        ..Default::default()
+    }
+}
+
+/// Returns the string value of the key in the application properties
+fn string_property<'a>(
+    application_properties: &'a Option<ApplicationProperties>,
+    key: &str,
+) -> Result<&'a str, StatusError> {
+    match application_properties.as_ref().and_then(|ap| ap.get(key)) {
+        Some(SimpleValue::String(value)) => Ok(value),
+        Some(value) => Err(StatusError::new(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Invalid application property {:?}, expected String, found {:?}",
+                key, value
+            ),
+        )),
+        None => Err(StatusError::new(
+            StatusCode::BAD_REQUEST,
+            format!("Missing application property {:?}", key),
+        )),
+    }
+}
+
+/// A management node that answers the requests received on an incoming link with the handlers
+/// registered for the operation and type of the request.
+///
+/// The node holds the link pair accepted at the node address: the incoming link that the
+/// requests are received on and the outgoing link that the responses are sent on. The responses
+/// are addressed to the reply-to address of the requests and correlated with the correlation-id,
+/// or else the message-id, of the requests.
+///
+/// A request for an operation and type without a registered handler is answered with a status
+/// code of 501 (Not Implemented), and a request that is missing the operation or type or cannot
+/// be decoded is answered with a status code of 400 (Bad Request).
+pub struct ManagementNode {
+    address: String,
+    receiver: Receiver,
+    sender: Sender,
+    handlers: HashMap<(String, String), Box<dyn Handler>>,
+}
+
+impl std::fmt::Debug for ManagementNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ManagementNode")
+            .field("address", &self.address)
+            .field("receiver", &self.receiver)
+            .field("sender", &self.sender)
+            .field("handlers", &self.handlers.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl ManagementNode {
+    /// Creates a builder for a management node.
+    pub fn builder() -> ManagementNodeBuilder {
+        ManagementNodeBuilder::default()
+    }
+
+    /// The address of the node
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Receives the next request and sends the response.
+    ///
+    /// The request is accepted before it is handled, and a request that cannot be handled is
+    /// answered with an error status code. An error is returned only if the links fail.
+    pub async fn handle_request(&mut self) -> Result<SendReceipt, ServerError> {
+        let delivery = self.receiver.recv_raw().await?;
+        self.receiver.accept(&delivery).await?;
+
+        let (properties, application_properties) = match delivery.message_mut() {
+            Ok(message) => (
+                message.decode::<Properties>().ok().flatten(),
+                message.decode::<ApplicationProperties>(),
+            ),
+            Err(err) => (None, Err(err)),
+        };
+        let properties = response_properties(properties);
+
+        let handler = application_properties
+            .map_err(|err| StatusError::new(StatusCode::BAD_REQUEST, err.to_string()))
+            .and_then(|ap| {
+                let operation = string_property(&ap, constants::OPERATION)?;
+                let r#type = string_property(&ap, constants::TYPE)?;
+                self.handlers
+                    .get_mut(&(operation.to_string(), r#type.to_string()))
+                    .ok_or_else(|| {
+                        StatusError::new(
+                            StatusCode::NOT_IMPLEMENTED,
+                            format!(
+                                "Operation {:?} is not implemented for type {:?}",
+                                operation, r#type
+                            ),
+                        )
+                    })
+            });
+
+        let receipt = match handler {
+            Ok(handler) => {
+                handler
+                    .handle(&delivery, properties, &mut self.sender)
+                    .await?
+            }
+            Err(status) => self.sender.send(status_message(status, properties)).await?,
+        };
+        Ok(receipt)
+    }
+
+    /// Handles requests until the incoming link is detached or closed by the remote peer, and
+    /// then closes the outgoing link.
+    pub async fn run(mut self) -> Result<(), ServerError> {
+        loop {
+            match self.handle_request().await {
+                Ok(_) => {}
+                Err(ServerError::Recv(RecvError::LinkStateError(
+                    LinkStateError::RemoteDetached | LinkStateError::RemoteClosed,
+                ))) => break,
+                Err(err) => return Err(err),
+            }
+        }
+        self.sender.close().await?;
+        Ok(())
+    }
+
+    /// Closes the links of the node.
+    pub async fn close(self) -> Result<(), fe2o3_amqp::link::DetachError> {
+        self.sender.close().await?;
+        self.receiver.close().await?;
+        Ok(())
+    }
+}
+
+/// A builder for a management node.
+pub struct ManagementNodeBuilder {
+    address: String,
+    handlers: HashMap<(String, String), Box<dyn Handler>>,
+}
+
+impl std::fmt::Debug for ManagementNodeBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ManagementNodeBuilder")
+            .field("address", &self.address)
+            .field("handlers", &self.handlers.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Default for ManagementNodeBuilder {
+    fn default() -> Self {
+        Self {
+            address: String::from(MANAGEMENT_NODE_ADDRESS),
+            handlers: HashMap::new(),
+        }
+    }
+}
+
+impl ManagementNodeBuilder {
+    /// Set the address of the management node.
+    pub fn address(mut self, address: impl Into<String>) -> Self {
+        self.address = address.into();
+        self
+    }
+
+    /// Register a handler for the operation of the request type on the manageable entity type.
+    ///
+    /// The error returned by the handler is sent back as the status of the response. A handler
+    /// registered earlier for the same operation and type is replaced.
+    pub fn handler<Req, Res, F>(mut self, r#type: impl Into<String>, f: F) -> Self
+    where
+        Req: DecodeRequest + 'static,
+        Res: EncodeResponse + 'static,
+        <Res::Body as IntoBody>::Body: Send + 'static,
+        F: FnMut(Req) -> Result<Res, StatusError> + Send + 'static,
+    {
+        let handler = OperationHandler {
+            f,
+            marker: PhantomData,
+        };
+        self.handlers.insert(
+            (Req::OPERATION.to_string(), r#type.into()),
+            Box::new(handler),
+        );
+        self
+    }
+
+    /// Whether the link is attached to the address of the node
+    pub fn is_node_of(&self, link: &LinkEndpoint) -> bool {
+        let address = match link {
+            LinkEndpoint::Sender(sender) => sender
+                .source()
+                .as_ref()
+                .and_then(|source| source.address.as_ref()),
+            LinkEndpoint::Receiver(receiver) => receiver
+                .target()
+                .as_ref()
+                .and_then(|target| target.address.as_ref()),
+        };
+        address
+            .map(|address| address == &self.address)
+            .unwrap_or(false)
+    }
+
+    /// Builds the node with the incoming link that the requests are received on and the
+    /// outgoing link that the responses are sent on.
+    pub fn build(self, receiver: Receiver, sender: Sender) -> ManagementNode {
+        ManagementNode {
+            address: self.address,
+            receiver,
+            sender,
+            handlers: self.handlers,
+        }
+    }
+
+    /// Accepts the incoming and outgoing links of the node on the session.
+    ///
+    /// A link that is not attached to the address of the node, or that duplicates a link that is
+    /// already accepted, is returned in [`AcceptError::UnexpectedLink`].
+    pub async fn accept<FS, FT>(
+        self,
+        link_acceptor: &LinkAcceptor<FS, FT>,
+        session: &mut ListenerSessionHandle,
+    ) -> Result<ManagementNode, AcceptError>
+    where
+        FS: Fn(Source) -> Option<Source>,
+        FT: Fn(Target) -> Option<Target>,
+    {
+        let mut receiver = None;
+        let mut sender = None;
+        loop {
+            let link = link_acceptor.accept(session).await?;
+            if !self.is_node_of(&link) {
+                return Err(AcceptError::UnexpectedLink(link));
+            }
+            match link {
+                LinkEndpoint::Receiver(link) if receiver.is_none() => receiver = Some(link),
+                LinkEndpoint::Sender(link) if sender.is_none() => sender = Some(link),
+                link => return Err(AcceptError::UnexpectedLink(link)),
+            }
+
+            match (receiver.take(), sender.take()) {
+                (Some(receiver), Some(sender)) => return Ok(self.build(receiver, sender)),
+                (r, s) => {
+                    receiver = r;
+                    sender = s;
+                }
+            }
+        }
+    }
+}
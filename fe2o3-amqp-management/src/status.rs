@@ -8,6 +8,36 @@ use fe2o3_amqp_types::primitives::SimpleValue;
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash)]
 pub struct StatusCode(pub NonZeroU16);
 
+impl StatusCode {
+    /// 200 OK
+    pub const OK: Self = Self::from_u16(200);
+
+    /// 201 Created
+    pub const CREATED: Self = Self::from_u16(201);
+
+    /// 204 No Content
+    pub const NO_CONTENT: Self = Self::from_u16(204);
+
+    /// 400 Bad Request
+    pub const BAD_REQUEST: Self = Self::from_u16(400);
+
+    /// 404 Not Found
+    pub const NOT_FOUND: Self = Self::from_u16(404);
+
+    /// 500 Internal Server Error
+    pub const INTERNAL_SERVER_ERROR: Self = Self::from_u16(500);
+
+    /// 501 Not Implemented
+    pub const NOT_IMPLEMENTED: Self = Self::from_u16(501);
+
+    const fn from_u16(code: u16) -> Self {
+        match NonZeroU16::new(code) {
+            Some(code) => Self(code),
+            None => panic!("status code must be non-zero"),
+        }
+    }
+}
+
 impl TryFrom<SimpleValue> for StatusCode {
     type Error = SimpleValue;
 
@@ -0,0 +1,312 @@
+//! Tests of the management node against the management client
+
+#![cfg(feature = "server")]
+
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use fe2o3_amqp::{
+    acceptor::{ConnectionAcceptor, LinkAcceptor, ListenerSessionHandle, SessionAcceptor},
+    types::{
+        messaging::{AmqpValue, ApplicationProperties, Message, MessageId, Properties},
+        primitives::{SimpleValue, Value},
+    },
+    Connection, Receiver, SendReceipt, Sender, Session,
+};
+use fe2o3_amqp_management::{
+    error::{Error, StatusError},
+    mgmt_ext::AmqpMessageManagementExt,
+    operations::node::{DeregisterRequest, DeregisterResponse, RegisterRequest, RegisterResponse},
+    request::DecodeRequest,
+    response::EncodeResponse,
+    status::StatusCode,
+    ManagementNode, MgmtClient, Request, Response, MANAGEMENT_NODE_ADDRESS,
+};
+use tokio::net::TcpListener;
+
+const NODE_TYPE: &str = "org.example:node";
+const GREETER_TYPE: &str = "org.example:greeter";
+const GREET: &str = "GREET";
+
+/// A custom operation that greets the name in the body
+struct GreetRequest {
+    name: String,
+}
+
+impl Request for GreetRequest {
+    const OPERATION: &'static str = GREET;
+
+    type Response = GreetResponse;
+    type Body = String;
+
+    fn manageable_entity_type(&mut self) -> Option<String> {
+        Some(GREETER_TYPE.to_string())
+    }
+
+    fn encode_body(self) -> Self::Body {
+        self.name
+    }
+}
+
+impl DecodeRequest for GreetRequest {
+    const OPERATION: &'static str = GREET;
+
+    type Body = String;
+
+    fn decode_message(message: Message<Self::Body>) -> Result<Self, StatusError> {
+        Ok(Self { name: message.body })
+    }
+}
+
+#[derive(Debug)]
+struct GreetResponse {
+    greeting: String,
+}
+
+impl Response for GreetResponse {
+    const STATUS_CODE: u16 = 200;
+
+    // The body of an error response is null
+    type Body = Option<String>;
+    type Error = Error;
+
+    fn decode_message(message: Message<Self::Body>) -> Result<Self, Self::Error> {
+        Ok(Self {
+            greeting: message.body.unwrap_or_default(),
+        })
+    }
+}
+
+impl EncodeResponse for GreetResponse {
+    const STATUS_CODE: u16 = 200;
+
+    type Body = String;
+
+    fn encode_body(self) -> Self::Body {
+        self.greeting
+    }
+}
+
+/// A REGISTER request that is missing the address
+struct MalformedRegisterRequest;
+
+impl Request for MalformedRegisterRequest {
+    const OPERATION: &'static str = fe2o3_amqp_management::constants::REGISTER;
+
+    type Response = RegisterResponse;
+    type Body = ();
+
+    fn manageable_entity_type(&mut self) -> Option<String> {
+        Some(NODE_TYPE.to_string())
+    }
+
+    fn encode_body(self) -> Self::Body {}
+}
+
+type Registered = Arc<Mutex<Vec<String>>>;
+
+async fn spawn_management_node(registered: Registered) -> SocketAddr {
+    let tcp_listener = TcpListener::bind("localhost:0").await.unwrap();
+    let addr = tcp_listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let connection_acceptor = ConnectionAcceptor::new("test-management-node");
+        while let Ok((stream, _)) = tcp_listener.accept().await {
+            let mut connection = connection_acceptor.accept(stream).await.unwrap();
+            let registered = registered.clone();
+            tokio::spawn(async move {
+                let session_acceptor = SessionAcceptor::new();
+                while let Ok(session) = session_acceptor.accept(&mut connection).await {
+                    tokio::spawn(session_main(session, registered.clone()));
+                }
+                let _ = connection.on_close().await;
+            });
+        }
+    });
+
+    addr
+}
+
+async fn session_main(mut session: ListenerSessionHandle, registered: Registered) {
+    let link_acceptor = LinkAcceptor::new();
+    let node = ManagementNode::builder()
+        .handler(NODE_TYPE, move |request: RegisterRequest<'static>| {
+            registered
+                .lock()
+                .unwrap()
+                .push(request.address.into_owned());
+            Ok(RegisterResponse {})
+        })
+        .handler(GREETER_TYPE, |request: GreetRequest| {
+            if request.name.is_empty() {
+                return Err(StatusError::new(StatusCode::NOT_FOUND, "Nobody to greet"));
+            }
+            Ok(GreetResponse {
+                greeting: format!("Hello, {}", request.name),
+            })
+        })
+        .accept(&link_acceptor, &mut session)
+        .await
+        .unwrap();
+    node.run().await.unwrap();
+    let _ = session.on_end().await;
+}
+
+#[tokio::test]
+async fn client_calls_register_and_custom_operation() {
+    let registered = Registered::default();
+    let addr = spawn_management_node(registered.clone()).await;
+    let mut connection = Connection::open("test-connection", &format!("amqp://{}", addr)[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut client = MgmtClient::attach(&mut session, "test-client")
+        .await
+        .unwrap();
+
+    let request = RegisterRequest::new("q1", NODE_TYPE, None::<String>);
+    let _: RegisterResponse = client.call(request).await.unwrap();
+    assert_eq!(*registered.lock().unwrap(), vec![String::from("q1")]);
+
+    let request = GreetRequest {
+        name: String::from("amqp"),
+    };
+    let response = client.call(request).await.unwrap();
+    assert_eq!(response.greeting, "Hello, amqp");
+
+    // The error returned by the handler is the status of the response
+    let request = GreetRequest {
+        name: String::new(),
+    };
+    match client.call(request).await {
+        Err(Error::Status(StatusError { code, description })) => {
+            assert_eq!(code, StatusCode::NOT_FOUND);
+            assert_eq!(description.as_deref(), Some("Nobody to greet"));
+        }
+        other => panic!("unexpected response {:?}", other),
+    }
+
+    // Operations without a handler are not implemented
+    let request = DeregisterRequest::new("q1", NODE_TYPE, None::<String>);
+    match client.call::<_, DeregisterResponse>(request).await {
+        Err(Error::Status(StatusError { code, description })) => {
+            assert_eq!(code, StatusCode::NOT_IMPLEMENTED);
+            assert!(description.is_some());
+        }
+        other => panic!("unexpected response {:?}", other),
+    }
+    let request = RegisterRequest::new("q2", GREETER_TYPE, None::<String>);
+    match client.call(request).await {
+        Err(Error::Status(StatusError { code, .. })) => {
+            assert_eq!(code, StatusCode::NOT_IMPLEMENTED)
+        }
+        other => panic!("unexpected response {:?}", other),
+    }
+
+    // Requests that cannot be decoded are bad requests
+    match client.call(MalformedRegisterRequest).await {
+        Err(Error::Status(StatusError { code, description })) => {
+            assert_eq!(code, StatusCode::BAD_REQUEST);
+            assert!(description.unwrap().contains("address"));
+        }
+        other => panic!("unexpected response {:?}", other),
+    }
+    assert_eq!(registered.lock().unwrap().len(), 1);
+
+    // The node keeps serving after the errors
+    let request = RegisterRequest::new("q3", NODE_TYPE, None::<String>);
+    let _: RegisterResponse = client.call(request).await.unwrap();
+    assert_eq!(
+        *registered.lock().unwrap(),
+        vec![String::from("q1"), String::from("q3")]
+    );
+
+    client.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn responses_are_correlated_with_requests() {
+    let addr = spawn_management_node(Registered::default()).await;
+    let mut connection = Connection::open("test-connection", &format!("amqp://{}", addr)[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::attach(&mut session, "test-sender", MANAGEMENT_NODE_ADDRESS)
+        .await
+        .unwrap();
+    let mut receiver = Receiver::builder()
+        .name("test-receiver")
+        .source(MANAGEMENT_NODE_ADDRESS)
+        .target("test-client")
+        .attach(&mut session)
+        .await
+        .unwrap();
+
+    // The correlation-id of the response is the message-id of the request
+    let message = Message::builder()
+        .properties(
+            Properties::builder()
+                .message_id(MessageId::from(7u64))
+                .reply_to("test-client")
+                .build(),
+        )
+        .application_properties(
+            ApplicationProperties::builder()
+                .insert("operation", GREET)
+                .insert("type", GREETER_TYPE)
+                .build(),
+        )
+        .value("amqp")
+        .build();
+    let receipt = sender.send(message).await.unwrap();
+    assert!(matches!(receipt, SendReceipt::Accepted(_)));
+    let delivery = receiver.recv::<AmqpValue<String>>().await.unwrap();
+    receiver.accept(&delivery).await.unwrap();
+    let response = delivery.into_message();
+    let properties = response.properties.as_ref().unwrap();
+    assert_eq!(properties.correlation_id, Some(MessageId::from(7u64)));
+    assert_eq!(properties.to.as_deref(), Some("test-client"));
+    assert_eq!(response.status_code().unwrap().unwrap(), StatusCode::OK);
+    assert_eq!(response.body.0, "Hello, amqp");
+
+    // The correlation-id of the request takes precedence, and requests without an operation
+    // are bad requests
+    let message = Message::builder()
+        .properties(
+            Properties::builder()
+                .message_id(MessageId::from(8u64))
+                .correlation_id(MessageId::from("request-8"))
+                .build(),
+        )
+        .value(Value::Null)
+        .build();
+    sender.send(message).await.unwrap();
+    let delivery = receiver.recv::<Value>().await.unwrap();
+    receiver.accept(&delivery).await.unwrap();
+    let response = delivery.into_message();
+    assert_eq!(
+        response.correlation_id(),
+        Some(&MessageId::from("request-8"))
+    );
+    assert_eq!(
+        response.status_code().unwrap().unwrap(),
+        StatusCode::BAD_REQUEST
+    );
+    assert!(matches!(
+        response
+            .application_properties
+            .as_ref()
+            .unwrap()
+            .get("statusDescription"),
+        Some(SimpleValue::String(_))
+    ));
+
+    sender.close().await.unwrap();
+    receiver.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
@@ -1,4 +1,4 @@
-use std::{
+use core::{
     convert::{TryFrom, TryInto},
     fmt::{Debug, Display},
 };
@@ -62,12 +62,12 @@ pub enum AmqpError {
 }
 
 impl Display for AmqpError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         Debug::fmt(&self, f)
     }
 }
 
-impl std::error::Error for AmqpError {}
+impl core::error::Error for AmqpError {}
 
 impl From<AmqpError> for ErrorCondition {
     fn from(err: AmqpError) -> Self {
@@ -1,4 +1,4 @@
-use std::{
+use core::{
     convert::{TryFrom, TryInto},
     fmt::{Debug, Display},
 };
@@ -32,12 +32,12 @@ pub enum ConnectionError {
 }
 
 impl Display for ConnectionError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         Debug::fmt(&self, f)
     }
 }
 
-impl std::error::Error for ConnectionError {}
+impl core::error::Error for ConnectionError {}
 
 impl From<ConnectionError> for ErrorCondition {
     fn from(err: ConnectionError) -> Self {
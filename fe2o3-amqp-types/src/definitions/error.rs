@@ -1,6 +1,7 @@
+use alloc::string::String;
 use serde_amqp::macros::{DeserializeComposite, SerializeComposite};
 
-use super::{ErrorCondition, Fields};
+use super::{fmt_fields, ErrorCondition, Fields};
 
 /// <type name="error" class="composite" source="list">
 /// <descriptor name="amqp:error:list" code="0x00000000:0x0000001d"/>
@@ -23,17 +24,23 @@ pub struct Error {
     pub info: Option<Fields>,
 }
 
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Error")
-            .field("condition", &self.condition)
-            .field("description", &self.description)
-            .field("info", &self.info)
-            .finish()
+/// Displays the condition followed by the description and the info entries if they are present,
+/// eg. `amqp:not-allowed: quota exceeded {reason: String("Quota")}`
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.condition)?;
+        if let Some(description) = &self.description {
+            write!(f, ": {}", description)?;
+        }
+        if let Some(info) = &self.info {
+            f.write_str(" ")?;
+            fmt_fields(info, f)?;
+        }
+        Ok(())
     }
 }
 
-impl std::error::Error for Error {}
+impl core::error::Error for Error {}
 
 impl Error {
     /// Creates a new Error
@@ -68,10 +75,31 @@ mod tests {
 
     use serde_amqp::{from_slice, to_vec};
 
-    use crate::definitions::AmqpError;
+    use serde_amqp::{primitives::Symbol, Value};
+
+    use crate::definitions::{AmqpError, Fields};
 
     use super::Error;
 
+    #[test]
+    fn display_error_with_description_and_info() {
+        let mut info = Fields::new();
+        info.insert(Symbol::from("reason"), Value::from("QuotaExceeded"));
+        info.insert(Symbol::from("retry-after"), Value::from(30u32));
+        let error = Error::new(
+            AmqpError::ResourceLimitExceeded,
+            Some("quota exceeded".to_string()),
+            info,
+        );
+        assert_eq!(
+            error.to_string(),
+            r#"amqp:resource-limit-exceeded: quota exceeded {reason: String("QuotaExceeded"), retry-after: Uint(30)}"#
+        );
+
+        let error = Error::new(AmqpError::NotAllowed, None, None);
+        assert_eq!(error.to_string(), "amqp:not-allowed");
+    }
+
     #[test]
     fn test_serde_error() {
         let expected = Error::new(AmqpError::DecodeError, None, None);
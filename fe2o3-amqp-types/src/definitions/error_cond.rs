@@ -1,4 +1,4 @@
-use std::convert::TryFrom;
+use core::convert::TryFrom;
 
 use serde::{de, ser};
 
@@ -53,12 +53,34 @@ impl ser::Serialize for ErrorCondition {
     }
 }
 
+impl From<&ErrorCondition> for Symbol {
+    fn from(value: &ErrorCondition) -> Self {
+        match value {
+            ErrorCondition::AmqpError(err) => Symbol::from(err),
+            ErrorCondition::ConnectionError(err) => Symbol::from(err),
+            ErrorCondition::SessionError(err) => Symbol::from(err),
+            ErrorCondition::LinkError(err) => Symbol::from(err),
+            ErrorCondition::Custom(symbol) => symbol.clone(),
+
+            #[cfg(feature = "transaction")]
+            ErrorCondition::TransactionError(err) => Symbol::from(err),
+        }
+    }
+}
+
+/// Displays the symbolic name of the condition, eg. `amqp:not-allowed`
+impl core::fmt::Display for ErrorCondition {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(Symbol::from(self).as_str())
+    }
+}
+
 // struct Visitor {}
 
 // impl<'de> de::Visitor<'de> for Visitor {
 //     type Value = ErrorCondition;
 
-//     fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+//     fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
 //         formatter.write_str("enum ErrorCondition")
 //     }
 
@@ -0,0 +1,353 @@
+use alloc::vec::Vec;
+use core::{any::type_name, convert::TryFrom};
+
+use serde_amqp::{
+    described::Described,
+    descriptor::Descriptor,
+    primitives::{Array, OrderedMap, Symbol},
+    Value,
+};
+
+use super::Fields;
+
+/// Builds a [`Fields`] map with typed insertion of the structured values found in connection,
+/// session and link properties
+///
+/// Keys are always encoded as symbols, including the keys of nested maps.
+///
+/// # Example
+///
+/// ```rust
+/// use fe2o3_amqp_types::definitions::{Fields, FieldsBuilder, FieldsExt};
+/// use fe2o3_amqp_types::primitives::Symbol;
+///
+/// let fields: Fields = FieldsBuilder::new()
+///     .insert("product", "example-client")
+///     .insert_symbol_list("qpid.features", ["filters", "shared-subscriptions"])
+///     .build();
+///
+/// let features: Vec<Symbol> = fields.get_as("qpid.features").unwrap();
+/// assert_eq!(features, vec![Symbol::from("filters"), Symbol::from("shared-subscriptions")]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FieldsBuilder {
+    fields: Fields,
+}
+
+impl FieldsBuilder {
+    /// Creates an empty builder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts a value, replacing the entry with the same key
+    pub fn insert(mut self, key: impl Into<Symbol>, value: impl Into<Value>) -> Self {
+        self.fields.insert(key.into(), value.into());
+        self
+    }
+
+    /// Inserts a symbol, which [`insert`](#method.insert) would otherwise encode as a string
+    pub fn insert_symbol(self, key: impl Into<Symbol>, value: impl Into<Symbol>) -> Self {
+        self.insert(key, Value::Symbol(value.into()))
+    }
+
+    /// Inserts a list of symbols
+    pub fn insert_symbol_list<I>(self, key: impl Into<Symbol>, symbols: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Symbol>,
+    {
+        let list: Vec<Value> = symbols
+            .into_iter()
+            .map(|symbol| Value::Symbol(symbol.into()))
+            .collect();
+        self.insert(key, Value::List(list))
+    }
+
+    /// Inserts an array of symbols, which is how the spec encodes a `multiple` symbol field
+    pub fn insert_symbol_array<I>(self, key: impl Into<Symbol>, symbols: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Symbol>,
+    {
+        let array: Array<Value> = symbols
+            .into_iter()
+            .map(|symbol| Value::Symbol(symbol.into()))
+            .collect();
+        self.insert(key, Value::Array(array))
+    }
+
+    /// Inserts a nested map whose keys are encoded as symbols
+    pub fn insert_map<I, K, V>(self, key: impl Into<Symbol>, entries: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<Symbol>,
+        V: Into<Value>,
+    {
+        let map: OrderedMap<Value, Value> = entries
+            .into_iter()
+            .map(|(key, value)| (Value::Symbol(key.into()), value.into()))
+            .collect();
+        self.insert(key, Value::Map(map))
+    }
+
+    /// Inserts a described value
+    pub fn insert_described(
+        self,
+        key: impl Into<Symbol>,
+        descriptor: Descriptor,
+        value: impl Into<Value>,
+    ) -> Self {
+        let described = Described {
+            descriptor,
+            value: value.into(),
+        };
+        self.insert(key, Value::Described(alloc::boxed::Box::new(described)))
+    }
+
+    /// Returns the map
+    pub fn build(self) -> Fields {
+        self.fields
+    }
+}
+
+impl From<FieldsBuilder> for Fields {
+    fn from(builder: FieldsBuilder) -> Self {
+        builder.fields
+    }
+}
+
+/// Error of the typed extraction of a [`Fields`] entry
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldError {
+    /// There is no entry with the key
+    Missing(Symbol),
+
+    /// The value of the entry is not of the requested type
+    Mismatch {
+        /// The key of the entry
+        key: Symbol,
+
+        /// The name of the requested type
+        expected: &'static str,
+
+        /// The value of the entry
+        found: Value,
+    },
+}
+
+impl core::fmt::Display for FieldError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            FieldError::Missing(key) => write!(f, "Field {} is missing", key.as_str()),
+            FieldError::Mismatch {
+                key,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Field {} cannot be read as {}, found {:?}",
+                key.as_str(),
+                expected,
+                found
+            ),
+        }
+    }
+}
+
+impl core::error::Error for FieldError {}
+
+/// Typed extraction of [`Fields`] entries
+pub trait FieldsExt {
+    /// Returns the value of the entry converted to `T`
+    ///
+    /// Besides a list, a `Vec<T>` is also read from an array since peers don't agree on how
+    /// a sequence of symbols is encoded.
+    fn get_as<T>(&self, key: &str) -> Result<T, FieldError>
+    where
+        T: TryFrom<Value, Error = Value>;
+
+    /// Returns a nested map with symbol keys, such as one inserted by
+    /// [`FieldsBuilder::insert_map`], whose values can be of any type
+    fn get_fields(&self, key: &str) -> Result<Fields, FieldError>;
+}
+
+impl FieldsExt for Fields {
+    fn get_as<T>(&self, key: &str) -> Result<T, FieldError>
+    where
+        T: TryFrom<Value, Error = Value>,
+    {
+        let key = Symbol::from(key);
+        let value = match self.get(&key) {
+            Some(value) => value.clone(),
+            None => return Err(FieldError::Missing(key)),
+        };
+        let mismatch = |found| FieldError::Mismatch {
+            key: key.clone(),
+            expected: type_name::<T>(),
+            found,
+        };
+        match T::try_from(value) {
+            Ok(value) => Ok(value),
+            Err(Value::Array(array)) => match T::try_from(Value::List(array.0)) {
+                Ok(value) => Ok(value),
+                Err(Value::List(list)) => Err(mismatch(Value::Array(Array(list)))),
+                Err(found) => Err(mismatch(found)),
+            },
+            Err(found) => Err(mismatch(found)),
+        }
+    }
+
+    fn get_fields(&self, key: &str) -> Result<Fields, FieldError> {
+        let key = Symbol::from(key);
+        let mismatch = |found| FieldError::Mismatch {
+            key: key.clone(),
+            expected: "Fields",
+            found,
+        };
+        match self.get(&key) {
+            Some(Value::Map(map)) => map
+                .iter()
+                .map(|(k, v)| match k {
+                    Value::Symbol(k) => Ok((k.clone(), v.clone())),
+                    _ => Err(mismatch(Value::Map(map.clone()))),
+                })
+                .collect(),
+            Some(found) => Err(mismatch(found.clone())),
+            None => Err(FieldError::Missing(key)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::String, vec, vec::Vec};
+
+    use serde_amqp::{descriptor::Descriptor, from_slice, primitives::Symbol, to_vec, Value};
+
+    use crate::definitions::Fields;
+
+    use super::{FieldError, FieldsBuilder, FieldsExt};
+
+    fn round_trip(fields: &Fields) -> Fields {
+        let buf = to_vec(fields).unwrap();
+        from_slice(&buf).unwrap()
+    }
+
+    #[test]
+    fn artemis_properties_round_trip() {
+        let fields = FieldsBuilder::new()
+            .insert("product", "apache-activemq-artemis")
+            .insert("qpid.client_pid", 4242i32)
+            .insert_symbol_list("qpid.features", ["filters", "shared-subscriptions"])
+            .insert_symbol_array("qpid.capabilities", ["DELAYED_DELIVERY", "ANONYMOUS-RELAY"])
+            .insert_map(
+                "qpid.session_flow",
+                [
+                    ("window", Value::Uint(2048)),
+                    ("mode", Value::from("credit")),
+                ],
+            )
+            .build();
+
+        let fields = round_trip(&fields);
+        assert_eq!(
+            fields.get_as::<String>("product").unwrap(),
+            "apache-activemq-artemis"
+        );
+        assert_eq!(fields.get_as::<i32>("qpid.client_pid").unwrap(), 4242);
+        assert_eq!(
+            fields.get_as::<Vec<Symbol>>("qpid.features").unwrap(),
+            vec![
+                Symbol::from("filters"),
+                Symbol::from("shared-subscriptions")
+            ]
+        );
+        // An array of symbols is read as a `Vec` too
+        assert_eq!(
+            fields.get_as::<Vec<Symbol>>("qpid.capabilities").unwrap(),
+            vec![
+                Symbol::from("DELAYED_DELIVERY"),
+                Symbol::from("ANONYMOUS-RELAY")
+            ]
+        );
+        let flow = fields.get_fields("qpid.session_flow").unwrap();
+        assert_eq!(flow.get(&Symbol::from("window")), Some(&Value::Uint(2048)));
+        assert_eq!(
+            flow.get(&Symbol::from("mode")),
+            Some(&Value::from("credit"))
+        );
+    }
+
+    #[test]
+    fn service_bus_properties_round_trip() {
+        let filter = Descriptor::Name(Symbol::from("com.microsoft:sql-filter"));
+        let fields = FieldsBuilder::new()
+            .insert_symbol("com.microsoft:entity-type", "com.microsoft:queue")
+            .insert("com.microsoft:timeout", Value::Uint(60_000))
+            .insert_described(
+                "com.microsoft:filter",
+                filter.clone(),
+                "sys.Label = 'urgent'",
+            )
+            .insert_map(
+                "com.microsoft:client-agent",
+                [("name", "example-client"), ("version", "1.0.0")],
+            )
+            .build();
+
+        let decoded = round_trip(&fields);
+        assert_eq!(decoded, fields);
+
+        assert_eq!(
+            decoded
+                .get_as::<Symbol>("com.microsoft:entity-type")
+                .unwrap(),
+            Symbol::from("com.microsoft:queue")
+        );
+        match decoded.get(&Symbol::from("com.microsoft:filter")) {
+            Some(Value::Described(described)) => {
+                assert_eq!(described.descriptor, filter);
+                assert_eq!(described.value, Value::from("sys.Label = 'urgent'"));
+            }
+            value => panic!("Expecting a described value, found {:?}", value),
+        }
+        // The keys of the nested map are symbols on the wire
+        match decoded.get(&Symbol::from("com.microsoft:client-agent")) {
+            Some(Value::Map(map)) => {
+                assert!(map.keys().all(|key| matches!(key, Value::Symbol(_))));
+            }
+            value => panic!("Expecting a map, found {:?}", value),
+        }
+    }
+
+    #[test]
+    fn get_as_reports_missing_and_mismatched_fields() {
+        let fields = FieldsBuilder::new()
+            .insert("qpid.client_pid", 4242i32)
+            .build();
+
+        assert_eq!(
+            fields.get_as::<i32>("qpid.client_process"),
+            Err(FieldError::Missing(Symbol::from("qpid.client_process")))
+        );
+        let error = fields.get_as::<Vec<Symbol>>("qpid.client_pid").unwrap_err();
+        assert_eq!(
+            error,
+            FieldError::Mismatch {
+                key: Symbol::from("qpid.client_pid"),
+                expected: core::any::type_name::<Vec<Symbol>>(),
+                found: Value::Int(4242),
+            }
+        );
+        assert_eq!(
+            fields.get_fields("qpid.client_pid"),
+            Err(FieldError::Mismatch {
+                key: Symbol::from("qpid.client_pid"),
+                expected: "Fields",
+                found: Value::Int(4242),
+            })
+        );
+        assert!(alloc::format!("{}", error).starts_with("Field qpid.client_pid cannot be read as"));
+    }
+}
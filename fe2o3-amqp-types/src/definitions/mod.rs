@@ -70,6 +70,22 @@ pub type IetfLanguageTag = Symbol;
 /// 2.8.13 Fields
 pub type Fields = OrderedMap<Symbol, Value>;
 
+/// Typed construction and extraction of [`Fields`]
+mod fields;
+pub use fields::{FieldError, FieldsBuilder, FieldsExt};
+
+/// Writes the entries of a [`Fields`] map as `{key: value, ...}`
+pub(crate) fn fmt_fields(fields: &Fields, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    f.write_str("{")?;
+    for (i, (key, value)) in fields.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{}: {:?}", key.as_str(), value)?;
+    }
+    f.write_str("}")
+}
+
 /// 2.8.14 Error
 mod error;
 pub use error::Error;
@@ -93,6 +109,10 @@ pub use session_error::SessionError;
 mod link_error;
 pub use link_error::LinkError;
 
+/// Redirect info of the connection and link redirect errors
+mod redirect;
+pub use redirect::Redirect;
+
 /// 2.8.19 Constant definition
 mod constant_def;
 pub use constant_def::{MAJOR, MINOR, MIN_MAX_FRAME_SIZE, PORT, REVISION, SECURE_PORT};
@@ -0,0 +1,260 @@
+use alloc::string::{String, ToString};
+use core::convert::TryFrom;
+
+use serde_amqp::{primitives::Symbol, Value};
+
+use super::{ConnectionError, Error, ErrorCondition, Fields, LinkError};
+
+const HOSTNAME: &str = "hostname";
+const NETWORK_HOST: &str = "network-host";
+const PORT: &str = "port";
+const ADDRESS: &str = "address";
+
+/// Where to reconnect as told by an `amqp:connection:redirect` or `amqp:link:redirect` error
+///
+/// The fields are carried in the `info` map of the error.
+///
+/// # Example
+///
+/// ```rust
+/// use fe2o3_amqp_types::definitions::{Error, Redirect};
+///
+/// let redirect = Redirect::new("broker-2", 5672).hostname("vhost.example.net");
+/// let error = redirect.clone().into_connection_error(None);
+/// assert_eq!(error.redirect(), Some(redirect));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    /// The hostname of the container, which should be supplied in the hostname field of the Open
+    /// frame and during the SASL and TLS negotiation
+    pub hostname: Option<String>,
+
+    /// The DNS hostname or IP address of the machine hosting the container
+    pub network_host: String,
+
+    /// The port number on the machine hosting the container
+    pub port: u16,
+
+    /// The address of the terminus at the new location. This is only carried by a link redirect
+    pub address: Option<String>,
+}
+
+impl Redirect {
+    /// Creates a redirect to the `network_host` and `port`
+    pub fn new(network_host: impl Into<String>, port: u16) -> Self {
+        Self {
+            hostname: None,
+            network_host: network_host.into(),
+            port,
+            address: None,
+        }
+    }
+
+    /// Sets the hostname of the container
+    pub fn hostname(mut self, hostname: impl Into<String>) -> Self {
+        self.hostname = Some(hostname.into());
+        self
+    }
+
+    /// Sets the address of the terminus at the new location
+    pub fn address(mut self, address: impl Into<String>) -> Self {
+        self.address = Some(address.into());
+        self
+    }
+
+    /// Parses the redirect from the `info` map of an error
+    ///
+    /// The port is accepted as any integer type or a string because peers don't agree on its
+    /// encoding. `network-host` falls back to `hostname` if it is absent. `None` is returned if
+    /// neither is present or the port is missing or invalid.
+    pub fn from_info(info: &Fields) -> Option<Self> {
+        let hostname = get_string(info, HOSTNAME);
+        let network_host = get_string(info, NETWORK_HOST).or_else(|| hostname.clone())?;
+        let port = get_port(info)?;
+        let address = get_string(info, ADDRESS);
+        Some(Self {
+            hostname,
+            network_host,
+            port,
+            address,
+        })
+    }
+
+    /// Writes the redirect into an `info` map
+    pub fn into_info(self) -> Fields {
+        let mut info = Fields::new();
+        if let Some(hostname) = self.hostname {
+            info.insert(Symbol::from(HOSTNAME), Value::String(hostname));
+        }
+        info.insert(Symbol::from(NETWORK_HOST), Value::String(self.network_host));
+        info.insert(Symbol::from(PORT), Value::Ushort(self.port));
+        if let Some(address) = self.address {
+            info.insert(Symbol::from(ADDRESS), Value::String(address));
+        }
+        info
+    }
+
+    /// Creates an `amqp:connection:redirect` error that carries the redirect
+    pub fn into_connection_error(self, description: impl Into<Option<String>>) -> Error {
+        Error::new(ConnectionError::Redirect, description, self.into_info())
+    }
+
+    /// Creates an `amqp:link:redirect` error that carries the redirect
+    pub fn into_link_error(self, description: impl Into<Option<String>>) -> Error {
+        Error::new(LinkError::Redirect, description, self.into_info())
+    }
+}
+
+impl Error {
+    /// Returns the redirect if this is an `amqp:connection:redirect` or `amqp:link:redirect` error
+    /// with a valid `info` map
+    pub fn redirect(&self) -> Option<Redirect> {
+        match &self.condition {
+            ErrorCondition::ConnectionError(ConnectionError::Redirect)
+            | ErrorCondition::LinkError(LinkError::Redirect) => {
+                self.info.as_ref().and_then(Redirect::from_info)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn get<'a>(info: &'a Fields, key: &str) -> Option<&'a Value> {
+    info.get(&Symbol::from(key))
+}
+
+fn get_string(info: &Fields, key: &str) -> Option<String> {
+    match get(info, key)? {
+        Value::String(s) => Some(s.clone()),
+        Value::Symbol(s) => Some(s.as_str().to_string()),
+        _ => None,
+    }
+}
+
+fn get_port(info: &Fields) -> Option<u16> {
+    match get(info, PORT)? {
+        Value::Ushort(port) => Some(*port),
+        Value::Ubyte(port) => Some(u16::from(*port)),
+        Value::Uint(port) => u16::try_from(*port).ok(),
+        Value::Ulong(port) => u16::try_from(*port).ok(),
+        Value::Short(port) => u16::try_from(*port).ok(),
+        Value::Int(port) => u16::try_from(*port).ok(),
+        Value::Long(port) => u16::try_from(*port).ok(),
+        Value::String(port) => port.parse().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_amqp::{from_slice, primitives::Symbol, to_vec, Value};
+
+    use crate::definitions::{AmqpError, ConnectionError, Error, Fields, LinkError};
+
+    use super::Redirect;
+
+    fn info(entries: &[(&str, Value)]) -> Fields {
+        entries
+            .iter()
+            .map(|(key, value)| (Symbol::from(*key), value.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn parse_connection_redirect_info() {
+        // As sent by a broker on a closing Open
+        let error = Error::new(
+            ConnectionError::Redirect,
+            Some("The container is moved".into()),
+            info(&[
+                ("hostname", Value::from("vhost.example.net")),
+                ("network-host", Value::from("10.0.0.17")),
+                ("port", Value::Uint(5671)),
+            ]),
+        );
+        let buf = to_vec(&error).unwrap();
+        let error: Error = from_slice(&buf).unwrap();
+
+        assert_eq!(
+            error.redirect(),
+            Some(Redirect::new("10.0.0.17", 5671).hostname("vhost.example.net"))
+        );
+    }
+
+    #[test]
+    fn parse_link_redirect_info() {
+        // As sent by Azure Event Hubs when a link is attached to a gateway that doesn't own the
+        // partition
+        let error = Error::new(
+            LinkError::Redirect,
+            None,
+            info(&[
+                ("hostname", Value::from("ns-1.servicebus.windows.net")),
+                (
+                    "network-host",
+                    Value::from("ns-1-partition-3.servicebus.windows.net"),
+                ),
+                ("port", Value::Int(5671)),
+                (
+                    "address",
+                    Value::from("amqps://ns-1.servicebus.windows.net/hub-1/Partitions/3"),
+                ),
+            ]),
+        );
+
+        assert_eq!(
+            error.redirect(),
+            Some(
+                Redirect::new("ns-1-partition-3.servicebus.windows.net", 5671)
+                    .hostname("ns-1.servicebus.windows.net")
+                    .address("amqps://ns-1.servicebus.windows.net/hub-1/Partitions/3")
+            )
+        );
+    }
+
+    #[test]
+    fn lenient_parsing() {
+        let parsed = Redirect::from_info(&info(&[
+            ("hostname", Value::Symbol(Symbol::from("broker-2"))),
+            ("port", Value::from("5672")),
+        ]));
+        assert_eq!(
+            parsed,
+            Some(Redirect::new("broker-2", 5672).hostname("broker-2"))
+        );
+
+        assert_eq!(
+            Redirect::from_info(&info(&[("network-host", Value::from("broker-2"))])),
+            None
+        );
+        assert_eq!(
+            Redirect::from_info(&info(&[
+                ("network-host", Value::from("broker-2")),
+                ("port", Value::Long(70_000)),
+            ])),
+            None
+        );
+    }
+
+    #[test]
+    fn other_conditions_are_not_redirects() {
+        let redirect_info = Redirect::new("broker-2", 5672).into_info();
+        let error = Error::new(AmqpError::NotAllowed, None, redirect_info);
+        assert_eq!(error.redirect(), None);
+
+        let error = Error::new(LinkError::Redirect, None, None);
+        assert_eq!(error.redirect(), None);
+    }
+
+    #[test]
+    fn link_redirect_round_trip() {
+        let redirect = Redirect::new("broker-2", 5672)
+            .hostname("vhost")
+            .address("q2");
+        let error = redirect.clone().into_link_error(Some("moved".into()));
+        let buf = to_vec(&error).unwrap();
+        let decoded: Error = from_slice(&buf).unwrap();
+        assert_eq!(decoded.condition, LinkError::Redirect.into());
+        assert_eq!(decoded.redirect(), Some(redirect));
+    }
+}
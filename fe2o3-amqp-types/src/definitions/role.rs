@@ -53,7 +53,7 @@ struct Visitor {}
 impl<'de> de::Visitor<'de> for Visitor {
     type Value = Role;
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
         formatter.write_str("enum Role")
     }
 
@@ -1,4 +1,5 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
+#![cfg_attr(not(feature = "std"), no_std)]
 #![deny(missing_docs, missing_debug_implementations)]
 
 //! Implements AMQP1.0 data types as defined in the core [specification](http://docs.oasis-open.org/amqp/core/v1.0/os/amqp-core-overview-v1.0-os.html).
@@ -16,9 +17,13 @@
 //! - `"messaging"`: enables the types defined in part 2.7 and part 3 defined in the core specification
 //! - `"transaction"`: enables the types defined in part 4.5 of the core specification
 //! - `"security"`: enables the types defined in part 5 of the core specifiction.
+//! - `"std"`: uses the standard library. Without it, the crate is `no_std` and only requires
+//!   `alloc`, which drops the conversions from/to `HashMap` and `DecodeIntoMessage`.
+//! - `"uuid"`: enables conversion of `MessageId` from `uuid::Uuid`.
 //!
 //! ```toml
 //! default = [
+//!     "std",
 //!     "primitive",
 //!     "transport",
 //!     "messaging",
@@ -87,6 +92,8 @@
 // 41. "sasl-code"
 //
 
+extern crate alloc;
+
 #[cfg_attr(docsrs, doc(cfg(feature = "primitive")))]
 #[cfg(feature = "primitive")]
 pub mod primitives;
@@ -1,9 +1,8 @@
-use std::{
-    borrow::Cow,
-    collections::{BTreeMap, HashMap},
-    rc::Rc,
-    sync::Arc,
+use alloc::{
+    borrow::Cow, boxed::Box, collections::BTreeMap, rc::Rc, string::String, sync::Arc, vec::Vec,
 };
+#[cfg(feature = "std")]
+use std::collections::HashMap;
 
 use serde::{de, ser, Deserialize, Serialize};
 use serde_amqp::{
@@ -21,7 +20,7 @@ use super::AmqpValue;
 use super::{AmqpSequence, Batch, Body, Data};
 
 pub(crate) mod __private {
-    use std::{rc::Rc, sync::Arc};
+    use alloc::{boxed::Box, rc::Rc, sync::Arc};
 
     /// Marker trait for message body.
     ///
@@ -391,7 +390,7 @@ impl<'a> IntoBody for SymbolRef<'a> {
 
 impl<K, V> IntoBody for OrderedMap<K, V>
 where
-    K: ser::Serialize + std::hash::Hash + Eq,
+    K: ser::Serialize + core::hash::Hash + Eq,
     V: ser::Serialize,
 {
     type Body = AmqpValue<Self>;
@@ -401,9 +400,10 @@ where
     }
 }
 
+#[cfg(feature = "std")]
 impl<K, V> IntoBody for HashMap<K, V>
 where
-    K: ser::Serialize + std::hash::Hash + Eq,
+    K: ser::Serialize + core::hash::Hash + Eq,
     V: ser::Serialize,
 {
     type Body = AmqpValue<Self>;
@@ -490,7 +490,7 @@ impl_from_deserializable_or_empty_body!(AmqpValue, T:Deserialize<'de>; Array);
 blanket_impl_from_empty_body!(K, V; OrderedMap);
 impl<'de, K, V> FromBody<'de> for OrderedMap<K, V>
 where
-    K: de::Deserialize<'de> + std::hash::Hash + Eq,
+    K: de::Deserialize<'de> + core::hash::Hash + Eq,
     V: de::Deserialize<'de>,
 {
     type Body = AmqpValue<Self>;
@@ -500,10 +500,12 @@ where
     }
 }
 
+#[cfg(feature = "std")]
 blanket_impl_from_empty_body!(K, V; HashMap);
+#[cfg(feature = "std")]
 impl<'de, K, V> FromBody<'de> for HashMap<K, V>
 where
-    K: de::Deserialize<'de> + std::hash::Hash + Eq,
+    K: de::Deserialize<'de> + core::hash::Hash + Eq,
     V: de::Deserialize<'de>,
 {
     type Body = AmqpValue<Self>;
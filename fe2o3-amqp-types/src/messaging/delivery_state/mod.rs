@@ -3,7 +3,7 @@
 use serde_amqp::macros::{DeserializeComposite, SerializeComposite};
 use serde_amqp::primitives::{Boolean, Uint, Ulong};
 
-use crate::definitions::{Error, Fields};
+use crate::definitions::{fmt_fields, Error, Fields};
 
 #[cfg(feature = "transaction")]
 use crate::transaction::Declared;
@@ -11,7 +11,7 @@ use crate::transaction::Declared;
 use crate::transaction::TransactionalState;
 
 /// 3.4 Delivery State
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DeliveryState {
     /// 3.4.1 Received
     Received(Received),
@@ -272,7 +272,7 @@ impl AsMut<DeliveryState> for DeliveryState {
 mod delivery_state_impl;
 
 /// A terminal delivery state is also referred to as Outcome
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Outcome {
     /// 3.4.2 Accepted
     Accepted(Accepted),
@@ -511,7 +511,7 @@ impl From<Received> for DeliveryState {
 /// <type name="accepted" class="composite" source="list" provides="delivery-state, outcome">
 ///     <descriptor name="amqp:accepted:list" code="0x00000000:0x00000024"/>
 /// </type>
-#[derive(Debug, Clone, DeserializeComposite, SerializeComposite)]
+#[derive(Debug, Clone, PartialEq, Eq, DeserializeComposite, SerializeComposite)]
 #[amqp_contract(
     name = "amqp:accepted:list",
     code = "0x0000_0000:0x0000_0024",
@@ -538,7 +538,7 @@ impl From<Accepted> for Outcome {
 /// <type name="rejected" class="composite" source="list" provides="delivery-state, outcome">
 ///     <descriptor name="amqp:rejected:list" code="0x00000000:0x00000025"/>
 /// </type>
-#[derive(Debug, Clone, DeserializeComposite, SerializeComposite)]
+#[derive(Debug, Clone, PartialEq, Eq, DeserializeComposite, SerializeComposite)]
 #[amqp_contract(
     name = "amqp:rejected:list",
     code = "0x0000_0000:0x0000_0025",
@@ -550,6 +550,17 @@ pub struct Rejected {
     pub error: Option<Error>,
 }
 
+/// Displays the error carried by the outcome, eg.
+/// `Rejected: amqp:not-allowed: quota exceeded {reason: String("Quota")}`
+impl core::fmt::Display for Rejected {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match &self.error {
+            Some(error) => write!(f, "Rejected: {}", error),
+            None => f.write_str("Rejected"),
+        }
+    }
+}
+
 impl From<Rejected> for DeliveryState {
     fn from(value: Rejected) -> Self {
         Self::Rejected(value)
@@ -567,7 +578,7 @@ impl From<Rejected> for Outcome {
 /// <type name="released" class="composite" source="list" provides="delivery-state, outcome">
 ///     <descriptor name="amqp:released:list" code="0x00000000:0x00000026"/>
 /// </type>
-#[derive(Debug, Clone, DeserializeComposite, SerializeComposite)]
+#[derive(Debug, Clone, PartialEq, Eq, DeserializeComposite, SerializeComposite)]
 #[amqp_contract(
     name = "amqp:released:list",
     code = "0x000_0000:0x0000_0026",
@@ -593,7 +604,7 @@ impl From<Released> for Outcome {
 /// <type name="modified" class="composite" source="list" provides="delivery-state, outcome">
 ///     <descriptor name="amqp:modified:list" code="0x00000000:0x00000027"/>
 /// </type>
-#[derive(Debug, Clone, DeserializeComposite, SerializeComposite)]
+#[derive(Debug, Clone, PartialEq, Eq, DeserializeComposite, SerializeComposite)]
 #[amqp_contract(
     name = "amqp:modified:list",
     code = "0x0000_0000:0x0000_0027",
@@ -611,6 +622,32 @@ pub struct Modified {
     pub message_annotations: Option<Fields>,
 }
 
+/// Displays the fields that are present, eg.
+/// `Modified (delivery-failed: true, message-annotations: {x-opt-reason: String("Busy")})`
+impl core::fmt::Display for Modified {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("Modified")?;
+        let mut separator = " (";
+        if let Some(delivery_failed) = self.delivery_failed {
+            write!(f, "{}delivery-failed: {}", separator, delivery_failed)?;
+            separator = ", ";
+        }
+        if let Some(undeliverable_here) = self.undeliverable_here {
+            write!(f, "{}undeliverable-here: {}", separator, undeliverable_here)?;
+            separator = ", ";
+        }
+        if let Some(message_annotations) = &self.message_annotations {
+            write!(f, "{}message-annotations: ", separator)?;
+            fmt_fields(message_annotations, f)?;
+            separator = ", ";
+        }
+        match separator {
+            ", " => f.write_str(")"),
+            _ => Ok(()),
+        }
+    }
+}
+
 impl From<Modified> for DeliveryState {
     fn from(value: Modified) -> Self {
         Self::Modified(value)
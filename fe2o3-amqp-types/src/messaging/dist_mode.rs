@@ -1,4 +1,4 @@
-use std::convert::{TryFrom, TryInto};
+use core::convert::{TryFrom, TryInto};
 
 use serde::{
     de::{self},
@@ -11,7 +11,7 @@ use serde_amqp::primitives::Symbol;
 /// <type name="std-dist-mode" class="restricted" source="symbol" provides="distribution-mode">
 /// </type>
 ///
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DistributionMode {
     /// <choice name="move" value="move"/>
     Move,
@@ -1,11 +1,12 @@
-use std::fmt::Display;
+use alloc::vec::Vec;
+use core::fmt::Display;
 
 use serde::{de, ser, Serialize};
 use serde_amqp::{DeserializeComposite, SerializeComposite};
 
 use crate::messaging::{
-    Batch, DeserializableBody, FromBody, FromEmptyBody, IntoBody, SerializableBody,
-    TransposeOption, __private::BodySection,
+    __private::BodySection, Batch, DeserializableBody, FromBody, FromEmptyBody, IntoBody,
+    SerializableBody, TransposeOption,
 };
 
 /// 3.2.7 AMQP Sequence
@@ -42,7 +43,7 @@ impl<T> Display for AmqpSequence<T>
 where
     T: Display,
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.write_str("AmqpSequence([")?;
         let len = self.0.len();
         for (i, val) in self.0.iter().enumerate() {
@@ -1,6 +1,7 @@
 //! Implements 3.2.10 Annotations
 
-use std::{
+use alloc::string::String;
+use core::{
     borrow::Borrow,
     hash::{Hash, Hasher},
 };
@@ -10,10 +11,10 @@ use serde::{
     Serialize,
 };
 use serde_amqp::{
+    __constants::VALUE,
     format_code::EncodingCodes,
     primitives::{OrderedMap, Symbol, SymbolRef, Ulong},
     Value,
-    __constants::VALUE,
 };
 
 /// 3.2.10 Annotations
@@ -114,7 +115,7 @@ struct FieldVisitor {}
 impl<'de> de::Visitor<'de> for FieldVisitor {
     type Value = Field;
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
         formatter.write_str("OwnedKey variant")
     }
 
@@ -149,7 +150,7 @@ struct Visitor {}
 impl<'de> de::Visitor<'de> for Visitor {
     type Value = OwnedKey;
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
         formatter.write_str("OwnedKey")
     }
 
@@ -265,7 +266,7 @@ impl<'a> PartialEq for (dyn AnnotationKey + 'a) {
 impl<'a> Eq for (dyn AnnotationKey + 'a) {}
 
 impl<'a> PartialOrd for (dyn AnnotationKey + 'a) {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
         // self.key().partial_cmp(&other.key())
 
         // clippy::non_canonical_partial_ord_impl
@@ -278,7 +279,7 @@ impl<'a> PartialOrd for (dyn AnnotationKey + 'a) {
 }
 
 impl<'a> Ord for (dyn AnnotationKey + 'a) {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
         self.key().cmp(&other.key())
     }
 }
@@ -1,10 +1,12 @@
-use std::{borrow::Cow, fmt::Display};
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+use core::fmt::Display;
 
 use serde_amqp::{primitives::Binary, DeserializeComposite, SerializeComposite, Value};
 
 use crate::messaging::{
-    Batch, DeserializableBody, FromBody, FromEmptyBody, IntoBody, SerializableBody,
-    TransposeOption, __private::BodySection,
+    __private::BodySection, Batch, DeserializableBody, FromBody, FromEmptyBody, IntoBody,
+    SerializableBody, TransposeOption,
 };
 
 /// 3.2.6 Data
@@ -64,7 +66,7 @@ impl TryFrom<Value> for Data {
 }
 
 impl Display for Data {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "Data of length: {}", self.0.len())
     }
 }
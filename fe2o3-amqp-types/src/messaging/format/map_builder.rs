@@ -1,6 +1,7 @@
 //! AnnotationBuilder for types that are simply a wrapper around Annotation
 
-use std::{hash::Hash, marker::PhantomData};
+use alloc::string::String;
+use core::{hash::Hash, marker::PhantomData};
 
 use serde_amqp::{primitives::OrderedMap, Value};
 
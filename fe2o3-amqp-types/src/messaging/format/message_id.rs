@@ -1,5 +1,6 @@
 //! Message ID
 
+use alloc::{string::String, vec::Vec};
 use serde::{
     de::{self, VariantAccess},
     Serialize,
@@ -54,6 +55,31 @@ impl From<String> for MessageId {
     }
 }
 
+impl From<&str> for MessageId {
+    fn from(value: &str) -> Self {
+        Self::String(String::from(value))
+    }
+}
+
+impl From<Vec<u8>> for MessageId {
+    fn from(value: Vec<u8>) -> Self {
+        Self::Binary(Binary::from(value))
+    }
+}
+
+impl From<&[u8]> for MessageId {
+    fn from(value: &[u8]) -> Self {
+        Self::Binary(Binary::from(value))
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl From<uuid::Uuid> for MessageId {
+    fn from(value: uuid::Uuid) -> Self {
+        Self::Uuid(Uuid::from(value))
+    }
+}
+
 impl Serialize for MessageId {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -80,7 +106,7 @@ struct FieldVisitor {}
 impl<'de> de::Visitor<'de> for FieldVisitor {
     type Value = Field;
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
         formatter.write_str("MessageId variant")
     }
 
@@ -117,7 +143,7 @@ struct Visitor {}
 impl<'de> de::Visitor<'de> for Visitor {
     type Value = MessageId;
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
         formatter.write_str("enum MessageId")
     }
 
@@ -167,7 +193,7 @@ mod tests {
         to_vec,
     };
 
-    use crate::messaging::MessageId;
+    use crate::messaging::{MessageId, Properties};
 
     #[test]
     fn test_message_id_ulong() {
@@ -200,4 +226,130 @@ mod tests {
         let deserialized: MessageId = from_slice(&buf).unwrap();
         assert_eq!(id, deserialized);
     }
+
+    #[test]
+    fn message_id_from_rust_types() {
+        assert_eq!(MessageId::from(7u64), MessageId::Ulong(7));
+        assert_eq!(
+            MessageId::from("id-1"),
+            MessageId::String(String::from("id-1"))
+        );
+        assert_eq!(
+            MessageId::from(vec![1u8, 2]),
+            MessageId::Binary(Binary::from(vec![1u8, 2]))
+        );
+        assert_eq!(
+            MessageId::from(&[1u8, 2][..]),
+            MessageId::Binary(Binary::from(vec![1u8, 2]))
+        );
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn message_id_from_uuid() {
+        let uuid = uuid::Uuid::from_bytes([0x11; 16]);
+        assert_eq!(
+            MessageId::from(uuid),
+            MessageId::Uuid(Uuid::from([0x11u8; 16]))
+        );
+    }
+
+    #[test]
+    fn each_variant_is_encoded_with_its_own_type() {
+        assert_eq!(to_vec(&MessageId::Ulong(0)).unwrap(), [0x44]);
+        assert_eq!(to_vec(&MessageId::Ulong(42)).unwrap(), [0x53, 42]);
+        assert_eq!(
+            to_vec(&MessageId::Uuid(Uuid::from([7u8; 16]))).unwrap()[0],
+            0x98
+        );
+        assert_eq!(
+            to_vec(&MessageId::Binary(Binary::from("ab"))).unwrap(),
+            [0xa0, 2, b'a', b'b']
+        );
+        assert_eq!(
+            to_vec(&MessageId::from("ab")).unwrap(),
+            [0xa1, 2, b'a', b'b']
+        );
+    }
+
+    #[test]
+    fn each_wire_form_decodes_to_its_variant() {
+        let cases: &[(&[u8], MessageId)] = &[
+            (&[0x44], MessageId::Ulong(0)),
+            (&[0x53, 0x2a], MessageId::Ulong(42)),
+            (&[0x80, 0, 0, 0, 0, 0, 0, 0x01, 0x00], MessageId::Ulong(256)),
+            (
+                &[0xa0, 2, 1, 2],
+                MessageId::Binary(Binary::from(vec![1u8, 2])),
+            ),
+            (
+                &[0xb0, 0, 0, 0, 2, 1, 2],
+                MessageId::Binary(Binary::from(vec![1u8, 2])),
+            ),
+            (&[0xa1, 2, b'i', b'd'], MessageId::from("id")),
+            (&[0xb1, 0, 0, 0, 2, b'i', b'd'], MessageId::from("id")),
+        ];
+        for (buf, expected) in cases {
+            let decoded: MessageId = from_slice(buf).unwrap();
+            assert_eq!(&decoded, expected);
+        }
+
+        // Only the four types that provide "message-id" are accepted
+        assert!(from_slice::<MessageId>(&[0x52, 0x2a]).is_err());
+        assert!(from_slice::<MessageId>(&[0xa3, 2, b'i', b'd']).is_err());
+    }
+
+    #[test]
+    fn properties_with_uuid_ids_from_broker() {
+        // Properties section of a message sent by a .NET client through a broker, which sets both
+        // the message-id and the correlation-id to a uuid
+        let message_id = [
+            0x6f, 0x3c, 0x2a, 0x1e, 0x8b, 0x4d, 0x4e, 0x0a, 0x9c, 0x55, 0x12, 0x9e, 0x3d, 0x07,
+            0xa1, 0xb2,
+        ];
+        let correlation_id = [
+            0x0d, 0x1f, 0x5e, 0x77, 0x3a, 0x90, 0x41, 0xc8, 0xb6, 0x2e, 0x44, 0x8f, 0x61, 0x05,
+            0xd9, 0x3e,
+        ];
+        let mut buf = vec![0x00, 0x53, 0x73, 0xc0, 42, 6, 0x98];
+        buf.extend_from_slice(&message_id);
+        buf.extend_from_slice(&[0x40, 0xa1, 0x02, b'q', b'1', 0x40, 0x40, 0x98]);
+        buf.extend_from_slice(&correlation_id);
+
+        let properties: Properties = from_slice(&buf).unwrap();
+        assert_eq!(
+            properties.message_id,
+            Some(MessageId::Uuid(Uuid::from(message_id)))
+        );
+        assert_eq!(
+            properties.correlation_id,
+            Some(MessageId::Uuid(Uuid::from(correlation_id)))
+        );
+        assert_eq!(properties.to.as_deref(), Some("q1"));
+
+        // Re-encoding keeps the uuid type
+        assert_eq!(to_vec(&properties).unwrap(), buf);
+    }
+
+    #[test]
+    fn properties_round_trip_each_variant() {
+        let ids = [
+            MessageId::from(u64::MAX),
+            MessageId::from(Uuid::from([0xabu8; 16])),
+            MessageId::from(vec![0u8, 1, 2, 3]),
+            MessageId::from("message-1"),
+        ];
+        for message_id in ids.iter() {
+            for correlation_id in ids.iter() {
+                let properties = Properties::builder()
+                    .message_id(message_id.clone())
+                    .correlation_id(correlation_id.clone())
+                    .build();
+                let buf = to_vec(&properties).unwrap();
+                let decoded: Properties = from_slice(&buf).unwrap();
+                assert_eq!(decoded.message_id.as_ref(), Some(message_id));
+                assert_eq!(decoded.correlation_id.as_ref(), Some(correlation_id));
+            }
+        }
+    }
 }
@@ -1,3 +1,4 @@
+use alloc::{boxed::Box, format, vec};
 use serde::{
     de::{self, VariantAccess},
     ser,
@@ -14,7 +15,7 @@ use crate::definitions::Fields;
 /// <type name="delete-on-close" class="composite" source="list" provides="lifetime-policy">
 ///     <descriptor name="amqp:delete-on-close:list" code="0x00000000:0x0000002b"/>
 /// </type>
-#[derive(Debug, Clone, SerializeComposite, DeserializeComposite, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, SerializeComposite, DeserializeComposite, Default)]
 #[amqp_contract(
     name = "amqp:delete-on-close:list",
     code = "0x0000_0000:0x0000_002b",
@@ -51,7 +52,7 @@ impl From<DeleteOnClose> for Value {
 // <type name="delete-on-no-links" class="composite" source="list" provides="lifetime-policy">
 //     <descriptor name="amqp:delete-on-no-links:list" code="0x00000000:0x0000002c"/>
 // </type>
-#[derive(Debug, Clone, SerializeComposite, DeserializeComposite, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, SerializeComposite, DeserializeComposite, Default)]
 #[amqp_contract(
     name = "amqp:delete-on-no-links:list",
     code = "0x0000_0000:0x0000_002c",
@@ -88,7 +89,7 @@ impl From<DeleteOnNoLinks> for Value {
 /// <type name="delete-on-no-messages" class="composite" source="list" provides="lifetime-policy">
 ///     <descriptor name="amqp:delete-on-no-messages:list" code="0x00000000:0x0000002d"/>
 /// </type>
-#[derive(Debug, Clone, SerializeComposite, DeserializeComposite, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, SerializeComposite, DeserializeComposite, Default)]
 #[amqp_contract(
     name = "amqp:delete-on-no-messages:list",
     code = "0x0000_0000:0x0000_002d",
@@ -125,7 +126,7 @@ impl From<DeleteOnNoMessages> for Value {
 /// <type name="delete-on-no-links-or-messages" class="composite" source="list" provides="lifetime-policy">
 ///     <descriptor name="amqp:delete-on-no-links-or-messages:list" code="0x00000000:0x0000002e"/>
 /// </type>
-#[derive(Debug, Clone, SerializeComposite, DeserializeComposite, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, SerializeComposite, DeserializeComposite, Default)]
 #[amqp_contract(
     name = "amqp:delete-on-no-links-or-messages:list",
     code = "0x0000_0000:0x0000_002e",
@@ -167,7 +168,7 @@ impl From<DeleteOnNoLinksOrMessages> for Value {
 /// delete-on-no-messages or delete-on-no-links-or-messages.
 ///
 /// TODO: impl Into Fields
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum LifetimePolicy {
     /// 3.5.10 Delete On Close
     /// Lifetime of dynamic node scoped to lifetime of link which caused creation.
@@ -224,7 +225,7 @@ struct FieldVisitor {}
 impl<'de> de::Visitor<'de> for FieldVisitor {
     type Value = Field;
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
         formatter.write_str("enum LifetimePolicy")
     }
 
@@ -278,7 +279,7 @@ struct Visitor {}
 impl<'de> de::Visitor<'de> for Visitor {
     type Value = LifetimePolicy;
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
         formatter.write_str("enum LifetimePolicy")
     }
 
@@ -1,4 +1,5 @@
-use std::{fmt::Display, marker::PhantomData};
+use alloc::{vec, vec::Vec};
+use core::{fmt::Display, marker::PhantomData};
 
 use serde::{
     de::{self, VariantAccess},
@@ -7,8 +8,8 @@ use serde::{
 use serde_amqp::{primitives::Binary, Value};
 
 use crate::messaging::{
-    AmqpSequence, AmqpValue, Batch, Data, DeserializableBody, FromBody, FromEmptyBody, IntoBody,
-    SerializableBody, TransposeOption, __private::BodySection,
+    __private::BodySection, AmqpSequence, AmqpValue, Batch, Data, DeserializableBody, FromBody,
+    FromEmptyBody, IntoBody, SerializableBody, TransposeOption,
 };
 
 /// The body consists of one of the following three choices: one or more data sections, one or more
@@ -37,7 +38,33 @@ pub enum Body<T> {
     Empty,
 }
 
+/// Kind of the body of a message, which tells how the body is encoded without decoding it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BodyKind {
+    /// One or more data sections
+    Data,
+
+    /// One or more amqp-sequence sections
+    Sequence,
+
+    /// A single amqp-value section
+    Value,
+
+    /// There is no body section at all
+    Empty,
+}
+
 impl<T> Body<T> {
+    /// Kind of the body
+    pub fn kind(&self) -> BodyKind {
+        match self {
+            Body::Value(_) => BodyKind::Value,
+            Body::Data(_) => BodyKind::Data,
+            Body::Sequence(_) => BodyKind::Sequence,
+            Body::Empty => BodyKind::Empty,
+        }
+    }
+
     /// Whether the body section is a [`Data`]
     pub fn is_data(&self) -> bool {
         matches!(self, Body::Data(_))
@@ -117,7 +144,7 @@ impl<T> Display for Body<T>
 where
     T: Display,
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match &self {
             Body::Value(val) => write!(f, "{}", val),
             Body::Data(_) => write!(f, "Data"),
@@ -183,7 +210,7 @@ enum Field {
 impl<'de> de::Visitor<'de> for FieldVisitor {
     type Value = Field;
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
         formatter.write_str("Body variant. One of Vec<Data>, Vec<AmqpSequence>, AmqpValue")
     }
 
@@ -231,7 +258,7 @@ where
 {
     type Value = Body<T>;
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
         formatter.write_str("enum Body")
     }
 
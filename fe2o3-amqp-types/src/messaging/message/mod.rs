@@ -1,6 +1,8 @@
 //! Implementation of Message as defined in AMQP 1.0 protocol Part 3.2
 
-use std::{io, marker::PhantomData};
+use core::marker::PhantomData;
+#[cfg(feature = "std")]
+use std::io;
 
 use serde::{
     de::{self},
@@ -10,13 +12,16 @@ use serde::{
 use serde_amqp::__constants::{DESCRIBED_BASIC, DESCRIPTOR};
 
 use super::{
-    AmqpSequence, AmqpValue, ApplicationProperties, Batch, Data, DeliveryAnnotations, Footer,
-    FromBody, Header, IntoBody, MessageAnnotations, Properties, SerializableBody,
+    annotations::OwnedKey, AmqpSequence, AmqpValue, ApplicationProperties, Batch, Data,
+    DeliveryAnnotations, Footer, FromBody, Header, IntoBody, MessageAnnotations, Modified,
+    Properties, SerializableBody,
 };
 
 mod body;
 pub use body::*;
 
+pub mod sections;
+
 #[doc(hidden)]
 pub mod __private {
     #[derive(Debug)]
@@ -35,6 +40,8 @@ use __private::{Deserializable, Serializable};
 ///
 /// 1. avoid confusion
 /// 2. The decoder type `T` itself is also the returned type
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
 pub trait DecodeIntoMessage: Sized {
     /// Error type associated with decoding
     type DecodeError;
@@ -43,6 +50,7 @@ pub trait DecodeIntoMessage: Sized {
     fn decode_into_message(reader: impl io::Read) -> Result<Message<Self>, Self::DecodeError>;
 }
 
+#[cfg(feature = "std")]
 impl<T> DecodeIntoMessage for T
 where
     for<'de> T: FromBody<'de>,
@@ -162,6 +170,39 @@ impl<T> Message<T> {
         }
     }
 
+    /// Removes the delivery annotations from the message and returns them.
+    ///
+    /// Delivery annotations are only meant for the immediate next hop, and an intermediary
+    /// should strip them before forwarding the message. Please see
+    /// [`sections`](crate::messaging::message::sections) to strip them from an encoded message
+    /// without encoding the other sections again.
+    pub fn strip_delivery_annotations(&mut self) -> Option<DeliveryAnnotations> {
+        self.delivery_annotations.take()
+    }
+
+    /// Applies a [`Modified`] outcome to the message before it is delivered again.
+    ///
+    /// The message annotations of the outcome are combined with the ones of the message. An
+    /// annotation whose key is already in the message replaces the existing value, and the other
+    /// annotations are added. If `delivery_failed` is set, the `delivery_count` of the header is
+    /// incremented, and a default header is added first if the message has none.
+    pub fn apply_modified(&mut self, modified: &Modified) {
+        if let Some(true) = modified.delivery_failed {
+            let header = self.header.get_or_insert_with(Header::default);
+            header.delivery_count = header.delivery_count.saturating_add(1);
+        }
+        if let Some(annotations) = &modified.message_annotations {
+            let message_annotations = self
+                .message_annotations
+                .get_or_insert_with(MessageAnnotations::default);
+            for (key, value) in annotations.iter() {
+                message_annotations
+                    .0
+                    .insert(OwnedKey::from(key.clone()), value.clone());
+            }
+        }
+    }
+
     /// Map body to SerializableBody
     pub fn map_body<F, B>(self, op: F) -> Message<B>
     where
@@ -227,7 +268,7 @@ struct FieldVisitor {}
 impl<'de> de::Visitor<'de> for FieldVisitor {
     type Value = Field;
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
         formatter.write_str("Field")
     }
 
@@ -285,7 +326,7 @@ where
 {
     type Value = Message<B>;
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
         formatter.write_str("struct Message")
     }
 
@@ -610,12 +651,13 @@ mod tests {
     use serde_bytes::ByteBuf;
 
     use crate::messaging::{
+        annotations::OwnedKey,
         message::{
             Body,
             __private::{Deserializable, Serializable},
         },
         AmqpSequence, AmqpValue, ApplicationProperties, Batch, Data, DeliveryAnnotations, Footer,
-        Header, MessageAnnotations, Properties,
+        Header, MessageAnnotations, Modified, Properties,
     };
 
     use super::Message;
@@ -936,4 +978,106 @@ mod tests {
             .build();
         assert_eq!(message.0, expected);
     }
+
+    #[test]
+    fn test_strip_delivery_annotations() {
+        let delivery_annotations = DeliveryAnnotations::builder().insert("key", 1i32).build();
+        let mut message = Message::builder()
+            .delivery_annotations(delivery_annotations.clone())
+            .value("hello")
+            .build();
+        assert_eq!(message.sections(), 2);
+
+        assert_eq!(
+            message.strip_delivery_annotations(),
+            Some(delivery_annotations)
+        );
+        assert_eq!(message.sections(), 1);
+        assert_eq!(message.strip_delivery_annotations(), None);
+    }
+
+    #[test]
+    fn test_deserialize_message_reports_location_of_corrupted_value() {
+        let message = Message::builder()
+            .application_properties(
+                ApplicationProperties::builder()
+                    .insert("a", 1i32)
+                    .insert("b", 2i32)
+                    .build(),
+            )
+            .value(true)
+            .build();
+        let mut buf = to_vec(&Serializable(message)).unwrap();
+
+        // Replace the format code of the value of "b"
+        let offset = buf
+            .windows(5)
+            .position(|w| w == [0xa1, 0x01, b'b', 0x54, 0x02])
+            .unwrap()
+            + 3;
+        buf[offset] = 0xff;
+
+        let err = from_slice::<Deserializable<Message<Body<Value>>>>(&buf).unwrap_err();
+        assert_eq!(err.offset(), Some(offset));
+        assert!(err.to_string().ends_with(&format!(
+            "at byte offset {} (root > [1] > value[1])",
+            offset
+        )));
+    }
+
+    #[test]
+    fn test_apply_modified_merges_annotations() {
+        let mut message = Message::builder()
+            .message_annotations(
+                MessageAnnotations::builder()
+                    .insert("x-retry-count", 1u32)
+                    .insert("x-origin", "orders")
+                    .build(),
+            )
+            .value("hello")
+            .build();
+        assert!(message.header.is_none());
+
+        let mut annotations = crate::definitions::Fields::new();
+        annotations.insert("x-retry-count".into(), Value::from(2u32));
+        annotations.insert("x-reason".into(), Value::from("timeout"));
+        let modified = Modified {
+            delivery_failed: Some(true),
+            undeliverable_here: Some(true),
+            message_annotations: Some(annotations),
+        };
+        message.apply_modified(&modified);
+
+        // Existing keys are replaced and new keys are added
+        let message_annotations = message.message_annotations.as_ref().unwrap();
+        assert_eq!(message_annotations.len(), 3);
+        assert_eq!(
+            message_annotations.get(&OwnedKey::from("x-retry-count")),
+            Some(&Value::from(2u32))
+        );
+        assert_eq!(
+            message_annotations.get(&OwnedKey::from("x-origin")),
+            Some(&Value::from("orders"))
+        );
+        assert_eq!(
+            message_annotations.get(&OwnedKey::from("x-reason")),
+            Some(&Value::from("timeout"))
+        );
+        assert_eq!(message.header.as_ref().unwrap().delivery_count, 1);
+
+        // The delivery-count is only incremented for a failed delivery
+        let modified = Modified {
+            delivery_failed: None,
+            undeliverable_here: None,
+            message_annotations: None,
+        };
+        message.apply_modified(&modified);
+        assert_eq!(message.header.as_ref().unwrap().delivery_count, 1);
+        assert_eq!(message.message_annotations.as_ref().unwrap().len(), 3);
+
+        let mut message = Message::builder().value("hello").build();
+        message.apply_modified(&modified);
+        assert!(message.header.is_none());
+        assert!(message.message_annotations.is_none());
+    }
 }
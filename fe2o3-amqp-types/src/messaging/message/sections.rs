@@ -0,0 +1,473 @@
+//! Section boundaries of an encoded message
+//!
+//! An intermediary that forwards a message may need to change one of its sections, eg. strip the
+//! delivery annotations which are only meant for the immediate next hop. Decoding the message and
+//! encoding it again does not guarantee that the other sections are encoded with the same bytes
+//! (eg. a `list8` may become a `list32`), so this module locates the sections in the encoded
+//! message instead, and the other sections can be copied as is.
+
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use serde::de::Error as _;
+use serde_amqp::{io, Error};
+
+use crate::messaging::{
+    AmqpSequence, AmqpValue, ApplicationProperties, BodyKind, Data, DeliveryAnnotations, Footer,
+    Header, MessageAnnotations, Properties,
+};
+
+/// Kind of a message section, which is identified by the descriptor of the section
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SectionKind {
+    /// 3.2.1 Header
+    Header,
+
+    /// 3.2.2 Delivery Annotations
+    DeliveryAnnotations,
+
+    /// 3.2.3 Message Annotations
+    MessageAnnotations,
+
+    /// 3.2.4 Properties
+    Properties,
+
+    /// 3.2.5 Application Properties
+    ApplicationProperties,
+
+    /// 3.2.6 Data
+    Data,
+
+    /// 3.2.7 AMQP Sequence
+    AmqpSequence,
+
+    /// 3.2.8 AMQP Value
+    AmqpValue,
+
+    /// 3.2.9 Footer
+    Footer,
+}
+
+impl SectionKind {
+    fn from_code(code: u64) -> Option<Self> {
+        let kind = match code {
+            0x0000_0000_0000_0070 => SectionKind::Header,
+            0x0000_0000_0000_0071 => SectionKind::DeliveryAnnotations,
+            0x0000_0000_0000_0072 => SectionKind::MessageAnnotations,
+            0x0000_0000_0000_0073 => SectionKind::Properties,
+            0x0000_0000_0000_0074 => SectionKind::ApplicationProperties,
+            0x0000_0000_0000_0075 => SectionKind::Data,
+            0x0000_0000_0000_0076 => SectionKind::AmqpSequence,
+            0x0000_0000_0000_0077 => SectionKind::AmqpValue,
+            0x0000_0000_0000_0078 => SectionKind::Footer,
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    fn from_name(name: &[u8]) -> Option<Self> {
+        let kind = match name {
+            b"amqp:header:list" => SectionKind::Header,
+            b"amqp:delivery-annotations:map" => SectionKind::DeliveryAnnotations,
+            b"amqp:message-annotations:map" => SectionKind::MessageAnnotations,
+            b"amqp:properties:list" => SectionKind::Properties,
+            b"amqp:application-properties:map" => SectionKind::ApplicationProperties,
+            b"amqp:data:binary" => SectionKind::Data,
+            b"amqp:amqp-sequence:list" => SectionKind::AmqpSequence,
+            b"amqp:amqp-value:*" => SectionKind::AmqpValue,
+            b"amqp:footer:map" => SectionKind::Footer,
+            _ => return None,
+        };
+        Some(kind)
+    }
+}
+
+impl SectionKind {
+    /// Whether the section is a part of the message body, which consists of one or more data
+    /// sections, one or more amqp-sequence sections, or a single amqp-value section
+    pub fn is_body(&self) -> bool {
+        self.body_kind().is_some()
+    }
+
+    /// Kind of the body that the section is a part of, or `None` if the section is not a part of
+    /// the message body
+    pub fn body_kind(&self) -> Option<BodyKind> {
+        match self {
+            SectionKind::Data => Some(BodyKind::Data),
+            SectionKind::AmqpSequence => Some(BodyKind::Sequence),
+            SectionKind::AmqpValue => Some(BodyKind::Value),
+            _ => None,
+        }
+    }
+}
+
+/// A type that is encoded as a message section
+pub trait MessageSection {
+    /// Kind of the encoded section
+    const KIND: SectionKind;
+}
+
+macro_rules! impl_message_section {
+    ($($ty:ty => $kind:ident),*) => {
+        $(
+            impl MessageSection for $ty {
+                const KIND: SectionKind = SectionKind::$kind;
+            }
+        )*
+    };
+}
+
+impl_message_section!(
+    Header => Header,
+    DeliveryAnnotations => DeliveryAnnotations,
+    MessageAnnotations => MessageAnnotations,
+    Properties => Properties,
+    ApplicationProperties => ApplicationProperties,
+    Data => Data,
+    Footer => Footer
+);
+
+impl<T> MessageSection for AmqpSequence<T> {
+    const KIND: SectionKind = SectionKind::AmqpSequence;
+}
+
+impl<T> MessageSection for AmqpValue<T> {
+    const KIND: SectionKind = SectionKind::AmqpValue;
+}
+
+/// A section of an encoded message
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EncodedSection {
+    /// Kind of the section
+    pub kind: SectionKind,
+
+    /// Range of the encoded section (including the descriptor) in the encoded message
+    pub range: Range<usize>,
+}
+
+/// Locates the sections of an encoded message in the order they appear.
+///
+/// Only the constructors and the size prefixes are read, the values of the sections are not
+/// decoded. An error is returned if a section is truncated or if its descriptor is not one of the
+/// message sections.
+pub fn sections(bytes: &[u8]) -> Result<Vec<EncodedSection>, Error> {
+    let mut sections = Vec::new();
+    let mut start = 0;
+    while start < bytes.len() {
+        let section = section_at(bytes, start)?;
+        start = section.range.end;
+        sections.push(section);
+    }
+    Ok(sections)
+}
+
+/// Locates the binary of a message body that is a single data section, without decoding any of
+/// the sections
+///
+/// Only the common encoding of the data section is recognized, which is a `vbin8` or `vbin32`
+/// described by the numeric code of the section. `None` is returned for anything else, eg. a
+/// body of several data sections, a data section described by its symbolic name or a malformed
+/// message, and the message should then be decoded as usual.
+pub fn data_range(bytes: &[u8]) -> Option<Range<usize>> {
+    let mut data = None;
+    let mut start = 0;
+    while start < bytes.len() {
+        let section = section_at(bytes, start).ok()?;
+        match section.kind {
+            SectionKind::Data if data.is_none() => data = Some(section.range.clone()),
+            // Another data section or another kind of body
+            kind if kind.is_body() => return None,
+            _ => {}
+        }
+        start = section.range.end;
+    }
+
+    let range = data?;
+    let value = match bytes[range.start + 1] {
+        // smallulong
+        0x53 => range.start + 3,
+        // ulong
+        0x80 => range.start + 10,
+        _ => return None,
+    };
+    // The size of the binary is checked when the section is located, so the binary ends with the
+    // section
+    match bytes.get(value)? {
+        0xa0 => Some(value + 2..range.end),
+        0xb0 => Some(value + 5..range.end),
+        _ => None,
+    }
+}
+
+/// Locates the delivery annotations section of an encoded message
+pub fn delivery_annotations_range(bytes: &[u8]) -> Result<Option<Range<usize>>, Error> {
+    let range = sections(bytes)?
+        .into_iter()
+        .find(|section| section.kind == SectionKind::DeliveryAnnotations)
+        .map(|section| section.range);
+    Ok(range)
+}
+
+/// Finds the kind of the body of an encoded message without decoding the body
+///
+/// [`BodyKind::Empty`] is returned if the message has no body section.
+pub fn body_kind(bytes: &[u8]) -> Result<BodyKind, Error> {
+    let kind = sections(bytes)?
+        .iter()
+        .find_map(|section| section.kind.body_kind())
+        .unwrap_or(BodyKind::Empty);
+    Ok(kind)
+}
+
+/// Locates the section that starts at `start`
+fn section_at(bytes: &[u8], start: usize) -> Result<EncodedSection, Error> {
+    let section = &bytes[start..];
+    if section.first() != Some(&0x00) {
+        return Err(Error::InvalidFormatCode);
+    }
+    let descriptor_len = primitive_len(&section[1..])?;
+    let kind = section_kind(&section[1..1 + descriptor_len])?;
+    let len = encoded_len(section)?;
+    Ok(EncodedSection {
+        kind,
+        range: start..start + len,
+    })
+}
+
+fn section_kind(descriptor: &[u8]) -> Result<SectionKind, Error> {
+    let kind = match descriptor {
+        // smallulong
+        [0x53, code] => SectionKind::from_code(*code as u64),
+        // ulong
+        [0x80, code @ ..] => code
+            .try_into()
+            .ok()
+            .and_then(|code| SectionKind::from_code(u64::from_be_bytes(code))),
+        // sym8
+        [0xa3, _, name @ ..] => SectionKind::from_name(name),
+        // sym32
+        [0xb3, _, _, _, _, name @ ..] => SectionKind::from_name(name),
+        _ => None,
+    };
+    kind.ok_or_else(|| Error::custom("Unknown identifier"))
+}
+
+/// Length of the encoded value at the start of `bytes`, including its constructor
+fn encoded_len(bytes: &[u8]) -> Result<usize, Error> {
+    // A described value may itself be described. Loop instead of recursing so that the stack is
+    // not exhausted by malformed input
+    let mut offset = 0;
+    loop {
+        match bytes.get(offset) {
+            Some(0x00) => {
+                offset += 1;
+                // A descriptor must be a primitive value
+                offset += primitive_len(&bytes[offset..])?;
+            }
+            Some(_) => return Ok(offset + primitive_len(&bytes[offset..])?),
+            None => return Err(unexpected_eof("Truncated described value")),
+        }
+    }
+}
+
+/// Length of the encoded primitive value at the start of `bytes`, including its format code
+fn primitive_len(bytes: &[u8]) -> Result<usize, Error> {
+    let code = *bytes
+        .first()
+        .ok_or_else(|| unexpected_eof("Expecting a format code"))?;
+
+    // The width of a value is determined by the subcategory of its format code (Part 1.2)
+    let len = match code >> 4 {
+        0x4 => 1,
+        0x5 => 2,
+        0x6 => 3,
+        0x7 => 5,
+        0x8 => 9,
+        0x9 => 17,
+        0xa | 0xc | 0xe => {
+            let size = bytes
+                .get(1)
+                .ok_or_else(|| unexpected_eof("Expecting a size"))?;
+            2 + *size as usize
+        }
+        0xb | 0xd | 0xf => {
+            let size: [u8; 4] = bytes
+                .get(1..5)
+                .and_then(|size| size.try_into().ok())
+                .ok_or_else(|| unexpected_eof("Expecting a size"))?;
+            5 + u32::from_be_bytes(size) as usize
+        }
+        _ => return Err(Error::InvalidFormatCode),
+    };
+
+    match len <= bytes.len() {
+        true => Ok(len),
+        false => Err(unexpected_eof("Truncated value")),
+    }
+}
+
+fn unexpected_eof(msg: &'static str) -> Error {
+    Error::Io(io::Error::new(io::ErrorKind::UnexpectedEof, msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_amqp::{primitives::Symbol, to_vec};
+
+    use crate::messaging::{
+        message::__private::Serializable, AmqpSequence, AmqpValue, ApplicationProperties, BodyKind,
+        Data, DeliveryAnnotations, Footer, Header, Message, Properties,
+    };
+
+    use super::{
+        body_kind, data_range, delivery_annotations_range, sections, MessageSection, SectionKind,
+    };
+
+    fn message() -> Message<AmqpValue<&'static str>> {
+        Message::builder()
+            .header(Header::builder().durable(true).build())
+            .delivery_annotations(DeliveryAnnotations::builder().insert("key", 1i32).build())
+            .properties(Properties::builder().message_id(7u64).build())
+            .value("hello")
+            .build()
+    }
+
+    #[test]
+    fn test_sections_of_encoded_message() {
+        let message = message();
+        let header = to_vec(&message.header).unwrap();
+        let delivery_annotations = to_vec(&message.delivery_annotations).unwrap();
+        let properties = to_vec(&message.properties).unwrap();
+        let bytes = to_vec(&Serializable(message)).unwrap();
+
+        let sections = sections(&bytes).unwrap();
+        let kinds: Vec<_> = sections.iter().map(|section| section.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                SectionKind::Header,
+                SectionKind::DeliveryAnnotations,
+                SectionKind::Properties,
+                SectionKind::AmqpValue
+            ]
+        );
+        assert_eq!(&bytes[sections[0].range.clone()], &header[..]);
+        assert_eq!(&bytes[sections[1].range.clone()], &delivery_annotations[..]);
+        assert_eq!(&bytes[sections[2].range.clone()], &properties[..]);
+        assert_eq!(sections[3].range.end, bytes.len());
+    }
+
+    #[test]
+    fn test_section_with_symbol_descriptor() {
+        // amqp-value section described by its symbolic name and holding a described value
+        let mut bytes = vec![0x00, 0xa3, 17];
+        bytes.extend_from_slice(b"amqp:amqp-value:*");
+        bytes.extend_from_slice(&[0x00, 0x53, 0x01, 0xa1, 0x02, b'h', b'i']);
+        let described_len = bytes.len();
+
+        let sections = sections(&bytes).unwrap();
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].kind, SectionKind::AmqpValue);
+        assert_eq!(sections[0].range, 0..described_len);
+        assert!(delivery_annotations_range(&bytes).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_malformed_sections() {
+        let bytes = to_vec(&Serializable(message())).unwrap();
+        assert!(sections(&bytes[..bytes.len() - 1]).is_err());
+
+        // Not a described value
+        assert!(sections(&[0xa1, 0x00]).is_err());
+
+        // Not a message section
+        let bytes = to_vec(&Symbol::from("foo")).unwrap();
+        assert!(sections(&[&[0x00, 0x53, 0x10][..], &bytes[..]].concat()).is_err());
+
+        // Deeply nested described values do not exhaust the stack
+        let mut bytes = vec![0x00, 0x53, 0x77];
+        bytes.extend(std::iter::repeat_n([0x00, 0x40], 100_000).flatten());
+        assert!(sections(&bytes).is_err());
+    }
+
+    fn encoded_kind<S: MessageSection + serde::Serialize>(section: S) -> SectionKind {
+        let bytes = to_vec(&section).unwrap();
+        let sections = sections(&bytes).unwrap();
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].kind, S::KIND);
+        S::KIND
+    }
+
+    #[test]
+    fn test_message_section_kinds() {
+        assert!(!encoded_kind(Header::default()).is_body());
+        assert!(
+            !encoded_kind(ApplicationProperties::builder().insert("hop", 1i32).build()).is_body()
+        );
+        assert!(!encoded_kind(Footer::default()).is_body());
+        assert!(encoded_kind(Data(vec![1, 2, 3].into())).is_body());
+        assert!(encoded_kind(AmqpValue("hello")).is_body());
+    }
+
+    #[test]
+    fn test_body_kind_of_encoded_message() {
+        let bytes = to_vec(&Serializable(message())).unwrap();
+        assert_eq!(body_kind(&bytes).unwrap(), BodyKind::Value);
+
+        let message = Message::builder()
+            .application_properties(ApplicationProperties::builder().insert("hop", 1i32).build())
+            .data_batch(vec![Data(vec![1].into()), Data(vec![2].into())])
+            .footer(Footer::default())
+            .build();
+        let bytes = to_vec(&Serializable(message)).unwrap();
+        assert_eq!(body_kind(&bytes).unwrap(), BodyKind::Data);
+
+        let message = Message::builder().sequence(vec![1i32, 2]).build();
+        let bytes = to_vec(&Serializable(message)).unwrap();
+        assert_eq!(body_kind(&bytes).unwrap(), BodyKind::Sequence);
+
+        let bytes = to_vec(&Header::default()).unwrap();
+        assert_eq!(body_kind(&bytes).unwrap(), BodyKind::Empty);
+        assert_eq!(body_kind(&[]).unwrap(), BodyKind::Empty);
+        assert_eq!(
+            encoded_kind(AmqpSequence(vec![1i32])).body_kind(),
+            Some(BodyKind::Sequence)
+        );
+    }
+
+    #[test]
+    fn test_data_range_of_encoded_message() {
+        let data = Message::builder()
+            .properties(Properties::builder().message_id(7u64).build())
+            .data(vec![1u8, 2, 3])
+            .footer(Footer::default())
+            .build();
+        let bytes = to_vec(&Serializable(data)).unwrap();
+        let range = data_range(&bytes).unwrap();
+        assert_eq!(&bytes[range], &[1, 2, 3]);
+
+        // vbin32 described by a ulong
+        let mut bytes = vec![0x00, 0x80, 0, 0, 0, 0, 0, 0, 0, 0x75];
+        bytes.extend_from_slice(&[0xb0, 0, 0, 0, 2, 4, 5]);
+        let range = data_range(&bytes).unwrap();
+        assert_eq!(&bytes[range], &[4, 5]);
+
+        // Data section described by its symbolic name
+        let mut bytes = vec![0x00, 0xa3, 16];
+        bytes.extend_from_slice(b"amqp:data:binary");
+        bytes.extend_from_slice(&[0xa0, 0x01, 0x01]);
+        assert_eq!(body_kind(&bytes).unwrap(), BodyKind::Data);
+        assert!(data_range(&bytes).is_none());
+
+        let batch = Message::builder()
+            .data_batch(vec![Data(vec![1].into()), Data(vec![2].into())])
+            .build();
+        let bytes = to_vec(&Serializable(batch)).unwrap();
+        assert!(data_range(&bytes).is_none());
+
+        let bytes = to_vec(&Serializable(message())).unwrap();
+        assert!(data_range(&bytes).is_none());
+        assert!(data_range(&bytes[..bytes.len() - 1]).is_none());
+        assert!(data_range(&[]).is_none());
+    }
+}
@@ -1,5 +1,6 @@
 //! Types defined in AMQP 1.0 specification Part 3: Messaging
 
+use alloc::vec;
 use serde::{Deserialize, Serialize};
 use serde_amqp::extensions::TransparentVec;
 use serde_amqp::primitives::{Array, OrderedMap};
@@ -9,7 +10,7 @@ mod body_section;
 pub use body_section::*;
 
 pub mod message;
-pub use message::{Body, Message};
+pub use message::{Body, BodyKind, Message};
 
 /* -------------------------- 3.2 Messaging Format -------------------------- */
 mod format;
@@ -102,7 +103,7 @@ pub type NodeProperties = Fields;
 /// attributes:
 ///
 /// type=“symbol” multiple=“true” requires=“distribution-mode”
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct SupportedDistModes(Array<DistributionMode>);
 
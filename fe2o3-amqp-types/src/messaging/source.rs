@@ -15,7 +15,7 @@ use super::{
 /// <type name="source" class="composite" source="list" provides="source">
 ///     <descriptor name="amqp:source:list" code="0x00000000:0x00000028"/>
 /// </type>
-#[derive(Debug, Clone, Default, DeserializeComposite, SerializeComposite)]
+#[derive(Debug, Clone, PartialEq, Eq, Default, DeserializeComposite, SerializeComposite)]
 #[amqp_contract(
     name = "amqp:source:list",
     code = "0x0000_0000:0x0000_0028",
@@ -295,3 +295,85 @@ impl<T: Into<Address>> From<T> for Source {
         Self::builder().address(val.into()).build()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::{boxed::Box, vec};
+
+    use serde_amqp::{
+        described::Described, descriptor::Descriptor, from_slice, primitives::Symbol, to_vec, Value,
+    };
+
+    use crate::messaging::{Outcome, Rejected};
+
+    use super::Source;
+
+    #[test]
+    fn outcomes_are_encoded_as_array_of_symbols() {
+        let source = Source::builder()
+            .address("q1")
+            .outcomes(vec![
+                Symbol::from("amqp:accepted:list"),
+                Symbol::from("amqp:released:list"),
+            ])
+            .build();
+        let buf = to_vec(&source).unwrap();
+
+        let value: Value = from_slice(&buf).unwrap();
+        let fields = match value {
+            Value::Described(described) => match described.value {
+                Value::List(fields) => fields,
+                other => panic!("Expecting a list, found {:?}", other),
+            },
+            other => panic!("Expecting a described value, found {:?}", other),
+        };
+        assert_eq!(
+            fields[9],
+            Value::Array(
+                vec![
+                    Value::Symbol(Symbol::from("amqp:accepted:list")),
+                    Value::Symbol(Symbol::from("amqp:released:list")),
+                ]
+                .into()
+            )
+        );
+
+        let decoded: Source = from_slice(&buf).unwrap();
+        assert_eq!(decoded, source);
+    }
+
+    #[test]
+    fn single_outcome_is_decoded_as_array() {
+        let mut fields = vec![Value::Null; 9];
+        fields.push(Value::Symbol(Symbol::from("amqp:released:list")));
+        let value = Value::Described(Box::new(Described {
+            descriptor: Descriptor::Code(0x28),
+            value: Value::List(fields),
+        }));
+        let buf = to_vec(&value).unwrap();
+
+        let source: Source = from_slice(&buf).unwrap();
+        assert_eq!(
+            source.outcomes,
+            Some(vec![Symbol::from("amqp:released:list")].into())
+        );
+    }
+
+    #[test]
+    fn rejected_default_outcome_round_trip() {
+        let source = Source::builder()
+            .address("q1")
+            .default_outcome(Outcome::Rejected(Rejected { error: None }))
+            .outcomes(vec![Symbol::from("amqp:rejected:list")])
+            .capabilities(vec![Symbol::from("queue")])
+            .build();
+        let buf = to_vec(&source).unwrap();
+
+        let decoded: Source = from_slice(&buf).unwrap();
+        assert_eq!(
+            decoded.default_outcome,
+            Some(Outcome::Rejected(Rejected { error: None }))
+        );
+        assert_eq!(decoded, source);
+    }
+}
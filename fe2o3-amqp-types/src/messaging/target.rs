@@ -16,7 +16,7 @@ use crate::transaction::Coordinator;
 ///
 /// For details, please see part 1.3, 3.5.4, and 4.5.1 in the core
 /// specification.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TargetArchetype {
     /// 3.5.4 Target
     Target(Target),
@@ -59,7 +59,7 @@ mod target_archetype_serde_impl {
     impl<'de> de::Visitor<'de> for FieldVisitor {
         type Value = Field;
 
-        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
             formatter.write_str("variant identifier for TargetArchetype")
         }
 
@@ -112,7 +112,7 @@ mod target_archetype_serde_impl {
     impl<'de> de::Visitor<'de> for Visitor {
         type Value = TargetArchetype;
 
-        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
             formatter.write_str("variant identifier for TargetArchetype")
         }
 
@@ -155,7 +155,7 @@ mod target_archetype_serde_impl {
 /// <type name="target" class="composite" source="list" provides="target">
 ///     <descriptor name="amqp:target:list" code="0x00000000:0x00000029"/>
 /// </type>
-#[derive(Debug, Clone, Default, DeserializeComposite, SerializeComposite)]
+#[derive(Debug, Clone, PartialEq, Eq, Default, DeserializeComposite, SerializeComposite)]
 #[amqp_contract(
     name = "amqp:target:list",
     code = "0x0000_0000:0x0000_0029",
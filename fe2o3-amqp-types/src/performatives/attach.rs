@@ -1,3 +1,4 @@
+use alloc::{boxed::Box, string::String};
 use serde_amqp::{
     macros::{DeserializeComposite, SerializeComposite},
     primitives::{Array, Boolean, OrderedMap, Symbol, Ulong},
@@ -29,7 +30,7 @@ use crate::{
 ///     <field name="desired-capabilities" type="symbol" multiple="true"/>
 ///     <field name="properties" type="fields"/>
 /// </type>
-#[derive(Debug, Clone, DeserializeComposite, SerializeComposite)]
+#[derive(Debug, Clone, PartialEq, Eq, DeserializeComposite, SerializeComposite)]
 #[amqp_contract(
     name = "amqp:attach:list",
     code = "0x0000_0000:0x0000_0012",
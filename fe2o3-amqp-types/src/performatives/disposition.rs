@@ -13,7 +13,7 @@ use crate::{
 /// <type name="disposition" class="composite" source="list" provides="frame">
 ///     <descriptor name="amqp:disposition:list" code="0x00000000:0x00000015"/>
 /// </type>
-#[derive(Debug, Clone, DeserializeComposite, SerializeComposite)]
+#[derive(Debug, Clone, PartialEq, Eq, DeserializeComposite, SerializeComposite)]
 // #[serde(rename_all = "kebab-case")]
 #[amqp_contract(
     name = "amqp:disposition:list",
@@ -0,0 +1,581 @@
+//! Golden byte encodings of the performatives, the message sections and the delivery states
+//!
+//! The expected bytes are derived by hand from the AMQP 1.0 specification and pin the wire
+//! format. A failure here means that a change to the serializer altered the encoding.
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_amqp::{
+    from_slice,
+    primitives::{Array, Binary, Symbol},
+    to_vec, Value,
+};
+
+use crate::{
+    definitions::{AmqpError, Error, Handle, ReceiverSettleMode, Role, SenderSettleMode},
+    messaging::{
+        Accepted, AmqpValue, Data, Header, MessageId, Modified, Properties, Received, Rejected,
+        Released,
+    },
+};
+
+use super::{
+    Attach, Begin, ChannelMax, Close, Detach, Disposition, End, Flow, MaxFrameSize, Open,
+    Performative, Transfer,
+};
+
+fn assert_golden<T>(value: T, expected: &[u8])
+where
+    T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug,
+{
+    let buf = to_vec(&value).unwrap();
+    assert_eq!(buf, expected);
+    let decoded: T = from_slice(expected).unwrap();
+    assert_eq!(decoded, value);
+}
+
+fn assert_golden_performative(performative: Performative, expected: &[u8]) {
+    let buf = to_vec(&performative).unwrap();
+    assert_eq!(buf, expected);
+    let decoded: Performative = from_slice(expected).unwrap();
+    assert_eq!(decoded, performative);
+}
+
+/// amqp:error:list with condition `amqp:not-found` and description `"x"`
+const NOT_FOUND_ERROR: &[u8] = &[
+    0x00, 0x53, 0x1d, 0xc0, 0x14, 0x02, // descriptor, list8, size 20, count 2
+    0xa3, 0x0e, b'a', b'm', b'q', b'p', b':', b'n', b'o', b't', b'-', b'f', b'o', b'u', b'n',
+    b'd', // sym8 "amqp:not-found"
+    0xa1, 0x01, b'x', // str8 "x"
+];
+
+fn not_found_error() -> Error {
+    Error::new(AmqpError::NotFound, Some(String::from("x")), None)
+}
+
+fn with_error(head: &[u8]) -> Vec<u8> {
+    let mut buf = head.to_vec();
+    buf.extend_from_slice(NOT_FOUND_ERROR);
+    buf
+}
+
+#[test]
+fn open_with_only_mandatory_fields() {
+    let open = Open {
+        container_id: String::from("c1"),
+        hostname: None,
+        max_frame_size: MaxFrameSize::default(),
+        channel_max: ChannelMax::default(),
+        idle_time_out: None,
+        outgoing_locales: None,
+        incoming_locales: None,
+        offered_capabilities: None,
+        desired_capabilities: None,
+        properties: None,
+    };
+    let expected = &[
+        0x00, 0x53, 0x10, 0xc0, 0x05, 0x01, // descriptor, list8, size 5, count 1
+        0xa1, 0x02, b'c', b'1', // container-id
+    ];
+    assert_golden(open.clone(), expected);
+    assert_golden_performative(Performative::Open(open), expected);
+}
+
+#[test]
+fn open_with_max_width_fields() {
+    let open = Open {
+        container_id: String::from("c1"),
+        hostname: Some(String::from("h")),
+        max_frame_size: MaxFrameSize(u32::MAX - 1),
+        channel_max: ChannelMax(u16::MAX - 1),
+        idle_time_out: Some(u32::MAX),
+        outgoing_locales: None,
+        incoming_locales: None,
+        offered_capabilities: Some(Array(vec![Symbol::from("a")])),
+        desired_capabilities: None,
+        properties: None,
+    };
+    let expected = &[
+        0x00, 0x53, 0x10, 0xc0, 0x20, 0x08, // descriptor, list8, size 32, count 8
+        0xa1, 0x02, b'c', b'1', // container-id
+        0xa1, 0x01, b'h', // hostname
+        0x70, 0xff, 0xff, 0xff, 0xfe, // max-frame-size
+        0x60, 0xff, 0xfe, // channel-max
+        0x70, 0xff, 0xff, 0xff, 0xff, // idle-time-out
+        0x40, 0x40, // outgoing-locales, incoming-locales
+        // offered-capabilities, the elements of an array use the sym32 constructor
+        0xe0, 0x07, 0x01, 0xb3, 0x00, 0x00, 0x00, 0x01, b'a',
+    ];
+    assert_golden(open.clone(), expected);
+    assert_golden_performative(Performative::Open(open), expected);
+}
+
+#[test]
+fn begin_with_default_handle_max() {
+    let begin = Begin {
+        remote_channel: None,
+        next_outgoing_id: 1,
+        incoming_window: 2048,
+        outgoing_window: u32::MAX,
+        handle_max: Handle::default(),
+        offered_capabilities: None,
+        desired_capabilities: None,
+        properties: None,
+    };
+    let expected = &[
+        0x00, 0x53, 0x11, 0xc0, 0x0e, 0x04, // descriptor, list8, size 14, count 4
+        0x40, // remote-channel
+        0x52, 0x01, // next-outgoing-id
+        0x70, 0x00, 0x00, 0x08, 0x00, // incoming-window
+        0x70, 0xff, 0xff, 0xff, 0xff, // outgoing-window
+    ];
+    assert_golden(begin.clone(), expected);
+    assert_golden_performative(Performative::Begin(begin), expected);
+}
+
+#[test]
+fn begin_with_zero_width_fields() {
+    let begin = Begin {
+        remote_channel: Some(u16::MAX),
+        next_outgoing_id: 0,
+        incoming_window: 0,
+        outgoing_window: 0,
+        handle_max: Handle(0),
+        offered_capabilities: None,
+        desired_capabilities: None,
+        properties: None,
+    };
+    let expected = &[
+        0x00, 0x53, 0x11, 0xc0, 0x08, 0x05, // descriptor, list8, size 8, count 5
+        0x60, 0xff, 0xff, // remote-channel
+        0x43, 0x43, 0x43, // next-outgoing-id, incoming-window, outgoing-window
+        0x43, // handle-max
+    ];
+    assert_golden(begin.clone(), expected);
+    assert_golden_performative(Performative::Begin(begin), expected);
+}
+
+#[test]
+fn attach_with_only_mandatory_fields() {
+    let attach = Attach {
+        name: String::from("l"),
+        handle: Handle(0),
+        role: Role::Sender,
+        snd_settle_mode: SenderSettleMode::default(),
+        rcv_settle_mode: ReceiverSettleMode::default(),
+        source: None,
+        target: None,
+        unsettled: None,
+        incomplete_unsettled: false,
+        initial_delivery_count: None,
+        max_message_size: None,
+        offered_capabilities: None,
+        desired_capabilities: None,
+        properties: None,
+    };
+    let expected = &[
+        0x00, 0x53, 0x12, 0xc0, 0x06, 0x03, // descriptor, list8, size 6, count 3
+        0xa1, 0x01, b'l', // name
+        0x43, // handle
+        0x42, // role
+    ];
+    assert_golden(attach.clone(), expected);
+    assert_golden_performative(Performative::Attach(attach), expected);
+}
+
+#[test]
+fn attach_with_max_width_fields() {
+    let attach = Attach {
+        name: String::from("l"),
+        handle: Handle(u32::MAX),
+        role: Role::Receiver,
+        snd_settle_mode: SenderSettleMode::Settled,
+        rcv_settle_mode: ReceiverSettleMode::Second,
+        source: None,
+        target: None,
+        unsettled: None,
+        incomplete_unsettled: false,
+        initial_delivery_count: Some(0),
+        max_message_size: Some(u64::MAX),
+        offered_capabilities: None,
+        desired_capabilities: None,
+        properties: None,
+    };
+    let expected = &[
+        0x00, 0x53, 0x12, 0xc0, 0x1c, 0x0b, // descriptor, list8, size 28, count 11
+        0xa1, 0x01, b'l', // name
+        0x70, 0xff, 0xff, 0xff, 0xff, // handle
+        0x41, // role
+        0x50, 0x01, // snd-settle-mode
+        0x50, 0x01, // rcv-settle-mode
+        0x40, 0x40, 0x40, 0x40, // source, target, unsettled, incomplete-unsettled
+        0x43, // initial-delivery-count
+        0x80, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, // max-message-size
+    ];
+    assert_golden(attach.clone(), expected);
+    assert_golden_performative(Performative::Attach(attach), expected);
+}
+
+#[test]
+fn flow_for_link() {
+    let flow = Flow {
+        next_incoming_id: None,
+        incoming_window: 2048,
+        next_outgoing_id: 0,
+        outgoing_window: u32::MAX,
+        handle: Some(Handle(0)),
+        delivery_count: Some(0),
+        link_credit: Some(200),
+        available: None,
+        drain: false,
+        echo: false,
+        properties: None,
+    };
+    let expected = &[
+        0x00, 0x53, 0x13, 0xc0, 0x11, 0x07, // descriptor, list8, size 17, count 7
+        0x40, // next-incoming-id
+        0x70, 0x00, 0x00, 0x08, 0x00, // incoming-window
+        0x43, // next-outgoing-id
+        0x70, 0xff, 0xff, 0xff, 0xff, // outgoing-window
+        0x43, 0x43, // handle, delivery-count
+        0x52, 0xc8, // link-credit
+    ];
+    assert_golden(flow.clone(), expected);
+    assert_golden_performative(Performative::Flow(flow), expected);
+}
+
+#[test]
+fn flow_for_session_with_echo() {
+    let flow = Flow {
+        next_incoming_id: Some(1),
+        incoming_window: 0,
+        next_outgoing_id: 0,
+        outgoing_window: 0,
+        handle: None,
+        delivery_count: None,
+        link_credit: None,
+        available: None,
+        drain: false,
+        echo: true,
+        properties: None,
+    };
+    let expected = &[
+        0x00, 0x53, 0x13, 0xc0, 0x0c, 0x0a, // descriptor, list8, size 12, count 10
+        0x52, 0x01, // next-incoming-id
+        0x43, 0x43, 0x43, // incoming-window, next-outgoing-id, outgoing-window
+        0x40, 0x40, 0x40, 0x40, 0x40, // handle, delivery-count, link-credit, available, drain
+        0x41, // echo
+    ];
+    assert_golden(flow.clone(), expected);
+    assert_golden_performative(Performative::Flow(flow), expected);
+}
+
+#[test]
+fn transfer_first_frame() {
+    let transfer = Transfer {
+        handle: Handle(0),
+        delivery_id: Some(0),
+        delivery_tag: Some(Binary::from(vec![0, 0, 0, 1])),
+        message_format: Some(0),
+        settled: Some(false),
+        more: false,
+        rcv_settle_mode: None,
+        state: None,
+        resume: false,
+        aborted: false,
+        batchable: false,
+    };
+    let expected = &[
+        0x00, 0x53, 0x14, 0xc0, 0x0b, 0x05, // descriptor, list8, size 11, count 5
+        0x43, 0x43, // handle, delivery-id
+        0xa0, 0x04, 0x00, 0x00, 0x00, 0x01, // delivery-tag
+        0x43, // message-format
+        0x42, // settled
+    ];
+    assert_golden(transfer.clone(), expected);
+    assert_golden_performative(Performative::Transfer(transfer), expected);
+}
+
+#[test]
+fn transfer_continuation_frame() {
+    let transfer = Transfer {
+        handle: Handle(0),
+        delivery_id: None,
+        delivery_tag: None,
+        message_format: None,
+        settled: None,
+        more: true,
+        rcv_settle_mode: None,
+        state: None,
+        resume: false,
+        aborted: false,
+        batchable: false,
+    };
+    let expected = &[
+        0x00, 0x53, 0x14, 0xc0, 0x07, 0x06, // descriptor, list8, size 7, count 6
+        0x43, // handle
+        0x40, 0x40, 0x40, 0x40, // delivery-id, delivery-tag, message-format, settled
+        0x41, // more
+    ];
+    assert_golden(transfer.clone(), expected);
+    assert_golden_performative(Performative::Transfer(transfer), expected);
+}
+
+#[test]
+fn transfer_aborted() {
+    let transfer = Transfer {
+        handle: Handle(1),
+        delivery_id: None,
+        delivery_tag: None,
+        message_format: None,
+        settled: None,
+        more: false,
+        rcv_settle_mode: None,
+        state: None,
+        resume: false,
+        aborted: true,
+        batchable: false,
+    };
+    let expected = &[
+        0x00, 0x53, 0x14, 0xc0, 0x0c, 0x0a, // descriptor, list8, size 12, count 10
+        0x52, 0x01, // handle
+        0x40, 0x40, 0x40, 0x40, // delivery-id, delivery-tag, message-format, settled
+        0x40, 0x40, 0x40, 0x40, // more, rcv-settle-mode, state, resume
+        0x41, // aborted
+    ];
+    assert_golden(transfer.clone(), expected);
+    assert_golden_performative(Performative::Transfer(transfer), expected);
+}
+
+#[test]
+fn flow_for_session_with_only_window_fields() {
+    let flow = Flow {
+        next_incoming_id: Some(0),
+        incoming_window: 2048,
+        next_outgoing_id: 0,
+        outgoing_window: 2048,
+        handle: None,
+        delivery_count: None,
+        link_credit: None,
+        available: None,
+        drain: false,
+        echo: false,
+        properties: None,
+    };
+    let expected = &[
+        0x00, 0x53, 0x13, 0xc0, 0x0d, 0x04, // descriptor, list8, size 13, count 4
+        0x43, // next-incoming-id
+        0x70, 0x00, 0x00, 0x08, 0x00, // incoming-window
+        0x43, // next-outgoing-id
+        0x70, 0x00, 0x00, 0x08, 0x00, // outgoing-window
+    ];
+    assert_golden(flow.clone(), expected);
+    assert_golden_performative(Performative::Flow(flow), expected);
+}
+
+#[test]
+fn transfer_with_explicit_trailing_nulls() {
+    // A peer may encode every field. The trailing nulls are decoded as absent fields and are
+    // omitted when the transfer is encoded again
+    let buf = &[
+        0x00, 0x53, 0x14, 0xc0, 0x0d, 0x0b, // descriptor, list8, size 13, count 11
+        0x43, 0x43, // handle, delivery-id
+        0xa0, 0x04, 0x00, 0x00, 0x00, 0x01, // delivery-tag
+        0x43, // message-format
+        0x41, // settled
+        0x40, 0x40, 0x40, 0x40, // more, rcv-settle-mode, state, resume
+        0x40, 0x40, // aborted, batchable
+    ];
+    let expected = &[
+        0x00, 0x53, 0x14, 0xc0, 0x0b, 0x05, // descriptor, list8, size 11, count 5
+        0x43, 0x43, // handle, delivery-id
+        0xa0, 0x04, 0x00, 0x00, 0x00, 0x01, // delivery-tag
+        0x43, // message-format
+        0x41, // settled
+    ];
+    let transfer: Transfer = from_slice(buf).unwrap();
+    assert_eq!(
+        transfer,
+        Transfer {
+            handle: Handle(0),
+            delivery_id: Some(0),
+            delivery_tag: Some(Binary::from(vec![0, 0, 0, 1])),
+            message_format: Some(0),
+            settled: Some(true),
+            more: false,
+            rcv_settle_mode: None,
+            state: None,
+            resume: false,
+            aborted: false,
+            batchable: false,
+        }
+    );
+    assert_golden(transfer, expected);
+}
+
+#[test]
+fn disposition_with_accepted_state() {
+    let disposition = Disposition {
+        role: Role::Receiver,
+        first: 0,
+        last: None,
+        settled: true,
+        state: Some(Accepted {}.into()),
+        batchable: false,
+    };
+    let expected = &[
+        0x00, 0x53, 0x15, 0xc0, 0x09, 0x05, // descriptor, list8, size 9, count 5
+        0x41, 0x43, 0x40, 0x41, // role, first, last, settled
+        0x00, 0x53, 0x24, 0x45, // state
+    ];
+    assert_golden(disposition.clone(), expected);
+    assert_golden_performative(Performative::Disposition(disposition), expected);
+}
+
+#[test]
+fn disposition_with_max_width_range() {
+    let disposition = Disposition {
+        role: Role::Sender,
+        first: u32::MAX,
+        last: Some(u32::MAX),
+        settled: false,
+        state: None,
+        batchable: true,
+    };
+    let expected = &[
+        0x00, 0x53, 0x15, 0xc0, 0x0f, 0x06, // descriptor, list8, size 15, count 6
+        0x42, // role
+        0x70, 0xff, 0xff, 0xff, 0xff, // first
+        0x70, 0xff, 0xff, 0xff, 0xff, // last
+        0x40, 0x40, // settled, state
+        0x41, // batchable
+    ];
+    assert_golden(disposition.clone(), expected);
+    assert_golden_performative(Performative::Disposition(disposition), expected);
+}
+
+#[test]
+fn detach_closed_without_error() {
+    let detach = Detach {
+        handle: Handle(0),
+        closed: true,
+        error: None,
+    };
+    let expected = &[
+        0x00, 0x53, 0x16, 0xc0, 0x03, 0x02, // descriptor, list8, size 3, count 2
+        0x43, 0x41, // handle, closed
+    ];
+    assert_golden(detach.clone(), expected);
+    assert_golden_performative(Performative::Detach(detach), expected);
+}
+
+#[test]
+fn detach_with_error() {
+    let detach = Detach {
+        handle: Handle(0),
+        closed: false,
+        error: Some(not_found_error()),
+    };
+    let expected = with_error(&[
+        0x00, 0x53, 0x16, 0xc0, 0x1c, 0x03, // descriptor, list8, size 28, count 3
+        0x43, 0x40, // handle, closed
+    ]);
+    assert_golden(detach.clone(), &expected);
+    assert_golden_performative(Performative::Detach(detach), &expected);
+}
+
+#[test]
+fn end_and_close_without_error() {
+    let end = End { error: None };
+    let expected = &[0x00, 0x53, 0x17, 0x45];
+    assert_golden(end.clone(), expected);
+    assert_golden_performative(Performative::End(end), expected);
+
+    let close = Close { error: None };
+    let expected = &[0x00, 0x53, 0x18, 0x45];
+    assert_golden(close.clone(), expected);
+    assert_golden_performative(Performative::Close(close), expected);
+}
+
+#[test]
+fn end_and_close_with_error() {
+    let end = End {
+        error: Some(not_found_error()),
+    };
+    let expected = with_error(&[0x00, 0x53, 0x17, 0xc0, 0x1a, 0x01]);
+    assert_golden(end.clone(), &expected);
+    assert_golden_performative(Performative::End(end), &expected);
+
+    let close = Close {
+        error: Some(not_found_error()),
+    };
+    let expected = with_error(&[0x00, 0x53, 0x18, 0xc0, 0x1a, 0x01]);
+    assert_golden(close.clone(), &expected);
+    assert_golden_performative(Performative::Close(close), &expected);
+}
+
+#[test]
+fn message_sections() {
+    assert_golden(Header::default(), &[0x00, 0x53, 0x70, 0x45]);
+
+    let header = Header {
+        durable: true,
+        ttl: Some(1000),
+        ..Default::default()
+    };
+    let expected = &[
+        0x00, 0x53, 0x70, 0xc0, 0x08, 0x03, // descriptor, list8, size 8, count 3
+        0x41, 0x40, // durable, priority
+        0x70, 0x00, 0x00, 0x03, 0xe8, // ttl
+    ];
+    assert_golden(header, expected);
+
+    let properties = Properties {
+        message_id: Some(MessageId::Ulong(1)),
+        ..Default::default()
+    };
+    let expected = &[
+        0x00, 0x53, 0x73, 0xc0, 0x03, 0x01, // descriptor, list8, size 3, count 1
+        0x53, 0x01, // message-id
+    ];
+    assert_golden(properties, expected);
+
+    let data = Data(Binary::from(vec![1, 2]));
+    assert_golden(data, &[0x00, 0x53, 0x75, 0xa0, 0x02, 0x01, 0x02]);
+
+    let value = AmqpValue(Value::String(String::from("hi")));
+    assert_golden(value, &[0x00, 0x53, 0x77, 0xa1, 0x02, b'h', b'i']);
+}
+
+#[test]
+fn delivery_states() {
+    assert_golden(Accepted {}, &[0x00, 0x53, 0x24, 0x45]);
+    assert_golden(Rejected { error: None }, &[0x00, 0x53, 0x25, 0x45]);
+    assert_golden(Released {}, &[0x00, 0x53, 0x26, 0x45]);
+
+    let rejected = Rejected {
+        error: Some(not_found_error()),
+    };
+    let expected = with_error(&[0x00, 0x53, 0x25, 0xc0, 0x1a, 0x01]);
+    assert_golden(rejected, &expected);
+
+    let received = Received {
+        section_number: 0,
+        section_offset: 0,
+    };
+    let expected = &[
+        0x00, 0x53, 0x23, 0xc0, 0x03, 0x02, // descriptor, list8, size 3, count 2
+        0x43, 0x44, // section-number, section-offset
+    ];
+    assert_golden(received, expected);
+
+    let modified = Modified {
+        delivery_failed: Some(true),
+        undeliverable_here: None,
+        message_annotations: None,
+    };
+    let expected = &[
+        0x00, 0x53, 0x27, 0xc0, 0x02, 0x01, // descriptor, list8, size 2, count 1
+        0x41, // delivery-failed
+    ];
+    assert_golden(modified, expected);
+}
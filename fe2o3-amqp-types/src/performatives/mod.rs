@@ -21,7 +21,7 @@ pub use open::*;
 pub use transfer::*;
 
 /// AMQP 1.0 Performatives
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Performative {
     /// Open
     Open(Open),
@@ -52,6 +52,7 @@ pub enum Performative {
 }
 
 mod performative_impl {
+    use alloc::format;
     use serde::{
         de::{self, VariantAccess},
         ser,
@@ -95,7 +96,7 @@ mod performative_impl {
     impl<'de> de::Visitor<'de> for FieldVisitor {
         type Value = Field;
 
-        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
             formatter.write_str("variant identifier")
         }
 
@@ -158,7 +159,7 @@ mod performative_impl {
     impl<'de> de::Visitor<'de> for Visitor {
         type Value = Performative;
 
-        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
             formatter.write_str("enum DeliveryState")
         }
 
@@ -230,6 +231,9 @@ mod performative_impl {
     }
 }
 
+#[cfg(test)]
+mod golden_tests;
+
 #[cfg(test)]
 mod tests {
     use super::Performative;
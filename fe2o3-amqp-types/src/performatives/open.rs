@@ -1,3 +1,4 @@
+use alloc::string::String;
 use serde::{Deserialize, Serialize};
 use serde_amqp::{
     macros::{DeserializeComposite, SerializeComposite},
@@ -10,7 +11,7 @@ use crate::definitions::{Fields, IetfLanguageTag, Milliseconds};
 /// <type name="open" class="composite" source="list" provides="frame">
 ///     <descriptor name="amqp:open:list" code="0x00000000:0x00000010"/>
 /// </type>
-#[derive(Debug, Clone, DeserializeComposite, SerializeComposite)]
+#[derive(Debug, Clone, PartialEq, Eq, DeserializeComposite, SerializeComposite)]
 // #[serde(rename_all = "kebab-case")]
 #[amqp_contract(
     name = "amqp:open:list",
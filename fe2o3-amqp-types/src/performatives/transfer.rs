@@ -13,7 +13,7 @@ use crate::{
 /// <type name="transfer" class="composite" source="list" provides="frame">
 ///     <descriptor name="amqp:transfer:list" code="0x00000000:0x00000014"/>
 /// </type>
-#[derive(Debug, Clone, DeserializeComposite, SerializeComposite)]
+#[derive(Debug, Clone, PartialEq, Eq, DeserializeComposite, SerializeComposite)]
 // #[serde(rename_all = "kebab-case")]
 #[amqp_contract(
     name = "amqp:transfer:list",
@@ -1,6 +1,6 @@
 //! Primitive types defined in the AMQP1.0 specification Part 1.6
 
-use std::convert::{TryFrom, TryInto};
+use core::convert::{TryFrom, TryInto};
 
 use ordered_float::OrderedFloat;
 use serde::{de, ser};
@@ -1,9 +1,10 @@
 //! Simple values. A subset of the primitive types.
 
 use super::*;
+use alloc::string::{String, ToString};
 
 /// A subset of `Value`
-#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[derive(Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Default)]
 pub enum SimpleValue {
     /// Indicates an empty value
     ///
@@ -213,6 +214,35 @@ pub enum SimpleValue {
     Symbol(Symbol),
 }
 
+/// Binary values are formatted like [`Value::Binary`]
+impl core::fmt::Debug for SimpleValue {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Null => write!(f, "Null"),
+            Self::Bool(arg0) => f.debug_tuple("Bool").field(arg0).finish(),
+            Self::Ubyte(arg0) => f.debug_tuple("Ubyte").field(arg0).finish(),
+            Self::Ushort(arg0) => f.debug_tuple("Ushort").field(arg0).finish(),
+            Self::Uint(arg0) => f.debug_tuple("Uint").field(arg0).finish(),
+            Self::Ulong(arg0) => f.debug_tuple("Ulong").field(arg0).finish(),
+            Self::Byte(arg0) => f.debug_tuple("Byte").field(arg0).finish(),
+            Self::Short(arg0) => f.debug_tuple("Short").field(arg0).finish(),
+            Self::Int(arg0) => f.debug_tuple("Int").field(arg0).finish(),
+            Self::Long(arg0) => f.debug_tuple("Long").field(arg0).finish(),
+            Self::Float(arg0) => f.debug_tuple("Float").field(arg0).finish(),
+            Self::Double(arg0) => f.debug_tuple("Double").field(arg0).finish(),
+            Self::Decimal32(arg0) => f.debug_tuple("Decimal32").field(arg0).finish(),
+            Self::Decimal64(arg0) => f.debug_tuple("Decimal64").field(arg0).finish(),
+            Self::Decimal128(arg0) => f.debug_tuple("Decimal128").field(arg0).finish(),
+            Self::Char(arg0) => f.debug_tuple("Char").field(arg0).finish(),
+            Self::Timestamp(arg0) => f.debug_tuple("Timestamp").field(arg0).finish(),
+            Self::Uuid(arg0) => f.debug_tuple("Uuid").field(arg0).finish(),
+            Self::Binary(arg0) => f.debug_tuple("Binary").field(&BinaryRef(arg0)).finish(),
+            Self::String(arg0) => f.debug_tuple("String").field(arg0).finish(),
+            Self::Symbol(arg0) => f.debug_tuple("Symbol").field(arg0).finish(),
+        }
+    }
+}
+
 impl SimpleValue {
     /// Get the format code of the type
     pub fn format_code(&self) -> u8 {
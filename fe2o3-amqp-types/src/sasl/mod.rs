@@ -1,7 +1,10 @@
 //! Types defined in AMQP 1.0 specification Part 5.3: SASL
 
+use alloc::string::String;
+use core::fmt::Debug;
+
 use serde_amqp::{
-    primitives::{Array, Binary, Symbol},
+    primitives::{Array, Binary, BinaryRef, Symbol},
     DeserializeComposite, SerializeComposite,
 };
 use serde_repr::{Deserialize_repr, Serialize_repr};
@@ -19,7 +22,7 @@ mod mechanisms;
 /// NOTE: Serialize and Deserialize are manually implemented because
 /// > A field which is defined as both multiple and mandatory MUST contain at least one value
 /// > (i.e. for such a field both null and an array with no entries are invalid).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SaslMechanisms {
     /// sasl-server-mechanisms supported sasl mechanisms
     ///
@@ -40,7 +43,7 @@ pub struct SaslMechanisms {
 ///     <field name="hostname" type="string"/>
 /// </type>
 /// Selects the sasl mechanism and provides the initial response if needed.
-#[derive(Debug, Clone, SerializeComposite, DeserializeComposite)]
+#[derive(Clone, PartialEq, Eq, SerializeComposite, DeserializeComposite)]
 #[amqp_contract(
     name = "amqp:sasl-init:list",
     code = "0x0000_0000:0x0000_0041",
@@ -75,6 +78,20 @@ pub struct SaslInit {
     pub hostname: Option<String>,
 }
 
+/// The initial response carries the credentials of mechanisms like PLAIN and is never formatted
+impl Debug for SaslInit {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SaslInit")
+            .field("mechanism", &self.mechanism)
+            .field(
+                "initial_response",
+                &self.initial_response.as_ref().map(|r| Redacted(r.len())),
+            )
+            .field("hostname", &self.hostname)
+            .finish()
+    }
+}
+
 /// 5.3.3.3 SASL Challenge
 /// Security mechanism challenge.
 /// <type name="sasl-challenge" class="composite" source="list" provides="sasl-frame">
@@ -82,7 +99,7 @@ pub struct SaslInit {
 ///     <field name="challenge" type="binary" mandatory="true"/>
 /// </type>
 /// Send the SASL challenge data as defined by the SASL specification.
-#[derive(Debug, Clone, SerializeComposite, DeserializeComposite)]
+#[derive(Clone, PartialEq, Eq, SerializeComposite, DeserializeComposite)]
 #[amqp_contract(
     name = "amqp:sasl-challenge:list",
     code = "0x0000_0000:0x0000_0042",
@@ -94,6 +111,14 @@ pub struct SaslChallenge {
     pub challenge: Binary,
 }
 
+impl Debug for SaslChallenge {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SaslChallenge")
+            .field("challenge", &BinaryRef(&self.challenge))
+            .finish()
+    }
+}
+
 /// 5.3.3.4 SASL Response
 /// Security mechanism response.
 /// <type name="sasl-response" class="composite" source="list" provides="sasl-frame">
@@ -101,7 +126,7 @@ pub struct SaslChallenge {
 ///     <field name="response" type="binary" mandatory="true"/>
 /// </type>
 /// Send the SASL response data as defined by the SASL specification.
-#[derive(Debug, Clone, SerializeComposite, DeserializeComposite)]
+#[derive(Clone, PartialEq, Eq, SerializeComposite, DeserializeComposite)]
 #[amqp_contract(
     name = "amqp:sasl-response:list",
     code = "0x0000_0000:0x0000_0043",
@@ -113,6 +138,15 @@ pub struct SaslResponse {
     pub response: Binary,
 }
 
+/// The response carries the credentials of the mechanism and is never formatted
+impl Debug for SaslResponse {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SaslResponse")
+            .field("response", &Redacted(self.response.len()))
+            .finish()
+    }
+}
+
 /// 5.3.3.5 SASL Outcome
 /// Indicates the outcome of the sasl dialog.
 /// <type name="sasl-outcome" class="composite" source="list" provides="sasl-frame">
@@ -123,7 +157,7 @@ pub struct SaslResponse {
 /// This frame indicates the outcome of the SASL dialog. Upon successful completion of the SASL
 /// dialog the security layer has been established, and the peers MUST exchange protocol headers
 /// to either start a nested security layer, or to establish the AMQP connection.
-#[derive(Debug, Clone, SerializeComposite, DeserializeComposite)]
+#[derive(Clone, PartialEq, Eq, SerializeComposite, DeserializeComposite)]
 #[amqp_contract(
     name = "amqp:sasl-outcome:list",
     code = "0x0000_0000:0x0000_0044",
@@ -137,6 +171,27 @@ pub struct SaslOutcome {
     pub additional_data: Option<Binary>,
 }
 
+impl Debug for SaslOutcome {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SaslOutcome")
+            .field("code", &self.code)
+            .field(
+                "additional_data",
+                &self.additional_data.as_ref().map(|d| BinaryRef(d)),
+            )
+            .finish()
+    }
+}
+
+/// Formats the length of the security data in place of the data
+struct Redacted(usize);
+
+impl Debug for Redacted {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "<redacted, {} bytes>", self.0)
+    }
+}
+
 /// 5.3.3.6 SASL Code
 /// Codes to indicate the outcome of the sasl dialog.
 /// <type name="sasl-code" class="restricted" source="ubyte">
@@ -179,7 +234,7 @@ pub mod constant {
 mod tests {
     use serde_amqp::{format_code::EncodingCodes, from_slice, to_vec};
 
-    use super::SaslCode;
+    use super::{SaslCode, SaslInit, SaslResponse};
 
     fn assert_eq_on_sasl_code_and_deserialized(code: SaslCode, buf: Vec<u8>) {
         let deserialized: SaslCode = from_slice(&buf).unwrap();
@@ -218,4 +273,22 @@ mod tests {
         assert_eq!(&buf, &expected);
         assert_eq_on_sasl_code_and_deserialized(code, expected);
     }
+
+    #[test]
+    fn test_debug_redacts_security_data() {
+        let init = SaslInit {
+            mechanism: "PLAIN".into(),
+            initial_response: Some(b"\0guest\0secret".to_vec().into()),
+            hostname: Some("localhost".into()),
+        };
+        let debug = format!("{:?}", init);
+        assert!(!debug.contains("secret"));
+        assert!(debug.contains("initial_response: Some(<redacted, 13 bytes>)"));
+
+        let response = SaslResponse {
+            response: b"c=biws,r=nonce,p=proof".to_vec().into(),
+        };
+        let debug = format!("{:?}", response);
+        assert_eq!(debug, "SaslResponse { response: <redacted, 22 bytes> }");
+    }
 }
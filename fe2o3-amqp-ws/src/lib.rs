@@ -27,8 +27,8 @@
 //!
 //! ```rust,no_run
 //! use fe2o3_amqp::{
-//!     types::{messaging::Outcome, primitives::Value},
-//!     Connection, Delivery, Receiver, Sender, Session,
+//!     types::primitives::Value,
+//!     Connection, Delivery, Receiver, SendReceipt, Sender, Session,
 //! };
 //! use fe2o3_amqp_ws::WebSocketStream;
 //!
@@ -56,7 +56,7 @@
 //!     let delivery: Delivery<Value> = receiver.recv().await.unwrap();
 //!     receiver.accept(&delivery).await.unwrap();
 //!
-//!     let outcome: Outcome = fut.await.unwrap();
+//!     let outcome: SendReceipt = fut.await.unwrap();
 //!     outcome.accepted_or_else(|state| state).unwrap(); // Handle delivery outcome
 //!
 //!     sender.close().await.unwrap();
@@ -122,8 +122,8 @@ pin_project! {
     ///
     /// ```rust,no_run
     /// use fe2o3_amqp::{
-    ///     types::{messaging::Outcome, primitives::Value},
-    ///     Connection, Delivery, Receiver, Sender, Session,
+    ///     types::primitives::Value,
+    ///     Connection, Delivery, Receiver, SendReceipt, Sender, Session,
     /// };
     /// use fe2o3_amqp_ws::WebSocketStream;
     ///
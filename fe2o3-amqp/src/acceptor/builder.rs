@@ -1,26 +1,34 @@
 //! Builder for acceptors
 
-use std::marker::PhantomData;
+use std::{future::Future, marker::PhantomData, sync::Arc, time::Duration};
 
 use fe2o3_amqp_types::{
     definitions::{
-        Fields, Handle, IetfLanguageTag, Milliseconds, ReceiverSettleMode, SenderSettleMode,
-        SequenceNo, TransferNumber, MIN_MAX_FRAME_SIZE,
+        self, Fields, Handle, IetfLanguageTag, Milliseconds, ReceiverSettleMode, Redirect,
+        SenderSettleMode, SequenceNo, TransferNumber,
     },
-    messaging::{Source, Target},
-    performatives::{ChannelMax, MaxFrameSize, Open},
+    messaging::{Outcome, Source, Target},
+    performatives::{Begin, ChannelMax, MaxFrameSize, Open},
     primitives::{Array, Symbol, Ulong},
 };
+use futures_util::future;
 
 use crate::{
     connection::{DEFAULT_CHANNEL_MAX, DEFAULT_MAX_FRAME_SIZE, DEFAULT_OUTGOING_BUFFER_SIZE},
-    util::{Initialized, Uninitialized},
+    frames::amqp::LogRedactor,
+    link::receiver::CreditMode,
+    session::Builder as SessionBuilder,
 };
 
+pub use crate::util::{Initialized, Uninitialized};
+
 use super::{
-    link::LinkAcceptor, local_receiver_link::LocalReceiverLinkAcceptor,
-    local_sender_link::LocalSenderLinkAcceptor, session::SessionAcceptor, ConnectionAcceptor,
-    SaslAcceptor, SupportedReceiverSettleModes, SupportedSenderSettleModes,
+    link::LinkAcceptor,
+    local_receiver_link::LocalReceiverLinkAcceptor,
+    local_sender_link::LocalSenderLinkAcceptor,
+    loopback::LoopbackNode,
+    session::{OnBegin, SessionAcceptor},
+    ConnectionAcceptor, SaslAcceptor, SupportedReceiverSettleModes, SupportedSenderSettleModes,
 };
 
 cfg_transaction! {
@@ -74,6 +82,10 @@ impl Builder<ConnectionAcceptor<(), ()>, Uninitialized> {
             tls_acceptor: (),
             sasl_acceptor: (),
             buffer_size: DEFAULT_OUTGOING_BUFFER_SIZE,
+            properties_fn: None,
+            redirect_fn: None,
+            open_fn: None,
+            log_redactor: None,
         };
 
         Self {
@@ -104,10 +116,14 @@ impl<M, Tls, Sasl> Builder<ConnectionAcceptor<Tls, Sasl>, M> {
     }
 
     /// Proposed maximum frame size
+    ///
+    /// Accepting a connection fails with
+    /// [`OpenError::MaxFrameSizeTooSmall`](crate::connection::OpenError::MaxFrameSizeTooSmall)
+    /// if this is smaller than the minimum max frame size of 512 bytes. The value cannot be
+    /// rejected here because the builder methods return the builder rather than a `Result`, so it
+    /// is checked before anything is read from or written to the stream.
     pub fn max_frame_size(mut self, max_frame_size: impl Into<MaxFrameSize>) -> Self {
-        let max_frame_size = max_frame_size.into();
-        let max_frame_size = std::cmp::max(MIN_MAX_FRAME_SIZE as u32, max_frame_size.0);
-        self.inner.local_open.max_frame_size = MaxFrameSize::from(max_frame_size);
+        self.inner.local_open.max_frame_size = max_frame_size.into();
         self
     }
 
@@ -208,6 +224,69 @@ impl<M, Tls, Sasl> Builder<ConnectionAcceptor<Tls, Sasl>, M> {
         self
     }
 
+    /// Computes connection properties for each incoming connection from the Open sent by the
+    /// remote peer (eg. to echo a property sent by the client).
+    ///
+    /// The returned properties are added to the ones set by [`properties`](#method.properties),
+    /// replacing entries with the same key
+    pub fn properties_with<F>(mut self, op: F) -> Self
+    where
+        F: Fn(&Open) -> Fields + Send + Sync + 'static,
+    {
+        self.inner.properties_fn = Some(Arc::new(op));
+        self
+    }
+
+    /// Decides for each incoming connection from the Open sent by the remote peer whether to
+    /// redirect the connection to another container instead of accepting it
+    ///
+    /// A redirected connection is refused with an Open that has the
+    /// `amqp:connection-establishment-failed` property set, immediately followed by a Close
+    /// carrying an `amqp:connection:redirect` error, and `accept` returns
+    /// [`OpenError::Redirected`](crate::connection::OpenError::Redirected)
+    pub fn redirect_with<F>(mut self, op: F) -> Self
+    where
+        F: Fn(&Open) -> Option<Redirect> + Send + Sync + 'static,
+    {
+        self.inner.redirect_fn = Some(Arc::new(op));
+        self
+    }
+
+    /// Validates the Open sent by the remote peer for each incoming connection before the local
+    /// Open is sent and before any session is started (eg. to check the container id against an
+    /// allowlist)
+    ///
+    /// A rejected connection is refused with an Open that has the
+    /// `amqp:connection-establishment-failed` property set, immediately followed by a Close
+    /// carrying the returned error, and `accept` returns
+    /// [`OpenError::Rejected`](crate::connection::OpenError::Rejected). Frames pipelined by the
+    /// remote peer behind its Open are discarded. A rejection takes precedence over
+    /// [`redirect_with`](#method.redirect_with)
+    pub fn on_open<F>(self, op: F) -> Self
+    where
+        F: Fn(&Open) -> Result<(), definitions::Error> + Send + Sync + 'static,
+    {
+        self.on_open_async(move |open| future::ready(op(open)))
+    }
+
+    /// Same as [`on_open`](#method.on_open) but with an asynchronous validation
+    pub fn on_open_async<F, Fut>(mut self, op: F) -> Self
+    where
+        F: Fn(&Open) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), definitions::Error>> + Send + 'static,
+    {
+        self.inner.open_fn = Some(Arc::new(move |open| Box::pin(op(open))));
+        self
+    }
+
+    /// Rewrites the frames of the accepted connections with `redactor` before they are formatted
+    /// in the trace and log events, eg. to mask the credentials in the properties of the Open
+    /// sent by the remote peer
+    pub fn log_redactor(mut self, redactor: LogRedactor) -> Self {
+        self.inner.log_redactor = Some(redactor);
+        self
+    }
+
     /// Sets the TLS Acceptor
     pub fn tls_acceptor<T>(self, tls_acceptor: T) -> Builder<ConnectionAcceptor<T, Sasl>, M> {
         let inner = ConnectionAcceptor {
@@ -215,6 +294,10 @@ impl<M, Tls, Sasl> Builder<ConnectionAcceptor<Tls, Sasl>, M> {
             tls_acceptor,
             sasl_acceptor: self.inner.sasl_acceptor,
             buffer_size: self.inner.buffer_size,
+            properties_fn: self.inner.properties_fn,
+            redirect_fn: self.inner.redirect_fn,
+            open_fn: self.inner.open_fn,
+            log_redactor: self.inner.log_redactor,
         };
         Builder {
             inner,
@@ -232,6 +315,10 @@ impl<M, Tls, Sasl> Builder<ConnectionAcceptor<Tls, Sasl>, M> {
             tls_acceptor: self.inner.tls_acceptor,
             sasl_acceptor,
             buffer_size: self.inner.buffer_size,
+            properties_fn: self.inner.properties_fn,
+            redirect_fn: self.inner.redirect_fn,
+            open_fn: self.inner.open_fn,
+            log_redactor: self.inner.log_redactor,
         };
         Builder {
             inner,
@@ -334,6 +421,32 @@ impl Builder<SessionAcceptor, Initialized> {
         self
     }
 
+    /// Computes the configuration of each incoming session from the Begin sent by the remote peer
+    /// (eg. to cap the handle-max or to advertise a smaller incoming-window to some clients).
+    ///
+    /// The hook is given the configuration of the acceptor and returns the configuration used for
+    /// the session, including its flow control. Returning an error refuses the session, which is
+    /// then ended with the error
+    pub fn on_begin<F>(mut self, op: F) -> Self
+    where
+        F: Fn(&Begin, SessionBuilder) -> Result<SessionBuilder, definitions::Error>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.inner.0.on_begin = Some(OnBegin(Arc::new(op)));
+        self
+    }
+
+    /// Duration a link that is detached by the remote peer and parked in the session's
+    /// [`ParkedLinks`](crate::acceptor::ParkedLinks) is kept before it is discarded.
+    ///
+    /// Default to 60 seconds
+    pub fn parked_link_expiry(mut self, expiry: Duration) -> Self {
+        self.inner.0.parked_link_expiry = expiry;
+        self
+    }
+
     cfg_transaction! {
         /// Enable handling remotely initiated control link and transaction by setting the
         /// `control_link_acceptor` field
@@ -390,7 +503,7 @@ where
     }
 
     /// The sender settle mode to fallback to when the mode desired
-    /// by the remote peer is not supported
+    /// by the remote receiver is not supported
     pub fn fallback_sender_settle_mode(mut self, mode: SenderSettleMode) -> Self {
         self.inner.shared.fallback_snd_settle_mode = mode;
         self
@@ -403,7 +516,7 @@ where
     }
 
     /// The receiver settle mode to fallback to when the mode desired
-    /// by the remote peer is not supported
+    /// by the remote sender is not supported
     pub fn fallback_receiver_settle_mode(mut self, mode: ReceiverSettleMode) -> Self {
         self.inner.shared.fallback_rcv_settle_mode = mode;
         self
@@ -477,6 +590,29 @@ where
         self
     }
 
+    /// Set the outcomes supported by the source of the accepted senders
+    ///
+    /// The outcomes of the source requested by the remote receiver are echoed back if this is
+    /// not set
+    pub fn source_outcomes(
+        mut self,
+        outcomes: impl IntoIterator<Item = impl Into<Symbol>>,
+    ) -> Self {
+        let outcomes = outcomes.into_iter().map(Into::into).collect();
+        self.inner.local_sender_acceptor.source_outcomes = Some(outcomes);
+        self
+    }
+
+    /// Set the default outcome of the source of the accepted senders, which applies to the
+    /// deliveries that are still unsettled when the link is closed
+    ///
+    /// The default outcome of the source requested by the remote receiver is echoed back if this
+    /// is not set
+    pub fn default_outcome(mut self, outcome: impl Into<Outcome>) -> Self {
+        self.inner.local_sender_acceptor.default_outcome = Some(outcome.into());
+        self
+    }
+
     /// Set whether the link should verify the `source` field of incoming Attach frames
     pub fn verify_incoming_source(mut self, verify: bool) -> Self {
         self.inner.local_receiver_acceptor.verify_incoming_source = verify;
@@ -491,6 +627,32 @@ where
         self
     }
 
+    /// Credit mode of the accepted receivers. This has no effect on the accepted senders
+    ///
+    /// With [`CreditMode::Auto`] and [`CreditMode::Threshold`], the initial credit is issued
+    /// right after the Attach is echoed, before the receiver is returned by
+    /// [`LinkAcceptor::accept`](crate::acceptor::LinkAcceptor::accept). With
+    /// [`CreditMode::Manual`], no credit is issued until
+    /// [`Receiver::set_credit`](crate::Receiver::set_credit) or
+    /// [`Receiver::add_credit`](crate::Receiver::add_credit) is called on the accepted receiver
+    pub fn credit_mode(mut self, credit_mode: CreditMode) -> Self {
+        self.inner.local_receiver_acceptor.credit_mode = credit_mode;
+        self
+    }
+
+    /// Whether the accepted receivers automatically accept all incoming deliveries
+    pub fn auto_accept(mut self, auto_accept: bool) -> Self {
+        self.inner.local_receiver_acceptor.auto_accept = auto_accept;
+        self
+    }
+
+    /// Add an in-memory node. Links attached to the address of the node are handed over to the
+    /// node and are not returned by [`LinkAcceptor::accept`]
+    pub fn loopback_node(mut self, node: LoopbackNode) -> Self {
+        self.inner.shared.loopback_nodes.push(node);
+        self
+    }
+
     /// Sets how to handle dynamic target
     ///
     /// If a valid target is created, a `Some(target)` should be returned. If dynamic
@@ -535,6 +697,8 @@ where
         let local_sender_acceptor = LocalSenderLinkAcceptor {
             initial_delivery_count: self.inner.local_sender_acceptor.initial_delivery_count,
             source_capabilities: self.inner.local_sender_acceptor.source_capabilities,
+            source_outcomes: self.inner.local_sender_acceptor.source_outcomes,
+            default_outcome: self.inner.local_sender_acceptor.default_outcome,
             on_dynamic_source: op,
             verify_incoming_source: self.inner.local_sender_acceptor.verify_incoming_source,
             verify_incoming_target: self.inner.local_sender_acceptor.verify_incoming_target,
@@ -577,7 +741,7 @@ cfg_transaction! {
         }
     
         /// The sender settle mode to fallback to when the mode desired
-        /// by the remote peer is not supported
+        /// by the remote receiver is not supported
         pub fn fallback_sender_settle_mode(mut self, mode: SenderSettleMode) -> Self {
             self.inner.shared.fallback_snd_settle_mode = mode;
             self
@@ -590,7 +754,7 @@ cfg_transaction! {
         }
     
         /// The receiver settle mode to fallback to when the mode desired
-        /// by the remote peer is not supported
+        /// by the remote sender is not supported
         pub fn fallback_receiver_settle_mode(mut self, mode: ReceiverSettleMode) -> Self {
             self.inner.shared.fallback_rcv_settle_mode = mode;
             self
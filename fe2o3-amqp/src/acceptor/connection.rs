@@ -1,15 +1,16 @@
 //! Connection Listener
 
-use std::{io, marker::PhantomData, time::Duration};
+use std::{io, marker::PhantomData, sync::Arc, time::Duration};
 
 
 use fe2o3_amqp_types::{
-    definitions::{self},
+    definitions::{self, Fields, Redirect, MIN_MAX_FRAME_SIZE},
     performatives::{Begin, Close, End, Open},
+    primitives::{Symbol, Value},
     sasl::{SaslCode, SaslOutcome},
     states::ConnectionState,
 };
-use futures_util::{Sink, SinkExt, StreamExt};
+use futures_util::{future::BoxFuture, Sink, SinkExt, StreamExt};
 use tokio::{
     io::{AsyncRead, AsyncWrite, ReadHalf, WriteHalf},
     sync::mpsc::{self, Receiver},
@@ -19,13 +20,15 @@ use tokio_util::codec::{FramedRead, FramedWrite};
 use crate::{
     acceptor::sasl_acceptor::SaslServerFrame,
     connection::{
-        self, engine::ConnectionEngine, ConnectionHandle, OpenError, DEFAULT_CONTROL_CHAN_BUF,
+        self, engine::ConnectionEngine, ConnectionHandle, OpenError, SessionRelay,
+        DEFAULT_CONTROL_CHAN_BUF,
     },
     endpoint::{self, IncomingChannel, OutgoingChannel},
     frames::{
-        amqp::{self, Frame},
+        amqp::{self, Frame, LogRedactor},
         sasl,
     },
+    rt::Spawner,
     session::frame::{SessionFrame, SessionFrameBody},
     transport::{protocol_header::ProtocolHeaderCodec, Transport},
     util::{Initialized, Uninitialized},
@@ -40,6 +43,16 @@ use super::{
 /// Type alias for listener connection handle
 pub type ListenerConnectionHandle = ConnectionHandle<Receiver<IncomingSession>>;
 
+/// Computes the connection properties of the local Open from the Open sent by the remote peer
+pub type PropertiesFn = Arc<dyn Fn(&Open) -> Fields + Send + Sync>;
+
+/// Decides from the Open sent by the remote peer whether to redirect the connection
+pub type RedirectFn = Arc<dyn Fn(&Open) -> Option<Redirect> + Send + Sync>;
+
+/// Validates the Open sent by the remote peer before the connection is accepted
+pub type OpenFn =
+    Arc<dyn Fn(&Open) -> BoxFuture<'static, Result<(), definitions::Error>> + Send + Sync>;
+
 impl ListenerConnectionHandle {
     /// Waits for the next incoming session asynchronously
     pub async fn next_incoming_session(&mut self) -> Option<IncomingSession> {
@@ -136,7 +149,6 @@ impl ListenerConnectionHandle {
 ///     .sasl_acceptor(SaslPlainMechanism::new("guest", "guest"))
 ///     .build();
 /// ```
-#[derive(Debug)]
 pub struct ConnectionAcceptor<Tls, Sasl> {
     /// Local Open performative that holds the majority of configurable fields
     pub local_open: Open,
@@ -149,6 +161,38 @@ pub struct ConnectionAcceptor<Tls, Sasl> {
 
     /// Buffer size for the underlying channel
     pub buffer_size: usize,
+
+    /// Computes additional connection properties from the Open sent by the remote peer
+    pub properties_fn: Option<PropertiesFn>,
+
+    /// Decides whether to redirect the connection from the Open sent by the remote peer
+    pub redirect_fn: Option<RedirectFn>,
+
+    /// Validates the Open sent by the remote peer before the connection is accepted
+    pub open_fn: Option<OpenFn>,
+
+    /// Rewrites the frames before they are formatted in the trace and log events of the
+    /// accepted connections
+    pub log_redactor: Option<LogRedactor>,
+}
+
+impl<Tls, Sasl> std::fmt::Debug for ConnectionAcceptor<Tls, Sasl>
+where
+    Tls: std::fmt::Debug,
+    Sasl: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConnectionAcceptor")
+            .field("local_open", &self.local_open)
+            .field("tls_acceptor", &self.tls_acceptor)
+            .field("sasl_acceptor", &self.sasl_acceptor)
+            .field("buffer_size", &self.buffer_size)
+            .field("properties_fn", &self.properties_fn.as_ref().map(|_| "Fn"))
+            .field("redirect_fn", &self.redirect_fn.as_ref().map(|_| "Fn"))
+            .field("open_fn", &self.open_fn.as_ref().map(|_| "Fn"))
+            .field("log_redactor", &self.log_redactor)
+            .finish()
+    }
 }
 
 impl ConnectionAcceptor<(), ()> {
@@ -173,6 +217,15 @@ impl<Tls, Sasl> ConnectionAcceptor<Tls, Sasl> {
         }
     }
 
+    /// Checks the local max frame size before anything is read from the stream
+    fn validate_max_frame_size(&self) -> Result<(), OpenError> {
+        let max_frame_size = self.local_open.max_frame_size.0;
+        if max_frame_size < MIN_MAX_FRAME_SIZE as u32 {
+            return Err(OpenError::MaxFrameSizeTooSmall(max_frame_size));
+        }
+        Ok(())
+    }
+
     async fn negotiate_amqp_with_framed<Io>(
         &self,
         framed_write: FramedWrite<WriteHalf<Io>, ProtocolHeaderCodec>,
@@ -198,23 +251,78 @@ impl<Tls, Sasl> ConnectionAcceptor<Tls, Sasl> {
         let (outgoing_tx, outgoing_rx) = mpsc::channel(self.buffer_size);
         let (begin_tx, begin_rx) = mpsc::channel(self.buffer_size);
 
-        let connection = connection::Connection::new(local_state, self.local_open.clone());
+        let connection = connection::Connection::new(
+            local_state,
+            self.local_open.clone(),
+            None,
+            self.log_redactor,
+        );
+        let active_sessions = connection.active_sessions.clone();
         let listener_connection = ListenerConnection {
             connection,
             session_listener: begin_tx,
+            properties_fn: self.properties_fn.clone(),
+            redirect_fn: self.redirect_fn.clone(),
+            redirect: None,
         };
 
-        let engine =
-            ConnectionEngine::open(transport, listener_connection, control_rx, outgoing_rx).await?;
-        let (handle, outcome) = engine.spawn();
+        let mut engine =
+            ConnectionEngine::accept(transport, listener_connection, control_rx, outgoing_rx)
+                .await?;
+
+        // The remote Open is validated before the local Open is sent and before any session is
+        // started. A rejection takes precedence over a redirect
+        let rejection = match (&self.open_fn, engine.remote_open()) {
+            (Some(open_fn), Some(remote_open)) => open_fn(remote_open).await.err(),
+            _ => None,
+        };
+        let redirect = engine.connection_mut().redirect.take();
+        let refusal = match (rejection, redirect) {
+            (Some(error), _) => Some((error.clone(), OpenError::Rejected(error))),
+            (None, Some(redirect)) => Some((
+                redirect.clone().into_connection_error(None),
+                OpenError::Redirected(redirect),
+            )),
+            (None, None) => None,
+        };
+        if refusal.is_some() {
+            engine.connection_mut().mark_establishment_failed();
+        }
+
+        let engine = engine.send_local_open().await?;
+        if let Some((error, open_error)) = refusal {
+            // The Open that refuses the connection is immediately followed by the Close
+            let _ = engine.refuse(error).await;
+            return Err(open_error);
+        }
+        let remote_open = engine
+            .remote_open()
+            .cloned()
+            .ok_or(OpenError::IllegalState)?;
+        let container_id = engine.container_id().to_string();
+        let max_frame_size = engine.max_frame_size();
+        let frame_activity = engine.frame_activity();
+        let events = engine.events();
+        let local_idle_timeout = engine.local_idle_timeout();
+        let heartbeat_interval = engine.heartbeat_interval();
+        let (handle, outcome) = engine.spawn(&Spawner::default());
 
         let connection_handle = ConnectionHandle {
             is_closed: false,
             control: control_tx,
             handle,
             outcome,
+            spawner: Spawner::default(),
             outgoing: outgoing_tx,
             session_listener: begin_rx,
+            container_id,
+            remote_open,
+            max_frame_size,
+            active_sessions,
+            frame_activity,
+            events,
+            local_idle_timeout,
+            heartbeat_interval,
         };
         Ok(connection_handle)
     }
@@ -273,7 +381,7 @@ where
                         additional_data: None,
                     };
                     transport.send(sasl::Frame::Outcome(outcome)).await?;
-                    return Err(OpenError::SaslError {
+                    return Err(OpenError::SaslOutcome {
                         code: SaslCode::Sys,
                         additional_data: None,
                     });
@@ -391,6 +499,7 @@ impl ConnectionAcceptor<(), ()> {
     where
         Io: AsyncRead + AsyncWrite + std::fmt::Debug + Send + Unpin + 'static,
     {
+        self.validate_max_frame_size()?;
         self.negotiate_amqp_with_stream(stream).await
     }
 }
@@ -404,6 +513,7 @@ where
     where
         Io: AsyncRead + AsyncWrite + std::fmt::Debug + Send + Unpin + 'static,
     {
+        self.validate_max_frame_size()?;
         self.negotiate_sasl_with_stream(stream).await
     }
 }
@@ -415,6 +525,7 @@ cfg_native_tls! {
         where
             Io: AsyncRead + AsyncWrite + std::fmt::Debug + Send + Unpin + 'static,
         {
+            self.validate_max_frame_size()?;
             self.negotiate_tls_with_native_tls(stream).await
         }
     }
@@ -428,6 +539,7 @@ cfg_native_tls! {
         where
             Io: AsyncRead + AsyncWrite + std::fmt::Debug + Send + Unpin + 'static,
         {
+            self.validate_max_frame_size()?;
             self.negotiate_tls_with_native_tls(stream).await
         }
     }
@@ -440,6 +552,7 @@ cfg_rustls! {
         where
             Io: AsyncRead + AsyncWrite + std::fmt::Debug + Send + Unpin + 'static,
         {
+            self.validate_max_frame_size()?;
             self.negotiate_tls_with_rustls(stream).await
         }
     }
@@ -453,18 +566,46 @@ cfg_rustls! {
         where
             Io: AsyncRead + AsyncWrite + std::fmt::Debug + Send + Unpin + 'static,
         {
+            self.validate_max_frame_size()?;
             self.negotiate_tls_with_rustls(stream).await
         }
     }
 }
 
 /// A connection on the listener side
-#[derive(Debug)]
 pub struct ListenerConnection {
     pub(crate) connection: connection::Connection,
     pub(crate) session_listener: mpsc::Sender<IncomingSession>,
+    pub(crate) properties_fn: Option<PropertiesFn>,
+    pub(crate) redirect_fn: Option<RedirectFn>,
+    pub(crate) redirect: Option<Redirect>,
 }
 
+impl std::fmt::Debug for ListenerConnection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ListenerConnection")
+            .field("connection", &self.connection)
+            .field("session_listener", &self.session_listener)
+            .field("properties_fn", &self.properties_fn.as_ref().map(|_| "Fn"))
+            .field("redirect_fn", &self.redirect_fn.as_ref().map(|_| "Fn"))
+            .finish()
+    }
+}
+
+
+impl ListenerConnection {
+    /// Marks the local Open as the one that refuses the connection
+    fn mark_establishment_failed(&mut self) {
+        self.connection
+            .local_open
+            .properties
+            .get_or_insert_with(Fields::new)
+            .insert(
+                Symbol::from(connection::CONNECTION_ESTABLISHMENT_FAILED),
+                Value::Bool(true),
+            );
+    }
+}
 
 impl endpoint::Connection for ListenerConnection {
     type AllocError = <connection::Connection as endpoint::Connection>::AllocError;
@@ -484,12 +625,22 @@ impl endpoint::Connection for ListenerConnection {
         self.connection.local_open()
     }
 
+    #[inline]
+    fn remote_open(&self) -> Option<&Open> {
+        self.connection.remote_open()
+    }
+
+    #[inline]
+    fn log_redactor(&self) -> Option<LogRedactor> {
+        self.connection.log_redactor()
+    }
+
     #[inline]
     fn allocate_session(
         &mut self,
-        tx: mpsc::Sender<crate::session::frame::SessionIncomingItem>,
+        relay: SessionRelay,
     ) -> Result<OutgoingChannel, Self::AllocError> {
-        self.connection.allocate_session(tx)
+        self.connection.allocate_session(relay)
     }
 
     #[inline]
@@ -503,6 +654,20 @@ impl endpoint::Connection for ListenerConnection {
         channel: IncomingChannel,
         open: Open,
     ) -> Result<(), Self::OpenError> {
+        // The remote Open is received before the local Open is sent
+        if let Some(properties_fn) = &self.properties_fn {
+            let properties = self
+                .connection
+                .local_open
+                .properties
+                .get_or_insert_with(Fields::new);
+            for (key, value) in properties_fn(&open) {
+                properties.insert(key, value);
+            }
+        }
+        if let Some(redirect_fn) = &self.redirect_fn {
+            self.redirect = redirect_fn(&open);
+        }
         self.connection.on_incoming_open(channel, open)
     }
 
@@ -517,7 +682,7 @@ impl endpoint::Connection for ListenerConnection {
             Some(relay) => {
                 // forward begin to session
                 let sframe = SessionFrame::new(channel, SessionFrameBody::Begin(begin));
-                relay.send(sframe).await?;
+                relay.tx.send(sframe.into()).await?;
             }
             None => {
                 // If a session is locally initiated, the remote-channel MUST NOT be set. When an endpoint responds
@@ -618,10 +783,20 @@ impl endpoint::Connection for ListenerConnection {
     }
 
     #[inline]
-    fn session_tx_by_incoming_channel(
+    fn session_relay_by_incoming_channel(
         &mut self,
         channel: IncomingChannel,
-    ) -> Option<&mpsc::Sender<crate::session::frame::SessionIncomingItem>> {
-        self.connection.session_tx_by_incoming_channel(channel)
+    ) -> Option<&Arc<SessionRelay>> {
+        self.connection.session_relay_by_incoming_channel(channel)
+    }
+
+    #[inline]
+    fn session_channels(&self) -> Vec<u16> {
+        self.connection.session_channels()
+    }
+
+    #[inline]
+    fn drop_session_relays(&mut self) {
+        self.connection.drop_session_relays()
     }
 }
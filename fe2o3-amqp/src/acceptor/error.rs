@@ -1,6 +1,6 @@
 //! Implements errors for the acceptors
 
-use crate::link::{ReceiverAttachError, SenderAttachError};
+use crate::link::{ReceiverAttachError, ReceiverResumeError, SenderAttachError, SenderResumeError};
 
 /// Error accepting incoming attach
 #[derive(Debug, thiserror::Error)]
@@ -16,6 +16,14 @@ pub enum AcceptorAttachError {
     /// Local receiver is unable to accept incoming attach from remote sender
     #[error("Local receiver is unable to accept incoming attach from remote sender")]
     LocalReceiver(ReceiverAttachError),
+
+    /// Parked local sender is unable to resume with incoming attach from remote receiver
+    #[error("Parked local sender is unable to resume with incoming attach from remote receiver")]
+    ResumeLocalSender(SenderResumeError),
+
+    /// Parked local receiver is unable to resume with incoming attach from remote sender
+    #[error("Parked local receiver is unable to resume with incoming attach from remote sender")]
+    ResumeLocalReceiver(ReceiverResumeError),
 }
 
 impl From<SenderAttachError> for AcceptorAttachError {
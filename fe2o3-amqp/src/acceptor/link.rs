@@ -17,8 +17,8 @@ use crate::{connection::DEFAULT_OUTGOING_BUFFER_SIZE, session::SessionHandle, ut
 
 use super::{
     builder::Builder, error::AcceptorAttachError, local_receiver_link::LocalReceiverLinkAcceptor,
-    local_sender_link::LocalSenderLinkAcceptor, session::ListenerSessionHandle,
-    SupportedReceiverSettleModes, SupportedSenderSettleModes,
+    local_sender_link::LocalSenderLinkAcceptor, loopback::LoopbackNode,
+    session::ListenerSessionHandle, SupportedReceiverSettleModes, SupportedSenderSettleModes,
 };
 
 /// Listener side link endpoint
@@ -31,6 +31,56 @@ pub enum LinkEndpoint {
     Receiver(crate::link::Receiver),
 }
 
+impl LinkEndpoint {
+    /// The source of the link once the attach is exchanged. The source of an accepted sender is
+    /// the one echoed back to the remote receiver, and the source of an accepted receiver is the
+    /// one sent by the remote sender
+    pub fn source(&self) -> &Option<Source> {
+        match self {
+            LinkEndpoint::Sender(sender) => sender.source(),
+            LinkEndpoint::Receiver(receiver) => receiver.source(),
+        }
+    }
+
+    /// The target of the link once the attach is exchanged. The target of an accepted receiver is
+    /// the one echoed back to the remote sender, and the target of an accepted sender is the one
+    /// sent by the remote receiver
+    pub fn target(&self) -> &Option<Target> {
+        match self {
+            LinkEndpoint::Sender(sender) => sender.target(),
+            LinkEndpoint::Receiver(receiver) => receiver.target(),
+        }
+    }
+
+    /// The sender settle mode in use once the attach is exchanged
+    pub fn snd_settle_mode(&self) -> &SenderSettleMode {
+        match self {
+            LinkEndpoint::Sender(sender) => sender.snd_settle_mode(),
+            LinkEndpoint::Receiver(receiver) => receiver.snd_settle_mode(),
+        }
+    }
+
+    /// The receiver settle mode in use once the attach is exchanged
+    pub fn rcv_settle_mode(&self) -> &ReceiverSettleMode {
+        match self {
+            LinkEndpoint::Sender(sender) => sender.rcv_settle_mode(),
+            LinkEndpoint::Receiver(receiver) => receiver.rcv_settle_mode(),
+        }
+    }
+}
+
+impl From<crate::link::Sender> for LinkEndpoint {
+    fn from(value: crate::link::Sender) -> Self {
+        Self::Sender(value)
+    }
+}
+
+impl From<crate::link::Receiver> for LinkEndpoint {
+    fn from(value: crate::link::Receiver) -> Self {
+        Self::Receiver(value)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct SharedLinkAcceptorFields {
     /// The maximum message size supported by the link endpoint
@@ -49,24 +99,31 @@ pub(crate) struct SharedLinkAcceptorFields {
     pub desired_capabilities: Option<Vec<Symbol>>,
 
     /// Supported sender settle mode
+    ///
+    /// This only applies to the links accepted as a sender, because the sender settle mode of a
+    /// remote sender is the mode in use
     pub supported_snd_settle_modes: SupportedSenderSettleModes,
 
     /// The sender settle mode to fallback to when the mode desired
-    /// by the remote peer is not supported.
+    /// by the remote receiver is not supported.
     ///
-    /// If this field is None, an incoming attach whose desired sender settle
-    /// mode is not supported will then be rejected
+    /// This is the mode sent in the responding attach, which the remote receiver then uses
     pub fallback_snd_settle_mode: SenderSettleMode,
 
     /// Supported receiver settle mode
+    ///
+    /// This only applies to the links accepted as a receiver, because the receiver settle mode of
+    /// a remote receiver is the mode in use
     pub supported_rcv_settle_modes: SupportedReceiverSettleModes,
 
     /// The receiver settle mode to fallback to when the mode desired
-    /// by the remote peer is not supported
+    /// by the remote sender is not supported
     ///
-    /// If this field is None, an incoming attach whose desired receiver settle
-    /// mode is not supported will then be rejected
+    /// This is the mode sent in the responding attach, which the remote sender then uses
     pub fallback_rcv_settle_mode: ReceiverSettleMode,
+
+    /// In-memory nodes that handle the links attached to their addresses
+    pub loopback_nodes: Vec<LoopbackNode>,
 }
 
 impl Default for SharedLinkAcceptorFields {
@@ -81,6 +138,7 @@ impl Default for SharedLinkAcceptorFields {
             fallback_snd_settle_mode: SenderSettleMode::default(),
             supported_rcv_settle_modes: SupportedReceiverSettleModes::default(),
             fallback_rcv_settle_mode: ReceiverSettleMode::default(),
+            loopback_nodes: Vec::new(),
         }
     }
 }
@@ -102,16 +160,22 @@ impl Default for SharedLinkAcceptorFields {
 /// | Field | Default Value |
 /// |-------|---------------|
 /// |`supported_snd_settle_modes`|[`SupportedSenderSettleModes::All`]|
-/// |`fallback_snd_settle_mode`| `None` |
+/// |`fallback_snd_settle_mode`| [`SenderSettleMode::Mixed`] |
 /// |`supported_rcv_settle_modes`|[`SupportedReceiverSettleModes::Both`]|
-/// |`fallback_rcv_settle_mode`| `None` |
+/// |`fallback_rcv_settle_mode`| [`ReceiverSettleMode::First`] |
 /// |`initial_delivery_count`| `0` |
 /// |`max_message_size`| `None` |
 /// |`offered_capabilities`| `None` |
 /// |`desired_capabilities`| `None` |
 /// |`properties`| `None` |
+/// |`source_capabilities`| `None` |
+/// |`source_outcomes`| `None`, echoes the outcomes of the remote source |
+/// |`default_outcome`| `None`, echoes the default outcome of the remote source |
+/// |`target_capabilities`| `None` |
 /// |`buffer_size`| [`u16::MAX`] |
 /// |`credit_mode`| [`CreditMode::Auto(DEFAULT_CREDIT)`] |
+/// |`auto_accept`| `false` |
+/// |`loopback_nodes`| empty |
 ///
 /// # Customize acceptor
 ///
@@ -211,14 +275,35 @@ where
     }
 
     /// Accept incoming link by waiting for an incoming Attach performative
+    ///
+    /// Links attached to the address of a [`LoopbackNode`] added to the acceptor are handed over
+    /// to the node, and this waits for the next incoming Attach instead.
+    ///
+    /// An incoming Attach with the name of a link parked in the session's
+    /// [`ParkedLinks`](crate::acceptor::ParkedLinks) resumes the parked link instead of attaching
+    /// a new one.
     pub async fn accept(
         &self,
         session: &mut ListenerSessionHandle,
     ) -> Result<LinkEndpoint, AcceptorAttachError> {
-        let remote_attach = session
-            .next_incoming_attach()
-            .await
-            .ok_or(AcceptorAttachError::IllegalSessionState)?;
-        self.accept_incoming_attach(remote_attach, session).await
+        loop {
+            let remote_attach = session
+                .next_incoming_attach()
+                .await
+                .ok_or(AcceptorAttachError::IllegalSessionState)?;
+            if let Some(parked) = session.parked_links().take(&remote_attach) {
+                return parked.resume_incoming_attach(remote_attach).await;
+            }
+            let link = self.accept_incoming_attach(remote_attach, session).await?;
+            match self
+                .shared
+                .loopback_nodes
+                .iter()
+                .find(|node| node.is_node_of(&link))
+            {
+                Some(node) => node.attach(link),
+                None => return Ok(link),
+            }
+        }
     }
 }
@@ -11,16 +11,17 @@ use fe2o3_amqp_types::{
     primitives::Symbol,
 };
 use parking_lot::RwLock;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, watch};
 
 use crate::{
     control::SessionControl,
     endpoint::{InputHandle, LinkAttach, LinkExt},
     link::{
-        receiver::{CreditMode, ReceiverInner},
+        receiver::{CreditMode, OnCloseUnsettled, ReceiverInner},
+        remote_settlement::RemoteSettlements,
         state::{LinkFlowState, LinkFlowStateInner, LinkState},
         target_archetype::TargetArchetypeExt,
-        LinkFrame, LinkIncomingItem, LinkRelay, ReceiverAttachError, ReceiverLink,
+        LinkFrame, LinkId, LinkIncomingItem, LinkRelay, ReceiverAttachError, ReceiverLink,
     },
     session::SessionHandle,
     Receiver,
@@ -119,14 +120,8 @@ where
             + Send
             + Sync,
     {
-        let snd_settle_mode = if shared
-            .supported_snd_settle_modes
-            .supports(&remote_attach.snd_settle_mode)
-        {
-            remote_attach.snd_settle_mode.clone()
-        } else {
-            shared.fallback_snd_settle_mode.clone()
-        };
+        // The `snd_settle_mode` of the sender is the actual mode in use
+        let snd_settle_mode = remote_attach.snd_settle_mode.clone();
         // The receiver SHOULD respect the sender’s desired settlement mode if
         // the sender initiates the attach exchange and the receiver supports the desired mode
         let rcv_settle_mode = if shared
@@ -143,8 +138,8 @@ where
 
         // Create shared flow state
         let flow_state_inner = LinkFlowStateInner {
-            initial_delivery_count: 0, // This will be set in `on_incoming_attach`
-            delivery_count: 0,
+            initial_delivery_count: 0.into(), // This will be set in `on_incoming_attach`
+            delivery_count: 0.into(),
             link_credit: 0, // The link-credit and available variables are initialized to zero.
             available: 0,
             drain: false, // The drain flag is initialized to false.
@@ -156,18 +151,20 @@ where
 
         // Comparing unsettled should be taken care of in `on_incoming_attach`
         let unsettled = Arc::new(RwLock::new(None));
+        let remote_settlements = Arc::new(RemoteSettlements::default());
         let link_handle = LinkRelay::Receiver {
             tx: incoming_tx,
             output_handle: (),
             flow_state: flow_state_producer,
             unsettled: unsettled.clone(),
+            remote_settlements: remote_settlements.clone(),
             receiver_settle_mode: rcv_settle_mode.clone(),
             more: false,
         };
 
         // Allocate link in session
         let input_handle = InputHandle::from(remote_attach.handle.clone());
-        let output_handle = super::session::allocate_incoming_link(
+        let output_handle = crate::session::allocate_incoming_link(
             &control,
             remote_attach.name.clone(),
             link_handle,
@@ -203,6 +200,7 @@ where
 
         let mut link = ReceiverLink::<T> {
             role: PhantomData,
+            id: LinkId::new(),
             local_state: LinkState::Unattached, // State change will be taken care of in `on_incoming_attach`
             name: remote_attach.name.clone(),
             output_handle: Some(output_handle),
@@ -216,8 +214,12 @@ where
             desired_capabilities: shared.desired_capabilities.clone(),
             flow_state: flow_state_consumer,
             unsettled,
+            unsettled_store: None,
+            restored_unsettled: None,
+            incomplete_delivery: None,
             verify_incoming_source: self.verify_incoming_source,
             verify_incoming_target: self.verify_incoming_target,
+            state_notifier: watch::channel(LinkState::Unattached).0,
         };
 
         // `on_incoming_attach` should always be evaluated
@@ -225,22 +227,9 @@ where
             (Some(attach_error), _) | (_, Err(attach_error)) => {
                 // Complete attach anyway
                 link.send_attach(&outgoing, &control, false).await?;
-                match attach_error {
-                    // ReceiverAttachError::SndSettleModeNotSupported
-                    ReceiverAttachError::RcvSettleModeNotSupported => {
-                        // FIXME: Ths initiating end should be responsible for checking whether the mode is supported
-                    }
-                    _ => {
-                        return Err(link
-                            .handle_attach_error(
-                                attach_error,
-                                &outgoing,
-                                &mut incoming_rx,
-                                &control,
-                            )
-                            .await)
-                    }
-                }
+                return Err(link
+                    .handle_attach_error(attach_error, &outgoing, &mut incoming_rx, &control)
+                    .await);
             }
             _ => link.send_attach(&outgoing, &control, false).await?,
         }
@@ -250,14 +239,28 @@ where
             buffer_size: shared.buffer_size,
             credit_mode: self.credit_mode.clone(),
             processed: AtomicU32::new(0),
+            granted: AtomicU32::new(0),
+            paused: false,
             auto_accept: self.auto_accept,
+            index_sections: false,
+            credit_pool: None,
             session: control.clone(),
             outgoing,
             incoming: incoming_rx,
             incomplete_transfer: None,
+            dedup_window: None,
+            aborted_deliveries: 0,
+            remote_settlements,
+            on_close_unsettled: OnCloseUnsettled::Leave,
+            unsettled_infos: Vec::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            settlement_timeout: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            warn_after_no_delivery: None,
         };
 
-        if let CreditMode::Auto(credit) = inner.credit_mode {
+        // The initial credit is issued right after the Attach is echoed
+        if let Some(credit) = inner.credit_mode.refill_credit() {
             #[cfg(feature = "tracing")]
             tracing::debug!("Setting credits");
             #[cfg(feature = "log")]
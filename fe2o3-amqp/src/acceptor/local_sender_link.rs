@@ -4,19 +4,19 @@ use std::{marker::PhantomData, sync::Arc};
 
 use fe2o3_amqp_types::{
     definitions::SequenceNo,
-    messaging::{Source, Target},
+    messaging::{Outcome, Source, Target},
     performatives::Attach,
     primitives::Symbol,
 };
 use parking_lot::RwLock;
-use tokio::sync::{mpsc, Notify};
+use tokio::sync::{mpsc, watch, Notify};
 
 use crate::{
     endpoint::{InputHandle, LinkAttach, LinkExt},
     link::{
         sender::SenderInner,
         state::{LinkFlowState, LinkFlowStateInner, LinkState},
-        LinkRelay, SenderAttachError, SenderLink,
+        LinkId, LinkRelay, SenderAttachError, SenderLink,
     },
     session::SessionHandle,
     util::{Consumer, Producer},
@@ -42,6 +42,13 @@ where
     /// the extension capabilities the sender supports/desires
     pub source_capabilities: Option<Vec<C>>,
 
+    /// The outcomes the source supports, which replace the outcomes of the remote source if set
+    pub source_outcomes: Option<Vec<C>>,
+
+    /// The default outcome of the source, which replaces the default outcome of the remote
+    /// source if set
+    pub default_outcome: Option<Outcome>,
+
     pub on_dynamic_source: F,
 
     /// Whether the local link will verify the incoming source/target
@@ -61,6 +68,8 @@ where
         Self {
             initial_delivery_count: 0,
             source_capabilities: None,
+            source_outcomes: None,
+            default_outcome: None,
             on_dynamic_source: reject_dynamic_source,
             verify_incoming_source: true,
             verify_incoming_target: true,
@@ -72,6 +81,18 @@ impl<F> LocalSenderLinkAcceptor<Symbol, F>
 where
     F: Fn(Source) -> Option<Source>,
 {
+    /// Applies the terminus settings of the acceptor to the source that is echoed back
+    fn shape_source(&self, mut source: Source) -> Source {
+        source.capabilities = self.source_capabilities.clone().map(Into::into);
+        if let Some(outcomes) = &self.source_outcomes {
+            source.outcomes = Some(outcomes.clone().into());
+        }
+        if let Some(outcome) = &self.default_outcome {
+            source.default_outcome = Some(outcome.clone());
+        }
+        source
+    }
+
     /// Accepts an incoming attach as a local sender
     pub async fn accept_incoming_attach<R>(
         &self,
@@ -79,6 +100,10 @@ where
         remote_attach: Attach,
         session: &mut SessionHandle<R>,
     ) -> Result<Sender, SenderAttachError> {
+        // The sender SHOULD respect the receiver’s desired settlement mode if the receiver
+        // initiates the attach exchange and the sender supports the desired mode. The
+        // `rcv_settle_mode` of the receiver is the actual mode in use, which is taken in
+        // `on_incoming_attach`
         let snd_settle_mode = if shared
             .supported_snd_settle_modes
             .supports(&remote_attach.snd_settle_mode)
@@ -87,20 +112,13 @@ where
         } else {
             shared.fallback_snd_settle_mode.clone()
         };
-        let rcv_settle_mode = if shared
-            .supported_rcv_settle_modes
-            .supports(&remote_attach.rcv_settle_mode)
-        {
-            remote_attach.rcv_settle_mode.clone()
-        } else {
-            shared.fallback_rcv_settle_mode.clone()
-        };
+        let rcv_settle_mode = remote_attach.rcv_settle_mode.clone();
 
         let (incoming_tx, mut incoming_rx) = mpsc::channel(shared.buffer_size);
 
         let flow_state_inner = LinkFlowStateInner {
-            initial_delivery_count: self.initial_delivery_count,
-            delivery_count: self.initial_delivery_count,
+            initial_delivery_count: self.initial_delivery_count.into(),
+            delivery_count: self.initial_delivery_count.into(),
             link_credit: 0,
             available: 0,
             drain: false,
@@ -122,7 +140,7 @@ where
 
         // Allocate link in session
         let input_handle = InputHandle::from(remote_attach.handle.clone());
-        let output_handle = super::session::allocate_incoming_link(
+        let output_handle = crate::session::allocate_incoming_link(
             &session.control,
             remote_attach.name.clone(),
             link_handle,
@@ -134,19 +152,15 @@ where
         // version of the source properties
         let local_source = remote_attach.source.clone().and_then(|s| {
             if s.dynamic {
-                (self.on_dynamic_source)(*s).map(|mut s| {
-                    s.capabilities = self.source_capabilities.clone().map(Into::into);
-                    s
-                })
+                (self.on_dynamic_source)(*s).map(|s| self.shape_source(s))
             } else {
-                let mut source = *s;
-                source.capabilities = self.source_capabilities.clone().map(Into::into);
-                Some(source)
+                Some(self.shape_source(*s))
             }
         });
 
         let mut link = SenderLink::<Target> {
             role: PhantomData,
+            id: LinkId::new(),
             local_state: LinkState::Unattached, // will be set in `on_incoming_attach`
 
             name: remote_attach.name.clone(),
@@ -161,8 +175,12 @@ where
             desired_capabilities: shared.desired_capabilities.clone(),
             flow_state: flow_state_consumer,
             unsettled,
+            unsettled_store: None,
+            restored_unsettled: None,
+            incomplete_delivery: None,
             verify_incoming_source: self.verify_incoming_source,
             verify_incoming_target: self.verify_incoming_target,
+            state_notifier: watch::channel(LinkState::Unattached).0,
         };
 
         let outgoing = session.outgoing.clone();
@@ -172,22 +190,14 @@ where
             Err(attach_error) => {
                 // Complete attach then detach should any error happen
                 link.send_attach(&outgoing, &session.control, false).await?;
-                match attach_error {
-                    SenderAttachError::SndSettleModeNotSupported
-                    | SenderAttachError::RcvSettleModeNotSupported => {
-                        // FIXME: The initiating side is responsible for checking whether the desired modes are supported?
-                    }
-                    _ => {
-                        return Err(link
-                            .handle_attach_error(
-                                attach_error,
-                                &outgoing,
-                                &mut incoming_rx,
-                                &session.control,
-                            )
-                            .await);
-                    }
-                }
+                return Err(link
+                    .handle_attach_error(
+                        attach_error,
+                        &outgoing,
+                        &mut incoming_rx,
+                        &session.control,
+                    )
+                    .await);
             }
         }
 
@@ -197,6 +207,18 @@ where
             session: session.control.clone(),
             outgoing,
             incoming: incoming_rx,
+            rejected_as_error: false,
+            #[cfg(feature = "compression")]
+            body_compression: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            warn_after_no_credit: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            fail_after_no_credit: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            queue_policy: Default::default(),
+            validator: Default::default(),
+            batch_validation_policy: Default::default(),
+            pending_outcomes: Vec::new(),
         };
         Ok(Sender { inner })
     }
@@ -0,0 +1,700 @@
+//! In-memory node that forwards deliveries between the links attached to it
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+
+use fe2o3_amqp_types::{
+    definitions::MessageFormat,
+    messaging::{Accepted, Released, MESSAGE_FORMAT},
+};
+use futures_util::{stream::FuturesUnordered, StreamExt};
+use parking_lot::Mutex;
+use tokio::sync::{mpsc, oneshot, Notify};
+
+use crate::{
+    link::{
+        delivery::DeliveryInfo, receiver::TerminalDeliveryState, role::SenderMarker,
+        state::LinkFlowState, DispositionError, SendError,
+    },
+    Payload, Receiver, SendReceipt, Sender,
+};
+
+use super::LinkEndpoint;
+
+/// Decides which of the outgoing links of a [`LoopbackNode`] receives each message.
+///
+/// Only the outgoing links that have link credit and are not sending another message are
+/// considered. A message that is released or modified by the remote receiver of an outgoing link
+/// is dispatched again to another outgoing link, and its outcome is only returned to the incoming
+/// link if no other outgoing link can take it.
+#[derive(Debug, Clone, Default)]
+pub enum DeliveryPolicy {
+    /// The outgoing links take turns in the order they are attached
+    #[default]
+    RoundRobin,
+
+    /// Each outgoing link receives a share of the messages in proportion to the weight of its
+    /// link name. A link whose name is not in the map has a weight of 1, and a link with a weight
+    /// of 0 receives no message
+    Weighted(HashMap<String, u32>),
+
+    /// Only one outgoing link, which is the first one attached, receives the messages.
+    ///
+    /// If `failover` is true, the outgoing link that is attached the longest becomes active once
+    /// the active link is detached. Otherwise the messages wait for the next outgoing link to be
+    /// attached
+    SingleActive {
+        /// Whether another attached link takes over once the active link is detached
+        failover: bool,
+    },
+}
+
+/// A node hosted by the listener that forwards the messages received on its incoming links to its
+/// outgoing links.
+///
+/// A link is attached to the node if the target address of a listener side receiver or the
+/// source address of a listener side sender is the same as the address of the node. Each message
+/// is forwarded as the encoded payload it was received with, so it is not decoded into a
+/// [`Message`](fe2o3_amqp_types::messaging::Message) and encoded again on the listener. The
+/// delivery annotations, which are only meant for the node, are stripped before forwarding.
+///
+/// The outgoing links compete for the messages, and the [`DeliveryPolicy`] of the node decides
+/// which of them receives each message. The outcome returned by the remote receiver is used to
+/// dispose of the delivery on the incoming link. A delivery is released if its outgoing link is
+/// detached before an outcome is returned. The remote peers see the same frames as if the
+/// listener decoded and re-sent each message itself.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let node = LoopbackNode::new("q1").delivery_policy(DeliveryPolicy::SingleActive { failover: true });
+/// let link_acceptor = LinkAcceptor::builder().loopback_node(node).build();
+///
+/// // Links attached to "q1" are handled by the node and not returned here
+/// while let Ok(link) = link_acceptor.accept(&mut session).await {
+///     // ...
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct LoopbackNode {
+    address: String,
+    queue: Arc<Mutex<Queue>>,
+}
+
+/// A delivery that is waiting for an outgoing link of the node
+#[derive(Debug)]
+struct Forward {
+    payload: Payload,
+    message_format: MessageFormat,
+    settled: bool,
+
+    // The outgoing links that released or modified the delivery
+    refused: Vec<u64>,
+
+    // Notified once the delivery is taken by an outgoing link for the first time
+    taken: Option<oneshot::Sender<()>>,
+    responder: oneshot::Sender<Result<SendReceipt, SendError>>,
+}
+
+/// An outgoing link of the node
+#[derive(Debug)]
+struct Consumer {
+    id: u64,
+    name: String,
+    flow_state: Arc<LinkFlowState<SenderMarker>>,
+    tx: mpsc::UnboundedSender<Forward>,
+
+    // Whether the link is waiting for the next delivery
+    ready: bool,
+
+    // The current weight of the smooth weighted round robin in `DeliveryPolicy::Weighted`
+    current_weight: i64,
+}
+
+/// The deliveries that wait for an outgoing link and the outgoing links of the node
+#[derive(Debug, Default)]
+struct Queue {
+    policy: DeliveryPolicy,
+    deliveries: VecDeque<Forward>,
+    consumers: Vec<Consumer>,
+    next_id: u64,
+
+    // The position of the next consumer in `DeliveryPolicy::RoundRobin`
+    cursor: usize,
+
+    // The consumer that receives the deliveries in `DeliveryPolicy::SingleActive`
+    active: Option<u64>,
+}
+
+impl LoopbackNode {
+    /// Creates a new node with the given address and the [`DeliveryPolicy::RoundRobin`] policy
+    pub fn new(address: impl Into<String>) -> Self {
+        Self {
+            address: address.into(),
+            queue: Default::default(),
+        }
+    }
+
+    /// Sets how the messages are distributed among the outgoing links of the node. This should be
+    /// set before any outgoing link is attached
+    pub fn delivery_policy(self, policy: DeliveryPolicy) -> Self {
+        self.queue.lock().policy = policy;
+        self
+    }
+
+    /// The address of the node
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Whether the link is attached to the address of the node
+    pub fn is_node_of(&self, link: &LinkEndpoint) -> bool {
+        let address = match link {
+            LinkEndpoint::Sender(sender) => sender
+                .source()
+                .as_ref()
+                .and_then(|source| source.address.as_ref()),
+            LinkEndpoint::Receiver(receiver) => receiver
+                .target()
+                .as_ref()
+                .and_then(|target| target.address.as_ref()),
+        };
+        address
+            .map(|address| address == &self.address)
+            .unwrap_or(false)
+    }
+
+    /// Hands the link over to the node. The link is driven by a spawned task until it is detached
+    pub fn attach(&self, link: LinkEndpoint) {
+        match link {
+            LinkEndpoint::Sender(sender) => {
+                // The outgoing link is added here so that the links are kept in the order they
+                // are attached
+                let (tx, rx) = mpsc::unbounded_channel();
+                let flow_state = sender.inner.link.flow_state.state().clone();
+                let id = self.queue.lock().attach(sender.name(), flow_state, tx);
+                crate::rt::spawn(outgoing_link(sender, self.clone(), id, rx));
+            }
+            LinkEndpoint::Receiver(receiver) => {
+                crate::rt::spawn(incoming_link(receiver, self.clone()));
+            }
+        }
+    }
+}
+
+impl Queue {
+    fn attach(
+        &mut self,
+        name: &str,
+        flow_state: Arc<LinkFlowState<SenderMarker>>,
+        tx: mpsc::UnboundedSender<Forward>,
+    ) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.consumers.push(Consumer {
+            id,
+            name: name.to_string(),
+            flow_state,
+            tx,
+            ready: false,
+            current_weight: 0,
+        });
+        if matches!(self.policy, DeliveryPolicy::SingleActive { .. }) && self.active.is_none() {
+            self.active = Some(id);
+        }
+        id
+    }
+
+    /// Removes the consumer and dispatches the deliveries that it has not taken yet to the other
+    /// consumers
+    fn detach(&mut self, id: u64, untaken: Vec<Forward>) {
+        if let Some(index) = self.consumers.iter().position(|c| c.id == id) {
+            self.consumers.remove(index);
+            if index < self.cursor {
+                self.cursor -= 1;
+            }
+        }
+        if self.active == Some(id) {
+            self.active = match self.policy {
+                DeliveryPolicy::SingleActive { failover: true } => {
+                    self.consumers.first().map(|c| c.id)
+                }
+                _ => None,
+            };
+        }
+        for forward in untaken.into_iter().rev() {
+            self.deliveries.push_front(forward);
+        }
+        self.dispatch();
+    }
+
+    /// Marks the consumer as waiting for the next delivery. This is also called when the link
+    /// credit of a waiting consumer changes
+    fn ready(&mut self, id: u64) {
+        if let Some(consumer) = self.consumers.iter_mut().find(|c| c.id == id) {
+            consumer.ready = true;
+        }
+        self.dispatch();
+    }
+
+    fn push(&mut self, forward: Forward) {
+        self.deliveries.push_back(forward);
+        self.dispatch();
+    }
+
+    /// Returns the outcome to the incoming link, or dispatches the delivery again if it is
+    /// released or modified and another consumer can take it
+    fn on_outcome(
+        &mut self,
+        id: u64,
+        mut forward: Forward,
+        outcome: Result<SendReceipt, SendError>,
+    ) {
+        if let Ok(SendReceipt::Released(_)) | Ok(SendReceipt::Modified(_)) = outcome {
+            forward.refused.push(id);
+            if self
+                .consumers
+                .iter()
+                .any(|c| self.is_candidate(c, &forward.refused))
+            {
+                self.deliveries.push_front(forward);
+                self.dispatch();
+                return;
+            }
+        }
+        let _ = forward.responder.send(outcome);
+    }
+
+    /// Whether the consumer may take the delivery under the policy, regardless of whether it is
+    /// ready now
+    fn is_candidate(&self, consumer: &Consumer, refused: &[u64]) -> bool {
+        if refused.contains(&consumer.id) {
+            return false;
+        }
+        match &self.policy {
+            DeliveryPolicy::RoundRobin => true,
+            DeliveryPolicy::Weighted(weights) => weight(weights, consumer) > 0,
+            DeliveryPolicy::SingleActive { .. } => self.active == Some(consumer.id),
+        }
+    }
+
+    fn is_eligible(&self, consumer: &Consumer, refused: &[u64]) -> bool {
+        consumer.ready
+            && consumer.flow_state.link_credit() > 0
+            && self.is_candidate(consumer, refused)
+    }
+
+    /// Hands the waiting deliveries over to the ready consumers
+    fn dispatch(&mut self) {
+        let mut position = 0;
+        while position < self.deliveries.len() {
+            if !self.consumers.iter().any(|c| c.ready) {
+                break;
+            }
+            let refused = self.deliveries[position].refused.clone();
+            let index = match self.select(&refused) {
+                Some(index) => index,
+                None => {
+                    position += 1;
+                    continue;
+                }
+            };
+            let mut forward = match self.deliveries.remove(position) {
+                Some(forward) => forward,
+                None => break,
+            };
+            if let Some(taken) = forward.taken.take() {
+                let _ = taken.send(());
+            }
+            let consumer = &mut self.consumers[index];
+            consumer.ready = false;
+            if let Err(mpsc::error::SendError(forward)) = consumer.tx.send(forward) {
+                // The consumer is going away and will be removed
+                self.deliveries.insert(position, forward);
+                position += 1;
+            }
+        }
+    }
+
+    /// Selects the consumer of the delivery according to the policy
+    fn select(&mut self, refused: &[u64]) -> Option<usize> {
+        let len = self.consumers.len();
+        match &self.policy {
+            DeliveryPolicy::RoundRobin => {
+                let index = (0..len)
+                    .map(|offset| (self.cursor + offset) % len)
+                    .find(|index| self.is_eligible(&self.consumers[*index], refused))?;
+                self.cursor = index + 1;
+                Some(index)
+            }
+            DeliveryPolicy::Weighted(weights) => {
+                // Smooth weighted round robin among the eligible consumers
+                let eligible: Vec<usize> = (0..len)
+                    .filter(|index| self.is_eligible(&self.consumers[*index], refused))
+                    .collect();
+                let mut total = 0;
+                let mut selected: Option<usize> = None;
+                for index in eligible {
+                    let weight = weight(weights, &self.consumers[index]);
+                    total += weight;
+                    self.consumers[index].current_weight += weight;
+                    let current_weight = self.consumers[index].current_weight;
+                    if selected.map_or(true, |s| current_weight > self.consumers[s].current_weight)
+                    {
+                        selected = Some(index);
+                    }
+                }
+                let index = selected?;
+                self.consumers[index].current_weight -= total;
+                Some(index)
+            }
+            DeliveryPolicy::SingleActive { .. } => {
+                (0..len).find(|index| self.is_eligible(&self.consumers[*index], refused))
+            }
+        }
+    }
+}
+
+fn weight(weights: &HashMap<String, u32>, consumer: &Consumer) -> i64 {
+    weights.get(&consumer.name).copied().unwrap_or(1) as i64
+}
+
+/// Resolves once the link has link credit if it is not waiting for a delivery yet, and once the
+/// flow state of the link changes otherwise
+async fn credit_changed(notifier: &Notify, flow_state: &LinkFlowState<SenderMarker>, ready: bool) {
+    let notified = notifier.notified();
+    tokio::pin!(notified);
+    notified.as_mut().enable();
+    if !ready && flow_state.link_credit() > 0 {
+        return;
+    }
+    notified.await
+}
+
+/// Takes the deliveries dispatched to an outgoing link of the node and sends them
+async fn outgoing_link(
+    mut sender: Sender,
+    node: LoopbackNode,
+    id: u64,
+    mut rx: mpsc::UnboundedReceiver<Forward>,
+) {
+    let notifier = sender.inner.link.flow_state.notifier.clone();
+    let flow_state = sender.inner.link.flow_state.state().clone();
+    let mut outcomes = FuturesUnordered::new();
+    let mut ready = false;
+
+    loop {
+        tokio::select! {
+            _ = credit_changed(&notifier, &flow_state, ready) => {
+                ready = true;
+                node.queue.lock().ready(id);
+            }
+            forward = rx.recv() => {
+                let forward = match forward {
+                    Some(forward) => forward,
+                    None => break,
+                };
+                ready = false;
+                match sender
+                    .send_payload(
+                        forward.payload.clone(),
+                        forward.message_format,
+                        Some(forward.settled),
+                        false,
+                    )
+                    .await
+                {
+                    Ok(outcome) => outcomes.push(async move { (forward, outcome.await) }),
+                    Err(_) => {
+                        // The link is no longer usable. Leave the delivery to the other outgoing
+                        // links
+                        node.queue.lock().detach(id, vec![forward]);
+                        break;
+                    }
+                }
+            }
+            Some((forward, outcome)) = outcomes.next() => {
+                node.queue.lock().on_outcome(id, forward, outcome);
+            }
+            _ = sender.on_detach() => break,
+        }
+    }
+
+    rx.close();
+    let mut untaken = Vec::new();
+    while let Ok(forward) = rx.try_recv() {
+        untaken.push(forward);
+    }
+    node.queue.lock().detach(id, untaken);
+
+    let _ = sender.close().await;
+}
+
+/// Forwards the deliveries received on an incoming link of the node
+async fn incoming_link(mut receiver: Receiver, node: LoopbackNode) {
+    // The delivery that waits for an outgoing link to take it. The next delivery is not taken
+    // until it is, while the outcomes of the earlier deliveries are still returned
+    let mut untaken = FuturesUnordered::new();
+    let mut outcomes = FuturesUnordered::new();
+
+    loop {
+        tokio::select! {
+            delivery = receiver.recv_raw(), if untaken.is_empty() => {
+                let mut delivery = match delivery {
+                    Ok(delivery) => delivery,
+                    // The remote detach is already answered
+                    Err(_) => return,
+                };
+                // Delivery annotations are meant for this hop only. A payload whose sections
+                // cannot be located is forwarded as is
+                let _ = delivery.strip_delivery_annotations();
+
+                let (taken, taken_rx) = oneshot::channel();
+                let (responder, outcome) = oneshot::channel();
+                let forward = Forward {
+                    message_format: delivery.message_format.unwrap_or(MESSAGE_FORMAT),
+                    settled: delivery.info.settled,
+                    payload: delivery.payload,
+                    refused: Vec::new(),
+                    taken: Some(taken),
+                    responder,
+                };
+                node.queue.lock().push(forward);
+                untaken.push(taken_rx);
+                if !delivery.info.settled {
+                    let info = delivery.info;
+                    outcomes.push(async move { (info, outcome.await.ok()) });
+                }
+            }
+            Some(taken) = untaken.next() => {
+                if taken.is_err() {
+                    break;
+                }
+            }
+            Some((info, outcome)) = outcomes.next() => {
+                if dispose(&receiver, info, outcome).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    let _ = receiver.close().await;
+}
+
+async fn dispose(
+    receiver: &Receiver,
+    info: DeliveryInfo,
+    outcome: Option<Result<SendReceipt, SendError>>,
+) -> Result<(), DispositionError> {
+    let state = match outcome {
+        Some(Ok(SendReceipt::Settled)) | Some(Ok(SendReceipt::Accepted(_))) => {
+            TerminalDeliveryState::Accepted(Accepted {})
+        }
+        Some(Ok(SendReceipt::Rejected(rejected))) | Some(Err(SendError::Rejected(rejected))) => {
+            TerminalDeliveryState::Rejected(rejected)
+        }
+        Some(Ok(SendReceipt::Released(released))) => TerminalDeliveryState::Released(released),
+        Some(Ok(SendReceipt::Modified(modified))) => TerminalDeliveryState::Modified(modified),
+        // The outgoing link is detached before the outcome is returned
+        Some(Err(_)) | None => TerminalDeliveryState::Released(Released {}),
+    };
+    receiver.dispose(info, state).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use fe2o3_amqp_types::messaging::{Released, MESSAGE_FORMAT};
+    use tokio::sync::{mpsc, oneshot};
+
+    use crate::{
+        link::{
+            state::{LinkFlowState, LinkFlowStateInner},
+            SendError,
+        },
+        Payload, SendReceipt,
+    };
+
+    use super::{DeliveryPolicy, Forward, Queue};
+
+    struct TestConsumer {
+        id: u64,
+        rx: mpsc::UnboundedReceiver<Forward>,
+        received: usize,
+    }
+
+    fn attach(queue: &mut Queue, name: &str) -> TestConsumer {
+        let flow_state = Arc::new(LinkFlowState::sender(LinkFlowStateInner {
+            initial_delivery_count: 0.into(),
+            delivery_count: 0.into(),
+            link_credit: 100,
+            available: 0,
+            drain: false,
+            properties: None,
+        }));
+        let (tx, rx) = mpsc::unbounded_channel();
+        let id = queue.attach(name, flow_state, tx);
+        queue.ready(id);
+        TestConsumer {
+            id,
+            rx,
+            received: 0,
+        }
+    }
+
+    fn forward() -> (Forward, oneshot::Receiver<Result<SendReceipt, SendError>>) {
+        let (responder, outcome) = oneshot::channel();
+        let forward = Forward {
+            payload: Payload::from_static(b"message"),
+            message_format: MESSAGE_FORMAT,
+            settled: false,
+            refused: Vec::new(),
+            taken: None,
+            responder,
+        };
+        (forward, outcome)
+    }
+
+    /// Pushes `count` deliveries one at a time and returns the order of the consumers that take
+    /// them. Each consumer is ready again as soon as it takes a delivery
+    fn distribute(queue: &mut Queue, consumers: &mut [TestConsumer], count: usize) -> Vec<u64> {
+        let mut order = Vec::new();
+        for _ in 0..count {
+            queue.push(forward().0);
+            for consumer in consumers.iter_mut() {
+                while consumer.rx.try_recv().is_ok() {
+                    consumer.received += 1;
+                    order.push(consumer.id);
+                    queue.ready(consumer.id);
+                }
+            }
+        }
+        order
+    }
+
+    #[test]
+    fn round_robin_takes_turns_in_attach_order() {
+        let mut queue = Queue::default();
+        let mut consumers = vec![
+            attach(&mut queue, "a"),
+            attach(&mut queue, "b"),
+            attach(&mut queue, "c"),
+        ];
+
+        let order = distribute(&mut queue, &mut consumers, 30);
+        assert_eq!(&order[..6], &[0, 1, 2, 0, 1, 2]);
+        for consumer in &consumers {
+            assert_eq!(consumer.received, 10);
+        }
+    }
+
+    #[test]
+    fn weighted_distribution_follows_weights() {
+        let weights = HashMap::from([(String::from("a"), 3), (String::from("c"), 0)]);
+        let mut queue = Queue {
+            policy: DeliveryPolicy::Weighted(weights),
+            ..Default::default()
+        };
+        let mut consumers = vec![
+            attach(&mut queue, "a"),
+            attach(&mut queue, "b"),
+            attach(&mut queue, "c"),
+        ];
+
+        distribute(&mut queue, &mut consumers, 40);
+        assert_eq!(consumers[0].received, 30);
+        assert_eq!(consumers[1].received, 10);
+        assert_eq!(consumers[2].received, 0);
+    }
+
+    #[test]
+    fn deliveries_wait_for_link_credit() {
+        let mut queue = Queue::default();
+        let mut a = attach(&mut queue, "a");
+        let mut b = attach(&mut queue, "b");
+        queue.consumers[0].flow_state.lock.write().link_credit = 0;
+
+        queue.push(forward().0);
+        queue.push(forward().0);
+        assert!(a.rx.try_recv().is_err());
+        assert!(b.rx.try_recv().is_ok());
+        assert_eq!(queue.deliveries.len(), 1);
+
+        queue.consumers[0].flow_state.lock.write().link_credit = 1;
+        queue.ready(a.id);
+        assert!(a.rx.try_recv().is_ok());
+        assert!(queue.deliveries.is_empty());
+    }
+
+    #[test]
+    fn single_active_fails_over_to_the_oldest_consumer() {
+        let mut queue = Queue {
+            policy: DeliveryPolicy::SingleActive { failover: true },
+            ..Default::default()
+        };
+        let mut consumers = vec![
+            attach(&mut queue, "a"),
+            attach(&mut queue, "b"),
+            attach(&mut queue, "c"),
+        ];
+        distribute(&mut queue, &mut consumers, 5);
+        assert_eq!(consumers[0].received, 5);
+
+        // The delivery that waits for the active consumer goes to the next one once it is
+        // detached
+        queue.consumers[0].ready = false;
+        queue.push(forward().0);
+        assert!(consumers[1].rx.try_recv().is_err());
+        queue.detach(consumers[0].id, Vec::new());
+        let mut consumers = consumers.split_off(1);
+        assert!(consumers[0].rx.try_recv().is_ok());
+        queue.ready(consumers[0].id);
+
+        distribute(&mut queue, &mut consumers, 5);
+        assert_eq!(consumers[0].received, 5);
+        assert_eq!(consumers[1].received, 0);
+    }
+
+    #[test]
+    fn single_active_without_failover_waits_for_a_new_consumer() {
+        let mut queue = Queue {
+            policy: DeliveryPolicy::SingleActive { failover: false },
+            ..Default::default()
+        };
+        let a = attach(&mut queue, "a");
+        let mut b = attach(&mut queue, "b");
+        queue.detach(a.id, Vec::new());
+
+        queue.push(forward().0);
+        assert!(b.rx.try_recv().is_err());
+        let mut c = attach(&mut queue, "c");
+        assert!(c.rx.try_recv().is_ok());
+    }
+
+    #[test]
+    fn released_delivery_is_dispatched_to_another_consumer() {
+        let mut queue = Queue::default();
+        let mut a = attach(&mut queue, "a");
+        let mut b = attach(&mut queue, "b");
+
+        let (forward, mut outcome) = forward();
+        queue.push(forward);
+        let forward = a.rx.try_recv().unwrap();
+        queue.on_outcome(a.id, forward, Ok(SendReceipt::Released(Released {})));
+        assert!(outcome.try_recv().is_err());
+
+        // The outcome is returned once no other consumer can take the delivery
+        let forward = b.rx.try_recv().unwrap();
+        queue.on_outcome(b.id, forward, Ok(SendReceipt::Released(Released {})));
+        assert!(matches!(
+            outcome.try_recv(),
+            Ok(Ok(SendReceipt::Released(_)))
+        ));
+        queue.ready(a.id);
+        assert!(a.rx.try_recv().is_err());
+    }
+}
@@ -6,6 +6,9 @@ pub mod error;
 pub mod link;
 pub mod local_receiver_link;
 pub mod local_sender_link;
+pub mod loopback;
+pub mod parked_link;
+pub mod router;
 pub mod sasl_acceptor;
 pub mod session;
 
@@ -20,6 +23,9 @@ use fe2o3_amqp_types::{
 
 pub use self::connection::{ConnectionAcceptor, ListenerConnectionHandle};
 pub use self::link::{LinkAcceptor, LinkEndpoint};
+pub use self::loopback::{DeliveryPolicy, LoopbackNode};
+pub use self::parked_link::{DetachedLinkEndpoint, ParkedLinks};
+pub use self::router::{AddressPattern, HandlerError, Route, Router};
 pub use self::sasl_acceptor::{SaslAcceptor, SaslAnonymousMechanism, SaslPlainMechanism};
 pub use self::session::{ListenerSessionHandle, SessionAcceptor};
 
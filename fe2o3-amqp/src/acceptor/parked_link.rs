@@ -0,0 +1,191 @@
+//! Links that are detached by the remote peer and may be resumed by a later Attach
+
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use fe2o3_amqp_types::{definitions::Role, performatives::Attach};
+use parking_lot::Mutex;
+
+use crate::link::{receiver::DetachedReceiver, sender::DetachedSender, DetachError};
+
+use super::{error::AcceptorAttachError, LinkEndpoint};
+
+/// Default duration a parked link is kept before it is discarded
+pub const DEFAULT_PARKED_LINK_EXPIRY: Duration = Duration::from_secs(60);
+
+/// Listener side link endpoint that is detached
+#[derive(Debug)]
+pub enum DetachedLinkEndpoint {
+    /// Sender
+    Sender(DetachedSender),
+
+    /// Receiver
+    Receiver(DetachedReceiver),
+}
+
+impl DetachedLinkEndpoint {
+    /// Get the name of the link
+    pub fn name(&self) -> &str {
+        match self {
+            DetachedLinkEndpoint::Sender(sender) => sender.name(),
+            DetachedLinkEndpoint::Receiver(receiver) => receiver.name(),
+        }
+    }
+
+    /// Whether the link can be resumed by an Attach sent with the role of the remote peer
+    fn pairs_with(&self, remote_role: &Role) -> bool {
+        matches!(
+            (self, remote_role),
+            (DetachedLinkEndpoint::Sender(_), Role::Receiver)
+                | (DetachedLinkEndpoint::Receiver(_), Role::Sender)
+        )
+    }
+
+    /// Resume the link with an Attach sent by the remote peer
+    ///
+    /// A receiver is returned even if there are unsettled deliveries left to be resumed, which
+    /// the remote sender resumes by detaching and re-attaching the link again
+    pub async fn resume_incoming_attach(
+        self,
+        remote_attach: Attach,
+    ) -> Result<LinkEndpoint, AcceptorAttachError> {
+        match self {
+            DetachedLinkEndpoint::Sender(sender) => sender
+                .resume_incoming_attach(remote_attach)
+                .await
+                .map(LinkEndpoint::Sender)
+                .map_err(AcceptorAttachError::ResumeLocalSender),
+            DetachedLinkEndpoint::Receiver(receiver) => receiver
+                .resume_incoming_attach(remote_attach)
+                .await
+                .map(|resuming| LinkEndpoint::Receiver(resuming.into_receiver()))
+                .map_err(AcceptorAttachError::ResumeLocalReceiver),
+        }
+    }
+}
+
+impl From<DetachedSender> for DetachedLinkEndpoint {
+    fn from(value: DetachedSender) -> Self {
+        Self::Sender(value)
+    }
+}
+
+impl From<DetachedReceiver> for DetachedLinkEndpoint {
+    fn from(value: DetachedReceiver) -> Self {
+        Self::Receiver(value)
+    }
+}
+
+#[derive(Debug)]
+struct ParkedLink {
+    parked_at: Instant,
+    link: DetachedLinkEndpoint,
+}
+
+/// Links of a listener session that are kept after a non-closing Detach so that the remote peer
+/// can resume them.
+///
+/// An incoming Attach with the name of a parked link is routed to the parked link by
+/// [`LinkAcceptor::accept`](crate::acceptor::LinkAcceptor::accept), which resumes the link
+/// instead of attaching a new one. A link that is not resumed within the expiry set by
+/// [`parked_link_expiry`](crate::acceptor::builder::Builder::parked_link_expiry) is discarded
+/// the next time the registry is accessed.
+///
+/// The registry is shared by all clones obtained from
+/// [`ListenerSessionHandle::parked_links`](crate::acceptor::ListenerSessionHandle::parked_links),
+/// so links can be parked from the tasks that own them.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let parked_links = session.parked_links().clone();
+/// let link = link_acceptor.accept(&mut session).await.unwrap();
+/// if let LinkEndpoint::Receiver(mut receiver) = link {
+///     match receiver.recv::<Body<Value>>().await {
+///         Err(RecvError::LinkStateError(LinkStateError::RemoteDetached)) => {
+///             parked_links.park(receiver).await.unwrap();
+///         }
+///         _ => {}
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ParkedLinks {
+    expiry: Duration,
+    links: Arc<Mutex<HashMap<String, ParkedLink>>>,
+}
+
+impl ParkedLinks {
+    pub(crate) fn new(expiry: Duration) -> Self {
+        Self {
+            expiry,
+            links: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Duration a parked link is kept before it is discarded
+    pub fn expiry(&self) -> Duration {
+        self.expiry
+    }
+
+    /// Park a link so that it can be resumed by the remote peer.
+    ///
+    /// The link is detached with a non-closing Detach first, which also replies to the Detach
+    /// sent by the remote peer if the link has not replied yet. An error is returned and the link
+    /// is dropped if the link is closed instead.
+    pub async fn park(&self, link: impl Into<LinkEndpoint>) -> Result<(), DetachError> {
+        let detached = match link.into() {
+            LinkEndpoint::Sender(sender) => sender
+                .detach()
+                .await
+                .map(DetachedLinkEndpoint::Sender)
+                .map_err(|(_, err)| err)?,
+            LinkEndpoint::Receiver(receiver) => receiver
+                .detach()
+                .await
+                .map(DetachedLinkEndpoint::Receiver)
+                .map_err(|(_, err)| err)?,
+        };
+        let parked = ParkedLink {
+            parked_at: Instant::now(),
+            link: detached,
+        };
+
+        let mut links = self.links.lock();
+        self.discard_expired(&mut links);
+        links.insert(parked.link.name().to_string(), parked);
+        Ok(())
+    }
+
+    /// Number of links that are parked and not yet expired
+    pub fn len(&self) -> usize {
+        let mut links = self.links.lock();
+        self.discard_expired(&mut links);
+        links.len()
+    }
+
+    /// Whether there is no link that is parked and not yet expired
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Takes the parked link that can be resumed by the remote Attach
+    ///
+    /// A parked link with the same name whose role doesn't pair with the remote Attach is
+    /// discarded
+    pub(crate) fn take(&self, remote_attach: &Attach) -> Option<DetachedLinkEndpoint> {
+        let mut links = self.links.lock();
+        self.discard_expired(&mut links);
+        links
+            .remove(&remote_attach.name)
+            .map(|parked| parked.link)
+            .filter(|link| link.pairs_with(&remote_attach.role))
+    }
+
+    fn discard_expired(&self, links: &mut HashMap<String, ParkedLink>) {
+        links.retain(|_, parked| parked.parked_at.elapsed() < self.expiry);
+    }
+}
@@ -0,0 +1,581 @@
+//! Routes the links attached by remote senders to handlers by their target address
+//!
+//! A [`Router`] takes the incoming Attaches of a [`ListenerSessionHandle`], matches the target
+//! address of each remote sender against the [`AddressPattern`]s of its [`Route`]s and accepts
+//! the link with the [`LinkAcceptor`] of the first route that matches. Every delivery received on
+//! the link is passed to the async handler of the route, and the delivery is settled with the
+//! outcome of the handler: `Ok(())` accepts the delivery, and a [`HandlerError`] rejects, releases
+//! or modifies it.
+//!
+//! Attaches whose target address does not match any route, and Attaches of remote receivers, are
+//! refused with `amqp:not-found`: the link is attached with a null terminus and closed right away
+//! with the error.
+//!
+//! # Address patterns
+//!
+//! The segments of an address are separated by `/`.
+//!
+//! | Pattern | Matches |
+//! |---------|---------|
+//! | [`AddressPattern::Exact`] | the address only |
+//! | [`AddressPattern::Prefix`] | every address that starts with the prefix |
+//! | [`AddressPattern::Wildcard`] | `*` matches exactly one segment, `#` matches zero or more segments |
+//!
+//! A `&str` or `String` is converted into a wildcard pattern if any of its segments is `*` or `#`
+//! and into an exact pattern otherwise.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use fe2o3_amqp::{
+//!     acceptor::{router::{HandlerError, Route, Router}, LinkAcceptor},
+//!     link::delivery::Delivery,
+//! };
+//!
+//! let router = Router::new()
+//!     .route(
+//!         Route::new("orders/*/events", |delivery: Delivery<String>| async move {
+//!             match delivery.body().is_empty() {
+//!                 true => Err(HandlerError::Reject(None)),
+//!                 false => Ok(()),
+//!             }
+//!         })
+//!         .max_concurrency(8),
+//!     )
+//!     .route(Route::new("audit/#", |_: Delivery<String>| async { Ok(()) }));
+//!
+//! // Runs until the session ends or the shutdown signal resolves
+//! router.serve_with_shutdown(&mut session, shutdown_signal).await;
+//! ```
+
+use std::{future::Future, sync::Arc};
+
+use fe2o3_amqp_types::{
+    definitions::{self, AmqpError, Role},
+    messaging::{
+        Accepted, FromBody, Modified, Rejected, Released, Source, Target, TargetArchetype,
+    },
+    performatives::Attach,
+};
+use futures_util::{future::BoxFuture, stream::FuturesUnordered, FutureExt, StreamExt};
+use tokio::sync::{mpsc, watch, OwnedSemaphorePermit, Semaphore};
+
+use crate::link::{
+    delivery::{Delivery, DeliveryInfo},
+    receiver::{CreditMode, TerminalDeliveryState},
+    DispositionError, RecvError,
+};
+use crate::Receiver;
+
+use super::{error::AcceptorAttachError, LinkAcceptor, LinkEndpoint, ListenerSessionHandle};
+
+/// Separator of the segments of an address
+pub const SEGMENT_SEPARATOR: char = '/';
+
+/// Matches any single segment in an [`AddressPattern::Wildcard`]
+pub const SINGLE_SEGMENT_WILDCARD: &str = "*";
+
+/// Matches zero or more segments in an [`AddressPattern::Wildcard`]
+pub const MULTI_SEGMENT_WILDCARD: &str = "#";
+
+type DefaultLinkAcceptor = LinkAcceptor<fn(Source) -> Option<Source>, fn(Target) -> Option<Target>>;
+
+/// Serves the deliveries of an accepted link with the handler of a route
+type ServeLink = Arc<
+    dyn Fn(Receiver, Option<Arc<Semaphore>>, watch::Receiver<bool>) -> BoxFuture<'static, ()>
+        + Send
+        + Sync,
+>;
+
+/// A pattern that is matched against the target address of an incoming link
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AddressPattern {
+    /// Matches the address only
+    Exact(String),
+
+    /// Matches every address that starts with the prefix
+    Prefix(String),
+
+    /// Matches the segments of the address one by one. The segment `*` matches exactly one
+    /// segment and the segment `#` matches zero or more segments
+    Wildcard(String),
+}
+
+impl AddressPattern {
+    /// Creates a pattern that only matches `address`
+    pub fn exact(address: impl Into<String>) -> Self {
+        Self::Exact(address.into())
+    }
+
+    /// Creates a pattern that matches every address that starts with `prefix`
+    pub fn prefix(prefix: impl Into<String>) -> Self {
+        Self::Prefix(prefix.into())
+    }
+
+    /// Creates a pattern whose `*` and `#` segments are wildcards
+    pub fn wildcard(pattern: impl Into<String>) -> Self {
+        Self::Wildcard(pattern.into())
+    }
+
+    /// Whether the address matches the pattern
+    pub fn matches(&self, address: &str) -> bool {
+        match self {
+            AddressPattern::Exact(exact) => exact == address,
+            AddressPattern::Prefix(prefix) => address.starts_with(prefix.as_str()),
+            AddressPattern::Wildcard(pattern) => {
+                let pattern: Vec<&str> = pattern.split(SEGMENT_SEPARATOR).collect();
+                let address: Vec<&str> = address.split(SEGMENT_SEPARATOR).collect();
+                matches_segments(&pattern, &address)
+            }
+        }
+    }
+}
+
+fn matches_segments(pattern: &[&str], address: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => address.is_empty(),
+        Some((&MULTI_SEGMENT_WILDCARD, rest)) => {
+            (0..=address.len()).any(|skipped| matches_segments(rest, &address[skipped..]))
+        }
+        Some((segment, rest)) => match address.split_first() {
+            Some((first, remaining)) => {
+                (*segment == SINGLE_SEGMENT_WILDCARD || segment == first)
+                    && matches_segments(rest, remaining)
+            }
+            None => false,
+        },
+    }
+}
+
+impl From<String> for AddressPattern {
+    fn from(value: String) -> Self {
+        let is_wildcard = value
+            .split(SEGMENT_SEPARATOR)
+            .any(|segment| segment == SINGLE_SEGMENT_WILDCARD || segment == MULTI_SEGMENT_WILDCARD);
+        match is_wildcard {
+            true => Self::Wildcard(value),
+            false => Self::Exact(value),
+        }
+    }
+}
+
+impl From<&str> for AddressPattern {
+    fn from(value: &str) -> Self {
+        Self::from(value.to_string())
+    }
+}
+
+/// Error returned by the handler of a [`Route`], which determines how the delivery is settled
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum HandlerError {
+    /// The delivery is rejected with the optional error
+    #[error("Delivery is rejected with {:?}", .0)]
+    Reject(Option<definitions::Error>),
+
+    /// The delivery is released
+    #[error("Delivery is released")]
+    Release,
+
+    /// The delivery is modified
+    #[error("Delivery is modified with {:?}", .0)]
+    Modify(Modified),
+}
+
+impl From<definitions::Error> for HandlerError {
+    fn from(value: definitions::Error) -> Self {
+        Self::Reject(Some(value))
+    }
+}
+
+impl From<HandlerError> for TerminalDeliveryState {
+    fn from(value: HandlerError) -> Self {
+        match value {
+            HandlerError::Reject(error) => Self::Rejected(Rejected { error }),
+            HandlerError::Release => Self::Released(Released {}),
+            HandlerError::Modify(modified) => Self::Modified(modified),
+        }
+    }
+}
+
+/// An address pattern with the link acceptor and the handler of the links that match it
+///
+/// The deliveries of all links of the route are handled concurrently. A limit set with
+/// [`max_concurrency`](Route::max_concurrency) is shared by all links of the route, and a link
+/// waits for a handler of the route to finish before it takes its next delivery once the limit is
+/// reached.
+///
+/// The deliveries are settled by the router, so the link acceptor of a route should not
+/// [`auto_accept`](crate::acceptor::builder::Builder::auto_accept).
+#[derive(Clone)]
+pub struct Route {
+    pattern: AddressPattern,
+    link_acceptor: DefaultLinkAcceptor,
+    limit: Option<Arc<Semaphore>>,
+    serve_link: ServeLink,
+}
+
+impl std::fmt::Debug for Route {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Route")
+            .field("pattern", &self.pattern)
+            .field("link_acceptor", &self.link_acceptor)
+            .field("limit", &self.limit)
+            .finish()
+    }
+}
+
+impl Route {
+    /// Creates a route that hands the deliveries of the links matching `pattern` to `handler`
+    ///
+    /// The links are accepted with a default [`LinkAcceptor`] and the number of concurrent
+    /// handlers is not limited.
+    pub fn new<T, F, Fut>(pattern: impl Into<AddressPattern>, handler: F) -> Self
+    where
+        for<'de> T: FromBody<'de> + Send + 'static,
+        F: Fn(Delivery<T>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), HandlerError>> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        let serve_link: ServeLink = Arc::new(move |receiver, limit, stop| {
+            serve_link(receiver, handler.clone(), limit, stop).boxed()
+        });
+        Self {
+            pattern: pattern.into(),
+            link_acceptor: LinkAcceptor::new(),
+            limit: None,
+            serve_link,
+        }
+    }
+
+    /// The link acceptor that accepts the links of the route
+    pub fn link_acceptor(mut self, link_acceptor: DefaultLinkAcceptor) -> Self {
+        self.link_acceptor = link_acceptor;
+        self
+    }
+
+    /// The maximum number of deliveries of the route that are handled at the same time
+    pub fn max_concurrency(mut self, max: usize) -> Self {
+        self.limit = Some(Arc::new(Semaphore::new(max)));
+        self
+    }
+
+    /// The address pattern of the route
+    pub fn pattern(&self) -> &AddressPattern {
+        &self.pattern
+    }
+}
+
+/// Routes the links attached by remote senders to the handlers of its [`Route`]s
+///
+/// The routes are tried in the order they are added and the first route whose pattern matches
+/// the target address handles the link. See the [module](self) documentation for an example.
+#[derive(Debug, Clone)]
+pub struct Router {
+    routes: Vec<Route>,
+    refusal: DefaultLinkAcceptor,
+}
+
+impl Default for Router {
+    fn default() -> Self {
+        Self {
+            routes: Vec::new(),
+            refusal: LinkAcceptor::builder()
+                .credit_mode(CreditMode::Manual)
+                .build(),
+        }
+    }
+}
+
+impl Router {
+    /// Creates a router without routes, which refuses every link
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a route after the routes that are already added
+    pub fn route(mut self, route: Route) -> Self {
+        self.routes.push(route);
+        self
+    }
+
+    /// The routes in the order they are tried
+    pub fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+
+    /// Returns the first route whose pattern matches the address
+    pub fn find_route(&self, address: &str) -> Option<&Route> {
+        self.routes
+            .iter()
+            .find(|route| route.pattern.matches(address))
+    }
+
+    /// Routes the incoming links of the session until the session ends
+    ///
+    /// This returns once the session has ended and the handlers of every routed link have
+    /// finished.
+    pub async fn serve(&self, session: &mut ListenerSessionHandle) {
+        self.serve_with_shutdown(session, futures_util::future::pending())
+            .await
+    }
+
+    /// Routes the incoming links of the session until the session ends or `signal` resolves
+    ///
+    /// Once `signal` resolves, no more links are accepted and the routed links stop taking
+    /// deliveries. The handlers that are in flight finish and their deliveries are settled before
+    /// each link is closed. This returns once the remote peers have answered the Detach of every
+    /// routed link. The session is left open.
+    pub async fn serve_with_shutdown(
+        &self,
+        session: &mut ListenerSessionHandle,
+        signal: impl Future<Output = ()>,
+    ) {
+        let (stop_tx, stop_rx) = watch::channel(false);
+        // Every link task holds a sender, so the channel is closed once all of them are finished
+        let (running_tx, mut running_rx) = mpsc::channel::<()>(1);
+        let mut signal = std::pin::pin!(signal);
+
+        loop {
+            let remote_attach = tokio::select! {
+                _ = &mut signal => break,
+                remote_attach = session.next_incoming_attach() => match remote_attach {
+                    Some(remote_attach) => remote_attach,
+                    None => break,
+                },
+            };
+
+            let (route, receiver) = match self.accept_incoming_attach(remote_attach, session).await
+            {
+                Ok(Some(routed)) => routed,
+                Ok(None) => continue,
+                Err(AcceptorAttachError::IllegalSessionState) => break,
+                Err(_error) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::error!(error = ?_error, "Failed to accept routed link");
+                    #[cfg(feature = "log")]
+                    log::error!("Failed to accept routed link: {:?}", _error);
+                    continue;
+                }
+            };
+
+            let serve = (route.serve_link)(receiver, route.limit.clone(), stop_rx.clone());
+            let running = running_tx.clone();
+            crate::rt::spawn(async move {
+                serve.await;
+                drop(running);
+            });
+        }
+
+        stop_tx.send_replace(true);
+        drop(running_tx);
+        let _ = running_rx.recv().await;
+    }
+
+    /// Accepts the link with the acceptor of its route, or refuses it if there is no route
+    async fn accept_incoming_attach(
+        &self,
+        mut remote_attach: Attach,
+        session: &mut ListenerSessionHandle,
+    ) -> Result<Option<(&Route, Receiver)>, AcceptorAttachError> {
+        let address = match (&remote_attach.role, remote_attach.target.as_deref()) {
+            (Role::Sender, Some(TargetArchetype::Target(target))) => target.address.clone(),
+            _ => None,
+        };
+        let route = address
+            .as_deref()
+            .and_then(|address| self.find_route(address));
+        if let Some(route) = route {
+            return match route
+                .link_acceptor
+                .accept_incoming_attach(remote_attach, session)
+                .await?
+            {
+                LinkEndpoint::Receiver(receiver) => Ok(Some((route, receiver))),
+                // Only the links of remote senders are routed
+                LinkEndpoint::Sender(_) => Ok(None),
+            };
+        }
+
+        // The local terminus of a refused link is null
+        let description = match remote_attach.role {
+            Role::Sender => {
+                remote_attach.target = None;
+                format!("No route for the target address {:?}", address)
+            }
+            Role::Receiver => {
+                remote_attach.source = None;
+                String::from("Only the links of remote senders are routed")
+            }
+        };
+        #[cfg(feature = "tracing")]
+        tracing::debug!(name = %remote_attach.name, description, "Refusing link");
+        #[cfg(feature = "log")]
+        log::debug!("Refusing link {}: {}", remote_attach.name, description);
+
+        let error = definitions::Error::new(AmqpError::NotFound, description, None);
+        let _ = match self
+            .refusal
+            .accept_incoming_attach(remote_attach, session)
+            .await?
+        {
+            LinkEndpoint::Sender(sender) => sender.close_with_error(error).await,
+            LinkEndpoint::Receiver(receiver) => receiver.close_with_error(error).await,
+        };
+        Ok(None)
+    }
+}
+
+/// Hands the deliveries of the link to the handler until the link is detached or the router is
+/// shut down, and closes the link once the handlers in flight are finished
+async fn serve_link<T, F, Fut>(
+    mut receiver: Receiver,
+    handler: Arc<F>,
+    limit: Option<Arc<Semaphore>>,
+    mut stop: watch::Receiver<bool>,
+) where
+    for<'de> T: FromBody<'de> + Send + 'static,
+    F: Fn(Delivery<T>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), HandlerError>> + Send + 'static,
+{
+    let mut in_flight = FuturesUnordered::new();
+
+    loop {
+        tokio::select! {
+            // The router is shut down, or dropped
+            _ = stop.wait_for(|stop| *stop).map(|_| ()) => break,
+            Some((info, result)) = in_flight.next() => {
+                if settle(&receiver, info, result).await.is_err() {
+                    break;
+                }
+            }
+            next = next_delivery::<T>(&mut receiver, limit.clone()) => {
+                let (delivery, permit) = match next {
+                    Ok(next) => next,
+                    Err(RecvError::LinkStateError(_)) => break,
+                    Err(_error) => {
+                        #[cfg(feature = "tracing")]
+                        tracing::error!(error = ?_error, "Failed to receive routed delivery");
+                        #[cfg(feature = "log")]
+                        log::error!("Failed to receive routed delivery: {:?}", _error);
+                        continue;
+                    }
+                };
+                let info = delivery.info();
+                let handler = handler.clone();
+                in_flight.push(async move {
+                    let result = handler(delivery).await;
+                    drop(permit);
+                    (info, result)
+                });
+            }
+        }
+    }
+
+    while let Some((info, result)) = in_flight.next().await {
+        let _ = settle(&receiver, info, result).await;
+    }
+    let _ = receiver.close().await;
+}
+
+/// Waits for the concurrency limit of the route before taking the next delivery
+async fn next_delivery<T>(
+    receiver: &mut Receiver,
+    limit: Option<Arc<Semaphore>>,
+) -> Result<(Delivery<T>, Option<OwnedSemaphorePermit>), RecvError>
+where
+    for<'de> T: FromBody<'de> + Send,
+{
+    let permit = match limit {
+        Some(limit) => limit.acquire_owned().await.ok(),
+        None => None,
+    };
+    receiver.recv().await.map(|delivery| (delivery, permit))
+}
+
+async fn settle(
+    receiver: &Receiver,
+    info: DeliveryInfo,
+    result: Result<(), HandlerError>,
+) -> Result<(), DispositionError> {
+    if info.is_settled() {
+        return Ok(());
+    }
+    let state = match result {
+        Ok(()) => TerminalDeliveryState::Accepted(Accepted {}),
+        Err(error) => error.into(),
+    };
+    receiver.dispose(info, state).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AddressPattern;
+
+    #[test]
+    fn exact_and_prefix_patterns() {
+        let exact = AddressPattern::exact("orders");
+        assert!(exact.matches("orders"));
+        assert!(!exact.matches("orders/1"));
+        assert!(!exact.matches("order"));
+
+        let prefix = AddressPattern::prefix("orders/");
+        assert!(prefix.matches("orders/"));
+        assert!(prefix.matches("orders/1/events"));
+        assert!(!prefix.matches("orders"));
+
+        // Wildcard characters only have a meaning in wildcard patterns
+        assert!(AddressPattern::exact("orders/*").matches("orders/*"));
+        assert!(!AddressPattern::exact("orders/*").matches("orders/1"));
+    }
+
+    #[test]
+    fn wildcard_patterns() {
+        let single = AddressPattern::wildcard("orders/*/events");
+        assert!(single.matches("orders/1/events"));
+        assert!(single.matches("orders//events"));
+        assert!(!single.matches("orders/events"));
+        assert!(!single.matches("orders/1/2/events"));
+        assert!(!single.matches("orders/1/events/created"));
+
+        let multi = AddressPattern::wildcard("orders/#");
+        assert!(multi.matches("orders"));
+        assert!(multi.matches("orders/1"));
+        assert!(multi.matches("orders/1/events/created"));
+        assert!(!multi.matches("invoices/1"));
+
+        let inner = AddressPattern::wildcard("orders/#/created");
+        assert!(inner.matches("orders/created"));
+        assert!(inner.matches("orders/1/events/created"));
+        assert!(!inner.matches("orders/1/events/deleted"));
+
+        let mixed = AddressPattern::wildcard("*/#/events/*");
+        assert!(mixed.matches("orders/events/1"));
+        assert!(mixed.matches("orders/1/2/events/3"));
+        assert!(!mixed.matches("events/1"));
+
+        assert!(AddressPattern::wildcard("#").matches(""));
+        assert!(AddressPattern::wildcard("#").matches("a/b/c"));
+        // Only whole segments are wildcards
+        assert!(!AddressPattern::wildcard("orders/1*").matches("orders/12"));
+    }
+
+    #[test]
+    fn patterns_from_strings() {
+        assert_eq!(
+            AddressPattern::from("orders/*/events"),
+            AddressPattern::Wildcard("orders/*/events".into())
+        );
+        assert_eq!(
+            AddressPattern::from("orders/#"),
+            AddressPattern::Wildcard("orders/#".into())
+        );
+        assert_eq!(
+            AddressPattern::from("orders/1*"),
+            AddressPattern::Exact("orders/1*".into())
+        );
+        assert_eq!(
+            AddressPattern::from(String::from("q1")),
+            AddressPattern::Exact("q1".into())
+        );
+    }
+}
@@ -64,12 +64,21 @@ impl<T: SaslAcceptor> SaslAcceptorExt for T {}
 // }
 
 /// A naive acceptor for SASL PLAIN mechanism
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct SaslPlainMechanism {
     username: Arc<String>,
     password: Arc<String>,
 }
 
+impl std::fmt::Debug for SaslPlainMechanism {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SaslPlainMechanism")
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
 impl SaslPlainMechanism {
     /// Creates a new PLAIN mechanism acceptor
     pub fn new(username: impl Into<String>, password: impl Into<String>) -> Self {
@@ -1,5 +1,6 @@
 //! Session Listener
 
+use std::sync::Arc;
 
 use fe2o3_amqp_types::{
     definitions::{self, ConnectionError},
@@ -7,27 +8,29 @@ use fe2o3_amqp_types::{
     states::SessionState,
 };
 use tokio::sync::{mpsc, oneshot};
-use tokio::task::JoinHandle;
 
 use crate::{
-    connection::AllocSessionError,
+    connection::{AllocSessionError, SessionRelay},
     control::{ConnectionControl, SessionControl},
     endpoint::{
         self, IncomingChannel, InputHandle, LinkFlow, OutgoingChannel, OutputHandle, Session,
     },
+    introspect::{LinkSummary, RoutingDecision, RoutingRecord},
     link::{LinkFrame, LinkRelay},
+    rt::JoinHandle,
     session::{
         self,
         engine::SessionEngine,
         frame::{SessionFrame, SessionIncomingItem, SessionOutgoingItem},
-        error::{AllocLinkError, BeginError, Error, SessionInnerError}, SessionHandle, 
+        incoming_budget::{IncomingBudget, IncomingPermit},
+        error::{BeginError, Error, SessionInnerError}, SessionHandle, 
         DEFAULT_SESSION_CONTROL_BUFFER_SIZE,
     },
     util::Initialized,
     Payload,
 };
 
-use super::{builder::Builder, IncomingSession, ListenerConnectionHandle};
+use super::{builder::Builder, IncomingSession, ListenerConnectionHandle, ParkedLinks};
 
 cfg_transaction! {
     use fe2o3_amqp_types::{messaging::Accepted, transaction::TransactionError};
@@ -47,43 +50,47 @@ cfg_transaction! {
 
 type SessionBuilder = crate::session::Builder;
 
+/// Computes the configuration of an incoming session from the Begin sent by the remote peer.
+///
+/// The session is ended with the returned error if the session is refused
+pub type OnBeginFn =
+    Arc<dyn Fn(&Begin, SessionBuilder) -> Result<SessionBuilder, definitions::Error> + Send + Sync>;
+
+/// Wrapper of [`OnBeginFn`] that allows deriving `Debug` and `Clone` on the session builder
+#[derive(Clone)]
+pub(crate) struct OnBegin(pub(crate) OnBeginFn);
+
+impl std::fmt::Debug for OnBegin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Fn")
+    }
+}
+
 /// Type alias for listener session handle
-pub type ListenerSessionHandle = SessionHandle<mpsc::Receiver<Attach>>;
+///
+/// The session can be ended with an error using
+/// [`end_with_error`](SessionHandle::end_with_error), and an error carried by the remote End is
+/// returned by [`on_end`](SessionHandle::on_end) as [`Error::RemoteEndedWithError`]. Links on an
+/// ended session fail with [`LinkStateError::SessionEnded`](crate::link::LinkStateError::SessionEnded).
+pub type ListenerSessionHandle = SessionHandle<IncomingLinks>;
+
+/// Incoming Attaches and parked links of a listener session
+#[derive(Debug)]
+pub struct IncomingLinks {
+    attaches: mpsc::Receiver<Attach>,
+    parked: ParkedLinks,
+}
 
 impl ListenerSessionHandle {
     /// Waits for the next incoming link
     pub async fn next_incoming_attach(&mut self) -> Option<Attach> {
-        self.link_listener.recv().await
+        self.link_listener.attaches.recv().await
     }
-}
 
-pub(crate) async fn allocate_incoming_link(
-    control: &mpsc::Sender<SessionControl>,
-    link_name: String,
-    link_relay: LinkRelay<()>,
-    input_handle: InputHandle,
-) -> Result<OutputHandle, AllocLinkError> {
-    let (responder, resp_rx) = oneshot::channel();
-
-    control
-        .send(SessionControl::AllocateIncomingLink {
-            link_name,
-            link_relay,
-            input_handle,
-            responder,
-        })
-        .await
-        // The `SendError` could only happen when the receiving half is
-        // dropped, meaning the `SessionEngine::event_loop` has stopped.
-        // This would also mean the `Session` is Unmapped, and thus it
-        // may be treated as illegal state
-        .map_err(|_| AllocLinkError::IllegalSessionState)?;
-    resp_rx
-        .await
-        // The error could only occur when the sending half is dropped,
-        // indicating the `SessionEngine::even_loop` has stopped or
-        // unmapped. Thus it could be considered as illegal state
-        .map_err(|_| AllocLinkError::IllegalSessionState)?
+    /// Links of the session that are detached by the remote peer and may be resumed
+    pub fn parked_links(&self) -> &ParkedLinks {
+        &self.link_listener.parked
+    }
 }
 
 /// An acceptor for incoming session
@@ -127,6 +134,20 @@ pub(crate) async fn allocate_incoming_link(
 ///     .handle_max(16)
 ///     .build();
 /// ```
+///
+/// # Customize each incoming session
+///
+/// The configuration can be computed from the Begin sent by the remote peer with the
+/// [`on_begin`](Builder::on_begin) hook. Returning an error refuses the session.
+///
+/// ```rust
+/// use fe2o3_amqp::acceptor::SessionAcceptor;
+/// use fe2o3_amqp::types::performatives::Begin;
+///
+/// let session_acceptor = SessionAcceptor::builder()
+///     .on_begin(|remote: &Begin, local| Ok(local.incoming_window(remote.incoming_window.min(64))))
+///     .build();
+/// ```
 #[derive(Debug)]
 pub struct SessionAcceptor(pub SessionBuilder);
 
@@ -156,11 +177,12 @@ impl SessionAcceptor {
             connection: &crate::connection::ConnectionHandle<R>,
             _session_control_tx: &mpsc::Sender<SessionControl>,
             session_control_rx: mpsc::Receiver<SessionControl>,
-            incoming: mpsc::Receiver<SessionFrame>,
+            incoming: mpsc::Receiver<SessionIncomingItem>,
             outgoing_link_frames: mpsc::Receiver<LinkFrame>,
-        ) -> Result<(JoinHandle<()>, oneshot::Receiver<Result<(), Error>>), BeginError> {
+        ) -> Result<(Option<JoinHandle<()>>, oneshot::Receiver<Result<(), Error>>), BeginError> {
             let engine = SessionEngine::begin_listener_session(
                 connection.control.clone(),
+                connection.container_id.clone(),
                 listener_session,
                 session_control_rx,
                 incoming,
@@ -168,7 +190,7 @@ impl SessionAcceptor {
                 outgoing_link_frames,
             )
             .await?;
-            Ok(engine.spawn())
+            Ok(engine.spawn(&connection.spawner))
         }
     }
 
@@ -181,9 +203,9 @@ impl SessionAcceptor {
             connection: &crate::connection::ConnectionHandle<R>,
             session_control_tx: &mpsc::Sender<SessionControl>,
             session_control_rx: mpsc::Receiver<SessionControl>,
-            incoming: mpsc::Receiver<SessionFrame>,
+            incoming: mpsc::Receiver<SessionIncomingItem>,
             outgoing_link_frames: mpsc::Receiver<LinkFrame>,
-        ) -> Result<(JoinHandle<()>, oneshot::Receiver<Result<(), Error>>), BeginError> {
+        ) -> Result<(Option<JoinHandle<()>>, oneshot::Receiver<Result<(), Error>>), BeginError> {
             match self.0.control_link_acceptor.clone() {
                 Some(control_link_acceptor) => {
                     let txn_manager =
@@ -196,6 +218,7 @@ impl SessionAcceptor {
     
                     let engine = SessionEngine::begin_listener_session(
                         connection.control.clone(),
+                        connection.container_id.clone(),
                         listener_session,
                         session_control_rx,
                         incoming,
@@ -203,11 +226,12 @@ impl SessionAcceptor {
                         outgoing_link_frames,
                     )
                     .await?;
-                    Ok(engine.spawn())
+                    Ok(engine.spawn(&connection.spawner))
                 }
                 None => {
                     let engine = SessionEngine::begin_listener_session(
                         connection.control.clone(),
+                        connection.container_id.clone(),
                         listener_session,
                         session_control_rx,
                         incoming,
@@ -215,27 +239,45 @@ impl SessionAcceptor {
                         outgoing_link_frames,
                     )
                     .await?;
-                    Ok(engine.spawn())
+                    Ok(engine.spawn(&connection.spawner))
                 }
             }
         }
     }
 
     /// Accept an incoming session
+    ///
+    /// If the session is refused by the [`on_begin`](Builder::on_begin) hook, the session is
+    /// begun and then ended with the error, and `BeginError::Rejected` is returned
     pub async fn accept_incoming_session(
         &self,
         incoming_session: IncomingSession,
         connection: &mut ListenerConnectionHandle,
     ) -> Result<ListenerSessionHandle, BeginError> {
+        let (session_builder, rejection) = match &self.0.on_begin {
+            Some(on_begin) => match (on_begin.0)(&incoming_session.begin, self.0.clone()) {
+                Ok(session_builder) => (session_builder, None),
+                Err(error) => (self.0.clone(), Some(error)),
+            },
+            None => (self.0.clone(), None),
+        };
+
         let local_state = SessionState::Unmapped;
         let (session_control_tx, session_control_rx) =
             mpsc::channel::<SessionControl>(DEFAULT_SESSION_CONTROL_BUFFER_SIZE);
-        let (incoming_tx, incoming_rx) = mpsc::channel(self.0.buffer_size);
-        let (outgoing_tx, outgoing_rx) = mpsc::channel(self.0.buffer_size);
-        let (link_listener_tx, link_listener_rx) = mpsc::channel(self.0.buffer_size);
+        let (incoming_tx, incoming_rx) = mpsc::channel(session_builder.buffer_size);
+        let (outgoing_tx, outgoing_rx) = mpsc::channel(session_builder.buffer_size);
+        let (link_listener_tx, link_listener_rx) = mpsc::channel(session_builder.buffer_size);
+
+        let incoming_budget = IncomingBudget::new(session_builder.incoming_buffer_limit);
+        let parked_links = ParkedLinks::new(session_builder.parked_link_expiry);
 
         // create session in connection::Engine
-        let outgoing_channel = match connection.allocate_session(incoming_tx).await {
+        let relay = SessionRelay {
+            tx: incoming_tx,
+            incoming_budget: incoming_budget.clone(),
+        };
+        let outgoing_channel = match connection.allocate_session(relay).await {
             Ok(channel) => channel,
             Err(error) => match error {
                 AllocSessionError::IllegalState => return Err(BeginError::IllegalConnectionState),
@@ -255,9 +297,13 @@ impl SessionAcceptor {
 
                     return Err(BeginError::LocalChannelMaxReached);
                 }
+                AllocSessionError::MaxSessionsReached => {
+                    return Err(BeginError::LocalMaxSessionsReached)
+                }
             },
         };
-        let mut session = self.0.clone().into_session(outgoing_channel, local_state);
+        let mut session =
+            session_builder.into_session(outgoing_channel, local_state, connection.max_frame_size);
         session.on_incoming_begin(
             IncomingChannel(incoming_session.channel),
             incoming_session.begin,
@@ -280,19 +326,34 @@ impl SessionAcceptor {
             )
             .await?;
 
-        let handle = SessionHandle {
+        let mut handle = SessionHandle {
             is_ended: false,
             control: session_control_tx,
             engine_handle,
             outcome,
             outgoing: outgoing_tx,
-            link_listener: link_listener_rx,
+            link_listener: IncomingLinks {
+                attaches: link_listener_rx,
+                parked: parked_links,
+            },
+            incoming_budget,
+            container_id: connection.container_id.clone(),
+            #[cfg(feature = "testing")]
+            raw_incoming: None,
         };
-        Ok(handle)
+
+        match rejection {
+            Some(error) => {
+                // The session must be begun before it can be ended with an error
+                let _ = handle.end_with_error(error.clone()).await;
+                Err(BeginError::Rejected(error))
+            }
+            None => Ok(handle),
+        }
     }
 
     /// Waits for incoming session'e Begin performative and then accepts an incoming session
-    #[cfg_attr(feature = "tracing", tracing::instrument)]
+    #[cfg_attr(feature = "tracing", tracing::instrument(fields(container_id = %connection.container_id)))]
     pub async fn accept(
         &self,
         connection: &mut ListenerConnectionHandle,
@@ -313,6 +374,7 @@ where
 {
     pub async fn begin_listener_session(
         conn_control: mpsc::Sender<ConnectionControl>,
+        container_id: String,
         session: S,
         control: mpsc::Receiver<SessionControl>,
         incoming: mpsc::Receiver<SessionIncomingItem>,
@@ -325,11 +387,14 @@ where
         log::trace!("Instantiating session engine");
         let mut engine = Self {
             conn_control,
+            container_id,
             session,
             control,
             incoming,
             outgoing,
             outgoing_link_frames,
+            #[cfg(feature = "testing")]
+            raw_incoming: None,
         };
 
         // send a begin
@@ -369,6 +434,14 @@ impl endpoint::Session for ListenerSession {
         self.session.allocate_link(link_name, link_handle)
     }
 
+    fn reallocate_link(
+        &mut self,
+        link_name: String,
+        link_handle: LinkRelay<()>,
+    ) -> Result<OutputHandle, Self::AllocError> {
+        self.session.reallocate_link(link_name, link_handle)
+    }
+
     fn allocate_incoming_link(
         &mut self,
         link_name: String,
@@ -383,6 +456,26 @@ impl endpoint::Session for ListenerSession {
         self.session.deallocate_link(output_handle)
     }
 
+    fn release_link_name(&mut self, link_name: &str) {
+        self.session.release_link_name(link_name)
+    }
+
+    fn notify_links_ended(&mut self) {
+        self.session.notify_links_ended()
+    }
+
+    fn link_summaries(&self) -> Vec<LinkSummary> {
+        self.session.link_summaries()
+    }
+
+    fn incomplete_incoming_bytes(&self) -> usize {
+        self.session.incomplete_incoming_bytes()
+    }
+
+    fn routing_history(&self) -> Vec<RoutingRecord> {
+        self.session.routing_history()
+    }
+
     fn on_incoming_begin(
         &mut self,
         channel: IncomingChannel,
@@ -392,6 +485,12 @@ impl endpoint::Session for ListenerSession {
     }
 
     async fn on_incoming_attach(&mut self, attach: Attach) -> Result<(), Self::Error> {
+        self.session.check_incoming_attach_handle(&attach)?;
+        if self.session.on_incoming_abandoned_attach(&attach) {
+            return Ok(());
+        }
+
+        let handle = attach.handle.0;
         match self.session.link_by_name.get_mut(&attach.name) {
             Some(link) => match link.take() {
                 Some(mut relay) => {
@@ -407,6 +506,10 @@ impl endpoint::Session for ListenerSession {
                     }
 
                     let input_handle = attach.handle.clone().into(); // handle is just a wrapper around u32
+                    let decision = RoutingDecision::Routed {
+                        output_handle: relay.output_handle().0,
+                    };
+                    self.session.record_routing("attach", handle, decision);
                     relay
                         .send(LinkFrame::Attach(attach))
                         .await
@@ -417,6 +520,8 @@ impl endpoint::Session for ListenerSession {
                     Ok(())
                 }
                 None => {
+                    let decision = RoutingDecision::Forwarded;
+                    self.session.record_routing("attach", handle, decision);
                     self.link_listener.send(attach).await.map_err(|_| {
                         // SessionHandle must have been dropped, then treat it as if the acceptor doesn't exist
                         SessionInnerError::HandleInUse
@@ -430,6 +535,8 @@ impl endpoint::Session for ListenerSession {
                 // to an unused handle, and an attach frame is issued carrying
                 // the state of the newly created endpoint.
 
+                let decision = RoutingDecision::Forwarded;
+                self.session.record_routing("attach", handle, decision);
                 self.link_listener.send(attach).await.map_err(|_| {
                     // SessionHandle must have been dropped, then treat it as if the acceptor doesn't exist
                     SessionInnerError::UnattachedHandle
@@ -449,8 +556,11 @@ impl endpoint::Session for ListenerSession {
         &mut self,
         transfer: Transfer,
         payload: Payload,
+        permit: Option<IncomingPermit>,
     ) -> Result<Option<Disposition>, Self::Error> {
-        self.session.on_incoming_transfer(transfer, payload).await
+        self.session
+            .on_incoming_transfer(transfer, payload, permit)
+            .await
     }
 
     fn on_incoming_disposition(
@@ -472,6 +582,10 @@ impl endpoint::Session for ListenerSession {
         self.session.on_incoming_end(channel, end)
     }
 
+    async fn relay_overflow(&mut self) {
+        self.session.relay_overflow().await
+    }
+
     // Handling SessionFrames
     async fn send_begin(
         &mut self,
@@ -497,6 +611,10 @@ impl endpoint::Session for ListenerSession {
         self.session.on_outgoing_flow(flow)
     }
 
+    fn replenish_incoming_window(&mut self) -> Option<SessionFrame> {
+        self.session.replenish_incoming_window()
+    }
+
     fn on_outgoing_transfer(
         &mut self,
         input_handle: InputHandle,
@@ -15,7 +15,7 @@ enum ScramClientState {
     Complete,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub(crate) struct ScramClient {
     username: String,
     password: String,
@@ -23,6 +23,17 @@ pub(crate) struct ScramClient {
     state: ScramClientState,
 }
 
+impl std::fmt::Debug for ScramClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScramClient")
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .field("scram", &self.scram)
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
 impl ScramClient {
     pub fn new(
         username: impl Into<String>,
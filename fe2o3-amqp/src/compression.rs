@@ -0,0 +1,271 @@
+//! Transparent compression of the `Data` body section
+//!
+//! A sender built with [`compress_body`](crate::link::builder::Builder::compress_body) compresses
+//! the `Data` body section of the outgoing messages and sets the `content-encoding` of the message
+//! properties accordingly. The body of a received message is inflated with
+//! [`Delivery::decompressed_data`](crate::link::delivery::Delivery::decompressed_data).
+//!
+//! Only a body that consists of a single `Data` section is compressed. `AmqpValue` and
+//! `AmqpSequence` bodies are always sent as is.
+
+use std::{
+    borrow::Cow,
+    io::{self, Read, Write},
+};
+
+use bytes::{BufMut, BytesMut};
+use fe2o3_amqp_types::{
+    messaging::{message::__private::Serializable, Data, Message, SerializableBody},
+    primitives::{Binary, Symbol},
+};
+use flate2::{
+    read::{GzDecoder, ZlibDecoder},
+    write::{GzEncoder, ZlibEncoder},
+};
+use serde::Serialize;
+use serde_amqp::ser::Serializer;
+
+use crate::Payload;
+
+/// `content-encoding` of a gzip compressed body
+pub const GZIP: &str = "gzip";
+
+/// `content-encoding` of a deflate compressed body
+pub const DEFLATE: &str = "deflate";
+
+/// Compression algorithm applied to the `Data` body section
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// gzip (RFC 1952)
+    Gzip {
+        /// Compression level from 0 (no compression) to 9 (best compression)
+        level: u32,
+    },
+
+    /// deflate in the zlib format (RFC 1950), which is what the `deflate` content-coding of
+    /// HTTP refers to
+    Deflate {
+        /// Compression level from 0 (no compression) to 9 (best compression)
+        level: u32,
+    },
+}
+
+impl Compression {
+    /// The `content-encoding` of a message compressed with this algorithm
+    pub fn content_encoding(&self) -> &'static str {
+        match self {
+            Compression::Gzip { .. } => GZIP,
+            Compression::Deflate { .. } => DEFLATE,
+        }
+    }
+
+    fn compress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::Gzip { level } => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::new(*level));
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            Compression::Deflate { level } => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::new(*level));
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+        }
+    }
+}
+
+/// Compression of the `Data` body section of the messages sent by a sender
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BodyCompression {
+    /// The compression algorithm
+    pub compression: Compression,
+
+    /// Bodies with fewer bytes are sent uncompressed
+    pub min_size: usize,
+}
+
+impl BodyCompression {
+    /// Creates a new [`BodyCompression`]
+    pub fn new(compression: Compression, min_size: usize) -> Self {
+        Self {
+            compression,
+            min_size,
+        }
+    }
+
+    /// Encodes the message with its body compressed. `None` is returned if the message must be
+    /// sent as is, ie. if the body is not a single `Data` section, is smaller than `min_size`, or
+    /// if the message already has a `content-encoding`
+    pub(crate) fn encode_message<T>(
+        &self,
+        message: &Message<T>,
+    ) -> Result<Option<Payload>, serde_amqp::Error>
+    where
+        T: SerializableBody,
+    {
+        if content_encoding(message).is_some() {
+            return Ok(None);
+        }
+
+        // The body type is only known once encoded
+        let body = serde_amqp::to_vec(&message.body)?;
+        let data: Data = match serde_amqp::from_slice(&body) {
+            Ok(data) => data,
+            Err(_) => return Ok(None),
+        };
+        // A batch of more than one Data section is decoded as its first section
+        if serde_amqp::serialized_size(&data)? != body.len() || data.0.len() < self.min_size {
+            return Ok(None);
+        }
+
+        let compressed = self
+            .compression
+            .compress(&data.0)
+            .map_err(serde_amqp::Error::Io)?;
+        let mut properties = message.properties.clone().unwrap_or_default();
+        properties.content_encoding = Some(Symbol::from(self.compression.content_encoding()));
+        let message = Message {
+            header: message.header.clone(),
+            delivery_annotations: message.delivery_annotations.clone(),
+            message_annotations: message.message_annotations.clone(),
+            properties: Some(properties),
+            application_properties: message.application_properties.clone(),
+            body: Data(Binary::from(compressed)),
+            footer: message.footer.clone(),
+        };
+
+        let mut payload = BytesMut::new();
+        let mut serializer = Serializer::from((&mut payload).writer());
+        Serializable(message).serialize(&mut serializer)?;
+        Ok(Some(payload.freeze()))
+    }
+}
+
+/// Error with inflating the body of a message
+#[derive(Debug, thiserror::Error)]
+pub enum DecompressError {
+    /// The body is not made of `Data` sections
+    #[error("The body is not made of Data sections")]
+    NotData,
+
+    /// The `content-encoding` is not supported
+    #[error("Unsupported content-encoding {}", .0.as_str())]
+    UnsupportedEncoding(Symbol),
+
+    /// The body cannot be inflated
+    #[error("Failed to inflate body: {0}")]
+    Io(#[from] io::Error),
+}
+
+pub(crate) fn content_encoding<T>(message: &Message<T>) -> Option<&Symbol> {
+    message
+        .properties
+        .as_ref()
+        .and_then(|properties| properties.content_encoding.as_ref())
+}
+
+/// Inflates `data` according to the `content-encoding` of the message. The bytes are returned
+/// as is if there is no `content-encoding`
+pub(crate) fn decompress<'a>(
+    content_encoding: Option<&Symbol>,
+    data: Cow<'a, [u8]>,
+) -> Result<Cow<'a, [u8]>, DecompressError> {
+    let encoding = match content_encoding {
+        Some(encoding) => encoding,
+        None => return Ok(data),
+    };
+
+    let mut inflated = Vec::new();
+    match encoding.as_str() {
+        "identity" => return Ok(data),
+        GZIP | "x-gzip" => GzDecoder::new(&data[..]).read_to_end(&mut inflated)?,
+        DEFLATE => ZlibDecoder::new(&data[..]).read_to_end(&mut inflated)?,
+        _ => return Err(DecompressError::UnsupportedEncoding(encoding.clone())),
+    };
+    Ok(Cow::Owned(inflated))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use fe2o3_amqp_types::{
+        messaging::{message::DecodeIntoMessage, AmqpValue, Body, Message, Properties},
+        primitives::{Binary, Symbol, Value},
+    };
+
+    use super::{decompress, BodyCompression, Compression, DEFLATE, GZIP};
+
+    fn decode(payload: &[u8]) -> Message<Body<Value>> {
+        Body::<Value>::decode_into_message(payload).unwrap()
+    }
+
+    #[test]
+    fn data_body_round_trip() {
+        let json = br#"{"key":"value","numbers":[1,2,3,4,5,6,7,8,9,10]}"#.repeat(32);
+        let compressions = [
+            (Compression::Gzip { level: 6 }, GZIP),
+            (Compression::Deflate { level: 6 }, DEFLATE),
+        ];
+        for (compression, content_encoding) in compressions {
+            let message = Message::builder().data(json.clone()).build();
+            let payload = BodyCompression::new(compression, 64)
+                .encode_message(&message)
+                .unwrap()
+                .unwrap();
+
+            let decoded = decode(&payload);
+            let encoding = decoded.properties.unwrap().content_encoding.unwrap();
+            assert_eq!(encoding, Symbol::from(content_encoding));
+            let compressed = match decoded.body {
+                Body::Data(batch) => batch[0].0.to_vec(),
+                body => panic!("Expecting Data, found {:?}", body),
+            };
+            assert!(compressed.len() < json.len());
+
+            let inflated = decompress(Some(&encoding), Cow::Borrowed(&compressed)).unwrap();
+            assert_eq!(inflated, &json[..]);
+        }
+    }
+
+    #[test]
+    fn small_data_body_is_not_compressed() {
+        let message = Message::builder().data(b"small".to_vec()).build();
+        let compression = BodyCompression::new(Compression::Gzip { level: 6 }, 64);
+        assert!(compression.encode_message(&message).unwrap().is_none());
+    }
+
+    #[test]
+    fn value_body_and_encoded_body_are_not_compressed() {
+        let compression = BodyCompression::new(Compression::Gzip { level: 6 }, 0);
+
+        let message = Message::builder()
+            .value("a large string value".repeat(32))
+            .build();
+        assert!(compression.encode_message(&message).unwrap().is_none());
+
+        let message = Message::builder()
+            .body(AmqpValue(Binary::from(b"binary".to_vec())))
+            .build();
+        assert!(compression.encode_message(&message).unwrap().is_none());
+
+        // The body is already encoded by the application
+        let properties = Properties::builder().content_encoding(GZIP).build();
+        let message = Message::builder()
+            .properties(properties)
+            .data(b"compressed".to_vec())
+            .build();
+        assert!(compression.encode_message(&message).unwrap().is_none());
+    }
+
+    #[test]
+    fn body_without_content_encoding_is_borrowed() {
+        let data = b"plain".to_vec();
+        let inflated = decompress(None, Cow::Borrowed(&data)).unwrap();
+        assert!(matches!(inflated, Cow::Borrowed(_)));
+
+        let result = decompress(Some(&Symbol::from("br")), Cow::Borrowed(&data));
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,251 @@
+//! Settings of the connection builder
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    connection::{mode, Builder},
+    sasl_profile::SaslProfile,
+};
+
+use super::{resolve_env, to_fields, to_symbols, ConfigError};
+
+/// Settings of [`connection::Builder`](crate::connection::Builder)
+///
+/// A field that is not set keeps the default value of the builder.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConnectionConfig {
+    /// The url that the connection is opened at, eg. `amqp://localhost:5672`
+    pub url: String,
+
+    /// The id of the source container. An id of the form `fe2o3-<uuid>` is generated if this is
+    /// not set
+    pub container_id: Option<String>,
+
+    /// The name of the target host that is sent in the Open frame
+    pub hostname: Option<String>,
+
+    /// Proposed maximum frame size in bytes
+    pub max_frame_size: Option<u32>,
+
+    /// The maximum channel number that can be used on the connection
+    pub channel_max: Option<u16>,
+
+    /// Idle time-out in milliseconds
+    pub idle_time_out: Option<u32>,
+
+    /// Buffer size of the underlying channels
+    pub buffer_size: Option<usize>,
+
+    /// Maximum number of sessions that are begun at the same time
+    pub max_sessions: Option<usize>,
+
+    /// Maximum number of redirects that are followed
+    pub follow_redirects: Option<usize>,
+
+    /// The SASL mechanism and credentials. SASL is not negotiated if this is not set
+    pub sasl: Option<SaslConfig>,
+
+    /// Settings of the TLS establishment
+    pub tls: Option<TlsConfig>,
+
+    /// The extension capabilities the sender supports
+    pub offered_capabilities: Option<Vec<String>>,
+
+    /// The extension capabilities the sender can use if the receiver supports them
+    pub desired_capabilities: Option<Vec<String>>,
+
+    /// Connection properties, whose values are sent as strings
+    pub properties: Option<BTreeMap<String, String>>,
+}
+
+/// The SASL mechanism and credentials of a connection
+///
+/// The username and password may refer to environment variables with `${ENV_NAME}`. The password
+/// is redacted in the `Debug` output.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mechanism", deny_unknown_fields)]
+pub enum SaslConfig {
+    /// SASL ANONYMOUS
+    #[serde(rename = "ANONYMOUS")]
+    Anonymous,
+
+    /// SASL PLAIN
+    #[serde(rename = "PLAIN")]
+    Plain {
+        /// Username
+        username: String,
+
+        /// Password
+        password: String,
+    },
+
+    /// SASL EXTERNAL
+    #[serde(rename = "EXTERNAL")]
+    External,
+
+    /// SASL SCRAM-SHA-1
+    #[cfg_attr(docsrs, doc(cfg(feature = "scram")))]
+    #[cfg(feature = "scram")]
+    #[serde(rename = "SCRAM-SHA-1")]
+    ScramSha1 {
+        /// Username
+        username: String,
+
+        /// Password
+        password: String,
+    },
+
+    /// SASL SCRAM-SHA-256
+    #[cfg_attr(docsrs, doc(cfg(feature = "scram")))]
+    #[cfg(feature = "scram")]
+    #[serde(rename = "SCRAM-SHA-256")]
+    ScramSha256 {
+        /// Username
+        username: String,
+
+        /// Password
+        password: String,
+    },
+
+    /// SASL SCRAM-SHA-512
+    #[cfg_attr(docsrs, doc(cfg(feature = "scram")))]
+    #[cfg(feature = "scram")]
+    #[serde(rename = "SCRAM-SHA-512")]
+    ScramSha512 {
+        /// Username
+        username: String,
+
+        /// Password
+        password: String,
+    },
+}
+
+impl std::fmt::Debug for SaslConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (name, username) = match self {
+            Self::Anonymous => return write!(f, "Anonymous"),
+            Self::External => return write!(f, "External"),
+            Self::Plain { username, .. } => ("Plain", username),
+            #[cfg(feature = "scram")]
+            Self::ScramSha1 { username, .. } => ("ScramSha1", username),
+            #[cfg(feature = "scram")]
+            Self::ScramSha256 { username, .. } => ("ScramSha256", username),
+            #[cfg(feature = "scram")]
+            Self::ScramSha512 { username, .. } => ("ScramSha512", username),
+        };
+        f.debug_struct(name)
+            .field("username", username)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+impl SaslConfig {
+    /// Resolves the environment variables in the credentials
+    pub fn to_profile(&self) -> Result<SaslProfile, ConfigError> {
+        let profile = match self {
+            Self::Anonymous => SaslProfile::Anonymous,
+            Self::External => SaslProfile::External,
+            Self::Plain { username, password } => SaslProfile::Plain {
+                username: resolve_env(username)?,
+                password: resolve_env(password)?,
+            },
+            #[cfg(feature = "scram")]
+            Self::ScramSha1 { username, password } => {
+                SaslProfile::ScramSha1(crate::sasl_profile::SaslScramSha1::new(
+                    resolve_env(username)?,
+                    resolve_env(password)?,
+                ))
+            }
+            #[cfg(feature = "scram")]
+            Self::ScramSha256 { username, password } => {
+                SaslProfile::ScramSha256(crate::sasl_profile::SaslScramSha256::new(
+                    resolve_env(username)?,
+                    resolve_env(password)?,
+                ))
+            }
+            #[cfg(feature = "scram")]
+            Self::ScramSha512 { username, password } => {
+                SaslProfile::ScramSha512(crate::sasl_profile::SaslScramSha512::new(
+                    resolve_env(username)?,
+                    resolve_env(password)?,
+                ))
+            }
+        };
+        Ok(profile)
+    }
+}
+
+/// Settings of the TLS establishment of a connection
+///
+/// The TLS connector itself is set on the builder. TLS is only used if the scheme of the url is
+/// `amqps`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    /// The domain that the certificate of the remote peer is verified against. The host of the
+    /// url is used if this is not set
+    pub domain: Option<String>,
+
+    /// The name that is sent in the SNI extension. The domain is used if this is not set
+    pub sni_hostname: Option<String>,
+
+    /// Whether TLS is established without exchanging the AMQP TLS protocol header first
+    pub alt_tls_establishment: bool,
+}
+
+impl<'a> Builder<'a, mode::ConnectorWithId, ()> {
+    /// Creates a builder with the settings of `config`
+    ///
+    /// The connection is then opened at `config.url`. An error is returned if an environment
+    /// variable that the SASL credentials refer to is not set.
+    pub fn from_config(config: &'a ConnectionConfig) -> Result<Self, ConfigError> {
+        let builder = Builder::<'a, mode::ConnectorNoId, ()>::new();
+        let mut builder = match &config.container_id {
+            Some(container_id) => builder.container_id(container_id.clone()),
+            None => builder.container_id_auto(),
+        };
+
+        builder = builder.hostname(config.hostname.as_deref());
+        if let Some(max_frame_size) = config.max_frame_size {
+            builder = builder.max_frame_size(max_frame_size);
+        }
+        if let Some(channel_max) = config.channel_max {
+            builder = builder.channel_max(channel_max);
+        }
+        if let Some(idle_time_out) = config.idle_time_out {
+            builder = builder.idle_time_out(idle_time_out);
+        }
+        if let Some(buffer_size) = config.buffer_size {
+            builder = builder.buffer_size(buffer_size);
+        }
+        if let Some(max_sessions) = config.max_sessions {
+            builder = builder.max_sessions(max_sessions);
+        }
+        if let Some(max_hops) = config.follow_redirects {
+            builder = builder.follow_redirects(max_hops);
+        }
+        if let Some(sasl) = &config.sasl {
+            builder = builder.sasl_profile(sasl.to_profile()?);
+        }
+        if let Some(tls) = &config.tls {
+            builder = builder
+                .domain(tls.domain.as_deref())
+                .sni_hostname(tls.sni_hostname.as_deref())
+                .alt_tls_establishment(tls.alt_tls_establishment);
+        }
+        if let Some(capabilities) = &config.offered_capabilities {
+            builder = builder.set_offered_capabilities(to_symbols(capabilities));
+        }
+        if let Some(capabilities) = &config.desired_capabilities {
+            builder = builder.set_desired_capabilities(to_symbols(capabilities));
+        }
+        if let Some(properties) = &config.properties {
+            builder = builder.properties(to_fields(properties));
+        }
+        Ok(builder)
+    }
+}
@@ -0,0 +1,361 @@
+//! Settings of the link builders
+
+use std::collections::BTreeMap;
+
+use fe2o3_amqp_types::{
+    definitions::{ReceiverSettleMode, SenderSettleMode},
+    messaging::{Source, Target, TerminusDurability, TerminusExpiryPolicy},
+    primitives::Array,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::link::{
+    builder::{Builder, WithName, WithSource, WithTarget},
+    receiver::CreditMode,
+    role,
+};
+
+use super::{to_fields, to_symbols};
+
+/// Settings of the source or the target of a link
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TerminusConfig {
+    /// The address of the node
+    pub address: Option<String>,
+
+    /// Which parts of the terminus state are retained durably
+    pub durable: Option<DurabilityConfig>,
+
+    /// When the expiry timer of the terminus starts counting down
+    pub expiry_policy: Option<ExpiryPolicyConfig>,
+
+    /// Duration in seconds that an expiring terminus is retained
+    pub timeout: Option<u32>,
+
+    /// Whether the remote peer creates the node dynamically
+    pub dynamic: bool,
+
+    /// The extension capabilities of the terminus
+    pub capabilities: Option<Vec<String>>,
+}
+
+impl TerminusConfig {
+    /// Creates the source of a link
+    pub fn to_source(&self) -> Source {
+        let mut builder = Source::builder().dynamic(self.dynamic);
+        if let Some(address) = &self.address {
+            builder = builder.address(address.clone());
+        }
+        if let Some(durable) = self.durable {
+            builder = builder.durable(durable.into());
+        }
+        if let Some(expiry_policy) = self.expiry_policy {
+            builder = builder.expiry_policy(expiry_policy.into());
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(capabilities) = &self.capabilities {
+            builder = builder.capabilities(Array::from(to_symbols(capabilities)));
+        }
+        builder.build()
+    }
+
+    /// Creates the target of a link
+    pub fn to_target(&self) -> Target {
+        let mut builder = Target::builder().dynamic(self.dynamic);
+        if let Some(address) = &self.address {
+            builder = builder.address(address.clone());
+        }
+        if let Some(durable) = self.durable {
+            builder = builder.durable(durable.into());
+        }
+        if let Some(expiry_policy) = self.expiry_policy {
+            builder = builder.expiry_policy(expiry_policy.into());
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(capabilities) = &self.capabilities {
+            builder = builder.capabilities(Array::from(to_symbols(capabilities)));
+        }
+        builder.build()
+    }
+}
+
+/// [`TerminusDurability`] in the config, which is written as `none`, `configuration` or
+/// `unsettled_state`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DurabilityConfig {
+    /// No terminus state is retained durably
+    None,
+
+    /// Only the existence and configuration of the terminus is retained durably
+    Configuration,
+
+    /// In addition to the existence and configuration of the terminus, the unsettled state for
+    /// durable messages is retained durably
+    UnsettledState,
+}
+
+impl From<DurabilityConfig> for TerminusDurability {
+    fn from(value: DurabilityConfig) -> Self {
+        match value {
+            DurabilityConfig::None => TerminusDurability::None,
+            DurabilityConfig::Configuration => TerminusDurability::Configuration,
+            DurabilityConfig::UnsettledState => TerminusDurability::UnsettledState,
+        }
+    }
+}
+
+/// [`TerminusExpiryPolicy`] in the config, which is written as `link_detach`, `session_end`,
+/// `connection_close` or `never`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpiryPolicyConfig {
+    /// The expiry timer starts when the terminus is detached
+    LinkDetach,
+
+    /// The expiry timer starts when the most recently associated session is ended
+    SessionEnd,
+
+    /// The expiry timer starts when the most recently associated connection is closed
+    ConnectionClose,
+
+    /// The terminus never expires
+    Never,
+}
+
+impl From<ExpiryPolicyConfig> for TerminusExpiryPolicy {
+    fn from(value: ExpiryPolicyConfig) -> Self {
+        match value {
+            ExpiryPolicyConfig::LinkDetach => TerminusExpiryPolicy::LinkDetach,
+            ExpiryPolicyConfig::SessionEnd => TerminusExpiryPolicy::SessionEnd,
+            ExpiryPolicyConfig::ConnectionClose => TerminusExpiryPolicy::ConnectionClose,
+            ExpiryPolicyConfig::Never => TerminusExpiryPolicy::Never,
+        }
+    }
+}
+
+/// [`SenderSettleMode`] in the config, which is written as `unsettled`, `settled` or `mixed`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SenderSettleModeConfig {
+    /// The sender sends all deliveries initially unsettled
+    Unsettled,
+
+    /// The sender sends all deliveries settled
+    Settled,
+
+    /// The sender may send a mixture of settled and unsettled deliveries
+    Mixed,
+}
+
+impl From<SenderSettleModeConfig> for SenderSettleMode {
+    fn from(value: SenderSettleModeConfig) -> Self {
+        match value {
+            SenderSettleModeConfig::Unsettled => SenderSettleMode::Unsettled,
+            SenderSettleModeConfig::Settled => SenderSettleMode::Settled,
+            SenderSettleModeConfig::Mixed => SenderSettleMode::Mixed,
+        }
+    }
+}
+
+/// [`ReceiverSettleMode`] in the config, which is written as `first` or `second`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiverSettleModeConfig {
+    /// The receiver spontaneously settles all incoming transfers
+    First,
+
+    /// The receiver only settles after sending the disposition to the sender and receiving a
+    /// disposition indicating settlement of the delivery from the sender
+    Second,
+}
+
+impl From<ReceiverSettleModeConfig> for ReceiverSettleMode {
+    fn from(value: ReceiverSettleModeConfig) -> Self {
+        match value {
+            ReceiverSettleModeConfig::First => ReceiverSettleMode::First,
+            ReceiverSettleModeConfig::Second => ReceiverSettleMode::Second,
+        }
+    }
+}
+
+/// [`CreditMode`] in the config, which is written as `manual`, `{ "auto": <credit> }` or
+/// `{ "threshold": { "credit": <credit>, "threshold": <threshold> } }`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CreditModeConfig {
+    /// The credit is set manually
+    Manual,
+
+    /// The credit is refilled automatically
+    Auto(u32),
+
+    /// The credit is refilled automatically once the remaining credit reaches the threshold
+    Threshold {
+        /// The credit issued by every refill
+        credit: u32,
+
+        /// The remaining credit at or below which the credit is refilled
+        threshold: u32,
+    },
+}
+
+impl From<CreditModeConfig> for CreditMode {
+    fn from(value: CreditModeConfig) -> Self {
+        match value {
+            CreditModeConfig::Manual => CreditMode::Manual,
+            CreditModeConfig::Auto(credit) => CreditMode::Auto(credit),
+            CreditModeConfig::Threshold { credit, threshold } => {
+                CreditMode::Threshold { credit, threshold }
+            }
+        }
+    }
+}
+
+/// Settings of a link builder, which are [`SenderConfig`] and [`ReceiverConfig`]
+pub trait LinkConfig<Role> {
+    /// Creates a link builder with the settings
+    fn to_builder(&self) -> Builder<Role, Target, WithName, WithSource, WithTarget>;
+}
+
+impl<Role> Builder<Role, Target, WithName, WithSource, WithTarget> {
+    /// Creates a builder with the settings of a [`SenderConfig`] or a [`ReceiverConfig`]
+    pub fn from_config(config: &impl LinkConfig<Role>) -> Self {
+        config.to_builder()
+    }
+}
+
+/// Settings of the sender builder
+///
+/// A field that is not set keeps the default value of the builder.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SenderConfig {
+    /// The name of the link
+    pub name: String,
+
+    /// The source of the messages
+    pub source: Option<TerminusConfig>,
+
+    /// The target of the messages
+    pub target: TerminusConfig,
+
+    /// Settlement policy of the sender
+    pub snd_settle_mode: Option<SenderSettleModeConfig>,
+
+    /// Settlement policy of the receiver
+    pub rcv_settle_mode: Option<ReceiverSettleModeConfig>,
+
+    /// The maximum message size supported by the link endpoint
+    pub max_message_size: Option<u64>,
+
+    /// The initial delivery count of the link
+    pub initial_delivery_count: Option<u32>,
+
+    /// Whether a `Rejected` outcome is returned as an error
+    pub rejected_as_error: bool,
+
+    /// Link properties, whose values are sent as strings
+    pub properties: Option<BTreeMap<String, String>>,
+}
+
+impl LinkConfig<role::SenderMarker> for SenderConfig {
+    fn to_builder(&self) -> Builder<role::SenderMarker, Target, WithName, WithSource, WithTarget> {
+        let source = self.source.clone().unwrap_or_default();
+        let mut builder = Builder::<role::SenderMarker, Target, _, _, _>::default()
+            .name(self.name.clone())
+            .source(source.to_source())
+            .target(self.target.to_target())
+            .rejected_as_error(self.rejected_as_error);
+        if let Some(mode) = self.snd_settle_mode {
+            builder = builder.sender_settle_mode(mode.into());
+        }
+        if let Some(mode) = self.rcv_settle_mode {
+            builder = builder.receiver_settle_mode(mode.into());
+        }
+        if let Some(max_size) = self.max_message_size {
+            builder = builder.max_message_size(max_size);
+        }
+        if let Some(count) = self.initial_delivery_count {
+            builder = builder.initial_delivery_count(count);
+        }
+        if let Some(properties) = &self.properties {
+            builder = builder.properties(to_fields(properties));
+        }
+        builder
+    }
+}
+
+/// Settings of the receiver builder
+///
+/// A field that is not set keeps the default value of the builder.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReceiverConfig {
+    /// The name of the link
+    pub name: String,
+
+    /// The source of the messages
+    pub source: TerminusConfig,
+
+    /// The target of the messages
+    pub target: Option<TerminusConfig>,
+
+    /// Settlement policy of the sender
+    pub snd_settle_mode: Option<SenderSettleModeConfig>,
+
+    /// Settlement policy of the receiver
+    pub rcv_settle_mode: Option<ReceiverSettleModeConfig>,
+
+    /// The maximum message size supported by the link endpoint
+    pub max_message_size: Option<u64>,
+
+    /// How link credit is issued
+    pub credit_mode: Option<CreditModeConfig>,
+
+    /// Whether the deliveries are accepted automatically
+    pub auto_accept: bool,
+
+    /// Number of disposed delivery tags that are remembered to detect resent deliveries
+    pub dedup_window: Option<usize>,
+
+    /// Link properties, whose values are sent as strings
+    pub properties: Option<BTreeMap<String, String>>,
+}
+
+impl LinkConfig<role::ReceiverMarker> for ReceiverConfig {
+    fn to_builder(
+        &self,
+    ) -> Builder<role::ReceiverMarker, Target, WithName, WithSource, WithTarget> {
+        let target = self.target.clone().unwrap_or_default();
+        let mut builder = Builder::<role::ReceiverMarker, Target, _, _, _>::default()
+            .name(self.name.clone())
+            .source(self.source.to_source())
+            .target(target.to_target())
+            .auto_accept(self.auto_accept);
+        if let Some(mode) = self.snd_settle_mode {
+            builder = builder.sender_settle_mode(mode.into());
+        }
+        if let Some(mode) = self.rcv_settle_mode {
+            builder = builder.receiver_settle_mode(mode.into());
+        }
+        if let Some(max_size) = self.max_message_size {
+            builder = builder.max_message_size(max_size);
+        }
+        if let Some(credit_mode) = self.credit_mode {
+            builder = builder.credit_mode(credit_mode.into());
+        }
+        if let Some(capacity) = self.dedup_window {
+            builder = builder.dedup_window(capacity);
+        }
+        if let Some(properties) = &self.properties {
+            builder = builder.properties(to_fields(properties));
+        }
+        builder
+    }
+}
@@ -0,0 +1,138 @@
+//! Settings of the connection, session and link builders that can be read from config files
+//!
+//! The config structs only hold plain data and implement `serde::Serialize` and
+//! `serde::Deserialize`, so they can be read with any `serde` format. Each builder is created from
+//! its config with `from_config`, and the settings that are not part of the config can still be
+//! set on the returned builder.
+//!
+//! The username and password of [`SaslConfig`] may refer to environment variables with
+//! `${ENV_NAME}`, which are resolved when the connection builder is created.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use fe2o3_amqp::{
+//!     config::{ConnectionConfig, SenderConfig, SessionConfig},
+//!     connection, link, session,
+//! };
+//!
+//! // {
+//! //     "url": "amqp://localhost:5672",
+//! //     "container_id": "orders-service",
+//! //     "idle_time_out": 60000,
+//! //     "sasl": { "mechanism": "PLAIN", "username": "guest", "password": "${AMQP_PASSWORD}" }
+//! // }
+//! let config: ConnectionConfig = serde_json::from_str(json).unwrap();
+//! let mut connection = connection::Builder::from_config(&config)
+//!     .unwrap()
+//!     .open(&config.url[..])
+//!     .await
+//!     .unwrap();
+//!
+//! let mut session = session::Builder::from_config(&SessionConfig::default())
+//!     .begin(&mut connection)
+//!     .await
+//!     .unwrap();
+//!
+//! let sender_config: SenderConfig = serde_json::from_str(r#"{
+//!     "name": "orders-sender",
+//!     "target": { "address": "orders" }
+//! }"#).unwrap();
+//! let mut sender = link::builder::Builder::from_config(&sender_config)
+//!     .attach(&mut session)
+//!     .await
+//!     .unwrap();
+//! ```
+
+use std::collections::BTreeMap;
+
+use fe2o3_amqp_types::{
+    definitions::Fields,
+    primitives::{Symbol, Value},
+};
+
+mod connection;
+mod link;
+mod session;
+
+pub use connection::{ConnectionConfig, SaslConfig, TlsConfig};
+pub use link::{
+    CreditModeConfig, DurabilityConfig, ExpiryPolicyConfig, LinkConfig, ReceiverConfig,
+    ReceiverSettleModeConfig, SenderConfig, SenderSettleModeConfig, TerminusConfig,
+};
+pub use session::SessionConfig;
+
+/// Error with creating a builder from its config
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ConfigError {
+    /// The environment variable that a value refers to is not set or is not valid unicode
+    #[error("Environment variable {0} is not set")]
+    MissingEnvVar(String),
+
+    /// A `${` in a value is not closed with a `}`
+    #[error("Reference to an environment variable is not closed")]
+    UnterminatedEnvReference,
+}
+
+/// Replaces every `${ENV_NAME}` in the value with the value of the environment variable
+pub(crate) fn resolve_env(value: &str) -> Result<String, ConfigError> {
+    let mut resolved = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        resolved.push_str(&rest[..start]);
+        let reference = &rest[start + 2..];
+        let end = reference
+            .find('}')
+            .ok_or(ConfigError::UnterminatedEnvReference)?;
+        let name = &reference[..end];
+        let var = std::env::var(name).map_err(|_| ConfigError::MissingEnvVar(name.to_string()))?;
+        resolved.push_str(&var);
+        rest = &reference[end + 1..];
+    }
+    resolved.push_str(rest);
+    Ok(resolved)
+}
+
+/// Properties are written as strings in the config
+pub(crate) fn to_fields(properties: &BTreeMap<String, String>) -> Fields {
+    properties
+        .iter()
+        .map(|(key, value)| (Symbol::from(key.as_str()), Value::from(value.as_str())))
+        .collect()
+}
+
+pub(crate) fn to_symbols(capabilities: &[String]) -> Vec<Symbol> {
+    capabilities
+        .iter()
+        .map(|capability| Symbol::from(capability.as_str()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{resolve_env, ConfigError};
+
+    #[test]
+    fn test_resolve_env() {
+        std::env::set_var("FE2O3_CONFIG_RESOLVE_ENV", "secret");
+        assert_eq!(resolve_env("plain").unwrap(), "plain");
+        assert_eq!(
+            resolve_env("${FE2O3_CONFIG_RESOLVE_ENV}").unwrap(),
+            "secret"
+        );
+        assert_eq!(
+            resolve_env("a-${FE2O3_CONFIG_RESOLVE_ENV}-${FE2O3_CONFIG_RESOLVE_ENV}").unwrap(),
+            "a-secret-secret"
+        );
+        assert_eq!(
+            resolve_env("${FE2O3_CONFIG_NOT_SET}"),
+            Err(ConfigError::MissingEnvVar(
+                "FE2O3_CONFIG_NOT_SET".to_string()
+            ))
+        );
+        assert_eq!(
+            resolve_env("${FE2O3_CONFIG_RESOLVE_ENV"),
+            Err(ConfigError::UnterminatedEnvReference)
+        );
+    }
+}
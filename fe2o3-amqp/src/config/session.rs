@@ -0,0 +1,80 @@
+//! Settings of the session builder
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::session::Builder;
+
+use super::{to_fields, to_symbols};
+
+/// Settings of [`session::Builder`](crate::session::Builder)
+///
+/// A field that is not set keeps the default value of the builder.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionConfig {
+    /// The initial incoming-window of the sender
+    pub incoming_window: Option<u32>,
+
+    /// The initial outgoing-window of the sender
+    pub outgoing_window: Option<u32>,
+
+    /// The maximum handle value that can be used on the session
+    pub handle_max: Option<u32>,
+
+    /// Buffer size of the channels that are used by the links attached to the session
+    pub buffer_size: Option<usize>,
+
+    /// Maximum number of payload bytes of incoming transfers that are buffered for the session
+    /// but not yet taken by its receivers
+    pub incoming_buffer_limit: Option<usize>,
+
+    /// Maximum number of payload bytes of the incoming deliveries that are not complete yet
+    /// across all the links of the session
+    pub incomplete_incoming_limit: Option<usize>,
+
+    /// The extension capabilities the sender supports
+    pub offered_capabilities: Option<Vec<String>>,
+
+    /// The extension capabilities the sender can use if the receiver supports them
+    pub desired_capabilities: Option<Vec<String>>,
+
+    /// Session properties, whose values are sent as strings
+    pub properties: Option<BTreeMap<String, String>>,
+}
+
+impl Builder {
+    /// Creates a builder with the settings of `config`
+    pub fn from_config(config: &SessionConfig) -> Self {
+        let mut builder = Builder::new();
+        if let Some(incoming_window) = config.incoming_window {
+            builder = builder.incoming_window(incoming_window);
+        }
+        if let Some(outgoing_window) = config.outgoing_window {
+            builder = builder.outgoing_window(outgoing_window);
+        }
+        if let Some(handle_max) = config.handle_max {
+            builder = builder.handle_max(handle_max);
+        }
+        if let Some(buffer_size) = config.buffer_size {
+            builder = builder.buffer_size(buffer_size);
+        }
+        if let Some(bytes) = config.incoming_buffer_limit {
+            builder = builder.incoming_buffer_limit(bytes);
+        }
+        if let Some(bytes) = config.incomplete_incoming_limit {
+            builder = builder.incomplete_incoming_limit(bytes);
+        }
+        if let Some(capabilities) = &config.offered_capabilities {
+            builder = builder.set_offered_capabilities(to_symbols(capabilities));
+        }
+        if let Some(capabilities) = &config.desired_capabilities {
+            builder = builder.set_desired_capabilities(to_symbols(capabilities));
+        }
+        if let Some(properties) = &config.properties {
+            builder = builder.properties(to_fields(properties));
+        }
+        builder
+    }
+}
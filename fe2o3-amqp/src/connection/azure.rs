@@ -0,0 +1,214 @@
+//! Presets of the connection builder for Azure Service Bus and Azure Event Hubs
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use fe2o3_amqp::{connection::azure::{self, AzureCredential}, Connection};
+//!
+//! let namespace = "my-namespace.servicebus.windows.net";
+//! let credential = AzureCredential::SharedAccessKey {
+//!     key_name: "RootManageSharedAccessKey".to_string(),
+//!     key: "<key>".to_string(),
+//! };
+//! let connection = Connection::builder()
+//!     .service_bus(namespace, credential)
+//!     .idle_time_out(30_000u32) // Any field can still be overridden
+//!     .open(azure::url(namespace).unwrap())
+//!     .await
+//!     .unwrap();
+//! ```
+
+use std::time::Duration;
+
+use fe2o3_amqp_types::definitions::Milliseconds;
+
+use crate::sasl_profile::SaslProfile;
+
+use super::{mode, Builder};
+
+cfg_not_wasm32! {
+    use url::Url;
+}
+
+/// The port of the AMQP over TLS endpoint of Azure Service Bus and Azure Event Hubs
+pub const AZURE_PORT: u16 = 5671;
+
+/// The maximum frame size proposed by the presets, which matches the maximum frame size of Azure
+/// Service Bus and Azure Event Hubs
+pub const AZURE_MAX_FRAME_SIZE: u32 = 65_536;
+
+/// The idle time-out proposed by the presets, within which a connection that is dropped without a
+/// Close is detected
+pub const AZURE_IDLE_TIME_OUT: Duration = Duration::from_secs(60);
+
+/// Credential for a namespace of Azure Service Bus or Azure Event Hubs
+///
+/// The key is redacted in the `Debug` output.
+#[derive(Clone)]
+pub enum AzureCredential {
+    /// A shared access key, which is authenticated with SASL PLAIN. The name of the key is the
+    /// username and the key is the password
+    SharedAccessKey {
+        /// Name of the shared access policy
+        key_name: String,
+
+        /// Primary or secondary key of the shared access policy
+        key: String,
+    },
+
+    /// A token that is put on the `$cbs` node after the connection is opened, eg. with
+    /// `fe2o3_amqp_cbs::client::CbsClient::put_token`. The connection is opened with SASL
+    /// ANONYMOUS and the links can only be attached once the token is put
+    Cbs,
+}
+
+impl std::fmt::Debug for AzureCredential {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SharedAccessKey { key_name, key: _ } => f
+                .debug_struct("SharedAccessKey")
+                .field("key_name", key_name)
+                .field("key", &"<redacted>")
+                .finish(),
+            Self::Cbs => write!(f, "Cbs"),
+        }
+    }
+}
+
+impl From<AzureCredential> for SaslProfile {
+    fn from(credential: AzureCredential) -> Self {
+        match credential {
+            AzureCredential::SharedAccessKey { key_name, key } => SaslProfile::Plain {
+                username: key_name,
+                password: key,
+            },
+            AzureCredential::Cbs => SaslProfile::Anonymous,
+        }
+    }
+}
+
+cfg_not_wasm32! {
+    /// The url of the AMQP over TLS endpoint of a namespace, eg.
+    /// `amqps://my-namespace.servicebus.windows.net:5671`
+    pub fn url(fully_qualified_namespace: &str) -> Result<Url, url::ParseError> {
+        Url::parse(&format!("amqps://{}:{}", fully_qualified_namespace, AZURE_PORT))
+    }
+}
+
+impl<'a, Tls> Builder<'a, mode::ConnectorNoId, Tls> {
+    /// Sets the fields that are known to work with Azure Service Bus
+    ///
+    /// The container id is generated with the prefix `fe2o3-servicebus-`. The scheme is `amqps`
+    /// with the alternative TLS establishment, the hostname and the domain are the fully
+    /// qualified namespace, eg. `my-namespace.servicebus.windows.net`, and the maximum frame size
+    /// and the idle time-out are [`AZURE_MAX_FRAME_SIZE`] and [`AZURE_IDLE_TIME_OUT`]. Every
+    /// field can be overridden afterwards, and the connection is opened at the url returned by
+    /// [`url`].
+    pub fn service_bus(
+        self,
+        fully_qualified_namespace: &'a str,
+        credential: AzureCredential,
+    ) -> Builder<'a, mode::ConnectorWithId, Tls> {
+        self.azure("fe2o3-servicebus", fully_qualified_namespace, credential)
+    }
+
+    /// Sets the fields that are known to work with Azure Event Hubs
+    ///
+    /// This is the same as [`service_bus`](Self::service_bus) except that the container id is
+    /// generated with the prefix `fe2o3-eventhubs-`.
+    pub fn event_hubs(
+        self,
+        fully_qualified_namespace: &'a str,
+        credential: AzureCredential,
+    ) -> Builder<'a, mode::ConnectorWithId, Tls> {
+        self.azure("fe2o3-eventhubs", fully_qualified_namespace, credential)
+    }
+
+    fn azure(
+        self,
+        container_id_prefix: &str,
+        fully_qualified_namespace: &'a str,
+        credential: AzureCredential,
+    ) -> Builder<'a, mode::ConnectorWithId, Tls> {
+        let idle_time_out = AZURE_IDLE_TIME_OUT.as_millis() as Milliseconds;
+        self.container_id(format!("{}-{}", container_id_prefix, uuid::Uuid::new_v4()))
+            .scheme("amqps")
+            .hostname(fully_qualified_namespace)
+            .domain(fully_qualified_namespace)
+            .alt_tls_establishment(true)
+            .max_frame_size(AZURE_MAX_FRAME_SIZE)
+            .idle_time_out(idle_time_out)
+            .sasl_profile(credential)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fe2o3_amqp_types::performatives::Open;
+
+    use crate::{sasl_profile::SaslProfile, Connection};
+
+    use super::{AzureCredential, AZURE_MAX_FRAME_SIZE};
+
+    const NAMESPACE: &str = "my-namespace.servicebus.windows.net";
+
+    fn shared_access_key() -> AzureCredential {
+        AzureCredential::SharedAccessKey {
+            key_name: "RootManageSharedAccessKey".to_string(),
+            key: "secret-key".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_service_bus_preset() {
+        let builder = Connection::builder().service_bus(NAMESPACE, shared_access_key());
+        assert_eq!(builder.scheme, "amqps");
+        assert_eq!(builder.domain, Some(NAMESPACE));
+        assert!(builder.alt_tls_estab);
+        match &builder.sasl_profile {
+            Some(SaslProfile::Plain { username, password }) => {
+                assert_eq!(username, "RootManageSharedAccessKey");
+                assert_eq!(password, "secret-key");
+            }
+            profile => panic!("Expecting PLAIN, found {:?}", profile),
+        }
+
+        let open = Open::from(builder);
+        assert!(open.container_id.starts_with("fe2o3-servicebus-"));
+        assert_eq!(open.hostname.as_deref(), Some(NAMESPACE));
+        assert_eq!(open.max_frame_size.0, AZURE_MAX_FRAME_SIZE);
+        // Half of the idle time-out is sent to the remote peer
+        assert_eq!(open.idle_time_out, Some(30_000));
+    }
+
+    #[test]
+    fn test_event_hubs_preset_with_cbs_and_overrides() {
+        let builder = Connection::builder()
+            .event_hubs(NAMESPACE, AzureCredential::Cbs)
+            .idle_time_out(30_000u32)
+            .max_frame_size(16_384);
+        assert!(matches!(builder.sasl_profile, Some(SaslProfile::Anonymous)));
+
+        let open = Open::from(builder);
+        assert!(open.container_id.starts_with("fe2o3-eventhubs-"));
+        assert_eq!(open.idle_time_out, Some(15_000));
+        assert_eq!(open.max_frame_size.0, 16_384);
+    }
+
+    #[test]
+    fn test_azure_credential_debug_redacts_key() {
+        let debug = format!("{:?}", shared_access_key());
+        assert!(debug.contains("RootManageSharedAccessKey"));
+        assert!(!debug.contains("secret-key"));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_azure_url() {
+        let url = super::url(NAMESPACE).unwrap();
+        assert_eq!(
+            url.as_str(),
+            "amqps://my-namespace.servicebus.windows.net:5671"
+        );
+    }
+}
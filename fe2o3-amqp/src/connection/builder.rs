@@ -17,14 +17,18 @@ use tokio_util::codec::{FramedRead, FramedWrite};
 
 cfg_not_wasm32! {
     use std::convert::TryInto;
+    use fe2o3_amqp_types::definitions::Redirect;
     use url::Url;
-    use tokio::net::TcpStream;
+
+    use crate::rt::{Deferred, Spawner};
+
+    use super::{url_params::UrlParams, EngineFuture};
 }
 
 use crate::{
     connection::{Connection, ConnectionState},
     control::ConnectionControl,
-    frames::sasl,
+    frames::{amqp::LogRedactor, sasl},
     sasl_profile::{Negotiation, SaslProfile},
     session::frame::SessionFrame,
     transport::Transport,
@@ -33,8 +37,8 @@ use crate::{
 };
 
 use super::{
-    engine::ConnectionEngine, ConnectionHandle, OpenError, DEFAULT_CHANNEL_MAX,
-    DEFAULT_MAX_FRAME_SIZE,
+    engine::ConnectionEngine, heartbeat::HeartbeatPolicy, ConnectionHandle, OpenError,
+    WriteCoalescing, DEFAULT_CHANNEL_MAX, DEFAULT_MAX_FRAME_SIZE,
 };
 
 #[cfg(feature = "tracing")]
@@ -53,12 +57,14 @@ cfg_not_wasm32! {
     }
 }
 
-pub(crate) mod mode {
+pub mod mode {
+    //! Type states of the connection [`Builder`](super::Builder)
+
     /// Type state for [`crate::connection::Builder`]
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub struct ConnectorWithId {}
     /// Type state for [`crate::connection::Builder`]
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub struct ConnectorNoId {}
 }
 
@@ -68,15 +74,25 @@ pub struct Builder<'a, Mode, Tls> {
     /// The id of the source container
     pub container_id: String,
 
-    /// The name of the target host
+    /// The name of the target host, which is sent in the `hostname` field of the Open frame and
+    /// used in SASL negotiation
+    ///
+    /// If this is not set, the host of the URL passed to `open` is used
     pub hostname: Option<&'a str>,
 
     /// URL scheme
     pub scheme: &'a str,
 
     /// URL domain
+    ///
+    /// If this is not set, the domain of the URL passed to `open` is used
     pub domain: Option<&'a str>,
 
+    /// The server name sent in the TLS client hello (SNI)
+    ///
+    /// If this is not set, [`domain`](#structfield.domain) is used
+    pub sni_hostname: Option<&'a str>,
+
     /// Proposed maximum frame size
     ///
     /// This includes the 8 bytes taken by the frame header
@@ -92,6 +108,15 @@ pub struct Builder<'a, Mode, Tls> {
     /// Idle time-out
     pub idle_time_out: Option<Milliseconds>,
 
+    /// How often empty frames are sent relative to the idle time-out of the remote peer
+    ///
+    /// # Default
+    ///
+    /// ```rust, ignore
+    /// HeartbeatPolicy::HalfRemote
+    /// ```
+    pub heartbeat_policy: HeartbeatPolicy,
+
     /// Locales available for outgoing text
     pub outgoing_locales: Option<Vec<IetfLanguageTag>>,
 
@@ -139,6 +164,49 @@ pub struct Builder<'a, Mode, Tls> {
     /// actual TLS handshake
     pub alt_tls_estab: bool,
 
+    /// Coalescing of the outgoing Transfer frames into fewer writes
+    ///
+    /// Every frame is written to the transport as soon as it is sent if this is not set
+    pub write_coalescing: Option<WriteCoalescing>,
+
+    /// Maximum number of sessions that can be active on the connection at the same time
+    ///
+    /// Unlike `channel_max`, this is not sent to the remote peer and only limits the sessions
+    /// that are begun locally
+    pub max_sessions: Option<usize>,
+
+    /// Maximum number of `amqp:connection:redirect` errors that `open` follows by connecting to
+    /// the container the remote peer redirected to
+    ///
+    /// Redirects are not followed if this is zero
+    pub max_redirects: usize,
+
+    /// Rewrites the frames before they are formatted in the trace and log events of the
+    /// connection
+    pub log_redactor: Option<LogRedactor>,
+
+    /// Whether `open` fails with [`OpenError::UnknownUrlParam`] if the url has a query parameter
+    /// that is not a connection option
+    ///
+    /// A warning is logged instead if this is not set
+    pub strict_url_params: bool,
+
+    // Whether the container id is generated, in which case the container id in the url is used
+    generated_container_id: bool,
+
+    // Whether the max frame size and the channel max are set with the builder methods, in which
+    // case the values in the url are not used
+    max_frame_size_set: bool,
+    channel_max_set: bool,
+
+    // Where the event loops are run
+    #[cfg(not(target_arch = "wasm32"))]
+    spawner: Spawner,
+
+    // Additions to the default rustls client config
+    #[cfg(feature = "rustls")]
+    rustls_options: super::tls::RustlsOptions,
+
     // type state marker
     marker: PhantomData<Mode>,
 }
@@ -172,6 +240,7 @@ impl<'a, Mode: std::fmt::Debug> std::fmt::Debug for Builder<'a, Mode, ()> {
             .field("hostname", &self.hostname)
             .field("scheme", &self.scheme)
             .field("domain", &self.domain)
+            .field("sni_hostname", &self.sni_hostname)
             .field("max_frame_size", &self.max_frame_size)
             .field("channel_max", &self.channel_max)
             .field("idle_time_out", &self.idle_time_out)
@@ -183,6 +252,12 @@ impl<'a, Mode: std::fmt::Debug> std::fmt::Debug for Builder<'a, Mode, ()> {
             .field("tls_connector", &"()")
             .field("buffer_size", &self.buffer_size)
             .field("sasl_profile", &self.sasl_profile)
+            .field("heartbeat_policy", &self.heartbeat_policy)
+            .field("write_coalescing", &self.write_coalescing)
+            .field("max_sessions", &self.max_sessions)
+            .field("max_redirects", &self.max_redirects)
+            .field("log_redactor", &self.log_redactor)
+            .field("strict_url_params", &self.strict_url_params)
             .field("marker", &self.marker)
             .finish()
     }
@@ -196,6 +271,7 @@ cfg_rustls! {
                 .field("hostname", &self.hostname)
                 .field("scheme", &self.scheme)
                 .field("domain", &self.domain)
+                .field("sni_hostname", &self.sni_hostname)
                 .field("max_frame_size", &self.max_frame_size)
                 .field("channel_max", &self.channel_max)
                 .field("idle_time_out", &self.idle_time_out)
@@ -207,6 +283,12 @@ cfg_rustls! {
                 .field("tls_connector", &"tokio_rustls::TlsConnector")
                 .field("buffer_size", &self.buffer_size)
                 .field("sasl_profile", &self.sasl_profile)
+                .field("heartbeat_policy", &self.heartbeat_policy)
+                .field("write_coalescing", &self.write_coalescing)
+                .field("max_sessions", &self.max_sessions)
+                .field("max_redirects", &self.max_redirects)
+                .field("log_redactor", &self.log_redactor)
+                .field("strict_url_params", &self.strict_url_params)
                 .field("marker", &self.marker)
                 .finish()
         }
@@ -224,6 +306,7 @@ cfg_not_wasm32! {
                     .field("hostname", &self.hostname)
                     .field("scheme", &self.scheme)
                     .field("domain", &self.domain)
+                    .field("sni_hostname", &self.sni_hostname)
                     .field("max_frame_size", &self.max_frame_size)
                     .field("channel_max", &self.channel_max)
                     .field("idle_time_out", &self.idle_time_out)
@@ -235,6 +318,12 @@ cfg_not_wasm32! {
                     .field("tls_connector", &"tokio_native_tls::TlsConnector")
                     .field("buffer_size", &self.buffer_size)
                     .field("sasl_profile", &self.sasl_profile)
+                    .field("heartbeat_policy", &self.heartbeat_policy)
+                    .field("write_coalescing", &self.write_coalescing)
+                    .field("max_sessions", &self.max_sessions)
+                    .field("max_redirects", &self.max_redirects)
+                    .field("log_redactor", &self.log_redactor)
+                    .field("strict_url_params", &self.strict_url_params)
                     .field("marker", &self.marker)
                     .finish()
             }
@@ -256,6 +345,7 @@ impl<'a, Mode> Builder<'a, Mode, ()> {
             hostname: None,
             scheme: "amqp", // Assume non-TLS by default
             domain: None,
+            sni_hostname: None,
             // set to 512 before Open frame is sent
             max_frame_size: MaxFrameSize(DEFAULT_MAX_FRAME_SIZE),
             channel_max: ChannelMax(DEFAULT_CHANNEL_MAX),
@@ -271,6 +361,19 @@ impl<'a, Mode> Builder<'a, Mode, ()> {
             buffer_size: DEFAULT_OUTGOING_BUFFER_SIZE,
             sasl_profile: None,
             alt_tls_estab: false,
+            heartbeat_policy: HeartbeatPolicy::HalfRemote,
+            write_coalescing: None,
+            max_sessions: None,
+            max_redirects: 0,
+            log_redactor: None,
+            strict_url_params: false,
+            generated_container_id: false,
+            max_frame_size_set: false,
+            channel_max_set: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            spawner: Spawner::default(),
+            #[cfg(feature = "rustls")]
+            rustls_options: Default::default(),
 
             marker: PhantomData,
         }
@@ -278,6 +381,16 @@ impl<'a, Mode> Builder<'a, Mode, ()> {
 }
 
 impl<'a, Tls> Builder<'a, mode::ConnectorNoId, Tls> {
+    /// Use a generated container id of the form `fe2o3-<uuid>`
+    ///
+    /// This is useful when the container id does not need to be known in advance, eg. for
+    /// short-lived tools
+    pub fn container_id_auto(self) -> Builder<'a, mode::ConnectorWithId, Tls> {
+        let mut builder = self.container_id(format!("fe2o3-{}", uuid::Uuid::new_v4()));
+        builder.generated_container_id = true;
+        builder
+    }
+
     /// The id of the source container
     pub fn container_id(self, id: impl Into<String>) -> Builder<'a, mode::ConnectorWithId, Tls> {
         // In Rust, it’s more common to pass slices as arguments
@@ -288,6 +401,7 @@ impl<'a, Tls> Builder<'a, mode::ConnectorNoId, Tls> {
             hostname: self.hostname,
             scheme: self.scheme,
             domain: self.domain,
+            sni_hostname: self.sni_hostname,
             // set to 512 before Open frame is sent
             max_frame_size: self.max_frame_size,
             channel_max: self.channel_max,
@@ -303,6 +417,19 @@ impl<'a, Tls> Builder<'a, mode::ConnectorNoId, Tls> {
             buffer_size: self.buffer_size,
             sasl_profile: self.sasl_profile,
             alt_tls_estab: self.alt_tls_estab,
+            heartbeat_policy: self.heartbeat_policy,
+            write_coalescing: self.write_coalescing,
+            max_sessions: self.max_sessions,
+            max_redirects: self.max_redirects,
+            log_redactor: self.log_redactor,
+            strict_url_params: self.strict_url_params,
+            generated_container_id: false,
+            max_frame_size_set: self.max_frame_size_set,
+            channel_max_set: self.channel_max_set,
+            #[cfg(not(target_arch = "wasm32"))]
+            spawner: self.spawner,
+            #[cfg(feature = "rustls")]
+            rustls_options: self.rustls_options,
 
             marker: PhantomData,
         }
@@ -324,6 +451,9 @@ impl<'a, Mode, Tls> Builder<'a, Mode, Tls> {
         /// Set the TLS connector with `tokio-rustls`
         ///
         /// If only one of `"rustls"` or `"native-tls"` is enabled, a convenience alias function `tls_connector()` is provided.
+        ///
+        /// The options set with the `tls_*` methods only apply to the default connector and are
+        /// discarded.
         pub fn rustls_connector(
             self,
             tls_connector: tokio_rustls::TlsConnector,
@@ -336,6 +466,7 @@ impl<'a, Mode, Tls> Builder<'a, Mode, Tls> {
                 hostname: self.hostname,
                 scheme: self.scheme,
                 domain: self.domain,
+                sni_hostname: self.sni_hostname,
                 // set to 512 before Open frame is sent
                 max_frame_size: self.max_frame_size,
                 channel_max: self.channel_max,
@@ -351,6 +482,19 @@ impl<'a, Mode, Tls> Builder<'a, Mode, Tls> {
                 buffer_size: self.buffer_size,
                 sasl_profile: self.sasl_profile,
                 alt_tls_estab: self.alt_tls_estab,
+                heartbeat_policy: self.heartbeat_policy,
+                write_coalescing: self.write_coalescing,
+                max_sessions: self.max_sessions,
+                max_redirects: self.max_redirects,
+                log_redactor: self.log_redactor,
+                strict_url_params: self.strict_url_params,
+                generated_container_id: self.generated_container_id,
+                max_frame_size_set: self.max_frame_size_set,
+                channel_max_set: self.channel_max_set,
+                #[cfg(not(target_arch = "wasm32"))]
+                spawner: self.spawner,
+                #[cfg(feature = "rustls")]
+                rustls_options: Default::default(),
 
                 marker: PhantomData,
             }
@@ -399,6 +543,7 @@ impl<'a, Mode, Tls> Builder<'a, Mode, Tls> {
                     hostname: self.hostname,
                     scheme: self.scheme,
                     domain: self.domain,
+                    sni_hostname: self.sni_hostname,
                     // set to 512 before Open frame is sent
                     max_frame_size: self.max_frame_size,
                     channel_max: self.channel_max,
@@ -414,6 +559,19 @@ impl<'a, Mode, Tls> Builder<'a, Mode, Tls> {
                     buffer_size: self.buffer_size,
                     sasl_profile: self.sasl_profile,
                     alt_tls_estab: self.alt_tls_estab,
+                    heartbeat_policy: self.heartbeat_policy,
+                    write_coalescing: self.write_coalescing,
+                    max_sessions: self.max_sessions,
+                    max_redirects: self.max_redirects,
+                    log_redactor: self.log_redactor,
+                    strict_url_params: self.strict_url_params,
+                    generated_container_id: self.generated_container_id,
+                    max_frame_size_set: self.max_frame_size_set,
+                    channel_max_set: self.channel_max_set,
+                    #[cfg(not(target_arch = "wasm32"))]
+                    spawner: self.spawner,
+                    #[cfg(feature = "rustls")]
+                    rustls_options: Default::default(),
 
                     marker: PhantomData,
                 }
@@ -422,13 +580,79 @@ impl<'a, Mode, Tls> Builder<'a, Mode, Tls> {
     }
 }
 
+cfg_rustls! {
+    impl<'a, Mode> Builder<'a, Mode, ()> {
+        /// Trust `cert` in addition to the webpki roots when the default `rustls` connector is
+        /// used
+        ///
+        /// This can be called multiple times to trust more than one certificate, eg. the
+        /// certificate of a private CA. The options of the default connector are discarded if a
+        /// connector is supplied with [`rustls_connector`](#method.rustls_connector).
+        pub fn tls_add_root_certificate(
+            mut self,
+            cert: librustls::pki_types::CertificateDer<'static>,
+        ) -> Self {
+            self.rustls_options.root_certificates.push(cert);
+            self
+        }
+
+        /// Present `cert_chain` and `key` to the server when the default `rustls` connector is
+        /// used
+        ///
+        /// If the SASL profile is not set, SASL EXTERNAL is used so that the server can
+        /// authenticate the client with the certificate. ANONYMOUS is used instead if the server
+        /// does not offer EXTERNAL.
+        pub fn tls_client_auth_cert(
+            mut self,
+            cert_chain: Vec<librustls::pki_types::CertificateDer<'static>>,
+            key: librustls::pki_types::PrivateKeyDer<'static>,
+        ) -> Self {
+            self.rustls_options.client_auth = Some(super::tls::ClientAuth::new(cert_chain, key));
+            self
+        }
+
+        /// **DANGER** Accept any server certificate, including self-signed, expired and
+        /// mismatched ones, when the default `rustls` connector is used
+        ///
+        /// This leaves the connection open to man-in-the-middle attacks and should only be used
+        /// for testing. Prefer [`tls_add_root_certificate`](#method.tls_add_root_certificate) to
+        /// trust a self-signed certificate.
+        #[cfg_attr(docsrs, doc(cfg(feature = "dangerous-tls")))]
+        #[cfg(feature = "dangerous-tls")]
+        pub fn tls_danger_accept_invalid_certs(mut self, value: bool) -> Self {
+            self.rustls_options.accept_invalid_certs = value;
+            self
+        }
+    }
+}
+
 impl<'a, Mode, Tls> Builder<'a, Mode, Tls> {
-    /// The name of the target host
+    /// The name of the target host, which is sent in the `hostname` field of the Open frame
+    ///
+    /// This can be used to select a virtual host that differs from the host the TCP connection
+    /// is made to. The `hostname` sent in the Open frame is chosen in the following order
+    ///
+    /// 1. the value set with this method
+    /// 2. the host of the URL passed to `open`
     pub fn hostname(mut self, hostname: impl Into<Option<&'a str>>) -> Self {
         self.hostname = hostname.into();
         self
     }
 
+    /// The server name sent in the TLS client hello (SNI)
+    ///
+    /// The server name is chosen in the following order
+    ///
+    /// 1. the value set with this method
+    /// 2. the value set with [`domain`](#method.domain)
+    /// 3. the domain of the URL passed to `open`
+    ///
+    /// Please note that the `hostname` sent in the Open frame is never used as the server name
+    pub fn sni_hostname(mut self, sni_hostname: impl Into<Option<&'a str>>) -> Self {
+        self.sni_hostname = sni_hostname.into();
+        self
+    }
+
     /// URL scheme
     pub fn scheme(mut self, scheme: &'a str) -> Self {
         self.scheme = scheme;
@@ -436,16 +660,33 @@ impl<'a, Mode, Tls> Builder<'a, Mode, Tls> {
     }
 
     /// URL domain
+    ///
+    /// This takes precedence over the domain of the URL passed to `open`
     pub fn domain(mut self, domain: impl Into<Option<&'a str>>) -> Self {
         self.domain = domain.into();
         self
     }
 
+    #[cfg(any(feature = "rustls", feature = "native-tls"))]
+    fn tls_server_name(&self) -> Result<&'a str, OpenError> {
+        self.sni_hostname
+            .or(self.domain)
+            .ok_or(OpenError::InvalidDomain)
+    }
+
     /// Proposed maximum frame size
     ///
-    /// This includes the 8 bytes taken by the frame header
+    /// This includes the 8 bytes taken by the frame header. Opening the connection fails with
+    /// [`OpenError::MaxFrameSizeTooSmall`] if this is smaller than the minimum max frame size of
+    /// 512 bytes. The value cannot be rejected here because the builder methods return the builder
+    /// rather than a `Result`, so it is checked before anything is sent on the connection.
+    ///
+    /// The frames sent on the connection are limited to the smaller of this and the max frame size
+    /// of the remote peer, see
+    /// [`ConnectionHandle::negotiated_max_frame_size`](crate::connection::ConnectionHandle::negotiated_max_frame_size)
     pub fn max_frame_size(mut self, max_frame_size: impl Into<MaxFrameSize>) -> Self {
         self.max_frame_size = max_frame_size.into();
+        self.max_frame_size_set = true;
         self
     }
 
@@ -456,6 +697,7 @@ impl<'a, Mode, Tls> Builder<'a, Mode, Tls> {
     /// connection
     pub fn channel_max(mut self, channel_max: impl Into<ChannelMax>) -> Self {
         self.channel_max = channel_max.into();
+        self.channel_max_set = true;
         self
     }
 
@@ -467,6 +709,7 @@ impl<'a, Mode, Tls> Builder<'a, Mode, Tls> {
         let mut channel_max = session_max.into();
         channel_max.0 = channel_max.0.saturating_sub(1);
         self.channel_max = channel_max;
+        self.channel_max_set = true;
         self
     }
 
@@ -476,6 +719,16 @@ impl<'a, Mode, Tls> Builder<'a, Mode, Tls> {
         self
     }
 
+    /// How often empty frames are sent relative to the idle time-out of the remote peer
+    ///
+    /// `open` fails with [`OpenError::IdleTimeoutUnsatisfiable`] and closes the connection with an
+    /// `amqp:not-allowed` error if the policy cannot keep the connection alive within the remote
+    /// idle time-out. Please see [`HeartbeatPolicy`].
+    pub fn heartbeat_policy(mut self, policy: HeartbeatPolicy) -> Self {
+        self.heartbeat_policy = policy;
+        self
+    }
+
     /// Add one locales available for outgoing text
     pub fn add_outgoing_locales(mut self, locale: impl Into<IetfLanguageTag>) -> Self {
         match &mut self.outgoing_locales {
@@ -566,6 +819,119 @@ impl<'a, Mode, Tls> Builder<'a, Mode, Tls> {
         self.alt_tls_estab = value;
         self
     }
+
+    /// Buffer the outgoing Transfer frames and write them to the transport together, which
+    /// reduces the number of writes when many small messages are sent
+    ///
+    /// The buffer is written once `max_delay` has passed since the first Transfer frame was
+    /// buffered, once it holds at least `max_bytes`, or when any other frame is sent, so the
+    /// control frames are never delayed. Please see [`WriteCoalescing`].
+    pub fn write_coalescing(mut self, max_delay: Duration, max_bytes: usize) -> Self {
+        self.write_coalescing = Some(WriteCoalescing::new(max_delay, max_bytes));
+        self
+    }
+
+    /// The maximum number of sessions that can be active on the connection at the same time
+    ///
+    /// This is independent of the `channel-max`, which only bounds the channel numbers. A channel
+    /// is reused once the End frame has been exchanged in both directions, and beginning a new
+    /// session when the limit is reached fails with
+    /// [`BeginError::LocalMaxSessionsReached`](crate::session::BeginError::LocalMaxSessionsReached)
+    pub fn max_sessions(mut self, max_sessions: usize) -> Self {
+        self.max_sessions = Some(max_sessions);
+        self
+    }
+
+    /// Follow up to `max_hops` `amqp:connection:redirect` errors when opening the connection
+    ///
+    /// `open` connects to the `network-host` and `port` of the redirect with the same scheme and
+    /// credentials, and uses the `hostname` of the redirect as the hostname and the TLS domain.
+    /// The redirect error is returned once `max_hops` redirects have been followed.
+    pub fn follow_redirects(mut self, max_hops: usize) -> Self {
+        self.max_redirects = max_hops;
+        self
+    }
+
+    /// Rewrites the frames with `redactor` before they are formatted in the trace and log events
+    /// of the connection, eg. to mask the credentials in the properties of the Open frame.
+    ///
+    /// The SASL frames always redact the initial response and the response regardless of this.
+    pub fn log_redactor(mut self, redactor: LogRedactor) -> Self {
+        self.log_redactor = Some(redactor);
+        self
+    }
+
+    /// Fail `open` with [`OpenError::UnknownUrlParam`] if the url has a query parameter that is
+    /// not a connection option, instead of logging a warning
+    ///
+    /// # Connection options in the url
+    ///
+    /// The query of the url may set the following connection options, which are only used if they
+    /// are not set on the builder.
+    ///
+    /// | Query parameter | Builder setting |
+    /// |-----------------|-----------------|
+    /// | `container_id` | [`container_id`](#structfield.container_id), if it is generated by `container_id_auto` |
+    /// | `idle_timeout_ms` | [`idle_time_out`](#method.idle_time_out) |
+    /// | `max_frame_size` | [`max_frame_size`](#method.max_frame_size) |
+    /// | `channel_max` | [`channel_max`](#method.channel_max) |
+    /// | `verify_peer` | `false` accepts any server certificate with the default `rustls` connector, which requires the `"dangerous-tls"` feature |
+    ///
+    /// Any other query parameter is ignored with a warning unless this is set.
+    ///
+    /// ```rust, ignore
+    /// let connection = Connection::builder()
+    ///     .container_id_auto()
+    ///     .open("amqp://localhost:5672?idle_timeout_ms=30000&max_frame_size=65536&container_id=cli-tool")
+    ///     .await.unwrap();
+    /// ```
+    pub fn strict_url_params(mut self, value: bool) -> Self {
+        self.strict_url_params = value;
+        self
+    }
+}
+
+cfg_not_wasm32! {
+    #[cfg(not(feature = "rt-async-std"))]
+    impl<'a, Mode, Tls> Builder<'a, Mode, Tls> {
+        /// Spawn the event loop of the connection on the runtime of `handle` instead of the
+        /// current runtime
+        ///
+        /// The sessions begun on the connection are spawned on the same runtime unless it is
+        /// overridden on the [session builder](crate::session::Builder).
+        pub fn spawn_on(mut self, handle: tokio::runtime::Handle) -> Self {
+            self.spawner = Spawner::Handle(handle);
+            self
+        }
+
+        /// Spawn the event loop of the connection with [`tokio::task::spawn_local`] if `value` is
+        /// true, which requires the connection to be opened within a [`tokio::task::LocalSet`]
+        ///
+        /// The sessions begun on the connection are spawned on the same `LocalSet` unless it is
+        /// overridden on the [session builder](crate::session::Builder).
+        pub fn spawn_local(mut self, value: bool) -> Self {
+            self.spawner = match value {
+                true => Spawner::Local,
+                false => Spawner::Current,
+            };
+            self
+        }
+    }
+
+    impl<'a, Mode, Tls> Builder<'a, Mode, Tls> {
+        /// Leaves the event loop in the returned slot instead of spawning it
+        fn defer(&mut self) -> Deferred {
+            let slot = Deferred::default();
+            self.spawner = Spawner::Deferred(slot.clone());
+            slot
+        }
+    }
+
+    fn take_deferred(slot: &Deferred) -> EngineFuture {
+        slot.lock()
+            .take()
+            .expect("The event loop is left in the slot once the connection is opened")
+    }
 }
 
 impl<'a, Tls> Builder<'a, mode::ConnectorWithId, Tls> {
@@ -575,7 +941,22 @@ impl<'a, Tls> Builder<'a, mode::ConnectorWithId, Tls> {
         &mut self,
         transport: &mut Transport<Io, sasl::Frame>,
         // hostname: Option<&str>,
+        profile: SaslProfile,
+    ) -> Result<(), NegotiationError>
+    where
+        Io: AsyncRead + AsyncWrite + std::fmt::Debug + Unpin + 'static,
+    {
+        self.negotiate_sasl_with_fallback(transport, profile, None)
+            .await
+    }
+
+    /// Performs SASL negotiation with `fallback` if the server does not offer the mechanism of
+    /// `profile`
+    async fn negotiate_sasl_with_fallback<Io>(
+        &mut self,
+        transport: &mut Transport<Io, sasl::Frame>,
         mut profile: SaslProfile,
+        mut fallback: Option<SaslProfile>,
     ) -> Result<(), NegotiationError>
     where
         Io: AsyncRead + AsyncWrite + std::fmt::Debug + Unpin + 'static,
@@ -590,6 +971,18 @@ impl<'a, Tls> Builder<'a, mode::ConnectorWithId, Tls> {
             #[cfg(feature = "log")]
             log::trace!("received = {:?}", frame);
 
+            if let sasl::Frame::Mechanisms(mechanisms) = &frame {
+                if !mechanisms
+                    .sasl_server_mechanisms
+                    .0
+                    .contains(&profile.mechanism())
+                {
+                    if let Some(fallback) = fallback.take() {
+                        profile = fallback;
+                    }
+                }
+            }
+
             match profile.on_frame(frame, self.hostname)? {
                 Negotiation::Init(init) => {
                     let frame = sasl::Frame::Init(init);
@@ -610,7 +1003,7 @@ impl<'a, Tls> Builder<'a, mode::ConnectorWithId, Tls> {
                 Negotiation::Outcome(outcome) => match outcome.code {
                     SaslCode::Ok => return Ok(()),
                     code => {
-                        return Err(NegotiationError::SaslError {
+                        return Err(NegotiationError::SaslOutcome {
                             code,
                             additional_data: outcome.additional_data,
                         })
@@ -624,6 +1017,14 @@ impl<'a, Tls> Builder<'a, mode::ConnectorWithId, Tls> {
         )))
     }
 
+    /// Checks the proposed max frame size, which is otherwise only rejected by the remote peer
+    fn validate_max_frame_size(&self) -> Result<(), OpenError> {
+        if self.max_frame_size.0 < MIN_MAX_FRAME_SIZE as u32 {
+            return Err(OpenError::MaxFrameSizeTooSmall(self.max_frame_size.0));
+        }
+        Ok(())
+    }
+
     async fn connect_with_stream<Io, F>(
         mut self,
         stream: Io,
@@ -637,14 +1038,20 @@ impl<'a, Tls> Builder<'a, mode::ConnectorWithId, Tls> {
             mpsc::Sender<SessionFrame>,
         ) -> Result<ConnectionHandle<()>, OpenError>,
     {
-        match self.sasl_profile.take() {
+        self.validate_max_frame_size()?;
+        let (profile, fallback) = match self.sasl_profile.take() {
+            Some(profile) => (Some(profile), None),
+            None => self.implied_sasl_profile(),
+        };
+        match profile {
             Some(profile) => {
                 let (reader, writer) = tokio::io::split(stream);
                 let framed_write = FramedWrite::new(writer, ProtocolHeaderCodec::new());
                 let framed_read = FramedRead::new(reader, ProtocolHeaderCodec::new());
                 let mut transport =
                     Transport::negotiate_sasl_header(framed_write, framed_read).await?;
-                self.negotiate_sasl(&mut transport, profile).await?;
+                self.negotiate_sasl_with_fallback(&mut transport, profile, fallback)
+                    .await?;
 
                 // NOTE: LengthDelimitedCodec itself doesn't seem to carry any buffer, so
                 // it should be fine to simply drop it.
@@ -660,6 +1067,18 @@ impl<'a, Tls> Builder<'a, mode::ConnectorWithId, Tls> {
         }
     }
 
+    /// The SASL profile and its fallback that are used if the SASL profile is not set
+    ///
+    /// SASL EXTERNAL is used if a TLS client certificate is presented by the default connector,
+    /// and ANONYMOUS is used instead if the server does not offer EXTERNAL
+    fn implied_sasl_profile(&self) -> (Option<SaslProfile>, Option<SaslProfile>) {
+        #[cfg(feature = "rustls")]
+        if self.scheme == "amqps" && self.rustls_options.client_auth.is_some() {
+            return (Some(SaslProfile::External), Some(SaslProfile::Anonymous));
+        }
+        (None, None)
+    }
+
     async fn connect_amqp_with_stream<Io, F>(
         self,
         stream: Io,
@@ -700,6 +1119,10 @@ impl<'a, Tls> Builder<'a, mode::ConnectorWithId, Tls> {
             .idle_time_out
             .map(|millis| Duration::from_millis(millis as u64));
         let buffer_size = self.buffer_size;
+        let write_coalescing = self.write_coalescing;
+        let heartbeat_policy = self.heartbeat_policy;
+        let max_sessions = self.max_sessions;
+        let log_redactor = self.log_redactor;
         let transport = Transport::negotiate_amqp_header(
             framed_write,
             framed_read,
@@ -713,20 +1136,121 @@ impl<'a, Tls> Builder<'a, mode::ConnectorWithId, Tls> {
         // Create channels
         let (control_tx, control_rx) = mpsc::channel(DEFAULT_CONTROL_CHAN_BUF);
         let (outgoing_tx, outgoing_rx) = mpsc::channel(buffer_size);
-        let connection = Connection::new(local_state, local_open);
-
-        let engine = ConnectionEngine::open(transport, connection, control_rx, outgoing_rx).await?;
+        let connection = Connection::new(local_state, local_open, max_sessions, log_redactor);
+
+        let engine = ConnectionEngine::open(
+            transport,
+            connection,
+            control_rx,
+            outgoing_rx,
+            heartbeat_policy,
+        )
+        .await?
+        .with_write_coalescing(write_coalescing);
         // Self::spawn_engine(engine, control_tx, outgoing_tx)
         (spawn_engine_fn)(engine, control_tx, outgoing_tx)
     }
 }
 
+cfg_not_wasm32! {
+    impl<'a, Mode, Tls> Builder<'a, Mode, Tls> {
+        /// The scheme and the SASL profile of the url override the builder fields, while the
+        /// host, the domain and the connection options in the query of the url are only used if
+        /// they are not set on the builder
+        fn apply_url(&mut self, url: &'a Url) -> Result<(), OpenError> {
+            self.scheme = url.scheme();
+            if self.hostname.is_none() {
+                self.hostname = url.host_str();
+            }
+            if self.domain.is_none() {
+                self.domain = url.domain();
+            }
+            if let Ok(profile) = SaslProfile::try_from(url) {
+                self.sasl_profile = Some(profile);
+            }
+            self.apply_url_params(UrlParams::parse(url)?)
+        }
+
+        /// A builder field is considered set if it differs from the default
+        fn apply_url_params(&mut self, params: UrlParams) -> Result<(), OpenError> {
+            for name in params.unknown {
+                if self.strict_url_params {
+                    return Err(OpenError::UnknownUrlParam(name));
+                }
+                #[cfg(feature = "tracing")]
+                tracing::warn!("Ignoring unknown query parameter {} in the url", name);
+                #[cfg(feature = "log")]
+                log::warn!("Ignoring unknown query parameter {} in the url", name);
+            }
+
+            if let Some(container_id) = params.container_id {
+                if self.container_id.is_empty() || self.generated_container_id {
+                    self.container_id = container_id;
+                    self.generated_container_id = false;
+                }
+            }
+            if let Some(idle_time_out) = params.idle_time_out {
+                self.idle_time_out.get_or_insert(idle_time_out);
+            }
+            if let Some(max_frame_size) = params.max_frame_size {
+                // A value assigned to the public field is kept as well unless it is the default
+                if !self.max_frame_size_set && self.max_frame_size.0 == DEFAULT_MAX_FRAME_SIZE {
+                    self.max_frame_size = MaxFrameSize(max_frame_size);
+                }
+            }
+            if let Some(channel_max) = params.channel_max {
+                if !self.channel_max_set && self.channel_max.0 == DEFAULT_CHANNEL_MAX {
+                    self.channel_max = ChannelMax(channel_max);
+                }
+            }
+            if params.verify_peer == Some(false) {
+                #[cfg(all(feature = "rustls", feature = "dangerous-tls"))]
+                {
+                    self.rustls_options.accept_invalid_certs = true;
+                }
+                #[cfg(not(all(feature = "rustls", feature = "dangerous-tls")))]
+                return Err(OpenError::InvalidUrlParam {
+                    name: String::from("verify_peer"),
+                    value: String::from("false"),
+                });
+            }
+            Ok(())
+        }
+    }
+
+    impl<'a, Mode: Clone, Tls: Clone> Builder<'a, Mode, Tls> {
+        /// Returns a copy of the builder that opens the connection at the container the remote
+        /// peer redirected to
+        fn redirected<'b>(&self, redirect: &'b Redirect) -> Builder<'b, Mode, Tls>
+        where
+            'a: 'b,
+        {
+            let hostname = redirect.hostname.as_deref().unwrap_or(&redirect.network_host);
+            let mut builder: Builder<'b, Mode, Tls> = self.clone();
+            builder.hostname = Some(hostname);
+            builder.domain = Some(hostname);
+            builder.sni_hostname = None;
+            builder
+        }
+    }
+
+    /// The url of the container the remote peer redirected to, which keeps the scheme and the
+    /// credentials of the original url
+    fn redirect_url(url: &Url, redirect: &Redirect) -> Result<Url, OpenError> {
+        let mut url = url.clone();
+        url.set_host(Some(&redirect.network_host))?;
+        url.set_port(Some(redirect.port))
+            .map_err(|_| OpenError::UrlError(url::ParseError::InvalidPort))?;
+        Ok(url)
+    }
+}
+
 /* -------------------------------------------------------------------------- */
 /*                                 Without TLS                                */
 /* -------------------------------------------------------------------------- */
 
 impl<'a> Builder<'a, mode::ConnectorWithId, ()> {
-    #[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+    #[cfg(feature = "rustls")]
     async fn connect_tls_with_rustls_default<Io, F>(
         self,
         stream: Io,
@@ -741,15 +1265,10 @@ impl<'a> Builder<'a, mode::ConnectorWithId, ()> {
             mpsc::Sender<SessionFrame>,
         ) -> Result<ConnectionHandle<()>, OpenError>,
     {
-        use librustls::{ClientConfig, RootCertStore};
         use std::sync::Arc;
         use tokio_rustls::TlsConnector;
 
-        let mut root_cert_store = RootCertStore::empty();
-        root_cert_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
-        let config = ClientConfig::builder()
-            .with_root_certificates(root_cert_store)
-            .with_no_client_auth();
+        let config = self.rustls_options.client_config()?;
         let connector = TlsConnector::from(Arc::new(config));
         let tls_stream =
             Transport::connect_tls_with_rustls(stream, domain, &connector, self.alt_tls_estab)
@@ -855,27 +1374,82 @@ cfg_not_wasm32! {
         ///     .await.unwrap();
         /// ```
         ///
+        /// The username and password in the url are percent-decoded.
+        ///
+        /// The query of the url may set connection options that are not set on the builder, see
+        /// [`strict_url_params`](#method.strict_url_params).
+        ///
         pub async fn open(
-            mut self,
+            self,
             url: impl TryInto<Url, Error = impl Into<OpenError>>,
         ) -> Result<ConnectionHandle<()>, OpenError> {
             let url = url.try_into().map_err(Into::into)?;
-
-            // Url info will override the builder fields
-            // only override if value exists
-            self.scheme = url.scheme();
-            if let Some(hostname) = url.host_str() {
-                self.hostname = Some(hostname);
-            }
-            if let Some(domain) = url.domain() {
-                self.domain = Some(domain);
+            if self.max_redirects == 0 {
+                return self.open_url(&url).await;
             }
-            if let Ok(profile) = SaslProfile::try_from(&url) {
-                self.sasl_profile = Some(profile);
+
+            let mut url = url;
+            let mut redirect: Option<Redirect> = None;
+            let mut hops = 0;
+            loop {
+                let builder = match &redirect {
+                    Some(redirect) => self.redirected(redirect),
+                    None => self.clone(),
+                };
+                match builder.open_url(&url).await {
+                    Err(error) if hops < self.max_redirects => match error.redirect() {
+                        Some(next) => {
+                            url = redirect_url(&url, &next)?;
+                            redirect = Some(next);
+                            hops += 1;
+                        }
+                        None => return Err(error),
+                    },
+                    result => return result,
+                }
             }
+        }
+
+        /// Opens the connection like [`open`](#method.open) but returns the event loop of the
+        /// connection instead of spawning it
+        ///
+        /// The caller must drive the returned future, eg. in a [`tokio::task::JoinSet`], for the
+        /// connection to make progress. Dropping the [`ConnectionHandle`] closes the connection
+        /// as usual while the future is being driven. See [`EngineFuture`] for the cancellation
+        /// semantics. The sessions begun on the connection are still spawned on the current
+        /// runtime unless they are begun with
+        /// [`begin_unspawned`](crate::session::Builder::begin_unspawned).
+        ///
+        /// # Example
+        ///
+        /// ```rust,ignore
+        /// let mut tasks = tokio::task::JoinSet::new();
+        /// let (mut connection, engine) = Connection::builder()
+        ///     .container_id("connection-1")
+        ///     .open_unspawned("amqp://localhost:5672")
+        ///     .await
+        ///     .unwrap();
+        /// tasks.spawn(engine);
+        ///
+        /// connection.close().await.unwrap();
+        /// tasks.join_next().await.unwrap().unwrap();
+        /// ```
+        pub async fn open_unspawned(
+            mut self,
+            url: impl TryInto<Url, Error = impl Into<OpenError>>,
+        ) -> Result<(ConnectionHandle<()>, EngineFuture), OpenError> {
+            let slot = self.defer();
+            let connection = self.open(url).await?;
+            Ok((connection, take_deferred(&slot)))
+        }
+
+        /// Opens the connection at the url without following redirects
+        async fn open_url(mut self, url: &'a Url) -> Result<ConnectionHandle<()>, OpenError> {
+            self.apply_url(url)?;
+            self.validate_max_frame_size()?;
 
             let addr = url.socket_addrs(|| default_port(url.scheme()))?;
-            let stream = TcpStream::connect(&*addr).await?; // std::io::Error
+            let stream = crate::rt::connect(&addr).await?; // std::io::Error
 
             self.open_with_stream(stream).await
         }
@@ -922,14 +1496,17 @@ cfg_not_wasm32! {
         where
             Io: AsyncRead + AsyncWrite + std::fmt::Debug + SendBound + Unpin + 'static,
         {
+            let spawner = self.spawner.clone();
             match self.scheme {
-                "amqp" => self.connect_with_stream(stream, spawn_engine).await,
+                "amqp" => self.connect_with_stream(stream, spawn_engine(spawner)).await,
                 "amqps" => {
-                    #[cfg(all(feature = "rustls", not(feature = "native-tls")))]
-                    {
-                        let domain = self.domain.ok_or(OpenError::InvalidDomain)?;
+                    // The default rustls connector is also used with "native-tls" enabled if any
+                    // of its options is set
+                    #[cfg(feature = "rustls")]
+                    if cfg!(not(feature = "native-tls")) || self.rustls_options.is_set() {
+                        let domain = self.tls_server_name()?;
                         return self
-                            .connect_tls_with_rustls_default(stream, domain, spawn_engine)
+                            .connect_tls_with_rustls_default(stream, domain, spawn_engine(spawner))
                             .await;
                     }
 
@@ -939,9 +1516,9 @@ cfg_not_wasm32! {
                         not(target_arch = "wasm32")
                     ))]
                     {
-                        let domain = self.domain.ok_or_else(|| OpenError::InvalidDomain)?;
+                        let domain = self.tls_server_name()?;
                         return self
-                            .connect_tls_with_native_tls_default(stream, domain, spawn_engine)
+                            .connect_tls_with_native_tls_default(stream, domain, spawn_engine(spawner))
                             .await;
                     }
 
@@ -974,7 +1551,7 @@ cfg_wasm32! {
                 "amqps" => {
                     #[cfg(all(feature = "rustls", not(feature = "native-tls")))]
                     {
-                        let domain = self.domain.ok_or(OpenError::InvalidDomain)?;
+                        let domain = self.tls_server_name()?;
                         let spawn_engine_fn = |engine, control_tx, outgoing_tx| {
                             spawn_engine_on_current_local_set(engine, control_tx, outgoing_tx)
                         };
@@ -989,7 +1566,7 @@ cfg_wasm32! {
                         not(target_arch = "wasm32")
                     ))]
                     {
-                        let domain = self.domain.ok_or_else(|| OpenError::InvalidDomain)?;
+                        let domain = self.tls_server_name()?;
                         return self
                             .connect_tls_with_native_tls_default(stream, domain, spawn_engine)
                             .await;
@@ -1021,7 +1598,7 @@ cfg_wasm32! {
                 "amqps" => {
                     #[cfg(all(feature = "rustls", not(feature = "native-tls")))]
                     {
-                        let domain = self.domain.ok_or(OpenError::InvalidDomain)?;
+                        let domain = self.tls_server_name()?;
                         let spawn_engine_fn = |engine, control_tx, outgoing_tx| {
                             spawn_engine_on_local_set(engine, control_tx, outgoing_tx, local_set)
                         };
@@ -1036,7 +1613,7 @@ cfg_wasm32! {
                         not(target_arch = "wasm32")
                     ))]
                     {
-                        let domain = self.domain.ok_or_else(|| OpenError::InvalidDomain)?;
+                        let domain = self.tls_server_name()?;
                         return self
                             .connect_tls_with_native_tls_default(stream, domain, spawn_engine)
                             .await;
@@ -1123,27 +1700,81 @@ cfg_not_wasm32! {
             ///     .await.unwrap();
             /// ```
             ///
+            /// The username and password in the url are percent-decoded.
+            ///
+            /// The query of the url may set connection options that are not set on the builder, see
+            /// [`strict_url_params`](#method.strict_url_params).
+            ///
             pub async fn open(
-                mut self,
+                self,
                 url: impl TryInto<Url, Error = impl Into<OpenError>>,
             ) -> Result<ConnectionHandle<()>, OpenError> {
                 let url = url.try_into().map_err(Into::into)?;
-
-                // Url info will override the builder fields
-                // only override if value exists
-                self.scheme = url.scheme();
-                if let Some(hostname) = url.host_str() {
-                    self.hostname = Some(hostname);
-                }
-                if let Some(domain) = url.domain() {
-                    self.domain = Some(domain);
+                if self.max_redirects == 0 {
+                    return self.open_url(&url).await;
                 }
-                if let Ok(profile) = SaslProfile::try_from(&url) {
-                    self.sasl_profile = Some(profile);
+
+                let mut url = url;
+                let mut redirect: Option<Redirect> = None;
+                let mut hops = 0;
+                loop {
+                    let builder = match &redirect {
+                        Some(redirect) => self.redirected(redirect),
+                        None => self.clone(),
+                    };
+                    match builder.open_url(&url).await {
+                        Err(error) if hops < self.max_redirects => match error.redirect() {
+                            Some(next) => {
+                                url = redirect_url(&url, &next)?;
+                                redirect = Some(next);
+                                hops += 1;
+                            }
+                            None => return Err(error),
+                        },
+                        result => return result,
+                    }
                 }
+            }
+
+            /// Opens the connection like [`open`](#method.open) but returns the event loop of the
+            /// connection instead of spawning it
+            ///
+            /// The caller must drive the returned future, eg. in a [`tokio::task::JoinSet`], for the
+            /// connection to make progress. Dropping the [`ConnectionHandle`] closes the connection
+            /// as usual while the future is being driven. See [`EngineFuture`] for the cancellation
+            /// semantics. The sessions begun on the connection are still spawned on the current
+            /// runtime unless they are begun with
+            /// [`begin_unspawned`](crate::session::Builder::begin_unspawned).
+            ///
+            /// # Example
+            ///
+            /// ```rust,ignore
+            /// let mut tasks = tokio::task::JoinSet::new();
+            /// let (mut connection, engine) = Connection::builder()
+            ///     .container_id("connection-1")
+            ///     .open_unspawned("amqp://localhost:5672")
+            ///     .await
+            ///     .unwrap();
+            /// tasks.spawn(engine);
+            ///
+            /// connection.close().await.unwrap();
+            /// tasks.join_next().await.unwrap().unwrap();
+            /// ```
+            pub async fn open_unspawned(
+                mut self,
+                url: impl TryInto<Url, Error = impl Into<OpenError>>,
+            ) -> Result<(ConnectionHandle<()>, EngineFuture), OpenError> {
+                let slot = self.defer();
+                let connection = self.open(url).await?;
+                Ok((connection, take_deferred(&slot)))
+            }
+
+            /// Opens the connection at the url without following redirects
+            async fn open_url(mut self, url: &'a Url) -> Result<ConnectionHandle<()>, OpenError> {
+                self.apply_url(url)?;
 
                 let addr = url.socket_addrs(|| default_port(url.scheme()))?;
-                let stream = TcpStream::connect(&*addr).await?; // std::io::Error
+                let stream = crate::rt::connect(&addr).await?; // std::io::Error
 
                 self.open_with_stream(stream).await
             }
@@ -1159,10 +1790,11 @@ cfg_not_wasm32! {
             where
                 Io: AsyncRead + AsyncWrite + std::fmt::Debug + SendBound + Unpin + 'static,
             {
+                let spawner = self.spawner.clone();
                 match self.scheme {
-                    "amqp" => self.connect_with_stream(stream, spawn_engine).await,
+                    "amqp" => self.connect_with_stream(stream, spawn_engine(spawner)).await,
                     "amqps" => {
-                        let domain = self.domain.ok_or(OpenError::InvalidDomain)?;
+                        let domain = self.tls_server_name()?;
                         let tls_stream = Transport::connect_tls_with_rustls(
                             stream,
                             domain,
@@ -1170,7 +1802,7 @@ cfg_not_wasm32! {
                             self.alt_tls_estab,
                         )
                         .await?;
-                        self.connect_with_stream(tls_stream, spawn_engine).await
+                        self.connect_with_stream(tls_stream, spawn_engine(spawner)).await
                     }
                     _ => Err(OpenError::InvalidScheme),
                 }
@@ -1251,27 +1883,81 @@ cfg_not_wasm32! {
             ///     .await.unwrap();
             /// ```
             ///
+            /// The username and password in the url are percent-decoded.
+            ///
+            /// The query of the url may set connection options that are not set on the builder, see
+            /// [`strict_url_params`](#method.strict_url_params).
+            ///
             pub async fn open(
-                mut self,
+                self,
                 url: impl TryInto<Url, Error = impl Into<OpenError>>,
             ) -> Result<ConnectionHandle<()>, OpenError> {
                 let url = url.try_into().map_err(Into::into)?;
-
-                // Url info will override the builder fields
-                // only override if value exists
-                self.scheme = url.scheme();
-                if let Some(hostname) = url.host_str() {
-                    self.hostname = Some(hostname);
-                }
-                if let Some(domain) = url.domain() {
-                    self.domain = Some(domain);
+                if self.max_redirects == 0 {
+                    return self.open_url(&url).await;
                 }
-                if let Ok(profile) = SaslProfile::try_from(&url) {
-                    self.sasl_profile = Some(profile);
+
+                let mut url = url;
+                let mut redirect: Option<Redirect> = None;
+                let mut hops = 0;
+                loop {
+                    let builder = match &redirect {
+                        Some(redirect) => self.redirected(redirect),
+                        None => self.clone(),
+                    };
+                    match builder.open_url(&url).await {
+                        Err(error) if hops < self.max_redirects => match error.redirect() {
+                            Some(next) => {
+                                url = redirect_url(&url, &next)?;
+                                redirect = Some(next);
+                                hops += 1;
+                            }
+                            None => return Err(error),
+                        },
+                        result => return result,
+                    }
                 }
+            }
+
+            /// Opens the connection like [`open`](#method.open) but returns the event loop of the
+            /// connection instead of spawning it
+            ///
+            /// The caller must drive the returned future, eg. in a [`tokio::task::JoinSet`], for the
+            /// connection to make progress. Dropping the [`ConnectionHandle`] closes the connection
+            /// as usual while the future is being driven. See [`EngineFuture`] for the cancellation
+            /// semantics. The sessions begun on the connection are still spawned on the current
+            /// runtime unless they are begun with
+            /// [`begin_unspawned`](crate::session::Builder::begin_unspawned).
+            ///
+            /// # Example
+            ///
+            /// ```rust,ignore
+            /// let mut tasks = tokio::task::JoinSet::new();
+            /// let (mut connection, engine) = Connection::builder()
+            ///     .container_id("connection-1")
+            ///     .open_unspawned("amqp://localhost:5672")
+            ///     .await
+            ///     .unwrap();
+            /// tasks.spawn(engine);
+            ///
+            /// connection.close().await.unwrap();
+            /// tasks.join_next().await.unwrap().unwrap();
+            /// ```
+            pub async fn open_unspawned(
+                mut self,
+                url: impl TryInto<Url, Error = impl Into<OpenError>>,
+            ) -> Result<(ConnectionHandle<()>, EngineFuture), OpenError> {
+                let slot = self.defer();
+                let connection = self.open(url).await?;
+                Ok((connection, take_deferred(&slot)))
+            }
+
+            /// Opens the connection at the url without following redirects
+            async fn open_url(mut self, url: &'a Url) -> Result<ConnectionHandle<()>, OpenError> {
+                self.apply_url(url)?;
 
                 let addr = url.socket_addrs(|| default_port(url.scheme()))?;
-                let stream = TcpStream::connect(&*addr).await?; // std::io::Error
+                let stream = crate::rt::connect(&addr).await?; // std::io::Error
 
                 self.open_with_stream(stream).await
             }
@@ -1287,10 +1973,11 @@ cfg_not_wasm32! {
             where
                 Io: AsyncRead + AsyncWrite + std::fmt::Debug + SendBound + Unpin + 'static,
             {
+                let spawner = self.spawner.clone();
                 match self.scheme {
-                    "amqp" => self.connect_with_stream(stream, spawn_engine).await,
+                    "amqp" => self.connect_with_stream(stream, spawn_engine(spawner)).await,
                     "amqps" => {
-                        let domain = self.domain.ok_or(OpenError::InvalidDomain)?;
+                        let domain = self.tls_server_name()?;
                         let tls_stream = Transport::connect_tls_with_native_tls(
                             stream,
                             domain,
@@ -1298,7 +1985,7 @@ cfg_not_wasm32! {
                             self.alt_tls_estab,
                         )
                         .await?;
-                        self.connect_with_stream(tls_stream, spawn_engine).await
+                        self.connect_with_stream(tls_stream, spawn_engine(spawner)).await
                     }
                     _ => Err(OpenError::InvalidScheme),
                 }
@@ -1308,23 +1995,66 @@ cfg_not_wasm32! {
 }
 
 cfg_not_wasm32! {
-    fn spawn_engine<Io>(
+    type SpawnEngineFn<Io> = Box<
+        dyn FnOnce(
+            ConnectionEngine<Io, Connection>,
+            mpsc::Sender<ConnectionControl>,
+            mpsc::Sender<SessionFrame>,
+        ) -> Result<ConnectionHandle<()>, OpenError>
+            + Send,
+    >;
+
+    fn spawn_engine<Io>(spawner: Spawner) -> SpawnEngineFn<Io>
+    where
+        Io: AsyncRead + AsyncWrite + std::fmt::Debug + Send + Unpin + 'static,
+    {
+        Box::new(move |engine, control_tx, outgoing_tx| {
+            spawn_engine_with(engine, control_tx, outgoing_tx, spawner)
+        })
+    }
+
+    fn spawn_engine_with<Io>(
         engine: ConnectionEngine<Io, Connection>,
         control_tx: mpsc::Sender<ConnectionControl>,
         outgoing_tx: mpsc::Sender<SessionFrame>,
+        spawner: Spawner,
     ) -> Result<ConnectionHandle<()>, OpenError>
     where
         Io: AsyncRead + AsyncWrite + std::fmt::Debug + Send + Unpin + 'static,
     {
-        let (handle, outcome) = engine.spawn();
+        let remote_open = engine
+            .remote_open()
+            .cloned()
+            .ok_or(OpenError::IllegalState)?;
+        let container_id = engine.container_id().to_string();
+        let max_frame_size = engine.max_frame_size();
+        let active_sessions = engine.active_sessions();
+        let frame_activity = engine.frame_activity();
+        let events = engine.events();
+        let local_idle_timeout = engine.local_idle_timeout();
+        let heartbeat_interval = engine.heartbeat_interval();
+        let (handle, outcome) = engine.spawn(&spawner);
 
         let connection_handle = ConnectionHandle {
             is_closed: false,
             control: control_tx,
             handle,
             outcome,
+            // The sessions are spawned unless they are begun unspawned themselves
+            spawner: match spawner {
+                Spawner::Deferred(_) => Spawner::default(),
+                spawner => spawner,
+            },
             outgoing: outgoing_tx, // session_control: session_control_tx
             session_listener: (),
+            container_id,
+            remote_open,
+            max_frame_size,
+            active_sessions,
+            frame_activity,
+            events,
+            local_idle_timeout,
+            heartbeat_interval,
         };
 
         Ok(connection_handle)
@@ -1341,15 +2071,34 @@ cfg_wasm32! {
     where
         Io: AsyncRead + AsyncWrite + std::fmt::Debug + Unpin + 'static,
     {
+        let remote_open = engine
+            .remote_open()
+            .cloned()
+            .ok_or(OpenError::IllegalState)?;
+        let container_id = engine.container_id().to_string();
+        let max_frame_size = engine.max_frame_size();
+        let active_sessions = engine.active_sessions();
+        let frame_activity = engine.frame_activity();
+        let events = engine.events();
+        let local_idle_timeout = engine.local_idle_timeout();
+        let heartbeat_interval = engine.heartbeat_interval();
         let (handle, outcome) = engine.spawn_on_local_set(local_set);
 
         let connection_handle = ConnectionHandle {
             is_closed: false,
             control: control_tx,
-            handle,
+            handle: Some(handle),
             outcome,
             outgoing: outgoing_tx, // session_control: session_control_tx
             session_listener: (),
+            container_id,
+            remote_open,
+            max_frame_size,
+            active_sessions,
+            frame_activity,
+            events,
+            local_idle_timeout,
+            heartbeat_interval,
         };
 
         Ok(connection_handle)
@@ -1363,15 +2112,34 @@ cfg_wasm32! {
     where
         Io: AsyncRead + AsyncWrite + std::fmt::Debug + Unpin + 'static,
     {
+        let remote_open = engine
+            .remote_open()
+            .cloned()
+            .ok_or(OpenError::IllegalState)?;
+        let container_id = engine.container_id().to_string();
+        let max_frame_size = engine.max_frame_size();
+        let active_sessions = engine.active_sessions();
+        let frame_activity = engine.frame_activity();
+        let events = engine.events();
+        let local_idle_timeout = engine.local_idle_timeout();
+        let heartbeat_interval = engine.heartbeat_interval();
         let (handle, outcome) = engine.spawn_local();
 
         let connection_handle = ConnectionHandle {
             is_closed: false,
             control: control_tx,
-            handle,
+            handle: Some(handle),
             outcome,
             outgoing: outgoing_tx, // session_control: session_control_tx
             session_listener: (),
+            container_id,
+            remote_open,
+            max_frame_size,
+            active_sessions,
+            frame_activity,
+            events,
+            local_idle_timeout,
+            heartbeat_interval,
         };
 
         Ok(connection_handle)
@@ -1380,12 +2148,243 @@ cfg_wasm32! {
 
 #[cfg(test)]
 mod tests {
+    use fe2o3_amqp_types::performatives::Open;
     use url::Url;
 
+    use crate::Connection;
+
     #[test]
     fn test_url_name_resolution() {
         let url: Url = "amqp://example.net/".try_into().unwrap();
         assert_eq!(url.port(), None);
         let _addrs = url.socket_addrs(|| Some(5672)).unwrap();
     }
+
+    #[test]
+    fn container_id_auto_is_generated() {
+        let builder = Connection::builder().container_id_auto();
+        assert!(builder.container_id.starts_with("fe2o3-"));
+
+        let other = Connection::builder().container_id_auto();
+        assert_ne!(builder.container_id, other.container_id);
+    }
+
+    #[test]
+    fn open_hostname_falls_back_to_url_host() {
+        let url: Url = "amqps://broker.example.net:5671".try_into().unwrap();
+        let mut builder = Connection::builder().container_id("connection-1");
+        builder.apply_url(&url).unwrap();
+
+        assert_eq!(builder.domain, Some("broker.example.net"));
+        let open = Open::from(builder);
+        assert_eq!(open.hostname.as_deref(), Some("broker.example.net"));
+    }
+
+    #[test]
+    fn open_hostname_override_takes_precedence_over_url() {
+        let url: Url = "amqps://broker.example.net:5671".try_into().unwrap();
+        let mut builder = Connection::builder()
+            .container_id("connection-1")
+            .hostname("vhost-1")
+            .sni_hostname("sni.example.net");
+        builder.apply_url(&url).unwrap();
+
+        assert_eq!(builder.scheme, "amqps");
+        assert_eq!(builder.domain, Some("broker.example.net"));
+        assert_eq!(builder.sni_hostname, Some("sni.example.net"));
+        let open = Open::from(builder);
+        assert_eq!(open.hostname.as_deref(), Some("vhost-1"));
+    }
+
+    #[cfg(any(feature = "rustls", feature = "native-tls"))]
+    #[test]
+    fn tls_server_name_precedence() {
+        let url: Url = "amqps://broker.example.net:5671".try_into().unwrap();
+
+        let mut builder = Connection::builder().container_id("connection-1");
+        builder.apply_url(&url).unwrap();
+        assert_eq!(builder.tls_server_name().unwrap(), "broker.example.net");
+
+        let mut builder = Connection::builder()
+            .container_id("connection-1")
+            .domain("domain.example.net");
+        builder.apply_url(&url).unwrap();
+        assert_eq!(builder.tls_server_name().unwrap(), "domain.example.net");
+
+        let mut builder = Connection::builder()
+            .container_id("connection-1")
+            .hostname("vhost-1")
+            .domain("domain.example.net")
+            .sni_hostname("sni.example.net");
+        builder.apply_url(&url).unwrap();
+        assert_eq!(builder.tls_server_name().unwrap(), "sni.example.net");
+    }
+
+    #[cfg(all(feature = "rustls", not(feature = "native-tls")))]
+    #[tokio::test]
+    async fn sni_hostname_is_sent_in_client_hello() {
+        use tokio::{
+            io::{AsyncReadExt, AsyncWriteExt},
+            net::TcpListener,
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut header = [0u8; 8];
+            stream.read_exact(&mut header).await.unwrap();
+            stream.write_all(&header).await.unwrap();
+
+            let acceptor = tokio_rustls::LazyConfigAcceptor::new(
+                librustls::server::Acceptor::default(),
+                stream,
+            );
+            let start = acceptor.await.unwrap();
+            start.client_hello().server_name().map(ToString::to_string)
+        });
+
+        let url = format!("amqps://{}", addr);
+        let result = Connection::builder()
+            .container_id("connection-1")
+            .hostname("vhost-1")
+            .sni_hostname("sni.example.net")
+            .open(&url[..])
+            .await;
+        assert!(result.is_err());
+
+        let server_name = server.await.unwrap();
+        assert_eq!(server_name.as_deref(), Some("sni.example.net"));
+    }
+
+    /// Accepts one connection, offers `mechanisms` and answers the SASL init with `outcome`
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn mock_sasl_server(
+        mechanisms: Vec<&'static str>,
+        outcome: fe2o3_amqp_types::sasl::SaslOutcome,
+    ) -> std::net::SocketAddr {
+        use fe2o3_amqp_types::{primitives::Array, sasl::SaslMechanisms};
+        use futures_util::{SinkExt, StreamExt};
+        use tokio::{
+            io::{AsyncReadExt, AsyncWriteExt},
+            net::TcpListener,
+        };
+
+        use crate::{frames::sasl, transport::Transport};
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut header = [0u8; 8];
+            stream.read_exact(&mut header).await.unwrap();
+            stream.write_all(&header).await.unwrap();
+
+            let mut transport = Transport::<_, sasl::Frame>::bind(stream, 512, None);
+            let mechanisms = SaslMechanisms {
+                sasl_server_mechanisms: Array::from(
+                    mechanisms.into_iter().map(Into::into).collect::<Vec<_>>(),
+                ),
+            };
+            transport
+                .send(sasl::Frame::Mechanisms(mechanisms))
+                .await
+                .unwrap();
+            if let Some(Ok(sasl::Frame::Init(_))) = transport.next().await {
+                transport.send(sasl::Frame::Outcome(outcome)).await.unwrap();
+            }
+        });
+
+        addr
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn sasl_outcome_code_is_returned_in_open_error() {
+        use fe2o3_amqp_types::{
+            primitives::Binary,
+            sasl::{SaslCode, SaslOutcome},
+        };
+
+        use crate::{connection::OpenError, sasl_profile::SaslProfile};
+
+        for code in [
+            SaslCode::Auth,
+            SaslCode::Sys,
+            SaslCode::SysPerm,
+            SaslCode::SysTemp,
+        ] {
+            let additional_data = Some(Binary::from(b"try again later".to_vec()));
+            let outcome = SaslOutcome {
+                code: code.clone(),
+                additional_data: additional_data.clone(),
+            };
+            let addr = mock_sasl_server(vec!["PLAIN"], outcome).await;
+
+            let url = format!("amqp://{}", addr);
+            let profile = SaslProfile::Plain {
+                username: "user".to_string(),
+                password: "password".to_string(),
+            };
+            let result = Connection::builder()
+                .container_id("connection-1")
+                .sasl_profile(profile)
+                .open(&url[..])
+                .await;
+
+            match result {
+                Err(OpenError::SaslOutcome {
+                    code: returned_code,
+                    additional_data: returned_data,
+                }) => {
+                    assert_eq!(returned_code, code);
+                    assert_eq!(returned_data, additional_data);
+                }
+                other => panic!("Expecting OpenError::SaslOutcome, found {:?}", other),
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn sasl_mechanisms_offered_by_server_are_returned_on_mismatch() {
+        use fe2o3_amqp_types::{
+            primitives::Symbol,
+            sasl::{SaslCode, SaslOutcome},
+        };
+
+        use crate::{connection::OpenError, sasl_profile::SaslProfile};
+
+        let outcome = SaslOutcome {
+            code: SaslCode::Auth,
+            additional_data: None,
+        };
+        let addr = mock_sasl_server(vec!["EXTERNAL", "ANONYMOUS"], outcome).await;
+
+        let url = format!("amqp://{}", addr);
+        let profile = SaslProfile::Plain {
+            username: "user".to_string(),
+            password: "password".to_string(),
+        };
+        let result = Connection::builder()
+            .container_id("connection-1")
+            .sasl_profile(profile)
+            .open(&url[..])
+            .await;
+
+        match result {
+            Err(OpenError::SaslMechanismMismatch { offered }) => {
+                assert_eq!(
+                    offered,
+                    vec![Symbol::from("EXTERNAL"), Symbol::from("ANONYMOUS")]
+                );
+            }
+            other => panic!(
+                "Expecting OpenError::SaslMechanismMismatch, found {:?}",
+                other
+            ),
+        }
+    }
 }
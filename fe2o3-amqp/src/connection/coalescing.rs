@@ -0,0 +1,119 @@
+//! Coalescing of outgoing Transfer frames into fewer writes to the transport
+
+use std::time::Duration;
+
+use crate::util::IdleTimeout;
+
+/// Buffers the encoded Transfer frames of a connection and writes them to the transport together.
+///
+/// The buffer is written once `max_delay` has passed since the first Transfer frame was buffered,
+/// once it holds at least `max_bytes`, or when any other performative is sent. Control frames
+/// (eg. Flow, Disposition and Detach) are therefore never delayed, and the frames are written in
+/// the order they are sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteCoalescing {
+    /// The longest time a buffered Transfer frame waits before it is written
+    pub max_delay: Duration,
+
+    /// The number of buffered bytes at which the buffer is written without waiting for
+    /// `max_delay`
+    pub max_bytes: usize,
+}
+
+impl WriteCoalescing {
+    /// Creates a new [`WriteCoalescing`]
+    pub fn new(max_delay: Duration, max_bytes: usize) -> Self {
+        Self {
+            max_delay,
+            max_bytes,
+        }
+    }
+}
+
+/// Transfer frames that are buffered by the connection engine but not yet written
+#[derive(Debug)]
+pub(crate) struct PendingWrites {
+    max_bytes: usize,
+    delay: IdleTimeout,
+
+    /// Whether the delay is started for the buffered frames
+    armed: bool,
+}
+
+impl PendingWrites {
+    pub(crate) fn new(coalescing: WriteCoalescing) -> Self {
+        Self {
+            max_bytes: coalescing.max_bytes,
+            delay: IdleTimeout::new(coalescing.max_delay),
+            armed: false,
+        }
+    }
+
+    pub(crate) fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    pub(crate) fn is_armed(&self) -> bool {
+        self.armed
+    }
+
+    /// Starts the delay if this is the first frame buffered since the last write
+    pub(crate) fn on_buffered(&mut self) {
+        if !self.armed {
+            self.delay.reset();
+            self.armed = true;
+        }
+    }
+
+    pub(crate) fn on_written(&mut self) {
+        self.armed = false;
+    }
+
+    /// Waits until the buffered frames need to be written
+    ///
+    /// # Cancel safety
+    ///
+    /// This is cancel safe
+    pub(crate) async fn expired(&mut self) {
+        let _ = (&mut self.delay).await;
+    }
+}
+
+/// Waits until the buffered frames need to be written. This never resolves if the writes are not
+/// coalesced
+///
+/// # Cancel safety
+///
+/// This is cancel safe
+pub(crate) async fn expired(pending_writes: &mut Option<PendingWrites>) {
+    match pending_writes {
+        Some(pending_writes) => pending_writes.expired().await,
+        None => futures_util::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{PendingWrites, WriteCoalescing};
+
+    #[tokio::test]
+    async fn delay_starts_from_first_buffered_frame() {
+        let coalescing = WriteCoalescing::new(Duration::from_millis(100), 1024);
+        let mut pending = PendingWrites::new(coalescing);
+        assert!(!pending.is_armed());
+
+        pending.on_buffered();
+        assert!(pending.is_armed());
+        tokio::time::sleep(Duration::from_millis(60)).await;
+
+        // Frames buffered later do not push the write back
+        pending.on_buffered();
+        let expired = tokio::time::timeout(Duration::from_millis(80), pending.expired()).await;
+        assert!(expired.is_ok());
+
+        pending.on_written();
+        assert!(!pending.is_armed());
+    }
+}
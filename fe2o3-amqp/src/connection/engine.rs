@@ -1,27 +1,52 @@
 //! The engine handles incoming and outgoing frames and messages to reduce
 //! transferring frames/messages over channels
 
+#[cfg(any(feature = "tracing", feature = "log"))]
+use std::borrow::Cow;
 use std::io;
+use std::sync::atomic::AtomicUsize;
+use std::sync::Arc;
 use std::time::Duration;
 
-use fe2o3_amqp_types::definitions::{self, AmqpError};
-use fe2o3_amqp_types::performatives::Close;
+use fe2o3_amqp_types::definitions::{self, AmqpError, ConnectionError, Milliseconds};
+use fe2o3_amqp_types::performatives::{Close, Open};
+use fe2o3_amqp_types::primitives::{Symbol, Value};
 use futures_util::{SinkExt, StreamExt};
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::sync::mpsc::Receiver;
-use tokio::sync::oneshot;
-use tokio::task::JoinHandle;
+use tokio::sync::{broadcast, oneshot};
 
 use crate::control::ConnectionControl;
 use crate::endpoint::{IncomingChannel, OutgoingChannel};
-use crate::frames::amqp::{self, Frame, FrameBody};
-use crate::session::frame::{SessionFrame, SessionFrameBody};
-use crate::transport::Transport;
+#[cfg(any(feature = "tracing", feature = "log"))]
+use crate::frames::amqp::Redacted;
+use crate::frames::amqp::{self, Frame, FrameBody, InvalidFrame, InvalidFrameKind};
+use crate::rt::JoinHandle;
+use crate::session::frame::{SessionFrame, SessionFrameBody, SessionIncomingItem};
+use crate::transport::{FrameActivity, Transport};
 use crate::util::Running;
 use crate::{endpoint, transport, SendBound};
 
-use super::{heartbeat::HeartBeat, ConnectionState};
-use super::{AllocSessionError, ConnectionInnerError, ConnectionStateError, Error, OpenError};
+use super::coalescing::{self, PendingWrites, WriteCoalescing};
+use super::heartbeat::{HeartBeat, HeartbeatPolicy};
+use super::ConnectionState;
+use super::{
+    AllocSessionError, ConnectionEvent, ConnectionInnerError, ConnectionStateError, Error,
+    OpenError, CONNECTION_ESTABLISHMENT_FAILED, EVENTS_CAPACITY,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use super::DROPPED_HANDLE_GRACE;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::rt::{timeout, Spawner};
+
+fn is_establishment_failed(open: &Open) -> bool {
+    open.properties
+        .as_ref()
+        .and_then(|properties| properties.get(&Symbol::from(CONNECTION_ESTABLISHMENT_FAILED)))
+        .map(|value| matches!(value, Value::Bool(true)))
+        .unwrap_or(false)
+}
 
 #[derive(Debug)]
 pub(crate) struct ConnectionEngine<Io, C> {
@@ -30,6 +55,56 @@ pub(crate) struct ConnectionEngine<Io, C> {
     control: Receiver<ConnectionControl>,
     outgoing_session_frames: Receiver<SessionFrame>,
     heartbeat: HeartBeat,
+    heartbeat_policy: HeartbeatPolicy,
+    heartbeat_interval: Option<Duration>,
+    pending_writes: Option<PendingWrites>,
+    events: broadcast::Sender<ConnectionEvent>,
+}
+
+impl<Io> ConnectionEngine<Io, super::Connection> {
+    /// The number of active sessions shared with the connection handle
+    pub(crate) fn active_sessions(&self) -> Arc<AtomicUsize> {
+        self.connection.active_sessions.clone()
+    }
+}
+
+impl<Io, C> ConnectionEngine<Io, C> {
+    /// The instants of the last frames shared with the connection handle
+    pub(crate) fn frame_activity(&self) -> Arc<FrameActivity> {
+        self.transport.frame_activity()
+    }
+
+    /// The sender of the events shared with the connection handle, which subscribes to it
+    pub(crate) fn events(&self) -> broadcast::Sender<ConnectionEvent> {
+        self.events.clone()
+    }
+
+    /// The interval at which empty frames are sent, or `None` if the heartbeat is disabled
+    pub(crate) fn heartbeat_interval(&self) -> Option<Duration> {
+        self.heartbeat_interval
+    }
+
+    /// Sets the heartbeat according to the idle time-out of the remote Open
+    fn set_heartbeat(
+        &mut self,
+        remote_idle_timeout: Option<Milliseconds>,
+    ) -> Result<(), OpenError> {
+        let remote_idle_timeout =
+            remote_idle_timeout.map(|millis| Duration::from_millis(millis as u64));
+        let policy = self.heartbeat_policy;
+        let interval = policy
+            .interval(remote_idle_timeout)
+            .map_err(|remote_idle_timeout| OpenError::IdleTimeoutUnsatisfiable {
+                remote_idle_timeout,
+                policy,
+            })?;
+        self.heartbeat = match interval {
+            Some(period) => HeartBeat::new(period),
+            None => HeartBeat::never(),
+        };
+        self.heartbeat_interval = interval;
+        Ok(())
+    }
 }
 
 cfg_not_wasm32! {
@@ -44,9 +119,12 @@ cfg_not_wasm32! {
         ConnectionStateError: From<C::OpenError> + From<C::CloseError>,
         OpenError: From<C::OpenError>,
     {
-        pub fn spawn(self) -> (JoinHandle<()>, oneshot::Receiver<Result<(), Error>>) {
+        pub fn spawn(
+            self,
+            spawner: &Spawner,
+        ) -> (Option<JoinHandle<()>>, oneshot::Receiver<Result<(), Error>>) {
             let (tx, rx) = oneshot::channel();
-            let handle = tokio::spawn(self.event_loop(tx));
+            let handle = spawner.spawn(self.event_loop(tx));
             (handle, rx)
         }
     }
@@ -140,12 +218,17 @@ where
         discard_other: bool,
     ) -> Result<(IncomingChannel, Close), ConnectionInnerError> {
         loop {
-            let frame = self.transport.next().await.ok_or_else(|| {
+            let frame = match self.transport.next().await.ok_or_else(|| {
                 transport::Error::Io(io::Error::new(
                     io::ErrorKind::UnexpectedEof,
                     "Expecting remote close",
                 ))
-            })??;
+            })? {
+                Ok(frame) => frame,
+                // The connection is already closing
+                Err(transport::Error::InvalidFrame(_)) => continue,
+                Err(err) => return Err(err.into()),
+            };
 
             match frame.body {
                 FrameBody::Close(close) => return Ok((IncomingChannel(frame.channel), close)),
@@ -160,7 +243,10 @@ where
 
     async fn open_inner(&mut self) -> Result<(), OpenError> {
         self.connection.send_open(&mut self.transport).await?;
+        self.recv_remote_open().await
+    }
 
+    async fn recv_remote_open(&mut self) -> Result<(), OpenError> {
         // Wait for an Open
         let frame = match self.transport.next().await {
             Some(frame) => match frame {
@@ -188,25 +274,60 @@ where
         // Handle incoming remote_open
         let remote_max_frame_size = remote_open.max_frame_size.0 as usize;
         let remote_idle_timeout = remote_open.idle_time_out;
+        let establishment_failed = is_establishment_failed(&remote_open);
         self.connection.on_incoming_open(channel, remote_open)?;
 
-        // update transport setting
+        if establishment_failed {
+            return self.recv_refusing_close().await;
+        }
+
+        // update transport setting. Outgoing frames are limited to the smaller of the two max
+        // frame sizes
         let local_max_frame_size = self.connection.local_open().max_frame_size.0 as usize;
+        if remote_max_frame_size < local_max_frame_size {
+            #[cfg(feature = "tracing")]
+            tracing::info!(
+                local_max_frame_size,
+                remote_max_frame_size,
+                "Outgoing frames are limited to the smaller max frame size of the remote peer"
+            );
+            #[cfg(feature = "log")]
+            log::info!(
+                "Outgoing frames are limited to the max frame size {} of the remote peer instead of {}",
+                remote_max_frame_size,
+                local_max_frame_size
+            );
+        }
         self.transport
-            .set_encoder_max_frame_size(remote_max_frame_size)
+            .set_encoder_max_frame_size(std::cmp::min(remote_max_frame_size, local_max_frame_size))
             .set_decoder_max_frame_size(local_max_frame_size);
 
         // Set heartbeat here because in pipelined-open, the Open frame
         // may be recved after mux loop is started
-        match &remote_idle_timeout {
-            Some(0) | None => self.heartbeat = HeartBeat::never(),
-            Some(millis) => {
-                let period = Duration::from_millis(*millis as u64);
-                self.heartbeat = HeartBeat::new(period);
-            }
-        };
+        self.set_heartbeat(remote_idle_timeout)
+    }
 
-        Ok(())
+    /// Waits for the Close that immediately follows an Open which has the
+    /// `amqp:connection-establishment-failed` property set
+    async fn recv_refusing_close(&mut self) -> Result<(), OpenError> {
+        loop {
+            let frame = self.transport.next().await.ok_or_else(|| {
+                OpenError::Io(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Expecting a Close frame",
+                ))
+            })??;
+            match frame.body {
+                FrameBody::Close(close) => {
+                    self.connection
+                        .on_incoming_close(IncomingChannel(frame.channel), close)
+                        .map_err(ConnectionStateError::from)?;
+                    return Err(OpenError::RemoteClosed);
+                }
+                FrameBody::Empty => continue,
+                _ => return Err(OpenError::IllegalState),
+            }
+        }
     }
 
     /// Open Connection without starting the Engine::event_loop()
@@ -215,6 +336,35 @@ where
         connection: C,
         control: Receiver<ConnectionControl>,
         outgoing_session_frames: Receiver<SessionFrame>,
+        heartbeat_policy: HeartbeatPolicy,
+    ) -> Result<Self, OpenError> {
+        let mut engine = Self {
+            transport,
+            connection,
+            control,
+            outgoing_session_frames,
+            heartbeat: HeartBeat::never(),
+            heartbeat_policy,
+            heartbeat_interval: None,
+            pending_writes: None,
+            events: broadcast::channel(EVENTS_CAPACITY).0,
+        };
+
+        let result = engine.open_inner().await;
+        engine.on_open_result(result).await
+    }
+
+    /// Receives the remote Open on the listener side without sending the local Open or starting
+    /// the Engine::event_loop().
+    ///
+    /// The remote Open is received before the local Open is sent so that the local Open can
+    /// depend on the remote Open. [`send_local_open`](Self::send_local_open) must be called next
+    #[cfg(feature = "acceptor")]
+    pub(crate) async fn accept(
+        transport: Transport<Io, amqp::Frame>,
+        connection: C,
+        control: Receiver<ConnectionControl>,
+        outgoing_session_frames: Receiver<SessionFrame>,
     ) -> Result<Self, OpenError> {
         let mut engine = Self {
             transport,
@@ -222,18 +372,104 @@ where
             control,
             outgoing_session_frames,
             heartbeat: HeartBeat::never(),
+            heartbeat_policy: HeartbeatPolicy::default(),
+            heartbeat_interval: None,
+            pending_writes: None,
+            events: broadcast::channel(EVENTS_CAPACITY).0,
         };
 
-        match engine.open_inner().await {
-            Ok(_) => Ok(engine),
+        // The local Open has not been sent, so the connection cannot be closed with a Close frame
+        engine.recv_remote_open().await?;
+        Ok(engine)
+    }
+
+    /// Sends the local Open after the remote Open is received by [`accept`](Self::accept)
+    #[cfg(feature = "acceptor")]
+    pub(crate) async fn send_local_open(mut self) -> Result<Self, OpenError> {
+        let result = self
+            .connection
+            .send_open(&mut self.transport)
+            .await
+            .map_err(Into::into);
+        self.on_open_result(result).await
+    }
+
+    /// Closes a connection that is refused right after the local Open is sent
+    ///
+    /// Frames pipelined by the remote peer behind its Open (eg. Begin or Attach) are discarded
+    /// until the remote Close arrives so that no session is started for a refused connection
+    #[cfg(feature = "acceptor")]
+    pub(crate) async fn refuse(
+        mut self,
+        error: definitions::Error,
+    ) -> Result<(), ConnectionInnerError> {
+        self.connection
+            .send_close(&mut self.transport, Some(error))
+            .await?;
+        let (channel, close) = self.wait_for_remote_close(true).await?;
+        self.connection.on_incoming_close(channel, close)?;
+        Ok(())
+    }
+
+    /// The remote Open if it has been received
+    pub(crate) fn remote_open(&self) -> Option<&Open> {
+        self.connection.remote_open()
+    }
+
+    /// The container id sent in the local Open
+    pub(crate) fn container_id(&self) -> &str {
+        &self.connection.local_open().container_id
+    }
+
+    /// The idle time-out advertised in the local Open
+    pub(crate) fn local_idle_timeout(&self) -> Option<Duration> {
+        self.connection
+            .local_open()
+            .idle_time_out
+            .filter(|millis| *millis > 0)
+            .map(|millis| Duration::from_millis(millis as u64))
+    }
+
+    /// The connection endpoint driven by the engine
+    #[cfg(feature = "acceptor")]
+    pub(crate) fn connection_mut(&mut self) -> &mut C {
+        &mut self.connection
+    }
+
+    /// Buffers the outgoing Transfer frames and writes them to the transport together
+    pub(crate) fn with_write_coalescing(mut self, coalescing: Option<WriteCoalescing>) -> Self {
+        if let Some(coalescing) = coalescing {
+            // The buffer is written by the engine once it holds `max_bytes`
+            self.transport
+                .set_backpressure_boundary(coalescing.max_bytes);
+            self.pending_writes = Some(PendingWrites::new(coalescing));
+        }
+        self
+    }
+
+    /// The max frame size of outgoing frames
+    pub(crate) fn max_frame_size(&self) -> usize {
+        self.transport.encoder_max_frame_size()
+    }
+
+    async fn on_open_result(mut self, result: Result<(), OpenError>) -> Result<Self, OpenError> {
+        match result {
+            Ok(_) => Ok(self),
             Err(error) => {
-                match engine.close_connection(None).await {
+                // The remote peer is told why the connection is closed if the local policy
+                // rejected its Open
+                let close_error =
+                    matches!(error, OpenError::IdleTimeoutUnsatisfiable { .. }).then(|| {
+                        definitions::Error::new(AmqpError::NotAllowed, error.to_string(), None)
+                    });
+                match self.close_connection(close_error).await {
                     Ok(_) => Err(error),
                     Err(error) => match error {
                         ConnectionInnerError::TransportError(e) => {
                             Err(OpenError::TransportError(e))
                         }
-                        ConnectionInnerError::IllegalState => Err(OpenError::IllegalState),
+                        ConnectionInnerError::IllegalState
+                        | ConnectionInnerError::NotAllowed(_) => Err(OpenError::IllegalState),
                         ConnectionInnerError::NotImplemented(e) => {
                             Err(OpenError::NotImplemented(e))
                         }
@@ -258,26 +494,85 @@ where
     async fn forward_to_session(
         &mut self,
         channel: IncomingChannel,
-        frame: SessionFrame,
+        mut frame: SessionFrame,
     ) -> Result<(), ConnectionInnerError> {
         match &self.connection.local_state() {
             ConnectionState::Opened => {}
             _ => return Err(ConnectionInnerError::IllegalState),
         };
 
-        match self.connection.session_tx_by_incoming_channel(channel) {
-            Some(tx) => tx.send(frame).await?,
+        let relay = match self.connection.session_relay_by_incoming_channel(channel) {
+            Some(relay) => relay.clone(),
             None => return Err(ConnectionInnerError::NotFound(None)),
         };
+
+        // Stop reading from the transport until the session has room for the payload. This
+        // applies backpressure to the remote peer instead of buffering more frames
+        if let SessionFrameBody::Transfer { payload, .. } = &frame.body {
+            let permit = relay.incoming_budget.acquire(payload.len()).await;
+            frame.incoming_permit = Some(permit);
+        }
+        relay.tx.send(frame.into()).await?;
         Ok(())
     }
 
+    /// Skips a frame that is unknown to this implementation and ends the session on the channel
+    /// of a malformed frame that is handled by a session. The other invalid frames are returned
+    /// as an error that closes the connection with a framing-error
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    async fn on_invalid_frame(
+        &mut self,
+        invalid: InvalidFrame,
+    ) -> Result<Running, ConnectionInnerError> {
+        #[cfg(feature = "tracing")]
+        tracing::warn!("{}", invalid);
+        #[cfg(feature = "log")]
+        log::warn!("{}", invalid);
+
+        let channel = invalid.channel;
+        if invalid.kind.is_skippable() {
+            let reason = invalid.kind.to_string();
+            let _ = self
+                .events
+                .send(ConnectionEvent::FrameSkipped { channel, reason });
+            return Ok(Running::Continue);
+        }
+
+        if let InvalidFrameKind::Malformed {
+            performative: Some(performative),
+            source,
+        } = &invalid.kind
+        {
+            let relay = self
+                .connection
+                .session_relay_by_incoming_channel(IncomingChannel(channel));
+            if let (true, Some(relay)) = (performative.is_session_scoped(), relay) {
+                let description = format!("Malformed {:?}: {}", performative, source);
+                let error = definitions::Error::new(AmqpError::DecodeError, description, None);
+                relay
+                    .tx
+                    .send(SessionIncomingItem::Malformed(error.clone()))
+                    .await?;
+                let _ = self
+                    .events
+                    .send(ConnectionEvent::SessionEnded { channel, error });
+                return Ok(Running::Continue);
+            }
+        }
+
+        Err(transport::Error::InvalidFrame(invalid).into())
+    }
+
     #[cfg_attr(feature = "tracing", tracing::instrument(name = "RECV", skip_all))]
     async fn on_incoming(&mut self, frame: Frame) -> Result<Running, ConnectionInnerError> {
         #[cfg(feature = "tracing")]
-        tracing::trace!(?frame);
+        tracing::trace!(channel = frame.channel, frame = ?Redacted::new(Cow::Borrowed(&frame.body), self.connection.log_redactor()));
         #[cfg(feature = "log")]
-        log::trace!("RECV frame={:?}", frame);
+        log::trace!(
+            "RECV channel = {}, frame = {:?}",
+            frame.channel,
+            Redacted::new(Cow::Borrowed(&frame.body), self.connection.log_redactor())
+        );
 
         let Frame { channel, body } = frame;
         let channel = IncomingChannel(channel);
@@ -288,13 +583,8 @@ where
 
                 // Set heartbeat here because in pipelined-open, the Open frame
                 // may be recved after mux loop is started
-                match &remote_idle_timeout {
-                    Some(millis) => {
-                        let period = Duration::from_millis(*millis as u64);
-                        self.heartbeat = HeartBeat::new(period);
-                    }
-                    None => self.heartbeat = HeartBeat::never(),
-                };
+                self.set_heartbeat(remote_idle_timeout)
+                    .map_err(|error| ConnectionInnerError::NotAllowed(Some(error.to_string())))?;
             }
             FrameBody::Begin(begin) => {
                 self.connection.on_incoming_begin(channel, begin).await?;
@@ -380,8 +670,8 @@ where
                     .send_close(&mut self.transport, error)
                     .await?;
             }
-            ConnectionControl::AllocateSession { tx, responder } => {
-                let result = self.connection.allocate_session(tx).map_err(Into::into);
+            ConnectionControl::AllocateSession { relay, responder } => {
+                let result = self.connection.allocate_session(relay).map_err(Into::into);
                 responder
                     .send(result)
                     .map_err(|_| ConnectionInnerError::IllegalState)?;
@@ -399,6 +689,10 @@ where
                     log::error!("{:?}", error);
                 }
             }
+            ConnectionControl::GetSessionChannels(resp) => {
+                // The handle may have given up waiting for the answer
+                let _ = resp.send(self.connection.session_channels());
+            }
         }
 
         match self.connection.local_state() {
@@ -418,8 +712,9 @@ where
             _ => return Err(ConnectionInnerError::IllegalState),
         }
 
-        let SessionFrame { channel, body } = frame;
+        let SessionFrame { channel, body, .. } = frame;
         let channel = OutgoingChannel(channel);
+        let is_transfer = matches!(body, SessionFrameBody::Transfer { .. });
         let frame = match body {
             SessionFrameBody::Begin(begin) => self.connection.on_outgoing_begin(channel, begin)?,
             SessionFrameBody::Attach(attach) => Frame::new(channel, FrameBody::Attach(attach)),
@@ -442,10 +737,39 @@ where
         };
 
         #[cfg(feature = "tracing")]
-        tracing::trace!(channel = frame.channel, frame = ?frame.body);
+        tracing::trace!(channel = frame.channel, frame = ?Redacted::new(Cow::Borrowed(&frame.body), self.connection.log_redactor()));
         #[cfg(feature = "log")]
-        log::trace!("SEND channel = {}, frame = {:?}", frame.channel, frame.body);
-        self.transport.send(frame).await?;
+        log::trace!(
+            "SEND channel = {}, frame = {:?}",
+            frame.channel,
+            Redacted::new(Cow::Borrowed(&frame.body), self.connection.log_redactor())
+        );
+        match &mut self.pending_writes {
+            Some(pending_writes) if is_transfer => {
+                self.transport.feed(frame).await?;
+                if self.transport.buffered_outgoing_bytes() >= pending_writes.max_bytes() {
+                    self.transport.flush().await?;
+                    pending_writes.on_written();
+                } else {
+                    pending_writes.on_buffered();
+                }
+            }
+            // Sending any other frame also writes the buffered Transfer frames before it
+            Some(pending_writes) => {
+                self.transport.send(frame).await?;
+                pending_writes.on_written();
+            }
+            None => self.transport.send(frame).await?,
+        }
+        Ok(Running::Continue)
+    }
+
+    #[inline]
+    async fn on_pending_writes_expired(&mut self) -> Result<Running, ConnectionInnerError> {
+        self.transport.flush().await?;
+        if let Some(pending_writes) = &mut self.pending_writes {
+            pending_writes.on_written();
+        }
         Ok(Running::Continue)
     }
 
@@ -462,12 +786,54 @@ where
         Ok(Running::Continue)
     }
 
+    /// Closes the connection once the handle is dropped
+    ///
+    /// The Close frame and the session frames queued before it are written ahead of any other
+    /// event, and giving up after [`DROPPED_HANDLE_GRACE`] stops the event loop instead of
+    /// leaving it stuck on a transport that does not make progress. The remote Close is then
+    /// awaited in the event loop as usual.
+    async fn on_handle_dropped(&mut self) -> Result<Running, ConnectionInnerError> {
+        match self.connection.local_state() {
+            ConnectionState::Opened
+            | ConnectionState::CloseReceived
+            | ConnectionState::OpenSent
+            | ConnectionState::OpenPipe => {}
+            // The Close is already sent or the connection has not been opened
+            _ => return Ok(Running::Continue),
+        }
+
+        let close = self.on_control(ConnectionControl::Close(None));
+        #[cfg(not(target_arch = "wasm32"))]
+        let close = async {
+            match timeout(DROPPED_HANDLE_GRACE, close).await {
+                Ok(result) => result,
+                Err(_) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::error!("Timed out writing the Close frame of a dropped handle");
+                    #[cfg(feature = "log")]
+                    log::error!("Timed out writing the Close frame of a dropped handle");
+                    Ok(Running::Stop)
+                }
+            }
+        };
+        close.await
+    }
+
     #[inline]
     async fn on_error(
         &mut self,
         error: &ConnectionInnerError,
     ) -> Result<Running, ConnectionInnerError> {
         match error {
+            ConnectionInnerError::TransportError(transport::Error::InvalidFrame(invalid)) => {
+                let error = definitions::Error::new(
+                    ConnectionError::FramingError,
+                    invalid.to_string(),
+                    None,
+                );
+                self.close_connection(Some(error)).await?;
+                Ok(Running::Stop)
+            }
             ConnectionInnerError::TransportError(_) => Ok(Running::Stop),
             ConnectionInnerError::IllegalState => {
                 let error = definitions::Error::new(AmqpError::IllegalState, None, None);
@@ -485,6 +851,12 @@ where
                 self.close_connection(Some(error)).await?;
                 Ok(Running::Stop)
             }
+            ConnectionInnerError::NotAllowed(description) => {
+                let error =
+                    definitions::Error::new(AmqpError::NotAllowed, description.clone(), None);
+                self.close_connection(Some(error)).await?;
+                Ok(Running::Stop)
+            }
             ConnectionInnerError::RemoteClosed | ConnectionInnerError::RemoteClosedWithError(_) => {
                 self.close_connection(None).await
             }
@@ -492,16 +864,35 @@ where
     }
 
     #[cfg_attr(feature = "tracing", tracing::instrument(name = "Connection::event_loop", skip(self), fields(container_id = %self.connection.local_open().container_id)))]
-    async fn event_loop(mut self, tx: oneshot::Sender<Result<(), Error>>) {
+    async fn event_loop(mut self, mut tx: oneshot::Sender<Result<(), Error>>) {
         let mut outcome = Ok(());
+        let mut outgoing_session_frames_closed = false;
+        let mut handle_dropped = false;
         loop {
+            let has_pending_writes = self
+                .pending_writes
+                .as_ref()
+                .map(|pending_writes| pending_writes.is_armed())
+                .unwrap_or(false);
             let result = tokio::select! {
+                // The handle owns the receiving half of the outcome, which is never full unlike the
+                // control channel
+                _ = tx.closed(), if !handle_dropped => {
+                    handle_dropped = true;
+                    self.on_handle_dropped().await
+                },
                 _ = self.heartbeat.next() => self.on_heartbeat().await,
+                _ = coalescing::expired(&mut self.pending_writes), if has_pending_writes => {
+                    self.on_pending_writes_expired().await
+                },
                 incoming = self.transport.next() => {
                     let result = match incoming {
                         Some(incoming) => {
                             match incoming {
                                 Ok(frame) => self.on_incoming(frame).await,
+                                Err(transport::Error::InvalidFrame(invalid)) => {
+                                    self.on_invalid_frame(invalid).await
+                                }
                                 Err(err) => Err(err.into()),
                             }
                         },
@@ -574,13 +965,14 @@ where
                         }
                     }
                 },
-                frame = self.outgoing_session_frames.recv() => {
+                frame = self.outgoing_session_frames.recv(), if !outgoing_session_frames_closed => {
                     match frame {
                         Some(frame) => self.on_outgoing_session_frames(frame).await,
                         None => {
                             // Upon closing, the outgoing_session_frames channel will be closed
                             // first while the connection may still be waiting for remote
                             // close frame.
+                            outgoing_session_frames_closed = true;
                             Ok(Running::Continue)
                         }
                     }
@@ -628,6 +1020,12 @@ where
         // at which point the receiver can be dropped.
         self.control.close();
         self.outgoing_session_frames.close();
+
+        // Closing the transport may take a while, so the requests that are still queued are
+        // failed and the sessions are told that the connection has stopped before that
+        while self.control.try_recv().is_ok() {}
+        while self.outgoing_session_frames.try_recv().is_ok() {}
+        self.connection.drop_session_relays();
         let close = self.transport.close().await.map_err(Into::into);
 
         #[cfg(feature = "tracing")]
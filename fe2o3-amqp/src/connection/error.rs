@@ -1,13 +1,19 @@
 //! Implements errors associated with the connection
 
-use std::{convert::Infallible, io};
+use std::{convert::Infallible, io, time::Duration};
 
 use bytes::Bytes;
-use fe2o3_amqp_types::{definitions, primitives::Binary, sasl::SaslCode};
+use fe2o3_amqp_types::{
+    definitions,
+    primitives::{Binary, Symbol},
+    sasl::SaslCode,
+};
 use tokio::{sync::mpsc, task::JoinError};
 
 use crate::transport::{self, error::NegotiationError};
 
+use super::heartbeat::HeartbeatPolicy;
+
 cfg_scram! {
     use crate::auth::error::ScramErrorKind;
 }
@@ -23,6 +29,26 @@ pub enum OpenError {
     #[error(transparent)]
     UrlError(#[from] url::ParseError),
 
+    /// The url has a query parameter that is not a connection option and
+    /// [`strict_url_params`](crate::connection::Builder::strict_url_params) is set
+    #[error("Unknown query parameter {0} in the url")]
+    UnknownUrlParam(String),
+
+    /// The value of a connection option in the query of the url is invalid or is not supported
+    /// with the enabled features
+    #[error("Invalid value {value:?} of query parameter {name} in the url")]
+    InvalidUrlParam {
+        /// Name of the query parameter
+        name: String,
+        /// Value of the query parameter
+        value: String,
+    },
+
+    /// The max frame size set on the builder is smaller than the minimum max frame size of 512
+    /// bytes, which every peer must be able to accept
+    #[error("Max frame size {0} is smaller than the minimum max frame size of 512 bytes")]
+    MaxFrameSizeTooSmall(u32),
+
     /// Domain is invalid or not found
     #[error("Invalid domain")]
     InvalidDomain,
@@ -39,15 +65,25 @@ pub enum OpenError {
     #[error("Protocol header mismatch. Found {0:?}")]
     ProtocolHeaderMismatch(Bytes),
 
-    /// SASL negotiation failed
-    #[error("SASL error code {:?}, additional data: {:?}", .code, .additional_data)]
-    SaslError {
+    /// SASL negotiation failed with the outcome sent by the server
+    ///
+    /// The code tells an authentication failure (`Auth`) apart from a permanent (`SysPerm`) or
+    /// temporary (`SysTemp`) system error, or an unspecified system error (`Sys`)
+    #[error("SASL outcome code {:?}, additional data: {:?}", .code, .additional_data)]
+    SaslOutcome {
         /// SASL outcome code
         code: SaslCode,
         /// Additional information for the failed negotiation
         additional_data: Option<Binary>,
     },
 
+    /// The mechanism of the SASL profile is not offered by the server
+    #[error("SASL mechanism is not offered by the server. Offered: {:?}", .offered)]
+    SaslMechanismMismatch {
+        /// Mechanisms offered by the server
+        offered: Vec<Symbol>,
+    },
+
     /// Error with SCRAM
     #[cfg_attr(docsrs, doc(cfg(feature = "scram")))]
     #[cfg(feature = "scram")]
@@ -64,7 +100,7 @@ pub enum OpenError {
 
     /// Decode error
     #[error("Decode error")]
-    DecodeError(String),
+    DecodeError(#[source] serde_amqp::Error),
 
     /// Transport error
     #[error(transparent)]
@@ -77,6 +113,36 @@ pub enum OpenError {
     /// Remote peer closed connection with error during openning process
     #[error("Remote peer closed connection with error {}", .0)]
     RemoteClosedWithError(definitions::Error),
+
+    /// The connection was refused locally and the remote peer was redirected to another container
+    #[error("Connection is redirected to {}:{}", .0.network_host, .0.port)]
+    Redirected(definitions::Redirect),
+
+    /// The connection was refused locally because the Open sent by the remote peer was rejected
+    #[error("Connection is rejected {}", .0)]
+    Rejected(definitions::Error),
+
+    /// The heartbeat policy cannot keep the connection alive within the idle time-out of the
+    /// remote peer. The connection is closed with an `amqp:not-allowed` error
+    #[error("Heartbeat policy {policy:?} cannot satisfy the remote idle time-out of {remote_idle_timeout:?}")]
+    IdleTimeoutUnsatisfiable {
+        /// Idle time-out advertised by the remote peer
+        remote_idle_timeout: Duration,
+        /// Local heartbeat policy
+        policy: HeartbeatPolicy,
+    },
+}
+
+impl OpenError {
+    /// Returns where to connect instead if the remote peer refused the connection with an
+    /// `amqp:connection:redirect` error
+    pub fn redirect(&self) -> Option<definitions::Redirect> {
+        match self {
+            Self::RemoteClosedWithError(error) => error.redirect(),
+            Self::Redirected(redirect) => Some(redirect.clone()),
+            _ => None,
+        }
+    }
 }
 
 impl From<NegotiationError> for OpenError {
@@ -85,13 +151,16 @@ impl From<NegotiationError> for OpenError {
             NegotiationError::Io(err) => Self::Io(err),
             NegotiationError::ProtocolHeaderMismatch(buf) => Self::ProtocolHeaderMismatch(buf),
             NegotiationError::InvalidDomain => Self::InvalidDomain,
-            NegotiationError::SaslError {
+            NegotiationError::SaslOutcome {
                 code,
                 additional_data,
-            } => Self::SaslError {
+            } => Self::SaslOutcome {
                 code,
                 additional_data,
             },
+            NegotiationError::SaslMechanismMismatch { offered } => {
+                Self::SaslMechanismMismatch { offered }
+            }
             NegotiationError::DecodeError(val) => Self::DecodeError(val),
             NegotiationError::NotImplemented(description) => Self::NotImplemented(description),
             NegotiationError::IllegalState => Self::IllegalState,
@@ -160,6 +229,10 @@ pub(crate) enum ConnectionInnerError {
     #[error("Not found {:?}", .0)]
     NotFound(Option<String>),
 
+    /// Not allowed
+    #[error("Not allowed {:?}", .0)]
+    NotAllowed(Option<String>),
+
     /// Remote peer closed connection
     #[error("Remote peer closed")]
     RemoteClosed,
@@ -232,6 +305,7 @@ impl From<ConnectionInnerError> for Error {
             ConnectionInnerError::IllegalState => Self::IllegalState,
             ConnectionInnerError::NotImplemented(val) => Self::NotImplemented(val),
             ConnectionInnerError::NotFound(val) => Self::NotFound(val),
+            ConnectionInnerError::NotAllowed(val) => Self::NotAllowed(val),
             ConnectionInnerError::RemoteClosed => Self::RemoteClosed,
             ConnectionInnerError::RemoteClosedWithError(val) => Self::RemoteClosedWithError(val),
         }
@@ -257,6 +331,9 @@ pub(crate) enum AllocSessionError {
 
     #[error("Reached connection channel max")]
     ChannelMaxReached,
+
+    #[error("Reached connection max sessions")]
+    MaxSessionsReached,
 }
 
 pub(crate) enum DeallcoSessionError {
@@ -0,0 +1,35 @@
+//! Events reported by the connection event loop
+
+use fe2o3_amqp_types::definitions;
+
+/// How many events are buffered for a subscriber before the oldest events are dropped
+pub(crate) const EVENTS_CAPACITY: usize = 64;
+
+/// An event reported by the connection event loop to the subscribers returned by
+/// [`ConnectionHandle::events`](super::ConnectionHandle::events)
+///
+/// Events are only reported to the subscribers that exist when the event happens, and a
+/// subscriber that falls behind by more than 64 events misses the oldest ones.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum ConnectionEvent {
+    /// A frame that is unknown to this implementation was received and skipped. The connection
+    /// and its sessions are not affected
+    FrameSkipped {
+        /// The channel of the frame
+        channel: u16,
+
+        /// Why the frame is skipped
+        reason: String,
+    },
+
+    /// A frame that cannot be decoded was received on the channel of a session, and only that
+    /// session is ended with the error
+    SessionEnded {
+        /// The incoming channel of the session
+        channel: u16,
+
+        /// The error that the session is ended with
+        error: definitions::Error,
+    },
+}
@@ -1,4 +1,4 @@
-//! Implements an asynchronous heartbeat
+//! Implements an asynchronous heartbeat and the policy that picks its interval
 
 use std::{io, pin::Pin, task::Poll, time::Duration};
 
@@ -6,17 +6,16 @@ use futures_util::Stream;
 use pin_project_lite::pin_project;
 
 cfg_not_wasm32! {
-    use tokio_stream::wrappers::IntervalStream;
+    use crate::rt::Interval;
 
     #[derive(Debug)]
     struct InnerStream {
-        interval: IntervalStream,
+        interval: Interval,
     }
 
     impl InnerStream {
         fn new(period: Duration) -> Self {
-            let interval = tokio::time::interval(period);
-            let interval = IntervalStream::new(interval);
+            let interval = Interval::new(period);
             Self { interval }
         }
     }
@@ -30,7 +29,7 @@ cfg_not_wasm32! {
         ) -> std::task::Poll<Option<Self::Item>> {
             let interval = Pin::new(&mut self.interval);
             match interval.poll_next(cx) {
-                Poll::Ready(Some(_instant)) => Poll::Ready(Some(Ok(()))),
+                Poll::Ready(Some(())) => Poll::Ready(Some(Ok(()))),
                 Poll::Ready(None) => Poll::Ready(None),
                 Poll::Pending => Poll::Pending,
             }
@@ -70,8 +69,63 @@ cfg_wasm32! {
     }
 }
 
+/// The smallest interval at which empty frames are sent to keep the connection alive
+///
+/// A remote peer whose idle time-out cannot be satisfied with a larger interval fails the open
+/// with [`OpenError::IdleTimeoutUnsatisfiable`](super::OpenError::IdleTimeoutUnsatisfiable).
+pub const MIN_HEARTBEAT_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How often empty frames are sent relative to the idle time-out advertised by the remote peer
+///
+/// The remote peer closes the connection if no frame arrives within its idle time-out, so an
+/// interval that is not shorter than the remote idle time-out, or a heartbeat that is disabled
+/// while the remote peer has an idle time-out, fails the open instead of getting the connection
+/// dropped later.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HeartbeatPolicy {
+    /// Sends an empty frame every half of the remote idle time-out, as recommended by the spec
+    #[default]
+    HalfRemote,
+
+    /// Sends an empty frame at a fixed interval, even if the remote peer has no idle time-out.
+    /// An interval shorter than [`MIN_HEARTBEAT_INTERVAL`] is raised to it
+    Fixed(Duration),
+
+    /// Never sends empty frames. This only works with a remote peer that has no idle time-out
+    Disabled,
+}
+
+impl HeartbeatPolicy {
+    /// Returns the interval of the heartbeat for the idle time-out of the remote peer, or `None`
+    /// if no empty frame needs to be sent
+    ///
+    /// `None` and zero are both treated as no remote idle time-out. The remote idle time-out is
+    /// returned as the error if the policy cannot satisfy it.
+    pub fn interval(
+        &self,
+        remote_idle_timeout: Option<Duration>,
+    ) -> Result<Option<Duration>, Duration> {
+        let remote_idle_timeout = remote_idle_timeout.filter(|timeout| !timeout.is_zero());
+        match (self, remote_idle_timeout) {
+            (Self::HalfRemote, None) | (Self::Disabled, None) => Ok(None),
+            (Self::HalfRemote, Some(remote)) => match remote / 2 {
+                interval if interval < MIN_HEARTBEAT_INTERVAL => Err(remote),
+                interval => Ok(Some(interval)),
+            },
+            (Self::Fixed(interval), remote) => {
+                let interval = (*interval).max(MIN_HEARTBEAT_INTERVAL);
+                match remote {
+                    Some(remote) if interval >= remote => Err(remote),
+                    _ => Ok(Some(interval)),
+                }
+            }
+            (Self::Disabled, Some(remote)) => Err(remote),
+        }
+    }
+}
+
 pin_project! {
-    /// A wrapper over an `Option<IntervalStream>` which will never tick ready if the underlying
+    /// A wrapper over an `Option<Interval>` which will never tick ready if the underlying
     /// `Interval` is `None`
     #[derive(Debug)]
     pub struct HeartBeat {
@@ -107,3 +161,49 @@ impl Stream for HeartBeat {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{HeartbeatPolicy, MIN_HEARTBEAT_INTERVAL};
+
+    #[test]
+    fn test_heartbeat_policy_interval() {
+        let ms = Duration::from_millis;
+
+        assert_eq!(HeartbeatPolicy::HalfRemote.interval(None), Ok(None));
+        assert_eq!(HeartbeatPolicy::HalfRemote.interval(Some(ms(0))), Ok(None));
+        assert_eq!(
+            HeartbeatPolicy::HalfRemote.interval(Some(ms(100))),
+            Ok(Some(ms(50)))
+        );
+        assert_eq!(
+            HeartbeatPolicy::HalfRemote.interval(Some(MIN_HEARTBEAT_INTERVAL)),
+            Err(MIN_HEARTBEAT_INTERVAL)
+        );
+
+        let fixed = HeartbeatPolicy::Fixed(ms(200));
+        assert_eq!(fixed.interval(None), Ok(Some(ms(200))));
+        assert_eq!(fixed.interval(Some(ms(1000))), Ok(Some(ms(200))));
+        assert_eq!(fixed.interval(Some(ms(200))), Err(ms(200)));
+        assert_eq!(
+            HeartbeatPolicy::Fixed(ms(1)).interval(Some(ms(100))),
+            Ok(Some(MIN_HEARTBEAT_INTERVAL))
+        );
+        assert_eq!(
+            HeartbeatPolicy::Fixed(ms(1)).interval(Some(MIN_HEARTBEAT_INTERVAL)),
+            Err(MIN_HEARTBEAT_INTERVAL)
+        );
+        assert_eq!(
+            HeartbeatPolicy::Fixed(ms(1)).interval(None),
+            Ok(Some(MIN_HEARTBEAT_INTERVAL))
+        );
+
+        assert_eq!(HeartbeatPolicy::Disabled.interval(None), Ok(None));
+        assert_eq!(
+            HeartbeatPolicy::Disabled.interval(Some(ms(100))),
+            Err(ms(100))
+        );
+    }
+}
@@ -1,6 +1,16 @@
 //! Implements AMQP1.0 Connection
 
-use std::{cmp::min, collections::HashMap, sync::Arc};
+#[cfg(any(feature = "tracing", feature = "log"))]
+use std::borrow::Cow;
+use std::{
+    cmp::min,
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
 use fe2o3_amqp_types::{
     definitions::{self},
@@ -9,36 +19,75 @@ use fe2o3_amqp_types::{
 };
 use futures_util::{Sink, SinkExt};
 use slab::Slab;
-use tokio::{
-    sync::{
-        mpsc::Sender,
-        oneshot::{self, error::TryRecvError},
-    },
-    task::JoinHandle,
+use tokio::sync::{
+    broadcast,
+    mpsc::Sender,
+    oneshot::{self, error::TryRecvError},
 };
 
 cfg_not_wasm32! {
-    use std::convert::TryInto;
+    use std::{convert::TryInto, future::Future, pin::Pin};
     use url::Url;
+
+    use crate::{
+        introspect::{self, Queried},
+        rt::Spawner,
+    };
 }
 
 use crate::{
     control::ConnectionControl,
     endpoint::{self, IncomingChannel, OutgoingChannel},
-    frames::amqp::{Frame, FrameBody},
+    frames::amqp::{Frame, FrameBody, LogRedactor},
+    rt::JoinHandle,
     session::frame::{SessionFrame, SessionFrameBody, SessionIncomingItem},
+    session::incoming_budget::IncomingBudget,
     session::Session,
+    transport::FrameActivity,
     SendBound,
 };
 
+#[cfg(any(feature = "tracing", feature = "log"))]
+use crate::frames::amqp::Redacted;
+
+pub mod azure;
+
 mod builder;
 pub use builder::*;
 
+mod coalescing;
+pub use coalescing::WriteCoalescing;
+
 pub(crate) mod engine;
 
 mod error;
 pub mod heartbeat;
 pub use error::*;
+pub use heartbeat::HeartbeatPolicy;
+
+mod event;
+pub use event::ConnectionEvent;
+pub(crate) use event::EVENTS_CAPACITY;
+
+cfg_rustls! {
+    mod tls;
+}
+
+cfg_not_wasm32! {
+    mod url_params;
+}
+
+cfg_not_wasm32! {
+    /// The event loop of a connection or a session that is returned instead of being spawned
+    ///
+    /// See [`Builder::open_unspawned`] and
+    /// [`session::Builder::begin_unspawned`](crate::session::Builder::begin_unspawned). The event
+    /// loop completes once the connection is closed or the session is ended, and the outcome is
+    /// returned by `on_close` or `on_end` of the handle. Dropping the future stops the event loop
+    /// without exchanging Close or End frames, after which the handle fails with
+    /// `IllegalState`.
+    pub type EngineFuture = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+}
 
 /// Default max-frame-size.
 ///
@@ -51,37 +100,180 @@ pub const DEFAULT_MAX_FRAME_SIZE: u32 = 256 * 1024;
 /// This value is taken from `AmqpNetLite`
 pub const DEFAULT_CHANNEL_MAX: u16 = 255;
 
-type SessionRelay = Arc<Sender<SessionIncomingItem>>;
+/// How long the event loop tries to write the Close or End frame after the handle is dropped
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) const DROPPED_HANDLE_GRACE: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Key of the Open property that tells the remote peer that the Open is immediately followed by
+/// a Close that refuses the connection
+pub const CONNECTION_ESTABLISHMENT_FAILED: &str = "amqp:connection-establishment-failed";
+
+/// The incoming channel of a session and the bytes buffered by it
+#[derive(Debug)]
+pub(crate) struct SessionRelay {
+    pub(crate) tx: Sender<SessionIncomingItem>,
+    pub(crate) incoming_budget: Arc<IncomingBudget>,
+}
+
+/// Type alias for the handle of a connection opened with [`Connection::open`] or
+/// [`Connection::builder`]
+pub type ClientConnectionHandle = ConnectionHandle<()>;
 
 /// A handle to the [`Connection`] event loop.
 ///
-/// Dropping the handle will also stop the [`Connection`] event loop.
+/// Dropping the handle will also stop the [`Connection`] event loop. The event loop writes the
+/// Close frame right away, without blocking in `drop`, and then waits for the Close of the remote
+/// peer.
 #[allow(dead_code)]
 pub struct ConnectionHandle<R> {
     /// Only change this value in `on_close` method
     pub(crate) is_closed: bool,
     pub(crate) control: Sender<ConnectionControl>,
-    pub(crate) handle: JoinHandle<()>,
+    pub(crate) handle: Option<JoinHandle<()>>,
     pub(crate) outcome: oneshot::Receiver<Result<(), Error>>,
 
+    // Where the event loops of the sessions begun on this connection are run by default
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) spawner: Spawner,
+
     // outgoing channel for session
     pub(crate) outgoing: Sender<SessionFrame>,
     pub(crate) session_listener: R,
+
+    // Container id sent in the local Open, which is recorded in the tracing spans of the sessions
+    // and links
+    pub(crate) container_id: String,
+
+    // Open performative received from the remote peer
+    pub(crate) remote_open: Open,
+
+    // Negotiated max frame size of outgoing frames
+    pub(crate) max_frame_size: usize,
+
+    // Number of active sessions, updated by the connection engine
+    pub(crate) active_sessions: Arc<AtomicUsize>,
+
+    // Instants of the last frames, updated by the transport of the connection engine
+    pub(crate) frame_activity: Arc<FrameActivity>,
+
+    // Events reported by the connection engine
+    pub(crate) events: broadcast::Sender<ConnectionEvent>,
+
+    // Idle time-out advertised in the local Open
+    pub(crate) local_idle_timeout: Option<Duration>,
+
+    // Interval at which empty frames are sent to the remote peer
+    pub(crate) heartbeat_interval: Option<Duration>,
 }
 
 impl<R> std::fmt::Debug for ConnectionHandle<R> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("ConnectionHandle").finish()
+        f.debug_struct("ConnectionHandle")
+            .field("is_closed", &self.is_closed())
+            .field("remote_container_id", &self.remote_open.container_id)
+            .field("max_frame_size", &self.max_frame_size)
+            .field("active_sessions", &self.active_session_count())
+            .finish()
     }
 }
 
-impl<R> Drop for ConnectionHandle<R> {
-    fn drop(&mut self) {
-        let _ = self.control.try_send(ConnectionControl::Close(None));
+impl<R> ConnectionHandle<R> {
+    /// The Open performative received from the remote peer, which carries fields like the remote
+    /// `container_id`, `offered_capabilities` and `properties`
+    pub fn remote_open(&self) -> &Open {
+        &self.remote_open
+    }
+
+    /// The max frame size negotiated with the remote peer, which is the smaller of the local and
+    /// the remote `max_frame_size` and bounds the size of the outgoing frames
+    ///
+    /// A message that does not fit in a single frame is split into several Transfer frames.
+    pub fn max_frame_size(&self) -> usize {
+        self.max_frame_size
+    }
+
+    /// The max frame size negotiated with the remote peer
+    ///
+    /// This is an alias of [`max_frame_size`](#method.max_frame_size), which already returns the
+    /// negotiated value rather than the one set on the builder.
+    pub fn negotiated_max_frame_size(&self) -> usize {
+        self.max_frame_size
+    }
+
+    /// The idle time-out advertised in the local Open, or `None` if there is no local idle
+    /// time-out
+    ///
+    /// This is half of the [`idle_time_out`](Builder::idle_time_out) set on the builder, so that
+    /// the remote peer has time to send an empty frame before the connection is closed for being
+    /// idle.
+    pub fn local_idle_timeout(&self) -> Option<Duration> {
+        self.local_idle_timeout
+    }
+
+    /// The idle time-out advertised in the Open received from the remote peer, or `None` if the
+    /// remote peer has no idle time-out
+    pub fn remote_idle_timeout(&self) -> Option<Duration> {
+        self.remote_open
+            .idle_time_out
+            .filter(|millis| *millis > 0)
+            .map(|millis| Duration::from_millis(millis as u64))
+    }
+
+    /// The interval at which empty frames are sent to keep the connection alive, or `None` if no
+    /// empty frame is sent
+    ///
+    /// This is chosen by the [`HeartbeatPolicy`] from the [remote idle
+    /// time-out](Self::remote_idle_timeout).
+    pub fn heartbeat_interval(&self) -> Option<Duration> {
+        self.heartbeat_interval
+    }
+
+    /// Returns the number of sessions that are currently active on the connection
+    ///
+    /// A session is no longer counted once it has stopped, even if the End from the remote peer
+    /// has not arrived yet.
+    pub fn active_session_count(&self) -> usize {
+        self.active_sessions.load(Ordering::Acquire)
+    }
+
+    /// Returns the instant at which the last frame, including an empty heartbeat frame, was
+    /// received from the remote peer, or `None` if no frame has been received yet
+    ///
+    /// This is read without going through the connection event loop and is cheap enough to be
+    /// polled by a health check. The instant follows the tokio clock and is always `None` on
+    /// wasm32.
+    pub fn last_received_at(&self) -> Option<Instant> {
+        self.frame_activity.received.get()
+    }
+
+    /// Returns the instant at which the last frame, including an empty heartbeat frame, was
+    /// handed to the transport to be sent to the remote peer, or `None` if no frame has been sent
+    /// yet
+    ///
+    /// See [`last_received_at`](Self::last_received_at) for how the instant is tracked.
+    pub fn last_sent_at(&self) -> Option<Instant> {
+        self.frame_activity.sent.get()
+    }
+
+    /// Subscribes to the events reported by the connection event loop, eg. a frame that cannot be
+    /// decoded and is skipped or ends only the session on its channel
+    ///
+    /// The subscriber only receives the events that happen after it is created.
+    pub fn events(&self) -> broadcast::Receiver<ConnectionEvent> {
+        self.events.subscribe()
+    }
+
+    cfg_not_wasm32! {
+        /// Queries the connection event loop for the outgoing channels of the active sessions
+        ///
+        /// [`Queried::Unresponsive`] is returned if the event loop does not answer within a short
+        /// timeout or has stopped. See [`introspect`](crate::introspect) for printing the tree of a
+        /// connection.
+        pub async fn session_channels(&self) -> Queried<Vec<u16>> {
+            introspect::query(&self.control, ConnectionControl::GetSessionChannels).await
+        }
     }
-}
 
-impl<R> ConnectionHandle<R> {
     /// Checks if the underlying event loop has stopped
     pub fn is_closed(&self) -> bool {
         match self.is_closed {
@@ -185,11 +377,11 @@ impl<R> ConnectionHandle<R> {
     /// Allocte (channel, session_id) for a new session
     pub(crate) async fn allocate_session(
         &mut self,
-        tx: Sender<SessionIncomingItem>,
+        relay: SessionRelay,
     ) -> Result<OutgoingChannel, AllocSessionError> {
         let (responder, resp_rx) = oneshot::channel();
         self.control
-            .send(ConnectionControl::AllocateSession { tx, responder })
+            .send(ConnectionControl::AllocateSession { relay, responder })
             .await
             .map_err(|_| AllocSessionError::IllegalState)?; // Connection must have stopped
         resp_rx.await.map_err(|_| AllocSessionError::IllegalState)?
@@ -401,14 +593,27 @@ pub struct Connection {
     // local
     pub(crate) local_state: ConnectionState,
     pub(crate) local_open: Open,
-    pub(crate) session_by_incoming_channel: HashMap<IncomingChannel, SessionRelay>,
-    pub(crate) session_by_outgoing_channel: Slab<SessionRelay>,
+    pub(crate) session_by_incoming_channel: HashMap<IncomingChannel, Arc<SessionRelay>>,
+    pub(crate) session_by_outgoing_channel: Slab<Arc<SessionRelay>>,
 
     // remote
     pub(crate) remote_open: Option<Open>,
 
     // mutually agreed channel max
     pub(crate) agreed_channel_max: u16,
+
+    // Outgoing channels of the stopped sessions that are still waiting for the End from the
+    // remote peer. A channel is only reused once End has been exchanged in both directions
+    pub(crate) ending_sessions: HashMap<IncomingChannel, OutgoingChannel>,
+
+    // Maximum number of active sessions regardless of the channel-max
+    pub(crate) max_sessions: Option<usize>,
+
+    // Number of active sessions, shared with the connection handle
+    pub(crate) active_sessions: Arc<AtomicUsize>,
+
+    // Rewrites the frames before they are traced or logged
+    pub(crate) log_redactor: Option<LogRedactor>,
 }
 
 /* ------------------------------- Public API ------------------------------- */
@@ -511,6 +716,8 @@ impl Connection {
         // control: Sender<ConnectionControl>,
         local_state: ConnectionState,
         local_open: Open,
+        max_sessions: Option<usize>,
+        log_redactor: Option<LogRedactor>,
     ) -> Self {
         let agreed_channel_max = local_open.channel_max.0;
         Self {
@@ -522,8 +729,22 @@ impl Connection {
 
             remote_open: None,
             agreed_channel_max,
+            ending_sessions: HashMap::new(),
+            max_sessions,
+            active_sessions: Arc::new(AtomicUsize::new(0)),
+            log_redactor,
         }
     }
+
+    /// Number of sessions that have not stopped yet
+    fn active_session_count(&self) -> usize {
+        self.session_by_outgoing_channel.len() - self.ending_sessions.len()
+    }
+
+    fn update_active_sessions(&self) {
+        self.active_sessions
+            .store(self.active_session_count(), Ordering::Release);
+    }
 }
 
 impl endpoint::Connection for Connection {
@@ -542,9 +763,17 @@ impl endpoint::Connection for Connection {
         &self.local_open
     }
 
+    fn remote_open(&self) -> Option<&Open> {
+        self.remote_open.as_ref()
+    }
+
+    fn log_redactor(&self) -> Option<LogRedactor> {
+        self.log_redactor
+    }
+
     fn allocate_session(
         &mut self,
-        tx: Sender<SessionIncomingItem>,
+        relay: SessionRelay,
     ) -> Result<OutgoingChannel, Self::AllocError> {
         match &self.local_state {
             ConnectionState::Start
@@ -558,7 +787,14 @@ impl endpoint::Connection for Connection {
             _ => {}
         };
 
-        // get new entry index
+        if let Some(max_sessions) = self.max_sessions {
+            if self.active_session_count() >= max_sessions {
+                return Err(AllocSessionError::MaxSessionsReached);
+            }
+        }
+
+        // The slab hands out the lowest vacant entry, so the channels released by the ended
+        // sessions are reused
         let entry = self.session_by_outgoing_channel.vacant_entry();
         let outgoing_channel = entry.key();
 
@@ -566,14 +802,41 @@ impl endpoint::Connection for Connection {
         if outgoing_channel > self.agreed_channel_max as usize {
             Err(AllocSessionError::ChannelMaxReached)
         } else {
-            entry.insert(Arc::new(tx));
+            entry.insert(Arc::new(relay));
+            self.update_active_sessions();
             Ok(OutgoingChannel(outgoing_channel as u16))
         }
     }
 
     fn deallocate_session(&mut self, outgoing_channel: OutgoingChannel) {
-        self.session_by_outgoing_channel
-            .remove(outgoing_channel.0 as usize);
+        let relay = match self
+            .session_by_outgoing_channel
+            .get(outgoing_channel.0 as usize)
+        {
+            Some(relay) => relay,
+            None => return,
+        };
+
+        // The session has stopped before the remote peer ended it. The channel stays allocated
+        // until the End from the remote peer arrives, otherwise a new session could begin on a
+        // channel that the remote peer still considers in use
+        let incoming_channel = self
+            .session_by_incoming_channel
+            .iter()
+            .find(|(_, incoming)| Arc::ptr_eq(incoming, relay))
+            .map(|(channel, _)| *channel);
+        match incoming_channel {
+            Some(incoming_channel) => {
+                self.session_by_incoming_channel.remove(&incoming_channel);
+                self.ending_sessions
+                    .insert(incoming_channel, outgoing_channel);
+            }
+            None => {
+                self.session_by_outgoing_channel
+                    .remove(outgoing_channel.0 as usize);
+            }
+        }
+        self.update_active_sessions();
     }
 
     /// Reacting to remote Open frame
@@ -584,9 +847,12 @@ impl endpoint::Connection for Connection {
         open: Open,
     ) -> Result<(), Self::OpenError> {
         #[cfg(feature = "tracing")]
-        tracing::trace!(frame = ?open);
+        tracing::trace!(frame = ?Redacted::new(Cow::Owned(FrameBody::Open(open.clone())), self.log_redactor));
         #[cfg(feature = "log")]
-        log::trace!("RECV frame = {:?}", open);
+        log::trace!(
+            "RECV frame = {:?}",
+            Redacted::new(Cow::Owned(FrameBody::Open(open.clone())), self.log_redactor)
+        );
 
         match &self.local_state {
             ConnectionState::HeaderExchange => self.local_state = ConnectionState::OpenReceived,
@@ -613,7 +879,7 @@ impl endpoint::Connection for Connection {
                 // forward begin to session
                 let sframe = SessionFrame::new(channel.0, SessionFrameBody::Begin(begin));
                 // self.send_to_session(session_id, sframe).await?;
-                relay.send(sframe).await?;
+                relay.tx.send(sframe.into()).await?;
                 Ok(())
             }
             None => {
@@ -638,6 +904,15 @@ impl endpoint::Connection for Connection {
             _ => return Err(ConnectionInnerError::IllegalState),
         }
 
+        // The session has already stopped, and the channel can be reused now that End has been
+        // exchanged in both directions
+        if let Some(outgoing_channel) = self.ending_sessions.remove(&channel) {
+            self.session_by_outgoing_channel
+                .remove(outgoing_channel.0 as usize);
+            self.update_active_sessions();
+            return Ok(());
+        }
+
         // Forward to session
         let sframe = SessionFrame::new(channel, SessionFrameBody::End(end));
         // Drop incoming channel
@@ -645,7 +920,7 @@ impl endpoint::Connection for Connection {
             .session_by_incoming_channel
             .remove(&channel)
             .ok_or(ConnectionInnerError::NotFound(None))?;
-        relay.send(sframe).await?;
+        relay.tx.send(sframe.into()).await?;
 
         Ok(())
     }
@@ -690,9 +965,12 @@ impl endpoint::Connection for Connection {
         let body = FrameBody::Open(self.local_open.clone());
         let frame = Frame::new(0u16, body);
         #[cfg(feature = "tracing")]
-        tracing::trace!(?frame);
+        tracing::trace!(frame = ?Redacted::new(Cow::Borrowed(&frame.body), self.log_redactor));
         #[cfg(feature = "log")]
-        log::trace!("SEND frame = {:?}", frame);
+        log::trace!(
+            "SEND frame = {:?}",
+            Redacted::new(Cow::Borrowed(&frame.body), self.log_redactor)
+        );
         writer.send(frame).await.map_err(Into::into)?;
 
         // change local state after successfully sending the frame
@@ -759,13 +1037,25 @@ impl endpoint::Connection for Connection {
         Ok(frame)
     }
 
-    fn session_tx_by_incoming_channel(
+    fn session_relay_by_incoming_channel(
         &mut self,
         incoming_channel: IncomingChannel,
-    ) -> Option<&Sender<SessionIncomingItem>> {
-        self.session_by_incoming_channel
-            .get(&incoming_channel)
-            .map(AsRef::as_ref)
+    ) -> Option<&Arc<SessionRelay>> {
+        self.session_by_incoming_channel.get(&incoming_channel)
+    }
+
+    fn session_channels(&self) -> Vec<u16> {
+        self.session_by_outgoing_channel
+            .iter()
+            .map(|(channel, _)| channel as u16)
+            .collect()
+    }
+
+    fn drop_session_relays(&mut self) {
+        self.session_by_incoming_channel.clear();
+        self.session_by_outgoing_channel.clear();
+        self.ending_sessions.clear();
+        self.update_active_sessions();
     }
 }
 
@@ -779,7 +1069,7 @@ impl Connection {
         &mut self,
         channel: IncomingChannel,
         begin: &Begin,
-    ) -> Result<Option<&SessionRelay>, ConnectionInnerError> {
+    ) -> Result<Option<&Arc<SessionRelay>>, ConnectionInnerError> {
         match &self.local_state {
             ConnectionState::Opened => {}
             // TODO: what about pipelined
@@ -808,3 +1098,112 @@ impl Connection {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use fe2o3_amqp_types::{
+        definitions::Handle,
+        performatives::{Begin, End, Open},
+        states::ConnectionState,
+    };
+    use tokio::sync::mpsc;
+
+    use crate::{
+        connection::SessionRelay,
+        endpoint::{self, IncomingChannel, OutgoingChannel},
+        session::incoming_budget::IncomingBudget,
+    };
+
+    use super::{AllocSessionError, Connection, Ordering};
+
+    fn connection(channel_max: u16, max_sessions: Option<usize>) -> Connection {
+        let open = Open {
+            container_id: "test".into(),
+            hostname: None,
+            max_frame_size: Default::default(),
+            channel_max: channel_max.into(),
+            idle_time_out: None,
+            outgoing_locales: None,
+            incoming_locales: None,
+            offered_capabilities: None,
+            desired_capabilities: None,
+            properties: None,
+        };
+        Connection::new(ConnectionState::Opened, open, max_sessions, None)
+    }
+
+    fn relay() -> (SessionRelay, mpsc::Receiver<super::SessionIncomingItem>) {
+        let (tx, rx) = mpsc::channel(1);
+        let relay = SessionRelay {
+            tx,
+            incoming_budget: IncomingBudget::new(None),
+        };
+        (relay, rx)
+    }
+
+    fn begin(outgoing_channel: OutgoingChannel) -> Begin {
+        Begin {
+            remote_channel: Some(outgoing_channel.0),
+            next_outgoing_id: 0,
+            incoming_window: 0,
+            outgoing_window: 0,
+            handle_max: Handle::default(),
+            offered_capabilities: None,
+            desired_capabilities: None,
+            properties: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn channel_is_reused_only_after_remote_end() {
+        let mut connection = connection(0, None);
+
+        let (relay0, _rx0) = relay();
+        let outgoing = endpoint::Connection::allocate_session(&mut connection, relay0).unwrap();
+        assert_eq!(outgoing, OutgoingChannel(0));
+        connection
+            .on_incoming_begin_inner(IncomingChannel(3), &begin(outgoing))
+            .unwrap();
+        assert_eq!(connection.active_sessions.load(Ordering::Acquire), 1);
+
+        // The session stops before the remote peer has ended it
+        endpoint::Connection::deallocate_session(&mut connection, outgoing);
+        assert_eq!(connection.active_sessions.load(Ordering::Acquire), 0);
+        let (relay1, _rx1) = relay();
+        assert!(matches!(
+            endpoint::Connection::allocate_session(&mut connection, relay1),
+            Err(AllocSessionError::ChannelMaxReached)
+        ));
+
+        // The late End is consumed by the connection and releases the channel
+        endpoint::Connection::on_incoming_end(
+            &mut connection,
+            IncomingChannel(3),
+            End { error: None },
+        )
+        .await
+        .unwrap();
+        let (relay2, _rx2) = relay();
+        let outgoing = endpoint::Connection::allocate_session(&mut connection, relay2).unwrap();
+        assert_eq!(outgoing, OutgoingChannel(0));
+    }
+
+    #[test]
+    fn max_sessions_is_independent_of_channel_max() {
+        let mut connection = connection(8, Some(1));
+
+        let (relay0, _rx0) = relay();
+        let outgoing = endpoint::Connection::allocate_session(&mut connection, relay0).unwrap();
+        let (relay1, _rx1) = relay();
+        assert!(matches!(
+            endpoint::Connection::allocate_session(&mut connection, relay1),
+            Err(AllocSessionError::MaxSessionsReached)
+        ));
+
+        // The session never received a Begin from the remote peer, so the channel is released
+        // right away
+        endpoint::Connection::deallocate_session(&mut connection, outgoing);
+        let (relay2, _rx2) = relay();
+        assert!(endpoint::Connection::allocate_session(&mut connection, relay2).is_ok());
+    }
+}
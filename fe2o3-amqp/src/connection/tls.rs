@@ -0,0 +1,151 @@
+//! Options that compose the default `rustls` client config of the connection builder
+
+use std::{io, sync::Arc};
+
+use librustls::{
+    pki_types::{CertificateDer, PrivateKeyDer},
+    ClientConfig, RootCertStore,
+};
+
+/// Additions to the default `rustls` client config that are set on the connection builder
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RustlsOptions {
+    /// Trust anchors that are trusted in addition to the webpki roots
+    pub(crate) root_certificates: Vec<CertificateDer<'static>>,
+
+    /// Certificate chain and private key presented to the server
+    pub(crate) client_auth: Option<ClientAuth>,
+
+    /// Skips the verification of the server certificate
+    #[cfg(feature = "dangerous-tls")]
+    pub(crate) accept_invalid_certs: bool,
+}
+
+/// Certificate chain and private key presented to the server
+#[derive(Debug)]
+pub(crate) struct ClientAuth {
+    cert_chain: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+}
+
+impl Clone for ClientAuth {
+    fn clone(&self) -> Self {
+        Self {
+            cert_chain: self.cert_chain.clone(),
+            key: self.key.clone_key(),
+        }
+    }
+}
+
+impl ClientAuth {
+    pub(crate) fn new(
+        cert_chain: Vec<CertificateDer<'static>>,
+        key: PrivateKeyDer<'static>,
+    ) -> Self {
+        Self { cert_chain, key }
+    }
+}
+
+impl RustlsOptions {
+    /// Whether any option differs from the default client config
+    pub(crate) fn is_set(&self) -> bool {
+        #[cfg(feature = "dangerous-tls")]
+        if self.accept_invalid_certs {
+            return true;
+        }
+        !self.root_certificates.is_empty() || self.client_auth.is_some()
+    }
+
+    /// Composes the client config from the webpki roots and the options
+    pub(crate) fn client_config(&self) -> Result<ClientConfig, io::Error> {
+        let builder = ClientConfig::builder();
+
+        #[cfg(feature = "dangerous-tls")]
+        let builder = if self.accept_invalid_certs {
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(danger::NoServerCertVerification::new()))
+        } else {
+            builder.with_root_certificates(self.root_cert_store()?)
+        };
+        #[cfg(not(feature = "dangerous-tls"))]
+        let builder = builder.with_root_certificates(self.root_cert_store()?);
+
+        match &self.client_auth {
+            Some(auth) => builder
+                .with_client_auth_cert(auth.cert_chain.clone(), auth.key.clone_key())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e)),
+            None => Ok(builder.with_no_client_auth()),
+        }
+    }
+
+    fn root_cert_store(&self) -> Result<Arc<RootCertStore>, io::Error> {
+        let mut root_cert_store = RootCertStore::empty();
+        root_cert_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        for cert in &self.root_certificates {
+            root_cert_store
+                .add(cert.clone())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        }
+        Ok(Arc::new(root_cert_store))
+    }
+}
+
+#[cfg(feature = "dangerous-tls")]
+mod danger {
+    use librustls::{
+        client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        crypto::{ring, verify_tls12_signature, verify_tls13_signature, WebPkiSupportedAlgorithms},
+        pki_types::{CertificateDer, ServerName, UnixTime},
+        DigitallySignedStruct, SignatureScheme,
+    };
+
+    /// Accepts any server certificate while still checking the handshake signatures
+    #[derive(Debug)]
+    pub(super) struct NoServerCertVerification {
+        algorithms: WebPkiSupportedAlgorithms,
+    }
+
+    impl NoServerCertVerification {
+        pub(super) fn new() -> Self {
+            Self {
+                algorithms: ring::default_provider().signature_verification_algorithms,
+            }
+        }
+    }
+
+    impl ServerCertVerifier for NoServerCertVerification {
+        fn verify_server_cert(
+            &self,
+            _end_entity: &CertificateDer<'_>,
+            _intermediates: &[CertificateDer<'_>],
+            _server_name: &ServerName<'_>,
+            _ocsp_response: &[u8],
+            _now: UnixTime,
+        ) -> Result<ServerCertVerified, librustls::Error> {
+            Ok(ServerCertVerified::assertion())
+        }
+
+        fn verify_tls12_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, librustls::Error> {
+            verify_tls12_signature(message, cert, dss, &self.algorithms)
+        }
+
+        fn verify_tls13_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, librustls::Error> {
+            verify_tls13_signature(message, cert, dss, &self.algorithms)
+        }
+
+        fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+            self.algorithms.supported_schemes()
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Connection options that are given as query parameters of the url
+
+use fe2o3_amqp_types::definitions::Milliseconds;
+use url::Url;
+
+use super::OpenError;
+
+/// Name of the query parameter that sets the container id
+const CONTAINER_ID: &str = "container_id";
+
+/// Name of the query parameter that sets the idle time-out in milliseconds
+const IDLE_TIMEOUT_MS: &str = "idle_timeout_ms";
+
+/// Name of the query parameter that sets the proposed maximum frame size
+const MAX_FRAME_SIZE: &str = "max_frame_size";
+
+/// Name of the query parameter that sets the maximum channel number
+const CHANNEL_MAX: &str = "channel_max";
+
+/// Name of the query parameter that disables the verification of the server certificate if it
+/// is `false`
+const VERIFY_PEER: &str = "verify_peer";
+
+/// The connection options in the query of the url
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct UrlParams {
+    pub(crate) container_id: Option<String>,
+    pub(crate) idle_time_out: Option<Milliseconds>,
+    pub(crate) max_frame_size: Option<u32>,
+    pub(crate) channel_max: Option<u16>,
+    pub(crate) verify_peer: Option<bool>,
+
+    /// Names of the query parameters that are not connection options
+    pub(crate) unknown: Vec<String>,
+}
+
+impl UrlParams {
+    /// Returns an error if the value of a connection option is invalid
+    pub(crate) fn parse(url: &Url) -> Result<Self, OpenError> {
+        let mut params = Self::default();
+        for (name, value) in url.query_pairs() {
+            let invalid = || OpenError::InvalidUrlParam {
+                name: name.to_string(),
+                value: value.to_string(),
+            };
+            match name.as_ref() {
+                CONTAINER_ID if !value.is_empty() => params.container_id = Some(value.to_string()),
+                IDLE_TIMEOUT_MS => {
+                    params.idle_time_out = Some(value.parse().map_err(|_| invalid())?)
+                }
+                MAX_FRAME_SIZE => {
+                    params.max_frame_size = Some(value.parse().map_err(|_| invalid())?)
+                }
+                CHANNEL_MAX => params.channel_max = Some(value.parse().map_err(|_| invalid())?),
+                VERIFY_PEER => params.verify_peer = Some(value.parse().map_err(|_| invalid())?),
+                CONTAINER_ID => return Err(invalid()),
+                _ => params.unknown.push(name.to_string()),
+            }
+        }
+        Ok(params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use crate::connection::OpenError;
+
+    use super::UrlParams;
+
+    #[test]
+    fn test_parse_url_params() {
+        let url = Url::parse(
+            "amqp://localhost:5672?idle_timeout_ms=30000&max_frame_size=65536&container_id=cli%20tool&verify_peer=false&channel_max=7&foo=bar",
+        )
+        .unwrap();
+        let params = UrlParams::parse(&url).unwrap();
+        assert_eq!(
+            params,
+            UrlParams {
+                container_id: Some("cli tool".to_string()),
+                idle_time_out: Some(30000),
+                max_frame_size: Some(65536),
+                channel_max: Some(7),
+                verify_peer: Some(false),
+                unknown: vec!["foo".to_string()],
+            }
+        );
+
+        let url = Url::parse("amqp://localhost:5672").unwrap();
+        assert_eq!(UrlParams::parse(&url).unwrap(), UrlParams::default());
+    }
+
+    #[test]
+    fn test_parse_invalid_url_params() {
+        for query in [
+            "idle_timeout_ms=-1",
+            "max_frame_size=big",
+            "channel_max=65536",
+            "verify_peer=no",
+            "container_id=",
+        ] {
+            let url = Url::parse(&format!("amqp://localhost:5672?{}", query)).unwrap();
+            match UrlParams::parse(&url) {
+                Err(OpenError::InvalidUrlParam { name, .. }) => {
+                    assert!(query.starts_with(&name))
+                }
+                result => panic!("Expecting InvalidUrlParam, found {:?}", result),
+            }
+        }
+    }
+}
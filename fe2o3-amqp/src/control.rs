@@ -7,12 +7,17 @@ use fe2o3_amqp_types::{
 use tokio::sync::{mpsc::Sender, oneshot};
 
 use crate::{
-    connection::AllocSessionError,
+    connection::{AllocSessionError, SessionRelay},
     endpoint::{InputHandle, OutgoingChannel, OutputHandle},
+    introspect::{LinkSummary, RoutingRecord},
     link::LinkRelay,
-    session::{error::AllocLinkError, frame::SessionIncomingItem},
+    session::error::AllocLinkError,
 };
 
+cfg_testing! {
+    use crate::session::frame::SessionFrameBody;
+}
+
 cfg_transaction! {
     use fe2o3_amqp_types::{
         messaging::Accepted, transaction::TransactionError, transaction::TransactionId,
@@ -26,11 +31,12 @@ pub(crate) enum ConnectionControl {
     // Open,
     Close(Option<definitions::Error>),
     AllocateSession {
-        tx: Sender<SessionIncomingItem>,
+        relay: SessionRelay,
         responder: oneshot::Sender<Result<OutgoingChannel, AllocSessionError>>,
     },
     DeallocateSession(OutgoingChannel),
     GetMaxFrameSize(oneshot::Sender<usize>),
+    GetSessionChannels(oneshot::Sender<Vec<u16>>),
 }
 
 impl std::fmt::Display for ConnectionControl {
@@ -38,11 +44,12 @@ impl std::fmt::Display for ConnectionControl {
         match self {
             Self::Close(err) => write!(f, "Close({:?})", err),
             Self::AllocateSession {
-                tx: _,
+                relay: _,
                 responder: _,
             } => write!(f, "AllocateSession"),
             Self::DeallocateSession(id) => write!(f, "DeallocateSession({})", id.0),
             Self::GetMaxFrameSize(_) => write!(f, "GetMaxFrameSize"),
+            Self::GetSessionChannels(_) => write!(f, "GetSessionChannels"),
         }
     }
 }
@@ -55,6 +62,11 @@ pub(crate) enum SessionControl {
         link_relay: LinkRelay<()>,
         responder: oneshot::Sender<Result<OutputHandle, AllocLinkError>>,
     },
+    ReallocateLink {
+        link_name: String,
+        link_relay: LinkRelay<()>,
+        responder: oneshot::Sender<Result<OutputHandle, AllocLinkError>>,
+    },
     AllocateIncomingLink {
         link_name: String,
         link_relay: LinkRelay<()>,
@@ -62,9 +74,19 @@ pub(crate) enum SessionControl {
         responder: oneshot::Sender<Result<OutputHandle, AllocLinkError>>,
     },
     DeallocateLink(OutputHandle),
+    ReleaseLinkName(String),
     Disposition(Disposition),
     CloseConnectionWithError((ConnectionError, Option<String>)),
     GetMaxFrameSize(oneshot::Sender<usize>),
+    GetLinks(oneshot::Sender<Vec<LinkSummary>>),
+    GetIncompleteIncomingBytes(oneshot::Sender<usize>),
+    GetRoutingHistory(oneshot::Sender<Vec<RoutingRecord>>),
+
+    // Raw frames for protocol testing
+    #[cfg(feature = "testing")]
+    SendRaw(SessionFrameBody),
+    #[cfg(feature = "testing")]
+    ObserveIncoming(Sender<SessionFrameBody>),
 
     // Transaction related controls
     #[cfg(feature = "transaction")]
@@ -94,6 +116,11 @@ impl std::fmt::Display for SessionControl {
                 link_relay: _,
                 responder: _,
             } => write!(f, "AllocateLink"),
+            SessionControl::ReallocateLink {
+                link_name: _,
+                link_relay: _,
+                responder: _,
+            } => write!(f, "ReallocateLink"),
             SessionControl::AllocateIncomingLink {
                 link_name: _,
                 link_relay: _,
@@ -101,9 +128,20 @@ impl std::fmt::Display for SessionControl {
                 responder: _,
             } => write!(f, "AllocateIncomingLink"),
             SessionControl::DeallocateLink(name) => write!(f, "DeallocateLink({:?})", name),
+            SessionControl::ReleaseLinkName(name) => write!(f, "ReleaseLinkName({})", name),
             SessionControl::Disposition(_) => write!(f, "Disposition"),
             SessionControl::CloseConnectionWithError(_) => write!(f, "CloseConnectionWithError"),
             SessionControl::GetMaxFrameSize(_) => write!(f, "GetMaxFrameSize"),
+            SessionControl::GetLinks(_) => write!(f, "GetLinks"),
+            SessionControl::GetIncompleteIncomingBytes(_) => {
+                write!(f, "GetIncompleteIncomingBytes")
+            }
+            SessionControl::GetRoutingHistory(_) => write!(f, "GetRoutingHistory"),
+
+            #[cfg(feature = "testing")]
+            SessionControl::SendRaw(body) => write!(f, "SendRaw({:?})", body),
+            #[cfg(feature = "testing")]
+            SessionControl::ObserveIncoming(_) => write!(f, "ObserveIncoming"),
 
             #[cfg(feature = "transaction")]
             SessionControl::AllocateTransactionId { .. } => write!(f, "AllocateTransactionId"),
@@ -1,15 +1,18 @@
 //! Defines trait for connection implementations
 
-use std::future::Future;
+use std::{future::Future, sync::Arc};
 
 use fe2o3_amqp_types::{
     definitions::Error,
     performatives::{Begin, Close, End, Open},
 };
 use futures_util::Sink;
-use tokio::sync::mpsc;
 
-use crate::{frames::amqp::Frame, session::frame::SessionIncomingItem, SendBound};
+use crate::{
+    connection::SessionRelay,
+    frames::amqp::{Frame, LogRedactor},
+    SendBound,
+};
 
 use super::{IncomingChannel, OutgoingChannel, Session};
 
@@ -24,11 +27,14 @@ pub(crate) trait Connection {
 
     fn local_state(&self) -> &Self::State;
     fn local_open(&self) -> &Open;
+    fn remote_open(&self) -> Option<&Open>;
+    #[cfg_attr(not(any(feature = "tracing", feature = "log")), allow(dead_code))]
+    fn log_redactor(&self) -> Option<LogRedactor>;
 
     // Allocate outgoing channel id and session id to a new session
     fn allocate_session(
         &mut self,
-        tx: mpsc::Sender<SessionIncomingItem>,
+        relay: SessionRelay,
     ) -> Result<OutgoingChannel, Self::AllocError>;
     // Remove outgoing id and session id association
     fn deallocate_session(&mut self, outgoing_channel: OutgoingChannel);
@@ -94,8 +100,15 @@ pub(crate) trait Connection {
     fn on_outgoing_end(&mut self, channel: OutgoingChannel, end: End)
         -> Result<Frame, Self::Error>;
 
-    fn session_tx_by_incoming_channel(
+    fn session_relay_by_incoming_channel(
         &mut self,
         incoming_channel: IncomingChannel,
-    ) -> Option<&mpsc::Sender<SessionIncomingItem>>;
+    ) -> Option<&Arc<SessionRelay>>;
+
+    // Outgoing channels of the active sessions
+    fn session_channels(&self) -> Vec<u16>;
+
+    // Stop relaying frames to the sessions once the connection has stopped, which closes their
+    // incoming channels
+    fn drop_session_relays(&mut self);
 }
@@ -2,8 +2,8 @@
 
 use fe2o3_amqp_types::{
     definitions::{DeliveryNumber, DeliveryTag, Error, Fields, MessageFormat, ReceiverSettleMode},
-    messaging::{DeliveryState, FromBody},
-    performatives::{Attach, Detach, Transfer},
+    messaging::DeliveryState,
+    performatives::{Attach, Detach, Disposition, Transfer},
 };
 use futures_util::Future;
 use tokio::sync::mpsc;
@@ -11,11 +11,12 @@ use tokio::sync::mpsc;
 use crate::{
     control::SessionControl,
     link::{
-        delivery::{Delivery, DeliveryInfo},
+        delivery::{DeliveryInfo, FromPayload},
+        receiver::OnCloseUnsettled,
         state::LinkState,
         LinkFrame,
     },
-    util::{AsByteIterator, IntoReader},
+    util::{AsByteIterator, IntoPayload, IntoReader},
     Payload,
 };
 
@@ -60,6 +61,9 @@ pub(crate) trait LinkExt: Link {
 
     fn local_state(&self) -> &LinkState;
 
+    /// The link is implicitly detached when the session ends
+    fn on_session_ended(&mut self);
+
     fn name(&self) -> &str;
 
     fn output_handle_mut(&mut self) -> &mut Option<OutputHandle>;
@@ -117,19 +121,31 @@ pub(crate) trait SenderLink: Link + LinkExt {
         // The delivery state should be attached on every transfer if specified
         state: Option<DeliveryState>,
         batchable: bool,
+        // The outcome is only reported through the confirm watermark
+        unconfirmed: bool,
     ) -> Result<Settlement, Self::TransferError>
     where
         Fut: Future<Output = Option<LinkFrame>> + Send;
 
-    /// Send message with delivery tag that is obtained by consuming a link credit
+    /// Send message with delivery tag that is obtained by consuming a link credit. The first frame
+    /// is sent with `permit`, which is reserved together with the link credit
     async fn send_payload_with_transfer(
         &mut self,
         writer: &mpsc::Sender<LinkFrame>,
+        permit: mpsc::Permit<'_, LinkFrame>,
         message_format: MessageFormat,
         transfer: Transfer,
         payload: Payload,
+        unconfirmed: bool,
     ) -> Result<Settlement, Self::TransferError>;
 
+    /// Aborts the delivery whose send was dropped after its first frame was handed to the session
+    /// but before its last one
+    async fn abort_incomplete_delivery(
+        &mut self,
+        writer: &mpsc::Sender<LinkFrame>,
+    ) -> Result<(), Self::TransferError>;
+
     /// Note that it is possible for a disposition sent from sender to receiver
     /// to refer to a delivery which has not yet completed (i.e., a delivery
     /// which is spread over multiple frames and not all frames have yet been
@@ -193,16 +209,16 @@ pub(crate) trait ReceiverLink: Link + LinkExt {
 
     // More than one transfer frames should be hanlded by the
     // `Receiver`
-    fn on_complete_transfer<'a, T, P>(
+    fn on_complete_transfer<'a, D, P>(
         &'a mut self,
         transfer: Transfer,
         payload: P,
         section_number: u32,
         section_offset: u64,
-    ) -> Result<Delivery<T>, Self::TransferError>
+    ) -> Result<D, Self::TransferError>
     where
-        for<'de> T: FromBody<'de> + Send,
-        for<'b> P: IntoReader + AsByteIterator<'b> + Send + 'a;
+        D: FromPayload + Send,
+        for<'b> P: IntoReader + IntoPayload + AsByteIterator<'b> + Send + 'a;
 
     async fn dispose(
         &self,
@@ -221,4 +237,12 @@ pub(crate) trait ReceiverLink: Link + LinkExt {
         state: DeliveryState,
         batchable: bool,
     ) -> Result<(), Self::DispositionError>;
+
+    /// Removes the deliveries that are not given an outcome yet from the unsettled map and returns
+    /// the dispositions that settle them with the outcome chosen by `policy`
+    fn settle_on_close(
+        &self,
+        delivery_infos: Vec<DeliveryInfo>,
+        policy: OnCloseUnsettled,
+    ) -> Vec<Disposition>;
 }
@@ -149,4 +149,7 @@ pub(crate) enum Settlement {
         delivery_tag: DeliveryTag,
         outcome: oneshot::Receiver<Option<DeliveryState>>,
     },
+    /// The delivery is unsettled and its outcome is only reported through the confirm watermark
+    /// of the sender
+    Unconfirmed(DeliveryTag),
 }
@@ -10,8 +10,12 @@ use fe2o3_amqp_types::{
 use tokio::sync::mpsc;
 
 use crate::{
+    introspect::{LinkSummary, RoutingRecord},
     link::LinkRelay,
-    session::frame::{SessionFrame, SessionOutgoingItem},
+    session::{
+        frame::{SessionFrame, SessionOutgoingItem},
+        incoming_budget::IncomingPermit,
+    },
     Payload, SendBound,
 };
 
@@ -35,6 +39,14 @@ pub(crate) trait Session {
         link_relay: Option<LinkRelay<()>>,
     ) -> Result<OutputHandle, Self::AllocError>;
 
+    // Allocate a new local handle for a link that is resuming, which may take the name reserved
+    // by its previous detach
+    fn reallocate_link(
+        &mut self,
+        link_name: String,
+        link_relay: LinkRelay<()>,
+    ) -> Result<OutputHandle, Self::AllocError>;
+
     fn allocate_incoming_link(
         &mut self,
         link_name: String,
@@ -44,6 +56,22 @@ pub(crate) trait Session {
 
     fn deallocate_link(&mut self, output_handle: OutputHandle);
 
+    // Release the name reserved by a detached link that will not be resumed
+    fn release_link_name(&mut self, link_name: &str);
+
+    // Tell the links that the session has ended along with the error carried by the End, and
+    // stop relaying frames to them
+    fn notify_links_ended(&mut self);
+
+    // Links that are allocated in the session
+    fn link_summaries(&self) -> Vec<LinkSummary>;
+
+    // Payload bytes of the incoming deliveries that are not complete yet
+    fn incomplete_incoming_bytes(&self) -> usize;
+
+    // The most recent routing decisions of the incoming frames
+    fn routing_history(&self) -> Vec<RoutingRecord>;
+
     fn on_incoming_begin(
         &mut self,
         channel: IncomingChannel,
@@ -61,10 +89,12 @@ pub(crate) trait Session {
         flow: Flow,
     ) -> impl Future<Output = Result<Option<SessionOutgoingItem>, Self::Error>> + Send;
 
+    /// The `permit` is released once the receiving link has taken the transfer
     fn on_incoming_transfer(
         &mut self,
         transfer: Transfer,
         payload: Payload,
+        permit: Option<IncomingPermit>,
     ) -> impl Future<Output = Result<Option<Disposition>, Self::Error>> + Send;
 
     /// An `Ok(Some(Disposition))` means an immediate disposition should be sent back
@@ -81,6 +111,10 @@ pub(crate) trait Session {
     fn on_incoming_end(&mut self, channel: IncomingChannel, end: End)
         -> Result<(), Self::EndError>;
 
+    /// Relays the incoming frames that are waiting for a link to read its earlier frames. This
+    /// never completes if no frame is waiting, and it is cancel safe
+    fn relay_overflow(&mut self) -> impl Future<Output = ()> + Send;
+
     // Handling SessionFrames
     async fn send_begin(
         &mut self,
@@ -98,6 +132,9 @@ pub(crate) trait Session {
 
     fn on_outgoing_flow(&mut self, flow: LinkFlow) -> Result<SessionFrame, Self::Error>;
 
+    /// A `Some(frame)` means a session flow should be sent to re-advertise the incoming-window
+    fn replenish_incoming_window(&mut self) -> Option<SessionFrame>;
+
     fn on_outgoing_transfer(
         &mut self,
         input_handle: InputHandle,
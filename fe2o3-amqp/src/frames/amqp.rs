@@ -1,16 +1,18 @@
 //! AMQP frame type and corresponding encoder and decoder
 
+use std::borrow::Cow;
+
 use bytes::{Buf, BufMut, BytesMut};
 use fe2o3_amqp_types::performatives::{
     Attach, Begin, Close, Detach, Disposition, End, Flow, Open, Performative, Transfer,
 };
 use serde::{ser::Serialize, Deserialize};
-use serde_amqp::{de::Deserializer, read::IoReader};
+use serde_amqp::{de::Deserializer, descriptor::Descriptor, read::SliceReader};
 use tokio_util::codec::{Decoder, Encoder};
 
 use crate::Payload;
 
-use super::{Error, FRAME_TYPE_AMQP};
+use super::{Error, FRAME_HEADER_SIZE, FRAME_TYPE_AMQP};
 
 /// AMQP frame
 #[derive(Debug)]
@@ -225,49 +227,200 @@ impl Decoder for FrameDecoder {
         let doff = src.get_u8();
         let ftype = src.get_u8();
         let channel = src.get_u16();
+        let invalid = |kind| Error::InvalidFrame(InvalidFrame { channel, kind });
 
         // check type byte
         if ftype != FRAME_TYPE_AMQP {
-            return Err(Error::NotImplemented);
+            return Err(invalid(InvalidFrameKind::UnknownFrameType(ftype)));
         }
 
-        match doff {
-            2 => {}
-            _ => return Err(Error::NotImplemented),
+        // The extended header is ignored
+        let extended_header_size = (doff as usize * 4).checked_sub(FRAME_HEADER_SIZE);
+        match extended_header_size {
+            Some(size) if size <= src.len() => src.advance(size),
+            _ => return Err(invalid(InvalidFrameKind::InvalidDataOffset(doff))),
         }
 
-        let body = if src.is_empty() {
-            FrameBody::Empty
-        } else {
-            let reader = IoReader::new(src.reader());
-            let mut deserializer = Deserializer::new(reader);
-            let performative: Performative = Deserialize::deserialize(&mut deserializer)?;
-
-            match performative {
-                Performative::Open(performative) => FrameBody::Open(performative),
-                Performative::Begin(performative) => FrameBody::Begin(performative),
-                Performative::Attach(performative) => FrameBody::Attach(performative),
-                Performative::Transfer(performative) => {
-                    let payload = src.split().into();
-                    FrameBody::Transfer {
-                        performative,
-                        payload,
-                    }
-                }
-                Performative::Flow(performative) => FrameBody::Flow(performative),
-                Performative::Disposition(performative) => FrameBody::Disposition(performative),
-                Performative::Detach(performative) => FrameBody::Detach(performative),
-                Performative::End(performative) => FrameBody::End(performative),
-                Performative::Close(performative) => FrameBody::Close(performative),
-            }
+        let body = decode_body(src).map_err(|source| invalid(classify_body_error(src, source)))?;
+        Ok(Some(Frame { channel, body }))
+    }
+}
+
+/// Tells whether the body that failed to decode is a malformed performative or a described type
+/// that is not a performative at all, which only needs the descriptor to be decodable
+fn classify_body_error(src: &[u8], source: serde_amqp::Error) -> InvalidFrameKind {
+    let mut deserializer = Deserializer::new(SliceReader::new(src));
+    match Descriptor::deserialize(&mut deserializer) {
+        Ok(descriptor) => match PerformativeKind::from_descriptor(&descriptor) {
+            Some(performative) => InvalidFrameKind::Malformed {
+                performative: Some(performative),
+                source,
+            },
+            None => InvalidFrameKind::UnknownDescriptor(descriptor),
+        },
+        Err(_) => InvalidFrameKind::Malformed {
+            performative: None,
+            source,
+        },
+    }
+}
+
+/// The performative of a frame, which is known from the descriptor even if the rest of the body
+/// cannot be decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PerformativeKind {
+    /// Open performative
+    Open,
+
+    /// Begin performative
+    Begin,
+
+    /// Attach performative
+    Attach,
+
+    /// Flow performative
+    Flow,
+
+    /// Transfer performative
+    Transfer,
+
+    /// Disposition performative
+    Disposition,
+
+    /// Detach performative
+    Detach,
+
+    /// End performative
+    End,
+
+    /// Close performative
+    Close,
+}
+
+impl PerformativeKind {
+    fn from_descriptor(descriptor: &Descriptor) -> Option<Self> {
+        let kind = match descriptor {
+            Descriptor::Name(name) => match name.as_str() {
+                "amqp:open:list" => Self::Open,
+                "amqp:begin:list" => Self::Begin,
+                "amqp:attach:list" => Self::Attach,
+                "amqp:flow:list" => Self::Flow,
+                "amqp:transfer:list" => Self::Transfer,
+                "amqp:disposition:list" => Self::Disposition,
+                "amqp:detach:list" => Self::Detach,
+                "amqp:end:list" => Self::End,
+                "amqp:close:list" => Self::Close,
+                _ => return None,
+            },
+            Descriptor::Code(code) => match code {
+                0x0000_0000_0000_0010 => Self::Open,
+                0x0000_0000_0000_0011 => Self::Begin,
+                0x0000_0000_0000_0012 => Self::Attach,
+                0x0000_0000_0000_0013 => Self::Flow,
+                0x0000_0000_0000_0014 => Self::Transfer,
+                0x0000_0000_0000_0015 => Self::Disposition,
+                0x0000_0000_0000_0016 => Self::Detach,
+                0x0000_0000_0000_0017 => Self::End,
+                0x0000_0000_0000_0018 => Self::Close,
+                _ => return None,
+            },
         };
+        Some(kind)
+    }
 
-        Ok(Some(Frame { channel, body }))
+    /// Whether the performative is handled by a session, which does not include the Begin and
+    /// End that map and unmap the session
+    pub fn is_session_scoped(&self) -> bool {
+        matches!(
+            self,
+            Self::Attach | Self::Flow | Self::Transfer | Self::Disposition | Self::Detach
+        )
+    }
+}
+
+/// A frame that is delimited correctly but cannot be decoded
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid frame on channel {channel}: {kind}")]
+pub struct InvalidFrame {
+    /// The channel of the frame
+    pub channel: u16,
+
+    /// What is wrong with the frame
+    pub kind: InvalidFrameKind,
+}
+
+/// What is wrong with an [`InvalidFrame`]
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum InvalidFrameKind {
+    /// The frame is not an AMQP frame
+    #[error("Unknown frame type {0:#04x}")]
+    UnknownFrameType(u8),
+
+    /// The data offset is smaller than the frame header or points past the end of the frame
+    #[error("Invalid data offset {0}")]
+    InvalidDataOffset(u8),
+
+    /// The body is a described type that is not a performative
+    #[error("Unknown descriptor {0:?}")]
+    UnknownDescriptor(Descriptor),
+
+    /// The body cannot be decoded. The performative is `None` if not even the descriptor can be
+    /// decoded
+    #[error("Malformed performative {performative:?}")]
+    Malformed {
+        /// The performative named by the descriptor
+        performative: Option<PerformativeKind>,
+
+        /// The decode error
+        #[source]
+        source: serde_amqp::Error,
+    },
+}
+
+impl InvalidFrameKind {
+    /// Whether the frame can be skipped without affecting the state of the connection or any
+    /// session, which is the case for frames that this implementation does not know about
+    pub fn is_skippable(&self) -> bool {
+        matches!(self, Self::UnknownFrameType(_) | Self::UnknownDescriptor(_))
     }
 }
 
+/// Decodes the frame body that follows the frame header
+pub(crate) fn decode_body(src: &mut BytesMut) -> Result<FrameBody, serde_amqp::Error> {
+    if src.is_empty() {
+        return Ok(FrameBody::Empty);
+    }
+
+    // The performative is decoded in place from the frame, which is a slice of the read
+    // buffer, and only the remaining bytes are kept as the payload
+    let mut deserializer = Deserializer::new(SliceReader::new(&src[..]));
+    let performative: Performative = Deserialize::deserialize(&mut deserializer)?;
+    let consumed = src.len() - deserializer.into_reader().remaining().len();
+    src.advance(consumed);
+
+    let body = match performative {
+        Performative::Open(performative) => FrameBody::Open(performative),
+        Performative::Begin(performative) => FrameBody::Begin(performative),
+        Performative::Attach(performative) => FrameBody::Attach(performative),
+        Performative::Transfer(performative) => {
+            let payload = src.split().freeze();
+            FrameBody::Transfer {
+                performative,
+                payload,
+            }
+        }
+        Performative::Flow(performative) => FrameBody::Flow(performative),
+        Performative::Disposition(performative) => FrameBody::Disposition(performative),
+        Performative::Detach(performative) => FrameBody::Detach(performative),
+        Performative::End(performative) => FrameBody::End(performative),
+        Performative::Close(performative) => FrameBody::Close(performative),
+    };
+    Ok(body)
+}
+
 /// AMQP frame body
-// #[derive(Debug)]
+#[derive(Clone)]
 pub enum FrameBody {
     // Frames handled by Link
     /// Attach performative
@@ -333,14 +486,49 @@ impl std::fmt::Debug for FrameBody {
     }
 }
 
+/// A function that rewrites a frame body before it is formatted in a trace or log event, eg. to
+/// mask the credentials in the properties of an Open or Attach.
+///
+/// The redactor is only called when the event is enabled. It is set with
+/// [`connection::Builder::log_redactor`](crate::connection::Builder::log_redactor) and, on the
+/// listener side, with `acceptor::Builder::log_redactor`.
+pub type LogRedactor = for<'a> fn(&'a FrameBody) -> Cow<'a, FrameBody>;
+
+/// Formats the frame body with `Debug` after it is rewritten by the redactor
+#[cfg(any(feature = "tracing", feature = "log"))]
+pub(crate) struct Redacted<'a> {
+    body: Cow<'a, FrameBody>,
+    redactor: Option<LogRedactor>,
+}
+
+#[cfg(any(feature = "tracing", feature = "log"))]
+impl<'a> Redacted<'a> {
+    pub(crate) fn new(body: Cow<'a, FrameBody>, redactor: Option<LogRedactor>) -> Self {
+        Self { body, redactor }
+    }
+}
+
+#[cfg(any(feature = "tracing", feature = "log"))]
+impl<'a> std::fmt::Debug for Redacted<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.redactor {
+            Some(redactor) => redactor(&self.body).fmt(f),
+            None => self.body.fmt(f),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use bytes::BytesMut;
     use tokio_util::codec::{Decoder, Encoder};
 
-    use crate::frames::amqp::{FrameDecoder, FrameEncoder};
+    use crate::frames::{
+        amqp::{FrameDecoder, FrameEncoder},
+        Error,
+    };
 
-    use super::Frame;
+    use super::{Frame, FrameBody, InvalidFrame, InvalidFrameKind, PerformativeKind};
 
     #[test]
     fn test_encoding_empty_frame() {
@@ -356,4 +544,46 @@ mod tests {
         let mut src = BytesMut::from(&[0x02, 0x00, 0x00, 0x00][..]);
         let _frame = decoder.decode(&mut src).unwrap();
     }
+
+    #[test]
+    fn test_decode_invalid_frames() {
+        let decode = |bytes: &[u8]| FrameDecoder {}.decode(&mut BytesMut::from(bytes));
+
+        // The extended header is skipped
+        let frame = decode(&[0x03, 0x00, 0x00, 0x01, 0xff, 0xff, 0xff, 0xff])
+            .unwrap()
+            .unwrap();
+        assert!(matches!(frame.body, FrameBody::Empty));
+
+        match decode(&[0x02, 0x00, 0x00, 0x01, 0x00, 0x53, 0x70, 0x45]) {
+            Err(Error::InvalidFrame(InvalidFrame { channel: 1, kind })) => {
+                assert!(kind.is_skippable());
+                assert!(matches!(kind, InvalidFrameKind::UnknownDescriptor(_)));
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        match decode(&[
+            0x02, 0x00, 0x00, 0x01, 0x00, 0x53, 0x13, 0xc0, 0x02, 0x01, 0x40,
+        ]) {
+            Err(Error::InvalidFrame(InvalidFrame { channel: 1, kind })) => {
+                assert!(!kind.is_skippable());
+                assert!(matches!(
+                    kind,
+                    InvalidFrameKind::Malformed {
+                        performative: Some(PerformativeKind::Flow),
+                        ..
+                    }
+                ));
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        match decode(&[0x01, 0x00, 0x00, 0x00]) {
+            Err(Error::InvalidFrame(InvalidFrame { kind, .. })) => {
+                assert!(matches!(kind, InvalidFrameKind::InvalidDataOffset(1)));
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
 }
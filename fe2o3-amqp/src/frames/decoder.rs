@@ -0,0 +1,419 @@
+//! Transport independent decoder of captured AMQP byte streams
+//!
+//! The [`Decoder`] parses the bytes sent in one direction of a connection, eg. exported from a
+//! packet capture, into protocol headers, SASL frames and AMQP frames. The bytes can be fed in
+//! chunks of any size.
+//!
+//! # Example
+//!
+//! ```rust
+//! use fe2o3_amqp::frames::decoder::{Decoder, ParsedFrame};
+//!
+//! let mut decoder = Decoder::new();
+//! decoder.feed(b"AMQP\x00\x01\x00\x00");
+//! // An empty frame on channel 0
+//! decoder.feed(&[0x00, 0x00, 0x00, 0x08, 0x02, 0x00, 0x00, 0x00]);
+//!
+//! while let Some(frame) = decoder.next_frame().unwrap() {
+//!     println!("{}", frame);
+//! }
+//! ```
+
+use bytes::{Buf, BytesMut};
+
+use crate::transport::protocol_header::{ProtocolHeader, ProtocolId};
+
+use super::{amqp, sasl, FRAME_HEADER_SIZE, FRAME_TYPE_AMQP, FRAME_TYPE_SASL};
+
+/// Size of the protocol header
+const PROTOCOL_HEADER_SIZE: usize = 8;
+
+/// Size of the frame size field
+const FRAME_SIZE_SIZE: usize = 4;
+
+/// Error with decoding a captured byte stream
+#[derive(Debug, thiserror::Error)]
+#[error("{kind} at byte offset {offset}")]
+pub struct DecodeError {
+    /// Offset of the protocol header or the frame in the stream
+    pub offset: u64,
+
+    /// What went wrong
+    pub kind: DecodeErrorKind,
+}
+
+/// Kind of [`DecodeError`]
+#[derive(Debug, thiserror::Error)]
+pub enum DecodeErrorKind {
+    /// The bytes are not a valid protocol header
+    #[error("Invalid protocol header {0:02x?}")]
+    InvalidProtocolHeader([u8; 8]),
+
+    /// The frames following a TLS protocol header are encrypted
+    #[error("TLS encrypted stream")]
+    TlsEncrypted,
+
+    /// The frame size is smaller than the frame header
+    #[error("Invalid frame size {0}")]
+    InvalidFrameSize(u32),
+
+    /// The data offset points into the frame header or beyond the end of the frame
+    #[error("Invalid data offset {0}")]
+    InvalidDataOffset(u8),
+
+    /// The frame type is neither AMQP nor SASL
+    #[error("Unknown frame type {0:#04x}")]
+    UnknownFrameType(u8),
+
+    /// The performative or the SASL frame body could not be decoded
+    #[error("Decode error {0}")]
+    Body(#[source] serde_amqp::Error),
+
+    /// The stream ends in the middle of a protocol header or a frame
+    #[error("Incomplete stream, {0} bytes left")]
+    Incomplete(usize),
+}
+
+/// A protocol header or a frame decoded from a captured byte stream
+#[derive(Debug)]
+pub enum ParsedFrame {
+    /// Protocol header
+    Header {
+        /// Offset of the protocol header in the stream
+        offset: u64,
+
+        /// Protocol header
+        header: ProtocolHeader,
+    },
+
+    /// AMQP frame
+    Amqp {
+        /// Offset of the frame in the stream
+        offset: u64,
+
+        /// Channel of the frame
+        channel: u16,
+
+        /// Performative and payload of the frame
+        body: amqp::FrameBody,
+    },
+
+    /// SASL frame
+    Sasl {
+        /// Offset of the frame in the stream
+        offset: u64,
+
+        /// SASL frame
+        frame: sasl::Frame,
+    },
+}
+
+impl ParsedFrame {
+    /// Offset of the protocol header or the frame in the stream
+    pub fn offset(&self) -> u64 {
+        match self {
+            ParsedFrame::Header { offset, .. }
+            | ParsedFrame::Amqp { offset, .. }
+            | ParsedFrame::Sasl { offset, .. } => *offset,
+        }
+    }
+}
+
+impl std::fmt::Display for ParsedFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParsedFrame::Header { offset, header } => write!(
+                f,
+                "{:>8}  HEADER  {:?} {}.{}.{}",
+                offset, header.id, header.major, header.minor, header.revision
+            ),
+            ParsedFrame::Amqp {
+                offset,
+                channel,
+                body,
+            } => write!(f, "{:>8}  AMQP    ch {:<5} {:?}", offset, channel, body),
+            ParsedFrame::Sasl { offset, frame } => {
+                write!(f, "{:>8}  SASL            {:?}", offset, frame)
+            }
+        }
+    }
+}
+
+/// What is expected next in the stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Expecting {
+    Header,
+    SaslFrame,
+    Frame,
+    Encrypted,
+}
+
+/// Decoder of the bytes sent in one direction of a connection
+///
+/// The stream is expected to start with a protocol header. The SASL frames are followed by the
+/// AMQP protocol header, which is recognized in place of the next frame since the client side of
+/// the negotiation does not see the SASL outcome in its own direction.
+#[derive(Debug)]
+pub struct Decoder {
+    buf: BytesMut,
+    offset: u64,
+    expecting: Expecting,
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder {
+    /// Creates a decoder that expects a protocol header at the start of the stream
+    pub fn new() -> Self {
+        Self {
+            buf: BytesMut::new(),
+            offset: 0,
+            expecting: Expecting::Header,
+        }
+    }
+
+    /// Appends a chunk of the stream
+    pub fn feed(&mut self, chunk: &[u8]) {
+        self.buf.extend_from_slice(chunk);
+    }
+
+    /// Offset in the stream of the bytes that are not decoded yet
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Number of bytes that are fed but not decoded yet
+    pub fn remaining(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Decodes the next protocol header or frame
+    ///
+    /// `Ok(None)` is returned if more bytes need to be fed. The stream cannot be decoded any
+    /// further after an error is returned.
+    pub fn next_frame(&mut self) -> Result<Option<ParsedFrame>, DecodeError> {
+        match self.expecting {
+            Expecting::Header => self.next_header(),
+            Expecting::SaslFrame => match self.buf.starts_with(b"AMQP") {
+                true => self.next_header(),
+                // The frame size cannot be decided until at least 4 bytes are fed
+                false if self.buf.len() < FRAME_SIZE_SIZE => Ok(None),
+                false => self.next_body(),
+            },
+            Expecting::Frame => self.next_body(),
+            Expecting::Encrypted => match self.buf.is_empty() {
+                true => Ok(None),
+                false => Err(self.error(DecodeErrorKind::TlsEncrypted)),
+            },
+        }
+    }
+
+    /// Returns an error if the stream ends in the middle of a protocol header or a frame
+    pub fn finish(&self) -> Result<(), DecodeError> {
+        match self.buf.len() {
+            0 => Ok(()),
+            len => Err(self.error(DecodeErrorKind::Incomplete(len))),
+        }
+    }
+
+    fn error(&self, kind: DecodeErrorKind) -> DecodeError {
+        DecodeError {
+            offset: self.offset,
+            kind,
+        }
+    }
+
+    fn next_header(&mut self) -> Result<Option<ParsedFrame>, DecodeError> {
+        if self.buf.len() < PROTOCOL_HEADER_SIZE {
+            return Ok(None);
+        }
+
+        let mut bytes = [0u8; PROTOCOL_HEADER_SIZE];
+        bytes.copy_from_slice(&self.buf[..PROTOCOL_HEADER_SIZE]);
+        let header = ProtocolHeader::try_from(bytes)
+            .map_err(|bytes| self.error(DecodeErrorKind::InvalidProtocolHeader(bytes)))?;
+
+        self.expecting = match header.id {
+            ProtocolId::Tls => Expecting::Encrypted,
+            ProtocolId::Sasl => Expecting::SaslFrame,
+            ProtocolId::Amqp => Expecting::Frame,
+        };
+        let offset = self.offset;
+        self.buf.advance(PROTOCOL_HEADER_SIZE);
+        self.offset += PROTOCOL_HEADER_SIZE as u64;
+        Ok(Some(ParsedFrame::Header { offset, header }))
+    }
+
+    fn next_body(&mut self) -> Result<Option<ParsedFrame>, DecodeError> {
+        if self.buf.len() < FRAME_SIZE_SIZE {
+            return Ok(None);
+        }
+
+        let size = u32::from_be_bytes([self.buf[0], self.buf[1], self.buf[2], self.buf[3]]);
+        if (size as usize) < FRAME_HEADER_SIZE {
+            return Err(self.error(DecodeErrorKind::InvalidFrameSize(size)));
+        }
+        if self.buf.len() < size as usize {
+            return Ok(None);
+        }
+
+        let doff = self.buf[4];
+        let ftype = self.buf[5];
+        let channel = u16::from_be_bytes([self.buf[6], self.buf[7]]);
+        let data_offset = doff as usize * 4;
+        if data_offset < FRAME_HEADER_SIZE || data_offset > size as usize {
+            return Err(self.error(DecodeErrorKind::InvalidDataOffset(doff)));
+        }
+        if ftype != FRAME_TYPE_AMQP && ftype != FRAME_TYPE_SASL {
+            return Err(self.error(DecodeErrorKind::UnknownFrameType(ftype)));
+        }
+
+        let offset = self.offset;
+        let mut frame = self.buf.split_to(size as usize);
+        // The extended header is ignored
+        frame.advance(data_offset);
+        self.offset += size as u64;
+
+        let parsed = match ftype {
+            FRAME_TYPE_AMQP => {
+                let body = amqp::decode_body(&mut frame).map_err(|error| DecodeError {
+                    offset,
+                    kind: DecodeErrorKind::Body(error),
+                })?;
+                ParsedFrame::Amqp {
+                    offset,
+                    channel,
+                    body,
+                }
+            }
+            _ => {
+                let frame = sasl::decode_body(&frame[..]).map_err(|error| DecodeError {
+                    offset,
+                    kind: DecodeErrorKind::Body(error),
+                })?;
+                // The AMQP protocol header follows the outcome of the SASL negotiation
+                if let sasl::Frame::Outcome(_) = frame {
+                    self.expecting = Expecting::Header;
+                }
+                ParsedFrame::Sasl { offset, frame }
+            }
+        };
+        Ok(Some(parsed))
+    }
+}
+
+/// Decodes a complete captured byte stream
+///
+/// An error is returned if the stream ends in the middle of a protocol header or a frame.
+pub fn decode_stream(bytes: &[u8]) -> Result<Vec<ParsedFrame>, DecodeError> {
+    let mut decoder = Decoder::new();
+    decoder.feed(bytes);
+
+    let mut frames = Vec::new();
+    while let Some(frame) = decoder.next_frame()? {
+        frames.push(frame);
+    }
+    decoder.finish()?;
+    Ok(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_stream, DecodeErrorKind, Decoder, ParsedFrame};
+    use crate::frames::amqp::FrameBody;
+
+    const AMQP_HEADER: &[u8] = b"AMQP\x00\x01\x00\x00";
+    const EMPTY_FRAME: &[u8] = &[0x00, 0x00, 0x00, 0x08, 0x02, 0x00, 0x00, 0x03];
+
+    #[test]
+    fn test_decode_in_chunks_of_one_byte() {
+        let stream = [AMQP_HEADER, EMPTY_FRAME].concat();
+        let mut decoder = Decoder::new();
+        let mut frames = Vec::new();
+        for byte in stream.iter() {
+            decoder.feed(&[*byte]);
+            while let Some(frame) = decoder.next_frame().unwrap() {
+                frames.push(frame);
+            }
+        }
+        decoder.finish().unwrap();
+
+        assert_eq!(frames.len(), 2);
+        assert!(matches!(frames[0], ParsedFrame::Header { offset: 0, .. }));
+        assert!(matches!(
+            frames[1],
+            ParsedFrame::Amqp {
+                offset: 8,
+                channel: 3,
+                body: FrameBody::Empty
+            }
+        ));
+    }
+
+    #[test]
+    fn test_extended_header_is_skipped() {
+        let frame = [
+            0x00, 0x00, 0x00, 0x0c, 0x03, 0x00, 0x00, 0x01, 0xff, 0xff, 0xff, 0xff,
+        ];
+        let frames = decode_stream(&[AMQP_HEADER, &frame[..]].concat()).unwrap();
+        assert!(matches!(
+            frames[1],
+            ParsedFrame::Amqp {
+                channel: 1,
+                body: FrameBody::Empty,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_errors_report_offsets() {
+        let error = decode_stream(b"HTTP/1.1").unwrap_err();
+        assert_eq!(error.offset, 0);
+        assert!(matches!(
+            error.kind,
+            DecodeErrorKind::InvalidProtocolHeader(_)
+        ));
+
+        let frame = [0x00, 0x00, 0x00, 0x04];
+        let error = decode_stream(&[AMQP_HEADER, EMPTY_FRAME, &frame[..]].concat()).unwrap_err();
+        assert_eq!(error.offset, 16);
+        assert!(matches!(error.kind, DecodeErrorKind::InvalidFrameSize(4)));
+
+        let frame = [0x00, 0x00, 0x00, 0x08, 0x01, 0x00, 0x00, 0x00];
+        let error = decode_stream(&[AMQP_HEADER, &frame[..]].concat()).unwrap_err();
+        assert_eq!(error.offset, 8);
+        assert!(matches!(error.kind, DecodeErrorKind::InvalidDataOffset(1)));
+
+        let frame = [0x00, 0x00, 0x00, 0x08, 0x02, 0x07, 0x00, 0x00];
+        let error = decode_stream(&[AMQP_HEADER, &frame[..]].concat()).unwrap_err();
+        assert!(matches!(
+            error.kind,
+            DecodeErrorKind::UnknownFrameType(0x07)
+        ));
+
+        // A described list with an unknown descriptor
+        let frame = [
+            0x00, 0x00, 0x00, 0x0c, 0x02, 0x00, 0x00, 0x00, 0x00, 0x53, 0x30, 0x45,
+        ];
+        let error = decode_stream(&[AMQP_HEADER, EMPTY_FRAME, &frame[..]].concat()).unwrap_err();
+        assert_eq!(error.offset, 16);
+        assert!(matches!(error.kind, DecodeErrorKind::Body(_)));
+
+        let error = decode_stream(&[AMQP_HEADER, &EMPTY_FRAME[..5]].concat()).unwrap_err();
+        assert_eq!(error.offset, 8);
+        assert!(matches!(error.kind, DecodeErrorKind::Incomplete(5)));
+    }
+
+    #[test]
+    fn test_tls_stream_is_not_decoded() {
+        let error = decode_stream(&[&b"AMQP\x02\x01\x00\x00"[..], &[0x16, 0x03, 0x01]].concat())
+            .unwrap_err();
+        assert_eq!(error.offset, 8);
+        assert!(matches!(error.kind, DecodeErrorKind::TlsEncrypted));
+    }
+}
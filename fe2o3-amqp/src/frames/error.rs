@@ -1,5 +1,7 @@
 use std::io;
 
+use super::amqp::InvalidFrame;
+
 /// Errors associated with frame encoder and decoder
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -9,18 +11,22 @@ pub enum Error {
 
     /// AMQP error: decode error
     #[error("Decode Error")]
-    DecodeError(String),
+    DecodeError(#[source] serde_amqp::Error),
 
     /// AMQP error: not implemented
     #[error("AmqpError: NotImplemented")]
     NotImplemented,
+
+    /// A frame that cannot be decoded, which may be skipped or be fatal to only one session
+    #[error(transparent)]
+    InvalidFrame(InvalidFrame),
 }
 
 impl From<serde_amqp::Error> for Error {
     fn from(err: serde_amqp::Error) -> Self {
         match err {
             serde_amqp::Error::Io(e) => Self::Io(e),
-            other => Self::DecodeError(other.to_string()),
+            other => Self::DecodeError(other),
         }
     }
 }
@@ -1,13 +1,20 @@
 //! Implements frame encoder and decoder
 
 pub mod amqp;
+pub mod decoder;
 pub mod sasl;
 
+pub use decoder::decode_stream;
+
 /// Type byte of AMQP frame
 pub const FRAME_TYPE_AMQP: u8 = 0x00;
 
 /// Type byte of SASL frame
 pub const FRAME_TYPE_SASL: u8 = 0x01;
 
+/// Size of the frame header, which includes the 4 bytes of the frame size, doff, type and the 2
+/// bytes of the type specific field
+pub(crate) const FRAME_HEADER_SIZE: usize = 8;
+
 mod error;
 pub use error::Error;
@@ -6,7 +6,7 @@ use serde::{
 };
 
 use fe2o3_amqp_types::sasl::{SaslChallenge, SaslInit, SaslMechanisms, SaslOutcome, SaslResponse};
-use serde_amqp::read::IoReader;
+use serde_amqp::read::SliceReader;
 use tokio_util::codec::{Decoder, Encoder};
 
 use super::{Error, FRAME_TYPE_SASL};
@@ -60,7 +60,6 @@ impl Decoder for FrameCodec {
 
     fn decode(&mut self, src: &mut bytes::BytesMut) -> Result<Option<Self::Item>, Self::Error> {
         use bytes::Buf;
-        use serde_amqp::de::Deserializer;
 
         let doff = src.get_u8();
         let ftype = src.get_u8();
@@ -74,13 +73,20 @@ impl Decoder for FrameCodec {
             return Err(Error::NotImplemented);
         }
 
-        let reader = IoReader::new(src.reader());
-        let mut deserializer = Deserializer::new(reader);
-        let frame: Frame = Deserialize::deserialize(&mut deserializer)?;
+        let frame = decode_body(&src[..])?;
         Ok(Some(frame))
     }
 }
 
+/// Decodes the frame body that follows the frame header
+pub(crate) fn decode_body(src: &[u8]) -> Result<Frame, serde_amqp::Error> {
+    use serde_amqp::de::Deserializer;
+
+    let reader = SliceReader::new(src);
+    let mut deserializer = Deserializer::new(reader);
+    Deserialize::deserialize(&mut deserializer)
+}
+
 impl ser::Serialize for Frame {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -0,0 +1,157 @@
+//! Introspection of the connection and session event loops for debugging
+//!
+//! [`ConnectionHandle::session_channels`](crate::connection::ConnectionHandle::session_channels)
+//! and [`SessionHandle::links`](crate::session::SessionHandle::links) query the event loops
+//! directly, so the answers reflect the current state of the event loops.
+//! [`SessionHandle::routing_history`](crate::session::SessionHandle::routing_history) returns the
+//! links that the recent incoming frames were routed to, which helps finding frames that refer to
+//! a stale or colliding handle. A query that is not
+//! answered within a short timeout, for example because the event loop is wedged or has
+//! stopped, yields [`Queried::Unresponsive`] which is printed as `<unresponsive>`.
+//!
+//! # Example
+//!
+//! Printing the tree of a live connection
+//!
+//! ```rust,ignore
+//! let mut connection = Connection::open("connection-1", "amqp://localhost:5672").await.unwrap();
+//! let mut session = Session::begin(&mut connection).await.unwrap();
+//! let sender = Sender::attach(&mut session, "rust-sender-link-1", "q1").await.unwrap();
+//! let receiver = Receiver::attach(&mut session, "rust-receiver-link-1", "q1").await.unwrap();
+//!
+//! println!("{:?}", connection);
+//! println!("  session channels: {:?}", connection.session_channels().await);
+//! println!("  {:?}", session);
+//! println!("    links: {:?}", session.links().await);
+//! println!("    {:?}", sender);
+//! println!("    {:?}", receiver);
+//! ```
+//!
+//! which prints something like
+//!
+//! ```text
+//! ConnectionHandle { is_closed: false, remote_container_id: "broker", max_frame_size: 65536, active_sessions: 1 }
+//!   session channels: [0]
+//!   SessionHandle { is_ended: false, buffered_incoming_bytes: 0 }
+//!     links: [LinkSummary { name: "rust-sender-link-1", role: Sender, output_handle: 0, input_handle: Some(0) }, LinkSummary { name: "rust-receiver-link-1", role: Receiver, output_handle: 1, input_handle: Some(1) }]
+//!     Sender { name: "rust-sender-link-1", state: Attached, snd_settle_mode: Mixed, rcv_settle_mode: First, source: None, target: Some("q1"), flow: LinkFlowSnapshot { .. } }
+//!     Receiver { name: "rust-receiver-link-1", state: Attached, snd_settle_mode: Mixed, rcv_settle_mode: First, source: Some("q1"), target: None, flow: LinkFlowSnapshot { .. } }
+//! ```
+
+use std::fmt;
+
+use fe2o3_amqp_types::definitions::Role;
+
+cfg_not_wasm32! {
+    use std::time::Duration;
+
+    use tokio::sync::{mpsc, oneshot};
+
+    use crate::rt::timeout;
+
+    /// Duration a query waits for the event loop to answer
+    pub(crate) const QUERY_TIMEOUT: Duration = Duration::from_millis(500);
+
+    /// Sends a query to an event loop and waits for the answer for at most [`QUERY_TIMEOUT`]
+    pub(crate) async fn query<C, T>(
+        control: &mpsc::Sender<C>,
+        make_query: impl FnOnce(oneshot::Sender<T>) -> C,
+    ) -> Queried<T> {
+        let (tx, rx) = oneshot::channel();
+        let answer = async {
+            control.send(make_query(tx)).await.ok()?;
+            rx.await.ok()
+        };
+        match timeout(QUERY_TIMEOUT, answer).await {
+            Ok(Some(value)) => Queried::Answered(value),
+            Ok(None) | Err(_) => Queried::Unresponsive,
+        }
+    }
+}
+
+/// Answer of an event loop to an introspection query
+#[derive(Clone, PartialEq, Eq)]
+pub enum Queried<T> {
+    /// The event loop answered the query
+    Answered(T),
+
+    /// The event loop did not answer within the timeout or has stopped
+    Unresponsive,
+}
+
+impl<T> Queried<T> {
+    /// Returns the answer if the event loop has answered the query
+    pub fn answered(self) -> Option<T> {
+        match self {
+            Queried::Answered(value) => Some(value),
+            Queried::Unresponsive => None,
+        }
+    }
+
+    /// Whether the event loop did not answer the query
+    pub fn is_unresponsive(&self) -> bool {
+        matches!(self, Queried::Unresponsive)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Queried<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Queried::Answered(value) => value.fmt(f),
+            Queried::Unresponsive => f.write_str("<unresponsive>"),
+        }
+    }
+}
+
+/// A link of a session as seen by the session event loop
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkSummary {
+    /// Name of the link
+    pub name: String,
+
+    /// Role of the local link endpoint
+    pub role: Role,
+
+    /// Handle of the local link endpoint
+    pub output_handle: u32,
+
+    /// Handle of the remote link endpoint, which is `None` until the remote Attach is received
+    pub input_handle: Option<u32>,
+}
+
+/// How the session event loop routed an incoming frame that refers to a link by its handle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoutingRecord {
+    /// Name of the performative, eg. `"transfer"`
+    pub performative: &'static str,
+
+    /// The handle in the frame, which is the handle of the remote link endpoint
+    pub handle: u32,
+
+    /// What the session did with the frame
+    pub decision: RoutingDecision,
+}
+
+/// What the session event loop did with an incoming frame that refers to a link by its handle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RoutingDecision {
+    /// The frame is routed to the local link endpoint with this output handle
+    Routed {
+        /// Handle of the local link endpoint
+        output_handle: u32,
+    },
+
+    /// The Attach is passed to the link acceptor of the listener session
+    Forwarded,
+
+    /// No local link endpoint has the name in the Attach, which ends the session
+    UnknownLinkName,
+
+    /// No link is attached with the handle, which ends the session with
+    /// `amqp:session:unattached-handle`
+    UnattachedHandle,
+
+    /// The handle or the name in the Attach is still used by a link that is not fully detached,
+    /// which ends the session with `amqp:session:handle-in-use`
+    HandleInUse,
+}
@@ -16,16 +16,22 @@
 //! # Feature flags
 //!
 //! ```toml
-//! default = []
+//! default = ["rt-tokio"]
 //! ```
 //!
 //! | Feature | Description |
 //! |---------|-------------|
+//! |`"rt-tokio"`| uses tokio to spawn the event loops, drive the timers and open TCP connections |
+//! |`"rt-async-std"`| uses async-std instead of tokio, takes precedence over `"rt-tokio"`. TLS streams from `"rustls"` and `"native-tls"` do not require a tokio runtime |
 //! |`"rustls"`| enables TLS integration with `tokio-rustls` and `rustls` |
 //! |`"native-tls"`| enables TLS integration with `tokio-native-tls` and `native-tls`|
+//! |`"dangerous-tls"`| enables `connection::Builder::tls_danger_accept_invalid_certs`, which skips the verification of the server certificate |
 //! |`"acceptor"`| enables `ConnectionAcceptor`, `SessionAcceptor`, and `LinkAcceptor`|
 //! |`"transaction"`| enables `Controller`, `Transaction`, `OwnedTransaction` and `control_link_acceptor` |
 //! |`"scram"`| enables SCRAM auth |
+//! |`"compression"`| enables gzip and deflate compression of the `Data` body section |
+//! |`"config"`| enables `ConnectionConfig`, `SessionConfig`, `SenderConfig` and `ReceiverConfig`, which can be read from config files with `serde` |
+//! |`"testing"`| enables `SessionHandle::send_raw` and `SessionHandle::next_raw_incoming` for protocol testing |
 //! |`"tracing"`| enables logging with `tracing` |
 //! |`"log"`| enables logging with `log` |
 //!
@@ -48,11 +54,11 @@
 //! ./TestAmqpBroker.exe amqp://localhost:5672 /creds:guest:guest /queues:q1
 //! ```
 //!
-//! The following code requires the [`tokio`] async runtime added to the dependencies.
+//! The following code requires the [`tokio`] async runtime added to the dependencies. The items
+//! that most programs need can also be imported at once with `use fe2o3_amqp::prelude::*`.
 //!
 //! ```rust,no_run
-//! use fe2o3_amqp::{Connection, Session, Sender, Receiver};
-//! use fe2o3_amqp::types::messaging::Outcome;
+//! use fe2o3_amqp::{Connection, Session, Sender, Receiver, SendReceipt};
 //!
 //! #[tokio::main]
 //! async fn main() {
@@ -78,12 +84,12 @@
 //!     ).await.unwrap();
 //!     
 //!     // Send a message to the broker and wait for outcome (Disposition)
-//!     let outcome: Outcome = sender.send("hello AMQP").await.unwrap();
+//!     let outcome: SendReceipt = sender.send("hello AMQP").await.unwrap();
 //!     outcome.accepted_or_else(|state| state).unwrap(); // Handle delivery outcome
 //!
 //!     // Send a message with batchable field set to true
 //!     let fut = sender.send_batchable("hello batchable AMQP").await.unwrap();
-//!     let outcome: Outcome = fut.await.unwrap(); // Wait for outcome (Disposition)
+//!     let outcome: SendReceipt = fut.await.unwrap(); // Wait for outcome (Disposition)
 //!     outcome.accepted_or_else(|state| state).unwrap(); // Handle delivery outcome
 //!
 //!     // Receive the message from the broker
@@ -99,7 +105,11 @@
 //!
 //! ## Listener
 //!
+//! The listener requires the `"acceptor"` feature.
+//!
 //! ```rust,no_run
+//! # #[cfg(feature = "acceptor")]
+//! # mod listener {
 //! use tokio::net::TcpListener;
 //! use fe2o3_amqp::acceptor::{ConnectionAcceptor, SessionAcceptor, LinkAcceptor, LinkEndpoint};
 //!
@@ -124,6 +134,8 @@
 //!         });
 //!     }
 //! }
+//! # }
+//! # fn main() {}
 //! ```
 //!
 //! ## WebSocket
@@ -132,8 +144,8 @@
 //!
 //! ```rust,ignore
 //! use fe2o3_amqp::{
-//!     types::{messaging::Outcome, primitives::Value},
-//!     Connection, Delivery, Receiver, Sender, Session,
+//!     types::primitives::Value,
+//!     Connection, Delivery, Receiver, SendReceipt, Sender, Session,
 //! };
 //! use fe2o3_amqp_ws::WebSocketStream;
 //!
@@ -188,12 +200,15 @@ mod macros;
 
 pub(crate) mod control;
 pub(crate) mod endpoint;
+pub(crate) mod rt;
 pub(crate) mod util;
 
 pub mod auth;
 pub mod connection;
 pub mod frames;
+pub mod introspect;
 pub mod link;
+pub mod prelude;
 pub mod sasl_profile;
 pub mod session;
 pub mod transport;
@@ -206,6 +221,14 @@ cfg_transaction! {
     pub mod transaction;
 }
 
+cfg_compression! {
+    pub mod compression;
+}
+
+cfg_config! {
+    pub mod config;
+}
+
 pub mod types {
     //! Re-exporting `fe2o3-amqp-types`
     pub use fe2o3_amqp_types::*;
@@ -213,7 +236,7 @@ pub mod types {
 
 pub use connection::Connection;
 pub use link::{
-    delivery::{Delivery, Sendable},
+    delivery::{Delivery, SendReceipt, Sendable},
     Receiver, Sender,
 };
 pub use session::Session;
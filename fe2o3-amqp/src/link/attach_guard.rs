@@ -0,0 +1,129 @@
+//! Releases the output handle of a link whose attach does not complete
+
+use std::ops::{Deref, DerefMut};
+
+use fe2o3_amqp_types::performatives::Detach;
+use tokio::sync::mpsc;
+
+use crate::{
+    control::SessionControl,
+    endpoint::{LinkExt, OutputHandle},
+};
+
+use super::{LinkFrame, LinkState};
+
+/// How the output handle allocated in the session is given back
+enum Release {
+    /// The Attach is not sent, so the handle is simply deallocated
+    Deallocate(OutputHandle),
+
+    /// The Attach is sent, so the link is closed and the session deallocates the handle once the
+    /// closing Detach goes out
+    Detach(Detach),
+}
+
+/// Wraps a link that is attaching so that the output handle and the link name are released in the
+/// session if the attach fails or the attach future is dropped
+///
+/// The link takes over the output handle once the attach completes and the guard is
+/// [`disarm`](AttachGuard::disarm)ed.
+pub(crate) struct AttachGuard<L: LinkExt> {
+    link: Option<L>,
+    outgoing: mpsc::Sender<LinkFrame>,
+    control: mpsc::Sender<SessionControl>,
+}
+
+impl<L: LinkExt> std::fmt::Debug for AttachGuard<L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AttachGuard")
+            .field("name", &self.link.as_ref().map(|link| link.name()))
+            .finish()
+    }
+}
+
+impl<L: LinkExt> AttachGuard<L> {
+    pub(crate) fn new(
+        link: L,
+        outgoing: mpsc::Sender<LinkFrame>,
+        control: mpsc::Sender<SessionControl>,
+    ) -> Self {
+        Self {
+            link: Some(link),
+            outgoing,
+            control,
+        }
+    }
+
+    /// Returns the link once the attach has completed
+    pub(crate) fn disarm(mut self) -> L {
+        self.link
+            .take()
+            .expect("The link is only taken when disarmed")
+    }
+
+    /// Releases the output handle after the attach failed, unless the closing Detach is already
+    /// sent while handling the error
+    pub(crate) async fn release(mut self) {
+        match self.take_release() {
+            Some(Release::Deallocate(handle)) => {
+                let _ = self
+                    .control
+                    .send(SessionControl::DeallocateLink(handle))
+                    .await;
+            }
+            Some(Release::Detach(detach)) => {
+                let _ = self.outgoing.send(LinkFrame::Detach(detach)).await;
+            }
+            None => {}
+        }
+    }
+
+    fn take_release(&mut self) -> Option<Release> {
+        let link = self.link.as_mut()?;
+        // The handle is taken once a Detach is sent
+        let handle = link.output_handle_mut().take()?;
+        match link.local_state() {
+            LinkState::Unattached => Some(Release::Deallocate(handle)),
+            _ => Some(Release::Detach(Detach {
+                handle: handle.into(),
+                closed: true,
+                error: None,
+            })),
+        }
+    }
+}
+
+impl<L: LinkExt> Deref for AttachGuard<L> {
+    type Target = L;
+
+    fn deref(&self) -> &Self::Target {
+        self.link
+            .as_ref()
+            .expect("The link is only taken when disarmed")
+    }
+}
+
+impl<L: LinkExt> DerefMut for AttachGuard<L> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.link
+            .as_mut()
+            .expect("The link is only taken when disarmed")
+    }
+}
+
+impl<L: LinkExt> Drop for AttachGuard<L> {
+    fn drop(&mut self) {
+        // The attach future is dropped before the attach completes
+        match self.take_release() {
+            Some(Release::Deallocate(handle)) => {
+                let _ = self
+                    .control
+                    .try_send(SessionControl::DeallocateLink(handle));
+            }
+            Some(Release::Detach(detach)) => {
+                let _ = self.outgoing.try_send(LinkFrame::Detach(detach));
+            }
+            None => {}
+        }
+    }
+}
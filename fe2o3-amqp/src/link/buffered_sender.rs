@@ -0,0 +1,780 @@
+//! A sender that buffers messages while its link is re-attached
+//!
+//! [`BufferedSender`] owns a single sender link and the session it is attached on. The session is
+//! obtained from a user supplied factory, which is called again whenever the link or the session
+//! is lost. Messages sent in the meantime are kept in a bounded buffer and transferred in order
+//! once the link is re-attached.
+//!
+//! # Delivery guarantee
+//!
+//! Every message is kept until an outcome is received from the remote peer, and a message whose
+//! outcome is lost with the link is sent again after re-attaching. This gives at-least-once
+//! delivery. When the link can be resumed (see
+//! [`DetachedSender::resume_on_session`](crate::link::sender::DetachedSender::resume_on_session)),
+//! the unsettled deliveries are resumed with their original delivery tags so that a receiver with
+//! a [`dedup_window`](crate::link::builder::Builder::dedup_window) can recognize the duplicates.
+//! Otherwise the messages are sent again as new deliveries.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! let connection = Arc::new(Mutex::new(connection));
+//! let sender = BufferedSender::builder()
+//!     .name("buffered-sender")
+//!     .target("q1")
+//!     .max_messages(1000)
+//!     .spawn(move || {
+//!         let connection = connection.clone();
+//!         async move { Session::begin(&mut *connection.lock().await).await }
+//!     });
+//!
+//! let delivery = sender.send("hello AMQP").unwrap();
+//! let receipt = delivery.await.unwrap();
+//!
+//! sender.close().await.unwrap();
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use fe2o3_amqp_types::{
+    definitions::MessageFormat,
+    messaging::{SerializableBody, Target},
+};
+use futures_util::{future::BoxFuture, stream::FuturesUnordered, FutureExt, StreamExt};
+use parking_lot::Mutex;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{rt::Delay, session::SessionHandle, Payload};
+
+use super::{
+    delivery::{DeliveryFut, SendReceipt, Sendable},
+    sender::{encode_message, DetachedSender},
+    DetachError, SendError, Sender,
+};
+
+/// Default maximum number of messages kept by a [`BufferedSender`]
+pub const DEFAULT_MAX_MESSAGES: usize = 1024;
+
+/// Default time given to [`BufferedSender::flush`]
+pub const DEFAULT_FLUSH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default delay between two attempts to re-attach the link
+pub const DEFAULT_REATTACH_INTERVAL: Duration = Duration::from_secs(1);
+
+type SendResult = Result<SendReceipt, SendError>;
+
+/// Error with a message sent by a [`BufferedSender`]
+#[derive(Debug, thiserror::Error)]
+pub enum BufferedSendError {
+    /// The buffer already holds the maximum number of messages or bytes
+    #[error("The buffer is full")]
+    BufferFull,
+
+    /// Error serializing message
+    #[error("Error encoding message")]
+    MessageEncodeError(#[from] serde_amqp::Error),
+
+    /// The message is not settled before the deadline of a flush
+    ///
+    /// The message may still have been delivered if it was sent before the deadline
+    #[error("The message is not settled before the flush deadline")]
+    FlushTimeout,
+
+    /// The sender is closed or dropped before the message is settled
+    #[error("The buffered sender is closed")]
+    Closed,
+
+    /// The delivery failed with an error that is not caused by losing the link
+    #[error(transparent)]
+    Send(Box<SendError>),
+}
+
+/// Error with [`BufferedSender::flush`]
+#[derive(Debug, thiserror::Error)]
+pub enum FlushError {
+    /// Some messages were not settled before the deadline. The outcome of each of these messages
+    /// resolves to [`BufferedSendError::FlushTimeout`]
+    #[error("{} message(s) were not settled before the flush deadline", .failed)]
+    Timeout {
+        /// Number of messages that failed
+        failed: usize,
+    },
+
+    /// The sender is closed
+    #[error("The buffered sender is closed")]
+    Closed,
+}
+
+/// Builder for [`BufferedSender`]
+#[derive(Debug, Clone)]
+pub struct Builder {
+    /// Name of the link
+    pub name: String,
+
+    /// Target of the link
+    pub target: Target,
+
+    /// Maximum number of messages that are buffered or waiting for an outcome
+    pub max_messages: usize,
+
+    /// Maximum number of encoded bytes that are buffered or waiting for an outcome
+    pub max_bytes: Option<usize>,
+
+    /// Time given to [`BufferedSender::flush`] before the remaining messages fail
+    pub flush_timeout: Duration,
+
+    /// Delay between two attempts to re-attach the link
+    pub reattach_interval: Duration,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            target: Target::default(),
+            max_messages: DEFAULT_MAX_MESSAGES,
+            max_bytes: None,
+            flush_timeout: DEFAULT_FLUSH_TIMEOUT,
+            reattach_interval: DEFAULT_REATTACH_INTERVAL,
+        }
+    }
+}
+
+impl Builder {
+    /// Creates a new builder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Name of the link
+    ///
+    /// The same name is used every time the link is re-attached
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Target of the link
+    pub fn target(mut self, target: impl Into<Target>) -> Self {
+        self.target = target.into();
+        self
+    }
+
+    /// Maximum number of messages that are buffered or waiting for an outcome
+    pub fn max_messages(mut self, max_messages: usize) -> Self {
+        self.max_messages = max_messages;
+        self
+    }
+
+    /// Maximum number of encoded bytes that are buffered or waiting for an outcome
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Time given to [`BufferedSender::flush`] before the remaining messages fail
+    pub fn flush_timeout(mut self, flush_timeout: Duration) -> Self {
+        self.flush_timeout = flush_timeout;
+        self
+    }
+
+    /// Delay between two attempts to re-attach the link
+    pub fn reattach_interval(mut self, reattach_interval: Duration) -> Self {
+        self.reattach_interval = reattach_interval;
+        self
+    }
+
+    /// Spawns the event loop of the sender, which attaches the link on a session returned by
+    /// `session_factory`
+    ///
+    /// The factory is called again whenever the link is lost. An error returned by the factory is
+    /// logged, and the factory is called again after the
+    /// [`reattach_interval`](#method.reattach_interval).
+    pub fn spawn<F, Fut, E>(self, session_factory: F) -> BufferedSender
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<SessionHandle<()>, E>> + Send + 'static,
+        E: std::fmt::Debug + Send + 'static,
+    {
+        let (commands_tx, commands_rx) = mpsc::unbounded_channel();
+        let usage = Arc::new(Mutex::new(Usage::default()));
+        let event_loop = EventLoop {
+            name: self.name.clone(),
+            target: self.target,
+            flush_timeout: self.flush_timeout,
+            reattach_interval: self.reattach_interval,
+            session_factory,
+            commands: commands_rx,
+            usage: usage.clone(),
+            next_seq: 0,
+            queue: BTreeMap::new(),
+            in_flight: BTreeMap::new(),
+            outcomes: FuturesUnordered::new(),
+            flushes: Vec::new(),
+            session: None,
+            sender: None,
+            detached: None,
+            reattach_at: Instant::now(),
+        };
+        crate::rt::spawn(event_loop.run());
+
+        BufferedSender {
+            name: self.name,
+            max_messages: self.max_messages,
+            max_bytes: self.max_bytes,
+            commands: commands_tx,
+            usage,
+        }
+    }
+}
+
+/// Messages and bytes held by the sender
+#[derive(Debug, Default)]
+struct Usage {
+    messages: usize,
+    bytes: usize,
+}
+
+#[derive(Debug)]
+enum Command {
+    Send(Entry),
+    Flush(oneshot::Sender<Result<(), FlushError>>),
+    Close(oneshot::Sender<()>),
+}
+
+/// A message that is buffered or waiting for an outcome
+#[derive(Debug)]
+struct Entry {
+    payload: Payload,
+    message_format: MessageFormat,
+    settled: Option<bool>,
+    outcome: oneshot::Sender<Result<SendReceipt, BufferedSendError>>,
+}
+
+/// A sender that keeps messages in a bounded buffer while its link is re-attached
+///
+/// Please see the [module](crate::link::buffered_sender) documentation.
+///
+/// Dropping the `BufferedSender` stops the event loop, and the messages that are not settled yet
+/// fail with [`BufferedSendError::Closed`]. Use [`close`](#method.close) to flush the buffer
+/// first.
+#[derive(Debug)]
+pub struct BufferedSender {
+    name: String,
+    max_messages: usize,
+    max_bytes: Option<usize>,
+    commands: mpsc::UnboundedSender<Command>,
+    usage: Arc<Mutex<Usage>>,
+}
+
+impl BufferedSender {
+    /// Creates a builder for [`BufferedSender`]
+    pub fn builder() -> Builder {
+        Builder::new()
+    }
+
+    /// Name of the link
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Number of messages that are buffered or waiting for an outcome
+    pub fn pending(&self) -> usize {
+        self.usage.lock().messages
+    }
+
+    /// Number of encoded bytes that are buffered or waiting for an outcome
+    pub fn pending_bytes(&self) -> usize {
+        self.usage.lock().bytes
+    }
+
+    /// Buffers a message
+    ///
+    /// The message is transferred in order after the messages that are buffered before it. The
+    /// returned [`BufferedDelivery`] resolves once an outcome is received from the remote peer,
+    /// which may be after the link has been re-attached.
+    ///
+    /// [`BufferedSendError::BufferFull`] is returned if the message would exceed either
+    /// [`max_messages`](Builder::max_messages) or [`max_bytes`](Builder::max_bytes).
+    pub fn send<T: SerializableBody>(
+        &self,
+        sendable: impl Into<Sendable<T>>,
+    ) -> Result<BufferedDelivery, BufferedSendError> {
+        let Sendable {
+            message,
+            message_format,
+            settled,
+        } = sendable.into();
+        let payload = encode_message(&message)?;
+        let size = payload.len();
+
+        {
+            let mut usage = self.usage.lock();
+            let exceeds_bytes = self
+                .max_bytes
+                .map(|max_bytes| usage.bytes + size > max_bytes)
+                .unwrap_or(false);
+            if usage.messages >= self.max_messages || exceeds_bytes {
+                return Err(BufferedSendError::BufferFull);
+            }
+            usage.messages += 1;
+            usage.bytes += size;
+        }
+
+        let (tx, rx) = oneshot::channel();
+        let entry = Entry {
+            payload,
+            message_format,
+            settled,
+            outcome: tx,
+        };
+        if let Err(mpsc::error::SendError(Command::Send(entry))) =
+            self.commands.send(Command::Send(entry))
+        {
+            release(&self.usage, &entry);
+            return Err(BufferedSendError::Closed);
+        }
+        Ok(BufferedDelivery { outcome: rx })
+    }
+
+    /// Waits until every message buffered before this call is settled
+    ///
+    /// The messages that are not settled within the [`flush_timeout`](Builder::flush_timeout)
+    /// fail with [`BufferedSendError::FlushTimeout`], and the number of these messages is returned
+    /// in [`FlushError::Timeout`].
+    pub async fn flush(&self) -> Result<(), FlushError> {
+        let (tx, rx) = oneshot::channel();
+        self.commands
+            .send(Command::Flush(tx))
+            .map_err(|_| FlushError::Closed)?;
+        rx.await.map_err(|_| FlushError::Closed)?
+    }
+
+    /// Flushes the buffer, then closes the link and ends the session
+    pub async fn close(self) -> Result<(), FlushError> {
+        let result = self.flush().await;
+        let (tx, rx) = oneshot::channel();
+        if self.commands.send(Command::Close(tx)).is_ok() {
+            let _ = rx.await;
+        }
+        result
+    }
+}
+
+/// The outcome of a message sent by a [`BufferedSender`]
+#[derive(Debug)]
+pub struct BufferedDelivery {
+    outcome: oneshot::Receiver<Result<SendReceipt, BufferedSendError>>,
+}
+
+impl Future for BufferedDelivery {
+    type Output = Result<SendReceipt, BufferedSendError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.outcome.poll_unpin(cx) {
+            Poll::Ready(Ok(result)) => Poll::Ready(result),
+            Poll::Ready(Err(_)) => Poll::Ready(Err(BufferedSendError::Closed)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+fn release(usage: &Mutex<Usage>, entry: &Entry) {
+    let mut usage = usage.lock();
+    usage.messages -= 1;
+    usage.bytes -= entry.payload.len();
+}
+
+/// Whether the delivery failed because the link or the session is lost
+fn is_link_lost(error: &SendError) -> bool {
+    matches!(error, SendError::LinkStateError(_) | SendError::Detached(_))
+}
+
+type NextMessage = (u64, Payload, MessageFormat, Option<bool>);
+
+/// Sends the next message, or waits for the link to be detached if there is nothing to send, or
+/// waits until the link should be re-attached if there is no link
+async fn drive_link(
+    sender: &mut Option<Sender>,
+    next: Option<NextMessage>,
+    reattach_at: Instant,
+) -> LinkEvent {
+    match (sender, next) {
+        (Some(sender), Some((seq, payload, message_format, settled))) => {
+            let result = sender
+                .send_payload(payload, message_format, settled, false)
+                .await;
+            LinkEvent::Sent(seq, result)
+        }
+        (Some(sender), None) => LinkEvent::Detached(sender.on_detach().await),
+        (None, _) => {
+            sleep_until(Some(reattach_at)).await;
+            LinkEvent::Reattach
+        }
+    }
+}
+
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => Delay::new(deadline.saturating_duration_since(Instant::now())).await,
+        None => futures_util::future::pending().await,
+    }
+}
+
+enum LinkEvent {
+    Sent(u64, Result<DeliveryFut<SendResult>, SendError>),
+    Detached(DetachError),
+    Reattach,
+}
+
+struct FlushWaiter {
+    /// Messages with a smaller sequence number must be settled
+    until: u64,
+    deadline: Instant,
+    reply: oneshot::Sender<Result<(), FlushError>>,
+}
+
+struct EventLoop<F> {
+    name: String,
+    target: Target,
+    flush_timeout: Duration,
+    reattach_interval: Duration,
+    session_factory: F,
+
+    commands: mpsc::UnboundedReceiver<Command>,
+    usage: Arc<Mutex<Usage>>,
+
+    next_seq: u64,
+    /// Messages that are not sent on the current link
+    queue: BTreeMap<u64, Entry>,
+    /// Messages that are sent and waiting for an outcome
+    in_flight: BTreeMap<u64, Entry>,
+    outcomes: FuturesUnordered<BoxFuture<'static, (u64, SendResult)>>,
+    flushes: Vec<FlushWaiter>,
+
+    session: Option<SessionHandle<()>>,
+    sender: Option<Sender>,
+    detached: Option<DetachedSender>,
+    reattach_at: Instant,
+}
+
+impl<F, Fut, E> EventLoop<F>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<SessionHandle<()>, E>> + Send + 'static,
+    E: std::fmt::Debug + Send + 'static,
+{
+    async fn run(mut self) {
+        loop {
+            let next = self.queue.iter().next().map(|(seq, entry)| {
+                (
+                    *seq,
+                    entry.payload.clone(),
+                    entry.message_format,
+                    entry.settled,
+                )
+            });
+            let reattach_at = self.reattach_at;
+            let flush_deadline = self.flushes.iter().map(|waiter| waiter.deadline).min();
+
+            let link_event = drive_link(&mut self.sender, next, reattach_at);
+            let flush_timer = sleep_until(flush_deadline);
+
+            tokio::select! {
+                command = self.commands.recv() => match command {
+                    Some(Command::Send(entry)) => {
+                        self.queue.insert(self.next_seq, entry);
+                        self.next_seq += 1;
+                    }
+                    Some(Command::Flush(reply)) => {
+                        let deadline = Instant::now() + self.flush_timeout;
+                        self.flushes.push(FlushWaiter { until: self.next_seq, deadline, reply });
+                    }
+                    Some(Command::Close(reply)) => {
+                        self.close().await;
+                        let _ = reply.send(());
+                        return;
+                    }
+                    None => {
+                        self.close().await;
+                        return;
+                    }
+                },
+                Some((seq, result)) = self.outcomes.next(), if !self.outcomes.is_empty() => {
+                    self.on_outcome(seq, result).await;
+                }
+                event = link_event => match event {
+                    LinkEvent::Sent(seq, result) => self.on_sent(seq, result).await,
+                    LinkEvent::Detached(_error) => {
+                        #[cfg(feature = "tracing")]
+                        tracing::debug!(link = %self.name, error = ?_error, "Buffered sender link is detached");
+                        #[cfg(feature = "log")]
+                        log::debug!("Buffered sender link {} is detached: {:?}", self.name, _error);
+                        self.on_link_lost().await;
+                    }
+                    LinkEvent::Reattach => self.reattach().await,
+                },
+                _ = flush_timer => self.on_flush_deadline(),
+            }
+
+            self.complete_flushes();
+        }
+    }
+
+    async fn on_sent(&mut self, seq: u64, result: Result<DeliveryFut<SendResult>, SendError>) {
+        match result {
+            Ok(fut) => {
+                if let Some(entry) = self.queue.remove(&seq) {
+                    self.in_flight.insert(seq, entry);
+                    self.outcomes
+                        .push(fut.map(move |result| (seq, result)).boxed());
+                }
+            }
+            Err(error) if is_link_lost(&error) => self.on_link_lost().await,
+            Err(error) => {
+                if let Some(entry) = self.queue.remove(&seq) {
+                    self.resolve(entry, Err(BufferedSendError::Send(Box::new(error))));
+                }
+            }
+        }
+    }
+
+    async fn on_outcome(&mut self, seq: u64, result: SendResult) {
+        match result {
+            Err(error) if is_link_lost(&error) => {
+                // The message is sent again once the link is re-attached
+                if let Some(entry) = self.in_flight.remove(&seq) {
+                    self.queue.insert(seq, entry);
+                }
+                if self.sender.is_some() {
+                    self.on_link_lost().await;
+                }
+            }
+            result => {
+                if let Some(entry) = self.in_flight.remove(&seq) {
+                    self.resolve(
+                        entry,
+                        result.map_err(|error| BufferedSendError::Send(Box::new(error))),
+                    );
+                }
+            }
+        }
+    }
+
+    async fn on_link_lost(&mut self) {
+        if let Some(sender) = self.sender.take() {
+            // The detached sender keeps the unsettled deliveries so that the link can be resumed
+            let detached = match sender.detach().await {
+                Ok(detached) => detached,
+                Err((detached, _)) => detached,
+            };
+            self.detached = Some(detached);
+        }
+        if let Some(mut session) = self.session.take() {
+            let _ = session.end().await;
+        }
+        self.reattach_at = Instant::now();
+    }
+
+    async fn reattach(&mut self) {
+        let mut session = match (self.session_factory)().await {
+            Ok(session) => session,
+            Err(_error) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!(link = %self.name, error = ?_error, "Failed to begin session");
+                #[cfg(feature = "log")]
+                log::error!(
+                    "Failed to begin session for link {}: {:?}",
+                    self.name,
+                    _error
+                );
+                self.reattach_at = Instant::now() + self.reattach_interval;
+                return;
+            }
+        };
+
+        if let Some(detached) = self.detached.take() {
+            match detached.resume_on_session(&session).await {
+                Ok(sender) => {
+                    // The unsettled deliveries are resumed with their original delivery tags
+                    self.sender = Some(sender);
+                    self.session = Some(session);
+                    return;
+                }
+                Err(_error) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(link = %self.name, error = ?_error.kind, "Failed to resume link");
+                    #[cfg(feature = "log")]
+                    log::debug!("Failed to resume link {}: {:?}", self.name, _error.kind);
+                }
+            }
+        }
+
+        // The outcomes of the messages sent on the lost link will not arrive, so these messages
+        // are sent again as new deliveries in their original order
+        self.outcomes.clear();
+        let in_flight = std::mem::take(&mut self.in_flight);
+        self.queue.extend(in_flight);
+
+        let result = Sender::builder()
+            .name(self.name.clone())
+            .target(self.target.clone())
+            .attach(&mut session)
+            .await;
+        match result {
+            Ok(sender) => {
+                self.sender = Some(sender);
+                self.session = Some(session);
+            }
+            Err(_error) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!(link = %self.name, error = ?_error, "Failed to attach link");
+                #[cfg(feature = "log")]
+                log::error!("Failed to attach link {}: {:?}", self.name, _error);
+                let _ = session.end().await;
+                self.reattach_at = Instant::now() + self.reattach_interval;
+            }
+        }
+    }
+
+    fn resolve(&self, entry: Entry, result: Result<SendReceipt, BufferedSendError>) {
+        release(&self.usage, &entry);
+        let _ = entry.outcome.send(result);
+    }
+
+    /// Sequence number of the oldest message that is not settled
+    fn oldest_pending(&self) -> Option<u64> {
+        let queued = self.queue.keys().next();
+        let in_flight = self.in_flight.keys().next();
+        match (queued, in_flight) {
+            (Some(a), Some(b)) => Some(*a.min(b)),
+            (a, b) => a.or(b).copied(),
+        }
+    }
+
+    fn complete_flushes(&mut self) {
+        let oldest = self.oldest_pending();
+        let (completed, waiting) = std::mem::take(&mut self.flushes)
+            .into_iter()
+            .partition(|waiter| oldest.map(|seq| seq >= waiter.until).unwrap_or(true));
+        self.flushes = waiting;
+        for waiter in completed {
+            let _ = waiter.reply.send(Ok(()));
+        }
+    }
+
+    fn on_flush_deadline(&mut self) {
+        let now = Instant::now();
+        let (expired, waiting): (Vec<_>, _) = std::mem::take(&mut self.flushes)
+            .into_iter()
+            .partition(|waiter| waiter.deadline <= now);
+        self.flushes = waiting;
+
+        for waiter in expired {
+            let mut failed = 0;
+            for map in [&mut self.queue, &mut self.in_flight] {
+                let remaining = map.split_off(&waiter.until);
+                for (_, entry) in std::mem::replace(map, remaining) {
+                    release(&self.usage, &entry);
+                    let _ = entry.outcome.send(Err(BufferedSendError::FlushTimeout));
+                    failed += 1;
+                }
+            }
+            let result = match failed {
+                0 => Ok(()),
+                failed => Err(FlushError::Timeout { failed }),
+            };
+            let _ = waiter.reply.send(result);
+        }
+    }
+
+    async fn close(&mut self) {
+        let queue = std::mem::take(&mut self.queue);
+        let in_flight = std::mem::take(&mut self.in_flight);
+        for (_, entry) in queue.into_iter().chain(in_flight) {
+            self.resolve(entry, Err(BufferedSendError::Closed));
+        }
+        for waiter in self.flushes.drain(..) {
+            let _ = waiter.reply.send(Err(FlushError::Closed));
+        }
+
+        if let Some(sender) = self.sender.take() {
+            let _ = sender.close().await;
+        }
+        if let Some(mut session) = self.session.take() {
+            let _ = session.end().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::session::SessionHandle;
+
+    use super::{BufferedSendError, BufferedSender, FlushError};
+
+    #[tokio::test]
+    async fn messages_fail_individually_at_flush_deadline() {
+        let sender = BufferedSender::builder()
+            .name("buffered-sender")
+            .target("q1")
+            .max_messages(3)
+            .flush_timeout(Duration::from_millis(50))
+            .reattach_interval(Duration::from_millis(10))
+            .spawn(|| async { Err::<SessionHandle<()>, _>("unreachable") });
+
+        let deliveries: Vec<_> = (0..3)
+            .map(|i| sender.send(format!("message-{}", i)).unwrap())
+            .collect();
+        assert!(matches!(
+            sender.send("overflow"),
+            Err(BufferedSendError::BufferFull)
+        ));
+        assert_eq!(sender.pending(), 3);
+
+        let result = sender.flush().await;
+        assert!(matches!(result, Err(FlushError::Timeout { failed: 3 })));
+        for delivery in deliveries {
+            assert!(matches!(
+                delivery.await,
+                Err(BufferedSendError::FlushTimeout)
+            ));
+        }
+        assert_eq!(sender.pending(), 0);
+        assert_eq!(sender.pending_bytes(), 0);
+    }
+
+    #[tokio::test]
+    async fn max_bytes_limits_buffered_payloads() {
+        let sender = BufferedSender::builder()
+            .max_bytes(64)
+            .spawn(|| async { Err::<SessionHandle<()>, _>("unreachable") });
+
+        let _delivery = sender.send("small").unwrap();
+        assert!(sender.pending_bytes() > 0);
+        assert!(matches!(
+            sender.send("x".repeat(64)),
+            Err(BufferedSendError::BufferFull)
+        ));
+        assert_eq!(sender.pending(), 1);
+    }
+
+    #[tokio::test]
+    async fn dropping_the_sender_fails_pending_messages() {
+        let sender = BufferedSender::builder()
+            .spawn(|| async { Err::<SessionHandle<()>, _>("unreachable") });
+
+        let delivery = sender.send("message").unwrap();
+        drop(sender);
+        assert!(matches!(delivery.await, Err(BufferedSendError::Closed)));
+    }
+}
@@ -5,31 +5,41 @@ use std::{
     sync::{atomic::AtomicU32, Arc},
 };
 
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+
+#[cfg(not(target_arch = "wasm32"))]
+use super::shared_sender::QueuePolicy;
+
 use fe2o3_amqp_types::{
     definitions::{Fields, ReceiverSettleMode, SenderSettleMode, SequenceNo},
     messaging::{Source, Target, TargetArchetype},
     primitives::{Symbol, Ulong},
 };
 use parking_lot::RwLock;
-use tokio::sync::{mpsc, Notify};
+use tokio::sync::{mpsc, watch, Notify};
 
 use crate::{
     connection::DEFAULT_OUTGOING_BUFFER_SIZE,
     endpoint::{LinkExt, OutputHandle},
-    link::{Link, LinkIncomingItem, LinkRelay},
+    link::{AttachGuard, Link, LinkId, LinkIncomingItem, LinkRelay},
     session::{self, SessionHandle},
     util::{Consumer, Producer},
 };
 
 use super::{
-    receiver::{CreditMode, ReceiverInner},
+    dedup_window::DedupWindow,
+    receiver::{CreditMode, OnCloseUnsettled, ReceiverInner},
+    remote_settlement::RemoteSettlements,
     role,
     sender::SenderInner,
     state::{LinkFlowState, LinkFlowStateInner, LinkState},
     target_archetype::VerifyTargetArchetype,
+    unsettled_store::{LinkUnsettledStore, UnsettledStore},
+    validation::{BatchValidationPolicy, SendValidator, ValidatorChain},
     ArcUnsettledMap, Receiver, ReceiverAttachError, ReceiverFlowState, ReceiverLink,
-    ReceiverRelayFlowState, Sender, SenderAttachError, SenderFlowState, SenderLink,
-    SenderRelayFlowState,
+    ReceiverRelayFlowState, Sender, SenderAttachError, SenderAttachExchange, SenderFlowState,
+    SenderLink, SenderRelayFlowState, SenderResumeErrorKind,
 };
 
 cfg_transaction! {
@@ -38,6 +48,10 @@ cfg_transaction! {
     use fe2o3_amqp_types::transaction::Coordinator;
 }
 
+cfg_compression! {
+    use crate::compression::{BodyCompression, Compression};
+}
+
 /// Type state for link::builder::Builder;
 #[derive(Debug)]
 pub struct WithoutName;
@@ -112,6 +126,25 @@ pub struct Builder<Role, T, NameState, SS, TS> {
     /// `false`
     pub auto_accept: bool,
 
+    /// Whether the sender returns a `Rejected` outcome as `SendError::Rejected`
+    ///
+    /// This field has no effect on Receiver
+    ///
+    /// # Default
+    ///
+    /// `false`
+    pub rejected_as_error: bool,
+
+    /// Compression of the `Data` body section of the outgoing messages
+    ///
+    /// This field has no effect on Receiver
+    ///
+    /// # Default
+    ///
+    /// `None`
+    #[cfg(feature = "compression")]
+    pub body_compression: Option<BodyCompression>,
+
     /// Whether to verify the `source` field of the incoming Attach frame
     ///
     /// Default to true
@@ -122,6 +155,116 @@ pub struct Builder<Role, T, NameState, SS, TS> {
     /// Default to true
     pub verify_incoming_target: bool,
 
+    /// Persistence hook for the unsettled map of the link
+    ///
+    /// # Default
+    ///
+    /// `None`
+    pub unsettled_store: Option<Arc<dyn UnsettledStore>>,
+
+    /// Number of disposed delivery tags the receiver remembers to detect deliveries resent after
+    /// the link is resumed
+    ///
+    /// This field has no effect on Sender
+    ///
+    /// # Default
+    ///
+    /// `None`
+    pub dedup_window: Option<usize>,
+
+    /// Whether the receiver locates the sections of each incoming message and keeps the encoded
+    /// message in the [`Delivery`](crate::link::delivery::Delivery)
+    ///
+    /// This field has no effect on Sender
+    ///
+    /// # Default
+    ///
+    /// `false`
+    pub index_sections: bool,
+
+    /// Duration the receiver waits for the sender to settle a delivery that is disposed in
+    /// `ReceiverSettleMode::Second`
+    ///
+    /// This field has no effect on Sender
+    ///
+    /// # Default
+    ///
+    /// `None`, which waits until the sender settles the delivery or the link is detached
+    #[cfg(not(target_arch = "wasm32"))]
+    pub settlement_timeout: Option<Duration>,
+
+    /// Duration a send may wait for link credit before a warning is logged
+    ///
+    /// This field has no effect on Receiver
+    ///
+    /// # Default
+    ///
+    /// `None`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub warn_after_no_credit: Option<Duration>,
+
+    /// Duration a send may wait for link credit before it fails with
+    /// [`SendError::CreditTimeout`](crate::link::SendError::CreditTimeout)
+    ///
+    /// This field has no effect on Receiver
+    ///
+    /// # Default
+    ///
+    /// `None`, which waits until credit is issued or the link is detached
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fail_after_no_credit: Option<Duration>,
+
+    /// Duration the receiver may wait for a delivery while link credit is issued before a warning
+    /// is logged
+    ///
+    /// This field has no effect on Sender
+    ///
+    /// # Default
+    ///
+    /// `None`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub warn_after_no_delivery: Option<Duration>,
+
+    /// The order that the messages queued to a [split](crate::link::Sender::split) sender are
+    /// transferred in
+    ///
+    /// This field has no effect on Receiver
+    ///
+    /// # Default
+    ///
+    /// [`QueuePolicy::Fifo`]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub queue_policy: QueuePolicy,
+
+    /// Validators that every outgoing message must pass before it is sent
+    ///
+    /// This field has no effect on Receiver
+    ///
+    /// # Default
+    ///
+    /// An empty [`ValidatorChain`], which accepts every message
+    pub send_validator: ValidatorChain,
+
+    /// How [`Sender::send_batch`](crate::Sender::send_batch) handles messages that fail
+    /// validation
+    ///
+    /// This field has no effect on Receiver
+    ///
+    /// # Default
+    ///
+    /// [`BatchValidationPolicy::FailBatch`]
+    pub batch_validation_policy: BatchValidationPolicy,
+
+    /// What the receiver does with the deliveries it has received but not settled when the link
+    /// is closed, detached or dropped
+    ///
+    /// This field has no effect on Sender
+    ///
+    /// # Default
+    ///
+    /// [`OnCloseUnsettled::Leave`]
+    pub on_close_unsettled: OnCloseUnsettled,
+
     // Type state markers
     role: PhantomData<Role>,
     name_state: PhantomData<NameState>,
@@ -151,8 +294,27 @@ impl<Role, T> Default for Builder<Role, T, WithoutName, WithoutSource, WithoutTa
             target_state: PhantomData,
 
             auto_accept: false,
+            rejected_as_error: false,
+            #[cfg(feature = "compression")]
+            body_compression: None,
             verify_incoming_source: true,
             verify_incoming_target: true,
+            unsettled_store: None,
+            dedup_window: None,
+            index_sections: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            settlement_timeout: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            warn_after_no_credit: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            fail_after_no_credit: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            warn_after_no_delivery: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            queue_policy: QueuePolicy::Fifo,
+            send_validator: ValidatorChain::new(),
+            batch_validation_policy: BatchValidationPolicy::FailBatch,
+            on_close_unsettled: OnCloseUnsettled::Leave,
         }
     }
 }
@@ -178,6 +340,79 @@ impl<T, NameState, SS, TS> Builder<role::ReceiverMarker, T, NameState, SS, TS> {
         self.auto_accept = value;
         self
     }
+
+    /// Remembers the outcomes of the last `capacity` disposed deliveries.
+    ///
+    /// A delivery that is resent with a remembered delivery tag (for example after the link is
+    /// resumed) is settled with the recorded outcome and is not yielded to the application again.
+    /// The number of suppressed deliveries can be found with
+    /// [`Receiver::suppressed_duplicates`](crate::Receiver::suppressed_duplicates). The window is
+    /// kept when the receiver is detached and resumed.
+    ///
+    /// This relies on the remote sender not reusing the delivery tag of a disposed delivery.
+    ///
+    /// Default value: `None`
+    pub fn dedup_window(mut self, capacity: usize) -> Self {
+        self.dedup_window = Some(capacity);
+        self
+    }
+
+    /// Locates the sections of each incoming message and keeps the encoded message, so that
+    /// [`Delivery::sections`] and [`Delivery::message_mut`] are available.
+    ///
+    /// This allows changing a section of a received message and forwarding it without encoding the
+    /// other sections again. A message made of multiple transfers is joined into one buffer.
+    ///
+    /// Default value: `false`
+    ///
+    /// [`Delivery::sections`]: crate::link::delivery::Delivery::sections
+    /// [`Delivery::message_mut`]: crate::link::delivery::Delivery::message_mut
+    pub fn index_sections(mut self, value: bool) -> Self {
+        self.index_sections = value;
+        self
+    }
+
+    /// Fails the disposition of a delivery with [`DispositionError::SettlementTimeout`] if the
+    /// sender does not settle the delivery within `timeout`.
+    ///
+    /// This only applies to deliveries that are disposed in `ReceiverSettleMode::Second`. The
+    /// delivery is kept in the unsettled map after the timeout.
+    ///
+    /// Default value: `None`
+    ///
+    /// [`DispositionError::SettlementTimeout`]: crate::link::DispositionError::SettlementTimeout
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn settlement_timeout(mut self, timeout: Duration) -> Self {
+        self.settlement_timeout = Some(timeout);
+        self
+    }
+
+    /// Logs a warning if the receiver has waited for a delivery longer than `duration` while
+    /// link credit is issued.
+    ///
+    /// This helps finding a sender that stops sending while the link stays attached. The warning
+    /// is logged once for each call to `recv`.
+    ///
+    /// Default value: `None`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn warn_after_no_delivery(mut self, duration: Duration) -> Self {
+        self.warn_after_no_delivery = Some(duration);
+        self
+    }
+
+    /// Sets what happens to the deliveries that are received but not settled when the receiver
+    /// is closed, detached or dropped.
+    ///
+    /// With [`OnCloseUnsettled::Release`] or [`OnCloseUnsettled::Reject`], the deliveries are
+    /// settled with ranged dispositions that are sent before the Detach frame, so that the sender
+    /// does not have to wait for the link to time out. Deliveries given to the application while
+    /// `auto_accept` is set are not tracked.
+    ///
+    /// Default value: [`OnCloseUnsettled::Leave`]
+    pub fn on_close_unsettled(mut self, policy: OnCloseUnsettled) -> Self {
+        self.on_close_unsettled = policy;
+        self
+    }
 }
 
 impl<Role, T, NameState, SS, TS> Builder<Role, T, NameState, SS, TS> {
@@ -203,11 +438,39 @@ impl<Role, T, NameState, SS, TS> Builder<Role, T, NameState, SS, TS> {
             target_state: self.target_state,
 
             auto_accept: self.auto_accept,
+
+            rejected_as_error: self.rejected_as_error,
+            #[cfg(feature = "compression")]
+            body_compression: self.body_compression,
             verify_incoming_source: self.verify_incoming_source,
             verify_incoming_target: self.verify_incoming_target,
+            unsettled_store: self.unsettled_store,
+            dedup_window: self.dedup_window,
+            index_sections: self.index_sections,
+            #[cfg(not(target_arch = "wasm32"))]
+            settlement_timeout: self.settlement_timeout,
+            #[cfg(not(target_arch = "wasm32"))]
+            warn_after_no_credit: self.warn_after_no_credit,
+            #[cfg(not(target_arch = "wasm32"))]
+            fail_after_no_credit: self.fail_after_no_credit,
+            #[cfg(not(target_arch = "wasm32"))]
+            warn_after_no_delivery: self.warn_after_no_delivery,
+            #[cfg(not(target_arch = "wasm32"))]
+            queue_policy: self.queue_policy,
+            send_validator: self.send_validator,
+            batch_validation_policy: self.batch_validation_policy,
+            on_close_unsettled: self.on_close_unsettled,
         }
     }
 
+    /// Generate a unique name for the link in the form of `<prefix>-<uuid>`
+    ///
+    /// This avoids [`DuplicatedLinkName`](crate::link::SenderAttachError::DuplicatedLinkName)
+    /// when many links to the same address are attached on one session.
+    pub fn auto_name(self, prefix: impl AsRef<str>) -> Builder<Role, T, WithName, SS, TS> {
+        self.name(format!("{}-{}", prefix.as_ref(), uuid::Uuid::new_v4()))
+    }
+
     /// Set the link's role to sender
     pub fn sender(self) -> Builder<role::SenderMarker, T, NameState, SS, TS> {
         Builder {
@@ -230,8 +493,28 @@ impl<Role, T, NameState, SS, TS> Builder<Role, T, NameState, SS, TS> {
             target_state: self.target_state,
 
             auto_accept: self.auto_accept,
+
+            rejected_as_error: self.rejected_as_error,
+            #[cfg(feature = "compression")]
+            body_compression: self.body_compression,
             verify_incoming_source: self.verify_incoming_source,
             verify_incoming_target: self.verify_incoming_target,
+            unsettled_store: self.unsettled_store,
+            dedup_window: self.dedup_window,
+            index_sections: self.index_sections,
+            #[cfg(not(target_arch = "wasm32"))]
+            settlement_timeout: self.settlement_timeout,
+            #[cfg(not(target_arch = "wasm32"))]
+            warn_after_no_credit: self.warn_after_no_credit,
+            #[cfg(not(target_arch = "wasm32"))]
+            fail_after_no_credit: self.fail_after_no_credit,
+            #[cfg(not(target_arch = "wasm32"))]
+            warn_after_no_delivery: self.warn_after_no_delivery,
+            #[cfg(not(target_arch = "wasm32"))]
+            queue_policy: self.queue_policy,
+            send_validator: self.send_validator,
+            batch_validation_policy: self.batch_validation_policy,
+            on_close_unsettled: self.on_close_unsettled,
         }
     }
 
@@ -257,8 +540,28 @@ impl<Role, T, NameState, SS, TS> Builder<Role, T, NameState, SS, TS> {
             target_state: self.target_state,
 
             auto_accept: self.auto_accept,
+
+            rejected_as_error: self.rejected_as_error,
+            #[cfg(feature = "compression")]
+            body_compression: self.body_compression,
             verify_incoming_source: self.verify_incoming_source,
             verify_incoming_target: self.verify_incoming_target,
+            unsettled_store: self.unsettled_store,
+            dedup_window: self.dedup_window,
+            index_sections: self.index_sections,
+            #[cfg(not(target_arch = "wasm32"))]
+            settlement_timeout: self.settlement_timeout,
+            #[cfg(not(target_arch = "wasm32"))]
+            warn_after_no_credit: self.warn_after_no_credit,
+            #[cfg(not(target_arch = "wasm32"))]
+            fail_after_no_credit: self.fail_after_no_credit,
+            #[cfg(not(target_arch = "wasm32"))]
+            warn_after_no_delivery: self.warn_after_no_delivery,
+            #[cfg(not(target_arch = "wasm32"))]
+            queue_policy: self.queue_policy,
+            send_validator: self.send_validator,
+            batch_validation_policy: self.batch_validation_policy,
+            on_close_unsettled: self.on_close_unsettled,
         }
     }
 
@@ -275,6 +578,10 @@ impl<Role, T, NameState, SS, TS> Builder<Role, T, NameState, SS, TS> {
     }
 
     /// The source for messages
+    ///
+    /// A sender is authoritative for the source, which is sent as is in the Attach. If the
+    /// `outcomes` field is set, an outcome chosen by the receiver that is not among the declared
+    /// outcomes is returned as [`SendError::UndeclaredOutcome`](crate::link::SendError::UndeclaredOutcome).
     pub fn source(self, source: impl Into<Source>) -> Builder<Role, T, NameState, WithSource, TS> {
         Builder {
             name: self.name,
@@ -296,8 +603,28 @@ impl<Role, T, NameState, SS, TS> Builder<Role, T, NameState, SS, TS> {
             target_state: self.target_state,
 
             auto_accept: self.auto_accept,
+
+            rejected_as_error: self.rejected_as_error,
+            #[cfg(feature = "compression")]
+            body_compression: self.body_compression,
             verify_incoming_source: self.verify_incoming_source,
             verify_incoming_target: self.verify_incoming_target,
+            unsettled_store: self.unsettled_store,
+            dedup_window: self.dedup_window,
+            index_sections: self.index_sections,
+            #[cfg(not(target_arch = "wasm32"))]
+            settlement_timeout: self.settlement_timeout,
+            #[cfg(not(target_arch = "wasm32"))]
+            warn_after_no_credit: self.warn_after_no_credit,
+            #[cfg(not(target_arch = "wasm32"))]
+            fail_after_no_credit: self.fail_after_no_credit,
+            #[cfg(not(target_arch = "wasm32"))]
+            warn_after_no_delivery: self.warn_after_no_delivery,
+            #[cfg(not(target_arch = "wasm32"))]
+            queue_policy: self.queue_policy,
+            send_validator: self.send_validator,
+            batch_validation_policy: self.batch_validation_policy,
+            on_close_unsettled: self.on_close_unsettled,
         }
     }
 
@@ -326,8 +653,28 @@ impl<Role, T, NameState, SS, TS> Builder<Role, T, NameState, SS, TS> {
             target_state: PhantomData,
 
             auto_accept: self.auto_accept,
+
+            rejected_as_error: self.rejected_as_error,
+            #[cfg(feature = "compression")]
+            body_compression: self.body_compression,
             verify_incoming_source: self.verify_incoming_source,
             verify_incoming_target: self.verify_incoming_target,
+            unsettled_store: self.unsettled_store,
+            dedup_window: self.dedup_window,
+            index_sections: self.index_sections,
+            #[cfg(not(target_arch = "wasm32"))]
+            settlement_timeout: self.settlement_timeout,
+            #[cfg(not(target_arch = "wasm32"))]
+            warn_after_no_credit: self.warn_after_no_credit,
+            #[cfg(not(target_arch = "wasm32"))]
+            fail_after_no_credit: self.fail_after_no_credit,
+            #[cfg(not(target_arch = "wasm32"))]
+            warn_after_no_delivery: self.warn_after_no_delivery,
+            #[cfg(not(target_arch = "wasm32"))]
+            queue_policy: self.queue_policy,
+            send_validator: self.send_validator,
+            batch_validation_policy: self.batch_validation_policy,
+            on_close_unsettled: self.on_close_unsettled,
         }
     }
 
@@ -357,8 +704,28 @@ impl<Role, T, NameState, SS, TS> Builder<Role, T, NameState, SS, TS> {
                 target_state: PhantomData,
 
                 auto_accept: self.auto_accept,
+
+                rejected_as_error: self.rejected_as_error,
+                #[cfg(feature = "compression")]
+                body_compression: self.body_compression,
                 verify_incoming_source: self.verify_incoming_source,
                 verify_incoming_target: self.verify_incoming_target,
+                unsettled_store: self.unsettled_store,
+            dedup_window: self.dedup_window,
+            index_sections: self.index_sections,
+            #[cfg(not(target_arch = "wasm32"))]
+            settlement_timeout: self.settlement_timeout,
+            #[cfg(not(target_arch = "wasm32"))]
+            warn_after_no_credit: self.warn_after_no_credit,
+            #[cfg(not(target_arch = "wasm32"))]
+            fail_after_no_credit: self.fail_after_no_credit,
+            #[cfg(not(target_arch = "wasm32"))]
+            warn_after_no_delivery: self.warn_after_no_delivery,
+            #[cfg(not(target_arch = "wasm32"))]
+            queue_policy: self.queue_policy,
+            send_validator: self.send_validator,
+            batch_validation_policy: self.batch_validation_policy,
+            on_close_unsettled: self.on_close_unsettled,
             }
         }
     }
@@ -417,6 +784,17 @@ impl<Role, T, NameState, SS, TS> Builder<Role, T, NameState, SS, TS> {
         self
     }
 
+    /// Set the store that the unsettled map of the link is loaded from at attach and saved to
+    /// whenever a delivery changes state locally
+    ///
+    /// The loaded map is included in the Attach frame so that the remote peer can resume the
+    /// deliveries that were left unsettled, eg. before a process restart. See
+    /// [`unsettled_store`](crate::link::unsettled_store) for more details.
+    pub fn unsettled_store(mut self, store: impl UnsettledStore + 'static) -> Self {
+        self.unsettled_store = Some(Arc::new(store));
+        self
+    }
+
     pub(crate) fn create_link<C, M>(
         self,
         unsettled: ArcUnsettledMap<M>,
@@ -427,10 +805,15 @@ impl<Role, T, NameState, SS, TS> Builder<Role, T, NameState, SS, TS> {
         let local_state = LinkState::Unattached;
 
         let max_message_size = self.max_message_size.unwrap_or(0);
+        let unsettled_store = self
+            .unsettled_store
+            .map(|store| LinkUnsettledStore::new(self.name.clone(), store));
+        let restored_unsettled = unsettled_store.as_ref().and_then(|store| store.load());
 
         // Create a link
         Link::<Role, T, C, M> {
             role: PhantomData,
+            id: LinkId::new(),
             local_state,
             // state_code,
             name: self.name,
@@ -449,8 +832,12 @@ impl<Role, T, NameState, SS, TS> Builder<Role, T, NameState, SS, TS> {
             // flow_state: Consumer::new(notifier, flow_state),
             flow_state: flow_state_consumer,
             unsettled,
+            unsettled_store,
+            restored_unsettled,
+            incomplete_delivery: None,
             verify_incoming_source: self.verify_incoming_source,
             verify_incoming_target: self.verify_incoming_target,
+            state_notifier: watch::channel(LinkState::Unattached).0,
         }
     }
 }
@@ -463,14 +850,102 @@ impl<T, NameState, SS, TS> Builder<role::SenderMarker, T, NameState, SS, TS> {
         self.initial_delivery_count = count;
         self
     }
+
+    /// Sets the `rejected_as_error` field.
+    ///
+    /// If `true`, a `Rejected` outcome is returned as `Err(SendError::Rejected(_))` instead of
+    /// `Ok(SendReceipt::Rejected(_))`.
+    ///
+    /// Default value: `false`
+    pub fn rejected_as_error(mut self, value: bool) -> Self {
+        self.rejected_as_error = value;
+        self
+    }
+
+    /// Logs a warning if a send has waited for link credit longer than `duration`.
+    ///
+    /// This helps finding a receiver that stops issuing credit while the link stays attached.
+    /// The warning is logged once for each send.
+    ///
+    /// Default value: `None`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn warn_after_no_credit(mut self, duration: Duration) -> Self {
+        self.warn_after_no_credit = Some(duration);
+        self
+    }
+
+    /// Fails a send with [`SendError::CreditTimeout`] if it has waited for link credit longer
+    /// than `duration`.
+    ///
+    /// The message is not sent and the link stays attached, so the send can be retried.
+    ///
+    /// Default value: `None`
+    ///
+    /// [`SendError::CreditTimeout`]: crate::link::SendError::CreditTimeout
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn fail_after_no_credit(mut self, duration: Duration) -> Self {
+        self.fail_after_no_credit = Some(duration);
+        self
+    }
+
+    /// Sets the order that the messages queued to the [split](crate::link::Sender::split) sender
+    /// are transferred in.
+    ///
+    /// With [`QueuePolicy::Priority`], the queued message of the highest priority is transferred
+    /// first when credit is issued. See the [priority](crate::link::shared_sender#priority)
+    /// section of the `shared_sender` documentation.
+    ///
+    /// Default value: [`QueuePolicy::Fifo`]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn queue_policy(mut self, policy: QueuePolicy) -> Self {
+        self.queue_policy = policy;
+        self
+    }
+
+    /// Adds a validator that every outgoing message must pass before it is sent.
+    ///
+    /// The validators run in the order they are added, after the message is encoded and before
+    /// any link credit is consumed. A message that fails validation is not sent and the send
+    /// returns [`SendError::Validation`]. Please see the
+    /// [`validation`](crate::link::validation) documentation.
+    ///
+    /// [`SendError::Validation`]: crate::link::SendError::Validation
+    pub fn send_validator(mut self, validator: impl SendValidator + 'static) -> Self {
+        self.send_validator.push(validator);
+        self
+    }
+
+    /// Sets how [`Sender::send_batch`](crate::Sender::send_batch) handles messages that fail
+    /// validation.
+    ///
+    /// Default value: [`BatchValidationPolicy::FailBatch`]
+    pub fn batch_validation_policy(mut self, policy: BatchValidationPolicy) -> Self {
+        self.batch_validation_policy = policy;
+        self
+    }
+
+    cfg_compression! {
+        /// Compresses the `Data` body section of the outgoing messages and sets the
+        /// `content-encoding` of the message properties accordingly.
+        ///
+        /// Bodies smaller than `min_size` bytes, bodies that are not a single `Data` section, and
+        /// messages that already have a `content-encoding` are sent as is.
+        ///
+        /// Default value: `None`
+        pub fn compress_body(mut self, compression: Compression, min_size: usize) -> Self {
+            self.body_compression = Some(BodyCompression::new(compression, min_size));
+            self
+        }
+    }
 }
 
 impl<T, NameState, SS, TS> Builder<role::ReceiverMarker, T, NameState, SS, TS> {
     /// Set the credit mode for the receiver.
     ///
     /// If the credit mode is `Auto`, the receiver will automatically send flow frames when the
-    /// remaining credit if below 50% of the assigned credit. An initial flow frame will also be
-    /// sent if the mode if `Auto`.
+    /// remaining credit if below 50% of the assigned credit. If the credit mode is `Threshold`,
+    /// the flow frames are sent once the remaining credit reaches the threshold instead. An
+    /// initial flow frame will also be sent if the mode if `Auto` or `Threshold`.
     pub fn credit_mode(mut self, credit_mode: CreditMode) -> Self {
         self.credit_mode = credit_mode;
         self
@@ -491,14 +966,21 @@ impl Builder<role::SenderMarker, Target, WithName, WithSource, WithTarget> {
     ///     .await
     ///     .unwrap();
     /// ```
-    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, session)))]
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, session), fields(container_id = %session.container_id, link_name = %self.name)))]
     pub async fn attach<R>(
         self,
         session: &mut SessionHandle<R>,
     ) -> Result<Sender, SenderAttachError> {
-        self.attach_inner(session)
+        let (mut inner, exchange) = self.attach_inner(session).await?;
+        // The remote peer may return unsettled deliveries if an `UnsettledStore` is used
+        inner
+            .complete_attach_exchange(exchange, false)
             .await
-            .map(|inner| Sender { inner })
+            .map_err(|kind| match kind {
+                SenderResumeErrorKind::AttachError(error) => error,
+                _ => SenderAttachError::IllegalState,
+            })?;
+        Ok(Sender { inner })
     }
 }
 
@@ -514,8 +996,8 @@ where
     fn create_flow_state_containers(&mut self) -> (SenderRelayFlowState, SenderFlowState) {
         // Create shared link flow state
         let flow_state_inner = LinkFlowStateInner {
-            initial_delivery_count: self.initial_delivery_count,
-            delivery_count: self.initial_delivery_count,
+            initial_delivery_count: self.initial_delivery_count.into(),
+            delivery_count: self.initial_delivery_count.into(),
             link_credit: 0, // The link-credit and available variables are initialized to zero.
             available: 0,
             drain: false, // The drain flag is initialized to false.
@@ -531,8 +1013,19 @@ where
     async fn attach_inner<R>(
         mut self,
         session: &mut SessionHandle<R>,
-    ) -> Result<SenderInner<SenderLink<T>>, SenderAttachError> {
+    ) -> Result<(SenderInner<SenderLink<T>>, SenderAttachExchange), SenderAttachError> {
         let buffer_size = self.buffer_size;
+        let rejected_as_error = self.rejected_as_error;
+        #[cfg(feature = "compression")]
+        let body_compression = self.body_compression;
+        #[cfg(not(target_arch = "wasm32"))]
+        let warn_after_no_credit = self.warn_after_no_credit;
+        #[cfg(not(target_arch = "wasm32"))]
+        let fail_after_no_credit = self.fail_after_no_credit;
+        #[cfg(not(target_arch = "wasm32"))]
+        let queue_policy = self.queue_policy;
+        let validator = self.send_validator.clone();
+        let batch_validation_policy = self.batch_validation_policy;
         let (incoming_tx, mut incoming_rx) = mpsc::channel::<LinkIncomingItem>(self.buffer_size);
         let outgoing = session.outgoing.clone();
         let (producer, consumer) = self.create_flow_state_containers();
@@ -541,9 +1034,11 @@ where
         let link_relay = LinkRelay::new_sender(incoming_tx, producer, unsettled.clone());
         let output_handle =
             session::allocate_link(&session.control, self.name.clone(), link_relay).await?;
-        let mut link = self.create_link(unsettled, output_handle, consumer);
+        let link = self.create_link(unsettled, output_handle, consumer);
+        // The output handle is released if the attach fails or this future is dropped
+        let mut link = AttachGuard::new(link, outgoing.clone(), session.control.clone());
 
-        match link
+        let exchange = match link
             .exchange_attach(&session.outgoing, &mut incoming_rx, &session.control, false)
             .await
         {
@@ -552,7 +1047,7 @@ where
                 tracing::debug!(?exchange);
                 #[cfg(feature = "log")]
                 log::debug!("exchange = {:?}", exchange);
-                exchange.complete_or(SenderAttachError::IllegalState)?
+                exchange
             }
             Err(attach_error) => {
                 #[cfg(feature = "tracing")]
@@ -567,9 +1062,12 @@ where
                         &session.control,
                     )
                     .await;
+                link.release().await;
                 return Err(err);
             }
-        }
+        };
+        let link = link.disarm();
+        link.save_unsettled();
 
         // Attach completed, return Sender
         let inner = SenderInner {
@@ -578,9 +1076,21 @@ where
             session: session.control.clone(),
             outgoing,
             incoming: incoming_rx,
+            rejected_as_error,
+            #[cfg(feature = "compression")]
+            body_compression,
+            #[cfg(not(target_arch = "wasm32"))]
+            warn_after_no_credit,
+            #[cfg(not(target_arch = "wasm32"))]
+            fail_after_no_credit,
+            #[cfg(not(target_arch = "wasm32"))]
+            queue_policy,
+            validator,
+            batch_validation_policy,
+            pending_outcomes: Vec::new(),
             // marker: PhantomData,
         };
-        Ok(inner)
+        Ok((inner, exchange))
     }
 }
 
@@ -597,7 +1107,7 @@ impl Builder<role::ReceiverMarker, Target, WithName, WithSource, WithTarget> {
     ///     .await
     ///     .unwrap();
     /// ```
-    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, session)))]
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, session), fields(container_id = %session.container_id, link_name = %self.name)))]
     pub async fn attach<R>(
         self,
         session: &mut SessionHandle<R>,
@@ -620,8 +1130,8 @@ where
     fn create_flow_state_containers(&mut self) -> (ReceiverRelayFlowState, ReceiverFlowState) {
         // Create shared link flow state
         let flow_state_inner = LinkFlowStateInner {
-            initial_delivery_count: self.initial_delivery_count,
-            delivery_count: self.initial_delivery_count,
+            initial_delivery_count: self.initial_delivery_count.into(),
+            delivery_count: self.initial_delivery_count.into(),
             link_credit: 0, // The link-credit and available variables are initialized to zero.
             available: 0,
             drain: false, // The drain flag is initialized to false.
@@ -643,23 +1153,38 @@ where
         let (relay_flow_state, flow_state) = self.create_flow_state_containers();
         let unsettled = Arc::new(RwLock::new(None));
         let auto_accept = self.auto_accept;
+        let dedup_window = self.dedup_window.map(DedupWindow::new);
+        let index_sections = self.index_sections;
+        let on_close_unsettled = self.on_close_unsettled;
+        let remote_settlements = Arc::new(RemoteSettlements::default());
+        #[cfg(not(target_arch = "wasm32"))]
+        let settlement_timeout = self.settlement_timeout;
+        #[cfg(not(target_arch = "wasm32"))]
+        let warn_after_no_delivery = self.warn_after_no_delivery;
 
         let link_relay = LinkRelay::new_receiver(
             incoming_tx,
             relay_flow_state,
             unsettled.clone(),
+            remote_settlements.clone(),
             self.rcv_settle_mode.clone(),
         );
         // Create Link in Session
         // Any error here will be on the Session level and thus it should immediately return with an error
         let output_handle =
             session::allocate_link(&session.control, self.name.clone(), link_relay).await?;
-        let mut link = self.create_link(unsettled, output_handle, flow_state);
+        let link = self.create_link(unsettled, output_handle, flow_state);
+        // The output handle is released if the attach fails or this future is dropped
+        let mut link = AttachGuard::new(link, outgoing.clone(), session.control.clone());
+        let is_resuming = link.restored_unsettled.is_some();
 
         match link
             .exchange_attach(&session.outgoing, &mut incoming_rx, &session.control, false)
             .await
         {
+            // The remote peer resumes the unsettled deliveries restored from the
+            // `UnsettledStore` with transfers that are handled like any other transfer
+            Ok(_) if is_resuming => {}
             Ok(outcome) => outcome.complete_or(ReceiverAttachError::IllegalState)?,
             Err(attach_error) => {
                 let err = link
@@ -670,23 +1195,39 @@ where
                         &session.control,
                     )
                     .await;
+                link.release().await;
                 return Err(err);
             }
         }
+        let link = link.disarm();
+        link.save_unsettled();
 
         let mut inner = ReceiverInner {
             link,
             buffer_size,
             credit_mode,
             processed: AtomicU32::new(0),
+            granted: AtomicU32::new(0),
+            paused: false,
             auto_accept,
+            index_sections,
+            credit_pool: None,
             session: session.control.clone(),
             outgoing,
             incoming: incoming_rx,
             incomplete_transfer: None,
+            dedup_window,
+            aborted_deliveries: 0,
+            remote_settlements,
+            on_close_unsettled,
+            unsettled_infos: Vec::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            settlement_timeout,
+            #[cfg(not(target_arch = "wasm32"))]
+            warn_after_no_delivery,
         };
 
-        if let CreditMode::Auto(credit) = inner.credit_mode {
+        if let Some(credit) = inner.credit_mode.refill_credit() {
             inner.set_credit(credit).await?;
         }
 
@@ -703,7 +1244,9 @@ cfg_transaction! {
         ) -> Result<Controller, SenderAttachError> {
             use tokio::sync::Mutex;
 
-            self.attach_inner(session).await.map(|inner| Controller {
+            let (inner, exchange) = self.attach_inner(session).await?;
+            exchange.complete_or(SenderAttachError::IllegalState)?;
+            Ok(Controller {
                 inner: Mutex::new(inner),
             })
         }
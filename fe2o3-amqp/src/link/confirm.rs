@@ -0,0 +1,375 @@
+//! Tracks which deliveries of a sender are accepted as a watermark over their delivery ids
+//!
+//! The delivery ids are assigned by the session, so the tracker is shared by the sender and the
+//! link relay in the session, which sees the delivery id of every outgoing delivery and of every
+//! incoming Disposition.
+
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use fe2o3_amqp_types::{
+    definitions::{DeliveryNumber, DeliveryTag},
+    messaging::DeliveryState,
+};
+use parking_lot::Mutex;
+use tokio::sync::{mpsc, watch};
+
+/// A delivery tracked by the confirm watermark that the receiver did not accept
+///
+/// See [`Sender::confirm_failures`](crate::Sender::confirm_failures)
+#[derive(Debug, Clone)]
+pub struct ConfirmFailure {
+    /// The delivery id assigned by the session
+    pub delivery_id: DeliveryNumber,
+
+    /// The delivery tag of the delivery
+    pub delivery_tag: DeliveryTag,
+
+    /// The outcome reported by the receiver, or `None` if the delivery was settled without one
+    /// or the link was detached before an outcome arrived
+    pub state: Option<DeliveryState>,
+}
+
+#[derive(Debug)]
+enum Confirm {
+    Pending(DeliveryTag),
+    Accepted,
+    Failed,
+}
+
+#[derive(Debug, Default)]
+struct Outstanding {
+    /// Whether the watermark has been moved to just before the first tracked delivery
+    started: bool,
+
+    /// Deliveries that are not below the watermark yet, in the order they were sent. The delivery
+    /// ids increase but may have gaps, which are the deliveries of the other links of the session
+    deliveries: VecDeque<(DeliveryNumber, Confirm)>,
+}
+
+impl Outstanding {
+    fn position(&self, delivery_id: DeliveryNumber) -> Option<usize> {
+        let (first, _) = self.deliveries.front()?;
+        let offset = delivery_id.wrapping_sub(*first);
+        self.deliveries
+            .binary_search_by_key(&offset, |(id, _)| id.wrapping_sub(*first))
+            .ok()
+    }
+
+    /// Removes the accepted deliveries at the front and returns the last one removed
+    fn advance(&mut self) -> Option<DeliveryNumber> {
+        let mut watermark = None;
+        while let Some((delivery_id, Confirm::Accepted)) = self.deliveries.front() {
+            watermark = Some(*delivery_id);
+            self.deliveries.pop_front();
+        }
+        watermark
+    }
+}
+
+/// Shared by the sender and the link relay in the session. Nothing is tracked until the
+/// application asks for the watermark or sends an unconfirmed message
+#[derive(Debug)]
+pub(crate) struct ConfirmTracker {
+    enabled: AtomicBool,
+    outstanding: Mutex<Outstanding>,
+    watermark: watch::Sender<DeliveryNumber>,
+    failures: mpsc::UnboundedSender<ConfirmFailure>,
+    failures_rx: Mutex<Option<mpsc::UnboundedReceiver<ConfirmFailure>>>,
+}
+
+impl Default for ConfirmTracker {
+    fn default() -> Self {
+        let (failures, failures_rx) = mpsc::unbounded_channel();
+        Self {
+            enabled: AtomicBool::new(false),
+            outstanding: Mutex::new(Outstanding::default()),
+            watermark: watch::channel(0).0,
+            failures,
+            failures_rx: Mutex::new(Some(failures_rx)),
+        }
+    }
+}
+
+impl ConfirmTracker {
+    /// Starts tracking the deliveries that are sent from now on
+    pub(crate) fn enable(&self) {
+        self.enabled.store(true, Ordering::Release);
+    }
+
+    pub(crate) fn watermark(&self) -> watch::Receiver<DeliveryNumber> {
+        self.enable();
+        self.watermark.subscribe()
+    }
+
+    /// Takes the receiving half of the failures, which is only returned once
+    pub(crate) fn failures(&self) -> Option<mpsc::UnboundedReceiver<ConfirmFailure>> {
+        self.enable();
+        self.failures_rx.lock().take()
+    }
+
+    /// The session has assigned `delivery_id` to an unsettled delivery of the link
+    pub(crate) fn on_sent(&self, delivery_id: DeliveryNumber, delivery_tag: &DeliveryTag) {
+        if !self.enabled.load(Ordering::Acquire) {
+            return;
+        }
+        let mut outstanding = self.outstanding.lock();
+        if !outstanding.started {
+            outstanding.started = true;
+            // Nothing of the link is below the first delivery, which is not a change that the
+            // application needs to be woken up for
+            self.watermark.send_if_modified(|watermark| {
+                *watermark = delivery_id.wrapping_sub(1);
+                false
+            });
+        }
+        outstanding
+            .deliveries
+            .push_back((delivery_id, Confirm::Pending(delivery_tag.clone())));
+    }
+
+    /// A Disposition from the receiver covers `delivery_id`
+    pub(crate) fn on_disposition(
+        &self,
+        delivery_id: DeliveryNumber,
+        settled: bool,
+        state: &Option<DeliveryState>,
+    ) {
+        let accepted = matches!(state, Some(DeliveryState::Accepted(_)));
+        let is_terminal = state.as_ref().map(|s| s.is_terminal()).unwrap_or(false);
+        if !settled && !is_terminal {
+            return;
+        }
+
+        let mut outstanding = self.outstanding.lock();
+        let Some(index) = outstanding.position(delivery_id) else {
+            return;
+        };
+        let (_, confirm) = &mut outstanding.deliveries[index];
+        let delivery_tag = match std::mem::replace(confirm, Confirm::Accepted) {
+            Confirm::Pending(delivery_tag) => delivery_tag,
+            // Already confirmed by an earlier Disposition
+            previous => {
+                *confirm = previous;
+                return;
+            }
+        };
+        if !accepted {
+            *confirm = Confirm::Failed;
+            let _ = self.failures.send(ConfirmFailure {
+                delivery_id,
+                delivery_tag,
+                state: state.clone(),
+            });
+        }
+        self.advance(&mut outstanding);
+    }
+
+    /// The deliveries that are still pending will not be confirmed on this attachment of the
+    /// link, and are reported as failures without a state
+    pub(crate) fn on_detached(&self) {
+        let mut outstanding = self.outstanding.lock();
+        for (delivery_id, confirm) in outstanding.deliveries.iter_mut() {
+            if let Confirm::Pending(delivery_tag) = confirm {
+                let _ = self.failures.send(ConfirmFailure {
+                    delivery_id: *delivery_id,
+                    delivery_tag: delivery_tag.clone(),
+                    state: None,
+                });
+                *confirm = Confirm::Failed;
+            }
+        }
+    }
+
+    /// The application has dealt with a failed delivery, which no longer holds the watermark
+    /// back
+    pub(crate) fn dismiss(&self, delivery_id: DeliveryNumber) {
+        let mut outstanding = self.outstanding.lock();
+        let Some(index) = outstanding.position(delivery_id) else {
+            return;
+        };
+        let (_, confirm) = &mut outstanding.deliveries[index];
+        if let Confirm::Failed = confirm {
+            *confirm = Confirm::Accepted;
+            self.advance(&mut outstanding);
+        }
+    }
+
+    fn advance(&self, outstanding: &mut Outstanding) {
+        if let Some(watermark) = outstanding.advance() {
+            self.watermark.send_replace(watermark);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fe2o3_amqp_types::{
+        definitions::DeliveryTag,
+        messaging::{Accepted, DeliveryState, Modified, Received, Rejected, Released},
+    };
+
+    use super::ConfirmTracker;
+
+    fn tag(delivery_id: u32) -> DeliveryTag {
+        DeliveryTag::from(delivery_id.to_be_bytes().to_vec())
+    }
+
+    fn tracker(delivery_ids: impl IntoIterator<Item = u32>) -> ConfirmTracker {
+        let tracker = ConfirmTracker::default();
+        tracker.enable();
+        for delivery_id in delivery_ids {
+            tracker.on_sent(delivery_id, &tag(delivery_id));
+        }
+        tracker
+    }
+
+    fn accepted() -> Option<DeliveryState> {
+        Some(DeliveryState::Accepted(Accepted {}))
+    }
+
+    #[test]
+    fn nothing_is_tracked_until_enabled() {
+        let tracker = ConfirmTracker::default();
+        tracker.on_sent(0, &tag(0));
+        tracker.enable();
+        tracker.on_sent(1, &tag(1));
+        let watermark = tracker.watermark();
+        assert_eq!(*watermark.borrow(), 0);
+
+        tracker.on_disposition(1, true, &accepted());
+        assert_eq!(*watermark.borrow(), 1);
+    }
+
+    #[test]
+    fn watermark_advances_over_contiguous_accepted_deliveries() {
+        let tracker = tracker(10..15);
+        let mut watermark = tracker.watermark();
+        assert_eq!(*watermark.borrow_and_update(), 9);
+
+        tracker.on_disposition(10, true, &accepted());
+        assert!(watermark.has_changed().unwrap());
+        assert_eq!(*watermark.borrow_and_update(), 10);
+
+        // Out of order
+        tracker.on_disposition(13, true, &accepted());
+        tracker.on_disposition(12, true, &accepted());
+        assert!(!watermark.has_changed().unwrap());
+        assert_eq!(*watermark.borrow(), 10);
+
+        // Fills the gap
+        tracker.on_disposition(11, true, &accepted());
+        assert_eq!(*watermark.borrow_and_update(), 13);
+
+        tracker.on_disposition(14, true, &accepted());
+        assert_eq!(*watermark.borrow(), 14);
+    }
+
+    #[test]
+    fn ranged_dispositions_advance_over_ids_of_other_links() {
+        // The ids in between belong to the other links of the session
+        let tracker = tracker([2, 3, 7, 8, 20]);
+        let watermark = tracker.watermark();
+
+        // A ranged Disposition is relayed to the link one delivery id at a time
+        for delivery_id in 7..=20 {
+            tracker.on_disposition(delivery_id, true, &accepted());
+        }
+        assert_eq!(*watermark.borrow(), 1);
+        for delivery_id in 0..=3 {
+            tracker.on_disposition(delivery_id, true, &accepted());
+        }
+        assert_eq!(*watermark.borrow(), 20);
+    }
+
+    #[test]
+    fn watermark_advances_across_wrap_around() {
+        let tracker = tracker([u32::MAX - 1, u32::MAX, 0, 1]);
+        let watermark = tracker.watermark();
+        assert_eq!(*watermark.borrow(), u32::MAX - 2);
+
+        tracker.on_disposition(0, true, &accepted());
+        tracker.on_disposition(u32::MAX, true, &accepted());
+        tracker.on_disposition(u32::MAX - 1, true, &accepted());
+        assert_eq!(*watermark.borrow(), 0);
+        tracker.on_disposition(1, true, &accepted());
+        assert_eq!(*watermark.borrow(), 1);
+    }
+
+    #[test]
+    fn non_terminal_state_does_not_confirm() {
+        let tracker = tracker([0]);
+        let watermark = tracker.watermark();
+        let received = Some(DeliveryState::Received(Received {
+            section_number: 0,
+            section_offset: 0,
+        }));
+        tracker.on_disposition(0, false, &received);
+        assert_eq!(*watermark.borrow(), u32::MAX);
+
+        // An unsettled terminal state in receiver settle mode `second`
+        tracker.on_disposition(0, false, &accepted());
+        assert_eq!(*watermark.borrow(), 0);
+    }
+
+    #[test]
+    fn failed_deliveries_hold_the_watermark_back_and_are_reported() {
+        let tracker = tracker(0..4);
+        let watermark = tracker.watermark();
+        let mut failures = tracker.failures().unwrap();
+        assert!(tracker.failures().is_none());
+
+        let rejected = Some(DeliveryState::Rejected(Rejected { error: None }));
+        tracker.on_disposition(1, true, &rejected);
+        tracker.on_disposition(2, true, &Some(DeliveryState::Released(Released {})));
+        tracker.on_disposition(0, true, &accepted());
+        tracker.on_disposition(3, true, &accepted());
+        assert_eq!(*watermark.borrow(), 0);
+
+        let failure = failures.try_recv().unwrap();
+        assert_eq!(failure.delivery_id, 1);
+        assert_eq!(failure.delivery_tag, tag(1));
+        assert_eq!(failure.state, rejected);
+        let failure = failures.try_recv().unwrap();
+        assert_eq!(failure.delivery_tag, tag(2));
+        assert!(failures.try_recv().is_err());
+
+        // A later Disposition of a failed delivery does not accept it
+        tracker.on_disposition(1, true, &accepted());
+        assert_eq!(*watermark.borrow(), 0);
+
+        tracker.dismiss(1);
+        assert_eq!(*watermark.borrow(), 1);
+        // Only failed deliveries can be dismissed
+        tracker.dismiss(3);
+        tracker.dismiss(2);
+        assert_eq!(*watermark.borrow(), 3);
+    }
+
+    #[test]
+    fn pending_deliveries_fail_when_detached() {
+        let tracker = tracker(0..3);
+        let watermark = tracker.watermark();
+        let mut failures = tracker.failures().unwrap();
+        let modified = Some(DeliveryState::Modified(Modified {
+            delivery_failed: Some(true),
+            undeliverable_here: None,
+            message_annotations: None,
+        }));
+        tracker.on_disposition(0, true, &modified);
+        tracker.on_disposition(1, true, &accepted());
+        tracker.on_detached();
+
+        let failed: Vec<_> = std::iter::from_fn(|| failures.try_recv().ok())
+            .map(|failure| (failure.delivery_id, failure.state.is_some()))
+            .collect();
+        assert_eq!(failed, vec![(0, true), (2, false)]);
+        assert_eq!(*watermark.borrow(), u32::MAX);
+
+        tracker.dismiss(0);
+        tracker.dismiss(2);
+        assert_eq!(*watermark.borrow(), 2);
+    }
+}
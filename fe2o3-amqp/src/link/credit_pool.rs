@@ -0,0 +1,380 @@
+//! Distributes a budget of link credit across many receivers
+//!
+//! A process that consumes from many links (eg. one per tenant queue) would prefetch the auto
+//! credit of every link. A [`CreditPool`] bounds the link credit of all the receivers that join
+//! it and divides it among them according to a [`CreditPolicy`].
+//!
+//! ```rust,ignore
+//! use fe2o3_amqp::link::{CreditPolicy, CreditPool};
+//!
+//! let pool = CreditPool::new(500).policy(CreditPolicy::Demand);
+//! tokio::spawn(pool.clone().run(Duration::from_secs(1)));
+//!
+//! for queue in queues {
+//!     let mut receiver = Receiver::attach(&mut session, queue, queue).await.unwrap();
+//!     receiver.join_credit_pool(&pool, 1).await.unwrap();
+//!     tokio::spawn(consume(receiver));
+//! }
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+};
+
+use parking_lot::Mutex;
+use tokio::sync::mpsc;
+
+use crate::{endpoint::OutputHandle, util::SerialNumber};
+
+use super::{frame::LinkFrame, ReceiverFlowState};
+
+cfg_not_wasm32! {
+    use std::time::Duration;
+
+    use futures_util::StreamExt;
+
+    use crate::rt::Interval;
+}
+
+/// The default percentage by which a share must change before it is issued to the link
+pub const DEFAULT_HYSTERESIS: u32 = 20;
+
+/// The default link credit kept by an idle receiver in [`CreditPolicy::Demand`]
+pub const DEFAULT_IDLE_CREDIT: u32 = 1;
+
+/// How a [`CreditPool`] divides its budget among the receivers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CreditPolicy {
+    /// Every receiver gets the same share
+    #[default]
+    Equal,
+
+    /// The shares are proportional to the weights the receivers joined with
+    Weighted,
+
+    /// The shares are proportional to the weighted demand of the receivers, which is their recent
+    /// delivery rate plus what the senders report as available. Every receiver keeps the idle
+    /// credit so that a message arriving on an idle link is still delivered
+    Demand,
+}
+
+/// The state of a receiver in a [`CreditPool`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreditPoolLinkStats {
+    /// The name of the link
+    pub name: String,
+
+    /// The weight the receiver joined with
+    pub weight: u32,
+
+    /// The link credit the pool currently gives to the receiver
+    pub share: u32,
+
+    /// The link credit that is left on the link
+    pub link_credit: u32,
+
+    /// What the sender reports as available, if it does
+    pub available: Option<u32>,
+
+    /// The smoothed number of deliveries received between two rebalances
+    pub delivery_rate: f64,
+}
+
+/// Distributes a global budget of link credit across the receivers that join it
+///
+/// The shares are recomputed by [`rebalance`](Self::rebalance), which is called periodically by
+/// [`run`](Self::run). A new share is only issued to the link if it differs from the current one
+/// by more than the hysteresis, and the receiver refills its link credit up to its share as it
+/// disposes deliveries. A receiver that detaches leaves the pool, and its share is given to the
+/// other receivers by the next rebalance.
+///
+/// The pool is cloned to be shared. The configuration should be set before it is cloned.
+#[derive(Debug, Clone)]
+pub struct CreditPool {
+    budget: u32,
+    policy: CreditPolicy,
+    hysteresis: u32,
+    idle_credit: u32,
+    members: Arc<Mutex<Members>>,
+}
+
+#[derive(Debug, Default)]
+struct Members {
+    next_id: u64,
+    links: BTreeMap<u64, Member>,
+}
+
+#[derive(Debug)]
+struct Member {
+    name: String,
+    weight: u32,
+    flow_state: ReceiverFlowState,
+    output_handle: OutputHandle,
+    outgoing: mpsc::Sender<LinkFrame>,
+    share: Arc<AtomicU32>,
+    last_delivery_count: SerialNumber,
+    delivery_rate: f64,
+}
+
+impl CreditPool {
+    /// Creates a pool with [`CreditPolicy::Equal`] that gives out at most `budget` link credit
+    pub fn new(budget: u32) -> Self {
+        Self {
+            budget,
+            policy: CreditPolicy::default(),
+            hysteresis: DEFAULT_HYSTERESIS,
+            idle_credit: DEFAULT_IDLE_CREDIT,
+            members: Arc::new(Mutex::new(Members::default())),
+        }
+    }
+
+    /// Sets how the budget is divided
+    pub fn policy(mut self, policy: CreditPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Sets the percentage by which a share must change before it is issued to the link. This
+    /// avoids sending a flow for every small change of the delivery rates
+    pub fn hysteresis(mut self, percent: u32) -> Self {
+        self.hysteresis = percent;
+        self
+    }
+
+    /// Sets the link credit kept by an idle receiver in [`CreditPolicy::Demand`]
+    pub fn idle_credit(mut self, credit: u32) -> Self {
+        self.idle_credit = credit;
+        self
+    }
+
+    /// The total link credit given out by the pool
+    pub fn budget(&self) -> u32 {
+        self.budget
+    }
+
+    /// The number of receivers in the pool
+    pub fn len(&self) -> usize {
+        self.members.lock().links.len()
+    }
+
+    /// Whether there is no receiver in the pool
+    pub fn is_empty(&self) -> bool {
+        self.members.lock().links.is_empty()
+    }
+
+    /// Returns the state of each receiver in the pool
+    pub fn stats(&self) -> Vec<CreditPoolLinkStats> {
+        self.members
+            .lock()
+            .links
+            .values()
+            .map(|member| CreditPoolLinkStats {
+                name: member.name.clone(),
+                weight: member.weight,
+                share: member.share.load(Ordering::Acquire),
+                link_credit: member.flow_state.link_credit(),
+                available: member.flow_state.available(),
+                delivery_rate: member.delivery_rate,
+            })
+            .collect()
+    }
+
+    /// Recomputes the shares and sends a flow to each link whose share changed by more than the
+    /// hysteresis
+    pub async fn rebalance(&self) {
+        let flows = {
+            let mut members = self.members.lock();
+            let demands: Vec<(u32, f64)> = members
+                .links
+                .values_mut()
+                .map(|member| {
+                    let delivery_count = member.flow_state.snapshot(0).delivery_count.into();
+                    // A delivery-count that goes back counts as no delivery
+                    let delivered = member
+                        .last_delivery_count
+                        .distance_to(delivery_count)
+                        .unwrap_or(0);
+                    member.last_delivery_count = delivery_count;
+                    member.delivery_rate = (member.delivery_rate + f64::from(delivered)) / 2.0;
+                    let available = member.flow_state.available().unwrap_or(0);
+                    (member.weight, member.delivery_rate + f64::from(available))
+                })
+                .collect();
+            let shares = shares(self.policy, self.budget, self.idle_credit, &demands);
+
+            let mut flows = Vec::new();
+            for (member, share) in members.links.values().zip(shares) {
+                let current = member.share.load(Ordering::Acquire);
+                if is_significant(current, share, self.hysteresis) {
+                    member.share.store(share, Ordering::Release);
+                    let handle = member.output_handle.clone().into();
+                    let flow = member.flow_state.issue_credit(handle, share);
+                    flows.push((member.outgoing.clone(), flow));
+                }
+            }
+            flows
+        };
+
+        for (outgoing, flow) in flows {
+            // The link may have been detached since, which leaves the pool
+            let _ = outgoing.send(LinkFrame::Flow(flow)).await;
+        }
+    }
+
+    cfg_not_wasm32! {
+        /// Rebalances the pool once per `period` until there is no other handle to the pool and
+        /// no receiver left in it
+        pub async fn run(self, period: Duration) {
+            let mut interval = Interval::new(period);
+            while interval.next().await.is_some() {
+                if Arc::strong_count(&self.members) == 1 && self.is_empty() {
+                    break;
+                }
+                self.rebalance().await;
+            }
+        }
+    }
+
+    pub(crate) fn register(
+        &self,
+        name: String,
+        weight: u32,
+        flow_state: ReceiverFlowState,
+        output_handle: OutputHandle,
+        outgoing: mpsc::Sender<LinkFrame>,
+    ) -> CreditPoolMember {
+        let mut members = self.members.lock();
+        let id = members.next_id;
+        members.next_id += 1;
+        let share = Arc::new(AtomicU32::new(0));
+        let last_delivery_count = flow_state.snapshot(0).delivery_count.into();
+        let member = Member {
+            name,
+            weight: weight.max(1),
+            flow_state,
+            output_handle,
+            outgoing,
+            share: share.clone(),
+            last_delivery_count,
+            delivery_rate: 0.0,
+        };
+        members.links.insert(id, member);
+        CreditPoolMember {
+            members: self.members.clone(),
+            id,
+            share,
+        }
+    }
+}
+
+/// The membership of a receiver in a [`CreditPool`], which leaves the pool when dropped
+#[derive(Debug)]
+pub(crate) struct CreditPoolMember {
+    members: Arc<Mutex<Members>>,
+    id: u64,
+    share: Arc<AtomicU32>,
+}
+
+impl CreditPoolMember {
+    /// The link credit the pool currently gives to the receiver
+    pub(crate) fn share(&self) -> u32 {
+        self.share.load(Ordering::Acquire)
+    }
+}
+
+impl Drop for CreditPoolMember {
+    fn drop(&mut self) {
+        self.members.lock().links.remove(&self.id);
+    }
+}
+
+/// Divides the budget among the links given their weight and demand
+fn shares(policy: CreditPolicy, budget: u32, idle_credit: u32, links: &[(u32, f64)]) -> Vec<u32> {
+    if links.is_empty() {
+        return Vec::new();
+    }
+    let weights: Vec<f64> = links.iter().map(|(w, _)| f64::from(*w)).collect();
+    match policy {
+        CreditPolicy::Equal => divide(budget, &vec![1.0; links.len()]),
+        CreditPolicy::Weighted => divide(budget, &weights),
+        CreditPolicy::Demand => {
+            let demands: Vec<f64> = links.iter().map(|(w, d)| f64::from(*w) * d).collect();
+            if demands.iter().sum::<f64>() <= 0.0 {
+                // Nothing is known about the demand yet
+                return divide(budget, &weights);
+            }
+            let base = idle_credit.min(budget / links.len() as u32);
+            let rest = budget - base * links.len() as u32;
+            divide(rest, &demands)
+                .into_iter()
+                .map(|share| share + base)
+                .collect()
+        }
+    }
+}
+
+fn divide(amount: u32, parts: &[f64]) -> Vec<u32> {
+    let total: f64 = parts.iter().sum();
+    parts
+        .iter()
+        .map(|part| match total > 0.0 {
+            true => (f64::from(amount) * part / total).floor() as u32,
+            false => 0,
+        })
+        .collect()
+}
+
+/// Whether the share changed by more than `hysteresis` percent of the current share
+fn is_significant(current: u32, new: u32, hysteresis: u32) -> bool {
+    let change = current.abs_diff(new);
+    let threshold = (u64::from(current) * u64::from(hysteresis) / 100).max(1);
+    u64::from(change) >= threshold
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_significant, shares, CreditPolicy};
+
+    #[test]
+    fn equal_and_weighted_shares() {
+        let links = [(1, 0.0), (3, 10.0), (4, 0.0)];
+        assert_eq!(shares(CreditPolicy::Equal, 90, 1, &links), vec![30, 30, 30]);
+        assert_eq!(
+            shares(CreditPolicy::Weighted, 80, 1, &links),
+            vec![10, 30, 40]
+        );
+        assert!(shares(CreditPolicy::Equal, 90, 1, &[]).is_empty());
+    }
+
+    #[test]
+    fn demand_shares_follow_the_delivery_rate() {
+        // A busy, a slow and an idle link
+        let links = [(1, 90.0), (1, 10.0), (1, 0.0)];
+        let demand_shares = shares(CreditPolicy::Demand, 103, 1, &links);
+        assert_eq!(demand_shares, vec![91, 11, 1]);
+        assert!(demand_shares.iter().sum::<u32>() <= 103);
+
+        // Weights scale the demand
+        let links = [(1, 10.0), (3, 10.0)];
+        assert_eq!(shares(CreditPolicy::Demand, 42, 1, &links), vec![11, 31]);
+    }
+
+    #[test]
+    fn demand_shares_without_demand_are_weighted() {
+        let links = [(1, 0.0), (1, 0.0)];
+        assert_eq!(shares(CreditPolicy::Demand, 100, 1, &links), vec![50, 50]);
+    }
+
+    #[test]
+    fn small_changes_are_not_significant() {
+        assert!(!is_significant(100, 110, 20));
+        assert!(is_significant(100, 120, 20));
+        assert!(is_significant(100, 70, 20));
+        assert!(is_significant(0, 1, 20));
+        assert!(!is_significant(5, 5, 0));
+    }
+}
@@ -0,0 +1,100 @@
+//! Detection of deliveries that are resent by the remote sender after a link is resumed
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use fe2o3_amqp_types::{
+    definitions::DeliveryTag, messaging::DeliveryState, primitives::OrderedMap,
+};
+use parking_lot::Mutex;
+
+/// Remembers the outcomes of the last `capacity` deliveries disposed by a receiver
+///
+/// Delivery tags are assumed to be unique among all the deliveries in the window, which holds for
+/// senders that generate a new tag for every delivery (as the `Sender` in this crate does).
+#[derive(Debug)]
+pub(crate) struct DedupWindow {
+    capacity: usize,
+    outcomes: Mutex<OrderedMap<DeliveryTag, DeliveryState>>,
+    suppressed: AtomicU64,
+
+    /// Whether the remaining transfers of a suppressed multi-transfer delivery are discarded
+    pub(crate) discarding: bool,
+}
+
+impl DedupWindow {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            outcomes: Mutex::new(OrderedMap::new()),
+            suppressed: AtomicU64::new(0),
+            discarding: false,
+        }
+    }
+
+    /// Records the terminal outcome of a delivery, evicting the oldest entries once the window is
+    /// full
+    pub(crate) fn record(&self, delivery_tag: DeliveryTag, state: DeliveryState) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut outcomes = self.outcomes.lock();
+        // Re-inserting moves the entry to the back so that it is evicted last
+        outcomes.shift_remove(&delivery_tag);
+        outcomes.insert(delivery_tag, state);
+        let len = outcomes.len();
+        if len > self.capacity {
+            outcomes.drain(..len - self.capacity);
+        }
+    }
+
+    /// Returns the recorded outcome if the delivery is already disposed
+    pub(crate) fn outcome(&self, delivery_tag: &DeliveryTag) -> Option<DeliveryState> {
+        self.outcomes.lock().get(delivery_tag).cloned()
+    }
+
+    pub(crate) fn on_suppressed(&self) {
+        self.suppressed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of duplicated deliveries that are settled without being yielded to the application
+    pub(crate) fn suppressed(&self) -> u64 {
+        self.suppressed.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fe2o3_amqp_types::{
+        definitions::DeliveryTag,
+        messaging::{Accepted, DeliveryState, Released},
+    };
+
+    use super::DedupWindow;
+
+    fn tag(value: u8) -> DeliveryTag {
+        DeliveryTag::from(vec![value])
+    }
+
+    #[test]
+    fn oldest_outcome_is_evicted() {
+        let window = DedupWindow::new(2);
+        window.record(tag(1), DeliveryState::Accepted(Accepted {}));
+        window.record(tag(2), DeliveryState::Released(Released {}));
+        window.record(tag(3), DeliveryState::Accepted(Accepted {}));
+
+        assert!(window.outcome(&tag(1)).is_none());
+        assert_eq!(
+            window.outcome(&tag(2)),
+            Some(DeliveryState::Released(Released {}))
+        );
+        assert!(window.outcome(&tag(3)).is_some());
+    }
+
+    #[test]
+    fn zero_capacity_records_nothing() {
+        let window = DedupWindow::new(0);
+        window.record(tag(1), DeliveryState::Accepted(Accepted {}));
+        assert!(window.outcome(&tag(1)).is_none());
+    }
+}
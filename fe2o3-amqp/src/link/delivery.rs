@@ -1,24 +1,46 @@
 //! Helper types differentiating message delivery
 
+use bytes::BytesMut;
 use fe2o3_amqp_types::{
     definitions::{DeliveryNumber, DeliveryTag, Handle, MessageFormat, ReceiverSettleMode},
-    messaging::{Accepted, DeliveryState, Message, Outcome, SerializableBody, MESSAGE_FORMAT},
-    primitives::BinaryRef,
+    messaging::{
+        message::{
+            sections::{self, EncodedSection, MessageSection, SectionKind},
+            DecodeIntoMessage,
+        },
+        Accepted, Body, BodyKind, Data, DeliveryAnnotations, DeliveryState, FromBody, Message,
+        Modified, Outcome, Rejected, Released, SerializableBody, MESSAGE_FORMAT,
+    },
+    primitives::{Array, BinaryRef, Symbol, Value},
 };
 use futures_util::FutureExt;
 use pin_project_lite::pin_project;
-use std::{future::Future, marker::PhantomData, task::Poll};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{future::Future, marker::PhantomData, ops::Range, task::Poll};
 use tokio::sync::oneshot::{self, error::RecvError};
 
 use crate::{
     endpoint::Settlement,
-    util::{Sealed, Uninitialized},
+    util::{IntoPayload, IntoReader, Sealed},
 };
 use crate::{util::AsDeliveryState, Payload};
 
-use super::{LinkStateError, SendError};
+pub use crate::util::Uninitialized;
+
+use super::{LinkId, LinkStateError, MessageDecodeError, SendError};
+
+cfg_compression! {
+    use std::borrow::Cow;
+
+    use crate::compression::{self, DecompressError};
+}
 
 /// Delivery information that is needed for disposing a message
+///
+/// This can be taken from a delivery with [`Delivery::info`] or [`Delivery::into_parts`] and
+/// passed to [`Receiver::accept`](crate::Receiver::accept) and the other methods that dispose
+/// deliveries, so the message can be moved elsewhere before the delivery is disposed. It is
+/// `Send + 'static` and can be sent to another task.
 #[derive(Clone)]
 pub struct DeliveryInfo {
     /// Delivery ID carried by the transfer frame
@@ -30,6 +52,12 @@ pub struct DeliveryInfo {
     /// Receiver settle mode that is carried by the transfer frame
     pub(crate) rcv_settle_mode: Option<ReceiverSettleMode>,
 
+    /// The link that the delivery is received on
+    pub(crate) link_id: LinkId,
+
+    /// Whether the delivery is settled by the sender
+    pub(crate) settled: bool,
+
     pub(crate) _sealed: Sealed,
 }
 
@@ -48,6 +76,11 @@ impl DeliveryInfo {
     pub fn rcv_settle_mode(&self) -> &Option<ReceiverSettleMode> {
         &self.rcv_settle_mode
     }
+
+    /// Whether the delivery is settled by the remote sender
+    pub fn is_settled(&self) -> bool {
+        self.settled
+    }
 }
 
 impl std::fmt::Debug for DeliveryInfo {
@@ -56,44 +89,47 @@ impl std::fmt::Debug for DeliveryInfo {
             .field("delivery_id", &self.delivery_id)
             .field("delivery_tag", &self.delivery_tag)
             .field("rcv_settle_mode", &self.rcv_settle_mode)
+            .field("settled", &self.settled)
             .finish()
     }
 }
 
 impl<T> From<Delivery<T>> for DeliveryInfo {
     fn from(delivery: Delivery<T>) -> Self {
-        Self {
-            delivery_id: delivery.delivery_id,
-            delivery_tag: delivery.delivery_tag,
-            rcv_settle_mode: delivery.rcv_settle_mode,
-            _sealed: Sealed {},
-        }
+        delivery.info
     }
 }
 
 impl<T> From<&Delivery<T>> for DeliveryInfo {
     fn from(delivery: &Delivery<T>) -> Self {
-        Self {
-            delivery_id: delivery.delivery_id,
-            delivery_tag: delivery.delivery_tag.clone(),
-            rcv_settle_mode: delivery.rcv_settle_mode.clone(),
-            _sealed: Sealed {},
-        }
+        delivery.info.clone()
     }
 }
 
+/// Type alias for a delivery whose body can be any of the body sections, which is the safest
+/// choice if the type of the body is not known in advance
+pub type ValueDelivery = Delivery<Body<Value>>;
+
 /// Reserved for receiver side
 #[derive(Debug)]
 pub struct Delivery<T> {
     /// Verify whether this message is bound to a link
     pub(crate) link_output_handle: Handle,
-    pub(crate) delivery_id: DeliveryNumber,
-    pub(crate) delivery_tag: DeliveryTag,
-
+    pub(crate) info: DeliveryInfo,
     pub(crate) message_format: Option<MessageFormat>,
-    pub(crate) rcv_settle_mode: Option<ReceiverSettleMode>,
 
     pub(crate) message: Message<T>,
+
+    /// The encoded message and its sections, which are only kept if the receiver is built with
+    /// `index_sections`
+    pub(crate) sections: Option<SectionIndex>,
+}
+
+/// The encoded message of a delivery and the sections located in it
+#[derive(Debug)]
+pub(crate) struct SectionIndex {
+    pub(crate) payload: Payload,
+    pub(crate) sections: Vec<EncodedSection>,
 }
 
 impl<T> Delivery<T> {
@@ -109,12 +145,12 @@ impl<T> Delivery<T> {
 
     /// Get the delivery ID
     pub fn delivery_id(&self) -> &DeliveryNumber {
-        &self.delivery_id
+        &self.info.delivery_id
     }
 
     /// Get the delivery tag
     pub fn delivery_tag(&self) -> &DeliveryTag {
-        &self.delivery_tag
+        &self.info.delivery_tag
     }
 
     /// Get the message format
@@ -122,6 +158,11 @@ impl<T> Delivery<T> {
         &self.message_format
     }
 
+    /// Get the delivery info, which can dispose the delivery after the message is consumed
+    pub fn info(&self) -> DeliveryInfo {
+        self.info.clone()
+    }
+
     /// Consume the delivery into the message
     pub fn into_message(self) -> Message<T> {
         self.message
@@ -137,18 +178,521 @@ impl<T> Delivery<T> {
         self.message.body
     }
 
+    /// Get the sections of the encoded message in the order they appear
+    ///
+    /// This returns `None` unless the receiver is built with
+    /// [`index_sections`](crate::link::builder::Builder::index_sections).
+    pub fn sections(&self) -> Option<&[EncodedSection]> {
+        self.sections.as_ref().map(|index| &index.sections[..])
+    }
+
+    /// Get the encoded message for changing some of its sections without encoding the other
+    /// sections again
+    ///
+    /// This returns `None` unless the receiver is built with
+    /// [`index_sections`](crate::link::builder::Builder::index_sections).
+    pub fn message_mut(&self) -> Option<MessageMut> {
+        self.sections.as_ref().map(|index| {
+            MessageMut::from_index(self.message_format, index.payload.clone(), &index.sections)
+        })
+    }
+
     /// Consume the delivery into the delivery info and message.
     /// The message format will be lost.
     pub fn into_parts(self) -> (DeliveryInfo, Message<T>) {
-        (
-            DeliveryInfo {
-                delivery_id: self.delivery_id,
-                delivery_tag: self.delivery_tag,
-                rcv_settle_mode: self.rcv_settle_mode,
-                _sealed: Sealed {},
-            },
-            self.message,
-        )
+        (self.info, self.message)
+    }
+}
+
+cfg_compression! {
+    impl Delivery<Data> {
+        /// Returns the body inflated according to the `content-encoding` of the message properties.
+        ///
+        /// The body is borrowed as is if the message has no `content-encoding`. The message itself
+        /// is not modified so that it can be forwarded with the original bytes.
+        pub fn decompressed_data(&self) -> Result<Cow<'_, [u8]>, DecompressError> {
+            let data = Cow::Borrowed(&self.message.body.0[..]);
+            compression::decompress(compression::content_encoding(&self.message), data)
+        }
+    }
+
+    impl<T> Delivery<Body<T>> {
+        /// Returns the `Data` sections of the body inflated according to the `content-encoding` of
+        /// the message properties. Multiple `Data` sections are concatenated before being inflated.
+        ///
+        /// The body is borrowed as is if the message has no `content-encoding` and a single `Data`
+        /// section. The message itself is not modified so that it can be forwarded with the
+        /// original bytes. `DecompressError::NotData` is returned if the body is not made of `Data`
+        /// sections.
+        pub fn decompressed_data(&self) -> Result<Cow<'_, [u8]>, DecompressError> {
+            let data = match &self.message.body {
+                Body::Data(batch) if batch.len() == 1 => Cow::Borrowed(&batch[0].0[..]),
+                Body::Data(batch) => {
+                    Cow::Owned(batch.iter().flat_map(|data| data.0.iter().copied()).collect())
+                }
+                _ => return Err(DecompressError::NotData),
+            };
+            compression::decompress(compression::content_encoding(&self.message), data)
+        }
+    }
+}
+
+/// A delivery that is built from the payload of a complete incoming transfer
+pub(crate) trait FromPayload: Sized {
+    fn from_payload<P>(
+        link_output_handle: Handle,
+        info: DeliveryInfo,
+        message_format: Option<MessageFormat>,
+        payload: P,
+    ) -> Result<Self, MessageDecodeError>
+    where
+        P: IntoReader + IntoPayload;
+
+    fn delivery_info(&self) -> DeliveryInfo;
+
+    /// Keeps the encoded message and its sections. This is only called if the receiver is built
+    /// with `index_sections`
+    fn with_sections(self, _payload: Payload, _sections: Vec<EncodedSection>) -> Self {
+        self
+    }
+}
+
+impl<T> FromPayload for Delivery<T>
+where
+    for<'de> T: FromBody<'de> + Send,
+{
+    fn from_payload<P>(
+        link_output_handle: Handle,
+        info: DeliveryInfo,
+        message_format: Option<MessageFormat>,
+        payload: P,
+    ) -> Result<Self, MessageDecodeError>
+    where
+        P: IntoReader + IntoPayload,
+    {
+        match T::decode_into_message(payload.into_reader()) {
+            Ok(message) => Ok(Delivery {
+                link_output_handle,
+                info,
+                message_format,
+                message,
+                sections: None,
+            }),
+            Err(source) => Err(MessageDecodeError { source, info }),
+        }
+    }
+
+    fn delivery_info(&self) -> DeliveryInfo {
+        DeliveryInfo::from(self)
+    }
+
+    fn with_sections(mut self, payload: Payload, sections: Vec<EncodedSection>) -> Self {
+        self.sections = Some(SectionIndex { payload, sections });
+        self
+    }
+}
+
+/// A delivery whose payload is kept encoded
+///
+/// This is returned by [`Receiver::recv_raw`](crate::Receiver::recv_raw) and can be sent on
+/// another link with [`Sender::forward`](crate::Sender::forward). The message is not decoded and
+/// encoded again, so the forwarded sections keep the exact bytes they were received with.
+///
+/// The body can be decoded as different types with [`body_as`](RawDelivery::body_as) once the
+/// kind of the body is known from [`body_kind`](RawDelivery::body_kind).
+#[derive(Debug)]
+pub struct RawDelivery {
+    pub(crate) info: DeliveryInfo,
+    pub(crate) message_format: Option<MessageFormat>,
+    pub(crate) payload: Payload,
+}
+
+impl RawDelivery {
+    /// Get the delivery ID
+    pub fn delivery_id(&self) -> &DeliveryNumber {
+        &self.info.delivery_id
+    }
+
+    /// Get the delivery tag
+    pub fn delivery_tag(&self) -> &DeliveryTag {
+        &self.info.delivery_tag
+    }
+
+    /// Get the message format
+    pub fn message_format(&self) -> &Option<MessageFormat> {
+        &self.message_format
+    }
+
+    /// Whether the delivery is settled by the remote sender
+    pub fn is_settled(&self) -> bool {
+        self.info.settled
+    }
+
+    /// Get the encoded message
+    pub fn payload(&self) -> &Payload {
+        &self.payload
+    }
+
+    /// Get the delivery info, which can dispose the delivery after the payload is consumed
+    pub fn info(&self) -> DeliveryInfo {
+        self.info.clone()
+    }
+
+    /// Consume the delivery into the delivery info and the encoded message
+    pub fn into_parts(self) -> (DeliveryInfo, Payload) {
+        (self.info, self.payload)
+    }
+
+    /// Decode the message
+    pub fn decode<T>(&self) -> Result<Message<T>, serde_amqp::Error>
+    where
+        for<'de> T: FromBody<'de>,
+    {
+        T::decode_into_message(self.payload.clone().into_reader())
+    }
+
+    /// Kind of the message body, which is found without decoding the body
+    pub fn body_kind(&self) -> Result<BodyKind, serde_amqp::Error> {
+        sections::body_kind(&self.payload)
+    }
+
+    /// Decode the message body as `T`
+    ///
+    /// The payload is kept encoded, so this can be called again with another type if the body
+    /// does not decode as `T`. The delivery can be disposed of whether or not the body is decoded.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let delivery = receiver.recv_raw().await.unwrap();
+    /// match delivery.body_kind().unwrap() {
+    ///     BodyKind::Data => {
+    ///         let data = delivery.body_as::<Data>().unwrap();
+    ///     }
+    ///     _ => {
+    ///         let value = delivery.body_as::<Body<Value>>().unwrap();
+    ///     }
+    /// }
+    /// receiver.accept(&delivery).await.unwrap();
+    /// ```
+    pub fn body_as<T>(&self) -> Result<T, serde_amqp::Error>
+    where
+        for<'de> T: FromBody<'de>,
+    {
+        self.decode::<T>().map(|message| message.body)
+    }
+
+    /// Get the binary of a body that is a single data section without decoding the message
+    ///
+    /// The data section is located by reading only the constructors and the size prefixes of the
+    /// sections, and the binary is a slice of the payload that is not copied. This is much cheaper
+    /// than [`body_as::<Data>`](RawDelivery::body_as) for messages that always have the same
+    /// shape, eg. the events of Azure Event Hubs. The other sections can still be decoded on demand
+    /// with [`section`](RawDelivery::section).
+    ///
+    /// `None` is returned if the body is not a single data section or the section is encoded in
+    /// an unusual way. [`data`](RawDelivery::data) falls back to the generic decoder in that case.
+    pub fn try_data_fast(&self) -> Option<Payload> {
+        sections::data_range(&self.payload).map(|range| self.payload.slice(range))
+    }
+
+    /// Get the binary of a body that is a single data section
+    ///
+    /// This takes the fast path of [`try_data_fast`](RawDelivery::try_data_fast) if possible and
+    /// decodes the body as [`Data`] otherwise, in which case the binary is copied.
+    pub fn data(&self) -> Result<Payload, serde_amqp::Error> {
+        match self.try_data_fast() {
+            Some(data) => Ok(data),
+            None => self
+                .body_as::<Data>()
+                .map(|data| Payload::from(data.0.into_vec())),
+        }
+    }
+
+    /// Decode a section of the message without decoding the other sections
+    ///
+    /// The first section of the kind is decoded if there are several, eg. a body of several data
+    /// sections.
+    pub fn section<S>(&self) -> Result<Option<S>, serde_amqp::Error>
+    where
+        S: MessageSection + DeserializeOwned,
+    {
+        sections::sections(&self.payload)?
+            .into_iter()
+            .find(|section| section.kind == S::KIND)
+            .map(|section| serde_amqp::from_slice(&self.payload[section.range]))
+            .transpose()
+    }
+
+    /// Decode the delivery annotations without decoding the other sections of the message
+    pub fn delivery_annotations(&self) -> Result<Option<DeliveryAnnotations>, serde_amqp::Error> {
+        sections::delivery_annotations_range(&self.payload)?
+            .map(|range| serde_amqp::from_slice(&self.payload[range]))
+            .transpose()
+    }
+
+    /// Removes the delivery annotations section from the encoded message and returns the decoded
+    /// annotations.
+    ///
+    /// Delivery annotations are only meant for the immediate next hop, so an intermediary should
+    /// strip them before forwarding the message. The other sections are left untouched.
+    pub fn strip_delivery_annotations(
+        &mut self,
+    ) -> Result<Option<DeliveryAnnotations>, serde_amqp::Error> {
+        let range = match sections::delivery_annotations_range(&self.payload)? {
+            Some(range) => range,
+            None => return Ok(None),
+        };
+        let delivery_annotations = serde_amqp::from_slice(&self.payload[range.clone()])?;
+
+        self.payload = match range.start {
+            // Only the header section may come before the delivery annotations
+            0 => self.payload.slice(range.end..),
+            _ => {
+                let mut buf = BytesMut::with_capacity(self.payload.len() - range.len());
+                buf.extend_from_slice(&self.payload[..range.start]);
+                buf.extend_from_slice(&self.payload[range.end..]);
+                buf.freeze()
+            }
+        };
+        Ok(Some(delivery_annotations))
+    }
+
+    /// Get the encoded message for changing some of its sections without encoding the other
+    /// sections again
+    pub fn message_mut(&self) -> Result<MessageMut, serde_amqp::Error> {
+        let index = sections::sections(&self.payload)?;
+        Ok(MessageMut::from_index(
+            self.message_format,
+            self.payload.clone(),
+            &index,
+        ))
+    }
+}
+
+/// An encoded message whose sections can be replaced, inserted or removed one at a time
+///
+/// This is returned by [`Delivery::message_mut`] and [`RawDelivery::message_mut`], and can be
+/// sent with [`Sender::forward_edited`](crate::Sender::forward_edited). The untouched sections
+/// are not decoded and encoded again, so they keep the exact bytes they were received with. They
+/// refer to the received payload until [`into_payload`](#method.into_payload), which copies them
+/// into the new payload unless they are still contiguous in the received payload.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let delivery: Delivery<Body<Value>> = receiver.recv().await?;
+/// let mut message = delivery.message_mut().unwrap();
+/// let mut app_props = message.decode::<ApplicationProperties>()?.unwrap_or_default();
+/// app_props.0.insert(String::from("hops"), SimpleValue::from(1u32));
+/// message.set(&app_props)?;
+/// sender.forward_edited(message).await?;
+/// receiver.accept(&delivery).await?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct MessageMut {
+    message_format: Option<MessageFormat>,
+    source: Payload,
+    sections: Vec<(SectionKind, Segment)>,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    /// Range of an untouched section in the source payload
+    Source(Range<usize>),
+
+    /// A section that is encoded separately
+    Encoded(Payload),
+}
+
+impl MessageMut {
+    /// Locates the sections of an encoded message
+    pub fn from_payload(payload: Payload) -> Result<Self, serde_amqp::Error> {
+        let index = sections::sections(&payload)?;
+        Ok(Self::from_index(None, payload, &index))
+    }
+
+    pub(crate) fn from_index(
+        message_format: Option<MessageFormat>,
+        source: Payload,
+        index: &[EncodedSection],
+    ) -> Self {
+        let sections = index
+            .iter()
+            .map(|section| (section.kind, Segment::Source(section.range.clone())))
+            .collect();
+        Self {
+            message_format,
+            source,
+            sections,
+        }
+    }
+
+    /// Get the message format
+    pub fn message_format(&self) -> &Option<MessageFormat> {
+        &self.message_format
+    }
+
+    /// Get the kinds of the sections in the order they appear
+    pub fn kinds(&self) -> impl Iterator<Item = SectionKind> + '_ {
+        self.sections.iter().map(|(kind, _)| *kind)
+    }
+
+    /// Get the encoded bytes of the first section of `kind`
+    pub fn get(&self, kind: SectionKind) -> Option<Payload> {
+        self.sections
+            .iter()
+            .find(|(k, _)| *k == kind)
+            .map(|(_, segment)| match segment {
+                Segment::Source(range) => self.source.slice(range.clone()),
+                Segment::Encoded(encoded) => encoded.clone(),
+            })
+    }
+
+    /// Decodes the first section of type `S` without decoding the other sections
+    pub fn decode<S>(&self) -> Result<Option<S>, serde_amqp::Error>
+    where
+        S: MessageSection + DeserializeOwned,
+    {
+        self.get(S::KIND)
+            .map(|encoded| serde_amqp::from_slice(&encoded))
+            .transpose()
+    }
+
+    /// Replaces the section of the same kind with `section`, or inserts it in the position
+    /// defined by the specification if there is none. A body section replaces the whole body.
+    pub fn set<S>(&mut self, section: &S) -> Result<(), serde_amqp::Error>
+    where
+        S: MessageSection + Serialize,
+    {
+        let encoded = serde_amqp::to_vec(section)?;
+        self.splice(S::KIND, Payload::from(encoded));
+        Ok(())
+    }
+
+    /// Same as [`set`](#method.set) but with a section that is already encoded. An error is
+    /// returned if `encoded` is not a single section of `kind`.
+    pub fn set_encoded(
+        &mut self,
+        kind: SectionKind,
+        encoded: Payload,
+    ) -> Result<(), serde_amqp::Error> {
+        match sections::sections(&encoded)?.as_slice() {
+            [section] if section.kind == kind => {}
+            _ => {
+                return Err(serde::de::Error::custom(
+                    "Expecting a single section of the given kind",
+                ))
+            }
+        }
+        self.splice(kind, encoded);
+        Ok(())
+    }
+
+    /// Removes the sections of `kind`. Returns whether any section is removed.
+    pub fn remove(&mut self, kind: SectionKind) -> bool {
+        let len = self.sections.len();
+        self.sections.retain(|(k, _)| *k != kind);
+        self.sections.len() != len
+    }
+
+    fn splice(&mut self, kind: SectionKind, encoded: Payload) {
+        let is_replaced = |k: &SectionKind| match kind.is_body() {
+            true => k.is_body(),
+            false => *k == kind,
+        };
+        let position = self
+            .sections
+            .iter()
+            .position(|(k, _)| is_replaced(k) || section_order(k) > section_order(&kind))
+            .unwrap_or(self.sections.len());
+        self.sections.retain(|(k, _)| !is_replaced(k));
+        self.sections
+            .insert(position, (kind, Segment::Encoded(encoded)));
+    }
+
+    /// Joins the sections into the encoded message
+    pub fn into_payload(self) -> Payload {
+        if let Some(range) = self.source_range() {
+            return self.source.slice(range);
+        }
+
+        let segments: Vec<&[u8]> = self
+            .sections
+            .iter()
+            .map(|(_, segment)| match segment {
+                Segment::Source(range) => &self.source[range.clone()],
+                Segment::Encoded(encoded) => &encoded[..],
+            })
+            .collect();
+        let len = segments.iter().map(|segment| segment.len()).sum();
+        let mut buf = BytesMut::with_capacity(len);
+        for segment in segments {
+            buf.extend_from_slice(segment);
+        }
+        buf.freeze()
+    }
+
+    /// The range of the source payload if all the sections are untouched and contiguous
+    fn source_range(&self) -> Option<Range<usize>> {
+        let mut joined: Option<Range<usize>> = None;
+        for (_, segment) in &self.sections {
+            match (segment, &mut joined) {
+                (Segment::Source(range), None) => joined = Some(range.clone()),
+                (Segment::Source(range), Some(joined)) if joined.end == range.start => {
+                    joined.end = range.end
+                }
+                _ => return None,
+            }
+        }
+        Some(joined.unwrap_or(0..0))
+    }
+}
+
+/// The order of the sections in a message, where the body sections share the same position
+fn section_order(kind: &SectionKind) -> u8 {
+    match kind {
+        SectionKind::Header => 0,
+        SectionKind::DeliveryAnnotations => 1,
+        SectionKind::MessageAnnotations => 2,
+        SectionKind::Properties => 3,
+        SectionKind::ApplicationProperties => 4,
+        SectionKind::Data | SectionKind::AmqpSequence | SectionKind::AmqpValue => 5,
+        SectionKind::Footer => 6,
+    }
+}
+
+impl From<RawDelivery> for DeliveryInfo {
+    fn from(delivery: RawDelivery) -> Self {
+        delivery.info
+    }
+}
+
+impl From<&RawDelivery> for DeliveryInfo {
+    fn from(delivery: &RawDelivery) -> Self {
+        delivery.info.clone()
+    }
+}
+
+impl FromPayload for RawDelivery {
+    fn from_payload<P>(
+        _link_output_handle: Handle,
+        info: DeliveryInfo,
+        message_format: Option<MessageFormat>,
+        payload: P,
+    ) -> Result<Self, MessageDecodeError>
+    where
+        P: IntoReader + IntoPayload,
+    {
+        Ok(Self {
+            info,
+            message_format,
+            payload: payload.into_payload(),
+        })
+    }
+
+    fn delivery_info(&self) -> DeliveryInfo {
+        self.info.clone()
     }
 }
 
@@ -298,7 +842,9 @@ pub(crate) struct UnsettledMessage {
     pub(crate) payload: Payload,
     pub(crate) state: Option<DeliveryState>,
     pub(crate) message_format: u32,
-    pub(crate) sender: oneshot::Sender<Option<DeliveryState>>,
+
+    /// The outcome is only reported through the confirm watermark of the sender if this is `None`
+    pub(crate) sender: Option<oneshot::Sender<Option<DeliveryState>>>,
 }
 
 impl UnsettledMessage {
@@ -306,7 +852,7 @@ impl UnsettledMessage {
         payload: Payload,
         state: Option<DeliveryState>,
         message_format: u32,
-        sender: oneshot::Sender<Option<DeliveryState>>,
+        sender: Option<oneshot::Sender<Option<DeliveryState>>>,
     ) -> Self {
         Self {
             payload,
@@ -316,15 +862,20 @@ impl UnsettledMessage {
         }
     }
 
-    pub fn settle(self) -> Result<(), Option<DeliveryState>> {
-        self.sender.send(self.state)
+    /// The state is boxed in the error if the outcome can no longer be reported
+    pub fn settle(mut self) -> Result<(), Box<Option<DeliveryState>>> {
+        let state = self.state.take();
+        self.settle_with_state(state)
     }
 
     pub fn settle_with_state(
         self,
         state: Option<DeliveryState>,
-    ) -> Result<(), Option<DeliveryState>> {
-        self.sender.send(state)
+    ) -> Result<(), Box<Option<DeliveryState>>> {
+        match self.sender {
+            Some(sender) => sender.send(state).map_err(Box::new),
+            None => Ok(()),
+        }
     }
 }
 
@@ -334,6 +885,103 @@ impl AsDeliveryState for UnsettledMessage {
     }
 }
 
+/// The result of a delivery sent by a [`Sender`](crate::Sender)
+///
+/// Unlike [`Outcome`], this distinguishes a delivery that was settled by the sender, for which
+/// the receiver will never report an outcome, from one that was accepted by the receiver.
+#[derive(Debug, Clone)]
+pub enum SendReceipt {
+    /// The delivery was settled by the sender (either because the negotiated `snd-settle-mode`
+    /// is `Settled` or because the message was sent pre-settled), and no outcome will be
+    /// returned by the receiver
+    Settled,
+
+    /// The receiver accepted the delivery
+    Accepted(Accepted),
+
+    /// The receiver rejected the delivery
+    Rejected(Rejected),
+
+    /// The receiver released the delivery
+    Released(Released),
+
+    /// The receiver modified the delivery
+    Modified(Modified),
+}
+
+/// Displays the outcome together with the error of a [`Rejected`] or the fields of a [`Modified`]
+impl std::fmt::Display for SendReceipt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Settled => f.write_str("Settled"),
+            Self::Accepted(_) => f.write_str("Accepted"),
+            Self::Rejected(rejected) => rejected.fmt(f),
+            Self::Released(_) => f.write_str("Released"),
+            Self::Modified(modified) => modified.fmt(f),
+        }
+    }
+}
+
+impl SendReceipt {
+    /// Returns true if the delivery was settled by the sender
+    pub fn is_settled(&self) -> bool {
+        matches!(self, Self::Settled)
+    }
+
+    /// Returns true if the receipt is [`Accepted`]
+    pub fn is_accepted(&self) -> bool {
+        matches!(self, Self::Accepted(_))
+    }
+
+    /// Returns true if the receipt is [`Rejected`]
+    pub fn is_rejected(&self) -> bool {
+        matches!(self, Self::Rejected(_))
+    }
+
+    /// Returns true if the receipt is [`Released`]
+    pub fn is_released(&self) -> bool {
+        matches!(self, Self::Released(_))
+    }
+
+    /// Returns true if the receipt is [`Modified`]
+    pub fn is_modified(&self) -> bool {
+        matches!(self, Self::Modified(_))
+    }
+
+    /// Returns the outcome reported by the receiver, or `None` if the delivery was settled by
+    /// the sender
+    pub fn into_outcome(self) -> Option<Outcome> {
+        match self {
+            Self::Settled => None,
+            Self::Accepted(value) => Some(Outcome::Accepted(value)),
+            Self::Rejected(value) => Some(Outcome::Rejected(value)),
+            Self::Released(value) => Some(Outcome::Released(value)),
+            Self::Modified(value) => Some(Outcome::Modified(value)),
+        }
+    }
+
+    /// Transforms the [`SendReceipt`] into a `Result<Accepted, E>`,
+    /// mapping Accepted(accepted) to Ok(accepted) and other variants to Err(err).
+    pub fn accepted_or<E>(self, err: E) -> Result<Accepted, E> {
+        match self {
+            Self::Accepted(value) => Ok(value),
+            _ => Err(err),
+        }
+    }
+
+    /// Transforms the [`SendReceipt`] into a `Result<Accepted, E>`,
+    /// mapping Accepted(accepted) to Ok(accepted) and other variants to Err(op(self)).
+    pub fn accepted_or_else<E, F>(self, op: F) -> Result<Accepted, E>
+    where
+        F: FnOnce(Self) -> E,
+    {
+        match self {
+            Self::Accepted(value) => Ok(value),
+            _ => Err(op(self)),
+        }
+    }
+}
+
 pin_project! {
     /// A future for delivery that can be `.await`ed for the settlement
     /// from receiver
@@ -341,6 +989,8 @@ pin_project! {
         #[pin]
         // Reserved for future use on actively sending disposition from Sender
         settlement: Settlement,
+        rejected_as_error: bool,
+        declared_outcomes: Option<Array<Symbol>>,
         outcome_marker: PhantomData<O>
     }
 }
@@ -349,19 +999,59 @@ impl<O> DeliveryFut<O> {
     /// Get the delivery tag
     pub fn delivery_tag(&self) -> &DeliveryTag {
         match &self.settlement {
-            Settlement::Settled(delivery_tag) => delivery_tag,
+            Settlement::Settled(delivery_tag) | Settlement::Unconfirmed(delivery_tag) => {
+                delivery_tag
+            }
             Settlement::Unsettled {
                 delivery_tag,
                 outcome: _,
             } => delivery_tag,
         }
     }
+
+    pub(crate) fn into_settlement(self) -> Settlement {
+        self.settlement
+    }
+
+    /// Whether a `Rejected` outcome should be interpreted as an error
+    pub(crate) fn rejected_as_error(mut self, value: bool) -> Self {
+        self.rejected_as_error = value;
+        self
+    }
+
+    /// The outcomes declared in the `outcomes` field of the local source. An outcome that is not
+    /// declared is interpreted by [`FromDeliveryState::from_undeclared_outcome`]
+    pub(crate) fn declared_outcomes(mut self, outcomes: Option<Array<Symbol>>) -> Self {
+        self.declared_outcomes = outcomes;
+        self
+    }
+}
+
+/// Symbolic descriptor of the outcome, or `None` if the delivery state is not an outcome
+fn outcome_descriptor(state: &DeliveryState) -> Option<&'static str> {
+    match state {
+        DeliveryState::Accepted(_) => Some("amqp:accepted:list"),
+        DeliveryState::Rejected(_) => Some("amqp:rejected:list"),
+        DeliveryState::Released(_) => Some("amqp:released:list"),
+        DeliveryState::Modified(_) => Some("amqp:modified:list"),
+        _ => None,
+    }
+}
+
+/// Whether the delivery state is an outcome that is not among the declared outcomes
+fn is_undeclared_outcome(declared: &Option<Array<Symbol>>, state: &DeliveryState) -> bool {
+    match (declared, outcome_descriptor(state)) {
+        (Some(declared), Some(descriptor)) => !declared.0.iter().any(|s| s.as_str() == descriptor),
+        _ => false,
+    }
 }
 
 impl<O> From<Settlement> for DeliveryFut<O> {
     fn from(settlement: Settlement) -> Self {
         Self {
             settlement,
+            rejected_as_error: false,
+            declared_outcomes: None,
             outcome_marker: PhantomData,
         }
     }
@@ -384,6 +1074,22 @@ pub trait FromDeliveryState {
 
     /// how to interprete a DeliveryState
     fn from_delivery_state(state: DeliveryState) -> Self;
+
+    /// how to interprete a `Rejected` outcome if it should be treated as an error
+    fn from_rejected_as_error(rejected: Rejected) -> Self
+    where
+        Self: Sized,
+    {
+        Self::from_delivery_state(DeliveryState::Rejected(rejected))
+    }
+
+    /// how to interprete an outcome that is not declared in the `outcomes` field of the source
+    fn from_undeclared_outcome(state: DeliveryState) -> Self
+    where
+        Self: Sized,
+    {
+        Self::from_delivery_state(state)
+    }
 }
 
 /// This trait defines how to interprete `tokio::sync::oneshot::error::RecvError`
@@ -400,11 +1106,11 @@ impl FromOneshotRecvError for SendResult {
     }
 }
 
-pub(crate) type SendResult = Result<Outcome, SendError>;
+pub(crate) type SendResult = Result<SendReceipt, SendError>;
 
 impl FromPreSettled for SendResult {
     fn from_settled() -> Self {
-        Ok(Outcome::Accepted(Accepted {}))
+        Ok(SendReceipt::Settled)
     }
 }
 
@@ -419,10 +1125,10 @@ impl FromDeliveryState for SendResult {
             // DeliveryState::Rejected(rejected) => Err(SendError::Rejected(rejected)),
             // DeliveryState::Released(released) => Err(SendError::Released(released)),
             // DeliveryState::Modified(modified) => Err(SendError::Modified(modified)),
-            DeliveryState::Accepted(accepted) => Ok(Outcome::Accepted(accepted)),
-            DeliveryState::Rejected(rejected) => Ok(Outcome::Rejected(rejected)),
-            DeliveryState::Released(released) => Ok(Outcome::Released(released)),
-            DeliveryState::Modified(modified) => Ok(Outcome::Modified(modified)),
+            DeliveryState::Accepted(accepted) => Ok(SendReceipt::Accepted(accepted)),
+            DeliveryState::Rejected(rejected) => Ok(SendReceipt::Rejected(rejected)),
+            DeliveryState::Released(released) => Ok(SendReceipt::Released(released)),
+            DeliveryState::Modified(modified) => Ok(SendReceipt::Modified(modified)),
             DeliveryState::Received(_) => Err(SendError::NonTerminalDeliveryState),
             #[cfg(feature = "transaction")]
             DeliveryState::Declared(_) | DeliveryState::TransactionalState(_) => {
@@ -430,6 +1136,14 @@ impl FromDeliveryState for SendResult {
             }
         }
     }
+
+    fn from_rejected_as_error(rejected: Rejected) -> Self {
+        Err(SendError::Rejected(rejected))
+    }
+
+    fn from_undeclared_outcome(state: DeliveryState) -> Self {
+        Err(SendError::UndeclaredOutcome(state))
+    }
 }
 
 impl<O> Future for DeliveryFut<O>
@@ -447,6 +1161,8 @@ where
 
         match &mut *settlement {
             Settlement::Settled(_) => Poll::Ready(O::from_settled()),
+            // The outcome is only reported through the confirm watermark
+            Settlement::Unconfirmed(_) => Poll::Ready(O::from_none()),
             Settlement::Unsettled {
                 delivery_tag: _,
                 outcome,
@@ -455,6 +1171,16 @@ where
                     Poll::Pending => Poll::Pending,
                     Poll::Ready(result) => {
                         match result {
+                            Ok(Some(state))
+                                if is_undeclared_outcome(this.declared_outcomes, &state) =>
+                            {
+                                Poll::Ready(O::from_undeclared_outcome(state))
+                            }
+                            Ok(Some(DeliveryState::Rejected(rejected)))
+                                if *this.rejected_as_error =>
+                            {
+                                Poll::Ready(O::from_rejected_as_error(rejected))
+                            }
                             Ok(Some(state)) => Poll::Ready(O::from_delivery_state(state)),
                             Ok(None) => Poll::Ready(O::from_none()),
                             Err(err) => {
@@ -472,12 +1198,20 @@ where
 
 #[cfg(test)]
 mod tests {
+    use bytes::Bytes;
     use fe2o3_amqp_types::{
-        messaging::{AmqpValue, Body, Data, Message},
-        primitives::Binary,
+        definitions::DeliveryTag,
+        messaging::{
+            message::sections::{sections, SectionKind},
+            AmqpValue, ApplicationProperties, Body, BodyKind, Data, DeliveryAnnotations, Header,
+            Message, MessageAnnotations, MessageId, Properties,
+        },
+        primitives::{Binary, Symbol, Timestamp, Value},
     };
 
-    use crate::Sendable;
+    use crate::{link::sender::encode_message, util::Sealed, Sendable};
+
+    use super::{DeliveryInfo, LinkId, MessageMut, RawDelivery};
 
     struct Foo {}
 
@@ -518,4 +1252,406 @@ mod tests {
         let sendable = Sendable::from(value);
         assert_eq!(sendable.message.body, Data(Binary::from("Foo")));
     }
+
+    fn raw_delivery(payload: Bytes) -> RawDelivery {
+        RawDelivery {
+            info: DeliveryInfo {
+                delivery_id: 0,
+                delivery_tag: DeliveryTag::from(vec![0]),
+                rcv_settle_mode: None,
+                link_id: LinkId::new(),
+                settled: false,
+                _sealed: Sealed {},
+            },
+            message_format: None,
+            payload,
+        }
+    }
+
+    #[test]
+    fn test_strip_delivery_annotations_keeps_other_sections() {
+        let header = serde_amqp::to_vec(&Header::builder().durable(true).build()).unwrap();
+        let delivery_annotations = DeliveryAnnotations::builder().insert("key", 1i32).build();
+        let encoded_delivery_annotations = serde_amqp::to_vec(&delivery_annotations).unwrap();
+        // Properties encoded as a list32, which would be encoded as a list8 if the message was
+        // decoded and encoded again
+        let properties = [
+            0x00, 0x53, 0x73, 0xd0, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x01, 0xa1, 0x02,
+            b'i', b'd',
+        ];
+        let body = serde_amqp::to_vec(&AmqpValue("hello")).unwrap();
+        let payload = [
+            &header[..],
+            &encoded_delivery_annotations[..],
+            &properties[..],
+            &body[..],
+        ]
+        .concat();
+
+        let mut delivery = raw_delivery(Bytes::from(payload));
+        assert_eq!(
+            delivery.delivery_annotations().unwrap(),
+            Some(delivery_annotations.clone())
+        );
+        assert_eq!(
+            delivery.strip_delivery_annotations().unwrap(),
+            Some(delivery_annotations)
+        );
+        let expected = [&header[..], &properties[..], &body[..]].concat();
+        assert_eq!(&delivery.payload()[..], &expected[..]);
+        assert_eq!(delivery.strip_delivery_annotations().unwrap(), None);
+
+        let message = delivery.decode::<Value>().unwrap();
+        assert!(message.delivery_annotations.is_none());
+        assert_eq!(
+            message.properties.unwrap().message_id,
+            Some(MessageId::from(String::from("id")))
+        );
+        assert_eq!(message.body, Value::from("hello"));
+    }
+
+    #[test]
+    fn test_body_as_decodes_retained_payload_again() {
+        let message = Message::builder()
+            .data(Binary::from(r#"{"price":1}"#))
+            .build();
+        let delivery = raw_delivery(encode_message(&message).unwrap());
+        assert_eq!(delivery.body_kind().unwrap(), BodyKind::Data);
+
+        // Guessing the wrong type does not consume the delivery
+        assert!(delivery.body_as::<String>().is_err());
+        assert_eq!(
+            delivery.body_as::<Data>().unwrap(),
+            Data(Binary::from(r#"{"price":1}"#))
+        );
+        let body = delivery.body_as::<Body<Value>>().unwrap();
+        assert_eq!(body.kind(), BodyKind::Data);
+
+        let message = Message::builder().value("hello").build();
+        let delivery = raw_delivery(encode_message(&message).unwrap());
+        assert_eq!(delivery.body_kind().unwrap(), BodyKind::Value);
+        assert_eq!(delivery.body_as::<String>().unwrap(), "hello");
+        assert_eq!(delivery.body_as::<Value>().unwrap(), Value::from("hello"));
+    }
+
+    #[test]
+    fn test_strip_leading_delivery_annotations_does_not_copy() {
+        let delivery_annotations = DeliveryAnnotations::builder().insert("key", 1i32).build();
+        let message = Message::builder()
+            .delivery_annotations(delivery_annotations.clone())
+            .value("hello")
+            .build();
+        let payload = crate::link::sender::encode_message(&message).unwrap();
+        let da_len = serde_amqp::to_vec(&delivery_annotations).unwrap().len();
+
+        let mut delivery = raw_delivery(payload.clone());
+        delivery.strip_delivery_annotations().unwrap();
+        assert_eq!(delivery.payload(), &payload.slice(da_len..));
+        assert_eq!(delivery.payload().as_ptr(), payload[da_len..].as_ptr());
+    }
+
+    fn forwarded_message(application_properties: Option<ApplicationProperties>) -> Message<Data> {
+        let builder = Message::builder()
+            .header(Header::builder().durable(true).build())
+            .properties(Properties::builder().message_id(7u64).build());
+        let builder = match application_properties {
+            Some(application_properties) => builder.application_properties(application_properties),
+            None => builder,
+        };
+        builder.data(Binary::from(vec![0xab; 1024 * 1024])).build()
+    }
+
+    fn section_bytes(bytes: &[u8], kind: SectionKind) -> &[u8] {
+        let sections = sections(bytes).unwrap();
+        let section = sections
+            .iter()
+            .find(|section| section.kind == kind)
+            .unwrap();
+        &bytes[section.range.clone()]
+    }
+
+    /// Asserts that the sections of `kinds` in `spliced` are byte-identical to the ones in
+    /// `original`
+    fn assert_untouched(original: &[u8], spliced: &[u8], kinds: &[SectionKind]) {
+        for kind in kinds {
+            assert_eq!(
+                section_bytes(original, *kind),
+                section_bytes(spliced, *kind)
+            );
+        }
+    }
+
+    #[test]
+    fn test_message_mut_replaces_application_properties() {
+        let application_properties = ApplicationProperties::builder()
+            .insert("hops", 0u32)
+            .build();
+        let original = encode_message(&forwarded_message(Some(application_properties))).unwrap();
+
+        let mut message = MessageMut::from_payload(original.clone()).unwrap();
+        let mut application_properties =
+            message.decode::<ApplicationProperties>().unwrap().unwrap();
+        application_properties
+            .0
+            .insert(String::from("hops"), 1u32.into());
+        message.set(&application_properties).unwrap();
+        let spliced = message.into_payload();
+
+        // The spliced message is the same as a message that is built and encoded again
+        let rebuilt = encode_message(&forwarded_message(Some(application_properties))).unwrap();
+        assert_eq!(spliced, rebuilt);
+        let decoded = RawDelivery::decode::<Data>(&raw_delivery(spliced.clone())).unwrap();
+        let expected = RawDelivery::decode::<Data>(&raw_delivery(rebuilt)).unwrap();
+        assert_eq!(decoded, expected);
+
+        assert_untouched(
+            &original,
+            &spliced,
+            &[
+                SectionKind::Header,
+                SectionKind::Properties,
+                SectionKind::Data,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_message_mut_inserts_section_in_order() {
+        let original = encode_message(&forwarded_message(None)).unwrap();
+        let mut message = MessageMut::from_payload(original.clone()).unwrap();
+        assert!(message.decode::<ApplicationProperties>().unwrap().is_none());
+
+        let application_properties = ApplicationProperties::builder()
+            .insert("hops", 1u32)
+            .build();
+        message.set(&application_properties).unwrap();
+        assert_eq!(
+            message.kinds().collect::<Vec<_>>(),
+            vec![
+                SectionKind::Header,
+                SectionKind::Properties,
+                SectionKind::ApplicationProperties,
+                SectionKind::Data
+            ]
+        );
+        let spliced = message.into_payload();
+
+        let rebuilt = encode_message(&forwarded_message(Some(application_properties))).unwrap();
+        assert_eq!(spliced, rebuilt);
+        assert_untouched(
+            &original,
+            &spliced,
+            &[
+                SectionKind::Header,
+                SectionKind::Properties,
+                SectionKind::Data,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_message_mut_reuses_contiguous_sections() {
+        let original = encode_message(&forwarded_message(None)).unwrap();
+
+        // Nothing is changed
+        let message = MessageMut::from_payload(original.clone()).unwrap();
+        let payload = message.into_payload();
+        assert_eq!(payload.as_ptr(), original.as_ptr());
+        assert_eq!(payload.len(), original.len());
+
+        // The remaining sections are still contiguous after the header is removed
+        let mut message = MessageMut::from_payload(original.clone()).unwrap();
+        assert!(message.remove(SectionKind::Header));
+        assert!(!message.remove(SectionKind::Header));
+        let header_len = message.get(SectionKind::Properties).unwrap().as_ptr() as usize
+            - original.as_ptr() as usize;
+        let payload = message.into_payload();
+        assert_eq!(payload, original.slice(header_len..));
+        assert_eq!(payload.as_ptr(), original[header_len..].as_ptr());
+    }
+
+    #[test]
+    fn test_message_mut_replaces_whole_body() {
+        let message = Message::builder()
+            .properties(Properties::builder().message_id(7u64).build())
+            .data_batch(vec![Data(Binary::from("a")), Data(Binary::from("b"))])
+            .build();
+        let mut message_mut = MessageMut::from_payload(encode_message(&message).unwrap()).unwrap();
+
+        let body = serde_amqp::to_vec(&AmqpValue("hello")).unwrap();
+        assert!(message_mut
+            .set_encoded(SectionKind::Data, Bytes::from(body.clone()))
+            .is_err());
+        message_mut
+            .set_encoded(SectionKind::AmqpValue, Bytes::from(body))
+            .unwrap();
+
+        let decoded =
+            RawDelivery::decode::<Value>(&raw_delivery(message_mut.into_payload())).unwrap();
+        assert_eq!(decoded.properties, message.properties);
+        assert_eq!(decoded.body, Value::from("hello"));
+    }
+
+    /// Message annotations that Event Hubs adds to every event
+    fn event_hubs_annotations() -> Vec<u8> {
+        let annotations = MessageAnnotations::builder()
+            .insert(Symbol::from("x-opt-sequence-number"), 1_042i64)
+            .insert(Symbol::from("x-opt-offset"), "4294967296")
+            .insert(
+                Symbol::from("x-opt-enqueued-time"),
+                Timestamp::from_milliseconds(1_700_000_000_000),
+            )
+            .insert(Symbol::from("x-opt-partition-key"), "device-7")
+            .build();
+        serde_amqp::to_vec(&annotations).unwrap()
+    }
+
+    /// Payloads in the shapes that Event Hubs delivers events in, with some unusual encodings that
+    /// the fast path must leave to the generic decoder
+    fn event_hubs_corpus() -> Vec<(&'static str, Vec<u8>, bool)> {
+        let annotations = event_hubs_annotations();
+        // map32 with only the sequence number
+        let mut annotations32 = vec![0x00, 0x53, 0x72, 0xd1, 0x00, 0x00, 0x00, 0x24];
+        annotations32.extend_from_slice(&[0x00, 0x00, 0x00, 0x02, 0xa3, 0x15]);
+        annotations32.extend_from_slice(b"x-opt-sequence-number");
+        annotations32.extend_from_slice(&[0x81, 0, 0, 0, 0, 0, 0, 0x04, 0x12]);
+        let header = serde_amqp::to_vec(&Header::builder().durable(true).build()).unwrap();
+        let delivery_annotations =
+            serde_amqp::to_vec(&DeliveryAnnotations::builder().insert("hop", 1i32).build())
+                .unwrap();
+        let properties = serde_amqp::to_vec(
+            &Properties::builder()
+                .message_id(7u64)
+                .content_type(Symbol::from("application/json"))
+                .build(),
+        )
+        .unwrap();
+        let application_properties = serde_amqp::to_vec(
+            &ApplicationProperties::builder()
+                .insert("source", "sensor")
+                .build(),
+        )
+        .unwrap();
+        let footer = [0x00, 0x53, 0x78, 0xc1, 0x01, 0x00];
+
+        let data8 = |bytes: &[u8]| {
+            let mut data = vec![0x00, 0x53, 0x75, 0xa0, bytes.len() as u8];
+            data.extend_from_slice(bytes);
+            data
+        };
+        let data32 = |bytes: &[u8]| {
+            // Described by a ulong instead of a smallulong
+            let mut data = vec![0x00, 0x80, 0, 0, 0, 0, 0, 0, 0, 0x75, 0xb0];
+            data.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+            data.extend_from_slice(bytes);
+            data
+        };
+        let json = br#"{"temperature":21.5}"#;
+        let large = vec![0xab; 70_000];
+
+        let mut data_symbol = vec![0x00, 0xa3, 16];
+        data_symbol.extend_from_slice(b"amqp:data:binary");
+        data_symbol.extend_from_slice(&[0xa0, 0x02, 0x01, 0x02]);
+        let value = serde_amqp::to_vec(&AmqpValue("event")).unwrap();
+        let truncated = [&annotations[..], &data8(json)[..10]].concat();
+
+        vec![
+            ("event", [&annotations[..], &data8(json)].concat(), true),
+            (
+                "event with all sections",
+                [
+                    &header[..],
+                    &delivery_annotations,
+                    &annotations,
+                    &properties,
+                    &application_properties,
+                    &data8(json),
+                    &footer,
+                ]
+                .concat(),
+                true,
+            ),
+            (
+                "map32 annotations",
+                [&annotations32[..], &data8(json)].concat(),
+                true,
+            ),
+            (
+                "empty event",
+                [&annotations[..], &data8(&[])].concat(),
+                true,
+            ),
+            (
+                "large event",
+                [&annotations[..], &data32(&large)].concat(),
+                true,
+            ),
+            ("bare data", data8(b"raw"), true),
+            (
+                "symbol descriptor",
+                [&annotations[..], &data_symbol].concat(),
+                false,
+            ),
+            (
+                "batch of data",
+                [&annotations[..], &data8(b"a"), &data8(b"b")].concat(),
+                false,
+            ),
+            ("value body", [&annotations[..], &value].concat(), false),
+            ("no body", annotations.clone(), false),
+            ("truncated", truncated, false),
+        ]
+    }
+
+    #[test]
+    fn test_data_fast_path_agrees_with_generic_decoder() {
+        for (name, payload, fast) in event_hubs_corpus() {
+            let payload = Bytes::from(payload);
+            let delivery = raw_delivery(payload.clone());
+            let generic = delivery
+                .body_as::<Data>()
+                .map(|data| Bytes::from(data.0.into_vec()));
+
+            match delivery.try_data_fast() {
+                Some(data) => {
+                    assert!(fast, "{}", name);
+                    assert_eq!(Some(&data), generic.as_ref().ok(), "{}", name);
+                    // A slice of the payload that is not copied
+                    let start = data.as_ptr() as usize - payload.as_ptr() as usize;
+                    assert_eq!(&payload[start..start + data.len()], &data[..], "{}", name);
+                }
+                None => assert!(!fast, "{}", name),
+            }
+            match (delivery.data(), generic) {
+                (Ok(data), Ok(generic)) => assert_eq!(data, generic, "{}", name),
+                (Err(_), Err(_)) => {}
+                (data, generic) => panic!("{}: {:?} != {:?}", name, data, generic),
+            }
+        }
+    }
+
+    #[test]
+    fn test_sections_of_raw_delivery_decode_lazily() {
+        for (name, payload, _) in event_hubs_corpus() {
+            let delivery = raw_delivery(Bytes::from(payload));
+            let message = match delivery.decode::<Body<Value>>() {
+                Ok(message) => message,
+                Err(_) => continue,
+            };
+            assert_eq!(delivery.section().unwrap(), message.header, "{}", name);
+            assert_eq!(
+                delivery.section().unwrap(),
+                message.message_annotations,
+                "{}",
+                name
+            );
+            assert_eq!(delivery.section().unwrap(), message.properties, "{}", name);
+            assert_eq!(
+                delivery.section().unwrap(),
+                message.application_properties,
+                "{}",
+                name
+            );
+        }
+    }
 }
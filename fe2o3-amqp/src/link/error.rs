@@ -1,4 +1,8 @@
-use fe2o3_amqp_types::definitions::{self, AmqpError, ErrorCondition, SessionError};
+use fe2o3_amqp_types::{
+    definitions::{self, AmqpError, DeliveryNumber, ErrorCondition, SessionError},
+    messaging::{DeliveryState, Rejected},
+    performatives::Detach,
+};
 use serde_amqp::primitives::Symbol;
 
 use crate::session::error::AllocLinkError;
@@ -6,7 +10,10 @@ use crate::session::error::AllocLinkError;
 #[cfg(docsrs)]
 use fe2o3_amqp_types::transaction::Coordinator;
 
-use super::{delivery::DeliveryInfo, receiver::DetachedReceiver, sender::DetachedSender};
+use super::{
+    delivery::DeliveryInfo, receiver::DetachedReceiver, sender::DetachedSender,
+    validation::ValidationError,
+};
 
 /// Error associated with detaching
 #[derive(Debug, thiserror::Error)]
@@ -37,6 +44,11 @@ pub enum DetachError {
     /// Remote peer closed the link with an error
     #[error("Remote peer closed the link with an error: {}", .0)]
     RemoteClosedWithError(definitions::Error),
+
+    /// The session ended before the remote Detach arrived, which implicitly detaches the link.
+    /// This carries the error of the End performative if there is one
+    #[error("Session ended with error: {:?}", .0)]
+    SessionEnded(Option<definitions::Error>),
 }
 
 /// Errors associated with attaching a link as sender
@@ -51,6 +63,10 @@ pub enum SenderAttachError {
     #[error("Link name is not unique.")]
     DuplicatedLinkName,
 
+    /// All handles up to the handle-max of the session are in use
+    #[error("Handle max of the session is reached.")]
+    HandleMaxReached,
+
     /// Illegal link state
     #[error("Illegal session state")]
     IllegalState,
@@ -106,9 +122,17 @@ pub enum SenderAttachError {
     #[error("Desired transaction capability is not supported")]
     DesireTxnCapabilitiesNotSupported,
 
+    /// The `ANONYMOUS-RELAY` capability is desired but not offered by the remote peer
+    #[error("The remote peer does not offer the ANONYMOUS-RELAY capability")]
+    AnonymousRelayNotSupported,
+
     /// Remote peer closed the link with an error
     #[error("Remote peer closed with error {:?}", .0)]
     RemoteClosedWithError(definitions::Error),
+
+    /// Remote peer refused the attach and closed the link with the carried Detach
+    #[error("Remote peer refused the attach with {:?}", .0)]
+    RemoteDetached(Box<Detach>),
 }
 
 /// Error associated with sending a message
@@ -120,7 +144,7 @@ pub enum SendError {
 
     /// The remote peer detached with error
     #[error("Link is detached {:?}", .0)]
-    Detached(DetachError),
+    Detached(#[from] DetachError),
 
     /// A non-terminal delivery state is received while expecting
     /// an outcome
@@ -133,18 +157,47 @@ pub enum SendError {
 
     /// Error serializing message
     #[error("Error encoding message")]
-    MessageEncodeError,
-}
+    MessageEncodeError(#[from] serde_amqp::Error),
 
-impl From<serde_amqp::Error> for SendError {
-    fn from(_: serde_amqp::Error) -> Self {
-        Self::MessageEncodeError
-    }
+    /// The message was rejected by the receiver
+    ///
+    /// This is only returned if the sender is built with `rejected_as_error` set to `true`
+    #[error("Outcome {}", .0)]
+    Rejected(Rejected),
+
+    /// The receiver chose an outcome that is not declared in the `outcomes` field of the source
+    ///
+    /// This is only checked if the `outcomes` field of the source is set
+    #[error("Outcome {:?} is not declared in the source", .0)]
+    UndeclaredOutcome(DeliveryState),
+
+    /// No link credit was issued within the duration set with `fail_after_no_credit`
+    ///
+    /// The message is not sent and the link is still attached
+    #[error("No link credit was issued in time")]
+    CreditTimeout,
+
+    /// The message failed a validator set with `send_validator`
+    ///
+    /// The message is not sent and no link credit is consumed
+    #[error("Message failed validation: {0}")]
+    Validation(#[from] ValidationError),
+
+    /// The token given to `send_cancellable` is cancelled
+    ///
+    /// If the delivery was already handed to the session, its outcome can be queried with
+    /// `Sender::pending_outcomes`
+    #[error("The send is cancelled")]
+    Cancelled,
 }
 
-impl From<DetachError> for SendError {
-    fn from(error: DetachError) -> Self {
-        Self::Detached(error)
+/// A send waited for link credit longer than `fail_after_no_credit`
+#[derive(Debug)]
+pub(crate) struct CreditTimeout;
+
+impl From<CreditTimeout> for SendError {
+    fn from(_: CreditTimeout) -> Self {
+        Self::CreditTimeout
     }
 }
 
@@ -201,6 +254,10 @@ pub enum ReceiverAttachError {
     #[error("Link name is not unique.")]
     DuplicatedLinkName,
 
+    /// All handles up to the handle-max of the session are in use
+    #[error("Handle max of the session is reached.")]
+    HandleMaxReached,
+
     /// Illegal link state
     #[error("Illegal session state")]
     IllegalState,
@@ -259,16 +316,38 @@ pub enum ReceiverAttachError {
     #[error("Remote peer closed with error {:?}", .0)]
     RemoteClosedWithError(definitions::Error),
 
+    /// Remote peer refused the attach and closed the link with the carried Detach
+    #[error("Remote peer refused the attach with {:?}", .0)]
+    RemoteDetached(Box<Detach>),
+
     /// The desired filter(s) on the receiver is not supported by the remote peer
     #[error("{:?}", .0)]
     DesiredFilterNotSupported(#[from] DesiredFilterNotSupported),
 }
 
+impl ReceiverAttachError {
+    /// Returns the error sent by the remote peer if the remote peer closed the link with an error
+    pub fn remote_error(&self) -> Option<&definitions::Error> {
+        match self {
+            Self::RemoteClosedWithError(error) => Some(error),
+            Self::RemoteDetached(detach) => detach.error.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Returns where to attach the link instead if the remote peer refused the link with an
+    /// `amqp:link:redirect` error
+    pub fn redirect(&self) -> Option<definitions::Redirect> {
+        self.remote_error().and_then(definitions::Error::redirect)
+    }
+}
+
 impl From<AllocLinkError> for ReceiverAttachError {
     fn from(value: AllocLinkError) -> Self {
         match value {
             AllocLinkError::IllegalSessionState => Self::IllegalSessionState,
             AllocLinkError::DuplicatedLinkName => Self::DuplicatedLinkName,
+            AllocLinkError::HandleMaxReached => Self::HandleMaxReached,
         }
     }
 }
@@ -301,11 +380,29 @@ impl<'a> TryFrom<&'a ReceiverAttachError> for definitions::Error {
     }
 }
 
+impl SenderAttachError {
+    /// Returns the error sent by the remote peer if the remote peer closed the link with an error
+    pub fn remote_error(&self) -> Option<&definitions::Error> {
+        match self {
+            Self::RemoteClosedWithError(error) => Some(error),
+            Self::RemoteDetached(detach) => detach.error.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Returns where to attach the link instead if the remote peer refused the link with an
+    /// `amqp:link:redirect` error
+    pub fn redirect(&self) -> Option<definitions::Redirect> {
+        self.remote_error().and_then(definitions::Error::redirect)
+    }
+}
+
 impl From<AllocLinkError> for SenderAttachError {
     fn from(value: AllocLinkError) -> Self {
         match value {
             AllocLinkError::IllegalSessionState => Self::IllegalSessionState,
             AllocLinkError::DuplicatedLinkName => Self::DuplicatedLinkName,
+            AllocLinkError::HandleMaxReached => Self::HandleMaxReached,
         }
     }
 }
@@ -316,7 +413,9 @@ impl TryFrom<DetachError> for SenderAttachError {
     fn try_from(value: DetachError) -> Result<Self, Self::Error> {
         match value {
             DetachError::IllegalState => Ok(Self::IllegalState),
-            DetachError::IllegalSessionState => Ok(Self::IllegalSessionState),
+            DetachError::IllegalSessionState | DetachError::SessionEnded(_) => {
+                Ok(Self::IllegalSessionState)
+            }
             DetachError::RemoteDetachedWithError(error)
             | DetachError::RemoteClosedWithError(error) => {
                 // A closing detach is used for errors during attach anyway
@@ -334,7 +433,9 @@ impl TryFrom<DetachError> for ReceiverAttachError {
     fn try_from(value: DetachError) -> Result<Self, Self::Error> {
         match value {
             DetachError::IllegalState => Ok(Self::IllegalState),
-            DetachError::IllegalSessionState => Ok(Self::IllegalSessionState),
+            DetachError::IllegalSessionState | DetachError::SessionEnded(_) => {
+                Ok(Self::IllegalSessionState)
+            }
             DetachError::RemoteDetachedWithError(error)
             | DetachError::RemoteClosedWithError(error) => {
                 // A closing detach is used for errors during attach anyway
@@ -366,6 +467,7 @@ impl<'a> TryFrom<&'a SenderAttachError> for definitions::Error {
             SenderAttachError::SourceAddressIsSomeWhenDynamicIsTrue => {
                 AmqpError::InvalidField.into()
             }
+            SenderAttachError::AnonymousRelayNotSupported => AmqpError::NotImplemented.into(),
 
             #[cfg(feature = "transaction")]
             SenderAttachError::DesireTxnCapabilitiesNotSupported => return Err(value),
@@ -435,6 +537,11 @@ pub enum LinkStateError {
     /// an incoming Detach frame
     #[error("Expecting an immediate detach")]
     ExpectImmediateDetach,
+
+    /// The session has ended, which implicitly detaches the link. This carries the error of the
+    /// End performative if there is one
+    #[error("Session ended with error: {:?}", .0)]
+    SessionEnded(Option<definitions::Error>),
 }
 
 impl From<DetachError> for LinkStateError {
@@ -446,6 +553,7 @@ impl From<DetachError> for LinkStateError {
             DetachError::ClosedByRemote => Self::RemoteClosed,
             DetachError::DetachedByRemote => Self::RemoteDetached,
             DetachError::RemoteClosedWithError(error) => Self::RemoteClosedWithError(error),
+            DetachError::SessionEnded(error) => Self::SessionEnded(error),
             // DetachError::NonDetachFrameReceived => Self::ExpectImmediateDetach,
         }
     }
@@ -500,7 +608,11 @@ impl std::fmt::Display for MessageDecodeError {
     }
 }
 
-impl std::error::Error for MessageDecodeError {}
+impl std::error::Error for MessageDecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
 
 /// Errors associated with receiving
 #[derive(Debug, thiserror::Error)]
@@ -537,6 +649,26 @@ pub enum RecvError {
     /// Transactional acquision is not supported yet
     #[error("Transactional acquisition is not implemented")]
     TransactionalAcquisitionIsNotImeplemented,
+
+    /// The sender did not settle an automatically accepted delivery within the settlement
+    /// timeout of a receiver in `ReceiverSettleMode::Second`
+    #[error("Sender did not settle the delivery within the settlement timeout")]
+    SettlementTimeout,
+}
+
+impl From<DispositionError> for RecvError {
+    fn from(value: DispositionError) -> Self {
+        match value {
+            DispositionError::IllegalState => LinkStateError::IllegalState.into(),
+            DispositionError::IllegalSessionState => LinkStateError::IllegalSessionState.into(),
+            DispositionError::Detached => LinkStateError::RemoteDetached.into(),
+            DispositionError::SettlementTimeout => RecvError::SettlementTimeout,
+            // The delivery being auto-accepted is received on this link and is not settled yet
+            DispositionError::AlreadySettled(_) | DispositionError::LinkMismatch(_) => {
+                LinkStateError::IllegalState.into()
+            }
+        }
+    }
 }
 
 impl From<ReceiverTransferError> for RecvError {
@@ -559,8 +691,50 @@ impl From<ReceiverTransferError> for RecvError {
     }
 }
 
-/// Type alias for disposition error
-pub type DispositionError = IllegalLinkStateError;
+/// Errors associated with disposing deliveries
+///
+/// The deliveries are checked before any disposition is sent. Disposing a delivery that has
+/// already been settled or that is received by another receiver fails with
+/// [`AlreadySettled`](Self::AlreadySettled) or [`LinkMismatch`](Self::LinkMismatch), and the
+/// `_all` methods of [`Receiver`](crate::Receiver) send nothing if any of the deliveries fails
+/// this check. No disposition is sent for a delivery that is settled by the sender.
+#[derive(Debug, thiserror::Error)]
+pub enum DispositionError {
+    /// ILlegal link state
+    #[error("Illegal local state")]
+    IllegalState,
+
+    /// Session has dropped
+    #[error("Session has dropped")]
+    IllegalSessionState,
+
+    /// The link is detached before the sender settled a delivery that is disposed with
+    /// `ReceiverSettleMode::Second`
+    #[error("Link is detached before the sender settled the delivery")]
+    Detached,
+
+    /// The sender did not settle a delivery that is disposed with `ReceiverSettleMode::Second`
+    /// within the settlement timeout
+    #[error("Sender did not settle the delivery within the settlement timeout")]
+    SettlementTimeout,
+
+    /// The delivery is not found in the local unsettled map because it has already been settled
+    #[error("Delivery {0} is already settled")]
+    AlreadySettled(DeliveryNumber),
+
+    /// The delivery is received on a different link
+    #[error("Delivery {0} is not received on this link")]
+    LinkMismatch(DeliveryNumber),
+}
+
+impl From<IllegalLinkStateError> for DispositionError {
+    fn from(value: IllegalLinkStateError) -> Self {
+        match value {
+            IllegalLinkStateError::IllegalState => Self::IllegalState,
+            IllegalLinkStateError::IllegalSessionState => Self::IllegalSessionState,
+        }
+    }
+}
 
 /// Type alias for flow error
 pub type FlowError = IllegalLinkStateError;
@@ -701,7 +875,11 @@ impl std::fmt::Display for SenderResumeError {
     }
 }
 
-impl std::error::Error for SenderResumeError {}
+impl std::error::Error for SenderResumeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.kind)
+    }
+}
 
 /// Error kind of receiver resumption
 #[derive(Debug, thiserror::Error)]
@@ -739,7 +917,11 @@ impl std::fmt::Display for ReceiverResumeError {
     }
 }
 
-impl std::error::Error for ReceiverResumeError {}
+impl std::error::Error for ReceiverResumeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.kind)
+    }
+}
 
 /// Error with link relay
 #[derive(Debug, thiserror::Error)]
@@ -751,6 +933,10 @@ pub(crate) enum LinkRelayError {
     /// Found a transfer frame to sender
     #[error("Found transfer frame sent to a sender")]
     TransferFrameToSender,
+
+    /// A flow was received with a delivery-count that is ahead of the deliveries sent
+    #[error("A flow was received with a delivery-count that is ahead of the deliveries sent")]
+    InvalidDeliveryCount,
 }
 
 impl From<LinkRelayError> for definitions::Error {
@@ -766,6 +952,13 @@ impl From<LinkRelayError> for definitions::Error {
                 description: Some(String::from("Transfer frame must not be sent to Sender")),
                 info: None,
             },
+            LinkRelayError::InvalidDeliveryCount => definitions::Error {
+                condition: AmqpError::InvalidField.into(),
+                description: Some(String::from(
+                    "Flow carries a delivery-count that is ahead of the deliveries sent",
+                )),
+                info: None,
+            },
         }
     }
 }
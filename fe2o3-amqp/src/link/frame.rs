@@ -1,7 +1,11 @@
-use fe2o3_amqp_types::performatives::{Attach, Detach, Disposition, Transfer};
+use fe2o3_amqp_types::{
+    definitions,
+    performatives::{Attach, Detach, Disposition, Transfer},
+};
 
 use crate::{
     endpoint::{InputHandle, LinkFlow},
+    session::incoming_budget::IncomingPermit,
     Payload,
 };
 
@@ -21,10 +25,15 @@ pub(crate) enum LinkFrame {
         input_handle: InputHandle,
         performative: Transfer,
         payload: Payload,
+        /// Counts the payload against the buffer limit of the session until the frame is consumed
+        incoming_permit: Option<IncomingPermit>,
     },
     Disposition(Disposition),
     Detach(Detach),
 
+    /// The session has ended, with the error carried by the End if there is one
+    SessionEnded(Option<definitions::Error>),
+
     #[cfg(feature = "transaction")]
     /// Indicating to the receiver that Txn controller side is requesting for
     /// a transactional acquisition
@@ -40,6 +49,7 @@ impl std::fmt::Debug for LinkFrame {
                 input_handle,
                 performative,
                 payload,
+                incoming_permit: _,
             } => f
                 .debug_struct("Transfer")
                 .field("input_handle", input_handle)
@@ -48,6 +58,7 @@ impl std::fmt::Debug for LinkFrame {
                 .finish(),
             Self::Disposition(arg0) => f.debug_tuple("Disposition").field(arg0).finish(),
             Self::Detach(arg0) => f.debug_tuple("Detach").field(arg0).finish(),
+            Self::SessionEnded(arg0) => f.debug_tuple("SessionEnded").field(arg0).finish(),
             #[cfg(feature = "transaction")]
             Self::Acquisition(arg0) => f.debug_tuple("Acquisition").field(arg0).finish(),
         }
@@ -3,7 +3,7 @@ use fe2o3_amqp_types::performatives::Transfer;
 use crate::{util::AsByteIterator, Payload};
 
 use super::{
-    receiver_link::{count_number_of_sections_and_offset, is_section_header},
+    receiver_link::{count_number_of_sections_and_offset, SectionHeaders},
     ReceiverTransferError,
 };
 
@@ -120,18 +120,15 @@ impl IncompleteTransfer {
         section_number: u32,
         section_offset: u64,
     ) -> Option<usize> {
-        let b0 = self.buffer.as_byte_iterator();
-        let b1 = self.buffer.as_byte_iterator().skip(1);
-        let b2 = self.buffer.as_byte_iterator().skip(2);
-        let iter = b0.zip(b1.zip(b2));
+        let headers = SectionHeaders::new(self.buffer.as_byte_iterator());
 
         let mut cur_number = 0;
         let mut cur_offset = 0;
 
-        for (i, (&b0, (&b1, &b2))) in iter.enumerate() {
+        for (i, is_header) in headers.enumerate() {
             cur_offset += 1;
 
-            if is_section_header(b0, b1, b2) {
+            if is_header {
                 cur_number += 1;
                 cur_offset = 0;
             }
@@ -1,6 +1,13 @@
 //! Implements AMQP1.0 Link
 
-use std::{marker::PhantomData, sync::Arc};
+use std::{
+    future::Future,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 
 use bytes::{BufMut, BytesMut};
 use fe2o3_amqp_types::{
@@ -13,53 +20,84 @@ use fe2o3_amqp_types::{
     primitives::{OrderedMap, Symbol},
 };
 
+pub use confirm::ConfirmFailure;
+pub use credit_pool::{CreditPolicy, CreditPool, CreditPoolLinkStats};
 pub use error::*;
 
 use parking_lot::RwLock;
 pub use receiver::Receiver;
-pub use sender::Sender;
+pub use receiver_stream::{ReceiverStream, RecvStream};
+pub use sender::{PendingOutcome, Sender};
 use serde::Serialize;
 use serde_amqp::ser::Serializer;
-use tokio::sync::{mpsc, oneshot};
+pub use state::{LinkFlowSnapshot, LinkState};
+pub use subscription::Subscription;
+pub use validation::{BatchValidationPolicy, ValidationError, ValidatorChain};
+use tokio::sync::{mpsc, oneshot, watch};
+
+cfg_not_wasm32! {
+    pub use buffered_sender::BufferedSender;
+    pub use shared_sender::{QueuePolicy, QueuedSend, SenderHandle, SenderOwner};
+}
 
 use crate::{
     control::SessionControl,
     endpoint::{self, InputHandle, LinkAttach, LinkDetach, LinkFlow, OutputHandle, Settlement},
+    frames::FRAME_HEADER_SIZE,
     link::delivery::UnsettledMessage,
+    session::{incoming_budget::IncomingPermit, overflow::LinkOverflow},
     util::{AsDeliveryState, Consumer, Produce, Producer},
     Payload,
 };
 
 use self::{
-    delivery::Delivery,
-    resumption::ResumingDelivery,
-    state::{LinkFlowState, LinkState},
-    target_archetype::VerifyTargetArchetype,
+    delivery::Delivery, remote_detach::RemoteDetachNotifier,
+    remote_settlement::ArcRemoteSettlements, resumption::ResumingDelivery, state::LinkFlowState,
+    target_archetype::VerifyTargetArchetype, unsettled_store::LinkUnsettledStore,
 };
 
 cfg_transaction! {
     use crate::transaction::TXN_ID_KEY;
 }
 
+mod attach_guard;
+pub(crate) use attach_guard::AttachGuard;
 mod frame;
 pub(crate) use frame::*;
 pub mod builder;
+cfg_not_wasm32! {
+    pub mod buffered_sender;
+    pub mod shared_sender;
+}
+pub(crate) mod confirm;
+pub mod credit_pool;
+mod dedup_window;
 pub mod delivery;
 mod error;
 mod incomplete_transfer;
 pub mod receiver;
 mod receiver_link;
+mod receiver_stream;
+pub(crate) mod remote_detach;
+pub(crate) mod remote_settlement;
 pub(crate) mod resumption;
 pub mod sender;
 mod sender_link;
 pub(crate) mod shared_inner;
 mod source;
 pub(crate) mod state;
+pub mod subscription;
 pub mod target_archetype;
+pub mod unsettled_store;
+pub mod validation;
 
 /// Default amount of link credit
 pub const DEFAULT_CREDIT: SequenceNo = 200;
 
+/// The capability that indicates support for the anonymous terminus, ie. a sender link attached
+/// with a null target address whose messages are routed by the `to` field of their properties
+pub const ANONYMOUS_RELAY: &str = "ANONYMOUS-RELAY";
+
 /// An OrderedMap is used because Link may exchange their unsettled map
 /// and `Map` should be considered ordered
 pub(crate) type UnsettledMap<M> = OrderedMap<DeliveryTag, M>;
@@ -81,6 +119,22 @@ pub(crate) type ArcUnsettledMap<S> = Arc<RwLock<Option<UnsettledMap<S>>>>;
 pub(crate) type ArcSenderUnsettledMap = ArcUnsettledMap<UnsettledMessage>;
 pub(crate) type ArcReceiverUnsettledMap = ArcUnsettledMap<Option<DeliveryState>>;
 
+/// Identifies a link endpoint within the process. The id is kept when the link is resumed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct LinkId(u64);
+
+impl LinkId {
+    pub(crate) fn new() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// Number of deliveries in the unsettled map
+pub(crate) fn unsettled_len<S>(unsettled: &ArcUnsettledMap<S>) -> usize {
+    unsettled.read().as_ref().map_or(0, |map| map.len())
+}
+
 pub mod role {
     //! Type state definition of link role
 
@@ -140,6 +194,7 @@ impl std::fmt::Debug for SenderAttachExchange {
 }
 
 impl SenderAttachExchange {
+    #[cfg_attr(not(feature = "transaction"), allow(dead_code))]
     pub fn complete_or<E>(self, err: E) -> Result<(), E> {
         match self {
             Self::Complete => Ok(()),
@@ -189,6 +244,9 @@ impl ReceiverAttachExchange {
 pub(crate) struct Link<R, T, F, M> {
     pub(crate) role: PhantomData<R>,
 
+    /// Identifies the deliveries received on this link
+    pub(crate) id: LinkId,
+
     pub(crate) local_state: LinkState,
     // pub(crate) state_code: Arc<AtomicU8>,
     pub(crate) name: String,
@@ -215,8 +273,112 @@ pub(crate) struct Link<R, T, F, M> {
     pub(crate) flow_state: F,
     pub(crate) unsettled: ArcUnsettledMap<M>,
 
+    /// Persists `unsettled` if the link is built with an `UnsettledStore`
+    pub(crate) unsettled_store: Option<LinkUnsettledStore>,
+
+    /// Unsettled map loaded from the `UnsettledStore` at attach. This is only offered to the
+    /// remote peer in the Attach frame and is dropped once the remote Attach is received.
+    pub(crate) restored_unsettled: Option<UnsettledMap<Option<DeliveryState>>>,
+
+    /// Tag of an outgoing delivery whose first frames are handed to the session but not its last
+    /// one, because the send was dropped in between. It is aborted before the link sends anything
+    /// else
+    pub(crate) incomplete_delivery: Option<DeliveryTag>,
+
     pub(crate) verify_incoming_source: bool,
     pub(crate) verify_incoming_target: bool,
+
+    /// Publishes changes of `local_state` to the `detached` and `closed` futures
+    pub(crate) state_notifier: watch::Sender<LinkState>,
+}
+
+impl<R, T, F, M> Link<R, T, F, M> {
+    fn notify_local_state(&self) {
+        self.state_notifier.send_replace(self.local_state.clone());
+    }
+
+    fn on_incoming_detach_inner(&mut self, detach: Detach) -> Result<(), DetachError> {
+        match detach.closed {
+            true => match self.local_state {
+                LinkState::Attached
+                | LinkState::AttachSent
+                | LinkState::AttachReceived
+                | LinkState::IncompleteAttachExchanged
+                | LinkState::IncompleteAttachSent
+                | LinkState::IncompleteAttachReceived => {
+                    self.local_state = LinkState::CloseReceived;
+                    match detach.error {
+                        Some(error) => Err(DetachError::RemoteClosedWithError(error)),
+                        None => Ok(()),
+                    }
+                }
+                LinkState::DetachSent => {
+                    self.local_state = LinkState::CloseReceived;
+                    match detach.error {
+                        Some(error) => Err(DetachError::RemoteClosedWithError(error)),
+                        None => Err(DetachError::ClosedByRemote),
+                    }
+                }
+                LinkState::CloseSent => {
+                    self.local_state = LinkState::Closed;
+                    let _ = self.output_handle.take();
+                    match detach.error {
+                        Some(error) => Err(DetachError::RemoteClosedWithError(error)),
+                        None => Ok(()),
+                    }
+                }
+                _ => Err(DetachError::IllegalState),
+            },
+            false => {
+                match self.local_state {
+                    LinkState::Attached => self.local_state = LinkState::DetachReceived,
+                    LinkState::DetachSent => {
+                        self.local_state = LinkState::Detached;
+                        // Dropping output handle as it is already detached
+                        let _ = self.output_handle.take();
+                    }
+                    _ => return Err(DetachError::IllegalState),
+                }
+
+                match detach.error {
+                    Some(error) => Err(DetachError::RemoteDetachedWithError(error)),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+
+    /// Returns a future that resolves once the local state satisfies `f`, `session_ended`
+    /// resolves or the link is dropped
+    ///
+    /// The local state is not updated when the session ends because the link only finds out once
+    /// it reads its incoming frames, so the end of the session is waited for separately.
+    pub(crate) fn wait_for_local_state(
+        &self,
+        session_ended: impl Future<Output = ()> + Send + 'static,
+        f: impl FnMut(&LinkState) -> bool + Send + 'static,
+    ) -> impl Future<Output = ()> + Send + 'static {
+        let mut rx = self.state_notifier.subscribe();
+        async move {
+            tokio::select! {
+                // An error is returned only if the link has been dropped
+                _ = rx.wait_for(f) => {},
+                _ = session_ended => {},
+            }
+        }
+    }
+}
+
+impl<R, T, F, M> Link<R, T, F, M>
+where
+    M: AsDeliveryState,
+{
+    /// Writes the current unsettled map to the `UnsettledStore` if there is one
+    pub(crate) fn save_unsettled(&self) {
+        if let Some(store) = &self.unsettled_store {
+            store.save(&self.unsettled);
+        }
+    }
 }
 
 impl<R, T, F, M> Link<R, T, F, M>
@@ -237,23 +399,25 @@ where
         }
 
         let guard = self.unsettled.read();
-        let map = guard.as_ref()?;
-        match (map.len(), partial_unsettled) {
+        let local = guard
+            .iter()
+            .flatten()
+            .map(|(key, val)| (key.clone(), val.as_delivery_state().clone()));
+        // Deliveries restored from the `UnsettledStore` that are not tracked locally
+        let restored = self
+            .restored_unsettled
+            .iter()
+            .flatten()
+            .filter(|(key, _)| !guard.as_ref().is_some_and(|m| m.contains_key(*key)))
+            .map(|(key, val)| (key.clone(), val.clone()));
+        let total = guard.as_ref().map_or(0, |m| m.len())
+            + self.restored_unsettled.as_ref().map_or(0, |m| m.len());
+        match (total, partial_unsettled) {
             (0, _) => None,
-            (_, 0..=1) => {
-                let v = map
-                    .iter()
-                    .map(|(key, val)| (key.clone(), val.as_delivery_state().clone()))
-                    .collect();
-                Some(v)
-            }
+            (_, 0..=1) => Some(local.chain(restored).collect()),
             (total, denom) => {
                 let len = total / denom;
-                let v = (0..len)
-                    .zip(map.iter())
-                    .map(|(_, (key, val))| (key.clone(), val.as_delivery_state().clone()))
-                    .collect();
-                Some(v)
+                Some(local.chain(restored).take(len).collect())
             }
         }
     }
@@ -308,6 +472,7 @@ where
         is_reattaching: bool,
     ) -> Result<Attach, SendAttachErrorKind> {
         let mut denominator = 1usize; // This is going to be the denominator
+        let max_body_size = max_frame_size.saturating_sub(FRAME_HEADER_SIZE);
         let mut buf = BytesMut::new();
 
         let mut attach = self.as_attach_inner(handle.clone(), is_reattaching, denominator);
@@ -316,7 +481,7 @@ where
             .serialize(&mut serializer)
             .map_err(|_| SendAttachErrorKind::IllegalState)?; // This should not happen
 
-        while buf.len() > max_frame_size {
+        while buf.len() > max_body_size {
             buf.clear();
             denominator *= 2;
 
@@ -333,6 +498,7 @@ where
     /// # Cancel safety
     ///
     /// This is cancel safe if oneshot channel is cancel safe
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(link_name = %self.name, is_reattaching = is_reattaching)))]
     pub(crate) async fn send_attach_inner(
         &mut self,
         writer: &mpsc::Sender<LinkFrame>,
@@ -352,10 +518,15 @@ where
             // before it handles the remote attach.
             let mut guard = self.unsettled.write();
             *guard = None;
+            self.restored_unsettled = None;
             None
         } else {
             let guard = self.unsettled.read();
-            guard.as_ref().map(|m| m.len())
+            let restored_len = self.restored_unsettled.as_ref().map(|m| m.len());
+            match (guard.as_ref().map(|m| m.len()), restored_len) {
+                (Some(local), Some(restored)) => Some(local + restored),
+                (local, restored) => local.or(restored),
+            }
         };
 
         let attach = match unsettled_map_len {
@@ -366,6 +537,14 @@ where
             }
         };
         let incomplete_unsettled = attach.incomplete_unsettled;
+        #[cfg(feature = "tracing")]
+        tracing::debug!(handle = attach.handle.0, "Sending attach");
+        #[cfg(feature = "log")]
+        log::debug!(
+            "Sending attach: link_name = {:?}, handle = {:?}",
+            attach.name,
+            attach.handle
+        );
         let frame = LinkFrame::Attach(attach);
 
         match self.local_state {
@@ -391,6 +570,7 @@ where
             }
             _ => return Err(SendAttachErrorKind::IllegalState),
         }
+        self.notify_local_state();
 
         Ok(())
     }
@@ -421,67 +601,22 @@ where
     type DetachError = DetachError;
 
     /// Closing or not isn't taken care of here but outside
-    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(link_name = %self.name)))]
     fn on_incoming_detach(&mut self, detach: Detach) -> Result<(), Self::DetachError> {
         #[cfg(feature = "tracing")]
         tracing::trace!(detach = ?detach);
         #[cfg(feature = "log")]
         log::trace!("RECV detach = {:?}", detach);
 
-        match detach.closed {
-            true => match self.local_state {
-                LinkState::Attached
-                | LinkState::AttachSent
-                | LinkState::AttachReceived
-                | LinkState::IncompleteAttachExchanged
-                | LinkState::IncompleteAttachSent
-                | LinkState::IncompleteAttachReceived => {
-                    self.local_state = LinkState::CloseReceived;
-                    match detach.error {
-                        Some(error) => Err(DetachError::RemoteClosedWithError(error)),
-                        None => Ok(()),
-                    }
-                }
-                LinkState::DetachSent => {
-                    self.local_state = LinkState::CloseReceived;
-                    match detach.error {
-                        Some(error) => Err(DetachError::RemoteClosedWithError(error)),
-                        None => Err(DetachError::ClosedByRemote),
-                    }
-                }
-                LinkState::CloseSent => {
-                    self.local_state = LinkState::Closed;
-                    let _ = self.output_handle.take();
-                    match detach.error {
-                        Some(error) => Err(DetachError::RemoteClosedWithError(error)),
-                        None => Ok(()),
-                    }
-                }
-                _ => Err(DetachError::IllegalState),
-            },
-            false => {
-                match self.local_state {
-                    LinkState::Attached => self.local_state = LinkState::DetachReceived,
-                    LinkState::DetachSent => {
-                        self.local_state = LinkState::Detached;
-                        // Dropping output handle as it is already detached
-                        let _ = self.output_handle.take();
-                    }
-                    _ => return Err(DetachError::IllegalState),
-                }
-
-                match detach.error {
-                    Some(error) => Err(DetachError::RemoteDetachedWithError(error)),
-                    None => Ok(()),
-                }
-            }
-        }
+        let result = self.on_incoming_detach_inner(detach);
+        self.notify_local_state();
+        result
     }
 
     /// # Cancel safety
     ///
     /// This is cancel safe because it only .await on sending over `tokio::mpsc::Sender`
-    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(link_name = %self.name, closed = closed)))]
     async fn send_detach(
         &mut self,
         writer: &mpsc::Sender<LinkFrame>,
@@ -498,6 +633,9 @@ where
             (LinkState::CloseReceived, true) => self.local_state = LinkState::Closed,
             _ => return Err(DetachError::IllegalState),
         };
+        self.notify_local_state();
+        // Write back settlements made by the remote peer since the last local change
+        self.save_unsettled();
 
         match self.output_handle.clone() {
             Some(handle) => {
@@ -542,11 +680,22 @@ pub(crate) enum LinkRelay<O> {
         output_handle: O,
         flow_state: ReceiverRelayFlowState,
         unsettled: ArcReceiverUnsettledMap,
+        remote_settlements: ArcRemoteSettlements,
         receiver_settle_mode: ReceiverSettleMode,
         more: bool,
     },
 }
 
+impl<O> LinkRelay<O> {
+    /// Tells the link which side detached it first
+    pub(crate) fn remote_detach(&self) -> &RemoteDetachNotifier {
+        match self {
+            LinkRelay::Sender { flow_state, .. } => &flow_state.state().remote_detach,
+            LinkRelay::Receiver { flow_state, .. } => &flow_state.remote_detach,
+        }
+    }
+}
+
 impl LinkRelay<()> {
     pub fn new_sender(
         tx: mpsc::Sender<LinkIncomingItem>,
@@ -566,6 +715,7 @@ impl LinkRelay<()> {
         tx: mpsc::Sender<LinkIncomingItem>,
         flow_state: ReceiverRelayFlowState,
         unsettled: ArcReceiverUnsettledMap,
+        remote_settlements: ArcRemoteSettlements,
         receiver_settle_mode: ReceiverSettleMode,
     ) -> Self {
         Self::Receiver {
@@ -573,6 +723,7 @@ impl LinkRelay<()> {
             output_handle: (),
             flow_state,
             unsettled,
+            remote_settlements,
             receiver_settle_mode,
             more: false,
         }
@@ -597,6 +748,7 @@ impl LinkRelay<()> {
                 tx,
                 flow_state,
                 unsettled,
+                remote_settlements,
                 receiver_settle_mode,
                 more,
                 ..
@@ -605,6 +757,7 @@ impl LinkRelay<()> {
                 output_handle,
                 flow_state,
                 unsettled,
+                remote_settlements,
                 receiver_settle_mode,
                 more,
             },
@@ -613,6 +766,28 @@ impl LinkRelay<()> {
 }
 
 impl LinkRelay<OutputHandle> {
+    pub(crate) fn output_handle(&self) -> &OutputHandle {
+        match self {
+            LinkRelay::Sender { output_handle, .. } => output_handle,
+            LinkRelay::Receiver { output_handle, .. } => output_handle,
+        }
+    }
+
+    pub(crate) fn role(&self) -> Role {
+        match self {
+            LinkRelay::Sender { .. } => Role::Sender,
+            LinkRelay::Receiver { .. } => Role::Receiver,
+        }
+    }
+
+    /// Number of frames in the channel of a receiver that the link has not taken yet
+    pub(crate) fn buffered_frames(&self) -> usize {
+        match self {
+            LinkRelay::Sender { .. } => 0,
+            LinkRelay::Receiver { tx, .. } => tx.max_capacity() - tx.capacity(),
+        }
+    }
+
     pub(crate) async fn send(
         &mut self,
         frame: LinkFrame,
@@ -623,10 +798,23 @@ impl LinkRelay<OutputHandle> {
         }
     }
 
+    /// The rejected frame is boxed because link frames are large
+    pub(crate) fn try_send(
+        &mut self,
+        frame: LinkFrame,
+    ) -> Result<(), Box<mpsc::error::TrySendError<LinkFrame>>> {
+        match self {
+            LinkRelay::Sender { tx, .. } => tx.try_send(frame),
+            LinkRelay::Receiver { tx, .. } => tx.try_send(frame),
+        }
+        .map_err(Box::new)
+    }
+
     #[allow(unused_variables)]
     pub(crate) async fn on_incoming_flow(
         &mut self,
         flow: LinkFlow,
+        overflow: &mut LinkOverflow,
     ) -> Result<Option<LinkFlow>, LinkRelayError> {
         match self {
             LinkRelay::Sender {
@@ -641,16 +829,13 @@ impl LinkRelay<OutputHandle> {
                     match flow.properties.as_ref().and_then(|m| m.get(TXN_ID_KEY)) {
                         Some(Value::Binary(txn_id)) => {
                             let frame = LinkFrame::Acquisition(txn_id.clone());
-                            tx.send(frame)
-                                .await
-                                .map_err(|_| LinkRelayError::UnattachedHandle)?;
+                            overflow.send(tx, frame)?;
                         }
                         Some(_) | None => {}
                     }
                 }
 
-                let ret = flow_state.produce((flow, output_handle.clone())).await;
-                Ok(ret)
+                flow_state.produce((flow, output_handle.clone())).await
             }
             LinkRelay::Receiver {
                 flow_state,
@@ -672,14 +857,21 @@ impl LinkRelay<OutputHandle> {
         state: Option<DeliveryState>,
         // Disposition only contains the delivery ids, which are assigned by the
         // sessions
+        delivery_id: DeliveryNumber,
         delivery_tag: DeliveryTag,
     ) -> bool {
         match self {
             LinkRelay::Sender {
                 unsettled,
                 receiver_settle_mode,
+                flow_state,
                 ..
             } => {
+                flow_state.state().last_disposition_at.record();
+                flow_state
+                    .state()
+                    .confirms
+                    .on_disposition(delivery_id, settled, &state);
                 let echo = if settled {
                     // Upon receiving the updated delivery state from the receiver, the sender will, if it has not already spontaneously
                     // attained a terminal state (e.g., through the expiry of the TTL at the sender), update its view of the state and
@@ -727,21 +919,29 @@ impl LinkRelay<OutputHandle> {
                         ReceiverSettleMode::Second => {
                             // The receiver will only settle after sending the disposition to
                             // the sender and receiving a disposition indicating settlement of the
-                            // delivery from the sender.
-
-                            // is_terminal
-                            true
+                            // delivery from the sender. Only a terminal state indicates that
+                            // the receiver has finished processing the delivery
+                            is_terminal
                         }
                     }
                 };
 
                 echo
             }
-            LinkRelay::Receiver { unsettled, .. } => {
+            LinkRelay::Receiver {
+                unsettled,
+                remote_settlements,
+                ..
+            } => {
                 if settled {
-                    let mut guard = unsettled.write();
-                    // let _state = remove_from_unsettled(unsettled, &delivery_tag).await;
-                    let _state = guard.as_mut().and_then(|m| m.swap_remove(&delivery_tag));
+                    {
+                        let mut guard = unsettled.write();
+                        // let _state = remove_from_unsettled(unsettled, &delivery_tag).await;
+                        let _state = guard.as_mut().and_then(|m| m.swap_remove(&delivery_tag));
+                    }
+                    // The receiver may be waiting for the sender to settle a delivery that is
+                    // disposed in mode Second
+                    remote_settlements.settle(&delivery_tag);
                 } else {
                     let mut guard = unsettled.write();
                     if let Some(msg_state) = guard.as_mut().and_then(|m| m.get_mut(&delivery_tag)) {
@@ -758,16 +958,20 @@ impl LinkRelay<OutputHandle> {
 
     /// LinkRelay operates in session's event loop
     ///
-    /// The session needs a map of delivery_id and delivery_tag
-    pub(crate) async fn on_incoming_transfer(
+    /// The session needs a map of delivery_id and delivery_tag. The transfer is kept in the
+    /// overflow if the link is not reading its frames, so that the other links are not blocked
+    pub(crate) fn on_incoming_transfer(
         &mut self,
         transfer: Transfer,
         payload: Payload,
+        incoming_permit: Option<IncomingPermit>,
+        overflow: &mut LinkOverflow,
     ) -> Result<Option<(DeliveryNumber, DeliveryTag)>, LinkRelayError> {
         match self {
             LinkRelay::Sender { .. } => Err(LinkRelayError::TransferFrameToSender),
             LinkRelay::Receiver {
                 tx,
+                flow_state,
                 receiver_settle_mode,
                 more,
                 ..
@@ -776,14 +980,18 @@ impl LinkRelay<OutputHandle> {
                 let delivery_id = transfer.delivery_id;
                 let delivery_tag = transfer.delivery_tag.clone();
                 let transfer_more = transfer.more;
+                if !transfer_more && !transfer.aborted {
+                    flow_state.last_delivery_at.record();
+                }
+                flow_state.on_relayed_transfer(transfer_more, transfer.aborted);
 
-                tx.send(LinkFrame::Transfer {
+                let frame = LinkFrame::Transfer {
                     input_handle: InputHandle::from(transfer.handle.clone()),
                     performative: transfer,
                     payload,
-                })
-                .await
-                .map_err(|_| LinkRelayError::UnattachedHandle)?;
+                    incoming_permit,
+                };
+                overflow.send(tx, frame)?;
 
                 if !settled {
                     if let ReceiverSettleMode::Second = receiver_settle_mode {
@@ -811,17 +1019,30 @@ impl LinkRelay<OutputHandle> {
         }
     }
 
-    /// This is cancel safe because it only .await on sending over `tokio::mpsc::Sender`
-    pub async fn on_incoming_detach(
+    /// The Detach is kept in the overflow after the frames of the link that are still waiting
+    pub(crate) fn on_incoming_detach(
         &mut self,
         detach: Detach,
-    ) -> Result<(), mpsc::error::SendError<LinkFrame>> {
+        overflow: &mut LinkOverflow,
+    ) -> Result<(), LinkRelayError> {
+        // The link is told right away even if it is not reading its frames
+        self.remote_detach().on_remote_detach(&detach);
         match self {
-            LinkRelay::Sender { tx, .. } => {
-                tx.send(LinkFrame::Detach(detach)).await?;
+            LinkRelay::Sender { tx, flow_state, .. } => {
+                // The deliveries that are not confirmed yet will be given new delivery ids if the
+                // link resumes
+                flow_state.state().confirms.on_detached();
+                overflow.send(tx, LinkFrame::Detach(detach))?;
             }
-            LinkRelay::Receiver { tx, .. } => {
-                tx.send(LinkFrame::Detach(detach)).await?;
+            LinkRelay::Receiver {
+                tx,
+                remote_settlements,
+                ..
+            } => {
+                // The receiver may not be reading the incoming frames while it waits for the
+                // sender to settle, so the waiting deliveries are failed here
+                remote_settlements.on_detached();
+                overflow.send(tx, LinkFrame::Detach(detach))?;
             }
         }
         Ok(())
@@ -842,6 +1063,12 @@ pub(crate) fn get_max_message_size(local: u64, remote: Option<u64>) -> u64 {
     }
 }
 
+pub(crate) fn contains_capability(capabilities: Option<&Vec<Symbol>>, capability: &str) -> bool {
+    capabilities
+        .map(|c| c.iter().any(|s| s.as_str() == capability))
+        .unwrap_or(false)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::link::state::LinkFlowStateInner;
@@ -857,8 +1084,8 @@ mod tests {
 
         let notifier = Arc::new(Notify::new());
         let state = LinkFlowState::sender(LinkFlowStateInner {
-            initial_delivery_count: 0,
-            delivery_count: 0,
+            initial_delivery_count: 0.into(),
+            delivery_count: 0.into(),
             link_credit: 0,
             available: 0,
             drain: false,
@@ -869,7 +1096,7 @@ mod tests {
 
         let handle = tokio::spawn(async move {
             let item = (LinkFlow::default(), OutputHandle(0));
-            producer.produce(item).await;
+            producer.produce(item).await.unwrap();
         });
 
         notified.await;
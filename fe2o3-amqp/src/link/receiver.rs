@@ -1,40 +1,53 @@
 //! Implementation of AMQP1.0 receiver
 
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::{
+    future::Future,
+    sync::atomic::{AtomicU32, Ordering},
+    time::Instant,
+};
 
 use fe2o3_amqp_types::{
-    definitions::{self, DeliveryTag, Fields, SequenceNo},
+    definitions::{
+        self, DeliveryTag, Fields, ReceiverSettleMode, Role, SenderSettleMode, SequenceNo,
+    },
     messaging::{
-        Accepted, Address, DeliveryState, FromBody, Modified, Rejected, Released, Source, Target,
+        message::sections, Accepted, Address, DeliveryState, FromBody, Modified, Rejected,
+        Released, Source, Target,
     },
-    performatives::{Attach, Detach, Transfer},
+    performatives::{Attach, Detach, Disposition, Transfer},
 };
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
 
 cfg_not_wasm32! {
     use std::time::Duration;
-    use tokio::time::{error::Elapsed, timeout};
+    use crate::rt::{timeout, Elapsed};
 }
 
 use crate::{
     control::SessionControl,
-    endpoint::{self, LinkAttach, LinkDetach, LinkExt},
+    endpoint::{self, InputHandle, LinkAttach, LinkDetach, LinkExt},
     session::SessionHandle,
+    util::{AsByteIterator, IntoPayload, IntoReader},
     Payload,
 };
 
 use super::{
     builder::{self, WithTarget, WithoutName, WithoutSource},
-    delivery::{Delivery, DeliveryInfo},
+    credit_pool::{CreditPool, CreditPoolMember},
+    dedup_window::DedupWindow,
+    delivery::{Delivery, DeliveryInfo, FromPayload, RawDelivery},
     error::DetachError,
     incomplete_transfer::IncompleteTransfer,
     receiver_link::count_number_of_sections_and_offset,
+    remote_settlement::ArcRemoteSettlements,
     role,
     shared_inner::{LinkEndpointInner, LinkEndpointInnerDetach, LinkEndpointInnerReattach},
-    ArcReceiverUnsettledMap, DetachThenResumeReceiverError, DispositionError,
-    IllegalLinkStateError, LinkFrame, LinkRelay, LinkStateError, ReceiverAttachError,
-    ReceiverAttachExchange, ReceiverFlowState, ReceiverLink, ReceiverResumeError,
-    ReceiverResumeErrorKind, ReceiverTransferError, RecvError, DEFAULT_CREDIT,
+    state::{LinkFlowSnapshot, LinkState},
+    unsettled_len, ArcReceiverUnsettledMap, DetachThenResumeReceiverError, DispositionError,
+    IllegalLinkStateError, LinkFrame, LinkRelay, LinkStateError, MessageDecodeError,
+    ReceiverAttachError, ReceiverAttachExchange, ReceiverFlowState, ReceiverLink,
+    ReceiverResumeError, ReceiverResumeErrorKind, ReceiverStream, ReceiverTransferError, RecvError,
+    RecvStream, DEFAULT_CREDIT,
 };
 
 cfg_transaction! {
@@ -53,6 +66,18 @@ pub enum CreditMode {
 
     /// The receiver will automatically re-fill the credit
     Auto(SequenceNo),
+
+    /// The receiver will automatically re-fill the credit to `credit` once no more than
+    /// `threshold` of the issued credits are left
+    ///
+    /// The credit is re-filled after every delivery if `threshold` is not smaller than `credit`
+    Threshold {
+        /// The link credit that is issued by every re-fill
+        credit: SequenceNo,
+
+        /// The remaining link credit at or below which the credit is re-filled
+        threshold: SequenceNo,
+    },
 }
 
 impl Default for CreditMode {
@@ -62,6 +87,83 @@ impl Default for CreditMode {
     }
 }
 
+impl CreditMode {
+    /// The link credit that the receiver issues by itself or `None` in manual mode
+    pub(crate) fn refill_credit(&self) -> Option<SequenceNo> {
+        match self {
+            Self::Manual => None,
+            Self::Auto(credit) | Self::Threshold { credit, .. } => Some(*credit),
+        }
+    }
+
+    /// Whether the credit is re-filled once `processed` of the `granted` credits are used
+    pub(crate) fn should_refill(&self, processed: SequenceNo, granted: SequenceNo) -> bool {
+        match self {
+            Self::Manual => false,
+            Self::Auto(_) => processed >= granted / 2,
+            Self::Threshold { threshold, .. } => granted.saturating_sub(processed) <= *threshold,
+        }
+    }
+}
+
+/// What the receiver does with the deliveries it has received but not settled when the link is
+/// closed, detached or dropped
+///
+/// The dispositions are sent before the Detach frame. Deliveries with consecutive delivery ids are
+/// settled with a single ranged disposition. Deliveries that are already given an outcome and only
+/// wait for the sender to settle them are left as they are.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnCloseUnsettled {
+    /// Settles the deliveries with the `Released` outcome so that the sender can redeliver them
+    /// right away. The `Modified` outcome is used instead if the `outcomes` of the source do not
+    /// include `Released`
+    Release,
+
+    /// Settles the deliveries with the `Rejected` outcome
+    Reject,
+
+    /// Leaves the deliveries unsettled
+    #[default]
+    Leave,
+}
+
+impl OnCloseUnsettled {
+    /// The outcome given to the unsettled deliveries or `None` if the deliveries are left
+    /// unsettled
+    ///
+    /// The outcome must be one of the `outcomes` of the source if the field is set, and the
+    /// deliveries are left unsettled if none of the candidates is supported.
+    pub(crate) fn outcome(&self, source: Option<&Source>) -> Option<DeliveryState> {
+        let outcomes = source
+            .and_then(|source| source.outcomes.as_ref())
+            .filter(|outcomes| !outcomes.0.is_empty());
+        let supports = |descriptor: &str| match outcomes {
+            Some(outcomes) => outcomes
+                .0
+                .iter()
+                .any(|outcome| outcome.as_str() == descriptor),
+            None => true,
+        };
+        match self {
+            Self::Release if supports(RELEASED_DESCRIPTOR) => Some(Released {}.into()),
+            Self::Release if supports(MODIFIED_DESCRIPTOR) => Some(
+                Modified {
+                    delivery_failed: None,
+                    undeliverable_here: None,
+                    message_annotations: None,
+                }
+                .into(),
+            ),
+            Self::Reject if supports(REJECTED_DESCRIPTOR) => Some(Rejected { error: None }.into()),
+            _ => None,
+        }
+    }
+}
+
+const RELEASED_DESCRIPTOR: &str = "amqp:released:list";
+const MODIFIED_DESCRIPTOR: &str = "amqp:modified:list";
+const REJECTED_DESCRIPTOR: &str = "amqp:rejected:list";
+
 /// An AMQP1.0 receiver
 ///
 /// # Attach a new receiver with default configurations
@@ -96,6 +198,7 @@ impl Default for CreditMode {
 /// |`buffer_size`| `u16::MAX` |
 /// |`role`| `role::Sender` |
 /// |`auto_accept`|`false`|
+/// |`on_close_unsettled`|`OnCloseUnsettled::Leave`|
 ///
 /// # Customize configuration with [`builder::Builder`]
 ///
@@ -108,11 +211,26 @@ impl Default for CreditMode {
 ///     .await
 ///     .unwrap();
 /// ```
-#[derive(Debug)]
 pub struct Receiver {
     pub(crate) inner: ReceiverInner<ReceiverLink<Target>>,
 }
 
+impl std::fmt::Debug for Receiver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let source = self.source().as_ref().and_then(|s| s.address.as_ref());
+        let target = self.target().as_ref().and_then(|t| t.address.as_ref());
+        f.debug_struct("Receiver")
+            .field("name", &self.name())
+            .field("state", self.local_state())
+            .field("snd_settle_mode", self.snd_settle_mode())
+            .field("rcv_settle_mode", self.rcv_settle_mode())
+            .field("source", &source)
+            .field("target", &target)
+            .field("flow", &self.flow_snapshot())
+            .finish()
+    }
+}
+
 impl Receiver {
     /// Creates a builder for the [`Receiver`]
     pub fn builder(
@@ -126,12 +244,49 @@ impl Receiver {
         self.inner.link.name()
     }
 
+    /// Returns the current state of the link
+    pub fn local_state(&self) -> &LinkState {
+        &self.inner.link.local_state
+    }
+
+    /// Returns the sender settle mode in use, which is the one in the attach of the sender once
+    /// the attach is exchanged
+    pub fn snd_settle_mode(&self) -> &SenderSettleMode {
+        &self.inner.link.snd_settle_mode
+    }
+
+    /// Returns the receiver settle mode in use, which is the one in the attach of the receiver
+    /// once the attach is exchanged
+    pub fn rcv_settle_mode(&self) -> &ReceiverSettleMode {
+        &self.inner.link.rcv_settle_mode
+    }
+
     /// Returns the `max_message_size` of the link. A value of zero indicates that the link has no
     /// maximum message size, and thus a zero value is turned into a `None`
     pub fn max_message_size(&self) -> Option<u64> {
         self.inner.link.max_message_size()
     }
 
+    /// Returns a snapshot of the link credit, delivery count, available and drain flag of the
+    /// link, together with the number of unsettled incoming deliveries
+    ///
+    /// This does not change the state of the link.
+    pub fn flow_snapshot(&self) -> LinkFlowSnapshot {
+        let unsettled = unsettled_len(self.inner.link.unsettled());
+        self.inner.link.flow_state().snapshot(unsettled)
+    }
+
+    /// Returns the instant at which the last transfer of the last delivery on this link arrived
+    /// from the remote peer, or `None` if no delivery has arrived yet
+    ///
+    /// The instant is recorded by the session event loop when the delivery arrives, not when it
+    /// is taken with [`recv`](Self::recv), so a subscription that stops receiving deliveries
+    /// can be told apart from one that is not being read. It follows the tokio clock and is always
+    /// `None` on wasm32.
+    pub fn last_delivery_at(&self) -> Option<Instant> {
+        self.inner.link.flow_state().last_delivery_at.get()
+    }
+
     /// Get the current credit of the link
     pub fn credit_mode(&self) -> &CreditMode {
         &self.inner.credit_mode
@@ -140,10 +295,26 @@ impl Receiver {
     /// Set the credit mode
     ///
     /// This will not send a flow to the remote peer even if credits in `CreditMode::Auto` is changed.
+    /// Use [`update_credit_mode`](#method.update_credit_mode) to apply the new credit mode
+    /// immediately.
     pub fn set_credit_mode(&mut self, credit_mode: CreditMode) {
         self.inner.credit_mode = credit_mode;
     }
 
+    /// The number of messages the sender has reported as waiting for link credit, minus the
+    /// messages received since. Returns `None` if the sender has not reported it yet
+    ///
+    /// In [`CreditMode::Auto`], the link credit is refilled with no more than this value unless
+    /// it is zero.
+    pub fn available(&self) -> Option<u32> {
+        self.inner.link.flow_state().available()
+    }
+
+    /// Whether the link credit is withdrawn by [`pause`](#method.pause)
+    pub fn is_paused(&self) -> bool {
+        self.inner.paused
+    }
+
     /// Get the `auto_accept` field of receiver
     pub fn auto_accept(&self) -> bool {
         self.inner.auto_accept
@@ -154,6 +325,23 @@ impl Receiver {
         self.inner.auto_accept = value;
     }
 
+    /// Number of resent deliveries that are settled with a previously recorded outcome instead of
+    /// being yielded again. This is always zero if the receiver is built without a
+    /// [`dedup_window`](crate::link::builder::Builder::dedup_window)
+    pub fn suppressed_duplicates(&self) -> u64 {
+        self.inner
+            .dedup_window
+            .as_ref()
+            .map(|window| window.suppressed())
+            .unwrap_or(0)
+    }
+
+    /// Number of deliveries that are aborted by the sender. An aborted delivery is discarded
+    /// without being yielded or disposed, and its link credit is refilled by the credit mode
+    pub fn aborted_deliveries(&self) -> u64 {
+        self.inner.aborted_deliveries
+    }
+
     /// Get a reference to the link's source field
     pub fn source(&self) -> &Option<Source> {
         &self.inner.link.source
@@ -190,6 +378,25 @@ impl Receiver {
         self.inner.link.properties_mut(op)
     }
 
+    /// Replaces the properties of the link, which are carried by every Flow sent from now on
+    ///
+    /// The properties are shared with the session event loop, which reads them when it sends a
+    /// Flow, so they can be changed while the link is in use. No Flow is sent by this method.
+    /// These are the same properties that [`properties_mut`](Self::properties_mut) gives access
+    /// to, which are also sent in the Attach frame when the link is (re-)attached.
+    pub fn set_flow_properties(&mut self, properties: Fields) {
+        self.inner
+            .link
+            .flow_state()
+            .set_properties(Some(properties))
+    }
+
+    /// Returns the properties of the last Flow from the remote peer that carried any, or `None`
+    /// if no such Flow has arrived yet
+    pub fn remote_flow_properties(&self) -> Option<Fields> {
+        self.inner.link.flow_state().remote_properties()
+    }
+
     /// Attach the receiver link to a session with the default configuration
     /// with the `name` and `source` address set the specified value
     ///
@@ -210,6 +417,8 @@ impl Receiver {
     /// |`buffer_size`| `u16::MAX` |
     /// |`role`| `role::Sender` |
     /// |`auto_accept`|`false`|
+    /// |`dedup_window`|`None`|
+    /// |`on_close_unsettled`|`OnCloseUnsettled::Leave`|
     ///  
     /// # Example
     ///
@@ -220,6 +429,19 @@ impl Receiver {
     ///     "q1"                    // Source address
     /// ).await.unwrap();
     /// ```
+    ///
+    /// If the remote peer refuses the attach, the Detach sent by the remote peer is returned in
+    /// [`ReceiverAttachError::RemoteDetached`]
+    ///
+    /// ```rust, ignore
+    /// match Receiver::attach(&mut session, "rust-receiver-link-1", "q1").await {
+    ///     Ok(receiver) => { /* ... */ }
+    ///     Err(ReceiverAttachError::RemoteDetached(detach)) => {
+    ///         println!("Attach refused with {:?}", detach.error);
+    ///     }
+    ///     Err(error) => println!("Attach failed: {}", error),
+    /// }
+    /// ```
     pub async fn attach<R>(
         session: &mut SessionHandle<R>,
         name: impl Into<String>,
@@ -232,6 +454,31 @@ impl Receiver {
             .await
     }
 
+    /// Attach the link to a session with a generated unique name
+    ///
+    /// The name is `fe2o3-receiver-<uuid>`, see
+    /// [`auto_name`](crate::link::builder::Builder::auto_name) to use a different prefix. The
+    /// generated name can be retrieved with [`name`](#method.name).
+    ///
+    /// # Example
+    ///
+    /// ```rust, ignore
+    /// let mut receiver = Receiver::attach_auto_name(
+    ///     &mut session,           // mutable reference to SessionHandle
+    ///     "q1"                    // Source address
+    /// ).await.unwrap();
+    /// ```
+    pub async fn attach_auto_name<R>(
+        session: &mut SessionHandle<R>,
+        addr: impl Into<Address>,
+    ) -> Result<Receiver, ReceiverAttachError> {
+        Self::builder()
+            .auto_name("fe2o3-receiver")
+            .source(addr)
+            .attach(session)
+            .await
+    }
+
     /// Receive a message from the link
     ///
     /// # Example
@@ -253,6 +500,19 @@ impl Receiver {
     /// receiver.accept(&delivery).await.unwrap();
     /// ```
     ///
+    /// If the link carries messages of different shapes, [`recv_raw`](#method.recv_raw) keeps the
+    /// payload encoded, and [`RawDelivery::body_as`] can decode the body as one type after
+    /// another without consuming the delivery.
+    ///
+    /// ```rust,ignore
+    /// let delivery = receiver.recv_raw().await.unwrap();
+    /// let body = match delivery.body_as::<AmqpValue<KnownType>>() {
+    ///     Ok(AmqpValue(known)) => known,
+    ///     Err(_) => parse_json(&delivery.body_as::<Data>().unwrap().0)?,
+    /// };
+    /// receiver.accept(&delivery).await.unwrap();
+    /// ```
+    ///
     /// If the user is certain an [`AmqpValue`] body section is expected, then the user could use
     /// [`AmqpValue<KnownType>`] if the exact message type `KnownType` is known and implements
     /// [`serde::Deserialize`]. If the user is not sure about the exact message type, one could use
@@ -296,25 +556,301 @@ impl Receiver {
         self.inner.recv().await
     }
 
+    /// Receives the next delivery without decoding its payload
+    ///
+    /// The encoded message can be forwarded to another link with
+    /// [`Sender::forward`](crate::Sender::forward). The returned [`RawDelivery`] is disposed of
+    /// like a [`Delivery`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let mut delivery = receiver.recv_raw().await.unwrap();
+    /// // Delivery annotations are only meant for this hop
+    /// let delivery_annotations = delivery.strip_delivery_annotations().unwrap();
+    /// let receipt = sender.forward(&delivery).await.unwrap();
+    /// receiver.accept(&delivery).await.unwrap();
+    /// ```
+    ///
+    /// # Cancel safety
+    ///
+    /// This function is cancel-safe. See [#22](https://github.com/minghuaw/fe2o3-amqp/issues/22)
+    /// for more details.
+    pub async fn recv_raw(&mut self) -> Result<RawDelivery, RecvError> {
+        self.inner.recv_raw().await
+    }
+
+    cfg_not_wasm32! {
+        /// Receives up to `max` deliveries from the link
+        ///
+        /// If the credit mode is [`CreditMode::Auto`], the link credit is raised to `max` before
+        /// receiving if fewer credits are currently issued. The credit mode itself is not changed,
+        /// and any credit that is left over when fewer than `max` deliveries arrive stays with the
+        /// remote sender until it is reset by the next refill. In [`CreditMode::Manual`], the
+        /// credit is left to the user.
+        ///
+        /// The first delivery is awaited without a time limit. Once it has arrived, this waits at
+        /// most `max_wait` for the rest and returns early with the deliveries that have arrived
+        /// by then. A delivery that arrives after the deadline is not lost and will be returned by
+        /// the next receive.
+        ///
+        /// The deliveries can be settled with a single Disposition with
+        /// [`accept_all`](#method.accept_all) if their delivery IDs are contiguous.
+        ///
+        /// # Example
+        ///
+        /// ```rust,ignore
+        /// let deliveries: Vec<Delivery<String>> = receiver
+        ///     .recv_batch(100, Duration::from_millis(50))
+        ///     .await
+        ///     .unwrap();
+        /// receiver.accept_all(&deliveries).await.unwrap();
+        /// ```
+        ///
+        /// # Cancel safety
+        ///
+        /// This function is NOT cancel-safe. The deliveries that are received before the future is
+        /// dropped are lost and remain unsettled. The deliveries received before an error are also
+        /// dropped.
+        pub async fn recv_batch<T>(
+            &mut self,
+            max: usize,
+            max_wait: Duration,
+        ) -> Result<Vec<Delivery<T>>, RecvError>
+        where
+            for<'de> T: FromBody<'de> + Send,
+        {
+            self.inner.recv_batch(max, max_wait).await
+        }
+    }
+
+    /// Converts the receiver into a [`Stream`](futures_util::Stream) of deliveries
+    ///
+    /// The stream ends when the remote peer detaches or closes the link, and the error carried
+    /// by the remote Detach (if any) is yielded as the final item. The receiver can be taken back
+    /// with [`ReceiverStream::into_inner`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// use futures_util::StreamExt;
+    ///
+    /// let mut stream = receiver.into_stream::<String>();
+    /// while let Some(delivery) = stream.next().await {
+    ///     let delivery = delivery.unwrap();
+    ///     stream.get_ref().unwrap().accept(&delivery).await.unwrap();
+    /// }
+    /// ```
+    pub fn into_stream<T>(self) -> ReceiverStream<T>
+    where
+        for<'de> T: FromBody<'de> + Send + 'static,
+    {
+        ReceiverStream::new(self)
+    }
+
+    /// Returns a [`Stream`](futures_util::Stream) of deliveries that borrows the receiver
+    ///
+    /// See [`into_stream`](#method.into_stream) for more details.
+    ///
+    /// # Cancel safety
+    ///
+    /// Dropping the stream does not lose a partially received multi-frame delivery, which will
+    /// be completed by the next call to `recv` or `stream`.
+    pub fn stream<'a, T>(&'a mut self) -> RecvStream<'a, T>
+    where
+        for<'de> T: FromBody<'de> + Send + 'a,
+    {
+        RecvStream::new(self)
+    }
+
     /// Set the link credit. This will stop draining if the link is in a draining cycle
     pub async fn set_credit(&mut self, credit: SequenceNo) -> Result<(), IllegalLinkStateError> {
         self.inner.set_credit(credit).await
     }
 
+    /// Add `credit` to the current link credit. This will stop draining if the link is in a
+    /// draining cycle
+    ///
+    /// Unlike [`set_credit`](#method.set_credit), this does not change the credit that
+    /// [`CreditMode::Auto`] or [`CreditMode::Threshold`] re-fills to.
+    pub async fn add_credit(&mut self, credit: SequenceNo) -> Result<(), IllegalLinkStateError> {
+        self.inner.add_credit(credit).await
+    }
+
     /// Drain the link.
     ///
     /// This will send a `Flow` performative with the `drain` field set to true.
-    /// Setting the credit will set the `drain` field to false and stop draining
+    /// Setting the credit will set the `drain` field to false and stop draining. See
+    /// [`drained`](#method.drained) for waiting until the remote sender has answered.
     pub async fn drain(&mut self) -> Result<(), IllegalLinkStateError> {
         self.inner.drain().await
     }
 
+    /// Returns a future that resolves once the remote sender has answered the last
+    /// [`drain`](#method.drain) by using up the link credit
+    ///
+    /// The deliveries that the sender has transferred before its answer can still be received,
+    /// and the link credit is down to the number of these deliveries once the future resolves.
+    /// The future resolves right away if the last drain has already been answered, and also
+    /// resolves if the link is dropped. It does not resolve if the credit is set again before
+    /// the sender answers. The returned future does not borrow the link and thus can be awaited
+    /// while receiving.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// receiver.drain().await.unwrap();
+    /// let drained = receiver.drained();
+    /// tokio::select! {
+    ///     _ = drained => {},
+    ///     delivery = receiver.recv::<String>() => { /* sent before the answer */ },
+    /// }
+    /// ```
+    pub fn drained(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.inner.link.flow_state().wait_drained()
+    }
+
+    /// Stops the remote sender without detaching the link
+    ///
+    /// This sends a `Flow` performative with a link credit of zero and stops [`CreditMode::Auto`]
+    /// from re-issuing credit. The deliveries that the sender has transferred before it sees the
+    /// `Flow` are still received, and the link credit doesn't go below zero when they arrive.
+    /// The link stays paused until [`resume`](#method.resume) or
+    /// [`set_credit`](#method.set_credit) is called.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// receiver.pause().await.unwrap();
+    /// // Deliveries that were in flight still arrive
+    /// let in_flight = Duration::from_millis(100);
+    /// while let Ok(delivery) = timeout(in_flight, receiver.recv::<String>()).await {
+    ///     receiver.accept(&delivery.unwrap()).await.unwrap();
+    /// }
+    /// receiver.resume(100).await.unwrap();
+    /// ```
+    pub async fn pause(&mut self) -> Result<(), IllegalLinkStateError> {
+        self.inner.pause().await
+    }
+
+    /// Re-issues `credit` after [`pause`](#method.pause)
+    ///
+    /// This is the same as [`set_credit`](#method.set_credit), which also sets the credit of
+    /// [`CreditMode::Auto`].
+    pub async fn resume(&mut self, credit: SequenceNo) -> Result<(), IllegalLinkStateError> {
+        self.inner.set_credit(credit).await
+    }
+
+    /// Set the credit mode and apply it immediately
+    ///
+    /// Switching to [`CreditMode::Auto`] issues its credit unless the link is paused, in which
+    /// case the credit is issued by [`resume`](#method.resume). Switching to
+    /// [`CreditMode::Manual`] leaves the current link credit to the remote sender.
+    pub async fn update_credit_mode(
+        &mut self,
+        credit_mode: CreditMode,
+    ) -> Result<(), IllegalLinkStateError> {
+        self.inner.update_credit_mode(credit_mode).await
+    }
+
+    /// Joins a [`CreditPool`], which then decides the link credit of the receiver in place of the
+    /// credit mode
+    ///
+    /// The `weight` is used by [`CreditPolicy::Weighted`] and [`CreditPolicy::Demand`]. Joining
+    /// rebalances the pool, which issues the share of this link. The receiver leaves the pool when
+    /// it is detached or when [`set_credit`](#method.set_credit), [`pause`](#method.pause),
+    /// [`resume`](#method.resume) or [`update_credit_mode`](#method.update_credit_mode) is
+    /// called.
+    ///
+    /// [`CreditPolicy::Weighted`]: crate::link::CreditPolicy::Weighted
+    /// [`CreditPolicy::Demand`]: crate::link::CreditPolicy::Demand
+    pub async fn join_credit_pool(
+        &mut self,
+        pool: &CreditPool,
+        weight: u32,
+    ) -> Result<(), IllegalLinkStateError> {
+        let output_handle = self
+            .inner
+            .link
+            .output_handle
+            .clone()
+            .ok_or(IllegalLinkStateError::IllegalState)?;
+        // Leave the previous pool before the new one is rebalanced
+        self.inner.credit_pool = None;
+        let member = pool.register(
+            self.name().to_string(),
+            weight,
+            self.inner.link.flow_state.clone(),
+            output_handle,
+            self.inner.outgoing.clone(),
+        );
+        self.inner.paused = false;
+        self.inner.credit_pool = Some(member);
+        pool.rebalance().await;
+        Ok(())
+    }
+
+    /// Leaves the [`CreditPool`] the receiver has joined. The link keeps its current link credit
+    /// until it is refilled by the credit mode
+    pub fn leave_credit_pool(&mut self) {
+        self.inner.credit_pool = None;
+    }
+
+    /// The link credit given to the receiver by the [`CreditPool`] it has joined
+    pub fn credit_pool_share(&self) -> Option<u32> {
+        self.inner.credit_pool.as_ref().map(|member| member.share())
+    }
+
+    /// Returns a future that resolves once the link has been detached or closed
+    ///
+    /// The future resolves when the exchange of Detach performatives completes, regardless of which
+    /// side initiated it, when the session ends, which implicitly detaches the link, or when the
+    /// link is dropped. The returned future does not borrow the link and thus can be awaited from
+    /// a different task.
+    pub fn detached(&self) -> impl Future<Output = ()> + Send + 'static {
+        let session_ended = self.inner.link.flow_state().remote_detach.session_ended();
+        self.inner
+            .link
+            .wait_for_local_state(session_ended, |state| {
+                matches!(state, LinkState::Detached | LinkState::Closed)
+            })
+    }
+
+    /// Returns a future that resolves once the link has been closed
+    ///
+    /// The future resolves when the exchange of closing Detach performatives completes, regardless
+    /// of which side initiated it, when the session ends, after which the link can no longer be
+    /// closed, or when the link is dropped. The returned future does not borrow the link and thus
+    /// can be awaited from a different task.
+    pub fn closed(&self) -> impl Future<Output = ()> + Send + 'static {
+        let session_ended = self.inner.link.flow_state().remote_detach.session_ended();
+        self.inner
+            .link
+            .wait_for_local_state(session_ended, |state| matches!(state, LinkState::Closed))
+    }
+
+    /// Returns a future that resolves with the error carried by the Detach of the remote peer
+    /// once the remote peer detaches or closes the link
+    ///
+    /// This does not borrow the link and resolves as soon as the session receives the Detach,
+    /// even if the link is not receiving. A `recv` that is waiting at the same time fails with
+    /// the same error. The future resolves with `None` if the link is detached or closed locally
+    /// first, if the session ends or if the link is dropped. See
+    /// [`Sender::on_detach_by_remote`](crate::Sender::on_detach_by_remote).
+    pub fn on_detach_by_remote(
+        &self,
+    ) -> impl Future<Output = Option<DetachError>> + Send + 'static {
+        self.inner.link.flow_state().remote_detach.wait()
+    }
+
     /// Detach the link.
     ///
     /// This will send a `Detach` performative with the `closed` field set to false. If the remote
     /// peer responds with a Detach performative whose `closed` field is set to true, the link will
     /// re-attach and then close by exchanging closing Detach performatives.
     pub async fn detach(mut self) -> Result<DetachedReceiver, (DetachedReceiver, DetachError)> {
+        self.inner.credit_pool = None;
+        self.inner.settle_unsettled_on_close().await;
         match self.inner.detach_with_error(None).await {
             Ok(_) => Ok(DetachedReceiver { inner: self.inner }),
             Err(err) => Err((DetachedReceiver { inner: self.inner }, err)),
@@ -330,6 +866,8 @@ impl Receiver {
         mut self,
         error: impl Into<definitions::Error>,
     ) -> Result<DetachedReceiver, (DetachedReceiver, DetachError)> {
+        self.inner.credit_pool = None;
+        self.inner.settle_unsettled_on_close().await;
         match self.inner.detach_with_error(Some(error.into())).await {
             Ok(_) => Ok(DetachedReceiver { inner: self.inner }),
             Err(err) => Err((DetachedReceiver { inner: self.inner }, err)),
@@ -358,6 +896,8 @@ impl Receiver {
         &mut self,
         new_session: &SessionHandle<R>,
     ) -> Result<ReceiverAttachExchange, DetachThenResumeReceiverError> {
+        self.inner.credit_pool = None;
+
         // detach the link
         let detach_result = self
             .inner
@@ -387,6 +927,8 @@ impl Receiver {
     ///
     /// This will send a Detach performative with the `closed` field set to true.
     pub async fn close(mut self) -> Result<(), DetachError> {
+        self.inner.credit_pool = None;
+        self.inner.settle_unsettled_on_close().await;
         self.inner.close_with_error(None).await
     }
 
@@ -399,13 +941,20 @@ impl Receiver {
     ) -> Result<(), DetachError> {
         // Stop link transfer before closing
         self.set_credit(0).await?;
+        self.inner.settle_unsettled_on_close().await;
         self.inner.close_with_error(Some(error.into())).await
     }
 
     /// Accept the message by sending a disposition with the `delivery_state` field set
     /// to `Accept`.
     ///
-    /// This will not send disposition if the delivery is not found in the local unsettled map.
+    /// The delivery can be given as a [`DeliveryInfo`] taken with [`Delivery::info`] or
+    /// [`Delivery::into_parts`], so the message can be consumed before the delivery is accepted.
+    /// See [`DispositionError`] for the deliveries that cannot be disposed.
+    ///
+    /// If the delivery is received in `ReceiverSettleMode::Second`, the disposition is sent
+    /// unsettled and this waits for the sender to settle the delivery. See
+    /// [`dispose`](Self::dispose) for details.
     ///
     /// # Example
     ///
@@ -426,8 +975,6 @@ impl Receiver {
     /// Accept the message by sending one or more disposition(s) with the `delivery_state` field set
     /// to `Accept`
     ///
-    /// Only deliveries that are found in the local unsettled map will be included in the disposition frame(s).
-    ///
     /// # Example
     ///
     /// The code of the example below can be found in the [GitHub repo](https://github.com/minghuaw/fe2o3-amqp/blob/main/examples/dispose_multiple/src/main.rs)
@@ -447,8 +994,6 @@ impl Receiver {
 
     /// Reject the message by sending a disposition with the `delivery_state` field set
     /// to `Reject`
-    ///
-    /// This will not send disposition if the delivery is not found in the local unsettled map.
     pub async fn reject(
         &self,
         delivery_info: impl Into<DeliveryInfo>,
@@ -462,8 +1007,6 @@ impl Receiver {
 
     /// Reject the message by sending one or more disposition(s) with the `delivery_state` field set
     /// to `Reject`
-    ///
-    /// Only deliveries that are found in the local unsettled map will be included in the disposition frame(s).
     pub async fn reject_all(
         &self,
         deliveries: impl IntoIterator<Item = impl Into<DeliveryInfo>>,
@@ -477,8 +1020,6 @@ impl Receiver {
 
     /// Release the message by sending a disposition with the `delivery_state` field set
     /// to `Release`
-    ///
-    /// This will not send disposition if the delivery is not found in the local unsettled map.
     pub async fn release(
         &self,
         delivery_info: impl Into<DeliveryInfo>,
@@ -489,8 +1030,6 @@ impl Receiver {
 
     /// Release the message by sending one or more disposition(s) with the `delivery_state` field set
     /// to `Release`
-    ///
-    /// Only deliveries that are found in the local unsettled map will be included in the disposition frame(s).
     pub async fn release_all(
         &self,
         deliveries: impl IntoIterator<Item = impl Into<DeliveryInfo>>,
@@ -502,7 +1041,10 @@ impl Receiver {
     /// Modify the message by sending a disposition with the `delivery_state` field set
     /// to `Modify`
     ///
-    /// This will not send disposition if the delivery is not found in the local unsettled map.
+    /// The `message_annotations` of the [`Modified`] outcome, such as a retry count, are meant to
+    /// be merged into the message when it is delivered again. A sender receives the whole outcome
+    /// in [`SendReceipt::Modified`](crate::SendReceipt::Modified) and can merge the annotations
+    /// with [`Message::apply_modified`](fe2o3_amqp_types::messaging::Message::apply_modified).
     pub async fn modify(
         &self,
         delivery_info: impl Into<DeliveryInfo>,
@@ -514,8 +1056,6 @@ impl Receiver {
 
     /// Modify the message by sending one or more disposition(s) with the `delivery_state` field set
     /// to `Modify`
-    ///
-    /// Only deliveries that are found in the local unsettled map will be included in the disposition frame(s).
     pub async fn modify_all(
         &self,
         deliveries: impl IntoIterator<Item = impl Into<DeliveryInfo>>,
@@ -527,7 +1067,19 @@ impl Receiver {
 
     /// Dispose the message by sending a disposition with the provided state
     ///
-    /// This will not send disposition if the delivery is not found in the local unsettled map.
+    /// # `ReceiverSettleMode::Second`
+    ///
+    /// If the delivery is received in `ReceiverSettleMode::Second`, the disposition is sent with
+    /// `settled` set to `false` and the delivery is kept in the local unsettled map until the
+    /// sender settles it. This waits for the settlement from the sender, and the credit used by
+    /// the delivery is only replenished afterwards. The same applies to the other methods that
+    /// dispose deliveries, including [`accept`](Self::accept) and
+    /// [`dispose_all`](Self::dispose_all).
+    ///
+    /// [`DispositionError::Detached`] is returned if the remote peer detaches the link before
+    /// settling the delivery, and [`DispositionError::SettlementTimeout`] is returned if the
+    /// timeout set by
+    /// [`settlement_timeout`](crate::link::builder::Builder::settlement_timeout) elapses first.
     pub async fn dispose(
         &self,
         delivery_info: impl Into<DeliveryInfo>,
@@ -538,8 +1090,6 @@ impl Receiver {
     }
 
     /// Dispose the message by sending one or more disposition(s) with the provided state
-    ///
-    /// Only deliveries that are found in the local unsettled map will be included in the disposition frame(s).
     pub async fn dispose_all(
         &self,
         deliveries: impl IntoIterator<Item = impl Into<DeliveryInfo>>,
@@ -624,8 +1174,20 @@ pub(crate) struct ReceiverInner<L: endpoint::ReceiverLink> {
     pub(crate) buffer_size: usize,
     pub(crate) credit_mode: CreditMode,
     pub(crate) processed: AtomicU32, // SequenceNo,
+
+    // The link credit issued by the last flow, which decides when `CreditMode::Auto` refills
+    pub(crate) granted: AtomicU32,
+
+    // Whether the credit is withdrawn by `pause` and not re-issued by the credit mode
+    pub(crate) paused: bool,
     pub(crate) auto_accept: bool,
 
+    // Whether the encoded message and its sections are kept in the delivery
+    pub(crate) index_sections: bool,
+
+    // The pool that gives the link credit refilled in place of the credit mode
+    pub(crate) credit_pool: Option<CreditPoolMember>,
+
     // Control sender to the session
     pub(crate) session: mpsc::Sender<SessionControl>,
 
@@ -635,17 +1197,48 @@ pub(crate) struct ReceiverInner<L: endpoint::ReceiverLink> {
 
     // Wrap in a box to avoid clippy warning large_enum_variant on link acceptor's output
     pub(crate) incomplete_transfer: Option<Box<IncompleteTransfer>>,
+
+    // Outcomes of the recently disposed deliveries. This is kept across detach and resume
+    pub(crate) dedup_window: Option<DedupWindow>,
+
+    // Number of deliveries that are aborted by the sender and discarded
+    pub(crate) aborted_deliveries: u64,
+
+    // Deliveries disposed in `ReceiverSettleMode::Second` that wait for the sender to settle
+    pub(crate) remote_settlements: ArcRemoteSettlements,
+
+    // What happens to the unsettled deliveries when the link is closed, detached or dropped
+    pub(crate) on_close_unsettled: OnCloseUnsettled,
+
+    // Deliveries yielded unsettled to the application, which are only tracked if
+    // `on_close_unsettled` is not `Leave`. The ones settled since are removed lazily
+    pub(crate) unsettled_infos: Vec<DeliveryInfo>,
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) settlement_timeout: Option<Duration>,
+
+    // How long `recv` waits for a delivery while credit is issued before a warning is logged
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) warn_after_no_delivery: Option<Duration>,
 }
 
 impl<L: endpoint::ReceiverLink> Drop for ReceiverInner<L> {
     fn drop(&mut self) {
         if let Some(handle) = self.link.output_handle_mut().take() {
+            let infos = std::mem::take(&mut self.unsettled_infos);
+            for disposition in self.link.settle_on_close(infos, self.on_close_unsettled) {
+                let _ = self.outgoing.try_send(LinkFrame::Disposition(disposition));
+            }
             let detach = Detach {
                 handle: handle.into(),
                 closed: true,
                 error: None,
             };
             let _ = self.outgoing.try_send(LinkFrame::Detach(detach));
+        } else {
+            // The link is detached and will not be resumed, so its name can be taken by a new link
+            let name = self.link.name().to_string();
+            let _ = self.session.try_send(SessionControl::ReleaseLinkName(name));
         }
     }
 }
@@ -680,8 +1273,17 @@ where
         LinkRelay::Receiver {
             tx,
             output_handle: (),
-            flow_state: self.link.flow_state().clone(),
+            flow_state: {
+                // The new relay is created when the link is attached again
+                self.link.flow_state().remote_detach.on_attached();
+                self.link.flow_state().clone()
+            },
             unsettled: self.link.unsettled().clone(),
+            remote_settlements: {
+                // The new relay is created when the link is attached again
+                self.remote_settlements.on_attached();
+                self.remote_settlements.clone()
+            },
             receiver_settle_mode: self.link.rcv_settle_mode().clone(),
             // This only controls whether a multi-transfer delivery id
             // will be added to sessions map
@@ -758,7 +1360,7 @@ where
     L: endpoint::ReceiverLink<
             FlowError = IllegalLinkStateError,
             TransferError = ReceiverTransferError,
-            DispositionError = IllegalLinkStateError,
+            DispositionError = DispositionError,
             AttachError = ReceiverAttachError,
             DetachError = DetachError,
         > + LinkExt<FlowState = ReceiverFlowState, Unsettled = ArcReceiverUnsettledMap>
@@ -770,6 +1372,60 @@ where
     where
         for<'de> T: FromBody<'de> + Send,
     {
+        self.recv_delivery().await
+    }
+
+    /// Receives the next delivery without decoding its payload
+    pub(crate) async fn recv_raw(&mut self) -> Result<RawDelivery, RecvError> {
+        self.recv_delivery().await
+    }
+
+    cfg_not_wasm32! {
+        pub(crate) async fn recv_batch<T>(
+            &mut self,
+            max: usize,
+            max_wait: Duration,
+        ) -> Result<Vec<Delivery<T>>, RecvError>
+        where
+            for<'de> T: FromBody<'de> + Send,
+        {
+            let mut deliveries = Vec::with_capacity(max);
+            if max == 0 {
+                return Ok(deliveries);
+            }
+            let credit = SequenceNo::try_from(max).unwrap_or(SequenceNo::MAX);
+            self.raise_credit_if_auto(credit).await?;
+
+            deliveries.push(self.recv_delivery().await?);
+            let deadline = Instant::now() + max_wait;
+            while deliveries.len() < max {
+                // Only taking the frame is raced against the deadline so that a frame that has
+                // been taken is always handled
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                let frame = match timeout(remaining, self.incoming.recv()).await {
+                    Ok(frame) => frame.ok_or(LinkStateError::IllegalSessionState)?,
+                    Err(_) => break,
+                };
+                if let Some(delivery) = self.on_incoming_frame(frame).await? {
+                    deliveries.push(delivery);
+                }
+            }
+            Ok(deliveries)
+        }
+    }
+
+    async fn recv_delivery<D>(&mut self) -> Result<D, RecvError>
+    where
+        D: FromPayload + Send,
+    {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(warn_after) = self.warn_after_no_delivery {
+            let frame = self.recv_frame_or_warn(warn_after).await?;
+            if let Some(delivery) = self.on_incoming_frame(frame).await? {
+                return Ok(delivery);
+            }
+        }
+
         loop {
             match self.recv_inner().await? // FIXME: cancel safe? if oneshot channel is cancel safe
             {
@@ -779,22 +1435,75 @@ where
         }
     }
 
+    cfg_not_wasm32! {
+        /// Takes the next frame from the incoming channel. A warning is logged if no frame is
+        /// taken within `warn_after` while link credit is issued
+        ///
+        /// # Cancel safety
+        ///
+        /// This is cancel safe because only taking the frame is awaited
+        async fn recv_frame_or_warn(
+            &mut self,
+            warn_after: Duration,
+        ) -> Result<LinkFrame, RecvError> {
+            let frame = match timeout(warn_after, self.incoming.recv()).await {
+                Ok(frame) => frame,
+                Err(_) => {
+                    let link_credit = self.link.flow_state().link_credit();
+                    if link_credit > 0 {
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!(
+                            "No delivery has arrived for {:?} with {} link credit issued",
+                            warn_after,
+                            link_credit
+                        );
+                        #[cfg(feature = "log")]
+                        log::warn!(
+                            "No delivery has arrived for {:?} with {} link credit issued",
+                            warn_after,
+                            link_credit
+                        );
+                    }
+                    self.incoming.recv().await // cancel safe
+                }
+            };
+            frame.ok_or_else(|| LinkStateError::IllegalSessionState.into())
+        }
+    }
+
     /// # Cancel safety
     ///
     /// This should be cancel safe if oneshot channel is cancel safe
     #[inline]
-    pub(crate) async fn recv_inner<T>(&mut self) -> Result<Option<Delivery<T>>, RecvError>
+    pub(crate) async fn recv_inner<D>(&mut self) -> Result<Option<D>, RecvError>
     where
-        for<'de> T: FromBody<'de> + Send,
+        D: FromPayload + Send,
     {
         let frame = self
             .incoming
             .recv()
             .await // cancel safe
             .ok_or(LinkStateError::IllegalSessionState)?;
+        self.on_incoming_frame(frame).await
+    }
 
+    /// Handles a frame taken from the incoming channel. Partial deliveries are kept in
+    /// `incomplete_transfer`, so this can be driven separately from taking the frame.
+    ///
+    /// # Cancel safety
+    ///
+    /// This should be cancel safe if oneshot channel is cancel safe
+    pub(crate) async fn on_incoming_frame<D>(
+        &mut self,
+        frame: LinkFrame,
+    ) -> Result<Option<D>, RecvError>
+    where
+        D: FromPayload + Send,
+    {
         match frame {
             LinkFrame::Detach(detach) => {
+                // A detached link leaves the pool and gives its share to the other links
+                self.credit_pool = None;
                 let closed = detach.closed;
                 self.link.send_detach(&self.outgoing, closed, None).await?; // cancel safe
                 self.link
@@ -809,7 +1518,17 @@ where
                 input_handle: _,
                 performative,
                 payload,
-            } => self.on_incoming_transfer(performative, payload).await, // cancel safe
+                incoming_permit,
+            } => {
+                let result = self.on_incoming_transfer(performative, payload).await; // cancel safe
+                // The payload is no longer buffered by the session
+                drop(incoming_permit);
+                result
+            }
+            LinkFrame::SessionEnded(error) => {
+                self.link.on_session_ended();
+                Err(LinkStateError::SessionEnded(error).into())
+            }
             LinkFrame::Attach(_) => Err(LinkStateError::IllegalState.into()),
             LinkFrame::Flow(_) | LinkFrame::Disposition(_) => {
                 // Flow and Disposition are handled by LinkRelay which runs
@@ -892,13 +1611,13 @@ where
     /// # Cancel safety
     ///
     /// This is cancel safe because all internal `.await` point(s) are cancel safe
-    async fn on_resuming_transfer<T>(
+    async fn on_resuming_transfer<D>(
         &mut self,
         transfer: Transfer,
         payload: Payload,
-    ) -> Result<Option<Delivery<T>>, RecvError>
+    ) -> Result<Option<D>, RecvError>
     where
-        for<'de> T: FromBody<'de> + Send,
+        D: FromPayload + Send,
     {
         // need to check whether the incoming transfer matches
         match (
@@ -912,16 +1631,13 @@ where
                 if remote != local {
                     let (section_number, section_offset) =
                         count_number_of_sections_and_offset(&payload);
-                    let delivery = self.link.on_complete_transfer(
-                        transfer,
-                        payload,
-                        section_number,
-                        section_offset,
-                    )?;
+                    let delivery: D =
+                        self.complete_delivery(transfer, payload, section_number, section_offset)?;
 
                     // Auto accept the message and leave settled to be determined based on rcv_settle_mode
                     if self.auto_accept {
-                        self.dispose(&delivery, None, Accepted {}.into()).await?;
+                        let info = delivery.delivery_info();
+                        self.dispose(info, None, Accepted {}.into()).await?;
                         // cancel safe
                     }
 
@@ -938,23 +1654,63 @@ where
         }
     }
 
-    /// # Cancel safety
-    ///
-    /// This is cancel safe because all internal `.await` point(s) are cancel safe
-    async fn on_complete_transfer<T>(
+    /// Builds the delivery from the complete payload and keeps the encoded message with its
+    /// sections if `index_sections` is set
+    fn complete_delivery<D, P>(
         &mut self,
         transfer: Transfer,
-        payload: Payload,
-    ) -> Result<Option<Delivery<T>>, RecvError>
+        payload: P,
+        section_number: u32,
+        section_offset: u64,
+    ) -> Result<D, RecvError>
     where
-        for<'de> T: FromBody<'de> + Send,
+        D: FromPayload + Send,
+        for<'b> P: IntoReader + IntoPayload + AsByteIterator<'b> + Send,
     {
-        let delivery = match self.incomplete_transfer.take() {
-            Some(mut incomplete) => {
-                incomplete.or_assign(transfer)?;
+        if !self.index_sections {
+            let delivery = self.link.on_complete_transfer(
+                transfer,
+                payload,
+                section_number,
+                section_offset,
+            )?;
+            return Ok(delivery);
+        }
+
+        let payload = payload.into_payload();
+        let delivery: D = self.link.on_complete_transfer(
+            transfer,
+            payload.clone(),
+            section_number,
+            section_offset,
+        )?;
+        match sections::sections(&payload) {
+            Ok(sections) => Ok(delivery.with_sections(payload, sections)),
+            Err(source) => Err(MessageDecodeError {
+                info: delivery.delivery_info(),
+                source,
+            }
+            .into()),
+        }
+    }
+
+    /// # Cancel safety
+    ///
+    /// This is cancel safe because all internal `.await` point(s) are cancel safe
+    async fn on_complete_transfer<D>(
+        &mut self,
+        transfer: Transfer,
+        payload: Payload,
+    ) -> Result<Option<D>, RecvError>
+    where
+        D: FromPayload + Send,
+    {
+        let delivery: D = match self.incomplete_transfer.take() {
+            Some(mut incomplete) => {
+                incomplete.or_assign(transfer)?;
                 incomplete.append(payload); // This also computes the section number and offset incrementally
 
-                self.link.on_complete_transfer(
+                self.complete_delivery(
                     incomplete.performative,
                     incomplete.buffer,
                     incomplete.section_number.unwrap_or(0),
@@ -964,36 +1720,80 @@ where
             None => {
                 let (section_number, section_offset) =
                     count_number_of_sections_and_offset(&payload);
-                self.link
-                    .on_complete_transfer(transfer, payload, section_number, section_offset)?
+                self.complete_delivery(transfer, payload, section_number, section_offset)?
             }
         };
 
         // Auto accept the message and leave settled to be determined based on rcv_settle_mode
         if self.auto_accept {
-            self.dispose(&delivery, None, Accepted {}.into()).await?; // cancel safe
+            // Not holding a reference to the delivery across the `.await` so that the future
+            // doesn't require the message to be `Sync`
+            let info = delivery.delivery_info();
+            self.dispose(info, None, Accepted {}.into()).await?; // cancel safe
+        } else if !matches!(self.on_close_unsettled, OnCloseUnsettled::Leave) {
+            self.track_unsettled(delivery.delivery_info());
         }
 
         Ok(Some(delivery))
     }
 
+    /// Keeps the delivery to settle it when the link is closed. The deliveries that are settled
+    /// since are removed whenever the buffer is full, so that it only grows with the number of
+    /// unsettled deliveries
+    fn track_unsettled(&mut self, info: DeliveryInfo) {
+        if info.settled {
+            return;
+        }
+        if self.unsettled_infos.len() == self.unsettled_infos.capacity() {
+            let guard = self.link.unsettled().read();
+            self.unsettled_infos.retain(|info| {
+                guard
+                    .as_ref()
+                    .is_some_and(|map| map.contains_key(&info.delivery_tag))
+            });
+        }
+        self.unsettled_infos.push(info);
+    }
+
+    /// Settles the unsettled deliveries according to `on_close_unsettled`. This is called before
+    /// the Detach frame is sent
+    ///
+    /// # Cancel safety
+    ///
+    /// The deliveries are removed from the unsettled map before the dispositions are sent, so a
+    /// disposition that is not sent when this is cancelled is lost
+    pub(crate) async fn settle_unsettled_on_close(&mut self) {
+        let infos = std::mem::take(&mut self.unsettled_infos);
+        for disposition in self.link.settle_on_close(infos, self.on_close_unsettled) {
+            // The Detach that follows fails as well if the session is gone
+            let _ = self
+                .outgoing
+                .send(LinkFrame::Disposition(disposition))
+                .await;
+        }
+    }
+
     /// # Cancel safety
     ///
     /// This is cancel safe because all internal `.await` point(s) are cancel safe
     #[inline]
-    async fn on_incoming_transfer<T>(
+    async fn on_incoming_transfer<D>(
         &mut self,
         transfer: Transfer,
         payload: Payload,
-    ) -> Result<Option<Delivery<T>>, RecvError>
+    ) -> Result<Option<D>, RecvError>
     where
-        for<'de> T: FromBody<'de> + Send,
+        D: FromPayload + Send,
     {
         // Aborted messages SHOULD be discarded by the recipient (any payload
         // within the frame carrying the performative MUST be ignored). An aborted
         // message is implicitly settled
         if transfer.aborted {
-            let _ = self.incomplete_transfer.take();
+            self.on_aborted_transfer(&transfer).await?; // cancel safe
+            return Ok(None);
+        }
+
+        if self.on_duplicated_transfer(&transfer).await? {
             return Ok(None);
         }
 
@@ -1020,6 +1820,108 @@ where
         }
     }
 
+    /// Discards the partial delivery of an aborted transfer. The aborted delivery is implicitly
+    /// settled and thus not disposed, but it still takes up one link credit, which is refilled
+    /// the same way as for a disposed delivery.
+    ///
+    /// # Cancel safety
+    ///
+    /// This is cancel safe because all internal `.await` point(s) are cancel safe
+    async fn on_aborted_transfer(&mut self, transfer: &Transfer) -> Result<(), RecvError> {
+        // The remaining transfers of a suppressed duplicate are already accounted for
+        if let Some(window) = &mut self.dedup_window {
+            if window.discarding {
+                window.discarding = false;
+                return Ok(());
+            }
+        }
+
+        let delivery_tag = match self.incomplete_transfer.take() {
+            Some(incomplete) => incomplete.performative.delivery_tag,
+            // Only the first transfer of a delivery carries the delivery tag, so an aborted
+            // transfer without one has no delivery to discard
+            None => match &transfer.delivery_tag {
+                Some(delivery_tag) => Some(delivery_tag.clone()),
+                None => return Ok(()),
+            },
+        };
+        self.aborted_deliveries += 1;
+
+        self.link.flow_state().consume(1)?;
+        if let Some(delivery_tag) = delivery_tag {
+            if let Some(map) = self.link.unsettled().write().as_mut() {
+                map.swap_remove(&delivery_tag);
+            }
+        }
+
+        let prev = self.processed.fetch_add(1, Ordering::Release);
+        self.update_credit_if_auto(prev + 1).await?; // cancel safe
+        Ok(())
+    }
+
+    /// Settles a resent delivery with the outcome recorded in the dedup window. Returns `true` if
+    /// the transfer belongs to a duplicated delivery and should not be yielded.
+    ///
+    /// # Cancel safety
+    ///
+    /// This is cancel safe because all internal `.await` point(s) are cancel safe
+    async fn on_duplicated_transfer(&mut self, transfer: &Transfer) -> Result<bool, RecvError> {
+        let window = match &mut self.dedup_window {
+            Some(window) => window,
+            None => return Ok(false),
+        };
+
+        // The remaining transfers of a duplicated multi-transfer delivery
+        if window.discarding {
+            window.discarding = transfer.more;
+            return Ok(true);
+        }
+
+        let delivery_tag = match &transfer.delivery_tag {
+            Some(delivery_tag) => delivery_tag,
+            None => return Ok(false),
+        };
+        if let Some(incomplete) = &self.incomplete_transfer {
+            if incomplete.performative.delivery_tag.as_ref() == Some(delivery_tag) {
+                return Ok(false);
+            }
+        }
+        let state = match window.outcome(delivery_tag) {
+            Some(state) => state,
+            None => return Ok(false),
+        };
+        window.on_suppressed();
+        window.discarding = transfer.more;
+
+        // The resent delivery still takes up one link credit
+        self.link.flow_state().consume(1)?;
+        if let Some(map) = self.link.unsettled().write().as_mut() {
+            map.swap_remove(delivery_tag);
+        }
+
+        if !transfer.settled.unwrap_or(false) {
+            let delivery_id = transfer
+                .delivery_id
+                .ok_or(ReceiverTransferError::DeliveryIdIsNone)?;
+            let disposition = Disposition {
+                role: Role::Receiver,
+                first: delivery_id,
+                last: None,
+                settled: true,
+                state: Some(state),
+                batchable: false,
+            };
+            self.outgoing
+                .send(LinkFrame::Disposition(disposition))
+                .await // cancel safe
+                .map_err(|_| LinkStateError::IllegalSessionState)?;
+        }
+
+        let prev = self.processed.fetch_add(1, Ordering::Release);
+        self.update_credit_if_auto(prev + 1).await?; // cancel safe
+        Ok(true)
+    }
+
     /// Set the link credit. This will stop draining if the link is in a draining cycle
     ///
     /// # Cancel safety
@@ -1028,8 +1930,16 @@ where
     #[inline]
     pub async fn set_credit(&mut self, credit: SequenceNo) -> Result<(), IllegalLinkStateError> {
         self.processed = AtomicU32::new(0);
-        if let CreditMode::Auto(_) = self.credit_mode {
-            self.credit_mode = CreditMode::Auto(credit)
+        self.granted = AtomicU32::new(credit);
+        self.paused = false;
+        self.credit_pool = None;
+        match &mut self.credit_mode {
+            CreditMode::Auto(auto_credit)
+            | CreditMode::Threshold {
+                credit: auto_credit,
+                ..
+            } => *auto_credit = credit,
+            CreditMode::Manual => {}
         }
 
         self.link
@@ -1037,6 +1947,25 @@ where
             .await // cancel safe
     }
 
+    /// Add to the link credit without changing the credit mode. This will stop draining if the
+    /// link is in a draining cycle
+    ///
+    /// # Cancel safety
+    ///
+    /// This is cancel safe as internanlly it only `.await` on sending over `tokio::mpsc::Sender`
+    #[inline]
+    pub async fn add_credit(&mut self, credit: SequenceNo) -> Result<(), IllegalLinkStateError> {
+        let credit = self.link.flow_state().link_credit().saturating_add(credit);
+        self.processed = AtomicU32::new(0);
+        self.granted = AtomicU32::new(credit);
+        self.paused = false;
+        self.credit_pool = None;
+
+        self.link
+            .send_flow(&self.outgoing, Some(credit), Some(false), false)
+            .await // cancel safe
+    }
+
     /// This is cancel safe because all internal `.await` points are cancel safe
     #[inline]
     pub(crate) async fn dispose(
@@ -1046,9 +1975,26 @@ where
         state: DeliveryState,
     ) -> Result<(), DispositionError> {
         let delivery_info = delivery_info.into();
-        self.link
-            .dispose(&self.outgoing, delivery_info, settled, state, false)
-            .await?; // cancel safe
+        let settlements = self.register_remote_settlements(
+            std::slice::from_ref(&delivery_info),
+            settled,
+            &state,
+        )?;
+        let delivery_tag = delivery_info.delivery_tag.clone();
+        let result = self
+            .link
+            .dispose(&self.outgoing, delivery_info, settled, state.clone(), false)
+            .await; // cancel safe
+        if let Err(error) = result {
+            self.deregister_remote_settlements(&settlements);
+            return Err(error);
+        }
+        if let Some(window) = &self.dedup_window {
+            if state.is_terminal() {
+                window.record(delivery_tag, state);
+            }
+        }
+        self.wait_remote_settlements(settlements).await?;
 
         let prev = self.processed.fetch_add(1, Ordering::Release);
         self.update_credit_if_auto(prev + 1).await?; // cancel safe
@@ -1064,36 +2010,192 @@ where
         state: DeliveryState,
     ) -> Result<(), DispositionError> {
         let total = delivery_infos.len() as u32;
-        self.link
-            .dispose_all(&self.outgoing, delivery_infos, settled, state, false)
-            .await?; // cancel safe
+        let settlements = self.register_remote_settlements(&delivery_infos, settled, &state)?;
+        let delivery_tags: Vec<DeliveryTag> = match &self.dedup_window {
+            Some(_) if state.is_terminal() => delivery_infos
+                .iter()
+                .map(|info| info.delivery_tag.clone())
+                .collect(),
+            _ => Vec::new(),
+        };
+        let result = self
+            .link
+            .dispose_all(
+                &self.outgoing,
+                delivery_infos,
+                settled,
+                state.clone(),
+                false,
+            )
+            .await; // cancel safe
+        if let Err(error) = result {
+            self.deregister_remote_settlements(&settlements);
+            return Err(error);
+        }
+        if let Some(window) = &self.dedup_window {
+            for delivery_tag in delivery_tags {
+                window.record(delivery_tag, state.clone());
+            }
+        }
+        self.wait_remote_settlements(settlements).await?;
 
         let prev = self.processed.fetch_add(total, Ordering::Release);
         self.update_credit_if_auto(prev + total).await?; // cancel safe
         Ok(())
     }
 
+    /// Registers the deliveries that will wait for the sender to settle after the disposition is
+    /// sent, which are the unsettled deliveries given a terminal state in
+    /// `ReceiverSettleMode::Second`
+    fn register_remote_settlements(
+        &self,
+        delivery_infos: &[DeliveryInfo],
+        settled: Option<bool>,
+        state: &DeliveryState,
+    ) -> Result<Vec<(DeliveryTag, oneshot::Receiver<()>)>, DispositionError> {
+        if settled == Some(true) || !state.is_terminal() {
+            return Ok(Vec::new());
+        }
+
+        let guard = self.link.unsettled().read();
+        let mut settlements = Vec::new();
+        for info in delivery_infos {
+            let mode = info
+                .rcv_settle_mode
+                .as_ref()
+                .unwrap_or_else(|| self.link.rcv_settle_mode());
+            let is_unsettled = guard
+                .as_ref()
+                .is_some_and(|map| map.contains_key(&info.delivery_tag));
+            if !matches!(mode, ReceiverSettleMode::Second) || !is_unsettled {
+                continue;
+            }
+
+            match self.remote_settlements.register(info.delivery_tag.clone()) {
+                Some(rx) => settlements.push((info.delivery_tag.clone(), rx)),
+                None => {
+                    self.deregister_remote_settlements(&settlements);
+                    return Err(DispositionError::Detached);
+                }
+            }
+        }
+        Ok(settlements)
+    }
+
+    fn deregister_remote_settlements(&self, settlements: &[(DeliveryTag, oneshot::Receiver<()>)]) {
+        for (delivery_tag, _) in settlements {
+            self.remote_settlements.deregister(delivery_tag);
+        }
+    }
+
+    /// Waits for the sender to settle the deliveries that are disposed in
+    /// `ReceiverSettleMode::Second`
+    async fn wait_remote_settlements(
+        &self,
+        settlements: Vec<(DeliveryTag, oneshot::Receiver<()>)>,
+    ) -> Result<(), DispositionError> {
+        if settlements.is_empty() {
+            return Ok(());
+        }
+
+        let delivery_tags: Vec<DeliveryTag> =
+            settlements.iter().map(|(tag, _)| tag.clone()).collect();
+        let settled = async {
+            let receivers = settlements.into_iter().map(|(_, rx)| rx);
+            tokio::select! {
+                // The waiting deliveries are dropped if the remote peer detaches the link
+                result = futures_util::future::try_join_all(receivers) => {
+                    result.map(|_| ()).map_err(|_| DispositionError::Detached)
+                }
+                _ = self.outgoing.closed() => Err(DispositionError::IllegalSessionState),
+            }
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let result = match self.settlement_timeout {
+            Some(duration) => timeout(duration, settled)
+                .await
+                .unwrap_or(Err(DispositionError::SettlementTimeout)),
+            None => settled.await,
+        };
+        #[cfg(target_arch = "wasm32")]
+        let result = settled.await;
+
+        if result.is_err() {
+            for delivery_tag in &delivery_tags {
+                self.remote_settlements.deregister(delivery_tag);
+            }
+        }
+        result
+    }
+
+    /// Raises the link credit to `credit` without changing the credit mode if the credit mode is
+    /// auto and fewer credits are currently issued
+    async fn raise_credit_if_auto(&self, credit: SequenceNo) -> Result<(), IllegalLinkStateError> {
+        if self.paused {
+            return Ok(());
+        }
+        if self.credit_mode.refill_credit().is_some()
+            && self.link.flow_state().link_credit() < credit
+        {
+            self.granted.store(credit, Ordering::Release);
+            self.link
+                .send_flow(&self.outgoing, Some(credit), Some(false), false)
+                .await?; // cancel safe
+        }
+        Ok(())
+    }
+
     /// This is cancel safe because it only `.await` on a cancel safe future
     #[inline]
     async fn update_credit_if_auto(&self, processed: u32) -> Result<(), DispositionError> {
-        if let CreditMode::Auto(max_credit) = self.credit_mode {
-            if processed >= max_credit / 2 {
+        if self.paused {
+            return Ok(());
+        }
+        if let Some(member) = &self.credit_pool {
+            // The share already accounts for what the sender reports as available
+            let share = member.share();
+            let granted = self.granted.load(Ordering::Acquire).min(share);
+            if share > 0 && processed >= granted / 2 {
+                self.processed.swap(0, Ordering::Release);
+                self.granted.store(share, Ordering::Release);
+                self.link
+                    .send_flow(&self.outgoing, Some(share), Some(false), false)
+                    .await?; // cancel safe
+            }
+            return Ok(());
+        }
+        if let Some(max_credit) = self.credit_mode.refill_credit() {
+            let granted = self.granted.load(Ordering::Acquire).min(max_credit);
+            if self.credit_mode.should_refill(processed, granted) {
                 // Reset link credit
                 self.processed.swap(0, Ordering::Release);
+                let credit = self.auto_credit(max_credit);
+                self.granted.store(credit, Ordering::Release);
                 self.link
-                    .send_flow(&self.outgoing, Some(max_credit), Some(false), false)
+                    .send_flow(&self.outgoing, Some(credit), Some(false), false)
                     .await?; // cancel safe
             }
         }
         Ok(())
     }
 
+    /// The credit to refill in `CreditMode::Auto`, which is no more than what the sender
+    /// reports as available. A sender that reports nothing available gets `max_credit` so that
+    /// messages arriving at the sender later do not wait for another flow
+    fn auto_credit(&self, max_credit: u32) -> u32 {
+        match self.link.flow_state().available() {
+            Some(available) if available > 0 => available.min(max_credit),
+            _ => max_credit,
+        }
+    }
+
     /// Drain the link.
     ///
     /// This will send a `Flow` performative with the `drain` field set to true.
     /// Setting the credit will set the `drain` field to false and stop draining
     #[inline]
-    pub async fn drain(&mut self) -> Result<(), DispositionError> {
+    pub async fn drain(&mut self) -> Result<(), IllegalLinkStateError> {
         self.processed = AtomicU32::new(0);
 
         // Return if already draining
@@ -1106,6 +2208,35 @@ where
             .send_flow(&self.outgoing, None, Some(true), false)
             .await
     }
+
+    /// Withdraw the link credit until it is set again
+    ///
+    /// # Cancel safety
+    ///
+    /// This is cancel safe as internanlly it only `.await` on sending over `tokio::mpsc::Sender`
+    #[inline]
+    pub async fn pause(&mut self) -> Result<(), IllegalLinkStateError> {
+        self.processed = AtomicU32::new(0);
+        self.paused = true;
+        self.credit_pool = None;
+        self.link
+            .send_flow(&self.outgoing, Some(0), Some(false), false)
+            .await // cancel safe
+    }
+
+    /// Set the credit mode and issue the credit of `CreditMode::Auto` unless the link is paused
+    #[inline]
+    pub async fn update_credit_mode(
+        &mut self,
+        credit_mode: CreditMode,
+    ) -> Result<(), IllegalLinkStateError> {
+        self.credit_mode = credit_mode;
+        self.credit_pool = None;
+        match self.credit_mode.refill_credit() {
+            Some(credit) if !self.paused => self.set_credit(credit).await,
+            _ => Ok(()),
+        }
+    }
 }
 
 impl ReceiverInner<ReceiverLink<Target>> {
@@ -1114,7 +2245,13 @@ impl ReceiverInner<ReceiverLink<Target>> {
         mut initial_remote_attach: Option<Attach>,
         is_reattaching: bool,
     ) -> Result<ReceiverAttachExchange, ReceiverResumeErrorKind> {
-        self.reallocate_output_handle().await?;
+        match &initial_remote_attach {
+            Some(remote_attach) => {
+                let input_handle = InputHandle::from(remote_attach.handle.clone());
+                self.reallocate_incoming_output_handle(input_handle).await?
+            }
+            None => self.reallocate_output_handle().await?,
+        };
 
         let exchange = match initial_remote_attach.take() {
             Some(remote_attach) => {
@@ -1254,6 +2391,11 @@ impl From<ResumingReceiver> for Receiver {
 }
 
 impl DetachedReceiver {
+    /// Get the name of the link
+    pub fn name(&self) -> &str {
+        self.inner.link.name()
+    }
+
     /// Get a reference to the link's source field
     pub fn source(&self) -> &Option<Source> {
         &self.inner.link.source
@@ -1312,7 +2454,7 @@ impl DetachedReceiver {
         ) -> Result<ResumingReceiver, ReceiverResumeError> {
             let fut = self.inner.resume_incoming_attach(None, is_reattaching);
 
-            match tokio::time::timeout(duration, fut).await {
+            match timeout(duration, fut).await {
                 Ok(Ok(exchange)) => {
                     let receiver = Receiver { inner: self.inner };
                     let resuming_receiver = match exchange {
@@ -1452,7 +2594,7 @@ impl DetachedReceiver {
         ) -> Result<ResumingReceiver, ReceiverResumeError> {
             let fut = self.inner.resume_incoming_attach(Some(remote_attach), false);
 
-            match tokio::time::timeout(duration, fut).await {
+            match timeout(duration, fut).await {
                 Ok(Ok(exchange)) => {
                     let receiver = Receiver { inner: self.inner };
                     let resuming_receiver = match exchange {
@@ -1495,7 +2637,7 @@ impl DetachedReceiver {
 
             let fut = self.inner.resume_incoming_attach(Some(remote_attach), is_reattaching);
 
-            match tokio::time::timeout(duration, fut).await {
+            match timeout(duration, fut).await {
                 Ok(Ok(exchange)) => {
                     let receiver = Receiver { inner: self.inner };
                     let resuming_receiver = match exchange {
@@ -1522,3 +2664,674 @@ impl DetachedReceiver {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicU32, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use fe2o3_amqp_types::{
+        definitions::{DeliveryTag, ReceiverSettleMode, Role},
+        messaging::{
+            message::{__private::Serializable, sections::SectionKind},
+            Accepted, ApplicationProperties, DeliveryState, Message, Modified, Rejected, Released,
+            Source, Target,
+        },
+        performatives::Transfer,
+        primitives::Symbol,
+    };
+    use parking_lot::RwLock;
+    use tokio::sync::mpsc;
+
+    use crate::{
+        endpoint::{InputHandle, LinkFlow, OutputHandle},
+        link::{
+            dedup_window::DedupWindow,
+            state::{LinkFlowState, LinkFlowStateInner, LinkState},
+            DispositionError, LinkFrame, ReceiverLink,
+        },
+        Payload,
+    };
+
+    use super::{CreditMode, OnCloseUnsettled, Receiver, ReceiverInner};
+
+    fn receiver_inner(
+        capacity: usize,
+    ) -> (
+        ReceiverInner<ReceiverLink<Target>>,
+        mpsc::Sender<LinkFrame>,
+        mpsc::Receiver<LinkFrame>,
+    ) {
+        let flow_state = Arc::new(LinkFlowState::receiver(LinkFlowStateInner {
+            initial_delivery_count: 0.into(),
+            delivery_count: 0.into(),
+            link_credit: 100,
+            available: 0,
+            drain: false,
+            properties: None,
+        }));
+        let mut link = Receiver::builder()
+            .name("dedup-receiver")
+            .source("q1")
+            .create_link(Arc::new(RwLock::new(None)), OutputHandle(0), flow_state);
+        link.local_state = LinkState::Attached;
+
+        let (outgoing_tx, outgoing_rx) = mpsc::channel(16);
+        let (incoming_tx, incoming_rx) = mpsc::channel(16);
+        let (session_tx, _) = mpsc::channel(16);
+        let inner = ReceiverInner {
+            link,
+            buffer_size: 16,
+            credit_mode: CreditMode::Manual,
+            processed: AtomicU32::new(0),
+            granted: AtomicU32::new(0),
+            paused: false,
+            auto_accept: true,
+            index_sections: false,
+            credit_pool: None,
+            session: session_tx,
+            outgoing: outgoing_tx,
+            incoming: incoming_rx,
+            incomplete_transfer: None,
+            dedup_window: Some(DedupWindow::new(capacity)),
+            aborted_deliveries: 0,
+            remote_settlements: Default::default(),
+            on_close_unsettled: OnCloseUnsettled::Leave,
+            unsettled_infos: Vec::new(),
+            settlement_timeout: None,
+            warn_after_no_delivery: None,
+        };
+        (inner, incoming_tx, outgoing_rx)
+    }
+
+    fn transfer_frame(
+        delivery_id: u32,
+        tag: u8,
+        more: bool,
+        resume: bool,
+        payload: Payload,
+    ) -> LinkFrame {
+        let performative = Transfer {
+            handle: 0.into(),
+            delivery_id: Some(delivery_id),
+            delivery_tag: Some(DeliveryTag::from(vec![tag])),
+            message_format: Some(0),
+            settled: Some(false),
+            more,
+            rcv_settle_mode: None,
+            state: None,
+            resume,
+            aborted: false,
+            batchable: false,
+        };
+        LinkFrame::Transfer {
+            input_handle: InputHandle(0),
+            performative,
+            payload,
+            incoming_permit: None,
+        }
+    }
+
+    fn encode(body: &str) -> Payload {
+        let message = Message::builder().value(body.to_string()).build();
+        serde_amqp::to_vec(&Serializable(message)).unwrap().into()
+    }
+
+    fn assert_settled_disposition(frame: LinkFrame, delivery_id: u32) {
+        match frame {
+            LinkFrame::Disposition(disposition) => {
+                assert_eq!(disposition.role, Role::Receiver);
+                assert_eq!(disposition.first, delivery_id);
+                assert!(disposition.settled);
+                assert_eq!(
+                    disposition.state,
+                    Some(DeliveryState::Accepted(Accepted {}))
+                );
+            }
+            frame => panic!("Expecting Disposition, found {:?}", frame),
+        }
+    }
+
+    /// The whole delivery is resent with the same tag (delivery-tag 1 in the resumption table)
+    #[tokio::test]
+    async fn resent_delivery_is_settled_and_not_yielded() {
+        let (mut inner, incoming, mut outgoing) = receiver_inner(8);
+
+        incoming
+            .send(transfer_frame(0, 1, false, false, encode("m1")))
+            .await
+            .unwrap();
+        let delivery = inner.recv::<String>().await.unwrap();
+        assert_eq!(delivery.body(), "m1");
+        assert!(matches!(
+            outgoing.recv().await.unwrap(),
+            LinkFrame::Disposition(_)
+        ));
+
+        incoming
+            .send(transfer_frame(1, 1, false, false, encode("m1")))
+            .await
+            .unwrap();
+        incoming
+            .send(transfer_frame(2, 2, false, false, encode("m2")))
+            .await
+            .unwrap();
+        let delivery = inner.recv::<String>().await.unwrap();
+        assert_eq!(delivery.body(), "m2");
+
+        assert_settled_disposition(outgoing.recv().await.unwrap(), 1);
+        assert_eq!(inner.dedup_window.as_ref().unwrap().suppressed(), 1);
+    }
+
+    /// The remaining transfers of a completely received delivery are resumed (delivery-tag 6 in the
+    /// resumption table)
+    #[tokio::test]
+    async fn resumed_multi_transfer_delivery_is_discarded() {
+        let (mut inner, incoming, mut outgoing) = receiver_inner(8);
+
+        let payload = encode("m6");
+        let (head, tail) = payload.split_at(payload.len() / 2);
+        let head = Payload::copy_from_slice(head);
+        let tail = Payload::copy_from_slice(tail);
+        incoming
+            .send(transfer_frame(0, 6, true, false, head))
+            .await
+            .unwrap();
+        incoming
+            .send(transfer_frame(0, 6, false, false, tail.clone()))
+            .await
+            .unwrap();
+        let delivery = inner.recv::<String>().await.unwrap();
+        assert_eq!(delivery.body(), "m6");
+        assert!(matches!(
+            outgoing.recv().await.unwrap(),
+            LinkFrame::Disposition(_)
+        ));
+
+        let half = tail.len() / 2;
+        incoming
+            .send(transfer_frame(1, 6, true, true, tail.slice(..half)))
+            .await
+            .unwrap();
+        incoming
+            .send(transfer_frame(1, 6, false, true, tail.slice(half..)))
+            .await
+            .unwrap();
+        incoming
+            .send(transfer_frame(2, 7, false, false, encode("m7")))
+            .await
+            .unwrap();
+        let delivery = inner.recv::<String>().await.unwrap();
+        assert_eq!(delivery.body(), "m7");
+
+        assert_settled_disposition(outgoing.recv().await.unwrap(), 1);
+        assert_eq!(inner.dedup_window.as_ref().unwrap().suppressed(), 1);
+    }
+
+    fn aborted_frame(transfer: LinkFrame, more: bool) -> LinkFrame {
+        match transfer {
+            LinkFrame::Transfer {
+                input_handle,
+                mut performative,
+                payload,
+                incoming_permit,
+            } => {
+                performative.more = more;
+                performative.aborted = true;
+                LinkFrame::Transfer {
+                    input_handle,
+                    performative,
+                    payload,
+                    incoming_permit,
+                }
+            }
+            frame => frame,
+        }
+    }
+
+    fn continuation_frame(more: bool, payload: Payload) -> LinkFrame {
+        match transfer_frame(0, 0, more, false, payload) {
+            LinkFrame::Transfer {
+                input_handle,
+                mut performative,
+                payload,
+                incoming_permit,
+            } => {
+                performative.delivery_id = None;
+                performative.delivery_tag = None;
+                performative.message_format = None;
+                performative.settled = None;
+                LinkFrame::Transfer {
+                    input_handle,
+                    performative,
+                    payload,
+                    incoming_permit,
+                }
+            }
+            frame => frame,
+        }
+    }
+
+    /// Sends the frames of delivery-tag 1 up to the aborted one, which is the `position`th frame
+    /// of a delivery of three frames
+    async fn send_aborted_delivery(incoming: &mpsc::Sender<LinkFrame>, position: usize) {
+        let payload = encode("aborted");
+        let parts = [payload.slice(..2), payload.slice(2..4), payload.slice(4..)];
+        for (index, part) in parts.into_iter().enumerate().take(position + 1) {
+            let more = index + 1 < 3;
+            let frame = match index {
+                0 => transfer_frame(0, 1, more, false, part),
+                _ => continuation_frame(more, part),
+            };
+            let frame = match index == position {
+                true => aborted_frame(frame, more),
+                false => frame,
+            };
+            incoming.send(frame).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn aborted_delivery_is_discarded_at_any_frame() {
+        for position in 0..3 {
+            let (mut inner, incoming, mut outgoing) = receiver_inner(8);
+
+            send_aborted_delivery(&incoming, position).await;
+            incoming
+                .send(transfer_frame(1, 2, false, false, encode("m2")))
+                .await
+                .unwrap();
+            let delivery = inner.recv::<String>().await.unwrap();
+            assert_eq!(delivery.body(), "m2");
+
+            // Only the complete delivery is disposed
+            assert_settled_disposition(outgoing.recv().await.unwrap(), 1);
+            assert!(outgoing.try_recv().is_err());
+            assert!(inner.incomplete_transfer.is_none());
+            assert_eq!(inner.aborted_deliveries, 1);
+
+            // The aborted delivery still takes up one link credit
+            let flow_state = inner.link.flow_state.snapshot(0);
+            assert_eq!(flow_state.delivery_count, 2);
+            assert_eq!(flow_state.link_credit, 98);
+            let unsettled = inner.link.unsettled.read();
+            let tag = DeliveryTag::from(vec![1]);
+            assert!(!matches!(&*unsettled, Some(map) if map.contains_key(&tag)));
+        }
+    }
+
+    #[tokio::test]
+    async fn aborted_delivery_refills_auto_credit() {
+        let (mut inner, incoming, mut outgoing) = receiver_inner(8);
+        inner.credit_mode = CreditMode::Auto(2);
+        inner.granted = AtomicU32::new(2);
+
+        send_aborted_delivery(&incoming, 1).await;
+        incoming
+            .send(transfer_frame(1, 2, false, false, encode("m2")))
+            .await
+            .unwrap();
+        let delivery = inner.recv::<String>().await.unwrap();
+        assert_eq!(delivery.body(), "m2");
+
+        match outgoing.recv().await.unwrap() {
+            LinkFrame::Flow(flow) => assert_eq!(flow.link_credit, Some(2)),
+            frame => panic!("Expecting Flow, found {:?}", frame),
+        }
+        assert_settled_disposition(outgoing.recv().await.unwrap(), 1);
+        assert_eq!(inner.aborted_deliveries, 1);
+    }
+
+    fn assert_unsettled_disposition(frame: LinkFrame, delivery_id: u32) {
+        match frame {
+            LinkFrame::Disposition(disposition) => {
+                assert_eq!(disposition.first, delivery_id);
+                assert!(!disposition.settled);
+            }
+            frame => panic!("Expecting Disposition, found {:?}", frame),
+        }
+    }
+
+    #[tokio::test]
+    async fn mode_second_disposition_waits_for_sender_settlement() {
+        let (mut inner, incoming, mut outgoing) = receiver_inner(8);
+        inner.auto_accept = false;
+        inner.link.rcv_settle_mode = ReceiverSettleMode::Second;
+
+        incoming
+            .send(transfer_frame(0, 1, false, false, encode("m1")))
+            .await
+            .unwrap();
+        let delivery = inner.recv::<String>().await.unwrap();
+
+        let sender = async {
+            assert_unsettled_disposition(outgoing.recv().await.unwrap(), 0);
+            assert_eq!(inner.processed.load(Ordering::Acquire), 0);
+            inner.remote_settlements.settle(&DeliveryTag::from(vec![1]));
+        };
+        let (result, _) = tokio::join!(inner.dispose(&delivery, None, Accepted {}.into()), sender);
+        result.unwrap();
+        assert_eq!(inner.processed.load(Ordering::Acquire), 1);
+    }
+
+    #[tokio::test]
+    async fn mode_second_disposition_fails_on_remote_detach() {
+        let (mut inner, incoming, mut outgoing) = receiver_inner(8);
+        inner.auto_accept = false;
+        inner.link.rcv_settle_mode = ReceiverSettleMode::Second;
+
+        incoming
+            .send(transfer_frame(0, 1, false, false, encode("m1")))
+            .await
+            .unwrap();
+        incoming
+            .send(transfer_frame(1, 2, false, false, encode("m2")))
+            .await
+            .unwrap();
+        let delivery1 = inner.recv::<String>().await.unwrap();
+        let delivery2 = inner.recv::<String>().await.unwrap();
+
+        let sender = async {
+            assert_unsettled_disposition(outgoing.recv().await.unwrap(), 0);
+            inner.remote_settlements.on_detached();
+        };
+        let (result, _) = tokio::join!(inner.dispose(&delivery1, None, Accepted {}.into()), sender);
+        assert!(matches!(result, Err(DispositionError::Detached)));
+
+        // No disposition is sent after the remote peer has detached the link
+        let result = inner.dispose(&delivery2, None, Accepted {}.into()).await;
+        assert!(matches!(result, Err(DispositionError::Detached)));
+        assert!(outgoing.try_recv().is_err());
+        assert_eq!(inner.processed.load(Ordering::Acquire), 0);
+    }
+
+    #[tokio::test]
+    async fn mode_second_disposition_fails_on_settlement_timeout() {
+        let (mut inner, incoming, mut outgoing) = receiver_inner(8);
+        inner.auto_accept = false;
+        inner.link.rcv_settle_mode = ReceiverSettleMode::Second;
+        inner.settlement_timeout = Some(Duration::from_millis(50));
+
+        incoming
+            .send(transfer_frame(0, 1, false, false, encode("m1")))
+            .await
+            .unwrap();
+        let delivery = inner.recv::<String>().await.unwrap();
+
+        let result = inner.dispose(&delivery, None, Accepted {}.into()).await;
+        assert!(matches!(result, Err(DispositionError::SettlementTimeout)));
+        assert_unsettled_disposition(outgoing.recv().await.unwrap(), 0);
+        assert_eq!(inner.processed.load(Ordering::Acquire), 0);
+    }
+
+    #[tokio::test]
+    async fn indexed_sections_of_multi_transfer_delivery() {
+        let (mut inner, incoming, _outgoing) = receiver_inner(8);
+        inner.index_sections = true;
+
+        let message = Message::builder()
+            .application_properties(
+                ApplicationProperties::builder()
+                    .insert("hops", 0u32)
+                    .build(),
+            )
+            .value("m1")
+            .build();
+        let payload: Payload = serde_amqp::to_vec(&Serializable(message)).unwrap().into();
+        let (first, second) = (payload.slice(..4), payload.slice(4..));
+        incoming
+            .send(transfer_frame(0, 1, true, false, first))
+            .await
+            .unwrap();
+        incoming
+            .send(transfer_frame(0, 1, false, false, second))
+            .await
+            .unwrap();
+        let delivery = inner.recv::<String>().await.unwrap();
+        assert_eq!(delivery.body(), "m1");
+
+        let kinds: Vec<_> = delivery
+            .sections()
+            .unwrap()
+            .iter()
+            .map(|section| section.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![SectionKind::ApplicationProperties, SectionKind::AmqpValue]
+        );
+
+        let mut message = delivery.message_mut().unwrap();
+        let application_properties = ApplicationProperties::builder()
+            .insert("hops", 1u32)
+            .build();
+        message.set(&application_properties).unwrap();
+        let expected = Message::builder()
+            .application_properties(application_properties)
+            .value("m1")
+            .build();
+        assert_eq!(
+            message.into_payload(),
+            serde_amqp::to_vec(&Serializable(expected)).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn sections_are_not_indexed_by_default() {
+        let (mut inner, incoming, _outgoing) = receiver_inner(8);
+        incoming
+            .send(transfer_frame(0, 1, false, false, encode("m1")))
+            .await
+            .unwrap();
+        let delivery = inner.recv::<String>().await.unwrap();
+        assert!(delivery.sections().is_none());
+        assert!(delivery.message_mut().is_none());
+    }
+
+    fn assert_flow_credit(frame: LinkFrame, link_credit: u32) {
+        match frame {
+            LinkFrame::Flow(flow) => assert_eq!(flow.link_credit, Some(link_credit)),
+            frame => panic!("Expecting Flow, found {:?}", frame),
+        }
+    }
+
+    #[tokio::test]
+    async fn auto_credit_refill_is_limited_by_available() {
+        let (mut inner, incoming, mut outgoing) = receiver_inner(16);
+        inner.credit_mode = CreditMode::Auto(10);
+        inner.set_credit(10).await.unwrap();
+        assert_flow_credit(outgoing.recv().await.unwrap(), 10);
+        assert_eq!(inner.link.flow_state.available(), None);
+
+        for id in 0..4 {
+            incoming
+                .send(transfer_frame(id, id as u8, false, false, encode("m")))
+                .await
+                .unwrap();
+            inner.recv::<String>().await.unwrap();
+            assert_settled_disposition(outgoing.recv().await.unwrap(), id);
+        }
+        assert!(outgoing.try_recv().is_err());
+
+        // The sender reports that only four more messages are waiting
+        let link_flow = LinkFlow {
+            available: Some(4),
+            ..Default::default()
+        };
+        inner
+            .link
+            .flow_state
+            .on_incoming_flow(link_flow, OutputHandle(0));
+
+        // Each refill is sized by what is left, and the full credit is issued again once the
+        // sender has nothing more to report
+        for (id, credit) in [(4, 3), (5, 2), (6, 1), (7, 10)] {
+            incoming
+                .send(transfer_frame(id, id as u8, false, false, encode("m")))
+                .await
+                .unwrap();
+            inner.recv::<String>().await.unwrap();
+            assert_settled_disposition(outgoing.recv().await.unwrap(), id);
+            assert_flow_credit(outgoing.recv().await.unwrap(), credit);
+        }
+        assert_eq!(inner.link.flow_state.available(), Some(0));
+    }
+
+    #[tokio::test]
+    async fn threshold_credit_is_refilled_at_the_threshold() {
+        let (mut inner, incoming, mut outgoing) = receiver_inner(16);
+        inner.credit_mode = CreditMode::Threshold {
+            credit: 10,
+            threshold: 3,
+        };
+        inner.set_credit(10).await.unwrap();
+        assert_flow_credit(outgoing.recv().await.unwrap(), 10);
+
+        for id in 0..7 {
+            incoming
+                .send(transfer_frame(id, id as u8, false, false, encode("m")))
+                .await
+                .unwrap();
+            inner.recv::<String>().await.unwrap();
+            assert_settled_disposition(outgoing.recv().await.unwrap(), id);
+        }
+        // Three credits are left after the seventh delivery
+        assert_flow_credit(outgoing.recv().await.unwrap(), 10);
+        assert!(outgoing.try_recv().is_err());
+
+        // Adding credit does not change what the threshold refills to
+        let link_credit = inner.link.flow_state.link_credit();
+        inner.add_credit(5).await.unwrap();
+        assert_flow_credit(outgoing.recv().await.unwrap(), link_credit + 5);
+        assert!(matches!(
+            inner.credit_mode,
+            CreditMode::Threshold {
+                credit: 10,
+                threshold: 3
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn unsettled_deliveries_are_settled_with_ranged_dispositions_on_close() {
+        let (mut inner, incoming, mut outgoing) = receiver_inner(8);
+        inner.auto_accept = false;
+        inner.dedup_window = None;
+        inner.on_close_unsettled = OnCloseUnsettled::Release;
+
+        let mut deliveries = Vec::new();
+        for delivery_id in [0, 1, 2, 3, 4, 7, 8] {
+            incoming
+                .send(transfer_frame(
+                    delivery_id,
+                    delivery_id as u8,
+                    false,
+                    false,
+                    encode("m"),
+                ))
+                .await
+                .unwrap();
+            deliveries.push(inner.recv::<String>().await.unwrap());
+        }
+        inner
+            .dispose(&deliveries[2], None, Accepted {}.into())
+            .await
+            .unwrap();
+        assert_settled_disposition(outgoing.recv().await.unwrap(), 2);
+
+        inner.settle_unsettled_on_close().await;
+        let mut ranges = Vec::new();
+        while let Ok(frame) = outgoing.try_recv() {
+            match frame {
+                LinkFrame::Disposition(disposition) => {
+                    assert!(disposition.settled);
+                    assert_eq!(
+                        disposition.state,
+                        Some(DeliveryState::Released(Released {}))
+                    );
+                    ranges.push((disposition.first, disposition.last));
+                }
+                frame => panic!("Expecting Disposition, found {:?}", frame),
+            }
+        }
+        assert_eq!(ranges, vec![(0, Some(1)), (3, Some(4)), (7, Some(8))]);
+        assert!(inner.link.unsettled.read().as_ref().unwrap().is_empty());
+
+        // Nothing is left to settle
+        inner.settle_unsettled_on_close().await;
+        assert!(outgoing.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn stream_into_inner_completes_the_frame_being_handled() {
+        use futures_util::StreamExt;
+
+        let (inner, incoming, mut outgoing) = receiver_inner(8);
+        // Fill the outgoing channel so that the auto-accept disposition cannot be sent
+        let filler = inner.outgoing.clone();
+        for _ in 0..16 {
+            filler.try_send(LinkFrame::SessionEnded(None)).unwrap();
+        }
+        let mut stream = Receiver { inner }.into_stream::<String>();
+
+        incoming
+            .send(transfer_frame(0, 1, false, false, encode("m1")))
+            .await
+            .unwrap();
+        let next = tokio::time::timeout(Duration::from_millis(50), stream.next()).await;
+        assert!(next.is_err());
+        assert!(stream.get_ref().is_none());
+
+        let session = async {
+            for _ in 0..16 {
+                assert!(matches!(
+                    outgoing.recv().await.unwrap(),
+                    LinkFrame::SessionEnded(None)
+                ));
+            }
+            outgoing.recv().await.unwrap()
+        };
+        let ((receiver, delivery), disposition) = tokio::join!(stream.into_inner(), session);
+        assert_settled_disposition(disposition, 0);
+        assert_eq!(delivery.unwrap().unwrap().body(), "m1");
+        assert_eq!(receiver.inner.processed.load(Ordering::Acquire), 1);
+    }
+
+    #[test]
+    fn close_outcome_is_one_of_the_source_outcomes() {
+        let source = |outcomes: &[&str]| {
+            let outcomes: Vec<Symbol> = outcomes.iter().map(|&outcome| outcome.into()).collect();
+            Source::builder().outcomes(outcomes).build()
+        };
+        let modified = Modified {
+            delivery_failed: None,
+            undeliverable_here: None,
+            message_annotations: None,
+        };
+
+        assert_eq!(
+            OnCloseUnsettled::Release.outcome(None),
+            Some(Released {}.into())
+        );
+        assert_eq!(
+            OnCloseUnsettled::Release
+                .outcome(Some(&source(&["amqp:accepted:list", "amqp:modified:list"]))),
+            Some(modified.into())
+        );
+        assert_eq!(
+            OnCloseUnsettled::Reject
+                .outcome(Some(&source(&["amqp:accepted:list", "amqp:released:list"]))),
+            None
+        );
+        assert_eq!(
+            OnCloseUnsettled::Reject.outcome(Some(&source(&[]))),
+            Some(Rejected { error: None }.into())
+        );
+        assert_eq!(OnCloseUnsettled::Leave.outcome(None), None);
+    }
+}
@@ -1,15 +1,18 @@
-use fe2o3_amqp_types::{
-    definitions::{Fields, Handle},
-    messaging::{message::DecodeIntoMessage, FromBody},
-};
+use std::collections::VecDeque;
+
+use fe2o3_amqp_types::definitions::{Fields, Handle};
 use serde_amqp::format_code::EncodingCodes;
 
 use crate::{
     endpoint::LinkExt,
-    util::{is_consecutive, AsByteIterator, IntoReader, Sealed},
+    util::{is_consecutive, AsByteIterator, IntoPayload, IntoReader, Sealed},
 };
 
-use super::{delivery::DeliveryInfo, *};
+use super::{
+    delivery::{DeliveryInfo, FromPayload},
+    receiver::OnCloseUnsettled,
+    *,
+};
 
 pub(crate) const DESCRIBED_TYPE: u8 = EncodingCodes::DescribedType as u8;
 pub(crate) const SMALL_ULONG_TYPE: u8 = EncodingCodes::SmallUlong as u8;
@@ -123,16 +126,16 @@ where
         }
     }
 
-    fn on_complete_transfer<'a, T, P>(
+    fn on_complete_transfer<'a, D, P>(
         &'a mut self,
         transfer: Transfer,
         payload: P,
         section_number: u32,
         section_offset: u64,
-    ) -> Result<Delivery<T>, Self::TransferError>
+    ) -> Result<D, Self::TransferError>
     where
-        for<'de> T: FromBody<'de> + Send,
-        for<'b> P: IntoReader + AsByteIterator<'b> + Send + 'a,
+        D: FromPayload + Send,
+        for<'b> P: IntoReader + IntoPayload + AsByteIterator<'b> + Send + 'a,
     {
         match self.local_state {
             LinkState::Attached | LinkState::IncompleteAttachExchanged => {}
@@ -154,11 +157,10 @@ where
             .ok_or(Self::TransferError::DeliveryTagIsNone)?;
         let message_format = transfer.message_format;
 
-        let (result, mode) = if settled_by_sender {
+        let mode = if settled_by_sender {
             // If the message is pre-settled, there is no need to
             // add to the unsettled map and no need to reply to the Sender
-            let result = T::decode_into_message(payload.into_reader());
-            (result, None)
+            None
         } else {
             // If the message is being sent settled by the sender, the value of this
             // field is ignored.
@@ -176,8 +178,6 @@ where
                 None => None,
             };
 
-            let result = T::decode_into_message(payload.into_reader());
-
             let state = DeliveryState::Received(Received {
                 section_number, // What is section number?
                 section_offset,
@@ -199,20 +199,8 @@ where
                     .get_or_insert(OrderedMap::new())
                     .insert(delivery_tag.clone(), Some(state));
             }
-            (result, mode)
-        };
-
-        let message = match result {
-            Ok(message) => message,
-            Err(source) => {
-                let info = DeliveryInfo {
-                    delivery_id,
-                    delivery_tag,
-                    rcv_settle_mode: mode,
-                    _sealed: Sealed {},
-                };
-                return Err(MessageDecodeError { source, info }.into());
-            }
+            self.save_unsettled();
+            mode
         };
 
         let link_output_handle = self
@@ -221,14 +209,15 @@ where
             .ok_or(ReceiverTransferError::IllegalState)?
             .into();
 
-        let delivery = Delivery {
-            link_output_handle,
+        let info = DeliveryInfo {
             delivery_id,
             delivery_tag,
-            message_format,
             rcv_settle_mode: mode,
-            message,
+            link_id: self.id,
+            settled: settled_by_sender,
+            _sealed: Sealed {},
         };
+        let delivery = D::from_payload(link_output_handle, info, message_format, payload)?;
 
         Ok(delivery)
     }
@@ -242,59 +231,26 @@ where
         state: DeliveryState,
         batchable: bool,
     ) -> Result<(), Self::DispositionError> {
-        let settled = settled.unwrap_or({
-            match delivery_info
-                .rcv_settle_mode
-                .as_ref()
-                .unwrap_or(&self.rcv_settle_mode)
-            {
-                ReceiverSettleMode::First => {
-                    // If first, this indicates that the receiver MUST settle
-                    // the delivery once it has arrived without waiting
-                    // for the sender to settle first.
-
-                    // The delivery is not inserted into unsettled map if in First mode
-                    true
-                }
-                ReceiverSettleMode::Second => {
-                    // If second, this indicates that the receiver MUST NOT settle until sending
-                    // its disposition to the sender and receiving a settled disposition from
-                    // the sender.
-                    false
-                }
-            }
-        });
-
-        let unsettled_state = if settled {
-            let mut lock = self.unsettled.write();
-            lock.as_mut()
-                .and_then(|map| map.swap_remove(&delivery_info.delivery_tag))
-        } else {
-            let mut lock = self.unsettled.write();
-            // If the key is present in the map, the old value will be returned, which
-            // we don't really need
-            lock.get_or_insert(OrderedMap::new())
-                .insert(delivery_info.delivery_tag.clone(), Some(state.clone()))
-        };
-
-        // Only dispose if message is found in unsettled map
-        if unsettled_state.is_some() {
-            let disposition = Disposition {
-                role: Role::Receiver,
-                first: delivery_info.delivery_id,
-                last: None,
-                settled,
-                state: Some(state),
-                batchable,
-            };
-            let frame = LinkFrame::Disposition(disposition);
-            writer
-                .send(frame)
-                .await // cancel safe
-                .map_err(|_| Self::DispositionError::IllegalSessionState)?;
+        // The delivery is not in the unsettled map if it is settled by the sender, and there is
+        // no need to reply to the sender
+        if !self.update_unsettled(std::slice::from_ref(&delivery_info), settled, &state)? {
+            return Ok(());
         }
+        self.save_unsettled();
 
-        Ok(())
+        let disposition = Disposition {
+            role: Role::Receiver,
+            first: delivery_info.delivery_id,
+            last: None,
+            settled: self.settles(&delivery_info, settled),
+            state: Some(state),
+            batchable,
+        };
+        let frame = LinkFrame::Disposition(disposition);
+        writer
+            .send(frame)
+            .await // cancel safe
+            .map_err(|_| Self::DispositionError::IllegalSessionState)
     }
 
     /// This is cancel safe because all internal `.await` points are cancel safe
@@ -306,17 +262,12 @@ where
         state: DeliveryState,
         batchable: bool,
     ) -> Result<(), Self::DispositionError> {
+        self.update_unsettled(&delivery_infos, settled, &state)?;
+        self.save_unsettled();
+
         // sorting before filtering may be more cache/branch-prediction friendly?
         delivery_infos.sort_by(|left, right| left.delivery_id.cmp(&right.delivery_id));
-        {
-            let reader = self.unsettled.read();
-            delivery_infos.retain(|info| {
-                reader
-                    .as_ref()
-                    .map(|m| m.contains_key(&info.delivery_tag))
-                    .unwrap_or(false)
-            });
-        }
+        delivery_infos.retain(|info| !info.settled);
         let chunk_inds = consecutive_chunk_indices(&delivery_infos);
 
         let mut prev_ind = 0;
@@ -330,6 +281,62 @@ where
         self.dispose_consecutive(writer, final_slice, settled, state, batchable)
             .await // cancel safe
     }
+
+    fn settle_on_close(
+        &self,
+        mut delivery_infos: Vec<DeliveryInfo>,
+        policy: OnCloseUnsettled,
+    ) -> Vec<Disposition> {
+        let state = match policy.outcome(self.source.as_ref()) {
+            Some(state) => state,
+            None => return Vec::new(),
+        };
+
+        {
+            let mut lock = self.unsettled.write();
+            let map = match lock.as_mut() {
+                Some(map) => map,
+                None => return Vec::new(),
+            };
+            // A delivery that is given a terminal state only waits for the sender to settle it
+            delivery_infos.retain(|info| {
+                let is_pending = info.link_id == self.id
+                    && !info.settled
+                    && matches!(
+                        map.get(&info.delivery_tag),
+                        Some(state) if !state.as_ref().is_some_and(DeliveryState::is_terminal)
+                    );
+                if is_pending {
+                    map.swap_remove(&info.delivery_tag);
+                }
+                is_pending
+            });
+        }
+        self.save_unsettled();
+
+        delivery_infos.sort_by_key(|info| info.delivery_id);
+        let chunk_ends = consecutive_chunk_indices(&delivery_infos)
+            .into_iter()
+            .chain(std::iter::once(delivery_infos.len()));
+
+        let mut dispositions = Vec::new();
+        let mut prev_ind = 0;
+        for ind in chunk_ends {
+            let slice = &delivery_infos[prev_ind..ind];
+            if let (Some(first), Some(last)) = (slice.first(), slice.last()) {
+                dispositions.push(Disposition {
+                    role: Role::Receiver,
+                    first: first.delivery_id,
+                    last: Some(last.delivery_id),
+                    settled: true,
+                    state: Some(state.clone()),
+                    batchable: false,
+                });
+            }
+            prev_ind = ind;
+        }
+        dispositions
+    }
 }
 
 fn consecutive_chunk_indices(delivery_infos: &[DeliveryInfo]) -> Vec<usize> {
@@ -354,17 +361,13 @@ pub(crate) fn count_number_of_sections_and_offset<'a, B>(bytes: &'a B) -> (u32,
 where
     B: AsByteIterator<'a>,
 {
-    let b0 = bytes.as_byte_iterator();
-    let len = b0.len();
-    let b1 = bytes.as_byte_iterator().skip(1);
-    let b2 = bytes.as_byte_iterator().skip(2);
-    let iter = b0.zip(b1.zip(b2));
+    let len = bytes.as_byte_iterator().len();
 
     let mut last_pos = 0;
     let mut section_numbers = 0;
 
-    for (i, (&b0, (&b1, &b2))) in iter.enumerate() {
-        if is_section_header(b0, b1, b2) {
+    for (i, is_header) in SectionHeaders::new(bytes.as_byte_iterator()).enumerate() {
+        if is_header {
             section_numbers += 1;
             last_pos = i;
         }
@@ -374,31 +377,70 @@ where
     (section_numbers, offset as u64)
 }
 
-pub(crate) fn is_section_header(b0: u8, b1: u8, b2: u8) -> bool {
-    matches!(
-        (b0, b1, b2),
-        (DESCRIBED_TYPE, SMALL_ULONG_TYPE, HEADER_CODE)
-        | (DESCRIBED_TYPE, SMALL_ULONG_TYPE, DELIV_ANNOT_CODE)
-        | (DESCRIBED_TYPE, SMALL_ULONG_TYPE, MSG_ANNOT_CODE)
-        | (DESCRIBED_TYPE, SMALL_ULONG_TYPE, PROP_CODE)
-        | (DESCRIBED_TYPE, SMALL_ULONG_TYPE, APP_PROP_CODE)
-        | (DESCRIBED_TYPE, SMALL_ULONG_TYPE, DATA_CODE)
-        | (DESCRIBED_TYPE, SMALL_ULONG_TYPE, AMQP_SEQ_CODE)
-        | (DESCRIBED_TYPE, SMALL_ULONG_TYPE, AMQP_VAL_CODE)
-        | (DESCRIBED_TYPE, SMALL_ULONG_TYPE, FOOTER_CODE)
+/// The length of the longest section header, which is a descriptor code encoded as a ulong
+const MAX_SECTION_HEADER_LEN: usize = 10;
+
+/// Whether a message section starts at the beginning of `bytes`
+fn is_section_header(bytes: &[u8]) -> bool {
+    match bytes {
+        [DESCRIBED_TYPE, SMALL_ULONG_TYPE, code, ..] => is_section_code(*code),
         // Some implementation may use Ulong for all u64 numbers
-        | (DESCRIBED_TYPE, ULONG_TYPE, HEADER_CODE)
-        | (DESCRIBED_TYPE, ULONG_TYPE, DELIV_ANNOT_CODE)
-        | (DESCRIBED_TYPE, ULONG_TYPE, MSG_ANNOT_CODE)
-        | (DESCRIBED_TYPE, ULONG_TYPE, PROP_CODE)
-        | (DESCRIBED_TYPE, ULONG_TYPE, APP_PROP_CODE)
-        | (DESCRIBED_TYPE, ULONG_TYPE, DATA_CODE)
-        | (DESCRIBED_TYPE, ULONG_TYPE, AMQP_SEQ_CODE)
-        | (DESCRIBED_TYPE, ULONG_TYPE, AMQP_VAL_CODE)
-        | (DESCRIBED_TYPE, ULONG_TYPE, FOOTER_CODE)
+        [DESCRIBED_TYPE, ULONG_TYPE, 0, 0, 0, 0, 0, 0, 0, code, ..] => is_section_code(*code),
+        _ => false,
+    }
+}
+
+fn is_section_code(code: u8) -> bool {
+    matches!(
+        code,
+        HEADER_CODE
+            | DELIV_ANNOT_CODE
+            | MSG_ANNOT_CODE
+            | PROP_CODE
+            | APP_PROP_CODE
+            | DATA_CODE
+            | AMQP_SEQ_CODE
+            | AMQP_VAL_CODE
+            | FOOTER_CODE
     )
 }
 
+/// Yields for each position of the bytes whether a message section starts there
+pub(crate) struct SectionHeaders<I> {
+    bytes: I,
+    window: VecDeque<u8>,
+}
+
+impl<'a, I> SectionHeaders<I>
+where
+    I: Iterator<Item = &'a u8>,
+{
+    pub(crate) fn new(bytes: I) -> Self {
+        Self {
+            bytes,
+            window: VecDeque::with_capacity(MAX_SECTION_HEADER_LEN),
+        }
+    }
+}
+
+impl<'a, I> Iterator for SectionHeaders<I>
+where
+    I: Iterator<Item = &'a u8>,
+{
+    type Item = bool;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.window.len() < MAX_SECTION_HEADER_LEN {
+            match self.bytes.next() {
+                Some(byte) => self.window.push_back(*byte),
+                None => break,
+            }
+        }
+        let is_header = is_section_header(self.window.make_contiguous());
+        self.window.pop_front().map(|_| is_header)
+    }
+}
+
 impl ReceiverLink<Target> {
     cfg_transaction! {
         /// Set and send flow state
@@ -428,6 +470,10 @@ impl<T> ReceiverLink<T> {
         &mut self,
         remote_unsettled: Option<OrderedMap<DeliveryTag, Option<DeliveryState>>>,
     ) -> ReceiverAttachExchange {
+        // The restored map has been offered to the remote peer, which will resume the deliveries
+        // that it still knows of
+        self.restored_unsettled = None;
+
         let remote_is_empty = match remote_unsettled {
             Some(map) => map.is_empty(),
             None => true,
@@ -446,6 +492,65 @@ impl<T> ReceiverLink<T> {
         }
     }
 
+    /// Whether the disposition of a delivery is sent settled
+    fn settles(&self, delivery_info: &DeliveryInfo, settled: Option<bool>) -> bool {
+        settled.unwrap_or({
+            match delivery_info
+                .rcv_settle_mode
+                .as_ref()
+                .unwrap_or(&self.rcv_settle_mode)
+            {
+                // If first, this indicates that the receiver MUST settle
+                // the delivery once it has arrived without waiting
+                // for the sender to settle first.
+                ReceiverSettleMode::First => true,
+                // If second, this indicates that the receiver MUST NOT settle until sending
+                // its disposition to the sender and receiving a settled disposition from
+                // the sender.
+                ReceiverSettleMode::Second => false,
+            }
+        })
+    }
+
+    /// Removes the deliveries that are disposed settled from the unsettled map and updates the
+    /// state of the others, and returns whether any delivery needs a disposition.
+    ///
+    /// The unsettled map is left untouched if any delivery is received on another link or is
+    /// no longer in the unsettled map, which means it has already been settled.
+    fn update_unsettled(
+        &self,
+        delivery_infos: &[DeliveryInfo],
+        settled: Option<bool>,
+        state: &DeliveryState,
+    ) -> Result<bool, DispositionError> {
+        let mut lock = self.unsettled.write();
+        for info in delivery_infos {
+            if info.link_id != self.id {
+                return Err(DispositionError::LinkMismatch(info.delivery_id));
+            }
+            // Deliveries that are settled by the sender are never added to the unsettled map
+            let is_unsettled = lock
+                .as_ref()
+                .is_some_and(|map| map.contains_key(&info.delivery_tag));
+            if !info.settled && !is_unsettled {
+                return Err(DispositionError::AlreadySettled(info.delivery_id));
+            }
+        }
+
+        let mut needs_disposition = false;
+        if let Some(map) = lock.as_mut() {
+            for info in delivery_infos.iter().filter(|info| !info.settled) {
+                needs_disposition = true;
+                if self.settles(info, settled) {
+                    map.swap_remove(&info.delivery_tag);
+                } else if let Some(value) = map.get_mut(&info.delivery_tag) {
+                    *value = Some(state.clone());
+                }
+            }
+        }
+        Ok(needs_disposition)
+    }
+
     /// This is cancel safe because it only `.await` on sending over a `tokio::mpsc::Sender`
     async fn dispose_consecutive(
         &self,
@@ -460,37 +565,11 @@ impl<T> ReceiverLink<T> {
             return Ok(());
         }
 
-        let settled = settled.unwrap_or({
-            match consecutive_infos[0]
-                .rcv_settle_mode
-                .as_ref()
-                .unwrap_or(&self.rcv_settle_mode)
-            {
-                ReceiverSettleMode::First => true,
-                ReceiverSettleMode::Second => false,
-            }
-        });
-
-        // TODO: Individually checking whether a delivery is already dropped is probably too heavy?
-        if settled {
-            let mut lock = self.unsettled.write();
-            for info in consecutive_infos {
-                lock.as_mut()
-                    .and_then(|map| map.swap_remove(&info.delivery_tag));
-            }
-        } else {
-            let mut lock = self.unsettled.write();
-            for info in consecutive_infos {
-                lock.get_or_insert(OrderedMap::new())
-                    .insert(info.delivery_tag.clone(), Some(state.clone()));
-            }
-        }
-
         let disposition = Disposition {
             role: Role::Receiver,
             first: consecutive_infos[0].delivery_id,
             last: consecutive_infos.last().map(|el| el.delivery_id),
-            settled,
+            settled: self.settles(&consecutive_infos[0], settled),
             state: Some(state),
             batchable,
         };
@@ -511,14 +590,15 @@ impl<T> ReceiverLink<T> {
         match (link_credit, drain) {
             (Some(link_credit), Some(drain)) => {
                 let mut guard = self.flow_state.lock.write();
+                self.flow_state.revoke(guard.link_credit, link_credit);
                 guard.link_credit = link_credit;
-                guard.drain = drain;
+                self.flow_state.set_drain(&mut guard, drain);
                 LinkFlow {
                     handle,
                     // When the flow state is being sent from the receiver endpoint to the sender
                     // endpoint this field MUST be set to the last known value of the corresponding
                     // sending endpoint.
-                    delivery_count: Some(guard.delivery_count),
+                    delivery_count: Some(guard.delivery_count.into()),
                     link_credit: Some(link_credit),
                     // The receiver sets this to the last known value seen from the sender
                     // available: Some(writer.available),
@@ -530,13 +610,14 @@ impl<T> ReceiverLink<T> {
             }
             (Some(link_credit), None) => {
                 let mut guard = self.flow_state.lock.write();
+                self.flow_state.revoke(guard.link_credit, link_credit);
                 guard.link_credit = link_credit;
                 LinkFlow {
                     handle,
                     // When the flow state is being sent from the receiver endpoint to the sender
                     // endpoint this field MUST be set to the last known value of the corresponding
                     // sending endpoint.
-                    delivery_count: Some(guard.delivery_count),
+                    delivery_count: Some(guard.delivery_count.into()),
                     link_credit: Some(link_credit),
                     // The receiver sets this to the last known value seen from the sender
                     // available: Some(writer.available),
@@ -548,13 +629,13 @@ impl<T> ReceiverLink<T> {
             }
             (None, Some(drain)) => {
                 let mut guard = self.flow_state.lock.write();
-                guard.drain = drain;
+                self.flow_state.set_drain(&mut guard, drain);
                 LinkFlow {
                     handle,
                     // When the flow state is being sent from the receiver endpoint to the sender
                     // endpoint this field MUST be set to the last known value of the corresponding
                     // sending endpoint.
-                    delivery_count: Some(guard.delivery_count),
+                    delivery_count: Some(guard.delivery_count.into()),
                     link_credit: Some(guard.link_credit),
                     // The receiver sets this to the last known value seen from the sender
                     // available: Some(writer.available),
@@ -571,7 +652,7 @@ impl<T> ReceiverLink<T> {
                     // When the flow state is being sent from the receiver endpoint to the sender
                     // endpoint this field MUST be set to the last known value of the corresponding
                     // sending endpoint.
-                    delivery_count: Some(guard.delivery_count),
+                    delivery_count: Some(guard.delivery_count.into()),
                     link_credit: Some(guard.link_credit),
                     // The receiver sets this to the last known value seen from the sender
                     // available: Some(writer.available),
@@ -597,10 +678,20 @@ where
     type AttachExchange = ReceiverAttachExchange;
     type AttachError = ReceiverAttachError;
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(link_name = %self.name)))]
     fn on_incoming_attach(
         &mut self,
         remote_attach: Attach,
     ) -> Result<Self::AttachExchange, Self::AttachError> {
+        #[cfg(feature = "tracing")]
+        tracing::trace!(link_name = %remote_attach.name, handle = remote_attach.handle.0, "RECV attach");
+        #[cfg(feature = "log")]
+        log::trace!(
+            "RECV attach: link_name = {}, handle = {}",
+            remote_attach.name,
+            remote_attach.handle.0
+        );
+
         use self::source::VerifySource;
 
         match (&self.local_state, remote_attach.incomplete_unsettled) {
@@ -647,10 +738,8 @@ where
         // delivery is settled
         self.snd_settle_mode = remote_attach.snd_settle_mode;
 
-        // When set at the receiver this indicates the actual settlement mode in use
-        if self.rcv_settle_mode != remote_attach.rcv_settle_mode {
-            return Err(ReceiverAttachError::RcvSettleModeNotSupported);
-        }
+        // When set at the receiver this indicates the actual settlement mode in use, so the
+        // `rcv_settle_mode` set at the sender is only the mode it desires
 
         // The delivery-count is initialized by the sender when a link endpoint is
         // created, and is incremented whenever a message is sent
@@ -680,10 +769,11 @@ where
 
         self.flow_state
             .as_ref()
-            .initial_delivery_count_mut(|_| initial_delivery_count);
+            .initial_delivery_count_mut(|_| initial_delivery_count.into());
         self.flow_state
             .as_ref()
-            .delivery_count_mut(|_| initial_delivery_count);
+            .delivery_count_mut(|_| initial_delivery_count.into());
+        self.flow_state.as_ref().reset_relayed_deliveries();
 
         if let Some(remote_properties) = remote_attach.properties {
             self.properties_mut(|local_properties| {
@@ -740,6 +830,11 @@ where
         &self.local_state
     }
 
+    fn on_session_ended(&mut self) {
+        self.local_state = LinkState::Detached;
+        self.notify_local_state();
+    }
+
     fn name(&self) -> &str {
         &self.name
     }
@@ -822,7 +917,9 @@ where
             | ReceiverAttachError::IllegalState
             | ReceiverAttachError::NonAttachFrameReceived
             | ReceiverAttachError::ExpectImmediateDetach
-            | ReceiverAttachError::RemoteClosedWithError(_) => attach_error,
+            | ReceiverAttachError::RemoteClosedWithError(_)
+            | ReceiverAttachError::RemoteDetached(_)
+            | ReceiverAttachError::HandleMaxReached => attach_error,
 
             ReceiverAttachError::DuplicatedLinkName => {
                 let error = definitions::Error::new(
@@ -881,10 +978,16 @@ where
         + Sync,
 {
     match reader.recv().await {
-        Some(LinkFrame::Detach(remote_detach)) => match link.on_incoming_detach(remote_detach) {
-            Ok(_) => err,
-            Err(detach_error) => detach_error.try_into().unwrap_or(err),
-        },
+        Some(LinkFrame::Detach(remote_detach)) => {
+            // Keep the Detach that carries the error so that the caller can see what the remote
+            // peer sent
+            let refusal = remote_detach.error.is_some().then(|| remote_detach.clone());
+            match (link.on_incoming_detach(remote_detach), refusal) {
+                (Ok(_), _) => err,
+                (Err(_), Some(detach)) => ReceiverAttachError::RemoteDetached(Box::new(detach)),
+                (Err(detach_error), None) => detach_error.try_into().unwrap_or(err),
+            }
+        }
         Some(_) => ReceiverAttachError::NonAttachFrameReceived,
         None => ReceiverAttachError::IllegalSessionState,
     }
@@ -894,7 +997,7 @@ where
 mod tests {
     use fe2o3_amqp_types::{
         messaging::{
-            message::{Body, __private::Serializable},
+            message::{__private::Serializable, Body},
             AmqpValue, DeliveryAnnotations, Header, Message, MessageAnnotations,
         },
         primitives::{OrderedMap, Value},
@@ -929,6 +1032,43 @@ mod tests {
         let (_nums, _offset) = count_number_of_sections_and_offset(&buf);
     }
 
+    #[test]
+    fn test_section_numbers_with_ulong_descriptors() {
+        let message = Message {
+            header: Some(Header {
+                durable: true,
+                ..Default::default()
+            }),
+            delivery_annotations: None,
+            message_annotations: Some(MessageAnnotations(OrderedMap::new())),
+            properties: None,
+            application_properties: None,
+            body: Body::Value(AmqpValue(Value::Bool(true))),
+            footer: None,
+        };
+        let buf = to_vec(&Serializable(message)).unwrap();
+        let (nums, offset) = count_number_of_sections_and_offset(&buf);
+        assert_eq!(nums, 3);
+
+        // The same sections with the descriptor codes encoded as ulong
+        let mut ulong_buf = Vec::new();
+        let mut i = 0;
+        while i < buf.len() {
+            if buf[i..].starts_with(&[0x00, 0x53]) {
+                ulong_buf.extend_from_slice(&[0x00, 0x80, 0, 0, 0, 0, 0, 0, 0, buf[i + 2]]);
+                i += 3;
+            } else {
+                ulong_buf.push(buf[i]);
+                i += 1;
+            }
+        }
+        assert_eq!(ulong_buf.len(), buf.len() + 3 * 7);
+        assert_eq!(
+            count_number_of_sections_and_offset(&ulong_buf),
+            (nums, offset + 7)
+        );
+    }
+
     #[test]
     fn test_consecutive_chunks() {
         let expected = [vec![0u32, 1, 2, 3], vec![5, 6], vec![8, 9], vec![11]];
@@ -0,0 +1,264 @@
+//! Implements `Stream` for the receiver
+
+use std::{
+    borrow::BorrowMut,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use fe2o3_amqp_types::messaging::FromBody;
+use futures_util::{future::BoxFuture, ready, stream::FusedStream, Stream};
+
+use super::{delivery::Delivery, LinkStateError, Receiver, RecvError};
+
+type ProcessFrame<'a, R, T> = BoxFuture<'a, (R, Result<Option<Delivery<T>>, RecvError>)>;
+
+/// Whether the stream should end after the error
+enum ErrorKind {
+    /// The link was detached or closed by the remote peer without an error
+    Detached,
+
+    /// The link or the session can no longer be used
+    Terminal,
+
+    /// The error only concerns one delivery
+    Recoverable,
+}
+
+fn error_kind(error: &RecvError) -> ErrorKind {
+    match error {
+        RecvError::LinkStateError(LinkStateError::RemoteDetached)
+        | RecvError::LinkStateError(LinkStateError::RemoteClosed) => ErrorKind::Detached,
+        RecvError::LinkStateError(_) | RecvError::TransactionalAcquisitionIsNotImeplemented => {
+            ErrorKind::Terminal
+        }
+        _ => ErrorKind::Recoverable,
+    }
+}
+
+/// Shared implementation of [`ReceiverStream`] and [`RecvStream`]
+///
+/// A frame is taken from the incoming channel by polling the channel directly, which is cancel
+/// safe. The frame is then handled in a future that holds the receiver until it completes, and
+/// the receiver is put back before the delivery is yielded. Handling a frame may wait for the
+/// session to take the outgoing frames or for the sender to settle an accepted delivery, so it
+/// cannot be made synchronous. Instead the future is driven to completion by
+/// [`into_parts`](DeliveryStream::into_parts) when the receiver is taken out of the stream.
+struct DeliveryStream<'a, R, T> {
+    receiver: Option<R>,
+    processing: Option<ProcessFrame<'a, R, T>>,
+    terminated: bool,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<'a, R, T> DeliveryStream<'a, R, T>
+where
+    R: BorrowMut<Receiver> + Send + 'a,
+    for<'de> T: FromBody<'de> + Send + 'a,
+{
+    fn new(receiver: R) -> Self {
+        Self {
+            receiver: Some(receiver),
+            processing: None,
+            terminated: false,
+            marker: PhantomData,
+        }
+    }
+
+    fn poll_next_delivery(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Delivery<T>, RecvError>>> {
+        loop {
+            if self.terminated {
+                return Poll::Ready(None);
+            }
+
+            if let Some(processing) = self.processing.as_mut() {
+                let (receiver, result) = ready!(processing.as_mut().poll(cx));
+                self.processing = None;
+                self.receiver = Some(receiver);
+
+                match result {
+                    Ok(Some(delivery)) => return Poll::Ready(Some(Ok(delivery))),
+                    // Partial or aborted delivery
+                    Ok(None) => continue,
+                    Err(error) => match error_kind(&error) {
+                        ErrorKind::Detached => {
+                            self.terminated = true;
+                            return Poll::Ready(None);
+                        }
+                        ErrorKind::Terminal => {
+                            self.terminated = true;
+                            return Poll::Ready(Some(Err(error)));
+                        }
+                        ErrorKind::Recoverable => return Poll::Ready(Some(Err(error))),
+                    },
+                }
+            }
+
+            let receiver = match self.receiver.as_mut() {
+                Some(receiver) => receiver,
+                None => return Poll::Ready(None),
+            };
+            let frame = match ready!(receiver.borrow_mut().inner.incoming.poll_recv(cx)) {
+                Some(frame) => frame,
+                None => {
+                    self.terminated = true;
+                    let error = RecvError::LinkStateError(LinkStateError::IllegalSessionState);
+                    return Poll::Ready(Some(Err(error)));
+                }
+            };
+
+            if let Some(mut receiver) = self.receiver.take() {
+                self.processing = Some(Box::pin(async move {
+                    let result = receiver.borrow_mut().inner.on_incoming_frame(frame).await;
+                    (receiver, result)
+                }));
+            }
+        }
+    }
+
+    /// Completes the handling of the frame that is taken from the incoming channel, if any, and
+    /// returns the receiver with the item that the handling produced
+    async fn into_parts(self) -> (R, Option<Result<Delivery<T>, RecvError>>) {
+        match (self.receiver, self.processing) {
+            (_, Some(processing)) => {
+                let (receiver, result) = processing.await;
+                (receiver, result.transpose())
+            }
+            (Some(receiver), None) => (receiver, None),
+            (None, None) => unreachable!("The receiver is only taken by the processing future"),
+        }
+    }
+}
+
+/// A [`Stream`] of deliveries that owns the [`Receiver`]
+///
+/// This is created by [`Receiver::into_stream`]. Link credit is managed according to the
+/// credit mode of the receiver just like with [`Receiver::recv`]. The stream ends when the remote
+/// peer detaches or closes the link. If the remote peer detached or closed the link with an error,
+/// the error is yielded as the final item.
+///
+/// # Cancel safety
+///
+/// Dropping the future returned by `StreamExt::next()` does not lose a partially received
+/// multi-frame delivery, which is kept by the receiver until the remaining transfers arrive.
+pub struct ReceiverStream<T> {
+    inner: DeliveryStream<'static, Receiver, T>,
+}
+
+impl<T> std::fmt::Debug for ReceiverStream<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReceiverStream")
+            .field("receiver", &self.inner.receiver)
+            .field("terminated", &self.inner.terminated)
+            .finish()
+    }
+}
+
+impl<T> ReceiverStream<T>
+where
+    for<'de> T: FromBody<'de> + Send + 'static,
+{
+    pub(crate) fn new(receiver: Receiver) -> Self {
+        Self {
+            inner: DeliveryStream::new(receiver),
+        }
+    }
+
+    /// Get a reference to the receiver, eg. to dispose a delivery yielded by the stream
+    ///
+    /// This returns `None` if an incoming frame is being handled, which only happens after
+    /// `poll_next` returned `Poll::Pending`
+    pub fn get_ref(&self) -> Option<&Receiver> {
+        self.inner.receiver.as_ref()
+    }
+
+    /// Get a mutable reference to the receiver
+    ///
+    /// This returns `None` if an incoming frame is being handled, which only happens after
+    /// `poll_next` returned `Poll::Pending`
+    pub fn get_mut(&mut self) -> Option<&mut Receiver> {
+        self.inner.receiver.as_mut()
+    }
+
+    /// Consumes the stream and returns the receiver
+    ///
+    /// If an incoming frame is being handled, which only happens after `poll_next` returned
+    /// `Poll::Pending`, the handling is completed first so that neither the frame nor the
+    /// receiver is lost. The item produced by the frame, which the stream would have yielded
+    /// next, is returned along with the receiver.
+    pub async fn into_inner(self) -> (Receiver, Option<Result<Delivery<T>, RecvError>>) {
+        self.inner.into_parts().await
+    }
+}
+
+impl<T> Stream for ReceiverStream<T>
+where
+    for<'de> T: FromBody<'de> + Send + 'static,
+{
+    type Item = Result<Delivery<T>, RecvError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().inner.poll_next_delivery(cx)
+    }
+}
+
+impl<T> FusedStream for ReceiverStream<T>
+where
+    for<'de> T: FromBody<'de> + Send + 'static,
+{
+    fn is_terminated(&self) -> bool {
+        self.inner.terminated
+    }
+}
+
+/// A [`Stream`] of deliveries that mutably borrows the [`Receiver`]
+///
+/// This is created by [`Receiver::stream`] and behaves like [`ReceiverStream`]. Dropping the
+/// stream gives the receiver back without losing a partially received multi-frame delivery.
+pub struct RecvStream<'a, T> {
+    inner: DeliveryStream<'a, &'a mut Receiver, T>,
+}
+
+impl<'a, T> std::fmt::Debug for RecvStream<'a, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecvStream")
+            .field("receiver", &self.inner.receiver)
+            .field("terminated", &self.inner.terminated)
+            .finish()
+    }
+}
+
+impl<'a, T> RecvStream<'a, T>
+where
+    for<'de> T: FromBody<'de> + Send + 'a,
+{
+    pub(crate) fn new(receiver: &'a mut Receiver) -> Self {
+        Self {
+            inner: DeliveryStream::new(receiver),
+        }
+    }
+}
+
+impl<'a, T> Stream for RecvStream<'a, T>
+where
+    for<'de> T: FromBody<'de> + Send + 'a,
+{
+    type Item = Result<Delivery<T>, RecvError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().inner.poll_next_delivery(cx)
+    }
+}
+
+impl<'a, T> FusedStream for RecvStream<'a, T>
+where
+    for<'de> T: FromBody<'de> + Send + 'a,
+{
+    fn is_terminated(&self) -> bool {
+        self.inner.terminated
+    }
+}
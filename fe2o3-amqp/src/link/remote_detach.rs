@@ -0,0 +1,187 @@
+//! Tells a sender or receiver that the remote peer has detached the link while it is not reading
+//! its incoming frames
+
+use std::future::Future;
+
+use fe2o3_amqp_types::performatives::Detach;
+use tokio::sync::watch;
+
+use super::DetachError;
+
+/// Which side detached the link first since it was last attached
+#[derive(Debug, Clone, Default)]
+enum DetachedBy {
+    #[default]
+    None,
+    Local,
+    Remote(Detach),
+    SessionEnded,
+}
+
+/// Shared by the link and the link relay in the session, which sees the Detach frames in both
+/// directions as soon as they are sent or received
+#[derive(Debug)]
+pub(crate) struct RemoteDetachNotifier {
+    tx: watch::Sender<DetachedBy>,
+
+    /// Whether the session has ended since the link was last attached, regardless of which side
+    /// detached the link first
+    session_ended: watch::Sender<bool>,
+}
+
+impl Default for RemoteDetachNotifier {
+    fn default() -> Self {
+        Self {
+            tx: watch::channel(DetachedBy::None).0,
+            session_ended: watch::channel(false).0,
+        }
+    }
+}
+
+impl RemoteDetachNotifier {
+    fn first(&self, detached_by: impl FnOnce() -> DetachedBy) {
+        self.tx.send_if_modified(|state| match state {
+            DetachedBy::None => {
+                *state = detached_by();
+                true
+            }
+            _ => false,
+        });
+    }
+
+    /// The link has sent a Detach, so a Detach from the remote peer that follows is a reply
+    pub(crate) fn on_local_detach(&self) {
+        self.first(|| DetachedBy::Local)
+    }
+
+    /// The remote peer has sent a Detach
+    pub(crate) fn on_remote_detach(&self, detach: &Detach) {
+        self.first(|| DetachedBy::Remote(detach.clone()))
+    }
+
+    /// The session has ended before either side detached the link
+    pub(crate) fn on_session_ended(&self) {
+        self.first(|| DetachedBy::SessionEnded);
+        self.session_ended.send_replace(true);
+    }
+
+    /// The link is attached again
+    pub(crate) fn on_attached(&self) {
+        self.tx.send_replace(DetachedBy::None);
+        self.session_ended.send_replace(false);
+    }
+
+    /// Returns a future that resolves once the session ends or the link is dropped
+    ///
+    /// The link is implicitly detached when the session ends, including when the connection is
+    /// lost, even though its local state is left as is.
+    pub(crate) fn session_ended(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut rx = self.session_ended.subscribe();
+        async move {
+            // An error is returned only if the link has been dropped
+            let _ = rx.wait_for(|ended| *ended).await;
+        }
+    }
+
+    /// Returns a future that resolves with the error of the remote Detach if the remote peer
+    /// detaches the link first, or with `None` if the link is detached locally first, the session
+    /// ends or the link is dropped
+    pub(crate) fn wait(&self) -> impl Future<Output = Option<DetachError>> + Send + 'static {
+        let mut rx = self.tx.subscribe();
+        async move {
+            let detached_by = rx
+                .wait_for(|state| !matches!(state, DetachedBy::None))
+                .await
+                .ok()?
+                .clone();
+            match detached_by {
+                DetachedBy::Remote(detach) => Some(match (detach.closed, detach.error) {
+                    (true, Some(error)) => DetachError::RemoteClosedWithError(error),
+                    (true, None) => DetachError::ClosedByRemote,
+                    (false, Some(error)) => DetachError::RemoteDetachedWithError(error),
+                    (false, None) => DetachError::DetachedByRemote,
+                }),
+                DetachedBy::None | DetachedBy::Local | DetachedBy::SessionEnded => None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fe2o3_amqp_types::{
+        definitions::{self, AmqpError},
+        performatives::Detach,
+    };
+    use futures_util::poll;
+
+    use crate::link::DetachError;
+
+    use super::RemoteDetachNotifier;
+
+    fn detach(closed: bool, error: Option<definitions::Error>) -> Detach {
+        Detach {
+            handle: 0.into(),
+            closed,
+            error,
+        }
+    }
+
+    #[tokio::test]
+    async fn only_the_first_detach_is_notified() {
+        let notifier = RemoteDetachNotifier::default();
+        let wait = notifier.wait();
+        let error = definitions::Error::new(AmqpError::ResourceDeleted, None, None);
+        notifier.on_remote_detach(&detach(true, Some(error.clone())));
+        notifier.on_local_detach();
+        assert!(matches!(
+            wait.await,
+            Some(DetachError::RemoteClosedWithError(e)) if e == error
+        ));
+
+        // The Detach of the remote peer is a reply once the link has sent its own
+        notifier.on_attached();
+        let wait = notifier.wait();
+        notifier.on_local_detach();
+        notifier.on_remote_detach(&detach(false, None));
+        assert!(wait.await.is_none());
+
+        notifier.on_attached();
+        let wait = notifier.wait();
+        notifier.on_remote_detach(&detach(false, None));
+        assert!(matches!(wait.await, Some(DetachError::DetachedByRemote)));
+
+        // The future resolves right away if the remote peer has already detached
+        assert!(matches!(
+            notifier.wait().await,
+            Some(DetachError::DetachedByRemote)
+        ));
+
+        notifier.on_attached();
+        let wait = notifier.wait();
+        notifier.on_session_ended();
+        assert!(wait.await.is_none());
+
+        notifier.on_attached();
+        let wait = notifier.wait();
+        drop(notifier);
+        assert!(wait.await.is_none());
+    }
+
+    #[tokio::test]
+    async fn session_end_is_notified_after_local_detach() {
+        let notifier = RemoteDetachNotifier::default();
+        let ended = notifier.session_ended();
+        notifier.on_local_detach();
+        notifier.on_session_ended();
+        ended.await;
+
+        // A stale session end is forgotten once the link is attached again
+        notifier.on_attached();
+        let ended = notifier.session_ended();
+        tokio::pin!(ended);
+        assert!(poll!(&mut ended).is_pending());
+        drop(notifier);
+        ended.await;
+    }
+}
@@ -0,0 +1,65 @@
+//! Deliveries disposed by a receiver in `ReceiverSettleMode::Second` that wait for the sender to
+//! settle
+
+use std::{collections::HashMap, sync::Arc};
+
+use fe2o3_amqp_types::definitions::DeliveryTag;
+use parking_lot::Mutex;
+use tokio::sync::oneshot;
+
+pub(crate) type ArcRemoteSettlements = Arc<RemoteSettlements>;
+
+/// Shared by the receiver, which registers a delivery before sending its unsettled disposition,
+/// and the link relay in the session, which notifies the receiver once the sender settles the
+/// delivery
+#[derive(Debug, Default)]
+pub(crate) struct RemoteSettlements {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    /// Whether the remote peer has detached the link since it was last attached
+    detached: bool,
+    waiting: HashMap<DeliveryTag, oneshot::Sender<()>>,
+}
+
+impl RemoteSettlements {
+    /// Registers a delivery that waits for the sender to settle
+    ///
+    /// Returns `None` if the link is already detached by the remote peer
+    pub(crate) fn register(&self, delivery_tag: DeliveryTag) -> Option<oneshot::Receiver<()>> {
+        let mut inner = self.inner.lock();
+        if inner.detached {
+            return None;
+        }
+        let (tx, rx) = oneshot::channel();
+        inner.waiting.insert(delivery_tag, tx);
+        Some(rx)
+    }
+
+    /// Stops waiting for the sender to settle the delivery
+    pub(crate) fn deregister(&self, delivery_tag: &DeliveryTag) {
+        self.inner.lock().waiting.remove(delivery_tag);
+    }
+
+    /// The sender has settled the delivery
+    pub(crate) fn settle(&self, delivery_tag: &DeliveryTag) {
+        if let Some(tx) = self.inner.lock().waiting.remove(delivery_tag) {
+            let _ = tx.send(());
+        }
+    }
+
+    /// The remote peer has detached the link. The deliveries that are still waiting fail as the
+    /// sender can no longer settle them on this attachment
+    pub(crate) fn on_detached(&self) {
+        let mut inner = self.inner.lock();
+        inner.detached = true;
+        inner.waiting.clear();
+    }
+
+    /// The link is attached again
+    pub(crate) fn on_attached(&self) {
+        self.inner.lock().detached = false;
+    }
+}
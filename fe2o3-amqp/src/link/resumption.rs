@@ -6,7 +6,7 @@ use tokio::sync::oneshot;
 
 use crate::Payload;
 
-use super::{delivery::UnsettledMessage, receiver_link::is_section_header};
+use super::{delivery::UnsettledMessage, receiver_link::SectionHeaders};
 
 pub(crate) enum ResumingDelivery {
     Abort {
@@ -19,7 +19,7 @@ pub(crate) enum ResumingDelivery {
         payload: Payload,
         local_state: DeliveryState,
         message_format: MessageFormat,
-        sender: oneshot::Sender<Option<DeliveryState>>,
+        sender: Option<oneshot::Sender<Option<DeliveryState>>>,
     },
 }
 
@@ -45,7 +45,7 @@ pub(crate) fn resume_delivery(
             // Illegal delivery states?
             Some(ResumingDelivery::Abort {
                 message_format: local.message_format,
-                sender: Some(local.sender),
+                sender: local.sender,
             })
         }
 
@@ -115,7 +115,7 @@ pub(crate) fn resume_delivery(
                 // which is equivalent to (0, 0)
                 Some(ResumingDelivery::Abort {
                     message_format: local.message_format,
-                    sender: Some(local.sender),
+                    sender: local.sender,
                 })
             }
         }
@@ -168,7 +168,7 @@ pub(crate) fn resume_delivery(
         | (Some(DeliveryState::Released(_)), Some(DeliveryState::Received(_))) => {
             Some(ResumingDelivery::Abort {
                 message_format: local.message_format,
-                sender: Some(local.sender),
+                sender: local.sender,
             })
         }
 
@@ -205,16 +205,13 @@ fn split_off_at_section_and_offset(
     section: usize,
     offset: usize,
 ) -> Option<Payload> {
-    let b0 = payload.iter();
-    let b1 = payload.iter().skip(1);
-    let b2 = payload.iter().skip(2);
-    let zip = b0.zip(b1.zip(b2));
+    let headers = SectionHeaders::new(payload.iter());
 
     let mut section_counter = None;
     let mut last_section_index = 0;
 
-    for (i, (&b0, (&b1, &b2))) in zip.enumerate() {
-        if is_section_header(b0, b1, b2) {
+    for (i, is_header) in headers.enumerate() {
+        if is_header {
             match &mut section_counter {
                 Some(value) => *value += 1,
                 None => section_counter = Some(0),
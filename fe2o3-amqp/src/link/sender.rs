@@ -1,18 +1,31 @@
 //! Implementation of AMQP1.0 sender
 
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Instant,
+};
+
 use bytes::{Bytes, BytesMut};
-use tokio::sync::{mpsc, oneshot};
+use futures_util::FutureExt;
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio_util::sync::CancellationToken;
 
 cfg_not_wasm32! {
     use std::time::Duration;
-    use tokio::time::{error::Elapsed, timeout};
+    use crate::rt::{timeout, Elapsed};
+    use super::state::LinkFlowState;
 }
 
 use fe2o3_amqp_types::{
-    definitions::{self, DeliveryTag, Fields, MessageFormat, SenderSettleMode},
+    definitions::{
+        self, DeliveryNumber, DeliveryTag, Fields, MessageFormat, ReceiverSettleMode,
+        SenderSettleMode,
+    },
     messaging::{
-        message::__private::Serializable, Address, DeliveryState, Outcome, SerializableBody,
-        Source, Target,
+        message::__private::Serializable, Address, DeliveryState, Message, SerializableBody,
+        Source, Target, MESSAGE_FORMAT,
     },
     performatives::{Attach, Detach, Transfer},
     primitives::OrderedMap,
@@ -20,29 +33,31 @@ use fe2o3_amqp_types::{
 
 use crate::{
     control::SessionControl,
-    endpoint::{self, LinkAttach, LinkDetach, LinkExt, Settlement},
+    endpoint::{self, InputHandle, LinkAttach, LinkDetach, LinkExt, Settlement},
     session::SessionHandle,
     Payload,
 };
 
 use super::{
     builder::{self, WithSource, WithoutName, WithoutTarget},
-    delivery::{DeliveryFut, Sendable, UnsettledMessage},
+    confirm::ConfirmFailure,
+    delivery::{DeliveryFut, MessageMut, RawDelivery, SendReceipt, Sendable, UnsettledMessage},
     error::DetachError,
     resumption::ResumingDelivery,
     role,
     shared_inner::{
         recv_remote_detach, LinkEndpointInner, LinkEndpointInnerDetach, LinkEndpointInnerReattach,
     },
-    ArcSenderUnsettledMap, DetachThenResumeSenderError, LinkFrame, LinkRelay, LinkStateError,
-    SendError, SenderAttachError, SenderAttachExchange, SenderFlowState, SenderLink,
-    SenderResumeError, SenderResumeErrorKind,
+    state::{LinkFlowSnapshot, LinkState},
+    unsettled_len,
+    validation::{BatchValidationPolicy, ValidationError, ValidatorChain},
+    ArcSenderUnsettledMap, CreditTimeout, DetachThenResumeSenderError, LinkFrame, LinkRelay,
+    LinkStateError, SendError, SenderAttachError, SenderAttachExchange, SenderFlowState,
+    SenderLink, SenderResumeError, SenderResumeErrorKind,
 };
 
 #[cfg(docsrs)]
-use fe2o3_amqp_types::messaging::{
-    AmqpSequence, AmqpValue, Batch, Body, Data, IntoBody, Message, MESSAGE_FORMAT,
-};
+use fe2o3_amqp_types::messaging::{AmqpSequence, AmqpValue, Batch, Body, Data, IntoBody};
 
 /// An AMQP1.0 sender
 ///
@@ -55,13 +70,13 @@ use fe2o3_amqp_types::messaging::{
 ///     "q1"                    // Target address
 /// ).await.unwrap();
 ///
-/// let outcome = sender.send("hello AMQP").await.unwrap();
+/// let receipt = sender.send("hello AMQP").await.unwrap();
 ///
 /// // Checks the outcome of delivery
-/// if outcome.is_accepted() {
-///     tracing::info!("Outcome: {:?}", outcome)
-/// } else {
-///     tracing::error!("Outcome: {:?}", outcome)
+/// match receipt {
+///     SendReceipt::Accepted(_) => tracing::info!("Accepted"),
+///     SendReceipt::Settled => tracing::info!("Settled by the sender, no outcome will be returned"),
+///     _ => tracing::error!("Outcome: {:?}", receipt),
 /// }
 ///
 /// sender.close().await.unwrap();
@@ -101,7 +116,17 @@ pub struct Sender {
 
 impl std::fmt::Debug for Sender {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Sender").finish()
+        let source = self.source().as_ref().and_then(|s| s.address.as_ref());
+        let target = self.target().as_ref().and_then(|t| t.address.as_ref());
+        f.debug_struct("Sender")
+            .field("name", &self.name())
+            .field("state", self.local_state())
+            .field("snd_settle_mode", self.snd_settle_mode())
+            .field("rcv_settle_mode", self.rcv_settle_mode())
+            .field("source", &source)
+            .field("target", &target)
+            .field("flow", &self.flow_snapshot())
+            .finish()
     }
 }
 
@@ -117,12 +142,52 @@ impl Sender {
         self.inner.link.name()
     }
 
+    /// Returns the current state of the link
+    pub fn local_state(&self) -> &LinkState {
+        &self.inner.link.local_state
+    }
+
+    /// Returns the sender settle mode in use, which is the one in the attach of the sender once
+    /// the attach is exchanged
+    pub fn snd_settle_mode(&self) -> &SenderSettleMode {
+        &self.inner.link.snd_settle_mode
+    }
+
+    /// Returns the receiver settle mode in use, which is the one in the attach of the receiver
+    /// once the attach is exchanged
+    pub fn rcv_settle_mode(&self) -> &ReceiverSettleMode {
+        &self.inner.link.rcv_settle_mode
+    }
+
     /// Returns the `max_message_size` of the link. A value of zero indicates that the link has no
     /// maximum message size, and thus a zero value is turned into a `None`
     pub fn max_message_size(&self) -> Option<u64> {
         self.inner.link.max_message_size()
     }
 
+    /// Returns a snapshot of the link credit, delivery count, available and drain flag of the
+    /// link, together with the number of unsettled outgoing deliveries
+    ///
+    /// This does not change the state of the link.
+    pub fn flow_snapshot(&self) -> LinkFlowSnapshot {
+        let unsettled = unsettled_len(self.inner.link.unsettled());
+        self.inner.link.flow_state().state().snapshot(unsettled)
+    }
+
+    /// Returns the instant at which the last Disposition for this link arrived from the remote
+    /// peer, or `None` if none has arrived yet
+    ///
+    /// The instant is recorded by the session event loop, so it is up to date even if no send is
+    /// being awaited. It follows the tokio clock and is always `None` on wasm32.
+    pub fn last_disposition_at(&self) -> Option<Instant> {
+        self.inner
+            .link
+            .flow_state()
+            .state()
+            .last_disposition_at
+            .get()
+    }
+
     /// Get a reference to the link's source field
     pub fn source(&self) -> &Option<Source> {
         &self.inner.link.source
@@ -159,6 +224,26 @@ impl Sender {
         self.inner.link.properties_mut(op)
     }
 
+    /// Replaces the properties of the link, which are carried by every Flow sent from now on
+    ///
+    /// The properties are shared with the session event loop, which reads them when it sends a
+    /// Flow, so they can be changed while the link is in use. No Flow is sent by this method.
+    /// These are the same properties that [`properties_mut`](Self::properties_mut) gives access
+    /// to, which are also sent in the Attach frame when the link is (re-)attached.
+    pub fn set_flow_properties(&mut self, properties: Fields) {
+        self.inner
+            .link
+            .flow_state()
+            .state()
+            .set_properties(Some(properties))
+    }
+
+    /// Returns the properties of the last Flow from the remote peer that carried any, or `None`
+    /// if no such Flow has arrived yet
+    pub fn remote_flow_properties(&self) -> Option<Fields> {
+        self.inner.link.flow_state().state().remote_properties()
+    }
+
     /// Attach the sender link to a session with default configuration
     /// with the `name` and `target` address set to the specified values
     ///
@@ -189,6 +274,18 @@ impl Sender {
     /// ).await.unwrap();
     /// ```
     ///
+    /// If the remote peer refuses the attach, the Detach sent by the remote peer is returned in
+    /// [`SenderAttachError::RemoteDetached`]
+    ///
+    /// ```rust,ignore
+    /// match Sender::attach(&mut session, "rust-sender-link-1", "q1").await {
+    ///     Ok(sender) => { /* ... */ }
+    ///     Err(SenderAttachError::RemoteDetached(detach)) => {
+    ///         println!("Attach refused with {:?}", detach.error);
+    ///     }
+    ///     Err(error) => println!("Attach failed: {}", error),
+    /// }
+    /// ```
     pub async fn attach<R>(
         session: &mut SessionHandle<R>,
         name: impl Into<String>,
@@ -201,6 +298,127 @@ impl Sender {
             .await
     }
 
+    /// Attach the link to a session with a generated unique name
+    ///
+    /// The name is `fe2o3-sender-<uuid>`, see [`auto_name`](crate::link::builder::Builder::auto_name)
+    /// to use a different prefix. The generated name can be retrieved with
+    /// [`name`](#method.name).
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let sender = Sender::attach_auto_name(
+    ///     &mut session,           // mutable reference to SessionHandle
+    ///     "q1"                    // Target address
+    /// ).await.unwrap();
+    /// ```
+    pub async fn attach_auto_name<R>(
+        session: &mut SessionHandle<R>,
+        addr: impl Into<Address>,
+    ) -> Result<Sender, SenderAttachError> {
+        Self::builder()
+            .auto_name("fe2o3-sender")
+            .target(addr)
+            .attach(session)
+            .await
+    }
+
+    /// Attach an anonymous sender link to a session with the `name` set to the specified value
+    ///
+    /// The link is attached with a [`Target`] whose address is null and desires the
+    /// [`ANONYMOUS_RELAY`](super::ANONYMOUS_RELAY) capability. The attach will fail with
+    /// [`SenderAttachError::AnonymousRelayNotSupported`] if the remote peer does not offer the
+    /// capability. Messages sent over an anonymous link are routed by the `to` field of their
+    /// properties, see [`send_to`](#method.send_to).
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let mut sender = Sender::attach_anonymous(
+    ///     &mut session,               // mutable reference to SessionHandle
+    ///     "rust-anonymous-sender",    // link name
+    /// ).await.unwrap();
+    ///
+    /// sender.send_to("q1", "hello AMQP").await.unwrap();
+    /// ```
+    pub async fn attach_anonymous<R>(
+        session: &mut SessionHandle<R>,
+        name: impl Into<String>,
+    ) -> Result<Sender, SenderAttachError> {
+        Self::builder()
+            .name(name)
+            .target(Target::default())
+            .add_desired_capabilities(super::ANONYMOUS_RELAY)
+            .attach(session)
+            .await
+    }
+
+    /// Returns a future that resolves once the link has been detached or closed
+    ///
+    /// The future resolves when the exchange of Detach performatives completes, regardless of which
+    /// side initiated it, when the session ends, which implicitly detaches the link, or when the
+    /// link is dropped. The returned future does not borrow the link and thus can be awaited from
+    /// a different task.
+    pub fn detached(&self) -> impl Future<Output = ()> + Send + 'static {
+        let session_ended = self
+            .inner
+            .link
+            .flow_state()
+            .state()
+            .remote_detach
+            .session_ended();
+        self.inner
+            .link
+            .wait_for_local_state(session_ended, |state| {
+                matches!(state, LinkState::Detached | LinkState::Closed)
+            })
+    }
+
+    /// Returns a future that resolves once the link has been closed
+    ///
+    /// The future resolves when the exchange of closing Detach performatives completes, regardless
+    /// of which side initiated it, when the session ends, after which the link can no longer be
+    /// closed, or when the link is dropped. The returned future does not borrow the link and thus
+    /// can be awaited from a different task.
+    pub fn closed(&self) -> impl Future<Output = ()> + Send + 'static {
+        let session_ended = self
+            .inner
+            .link
+            .flow_state()
+            .state()
+            .remote_detach
+            .session_ended();
+        self.inner
+            .link
+            .wait_for_local_state(session_ended, |state| matches!(state, LinkState::Closed))
+    }
+
+    /// Returns a future that resolves with the error carried by the Detach of the remote peer
+    /// once the remote peer detaches or closes the link
+    ///
+    /// Unlike [`on_detach`](#method.on_detach), this does not borrow the link and resolves as
+    /// soon as the session receives the Detach, even if the link is idle, so that another task
+    /// can re-attach or raise an alert right away. A `send` that is waiting at the same time
+    /// fails with the same error. The future resolves with `None` if the link is detached or
+    /// closed locally first, if the session ends or if the link is dropped. Once the link is
+    /// attached again, a new future waits for the next Detach.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let detached = sender.on_detach_by_remote();
+    /// tokio::spawn(async move {
+    ///     if let Some(error) = detached.await {
+    ///         eprintln!("The broker detached the sender: {:?}", error);
+    ///     }
+    /// });
+    /// ```
+    pub fn on_detach_by_remote(
+        &self,
+    ) -> impl Future<Output = Option<DetachError>> + Send + 'static {
+        self.inner.link.flow_state().state().remote_detach.wait()
+    }
+
     /// Detach the link
     ///
     /// The Sender will send a detach frame with closed field set to false,
@@ -378,23 +596,155 @@ impl Sender {
     ///     .message("hello AMQP")
     ///     .settled(true)
     ///     .build();
-    /// let outcome = sender.send(sendable).await.unwrap():
+    /// let receipt = sender.send(sendable).await.unwrap();
+    /// assert!(receipt.is_settled());
     /// ```
     ///
+    /// # Send receipt
+    ///
+    /// The returned [`SendReceipt`] is [`SendReceipt::Settled`] if the delivery is settled by the
+    /// sender, in which case the receiver will never report an outcome. Otherwise, it carries the
+    /// outcome reported by the receiver. A `Rejected` outcome is returned as
+    /// `Err(SendError::Rejected(_))` instead if the sender is built with
+    /// [`rejected_as_error(true)`](crate::link::builder::Builder::rejected_as_error).
+    ///
     /// # Cancel safety
     ///
-    /// This function is cancel-safe. See [#22](https://github.com/minghuaw/fe2o3-amqp/issues/22)
-    /// for more details.
+    /// This function is cancel-safe. What dropping the future leaves behind depends on how far
+    /// the send has got (see also [#22](https://github.com/minghuaw/fe2o3-amqp/issues/22)):
+    ///
+    /// - Before the first transfer frame is handed to the session, which includes waiting for
+    ///   link credit, nothing is consumed. No link credit or delivery tag is used and the message
+    ///   is not sent.
+    /// - After the first frame of a delivery that is split into several transfer frames but before
+    ///   its last one, the delivery is aborted with an aborted transfer before the link sends
+    ///   anything else, including a Detach. An aborted delivery is implicitly settled.
+    /// - After the last frame, the delivery proceeds and stays in the unsettled map. Its outcome
+    ///   can be queried with [`pending_outcomes`](#method.pending_outcomes).
+    ///
+    /// Use [`send_cancellable`](#method.send_cancellable) to abort a split delivery right away
+    /// when cancelling.
     pub async fn send<T: SerializableBody>(
         &mut self,
         sendable: impl Into<Sendable<T>>,
-    ) -> Result<Outcome, SendError> {
-        let fut = self
+    ) -> Result<SendReceipt, SendError> {
+        let settlement = self
             .inner
             .send_with_state::<T, SendError>(sendable.into(), None, false)
-            .await
-            .map(DeliveryFut::from)?;
-        fut.await
+            .await?;
+        self.tracked_outcome(settlement).await
+    }
+
+    /// Like [`send()`](#method.send) but stops once `token` is cancelled, returning
+    /// `Err(SendError::Cancelled)`
+    ///
+    /// Cancelling has the same effect as dropping the future returned by `send()`, except that a
+    /// delivery that is split into several transfer frames and cut off by the cancellation is
+    /// aborted before this returns. Nothing is sent if the token is already cancelled.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let token = CancellationToken::new();
+    /// match sender.send_cancellable(token.clone(), "hello").await {
+    ///     Ok(receipt) => println!("{:?}", receipt),
+    ///     Err(SendError::Cancelled) => {
+    ///         // The outcome of a delivery that was already sent arrives later
+    ///         let pending = sender.pending_outcomes();
+    ///     }
+    ///     Err(error) => return Err(error),
+    /// }
+    /// ```
+    pub async fn send_cancellable<T: SerializableBody>(
+        &mut self,
+        token: CancellationToken,
+        sendable: impl Into<Sendable<T>>,
+    ) -> Result<SendReceipt, SendError> {
+        let settlement = tokio::select! {
+            biased;
+            _ = token.cancelled() => {
+                let inner = &mut self.inner;
+                endpoint::SenderLink::abort_incomplete_delivery(&mut inner.link, &inner.outgoing)
+                    .await?;
+                return Err(SendError::Cancelled);
+            }
+            settlement = self.inner.send_with_state::<T, SendError>(sendable.into(), None, false) => {
+                settlement?
+            }
+        };
+
+        let outcome = self.tracked_outcome(settlement);
+        tokio::select! {
+            biased;
+            _ = token.cancelled() => Err(SendError::Cancelled),
+            result = outcome => result,
+        }
+    }
+
+    /// Returns the deliveries whose send was dropped or cancelled after the delivery was handed to
+    /// the session
+    ///
+    /// A delivery whose outcome has arrived is returned with the outcome once and is no longer
+    /// tracked. The others are returned with `None` and are still tracked.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// for pending in sender.pending_outcomes() {
+    ///     if let Some(outcome) = pending.outcome {
+    ///         println!("{:?}: {:?}", pending.delivery_tag, outcome);
+    ///     }
+    /// }
+    /// ```
+    pub fn pending_outcomes(&mut self) -> Vec<PendingOutcome> {
+        let tracked = std::mem::take(&mut self.inner.pending_outcomes);
+        let mut pending_outcomes = Vec::with_capacity(tracked.len());
+        for (delivery_tag, outcome) in tracked {
+            let settlement = Settlement::Unsettled {
+                delivery_tag: delivery_tag.clone(),
+                outcome,
+            };
+            let mut delivery = self.delivery_fut(settlement);
+            let outcome = (&mut delivery).now_or_never();
+            if outcome.is_none() {
+                if let Settlement::Unsettled {
+                    delivery_tag,
+                    outcome,
+                } = delivery.into_settlement()
+                {
+                    self.inner.pending_outcomes.push((delivery_tag, outcome));
+                }
+            }
+            pending_outcomes.push(PendingOutcome {
+                delivery_tag,
+                outcome,
+            });
+        }
+        pending_outcomes
+    }
+
+    /// Send a message to the node at `addr` and wait for acknowledgement (disposition)
+    ///
+    /// This sets the `to` field of the message properties before sending and is intended to be
+    /// used with a sender attached with [`attach_anonymous`](#method.attach_anonymous).
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let outcome = sender.send_to("q1", "hello AMQP").await.unwrap();
+    /// ```
+    pub async fn send_to<T: SerializableBody>(
+        &mut self,
+        addr: impl Into<Address>,
+        sendable: impl Into<Sendable<T>>,
+    ) -> Result<SendReceipt, SendError> {
+        let mut sendable = sendable.into();
+        sendable
+            .message
+            .properties
+            .get_or_insert_with(Default::default)
+            .to = Some(addr.into());
+        self.send(sendable).await
     }
 
     /// Like [`send()`](#method.send) but takes a reference to the message
@@ -404,13 +754,68 @@ impl Sender {
     pub async fn send_ref<T: SerializableBody>(
         &mut self,
         sendable: &Sendable<T>,
-    ) -> Result<Outcome, SendError> {
-        let fut = self
+    ) -> Result<SendReceipt, SendError> {
+        let settlement = self
             .inner
             .send_ref_with_state::<T, SendError>(sendable, None, false)
+            .await?;
+        self.tracked_outcome(settlement).await
+    }
+
+    /// Forward a delivery received with [`Receiver::recv_raw`](crate::Receiver::recv_raw) and
+    /// wait for acknowledgement (disposition)
+    ///
+    /// The encoded message is sent as is, so the receiving peer gets the exact bytes of each
+    /// section. Delivery annotations are only meant for the immediate next hop and should be
+    /// removed with [`RawDelivery::strip_delivery_annotations`] before forwarding. Whether the
+    /// message is sent pre-settled depends on the settle mode of this sender and not on whether
+    /// the delivery was settled by the previous hop.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let mut delivery = receiver.recv_raw().await.unwrap();
+    /// delivery.strip_delivery_annotations().unwrap();
+    /// let receipt = sender.forward(&delivery).await.unwrap();
+    /// receiver.accept(&delivery).await.unwrap();
+    /// ```
+    pub async fn forward(&mut self, delivery: &RawDelivery) -> Result<SendReceipt, SendError> {
+        self.forward_payload(delivery.payload.clone(), delivery.message_format)
             .await
-            .map(DeliveryFut::from)?;
-        fut.await
+    }
+
+    /// Send a message whose sections are changed with [`MessageMut`] and wait for
+    /// acknowledgement
+    ///
+    /// Like [`forward`](#method.forward), the untouched sections are sent with the exact bytes
+    /// they were received with.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let delivery: Delivery<Body<Value>> = receiver.recv().await.unwrap();
+    /// let mut message = delivery.message_mut().unwrap();
+    /// message.set(&ApplicationProperties::builder().insert("hops", 1u32).build()).unwrap();
+    /// let receipt = sender.forward_edited(message).await.unwrap();
+    /// receiver.accept(&delivery).await.unwrap();
+    /// ```
+    pub async fn forward_edited(&mut self, message: MessageMut) -> Result<SendReceipt, SendError> {
+        let message_format = *message.message_format();
+        self.forward_payload(message.into_payload(), message_format)
+            .await
+    }
+
+    async fn forward_payload(
+        &mut self,
+        payload: Payload,
+        message_format: Option<MessageFormat>,
+    ) -> Result<SendReceipt, SendError> {
+        let message_format = message_format.unwrap_or(MESSAGE_FORMAT);
+        let settlement = self
+            .inner
+            .send_payload::<SendError>(payload, message_format, None, None, false, false)
+            .await?;
+        self.tracked_outcome(settlement).await
     }
 
     cfg_not_wasm32! {
@@ -421,7 +826,7 @@ impl Sender {
             &mut self,
             sendable: impl Into<Sendable<T>>,
             duration: Duration,
-        ) -> Result<Result<Outcome, SendError>, Elapsed> {
+        ) -> Result<Result<SendReceipt, SendError>, Elapsed> {
             timeout(duration, self.send(sendable)).await
         }
     }
@@ -440,11 +845,11 @@ impl Sender {
     pub async fn send_batchable<T: SerializableBody>(
         &mut self,
         sendable: impl Into<Sendable<T>>,
-    ) -> Result<DeliveryFut<Result<Outcome, SendError>>, SendError> {
+    ) -> Result<DeliveryFut<Result<SendReceipt, SendError>>, SendError> {
         self.inner
             .send_with_state(sendable.into(), None, true)
             .await
-            .map(DeliveryFut::from)
+            .map(|settlement| self.delivery_fut(settlement))
     }
 
     /// Like [`send_batchable()`](#method.send_batchable) but this only takes a reference.
@@ -454,11 +859,269 @@ impl Sender {
     pub async fn send_batchable_ref<T: SerializableBody>(
         &mut self,
         sendable: &Sendable<T>,
-    ) -> Result<DeliveryFut<Result<Outcome, SendError>>, SendError> {
+    ) -> Result<DeliveryFut<Result<SendReceipt, SendError>>, SendError> {
         self.inner
             .send_ref_with_state(sendable, None, true)
             .await
-            .map(DeliveryFut::from)
+            .map(|settlement| self.delivery_fut(settlement))
+    }
+
+    /// Send a batch of messages without waiting for the acknowledgements
+    ///
+    /// Every message is encoded and validated before any of them is sent. If the sender is built
+    /// with [`BatchValidationPolicy::FailBatch`], which is the default, nothing is sent and
+    /// `Err(SendError::Validation(_))` is returned for the first message that fails validation.
+    /// With [`BatchValidationPolicy::SkipInvalid`], the invalid messages are skipped and their
+    /// entries in the returned `Vec` carry the [`ValidationError`].
+    ///
+    /// The returned `Vec` has one entry for each message of the batch, in order. The messages are
+    /// sent with the batchable field of the `Transfer` performative set, except for the last one.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let results = sender.send_batch(["hello", "AMQP"]).await.unwrap();
+    /// for result in results {
+    ///     let receipt = result.unwrap().await.unwrap();
+    /// }
+    /// ```
+    pub async fn send_batch<T, I>(
+        &mut self,
+        sendables: I,
+    ) -> Result<Vec<Result<DeliveryFut<Result<SendReceipt, SendError>>, ValidationError>>, SendError>
+    where
+        T: SerializableBody,
+        I: IntoIterator,
+        I::Item: Into<Sendable<T>>,
+    {
+        let mut encoded = Vec::new();
+        for sendable in sendables {
+            let Sendable {
+                message,
+                message_format,
+                settled,
+            } = sendable.into();
+            let payload = self.inner.encode_message(&message)?;
+            match self
+                .inner
+                .validator
+                .validate_message(&message, payload.len())
+            {
+                Ok(()) => encoded.push(Ok((payload, message_format, settled))),
+                Err(error) => match self.inner.batch_validation_policy {
+                    BatchValidationPolicy::FailBatch => return Err(error.into()),
+                    BatchValidationPolicy::SkipInvalid => encoded.push(Err(error)),
+                },
+            }
+        }
+
+        let last_valid = encoded.iter().rposition(|entry| entry.is_ok());
+        let mut results = Vec::with_capacity(encoded.len());
+        for (index, entry) in encoded.into_iter().enumerate() {
+            let result = match entry {
+                Ok((payload, message_format, settled)) => {
+                    let batchable = Some(index) != last_valid;
+                    let settlement = self
+                        .inner
+                        .send_payload::<SendError>(
+                            payload,
+                            message_format,
+                            settled,
+                            None,
+                            batchable,
+                            false,
+                        )
+                        .await?;
+                    Ok(self.delivery_fut(settlement))
+                }
+                Err(error) => Err(error),
+            };
+            results.push(result);
+        }
+        Ok(results)
+    }
+
+    /// Send a message whose outcome is only reported through the
+    /// [`confirm_watermark`](#method.confirm_watermark)
+    ///
+    /// No future is allocated for the outcome of the delivery, which makes this cheaper than
+    /// [`send_batchable`](#method.send_batchable) when the application only needs to know how far
+    /// the receiver has accepted the messages, eg. to advance the cursor of its input. The
+    /// returned delivery tag is the one carried by a [`ConfirmFailure`] if the receiver does not
+    /// accept the delivery. The message is sent with the batchable field of the `Transfer`
+    /// performative set.
+    ///
+    /// A message that is sent pre-settled is not tracked by the watermark.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let mut watermark = sender.confirm_watermark();
+    /// for line in lines {
+    ///     sender.send_unconfirmed(line).await.unwrap();
+    /// }
+    /// watermark.changed().await.unwrap();
+    /// ```
+    pub async fn send_unconfirmed<T: SerializableBody>(
+        &mut self,
+        sendable: impl Into<Sendable<T>>,
+    ) -> Result<DeliveryTag, SendError> {
+        let Sendable {
+            message,
+            message_format,
+            settled,
+        } = sendable.into();
+        let payload = self.inner.encode_message(&message)?;
+        self.inner
+            .validator
+            .validate_message(&message, payload.len())?;
+
+        self.inner.link.flow_state().state().confirms.enable();
+        let settlement = self
+            .inner
+            .send_payload::<SendError>(payload, message_format, settled, None, true, true)
+            .await?;
+        match settlement {
+            Settlement::Settled(delivery_tag)
+            | Settlement::Unconfirmed(delivery_tag)
+            | Settlement::Unsettled { delivery_tag, .. } => Ok(delivery_tag),
+        }
+    }
+
+    /// Returns the delivery id up to which the receiver has accepted every delivery of this
+    /// sender
+    ///
+    /// The watermark advances once the deliveries before it are all accepted, whether their
+    /// dispositions arrive in order, out of order or as ranges. The delivery ids are assigned by
+    /// the session, so the ids of the deliveries of the other links on the same session are
+    /// skipped. Only the unsettled deliveries sent after the watermark or the
+    /// [`confirm_failures`](#method.confirm_failures) are first asked for are tracked, and the
+    /// watermark starts just before the first of them.
+    ///
+    /// A delivery that is not accepted holds the watermark back until it is dismissed with
+    /// [`dismiss_confirm_failure`](#method.dismiss_confirm_failure). This is reported through
+    /// the [`confirm_failures`](#method.confirm_failures) so that the message can be sent again.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let mut watermark = sender.confirm_watermark();
+    /// while watermark.changed().await.is_ok() {
+    ///     let delivery_id = *watermark.borrow_and_update();
+    ///     cursor.advance_to(delivery_id);
+    /// }
+    /// ```
+    pub fn confirm_watermark(&self) -> watch::Receiver<DeliveryNumber> {
+        self.inner.link.flow_state().state().confirms.watermark()
+    }
+
+    /// Returns the deliveries tracked by the [`confirm_watermark`](#method.confirm_watermark)
+    /// that the receiver rejected, released or modified, or that were still waiting for an
+    /// outcome when the link was detached
+    ///
+    /// This returns `None` if it has been called before.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let mut failures = sender.confirm_failures().unwrap();
+    /// while let Some(failure) = failures.recv().await {
+    ///     let message = outstanding.remove(&failure.delivery_tag).unwrap();
+    ///     let delivery_tag = sender.send_unconfirmed(message).await?;
+    ///     sender.dismiss_confirm_failure(&failure);
+    /// }
+    /// ```
+    pub fn confirm_failures(&self) -> Option<mpsc::UnboundedReceiver<ConfirmFailure>> {
+        self.inner.link.flow_state().state().confirms.failures()
+    }
+
+    /// Lets the [`confirm_watermark`](#method.confirm_watermark) advance past a delivery that
+    /// was not accepted, once the application has dealt with it, eg. by sending the message again
+    pub fn dismiss_confirm_failure(&self, failure: &ConfirmFailure) {
+        self.inner
+            .link
+            .flow_state()
+            .state()
+            .confirms
+            .dismiss(failure.delivery_id)
+    }
+
+    cfg_transaction! {
+        /// Post a message in a transaction without waiting for the transaction to be discharged
+        ///
+        /// The state of the transfer carries the id of the transaction, and the resource does not
+        /// make the message available until the transaction is committed. The returned
+        /// [`TxnDelivery`](crate::transaction::TxnDelivery) resolves once the transaction is
+        /// discharged, to the outcome reported by the resource if it is committed or to
+        /// `Released` if it is rolled back.
+        ///
+        /// # Example
+        ///
+        /// ```rust,ignore
+        /// let mut txn = Transaction::declare(&controller, None).await.unwrap();
+        /// let delivery = sender.send_txn(&mut txn, "hello").await.unwrap();
+        /// txn.commit().await.unwrap();
+        /// assert!(delivery.await.unwrap().is_accepted());
+        /// ```
+        pub async fn send_txn<T, Txn>(
+            &mut self,
+            txn: &mut Txn,
+            sendable: impl Into<Sendable<T>>,
+        ) -> Result<crate::transaction::TxnDelivery, crate::transaction::PostError>
+        where
+            T: SerializableBody,
+            Txn: crate::transaction::TransactionalPosting,
+        {
+            use fe2o3_amqp_types::transaction::TransactionalState;
+
+            // Note that if delivery is split across several transfer frames then all frames MUST
+            // be explicitly associated with the same transaction.
+            let state = TransactionalState {
+                txn_id: txn.txn_id().clone(),
+                outcome: None,
+            };
+            let settlement = self
+                .inner
+                .send_with_state::<T, crate::transaction::PostError>(
+                    sendable.into(),
+                    Some(DeliveryState::TransactionalState(state)),
+                    false,
+                )
+                .await?;
+            Ok(txn.track_posted(DeliveryFut::from(settlement)))
+        }
+    }
+
+    /// Sends an already encoded message without waiting for the acknowledgement
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) async fn send_payload(
+        &mut self,
+        payload: Payload,
+        message_format: MessageFormat,
+        settled: Option<bool>,
+        batchable: bool,
+    ) -> Result<DeliveryFut<Result<SendReceipt, SendError>>, SendError> {
+        self.inner
+            .send_payload(payload, message_format, settled, None, batchable, false)
+            .await
+            .map(|settlement| self.delivery_fut(settlement))
+    }
+
+    fn delivery_fut(&self, settlement: Settlement) -> DeliveryFut<Result<SendReceipt, SendError>> {
+        let declared_outcomes = self.source().as_ref().and_then(|s| s.outcomes.clone());
+        DeliveryFut::from(settlement)
+            .rejected_as_error(self.inner.rejected_as_error)
+            .declared_outcomes(declared_outcomes)
+    }
+
+    /// Waits for the outcome of a delivery that is handed to the session, which is kept for
+    /// `pending_outcomes` if the wait is dropped
+    fn tracked_outcome(&mut self, settlement: Settlement) -> TrackedOutcome<'_> {
+        let delivery = self.delivery_fut(settlement);
+        TrackedOutcome {
+            pending_outcomes: &mut self.inner.pending_outcomes,
+            delivery: Some(delivery),
+        }
     }
 
     /// Returns when the remote peer detach/close the link
@@ -482,6 +1145,71 @@ impl Sender {
     }
 }
 
+/// A delivery whose send was dropped or cancelled after the delivery was handed to the session
+///
+/// See [`Sender::pending_outcomes`]
+#[derive(Debug)]
+pub struct PendingOutcome {
+    /// The delivery tag of the delivery
+    pub delivery_tag: DeliveryTag,
+
+    /// The outcome of the delivery, or `None` if it has not arrived yet
+    pub outcome: Option<Result<SendReceipt, SendError>>,
+}
+
+/// Outcomes of deliveries whose send was dropped after the delivery was handed to the session
+pub(crate) type PendingOutcomes = Vec<(DeliveryTag, oneshot::Receiver<Option<DeliveryState>>)>;
+
+/// Resolves to the outcome of a delivery. The delivery is added to the pending outcomes of the
+/// sender if this is dropped before
+struct TrackedOutcome<'a> {
+    pending_outcomes: &'a mut PendingOutcomes,
+    delivery: Option<DeliveryFut<Result<SendReceipt, SendError>>>,
+}
+
+impl Future for TrackedOutcome<'_> {
+    type Output = Result<SendReceipt, SendError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let delivery = self
+            .delivery
+            .as_mut()
+            .expect("TrackedOutcome polled after completion");
+        let result = std::task::ready!(delivery.poll_unpin(cx));
+        self.delivery = None;
+        Poll::Ready(result)
+    }
+}
+
+impl Drop for TrackedOutcome<'_> {
+    fn drop(&mut self) {
+        if let Some(delivery) = self.delivery.take() {
+            if let Settlement::Unsettled {
+                delivery_tag,
+                outcome,
+            } = delivery.into_settlement()
+            {
+                self.pending_outcomes.push((delivery_tag, outcome));
+            }
+        }
+    }
+}
+
+/// Encodes the message into the payload of a delivery
+pub(crate) fn encode_message<T>(message: &Message<T>) -> Result<Payload, serde_amqp::Error>
+where
+    T: SerializableBody,
+{
+    use bytes::BufMut;
+    use serde::Serialize;
+    use serde_amqp::ser::Serializer;
+
+    let mut payload = BytesMut::new();
+    let mut serializer = Serializer::from((&mut payload).writer());
+    Serializable(message).serialize(&mut serializer)?;
+    Ok(payload.freeze())
+}
+
 /// This is so that the transaction controller can re-use
 /// the sender
 #[derive(Debug)]
@@ -496,6 +1224,30 @@ pub(crate) struct SenderInner<L: endpoint::SenderLink> {
     // Outgoing mpsc channel to send the Link frames
     pub(crate) outgoing: mpsc::Sender<LinkFrame>,
     pub(crate) incoming: mpsc::Receiver<LinkFrame>,
+
+    // Whether a `Rejected` outcome is returned as `SendError::Rejected`
+    pub(crate) rejected_as_error: bool,
+
+    // Compression of the `Data` body section of the outgoing messages
+    #[cfg(feature = "compression")]
+    pub(crate) body_compression: Option<crate::compression::BodyCompression>,
+
+    // How long a send waits for link credit before a warning is logged or the send fails
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) warn_after_no_credit: Option<Duration>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fail_after_no_credit: Option<Duration>,
+
+    // The order that the messages queued to a split sender are transferred in
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) queue_policy: super::shared_sender::QueuePolicy,
+
+    // Validators of the outgoing messages and how `send_batch` handles the invalid ones
+    pub(crate) validator: ValidatorChain,
+    pub(crate) batch_validation_policy: BatchValidationPolicy,
+
+    // Outcomes of the deliveries whose send was dropped while waiting for the outcome
+    pub(crate) pending_outcomes: PendingOutcomes,
 }
 
 impl<L: endpoint::SenderLink> Drop for SenderInner<L> {
@@ -507,6 +1259,10 @@ impl<L: endpoint::SenderLink> Drop for SenderInner<L> {
                 error: None,
             };
             let _ = self.outgoing.try_send(LinkFrame::Detach(detach));
+        } else {
+            // The link is detached and will not be resumed, so its name can be taken by a new link
+            let name = self.link.name().to_string();
+            let _ = self.session.try_send(SessionControl::ReleaseLinkName(name));
         }
     }
 }
@@ -541,7 +1297,11 @@ where
         LinkRelay::Sender {
             tx,
             output_handle: (),
-            flow_state: self.link.flow_state().producer(),
+            flow_state: {
+                // The new relay is created when the link is attached again
+                self.link.flow_state().state().remote_detach.on_attached();
+                self.link.flow_state().producer()
+            },
             // TODO: what else to do during re-attaching
             unsettled: self.link.unsettled().clone(),
             receiver_settle_mode: self.link.rcv_settle_mode().clone(),
@@ -585,6 +1345,8 @@ where
         closed: bool,
         error: Option<definitions::Error>,
     ) -> Result<(), <Self::Link as LinkDetach>::DetachError> {
+        // A delivery that is cut off by a dropped send is aborted before the link is detached
+        let _ = self.link.abort_incomplete_delivery(&self.outgoing).await;
         self.link.send_detach(&self.outgoing, closed, error).await
     }
 }
@@ -629,25 +1391,20 @@ where
     ) -> Result<Settlement, E>
     where
         T: SerializableBody,
-        E: From<L::TransferError> + From<serde_amqp::Error>,
+        E: From<L::TransferError>
+            + From<serde_amqp::Error>
+            + From<CreditTimeout>
+            + From<ValidationError>,
     {
-        use bytes::BufMut;
-        use serde::Serialize;
-        use serde_amqp::ser::Serializer;
-
         let Sendable {
             message,
             message_format,
             settled,
         } = sendable;
 
-        // serialize message
-        let mut payload = BytesMut::new();
-        let mut serializer = Serializer::from((&mut payload).writer());
-        Serializable(message).serialize(&mut serializer)?;
-        let payload = payload.freeze();
-
-        self.send_payload(payload, message_format, settled, state, batchable)
+        let payload = self.encode_message(&message)?;
+        self.validator.validate_message(&message, payload.len())?;
+        self.send_payload(payload, message_format, settled, state, batchable, false)
             .await
     }
 
@@ -659,28 +1416,40 @@ where
     ) -> Result<Settlement, E>
     where
         T: SerializableBody,
-        E: From<L::TransferError> + From<serde_amqp::Error>,
+        E: From<L::TransferError>
+            + From<serde_amqp::Error>
+            + From<CreditTimeout>
+            + From<ValidationError>,
     {
-        use bytes::BufMut;
-        use serde::Serialize;
-        use serde_amqp::ser::Serializer;
-
         let Sendable {
             message,
             message_format,
             settled,
         } = sendable;
 
-        // serialize message
-        let mut payload = BytesMut::new();
-        let mut serializer = Serializer::from((&mut payload).writer());
-        Serializable(message).serialize(&mut serializer)?;
-        let payload = payload.freeze();
-
-        self.send_payload(payload, *message_format, *settled, state, batchable)
+        let payload = self.encode_message(message)?;
+        self.validator.validate_message(message, payload.len())?;
+        self.send_payload(payload, *message_format, *settled, state, batchable, false)
             .await
     }
 
+    pub(crate) fn encode_message<T>(
+        &self,
+        message: &Message<T>,
+    ) -> Result<Payload, serde_amqp::Error>
+    where
+        T: SerializableBody,
+    {
+        #[cfg(feature = "compression")]
+        if let Some(body_compression) = &self.body_compression {
+            if let Some(payload) = body_compression.encode_message(message)? {
+                return Ok(payload);
+            }
+        }
+
+        encode_message(message)
+    }
+
     pub(crate) async fn send_payload<E>(
         &mut self,
         payload: Payload,
@@ -688,28 +1457,90 @@ where
         settled: Option<bool>,
         state: Option<DeliveryState>,
         batchable: bool,
+        unconfirmed: bool,
     ) -> Result<Settlement, E>
     where
-        E: From<L::TransferError> + From<serde_amqp::Error>,
+        E: From<L::TransferError> + From<serde_amqp::Error> + From<CreditTimeout>,
     {
+        #[cfg(not(target_arch = "wasm32"))]
+        let flow_state = self.link.flow_state().state().clone();
+
         // send a transfer, checking state will be implemented in SenderLink
         let detached_fut = self.incoming.recv(); // cancel safe
-        let settlement = self
-            .link
-            .send_payload(
-                &self.outgoing,
-                detached_fut,
-                payload,
-                message_format,
-                settled,
-                state,
-                batchable,
-            )
-            .await?;
+        let send = self.link.send_payload(
+            &self.outgoing,
+            detached_fut,
+            payload,
+            message_format,
+            settled,
+            state,
+            batchable,
+            unconfirmed,
+        );
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let settlement = watch_credit::<_, E>(
+            send,
+            &flow_state,
+            self.warn_after_no_credit,
+            self.fail_after_no_credit,
+        )
+        .await?;
+        #[cfg(target_arch = "wasm32")]
+        let settlement = send.await?;
         Ok(settlement)
     }
 }
 
+cfg_not_wasm32! {
+    /// Awaits a send that may be waiting for link credit. A warning is logged once the send has
+    /// waited for `warn_after` and the send fails with `CreditTimeout` once it has waited for
+    /// `fail_after`.
+    ///
+    /// The send is only dropped if it has not consumed any link credit yet, so that a message
+    /// that is already being transferred is never cut off.
+    async fn watch_credit<F, E>(
+        send: F,
+        flow_state: &LinkFlowState<role::SenderMarker>,
+        warn_after: Option<Duration>,
+        fail_after: Option<Duration>,
+    ) -> Result<Settlement, E>
+    where
+        F: Future<Output = Result<Settlement, LinkStateError>>,
+        E: From<LinkStateError> + From<CreditTimeout>,
+    {
+        let delivery_count = flow_state.lock.read().delivery_count;
+        let is_waiting_for_credit = || flow_state.lock.read().delivery_count == delivery_count;
+        let mut send = std::pin::pin!(send);
+
+        let mut waited = Duration::ZERO;
+        // A warning after the send has already failed is never logged
+        let warn_after = warn_after.filter(|warn| fail_after.map_or(true, |fail| *warn < fail));
+        if let Some(warn_after) = warn_after {
+            match timeout(warn_after, send.as_mut()).await {
+                Ok(result) => return result.map_err(Into::into),
+                Err(_) if is_waiting_for_credit() => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!("No link credit has been issued for {:?}", warn_after);
+                    #[cfg(feature = "log")]
+                    log::warn!("No link credit has been issued for {:?}", warn_after);
+                    waited = warn_after;
+                }
+                Err(_) => return send.await.map_err(Into::into),
+            }
+        }
+
+        if let Some(fail_after) = fail_after {
+            match timeout(fail_after - waited, send.as_mut()).await {
+                Ok(result) => return result.map_err(Into::into),
+                Err(_) if is_waiting_for_credit() => return Err(CreditTimeout.into()),
+                Err(_) => {}
+            }
+        }
+        send.await.map_err(Into::into)
+    }
+}
+
 impl SenderInner<SenderLink<Target>> {
     /// Resumes a delivery with the given state and payload.
     ///
@@ -791,7 +1622,8 @@ impl SenderInner<SenderLink<Target>> {
             }
             false => {
                 if let Some(sender) = sender {
-                    let unsettled = UnsettledMessage::new(payload, None, message_format, sender);
+                    let unsettled =
+                        UnsettledMessage::new(payload, None, message_format, Some(sender));
                     let mut guard = self.link.unsettled.write();
                     guard
                         .get_or_insert(OrderedMap::new())
@@ -862,7 +1694,7 @@ impl SenderInner<SenderLink<Target>> {
         message_format: MessageFormat,
         state: DeliveryState,
         payload: Payload,
-        sender: oneshot::Sender<Option<DeliveryState>>,
+        sender: Option<oneshot::Sender<Option<DeliveryState>>>,
     ) -> Result<(), SendError> {
         let handle = self
             .link
@@ -895,7 +1727,9 @@ impl SenderInner<SenderLink<Target>> {
 
         match settled {
             true => {
-                let _ = sender.send(None);
+                if let Some(sender) = sender {
+                    let _ = sender.send(None);
+                }
             }
             false => {
                 let unsettled = UnsettledMessage::new(payload, None, message_format, sender);
@@ -950,24 +1784,41 @@ impl SenderInner<SenderLink<Target>> {
 
     async fn resume_incoming_attach(
         &mut self,
-        mut initial_remote_attach: Option<Attach>,
+        initial_remote_attach: Option<Attach>,
         is_reattaching: bool,
     ) -> Result<(), SenderResumeErrorKind> {
-        self.reallocate_output_handle().await?;
+        match &initial_remote_attach {
+            Some(remote_attach) => {
+                let input_handle = InputHandle::from(remote_attach.handle.clone());
+                self.reallocate_incoming_output_handle(input_handle).await?
+            }
+            None => self.reallocate_output_handle().await?,
+        };
+
+        let attach_exchange = match initial_remote_attach {
+            Some(remote_attach) => {
+                self.link
+                    .send_attach(&self.outgoing, &self.session, is_reattaching)
+                    .await?;
+                self.link.on_incoming_attach(remote_attach)?
+            }
+            None => self.exchange_attach(is_reattaching).await?,
+        };
 
+        self.complete_attach_exchange(attach_exchange, is_reattaching)
+            .await
+    }
+
+    /// Resumes the unsettled deliveries found in the attach exchange and re-attempts the attach
+    /// until no unsettled delivery is left
+    pub(crate) async fn complete_attach_exchange(
+        &mut self,
+        mut attach_exchange: SenderAttachExchange,
+        is_reattaching: bool,
+    ) -> Result<(), SenderResumeErrorKind> {
         let mut resend_buf = Vec::new();
 
         loop {
-            let attach_exchange = match initial_remote_attach.take() {
-                Some(remote_attach) => {
-                    self.link
-                        .send_attach(&self.outgoing, &self.session, is_reattaching)
-                        .await?;
-                    self.link.on_incoming_attach(remote_attach)?
-                }
-                None => self.exchange_attach(is_reattaching).await?,
-            };
-
             match attach_exchange {
                 SenderAttachExchange::Complete => break,
                 SenderAttachExchange::IncompleteUnsettled(resuming_deliveries) => {
@@ -990,8 +1841,11 @@ impl SenderInner<SenderLink<Target>> {
                     // Upon completion of this reduction of state, the two parties MUST suspend and
                     // re-attempt to resume the link.
                     self.detach_with_error(None).await?;
+                    self.reallocate_output_handle().await?;
                 }
             }
+
+            attach_exchange = self.exchange_attach(is_reattaching).await?;
         }
 
         Ok(())
@@ -1032,6 +1886,11 @@ impl DetachedSender {
         Self { inner }
     }
 
+    /// Get the name of the link
+    pub fn name(&self) -> &str {
+        self.inner.link.name()
+    }
+
     /// Get a reference to the link's source field
     pub fn source(&self) -> &Option<Source> {
         &self.inner.link.source
@@ -1090,7 +1949,7 @@ impl DetachedSender {
         ) -> Result<Sender, SenderResumeError> {
             let fut = self.inner.resume_incoming_attach(None, is_reattaching);
 
-            match tokio::time::timeout(duration, fut).await {
+            match timeout(duration, fut).await {
                 Ok(Ok(_)) => Ok(Sender { inner: self.inner }),
                 Ok(Err(kind)) => Err(SenderResumeError {
                     detached_sender: self,
@@ -1123,7 +1982,7 @@ impl DetachedSender {
         ) -> Result<Sender, SenderResumeError> {
             let fut = self.inner.resume_incoming_attach(Some(remote_attach), is_reattaching);
 
-            match tokio::time::timeout(duration, fut).await {
+            match timeout(duration, fut).await {
                 Ok(Ok(_)) => Ok(Sender { inner: self.inner }),
                 Ok(Err(kind)) => Err(SenderResumeError {
                     detached_sender: self,
@@ -20,14 +20,33 @@ where
     pub(crate) async fn send_transfer_without_modifying_unsettled_map(
         &mut self,
         writer: &mpsc::Sender<LinkFrame>,
+        transfer: Transfer,
+        payload: Payload,
+    ) -> Result<bool, LinkStateError> {
+        endpoint::SenderLink::abort_incomplete_delivery(self, writer).await?; // cancel safe
+        let permit = writer
+            .reserve()
+            .await // cancel safe
+            .map_err(|_| LinkStateError::IllegalSessionState)?;
+        self.send_transfer_with_permit(writer, permit, transfer, payload)
+            .await
+    }
+
+    /// Sends the transfer frames of a delivery, the first of which with `permit`
+    ///
+    /// # Cancel safety
+    ///
+    /// The first frame is handed to the session on the first poll. If this is dropped before the
+    /// last frame is handed to the session, the delivery is kept in `incomplete_delivery` and
+    /// aborted before the link sends anything else.
+    async fn send_transfer_with_permit(
+        &mut self,
+        writer: &mpsc::Sender<LinkFrame>,
+        permit: mpsc::Permit<'_, LinkFrame>,
         mut transfer: Transfer,
         mut payload: Payload,
     ) -> Result<bool, LinkStateError> {
-        let settled = transfer.settled.unwrap_or(match self.snd_settle_mode {
-            SenderSettleMode::Settled => true,
-            SenderSettleMode::Unsettled => false,
-            SenderSettleMode::Mixed => false,
-        });
+        let settled = self.is_transfer_settled(&transfer);
         let input_handle = self
             .input_handle
             .clone()
@@ -38,35 +57,50 @@ where
         let more = (self.max_message_size != 0) && (payload.len() as u64 > self.max_message_size);
         if !more {
             transfer.more = false;
-            send_transfer(writer, input_handle, transfer, payload.clone()).await?;
-        // cancel safe
-        } else {
-            // Send the first frame
-            let partial = payload.split_to(self.max_message_size as usize);
-            transfer.more = true;
-            send_transfer(writer, input_handle.clone(), transfer.clone(), partial).await?; // cancel safe
-
-            // Send the transfers in the middle
-            while payload.len() > self.max_message_size as usize {
-                let partial = payload.split_to(self.max_message_size as usize);
-                transfer.delivery_tag = None;
-                transfer.message_format = None;
-                transfer.settled = None;
-                send_transfer(writer, input_handle.clone(), transfer.clone(), partial).await?;
-                // cancel safe
-            }
+            permit.send(transfer_frame(input_handle, transfer, payload));
+            return Ok(settled);
+        }
 
-            // Send the last transfer
-            // For messages that are too large to fit within the maximum frame size, additional
-            // data MAY be trans- ferred in additional transfer frames by setting the more flag on
-            // all but the last transfer frame
-            transfer.more = false;
-            send_transfer(writer, input_handle, transfer, payload).await?; // cancel safe
+        // Send the first frame
+        let delivery_tag = transfer.delivery_tag.clone();
+        let partial = payload.split_to(self.max_message_size as usize);
+        transfer.more = true;
+        permit.send(transfer_frame(
+            input_handle.clone(),
+            transfer.clone(),
+            partial,
+        ));
+        self.incomplete_delivery = delivery_tag;
+
+        // Send the transfers in the middle
+        while payload.len() > self.max_message_size as usize {
+            let partial = payload.split_to(self.max_message_size as usize);
+            transfer.delivery_tag = None;
+            transfer.message_format = None;
+            transfer.settled = None;
+            send_transfer(writer, input_handle.clone(), transfer.clone(), partial).await?;
+            // cancel safe
         }
 
+        // Send the last transfer
+        // For messages that are too large to fit within the maximum frame size, additional
+        // data MAY be trans- ferred in additional transfer frames by setting the more flag on
+        // all but the last transfer frame
+        transfer.more = false;
+        send_transfer(writer, input_handle, transfer, payload).await?; // cancel safe
+        self.incomplete_delivery = None;
+
         Ok(settled)
     }
 
+    fn is_transfer_settled(&self, transfer: &Transfer) -> bool {
+        transfer.settled.unwrap_or(match self.snd_settle_mode {
+            SenderSettleMode::Settled => true,
+            SenderSettleMode::Unsettled => false,
+            SenderSettleMode::Mixed => false,
+        })
+    }
+
     pub(crate) async fn get_delivery_tag_or_detached<Fut>(
         &mut self,
         writer: &mpsc::Sender<LinkFrame>,
@@ -87,36 +121,103 @@ where
                 // sender to wait for new link credit
                 Ok(tag)
             },
-            frame = detached => { // cancel safe
-                match frame {
-                    // If remote has detached the link
-                    Some(LinkFrame::Detach(detach)) => {
-                        // FIXME: if the sender is not trying to send anything, this is
-                        // probably not responsive enough
-                        let closed = detach.closed;
-                        self.send_detach(writer, closed, None).await?;
-                        let result = self.on_incoming_detach(detach);
-
-                        match (result, closed) {
-                            (Ok(_), true) => Err(LinkStateError::RemoteClosed),
-                            (Ok(_), false) => Err(LinkStateError::RemoteDetached),
-                            (Err(err), _) => Err(LinkStateError::from(err)),
-                        }
-                    },
-                    Some(_frame) => {
-                        // Other frames should not forwarded to the sender by the session
-                        #[cfg(feature = "tracing")]
-                        tracing::error!("Unexpected frame: {:?}", _frame);
-                        #[cfg(feature = "log")]
-                        log::error!("Unexpected frame: {:?}", _frame);
-
-                        Err(LinkStateError::ExpectImmediateDetach)
-                    }
-                    None => {
-                        // Other frames should not forwarded to the sender by the session
-                        Err(LinkStateError::ExpectImmediateDetach)
+            frame = detached => Err(self.on_frame_while_sending(writer, frame).await), // cancel safe
+        }
+    }
+
+    /// Waits for link credit and for capacity in the session channel for the first frame of a
+    /// delivery, and then consumes the credit
+    ///
+    /// # Cancel safety
+    ///
+    /// This is cancel safe. The link credit is only consumed once the capacity is reserved, so a
+    /// send that is dropped while waiting for either of them consumes nothing.
+    pub(crate) async fn reserve_delivery_or_detached<'w, Fut>(
+        &mut self,
+        writer: &'w mpsc::Sender<LinkFrame>,
+        detached: Fut,
+    ) -> Result<(mpsc::Permit<'w, LinkFrame>, [u8; 4]), LinkStateError>
+    where
+        Fut: Future<Output = Option<LinkFrame>> + Send,
+    {
+        let flow_state = &mut self.flow_state;
+        tokio::select! {
+            reserved = async move {
+                loop {
+                    flow_state.wait_for_credit(1).await; // cancel safe
+                    let permit = writer
+                        .reserve()
+                        .await // cancel safe
+                        .map_err(|_| LinkStateError::IllegalSessionState)?;
+                    // The credit may be taken away by a Flow while waiting for the capacity
+                    if let Some(tag) = flow_state.consume_now(1) {
+                        return Ok((permit, tag));
                     }
                 }
+            } => reserved,
+            frame = detached => Err(self.on_frame_while_sending(writer, frame).await), // cancel safe
+        }
+    }
+
+    /// Waits for the outcome of a delivery that is handed to the session
+    ///
+    /// The outcome never arrives once the link is detached or the session has ended, so this
+    /// fails as soon as the link is told about it.
+    pub(crate) async fn outcome_or_detached<Fut>(
+        &mut self,
+        writer: &mpsc::Sender<LinkFrame>,
+        outcome: oneshot::Receiver<Option<DeliveryState>>,
+        detached: Fut,
+    ) -> Result<Option<DeliveryState>, LinkStateError>
+    where
+        Fut: Future<Output = Option<LinkFrame>> + Send,
+    {
+        tokio::select! {
+            biased;
+            outcome = outcome => outcome.map_err(|_| LinkStateError::IllegalSessionState),
+            frame = detached => Err(self.on_frame_while_sending(writer, frame).await),
+        }
+    }
+
+    /// Handles a frame that is received while a send is waiting for link credit
+    async fn on_frame_while_sending(
+        &mut self,
+        writer: &mpsc::Sender<LinkFrame>,
+        frame: Option<LinkFrame>,
+    ) -> LinkStateError {
+        match frame {
+            // If remote has detached the link
+            Some(LinkFrame::Detach(detach)) => {
+                // FIXME: if the sender is not trying to send anything, this is
+                // probably not responsive enough
+                let closed = detach.closed;
+                if let Err(error) = self.send_detach(writer, closed, None).await {
+                    return LinkStateError::from(error);
+                }
+                let result = self.on_incoming_detach(detach);
+
+                match (result, closed) {
+                    (Ok(_), true) => LinkStateError::RemoteClosed,
+                    (Ok(_), false) => LinkStateError::RemoteDetached,
+                    (Err(err), _) => LinkStateError::from(err),
+                }
+            }
+            Some(LinkFrame::SessionEnded(error)) => {
+                self.on_session_ended();
+                LinkStateError::SessionEnded(error)
+            }
+            Some(_frame) => {
+                // Other frames should not forwarded to the sender by the session
+                #[cfg(feature = "tracing")]
+                tracing::error!("Unexpected frame: {:?}", _frame);
+                #[cfg(feature = "log")]
+                log::error!("Unexpected frame: {:?}", _frame);
+
+                LinkStateError::ExpectImmediateDetach
+            }
+            None => {
+                // Other frames should not forwarded to the sender by the session
+                LinkStateError::ExpectImmediateDetach
             }
         }
     }
@@ -178,7 +279,7 @@ where
 {
     type FlowError = FlowError;
     type TransferError = LinkStateError;
-    type DispositionError = DispositionError;
+    type DispositionError = IllegalLinkStateError;
 
     async fn send_payload<Fut>(
         &mut self,
@@ -189,11 +290,13 @@ where
         settled: Option<bool>,
         state: Option<DeliveryState>,
         batchable: bool,
+        unconfirmed: bool,
     ) -> Result<Settlement, Self::TransferError>
     where
         Fut: Future<Output = Option<LinkFrame>> + Send,
     {
-        let tag = self.get_delivery_tag_or_detached(writer, detached).await?;
+        self.abort_incomplete_delivery(writer).await?;
+        let (permit, tag) = self.reserve_delivery_or_detached(writer, detached).await?;
         // Delivery count is incremented when consuming credit
         let delivery_tag = DeliveryTag::from(tag);
 
@@ -205,19 +308,29 @@ where
             batchable,
         )?;
 
-        self.send_payload_with_transfer(writer, message_format, transfer, payload)
-            .await
+        self.send_payload_with_transfer(
+            writer,
+            permit,
+            message_format,
+            transfer,
+            payload,
+            unconfirmed,
+        )
+        .await
     }
 
     /// # Cancel safety
     ///
-    /// This is cancel safe because all internal `.await` are cancel safe
+    /// This is cancel safe because all internal `.await` are cancel safe. The first frame is
+    /// handed to the session on the first poll
     async fn send_payload_with_transfer(
         &mut self,
         writer: &mpsc::Sender<LinkFrame>,
+        permit: mpsc::Permit<'_, LinkFrame>,
         message_format: MessageFormat,
         transfer: Transfer,
         payload: Payload,
+        unconfirmed: bool,
     ) -> Result<Settlement, Self::TransferError> {
         // Keep a copy for unsettled message
         // Clone should be very cheap on Bytes
@@ -226,29 +339,109 @@ where
             .delivery_tag
             .clone()
             .ok_or(LinkStateError::IllegalState)?;
-        let settled = self
-            .send_transfer_without_modifying_unsettled_map(writer, transfer, payload)
-            .await?;
-        match settled {
-            true => Ok(Settlement::Settled(delivery_tag)),
-            // If not set on the first (or only) transfer for a (multi-transfer)
-            // delivery, then the settled flag MUST be interpreted as being false.
+        // If not set on the first (or only) transfer for a (multi-transfer)
+        // delivery, then the settled flag MUST be interpreted as being false.
+        if self.is_transfer_settled(&transfer) {
+            self.send_transfer_with_permit(writer, permit, transfer, payload)
+                .await?;
+            return Ok(Settlement::Settled(delivery_tag));
+        }
+
+        // The delivery must be tracked before the transfer is handed to the session. Otherwise
+        // the disposition may arrive before the delivery is found in the unsettled map
+        let (tx, rx) = match unconfirmed {
+            true => (None, None),
             false => {
                 let (tx, rx) = oneshot::channel();
-                let unsettled = UnsettledMessage::new(payload_copy, None, message_format, tx);
-                {
-                    let mut guard = self.unsettled.write();
-                    guard
-                        .get_or_insert(OrderedMap::new())
-                        .insert(delivery_tag.clone(), unsettled);
-                }
+                (Some(tx), Some(rx))
+            }
+        };
+        let unsettled = UnsettledMessage::new(payload_copy, None, message_format, tx);
+        {
+            let mut guard = self.unsettled.write();
+            guard
+                .get_or_insert(OrderedMap::new())
+                .insert(delivery_tag.clone(), unsettled);
+        }
 
-                Ok(Settlement::Unsettled {
-                    delivery_tag,
-                    outcome: rx,
-                })
+        if let Err(error) = self
+            .send_transfer_with_permit(writer, permit, transfer, payload)
+            .await
+        {
+            if let Some(map) = self.unsettled.write().as_mut() {
+                map.swap_remove(&delivery_tag);
             }
+            return Err(error);
+        }
+        self.save_unsettled();
+
+        match rx {
+            Some(outcome) => Ok(Settlement::Unsettled {
+                delivery_tag,
+                outcome,
+            }),
+            None => Ok(Settlement::Unconfirmed(delivery_tag)),
+        }
+    }
+
+    /// Aborts the delivery whose send was dropped after its first frame was handed to the session
+    /// but before its last one
+    ///
+    /// # Cancel safety
+    ///
+    /// This is cancel safe. The delivery stays incomplete until the abort is handed to the session
+    async fn abort_incomplete_delivery(
+        &mut self,
+        writer: &mpsc::Sender<LinkFrame>,
+    ) -> Result<(), Self::TransferError> {
+        let Some(delivery_tag) = self.incomplete_delivery.clone() else {
+            return Ok(());
+        };
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            "Aborting incomplete delivery: delivery_tag: {:?}",
+            delivery_tag
+        );
+        #[cfg(feature = "log")]
+        log::debug!(
+            "Aborting incomplete delivery: delivery_tag: {:?}",
+            delivery_tag
+        );
+
+        let handle = self
+            .output_handle
+            .clone()
+            .ok_or(LinkStateError::IllegalState)?
+            .into();
+        let input_handle = self
+            .input_handle
+            .clone()
+            .ok_or(LinkStateError::IllegalState)?;
+        // The abort is a continuation of the incomplete delivery, so it carries neither a
+        // delivery-id nor a delivery-tag
+        let transfer = Transfer {
+            handle,
+            delivery_id: None,
+            delivery_tag: None,
+            message_format: None,
+            settled: None,
+            more: false,
+            rcv_settle_mode: None,
+            state: None,
+            resume: false,
+            aborted: true,
+            batchable: false,
+        };
+        send_transfer(writer, input_handle, transfer, Payload::new()).await?; // cancel safe
+        self.incomplete_delivery = None;
+
+        // An aborted delivery is implicitly settled
+        if let Some(map) = self.unsettled.write().as_mut() {
+            map.swap_remove(&delivery_tag);
         }
+        self.save_unsettled();
+        Ok(())
     }
 
     async fn dispose(
@@ -274,6 +467,7 @@ where
                 msg.state = Some(state.clone());
             }
         }
+        self.save_unsettled();
 
         send_disposition(writer, delivery_id, None, settled, Some(state), batchable).await
     }
@@ -346,6 +540,7 @@ where
                 }
             }
         }
+        self.save_unsettled();
 
         // if there is only one message to dispose
         if let (Some(first_id), None) = (first, last) {
@@ -365,13 +560,8 @@ async fn send_transfer(
     transfer: Transfer,
     payload: Payload,
 ) -> Result<(), LinkStateError> {
-    let frame = LinkFrame::Transfer {
-        input_handle,
-        performative: transfer,
-        payload,
-    };
     writer
-        .send(frame)
+        .send(transfer_frame(input_handle, transfer, payload))
         .await // cancel safe
         .map_err(|_| LinkStateError::IllegalSessionState)
 }
@@ -406,6 +596,9 @@ impl<T> SenderLink<T> {
         &mut self,
         remote_unsettled: Option<OrderedMap<DeliveryTag, Option<DeliveryState>>>,
     ) -> Result<SenderAttachExchange, SenderAttachError> {
+        // The restored map has been offered to the remote peer, and deliveries that the remote
+        // peer still knows of will show up in `remote_unsettled`
+        self.restored_unsettled = None;
         let mut guard = self.unsettled.write();
         let v: Vec<(DeliveryTag, ResumingDelivery)> = match (guard.take(), remote_unsettled) {
             (None, None) => return Ok(SenderAttachExchange::Complete),
@@ -491,10 +684,20 @@ where
     type AttachExchange = SenderAttachExchange;
     type AttachError = SenderAttachError;
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(link_name = %self.name)))]
     fn on_incoming_attach(
         &mut self,
         remote_attach: Attach,
     ) -> Result<Self::AttachExchange, Self::AttachError> {
+        #[cfg(feature = "tracing")]
+        tracing::trace!(link_name = %remote_attach.name, handle = remote_attach.handle.0, "RECV attach");
+        #[cfg(feature = "log")]
+        log::trace!(
+            "RECV attach: link_name = {}, handle = {}",
+            remote_attach.name,
+            remote_attach.handle.0
+        );
+
         use self::source::VerifySource;
 
         match (&self.local_state, remote_attach.incomplete_unsettled) {
@@ -518,6 +721,17 @@ where
 
         self.input_handle = Some(InputHandle::from(remote_attach.handle));
 
+        // A link that desires the anonymous terminus can only be used if the remote peer
+        // actually offers it
+        if contains_capability(self.desired_capabilities.as_ref(), ANONYMOUS_RELAY)
+            && !contains_capability(
+                remote_attach.offered_capabilities.as_deref(),
+                ANONYMOUS_RELAY,
+            )
+        {
+            return Err(SenderAttachError::AnonymousRelayNotSupported);
+        }
+
         // In this case, the sender is considered to hold the authoritative version of the
         // version of the source properties
         //
@@ -557,15 +771,10 @@ where
         }
         self.target = target;
 
-        // The sender SHOULD respect the receiver’s desired settlement mode if the receiver
-        // initiates the attach exchange and the sender supports the desired mode
-        if self.rcv_settle_mode != remote_attach.rcv_settle_mode {
-            return Err(SenderAttachError::RcvSettleModeNotSupported);
-        }
-
-        if self.snd_settle_mode != remote_attach.snd_settle_mode {
-            return Err(SenderAttachError::SndSettleModeNotSupported);
-        }
+        // When set at the receiver this indicates the actual settlement mode in use, which may
+        // be more restrictive than the one desired by the sender. The `snd_settle_mode` set at
+        // the receiver is only the mode it desires, and the local one remains in use
+        self.rcv_settle_mode = remote_attach.rcv_settle_mode;
 
         self.max_message_size =
             get_max_message_size(self.max_message_size, remote_attach.max_message_size);
@@ -621,6 +830,11 @@ where
         &self.local_state
     }
 
+    fn on_session_ended(&mut self) {
+        self.local_state = LinkState::Detached;
+        self.notify_local_state();
+    }
+
     fn name(&self) -> &str {
         &self.name
     }
@@ -700,7 +914,9 @@ where
             | SenderAttachError::IllegalState
             | SenderAttachError::NonAttachFrameReceived
             | SenderAttachError::ExpectImmediateDetach
-            | SenderAttachError::RemoteClosedWithError(_) => attach_error,
+            | SenderAttachError::RemoteClosedWithError(_)
+            | SenderAttachError::RemoteDetached(_)
+            | SenderAttachError::HandleMaxReached => attach_error,
 
             SenderAttachError::DuplicatedLinkName => {
                 let error = definitions::Error::new(
@@ -730,7 +946,8 @@ where
             SenderAttachError::CoordinatorIsNotImplemented
             | SenderAttachError::SourceAddressIsSomeWhenDynamicIsTrue
             | SenderAttachError::TargetAddressIsNoneWhenDynamicIsTrue
-            | SenderAttachError::DynamicNodePropertiesIsSomeWhenDynamicIsFalse => {
+            | SenderAttachError::DynamicNodePropertiesIsSomeWhenDynamicIsFalse
+            | SenderAttachError::AnonymousRelayNotSupported => {
                 try_detach_with_error(self, attach_error, writer, reader).await
             }
             #[cfg(feature = "transaction")]
@@ -782,11 +999,192 @@ where
         + Sync,
 {
     match reader.recv().await {
-        Some(LinkFrame::Detach(remote_detach)) => match link.on_incoming_detach(remote_detach) {
-            Ok(_) => err,
-            Err(detach_error) => detach_error.try_into().unwrap_or(err),
-        },
+        Some(LinkFrame::Detach(remote_detach)) => {
+            // Keep the Detach that carries the error so that the caller can see what the remote
+            // peer sent
+            let refusal = remote_detach.error.is_some().then(|| remote_detach.clone());
+            match (link.on_incoming_detach(remote_detach), refusal) {
+                (Ok(_), _) => err,
+                (Err(_), Some(detach)) => SenderAttachError::RemoteDetached(Box::new(detach)),
+                (Err(detach_error), None) => detach_error.try_into().unwrap_or(err),
+            }
+        }
         Some(_) => SenderAttachError::NonAttachFrameReceived,
         None => SenderAttachError::IllegalSessionState,
     }
 }
+
+#[inline]
+fn transfer_frame(input_handle: InputHandle, transfer: Transfer, payload: Payload) -> LinkFrame {
+    LinkFrame::Transfer {
+        input_handle,
+        performative: transfer,
+        payload,
+        incoming_permit: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bytes::Bytes;
+    use fe2o3_amqp_types::{
+        definitions::DeliveryTag,
+        messaging::{Target, MESSAGE_FORMAT},
+        performatives::Detach,
+    };
+    use futures_util::poll;
+    use parking_lot::RwLock;
+    use tokio::sync::{mpsc, Notify};
+
+    use crate::{
+        endpoint::{self, InputHandle, OutputHandle, Settlement},
+        link::{
+            state::{LinkFlowState, LinkFlowStateInner},
+            LinkFrame, SenderLink,
+        },
+        util::Consumer,
+        Sender,
+    };
+
+    fn sender_link(max_message_size: u64, link_credit: u32) -> SenderLink<Target> {
+        let flow_state = LinkFlowState::sender(LinkFlowStateInner {
+            initial_delivery_count: 0.into(),
+            delivery_count: 0.into(),
+            link_credit,
+            available: 0,
+            drain: false,
+            properties: None,
+        });
+        let consumer = Consumer::new(Arc::new(Notify::new()), Arc::new(flow_state));
+        let mut link = Sender::builder()
+            .name("cancel-safety")
+            .target("q1")
+            .max_message_size(max_message_size)
+            .create_link(Arc::new(RwLock::new(None)), OutputHandle(0), consumer);
+        link.input_handle = Some(InputHandle(0));
+        link
+    }
+
+    async fn send_payload(
+        link: &mut SenderLink<Target>,
+        writer: &mpsc::Sender<LinkFrame>,
+        payload: &'static [u8],
+    ) -> Settlement {
+        endpoint::SenderLink::send_payload(
+            link,
+            writer,
+            std::future::pending(),
+            Bytes::from_static(payload),
+            MESSAGE_FORMAT,
+            None,
+            None,
+            false,
+            false,
+        )
+        .await
+        .unwrap()
+    }
+
+    fn delivery_count_and_credit(link: &SenderLink<Target>) -> (u32, u32) {
+        let state = link.flow_state.state().lock.read();
+        (state.delivery_count.into(), state.link_credit)
+    }
+
+    fn unsettled_tags(link: &SenderLink<Target>) -> Vec<DeliveryTag> {
+        link.unsettled
+            .read()
+            .as_ref()
+            .map(|map| map.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn filler() -> LinkFrame {
+        LinkFrame::Detach(Detach {
+            handle: 0.into(),
+            closed: false,
+            error: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn dropped_send_waiting_for_credit_consumes_nothing() {
+        let mut link = sender_link(0, 0);
+        let (writer, mut rx) = mpsc::channel(4);
+        {
+            let mut send = std::pin::pin!(send_payload(&mut link, &writer, b"hello"));
+            assert!(poll!(send.as_mut()).is_pending());
+        }
+
+        assert_eq!(delivery_count_and_credit(&link), (0, 0));
+        assert!(unsettled_tags(&link).is_empty());
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn dropped_send_waiting_for_session_capacity_consumes_nothing() {
+        let mut link = sender_link(0, 1);
+        let (writer, mut rx) = mpsc::channel(1);
+        writer.send(filler()).await.unwrap();
+        {
+            let mut send = std::pin::pin!(send_payload(&mut link, &writer, b"hello"));
+            assert!(poll!(send.as_mut()).is_pending());
+        }
+
+        assert_eq!(delivery_count_and_credit(&link), (0, 1));
+        assert!(unsettled_tags(&link).is_empty());
+
+        // The credit is still there for the next send
+        assert!(matches!(rx.recv().await, Some(LinkFrame::Detach(_))));
+        send_payload(&mut link, &writer, b"hello").await;
+        match rx.recv().await {
+            Some(LinkFrame::Transfer { performative, .. }) => {
+                assert_eq!(performative.delivery_tag, Some(DeliveryTag::from([0u8; 4])));
+                assert!(!performative.more);
+            }
+            _ => panic!("Expecting transfer"),
+        }
+    }
+
+    #[tokio::test]
+    async fn dropped_split_send_is_aborted_before_next_transfer() {
+        let mut link = sender_link(4, 2);
+        let (writer, mut rx) = mpsc::channel(1);
+        {
+            // The first frame is handed to the session and the second one waits for capacity
+            let mut send = std::pin::pin!(send_payload(&mut link, &writer, b"0123456789ab"));
+            assert!(poll!(send.as_mut()).is_pending());
+        }
+        let first = DeliveryTag::from([0u8; 4]);
+        assert_eq!(link.incomplete_delivery, Some(first.clone()));
+        assert_eq!(unsettled_tags(&link), vec![first]);
+        match rx.recv().await {
+            Some(LinkFrame::Transfer { performative, .. }) => assert!(performative.more),
+            _ => panic!("Expecting transfer"),
+        }
+
+        let (_, frames) = tokio::join!(send_payload(&mut link, &writer, b"next"), async {
+            let mut frames = Vec::new();
+            for _ in 0..2 {
+                match rx.recv().await {
+                    Some(LinkFrame::Transfer { performative, .. }) => frames.push(performative),
+                    _ => panic!("Expecting transfer"),
+                }
+            }
+            frames
+        });
+        assert!(frames[0].aborted);
+        assert!(!frames[0].more);
+        assert!(frames[0].delivery_tag.is_none());
+        assert!(!frames[1].aborted);
+        assert_eq!(
+            frames[1].delivery_tag,
+            Some(DeliveryTag::from([0, 0, 0, 1]))
+        );
+
+        // The aborted delivery is implicitly settled
+        assert!(link.incomplete_delivery.is_none());
+        assert_eq!(unsettled_tags(&link), vec![DeliveryTag::from([0, 0, 0, 1])]);
+    }
+}
@@ -3,7 +3,7 @@ use tokio::sync::mpsc;
 
 use crate::{
     control::SessionControl,
-    endpoint::{self, LinkAttach, LinkDetach, LinkExt},
+    endpoint::{self, InputHandle, LinkAttach, LinkDetach, LinkExt},
     session::{self, error::AllocLinkError},
 };
 
@@ -55,7 +55,29 @@ where
         let link_relay = self.as_new_link_relay(tx);
         *self.reader_mut() = incoming;
         let link_name = self.link().name().to_string();
-        let handle = session::allocate_link(self.session_control(), link_name, link_relay).await?; // FIXME: cancel safe?
+        let handle = session::reallocate_link(self.session_control(), link_name, link_relay).await?; // FIXME: cancel safe?
+        *self.link_mut().output_handle_mut() = Some(handle);
+        Ok(())
+    }
+
+    /// Same as [`reallocate_output_handle`](Self::reallocate_output_handle) but for a link that
+    /// is resumed by an Attach sent by the remote peer, which is mapped to the input handle of
+    /// the remote Attach
+    async fn reallocate_incoming_output_handle(
+        &mut self,
+        input_handle: InputHandle,
+    ) -> Result<(), <Self::Link as LinkAttach>::AttachError> {
+        let (tx, incoming) = mpsc::channel(self.buffer_size());
+        let link_relay = self.as_new_link_relay(tx);
+        *self.reader_mut() = incoming;
+        let link_name = self.link().name().to_string();
+        let handle = session::allocate_incoming_link(
+            self.session_control(),
+            link_name,
+            link_relay,
+            input_handle,
+        )
+        .await?;
         *self.link_mut().output_handle_mut() = Some(handle);
         Ok(())
     }
@@ -282,6 +304,11 @@ where
             .ok_or(DetachError::IllegalSessionState)?
         {
             LinkFrame::Detach(detach) => return Ok(detach),
+            // The remote Detach will never arrive once the session has ended
+            LinkFrame::SessionEnded(error) => {
+                link_inner.link_mut().on_session_ended();
+                return Err(DetachError::SessionEnded(error));
+            }
             _frame => {
                 // The only other frames should be Attach or Detach, (or Transfer if receiver).
                 // Ignore all other frames
@@ -0,0 +1,683 @@
+//! A sender that can be shared by multiple tasks
+//!
+//! [`Sender::split`] moves the sender into an event loop and returns a [`SenderOwner`] and a
+//! cloneable [`SenderHandle`]. Every handle takes `&self` to send, so the handles can be used
+//! from multiple tasks without a mutex. The messages are encoded on the sending task and queued
+//! to the event loop, which transfers them on the single link in the order they are queued. The
+//! outcome of each delivery is tracked by its delivery tag, so the outcomes resolve on the task
+//! that sent the message regardless of how the sends from different tasks interleave.
+//!
+//! # Ordering
+//!
+//! The messages are transferred in the order they are queued. [`SenderHandle::send`] queues its
+//! message when it is first polled, and the sends waiting for room in a full queue are queued in
+//! the order they started waiting. Spawning a task per send therefore does not keep the order the
+//! tasks are spawned in, because the tasks race to be polled. [`SenderHandle::enqueue`] returns
+//! once the message is queued, so the messages enqueued by a task keep their order while their
+//! outcomes are awaited elsewhere.
+//!
+//! # Priority
+//!
+//! A sender built with [`QueuePolicy::Priority`](crate::link::shared_sender::QueuePolicy) holds
+//! the queued messages in one lane per priority level instead. The priority of a message is the
+//! `priority` field of its header, which is 4 if the message has no header, unless it is sent
+//! with [`SenderHandle::send_with_priority`] or [`SenderHandle::enqueue_with_priority`]. When
+//! credit is issued, the queued message of the highest priority is transferred first, and the
+//! messages of the same priority are transferred in the order they are queued. A message that
+//! waits for credit is put back in the queue if a message of a higher priority is queued
+//! meanwhile. The messages of a low priority are promoted by one level for every `aging` they
+//! wait, so they are not starved by a steady stream of messages of a higher priority.
+//!
+//! Detaching and closing the link remain on the [`SenderOwner`], which waits for the messages
+//! queued before it to be transferred. Once the link is detached or closed, sending with any of
+//! the handles fails with [`LinkStateError::IllegalState`].
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! let sender = Sender::attach(&mut session, "rust-sender-link-1", "q1").await.unwrap();
+//! let (owner, handle) = sender.split();
+//!
+//! let tasks: Vec<_> = (0..4)
+//!     .map(|i| {
+//!         let handle = handle.clone();
+//!         tokio::spawn(async move { handle.send(format!("message-{}", i)).await })
+//!     })
+//!     .collect();
+//! for task in tasks {
+//!     let outcome = task.await.unwrap().unwrap();
+//!     outcome.accepted_or_else(|outcome| outcome).unwrap();
+//! }
+//!
+//! owner.close().await.unwrap();
+//! ```
+//!
+//! Keeping the order of the messages while awaiting the outcomes on other tasks
+//!
+//! ```rust,ignore
+//! for i in 0..100 {
+//!     let queued = handle.enqueue(format!("message-{}", i)).await.unwrap();
+//!     tokio::spawn(async move { queued.await });
+//! }
+//! ```
+//!
+//! Letting control messages jump ahead of the bulk traffic
+//!
+//! ```rust,ignore
+//! let sender = Sender::builder()
+//!     .name("rust-sender-link-1")
+//!     .target("q1")
+//!     .queue_policy(QueuePolicy::Priority {
+//!         levels: 10,
+//!         aging: Some(Duration::from_secs(1)),
+//!     })
+//!     .attach(&mut session)
+//!     .await
+//!     .unwrap();
+//! let (owner, handle) = sender.split();
+//!
+//! let bulk = handle.enqueue("bulk").await.unwrap();
+//! let control = handle.enqueue_with_priority("control", 9).await.unwrap();
+//! ```
+
+use std::{
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use fe2o3_amqp_types::{
+    definitions::MessageFormat,
+    messaging::{Message, SerializableBody},
+};
+use futures_util::FutureExt;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::Payload;
+
+use super::{
+    delivery::{DeliveryFut, SendReceipt, Sendable},
+    sender::{encode_message, DetachedSender},
+    validation::ValidatorChain,
+    DetachError, LinkStateError, SendError, Sender,
+};
+
+/// Number of messages that can be queued to the event loop before sending waits
+pub const DEFAULT_QUEUE_SIZE: usize = 64;
+
+type SendResult = Result<SendReceipt, SendError>;
+
+type TransferReply = oneshot::Receiver<Result<DeliveryFut<SendResult>, SendError>>;
+
+enum Command {
+    Send {
+        payload: Payload,
+        message_format: MessageFormat,
+        settled: Option<bool>,
+        batchable: bool,
+        priority: u8,
+        reply: oneshot::Sender<Result<DeliveryFut<SendResult>, SendError>>,
+    },
+    Take(oneshot::Sender<Sender>),
+}
+
+/// The order that the messages queued to a [split](Sender::split) sender are transferred in
+///
+/// [`Fifo`](Self::Fifo) keeps the order the messages are queued in, and
+/// [`Priority`](Self::Priority) transfers the queued message of the highest priority first. See the
+/// [priority](crate::link::shared_sender#priority) section of the module documentation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QueuePolicy {
+    /// The messages are transferred in the order they are queued
+    #[default]
+    Fifo,
+
+    /// The queued message of the highest priority is transferred first
+    Priority {
+        /// Number of priority levels. A message of priority `p` is queued at level
+        /// `min(p, levels - 1)`
+        levels: u8,
+
+        /// A queued message is promoted by one level for every `aging` it waits. The messages are
+        /// never promoted if this is `None`
+        aging: Option<Duration>,
+    },
+}
+
+impl Sender {
+    /// Splits the sender into an owner, which detaches or closes the link, and a cloneable handle
+    /// that sends from multiple tasks
+    ///
+    /// The sender is moved into an event loop, which transfers the messages queued by the handles.
+    /// See the [module](crate::link::shared_sender) documentation.
+    pub fn split(self) -> (SenderOwner, SenderHandle) {
+        let (commands_tx, commands_rx) = mpsc::channel(DEFAULT_QUEUE_SIZE);
+        let handle = SenderHandle {
+            name: self.name().to_string(),
+            commands: commands_tx,
+            #[cfg(feature = "compression")]
+            body_compression: self.inner.body_compression,
+            validator: self.inner.validator.clone(),
+        };
+        let owner = SenderOwner {
+            handle: handle.clone(),
+        };
+        match self.inner.queue_policy {
+            QueuePolicy::Fifo => crate::rt::spawn(event_loop(self, commands_rx)),
+            QueuePolicy::Priority { levels, aging } => {
+                let queue = PriorityQueue::new(levels, aging);
+                crate::rt::spawn(priority_event_loop(self, commands_rx, queue))
+            }
+        };
+        (owner, handle)
+    }
+}
+
+/// Transfers the queued messages in order until the owner takes the sender back. The sender is
+/// dropped, which closes the link, if the owner and all handles are dropped
+async fn event_loop(mut sender: Sender, mut commands: mpsc::Receiver<Command>) {
+    while let Some(command) = commands.recv().await {
+        match command {
+            Command::Send {
+                payload,
+                message_format,
+                settled,
+                batchable,
+                reply,
+                ..
+            } => {
+                let result = sender
+                    .send_payload(payload, message_format, settled, batchable)
+                    .await;
+                let _ = reply.send(result);
+            }
+            Command::Take(reply) => {
+                let _ = reply.send(sender);
+                return;
+            }
+        }
+    }
+}
+
+/// Transfers the queued messages of the highest priority first until the owner takes the sender
+/// back
+///
+/// The owner takes the sender once the messages queued before are transferred.
+async fn priority_event_loop(
+    mut sender: Sender,
+    mut commands: mpsc::Receiver<Command>,
+    mut queue: PriorityQueue,
+) {
+    let flow_state = sender.inner.link.flow_state.state().clone();
+    let mut take: Option<oneshot::Sender<Sender>> = None;
+    loop {
+        let transfer = match queue.pop() {
+            Some(transfer) => transfer,
+            None => {
+                if let Some(reply) = take {
+                    let _ = reply.send(sender);
+                    return;
+                }
+                match commands.recv().await {
+                    Some(command) => take = queue.push_command(command),
+                    None => return,
+                }
+                continue;
+            }
+        };
+
+        let delivery_count = flow_state.lock.read().delivery_count;
+        let send = sender.send_payload(
+            transfer.payload.clone(),
+            transfer.message_format,
+            transfer.settled,
+            transfer.batchable,
+        );
+        let mut send = std::pin::pin!(send);
+        let result = loop {
+            let has_room = take.is_none() && queue.len() < DEFAULT_QUEUE_SIZE;
+            tokio::select! {
+                result = &mut send => break Some(result),
+                Some(command) = commands.recv(), if has_room => {
+                    take = queue.push_command(command);
+                    // The send is only dropped before it takes credit
+                    let is_waiting_for_credit =
+                        flow_state.lock.read().delivery_count == delivery_count;
+                    if is_waiting_for_credit && queue.outranks(&transfer) {
+                        break None;
+                    }
+                }
+            }
+        };
+
+        match result {
+            Some(result) => {
+                let _ = transfer.reply.send(result);
+            }
+            None => queue.push_front(transfer),
+        }
+    }
+}
+
+/// A message queued to [`priority_event_loop`]
+struct QueuedTransfer {
+    payload: Payload,
+    message_format: MessageFormat,
+    settled: Option<bool>,
+    batchable: bool,
+    reply: oneshot::Sender<Result<DeliveryFut<SendResult>, SendError>>,
+    level: usize,
+    queued_at: Instant,
+    seq: u64,
+}
+
+/// One FIFO lane per priority level
+struct PriorityQueue {
+    lanes: Vec<VecDeque<QueuedTransfer>>,
+    aging: Option<Duration>,
+    len: usize,
+    next_seq: u64,
+}
+
+impl PriorityQueue {
+    fn new(levels: u8, aging: Option<Duration>) -> Self {
+        let lanes = (0..levels.max(1)).map(|_| VecDeque::new()).collect();
+        Self {
+            lanes,
+            aging: aging.filter(|aging| !aging.is_zero()),
+            len: 0,
+            next_seq: 0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Queues a message, or returns the reply of the owner taking the sender back
+    fn push_command(&mut self, command: Command) -> Option<oneshot::Sender<Sender>> {
+        match command {
+            Command::Send {
+                payload,
+                message_format,
+                settled,
+                batchable,
+                priority,
+                reply,
+            } => {
+                let level = (priority as usize).min(self.lanes.len() - 1);
+                let transfer = QueuedTransfer {
+                    payload,
+                    message_format,
+                    settled,
+                    batchable,
+                    reply,
+                    level,
+                    queued_at: Instant::now(),
+                    seq: self.next_seq,
+                };
+                self.next_seq += 1;
+                self.lanes[level].push_back(transfer);
+                self.len += 1;
+                None
+            }
+            Command::Take(reply) => Some(reply),
+        }
+    }
+
+    /// Puts a popped message back in front of its lane
+    fn push_front(&mut self, transfer: QueuedTransfer) {
+        self.lanes[transfer.level].push_front(transfer);
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<QueuedTransfer> {
+        let now = Instant::now();
+        let lane = self
+            .lanes
+            .iter()
+            .enumerate()
+            .filter_map(|(lane, transfers)| Some((lane, self.rank(transfers.front()?, now))))
+            .max_by_key(|(_, rank)| *rank)
+            .map(|(lane, _)| lane)?;
+        self.len -= 1;
+        self.lanes[lane].pop_front()
+    }
+
+    /// Whether a queued message would be transferred before `transfer`
+    fn outranks(&self, transfer: &QueuedTransfer) -> bool {
+        let now = Instant::now();
+        let rank = self.rank(transfer, now);
+        self.lanes
+            .iter()
+            .filter_map(|transfers| transfers.front())
+            .any(|queued| self.rank(queued, now) > rank)
+    }
+
+    /// The promoted level, and then the older message first
+    fn rank(&self, transfer: &QueuedTransfer, now: Instant) -> (usize, std::cmp::Reverse<u64>) {
+        let promoted = match self.aging {
+            Some(aging) => {
+                let waited = now.saturating_duration_since(transfer.queued_at);
+                (waited.as_nanos() / aging.as_nanos()) as usize
+            }
+            None => 0,
+        };
+        let level = transfer
+            .level
+            .saturating_add(promoted)
+            .min(self.lanes.len() - 1);
+        (level, std::cmp::Reverse(transfer.seq))
+    }
+}
+
+/// A cloneable handle that sends messages on a link shared with other handles
+///
+/// Sending takes `&self` and queues the message to the event loop of the link, so the handles can
+/// be used from multiple tasks without a mutex. See the
+/// [ordering](crate::link::shared_sender#ordering) section of the module documentation.
+#[derive(Debug, Clone)]
+pub struct SenderHandle {
+    name: String,
+    commands: mpsc::Sender<Command>,
+    #[cfg(feature = "compression")]
+    body_compression: Option<crate::compression::BodyCompression>,
+    validator: ValidatorChain,
+}
+
+impl SenderHandle {
+    /// Name of the link
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the link is detached or closed by the owner
+    pub fn is_closed(&self) -> bool {
+        self.commands.is_closed()
+    }
+
+    /// Send a message and wait for acknowledgement (disposition)
+    ///
+    /// This behaves like [`Sender::send`], and the messages sent by all handles are transferred
+    /// in the order they are queued.
+    pub async fn send<T: SerializableBody>(
+        &self,
+        sendable: impl Into<Sendable<T>>,
+    ) -> Result<SendReceipt, SendError> {
+        self.send_inner(sendable.into(), false, None).await?.await
+    }
+
+    /// Send a message of the given priority and wait for acknowledgement (disposition)
+    ///
+    /// The priority only changes the order of the queued messages if the sender is built with
+    /// [`QueuePolicy::Priority`], and it is not written to the header of the message.
+    pub async fn send_with_priority<T: SerializableBody>(
+        &self,
+        sendable: impl Into<Sendable<T>>,
+        priority: u8,
+    ) -> Result<SendReceipt, SendError> {
+        self.send_inner(sendable.into(), false, Some(priority))
+            .await?
+            .await
+    }
+
+    /// Send a message without waiting for the acknowledgement
+    ///
+    /// This behaves like [`Sender::send_batchable`], and returns once the message is transferred.
+    pub async fn send_batchable<T: SerializableBody>(
+        &self,
+        sendable: impl Into<Sendable<T>>,
+    ) -> Result<DeliveryFut<SendResult>, SendError> {
+        self.send_inner(sendable.into(), true, None).await
+    }
+
+    /// Queue a message without waiting for the transfer or the acknowledgement
+    ///
+    /// This returns once the message has its place in the queue, which fixes the order it is
+    /// transferred in relative to the other messages. The returned [`QueuedSend`] resolves to the
+    /// outcome like [`send`](#method.send), and the message is transferred whether or not it is
+    /// polled. See the [ordering](crate::link::shared_sender#ordering) section of the module
+    /// documentation.
+    pub async fn enqueue<T: SerializableBody>(
+        &self,
+        sendable: impl Into<Sendable<T>>,
+    ) -> Result<QueuedSend, SendError> {
+        let transfer = self.enqueue_inner(sendable.into(), false, None).await?;
+        Ok(QueuedSend {
+            state: QueuedState::Queued(transfer),
+        })
+    }
+
+    /// Queue a message of the given priority without waiting for the transfer or the
+    /// acknowledgement
+    ///
+    /// This behaves like [`enqueue`](#method.enqueue). The priority only changes the order of the
+    /// queued messages if the sender is built with [`QueuePolicy::Priority`], and it is not
+    /// written to the header of the message.
+    pub async fn enqueue_with_priority<T: SerializableBody>(
+        &self,
+        sendable: impl Into<Sendable<T>>,
+        priority: u8,
+    ) -> Result<QueuedSend, SendError> {
+        let transfer = self
+            .enqueue_inner(sendable.into(), false, Some(priority))
+            .await?;
+        Ok(QueuedSend {
+            state: QueuedState::Queued(transfer),
+        })
+    }
+
+    async fn send_inner<T: SerializableBody>(
+        &self,
+        sendable: Sendable<T>,
+        batchable: bool,
+        priority: Option<u8>,
+    ) -> Result<DeliveryFut<SendResult>, SendError> {
+        let transfer = self.enqueue_inner(sendable, batchable, priority).await?;
+        transfer.await.map_err(|_| LinkStateError::IllegalState)?
+    }
+
+    async fn enqueue_inner<T: SerializableBody>(
+        &self,
+        sendable: Sendable<T>,
+        batchable: bool,
+        priority: Option<u8>,
+    ) -> Result<TransferReply, SendError> {
+        let Sendable {
+            message,
+            message_format,
+            settled,
+        } = sendable;
+        let payload = self.encode_message(&message)?;
+        self.validator.validate_message(&message, payload.len())?;
+        let priority = priority.unwrap_or_else(|| {
+            let header = message.header.as_ref();
+            header.map(|header| header.priority).unwrap_or_default().0
+        });
+
+        let (tx, rx) = oneshot::channel();
+        let command = Command::Send {
+            payload,
+            message_format,
+            settled,
+            batchable,
+            priority,
+            reply: tx,
+        };
+        self.commands
+            .send(command)
+            .await
+            .map_err(|_| LinkStateError::IllegalState)?;
+        Ok(rx)
+    }
+
+    fn encode_message<T>(&self, message: &Message<T>) -> Result<Payload, serde_amqp::Error>
+    where
+        T: SerializableBody,
+    {
+        #[cfg(feature = "compression")]
+        if let Some(body_compression) = &self.body_compression {
+            if let Some(payload) = body_compression.encode_message(message)? {
+                return Ok(payload);
+            }
+        }
+
+        encode_message(message)
+    }
+}
+
+/// The outcome of a message queued with [`SenderHandle::enqueue`]
+///
+/// This resolves once the message is transferred and acknowledged, like [`SenderHandle::send`].
+pub struct QueuedSend {
+    state: QueuedState,
+}
+
+enum QueuedState {
+    Queued(TransferReply),
+    Transferred(DeliveryFut<SendResult>),
+}
+
+impl std::fmt::Debug for QueuedSend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = match &self.state {
+            QueuedState::Queued(_) => "Queued",
+            QueuedState::Transferred(_) => "Transferred",
+        };
+        f.debug_struct("QueuedSend").field("state", &state).finish()
+    }
+}
+
+impl Future for QueuedSend {
+    type Output = SendResult;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        loop {
+            match &mut self.state {
+                QueuedState::Queued(transfer) => match transfer.poll_unpin(cx) {
+                    Poll::Ready(Ok(Ok(delivery_fut))) => {
+                        self.state = QueuedState::Transferred(delivery_fut)
+                    }
+                    Poll::Ready(Ok(Err(error))) => return Poll::Ready(Err(error)),
+                    Poll::Ready(Err(_)) => {
+                        return Poll::Ready(Err(LinkStateError::IllegalState.into()))
+                    }
+                    Poll::Pending => return Poll::Pending,
+                },
+                QueuedState::Transferred(delivery_fut) => return delivery_fut.poll_unpin(cx),
+            }
+        }
+    }
+}
+
+/// Owner of a link whose messages are sent by [`SenderHandle`]s
+///
+/// The owner hands out more handles and detaches or closes the link once the messages queued
+/// before it are transferred. See the [module](crate::link::shared_sender) documentation.
+#[derive(Debug)]
+pub struct SenderOwner {
+    handle: SenderHandle,
+}
+
+impl SenderOwner {
+    /// Name of the link
+    pub fn name(&self) -> &str {
+        self.handle.name()
+    }
+
+    /// Returns a new handle that sends on the link
+    pub fn handle(&self) -> SenderHandle {
+        self.handle.clone()
+    }
+
+    /// Takes the sender back once the messages queued before this call are transferred
+    ///
+    /// Sending with any of the handles fails with [`LinkStateError::IllegalState`] afterwards.
+    pub async fn into_sender(self) -> Sender {
+        let (tx, rx) = oneshot::channel();
+        // The event loop only stops after the sender is taken, which requires the owner
+        self.handle
+            .commands
+            .send(Command::Take(tx))
+            .await
+            .expect("The event loop of a split sender stops only after the owner takes the sender");
+        rx.await
+            .expect("The event loop of a split sender stops only after the owner takes the sender")
+    }
+
+    /// Detach the link once the messages queued before this call are transferred
+    ///
+    /// See [`Sender::detach`]
+    pub async fn detach(self) -> Result<DetachedSender, (DetachedSender, DetachError)> {
+        self.into_sender().await.detach().await
+    }
+
+    /// Close the link once the messages queued before this call are transferred
+    ///
+    /// See [`Sender::close`]
+    pub async fn close(self) -> Result<(), DetachError> {
+        self.into_sender().await.close().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use tokio::sync::oneshot;
+
+    use crate::Payload;
+
+    use super::{Command, PriorityQueue};
+
+    fn push(queue: &mut PriorityQueue, body: &'static str, priority: u8) {
+        let (reply, _) = oneshot::channel();
+        let command = Command::Send {
+            payload: Payload::from_static(body.as_bytes()),
+            message_format: 0,
+            settled: None,
+            batchable: false,
+            priority,
+            reply,
+        };
+        assert!(queue.push_command(command).is_none());
+    }
+
+    fn pop_all(queue: &mut PriorityQueue) -> Vec<Payload> {
+        std::iter::from_fn(|| queue.pop())
+            .map(|transfer| transfer.payload)
+            .collect()
+    }
+
+    #[test]
+    fn test_priority_queue_order() {
+        let mut queue = PriorityQueue::new(4, None);
+        push(&mut queue, "low-1", 0);
+        push(&mut queue, "high-1", 3);
+        push(&mut queue, "low-2", 0);
+        // Priorities above the top level are queued at the top level
+        push(&mut queue, "high-2", 9);
+        push(&mut queue, "mid", 1);
+        assert_eq!(queue.len(), 5);
+        assert_eq!(
+            pop_all(&mut queue),
+            ["high-1", "high-2", "mid", "low-1", "low-2"]
+        );
+        assert_eq!(queue.len(), 0);
+    }
+
+    #[test]
+    fn test_priority_queue_aging() {
+        let mut queue = PriorityQueue::new(4, Some(Duration::from_secs(1)));
+        push(&mut queue, "low", 0);
+        push(&mut queue, "mid", 2);
+        push(&mut queue, "high", 3);
+        // Waiting for 3 seconds promotes the low priority message to the top level, where it is
+        // older than the high priority message
+        let low = queue.lanes[0].front_mut().unwrap();
+        low.queued_at = Instant::now() - Duration::from_secs(3);
+        assert_eq!(pop_all(&mut queue), ["low", "high", "mid"]);
+    }
+}
@@ -1,21 +1,32 @@
 //! Link state and link flow state
 
-use std::{marker::PhantomData, sync::Arc};
+use std::{
+    future::Future,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+    },
+};
 
-use fe2o3_amqp_types::definitions::{Fields, SequenceNo};
+use fe2o3_amqp_types::definitions::{Fields, Handle, SequenceNo};
 use parking_lot::RwLock;
+use tokio::sync::watch;
 
 use crate::{
     endpoint::{LinkFlow, OutputHandle},
-    util::{Consume, ProducerState},
+    util::{Consume, InstantCell, ProducerState, SerialNumber},
 };
 
-use super::{role, ReceiverTransferError, SenderFlowState};
+use super::{
+    confirm::ConfirmTracker, remote_detach::RemoteDetachNotifier, role, LinkRelayError,
+    ReceiverTransferError, SenderFlowState,
+};
 
 /// Link state.
 ///
 /// There is no official definition of the link state in the specification
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum LinkState {
     /// The initial state after initialization
     Unattached,
@@ -57,10 +68,33 @@ pub enum LinkState {
     Closed,
 }
 
+/// A snapshot of the flow state of a link
+///
+/// This is meant for debugging, eg. finding out why a sender is not sending. The values may
+/// already be outdated when they are inspected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkFlowSnapshot {
+    /// The current maximum number of messages that can be handled at the receiver endpoint of
+    /// the link
+    pub link_credit: u32,
+
+    /// The delivery-count of the link
+    pub delivery_count: SequenceNo,
+
+    /// The number of messages awaiting credit at the sender
+    pub available: u32,
+
+    /// The last known value of the drain flag
+    pub drain: bool,
+
+    /// The number of deliveries that are not settled yet
+    pub unsettled: usize,
+}
+
 #[derive(Debug)]
 pub(crate) struct LinkFlowStateInner {
-    pub initial_delivery_count: SequenceNo,
-    pub delivery_count: SequenceNo, // SequenceNo = u32
+    pub initial_delivery_count: SerialNumber,
+    pub delivery_count: SerialNumber,
     pub link_credit: u32,
     pub available: u32,
     pub drain: bool,
@@ -71,7 +105,7 @@ impl LinkFlowStateInner {
     pub fn as_link_flow(&self, output_handle: OutputHandle, echo: bool) -> LinkFlow {
         LinkFlow {
             handle: output_handle.into(),
-            delivery_count: Some(self.delivery_count),
+            delivery_count: Some(self.delivery_count.into()),
             link_credit: Some(self.link_credit),
             available: Some(self.available),
             drain: self.drain,
@@ -85,6 +119,45 @@ impl LinkFlowStateInner {
 #[derive(Debug)]
 pub(crate) struct LinkFlowState<R> {
     pub(crate) lock: RwLock<LinkFlowStateInner>,
+
+    /// Link credit withdrawn by the receiver that the sender may have used before it saw the
+    /// withdrawal. This is only used by the receiver
+    revoked_credit: AtomicU32,
+
+    /// Whether the sender has reported its `available`. This is only used by the receiver
+    available_reported: AtomicBool,
+
+    /// The deliveries that the session has relayed to the link but the link has not consumed
+    /// yet, which the delivery-count of the sender already includes. This is only used by the
+    /// receiver
+    pending_deliveries: AtomicU32,
+
+    /// Whether the last transfer relayed to the link is followed by more transfers of the same
+    /// delivery. This is only used by the receiver
+    relaying_delivery: AtomicBool,
+
+    /// When the last transfer of the last incoming delivery arrived. This is only used by the
+    /// receiver
+    pub(crate) last_delivery_at: InstantCell,
+
+    /// When the last incoming Disposition arrived. This is only used by the sender
+    pub(crate) last_disposition_at: InstantCell,
+
+    /// The properties of the last incoming Flow that carried any. This is only written while the
+    /// write lock on the flow state is held
+    remote_properties: RwLock<Option<Fields>>,
+
+    /// Whether the sender has answered the last drain of the receiver by using up the link
+    /// credit. This is only used by the receiver
+    drained: watch::Sender<bool>,
+
+    /// Whether the remote peer detached the link first, which is set by the link relay in the
+    /// session
+    pub(crate) remote_detach: RemoteDetachNotifier,
+
+    /// Which deliveries the receiver has accepted, which is updated by the link relay in the
+    /// session. This is only used by the sender
+    pub(crate) confirms: ConfirmTracker,
     role: PhantomData<R>,
 }
 
@@ -92,6 +165,16 @@ impl<R> LinkFlowState<R> {
     pub(crate) fn new(inner: LinkFlowStateInner) -> Self {
         Self {
             lock: RwLock::new(inner),
+            revoked_credit: AtomicU32::new(0),
+            available_reported: AtomicBool::new(false),
+            pending_deliveries: AtomicU32::new(0),
+            relaying_delivery: AtomicBool::new(false),
+            last_delivery_at: InstantCell::new(),
+            last_disposition_at: InstantCell::new(),
+            remote_properties: RwLock::new(None),
+            drained: watch::channel(false).0,
+            remote_detach: RemoteDetachNotifier::default(),
+            confirms: ConfirmTracker::default(),
             role: PhantomData,
         }
     }
@@ -113,14 +196,16 @@ impl LinkFlowState<role::SenderMarker> {
     /// Handles incoming Flow frame
     ///
     /// If an echo (reply with the local flow state) is requested, return an `Ok(Some(Flow))`,
-    /// otherwise, return a `Ok(None)`
+    /// otherwise, return a `Ok(None)`. Returns an error if the delivery-count of the receiver is
+    /// ahead of the deliveries sent
     #[inline]
     pub(crate) fn on_incoming_flow(
         &self,
         flow: LinkFlow,
         output_handle: OutputHandle,
-    ) -> Option<LinkFlow> {
+    ) -> Result<Option<LinkFlow>, LinkRelayError> {
         let mut state = self.lock.write();
+        self.record_remote_properties(&flow);
 
         // delivery count
         //
@@ -134,7 +219,7 @@ impl LinkFlowState<role::SenderMarker> {
         // MUST be set according to this formula when flow information is given by the
         // receiver:
         // link-credit_snd := delivery-count_rcv + link-credit_rcv - delivery-count_snd.
-        let delivery_count_rcv = flow.delivery_count.unwrap_or(
+        let delivery_count_rcv = flow.delivery_count.map(SerialNumber::from).unwrap_or(
             // In the event that the receiver does not yet know the delivery-count,
             // i.e., delivery-count_rcv is unspecified, the sender MUST assume that
             // the delivery-count_rcv is the first delivery-count_snd sent from sender
@@ -144,10 +229,12 @@ impl LinkFlowState<role::SenderMarker> {
         );
 
         if let Some(link_credit_rcv) = flow.link_credit {
-            let link_credit = delivery_count_rcv
-                .saturating_add(link_credit_rcv)
-                .saturating_sub(state.delivery_count);
-            state.link_credit = link_credit;
+            // The delivery-count is a serial number that may wrap around. The receiver cannot
+            // have counted more deliveries than sent
+            let in_flight = delivery_count_rcv
+                .distance_to(state.delivery_count)
+                .map_err(|_| LinkRelayError::InvalidDeliveryCount)?;
+            state.link_credit = link_credit_rcv.saturating_sub(in_flight);
         }
 
         // available
@@ -167,13 +254,13 @@ impl LinkFlowState<role::SenderMarker> {
             state.delivery_count = state.delivery_count.wrapping_add(state.link_credit);
             state.link_credit = 0;
 
-            return Some(state.as_link_flow(output_handle, false));
+            return Ok(Some(state.as_link_flow(output_handle, false)));
         }
 
         match flow.echo {
             // Should avoid constant ping-pong
-            true => Some(state.as_link_flow(output_handle, false)),
-            false => None,
+            true => Ok(Some(state.as_link_flow(output_handle, false))),
+            false => Ok(None),
         }
     }
 }
@@ -186,6 +273,7 @@ impl LinkFlowState<role::ReceiverMarker> {
         output_handle: OutputHandle,
     ) -> Option<LinkFlow> {
         let mut state = self.lock.write();
+        self.record_remote_properties(&flow);
 
         // delivery count
         //
@@ -193,8 +281,12 @@ impl LinkFlowState<role::ReceiverMarker> {
         // value from the sender and any subsequent messages received on the link. Note that,
         // despite its name, the delivery-count is not a count but a sequence number
         // initialized at an arbitrary point by the sender.
+        //
+        // The deliveries that are relayed to the link but not consumed yet are already counted
+        // by the sender and are counted again once consumed
         if let Some(delivery_count) = flow.delivery_count {
-            state.delivery_count = delivery_count;
+            let pending = self.pending_deliveries.load(Ordering::Acquire);
+            state.delivery_count = SerialNumber::from(delivery_count).wrapping_sub(pending);
         }
 
         // link credit
@@ -212,6 +304,7 @@ impl LinkFlowState<role::ReceiverMarker> {
         // calculation of the value of available.
         if let Some(available) = flow.available {
             state.available = available;
+            self.available_reported.store(true, Ordering::Release);
         }
 
         // drain
@@ -222,6 +315,14 @@ impl LinkFlowState<role::ReceiverMarker> {
         // consuming all link-credit, and send the flow state to the receiver. Only the
         // receiver can independently modify this field. The sender’s value is always the
         // last known value indicated by the receiver.
+        //
+        // A flow that reports no link credit while draining answers the drain, and only the
+        // deliveries that are relayed but not consumed yet are left of the link credit
+        if state.drain && flow.link_credit == Some(0) {
+            let pending = self.pending_deliveries.load(Ordering::Acquire);
+            state.link_credit = state.link_credit.min(pending);
+            self.drained.send_replace(true);
+        }
 
         match flow.echo {
             true => Some(state.as_link_flow(output_handle, false)),
@@ -231,6 +332,19 @@ impl LinkFlowState<role::ReceiverMarker> {
 }
 
 impl<R> LinkFlowState<R> {
+    /// Reads the flow state under a single read lock so that the fields are consistent with
+    /// each other
+    pub fn snapshot(&self, unsettled: usize) -> LinkFlowSnapshot {
+        let state = self.lock.read();
+        LinkFlowSnapshot {
+            link_credit: state.link_credit,
+            delivery_count: state.delivery_count.into(),
+            available: state.available,
+            drain: state.drain,
+            unsettled,
+        }
+    }
+
     pub fn link_credit(&self) -> u32 {
         self.lock.read().link_credit
     }
@@ -240,16 +354,16 @@ impl<R> LinkFlowState<R> {
     }
 
     pub fn initial_delivery_count(&self) -> SequenceNo {
-        self.lock.read().initial_delivery_count
+        self.lock.read().initial_delivery_count.into()
     }
 
-    pub fn initial_delivery_count_mut(&self, f: impl Fn(u32) -> u32) {
+    pub fn initial_delivery_count_mut(&self, f: impl Fn(SerialNumber) -> SerialNumber) {
         let mut guard = self.lock.write();
         let new = f(guard.initial_delivery_count);
         guard.initial_delivery_count = new;
     }
 
-    pub fn delivery_count_mut(&self, f: impl Fn(u32) -> u32) {
+    pub fn delivery_count_mut(&self, f: impl Fn(SerialNumber) -> SerialNumber) {
         let mut guard = self.lock.write();
         let new = f(guard.delivery_count);
         guard.delivery_count = new;
@@ -259,27 +373,137 @@ impl<R> LinkFlowState<R> {
     pub fn properties(&self) -> Option<Fields> {
         self.lock.read().properties.clone()
     }
+
+    /// Replaces the properties that are carried by the Flow frames sent from now on
+    pub fn set_properties(&self, properties: Option<Fields>) {
+        self.lock.write().properties = properties;
+    }
+
+    /// The properties of the last incoming Flow that carried any
+    pub fn remote_properties(&self) -> Option<Fields> {
+        self.remote_properties.read().clone()
+    }
+
+    /// Flows without properties leave the last known properties as is. This should be called
+    /// while holding the write lock on the flow state
+    fn record_remote_properties(&self, flow: &LinkFlow) {
+        if let Some(properties) = &flow.properties {
+            *self.remote_properties.write() = Some(properties.clone());
+        }
+    }
 }
 
 impl LinkFlowState<role::ReceiverMarker> {
     /// Consume one link credit if available. Returns an error if there is
     /// not enough link credit
+    ///
+    /// Transfers that exceed the link credit are still accepted up to the revoked credit, which
+    /// the sender may have used before it saw the flow that lowered the link credit.
     pub fn consume(&self, count: u32) -> Result<(), ReceiverTransferError> {
         let mut state = self.lock.write();
         if state.link_credit < count {
-            Err(ReceiverTransferError::TransferLimitExceeded)
+            let missing = count - state.link_credit;
+            self.revoked_credit
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |revoked| {
+                    revoked.checked_sub(missing)
+                })
+                .map_err(|_| ReceiverTransferError::TransferLimitExceeded)?;
+            state.link_credit = 0;
         } else {
-            state.delivery_count = state.delivery_count.wrapping_add(count);
-            state.link_credit = state.link_credit.saturating_sub(count);
-            Ok(())
+            state.link_credit -= count;
+        }
+        state.delivery_count = state.delivery_count.wrapping_add(count);
+        state.available = state.available.saturating_sub(count);
+        let _ =
+            self.pending_deliveries
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |pending| {
+                    Some(pending.saturating_sub(count))
+                });
+        Ok(())
+    }
+
+    /// Counts the deliveries that the session relays to the link. This is called by the link
+    /// relay for every incoming transfer before it is relayed
+    ///
+    /// An aborted delivery is counted as well, because it is consumed by the link once the abort
+    /// is taken.
+    pub fn on_relayed_transfer(&self, more: bool, aborted: bool) {
+        let continued = self
+            .relaying_delivery
+            .swap(more && !aborted, Ordering::AcqRel);
+        if !continued {
+            self.pending_deliveries.fetch_add(1, Ordering::AcqRel);
         }
     }
+
+    /// Forgets the deliveries relayed before the link is attached again
+    pub fn reset_relayed_deliveries(&self) {
+        self.pending_deliveries.store(0, Ordering::Release);
+        self.relaying_delivery.store(false, Ordering::Release);
+    }
+
+    /// The number of messages the sender could make use of credit for, which is the last value
+    /// reported by the sender minus the transfers received since. Returns `None` if the sender
+    /// has not reported it yet
+    pub fn available(&self) -> Option<u32> {
+        let available = self.lock.read().available;
+        self.available_reported
+            .load(Ordering::Acquire)
+            .then_some(available)
+    }
+
+    /// Starts or stops draining. This should be called while holding the write lock on the flow
+    /// state
+    pub fn set_drain(&self, state: &mut LinkFlowStateInner, drain: bool) {
+        state.drain = drain;
+        self.drained.send_replace(false);
+    }
+
+    /// Returns a future that resolves once the sender has answered the last drain or the flow
+    /// state is dropped
+    pub fn wait_drained(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut rx = self.drained.subscribe();
+        async move {
+            let _ = rx.wait_for(|drained| *drained).await;
+        }
+    }
+
+    /// Sets the link credit and stops draining, returning the flow that issues the credit
+    pub fn issue_credit(&self, handle: Handle, link_credit: u32) -> LinkFlow {
+        let mut guard = self.lock.write();
+        self.revoke(guard.link_credit, link_credit);
+        guard.link_credit = link_credit;
+        self.set_drain(&mut guard, false);
+        LinkFlow {
+            handle,
+            delivery_count: Some(guard.delivery_count.into()),
+            link_credit: Some(link_credit),
+            available: None,
+            drain: false,
+            echo: false,
+            properties: guard.properties.clone(),
+        }
+    }
+
+    /// Records the link credit that is withdrawn by a flow which sets the link credit from
+    /// `current` to `new`
+    ///
+    /// Until the sender sees the flow, it may use the credit issued before, which is at most
+    /// `current` plus the credit revoked earlier. Once it sees the flow, the transfers already
+    /// sent count against `new`.
+    pub fn revoke(&self, current: u32, new: u32) {
+        let _ = self
+            .revoked_credit
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |revoked| {
+                Some(current.saturating_add(revoked).saturating_sub(new))
+            });
+    }
 }
 
 impl ProducerState for Arc<LinkFlowState<role::SenderMarker>> {
     type Item = (LinkFlow, OutputHandle);
     // If echo is requested, a Some(LinkFlow) will be returned
-    type Outcome = Option<LinkFlow>;
+    type Outcome = Result<Option<LinkFlow>, LinkRelayError>;
 
     #[inline]
     fn update_state(&mut self, (flow, output_handle): Self::Item) -> Self::Outcome {
@@ -311,6 +535,24 @@ impl Consume for SenderFlowState {
     }
 }
 
+impl SenderFlowState {
+    /// Waits until there is enough link credit for `count` deliveries without consuming it
+    ///
+    /// # Cancel safety
+    ///
+    /// This is cancel safe for the same reason as [`Consume::consume`]
+    pub(crate) async fn wait_for_credit(&mut self, count: u32) {
+        while self.state().lock.read().link_credit < count {
+            self.notifier.notified().await
+        }
+    }
+
+    /// Consumes link credit if there is enough, returning the delivery tag
+    pub(crate) fn consume_now(&mut self, count: u32) -> Option<[u8; 4]> {
+        consume_link_credit(&self.state().lock, count).ok()
+    }
+}
+
 cfg_transaction! {
     impl crate::util::TryConsume for SenderFlowState {
         type Error = super::error::SenderTryConsumeError;
@@ -324,7 +566,7 @@ cfg_transaction! {
             if state.link_credit < item {
                 Err(Self::Error::InsufficientCredit)
             } else {
-                let tag = state.delivery_count.to_be_bytes();
+                let tag = u32::from(state.delivery_count).to_be_bytes();
                 state.delivery_count = state.delivery_count.wrapping_add(item);
                 state.link_credit = state.link_credit.saturating_sub(item);
                 Ok(tag)
@@ -342,7 +584,7 @@ fn consume_link_credit(
     if state.link_credit < count {
         Err(InsufficientCredit {})
     } else {
-        let tag = state.delivery_count.to_be_bytes();
+        let tag = u32::from(state.delivery_count).to_be_bytes();
         state.delivery_count = state.delivery_count.wrapping_add(count);
         state.link_credit = state.link_credit.saturating_sub(count);
         Ok(tag)
@@ -353,6 +595,7 @@ fn consume_link_credit(
 mod tests {
     use std::{sync::Arc, time::Duration};
 
+    use fe2o3_amqp_types::{definitions::Fields, primitives::Value};
     use futures_util::poll;
     use tokio::{sync::Notify, time::timeout};
 
@@ -360,12 +603,27 @@ mod tests {
         endpoint::{LinkFlow, OutputHandle},
         link::{
             role,
-            state::{LinkFlowState, LinkFlowStateInner},
-            SenderFlowState,
+            state::{LinkFlowSnapshot, LinkFlowState, LinkFlowStateInner},
+            LinkRelayError, ReceiverTransferError, SenderFlowState,
         },
-        util::{Consume, Consumer, Produce, Producer},
+        util::{Consume, Consumer, Produce, Producer, SerialNumber},
     };
 
+    /// Delivery-counts around the start and the end of the serial number space and around half
+    /// of it
+    const DELIVERY_COUNTS: [u32; 7] = [0, 1, 2, 1 << 31, (1 << 31) + 1, u32::MAX - 1, u32::MAX];
+
+    fn flow_state_inner(delivery_count: u32, link_credit: u32) -> LinkFlowStateInner {
+        LinkFlowStateInner {
+            initial_delivery_count: delivery_count.into(),
+            delivery_count: delivery_count.into(),
+            link_credit,
+            available: 0,
+            drain: false,
+            properties: None,
+        }
+    }
+
     macro_rules! assert_pending {
         ($fut:expr) => {
             let fut = Box::pin($fut);
@@ -387,8 +645,8 @@ mod tests {
         SenderFlowState,
     ) {
         let flow_state_inner = LinkFlowStateInner {
-            initial_delivery_count: 0,
-            delivery_count: 0,
+            initial_delivery_count: 0.into(),
+            delivery_count: 0.into(),
             link_credit: 0,
             available: 0,
             drain: false,
@@ -410,7 +668,7 @@ mod tests {
 
         // .await after notify with zero credit
         let item = (LinkFlow::default(), OutputHandle(0));
-        producer.produce(item).await;
+        producer.produce(item).await.unwrap();
         assert_pending!(consumer.consume(1));
 
         // .await after notify with non-zero credit
@@ -419,7 +677,7 @@ mod tests {
             ..Default::default()
         };
         let item = (link_flow, OutputHandle(0));
-        producer.produce(item).await;
+        producer.produce(item).await.unwrap();
         assert_ready!(consumer.consume(1));
         assert_ready!(consumer.consume(1));
 
@@ -441,7 +699,7 @@ mod tests {
             ..Default::default()
         };
         let item = (link_flow, OutputHandle(0));
-        producer.produce(item).await;
+        producer.produce(item).await.unwrap();
 
         let wait = timeout(Duration::from_millis(500), fut).await;
         assert!(wait.is_ok());
@@ -463,7 +721,7 @@ mod tests {
             ..Default::default()
         };
         let item = (link_flow, OutputHandle(0));
-        producer.produce(item).await;
+        producer.produce(item).await.unwrap();
 
         // If it is not cancel safe, we cannot consume all 2 credits
         assert_ready!(consumer.consume(1));
@@ -488,7 +746,7 @@ mod tests {
             ..Default::default()
         };
         let item = (link_flow, OutputHandle(0));
-        producer.produce(item).await;
+        producer.produce(item).await.unwrap();
 
         drop(pinned);
 
@@ -499,4 +757,405 @@ mod tests {
         // All credits have been consumed already
         assert_pending!(consumer.consume(1));
     }
+
+    #[test]
+    fn sender_link_credit_across_delivery_count_wrap_around() {
+        let flow_state_inner = LinkFlowStateInner {
+            initial_delivery_count: (u32::MAX - 5).into(),
+            delivery_count: (u32::MAX - 5).into(),
+            link_credit: 0,
+            available: 0,
+            drain: false,
+            properties: None,
+        };
+        let flow_state = LinkFlowState::sender(flow_state_inner);
+
+        // The receiver does not know the delivery-count yet
+        let link_flow = LinkFlow {
+            link_credit: Some(10),
+            ..Default::default()
+        };
+        flow_state
+            .on_incoming_flow(link_flow, OutputHandle(0))
+            .unwrap();
+        assert_eq!(flow_state.link_credit(), 10);
+
+        // Eight deliveries are sent and the delivery-count wraps around to 2
+        flow_state.delivery_count_mut(|count| count.wrapping_add(8));
+        let link_flow = LinkFlow {
+            delivery_count: Some(u32::MAX - 1),
+            link_credit: Some(10),
+            ..Default::default()
+        };
+        flow_state
+            .on_incoming_flow(link_flow, OutputHandle(0))
+            .unwrap();
+        assert_eq!(flow_state.link_credit(), 6);
+
+        let link_flow = LinkFlow {
+            delivery_count: Some(2),
+            link_credit: Some(10),
+            ..Default::default()
+        };
+        flow_state
+            .on_incoming_flow(link_flow, OutputHandle(0))
+            .unwrap();
+        assert_eq!(flow_state.link_credit(), 10);
+    }
+
+    #[tokio::test]
+    async fn sender_flow_snapshot_tracks_flows_and_sends() {
+        let (mut producer, mut consumer) = create_sender_flow_state_producer_and_consumer();
+        let snapshot = consumer.state().snapshot(0);
+        assert_eq!(
+            snapshot,
+            LinkFlowSnapshot {
+                link_credit: 0,
+                delivery_count: 0,
+                available: 0,
+                drain: false,
+                unsettled: 0,
+            }
+        );
+
+        let link_flow = LinkFlow {
+            delivery_count: Some(0),
+            link_credit: Some(5),
+            ..Default::default()
+        };
+        producer
+            .produce((link_flow, OutputHandle(0)))
+            .await
+            .unwrap();
+        assert_eq!(consumer.state().snapshot(0).link_credit, 5);
+
+        consumer.consume(2).await;
+        let snapshot = consumer.state().snapshot(2);
+        assert_eq!(snapshot.link_credit, 3);
+        assert_eq!(snapshot.delivery_count, 2);
+        assert_eq!(snapshot.unsettled, 2);
+
+        // Draining consumes all the remaining credit
+        let link_flow = LinkFlow {
+            delivery_count: Some(2),
+            link_credit: Some(3),
+            drain: true,
+            ..Default::default()
+        };
+        producer
+            .produce((link_flow, OutputHandle(0)))
+            .await
+            .unwrap();
+        let snapshot = consumer.state().snapshot(0);
+        assert_eq!(snapshot.link_credit, 0);
+        assert_eq!(snapshot.delivery_count, 5);
+        assert!(snapshot.drain);
+    }
+
+    #[test]
+    fn receiver_flow_snapshot_tracks_flows_and_transfers() {
+        let flow_state_inner = LinkFlowStateInner {
+            initial_delivery_count: 0.into(),
+            delivery_count: 0.into(),
+            link_credit: 4,
+            available: 0,
+            drain: false,
+            properties: None,
+        };
+        let flow_state = LinkFlowState::receiver(flow_state_inner);
+
+        let link_flow = LinkFlow {
+            delivery_count: Some(10),
+            available: Some(7),
+            ..Default::default()
+        };
+        flow_state.on_incoming_flow(link_flow, OutputHandle(0));
+        let snapshot = flow_state.snapshot(0);
+        assert_eq!(snapshot.delivery_count, 10);
+        assert_eq!(snapshot.available, 7);
+        assert_eq!(snapshot.link_credit, 4);
+
+        flow_state.consume(1).unwrap();
+        let snapshot = flow_state.snapshot(1);
+        assert_eq!(snapshot.delivery_count, 11);
+        assert_eq!(snapshot.link_credit, 3);
+        assert_eq!(snapshot.unsettled, 1);
+    }
+
+    #[tokio::test]
+    async fn receiver_is_drained_once_the_sender_uses_up_the_credit() {
+        let flow_state_inner = LinkFlowStateInner {
+            initial_delivery_count: 0.into(),
+            delivery_count: 0.into(),
+            link_credit: 5,
+            available: 0,
+            drain: false,
+            properties: None,
+        };
+        let flow_state = LinkFlowState::receiver(flow_state_inner);
+        flow_state.set_drain(&mut flow_state.lock.write(), true);
+        let drained = flow_state.wait_drained();
+
+        // A flow that still reports credit does not answer the drain
+        let link_flow = LinkFlow {
+            delivery_count: Some(0),
+            link_credit: Some(5),
+            drain: true,
+            ..Default::default()
+        };
+        flow_state.on_incoming_flow(link_flow, OutputHandle(0));
+        assert_eq!(flow_state.link_credit(), 5);
+
+        // Two deliveries are relayed before the answer but not consumed yet
+        flow_state.on_relayed_transfer(false, false);
+        flow_state.on_relayed_transfer(false, false);
+        let link_flow = LinkFlow {
+            delivery_count: Some(5),
+            link_credit: Some(0),
+            drain: true,
+            ..Default::default()
+        };
+        flow_state.on_incoming_flow(link_flow, OutputHandle(0));
+        drained.await;
+        let snapshot = flow_state.snapshot(0);
+        assert_eq!(snapshot.link_credit, 2);
+        assert_eq!(snapshot.delivery_count, 3);
+
+        flow_state.consume(2).unwrap();
+        assert_eq!(flow_state.link_credit(), 0);
+        assert_eq!(flow_state.snapshot(0).delivery_count, 5);
+
+        // Issuing credit starts over
+        flow_state.issue_credit(0.into(), 3);
+        let drained = flow_state.wait_drained();
+        assert!(
+            tokio::time::timeout(std::time::Duration::from_millis(10), drained)
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn receiver_accepts_transfers_in_flight_when_credit_is_withdrawn() {
+        let flow_state_inner = LinkFlowStateInner {
+            initial_delivery_count: 0.into(),
+            delivery_count: 0.into(),
+            link_credit: 5,
+            available: 0,
+            drain: false,
+            properties: None,
+        };
+        let flow_state = LinkFlowState::receiver(flow_state_inner);
+
+        // The credit is withdrawn after one transfer has arrived
+        flow_state.consume(1).unwrap();
+        flow_state.revoke(4, 0);
+        flow_state.lock.write().link_credit = 0;
+
+        // The sender may have used the rest before it sees the flow
+        for _ in 0..4 {
+            flow_state.consume(1).unwrap();
+            assert_eq!(flow_state.link_credit(), 0);
+        }
+        assert!(matches!(
+            flow_state.consume(1),
+            Err(ReceiverTransferError::TransferLimitExceeded)
+        ));
+        assert_eq!(flow_state.snapshot(0).delivery_count, 5);
+
+        // Transfers sent before the sender sees a new flow count against its credit
+        flow_state.revoke(0, 3);
+        flow_state.lock.write().link_credit = 3;
+        for _ in 0..3 {
+            flow_state.consume(1).unwrap();
+        }
+        assert!(flow_state.consume(1).is_err());
+    }
+
+    #[test]
+    fn receiver_tracks_available_and_echoes_flows() {
+        let flow_state_inner = LinkFlowStateInner {
+            initial_delivery_count: 0.into(),
+            delivery_count: 0.into(),
+            link_credit: 10,
+            available: 0,
+            drain: false,
+            properties: None,
+        };
+        let flow_state = LinkFlowState::receiver(flow_state_inner);
+        assert_eq!(flow_state.available(), None);
+
+        // A flow without echo is not answered
+        let link_flow = LinkFlow {
+            delivery_count: Some(3),
+            available: Some(5),
+            ..Default::default()
+        };
+        assert!(flow_state
+            .on_incoming_flow(link_flow, OutputHandle(0))
+            .is_none());
+        assert_eq!(flow_state.available(), Some(5));
+
+        // Incoming transfers are deducted from the reported value with a floor of zero
+        flow_state.consume(2).unwrap();
+        assert_eq!(flow_state.available(), Some(3));
+        flow_state.consume(4).unwrap();
+        assert_eq!(flow_state.available(), Some(0));
+
+        // A flow that omits available keeps the last known value
+        let link_flow = LinkFlow {
+            delivery_count: Some(9),
+            echo: true,
+            ..Default::default()
+        };
+        let echo = flow_state
+            .on_incoming_flow(link_flow, OutputHandle(1))
+            .unwrap();
+        assert_eq!(flow_state.available(), Some(0));
+        assert_eq!(echo.handle, OutputHandle(1).into());
+        assert_eq!(echo.delivery_count, Some(9));
+        assert_eq!(echo.link_credit, Some(4));
+        assert!(!echo.echo);
+    }
+
+    #[tokio::test]
+    async fn flow_properties_are_sent_and_last_remote_properties_are_kept() {
+        let (mut producer, consumer) = create_sender_flow_state_producer_and_consumer();
+        let flow_state = consumer.state();
+        assert_eq!(flow_state.remote_properties(), None);
+
+        let mut local = Fields::new();
+        local.insert("com.microsoft:tracking-id".into(), Value::from("local"));
+        flow_state.set_properties(Some(local.clone()));
+
+        let mut remote = Fields::new();
+        remote.insert("com.microsoft:tracking-id".into(), Value::from("remote"));
+        let link_flow = LinkFlow {
+            link_credit: Some(1),
+            echo: true,
+            properties: Some(remote.clone()),
+            ..Default::default()
+        };
+        let echo = producer
+            .produce((link_flow, OutputHandle(0)))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(echo.properties, Some(local));
+        assert_eq!(flow_state.remote_properties(), Some(remote.clone()));
+
+        // A flow without properties does not clear the last known properties
+        let link_flow = LinkFlow {
+            link_credit: Some(2),
+            ..Default::default()
+        };
+        assert!(producer
+            .produce((link_flow, OutputHandle(0)))
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(flow_state.remote_properties(), Some(remote));
+    }
+
+    #[test]
+    fn sender_flow_with_delivery_counts_around_wrap_around() {
+        for delivery_count_snd in DELIVERY_COUNTS {
+            for delivery_count_rcv in DELIVERY_COUNTS {
+                for link_credit_rcv in [0, 1, u32::MAX] {
+                    for drain in [false, true] {
+                        let flow_state =
+                            LinkFlowState::sender(flow_state_inner(delivery_count_snd, 0));
+                        let link_flow = LinkFlow {
+                            delivery_count: Some(delivery_count_rcv),
+                            link_credit: Some(link_credit_rcv),
+                            drain,
+                            ..Default::default()
+                        };
+                        let result = flow_state.on_incoming_flow(link_flow, OutputHandle(0));
+
+                        // The receiver cannot have counted more deliveries than sent
+                        let in_flight = SerialNumber::from(delivery_count_rcv)
+                            .distance_to(delivery_count_snd.into());
+                        let in_flight = match in_flight {
+                            Ok(in_flight) => in_flight,
+                            Err(_) => {
+                                assert!(matches!(
+                                    result,
+                                    Err(LinkRelayError::InvalidDeliveryCount)
+                                ));
+                                continue;
+                            }
+                        };
+                        result.unwrap();
+                        let link_credit = link_credit_rcv.saturating_sub(in_flight);
+                        let snapshot = flow_state.snapshot(0);
+                        match drain {
+                            true => {
+                                assert_eq!(snapshot.link_credit, 0);
+                                assert_eq!(
+                                    snapshot.delivery_count,
+                                    delivery_count_snd.wrapping_add(link_credit)
+                                );
+                            }
+                            false => {
+                                assert_eq!(snapshot.link_credit, link_credit);
+                                assert_eq!(snapshot.delivery_count, delivery_count_snd);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn receiver_flow_and_transfers_with_delivery_counts_around_wrap_around() {
+        for delivery_count_snd in DELIVERY_COUNTS {
+            for link_credit in [0, 1, u32::MAX] {
+                let flow_state = LinkFlowState::receiver(flow_state_inner(0, link_credit));
+                let link_flow = LinkFlow {
+                    delivery_count: Some(delivery_count_snd),
+                    ..Default::default()
+                };
+                assert!(flow_state
+                    .on_incoming_flow(link_flow, OutputHandle(0))
+                    .is_none());
+                assert_eq!(flow_state.snapshot(0).delivery_count, delivery_count_snd);
+
+                match flow_state.consume(1) {
+                    Ok(()) => assert_eq!(
+                        flow_state.snapshot(0).delivery_count,
+                        delivery_count_snd.wrapping_add(1)
+                    ),
+                    Err(_) => assert_eq!(link_credit, 0),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn receiver_does_not_count_relayed_deliveries_twice() {
+        let flow_state = LinkFlowState::receiver(flow_state_inner(u32::MAX - 1, 10));
+
+        // A delivery of two transfers, a single transfer delivery and an aborted delivery are
+        // relayed to the link before the flow of the sender, which counts all of them
+        flow_state.on_relayed_transfer(true, false);
+        flow_state.on_relayed_transfer(false, false);
+        flow_state.on_relayed_transfer(false, false);
+        flow_state.on_relayed_transfer(true, false);
+        flow_state.on_relayed_transfer(false, true);
+        let link_flow = LinkFlow {
+            delivery_count: Some(1),
+            ..Default::default()
+        };
+        flow_state.on_incoming_flow(link_flow, OutputHandle(0));
+
+        // The link consumes the complete deliveries and the aborted one after the flow
+        flow_state.consume(1).unwrap();
+        flow_state.consume(1).unwrap();
+        flow_state.consume(1).unwrap();
+        assert_eq!(flow_state.snapshot(0).delivery_count, 1);
+        let flow = flow_state.issue_credit(0.into(), 10);
+        assert_eq!(flow.delivery_count, Some(1));
+    }
 }
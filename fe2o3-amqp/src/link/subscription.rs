@@ -0,0 +1,356 @@
+//! Topic subscriptions expressed with the source capabilities understood by brokers such as
+//! ActiveMQ Artemis and Qpid
+//!
+//! A [`Subscription`] configures the source address, the source capabilities, the terminus
+//! durability and the link name of a receiver that consumes from a topic. The link name is
+//! derived from the subscription name, so attaching the same durable subscription again resumes
+//! it, and [`Subscription::unsubscribe`] performs the closing detach that deletes it.
+//!
+//! ```rust,ignore
+//! use fe2o3_amqp::link::Subscription;
+//!
+//! let subscription = Subscription::builder()
+//!     .topic("prices")
+//!     .shared(true)
+//!     .durable(true)
+//!     .subscription_name("worker-group")
+//!     .build()
+//!     .unwrap();
+//!
+//! let receiver = subscription.attach(&mut session).await.unwrap();
+//! // A non-closing detach keeps the durable subscription on the broker
+//! receiver.detach().await.unwrap();
+//!
+//! // Deletes the durable subscription
+//! subscription.unsubscribe(&mut session).await.unwrap();
+//! ```
+//!
+//! Link names must be unique between two containers, so a connection can only have one receiver
+//! attached to a shared subscription at a time.
+
+use fe2o3_amqp_types::{
+    messaging::{Source, Target, TerminusDurability, TerminusExpiryPolicy},
+    primitives::Symbol,
+};
+
+use crate::session::SessionHandle;
+
+use super::{
+    builder::{Builder, WithName, WithSource, WithTarget},
+    receiver::CreditMode,
+    role, DetachError, Receiver, ReceiverAttachError,
+};
+
+/// Source capability of a topic, ie. a node that delivers every message to every subscription
+pub const TOPIC: &str = "topic";
+
+/// Source capability of a subscription that is shared by the receivers attached with the same
+/// link name
+pub const SHARED: &str = "shared";
+
+/// Source capability of a shared subscription that is not scoped to the container id of the
+/// connection
+pub const GLOBAL: &str = "global";
+
+/// Desired link capability of a receiver that attaches to a shared subscription
+pub const SHARED_SUBS: &str = "SHARED-SUBS";
+
+/// Suffix of the link name of a global shared subscription
+const GLOBAL_SUFFIX: &str = "|global";
+
+/// Suffix of the link name of a subscription that is not durable
+const VOLATILE_SUFFIX: &str = "|volatile";
+
+/// Errors associated with building a [`Subscription`]
+#[derive(Debug, thiserror::Error)]
+pub enum SubscriptionBuilderError {
+    /// The topic is not set
+    #[error("The topic is not set")]
+    TopicIsNone,
+
+    /// A durable or shared subscription is identified by its name, which is not set
+    #[error("A durable or shared subscription requires a subscription name")]
+    SubscriptionNameIsNone,
+}
+
+/// Errors associated with deleting a subscription
+#[derive(Debug, thiserror::Error)]
+pub enum UnsubscribeError {
+    /// Failed to attach to the subscription
+    #[error(transparent)]
+    Attach(#[from] ReceiverAttachError),
+
+    /// Failed to close the link
+    #[error(transparent)]
+    Detach(#[from] DetachError),
+}
+
+/// A subscription to a topic
+///
+/// | Setting | Source address | Source capabilities | Durability | Link name |
+/// |---------|----------------|---------------------|------------|-----------|
+/// | default | topic | `topic` | `None`, expires on link detach | generated |
+/// | `shared` | topic | `topic`, `shared` | `None`, expires on link detach | `<name>\|volatile` |
+/// | `durable` | topic | `topic` | `UnsettledState`, never expires | `<name>` |
+/// | `global` | topic | adds `global` | | adds `\|global` |
+///
+/// A receiver attaching to a shared subscription also desires the [`SHARED_SUBS`] link
+/// capability.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subscription {
+    topic: String,
+    subscription_name: Option<String>,
+    shared: bool,
+    global: bool,
+    durable: bool,
+}
+
+impl Subscription {
+    /// Creates a builder for [`Subscription`]
+    pub fn builder() -> SubscriptionBuilder {
+        SubscriptionBuilder::default()
+    }
+
+    /// The address of the topic
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    /// The name of the subscription
+    pub fn subscription_name(&self) -> Option<&str> {
+        self.subscription_name.as_deref()
+    }
+
+    /// Whether the subscription is shared by the receivers attached with the same link name
+    pub fn is_shared(&self) -> bool {
+        self.shared
+    }
+
+    /// Whether the shared subscription is not scoped to the container id of the connection
+    pub fn is_global(&self) -> bool {
+        self.global
+    }
+
+    /// Whether the subscription outlives the link
+    pub fn is_durable(&self) -> bool {
+        self.durable
+    }
+
+    /// The name of the link that identifies the subscription, which is `None` if the
+    /// subscription is neither durable nor shared and has no name
+    pub fn link_name(&self) -> Option<String> {
+        let mut name = self.subscription_name.clone()?;
+        if self.global {
+            name.push_str(GLOBAL_SUFFIX);
+        }
+        if !self.durable {
+            name.push_str(VOLATILE_SUFFIX);
+        }
+        Some(name)
+    }
+
+    /// The source of the receiver
+    pub fn source(&self) -> Source {
+        let mut capabilities = vec![Symbol::from(TOPIC)];
+        if self.shared {
+            capabilities.push(Symbol::from(SHARED));
+        }
+        if self.global {
+            capabilities.push(Symbol::from(GLOBAL));
+        }
+
+        let builder = Source::builder()
+            .address(self.topic.clone())
+            .capabilities(capabilities);
+        match self.durable {
+            true => builder
+                .durable(TerminusDurability::UnsettledState)
+                .expiry_policy(TerminusExpiryPolicy::Never),
+            false => builder
+                .durable(TerminusDurability::None)
+                .expiry_policy(TerminusExpiryPolicy::LinkDetach),
+        }
+        .build()
+    }
+
+    /// Creates a receiver builder that attaches to the subscription
+    pub fn receiver_builder(
+        &self,
+    ) -> Builder<role::ReceiverMarker, Target, WithName, WithSource, WithTarget> {
+        let builder = Receiver::builder();
+        let builder = match self.link_name() {
+            Some(name) => builder.name(name),
+            None => builder.auto_name(&self.topic),
+        };
+        let builder = builder.source(self.source());
+        match self.shared {
+            true => builder.add_desired_capabilities(SHARED_SUBS),
+            false => builder,
+        }
+    }
+
+    /// Attaches a receiver to the subscription with the default configuration of the
+    /// [`receiver_builder`](Self::receiver_builder)
+    pub async fn attach<R>(
+        &self,
+        session: &mut SessionHandle<R>,
+    ) -> Result<Receiver, ReceiverAttachError> {
+        self.receiver_builder().attach(session).await
+    }
+
+    /// Deletes the subscription by attaching to it and closing the link
+    ///
+    /// The link does not issue any credit, so no message is taken from the subscription. Any
+    /// receiver that is still attached to the subscription must be detached first.
+    pub async fn unsubscribe<R>(
+        &self,
+        session: &mut SessionHandle<R>,
+    ) -> Result<(), UnsubscribeError> {
+        let receiver = self
+            .receiver_builder()
+            .credit_mode(CreditMode::Manual)
+            .attach(session)
+            .await?;
+        receiver.close().await?;
+        Ok(())
+    }
+}
+
+/// Builder for [`Subscription`]
+#[derive(Debug, Clone, Default)]
+pub struct SubscriptionBuilder {
+    topic: Option<String>,
+    subscription_name: Option<String>,
+    shared: bool,
+    global: bool,
+    durable: bool,
+}
+
+impl SubscriptionBuilder {
+    /// The address of the topic
+    pub fn topic(mut self, topic: impl Into<String>) -> Self {
+        self.topic = Some(topic.into());
+        self
+    }
+
+    /// The name of the subscription, which is required if the subscription is durable or shared
+    pub fn subscription_name(mut self, name: impl Into<String>) -> Self {
+        self.subscription_name = Some(name.into());
+        self
+    }
+
+    /// Whether the subscription is shared by the receivers attached with the same link name.
+    /// Defaults to `false`
+    pub fn shared(mut self, shared: bool) -> Self {
+        self.shared = shared;
+        self
+    }
+
+    /// Whether the shared subscription is not scoped to the container id of the connection.
+    /// Defaults to `false`
+    ///
+    /// This implies [`shared`](Self::shared).
+    pub fn global(mut self, global: bool) -> Self {
+        self.global = global;
+        self
+    }
+
+    /// Whether the subscription outlives the link. Defaults to `false`
+    pub fn durable(mut self, durable: bool) -> Self {
+        self.durable = durable;
+        self
+    }
+
+    /// Builds the [`Subscription`]
+    pub fn build(self) -> Result<Subscription, SubscriptionBuilderError> {
+        let topic = self.topic.ok_or(SubscriptionBuilderError::TopicIsNone)?;
+        let shared = self.shared || self.global;
+        if (shared || self.durable) && self.subscription_name.is_none() {
+            return Err(SubscriptionBuilderError::SubscriptionNameIsNone);
+        }
+
+        Ok(Subscription {
+            topic,
+            subscription_name: self.subscription_name,
+            shared,
+            global: self.global,
+            durable: self.durable,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fe2o3_amqp_types::{
+        messaging::{TerminusDurability, TerminusExpiryPolicy},
+        primitives::Symbol,
+    };
+
+    use super::{Subscription, SubscriptionBuilderError};
+
+    #[test]
+    fn durable_shared_subscription_is_named_after_subscription() {
+        let subscription = Subscription::builder()
+            .topic("prices")
+            .shared(true)
+            .durable(true)
+            .subscription_name("worker-group")
+            .build()
+            .unwrap();
+        assert_eq!(subscription.link_name().as_deref(), Some("worker-group"));
+
+        let source = subscription.source();
+        assert_eq!(source.address.as_deref(), Some("prices"));
+        assert_eq!(
+            source.capabilities.unwrap().0,
+            vec![Symbol::from("topic"), Symbol::from("shared")]
+        );
+        assert_eq!(source.durable, TerminusDurability::UnsettledState);
+        assert_eq!(source.expiry_policy, TerminusExpiryPolicy::Never);
+    }
+
+    #[test]
+    fn link_name_marks_global_and_volatile_subscriptions() {
+        let build = |global: bool, durable: bool| {
+            Subscription::builder()
+                .topic("prices")
+                .shared(true)
+                .global(global)
+                .durable(durable)
+                .subscription_name("sub")
+                .build()
+                .unwrap()
+                .link_name()
+                .unwrap()
+        };
+        assert_eq!(build(false, true), "sub");
+        assert_eq!(build(true, true), "sub|global");
+        assert_eq!(build(false, false), "sub|volatile");
+        assert_eq!(build(true, false), "sub|global|volatile");
+
+        let subscription = Subscription::builder().topic("prices").build().unwrap();
+        assert_eq!(subscription.link_name(), None);
+        let source = subscription.source();
+        assert_eq!(source.capabilities.unwrap().0, vec![Symbol::from("topic")]);
+        assert_eq!(source.expiry_policy, TerminusExpiryPolicy::LinkDetach);
+    }
+
+    #[test]
+    fn named_subscription_requires_name() {
+        let result = Subscription::builder()
+            .topic("prices")
+            .durable(true)
+            .build();
+        assert!(matches!(
+            result,
+            Err(SubscriptionBuilderError::SubscriptionNameIsNone)
+        ));
+        let result = Subscription::builder().topic("prices").global(true).build();
+        assert!(matches!(
+            result,
+            Err(SubscriptionBuilderError::SubscriptionNameIsNone)
+        ));
+        let result = Subscription::builder().subscription_name("sub").build();
+        assert!(matches!(result, Err(SubscriptionBuilderError::TopicIsNone)));
+    }
+}
@@ -0,0 +1,176 @@
+//! Hooks for persisting the unsettled map of a link across restarts
+//!
+//! A link configured with an [`UnsettledStore`] loads the map stored under its name when it is
+//! attached and offers it to the remote peer in the `unsettled` field of the Attach frame so that
+//! the peer's resumption logic can engage. The live unsettled map is written back to the store
+//! whenever the local link endpoint changes the state of a delivery.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use fe2o3_amqp::link::unsettled_store::InMemoryUnsettledStore;
+//!
+//! let store = Arc::new(InMemoryUnsettledStore::new());
+//! let receiver = Receiver::builder()
+//!     .name("rust-receiver-link-1")
+//!     .source("q1")
+//!     .unsettled_store(store.clone())
+//!     .attach(&mut session)
+//!     .await
+//!     .unwrap();
+//! ```
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use fe2o3_amqp_types::{definitions::DeliveryTag, messaging::DeliveryState};
+use parking_lot::Mutex;
+
+use crate::util::AsDeliveryState;
+
+use super::{ArcUnsettledMap, UnsettledMap};
+
+/// The unsettled map as it is persisted by an [`UnsettledStore`]
+pub type StoredUnsettledMap = BTreeMap<DeliveryTag, Option<DeliveryState>>;
+
+/// Persistence hook for the unsettled map of a link
+///
+/// Both methods are called from within the link's own async methods (eg. `send` or `accept`)
+/// and should therefore return quickly. Implementations that write to slow storage may want to
+/// hand the map off to a background task.
+///
+/// Changes made by the remote peer (ie. an incoming Disposition that settles a delivery) are
+/// batched and only written back on the next local change or when the link is detached.
+pub trait UnsettledStore: std::fmt::Debug + Send + Sync {
+    /// Loads the unsettled map that was last saved for the link with name `link_name`
+    ///
+    /// An empty map should be returned if nothing has been saved for the link
+    fn load(&self, link_name: &str) -> StoredUnsettledMap;
+
+    /// Saves the current unsettled map of the link with name `link_name`, replacing whatever was
+    /// saved before
+    fn save(&self, link_name: &str, map: &StoredUnsettledMap);
+}
+
+impl<S> UnsettledStore for Arc<S>
+where
+    S: UnsettledStore + ?Sized,
+{
+    fn load(&self, link_name: &str) -> StoredUnsettledMap {
+        (**self).load(link_name)
+    }
+
+    fn save(&self, link_name: &str, map: &StoredUnsettledMap) {
+        (**self).save(link_name, map)
+    }
+}
+
+/// An [`UnsettledStore`] that keeps the unsettled maps in memory
+///
+/// This does not survive a process restart, but it allows a link to be dropped and attached again
+/// with the same name, and it is useful for testing
+#[derive(Debug, Default)]
+pub struct InMemoryUnsettledStore {
+    maps: Mutex<BTreeMap<String, StoredUnsettledMap>>,
+}
+
+impl InMemoryUnsettledStore {
+    /// Creates an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl UnsettledStore for InMemoryUnsettledStore {
+    fn load(&self, link_name: &str) -> StoredUnsettledMap {
+        self.maps.lock().get(link_name).cloned().unwrap_or_default()
+    }
+
+    fn save(&self, link_name: &str, map: &StoredUnsettledMap) {
+        let mut maps = self.maps.lock();
+        if map.is_empty() {
+            maps.remove(link_name);
+        } else {
+            maps.insert(link_name.to_string(), map.clone());
+        }
+    }
+}
+
+/// An [`UnsettledStore`] bound to the name of a link
+#[derive(Debug, Clone)]
+pub(crate) struct LinkUnsettledStore {
+    link_name: String,
+    store: Arc<dyn UnsettledStore>,
+}
+
+impl LinkUnsettledStore {
+    pub(crate) fn new(link_name: String, store: Arc<dyn UnsettledStore>) -> Self {
+        Self { link_name, store }
+    }
+
+    /// Returns `None` if nothing was stored for the link
+    pub(crate) fn load(&self) -> Option<UnsettledMap<Option<DeliveryState>>> {
+        let map = self.store.load(&self.link_name);
+        match map.is_empty() {
+            true => None,
+            false => Some(map.into_iter().collect()),
+        }
+    }
+
+    pub(crate) fn save<M>(&self, unsettled: &ArcUnsettledMap<M>)
+    where
+        M: AsDeliveryState,
+    {
+        let map: StoredUnsettledMap = {
+            let guard = unsettled.read();
+            guard
+                .iter()
+                .flatten()
+                .map(|(tag, msg)| (tag.clone(), msg.as_delivery_state().clone()))
+                .collect()
+        };
+        self.store.save(&self.link_name, &map);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use fe2o3_amqp_types::{
+        definitions::DeliveryTag,
+        messaging::{Accepted, DeliveryState},
+        primitives::OrderedMap,
+    };
+    use parking_lot::RwLock;
+
+    use super::{InMemoryUnsettledStore, LinkUnsettledStore, UnsettledStore};
+
+    #[test]
+    fn link_store_round_trips_unsettled_map() {
+        let store = Arc::new(InMemoryUnsettledStore::new());
+        let link_store = LinkUnsettledStore::new(String::from("link-1"), store.clone());
+        assert!(link_store.load().is_none());
+
+        let mut map = OrderedMap::new();
+        map.insert(DeliveryTag::from(vec![1]), None);
+        map.insert(
+            DeliveryTag::from(vec![2]),
+            Some(DeliveryState::Accepted(Accepted {})),
+        );
+        let unsettled = Arc::new(RwLock::new(Some(map)));
+        link_store.save(&unsettled);
+
+        assert_eq!(store.load("link-1").len(), 2);
+        assert!(store.load("link-2").is_empty());
+        let loaded = link_store.load().unwrap();
+        assert!(matches!(
+            loaded.get(&DeliveryTag::from(vec![2])),
+            Some(Some(DeliveryState::Accepted(_)))
+        ));
+
+        // Saving an empty map forgets the link
+        *unsettled.write() = None;
+        link_store.save(&unsettled);
+        assert!(link_store.load().is_none());
+    }
+}
@@ -0,0 +1,316 @@
+//! Hooks that validate the outgoing messages of a sender
+//!
+//! A sender built with [`send_validator`](crate::link::builder::Builder::send_validator) runs the
+//! validators on every message after it is encoded and before any link credit is consumed or a
+//! delivery tag is allocated. A message that fails validation is not sent, the send returns
+//! [`SendError::Validation`](crate::link::SendError::Validation) and the link is left untouched.
+//!
+//! A validator is any type that implements [`SendValidator`], which includes closures taking an
+//! [`OutgoingMessage`]. Calling `send_validator` more than once adds the validators to a
+//! [`ValidatorChain`], which runs them in order and stops at the first error.
+//!
+//! [`Sender::send_batch`](crate::Sender::send_batch) validates each message of the batch and
+//! either fails the whole batch or skips the invalid messages, according to the
+//! [`BatchValidationPolicy`] of the sender.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use fe2o3_amqp::link::validation::{
+//!     MaxEncodedSize, OutgoingMessage, RequiredProperties, ValidationError,
+//! };
+//!
+//! let mut sender = Sender::builder()
+//!     .name("rust-sender-link-1")
+//!     .target("q1")
+//!     .send_validator(MaxEncodedSize::new(64 * 1024))
+//!     .send_validator(RequiredProperties::new(["tenant"]))
+//!     .send_validator(|message: &OutgoingMessage<'_>| {
+//!         let forbidden = message
+//!             .application_properties
+//!             .is_some_and(|properties| properties.contains_key("password"));
+//!         match forbidden {
+//!             true => Err(ValidationError::Invalid("password must not be sent".into())),
+//!             false => Ok(()),
+//!         }
+//!     })
+//!     .attach(&mut session)
+//!     .await
+//!     .unwrap();
+//! ```
+
+use std::sync::Arc;
+
+use fe2o3_amqp_types::messaging::{
+    ApplicationProperties, DeliveryAnnotations, Footer, Header, Message, MessageAnnotations,
+    Properties,
+};
+
+/// Error returned by a [`SendValidator`]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ValidationError {
+    /// The encoded message is larger than allowed
+    #[error("Encoded message of {size} bytes exceeds the maximum of {max} bytes")]
+    TooLarge {
+        /// Size of the encoded message in bytes
+        size: usize,
+
+        /// Maximum size in bytes
+        max: usize,
+    },
+
+    /// A required application property is not set
+    #[error("Application property {0} is missing")]
+    MissingProperty(String),
+
+    /// The message is rejected by a custom validator
+    #[error("Invalid message: {0}")]
+    Invalid(String),
+}
+
+/// The sections of an outgoing message that are checked by a [`SendValidator`]
+///
+/// The body is only represented by the size of the encoded message, which is the size of the
+/// payload that is split into transfers according to the `max_message_size` of the link. If body
+/// compression is enabled on the sender, this is the size after compression.
+#[derive(Debug, Clone, Copy)]
+pub struct OutgoingMessage<'a> {
+    /// Header section
+    pub header: Option<&'a Header>,
+
+    /// Delivery annotations section
+    pub delivery_annotations: Option<&'a DeliveryAnnotations>,
+
+    /// Message annotations section
+    pub message_annotations: Option<&'a MessageAnnotations>,
+
+    /// Properties section
+    pub properties: Option<&'a Properties>,
+
+    /// Application properties section
+    pub application_properties: Option<&'a ApplicationProperties>,
+
+    /// Footer section
+    pub footer: Option<&'a Footer>,
+
+    /// Size of the encoded message in bytes
+    pub encoded_size: usize,
+}
+
+impl<'a> OutgoingMessage<'a> {
+    pub(crate) fn new<T>(message: &'a Message<T>, encoded_size: usize) -> Self {
+        Self {
+            header: message.header.as_ref(),
+            delivery_annotations: message.delivery_annotations.as_ref(),
+            message_annotations: message.message_annotations.as_ref(),
+            properties: message.properties.as_ref(),
+            application_properties: message.application_properties.as_ref(),
+            footer: message.footer.as_ref(),
+            encoded_size,
+        }
+    }
+}
+
+/// Validates an outgoing message before it is sent
+///
+/// This is called from within the sender's own async methods and should therefore return quickly.
+pub trait SendValidator: Send + Sync {
+    /// Returns an error if the message must not be sent
+    fn validate(&self, message: &OutgoingMessage<'_>) -> Result<(), ValidationError>;
+}
+
+impl<F> SendValidator for F
+where
+    F: Fn(&OutgoingMessage<'_>) -> Result<(), ValidationError> + Send + Sync,
+{
+    fn validate(&self, message: &OutgoingMessage<'_>) -> Result<(), ValidationError> {
+        (self)(message)
+    }
+}
+
+/// Validators that are run in order, stopping at the first error
+///
+/// An empty chain accepts every message.
+#[derive(Clone, Default)]
+pub struct ValidatorChain {
+    validators: Vec<Arc<dyn SendValidator>>,
+}
+
+impl std::fmt::Debug for ValidatorChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ValidatorChain")
+            .field("validators", &self.validators.len())
+            .finish()
+    }
+}
+
+impl ValidatorChain {
+    /// Creates an empty chain
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a validator to the end of the chain
+    pub fn with(mut self, validator: impl SendValidator + 'static) -> Self {
+        self.push(validator);
+        self
+    }
+
+    /// Adds a validator to the end of the chain
+    pub fn push(&mut self, validator: impl SendValidator + 'static) {
+        self.validators.push(Arc::new(validator));
+    }
+
+    /// Number of validators in the chain
+    pub fn len(&self) -> usize {
+        self.validators.len()
+    }
+
+    /// Whether the chain has no validator
+    pub fn is_empty(&self) -> bool {
+        self.validators.is_empty()
+    }
+
+    pub(crate) fn validate_message<T>(
+        &self,
+        message: &Message<T>,
+        encoded_size: usize,
+    ) -> Result<(), ValidationError> {
+        if self.validators.is_empty() {
+            return Ok(());
+        }
+        self.validate(&OutgoingMessage::new(message, encoded_size))
+    }
+}
+
+impl SendValidator for ValidatorChain {
+    fn validate(&self, message: &OutgoingMessage<'_>) -> Result<(), ValidationError> {
+        self.validators
+            .iter()
+            .try_for_each(|validator| validator.validate(message))
+    }
+}
+
+/// Fails messages whose encoded size exceeds a maximum
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaxEncodedSize {
+    max: usize,
+}
+
+impl MaxEncodedSize {
+    /// Creates a validator that fails messages larger than `max` bytes once encoded
+    pub fn new(max: usize) -> Self {
+        Self { max }
+    }
+}
+
+impl SendValidator for MaxEncodedSize {
+    fn validate(&self, message: &OutgoingMessage<'_>) -> Result<(), ValidationError> {
+        match message.encoded_size > self.max {
+            true => Err(ValidationError::TooLarge {
+                size: message.encoded_size,
+                max: self.max,
+            }),
+            false => Ok(()),
+        }
+    }
+}
+
+/// Fails messages that do not set all of the given application properties
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequiredProperties {
+    keys: Vec<String>,
+}
+
+impl RequiredProperties {
+    /// Creates a validator that requires the application properties `keys`
+    pub fn new(keys: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            keys: keys.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl SendValidator for RequiredProperties {
+    fn validate(&self, message: &OutgoingMessage<'_>) -> Result<(), ValidationError> {
+        let missing = self.keys.iter().find(|key| {
+            !message
+                .application_properties
+                .is_some_and(|properties| properties.contains_key(key.as_str()))
+        });
+        match missing {
+            Some(key) => Err(ValidationError::MissingProperty(key.clone())),
+            None => Ok(()),
+        }
+    }
+}
+
+/// How [`Sender::send_batch`](crate::Sender::send_batch) handles messages that fail validation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BatchValidationPolicy {
+    /// Nothing is sent if any message of the batch fails validation
+    #[default]
+    FailBatch,
+
+    /// The messages that fail validation are skipped and the others are sent
+    SkipInvalid,
+}
+
+#[cfg(test)]
+mod tests {
+    use fe2o3_amqp_types::messaging::{ApplicationProperties, Message};
+
+    use super::{
+        MaxEncodedSize, OutgoingMessage, RequiredProperties, SendValidator, ValidationError,
+        ValidatorChain,
+    };
+
+    #[test]
+    fn test_validator_chain_stops_at_first_error() {
+        let message = Message::builder()
+            .application_properties(ApplicationProperties::builder().insert("tenant", 1).build())
+            .body("hello")
+            .build();
+
+        let chain = ValidatorChain::new()
+            .with(MaxEncodedSize::new(100))
+            .with(RequiredProperties::new(["tenant", "region"]))
+            .with(|_: &OutgoingMessage<'_>| Err(ValidationError::Invalid("never".into())));
+        assert_eq!(chain.len(), 3);
+        assert_eq!(
+            chain.validate(&OutgoingMessage::new(&message, 50)),
+            Err(ValidationError::MissingProperty("region".into()))
+        );
+        assert_eq!(
+            chain.validate(&OutgoingMessage::new(&message, 101)),
+            Err(ValidationError::TooLarge {
+                size: 101,
+                max: 100
+            })
+        );
+
+        let chain = ValidatorChain::new()
+            .with(MaxEncodedSize::new(100))
+            .with(RequiredProperties::new(["tenant"]));
+        assert_eq!(chain.validate(&OutgoingMessage::new(&message, 100)), Ok(()));
+        assert_eq!(
+            ValidatorChain::new().validate(&OutgoingMessage::new(&message, usize::MAX)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_required_properties_without_application_properties() {
+        let message = Message::builder().body("hello").build();
+        let validator = RequiredProperties::new(["tenant"]);
+        assert_eq!(
+            validator.validate(&OutgoingMessage::new(&message, 0)),
+            Err(ValidationError::MissingProperty("tenant".into()))
+        );
+        assert_eq!(
+            RequiredProperties::new(Vec::<String>::new())
+                .validate(&OutgoingMessage::new(&message, 0)),
+            Ok(())
+        );
+    }
+}
@@ -72,6 +72,16 @@ macro_rules! cfg_rustls {
     }
 }
 
+macro_rules! cfg_config {
+    ($($item:item)*) => {
+        $(
+            #[cfg_attr(docsrs, doc(cfg(feature = "config")))]
+            #[cfg(feature = "config")]
+            $item
+        )*
+    }
+}
+
 macro_rules! cfg_scram {
     ($($item:item)*) => {
         $(
@@ -81,3 +91,44 @@ macro_rules! cfg_scram {
         )*
     }
 }
+
+macro_rules! cfg_compression {
+    ($($item:item)*) => {
+        $(
+            #[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+            #[cfg(feature = "compression")]
+            $item
+        )*
+    }
+}
+
+macro_rules! cfg_testing {
+    ($($item:item)*) => {
+        $(
+            #[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
+            #[cfg(feature = "testing")]
+            $item
+        )*
+    }
+}
+
+/// tokio is the runtime unless `"rt-async-std"` is enabled, and is always used on wasm32 targets
+macro_rules! cfg_rt_tokio {
+    ($($item:item)*) => {
+        $(
+            #[cfg(any(target_arch = "wasm32", not(feature = "rt-async-std")))]
+            $item
+        )*
+    }
+}
+
+macro_rules! cfg_rt_async_std {
+    ($($item:item)*) => {
+        $(
+            #[cfg_attr(docsrs, doc(cfg(feature = "rt-async-std")))]
+            #[cfg(not(target_arch = "wasm32"))]
+            #[cfg(feature = "rt-async-std")]
+            $item
+        )*
+    }
+}
@@ -0,0 +1,60 @@
+//! Re-exports of the items that most programs need
+//!
+//! The handles returned by [`Connection::open`] and [`Session::begin`] can be named with
+//! [`ClientConnectionHandle`] and [`ClientSessionHandle`], and a delivery whose body type is not
+//! known in advance with [`ValueDelivery`].
+//!
+//! ```rust,no_run
+//! use fe2o3_amqp::prelude::*;
+//!
+//! async fn send_and_receive(
+//!     session: &mut ClientSessionHandle,
+//! ) -> Result<ValueDelivery, Box<dyn std::error::Error>> {
+//!     let mut sender = Sender::attach(session, "rust-sender-link-1", "q1").await?;
+//!     let mut receiver = Receiver::attach(session, "rust-receiver-link-1", "q1").await?;
+//!
+//!     let message = Message::builder().value(Value::from("hello AMQP")).build();
+//!     let sendable = Sendable::builder().message(message).settled(false).build();
+//!     let receipt: SendReceipt = sender.send(sendable).await?;
+//!     assert!(receipt.is_accepted());
+//!
+//!     let delivery: ValueDelivery = receiver.recv().await?;
+//!     receiver.accept(&delivery).await?;
+//!
+//!     sender.close().await?;
+//!     receiver.close().await?;
+//!     Ok(delivery)
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let mut connection: ClientConnectionHandle = Connection::builder()
+//!         .container_id("connection-1")
+//!         .sasl_profile(SaslProfile::plain("guest", "guest"))
+//!         .open("amqp://localhost:5672")
+//!         .await?;
+//!     let mut session = Session::begin(&mut connection).await?;
+//!
+//!     let delivery = send_and_receive(&mut session).await?;
+//!     println!("{:?}", delivery.body());
+//!
+//!     session.end().await?;
+//!     connection.close().await?;
+//!     Ok(())
+//! }
+//! ```
+
+pub use crate::{
+    connection::{ClientConnectionHandle, Connection, OpenError},
+    link::{
+        delivery::{Delivery, SendReceipt, Sendable, ValueDelivery},
+        DetachError, Receiver, ReceiverAttachError, RecvError, SendError, Sender,
+        SenderAttachError,
+    },
+    sasl_profile::SaslProfile,
+    session::{BeginError, ClientSessionHandle, Session},
+    types::{
+        messaging::{Body, Message},
+        primitives::Value,
+    },
+};
@@ -0,0 +1,131 @@
+//! async-std runtime
+
+use std::{
+    future::Future,
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use async_io::Timer;
+use futures_util::Stream;
+use tokio_util::compat::{Compat, FuturesAsyncReadCompatExt};
+
+pub(crate) use async_std::future::TimeoutError as Elapsed;
+pub(crate) use async_std::task::JoinHandle;
+
+/// An async-std `TcpStream` that implements tokio's `AsyncRead` and `AsyncWrite`
+pub(crate) type TcpStream = Compat<async_std::net::TcpStream>;
+
+pub(crate) fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    async_std::task::spawn(future)
+}
+
+/// Where the connection and session event loops are run
+#[derive(Clone, Default)]
+pub(crate) enum Spawner {
+    /// Spawned with `async_std::task::spawn`
+    #[default]
+    Current,
+
+    /// Not spawned but left in the slot for the caller to drive
+    Deferred(super::Deferred),
+}
+
+impl std::fmt::Debug for Spawner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Current => write!(f, "Current"),
+            Self::Deferred(_) => write!(f, "Deferred"),
+        }
+    }
+}
+
+impl Spawner {
+    /// Returns `None` if the event loop is deferred
+    pub(crate) fn spawn<F>(&self, future: F) -> Option<JoinHandle<()>>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        match self {
+            Self::Current => Some(async_std::task::spawn(future)),
+            Self::Deferred(slot) => {
+                *slot.lock() = Some(Box::pin(future));
+                None
+            }
+        }
+    }
+}
+
+pub(crate) async fn timeout<F: Future>(
+    duration: Duration,
+    future: F,
+) -> Result<F::Output, Elapsed> {
+    async_std::future::timeout(duration, future).await
+}
+
+pub(crate) fn now() -> Instant {
+    Instant::now()
+}
+
+pub(crate) async fn connect(addrs: &[SocketAddr]) -> io::Result<TcpStream> {
+    async_std::net::TcpStream::connect(addrs)
+        .await
+        .map(FuturesAsyncReadCompatExt::compat)
+}
+
+/// A timer that can be reset to expire after the same duration again
+#[derive(Debug)]
+pub(crate) struct Delay {
+    timer: Timer,
+    duration: Duration,
+}
+
+impl Delay {
+    pub(crate) fn new(duration: Duration) -> Self {
+        let timer = Timer::after(duration);
+        Self { timer, duration }
+    }
+
+    pub(crate) fn reset(&mut self) {
+        let duration = self.duration;
+        self.timer.set_after(duration);
+    }
+}
+
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.timer).poll(cx).map(|_| ())
+    }
+}
+
+/// A stream that yields immediately and then once per period
+#[derive(Debug)]
+pub(crate) struct Interval {
+    timer: Timer,
+}
+
+impl Interval {
+    pub(crate) fn new(period: Duration) -> Self {
+        let timer = Timer::interval_at(Instant::now(), period);
+        Self { timer }
+    }
+}
+
+impl Stream for Interval {
+    type Item = ();
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.timer)
+            .poll_next(cx)
+            .map(|instant| instant.map(|_| ()))
+    }
+}
@@ -0,0 +1,28 @@
+//! Abstraction over the async runtime
+//!
+//! The runtime spawns the connection and session event loops, drives the timers and opens the TCP
+//! connection in [`Connection::open`](crate::Connection::open). The channels are always from
+//! `tokio::sync`, which does not depend on a runtime.
+//!
+//! tokio is used unless the `"rt-async-std"` feature is enabled. wasm32 targets always use tokio.
+
+cfg_not_wasm32! {
+    use std::sync::Arc;
+
+    use parking_lot::Mutex;
+
+    use crate::connection::EngineFuture;
+
+    /// A slot that an event loop is left in instead of being spawned
+    pub(crate) type Deferred = Arc<Mutex<Option<EngineFuture>>>;
+}
+
+cfg_rt_tokio! {
+    mod tokio_rt;
+    pub(crate) use tokio_rt::*;
+}
+
+cfg_rt_async_std! {
+    mod async_std_rt;
+    pub(crate) use async_std_rt::*;
+}
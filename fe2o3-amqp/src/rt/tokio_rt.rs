@@ -0,0 +1,141 @@
+//! tokio runtime
+
+pub(crate) use tokio::task::JoinHandle;
+
+cfg_not_wasm32! {
+    use std::{
+        future::Future,
+        io,
+        net::SocketAddr,
+        pin::Pin,
+        task::{Context, Poll},
+        time::Duration,
+    };
+
+    use futures_util::Stream;
+    use tokio::time::{Instant, Sleep};
+    use tokio_stream::wrappers::IntervalStream;
+
+    pub(crate) use tokio::net::TcpStream;
+    pub(crate) use tokio::time::error::Elapsed;
+
+    pub(crate) fn spawn<F>(future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        tokio::spawn(future)
+    }
+
+    /// Where the connection and session event loops are run
+    #[derive(Clone, Default)]
+    pub(crate) enum Spawner {
+        /// Spawned with `tokio::spawn` on the current runtime
+        #[default]
+        Current,
+
+        /// Spawned on the runtime of the handle
+        Handle(tokio::runtime::Handle),
+
+        /// Spawned with `tokio::task::spawn_local` on the current `LocalSet`
+        Local,
+
+        /// Not spawned but left in the slot for the caller to drive
+        Deferred(super::Deferred),
+    }
+
+    impl std::fmt::Debug for Spawner {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::Current => write!(f, "Current"),
+                Self::Handle(_) => write!(f, "Handle"),
+                Self::Local => write!(f, "Local"),
+                Self::Deferred(_) => write!(f, "Deferred"),
+            }
+        }
+    }
+
+    impl Spawner {
+        /// Returns `None` if the event loop is deferred
+        pub(crate) fn spawn<F>(&self, future: F) -> Option<JoinHandle<()>>
+        where
+            F: Future<Output = ()> + Send + 'static,
+        {
+            match self {
+                Self::Current => Some(tokio::spawn(future)),
+                Self::Handle(handle) => Some(handle.spawn(future)),
+                Self::Local => Some(tokio::task::spawn_local(future)),
+                Self::Deferred(slot) => {
+                    *slot.lock() = Some(Box::pin(future));
+                    None
+                }
+            }
+        }
+    }
+
+    pub(crate) async fn timeout<F: Future>(
+        duration: Duration,
+        future: F,
+    ) -> Result<F::Output, Elapsed> {
+        tokio::time::timeout(duration, future).await
+    }
+
+    /// The current instant of the tokio clock, which follows the clock of a paused runtime
+    pub(crate) fn now() -> std::time::Instant {
+        Instant::now().into_std()
+    }
+
+    pub(crate) async fn connect(addrs: &[SocketAddr]) -> io::Result<TcpStream> {
+        TcpStream::connect(addrs).await
+    }
+
+    /// A timer that can be reset to expire after the same duration again
+    #[derive(Debug)]
+    pub(crate) struct Delay {
+        sleep: Pin<Box<Sleep>>,
+        duration: Duration,
+    }
+
+    impl Delay {
+        pub(crate) fn new(duration: Duration) -> Self {
+            let sleep = Box::pin(tokio::time::sleep(duration));
+            Self { sleep, duration }
+        }
+
+        pub(crate) fn reset(&mut self) {
+            let next = Instant::now() + self.duration;
+            self.sleep.as_mut().reset(next);
+        }
+    }
+
+    impl Future for Delay {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            self.sleep.as_mut().poll(cx)
+        }
+    }
+
+    /// A stream that yields immediately and then once per period
+    #[derive(Debug)]
+    pub(crate) struct Interval {
+        inner: IntervalStream,
+    }
+
+    impl Interval {
+        pub(crate) fn new(period: Duration) -> Self {
+            let inner = IntervalStream::new(tokio::time::interval(period));
+            Self { inner }
+        }
+    }
+
+    impl Stream for Interval {
+        type Item = ();
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            Pin::new(&mut self.inner)
+                .poll_next(cx)
+                .map(|instant| instant.map(|_| ()))
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use fe2o3_amqp_types::primitives::Symbol;
+
 #[cfg(feature = "scram")]
 use crate::auth::error::ScramErrorKind;
 
@@ -8,6 +10,13 @@ pub enum Error {
     #[error("Not implemented {0:?}")]
     NotImplemented(Option<String>),
 
+    /// The mechanism of the profile is not offered by the server
+    #[error("Mechanism is not offered by the server. Offered: {:?}", .offered)]
+    MechanismMismatch {
+        /// Mechanisms offered by the server
+        offered: Vec<Symbol>,
+    },
+
     /// Error with SCRAM
     #[cfg_attr(docsrs, doc(cfg(feature = "scram")))]
     #[cfg(feature = "scram")]
@@ -1,6 +1,6 @@
 //! Session builder
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use fe2o3_amqp_types::definitions::{Fields, Handle, TransferNumber};
 use serde_amqp::primitives::Symbol;
@@ -8,15 +8,25 @@ use slab::Slab;
 use tokio::sync::mpsc;
 
 use crate::{
-    connection::{AllocSessionError, ConnectionHandle},
+    connection::{AllocSessionError, ConnectionHandle, SessionRelay},
     control::SessionControl,
     endpoint::OutgoingChannel,
-    session::{engine::SessionEngine, SessionState},
+    session::{
+        engine::SessionEngine, incoming_budget::IncomingBudget, overflow::LinkOverflow,
+        SessionState,
+    },
     util::Constant,
     Session,
 };
 
-use super::{error::BeginError, SessionHandle, DEFAULT_WINDOW};
+use super::{error::BeginError, IncomingWindowPolicy, SessionHandle, DEFAULT_WINDOW};
+
+cfg_not_wasm32! {
+    use crate::{
+        connection::EngineFuture,
+        rt::{Deferred, Spawner},
+    };
+}
 
 pub(crate) const DEFAULT_SESSION_CONTROL_BUFFER_SIZE: usize = 128;
 pub(crate) const DEFAULT_SESSION_MUX_BUFFER_SIZE: usize = u16::MAX as usize;
@@ -30,6 +40,10 @@ pub struct Builder {
     /// The initial incoming-window of the sender
     pub incoming_window: TransferNumber,
 
+    /// How the incoming-window that is advertised to the remote peer is computed. The
+    /// `incoming_window` is the largest window that is advertised
+    pub incoming_window_policy: IncomingWindowPolicy,
+
     /// The initial outgoing-window of the sender
     pub outgoing_window: TransferNumber,
 
@@ -49,10 +63,34 @@ pub struct Builder {
     /// that are used by links attached to the session
     pub buffer_size: usize,
 
+    /// Maximum number of payload bytes of incoming transfers that are buffered for the session
+    /// but not yet taken by its receivers. The connection stops reading from the transport while
+    /// a session is over its limit. `None` means no limit
+    pub incoming_buffer_limit: Option<usize>,
+
+    /// Maximum number of payload bytes of the incoming deliveries that are not complete yet
+    /// across all the links of the session. The session is ended with
+    /// `amqp:resource-limit-exceeded` once the limit is exceeded. `None` means no limit
+    pub incomplete_incoming_limit: Option<usize>,
+
+    /// Where the event loop is run. The spawner of the connection is used if this is `None`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) spawner: Option<Spawner>,
+
     /// Acceptor for incoming transaction control links
     #[cfg(not(target_arch = "wasm32"))]
     #[cfg(all(feature = "transaction", feature = "acceptor"))]
     pub(crate) control_link_acceptor: Option<ControlLinkAcceptor>,
+
+    /// Computes the local Begin of an incoming session from the Begin sent by the remote peer
+    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg(feature = "acceptor")]
+    pub(crate) on_begin: Option<crate::acceptor::session::OnBegin>,
+
+    /// Duration a link detached by the remote peer is kept for the remote peer to resume it
+    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg(feature = "acceptor")]
+    pub(crate) parked_link_expiry: std::time::Duration,
 }
 
 impl Default for Builder {
@@ -60,16 +98,30 @@ impl Default for Builder {
         Self {
             next_outgoing_id: 0,
             incoming_window: DEFAULT_WINDOW,
+            incoming_window_policy: IncomingWindowPolicy::Static,
             outgoing_window: DEFAULT_WINDOW,
             handle_max: Default::default(),
             offered_capabilities: None,
             desired_capabilities: None,
             properties: None,
             buffer_size: DEFAULT_SESSION_MUX_BUFFER_SIZE,
+            incoming_buffer_limit: None,
+            incomplete_incoming_limit: None,
+
+            #[cfg(not(target_arch = "wasm32"))]
+            spawner: None,
 
             #[cfg(not(target_arch = "wasm32"))]
             #[cfg(all(feature = "transaction", feature = "acceptor"))]
             control_link_acceptor: None,
+
+            #[cfg(not(target_arch = "wasm32"))]
+            #[cfg(feature = "acceptor")]
+            on_begin: None,
+
+            #[cfg(not(target_arch = "wasm32"))]
+            #[cfg(feature = "acceptor")]
+            parked_link_expiry: crate::acceptor::parked_link::DEFAULT_PARKED_LINK_EXPIRY,
         }
     }
 }
@@ -88,21 +140,28 @@ cfg_transaction! {
                 outgoing_channel: OutgoingChannel,
                 control_link_acceptor: ControlLinkAcceptor,
                 local_state: SessionState,
+                max_frame_size: usize,
             ) -> TxnSession<Session> {
                 let txn_manager = TransactionManager::new(outgoing, control_link_acceptor);
                 let session = Session {
                     // control,
                     outgoing_channel,
                     local_state,
-                    initial_outgoing_id: Constant::new(self.next_outgoing_id),
-                    next_outgoing_id: self.next_outgoing_id,
+                    initial_outgoing_id: Constant::new(self.next_outgoing_id.into()),
+                    next_outgoing_id: self.next_outgoing_id.into(),
                     incoming_window: self.incoming_window,
+                    initial_incoming_window: Constant::new(self.incoming_window),
+                    incoming_window_policy: self.incoming_window_policy,
+                    advertised_incoming_window: self.incoming_window,
+                    incoming_window_limit: self.incoming_window,
                     outgoing_window: self.outgoing_window,
                     handle_max: self.handle_max,
                     incoming_channel: None,
-                    next_incoming_id: 0,
+                    remote_handle_max: Handle::default(),
+                    next_incoming_id: 0.into(),
                     remote_incoming_window: 0,
                     remote_incoming_window_exhausted_buffer: VecDeque::new(),
+                    max_frame_size,
                     remote_outgoing_window: 0,
                     offered_capabilities: self.offered_capabilities,
                     desired_capabilities: self.desired_capabilities,
@@ -111,7 +170,14 @@ cfg_transaction! {
                     link_name_by_output_handle: Slab::new(),
                     link_by_name: HashMap::new(),
                     link_by_input_handle: HashMap::new(),
+                    detached_link_names: HashSet::new(),
+                    abandoned_links: HashMap::new(),
                     delivery_tag_by_id: HashMap::new(),
+                    incomplete_incoming_limit: self.incomplete_incoming_limit,
+                    incomplete_incoming: HashMap::new(),
+                    end_error: None,
+                    link_overflow: LinkOverflow::default(),
+                    routing_history: VecDeque::new(),
                 };
 
                 TxnSession {
@@ -135,19 +201,26 @@ impl Builder {
         // control: mpsc::Sender<SessionControl>,
         outgoing_channel: OutgoingChannel,
         local_state: SessionState,
+        max_frame_size: usize,
     ) -> Session {
         Session {
             outgoing_channel,
             local_state,
-            initial_outgoing_id: Constant::new(self.next_outgoing_id),
-            next_outgoing_id: self.next_outgoing_id,
+            initial_outgoing_id: Constant::new(self.next_outgoing_id.into()),
+            next_outgoing_id: self.next_outgoing_id.into(),
             incoming_window: self.incoming_window,
+            initial_incoming_window: Constant::new(self.incoming_window),
+            incoming_window_policy: self.incoming_window_policy,
+            advertised_incoming_window: self.incoming_window,
+            incoming_window_limit: self.incoming_window,
             outgoing_window: self.outgoing_window,
             handle_max: self.handle_max,
             incoming_channel: None,
-            next_incoming_id: 0,
+            remote_handle_max: Handle::default(),
+            next_incoming_id: 0.into(),
             remote_incoming_window: 0,
             remote_incoming_window_exhausted_buffer: VecDeque::new(),
+            max_frame_size,
             remote_outgoing_window: 0,
             offered_capabilities: self.offered_capabilities,
             desired_capabilities: self.desired_capabilities,
@@ -156,7 +229,14 @@ impl Builder {
             link_name_by_output_handle: Slab::new(),
             link_by_name: HashMap::new(),
             link_by_input_handle: HashMap::new(),
+            detached_link_names: HashSet::new(),
+            abandoned_links: HashMap::new(),
             delivery_tag_by_id: HashMap::new(),
+            incomplete_incoming_limit: self.incomplete_incoming_limit,
+            incomplete_incoming: HashMap::new(),
+            end_error: None,
+            link_overflow: LinkOverflow::default(),
+            routing_history: VecDeque::new(),
         }
     }
 
@@ -172,6 +252,15 @@ impl Builder {
         self
     }
 
+    /// How the incoming-window that is advertised to the remote peer is computed
+    ///
+    /// The default is [`IncomingWindowPolicy::Static`], which always advertises the whole
+    /// `incoming_window`.
+    pub fn incoming_window_policy(mut self, policy: IncomingWindowPolicy) -> Self {
+        self.incoming_window_policy = policy;
+        self
+    }
+
     /// The initial outgoing-window of the sender
     pub fn outgoing_window(mut self, value: TransferNumber) -> Self {
         self.outgoing_window = value;
@@ -227,6 +316,28 @@ impl Builder {
         self
     }
 
+    /// Maximum number of payload bytes of incoming transfers that are buffered for the session
+    /// but not yet taken by its receivers.
+    ///
+    /// Once the limit is reached, the connection stops reading from the transport until the
+    /// receivers catch up, which also stalls the other sessions on the same connection. A single
+    /// transfer larger than the limit is still delivered once nothing else is buffered.
+    pub fn incoming_buffer_limit(mut self, bytes: usize) -> Self {
+        self.incoming_buffer_limit = Some(bytes);
+        self
+    }
+
+    /// Maximum number of payload bytes of the incoming deliveries that are not complete yet
+    /// across all the links of the session.
+    ///
+    /// A peer may keep a large multi-transfer delivery in flight on each of many links. Once the
+    /// partial deliveries add up to more than the limit, the session is ended with
+    /// `amqp:resource-limit-exceeded` and the name of the link whose transfer exceeded the limit.
+    pub fn incomplete_incoming_limit(mut self, bytes: usize) -> Self {
+        self.incomplete_incoming_limit = Some(bytes);
+        self
+    }
+
     // TODO
     // /// Enable handling remotely initiated control link and transaction by setting the
     // /// `control_link_acceptor` field
@@ -251,18 +362,28 @@ impl Builder {
         ///     .await.unwrap();
         /// ```
         ///
+        #[cfg_attr(feature = "tracing", tracing::instrument(name = "Session::begin", skip_all, fields(container_id = %connection.container_id)))]
         pub async fn begin(
             self,
             connection: &mut ConnectionHandle<()>,
         ) -> Result<SessionHandle<()>, BeginError> {
+            let spawner = self
+                .spawner
+                .clone()
+                .unwrap_or_else(|| connection.spawner.clone());
             let local_state = SessionState::Unmapped;
             let (session_control_tx, session_control_rx) =
                 mpsc::channel::<SessionControl>(DEFAULT_SESSION_CONTROL_BUFFER_SIZE);
             let (incoming_tx, incoming_rx) = mpsc::channel(self.buffer_size);
             let (outgoing_tx, outgoing_rx) = mpsc::channel(self.buffer_size);
+            let incoming_budget = IncomingBudget::new(self.incoming_buffer_limit);
 
             // create session in connection::Engine
-            let outgoing_channel = match connection.allocate_session(incoming_tx).await {
+            let relay = SessionRelay {
+                tx: incoming_tx,
+                incoming_budget: incoming_budget.clone(),
+            };
+            let outgoing_channel = match connection.allocate_session(relay).await {
                 Ok(channel) => channel,
                 Err(alloc_error) => match alloc_error {
                     AllocSessionError::IllegalState => return Err(BeginError::IllegalConnectionState),
@@ -270,14 +391,22 @@ impl Builder {
                         // Locally initiating session exceeded channel max
                         return Err(BeginError::LocalChannelMaxReached);
                     }
+                    AllocSessionError::MaxSessionsReached => {
+                        return Err(BeginError::LocalMaxSessionsReached);
+                    }
                 },
             };
 
             #[cfg(not(all(feature = "transaction", feature = "acceptor")))]
             let (engine_handle, outcome) = {
-                let session = self.into_session(outgoing_channel, local_state);
+                let session = self.into_session(
+                    outgoing_channel,
+                    local_state,
+                    connection.max_frame_size,
+                );
                 let engine = SessionEngine::begin_client_session(
                     connection.control.clone(),
+                    connection.container_id.clone(),
                     session,
                     session_control_rx,
                     incoming_rx,
@@ -285,7 +414,7 @@ impl Builder {
                     outgoing_rx,
                 )
                 .await?;
-                engine.spawn()
+                engine.spawn(&spawner)
             };
 
             #[cfg(all(feature = "transaction", feature = "acceptor"))]
@@ -299,9 +428,11 @@ impl Builder {
                             outgoing_channel,
                             control_link_acceptor,
                             local_state,
+                            connection.max_frame_size,
                         );
                         let engine = SessionEngine::begin_client_session(
                             connection.control.clone(),
+                            connection.container_id.clone(),
                             session,
                             session_control_rx,
                             incoming_rx,
@@ -309,12 +440,17 @@ impl Builder {
                             outgoing_rx,
                         )
                         .await?;
-                        engine.spawn()
+                        engine.spawn(&spawner)
                     }
                     None => {
-                        let session = this.into_session(outgoing_channel, local_state);
+                        let session = this.into_session(
+                            outgoing_channel,
+                            local_state,
+                            connection.max_frame_size,
+                        );
                         let engine = SessionEngine::begin_client_session(
                             connection.control.clone(),
+                            connection.container_id.clone(),
                             session,
                             session_control_rx,
                             incoming_rx,
@@ -322,7 +458,7 @@ impl Builder {
                             outgoing_rx,
                         )
                         .await?;
-                        engine.spawn()
+                        engine.spawn(&spawner)
                     }
                 }
             };
@@ -334,9 +470,63 @@ impl Builder {
                 outcome,
                 outgoing: outgoing_tx,
                 link_listener: (),
+                incoming_budget,
+                container_id: connection.container_id.clone(),
+                #[cfg(feature = "testing")]
+                raw_incoming: None,
             };
             Ok(handle)
         }
+
+        /// Begins a new session like [`begin`](#method.begin) but returns the event loop of the
+        /// session instead of spawning it
+        ///
+        /// The caller must drive the returned future, eg. in a [`tokio::task::JoinSet`], for the
+        /// session to make progress. Dropping the [`SessionHandle`] ends the session as usual
+        /// while the future is being driven. See [`EngineFuture`] for the cancellation semantics.
+        ///
+        /// # Example
+        ///
+        /// ```rust, ignore
+        /// let (mut session, engine) = Session::builder()
+        ///     .begin_unspawned(&mut connection)
+        ///     .await
+        ///     .unwrap();
+        /// tasks.spawn(engine);
+        /// ```
+        pub async fn begin_unspawned(
+            mut self,
+            connection: &mut ConnectionHandle<()>,
+        ) -> Result<(SessionHandle<()>, EngineFuture), BeginError> {
+            let slot = Deferred::default();
+            self.spawner = Some(Spawner::Deferred(slot.clone()));
+            let session = self.begin(connection).await?;
+            let engine = slot
+                .lock()
+                .take()
+                .expect("The event loop is left in the slot once the session is begun");
+            Ok((session, engine))
+        }
+
+        /// Spawn the event loop of the session on the runtime of `handle` instead of where the
+        /// event loop of the connection is spawned
+        #[cfg(not(feature = "rt-async-std"))]
+        pub fn spawn_on(mut self, handle: tokio::runtime::Handle) -> Self {
+            self.spawner = Some(Spawner::Handle(handle));
+            self
+        }
+
+        /// Spawn the event loop of the session with [`tokio::task::spawn_local`] if `value` is
+        /// true, which requires the session to be begun within a [`tokio::task::LocalSet`]. The
+        /// session is spawned on the current runtime if `value` is false
+        #[cfg(not(feature = "rt-async-std"))]
+        pub fn spawn_local(mut self, value: bool) -> Self {
+            self.spawner = Some(match value {
+                true => Spawner::Local,
+                false => Spawner::Current,
+            });
+            self
+        }
     }
 
     cfg_wasm32! {
@@ -351,6 +541,7 @@ impl Builder {
         ///     .await.unwrap();
         /// ```
         ///
+        #[cfg_attr(feature = "tracing", tracing::instrument(name = "Session::begin", skip_all, fields(container_id = %connection.container_id)))]
         pub async fn begin_on_local_set(
             self,
             connection: &mut ConnectionHandle<()>,
@@ -361,9 +552,14 @@ impl Builder {
                 mpsc::channel::<SessionControl>(DEFAULT_SESSION_CONTROL_BUFFER_SIZE);
             let (incoming_tx, incoming_rx) = mpsc::channel(self.buffer_size);
             let (outgoing_tx, outgoing_rx) = mpsc::channel(self.buffer_size);
+            let incoming_budget = IncomingBudget::new(self.incoming_buffer_limit);
 
             // create session in connection::Engine
-            let outgoing_channel = match connection.allocate_session(incoming_tx).await {
+            let relay = SessionRelay {
+                tx: incoming_tx,
+                incoming_budget: incoming_budget.clone(),
+            };
+            let outgoing_channel = match connection.allocate_session(relay).await {
                 Ok(channel) => channel,
                 Err(alloc_error) => match alloc_error {
                     AllocSessionError::IllegalState => return Err(BeginError::IllegalConnectionState),
@@ -371,13 +567,21 @@ impl Builder {
                         // Locally initiating session exceeded channel max
                         return Err(BeginError::LocalChannelMaxReached);
                     }
+                    AllocSessionError::MaxSessionsReached => {
+                        return Err(BeginError::LocalMaxSessionsReached);
+                    }
                 },
             };
 
             let (engine_handle, outcome) = {
-                let session = self.into_session(outgoing_channel, local_state);
+                let session = self.into_session(
+                    outgoing_channel,
+                    local_state,
+                    connection.max_frame_size,
+                );
                 let engine = SessionEngine::begin_client_session(
                     connection.control.clone(),
+                    connection.container_id.clone(),
                     session,
                     session_control_rx,
                     incoming_rx,
@@ -391,10 +595,14 @@ impl Builder {
             let handle = SessionHandle {
                 is_ended: false,
                 control: session_control_tx,
-                engine_handle,
+                engine_handle: Some(engine_handle),
                 outcome,
                 outgoing: outgoing_tx,
                 link_listener: (),
+                incoming_budget,
+                container_id: connection.container_id.clone(),
+                #[cfg(feature = "testing")]
+                raw_incoming: None,
             };
             Ok(handle)
         }
@@ -412,6 +620,7 @@ impl Builder {
         ///     .await.unwrap();
         /// ```
         ///
+        #[cfg_attr(feature = "tracing", tracing::instrument(name = "Session::begin", skip_all, fields(container_id = %connection.container_id)))]
         pub async fn begin_on_current_local_set(
             self,
             connection: &mut ConnectionHandle<()>,
@@ -421,9 +630,14 @@ impl Builder {
                 mpsc::channel::<SessionControl>(DEFAULT_SESSION_CONTROL_BUFFER_SIZE);
             let (incoming_tx, incoming_rx) = mpsc::channel(self.buffer_size);
             let (outgoing_tx, outgoing_rx) = mpsc::channel(self.buffer_size);
+            let incoming_budget = IncomingBudget::new(self.incoming_buffer_limit);
 
             // create session in connection::Engine
-            let outgoing_channel = match connection.allocate_session(incoming_tx).await {
+            let relay = SessionRelay {
+                tx: incoming_tx,
+                incoming_budget: incoming_budget.clone(),
+            };
+            let outgoing_channel = match connection.allocate_session(relay).await {
                 Ok(channel) => channel,
                 Err(alloc_error) => match alloc_error {
                     AllocSessionError::IllegalState => return Err(BeginError::IllegalConnectionState),
@@ -431,13 +645,21 @@ impl Builder {
                         // Locally initiating session exceeded channel max
                         return Err(BeginError::LocalChannelMaxReached);
                     }
+                    AllocSessionError::MaxSessionsReached => {
+                        return Err(BeginError::LocalMaxSessionsReached);
+                    }
                 },
             };
 
             let (engine_handle, outcome) = {
-                let session = self.into_session(outgoing_channel, local_state);
+                let session = self.into_session(
+                    outgoing_channel,
+                    local_state,
+                    connection.max_frame_size,
+                );
                 let engine = SessionEngine::begin_client_session(
                     connection.control.clone(),
+                    connection.container_id.clone(),
                     session,
                     session_control_rx,
                     incoming_rx,
@@ -451,10 +673,14 @@ impl Builder {
             let handle = SessionHandle {
                 is_ended: false,
                 control: session_control_tx,
-                engine_handle,
+                engine_handle: Some(engine_handle),
                 outcome,
                 outgoing: outgoing_tx,
                 link_listener: (),
+                incoming_budget,
+                container_id: connection.container_id.clone(),
+                #[cfg(feature = "testing")]
+                raw_incoming: None,
             };
             Ok(handle)
         }
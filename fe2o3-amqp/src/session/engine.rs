@@ -2,16 +2,14 @@ use fe2o3_amqp_types::{
     definitions::{self, AmqpError, SessionError},
     performatives::End,
 };
-use tokio::{
-    sync::{mpsc, oneshot},
-    task::JoinHandle,
-};
+use tokio::sync::{mpsc, oneshot};
 
 use crate::{
     connection::{self},
     control::{ConnectionControl, SessionControl},
     endpoint::{self, IncomingChannel, Session},
     link::LinkFrame,
+    rt::JoinHandle,
     util::Running,
     SendBound,
 };
@@ -51,12 +49,19 @@ async fn send_outgoing_item(
 
 pub(crate) struct SessionEngine<S: Session> {
     pub conn_control: mpsc::Sender<ConnectionControl>,
+    /// Container id of the connection, which is recorded in the span of the event loop
+    #[cfg_attr(not(feature = "tracing"), allow(dead_code))]
+    pub container_id: String,
     pub session: S,
     pub control: mpsc::Receiver<SessionControl>,
     pub incoming: mpsc::Receiver<SessionIncomingItem>,
     pub outgoing: mpsc::Sender<SessionFrame>,
 
     pub outgoing_link_frames: mpsc::Receiver<LinkFrame>,
+
+    /// Observer of the incoming frames, installed with `SessionControl::ObserveIncoming`
+    #[cfg(feature = "testing")]
+    pub raw_incoming: Option<mpsc::Sender<SessionFrameBody>>,
 }
 
 impl<S> SessionEngine<S>
@@ -66,6 +71,7 @@ where
 {
     pub(crate) async fn begin_client_session(
         conn_control: mpsc::Sender<ConnectionControl>,
+        container_id: String,
         session: S,
         control: mpsc::Receiver<SessionControl>,
         incoming: mpsc::Receiver<SessionIncomingItem>,
@@ -74,24 +80,29 @@ where
     ) -> Result<Self, BeginError> {
         let mut engine = Self {
             conn_control,
+            container_id,
             session,
             control,
             incoming,
             outgoing,
             outgoing_link_frames,
+            #[cfg(feature = "testing")]
+            raw_incoming: None,
         };
 
         // send a begin
         engine.session.send_begin(&engine.outgoing).await?;
         // wait for an incoming begin
         let frame = match engine.incoming.recv().await {
-            Some(frame) => frame,
+            Some(SessionIncomingItem::Frame(frame)) => frame,
+            // The channel of the session is not mapped to the remote peer before the remote Begin
+            Some(SessionIncomingItem::Malformed(_)) => return Err(BeginError::IllegalState),
             None => {
                 // Connection sender must have dropped
                 return Err(BeginError::IllegalConnectionState);
             }
         };
-        let SessionFrame { channel, body } = frame;
+        let SessionFrame { channel, body, .. } = frame;
         let channel = IncomingChannel(channel);
         let remote_begin = match body {
             SessionFrameBody::Begin(begin) => begin,
@@ -107,15 +118,23 @@ where
 }
 
 cfg_not_wasm32! {
+    use crate::{
+        connection::DROPPED_HANDLE_GRACE,
+        rt::{timeout, Spawner},
+    };
+
     impl<S> SessionEngine<S>
     where
         S: endpoint::SessionEndpoint<State = SessionState> + Send + Sync + 'static,
         AllocLinkError: From<S::AllocError>,
         SessionInnerError: From<S::Error> + From<S::BeginError> + From<S::EndError>,
     {
-        pub fn spawn(self) -> (JoinHandle<()>, oneshot::Receiver<Result<(), Error>>) {
+        pub fn spawn(
+            self,
+            spawner: &Spawner,
+        ) -> (Option<JoinHandle<()>>, oneshot::Receiver<Result<(), Error>>) {
             let (tx, rx) = oneshot::channel();
-            let handle = tokio::spawn(self.event_loop(tx));
+            let handle = spawner.spawn(self.event_loop(tx));
             (handle, rx)
         }
     }
@@ -154,8 +173,23 @@ where
         &mut self,
         incoming: SessionIncomingItem,
     ) -> Result<Running, SessionInnerError> {
-        let SessionFrame { channel, body } = incoming;
+        let SessionFrame {
+            channel,
+            body,
+            incoming_permit,
+        } = match incoming {
+            SessionIncomingItem::Frame(frame) => frame,
+            SessionIncomingItem::Malformed(error) => {
+                return Err(SessionInnerError::MalformedFrame(error))
+            }
+        };
         let channel = IncomingChannel(channel);
+
+        #[cfg(feature = "testing")]
+        if let Some(raw_incoming) = &self.raw_incoming {
+            // Frames are dropped if the observer is not keeping up
+            let _ = raw_incoming.try_send(body.clone());
+        }
         match body {
             SessionFrameBody::Begin(begin) => {
                 self.session.on_incoming_begin(channel, begin)?;
@@ -172,9 +206,25 @@ where
                 performative,
                 payload,
             } => {
-                self.session
-                    .on_incoming_transfer(performative, payload)
-                    .await?;
+                // The transactional resource informs the controller of the presumptive outcome of
+                // a posted transfer right away
+                if let Some(disposition) = self
+                    .session
+                    .on_incoming_transfer(performative, payload, incoming_permit)
+                    .await?
+                {
+                    let disposition = self.session.on_outgoing_disposition(disposition)?;
+                    self.outgoing
+                        .send(disposition)
+                        .await
+                        .map_err(|_| SessionInnerError::IllegalConnectionState)?;
+                }
+                if let Some(flow) = self.session.replenish_incoming_window() {
+                    self.outgoing
+                        .send(flow)
+                        .await
+                        .map_err(|_| SessionInnerError::IllegalConnectionState)?;
+                }
             }
             SessionFrameBody::Disposition(disposition) => {
                 if let Some(dispositions) = self.session.on_incoming_disposition(disposition)? {
@@ -241,6 +291,17 @@ where
                     // The receiving end (ie. link) must have been stopped
                     .map_err(|_| SessionInnerError::UnattachedHandle)?;
             }
+            SessionControl::ReallocateLink {
+                link_name,
+                link_relay,
+                responder,
+            } => {
+                let result = self.session.reallocate_link(link_name, link_relay);
+                responder
+                    .send(result.map_err(Into::into))
+                    // The receiving end (ie. link) must have been stopped
+                    .map_err(|_| SessionInnerError::UnattachedHandle)?;
+            }
             SessionControl::AllocateIncomingLink {
                 link_name,
                 link_relay,
@@ -258,6 +319,9 @@ where
             SessionControl::DeallocateLink(link_name) => {
                 self.session.deallocate_link(link_name);
             }
+            SessionControl::ReleaseLinkName(link_name) => {
+                self.session.release_link_name(&link_name);
+            }
             SessionControl::Disposition(disposition) => {
                 let disposition = self.session.on_outgoing_disposition(disposition)?;
                 self.outgoing
@@ -275,12 +339,32 @@ where
                     .await
                     .map_err(|_| SessionInnerError::IllegalConnectionState)?;
             }
+            #[cfg(feature = "testing")]
+            SessionControl::SendRaw(body) => {
+                let frame = SessionFrame::new(self.session.outgoing_channel(), body);
+                self.outgoing
+                    .send(frame)
+                    .await
+                    .map_err(|_| SessionInnerError::IllegalConnectionState)?;
+            }
+            #[cfg(feature = "testing")]
+            SessionControl::ObserveIncoming(tx) => self.raw_incoming = Some(tx),
             SessionControl::GetMaxFrameSize(resp) => {
                 self.conn_control
                     .send(ConnectionControl::GetMaxFrameSize(resp))
                     .await
                     .map_err(|_| SessionInnerError::IllegalConnectionState)?;
             }
+            SessionControl::GetLinks(resp) => {
+                // The handle may have given up waiting for the answer
+                let _ = resp.send(self.session.link_summaries());
+            }
+            SessionControl::GetIncompleteIncomingBytes(resp) => {
+                let _ = resp.send(self.session.incomplete_incoming_bytes());
+            }
+            SessionControl::GetRoutingHistory(resp) => {
+                let _ = resp.send(self.session.routing_history());
+            }
 
             #[cfg(feature = "transaction")]
             SessionControl::AllocateTransactionId { resp } => {
@@ -318,7 +402,8 @@ where
         frame: LinkFrame,
     ) -> Result<Running, SessionInnerError> {
         match self.session.local_state() {
-            SessionState::Mapped => {}
+            // Frames that are buffered when the remote End arrives are sent before the End reply
+            SessionState::Mapped | SessionState::EndReceived => {}
             _ => return Err(SessionInnerError::IllegalState), // End session with illegal state
         }
 
@@ -337,6 +422,7 @@ where
                 input_handle,
                 performative,
                 payload,
+                incoming_permit: _,
             } => self
                 .session
                 .on_outgoing_transfer(input_handle, performative, payload)?,
@@ -354,6 +440,9 @@ where
                 // This is purely used to notify sender about TxnAcquisition, which is not implemented
                 unreachable!("LinkFrame::Acquisition should not appear in outgoing link frames")
             }
+            LinkFrame::SessionEnded(_) => {
+                unreachable!("LinkFrame::SessionEnded should not appear in outgoing link frames")
+            }
         };
 
         if let Some(outgoing_item) = outgoing_item {
@@ -404,6 +493,42 @@ where
                 );
                 self.end_session(Some(error)).await
             }
+            SessionInnerError::WindowViolation => {
+                let error = Error::new(SessionError::WindowViolation, None, None);
+                self.end_session(Some(error)).await
+            }
+            SessionInnerError::InvalidFlow => {
+                let error = Error::new(
+                    AmqpError::InvalidField,
+                    Some(String::from(
+                        "Flow carries a transfer-id that is inconsistent with the session state",
+                    )),
+                    None,
+                );
+                self.end_session(Some(error)).await
+            }
+            SessionInnerError::InvalidDeliveryCount => {
+                let error = Error::new(
+                    AmqpError::InvalidField,
+                    Some(String::from(
+                        "Flow carries a delivery-count that is ahead of the deliveries sent on the link",
+                    )),
+                    None,
+                );
+                self.end_session(Some(error)).await
+            }
+            SessionInnerError::IncompleteIncomingLimitExceeded { link_name } => {
+                let error = Error::new(
+                    AmqpError::ResourceLimitExceeded,
+                    Some(format!(
+                        "Incomplete incoming deliveries exceed the limit of the session on link {}",
+                        link_name
+                    )),
+                    None,
+                );
+                self.end_session(Some(error)).await
+            }
+            SessionInnerError::MalformedFrame(error) => self.end_session(Some(error.clone())).await,
             SessionInnerError::RemoteEnded | SessionInnerError::RemoteEndedWithError(_) => {
                 self.end_session(None).await
             }
@@ -417,6 +542,36 @@ where
         }
     }
 
+    /// Ends the session once the handle is dropped
+    ///
+    /// The End frame and the link frames queued before it are sent ahead of any other event,
+    /// and giving up after [`DROPPED_HANDLE_GRACE`] stops the event loop instead of leaving it
+    /// stuck on a connection that does not take the frames. The remote End is then awaited in
+    /// the event loop as usual.
+    async fn on_handle_dropped(&mut self) -> Result<Running, SessionInnerError> {
+        match self.session.local_state() {
+            SessionState::Mapped | SessionState::EndReceived => {}
+            // The End is already sent or the session is not mapped
+            _ => return Ok(Running::Continue),
+        }
+
+        let end = self.on_control(SessionControl::End(None));
+        #[cfg(not(target_arch = "wasm32"))]
+        let end = async {
+            match timeout(DROPPED_HANDLE_GRACE, end).await {
+                Ok(result) => result,
+                Err(_) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::error!("Timed out sending the End frame of a dropped handle");
+                    #[cfg(feature = "log")]
+                    log::error!("Timed out sending the End frame of a dropped handle");
+                    Ok(Running::Stop)
+                }
+            }
+        };
+        end.await
+    }
+
     async fn end_session(
         &mut self,
         error: Option<definitions::Error>,
@@ -456,16 +611,21 @@ where
         discard_other_frame: bool,
     ) -> Result<(IncomingChannel, End), SessionInnerError> {
         loop {
-            let frame = self
+            let frame = match self
                 .incoming
                 .recv()
                 .await
-                .ok_or(SessionInnerError::IllegalConnectionState)?;
+                .ok_or(SessionInnerError::IllegalConnectionState)?
+            {
+                SessionIncomingItem::Frame(frame) => frame,
+                // The session is already ending
+                SessionIncomingItem::Malformed(_) => continue,
+            };
             match frame.body {
                 SessionFrameBody::End(end) => return Ok((IncomingChannel(frame.channel), end)),
                 _ => {
                     if !discard_other_frame {
-                        let _ = self.on_incoming(frame).await?;
+                        let _ = self.on_incoming(frame.into()).await?;
                     }
                 }
             }
@@ -484,11 +644,19 @@ where
         }
     }
 
-    #[cfg_attr(feature = "tracing", tracing::instrument(name = "Session::event_loop", skip(self), fields(outgoing_channel = %self.session.outgoing_channel().0)))]
-    async fn event_loop(mut self, tx: oneshot::Sender<Result<(), Error>>) {
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "Session::event_loop", skip(self), fields(container_id = %self.container_id, outgoing_channel = %self.session.outgoing_channel().0)))]
+    async fn event_loop(mut self, mut tx: oneshot::Sender<Result<(), Error>>) {
         let mut outcome = Ok(());
+        let mut outgoing_link_frames_closed = false;
+        let mut handle_dropped = false;
         loop {
             let result = tokio::select! {
+                // The handle owns the receiving half of the outcome, which is never full unlike the
+                // control channel
+                _ = tx.closed(), if !handle_dropped => {
+                    handle_dropped = true;
+                    self.on_handle_dropped().await
+                },
                 incoming = self.incoming.recv() => {
                     match incoming {
                         Some(incoming) => self.on_incoming(incoming).await,
@@ -544,7 +712,9 @@ where
                         }
                     }
                 },
-                frame = self.outgoing_link_frames.recv() => {
+                // The frames of a link whose channel was full are relayed once it has capacity
+                _ = self.session.relay_overflow() => Ok(Running::Continue),
+                frame = self.outgoing_link_frames.recv(), if !outgoing_link_frames_closed => {
                     match frame {
                         Some(frame) => self.on_outgoing_link_frames(frame).await,
                         None => {
@@ -552,6 +722,7 @@ where
                             //
                             // Upon ending, all link-to-session channels will be closed
                             // first while the session is still waitint for remote end frame.
+                            outgoing_link_frames_closed = true;
                             Ok(Running::Continue)
                         }
                     }
@@ -589,6 +760,16 @@ where
         tracing::debug!("Stopped");
         #[cfg(feature = "log")]
         log::debug!("Stopped");
+        self.session.notify_links_ended();
+
+        // Deallocating the session waits on the connection event loop, so the requests that are
+        // still queued are failed first. Dropping them drops their responders, and closing the
+        // channels makes any later request fail right away
+        self.control.close();
+        while self.control.try_recv().is_ok() {}
+        self.outgoing_link_frames.close();
+        while self.outgoing_link_frames.try_recv().is_ok() {}
+
         let _ =
             connection::deallocate_session(&mut self.conn_control, self.session.outgoing_channel())
                 .await;
@@ -46,6 +46,16 @@ pub enum BeginError {
     /// Channel max reached
     #[error("Local channel-max reached")]
     LocalChannelMaxReached,
+
+    /// The maximum number of active sessions set on the connection is reached
+    #[error("Local max-sessions reached")]
+    LocalMaxSessionsReached,
+
+    /// The incoming session is refused by the local session acceptor and is ended with the error
+    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg(feature = "acceptor")]
+    #[error("Session rejected: {}", .0)]
+    Rejected(definitions::Error),
 }
 
 impl From<SessionStateError> for BeginError {
@@ -85,6 +95,30 @@ pub(crate) enum SessionInnerError {
     #[error("Found Transfer frame being sent to a Sender")]
     TransferFrameToSender,
 
+    /// A transfer was received that exceeds the incoming-window of the session
+    #[error("A transfer was received that exceeds the incoming-window of the session")]
+    WindowViolation,
+
+    /// A flow was received that carries a transfer-id inconsistent with the session state
+    #[error("A flow was received with a next-incoming-id or next-outgoing-id that is inconsistent with the session state")]
+    InvalidFlow,
+
+    /// A flow was received with a delivery-count that is ahead of the deliveries sent on the link
+    #[error("A flow was received with a delivery-count that is ahead of the deliveries sent on the link")]
+    InvalidDeliveryCount,
+
+    /// The incomplete incoming deliveries exceed the limit of the session
+    #[error("Incomplete incoming deliveries exceed the limit of the session on link {link_name}")]
+    IncompleteIncomingLimitExceeded {
+        /// Name of the link whose transfer exceeded the limit
+        link_name: String,
+    },
+
+    /// A frame on the channel of the session cannot be decoded. The session is ended with the
+    /// error
+    #[error("A frame on the session cannot be decoded: {0}")]
+    MalformedFrame(definitions::Error),
+
     /// Remote session ended
     #[error("Remote session ended")]
     RemoteEnded,
@@ -116,6 +150,7 @@ impl From<LinkRelayError> for SessionInnerError {
         match error {
             LinkRelayError::UnattachedHandle => Self::UnattachedHandle,
             LinkRelayError::TransferFrameToSender => Self::TransferFrameToSender,
+            LinkRelayError::InvalidDeliveryCount => Self::InvalidDeliveryCount,
         }
     }
 }
@@ -148,6 +183,30 @@ pub enum Error {
     #[error("Found Transfer frame being sent to a Sender")]
     TransferFrameToSender,
 
+    /// A transfer was received that exceeds the incoming-window of the session
+    #[error("A transfer was received that exceeds the incoming-window of the session")]
+    WindowViolation,
+
+    /// A flow was received that carries a transfer-id inconsistent with the session state
+    #[error("A flow was received with a next-incoming-id or next-outgoing-id that is inconsistent with the session state")]
+    InvalidFlow,
+
+    /// A flow was received with a delivery-count that is ahead of the deliveries sent on the link
+    #[error("A flow was received with a delivery-count that is ahead of the deliveries sent on the link")]
+    InvalidDeliveryCount,
+
+    /// The incomplete incoming deliveries exceed the limit of the session
+    #[error("Incomplete incoming deliveries exceed the limit of the session on link {link_name}")]
+    IncompleteIncomingLimitExceeded {
+        /// Name of the link whose transfer exceeded the limit
+        link_name: String,
+    },
+
+    /// A frame on the channel of the session cannot be decoded. The session is ended with the
+    /// error
+    #[error("A frame on the session cannot be decoded: {0}")]
+    MalformedFrame(definitions::Error),
+
     /// Remote session ended
     #[error("Remote session ended")]
     RemoteEnded,
@@ -173,6 +232,13 @@ impl From<SessionInnerError> for Error {
             SessionInnerError::IllegalState => Self::IllegalState,
             SessionInnerError::IllegalConnectionState => Self::IllegalConnectionState,
             SessionInnerError::TransferFrameToSender => Self::TransferFrameToSender,
+            SessionInnerError::WindowViolation => Self::WindowViolation,
+            SessionInnerError::InvalidFlow => Self::InvalidFlow,
+            SessionInnerError::InvalidDeliveryCount => Self::InvalidDeliveryCount,
+            SessionInnerError::IncompleteIncomingLimitExceeded { link_name } => {
+                Self::IncompleteIncomingLimitExceeded { link_name }
+            }
+            SessionInnerError::MalformedFrame(err) => Self::MalformedFrame(err),
             SessionInnerError::RemoteEnded => Self::RemoteEnded,
             SessionInnerError::RemoteEndedWithError(err) => Self::RemoteEndedWithError(err),
 
@@ -190,6 +256,7 @@ impl From<LinkRelayError> for Error {
             LinkRelayError::TransferFrameToSender => {
                 unreachable!("A sender should not receive a transfer frame")
             }
+            LinkRelayError::InvalidDeliveryCount => Self::InvalidDeliveryCount,
         }
     }
 }
@@ -212,6 +279,9 @@ pub(crate) enum AllocLinkError {
 
     #[error("Link name must be unique")]
     DuplicatedLinkName,
+
+    #[error("All handles up to handle-max are in use")]
+    HandleMaxReached,
 }
 
 /// Error with attempting to end a session
@@ -1,8 +1,26 @@
-use fe2o3_amqp_types::performatives::{Attach, Begin, Detach, Disposition, End, Flow, Transfer};
+use fe2o3_amqp_types::{
+    definitions,
+    performatives::{Attach, Begin, Detach, Disposition, End, Flow, Transfer},
+};
 
 use crate::Payload;
 
-pub(crate) type SessionIncomingItem = SessionFrame;
+use super::incoming_budget::IncomingPermit;
+
+#[derive(Debug)]
+pub(crate) enum SessionIncomingItem {
+    Frame(SessionFrame),
+
+    /// A frame on the channel of the session that cannot be decoded. The session is ended with
+    /// the error
+    Malformed(definitions::Error),
+}
+
+impl From<SessionFrame> for SessionIncomingItem {
+    fn from(frame: SessionFrame) -> Self {
+        Self::Frame(frame)
+    }
+}
 
 pub(crate) enum SessionOutgoingItem {
     SingleFrame(SessionFrame),
@@ -15,6 +33,9 @@ pub(crate) enum SessionOutgoingItem {
 pub(crate) struct SessionFrame {
     pub channel: u16, // outgoing/local channel number
     pub body: SessionFrameBody,
+
+    /// Only set on incoming transfers that count against the buffer limit of the session
+    pub(crate) incoming_permit: Option<IncomingPermit>,
 }
 
 impl SessionFrame {
@@ -22,24 +43,35 @@ impl SessionFrame {
         Self {
             channel: channel.into(),
             body,
+            incoming_permit: None,
         }
     }
 }
 
-// #[derive(Debug)]
-pub(crate) enum SessionFrameBody {
+/// The body of a frame exchanged on a session
+#[derive(Clone)]
+pub enum SessionFrameBody {
     // Frames handled by Link
+    /// Attach performative
     Attach(Attach),
+    /// Flow performative
     Flow(Flow),
+    /// Transfer performative and its payload
     Transfer {
+        /// The transfer performative
         performative: Transfer,
+        /// The payload that follows the performative
         payload: Payload,
     },
+    /// Disposition performative
     Disposition(Disposition),
+    /// Detach performative
     Detach(Detach),
 
     // Frames handled by Session
+    /// Begin performative
     Begin(Begin),
+    /// End performative
     End(End),
 }
 
@@ -0,0 +1,124 @@
+//! Limits the incoming transfer payloads that are buffered for a session
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use tokio::sync::Notify;
+
+/// Number of payload bytes of the incoming transfers that are forwarded to a session but not yet
+/// taken by the receiving link.
+///
+/// The connection event loop acquires an [`IncomingPermit`] before forwarding a transfer to the
+/// session and waits while the session is over its limit, which stops the connection from reading
+/// the transport.
+#[derive(Debug)]
+pub(crate) struct IncomingBudget {
+    limit: Option<usize>,
+    buffered: AtomicUsize,
+    released: Notify,
+}
+
+impl IncomingBudget {
+    pub(crate) fn new(limit: Option<usize>) -> Arc<Self> {
+        Arc::new(Self {
+            limit,
+            buffered: AtomicUsize::new(0),
+            released: Notify::new(),
+        })
+    }
+
+    pub(crate) fn buffered(&self) -> usize {
+        self.buffered.load(Ordering::Acquire)
+    }
+
+    /// Waits until `bytes` fit in the limit. A payload larger than the limit is let through once
+    /// nothing else is buffered.
+    ///
+    /// # Cancel safety
+    ///
+    /// This is cancel safe. Nothing is reserved until the permit is returned
+    pub(crate) async fn acquire(self: &Arc<Self>, bytes: usize) -> IncomingPermit {
+        loop {
+            let buffered = self.buffered.load(Ordering::Acquire);
+            let fits = match self.limit {
+                Some(limit) => buffered == 0 || buffered.saturating_add(bytes) <= limit,
+                None => true,
+            };
+
+            if fits {
+                if self
+                    .buffered
+                    .compare_exchange(
+                        buffered,
+                        buffered + bytes,
+                        Ordering::AcqRel,
+                        Ordering::Acquire,
+                    )
+                    .is_ok()
+                {
+                    return IncomingPermit {
+                        budget: self.clone(),
+                        bytes,
+                    };
+                }
+            } else {
+                // `notify_one` stores a permit if nothing is waiting yet, so a release between the
+                // load above and here is not missed
+                self.released.notified().await;
+            }
+        }
+    }
+
+    fn release(&self, bytes: usize) {
+        self.buffered.fetch_sub(bytes, Ordering::AcqRel);
+        self.released.notify_one();
+    }
+}
+
+/// Bytes of an incoming transfer that count against the [`IncomingBudget`] of the session until
+/// this is dropped
+#[derive(Debug)]
+pub(crate) struct IncomingPermit {
+    budget: Arc<IncomingBudget>,
+    bytes: usize,
+}
+
+impl Drop for IncomingPermit {
+    fn drop(&mut self) {
+        self.budget.release(self.bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::IncomingBudget;
+
+    #[tokio::test]
+    async fn acquire_waits_until_bytes_are_released() {
+        let budget = IncomingBudget::new(Some(10));
+        let first = budget.acquire(6).await;
+        assert_eq!(budget.buffered(), 6);
+
+        let pending = tokio::time::timeout(Duration::from_millis(50), budget.acquire(6)).await;
+        assert!(pending.is_err());
+
+        drop(first);
+        let second = budget.acquire(6).await;
+        assert_eq!(budget.buffered(), 6);
+
+        // An oversized payload only waits for the buffer to be empty
+        let budget_clone = budget.clone();
+        let oversized = tokio::spawn(async move { budget_clone.acquire(100).await });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!oversized.is_finished());
+        drop(second);
+        let oversized = oversized.await.unwrap();
+        assert_eq!(budget.buffered(), 100);
+        drop(oversized);
+        assert_eq!(budget.buffered(), 0);
+    }
+}
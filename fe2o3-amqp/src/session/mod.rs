@@ -1,6 +1,8 @@
 //! Implements AMQP1.0 Session
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::sync::Arc;
 
 use fe2o3_amqp_types::{
     definitions::{
@@ -10,20 +12,23 @@ use fe2o3_amqp_types::{
     primitives::{Symbol, Uint},
     states::SessionState,
 };
+use serde_amqp::serialized_size;
 use slab::Slab;
 use tokio::{
     sync::{
         mpsc::{self},
         oneshot::{self, error::TryRecvError},
     },
-    task::JoinHandle,
 };
 
 use crate::{
     control::SessionControl,
     endpoint::{self, IncomingChannel, InputHandle, LinkFlow, OutgoingChannel, OutputHandle},
+    frames::FRAME_HEADER_SIZE,
+    introspect::{LinkSummary, RoutingDecision, RoutingRecord},
     link::{LinkFrame, LinkRelay},
-    util::{is_consecutive, Constant},
+    rt::JoinHandle,
+    util::{is_consecutive, Constant, SerialNumber},
     Payload,
 };
 
@@ -36,8 +41,20 @@ cfg_transaction! {
     };
 }
 
+cfg_not_wasm32! {
+    use crate::introspect::{self, Queried};
+}
+
 pub(crate) mod engine;
 pub(crate) mod frame;
+pub(crate) mod incoming_budget;
+pub(crate) mod overflow;
+
+cfg_testing! {
+    pub use frame::SessionFrameBody;
+}
+#[cfg(not(feature = "testing"))]
+use frame::SessionFrameBody;
 
 pub mod error;
 use error::{AllocLinkError, SessionInnerError, SessionStateError};
@@ -46,36 +63,108 @@ pub use error::{BeginError, Error, TryEndError};
 mod builder;
 pub use builder::*;
 
-use self::frame::{SessionFrame, SessionFrameBody, SessionOutgoingItem};
+use self::frame::{SessionFrame, SessionOutgoingItem};
+use self::{
+    incoming_budget::{IncomingBudget, IncomingPermit},
+    overflow::LinkOverflow,
+};
 
 /// Default incoming_window and outgoing_window
 pub const DEFAULT_WINDOW: Uint = 2048;
 
+/// How the incoming-window that the session advertises to the remote peer is computed
+///
+/// The window is computed whenever the session sends a Flow, which happens when a link issues
+/// credit and when the remote peer has used up half of the window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IncomingWindowPolicy {
+    /// The `incoming_window` of the session builder is always advertised in full
+    #[default]
+    Static,
+
+    /// The advertised window shrinks as the incoming transfer frames that the receivers of the
+    /// session have not taken yet pile up, so that a fast peer slows down to the pace of the
+    /// receivers instead of stalling once their buffers are full
+    ///
+    /// The full `incoming_window` of the session builder is advertised while at most
+    /// `low_watermark` frames are buffered, and a window of a single frame once `high_watermark`
+    /// frames are buffered. The window shrinks linearly in between.
+    BufferBased {
+        /// Number of buffered frames up to which the full window is advertised
+        low_watermark: usize,
+
+        /// Number of buffered frames from which a window of a single frame is advertised
+        high_watermark: usize,
+    },
+}
+
+impl IncomingWindowPolicy {
+    /// Computes the window to advertise out of the `max` window when `buffered` incoming frames
+    /// are not taken by the receivers yet
+    pub(crate) fn window(&self, max: TransferNumber, buffered: usize) -> TransferNumber {
+        let (low_watermark, high_watermark) = match *self {
+            IncomingWindowPolicy::Static => return max,
+            IncomingWindowPolicy::BufferBased {
+                low_watermark,
+                high_watermark,
+            } => (low_watermark, high_watermark),
+        };
+
+        if buffered <= low_watermark {
+            max
+        } else if buffered >= high_watermark {
+            max.min(1)
+        } else {
+            let free = (high_watermark - buffered) as u64;
+            let span = (high_watermark - low_watermark) as u64;
+            let window = u64::from(max) * free / span;
+            (window as TransferNumber).max(1)
+        }
+    }
+}
+
+/// Number of the most recent routing decisions that a session keeps for
+/// [`SessionHandle::routing_history`]
+pub const ROUTING_HISTORY_CAPACITY: usize = 32;
+
+/// An outgoing transfer that has not been sent yet
+type BufferedTransfer = (InputHandle, Transfer, Payload);
+
+/// Type alias for the handle of a session begun with [`Session::begin`] or
+/// [`Session::builder`]
+pub type ClientSessionHandle = SessionHandle<()>;
+
 /// A handle to the [`Session`] event loop
 ///
-/// Dropping the handle will also stop the [`Session`] event loop
+/// Dropping the handle will also stop the [`Session`] event loop. The event loop sends the End
+/// frame right away, without blocking in `drop`, and then waits for the End of the remote peer
 #[allow(dead_code)]
 pub struct SessionHandle<R> {
     /// This value should only be changed in the `on_end` method
     pub(crate) is_ended: bool,
     pub(crate) control: mpsc::Sender<SessionControl>,
-    pub(crate) engine_handle: JoinHandle<()>,
+    pub(crate) engine_handle: Option<JoinHandle<()>>,
     pub(crate) outcome: oneshot::Receiver<Result<(), Error>>,
 
     // outgoing for Link
     pub(crate) outgoing: mpsc::Sender<LinkFrame>,
     pub(crate) link_listener: R,
+
+    pub(crate) incoming_budget: Arc<IncomingBudget>,
+
+    // Container id of the connection, which is recorded in the tracing spans of the links
+    pub(crate) container_id: String,
+
+    #[cfg(feature = "testing")]
+    pub(crate) raw_incoming: Option<mpsc::Receiver<SessionFrameBody>>,
 }
 
 impl<R> std::fmt::Debug for SessionHandle<R> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("SessionHandle").finish()
-    }
-}
-
-impl<R> Drop for SessionHandle<R> {
-    fn drop(&mut self) {
-        let _ = self.control.try_send(SessionControl::End(None));
+        f.debug_struct("SessionHandle")
+            .field("is_ended", &self.is_ended())
+            .field("buffered_incoming_bytes", &self.buffered_incoming_bytes())
+            .finish()
     }
 }
 
@@ -125,6 +214,7 @@ impl<R> SessionHandle<R> {
         /// # wasm32 support
         ///
         /// This method is not supported on wasm32 targets, please use `drop()` instead.
+        #[cfg_attr(feature = "tracing", tracing::instrument(name = "Session::end", skip_all, fields(container_id = %self.container_id)))]
         pub async fn end(&mut self) -> Result<(), Error> {
             // If sending is unsuccessful, the `SessionEngine` event loop is
             // already dropped, this should be reflected by `JoinError` then.
@@ -150,6 +240,7 @@ impl<R> SessionHandle<R> {
         /// # wasm32 support
         ///
         /// This method is not supported on wasm32 targets, please use `drop()` instead.
+        #[cfg_attr(feature = "tracing", tracing::instrument(name = "Session::end", skip_all, fields(container_id = %self.container_id)))]
         pub async fn end_with_error(
             &mut self,
             error: impl Into<definitions::Error>,
@@ -185,6 +276,114 @@ impl<R> SessionHandle<R> {
             }
         }
     }
+
+    /// Number of payload bytes of the incoming transfers that are received by the connection but
+    /// not yet taken by the receivers attached to the session
+    ///
+    /// The connection stops reading from the transport while this exceeds the
+    /// [`incoming_buffer_limit`](crate::session::Builder::incoming_buffer_limit) of the session.
+    pub fn buffered_incoming_bytes(&self) -> usize {
+        self.incoming_budget.buffered()
+    }
+
+    cfg_not_wasm32! {
+        /// Queries the session event loop for the links that are allocated in the session
+        ///
+        /// [`Queried::Unresponsive`] is returned if the event loop does not answer within a short
+        /// timeout or has stopped. See [`introspect`](crate::introspect) for printing the tree of a
+        /// connection.
+        pub async fn links(&self) -> Queried<Vec<LinkSummary>> {
+            introspect::query(&self.control, SessionControl::GetLinks).await
+        }
+
+        /// Queries the session event loop for the number of payload bytes of the incoming
+        /// deliveries that are not complete yet across all the links of the session
+        ///
+        /// The session is ended once this exceeds the
+        /// [`incomplete_incoming_limit`](crate::session::Builder::incomplete_incoming_limit) of
+        /// the session.
+        pub async fn incomplete_incoming_bytes(&self) -> Queried<usize> {
+            introspect::query(&self.control, SessionControl::GetIncompleteIncomingBytes).await
+        }
+
+        /// Queries the session event loop for how the most recent incoming Attach, Flow, Transfer
+        /// and Detach frames were routed to the links, the oldest first
+        ///
+        /// At most [`ROUTING_HISTORY_CAPACITY`] decisions are kept. A frame that refers to a
+        /// handle that is not attached or is still in use ends the session, and its decision is
+        /// kept until the session event loop stops.
+        pub async fn routing_history(&self) -> Queried<Vec<RoutingRecord>> {
+            introspect::query(&self.control, SessionControl::GetRoutingHistory).await
+        }
+    }
+
+    /// Returns a future that resolves when the underlying event loop has fully stopped
+    ///
+    /// Unlike [`on_end`](#method.on_end), the returned future does not borrow the handle, so it
+    /// can be awaited from a different task than the one holding the handle, eg. by a supervisor
+    /// waiting for the orderly teardown of a connection. The links attached to the session are
+    /// implicitly detached once the session has ended.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let ended = session.ended();
+    /// tokio::spawn(async move {
+    ///     ended.await;
+    ///     println!("session ended");
+    /// });
+    /// session.end().await.unwrap();
+    /// ```
+    pub fn ended(&self) -> impl Future<Output = ()> + Send + 'static {
+        // The receiving half is owned by the session engine, which is only dropped after the
+        // event loop has stopped
+        let outgoing = self.outgoing.clone();
+        async move { outgoing.closed().await }
+    }
+
+    cfg_testing! {
+        /// Sends an arbitrary frame on the session, bypassing the session and link state
+        ///
+        /// The frame goes through the normal outgoing path of the session but none of the session
+        /// or link states are updated. This is meant for testing how a remote peer reacts to
+        /// frames that would otherwise not be sent, eg. a Flow with `echo` set or a Transfer that
+        /// exceeds the remote incoming window.
+        pub async fn send_raw(&self, body: SessionFrameBody) -> Result<(), Error> {
+            self.control
+                .send(SessionControl::SendRaw(body))
+                .await
+                .map_err(|_| Error::IllegalState)
+        }
+
+        /// Starts observing the incoming frames of the session
+        ///
+        /// Every frame received afterwards is copied before it is handled by the session and can
+        /// be retrieved with [`next_raw_incoming`](#method.next_raw_incoming). Frames are dropped
+        /// if more than [`DEFAULT_SESSION_CONTROL_BUFFER_SIZE`] frames are not retrieved.
+        pub async fn observe_raw_incoming(&mut self) -> Result<(), Error> {
+            if self.raw_incoming.is_some() {
+                return Ok(());
+            }
+
+            let (tx, rx) = mpsc::channel(DEFAULT_SESSION_CONTROL_BUFFER_SIZE);
+            self.control
+                .send(SessionControl::ObserveIncoming(tx))
+                .await
+                .map_err(|_| Error::IllegalState)?;
+            self.raw_incoming = Some(rx);
+            Ok(())
+        }
+
+        /// Returns the next incoming frame of the session
+        ///
+        /// The incoming frames are observed from the first call to this method or to
+        /// [`observe_raw_incoming`](#method.observe_raw_incoming) on. `None` is returned once the
+        /// session has ended.
+        pub async fn next_raw_incoming(&mut self) -> Option<SessionFrameBody> {
+            self.observe_raw_incoming().await.ok()?;
+            self.raw_incoming.as_mut()?.recv().await
+        }
+    }
 }
 
 /// # Cancel safety
@@ -218,6 +417,59 @@ pub(crate) async fn allocate_link(
         .map_err(|_| AllocLinkError::IllegalSessionState)?
 }
 
+/// Allocate a new output handle for a link that is resuming. Unlike [`allocate_link`], this may
+/// take the name that is reserved by the link's previous detach
+pub(crate) async fn reallocate_link(
+    control: &mpsc::Sender<SessionControl>,
+    link_name: String,
+    link_relay: LinkRelay<()>,
+) -> Result<OutputHandle, AllocLinkError> {
+    let (responder, resp_rx) = oneshot::channel();
+
+    control
+        .send(SessionControl::ReallocateLink {
+            link_name,
+            link_relay,
+            responder,
+        })
+        .await
+        .map_err(|_| AllocLinkError::IllegalSessionState)?;
+    resp_rx
+        .await
+        .map_err(|_| AllocLinkError::IllegalSessionState)?
+}
+
+/// Allocate a new output handle for a link that is attached by the remote peer. The link is
+/// mapped to the input handle of the remote Attach
+pub(crate) async fn allocate_incoming_link(
+    control: &mpsc::Sender<SessionControl>,
+    link_name: String,
+    link_relay: LinkRelay<()>,
+    input_handle: InputHandle,
+) -> Result<OutputHandle, AllocLinkError> {
+    let (responder, resp_rx) = oneshot::channel();
+
+    control
+        .send(SessionControl::AllocateIncomingLink {
+            link_name,
+            link_relay,
+            input_handle,
+            responder,
+        })
+        .await
+        // The `SendError` could only happen when the receiving half is
+        // dropped, meaning the `SessionEngine::event_loop` has stopped.
+        // This would also mean the `Session` is Unmapped, and thus it
+        // may be treated as illegal state
+        .map_err(|_| AllocLinkError::IllegalSessionState)?;
+    resp_rx
+        .await
+        // The error could only occur when the sending half is dropped,
+        // indicating the `SessionEngine::even_loop` has stopped or
+        // unmapped. Thus it could be considered as illegal state
+        .map_err(|_| AllocLinkError::IllegalSessionState)?
+}
+
 /// AMQP1.0 Session
 ///
 /// # Begin a new Session with default configuration
@@ -258,19 +510,33 @@ pub struct Session {
 
     // local amqp states
     pub(crate) local_state: SessionState,
-    pub(crate) initial_outgoing_id: Constant<TransferNumber>,
-    pub(crate) next_outgoing_id: TransferNumber,
+    pub(crate) initial_outgoing_id: Constant<SerialNumber>,
+    pub(crate) next_outgoing_id: SerialNumber,
     pub(crate) incoming_window: TransferNumber,
+    // The largest incoming-window that is advertised to the remote peer
+    pub(crate) initial_incoming_window: Constant<TransferNumber>,
+    // How the advertised incoming-window follows the frames buffered for the receivers
+    pub(crate) incoming_window_policy: IncomingWindowPolicy,
+    // The incoming-window advertised by the last Begin or Flow
+    pub(crate) advertised_incoming_window: TransferNumber,
+    // Incoming transfer frames that may still arrive without violating the incoming-window.
+    // Frames that the remote peer has sent before it received a smaller window still count
+    // against the larger window, so this may be larger than `incoming_window`
+    pub(crate) incoming_window_limit: TransferNumber,
     pub(crate) outgoing_window: TransferNumber,
     pub(crate) handle_max: Handle,
 
     // remote amqp states
     pub(crate) incoming_channel: Option<IncomingChannel>,
+    pub(crate) remote_handle_max: Handle,
     // initialize with 0 first and change after receiving the remote Begin
-    pub(crate) next_incoming_id: TransferNumber,
+    pub(crate) next_incoming_id: SerialNumber,
     pub(crate) remote_incoming_window: SequenceNo,
     // Outgoing transfers that are blocked by the remote-incoming-window
-    pub(crate) remote_incoming_window_exhausted_buffer: VecDeque<(InputHandle, Transfer, Payload)>,
+    pub(crate) remote_incoming_window_exhausted_buffer: VecDeque<BufferedTransfer>,
+
+    // The negotiated max frame size that outgoing transfers are split against
+    pub(crate) max_frame_size: usize,
 
     // The remote-outgoing-window reflects the maximum number of incoming transfers that MAY
     // arrive without exceeding the remote endpoint’s outgoing-window. This value MUST be
@@ -289,8 +555,24 @@ pub struct Session {
     pub(crate) link_name_by_output_handle: Slab<String>,
     pub(crate) link_by_name: HashMap<String, Option<LinkRelay<OutputHandle>>>,
     pub(crate) link_by_input_handle: HashMap<InputHandle, LinkRelay<OutputHandle>>,
+    // Names of links that are detached but may still be resumed. These names cannot be
+    // taken by a new link until the detached link is resumed, closed or dropped
+    pub(crate) detached_link_names: HashSet<String>,
+    // Relays of links that are closed before the remote Attach arrives, in the order the attaches
+    // are sent. The remote peer still replies with an Attach and a Detach for each of them
+    pub(crate) abandoned_links: HashMap<String, VecDeque<LinkRelay<OutputHandle>>>,
     // Maps from DeliveryId to link.DeliveryCount
     pub(crate) delivery_tag_by_id: HashMap<(Role, DeliveryNumber), (InputHandle, DeliveryTag)>, // Role must be the remote peer's role
+    // Payload bytes of the incoming delivery that is not complete yet on each link
+    pub(crate) incomplete_incoming_limit: Option<usize>,
+    pub(crate) incomplete_incoming: HashMap<InputHandle, usize>,
+    // Error carried by the End sent or received first, which is reported to the links once the
+    // session has ended
+    pub(crate) end_error: Option<definitions::Error>,
+    // Incoming frames of the links that are not reading them fast enough
+    pub(crate) link_overflow: LinkOverflow,
+    // The most recent routing decisions of the incoming frames, kept for introspection
+    pub(crate) routing_history: VecDeque<RoutingRecord>,
 }
 
 impl Session {
@@ -328,12 +610,147 @@ impl Session {
         }
     }
 
+    /// Takes the remote Attach that replies to a link closed before the reply arrived, and keeps
+    /// the relay until the Detach that follows it. Returns `false` if the Attach is not such a
+    /// reply.
+    ///
+    /// The remote peer replies in the order the attaches are sent, so the reply always belongs to
+    /// the oldest abandoned link of that name even if a new link of the same name is attaching.
+    pub(crate) fn on_incoming_abandoned_attach(&mut self, attach: &Attach) -> bool {
+        let relays = match self.abandoned_links.get_mut(&attach.name) {
+            Some(relays) => relays,
+            None => return false,
+        };
+        let relay = relays.pop_front();
+        if relays.is_empty() {
+            self.abandoned_links.remove(&attach.name);
+        }
+        match relay {
+            Some(relay) => {
+                let decision = RoutingDecision::Routed {
+                    output_handle: relay.output_handle().0,
+                };
+                self.record_routing("attach", attach.handle.0, decision);
+                let input_handle = InputHandle::from(attach.handle.clone());
+                self.link_by_input_handle.insert(input_handle, relay);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remembers how an incoming frame is routed, dropping the oldest decision once
+    /// [`ROUTING_HISTORY_CAPACITY`] decisions are kept
+    pub(crate) fn record_routing(
+        &mut self,
+        performative: &'static str,
+        handle: u32,
+        decision: RoutingDecision,
+    ) {
+        if self.routing_history.len() == ROUTING_HISTORY_CAPACITY {
+            self.routing_history.pop_front();
+        }
+        self.routing_history.push_back(RoutingRecord {
+            performative,
+            handle,
+            decision,
+        });
+    }
+
+    /// Finds the link that an incoming frame is routed to, failing with
+    /// [`SessionInnerError::UnattachedHandle`] if no link is attached with the handle
+    fn route_incoming(
+        &mut self,
+        performative: &'static str,
+        input_handle: &InputHandle,
+    ) -> Result<RoutingDecision, SessionInnerError> {
+        match self.link_by_input_handle.get(input_handle) {
+            Some(relay) => Ok(RoutingDecision::Routed {
+                output_handle: relay.output_handle().0,
+            }),
+            None => {
+                #[cfg(feature = "tracing")]
+                tracing::error!(handle = input_handle.0, performative, "Unattached handle");
+                #[cfg(feature = "log")]
+                log::error!("Unattached handle {} in {}", input_handle.0, performative);
+                let decision = RoutingDecision::UnattachedHandle;
+                self.record_routing(performative, input_handle.0, decision);
+                Err(SessionInnerError::UnattachedHandle)
+            }
+        }
+    }
+
+    /// The handle of an incoming Attach must not be used by a link that is not fully detached
+    pub(crate) fn check_incoming_attach_handle(
+        &mut self,
+        attach: &Attach,
+    ) -> Result<(), SessionInnerError> {
+        let input_handle = InputHandle::from(attach.handle.clone());
+        match self.link_by_input_handle.get(&input_handle) {
+            Some(relay) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!(
+                    handle = attach.handle.0,
+                    output_handle = relay.output_handle().0,
+                    "Attach reuses the handle of a link that is not detached"
+                );
+                #[cfg(feature = "log")]
+                log::error!(
+                    "Attach reuses the handle {} of a link that is not detached, output_handle = {}",
+                    attach.handle.0,
+                    relay.output_handle().0
+                );
+                #[cfg(not(any(feature = "tracing", feature = "log")))]
+                let _ = relay;
+                self.record_routing("attach", attach.handle.0, RoutingDecision::HandleInUse);
+                Err(SessionInnerError::HandleInUse)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the delivery ids in `first..=last` that are tracked for the remote peer's `role`,
+    /// in the order of the range.
+    ///
+    /// Delivery ids are serial numbers, so the range may wrap around. A range spanning more than
+    /// half of the number space is not well defined and only `first` is considered.
+    fn tracked_delivery_ids(
+        &self,
+        role: &Role,
+        first: DeliveryNumber,
+        last: DeliveryNumber,
+    ) -> Vec<DeliveryNumber> {
+        let span = match last.wrapping_sub(first) {
+            span if span > i32::MAX as u32 => 0,
+            span => span,
+        };
+
+        // Avoid walking a large range when only a few deliveries are tracked
+        if (span as usize) < self.delivery_tag_by_id.len() {
+            (0..=span)
+                .map(|offset| first.wrapping_add(offset))
+                .filter(|id| self.delivery_tag_by_id.contains_key(&(role.clone(), *id)))
+                .collect()
+        } else {
+            let mut ids: Vec<_> = self
+                .delivery_tag_by_id
+                .keys()
+                .filter(|(r, id)| r == role && id.wrapping_sub(first) <= span)
+                .map(|(_, id)| *id)
+                .collect();
+            ids.sort_by_key(|id| id.wrapping_sub(first));
+            ids
+        }
+    }
+
+    /// Returns the transfer frame and the continuation of the delivery if the payload does not fit
+    /// in a single frame
     fn on_outgoing_transfer_inner(
         &mut self,
         input_handle: InputHandle,
         mut transfer: Transfer,
-        payload: Payload,
-    ) -> Result<SessionFrame, SessionInnerError> {
+        mut payload: Payload,
+    ) -> Result<(SessionFrame, Option<BufferedTransfer>), SessionInnerError> {
         // Upon sending a transfer, the sending endpoint will increment its next-outgoing-id, decre-
         // ment its remote-incoming-window, and MAY (depending on policy) decrement its outgoing-
         // window.
@@ -355,18 +772,30 @@ impl Session {
         // Only the first transfer is required to have delivery_tag and delivery_id
         if let Some(delivery_tag) = &transfer.delivery_tag {
             // The next-outgoing-id is the transfer-id to assign to the next transfer frame.
-            let delivery_id = self.next_outgoing_id;
+            let delivery_id = u32::from(self.next_outgoing_id);
             transfer.delivery_id = Some(delivery_id);
 
             // Disposition doesn't carry delivery tag
             if !settled {
                 self.delivery_tag_by_id.insert(
                     (Role::Receiver, delivery_id),
-                    (input_handle, delivery_tag.clone()),
+                    (input_handle.clone(), delivery_tag.clone()),
                 );
+                if let Some(LinkRelay::Sender { flow_state, .. }) =
+                    self.link_by_input_handle.get(&input_handle)
+                {
+                    flow_state
+                        .state()
+                        .confirms
+                        .on_sent(delivery_id, delivery_tag);
+                }
             }
         }
 
+        let continuation = self
+            .split_off_continuation(&mut transfer, &mut payload)
+            .map(|(transfer, payload)| (input_handle, transfer, payload));
+
         self.next_outgoing_id = self.next_outgoing_id.wrapping_add(1);
 
         // The remote-incoming-window reflects the maximum number of outgoing
@@ -376,59 +805,218 @@ impl Session {
         // endpoint state.
         self.remote_incoming_window = self.remote_incoming_window.saturating_sub(1);
 
+        #[cfg(feature = "tracing")]
+        tracing::trace!(outgoing_channel = self.outgoing_channel.0, handle = transfer.handle.0, delivery_id = ?transfer.delivery_id, "SEND transfer");
+        #[cfg(feature = "log")]
+        log::trace!(
+            "channel {}, SEND transfer: handle = {}, delivery_id = {:?}",
+            self.outgoing_channel.0,
+            transfer.handle.0,
+            transfer.delivery_id
+        );
+
         let body = SessionFrameBody::Transfer {
             performative: transfer,
             payload,
         };
         let frame = SessionFrame::new(self.outgoing_channel, body);
-        Ok(frame)
+        Ok((frame, continuation))
     }
 
-    async fn on_incoming_flow_inner(
+    /// Splits the payload so that the transfer fits in a single frame of the negotiated max frame
+    /// size, and returns the continuation transfer that carries the rest of the payload
+    ///
+    /// The transfer is left intact if the performative alone does not leave room for any payload
+    fn split_off_continuation(
+        &self,
+        transfer: &mut Transfer,
+        payload: &mut Payload,
+    ) -> Option<(Transfer, Payload)> {
+        let max_body_size = self.max_frame_size.saturating_sub(FRAME_HEADER_SIZE);
+        let performative_size = serialized_size(transfer).ok()?;
+        if performative_size + payload.len() <= max_body_size {
+            return None;
+        }
+
+        let more = transfer.more;
+        transfer.more = true;
+        let split_index = serialized_size(transfer)
+            .ok()
+            .map(|size| max_body_size.saturating_sub(size))
+            .filter(|index| *index > 0);
+        let split_index = match split_index {
+            Some(index) => index,
+            None => {
+                transfer.more = more;
+                return None;
+            }
+        };
+        let rest = payload.split_off(split_index);
+
+        // Only the first transfer of a delivery is required to carry the delivery id, delivery
+        // tag, message format and settlement fields
+        let continuation = Transfer {
+            handle: transfer.handle.clone(),
+            delivery_id: None,
+            delivery_tag: None,
+            message_format: None,
+            settled: None,
+            more,
+            rcv_settle_mode: None,
+            state: transfer.state.clone(),
+            resume: transfer.resume,
+            aborted: transfer.aborted,
+            batchable: transfer.batchable,
+        };
+        Some((continuation, rest))
+    }
+
+    /// Validates an incoming transfer frame against the incoming-window and updates the incoming
+    /// flow state of the session
+    fn on_incoming_transfer_frame(&mut self) -> Result<(), SessionInnerError> {
+        debug_assert!(self.incoming_window_limit <= *self.initial_incoming_window.value());
+
+        // The incoming-window defines the maximum number of incoming transfer frames that the
+        // endpoint can currently receive
+        if self.incoming_window_limit == 0 {
+            return Err(SessionInnerError::WindowViolation);
+        }
+
+        // Upon receiving a transfer, the receiving endpoint will increment the next-incoming-id to
+        // match the implicit transfer-id of the incoming transfer plus one, as well as decrementing the
+        // remote-outgoing-window, and MAY (depending on policy) decrement its incoming-window.
+        self.next_incoming_id = self.next_incoming_id.wrapping_add(1);
+        self.remote_outgoing_window = self.remote_outgoing_window.saturating_sub(1);
+        self.incoming_window = self.incoming_window.saturating_sub(1);
+        self.incoming_window_limit -= 1;
+        Ok(())
+    }
+
+    /// Number of incoming frames that are relayed to the receivers of the session but not taken
+    /// by them yet
+    fn buffered_incoming_frames(&self) -> usize {
+        let in_channels: usize = self
+            .link_by_input_handle
+            .values()
+            .map(LinkRelay::buffered_frames)
+            .sum();
+        in_channels + self.link_overflow.parked_frames()
+    }
+
+    /// Computes the incoming-window to advertise according to the [`IncomingWindowPolicy`]
+    fn next_incoming_window(&self) -> TransferNumber {
+        let max = *self.initial_incoming_window.value();
+        match self.incoming_window_policy {
+            IncomingWindowPolicy::Static => max,
+            policy => policy.window(max, self.buffered_incoming_frames()),
+        }
+    }
+
+    /// Updates the incoming flow state of the session once `window` is advertised
+    fn advertise_incoming_window(&mut self, window: TransferNumber) {
+        self.incoming_window = window;
+        self.advertised_incoming_window = window;
+        self.incoming_window_limit = self.incoming_window_limit.max(window);
+    }
+
+    /// Accounts for the payload of an incoming transfer in the incomplete delivery of its link and
+    /// checks the total of all the links against the limit of the session
+    fn account_incomplete_incoming(
         &mut self,
-        flow: Flow,
-    ) -> Result<Option<LinkFlow>, SessionInnerError> {
-        // Handle session flow control
-        //
+        input_handle: &InputHandle,
+        transfer: &Transfer,
+        bytes: usize,
+    ) -> Result<(), SessionInnerError> {
+        if !transfer.more || transfer.aborted {
+            // The delivery is complete or aborted
+            self.incomplete_incoming.remove(input_handle);
+            return Ok(());
+        }
+
+        let link_bytes = self
+            .incomplete_incoming
+            .entry(input_handle.clone())
+            .or_default();
+        *link_bytes = link_bytes.saturating_add(bytes);
+
+        let total: usize = self.incomplete_incoming.values().sum();
+        match self.incomplete_incoming_limit {
+            Some(limit) if total > limit => {
+                let link_name = self
+                    .link_by_input_handle
+                    .get(input_handle)
+                    .and_then(|relay| {
+                        self.link_name_by_output_handle
+                            .get(relay.output_handle().0 as usize)
+                    })
+                    .cloned()
+                    .unwrap_or_default();
+                Err(SessionInnerError::IncompleteIncomingLimitExceeded { link_name })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Validates the session fields of an incoming flow and updates the incoming flow state of the
+    /// session
+    fn on_incoming_session_flow(&mut self, flow: &Flow) -> Result<(), SessionInnerError> {
+        // Transfers are received in order, so the next-outgoing-id of the peer cannot go back
+        // before the transfers that are already received
+        let flow_next_outgoing_id = SerialNumber::from(flow.next_outgoing_id);
+        self.next_incoming_id
+            .distance_to(flow_next_outgoing_id)
+            .map_err(|_| SessionInnerError::InvalidFlow)?;
+
+        // The peer cannot expect a transfer-id that has not been sent yet
+        if let Some(flow_next_incoming_id) = flow.next_incoming_id {
+            SerialNumber::from(flow_next_incoming_id)
+                .distance_to(self.next_outgoing_id)
+                .map_err(|_| SessionInnerError::InvalidFlow)?;
+        }
+
         // When the endpoint receives a flow frame from its peer, it MUST update
         // the next-incoming-id directly from the next-outgoing-id of the frame,
         // and it MUST update the remote-outgoing- window directly from the
         // outgoing-window of the frame.
-        self.next_incoming_id = flow.next_outgoing_id;
+        self.next_incoming_id = flow_next_outgoing_id;
         self.remote_outgoing_window = flow.outgoing_window;
+        Ok(())
+    }
 
-        match &flow.next_incoming_id {
-            Some(flow_next_incoming_id) => {
-                // The remote-incoming-window is computed as follows:
-                // next-incoming-id_flow + incoming-window_flow - next-outgoing-id_endpoint
-                self.remote_incoming_window = flow_next_incoming_id
-                    .saturating_add(flow.incoming_window)
-                    .saturating_sub(self.next_outgoing_id);
-            }
-            None => {
-                // If the next-incoming-id field of the flow frame is not set,
-                // then remote-incoming-window is computed as follows:
-                // initial-outgoing-id_endpoint + incoming-window_flow -
-                // next-outgoing-id_endpoint
-                self.remote_incoming_window = self
-                    .initial_outgoing_id
-                    .value()
-                    .saturating_add(flow.incoming_window)
-                    .saturating_sub(self.next_outgoing_id);
-            }
-        }
+    async fn on_incoming_flow_inner(
+        &mut self,
+        flow: Flow,
+    ) -> Result<Option<LinkFlow>, SessionInnerError> {
+        // Handle session flow control
+        self.on_incoming_session_flow(&flow)?;
+
+        // The remote-incoming-window is computed as follows:
+        // next-incoming-id_flow + incoming-window_flow - next-outgoing-id_endpoint
+        //
+        // If the next-incoming-id field of the flow frame is not set,
+        // then remote-incoming-window is computed as follows:
+        // initial-outgoing-id_endpoint + incoming-window_flow -
+        // next-outgoing-id_endpoint
+        //
+        // The transfer-ids are serial numbers that may wrap around
+        let in_flight = flow
+            .next_incoming_id
+            .map(SerialNumber::from)
+            .unwrap_or(*self.initial_outgoing_id.value())
+            .distance_to(self.next_outgoing_id)
+            .map_err(|_| SessionInnerError::InvalidFlow)?;
+        self.remote_incoming_window = flow.incoming_window.saturating_sub(in_flight);
 
         // Handle link flow control
         if let Ok(link_flow) = LinkFlow::try_from(flow) {
             let input_handle = InputHandle::from(link_flow.handle.clone());
-            match self.link_by_input_handle.get_mut(&input_handle) {
-                Some(link_relay) => {
-                    return link_relay
-                        .on_incoming_flow(link_flow)
-                        .await
-                        .map_err(Into::into);
-                }
-                None => return Err(SessionInnerError::UnattachedHandle), // End session with unattached handle?
+            let decision = self.route_incoming("flow", &input_handle)?;
+            self.record_routing("flow", input_handle.0, decision);
+            if let Some(link_relay) = self.link_by_input_handle.get_mut(&input_handle) {
+                return link_relay
+                    .on_incoming_flow(link_flow, &mut self.link_overflow)
+                    .await
+                    .map_err(Into::into);
             }
         }
 
@@ -439,47 +1027,25 @@ impl Session {
         &mut self,
         mut output_frame_buffer: Vec<SessionFrame>,
     ) -> Result<Vec<SessionFrame>, SessionInnerError> {
-        // Drain the buffered transfers as much as possible
+        // Drain the buffered transfers as much as possible. The continuation of a split delivery
+        // is put back at the front so that it is sent before any other buffered transfer
         while self.remote_incoming_window > 0 {
             if let Some((input_handle, transfer, payload)) =
                 self.remote_incoming_window_exhausted_buffer.pop_front()
             {
-                let frame = self.on_outgoing_transfer_inner(input_handle, transfer, payload)?;
+                let (frame, continuation) =
+                    self.on_outgoing_transfer_inner(input_handle, transfer, payload)?;
                 output_frame_buffer.push(frame);
+                if let Some(continuation) = continuation {
+                    self.remote_incoming_window_exhausted_buffer
+                        .push_front(continuation);
+                }
             } else {
                 break;
             }
         }
         Ok(output_frame_buffer)
     }
-
-    /// Drain the buffered transfers frames and current transfer frame as much as possible
-    fn prepare_session_frames_from_buffered_and_current_transfers(
-        &mut self,
-        output_frame_buffer: Vec<SessionFrame>,
-        cur_input_handle: InputHandle,
-        cur_transfer: Transfer,
-        cur_payload: Payload,
-    ) -> Result<Vec<SessionFrame>, SessionInnerError> {
-        // Drain the buffered transfers first
-        let mut frames =
-            self.prepare_session_frames_from_buffered_transfers(output_frame_buffer)?;
-
-        // Then process the current transfer if there is still space in the
-        // remote-incoming-window
-        if self.remote_incoming_window > 0 {
-            let frame =
-                self.on_outgoing_transfer_inner(cur_input_handle, cur_transfer, cur_payload)?;
-            frames.push(frame);
-        } else {
-            self.remote_incoming_window_exhausted_buffer.push_back((
-                cur_input_handle,
-                cur_transfer,
-                cur_payload,
-            ));
-        }
-        Ok(frames)
-    }
 }
 
 impl endpoint::Session for Session {
@@ -507,13 +1073,18 @@ impl endpoint::Session for Session {
             _ => return Err(AllocLinkError::IllegalSessionState),
         };
 
-        // check whether link name is duplciated
-        if self.link_by_name.contains_key(&link_name) {
+        // check whether link name is duplciated or reserved by a detached link
+        if self.link_by_name.contains_key(&link_name)
+            || self.detached_link_names.contains(&link_name)
+        {
             return Err(AllocLinkError::DuplicatedLinkName);
         }
 
-        // get a new entry index
+        // get a new entry index, which must not exceed the handle-max of either endpoint
         let entry = self.link_name_by_output_handle.vacant_entry();
+        if entry.key() > self.handle_max.0.min(self.remote_handle_max.0) as usize {
+            return Err(AllocLinkError::HandleMaxReached);
+        }
         let handle = OutputHandle(entry.key() as u32);
 
         entry.insert(link_name.clone());
@@ -522,12 +1093,31 @@ impl endpoint::Session for Session {
         Ok(handle)
     }
 
+    fn reallocate_link(
+        &mut self,
+        link_name: String,
+        link_relay: LinkRelay<()>,
+    ) -> Result<OutputHandle, Self::AllocError> {
+        let was_detached = self.detached_link_names.remove(&link_name);
+        match self.allocate_link(link_name.clone(), Some(link_relay)) {
+            Ok(output_handle) => Ok(output_handle),
+            Err(err) => {
+                if was_detached {
+                    self.detached_link_names.insert(link_name);
+                }
+                Err(err)
+            }
+        }
+    }
+
     fn allocate_incoming_link(
         &mut self,
         link_name: String,
         link_relay: LinkRelay<()>,
         input_handle: InputHandle,
     ) -> Result<OutputHandle, Self::AllocError> {
+        // An incoming attach with the name of a detached link is the remote peer resuming it
+        self.detached_link_names.remove(&link_name);
         match self.allocate_link(link_name, None) {
             Ok(output_handle) => {
                 let value = link_relay.with_output_handle(output_handle.clone());
@@ -548,6 +1138,72 @@ impl endpoint::Session for Session {
         }
     }
 
+    fn release_link_name(&mut self, link_name: &str) {
+        self.detached_link_names.remove(link_name);
+    }
+
+    /// Tells the links that the session has ended and drops their relays
+    ///
+    /// A link that is not reading its frames will find out once its channel is closed, which
+    /// happens right away rather than once the event loop has finished deallocating the session.
+    fn notify_links_ended(&mut self) {
+        let relays = self
+            .link_by_name
+            .values_mut()
+            .flatten()
+            .chain(self.link_by_input_handle.values_mut());
+        for relay in relays {
+            relay.remote_detach().on_session_ended();
+            if let LinkRelay::Sender { flow_state, .. } = relay {
+                flow_state.state().confirms.on_detached();
+            }
+            let _ = relay.try_send(LinkFrame::SessionEnded(self.end_error.clone()));
+        }
+        self.link_by_name.clear();
+        self.link_by_input_handle.clear();
+        // The frames kept for a link whose channel is full also hold on to the channel
+        self.link_overflow = LinkOverflow::default();
+    }
+
+    fn incomplete_incoming_bytes(&self) -> usize {
+        self.incomplete_incoming.values().sum()
+    }
+
+    fn routing_history(&self) -> Vec<RoutingRecord> {
+        self.routing_history.iter().cloned().collect()
+    }
+
+    fn link_summaries(&self) -> Vec<LinkSummary> {
+        // The relay of a link is moved from `link_by_name` to `link_by_input_handle` once the
+        // remote Attach is received
+        let unattached = self
+            .link_by_name
+            .values()
+            .flatten()
+            .map(|relay| (relay, None));
+        let attached = self
+            .link_by_input_handle
+            .iter()
+            .map(|(input_handle, relay)| (relay, Some(input_handle.0)));
+        let mut summaries: Vec<LinkSummary> = unattached
+            .chain(attached)
+            .filter_map(|(relay, input_handle)| {
+                let output_handle = relay.output_handle().0;
+                let name = self
+                    .link_name_by_output_handle
+                    .get(output_handle as usize)?;
+                Some(LinkSummary {
+                    name: name.clone(),
+                    role: relay.role(),
+                    output_handle,
+                    input_handle,
+                })
+            })
+            .collect();
+        summaries.sort_by_key(|summary| summary.output_handle);
+        summaries
+    }
+
     fn on_incoming_begin(
         &mut self,
         channel: IncomingChannel,
@@ -560,14 +1216,33 @@ impl endpoint::Session for Session {
         }
 
         self.incoming_channel = Some(channel);
-        self.next_incoming_id = begin.next_outgoing_id;
+        self.next_incoming_id = begin.next_outgoing_id.into();
         self.remote_incoming_window = begin.incoming_window;
         self.remote_outgoing_window = begin.outgoing_window;
+        self.remote_handle_max = begin.handle_max;
 
         Ok(())
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(outgoing_channel = self.outgoing_channel.0, link_name = %attach.name, handle = attach.handle.0)))]
     async fn on_incoming_attach(&mut self, attach: Attach) -> Result<(), Self::Error> {
+        // The frame itself is traced by the connection, which applies the log redactor to it
+        #[cfg(feature = "tracing")]
+        tracing::trace!("RECV attach");
+        #[cfg(feature = "log")]
+        log::trace!(
+            "channel {}, RECV attach: link_name = {}, handle = {}",
+            self.outgoing_channel.0,
+            attach.name,
+            attach.handle.0
+        );
+
+        self.check_incoming_attach_handle(&attach)?;
+        if self.on_incoming_abandoned_attach(&attach) {
+            return Ok(());
+        }
+
+        let handle = attach.handle.0;
         match self.link_by_name.get_mut(&attach.name) {
             Some(link) => match link.take() {
                 Some(mut relay) => {
@@ -583,6 +1258,10 @@ impl endpoint::Session for Session {
                     }
 
                     let input_handle = InputHandle::from(attach.handle.clone()); // handle is just a wrapper around u32
+                    let decision = RoutingDecision::Routed {
+                        output_handle: relay.output_handle().0,
+                    };
+                    self.record_routing("attach", handle, decision);
                     relay
                         .send(LinkFrame::Attach(attach))
                         .await
@@ -593,10 +1272,14 @@ impl endpoint::Session for Session {
                 }
                 None => {
                     // Link name is found but is already in use
+                    self.record_routing("attach", handle, RoutingDecision::HandleInUse);
                     Err(SessionInnerError::HandleInUse)
                 }
             },
-            None => Err(SessionInnerError::RemoteAttachingLinkNameNotFound), // End session with unattached handle?,
+            None => {
+                self.record_routing("attach", handle, RoutingDecision::UnknownLinkName);
+                Err(SessionInnerError::RemoteAttachingLinkNameNotFound) // End session with unattached handle?,
+            }
         }
     }
 
@@ -630,23 +1313,27 @@ impl endpoint::Session for Session {
         }
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(outgoing_channel = self.outgoing_channel.0, handle = transfer.handle.0, delivery_id = ?transfer.delivery_id)))]
     async fn on_incoming_transfer(
         &mut self,
         transfer: Transfer,
         payload: Payload,
+        permit: Option<IncomingPermit>,
     ) -> Result<Option<Disposition>, Self::Error> {
-        // Upon receiving a transfer, the receiving endpoint will increment the next-incoming-id to
-        // match the implicit transfer-id of the incoming transfer plus one, as well as decrementing the
-        // remote-outgoing-window, and MAY (depending on policy) decrement its incoming-window.
-        self.next_incoming_id = self.next_incoming_id.wrapping_add(1);
-        self.remote_outgoing_window = self.remote_outgoing_window.saturating_sub(1);
-
-        // TODO: allow user to define whether the incoming window should be decremented
+        self.on_incoming_transfer_frame()?;
 
         let input_handle = InputHandle::from(transfer.handle.clone());
+        let decision = self.route_incoming("transfer", &input_handle)?;
+        self.record_routing("transfer", input_handle.0, decision);
+        self.account_incomplete_incoming(&input_handle, &transfer, payload.len())?;
         match self.link_by_input_handle.get_mut(&input_handle) {
             Some(link_relay) => {
-                let id_and_tag = link_relay.on_incoming_transfer(transfer, payload).await?;
+                let id_and_tag = link_relay.on_incoming_transfer(
+                    transfer,
+                    payload,
+                    permit,
+                    &mut self.link_overflow,
+                )?;
 
                 // FIXME: If the unsettled map needs this
                 if let Some((delivery_id, delivery_tag)) = id_and_tag {
@@ -660,96 +1347,116 @@ impl endpoint::Session for Session {
         Ok(None)
     }
 
-    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(outgoing_channel = self.outgoing_channel.0)))]
     fn on_incoming_disposition(
         &mut self,
         disposition: Disposition,
     ) -> Result<Option<Vec<Disposition>>, Self::Error> {
         let first = disposition.first;
         let last = disposition.last.unwrap_or(first);
+        let is_terminal = disposition
+            .state
+            .as_ref()
+            .map(|s| s.is_terminal())
+            .unwrap_or(false);
 
         // A disposition frame may refer to deliveries on multiple links, each may be running
-        // in different mode. This counts the largest sections that can be echoed back together
-        if disposition.settled {
-            // If it is alrea
-            for delivery_id in first..=last {
-                let key = (disposition.role.clone(), delivery_id);
-                if let Some((handle, delivery_tag)) = self.delivery_tag_by_id.remove(&key) {
-                    if let Some(link_handle) = self.link_by_input_handle.get_mut(&handle) {
-                        let _echo = link_handle.on_incoming_disposition(
-                            disposition.role.clone(),
-                            disposition.settled,
-                            disposition.state.clone(),
-                            delivery_tag,
-                        );
-                    }
-                }
-            }
-
-            Ok(None)
-        } else {
-            let mut delivery_ids = Vec::new();
-            for delivery_id in first..=last {
-                let key = (disposition.role.clone(), delivery_id);
-                if let Some((handle, delivery_tag)) = self.delivery_tag_by_id.get(&key) {
-                    if let Some(link_handle) = self.link_by_input_handle.get_mut(handle) {
+        // in different mode. Each delivery is routed to the link it was transferred on, and the
+        // deliveries that need to be settled by the sender are echoed back in consecutive chunks
+        let mut delivery_ids = Vec::new();
+        for delivery_id in self.tracked_delivery_ids(&disposition.role, first, last) {
+            let key = (disposition.role.clone(), delivery_id);
+
+            // A delivery no longer needs to be tracked once it is settled. An outgoing delivery
+            // reaching a terminal state is either settled by the local sender or settled by the
+            // echoed disposition below
+            let is_done =
+                disposition.settled || (is_terminal && matches!(disposition.role, Role::Receiver));
+            let entry = match is_done {
+                true => self.delivery_tag_by_id.remove(&key),
+                false => self.delivery_tag_by_id.get(&key).cloned(),
+            };
+
+            if let Some((handle, delivery_tag)) = entry {
+                match self.link_by_input_handle.get_mut(&handle) {
+                    Some(link_handle) => {
                         // In mode Second, the receiver will first send a non-settled disposition,
                         // and wait for sender's settled disposition
                         let echo = link_handle.on_incoming_disposition(
                             disposition.role.clone(),
                             disposition.settled,
                             disposition.state.clone(),
-                            delivery_tag.clone(),
+                            delivery_id,
+                            delivery_tag,
                         );
 
                         if echo {
                             delivery_ids.push(delivery_id);
                         }
                     }
+                    None => {
+                        // The link has been detached
+                        self.delivery_tag_by_id.remove(&key);
+                    }
                 }
             }
+        }
 
-            let chunk_inds = consecutive_chunk_indices(&delivery_ids[..]);
+        if delivery_ids.is_empty() {
+            return Ok(None);
+        }
 
-            let mut dispositions = Vec::with_capacity(chunk_inds.len());
-            let mut prev_ind = 0;
-            for ind in chunk_inds {
-                let slice = &delivery_ids[prev_ind..ind];
-                let disposition = Disposition {
-                    role: Role::Sender,
-                    first: slice[0],
-                    last: slice.last().copied(),
-                    settled: true,
-                    state: disposition.state.clone(),
-                    batchable: false,
-                };
-                dispositions.push(disposition);
-                prev_ind = ind;
-            }
-            Ok(Some(dispositions))
+        let chunk_inds = consecutive_chunk_indices(&delivery_ids[..]);
+
+        let mut dispositions = Vec::with_capacity(chunk_inds.len() + 1);
+        let mut prev_ind = 0;
+        for ind in chunk_inds
+            .into_iter()
+            .chain(std::iter::once(delivery_ids.len()))
+        {
+            let slice = &delivery_ids[prev_ind..ind];
+            let disposition = Disposition {
+                role: Role::Sender,
+                first: slice[0],
+                last: slice.last().copied(),
+                settled: true,
+                state: disposition.state.clone(),
+                batchable: false,
+            };
+            dispositions.push(disposition);
+            prev_ind = ind;
         }
+        Ok(Some(dispositions))
     }
 
-    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(outgoing_channel = self.outgoing_channel.0)))]
     async fn on_incoming_detach(&mut self, detach: Detach) -> Result<(), Self::Error> {
         #[cfg(feature = "tracing")]
         tracing::trace!(frame = ?detach);
         #[cfg(feature = "log")]
         log::trace!("RECV frame = {:?}", detach);
         // Remove the link by input handle
-        match self
-            .link_by_input_handle
-            .remove(&InputHandle::from(detach.handle.clone()))
-        {
-            Some(mut link) => link
-                .on_incoming_detach(detach)
-                .await
-                .map_err(|_| SessionInnerError::UnattachedHandle),
+        let input_handle = InputHandle::from(detach.handle.clone());
+        let decision = self.route_incoming("detach", &input_handle)?;
+        self.record_routing("detach", input_handle.0, decision);
+        // Deliveries that are still unsettled will be assigned new delivery ids if the link
+        // resumes, and the handle may be reused by another link
+        self.delivery_tag_by_id
+            .retain(|_, (handle, _)| *handle != input_handle);
+        // An incomplete delivery is discarded by the link once it is detached
+        self.incomplete_incoming.remove(&input_handle);
+        match self.link_by_input_handle.remove(&input_handle) {
+            Some(mut link) => {
+                // A link that is dropped sends a closing Detach before it goes away, so this is the
+                // reply and there is no one left to forward it to
+                let _ = link.on_incoming_detach(detach, &mut self.link_overflow);
+                Ok(())
+            }
             None => Err(SessionInnerError::UnattachedHandle),
         }
     }
 
-    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(outgoing_channel = self.outgoing_channel.0)))]
     fn on_incoming_end(
         &mut self,
         _channel: IncomingChannel,
@@ -764,7 +1471,10 @@ impl endpoint::Session for Session {
                 self.local_state = SessionState::EndReceived;
 
                 match end.error {
-                    Some(err) => Err(SessionStateError::RemoteEndedWithError(err)),
+                    Some(err) => {
+                        self.end_error.get_or_insert_with(|| err.clone());
+                        Err(SessionStateError::RemoteEndedWithError(err))
+                    }
                     None => Err(SessionStateError::RemoteEnded),
                 }
             }
@@ -772,6 +1482,7 @@ impl endpoint::Session for Session {
                 self.local_state = SessionState::Unmapped;
 
                 if let Some(error) = end.error {
+                    self.end_error.get_or_insert_with(|| error.clone());
                     #[cfg(feature = "tracing")]
                     tracing::error!(remote_error = ?error);
                     #[cfg(feature = "log")]
@@ -784,13 +1495,17 @@ impl endpoint::Session for Session {
         }
     }
 
+    async fn relay_overflow(&mut self) {
+        self.link_overflow.relay().await
+    }
+
     async fn send_begin(
         &mut self,
         writer: &mpsc::Sender<SessionFrame>,
     ) -> Result<(), Self::BeginError> {
         let begin = Begin {
             remote_channel: self.incoming_channel.map(Into::into),
-            next_outgoing_id: self.next_outgoing_id,
+            next_outgoing_id: self.next_outgoing_id.into(),
             incoming_window: self.incoming_window,
             outgoing_window: self.outgoing_window,
             handle_max: self.handle_max.clone(),
@@ -838,6 +1553,9 @@ impl endpoint::Session for Session {
             _ => return Err(SessionStateError::IllegalState),
         }
 
+        if let Some(error) = &error {
+            self.end_error.get_or_insert_with(|| error.clone());
+        }
         let frame = SessionFrame::new(self.outgoing_channel, SessionFrameBody::End(End { error }));
         writer
             .send(frame)
@@ -849,17 +1567,29 @@ impl endpoint::Session for Session {
     }
 
     fn on_outgoing_attach(&mut self, attach: Attach) -> Result<SessionFrame, Self::Error> {
+        #[cfg(feature = "tracing")]
+        tracing::trace!(outgoing_channel = self.outgoing_channel.0, link_name = %attach.name, handle = attach.handle.0, "SEND attach");
+        #[cfg(feature = "log")]
+        log::trace!(
+            "channel {}, SEND attach: link_name = {}, handle = {}",
+            self.outgoing_channel.0,
+            attach.name,
+            attach.handle.0
+        );
         let body = SessionFrameBody::Attach(attach);
         let frame = SessionFrame::new(self.outgoing_channel, body);
         Ok(frame)
     }
 
     fn on_outgoing_flow(&mut self, flow: LinkFlow) -> Result<SessionFrame, Self::Error> {
+        // Every flow re-advertises the incoming-window
+        let window = self.next_incoming_window();
+        self.advertise_incoming_window(window);
         let flow = Flow {
             // Session flow states
-            next_incoming_id: Some(self.next_incoming_id),
+            next_incoming_id: Some(self.next_incoming_id.into()),
             incoming_window: self.incoming_window,
-            next_outgoing_id: self.next_outgoing_id,
+            next_outgoing_id: self.next_outgoing_id.into(),
             outgoing_window: self.outgoing_window,
             // Link flow states
             handle: Some(flow.handle),
@@ -876,39 +1606,52 @@ impl endpoint::Session for Session {
         Ok(frame)
     }
 
+    fn replenish_incoming_window(&mut self) -> Option<SessionFrame> {
+        // Transfers of a large delivery may exhaust the incoming-window before any link flow is
+        // sent, so the window is re-advertised once half of it is consumed. A window that has to
+        // shrink to half of the advertised one because the receivers fall behind is advertised
+        // right away
+        let window = self.next_incoming_window();
+        let exhausted = self.incoming_window <= window / 2;
+        let shrunk = window <= self.advertised_incoming_window / 2;
+        if !exhausted && !shrunk {
+            return None;
+        }
+
+        self.advertise_incoming_window(window);
+        let flow = Flow {
+            next_incoming_id: Some(self.next_incoming_id.into()),
+            incoming_window: self.incoming_window,
+            next_outgoing_id: self.next_outgoing_id.into(),
+            outgoing_window: self.outgoing_window,
+            handle: None,
+            delivery_count: None,
+            link_credit: None,
+            available: None,
+            drain: false,
+            echo: false,
+            properties: None,
+        };
+        let body = SessionFrameBody::Flow(flow);
+        Some(SessionFrame::new(self.outgoing_channel, body))
+    }
+
     fn on_outgoing_transfer(
         &mut self,
         input_handle: InputHandle,
         transfer: Transfer,
         payload: Payload,
     ) -> Result<Option<SessionOutgoingItem>, Self::Error> {
-        // Check if remote-incoming-window is exhausted
-        if self.remote_incoming_window == 0 {
-            // exhausted
-            self.remote_incoming_window_exhausted_buffer.push_back((
-                input_handle,
-                transfer,
-                payload,
-            ));
-            Ok(None)
-        } else if self.remote_incoming_window_exhausted_buffer.is_empty() {
-            // no buffered transfer
-            let frame = self.on_outgoing_transfer_inner(input_handle, transfer, payload)?;
-            Ok(Some(SessionOutgoingItem::SingleFrame(frame)))
-        } else {
-            let output_frame_buffer = Vec::with_capacity(
-                self.remote_incoming_window_exhausted_buffer
-                    .len()
-                    .saturating_add(1),
-            );
-            self.prepare_session_frames_from_buffered_and_current_transfers(
-                output_frame_buffer,
-                input_handle,
-                transfer,
-                payload,
-            )
-            .map(SessionOutgoingItem::MultipleFrames)
-            .map(Some)
+        // Transfers are queued behind the transfers that are blocked by the
+        // remote-incoming-window, and a transfer that does not fit in a single frame is sent as
+        // multiple transfer frames
+        self.remote_incoming_window_exhausted_buffer
+            .push_back((input_handle, transfer, payload));
+        let mut frames = self.prepare_session_frames_from_buffered_transfers(Vec::new())?;
+        match frames.len() {
+            0 => Ok(None),
+            1 => Ok(frames.pop().map(SessionOutgoingItem::SingleFrame)),
+            _ => Ok(Some(SessionOutgoingItem::MultipleFrames(frames))),
         }
     }
 
@@ -935,20 +1678,80 @@ impl endpoint::Session for Session {
             self.remote_outgoing_window = self.remote_outgoing_window.saturating_add(count);
         }
 
+        // Settled deliveries no longer need to be tracked. The keys use the remote peer's role
+        if disposition.settled {
+            let remote_role = match disposition.role {
+                Role::Sender => Role::Receiver,
+                Role::Receiver => Role::Sender,
+            };
+            let last = disposition.last.unwrap_or(disposition.first);
+            for delivery_id in self.tracked_delivery_ids(&remote_role, disposition.first, last) {
+                self.delivery_tag_by_id
+                    .remove(&(remote_role.clone(), delivery_id));
+            }
+        }
+
         let body = SessionFrameBody::Disposition(disposition);
         let frame = SessionFrame::new(self.outgoing_channel, body);
         Ok(frame)
     }
 
     fn on_outgoing_detach(&mut self, detach: Detach) -> SessionFrame {
-        self.deallocate_link(detach.handle.clone().into());
+        #[cfg(feature = "tracing")]
+        tracing::trace!(
+            outgoing_channel = self.outgoing_channel.0,
+            handle = detach.handle.0,
+            closed = detach.closed,
+            "SEND detach"
+        );
+        #[cfg(feature = "log")]
+        log::trace!(
+            "channel {}, SEND detach: handle = {}, closed = {}",
+            self.outgoing_channel.0,
+            detach.handle.0,
+            detach.closed
+        );
+        let output_handle = OutputHandle::from(detach.handle.clone());
+        // A Detach from the remote peer that follows is the reply to this one
+        if let Some(relay) = self
+            .link_by_input_handle
+            .values()
+            .chain(self.link_by_name.values().flatten())
+            .find(|relay| relay.output_handle() == &output_handle)
+        {
+            relay.remote_detach().on_local_detach();
+        }
+        let name = self
+            .link_name_by_output_handle
+            .get(output_handle.0 as usize)
+            .cloned();
+        // The relay is only left in place if the remote Attach has not arrived yet
+        let abandoned = name
+            .as_ref()
+            .and_then(|name| self.link_by_name.get_mut(name))
+            .and_then(Option::take);
+        self.deallocate_link(output_handle);
+        if let (Some(name), Some(relay)) = (&name, abandoned) {
+            self.abandoned_links
+                .entry(name.clone())
+                .or_default()
+                .push_back(relay);
+        }
+        // A link that is detached without closing may be resumed later, so its name stays reserved
+        if let (Some(name), false) = (name, detach.closed) {
+            self.detached_link_names.insert(name);
+        }
         let body = SessionFrameBody::Detach(detach);
         SessionFrame::new(self.outgoing_channel, body)
     }
 }
 
 fn num_messages_settled_by_disposition(first: u32, last: Option<u32>) -> u32 {
-    last.and_then(|last| last.checked_sub(first)).unwrap_or(0) + 1
+    // Delivery ids are serial numbers and the range may wrap around
+    last.map(|last| last.wrapping_sub(first))
+        .filter(|span| *span <= i32::MAX as u32)
+        .unwrap_or(0)
+        + 1
 }
 
 cfg_transaction! {
@@ -998,7 +1801,927 @@ fn consecutive_chunk_indices(delivery_ids: &[DeliveryNumber]) -> Vec<usize> {
 
 #[cfg(test)]
 mod tests {
-    use super::num_messages_settled_by_disposition;
+    use std::sync::Arc;
+
+    use bytes::Bytes;
+    use fe2o3_amqp_types::{
+        definitions::{AmqpError, DeliveryTag, Error, Handle, ReceiverSettleMode, Role},
+        messaging::{Accepted, DeliveryState},
+        performatives::{Attach, Detach, Disposition, Flow, Transfer},
+        states::SessionState,
+    };
+    use parking_lot::RwLock;
+    use serde_amqp::serialized_size;
+    use tokio::sync::{mpsc, oneshot, Notify};
+
+    use crate::{
+        connection::DEFAULT_MAX_FRAME_SIZE,
+        endpoint::{InputHandle, OutgoingChannel, OutputHandle, Session as _},
+        frames::FRAME_HEADER_SIZE,
+        link::{
+            delivery::UnsettledMessage,
+            state::{LinkFlowState, LinkFlowStateInner},
+            ArcSenderUnsettledMap, LinkFrame, LinkIncomingItem, LinkRelay, UnsettledMap,
+        },
+        util::{Producer, SerialNumber},
+        Payload,
+    };
+
+    use super::{
+        error::{AllocLinkError, SessionInnerError},
+        frame::{SessionFrame, SessionFrameBody, SessionOutgoingItem},
+        num_messages_settled_by_disposition, IncomingWindowPolicy, Session,
+    };
+
+    fn sender_relay(
+        output_handle: u32,
+        receiver_settle_mode: ReceiverSettleMode,
+    ) -> (LinkRelay<OutputHandle>, ArcSenderUnsettledMap) {
+        let (tx, _rx) = mpsc::channel(1);
+        let flow_state = LinkFlowState::sender(LinkFlowStateInner {
+            initial_delivery_count: 0.into(),
+            delivery_count: 0.into(),
+            link_credit: 0,
+            available: 0,
+            drain: false,
+            properties: None,
+        });
+        let unsettled: ArcSenderUnsettledMap = Arc::new(RwLock::new(Some(UnsettledMap::new())));
+        let relay = LinkRelay::Sender {
+            tx,
+            output_handle: OutputHandle(output_handle),
+            flow_state: Producer::new(Arc::new(Notify::new()), Arc::new(flow_state)),
+            unsettled: unsettled.clone(),
+            receiver_settle_mode,
+        };
+        (relay, unsettled)
+    }
+
+    /// Sends an unsettled transfer on the link and returns the receiving half of its outcome
+    fn send_unsettled(
+        session: &mut Session,
+        handle: u32,
+        unsettled: &ArcSenderUnsettledMap,
+        tag: u8,
+    ) -> oneshot::Receiver<Option<DeliveryState>> {
+        let delivery_tag = DeliveryTag::from(vec![handle as u8, tag]);
+        let (tx, rx) = oneshot::channel();
+        let message = UnsettledMessage::new(Payload::new(), None, 0, Some(tx));
+        unsettled
+            .write()
+            .as_mut()
+            .unwrap()
+            .insert(delivery_tag.clone(), message);
+
+        let transfer = Transfer {
+            handle: handle.into(),
+            delivery_id: None,
+            delivery_tag: Some(delivery_tag),
+            message_format: Some(0),
+            settled: Some(false),
+            more: false,
+            rcv_settle_mode: None,
+            state: None,
+            resume: false,
+            aborted: false,
+            batchable: false,
+        };
+        session
+            .on_outgoing_transfer_inner(InputHandle(handle), transfer, Payload::new())
+            .unwrap();
+        rx
+    }
+
+    fn receiver_relay(
+        output_handle: u32,
+    ) -> (LinkRelay<OutputHandle>, mpsc::Receiver<LinkIncomingItem>) {
+        let (tx, rx) = mpsc::channel(16);
+        let flow_state = LinkFlowState::receiver(LinkFlowStateInner {
+            initial_delivery_count: 0.into(),
+            delivery_count: 0.into(),
+            link_credit: 0,
+            available: 0,
+            drain: false,
+            properties: None,
+        });
+        let relay = LinkRelay::Receiver {
+            tx,
+            output_handle: OutputHandle(output_handle),
+            flow_state: Arc::new(flow_state),
+            unsettled: Arc::new(RwLock::new(Some(UnsettledMap::new()))),
+            remote_settlements: Default::default(),
+            receiver_settle_mode: ReceiverSettleMode::First,
+            more: false,
+        };
+        (relay, rx)
+    }
+
+    fn settled_transfer(handle: u32, delivery_id: u32) -> Transfer {
+        Transfer {
+            handle: handle.into(),
+            delivery_id: Some(delivery_id),
+            delivery_tag: Some(DeliveryTag::from(delivery_id.to_be_bytes().to_vec())),
+            message_format: Some(0),
+            settled: Some(true),
+            more: false,
+            rcv_settle_mode: None,
+            state: None,
+            resume: false,
+            aborted: false,
+            batchable: false,
+        }
+    }
+
+    fn session_flow(next_incoming_id: Option<u32>, next_outgoing_id: u32) -> Flow {
+        Flow {
+            next_incoming_id,
+            incoming_window: 100,
+            next_outgoing_id,
+            outgoing_window: 100,
+            handle: None,
+            delivery_count: None,
+            link_credit: None,
+            available: None,
+            drain: false,
+            echo: false,
+            properties: None,
+        }
+    }
+
+    fn new_session(next_outgoing_id: u32) -> Session {
+        let mut session = Session::builder()
+            .next_outgoing_id(next_outgoing_id)
+            .into_session(
+                OutgoingChannel(0),
+                SessionState::Mapped,
+                DEFAULT_MAX_FRAME_SIZE as usize,
+            );
+        session.remote_incoming_window = u32::MAX;
+        session
+    }
+
+    #[test]
+    fn ranged_disposition_is_routed_to_interleaved_links() {
+        // Start close to the end of the number space to also cover wrapping delivery ids
+        let mut session = new_session(u32::MAX - 3);
+        let (relay_a, unsettled_a) = sender_relay(0, ReceiverSettleMode::First);
+        let (relay_b, unsettled_b) = sender_relay(1, ReceiverSettleMode::First);
+        session.link_by_input_handle.insert(InputHandle(0), relay_a);
+        session.link_by_input_handle.insert(InputHandle(1), relay_b);
+
+        let mut outcomes = Vec::new();
+        for tag in 0..4 {
+            outcomes.push(send_unsettled(&mut session, 0, &unsettled_a, tag));
+            outcomes.push(send_unsettled(&mut session, 1, &unsettled_b, tag));
+        }
+        assert_eq!(session.delivery_tag_by_id.len(), 8);
+
+        // The receiver acks in two bulk ranges that each cover deliveries of both links
+        for (first, last) in [(u32::MAX - 3, 0), (1, 4)] {
+            let disposition = Disposition {
+                role: Role::Receiver,
+                first,
+                last: Some(last),
+                settled: true,
+                state: Some(DeliveryState::Accepted(Accepted {})),
+                batchable: false,
+            };
+            let echo = session.on_incoming_disposition(disposition).unwrap();
+            assert!(echo.is_none());
+        }
+
+        for mut outcome in outcomes {
+            let state = outcome.try_recv().unwrap();
+            assert!(matches!(state, Some(DeliveryState::Accepted(_))));
+        }
+        assert!(unsettled_a.read().as_ref().unwrap().is_empty());
+        assert!(unsettled_b.read().as_ref().unwrap().is_empty());
+        assert!(session.delivery_tag_by_id.is_empty());
+    }
+
+    #[test]
+    fn ranged_disposition_is_echoed_in_mode_second() {
+        let mut session = new_session(0);
+        let (relay_a, unsettled_a) = sender_relay(0, ReceiverSettleMode::Second);
+        let (relay_b, unsettled_b) = sender_relay(1, ReceiverSettleMode::First);
+        session.link_by_input_handle.insert(InputHandle(0), relay_a);
+        session.link_by_input_handle.insert(InputHandle(1), relay_b);
+
+        let mut outcomes = Vec::new();
+        for tag in 0..3 {
+            outcomes.push(send_unsettled(&mut session, 0, &unsettled_a, tag));
+            outcomes.push(send_unsettled(&mut session, 1, &unsettled_b, tag));
+        }
+
+        let disposition = Disposition {
+            role: Role::Receiver,
+            first: 0,
+            last: Some(5),
+            settled: false,
+            state: Some(DeliveryState::Accepted(Accepted {})),
+            batchable: false,
+        };
+        let echo = session
+            .on_incoming_disposition(disposition)
+            .unwrap()
+            .unwrap();
+
+        // Only the deliveries on the link in mode Second are settled by the sender
+        let echoed: Vec<_> = echo.iter().map(|d| (d.first, d.last)).collect();
+        assert_eq!(echoed, vec![(0, Some(0)), (2, Some(2)), (4, Some(4))]);
+        assert!(echo.iter().all(|d| d.settled && d.role == Role::Sender));
+
+        for mut outcome in outcomes {
+            assert!(outcome.try_recv().unwrap().is_some());
+        }
+        assert!(session.delivery_tag_by_id.is_empty());
+    }
+
+    #[test]
+    fn disposition_for_missing_link_is_dropped() {
+        let mut session = new_session(0);
+        let (relay_a, unsettled_a) = sender_relay(0, ReceiverSettleMode::First);
+        let (relay_b, unsettled_b) = sender_relay(1, ReceiverSettleMode::First);
+        session.link_by_input_handle.insert(InputHandle(0), relay_a);
+        session.link_by_input_handle.insert(InputHandle(1), relay_b);
+
+        let _a = send_unsettled(&mut session, 0, &unsettled_a, 0);
+        let _b = send_unsettled(&mut session, 1, &unsettled_b, 0);
+
+        // Link 0 is gone without a detach being processed
+        session.link_by_input_handle.remove(&InputHandle(0));
+        let disposition = Disposition {
+            role: Role::Receiver,
+            first: 0,
+            last: Some(1),
+            settled: false,
+            state: None,
+            batchable: false,
+        };
+        session.on_incoming_disposition(disposition).unwrap();
+        assert_eq!(session.delivery_tag_by_id.len(), 1);
+        assert!(session
+            .delivery_tag_by_id
+            .contains_key(&(Role::Receiver, 1)));
+    }
+
+    #[test]
+    fn large_transfer_is_split_against_max_frame_size() {
+        let max_frame_size = 512;
+        let mut session = Session::builder().next_outgoing_id(7).into_session(
+            OutgoingChannel(0),
+            SessionState::Mapped,
+            max_frame_size,
+        );
+        session.remote_incoming_window = 3;
+
+        let content: Vec<u8> = (0..2048u32).map(|i| i as u8).collect();
+        let transfer = Transfer {
+            handle: 0.into(),
+            delivery_id: None,
+            delivery_tag: Some(DeliveryTag::from(vec![1, 2, 3])),
+            message_format: Some(0),
+            settled: Some(true),
+            more: false,
+            rcv_settle_mode: None,
+            state: None,
+            resume: false,
+            aborted: false,
+            batchable: false,
+        };
+        let item = session
+            .on_outgoing_transfer(InputHandle(0), transfer, Payload::from(content.clone()))
+            .unwrap();
+        let mut frames = match item {
+            Some(SessionOutgoingItem::MultipleFrames(frames)) => frames,
+            _ => panic!("Expecting multiple frames"),
+        };
+
+        // Each frame counts against the remote incoming window and the rest is buffered
+        assert_eq!(frames.len(), 3);
+        assert_eq!(session.remote_incoming_window, 0);
+        assert_eq!(session.next_outgoing_id, 10);
+        assert_eq!(session.remote_incoming_window_exhausted_buffer.len(), 1);
+
+        session.remote_incoming_window = u32::MAX;
+        frames.extend(
+            session
+                .prepare_session_frames_from_buffered_transfers(Vec::new())
+                .unwrap(),
+        );
+        assert!(session.remote_incoming_window_exhausted_buffer.is_empty());
+
+        let mut received = Vec::new();
+        let last = frames.len() - 1;
+        for (i, frame) in frames.into_iter().enumerate() {
+            let (performative, payload) = match frame.body {
+                SessionFrameBody::Transfer {
+                    performative,
+                    payload,
+                } => (performative, payload),
+                _ => panic!("Expecting transfer"),
+            };
+            let size = serialized_size(&performative).unwrap() + payload.len();
+            assert!(size + FRAME_HEADER_SIZE <= max_frame_size);
+            assert_eq!(performative.more, i != last);
+            if i == 0 {
+                assert_eq!(performative.delivery_id, Some(7));
+                assert!(performative.delivery_tag.is_some());
+                assert_eq!(performative.settled, Some(true));
+            } else {
+                assert!(performative.delivery_id.is_none());
+                assert!(performative.delivery_tag.is_none());
+                assert!(performative.settled.is_none());
+            }
+            received.extend_from_slice(&payload);
+        }
+        assert_eq!(Bytes::from(received), Bytes::from(content));
+    }
+
+    #[test]
+    fn incoming_window_is_replenished_when_half_consumed() {
+        let mut session = Session::builder().incoming_window(10).into_session(
+            OutgoingChannel(0),
+            SessionState::Mapped,
+            DEFAULT_MAX_FRAME_SIZE as usize,
+        );
+        session.next_incoming_id = 42.into();
+
+        session.incoming_window = 6;
+        assert!(session.replenish_incoming_window().is_none());
+
+        session.incoming_window = 5;
+        let frame = session.replenish_incoming_window().unwrap();
+        match frame.body {
+            SessionFrameBody::Flow(flow) => {
+                assert_eq!(flow.next_incoming_id, Some(42));
+                assert_eq!(flow.incoming_window, 10);
+                assert!(flow.handle.is_none());
+            }
+            _ => panic!("Expecting flow"),
+        }
+        assert_eq!(session.incoming_window, 10);
+    }
+
+    #[tokio::test]
+    async fn transfer_beyond_incoming_window_is_window_violation() {
+        let mut session = Session::builder().incoming_window(2).into_session(
+            OutgoingChannel(0),
+            SessionState::Mapped,
+            DEFAULT_MAX_FRAME_SIZE as usize,
+        );
+        session.next_incoming_id = 10.into();
+        session.remote_outgoing_window = 5;
+        let (relay, mut rx) = receiver_relay(0);
+        session.link_by_input_handle.insert(InputHandle(0), relay);
+
+        for delivery_id in 0..2 {
+            session
+                .on_incoming_transfer(settled_transfer(0, delivery_id), Payload::new(), None)
+                .await
+                .unwrap();
+            assert!(rx.try_recv().is_ok());
+        }
+        assert_eq!(session.next_incoming_id, 12);
+        assert_eq!(session.incoming_window, 0);
+        assert_eq!(session.remote_outgoing_window, 3);
+
+        // The peer sends one more transfer than the window allows
+        let result = session
+            .on_incoming_transfer(settled_transfer(0, 2), Payload::new(), None)
+            .await;
+        assert!(matches!(result, Err(SessionInnerError::WindowViolation)));
+        assert!(rx.try_recv().is_err());
+        assert_eq!(session.next_incoming_id, 12);
+        assert_eq!(session.remote_outgoing_window, 3);
+    }
+
+    #[tokio::test]
+    async fn transfer_within_replenished_incoming_window_is_accepted() {
+        let mut session = Session::builder().incoming_window(2).into_session(
+            OutgoingChannel(0),
+            SessionState::Mapped,
+            DEFAULT_MAX_FRAME_SIZE as usize,
+        );
+        let (relay, _rx) = receiver_relay(0);
+        session.link_by_input_handle.insert(InputHandle(0), relay);
+
+        for delivery_id in 0..4 {
+            session
+                .on_incoming_transfer(settled_transfer(0, delivery_id), Payload::new(), None)
+                .await
+                .unwrap();
+            assert!(session.replenish_incoming_window().is_some());
+        }
+        assert_eq!(session.next_incoming_id, 4);
+        assert_eq!(session.incoming_window, 2);
+    }
+
+    #[test]
+    fn buffer_based_incoming_window_shrinks_between_watermarks() {
+        let policy = IncomingWindowPolicy::BufferBased {
+            low_watermark: 100,
+            high_watermark: 300,
+        };
+        assert_eq!(policy.window(1000, 0), 1000);
+        assert_eq!(policy.window(1000, 100), 1000);
+        assert_eq!(policy.window(1000, 150), 750);
+        assert_eq!(policy.window(1000, 200), 500);
+        assert_eq!(policy.window(1000, 299), 5);
+        assert_eq!(policy.window(1000, 300), 1);
+        assert_eq!(policy.window(1000, 10_000), 1);
+        assert_eq!(policy.window(3, 299), 1);
+        assert_eq!(policy.window(0, 0), 0);
+        assert_eq!(IncomingWindowPolicy::Static.window(1000, 10_000), 1000);
+    }
+
+    fn advertised_incoming_window(frame: Option<SessionFrame>) -> Option<u32> {
+        frame.map(|frame| match frame.body {
+            SessionFrameBody::Flow(flow) => flow.incoming_window,
+            _ => panic!("Expecting flow"),
+        })
+    }
+
+    #[tokio::test]
+    async fn incoming_window_shrinks_as_receiver_buffer_fills() {
+        let mut session = Session::builder()
+            .incoming_window(10)
+            .incoming_window_policy(IncomingWindowPolicy::BufferBased {
+                low_watermark: 2,
+                high_watermark: 6,
+            })
+            .into_session(
+                OutgoingChannel(0),
+                SessionState::Mapped,
+                DEFAULT_MAX_FRAME_SIZE as usize,
+            );
+        session.remote_outgoing_window = 100;
+        let (relay, mut rx) = receiver_relay(0);
+        session.link_by_input_handle.insert(InputHandle(0), relay);
+
+        // The receiver does not take any frame
+        let mut advertised = Vec::new();
+        for delivery_id in 0..7 {
+            session
+                .on_incoming_transfer(settled_transfer(0, delivery_id), Payload::new(), None)
+                .await
+                .unwrap();
+            advertised.push(advertised_incoming_window(
+                session.replenish_incoming_window(),
+            ));
+        }
+        assert_eq!(
+            advertised,
+            vec![None, None, None, Some(5), Some(2), Some(1), Some(1)]
+        );
+
+        // Frames sent before the smaller windows arrived are still within the larger window
+        for delivery_id in 7..10 {
+            session
+                .on_incoming_transfer(settled_transfer(0, delivery_id), Payload::new(), None)
+                .await
+                .unwrap();
+        }
+        let result = session
+            .on_incoming_transfer(settled_transfer(0, 10), Payload::new(), None)
+            .await;
+        assert!(matches!(result, Err(SessionInnerError::WindowViolation)));
+
+        // The whole window is advertised again once the receiver catches up
+        while rx.try_recv().is_ok() {}
+        assert_eq!(
+            advertised_incoming_window(session.replenish_incoming_window()),
+            Some(10)
+        );
+        assert_eq!(session.incoming_window, 10);
+    }
+
+    fn partial_transfer(handle: u32, delivery_id: u32, more: bool) -> Transfer {
+        Transfer {
+            settled: Some(false),
+            more,
+            ..settled_transfer(handle, delivery_id)
+        }
+    }
+
+    #[tokio::test]
+    async fn incomplete_incoming_deliveries_are_limited_across_links() {
+        let mut session = Session::builder()
+            .incomplete_incoming_limit(250)
+            .into_session(
+                OutgoingChannel(0),
+                SessionState::Mapped,
+                DEFAULT_MAX_FRAME_SIZE as usize,
+            );
+        let mut receivers = Vec::new();
+        for (handle, name) in ["link-a", "link-b", "link-c"].iter().enumerate() {
+            let output_handle = session.link_name_by_output_handle.insert(name.to_string());
+            let (relay, rx) = receiver_relay(output_handle as u32);
+            session
+                .link_by_input_handle
+                .insert(InputHandle(handle as u32), relay);
+            receivers.push(rx);
+        }
+
+        // (handle, delivery id, more, payload bytes, incomplete bytes after the transfer)
+        let transfers = [
+            (0, 0, true, 100, 100),
+            (1, 1, true, 100, 200),
+            // The delivery on link-a is complete
+            (0, 0, false, 10, 100),
+            (2, 2, true, 50, 150),
+            (1, 1, true, 60, 210),
+            // Exactly at the limit
+            (2, 2, true, 40, 250),
+        ];
+        for (handle, delivery_id, more, bytes, expected) in transfers {
+            let transfer = partial_transfer(handle, delivery_id, more);
+            let payload = Payload::from(vec![0u8; bytes]);
+            session
+                .on_incoming_transfer(transfer, payload, None)
+                .await
+                .unwrap();
+            assert_eq!(session.incomplete_incoming_bytes(), expected);
+        }
+
+        // An aborted delivery is discarded
+        let aborted = Transfer {
+            aborted: true,
+            ..partial_transfer(2, 2, true)
+        };
+        session
+            .on_incoming_transfer(aborted, Payload::new(), None)
+            .await
+            .unwrap();
+        assert_eq!(session.incomplete_incoming_bytes(), 160);
+
+        let transfer = partial_transfer(0, 3, true);
+        session
+            .on_incoming_transfer(transfer, Payload::from(vec![0u8; 90]), None)
+            .await
+            .unwrap();
+        assert_eq!(session.incomplete_incoming_bytes(), 250);
+
+        let transfer = partial_transfer(1, 1, true);
+        let result = session
+            .on_incoming_transfer(transfer, Payload::from(vec![0u8; 1]), None)
+            .await;
+        match result {
+            Err(SessionInnerError::IncompleteIncomingLimitExceeded { link_name }) => {
+                assert_eq!(link_name, "link-b")
+            }
+            other => panic!(
+                "Expecting IncompleteIncomingLimitExceeded, found {:?}",
+                other
+            ),
+        }
+    }
+
+    #[tokio::test]
+    async fn incomplete_incoming_delivery_is_released_on_detach() {
+        let mut session = Session::builder()
+            .incomplete_incoming_limit(100)
+            .into_session(
+                OutgoingChannel(0),
+                SessionState::Mapped,
+                DEFAULT_MAX_FRAME_SIZE as usize,
+            );
+        let (relay, _rx) = receiver_relay(0);
+        session.link_by_input_handle.insert(InputHandle(0), relay);
+
+        let transfer = partial_transfer(0, 0, true);
+        session
+            .on_incoming_transfer(transfer, Payload::from(vec![0u8; 80]), None)
+            .await
+            .unwrap();
+        assert_eq!(session.incomplete_incoming_bytes(), 80);
+
+        let detach = Detach {
+            handle: 0.into(),
+            closed: true,
+            error: None,
+        };
+        session.on_incoming_detach(detach).await.unwrap();
+        assert_eq!(session.incomplete_incoming_bytes(), 0);
+    }
+
+    #[tokio::test]
+    async fn flow_with_regressing_next_outgoing_id_is_rejected() {
+        let mut session = new_session(0);
+        session.next_incoming_id = 10.into();
+        session.remote_outgoing_window = 3;
+
+        let result = session.on_incoming_flow(session_flow(Some(0), 9)).await;
+        assert!(matches!(result, Err(SessionInnerError::InvalidFlow)));
+        assert_eq!(session.next_incoming_id, 10);
+        assert_eq!(session.remote_outgoing_window, 3);
+
+        session
+            .on_incoming_flow(session_flow(Some(0), 12))
+            .await
+            .unwrap();
+        assert_eq!(session.next_incoming_id, 12);
+        assert_eq!(session.remote_outgoing_window, 100);
+
+        // The next-outgoing-id of the peer may wrap around
+        session.next_incoming_id = u32::MAX.into();
+        session
+            .on_incoming_flow(session_flow(Some(0), 1))
+            .await
+            .unwrap();
+        assert_eq!(session.next_incoming_id, 1);
+    }
+
+    #[tokio::test]
+    async fn flow_acknowledging_unsent_transfers_is_rejected() {
+        let mut session = new_session(3);
+        session.remote_incoming_window = 7;
+
+        let result = session.on_incoming_flow(session_flow(Some(5), 0)).await;
+        assert!(matches!(result, Err(SessionInnerError::InvalidFlow)));
+        assert_eq!(session.remote_incoming_window, 7);
+
+        session
+            .on_incoming_flow(session_flow(Some(3), 0))
+            .await
+            .unwrap();
+        assert_eq!(session.remote_incoming_window, 100);
+    }
+
+    #[tokio::test]
+    async fn remote_incoming_window_across_transfer_id_wrap_around() {
+        let mut session = new_session(u32::MAX - 5);
+
+        // Eight transfers are sent and the next-outgoing-id wraps around to 2
+        session.next_outgoing_id = 2.into();
+
+        session
+            .on_incoming_flow(session_flow(Some(u32::MAX - 1), 0))
+            .await
+            .unwrap();
+        assert_eq!(session.remote_incoming_window, 96);
+
+        session
+            .on_incoming_flow(session_flow(Some(1), 0))
+            .await
+            .unwrap();
+        assert_eq!(session.remote_incoming_window, 99);
+
+        // Without next-incoming-id, the transfers in flight are counted from the
+        // initial-outgoing-id
+        session
+            .on_incoming_flow(session_flow(None, 0))
+            .await
+            .unwrap();
+        assert_eq!(session.remote_incoming_window, 92);
+    }
+
+    #[tokio::test]
+    async fn session_flow_with_transfer_ids_around_wrap_around() {
+        // Transfer-ids around the start and the end of the serial number space and around half
+        // of it
+        let ids = [0, 1, 2, 1 << 31, (1 << 31) + 1, u32::MAX - 1, u32::MAX];
+        let flow_next_incoming_ids = std::iter::once(None).chain(ids.into_iter().map(Some));
+        for initial_outgoing_id in ids {
+            for flow_next_incoming_id in flow_next_incoming_ids.clone() {
+                for incoming_window in [0, 1, u32::MAX] {
+                    let mut session = new_session(initial_outgoing_id);
+                    session.next_outgoing_id =
+                        SerialNumber::from(initial_outgoing_id).wrapping_add(3);
+                    let mut flow = session_flow(flow_next_incoming_id, 0);
+                    flow.incoming_window = incoming_window;
+                    let result = session.on_incoming_flow(flow).await;
+
+                    // The peer cannot have received more transfers than sent
+                    let in_flight = flow_next_incoming_id
+                        .map(SerialNumber::from)
+                        .unwrap_or(SerialNumber::from(initial_outgoing_id))
+                        .distance_to(session.next_outgoing_id);
+                    match in_flight {
+                        Ok(in_flight) => {
+                            assert!(result.is_ok());
+                            assert_eq!(
+                                session.remote_incoming_window,
+                                incoming_window.saturating_sub(in_flight)
+                            );
+                        }
+                        Err(_) => assert!(matches!(result, Err(SessionInnerError::InvalidFlow))),
+                    }
+                }
+            }
+        }
+
+        // The next-outgoing-id of the peer cannot go back before the transfers received
+        for next_incoming_id in ids {
+            for flow_next_outgoing_id in ids {
+                let mut session = new_session(0);
+                session.next_incoming_id = next_incoming_id.into();
+                let result = session
+                    .on_incoming_flow(session_flow(Some(0), flow_next_outgoing_id))
+                    .await;
+                let received =
+                    SerialNumber::from(next_incoming_id).distance_to(flow_next_outgoing_id.into());
+                match received {
+                    Ok(_) => {
+                        assert!(result.is_ok());
+                        assert_eq!(session.next_incoming_id, flow_next_outgoing_id);
+                    }
+                    Err(_) => {
+                        assert!(matches!(result, Err(SessionInnerError::InvalidFlow)));
+                        assert_eq!(session.next_incoming_id, next_incoming_id);
+                    }
+                }
+            }
+        }
+    }
+
+    fn new_receiver_relay() -> LinkRelay<()> {
+        new_receiver_relay_with_rx().0
+    }
+
+    fn new_receiver_relay_with_rx() -> (LinkRelay<()>, mpsc::Receiver<LinkIncomingItem>) {
+        let (relay, rx) = receiver_relay(0);
+        let relay = match relay {
+            LinkRelay::Receiver {
+                tx,
+                flow_state,
+                unsettled,
+                remote_settlements,
+                receiver_settle_mode,
+                more,
+                ..
+            } => LinkRelay::Receiver {
+                tx,
+                output_handle: (),
+                flow_state,
+                unsettled,
+                remote_settlements,
+                receiver_settle_mode,
+                more,
+            },
+            LinkRelay::Sender { .. } => unreachable!(),
+        };
+        (relay, rx)
+    }
+
+    fn detach(output_handle: OutputHandle, closed: bool) -> Detach {
+        Detach {
+            handle: output_handle.into(),
+            closed,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn duplicated_link_name_is_rejected() {
+        let mut session = new_session(0);
+        session.allocate_link("link".to_string(), None).unwrap();
+
+        let result = session.allocate_link("link".to_string(), None);
+        assert!(matches!(result, Err(AllocLinkError::DuplicatedLinkName)));
+        session.allocate_link("other".to_string(), None).unwrap();
+    }
+
+    #[test]
+    fn detached_link_name_is_reserved_until_resumed() {
+        let mut session = new_session(0);
+        let handle = session.allocate_link("link".to_string(), None).unwrap();
+        session.on_outgoing_detach(detach(handle, false));
+
+        let result = session.allocate_link("link".to_string(), None);
+        assert!(matches!(result, Err(AllocLinkError::DuplicatedLinkName)));
+
+        // Resuming takes the reserved name, and closing releases it
+        let handle = session
+            .reallocate_link("link".to_string(), new_receiver_relay())
+            .unwrap();
+        session.on_outgoing_detach(detach(handle, true));
+        session.allocate_link("link".to_string(), None).unwrap();
+    }
+
+    #[test]
+    fn released_link_name_can_be_reused() {
+        let mut session = new_session(0);
+        let handle = session.allocate_link("link".to_string(), None).unwrap();
+        session.on_outgoing_detach(detach(handle, false));
+
+        session.release_link_name("link");
+        session.allocate_link("link".to_string(), None).unwrap();
+    }
+
+    fn remote_attach(name: &str, handle: u32) -> Attach {
+        Attach {
+            name: name.to_string(),
+            handle: Handle(handle),
+            role: Role::Sender,
+            snd_settle_mode: Default::default(),
+            rcv_settle_mode: Default::default(),
+            source: None,
+            target: None,
+            unsettled: None,
+            incomplete_unsettled: false,
+            initial_delivery_count: Some(0),
+            max_message_size: None,
+            offered_capabilities: None,
+            desired_capabilities: None,
+            properties: None,
+        }
+    }
+
+    #[test]
+    fn links_are_limited_by_handle_max() {
+        let mut session = new_session(0);
+        session.handle_max = Handle(1);
+        let first = session.allocate_link("first".to_string(), None).unwrap();
+        session.allocate_link("second".to_string(), None).unwrap();
+
+        let result = session.allocate_link("third".to_string(), None);
+        assert!(matches!(result, Err(AllocLinkError::HandleMaxReached)));
+
+        // The handle of a closed link is available again
+        session.on_outgoing_detach(detach(first, true));
+        session.allocate_link("third".to_string(), None).unwrap();
+
+        // The handle-max of the remote peer applies as well
+        let mut session = new_session(0);
+        session.remote_handle_max = Handle(0);
+        session.allocate_link("first".to_string(), None).unwrap();
+        let result = session.allocate_link("second".to_string(), None);
+        assert!(matches!(result, Err(AllocLinkError::HandleMaxReached)));
+    }
+
+    #[tokio::test]
+    async fn replies_to_abandoned_attach_are_absorbed() {
+        let mut session = new_session(0);
+        let (relay, _abandoned_rx) = new_receiver_relay_with_rx();
+        let handle = session
+            .allocate_link("link".to_string(), Some(relay))
+            .unwrap();
+        // The link is closed before the remote Attach arrives, and the name is taken again
+        session.on_outgoing_detach(detach(handle, true));
+        let (relay, mut rx) = new_receiver_relay_with_rx();
+        session
+            .allocate_link("link".to_string(), Some(relay))
+            .unwrap();
+
+        session
+            .on_incoming_attach(remote_attach("link", 7))
+            .await
+            .unwrap();
+        session
+            .on_incoming_detach(detach(OutputHandle(7), true))
+            .await
+            .unwrap();
+        assert!(rx.try_recv().is_err());
+
+        session
+            .on_incoming_attach(remote_attach("link", 8))
+            .await
+            .unwrap();
+        assert!(matches!(rx.try_recv(), Ok(LinkFrame::Attach(attach)) if attach.handle.0 == 8));
+        assert!(session.abandoned_links.is_empty());
+    }
+
+    #[test]
+    fn ended_session_closes_the_channels_of_its_links() {
+        let mut session = new_session(0);
+        let (mut attached, _) = sender_relay(0, ReceiverSettleMode::First);
+        let (mut attaching, _) = sender_relay(1, ReceiverSettleMode::First);
+        let (attached_tx, mut attached_rx) = mpsc::channel(1);
+        let (attaching_tx, mut attaching_rx) = mpsc::channel(1);
+        for (relay, new_tx) in [(&mut attached, attached_tx), (&mut attaching, attaching_tx)] {
+            if let LinkRelay::Sender { tx, .. } = relay {
+                *tx = new_tx;
+            }
+        }
+        // The channel of the attached link is full, so it cannot be told that the session ended
+        attached
+            .try_send(LinkFrame::Detach(detach(OutputHandle(0), false)))
+            .unwrap();
+        session
+            .link_by_input_handle
+            .insert(InputHandle(0), attached);
+        session
+            .link_by_name
+            .insert(String::from("attaching"), Some(attaching));
+
+        session.end_error = Some(Error::new(AmqpError::InternalError, None, None));
+        session.notify_links_ended();
+
+        assert!(matches!(attached_rx.try_recv(), Ok(LinkFrame::Detach(_))));
+        assert!(matches!(
+            attached_rx.try_recv(),
+            Err(mpsc::error::TryRecvError::Disconnected)
+        ));
+        assert!(matches!(
+            attaching_rx.try_recv(),
+            Ok(LinkFrame::SessionEnded(Some(_)))
+        ));
+        assert!(matches!(
+            attaching_rx.try_recv(),
+            Err(mpsc::error::TryRecvError::Disconnected)
+        ));
+    }
 
     #[test]
     fn number_of_message_settled_by_disposition() {
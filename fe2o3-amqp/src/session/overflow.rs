@@ -0,0 +1,193 @@
+//! Incoming frames of links whose channel is full
+//!
+//! The session event loop relays the incoming frames to the links in the order they arrive. A
+//! link whose application task is not polling it would otherwise block the event loop once its
+//! channel is full, which delays the frames of every other link and of the session itself. The
+//! frames of such a link are kept here instead, and are relayed in order once its channel has
+//! capacity again.
+//!
+//! The number of frames kept for a link is bounded by the link credit it has issued, and their
+//! payload by the incoming buffer limit of the session.
+
+use std::{collections::VecDeque, future::Future, pin::Pin};
+
+use futures_util::{stream::FuturesUnordered, FutureExt, StreamExt};
+use tokio::sync::mpsc::{
+    self,
+    error::{SendError, TrySendError},
+    OwnedPermit,
+};
+
+use crate::link::{LinkFrame, LinkRelayError};
+
+/// A slot in the channel of a link, along with the channel it is reserved in
+type Capacity = (
+    mpsc::Sender<LinkFrame>,
+    Result<OwnedPermit<LinkFrame>, SendError<()>>,
+);
+
+// The session endpoint is required to be `Sync`, so the reservation has to be as well
+type Reserve = Pin<Box<dyn Future<Output = Capacity> + Send + Sync>>;
+
+/// The frames that are waiting for capacity in the channel of a link
+#[derive(Debug)]
+struct Parked {
+    tx: mpsc::Sender<LinkFrame>,
+    frames: VecDeque<LinkFrame>,
+}
+
+/// Incoming frames of the links whose channel is full
+#[derive(Default)]
+pub(crate) struct LinkOverflow {
+    // A link is identified by its channel rather than by its input handle, which is reused by
+    // another link once the remote peer has detached it
+    parked: Vec<Parked>,
+    capacity: FuturesUnordered<Reserve>,
+}
+
+impl std::fmt::Debug for LinkOverflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LinkOverflow")
+            .field("parked", &self.parked)
+            .finish()
+    }
+}
+
+fn reserve(tx: mpsc::Sender<LinkFrame>) -> Reserve {
+    let permit = tx.clone().reserve_owned();
+    Box::pin(permit.map(move |permit| (tx, permit)))
+}
+
+impl LinkOverflow {
+    /// Sends the frame to the link without waiting. The frame is kept if the channel is full or
+    /// if earlier frames of the link are still waiting, so that the frames keep their order
+    pub(crate) fn send(
+        &mut self,
+        tx: &mpsc::Sender<LinkFrame>,
+        frame: LinkFrame,
+    ) -> Result<(), LinkRelayError> {
+        if let Some(parked) = self.parked.iter_mut().find(|p| p.tx.same_channel(tx)) {
+            parked.frames.push_back(frame);
+            return Ok(());
+        }
+
+        match tx.try_send(frame) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(frame)) => {
+                self.parked.push(Parked {
+                    tx: tx.clone(),
+                    frames: VecDeque::from([frame]),
+                });
+                self.capacity.push(reserve(tx.clone()));
+                Ok(())
+            }
+            Err(TrySendError::Closed(_)) => Err(LinkRelayError::UnattachedHandle),
+        }
+    }
+
+    /// Number of frames that are waiting for capacity in the channels of the links
+    pub(crate) fn parked_frames(&self) -> usize {
+        self.parked.iter().map(|parked| parked.frames.len()).sum()
+    }
+
+    /// Waits until the channel of a link with waiting frames has capacity, and relays as many of
+    /// its frames as the channel takes. This never completes if no frame is waiting.
+    ///
+    /// This is cancel safe because the channel capacity is only taken once it is available.
+    pub(crate) async fn relay(&mut self) {
+        let (tx, permit) = match self.capacity.next().await {
+            Some(capacity) => capacity,
+            None => return std::future::pending().await,
+        };
+        let index = match self.parked.iter().position(|p| p.tx.same_channel(&tx)) {
+            Some(index) => index,
+            None => return,
+        };
+        let permit = match permit {
+            Ok(permit) => permit,
+            Err(_) => {
+                // The link has been dropped
+                self.parked.swap_remove(index);
+                return;
+            }
+        };
+
+        let parked = &mut self.parked[index];
+        if let Some(frame) = parked.frames.pop_front() {
+            permit.send(frame);
+        }
+        while let Some(frame) = parked.frames.pop_front() {
+            match parked.tx.try_send(frame) {
+                Ok(()) => {}
+                Err(TrySendError::Full(frame)) => {
+                    parked.frames.push_front(frame);
+                    self.capacity.push(reserve(parked.tx.clone()));
+                    return;
+                }
+                Err(TrySendError::Closed(_)) => break,
+            }
+        }
+        self.parked.swap_remove(index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fe2o3_amqp_types::performatives::Detach;
+    use tokio::sync::mpsc;
+
+    use crate::link::LinkFrame;
+
+    use super::LinkOverflow;
+
+    fn detach(handle: u32) -> LinkFrame {
+        LinkFrame::Detach(Detach {
+            handle: handle.into(),
+            closed: false,
+            error: None,
+        })
+    }
+
+    fn handle_of(frame: LinkFrame) -> u32 {
+        match frame {
+            LinkFrame::Detach(detach) => detach.handle.0,
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn full_link_does_not_block_other_links() {
+        let mut overflow = LinkOverflow::default();
+        let (stalled_tx, mut stalled_rx) = mpsc::channel(1);
+        let (active_tx, mut active_rx) = mpsc::channel(1);
+
+        for handle in 0..3 {
+            overflow.send(&stalled_tx, detach(handle)).unwrap();
+        }
+        overflow.send(&active_tx, detach(10)).unwrap();
+        assert_eq!(overflow.parked.len(), 1);
+        assert_eq!(handle_of(active_rx.try_recv().unwrap()), 10);
+
+        // The waiting frames are relayed in order as the stalled link takes its frames
+        for handle in 0..3 {
+            assert_eq!(handle_of(stalled_rx.recv().await.unwrap()), handle);
+            if handle < 2 {
+                overflow.relay().await;
+            }
+        }
+        assert!(overflow.parked.is_empty());
+    }
+
+    #[tokio::test]
+    async fn frames_of_dropped_link_are_discarded() {
+        let mut overflow = LinkOverflow::default();
+        let (tx, rx) = mpsc::channel(1);
+        overflow.send(&tx, detach(0)).unwrap();
+        overflow.send(&tx, detach(1)).unwrap();
+
+        drop(rx);
+        overflow.relay().await;
+        assert!(overflow.parked.is_empty());
+        assert!(overflow.send(&tx, detach(2)).is_err());
+    }
+}
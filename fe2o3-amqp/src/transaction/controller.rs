@@ -56,7 +56,7 @@ where
         .send_with_state::<T, link::SendError>(sendable, None, false)
         .await?
     {
-        Settlement::Settled(_) => Err(SendError::IllegalDeliveryState),
+        Settlement::Settled(_) | Settlement::Unconfirmed(_) => Err(SendError::IllegalDeliveryState),
         Settlement::Unsettled {
             delivery_tag: _,
             outcome,
@@ -64,6 +64,22 @@ where
     }
 }
 
+/// Waits for the outcome of a message sent on the control link, which fails if the control link
+/// is detached or the session ends before the outcome arrives
+async fn outcome_on_control_link(
+    sender: &mut SenderInner<ControlLink>,
+    outcome: oneshot::Receiver<Option<DeliveryState>>,
+) -> Result<Option<DeliveryState>, LinkStateError> {
+    let SenderInner {
+        link,
+        outgoing,
+        incoming,
+        ..
+    } = sender;
+    link.outcome_or_detached(outgoing, outcome, incoming.recv())
+        .await
+}
+
 impl Controller {
     /// Creates a new builder for controller
     pub fn builder() -> link::builder::Builder<
@@ -131,10 +147,10 @@ impl Controller {
         // the outcome of the declare from the receiver
         let sendable = Sendable::builder().message(message).settled(false).build();
 
-        send_on_control_link(&mut *self.inner.lock().await, sendable)
+        let mut inner = self.inner.lock().await;
+        let outcome = send_on_control_link(&mut inner, sendable).await?;
+        outcome_on_control_link(&mut inner, outcome)
             .await?
-            .await
-            .map_err(|_| LinkStateError::IllegalSessionState)?
             .ok_or(ControllerSendError::NonTerminalDeliveryState)?
             .declared_or_else(|state| {
                 if let DeliveryState::Rejected(rejected) = state {
@@ -159,10 +175,10 @@ impl Controller {
         let message = Message::builder().value(discharge).build();
         let sendable = Sendable::builder().message(message).settled(false).build();
 
-        send_on_control_link(&mut *self.inner.lock().await, sendable)
+        let mut inner = self.inner.lock().await;
+        let outcome = send_on_control_link(&mut inner, sendable).await?;
+        outcome_on_control_link(&mut inner, outcome)
             .await?
-            .await
-            .map_err(|_| LinkStateError::IllegalSessionState)?
             .ok_or(ControllerSendError::NonTerminalDeliveryState)?
             .accepted_or_else(|state| {
                 if let DeliveryState::Rejected(rejected) = state {
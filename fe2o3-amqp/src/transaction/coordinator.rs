@@ -19,7 +19,7 @@ use crate::{
         delivery::DeliveryInfo,
         receiver::ReceiverInner,
         shared_inner::{LinkEndpointInner, LinkEndpointInnerDetach},
-        IllegalLinkStateError, LinkFrame, ReceiverAttachError, ReceiverLink, RecvError,
+        DispositionError, LinkFrame, ReceiverAttachError, ReceiverLink, RecvError,
     },
     util::{Initialized, Running},
     Delivery,
@@ -178,7 +178,8 @@ impl TxnCoordinator {
                     let _ = self.inner.close_with_error(Some(error)).await;
                     Running::Stop
                 }
-                crate::link::LinkStateError::IllegalSessionState => {
+                crate::link::LinkStateError::IllegalSessionState
+                | crate::link::LinkStateError::SessionEnded(_) => {
                     #[cfg(feature = "tracing")]
                     tracing::error!(?error);
                     #[cfg(feature = "log")]
@@ -237,6 +238,14 @@ impl TxnCoordinator {
                 let _ = self.inner.close_with_error(Some(error)).await;
                 Running::Stop
             }
+            RecvError::SettlementTimeout => {
+                // Only the settlement of the delivery is lost
+                #[cfg(feature = "tracing")]
+                tracing::error!(?error);
+                #[cfg(feature = "log")]
+                log::error!("error = {:?}", error);
+                Running::Continue
+            }
         }
     }
 
@@ -282,16 +291,22 @@ impl TxnCoordinator {
         match disposition_result {
             Ok(_) => Running::Continue,
             Err(disposition_error) => match disposition_error {
-                IllegalLinkStateError::IllegalState => {
+                DispositionError::IllegalState => {
                     let error = definitions::Error::new(AmqpError::IllegalState, None, None);
                     // TODO: detach instead of closing
                     let _ = self.inner.close_with_error(Some(error)).await;
                     Running::Stop
                 }
-                IllegalLinkStateError::IllegalSessionState => {
+                DispositionError::IllegalSessionState => {
                     // Session must have already dropped
                     Running::Stop
                 }
+                // The dispositions are sent settled and never wait for the controller to settle
+                DispositionError::Detached | DispositionError::SettlementTimeout => Running::Stop,
+                // Only the delivery being disposed is affected
+                DispositionError::AlreadySettled(_) | DispositionError::LinkMismatch(_) => {
+                    Running::Continue
+                }
             },
         }
     }
@@ -301,7 +316,7 @@ impl TxnCoordinator {
         delivery_info: DeliveryInfo,
         error: TransactionError,
         description: impl Into<Option<String>>,
-    ) -> Result<(), IllegalLinkStateError> {
+    ) -> Result<(), DispositionError> {
         let error = definitions::Error::new(error, description, None);
         let state = DeliveryState::Rejected(Rejected { error: Some(error) });
 
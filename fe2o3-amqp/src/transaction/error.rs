@@ -2,7 +2,8 @@ use fe2o3_amqp_types::messaging::{Accepted, DeliveryState, Outcome, Rejected};
 
 use crate::link::{
     delivery::{FromDeliveryState, FromOneshotRecvError, FromPreSettled},
-    DetachError, IllegalLinkStateError, LinkStateError, SendError, SenderAttachError,
+    CreditTimeout, DetachError, IllegalLinkStateError, LinkStateError, SendError,
+    SenderAttachError, ValidationError,
 };
 
 /// Errors with allocation of new transacation ID
@@ -96,10 +97,10 @@ pub enum ControllerSendError {
 
     /// The remote peer detached with error
     #[error("Link is detached {:?}", .0)]
-    Detached(DetachError),
+    Detached(#[from] DetachError),
 
     /// The message was rejected
-    #[error("Outcome Rejected: {:?}", .0)]
+    #[error("Outcome {}", .0)]
     Rejected(Rejected),
 
     /// A non-terminal delivery state is received while expecting
@@ -113,7 +114,19 @@ pub enum ControllerSendError {
 
     /// Error serializing message
     #[error("Error encoding message")]
-    MessageEncodeError,
+    MessageEncodeError(#[from] serde_amqp::Error),
+
+    /// No link credit was issued within the duration set with `fail_after_no_credit`
+    #[error("No link credit was issued in time")]
+    CreditTimeout,
+
+    /// The message failed a validator of the sender
+    #[error("Message failed validation: {0}")]
+    Validation(ValidationError),
+
+    /// The send is cancelled
+    #[error("The send is cancelled")]
+    Cancelled,
 }
 
 impl From<SendError> for ControllerSendError {
@@ -123,7 +136,12 @@ impl From<SendError> for ControllerSendError {
             SendError::Detached(value) => Self::Detached(value),
             SendError::NonTerminalDeliveryState => Self::NonTerminalDeliveryState,
             SendError::IllegalDeliveryState => Self::IllegalDeliveryState,
-            SendError::MessageEncodeError => Self::MessageEncodeError,
+            SendError::MessageEncodeError(error) => Self::MessageEncodeError(error),
+            SendError::Rejected(rejected) => Self::Rejected(rejected),
+            SendError::UndeclaredOutcome(_) => Self::IllegalDeliveryState,
+            SendError::CreditTimeout => Self::CreditTimeout,
+            SendError::Validation(error) => Self::Validation(error),
+            SendError::Cancelled => Self::Cancelled,
         }
     }
 }
@@ -188,7 +206,7 @@ pub enum PostError {
 
     /// The remote peer detached with error
     #[error("Link is detached {:?}", .0)]
-    Detached(DetachError),
+    Detached(#[from] DetachError),
 
     /// A non-terminal delivery state is received while expecting
     /// an outcome
@@ -201,18 +219,28 @@ pub enum PostError {
 
     /// Error serializing message
     #[error("Error encoding message")]
-    MessageEncodeError,
-}
+    MessageEncodeError(#[from] serde_amqp::Error),
 
-impl From<serde_amqp::Error> for PostError {
-    fn from(_: serde_amqp::Error) -> Self {
-        Self::MessageEncodeError
-    }
+    /// The transaction could not be discharged, eg. because the control link is detached
+    #[error("The transaction could not be discharged")]
+    NotDischarged,
+
+    /// No link credit was issued within the duration set with `fail_after_no_credit`
+    ///
+    /// The message is not sent and the link is still attached
+    #[error("No link credit was issued in time")]
+    CreditTimeout,
+
+    /// The message failed a validator set with `send_validator`
+    ///
+    /// The message is not sent and no link credit is consumed
+    #[error("Message failed validation: {0}")]
+    Validation(#[from] ValidationError),
 }
 
-impl From<DetachError> for PostError {
-    fn from(error: DetachError) -> Self {
-        Self::Detached(error)
+impl From<CreditTimeout> for PostError {
+    fn from(_: CreditTimeout) -> Self {
+        Self::CreditTimeout
     }
 }
 
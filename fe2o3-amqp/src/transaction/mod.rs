@@ -68,6 +68,10 @@ use tokio::sync::{mpsc::error::TryRecvError, oneshot};
 mod owned;
 pub use owned::*;
 
+mod posting;
+pub(crate) use posting::PostedDeliveries;
+pub use posting::{TransactionalPosting, TxnDelivery};
+
 pub(crate) mod control_link_frame;
 
 cfg_acceptor! {
@@ -243,6 +247,21 @@ pub trait TransactionExt: TransactionDischarge + TransactionalRetirement {
 /// sender.close().await.unwrap();
 /// ```
 ///
+/// ## Transactional posting with outcomes resolved on discharge
+///
+/// Messages posted with [`Sender::send_txn`] are tracked by the transaction. `commit()` resolves
+/// them to the outcomes reported by the resource once the transaction is discharged, and
+/// `rollback()` resolves them as `Released`.
+///
+/// ```rust,ignore
+/// let mut txn = Transaction::declare(&controller, None).await.unwrap();
+/// let hello = sender.send_txn(&mut txn, "hello").await.unwrap();
+/// let world = sender.send_txn(&mut txn, "world").await.unwrap();
+/// txn.commit().await.unwrap();
+/// assert!(hello.await.unwrap().is_accepted());
+/// assert!(world.await.unwrap().is_accepted());
+/// ```
+///
 /// ## Transactional retirement
 ///
 /// ```rust,ignore
@@ -289,6 +308,7 @@ pub struct Transaction<'t> {
     controller: &'t Controller,
     declared: Declared,
     is_discharged: bool,
+    posted: PostedDeliveries,
 }
 
 
@@ -301,10 +321,16 @@ impl<'t> TransactionDischarge for Transaction<'t> {
 
     async fn discharge(&mut self, fail: bool) -> Result<(), Self::Error> {
         if !self.is_discharged {
-            self.controller
+            let result = self
+                .controller
                 .discharge(self.declared.txn_id.clone(), fail)
-                .await?;
+                .await;
+            if let Err(error) = result {
+                self.posted.on_discharge_error();
+                return Err(error.into());
+            }
             self.is_discharged = true;
+            self.posted.on_discharged(fail).await;
         }
         Ok(())
     }
@@ -344,6 +370,12 @@ impl<'t> TransactionExt for Transaction<'t> {
     }
 }
 
+impl<'t> TransactionalPosting for Transaction<'t> {
+    fn track_posted(&mut self, delivery: DeliveryFut<Result<Outcome, PostError>>) -> TxnDelivery {
+        self.posted.track(delivery)
+    }
+}
+
 impl<'t> Transaction<'t> {
     /// Declares a transaction with a default controller
     ///
@@ -357,6 +389,7 @@ impl<'t> Transaction<'t> {
             controller,
             declared,
             is_discharged: false,
+            posted: PostedDeliveries::default(),
         })
     }
 
@@ -560,7 +593,7 @@ impl<'t> Drop for Transaction<'t> {
                     };
                     // let tag = self.flow_state.state().delivery_count().await.to_be_bytes();
                     let tag = match inner.link.flow_state.state().lock.try_read() {
-                        Some(inner) => inner.delivery_count.to_be_bytes(),
+                        Some(inner) => u32::from(inner.delivery_count).to_be_bytes(),
                         None => return,
                     };
                     let delivery_tag = DeliveryTag::from(tag);
@@ -596,6 +629,7 @@ impl<'t> Drop for Transaction<'t> {
                         input_handle,
                         performative: transfer,
                         payload,
+                        incoming_permit: None,
                     };
                     if inner.outgoing.try_send(frame).is_err() {
                         // Channel is already closed
@@ -606,7 +640,7 @@ impl<'t> Drop for Transaction<'t> {
                     // The transfer is sent unsettled and will be
                     // inserted into
                     let (tx, mut rx) = oneshot::channel();
-                    let unsettled = UnsettledMessage::new(payload_copy, None, MESSAGE_FORMAT, tx);
+                    let unsettled = UnsettledMessage::new(payload_copy, None, MESSAGE_FORMAT, Some(tx));
                     {
                         let mut guard = match inner.link.unsettled.try_write() {
                             Some(guard) => guard,
@@ -21,7 +21,8 @@ use crate::{
 
 use super::{
     Controller, ControllerSendError, OwnedDeclareError, OwnedDischargeError, PostError,
-    TransactionDischarge, TransactionExt, TransactionalRetirement, TxnAcquisition, TXN_ID_KEY,
+    PostedDeliveries, TransactionDischarge, TransactionExt, TransactionalPosting,
+    TransactionalRetirement, TxnAcquisition, TxnDelivery, TXN_ID_KEY,
 };
 
 /// An owned transaction that has exclusive access to its own control link.
@@ -87,6 +88,7 @@ pub struct OwnedTransaction {
     controller: Controller,
     declared: Declared,
     is_discharged: bool,
+    posted: PostedDeliveries,
 }
 
 
@@ -99,10 +101,16 @@ impl TransactionDischarge for OwnedTransaction {
 
     async fn discharge(&mut self, fail: bool) -> Result<(), Self::Error> {
         if !self.is_discharged {
-            self.controller
+            let result = self
+                .controller
                 .discharge(self.declared.txn_id.clone(), fail)
-                .await?;
+                .await;
+            if let Err(error) = result {
+                self.posted.on_discharge_error();
+                return Err(error.into());
+            }
             self.is_discharged = true;
+            self.posted.on_discharged(fail).await;
         }
         Ok(())
     }
@@ -154,6 +162,12 @@ impl TransactionExt for OwnedTransaction {
     }
 }
 
+impl TransactionalPosting for OwnedTransaction {
+    fn track_posted(&mut self, delivery: DeliveryFut<Result<Outcome, PostError>>) -> TxnDelivery {
+        self.posted.track(delivery)
+    }
+}
+
 impl OwnedTransaction {
     /// Declare an transaction with an owned control link
     pub async fn declare<R>(
@@ -177,6 +191,7 @@ impl OwnedTransaction {
             controller,
             declared,
             is_discharged: false,
+            posted: PostedDeliveries::default(),
         })
     }
 
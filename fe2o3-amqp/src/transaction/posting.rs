@@ -0,0 +1,95 @@
+//! Messages posted in a transaction whose outcomes are resolved when it is discharged
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use fe2o3_amqp_types::messaging::{Outcome, Released};
+use futures_util::FutureExt;
+use tokio::sync::oneshot;
+
+use crate::link::delivery::DeliveryFut;
+
+use super::{PostError, TransactionExt};
+
+type PostResult = Result<Outcome, PostError>;
+
+/// Transactions that track the messages posted with
+/// [`Sender::send_txn`](crate::Sender::send_txn)
+pub trait TransactionalPosting: TransactionExt {
+    /// Tracks a message posted in the transaction until the transaction is discharged
+    fn track_posted(&mut self, delivery: DeliveryFut<Result<Outcome, PostError>>) -> TxnDelivery;
+}
+
+/// A message posted with [`Sender::send_txn`](crate::Sender::send_txn), which resolves once the
+/// transaction is discharged
+///
+/// The outcome reported by the resource is returned if the transaction is committed. `Released`
+/// is returned if the transaction is rolled back, which includes dropping the transaction without
+/// discharging it. [`PostError::NotDischarged`] is returned if the transaction could not be
+/// discharged, eg. because the control link is detached.
+#[derive(Debug)]
+pub struct TxnDelivery {
+    outcome: oneshot::Receiver<Result<Outcome, PostError>>,
+}
+
+impl Future for TxnDelivery {
+    type Output = Result<Outcome, PostError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.outcome.poll_unpin(cx) {
+            Poll::Ready(Ok(result)) => Poll::Ready(result),
+            // The transaction is rolled back when it is dropped without being discharged
+            Poll::Ready(Err(_)) => Poll::Ready(Ok(Outcome::Released(Released {}))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// The messages posted in a transaction that are not resolved yet
+#[derive(Default)]
+pub(crate) struct PostedDeliveries {
+    pending: Vec<(DeliveryFut<PostResult>, oneshot::Sender<PostResult>)>,
+}
+
+impl std::fmt::Debug for PostedDeliveries {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PostedDeliveries")
+            .field("pending", &self.pending.len())
+            .finish()
+    }
+}
+
+impl PostedDeliveries {
+    pub(crate) fn track(
+        &mut self,
+        delivery: DeliveryFut<Result<Outcome, PostError>>,
+    ) -> TxnDelivery {
+        let (tx, outcome) = oneshot::channel();
+        self.pending.push((delivery, tx));
+        TxnDelivery { outcome }
+    }
+
+    /// Resolves the posted messages once the transaction is discharged
+    ///
+    /// The outcomes reported by the resource take effect if the transaction is committed, and
+    /// the messages are released if it is rolled back
+    pub(crate) async fn on_discharged(&mut self, fail: bool) {
+        for (delivery, tx) in self.pending.drain(..) {
+            let result = match fail {
+                true => Ok(Outcome::Released(Released {})),
+                false => delivery.await,
+            };
+            let _ = tx.send(result);
+        }
+    }
+
+    /// Fails the posted messages if the transaction could not be discharged
+    pub(crate) fn on_discharge_error(&mut self) {
+        for (_, tx) in self.pending.drain(..) {
+            let _ = tx.send(Err(PostError::NotDischarged));
+        }
+    }
+}
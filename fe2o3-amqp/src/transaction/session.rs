@@ -13,10 +13,12 @@ use uuid::Uuid;
 use crate::{
     control::SessionControl,
     endpoint::{self, IncomingChannel, InputHandle, LinkFlow, OutgoingChannel, OutputHandle},
+    introspect::{LinkSummary, RoutingRecord},
     link::{target_archetype::VariantOfTargetArchetype, LinkRelay},
     session::{
         self,
         frame::{SessionFrame, SessionOutgoingItem},
+        incoming_budget::IncomingPermit,
     },
     Payload,
 };
@@ -103,7 +105,7 @@ where
         let control = self.control.clone();
         let outgoing = self.txn_manager.control_link_outgoing.clone();
 
-        tokio::spawn(async move {
+        crate::rt::spawn(async move {
             // Error accepting new control link is handled by acceptor
             if let Ok(coordinator) = acceptor
                 .accept_incoming_attach(remote_attach, control, outgoing)
@@ -164,7 +166,7 @@ where
 
                     // Committing shuold never need to send an immediate disposition
                     if let Some(disposition) =
-                        self.session.on_incoming_transfer(transfer, payload).await?
+                        self.session.on_incoming_transfer(transfer, payload, None).await?
                     {
                         self.control
                             .send(SessionControl::Disposition(disposition))
@@ -237,6 +239,14 @@ where
         self.session.allocate_link(link_name, link_relay)
     }
 
+    fn reallocate_link(
+        &mut self,
+        link_name: String,
+        link_relay: LinkRelay<()>,
+    ) -> Result<OutputHandle, Self::AllocError> {
+        self.session.reallocate_link(link_name, link_relay)
+    }
+
     fn allocate_incoming_link(
         &mut self,
         link_name: String,
@@ -251,6 +261,26 @@ where
         self.session.deallocate_link(output_handle)
     }
 
+    fn release_link_name(&mut self, link_name: &str) {
+        self.session.release_link_name(link_name)
+    }
+
+    fn notify_links_ended(&mut self) {
+        self.session.notify_links_ended()
+    }
+
+    fn link_summaries(&self) -> Vec<LinkSummary> {
+        self.session.link_summaries()
+    }
+
+    fn incomplete_incoming_bytes(&self) -> usize {
+        self.session.incomplete_incoming_bytes()
+    }
+
+    fn routing_history(&self) -> Vec<RoutingRecord> {
+        self.session.routing_history()
+    }
+
     fn on_incoming_begin(
         &mut self,
         channel: IncomingChannel,
@@ -288,6 +318,7 @@ where
         &mut self,
         transfer: Transfer,
         payload: Payload,
+        permit: Option<IncomingPermit>,
     ) -> Result<Option<Disposition>, Self::Error> {
         let (txn, txn_id) = match &transfer.state {
             Some(DeliveryState::TransactionalState(state)) => {
@@ -298,7 +329,12 @@ where
                     .map(|txn| (txn, txn_id.clone()))
                     .ok_or(S::Error::UnknownTxnId)?
             }
-            Some(_) | None => return self.session.on_incoming_transfer(transfer, payload).await,
+            Some(_) | None => {
+                return self
+                    .session
+                    .on_incoming_transfer(transfer, payload, permit)
+                    .await
+            }
         };
 
         Ok(txn.on_incoming_post(txn_id, transfer, payload))
@@ -338,6 +374,10 @@ where
         self.session.on_incoming_end(channel, end)
     }
 
+    async fn relay_overflow(&mut self) {
+        self.session.relay_overflow().await
+    }
+
     // Handling SessionFrames
     async fn send_begin(
         &mut self,
@@ -363,6 +403,10 @@ where
         self.session.on_outgoing_flow(flow)
     }
 
+    fn replenish_incoming_window(&mut self) -> Option<SessionFrame> {
+        self.session.replenish_incoming_window()
+    }
+
     fn on_outgoing_transfer(
         &mut self,
         input_handle: InputHandle,
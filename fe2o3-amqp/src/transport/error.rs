@@ -1,9 +1,18 @@
 use std::io;
 
 use bytes::Bytes;
-use fe2o3_amqp_types::{primitives::Binary, sasl::SaslCode};
-
-use crate::{frames, sasl_profile};
+use fe2o3_amqp_types::{
+    primitives::{Binary, Symbol},
+    sasl::SaslCode,
+};
+
+use crate::{
+    frames::{
+        self,
+        amqp::{InvalidFrame, InvalidFrameKind},
+    },
+    sasl_profile,
+};
 
 cfg_scram! {
     use crate::auth::error::ScramErrorKind;
@@ -22,7 +31,7 @@ pub enum Error {
 
     /// Decode error
     #[error("Decode error")]
-    DecodeError(String),
+    DecodeError(#[source] serde_amqp::Error),
 
     /// Not implemented
     #[error("Not implemented")]
@@ -31,13 +40,17 @@ pub enum Error {
     /// Connection error: framing error
     #[error("Connection error: framing error")]
     FramingError,
+
+    /// A frame that cannot be decoded
+    #[error(transparent)]
+    InvalidFrame(InvalidFrame),
 }
 
 impl From<serde_amqp::Error> for Error {
     fn from(err: serde_amqp::Error) -> Self {
         match err {
             serde_amqp::Error::Io(e) => Self::Io(e),
-            other => Self::DecodeError(other.to_string()),
+            other => Self::DecodeError(other),
         }
     }
 }
@@ -48,36 +61,55 @@ impl From<frames::Error> for Error {
             frames::Error::Io(io) => Self::Io(io),
             frames::Error::DecodeError(val) => Self::DecodeError(val),
             frames::Error::NotImplemented => Self::NotImplemented(None),
+            frames::Error::InvalidFrame(invalid) => Self::InvalidFrame(invalid),
         }
     }
 }
 
+/// Error with negotiating the protocol header, TLS or SASL before the AMQP connection is opened
 #[derive(Debug, thiserror::Error)]
 pub enum NegotiationError {
+    /// IO error
     #[error("IO Error {0:?}")]
     Io(#[from] io::Error),
 
+    /// The remote peer replied with a different protocol header
     #[error("Protocol header mismatch {0:?}")]
     ProtocolHeaderMismatch(Bytes),
 
+    /// The domain is not a valid server name
     #[error("Invalid domain")]
     InvalidDomain,
 
+    /// Decode error
     #[error("Decode error")]
-    DecodeError(String),
+    DecodeError(#[source] serde_amqp::Error),
 
+    /// Not implemented
     #[error("Not implemented")]
     NotImplemented(Option<String>),
 
+    /// A frame arrived that is not expected in the current state of the negotiation
     #[error("Illegal state")]
     IllegalState,
 
-    #[error("SASL error code {:?}, additional data: {:?}", .code, .additional_data)]
-    SaslError {
+    /// The SASL outcome is not a success
+    #[error("SASL outcome code {:?}, additional data: {:?}", .code, .additional_data)]
+    SaslOutcome {
+        /// Outcome of the SASL exchange
         code: SaslCode,
+
+        /// Additional data sent by the server
         additional_data: Option<Binary>,
     },
 
+    /// The SASL mechanism of the profile is not offered by the server
+    #[error("SASL mechanism is not offered by the server. Offered: {:?}", .offered)]
+    SaslMechanismMismatch {
+        /// The mechanisms offered by the server
+        offered: Vec<Symbol>,
+    },
+
     /// Error with SCRAM
     #[cfg_attr(docsrs, doc(cfg(feature = "scram")))]
     #[cfg(feature = "scram")]
@@ -92,6 +124,11 @@ impl From<frames::Error> for NegotiationError {
             frames::Error::Io(err) => Self::Io(err),
             frames::Error::DecodeError(val) => Self::DecodeError(val),
             frames::Error::NotImplemented => Self::NotImplemented(None),
+            frames::Error::InvalidFrame(InvalidFrame {
+                kind: InvalidFrameKind::Malformed { source, .. },
+                ..
+            }) => Self::DecodeError(source),
+            frames::Error::InvalidFrame(invalid) => Self::NotImplemented(Some(invalid.to_string())),
         }
     }
 }
@@ -100,6 +137,9 @@ impl From<sasl_profile::Error> for NegotiationError {
     fn from(err: sasl_profile::Error) -> Self {
         match err {
             sasl_profile::Error::NotImplemented(msg) => Self::NotImplemented(msg),
+            sasl_profile::Error::MechanismMismatch { offered } => {
+                Self::SaslMechanismMismatch { offered }
+            }
 
             #[cfg(feature = "scram")]
             sasl_profile::Error::ScramError(scram_error) => Self::ScramError(scram_error),
@@ -14,9 +14,9 @@ use fe2o3_amqp_types::{
     states::ConnectionState,
 };
 
-use std::{io, marker::PhantomData, task::Poll, time::Duration};
+use std::{io, marker::PhantomData, sync::Arc, task::Poll, time::Duration};
 
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 use futures_util::{Future, Sink, SinkExt, Stream, StreamExt};
 use pin_project_lite::pin_project;
 use tokio::io::{AsyncRead, AsyncWrite, ReadHalf, WriteHalf};
@@ -24,15 +24,15 @@ use tokio_util::codec::{Decoder, Encoder, FramedRead, FramedWrite, LengthDelimit
 
 use crate::{
     frames::{amqp, sasl},
-    util::IdleTimeout,
+    util::{IdleTimeout, InstantCell},
 };
 
 use protocol_header::ProtocolHeader;
 
-use self::{error::NegotiationError, protocol_header::ProtocolHeaderCodec};
+use self::protocol_header::ProtocolHeaderCodec;
 
 pub(crate) mod error;
-pub use error::Error;
+pub use error::{Error, NegotiationError};
 pub mod protocol_header;
 
 pin_project! {
@@ -47,11 +47,22 @@ pin_project! {
 
         #[pin]
         idle_timeout: Option<IdleTimeout>,
+
+        // instants of the last AMQP frames, shared with the connection handle
+        frame_activity: Arc<FrameActivity>,
+
         // frame type
         ftype: PhantomData<Ftype>,
     }
 }
 
+/// The instants of the last AMQP frame received and sent over a transport
+#[derive(Debug, Default)]
+pub(crate) struct FrameActivity {
+    pub(crate) received: InstantCell,
+    pub(crate) sent: InstantCell,
+}
+
 impl<Io, Ftype> Transport<Io, Ftype>
 where
     Io: AsyncRead + AsyncWrite + Unpin,
@@ -97,11 +108,19 @@ where
             framed_write,
             framed_read,
             idle_timeout,
+            frame_activity: Arc::new(FrameActivity::default()),
             ftype: PhantomData,
         }
     }
 }
 
+impl<Io, Ftype> Transport<Io, Ftype> {
+    /// The instants of the last AMQP frame received and sent
+    pub(crate) fn frame_activity(&self) -> Arc<FrameActivity> {
+        self.frame_activity.clone()
+    }
+}
+
 impl<Io> Transport<Io, ()>
 where
     Io: AsyncRead + AsyncWrite + Unpin,
@@ -253,7 +272,8 @@ where
 
     /// Get the max frame size of the encoder
     pub fn encoder_max_frame_size(&self) -> usize {
-        self.framed_write.encoder().max_frame_length()
+        // The length delimited encoder does not count the 4 bytes of the frame size
+        self.framed_write.encoder().max_frame_length() + 4
     }
 
     /// Change the max_frame_size for the transport length delimited decoder
@@ -275,6 +295,18 @@ where
         self.idle_timeout = idle_timeout;
         self
     }
+
+    /// Number of encoded bytes that are buffered but not yet written to the IO
+    pub(crate) fn buffered_outgoing_bytes(&self) -> usize {
+        self.framed_write.write_buffer().len()
+    }
+
+    /// Set the number of buffered bytes above which the buffer is written to the IO before
+    /// another frame is encoded
+    pub(crate) fn set_backpressure_boundary(&mut self, boundary: usize) -> &mut Self {
+        self.framed_write.set_backpressure_boundary(boundary);
+        self
+    }
 }
 
 /// Creates a LengthDelimitedCodec that can handle the AMQP and SASL frames
@@ -430,8 +462,7 @@ where
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Result<(), Self::Error>> {
         let this = self.project();
-        this.framed_write
-            .poll_ready(cx) // Result<_, std::io::Error>
+        Sink::<Bytes>::poll_ready(this.framed_write, cx) // Result<_, std::io::Error>
             .map_err(Into::into)
     }
 
@@ -455,7 +486,9 @@ where
         let writer = Pin::new(&mut self.framed_write);
         writer
             .start_send(bytesmut.freeze()) // Result<_, std::io::Error>
-            .map_err(Into::into)
+            .map_err(Into::<Error>::into)?;
+        self.frame_activity.sent.record();
+        Ok(())
     }
 
     fn poll_flush(
@@ -463,8 +496,7 @@ where
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Result<(), Self::Error>> {
         let this = self.project();
-        this.framed_write
-            .poll_flush(cx) // Result<_, std::io::Error>
+        Sink::<Bytes>::poll_flush(this.framed_write, cx) // Result<_, std::io::Error>
             .map_err(Into::into)
     }
 
@@ -473,8 +505,7 @@ where
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Result<(), Self::Error>> {
         let this = self.project();
-        this.framed_write
-            .poll_close(cx) // Result<_, std::io::Error>
+        Sink::<Bytes>::poll_close(this.framed_write, cx) // Result<_, std::io::Error>
             .map_err(Into::into)
     }
 }
@@ -506,7 +537,11 @@ where
                         };
                         // tracing::debug!("raw bytes {:#x?}", &src[..]);
                         let mut decoder = amqp::FrameDecoder {};
-                        Poll::Ready(decoder.decode(&mut src).map_err(Into::into).transpose())
+                        let frame = decoder.decode(&mut src).map_err(Into::into).transpose();
+                        if let Some(Ok(_)) = &frame {
+                            this.frame_activity.received.record();
+                        }
+                        Poll::Ready(frame)
                     }
                     None => Poll::Ready(None),
                 }
@@ -540,7 +575,7 @@ where
         cx: &mut std::task::Context<'_>,
     ) -> Poll<Result<(), Self::Error>> {
         let this = self.project();
-        this.framed_write.poll_ready(cx).map_err(Into::into)
+        Sink::<Bytes>::poll_ready(this.framed_write, cx).map_err(Into::into)
     }
 
     // #[instrument(skip_all)]
@@ -563,7 +598,7 @@ where
         cx: &mut std::task::Context<'_>,
     ) -> Poll<Result<(), Self::Error>> {
         let this = self.project();
-        this.framed_write.poll_flush(cx).map_err(Into::into)
+        Sink::<Bytes>::poll_flush(this.framed_write, cx).map_err(Into::into)
     }
 
     fn poll_close(
@@ -571,7 +606,7 @@ where
         cx: &mut std::task::Context<'_>,
     ) -> Poll<Result<(), Self::Error>> {
         let this = self.project();
-        this.framed_write.poll_close(cx).map_err(Into::into)
+        Sink::<Bytes>::poll_close(this.framed_write, cx).map_err(Into::into)
     }
 }
 
@@ -608,7 +643,7 @@ where
 
 #[cfg(test)]
 mod tests {
-    use bytes::{Bytes, BytesMut};
+    use bytes::{BufMut, Bytes, BytesMut};
     use fe2o3_amqp_types::{performatives::Open, states::ConnectionState};
     use futures_util::{SinkExt, StreamExt};
     use tokio_test::io::Builder;
@@ -703,6 +738,66 @@ mod tests {
         transport.send(frame).await.unwrap();
     }
 
+    #[tokio::test]
+    async fn frames_split_across_reads_are_decoded() {
+        use fe2o3_amqp_types::performatives::Transfer;
+
+        let mut encoder = FrameEncoder::new(1000);
+        let mut buf = BytesMut::new();
+        for i in 0..3u32 {
+            let transfer = Transfer {
+                handle: 0.into(),
+                delivery_id: Some(i),
+                delivery_tag: Some(i.to_be_bytes().to_vec().into()),
+                message_format: Some(0),
+                settled: None,
+                more: false,
+                rcv_settle_mode: None,
+                state: None,
+                resume: false,
+                aborted: false,
+                batchable: false,
+            };
+            let frame = Frame::new(
+                1u16,
+                FrameBody::Transfer {
+                    performative: transfer,
+                    payload: Bytes::from(vec![i as u8; 16]),
+                },
+            );
+            let mut frame_buf = BytesMut::new();
+            encoder.encode(frame, &mut frame_buf).unwrap();
+            buf.put_u32(frame_buf.len() as u32 + 4);
+            buf.extend_from_slice(&frame_buf);
+        }
+        buf.extend_from_slice(&[0x0, 0x0, 0x0, 0x8, 0x2, 0x0, 0x0, 0x0]);
+
+        // Every read ends in the middle of a frame
+        let mut mock = Builder::new();
+        for chunk in buf.chunks(5) {
+            mock.read(chunk);
+        }
+        let mut transport = Transport::<_, Frame>::bind(mock.build(), 1000, None);
+
+        for i in 0..3u32 {
+            let frame = transport.next().await.unwrap().unwrap();
+            assert_eq!(frame.channel, 1);
+            match frame.body {
+                FrameBody::Transfer {
+                    performative,
+                    payload,
+                } => {
+                    assert_eq!(performative.delivery_id, Some(i));
+                    assert_eq!(payload, Bytes::from(vec![i as u8; 16]));
+                }
+                body => panic!("Expecting a Transfer, found {:?}", body),
+            }
+        }
+        let frame = transport.next().await.unwrap().unwrap();
+        assert!(matches!(frame.body, FrameBody::Empty));
+        assert!(transport.next().await.is_none());
+    }
+
     #[tokio::test]
     async fn test_frame_sink() {
         // use std::io::Cursor;
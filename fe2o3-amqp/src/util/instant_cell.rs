@@ -0,0 +1,70 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+cfg_not_wasm32! {
+    use std::time::Duration;
+
+    /// The latest recorded instant, which is cheap to record and to read from another task
+    ///
+    /// The instant is kept as the number of nanoseconds since the creation of the cell plus one,
+    /// where zero stands for nothing recorded
+    #[derive(Debug)]
+    pub(crate) struct InstantCell {
+        origin: Instant,
+        nanos: AtomicU64,
+    }
+
+    impl InstantCell {
+        pub(crate) fn new() -> Self {
+            Self {
+                origin: crate::rt::now(),
+                nanos: AtomicU64::new(0),
+            }
+        }
+
+        /// Records the current instant. The recorded instant never goes backwards
+        pub(crate) fn record(&self) {
+            let elapsed = crate::rt::now().saturating_duration_since(self.origin);
+            let nanos = u64::try_from(elapsed.as_nanos())
+                .unwrap_or(u64::MAX)
+                .saturating_add(1);
+            self.nanos.fetch_max(nanos, Ordering::Relaxed);
+        }
+
+        /// Returns the latest recorded instant
+        pub(crate) fn get(&self) -> Option<Instant> {
+            match self.nanos.load(Ordering::Relaxed) {
+                0 => None,
+                nanos => Some(self.origin + Duration::from_nanos(nanos - 1)),
+            }
+        }
+    }
+}
+
+cfg_wasm32! {
+    /// `std::time::Instant::now()` is not available on wasm32, so nothing is recorded
+    #[derive(Debug)]
+    pub(crate) struct InstantCell {
+        _nanos: AtomicU64,
+    }
+
+    impl InstantCell {
+        pub(crate) fn new() -> Self {
+            Self {
+                _nanos: AtomicU64::new(0),
+            }
+        }
+
+        pub(crate) fn record(&self) {}
+
+        pub(crate) fn get(&self) -> Option<Instant> {
+            None
+        }
+    }
+}
+
+impl Default for InstantCell {
+    fn default() -> Self {
+        Self::new()
+    }
+}
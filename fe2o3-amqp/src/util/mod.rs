@@ -1,6 +1,6 @@
 //! Common utilities
 
-use bytes::{buf, Buf};
+use bytes::{buf, Buf, BytesMut};
 use fe2o3_amqp_types::definitions::DeliveryNumber;
 use fe2o3_amqp_types::messaging::DeliveryState;
 use futures_util::Future;
@@ -10,9 +10,13 @@ use std::slice::Iter;
 use std::{pin::Pin, task::Poll, time::Duration};
 
 mod consumer;
+mod instant_cell;
 mod producer;
+mod serial_number;
 pub use consumer::*;
+pub(crate) use instant_cell::InstantCell;
 pub use producer::*;
+pub(crate) use serial_number::SerialNumber;
 
 use crate::Payload;
 
@@ -23,25 +27,19 @@ pub(crate) enum Running {
 }
 
 cfg_not_wasm32! {
-    use tokio::time::{Instant, Sleep};
-
     #[derive(Debug)]
     struct InnerDelay {
-        delay: Pin<Box<Sleep>>,
-        duration: Duration,
+        delay: crate::rt::Delay,
     }
 
     impl InnerDelay {
         fn new(duration: Duration) -> Self {
-            let delay = Box::pin(tokio::time::sleep(duration));
-            Self { delay, duration }
+            let delay = crate::rt::Delay::new(duration);
+            Self { delay }
         }
 
         fn reset(&mut self) {
-            let now = Instant::now();
-            let next = now + self.duration;
-            // this is equivalent to wasm-timer's `reset_at`
-            self.delay.as_mut().reset(next);
+            self.delay.reset();
         }
     }
 
@@ -158,6 +156,32 @@ pub(crate) trait IntoReader {
     fn into_reader(self) -> Self::Reader;
 }
 
+/// Joins the payload of a delivery into one [`Payload`]
+pub(crate) trait IntoPayload {
+    fn into_payload(self) -> Payload;
+}
+
+impl IntoPayload for Payload {
+    fn into_payload(self) -> Payload {
+        self
+    }
+}
+
+impl IntoPayload for Vec<Payload> {
+    fn into_payload(mut self) -> Payload {
+        if self.len() == 1 {
+            return self.remove(0);
+        }
+
+        let len = self.iter().map(|p| p.len()).sum();
+        let mut buf = BytesMut::with_capacity(len);
+        for payload in self {
+            buf.extend_from_slice(&payload);
+        }
+        buf.freeze()
+    }
+}
+
 impl IntoReader for Payload {
     type Reader = buf::Reader<Payload>;
 
@@ -275,8 +299,8 @@ impl AsDeliveryState for Option<DeliveryState> {
 pub(crate) struct Sealed {}
 
 pub(crate) fn is_consecutive(left: &DeliveryNumber, right: &DeliveryNumber) -> bool {
-    // Assume ascending order
-    right - left == 1
+    // Assume ascending order. Delivery ids are serial numbers and may wrap around
+    SerialNumber::from(*left).is_followed_by(SerialNumber::from(*right))
 }
 
 #[cfg(test)]
@@ -285,7 +309,14 @@ mod tests {
 
     use bytes::{Buf, Bytes};
 
-    use super::{AsByteIterator, IntoReader};
+    use super::{is_consecutive, AsByteIterator, IntoReader};
+
+    #[test]
+    fn delivery_ids_are_consecutive_across_wrap_around() {
+        assert!(is_consecutive(&u32::MAX, &0));
+        assert!(!is_consecutive(&0, &u32::MAX));
+        assert!(!is_consecutive(&3, &3));
+    }
 
     #[test]
     fn test_multiple_payload_reader() {
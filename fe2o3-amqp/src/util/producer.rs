@@ -12,6 +12,10 @@ impl<State> Producer<State> {
     pub fn new(notifier: Arc<Notify>, state: State) -> Self {
         Self { notifier, state }
     }
+
+    pub fn state(&self) -> &State {
+        &self.state
+    }
 }
 
 pub(crate) trait Produce {
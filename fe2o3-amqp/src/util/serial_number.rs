@@ -0,0 +1,159 @@
+//! Serial numbers (RFC-1982) such as the delivery-count of a link and the transfer-ids of a
+//! session
+
+use std::{cmp::Ordering, fmt};
+
+/// Half of the serial number space. Two serial numbers that are this far apart cannot be compared
+const HALF: u32 = 1 << 31;
+
+/// A 32 bit serial number (RFC-1982) that wraps around after `u32::MAX`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub(crate) struct SerialNumber(u32);
+
+/// The serial number `to` does not come after the serial number `from`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Serial number {to} does not come after serial number {from}")]
+pub(crate) struct SerialNumberError {
+    pub from: u32,
+    pub to: u32,
+}
+
+impl SerialNumber {
+    /// Returns the serial number `n` steps after this one
+    pub fn wrapping_add(self, n: u32) -> Self {
+        Self(self.0.wrapping_add(n))
+    }
+
+    /// Returns the serial number `n` steps before this one
+    pub fn wrapping_sub(self, n: u32) -> Self {
+        Self(self.0.wrapping_sub(n))
+    }
+
+    /// Whether this serial number comes right before `other`
+    pub fn is_followed_by(self, other: Self) -> bool {
+        other.0.wrapping_sub(self.0) == 1
+    }
+
+    /// Returns the number of steps from this serial number to `later`, or an error if `later`
+    /// comes before this serial number or cannot be compared with it
+    pub fn distance_to(self, later: Self) -> Result<u32, SerialNumberError> {
+        match later.0.wrapping_sub(self.0) {
+            distance if distance < HALF => Ok(distance),
+            _ => Err(SerialNumberError {
+                from: self.0,
+                to: later.0,
+            }),
+        }
+    }
+}
+
+impl PartialOrd for SerialNumber {
+    /// Returns `None` for two serial numbers that are exactly half of the space apart
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match other.0.wrapping_sub(self.0) {
+            0 => Some(Ordering::Equal),
+            HALF => None,
+            distance if distance < HALF => Some(Ordering::Less),
+            _ => Some(Ordering::Greater),
+        }
+    }
+}
+
+impl From<u32> for SerialNumber {
+    fn from(value: u32) -> Self {
+        Self(value)
+    }
+}
+
+impl From<SerialNumber> for u32 {
+    fn from(value: SerialNumber) -> Self {
+        value.0
+    }
+}
+
+impl PartialEq<u32> for SerialNumber {
+    fn eq(&self, other: &u32) -> bool {
+        self.0 == *other
+    }
+}
+
+impl fmt::Display for SerialNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use super::{SerialNumber, SerialNumberError};
+
+    /// Values around the start and the end of the serial number space and around half of it
+    fn boundary_values() -> Vec<u32> {
+        let mut values = Vec::new();
+        for base in [0, 1 << 31, u32::MAX] {
+            for offset in 0..4u32 {
+                values.push(base.wrapping_add(offset));
+                values.push(base.wrapping_sub(offset));
+            }
+        }
+        values
+    }
+
+    #[test]
+    fn serial_number_comparison_at_wrap_boundary() {
+        let sn = SerialNumber::from;
+        assert!(sn(u32::MAX - 1) < sn(u32::MAX));
+        assert!(sn(u32::MAX) < sn(0));
+        assert!(sn(u32::MAX - 5) < sn(2));
+        assert!(sn(0) > sn(u32::MAX));
+        assert_eq!(sn(3).partial_cmp(&sn(3)), Some(Ordering::Equal));
+        assert_eq!(sn(0).partial_cmp(&sn(1 << 31)), None);
+
+        assert!(sn(u32::MAX).is_followed_by(sn(0)));
+        assert!(!sn(0).is_followed_by(sn(u32::MAX)));
+
+        assert_eq!(sn(u32::MAX - 5).wrapping_add(8), 2);
+        assert_eq!(sn(2).wrapping_sub(8), u32::MAX - 5);
+        assert_eq!(sn(u32::MAX - 5).distance_to(sn(2)), Ok(8));
+        assert_eq!(
+            sn(2).distance_to(sn(u32::MAX - 5)),
+            Err(SerialNumberError {
+                from: 2,
+                to: u32::MAX - 5
+            })
+        );
+        assert_eq!(sn(7).distance_to(sn(7)), Ok(0));
+    }
+
+    #[test]
+    fn distance_is_consistent_with_comparison_and_addition() {
+        let values = boundary_values();
+        for &from in &values {
+            for &to in &values {
+                let (from, to) = (SerialNumber::from(from), SerialNumber::from(to));
+                match from.distance_to(to) {
+                    Ok(distance) => {
+                        assert!(from <= to, "{} <= {}", from, to);
+                        assert_eq!(from.wrapping_add(distance), to);
+                        assert_eq!(to.wrapping_sub(distance), from);
+                    }
+                    Err(error) => {
+                        assert!(
+                            !matches!(
+                                from.partial_cmp(&to),
+                                Some(Ordering::Less | Ordering::Equal)
+                            ),
+                            "{} > {}",
+                            from,
+                            to
+                        );
+                        assert_eq!(error.from, u32::from(from));
+                        assert_eq!(error.to, u32::from(to));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! Tests of sending through the anonymous relay
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{link::SenderAttachError, Connection, SendReceipt, Sender, Session};
+
+use common::spawn_listener;
+
+#[tokio::test]
+async fn anonymous_sender_send_to() {
+    let addr = spawn_listener(true).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("anonymous-relay-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+
+    let mut sender = Sender::attach_anonymous(&mut session, "anonymous-sender")
+        .await
+        .unwrap();
+    assert!(sender.target().as_ref().unwrap().address.is_none());
+
+    let receipt = sender.send_to("q1", "hello q1").await.unwrap();
+    assert!(matches!(receipt, SendReceipt::Accepted(_)));
+    let receipt = sender.send_to("q2", "hello q2").await.unwrap();
+    assert!(matches!(receipt, SendReceipt::Accepted(_)));
+
+    sender.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn anonymous_sender_without_offered_capability() {
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("anonymous-relay-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+
+    let result = Sender::attach_anonymous(&mut session, "anonymous-sender").await;
+    assert!(matches!(
+        result,
+        Err(SenderAttachError::AnonymousRelayNotSupported)
+    ));
+
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
@@ -0,0 +1,122 @@
+//! Tests running the client and the listener on the async-std runtime without a tokio runtime
+
+#![cfg(all(
+    feature = "rt-async-std",
+    feature = "acceptor",
+    not(target_arch = "wasm32")
+))]
+
+use std::time::Duration;
+
+use async_std::{net::TcpListener, task};
+use fe2o3_amqp::{
+    acceptor::{
+        ConnectionAcceptor, LinkAcceptor, LinkEndpoint, ListenerConnectionHandle,
+        ListenerSessionHandle, SessionAcceptor,
+    },
+    Connection, Receiver, SendReceipt, Sender, Session,
+};
+use tokio_util::compat::FuturesAsyncReadCompatExt;
+
+async fn spawn_listener() -> String {
+    let tcp_listener = TcpListener::bind("localhost:0").await.unwrap();
+    let addr = tcp_listener.local_addr().unwrap();
+
+    task::spawn(async move {
+        let connection_acceptor = ConnectionAcceptor::builder()
+            .container_id("async-std-listener")
+            .build();
+        while let Ok((stream, _)) = tcp_listener.accept().await {
+            let connection = connection_acceptor.accept(stream.compat()).await.unwrap();
+            task::spawn(connection_main(connection));
+        }
+    });
+
+    format!("amqp://{}", addr)
+}
+
+async fn connection_main(mut connection: ListenerConnectionHandle) {
+    let session_acceptor = SessionAcceptor::new();
+    while let Ok(session) = session_acceptor.accept(&mut connection).await {
+        task::spawn(session_main(session));
+    }
+    let _ = connection.on_close().await;
+}
+
+async fn session_main(mut session: ListenerSessionHandle) {
+    let link_acceptor = LinkAcceptor::new();
+    while let Ok(link) = link_acceptor.accept(&mut session).await {
+        match link {
+            LinkEndpoint::Receiver(receiver) => {
+                task::spawn(echo_main(receiver));
+            }
+            LinkEndpoint::Sender(sender) => {
+                task::spawn(sender_main(sender));
+            }
+        }
+    }
+    let _ = session.on_end().await;
+}
+
+async fn echo_main(mut receiver: Receiver) {
+    while let Ok(delivery) = receiver.recv::<String>().await {
+        if receiver.accept(&delivery).await.is_err() {
+            return;
+        }
+    }
+}
+
+async fn sender_main(mut sender: Sender) {
+    for i in 0..3 {
+        if sender.send(format!("message-{}", i)).await.is_err() {
+            return;
+        }
+    }
+    let _ = sender.close().await;
+}
+
+#[test]
+fn send_and_receive_on_async_std() {
+    // The future is boxed because it overflows the stack of the test thread in debug builds
+    task::block_on(Box::pin(async {
+        let url = spawn_listener().await;
+        let mut connection = Connection::builder()
+            .container_id("async-std-connection")
+            .idle_time_out(1_000u32)
+            .open(&url[..])
+            .await
+            .unwrap();
+        let mut session = Session::begin(&mut connection).await.unwrap();
+
+        let mut sender = Sender::attach(&mut session, "async-std-sender", "q1")
+            .await
+            .unwrap();
+        let receipt = sender.send("hello async-std").await.unwrap();
+        assert!(matches!(receipt, SendReceipt::Accepted(_)));
+
+        let mut receiver = Receiver::attach(&mut session, "async-std-receiver", "q1")
+            .await
+            .unwrap();
+        for i in 0..3 {
+            let delivery = receiver.recv::<String>().await.unwrap();
+            assert_eq!(delivery.body(), &format!("message-{}", i));
+            receiver.accept(&delivery).await.unwrap();
+        }
+
+        // The timers are driven by async-std. The client advertises half of its idle timeout, so
+        // the connection only stays open if the listener sends heartbeats and the client's idle
+        // timeout is reset by them
+        task::sleep(Duration::from_millis(2_500)).await;
+        let receipt = sender
+            .send_with_timeout("still open", Duration::from_secs(5))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(receipt, SendReceipt::Accepted(_)));
+
+        sender.close().await.unwrap();
+        receiver.close().await.unwrap();
+        session.end().await.unwrap();
+        connection.close().await.unwrap();
+    }));
+}
@@ -0,0 +1,210 @@
+//! Tests of negotiating the termini and settle modes at attach
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use std::net::SocketAddr;
+
+use fe2o3_amqp::{
+    acceptor::{LinkAcceptor, SupportedReceiverSettleModes, SupportedSenderSettleModes},
+    types::{
+        definitions::{ReceiverSettleMode, SenderSettleMode},
+        messaging::{Outcome, Rejected, Released, Source, Target},
+        primitives::Symbol,
+    },
+    Connection, Receiver, Sender, Session,
+};
+
+use common::serve_session;
+
+/// Accepts the links of one session with an acceptor that shapes the echoed terminus, and
+/// reports the terminus of each accepted link
+async fn spawn_terminus_shaping_listener() -> (
+    SocketAddr,
+    tokio::sync::mpsc::UnboundedReceiver<(Option<Source>, Option<Target>)>,
+) {
+    let (terminus_tx, terminus_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (addr, _) = serve_session("terminus-shaping", move |mut session| async move {
+        let link_acceptor = LinkAcceptor::builder()
+            .source_outcomes(["amqp:accepted:list", "amqp:rejected:list"])
+            .default_outcome(Rejected { error: None })
+            .target_capabilities(vec![Symbol::from("queue")])
+            .build();
+        let mut links = Vec::new();
+        while let Ok(link) = link_acceptor.accept(&mut session).await {
+            let _ = terminus_tx.send((link.source().clone(), link.target().clone()));
+            links.push(link);
+        }
+    })
+    .await;
+    (addr, terminus_rx)
+}
+
+#[tokio::test]
+async fn acceptor_shapes_the_authoritative_terminus() {
+    let (addr, mut terminus_rx) = spawn_terminus_shaping_listener().await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("terminus-shaping-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+
+    // The accepted sender holds the authoritative source
+    let receiver = Receiver::builder()
+        .name("shaped-receiver")
+        .source(
+            Source::builder()
+                .address("q1")
+                .outcomes(vec![Symbol::from("amqp:released:list")])
+                .default_outcome(Outcome::Released(Released {}))
+                .build(),
+        )
+        .target("shaped-receiver")
+        .attach(&mut session)
+        .await
+        .unwrap();
+    let source = receiver.source().clone().unwrap();
+    assert_eq!(
+        source.outcomes,
+        Some(
+            vec![
+                Symbol::from("amqp:accepted:list"),
+                Symbol::from("amqp:rejected:list")
+            ]
+            .into()
+        )
+    );
+    assert_eq!(
+        source.default_outcome,
+        Some(Outcome::Rejected(Rejected { error: None }))
+    );
+    let (accepted_source, _) = terminus_rx.recv().await.unwrap();
+    assert_eq!(accepted_source.unwrap(), source);
+
+    // The accepted receiver holds the authoritative target, and leaves the source alone
+    let sender = Sender::builder()
+        .name("shaped-sender")
+        .source(
+            Source::builder()
+                .address("shaped-sender")
+                .default_outcome(Outcome::Released(Released {}))
+                .build(),
+        )
+        .target("q1")
+        .attach(&mut session)
+        .await
+        .unwrap();
+    let target = sender.target().clone().unwrap();
+    assert_eq!(
+        target.capabilities,
+        Some(vec![Symbol::from("queue")].into())
+    );
+    let (accepted_source, accepted_target) = terminus_rx.recv().await.unwrap();
+    assert_eq!(accepted_target.unwrap(), target);
+    assert_eq!(
+        accepted_source.unwrap().default_outcome,
+        Some(Outcome::Released(Released {}))
+    );
+
+    // The accepted links are kept by the listener without being polled, so they are ended along
+    // with the session
+    drop(receiver);
+    drop(sender);
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
+
+async fn spawn_settle_mode_listener() -> (
+    SocketAddr,
+    tokio::sync::mpsc::UnboundedReceiver<(SenderSettleMode, ReceiverSettleMode)>,
+) {
+    let (modes_tx, modes_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (addr, _) = serve_session("settle-mode", move |mut session| async move {
+        let link_acceptor = LinkAcceptor::builder()
+            .supported_sender_settle_modes(SupportedSenderSettleModes::UnsettledAndSettled)
+            .fallback_sender_settle_mode(SenderSettleMode::Settled)
+            .supported_receiver_settle_modes(SupportedReceiverSettleModes::First)
+            .fallback_receiver_settle_mode(ReceiverSettleMode::First)
+            .build();
+        let mut links = Vec::new();
+        while let Ok(link) = link_acceptor.accept(&mut session).await {
+            let modes = (
+                link.snd_settle_mode().clone(),
+                link.rcv_settle_mode().clone(),
+            );
+            let _ = modes_tx.send(modes);
+            links.push(link);
+        }
+    })
+    .await;
+    (addr, modes_rx)
+}
+
+#[tokio::test]
+async fn settle_modes_degrade_to_the_modes_of_the_authoritative_end() {
+    let (addr, mut modes_rx) = spawn_settle_mode_listener().await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("settle-mode-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+
+    let snd_settle_modes = [
+        SenderSettleMode::Unsettled,
+        SenderSettleMode::Settled,
+        SenderSettleMode::Mixed,
+    ];
+    let rcv_settle_modes = [ReceiverSettleMode::First, ReceiverSettleMode::Second];
+    let mut links = Vec::new();
+    for (i, snd_settle_mode) in snd_settle_modes.iter().enumerate() {
+        for (j, rcv_settle_mode) in rcv_settle_modes.iter().enumerate() {
+            // The accepted sender answers with the fallback mode if the desired sender settle
+            // mode is not supported, and takes the receiver settle mode of the receiver
+            let receiver = Receiver::builder()
+                .name(format!("settle-mode-receiver-{i}-{j}"))
+                .source("q1")
+                .target(format!("settle-mode-receiver-{i}-{j}"))
+                .sender_settle_mode(snd_settle_mode.clone())
+                .receiver_settle_mode(rcv_settle_mode.clone())
+                .attach(&mut session)
+                .await
+                .unwrap();
+            let expected = match snd_settle_mode {
+                SenderSettleMode::Mixed => SenderSettleMode::Settled,
+                mode => mode.clone(),
+            };
+            assert_eq!(receiver.snd_settle_mode(), &expected);
+            assert_eq!(receiver.rcv_settle_mode(), rcv_settle_mode);
+            let accepted = modes_rx.recv().await.unwrap();
+            assert_eq!(accepted, (expected, rcv_settle_mode.clone()));
+
+            // The accepted receiver takes the sender settle mode of the sender, and answers with
+            // the fallback mode if the desired receiver settle mode is not supported
+            let sender = Sender::builder()
+                .name(format!("settle-mode-sender-{i}-{j}"))
+                .source(format!("settle-mode-sender-{i}-{j}"))
+                .target("q1")
+                .sender_settle_mode(snd_settle_mode.clone())
+                .receiver_settle_mode(rcv_settle_mode.clone())
+                .attach(&mut session)
+                .await
+                .unwrap();
+            assert_eq!(sender.snd_settle_mode(), snd_settle_mode);
+            assert_eq!(sender.rcv_settle_mode(), &ReceiverSettleMode::First);
+            let accepted = modes_rx.recv().await.unwrap();
+            assert_eq!(
+                accepted,
+                (snd_settle_mode.clone(), ReceiverSettleMode::First)
+            );
+
+            links.push((receiver, sender));
+        }
+    }
+
+    // The accepted links are kept by the listener without being polled, so they are ended along
+    // with the session
+    drop(links);
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
@@ -0,0 +1,88 @@
+//! Tests of resending buffered messages
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use std::sync::Arc;
+
+use fe2o3_amqp::{
+    acceptor::{ConnectionAcceptor, LinkAcceptor, LinkEndpoint, SessionAcceptor},
+    Connection, SendReceipt, Session,
+};
+
+use common::serve_connection;
+
+#[tokio::test]
+async fn buffered_sender_resends_in_order_after_link_detach() {
+    use fe2o3_amqp::link::BufferedSender;
+    use tokio::sync::{mpsc, Mutex};
+
+    const COUNT: usize = 10;
+
+    let (received_tx, mut received_rx) = mpsc::unbounded_channel();
+    let acceptor = ConnectionAcceptor::new("test-listener");
+    let (addr, _) = serve_connection(acceptor, move |mut connection| async move {
+        let session_acceptor = SessionAcceptor::new();
+        let link_acceptor = LinkAcceptor::new();
+
+        // The first link is detached after three messages without disposing the third one
+        let mut detach_after = Some(3);
+        while let Ok(mut session) = session_acceptor.accept(&mut connection).await {
+            let mut receiver = match link_acceptor.accept(&mut session).await {
+                Ok(LinkEndpoint::Receiver(receiver)) => receiver,
+                _ => break,
+            };
+            let mut received = 0;
+            while let Ok(delivery) = receiver.recv::<String>().await {
+                received_tx.send(delivery.body().clone()).unwrap();
+                received += 1;
+                if Some(received) == detach_after {
+                    detach_after = None;
+                    let _ = receiver.detach().await;
+                    break;
+                }
+                receiver.accept(&delivery).await.unwrap();
+            }
+            let _ = session.on_end().await;
+        }
+    })
+    .await;
+
+    let url = format!("amqp://{}", addr);
+    let connection = Connection::open("buffered-sender-connection", &url[..])
+        .await
+        .unwrap();
+    let connection = Arc::new(Mutex::new(connection));
+    let factory_connection = connection.clone();
+    let sender = BufferedSender::builder()
+        .name("buffered-sender")
+        .target("q1")
+        .reattach_interval(std::time::Duration::from_millis(10))
+        .spawn(move || {
+            let connection = factory_connection.clone();
+            async move { Session::begin(&mut *connection.lock().await).await }
+        });
+
+    let deliveries: Vec<_> = (0..COUNT)
+        .map(|i| sender.send(format!("message-{}", i)).unwrap())
+        .collect();
+    sender.flush().await.unwrap();
+    assert_eq!(sender.pending(), 0);
+    for delivery in deliveries {
+        assert!(matches!(delivery.await, Ok(SendReceipt::Accepted(_))));
+    }
+
+    // The message that was not disposed before the detach is received again
+    let mut received = Vec::new();
+    while let Ok(body) = received_rx.try_recv() {
+        if received.last() != Some(&body) {
+            received.push(body);
+        }
+    }
+    let expected: Vec<_> = (0..COUNT).map(|i| format!("message-{}", i)).collect();
+    assert_eq!(received, expected);
+
+    sender.close().await.unwrap();
+    connection.lock().await.close().await.unwrap();
+}
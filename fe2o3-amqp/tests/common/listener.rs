@@ -0,0 +1,268 @@
+//! Listeners that the tests against the acceptors connect to
+
+use std::{future::Future, net::SocketAddr};
+
+use fe2o3_amqp::{
+    acceptor::{
+        ConnectionAcceptor, LinkAcceptor, LinkEndpoint, ListenerConnectionHandle,
+        ListenerSessionHandle, LoopbackNode, SessionAcceptor,
+    },
+    link::{receiver::CreditMode, ANONYMOUS_RELAY},
+    types::{
+        definitions::{self, AmqpError, Fields},
+        messaging::Modified,
+        primitives::{Symbol, Value},
+    },
+    Receiver, Sender,
+};
+use tokio::{net::TcpListener, sync::oneshot, task::JoinHandle};
+
+/// The number of messages sent by the echo listener to the source address "flood"
+pub const FLOOD_COUNT: usize = 64;
+
+/// The size of each message sent to the source address "flood"
+pub const FLOOD_MESSAGE_SIZE: usize = 8 * 1024;
+
+/// Binds a TCP listener to a free port of localhost
+pub async fn bind() -> (TcpListener, SocketAddr) {
+    let tcp_listener = TcpListener::bind("localhost:0").await.unwrap();
+    let addr = tcp_listener.local_addr().unwrap();
+    (tcp_listener, addr)
+}
+
+/// Accepts the first connection with `acceptor` and hands it to `f`, which runs on its own task
+pub async fn serve_connection<F, Fut>(
+    acceptor: ConnectionAcceptor<(), ()>,
+    f: F,
+) -> (SocketAddr, JoinHandle<Fut::Output>)
+where
+    F: FnOnce(ListenerConnectionHandle) -> Fut + Send + 'static,
+    Fut: Future + Send + 'static,
+    Fut::Output: Send + 'static,
+{
+    let (tcp_listener, addr) = bind().await;
+    let handle = tokio::spawn(async move {
+        let (stream, _) = tcp_listener.accept().await.unwrap();
+        let connection = acceptor.accept(stream).await.unwrap();
+        f(connection).await
+    });
+    (addr, handle)
+}
+
+/// Accepts the first session of the first connection with the given acceptors and hands it to
+/// `f`. The returned handle resolves once `f` has returned and the client has closed the
+/// connection
+pub async fn serve_session_with<F, Fut>(
+    acceptor: ConnectionAcceptor<(), ()>,
+    session_acceptor: SessionAcceptor,
+    f: F,
+) -> (SocketAddr, JoinHandle<Fut::Output>)
+where
+    F: FnOnce(ListenerSessionHandle) -> Fut + Send + 'static,
+    Fut: Future + Send + 'static,
+    Fut::Output: Send + 'static,
+{
+    serve_connection(acceptor, move |mut connection| async move {
+        let session = session_acceptor.accept(&mut connection).await.unwrap();
+        let output = f(session).await;
+        let _ = connection.on_close().await;
+        output
+    })
+    .await
+}
+
+/// [`serve_session_with`] with the default acceptors and the container id `container_id`
+pub async fn serve_session<F, Fut>(
+    container_id: &'static str,
+    f: F,
+) -> (SocketAddr, JoinHandle<Fut::Output>)
+where
+    F: FnOnce(ListenerSessionHandle) -> Fut + Send + 'static,
+    Fut: Future + Send + 'static,
+    Fut::Output: Send + 'static,
+{
+    serve_session_with(
+        ConnectionAcceptor::new(container_id),
+        SessionAcceptor::new(),
+        f,
+    )
+    .await
+}
+
+/// The receiver of an accepted link, which must have been attached by a client sender
+pub fn expect_receiver(link: LinkEndpoint) -> Receiver {
+    match link {
+        LinkEndpoint::Receiver(receiver) => receiver,
+        LinkEndpoint::Sender(_) => panic!("Expecting a receiver"),
+    }
+}
+
+/// The sender of an accepted link, which must have been attached by a client receiver
+pub fn expect_sender(link: LinkEndpoint) -> Sender {
+    match link {
+        LinkEndpoint::Sender(sender) => sender,
+        LinkEndpoint::Receiver(_) => panic!("Expecting a sender"),
+    }
+}
+
+/// Spawns the echo listener, which serves every connection with [`connection_main`]
+pub async fn spawn_listener(offer_anonymous_relay: bool) -> SocketAddr {
+    let (tcp_listener, addr) = bind().await;
+
+    tokio::spawn(async move {
+        let connection_acceptor = ConnectionAcceptor::new("test-listener");
+        while let Ok((stream, _)) = tcp_listener.accept().await {
+            let connection = connection_acceptor.accept(stream).await.unwrap();
+            tokio::spawn(connection_main(connection, offer_anonymous_relay));
+        }
+    });
+
+    addr
+}
+
+/// Serves every session of the connection with [`session_main`]
+pub async fn connection_main(
+    mut connection: ListenerConnectionHandle,
+    offer_anonymous_relay: bool,
+) {
+    let session_acceptor = SessionAcceptor::new();
+    while let Ok(session) = session_acceptor.accept(&mut connection).await {
+        tokio::spawn(session_main(session, offer_anonymous_relay));
+    }
+    let _ = connection.on_close().await;
+}
+
+/// Serves every link of the session with [`receiver_main`] or [`sender_main`]
+pub async fn session_main(mut session: ListenerSessionHandle, offer_anonymous_relay: bool) {
+    let link_acceptor = match offer_anonymous_relay {
+        true => LinkAcceptor::builder()
+            .add_offered_capabilities(ANONYMOUS_RELAY)
+            .build(),
+        false => LinkAcceptor::new(),
+    };
+
+    while let Ok(link) = link_acceptor.accept(&mut session).await {
+        match link {
+            LinkEndpoint::Receiver(receiver) => {
+                tokio::spawn(receiver_main(receiver));
+            }
+            LinkEndpoint::Sender(sender) => {
+                tokio::spawn(sender_main(sender));
+            }
+        }
+    }
+    let _ = session.on_end().await;
+}
+
+/// Disposes each delivery according to its body
+pub async fn receiver_main(mut receiver: Receiver) {
+    while let Ok(delivery) = receiver.recv::<Value>().await {
+        let result = match delivery.body() {
+            Value::String(s) if s == "reject" => {
+                let error = definitions::Error::new(AmqpError::NotAllowed, None, None);
+                receiver.reject(&delivery, error).await
+            }
+            Value::String(s) if s == "reject-with-info" => {
+                let mut info = Fields::new();
+                info.insert(Symbol::from("reason"), Value::from("QuotaExceeded"));
+                let error = definitions::Error::new(
+                    AmqpError::ResourceLimitExceeded,
+                    Some("quota exceeded".to_string()),
+                    info,
+                );
+                receiver.reject(&delivery, error).await
+            }
+            Value::String(s) if s == "release" => receiver.release(&delivery).await,
+            Value::String(s) if s == "modify" => {
+                let modified = Modified {
+                    delivery_failed: Some(true),
+                    undeliverable_here: None,
+                    message_annotations: None,
+                };
+                receiver.modify(&delivery, modified).await
+            }
+            Value::String(s) if s == "modify-with-annotations" => {
+                let mut annotations = Fields::new();
+                annotations.insert(Symbol::from("x-opt-reason"), Value::from("Busy"));
+                let modified = Modified {
+                    delivery_failed: Some(true),
+                    undeliverable_here: Some(false),
+                    message_annotations: Some(annotations),
+                };
+                receiver.modify(&delivery, modified).await
+            }
+            _ => receiver.accept(&delivery).await,
+        };
+        if result.is_err() {
+            break;
+        }
+    }
+}
+
+/// Sends three messages and then closes the link, with an error if the source address is
+/// "stream-error". Sends `FLOOD_COUNT` large messages without waiting for their outcomes if the
+/// source address is "flood"
+pub async fn sender_main(mut sender: Sender) {
+    let address = sender
+        .source()
+        .as_ref()
+        .and_then(|source| source.address.clone());
+    if address.as_deref() == Some("flood") {
+        let mut outcomes = Vec::new();
+        for i in 0..FLOOD_COUNT {
+            let body = format!("{:08}", i).repeat(FLOOD_MESSAGE_SIZE / 8);
+            match sender.send_batchable(body).await {
+                Ok(outcome) => outcomes.push(outcome),
+                Err(_) => return,
+            }
+        }
+        for outcome in outcomes {
+            let _ = outcome.await;
+        }
+        let _ = sender.close().await;
+        return;
+    }
+    for i in 0..3 {
+        if sender.send(format!("message-{}", i)).await.is_err() {
+            return;
+        }
+    }
+    let _ = match address.as_deref() {
+        Some("stream-error") => {
+            let error = definitions::Error::new(AmqpError::ResourceDeleted, None, None);
+            sender.close_with_error(error).await
+        }
+        _ => sender.close().await,
+    };
+}
+
+/// Spawns a listener that accepts a single link with manual credit and hands it over without
+/// issuing any credit
+pub async fn spawn_single_link_listener(
+    container_id: &'static str,
+) -> (SocketAddr, oneshot::Receiver<LinkEndpoint>) {
+    let (endpoint_tx, endpoint_rx) = oneshot::channel();
+    let (addr, _) = serve_session(container_id, move |mut session| async move {
+        let link_acceptor = LinkAcceptor::builder()
+            .credit_mode(CreditMode::Manual)
+            .build();
+        let endpoint = link_acceptor.accept(&mut session).await.unwrap();
+        let _ = endpoint_tx.send(endpoint);
+        let _ = session.on_end().await;
+    })
+    .await;
+    (addr, endpoint_rx)
+}
+
+/// Spawns a listener that hands every link of a single session to `node`. The returned handle
+/// resolves once the session ends, with the first link that was not handled by the node if any
+pub async fn spawn_loopback_listener(
+    node: LoopbackNode,
+) -> (SocketAddr, JoinHandle<Option<LinkEndpoint>>) {
+    serve_session("test-listener", move |mut session| async move {
+        let link_acceptor = LinkAcceptor::builder().loopback_node(node).build();
+        // Links attached to the node are never returned
+        link_acceptor.accept(&mut session).await.ok()
+    })
+    .await
+}
@@ -1,3 +1,14 @@
+//! Helpers shared by the integration tests. Each test crate only uses some of them
+
+#![allow(dead_code)]
+
+#[cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+mod listener;
+
+#[cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+#[allow(unused_imports)]
+pub use listener::*;
+
 use testcontainers::{clients::Cli, core::WaitFor, Container, GenericImage};
 use tokio::sync::OnceCell;
 
@@ -0,0 +1,77 @@
+//! Tests of compressed message bodies
+
+#![cfg(all(
+    feature = "acceptor",
+    feature = "compression",
+    not(target_arch = "wasm32")
+))]
+
+mod common;
+
+use fe2o3_amqp::{
+    acceptor::LinkAcceptor,
+    types::primitives::{Symbol, Value},
+    Connection, Sender, Session,
+};
+
+use common::{expect_receiver, serve_session};
+
+#[tokio::test]
+async fn compressed_data_body_round_trip() {
+    use fe2o3_amqp::{
+        compression::{Compression, GZIP},
+        types::messaging::{Body, Data, Message},
+    };
+    use tokio::sync::mpsc;
+
+    let (delivery_tx, mut delivery_rx) = mpsc::unbounded_channel();
+    let (addr, _) = serve_session("compression-listener", move |mut session| async move {
+        let mut receiver = expect_receiver(LinkAcceptor::new().accept(&mut session).await.unwrap());
+        while let Ok(delivery) = receiver.recv::<Body<Value>>().await {
+            receiver.accept(&delivery).await.unwrap();
+            delivery_tx.send(delivery).unwrap();
+        }
+        let _ = receiver.close().await;
+        let _ = session.on_end().await;
+    })
+    .await;
+
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("compression-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::builder()
+        .name("compression-sender")
+        .target("q1")
+        .compress_body(Compression::Gzip { level: 6 }, 256)
+        .attach(&mut session)
+        .await
+        .unwrap();
+
+    let json = br#"{"id":1,"tags":["a","b","c"],"payload":"lorem ipsum"}"#.repeat(64);
+    let message = Message::builder().data(Data::from(json.clone())).build();
+    sender.send(message).await.unwrap().accepted_or(()).unwrap();
+    let delivery = delivery_rx.recv().await.unwrap();
+    let properties = delivery.message().properties.as_ref().unwrap();
+    assert_eq!(properties.content_encoding, Some(Symbol::from(GZIP)));
+    let compressed = match delivery.body() {
+        Body::Data(batch) => batch[0].0.to_vec(),
+        body => panic!("Expecting Data, found {:?}", body),
+    };
+    assert!(compressed.len() < json.len());
+    assert_eq!(delivery.decompressed_data().unwrap(), &json[..]);
+
+    // Bodies under the threshold are sent as is
+    let message = Message::builder()
+        .data(Data::from(b"small".to_vec()))
+        .build();
+    sender.send(message).await.unwrap().accepted_or(()).unwrap();
+    let delivery = delivery_rx.recv().await.unwrap();
+    assert!(delivery.message().properties.is_none());
+    assert_eq!(delivery.decompressed_data().unwrap(), &b"small"[..]);
+
+    sender.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
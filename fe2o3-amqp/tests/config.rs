@@ -0,0 +1,188 @@
+//! Tests that connections, sessions and links can be created from config files
+
+#![cfg(all(feature = "config", feature = "acceptor", not(target_arch = "wasm32")))]
+
+use std::net::SocketAddr;
+
+use fe2o3_amqp::{
+    acceptor::{
+        ConnectionAcceptor, LinkAcceptor, LinkEndpoint, SaslPlainMechanism, SessionAcceptor,
+    },
+    config::{ConfigError, ConnectionConfig, ReceiverConfig, SenderConfig, SessionConfig},
+    connection, link, session,
+    types::primitives::Value,
+};
+use tokio::net::TcpListener;
+
+const CONNECTION_CONFIG: &str = r#"{
+    "url": "amqp://localhost:0",
+    "container_id": "config-client",
+    "max_frame_size": 65536,
+    "channel_max": 16,
+    "idle_time_out": 60000,
+    "sasl": {
+        "mechanism": "PLAIN",
+        "username": "guest",
+        "password": "${FE2O3_CONFIG_TEST_PASSWORD}"
+    },
+    "properties": { "product": "config-test" }
+}"#;
+
+const SESSION_CONFIG: &str = r#"{
+    "incoming_window": 1024,
+    "outgoing_window": 1024,
+    "handle_max": 32
+}"#;
+
+const SENDER_CONFIG: &str = r#"{
+    "name": "config-sender",
+    "target": { "address": "q1", "durable": "unsettled_state", "expiry_policy": "never" },
+    "snd_settle_mode": "unsettled",
+    "rcv_settle_mode": "first"
+}"#;
+
+const RECEIVER_CONFIG: &str = r#"{
+    "name": "config-receiver",
+    "source": { "address": "q1" },
+    "credit_mode": { "auto": 10 }
+}"#;
+
+/// Accepts connections authenticated with PLAIN. Receivers accept every delivery and senders
+/// send a single message
+async fn spawn_listener(password: &str) -> SocketAddr {
+    let tcp_listener = TcpListener::bind("localhost:0").await.unwrap();
+    let addr = tcp_listener.local_addr().unwrap();
+    let connection_acceptor = ConnectionAcceptor::builder()
+        .container_id("config-listener")
+        .sasl_acceptor(SaslPlainMechanism::new("guest", password))
+        .build();
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = tcp_listener.accept().await {
+            let Ok(mut connection) = connection_acceptor.accept(stream).await else {
+                continue;
+            };
+            tokio::spawn(async move {
+                let mut session = SessionAcceptor::new()
+                    .accept(&mut connection)
+                    .await
+                    .unwrap();
+                let link_acceptor = LinkAcceptor::new();
+                while let Ok(link) = link_acceptor.accept(&mut session).await {
+                    match link {
+                        LinkEndpoint::Receiver(mut receiver) => {
+                            tokio::spawn(async move {
+                                while let Ok(delivery) = receiver.recv::<Value>().await {
+                                    let _ = receiver.accept(&delivery).await;
+                                }
+                            });
+                        }
+                        LinkEndpoint::Sender(mut sender) => {
+                            tokio::spawn(async move {
+                                let _ = sender.send("from-listener").await;
+                            });
+                        }
+                    }
+                }
+                let _ = session.on_end().await;
+                let _ = connection.on_close().await;
+            });
+        }
+    });
+    addr
+}
+
+#[test]
+fn configs_round_trip_through_json() {
+    let connection_config: ConnectionConfig = serde_json::from_str(CONNECTION_CONFIG).unwrap();
+    let json = serde_json::to_string(&connection_config).unwrap();
+    assert_eq!(
+        serde_json::from_str::<ConnectionConfig>(&json).unwrap(),
+        connection_config
+    );
+    // The reference is kept as is until the builder is created
+    assert!(json.contains("${FE2O3_CONFIG_TEST_PASSWORD}"));
+
+    let session_config: SessionConfig = serde_json::from_str(SESSION_CONFIG).unwrap();
+    let json = serde_json::to_string(&session_config).unwrap();
+    assert_eq!(
+        serde_json::from_str::<SessionConfig>(&json).unwrap(),
+        session_config
+    );
+
+    let sender_config: SenderConfig = serde_json::from_str(SENDER_CONFIG).unwrap();
+    let json = serde_json::to_string(&sender_config).unwrap();
+    assert_eq!(
+        serde_json::from_str::<SenderConfig>(&json).unwrap(),
+        sender_config
+    );
+
+    let receiver_config: ReceiverConfig = serde_json::from_str(RECEIVER_CONFIG).unwrap();
+    let json = serde_json::to_string(&receiver_config).unwrap();
+    assert_eq!(
+        serde_json::from_str::<ReceiverConfig>(&json).unwrap(),
+        receiver_config
+    );
+
+    // Typos are not silently ignored
+    assert!(serde_json::from_str::<SessionConfig>(r#"{ "handle_maxx": 1 }"#).is_err());
+}
+
+#[test]
+fn sasl_password_is_redacted_and_missing_env_var_fails() {
+    let config: ConnectionConfig = serde_json::from_str(
+        r#"{ "sasl": { "mechanism": "PLAIN", "username": "guest", "password": "${FE2O3_CONFIG_TEST_NOT_SET}" } }"#,
+    )
+    .unwrap();
+    assert!(!format!("{:?}", config).contains("FE2O3_CONFIG_TEST_NOT_SET"));
+    match connection::Builder::from_config(&config) {
+        Err(ConfigError::MissingEnvVar(name)) => assert_eq!(name, "FE2O3_CONFIG_TEST_NOT_SET"),
+        result => panic!("Expecting MissingEnvVar, found {:?}", result.map(|_| ())),
+    }
+}
+
+#[tokio::test]
+async fn connection_session_and_links_from_config() {
+    let addr = spawn_listener("secret").await;
+    std::env::set_var("FE2O3_CONFIG_TEST_PASSWORD", "secret");
+
+    let mut connection_config: ConnectionConfig = serde_json::from_str(CONNECTION_CONFIG).unwrap();
+    connection_config.url = format!("amqp://{}", addr);
+    let mut connection = connection::Builder::from_config(&connection_config)
+        .unwrap()
+        .open(&connection_config.url[..])
+        .await
+        .unwrap();
+
+    let session_config: SessionConfig = serde_json::from_str(SESSION_CONFIG).unwrap();
+    let mut session = session::Builder::from_config(&session_config)
+        .begin(&mut connection)
+        .await
+        .unwrap();
+
+    let sender_config: SenderConfig = serde_json::from_str(SENDER_CONFIG).unwrap();
+    let mut sender = link::builder::Builder::from_config(&sender_config)
+        .attach(&mut session)
+        .await
+        .unwrap();
+    assert_eq!(
+        sender.target().as_ref().and_then(|t| t.address.as_deref()),
+        Some("q1")
+    );
+    let receipt = sender.send("from-config").await.unwrap();
+    assert!(receipt.is_accepted());
+
+    let receiver_config: ReceiverConfig = serde_json::from_str(RECEIVER_CONFIG).unwrap();
+    let mut receiver = link::builder::Builder::from_config(&receiver_config)
+        .attach(&mut session)
+        .await
+        .unwrap();
+    let delivery = receiver.recv::<String>().await.unwrap();
+    assert_eq!(delivery.body(), "from-listener");
+    receiver.accept(&delivery).await.unwrap();
+
+    sender.close().await.unwrap();
+    receiver.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
@@ -0,0 +1,94 @@
+//! Tests of the confirm watermark of a sender
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{acceptor::LinkAcceptor, Connection, Sender, Session};
+
+use common::{expect_receiver, serve_session};
+
+#[tokio::test]
+async fn confirm_watermark_follows_accepted_prefix_of_unconfirmed_sends() {
+    use std::time::Duration;
+
+    use fe2o3_amqp::types::messaging::DeliveryState;
+    use tokio::sync::oneshot;
+
+    let (receiver_tx, receiver_rx) = oneshot::channel();
+    let (addr, _) = serve_session("confirm-listener", move |mut session| async move {
+        let receiver = expect_receiver(LinkAcceptor::new().accept(&mut session).await.unwrap());
+        receiver_tx.send(receiver).unwrap();
+        let _ = session.on_end().await;
+    })
+    .await;
+
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("confirm-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::attach(&mut session, "confirm-sender", "q1")
+        .await
+        .unwrap();
+    let mut receiver = receiver_rx.await.unwrap();
+
+    let mut watermark = sender.confirm_watermark();
+    let mut failures = sender.confirm_failures().unwrap();
+    assert!(sender.confirm_failures().is_none());
+    let mut tags = Vec::new();
+    for i in 0..7 {
+        tags.push(sender.send_unconfirmed(format!("m{}", i)).await.unwrap());
+    }
+    let mut deliveries = Vec::new();
+    for _ in 0..7 {
+        deliveries.push(receiver.recv::<String>().await.unwrap());
+    }
+    assert_eq!(*watermark.borrow_and_update(), u32::MAX);
+
+    let wait_for = |watermark: &mut tokio::sync::watch::Receiver<u32>, delivery_id: u32| {
+        let mut watermark = watermark.clone();
+        async move {
+            tokio::time::timeout(
+                Duration::from_secs(5),
+                watermark.wait_for(|id| *id == delivery_id),
+            )
+            .await
+            .unwrap()
+            .map(|id| *id)
+            .unwrap()
+        }
+    };
+
+    // Out of order
+    receiver.accept(&deliveries[2]).await.unwrap();
+    receiver.accept(&deliveries[1]).await.unwrap();
+    receiver.accept(&deliveries[0]).await.unwrap();
+    assert_eq!(wait_for(&mut watermark, 2).await, 2);
+
+    // A rejected delivery holds the watermark back, the ranged disposition after it does not
+    receiver.reject(&deliveries[3], None).await.unwrap();
+    receiver
+        .accept_all([&deliveries[4], &deliveries[5], &deliveries[6]])
+        .await
+        .unwrap();
+    let failure = tokio::time::timeout(Duration::from_secs(5), failures.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(failure.delivery_id, 3);
+    assert_eq!(failure.delivery_tag, tags[3]);
+    assert!(matches!(failure.state, Some(DeliveryState::Rejected(_))));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(*watermark.borrow(), 2);
+
+    sender.dismiss_confirm_failure(&failure);
+    assert_eq!(wait_for(&mut watermark, 6).await, 6);
+    assert!(failures.try_recv().is_err());
+
+    let (sender_closed, receiver_closed) = tokio::join!(sender.close(), receiver.close());
+    sender_closed.unwrap();
+    receiver_closed.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
@@ -0,0 +1,210 @@
+//! Tests of the open frames exchanged with the connection acceptor
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{
+    acceptor::ConnectionAcceptor,
+    connection::OpenError,
+    types::{
+        definitions::Fields,
+        performatives::Open,
+        primitives::{Symbol, Value},
+    },
+    Connection,
+};
+
+use common::{bind, serve_connection};
+
+#[tokio::test]
+async fn connection_properties_are_exchanged() {
+    let mut listener_properties = Fields::new();
+    listener_properties.insert(Symbol::from("product"), Value::from("test-listener"));
+    let acceptor = ConnectionAcceptor::builder()
+        .container_id("test-listener")
+        .properties(listener_properties)
+        .add_offered_capabilities("test-capability")
+        .properties_with(|remote_open: &Open| {
+            let mut properties = Fields::new();
+            if let Some(request_id) = remote_open
+                .properties
+                .as_ref()
+                .and_then(|p| p.get(&Symbol::from("request-id")))
+            {
+                properties.insert(Symbol::from("request-id"), request_id.clone());
+            }
+            properties
+        })
+        .build();
+
+    let (addr, listener) = serve_connection(acceptor, |mut connection| async move {
+        let remote_open = connection.remote_open().clone();
+        let _ = connection.on_close().await;
+        remote_open
+    })
+    .await;
+
+    let mut client_properties = Fields::new();
+    client_properties.insert(Symbol::from("product"), Value::from("test-client"));
+    client_properties.insert(Symbol::from("request-id"), Value::from(42u32));
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::builder()
+        .container_id("test-client")
+        .properties(client_properties)
+        .open(&url[..])
+        .await
+        .unwrap();
+
+    let remote_open = connection.remote_open();
+    assert_eq!(remote_open.container_id, "test-listener");
+    let offered = remote_open.offered_capabilities.as_ref().unwrap();
+    assert!(offered.0.contains(&Symbol::from("test-capability")));
+    let properties = remote_open.properties.as_ref().unwrap();
+    assert_eq!(
+        properties.get(&Symbol::from("product")),
+        Some(&Value::from("test-listener"))
+    );
+    assert_eq!(
+        properties.get(&Symbol::from("request-id")),
+        Some(&Value::from(42u32))
+    );
+
+    connection.close().await.unwrap();
+
+    let client_open = listener.await.unwrap();
+    assert_eq!(client_open.container_id, "test-client");
+    assert_eq!(
+        client_open
+            .properties
+            .as_ref()
+            .unwrap()
+            .get(&Symbol::from("product")),
+        Some(&Value::from("test-client"))
+    );
+}
+
+#[tokio::test]
+async fn open_carries_hostname_override_and_generated_container_id() {
+    let (tcp_listener, addr) = bind().await;
+    let acceptor = ConnectionAcceptor::new("test-listener");
+
+    let listener = tokio::spawn(async move {
+        let mut remote_opens = Vec::new();
+        for _ in 0..2 {
+            let (stream, _) = tcp_listener.accept().await.unwrap();
+            let mut connection = acceptor.accept(stream).await.unwrap();
+            remote_opens.push(connection.remote_open().clone());
+            let _ = connection.on_close().await;
+        }
+        remote_opens
+    });
+
+    let url = format!("amqp://localhost:{}", addr.port());
+    let mut connection = Connection::builder()
+        .container_id_auto()
+        .open(&url[..])
+        .await
+        .unwrap();
+    connection.close().await.unwrap();
+
+    let mut connection = Connection::builder()
+        .container_id_auto()
+        .hostname("vhost-1")
+        .open(&url[..])
+        .await
+        .unwrap();
+    connection.close().await.unwrap();
+
+    let remote_opens = listener.await.unwrap();
+    assert!(remote_opens[0].container_id.starts_with("fe2o3-"));
+    assert_eq!(remote_opens[0].hostname.as_deref(), Some("localhost"));
+    assert!(remote_opens[1].container_id.starts_with("fe2o3-"));
+    assert_ne!(remote_opens[0].container_id, remote_opens[1].container_id);
+    assert_eq!(remote_opens[1].hostname.as_deref(), Some("vhost-1"));
+}
+
+#[tokio::test]
+async fn url_credentials_are_percent_decoded_and_query_sets_connection_options() {
+    use fe2o3_amqp::{
+        acceptor::SaslPlainMechanism,
+        connection::{DEFAULT_CHANNEL_MAX, DEFAULT_MAX_FRAME_SIZE},
+    };
+
+    let (tcp_listener, addr) = bind().await;
+    let acceptor = ConnectionAcceptor::builder()
+        .container_id("url-listener")
+        .sasl_acceptor(SaslPlainMechanism::new("us@er", "p@ss/wörd:%"))
+        .build();
+    let (remote_open_tx, mut remote_open_rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = tcp_listener.accept().await {
+            let mut connection = acceptor.accept(stream).await.unwrap();
+            let _ = remote_open_tx.send(connection.remote_open().clone());
+            let _ = connection.on_close().await;
+        }
+    });
+
+    let url = format!(
+        "amqp://us%40er:p%40ss%2Fw%C3%B6rd%3A%25@{}?container_id=cli-tool&idle_timeout_ms=30000&max_frame_size=65536&channel_max=7",
+        addr
+    );
+    let mut connection = Connection::builder()
+        .container_id_auto()
+        .open(&url[..])
+        .await
+        .unwrap();
+    let remote_open = remote_open_rx.recv().await.unwrap();
+    assert_eq!(remote_open.container_id, "cli-tool");
+    // Half of the idle time-out is sent to the remote peer
+    assert_eq!(remote_open.idle_time_out, Some(15000));
+    assert_eq!(remote_open.max_frame_size.0, 65536);
+    assert_eq!(remote_open.channel_max.0, 7);
+    connection.close().await.unwrap();
+
+    // The builder settings take precedence over the url
+    let mut connection = Connection::builder()
+        .container_id("explicit-id")
+        .idle_time_out(10000u32)
+        .max_frame_size(4096)
+        .open(&url[..])
+        .await
+        .unwrap();
+    let remote_open = remote_open_rx.recv().await.unwrap();
+    assert_eq!(remote_open.container_id, "explicit-id");
+    assert_eq!(remote_open.idle_time_out, Some(5000));
+    assert_eq!(remote_open.max_frame_size.0, 4096);
+    connection.close().await.unwrap();
+
+    // Including settings that are explicitly set to their default values
+    let mut connection = Connection::builder()
+        .container_id("default-valued-id")
+        .max_frame_size(DEFAULT_MAX_FRAME_SIZE)
+        .channel_max(DEFAULT_CHANNEL_MAX)
+        .open(&url[..])
+        .await
+        .unwrap();
+    let remote_open = remote_open_rx.recv().await.unwrap();
+    assert_eq!(remote_open.max_frame_size.0, DEFAULT_MAX_FRAME_SIZE);
+    assert_eq!(remote_open.channel_max.0, DEFAULT_CHANNEL_MAX);
+    connection.close().await.unwrap();
+
+    // Unknown query parameters are ignored unless the builder is strict
+    let url = format!("amqp://us%40er:p%40ss%2Fw%C3%B6rd%3A%25@{}?foo=bar", addr);
+    let mut connection = Connection::open("lenient-connection", &url[..])
+        .await
+        .unwrap();
+    connection.close().await.unwrap();
+    let result = Connection::builder()
+        .container_id("strict-connection")
+        .strict_url_params(true)
+        .open(&url[..])
+        .await;
+    assert!(matches!(result, Err(OpenError::UnknownUrlParam(name)) if name == "foo"));
+
+    let url = format!("amqp://{}?idle_timeout_ms=soon", addr);
+    let result = Connection::open("invalid-connection", &url[..]).await;
+    assert!(
+        matches!(result, Err(OpenError::InvalidUrlParam { name, .. }) if name == "idle_timeout_ms")
+    );
+}
@@ -0,0 +1,97 @@
+//! Tests of sharing a credit pool between receivers
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{
+    acceptor::LinkAcceptor, types::definitions::SenderSettleMode, Connection, Receiver, Session,
+};
+
+use common::{expect_sender, serve_session};
+
+#[tokio::test]
+async fn credit_pool_gives_busy_links_more_credit() {
+    use std::time::Duration;
+
+    use fe2o3_amqp::link::{CreditPolicy, CreditPool};
+
+    const BUDGET: u32 = 200;
+
+    let (addr, _) = serve_session("credit-pool-listener", move |mut session| async move {
+        let link_acceptor = LinkAcceptor::new();
+        for _ in 0..3 {
+            let mut sender = expect_sender(link_acceptor.accept(&mut session).await.unwrap());
+            // The busy link sends as fast as it gets credit, the slow link sends now and then
+            // and the idle link never sends
+            let delay = match sender.name() {
+                "busy" => Some(Duration::ZERO),
+                "slow" => Some(Duration::from_millis(25)),
+                _ => None,
+            };
+            tokio::spawn(async move {
+                let Some(delay) = delay else {
+                    let _ = sender.on_detach().await;
+                    return;
+                };
+                loop {
+                    if !delay.is_zero() {
+                        tokio::time::sleep(delay).await;
+                    }
+                    if sender.send("message").await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        let _ = session.on_end().await;
+    })
+    .await;
+
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("credit-pool-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let pool = CreditPool::new(BUDGET).policy(CreditPolicy::Demand);
+
+    let mut receivers = Vec::new();
+    for name in ["busy", "slow", "idle"] {
+        // Pre-settled deliveries let the busy sender go as fast as its link credit allows
+        let mut receiver = Receiver::builder()
+            .name(name)
+            .source(name)
+            .sender_settle_mode(SenderSettleMode::Settled)
+            .attach(&mut session)
+            .await
+            .unwrap();
+        receiver.join_credit_pool(&pool, 1).await.unwrap();
+        receivers.push(receiver);
+    }
+    let idle = receivers.pop().unwrap();
+    for mut receiver in receivers {
+        tokio::spawn(async move { while receiver.recv::<String>().await.is_ok() {} });
+    }
+
+    for _ in 0..10 {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        pool.rebalance().await;
+    }
+    let stats = pool.stats();
+    let share = |name: &str| stats.iter().find(|link| link.name == name).unwrap().share;
+    assert!(stats.iter().map(|link| link.share).sum::<u32>() <= BUDGET);
+    assert!(share("busy") > BUDGET / 2, "{:?}", stats);
+    assert!(share("slow") >= share("idle"), "{:?}", stats);
+    assert!(share("idle") <= 1, "{:?}", stats);
+    assert_eq!(idle.credit_pool_share(), Some(share("idle")));
+    assert!(idle.flow_snapshot().link_credit <= 1);
+
+    // A detached link leaves the pool
+    idle.close().await.unwrap();
+    assert_eq!(pool.len(), 2);
+    pool.rebalance().await;
+    assert!(pool.stats().iter().all(|link| link.name != "idle"));
+
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
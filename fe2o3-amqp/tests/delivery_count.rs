@@ -0,0 +1,39 @@
+//! Tests of wrapping transfer ids and delivery counts around
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{Connection, SendReceipt, Sender, Session};
+
+use common::spawn_listener;
+
+#[tokio::test]
+async fn transfer_ids_and_delivery_count_wrap_around() {
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("wrap-around-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::builder()
+        .next_outgoing_id(u32::MAX - 5)
+        .begin(&mut connection)
+        .await
+        .unwrap();
+    let mut sender = Sender::builder()
+        .name("wrap-around-sender")
+        .target("q1")
+        .initial_delivery_count(u32::MAX - 5)
+        .attach(&mut session)
+        .await
+        .unwrap();
+
+    for i in 0..16 {
+        let receipt = sender.send(format!("message-{}", i)).await.unwrap();
+        assert!(matches!(receipt, SendReceipt::Accepted(_)));
+    }
+
+    sender.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
@@ -0,0 +1,79 @@
+//! Tests of disposing deliveries by their info
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{Connection, Receiver, Session};
+
+use common::spawn_listener;
+
+#[tokio::test]
+async fn deliveries_are_disposed_by_info_after_the_message_is_consumed() {
+    use fe2o3_amqp::link::{delivery::DeliveryInfo, DispositionError};
+
+    fn assert_send_static<T: Send + 'static>(_: &T) {}
+
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("delivery-info-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut receiver = Receiver::builder()
+        .name("delivery-info-receiver")
+        .source("q1")
+        .attach(&mut session)
+        .await
+        .unwrap();
+    let mut other = Receiver::builder()
+        .name("delivery-info-other")
+        .source("q2")
+        .attach(&mut session)
+        .await
+        .unwrap();
+
+    // The message is moved into another task, which hands the info back for the disposition
+    let (info_tx, mut info_rx) = tokio::sync::mpsc::unbounded_channel::<DeliveryInfo>();
+    let (info, message) = receiver.recv::<String>().await.unwrap().into_parts();
+    assert_send_static(&info);
+    tokio::spawn(async move {
+        assert_eq!(message.body, "message-0");
+        info_tx.send(info).unwrap();
+    });
+    let info = info_rx.recv().await.unwrap();
+    receiver.accept(info.clone()).await.unwrap();
+    let delivery_id = info.delivery_id();
+    assert!(matches!(
+        receiver.accept(info.clone()).await,
+        Err(DispositionError::AlreadySettled(id)) if id == delivery_id
+    ));
+
+    // A delivery received by another receiver is not disposed
+    let other_delivery = other.recv::<String>().await.unwrap();
+    let other_id = *other_delivery.delivery_id();
+    assert!(matches!(
+        receiver.release(&other_delivery).await,
+        Err(DispositionError::LinkMismatch(id)) if id == other_id
+    ));
+    other.accept(other_delivery.info()).await.unwrap();
+
+    // The batch fails as a whole and the unsettled delivery is kept
+    let delivery = receiver.recv::<String>().await.unwrap();
+    assert_eq!(delivery.body(), "message-1");
+    assert!(matches!(
+        receiver.accept_all(vec![delivery.info(), info]).await,
+        Err(DispositionError::AlreadySettled(id)) if id == delivery_id
+    ));
+    assert_eq!(receiver.flow_snapshot().unsettled, 1);
+    receiver.accept_all(vec![delivery.info()]).await.unwrap();
+    assert_eq!(receiver.flow_snapshot().unsettled, 0);
+
+    let delivery = receiver.recv::<String>().await.unwrap();
+    assert_eq!(delivery.into_body(), "message-2");
+
+    drop(receiver);
+    drop(other);
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
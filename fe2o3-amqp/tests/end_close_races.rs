@@ -0,0 +1,204 @@
+//! Tests of remote ends and closes racing local operations
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use std::net::SocketAddr;
+
+use fe2o3_amqp::{
+    acceptor::{
+        ConnectionAcceptor, LinkAcceptor, LinkEndpoint, ListenerConnectionHandle,
+        ListenerSessionHandle, SessionAcceptor,
+    },
+    link::{DetachError, LinkStateError, SendError},
+    types::definitions::{self, AmqpError},
+    Connection, Sender, Session,
+};
+
+use common::serve_connection;
+
+/// Accepts `links` links and hands the listener's connection, session and links over to the
+/// test, which then scripts how the listener races the client. The links are never read, so the
+/// Detach of the client is not answered
+async fn spawn_scripted_listener(
+    container_id: &'static str,
+    links: usize,
+) -> (
+    SocketAddr,
+    tokio::sync::oneshot::Receiver<(
+        ListenerConnectionHandle,
+        ListenerSessionHandle,
+        Vec<LinkEndpoint>,
+    )>,
+) {
+    use fe2o3_amqp::link::receiver::CreditMode;
+
+    let (endpoints_tx, endpoints_rx) = tokio::sync::oneshot::channel();
+    let (addr, _) = serve_connection(
+        ConnectionAcceptor::new(container_id),
+        move |mut connection| async move {
+            let mut session = SessionAcceptor::new()
+                .accept(&mut connection)
+                .await
+                .unwrap();
+            let link_acceptor = LinkAcceptor::builder()
+                .credit_mode(CreditMode::Manual)
+                .build();
+            let mut endpoints = Vec::with_capacity(links);
+            for _ in 0..links {
+                endpoints.push(link_acceptor.accept(&mut session).await.unwrap());
+            }
+            let _ = endpoints_tx.send((connection, session, endpoints));
+        },
+    )
+    .await;
+    (addr, endpoints_rx)
+}
+
+/// How long a link or session operation may take to resolve once the session or connection
+/// has ended under it
+const RACE_BOUND: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[tokio::test]
+async fn remote_end_racing_link_close_resolves_close() {
+    use std::time::Duration;
+
+    let error = definitions::Error::new(AmqpError::InternalError, None, None);
+    // The End arrives before the close starts, together with the Detach of the client, and while
+    // the close is waiting for the remote Detach
+    for delay in [0, 10, 100] {
+        let (addr, endpoints_rx) = spawn_scripted_listener("end-close-race-listener", 1).await;
+        let url = format!("amqp://{}", addr);
+        let mut connection = Connection::open("end-close-race-connection", &url[..])
+            .await
+            .unwrap();
+        let mut session = Session::begin(&mut connection).await.unwrap();
+        let sender = Sender::attach(&mut session, "end-close-race-sender", "q1")
+            .await
+            .unwrap();
+        let (mut listener_connection, mut listener_session, _endpoints) =
+            endpoints_rx.await.unwrap();
+
+        let close = tokio::spawn(sender.close());
+        tokio::time::sleep(Duration::from_millis(delay)).await;
+        let end_error = error.clone();
+        let end = tokio::spawn(async move { listener_session.end_with_error(end_error).await });
+
+        let result = tokio::time::timeout(RACE_BOUND, close)
+            .await
+            .expect("close must resolve once the session has ended")
+            .unwrap();
+        match result {
+            Err(DetachError::SessionEnded(Some(e))) => assert_eq!(e, error),
+            // The session may have stopped before the Detach could be sent
+            Err(DetachError::IllegalSessionState) if delay == 0 => {}
+            other => panic!("Expecting SessionEnded, found {:?}", other),
+        }
+        match tokio::time::timeout(RACE_BOUND, session.on_end())
+            .await
+            .unwrap()
+        {
+            Err(fe2o3_amqp::session::Error::RemoteEndedWithError(e)) => assert_eq!(e, error),
+            other => panic!("Expecting RemoteEndedWithError, found {:?}", other),
+        }
+        end.await.unwrap().unwrap();
+
+        let _ = tokio::join!(connection.close(), listener_connection.on_close());
+    }
+}
+
+#[tokio::test]
+async fn remote_end_resolves_link_detach_and_send_waiting_for_credit() {
+    use std::time::Duration;
+
+    let (addr, endpoints_rx) = spawn_scripted_listener("end-detach-race-listener", 2).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("end-detach-race-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let sender = Sender::attach(&mut session, "end-detach-race-sender", "q1")
+        .await
+        .unwrap();
+    // The listener issues no credit, so the send waits until the session ends
+    let mut waiting = Sender::attach(&mut session, "end-send-race-sender", "q2")
+        .await
+        .unwrap();
+    let (mut listener_connection, mut listener_session, _endpoints) = endpoints_rx.await.unwrap();
+    let send = tokio::spawn(async move { waiting.send("never").await.map(|_| ()) });
+    let detach = tokio::spawn(sender.detach());
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    listener_session.end().await.unwrap();
+
+    let detach = tokio::time::timeout(RACE_BOUND, detach)
+        .await
+        .expect("detach must resolve once the session has ended")
+        .unwrap();
+    match detach {
+        Err((_, DetachError::SessionEnded(None))) => {}
+        other => panic!("Expecting SessionEnded, found {:?}", other.map(|_| ())),
+    }
+    let send = tokio::time::timeout(RACE_BOUND, send)
+        .await
+        .expect("send must resolve once the session has ended")
+        .unwrap();
+    match send {
+        Err(SendError::LinkStateError(LinkStateError::SessionEnded(None))) => {}
+        other => panic!("Expecting SessionEnded, found {:?}", other),
+    }
+    // The session is not usable anymore, so a new link fails right away
+    let attach = tokio::time::timeout(
+        RACE_BOUND,
+        Sender::attach(&mut session, "end-attach-race-sender", "q3"),
+    )
+    .await
+    .unwrap();
+    assert!(attach.is_err());
+
+    let _ = tokio::join!(connection.close(), listener_connection.on_close());
+}
+
+#[tokio::test]
+async fn remote_close_resolves_link_close_and_session_end() {
+    use std::time::Duration;
+
+    let (addr, endpoints_rx) = spawn_scripted_listener("close-race-listener", 1).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("close-race-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let sender = Sender::attach(&mut session, "close-race-sender", "q1")
+        .await
+        .unwrap();
+    let (mut listener_connection, _listener_session, _endpoints) = endpoints_rx.await.unwrap();
+
+    let close = tokio::spawn(sender.close());
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let listener_close = tokio::spawn(async move { listener_connection.close().await });
+
+    let result = tokio::time::timeout(RACE_BOUND, close)
+        .await
+        .expect("close must resolve once the connection has closed")
+        .unwrap();
+    assert!(
+        matches!(
+            result,
+            Err(DetachError::SessionEnded(None)) | Err(DetachError::IllegalSessionState)
+        ),
+        "Expecting the link to find out that the session has stopped, found {:?}",
+        result
+    );
+    let end = tokio::time::timeout(RACE_BOUND, session.end())
+        .await
+        .expect("end must resolve once the connection has closed");
+    assert!(end.is_err());
+
+    let _ = tokio::time::timeout(RACE_BOUND, listener_close)
+        .await
+        .unwrap();
+    let _ = tokio::time::timeout(RACE_BOUND, connection.on_close())
+        .await
+        .unwrap();
+}
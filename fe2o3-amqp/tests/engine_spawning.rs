@@ -0,0 +1,118 @@
+//! Tests of spawning the connection, session and link engines
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{Connection, Sender, Session};
+
+use common::spawn_listener;
+
+#[tokio::test]
+async fn unspawned_engines_are_driven_by_a_join_set() {
+    use std::time::Duration;
+
+    use tokio::task::JoinSet;
+
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+
+    let mut tasks = JoinSet::new();
+    let (mut connection, engine) = Connection::builder()
+        .container_id("unspawned-connection")
+        .open_unspawned(&url[..])
+        .await
+        .unwrap();
+    tasks.spawn(engine);
+    let (mut session, engine) = Session::builder()
+        .begin_unspawned(&mut connection)
+        .await
+        .unwrap();
+    tasks.spawn(engine);
+
+    let mut sender = Sender::attach(&mut session, "unspawned-sender", "q1")
+        .await
+        .unwrap();
+    assert!(sender.send("hello").await.unwrap().is_accepted());
+    sender.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+
+    // The event loops complete once the session is ended and the connection is closed
+    while let Some(result) = tasks.join_next().await {
+        result.unwrap();
+    }
+
+    // Dropping the handles still ends the session and closes the connection
+    let (mut connection, engine) = Connection::builder()
+        .container_id("unspawned-connection")
+        .open_unspawned(&url[..])
+        .await
+        .unwrap();
+    tasks.spawn(engine);
+    let session = Session::begin(&mut connection).await.unwrap();
+    drop(session);
+    drop(connection);
+    tokio::time::timeout(Duration::from_secs(5), tasks.join_next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+}
+
+#[cfg(not(feature = "rt-async-std"))]
+#[tokio::test]
+async fn engines_are_spawned_on_the_given_runtime() {
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .enable_all()
+        .build()
+        .unwrap();
+    let mut connection = Connection::builder()
+        .container_id("spawn-on-connection")
+        .spawn_on(runtime.handle().clone())
+        .open(&url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::attach(&mut session, "spawn-on-sender", "q1")
+        .await
+        .unwrap();
+    assert!(sender.send("hello").await.unwrap().is_accepted());
+
+    // The event loops stop with the runtime they are spawned on
+    runtime.shutdown_background();
+    assert!(sender.send("hello").await.is_err());
+    assert!(session.on_end().await.is_err());
+    assert!(connection.on_close().await.is_err());
+}
+
+#[cfg(not(feature = "rt-async-std"))]
+#[tokio::test]
+async fn engines_are_spawned_on_the_current_local_set() {
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+
+    let local_set = tokio::task::LocalSet::new();
+    local_set
+        .run_until(async move {
+            let mut connection = Connection::builder()
+                .container_id("spawn-local-connection")
+                .spawn_local(true)
+                .open(&url[..])
+                .await
+                .unwrap();
+            let mut session = Session::begin(&mut connection).await.unwrap();
+            let mut sender = Sender::attach(&mut session, "spawn-local-sender", "q1")
+                .await
+                .unwrap();
+            assert!(sender.send("hello").await.unwrap().is_accepted());
+            sender.close().await.unwrap();
+            session.end().await.unwrap();
+            connection.close().await.unwrap();
+        })
+        .await;
+}
@@ -0,0 +1,40 @@
+//! Tests of the errors kept from the remote peer
+
+#![cfg(all(
+    feature = "acceptor",
+    feature = "transaction",
+    not(target_arch = "wasm32")
+))]
+
+mod common;
+
+use fe2o3_amqp::{link::SenderAttachError, types::definitions::AmqpError, Connection, Session};
+
+use common::spawn_listener;
+
+#[tokio::test]
+async fn refused_attach_keeps_remote_detach() {
+    use fe2o3_amqp::transaction::Controller;
+
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("refused-attach-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+
+    // The listener does not accept control links and refuses the attach with a closing Detach
+    let error = match Controller::attach(&mut session, "refused-controller").await {
+        Err(error @ SenderAttachError::RemoteDetached(_)) => error,
+        other => panic!("Expecting RemoteDetached, found {:?}", other.map(|_| ())),
+    };
+    let remote_error = error.remote_error().unwrap();
+    assert_eq!(remote_error.condition, AmqpError::NotImplemented.into());
+    match error {
+        SenderAttachError::RemoteDetached(detach) => assert!(detach.closed),
+        _ => unreachable!(),
+    }
+
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
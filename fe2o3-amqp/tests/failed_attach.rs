@@ -0,0 +1,78 @@
+//! Tests of attaches that fail
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{
+    acceptor::{LinkAcceptor, LinkEndpoint},
+    link::SenderAttachError,
+    Connection, Sender, Session,
+};
+
+use common::{receiver_main, serve_session};
+
+#[tokio::test]
+async fn failed_attaches_do_not_leak_handles() {
+    use fe2o3_amqp::types::messaging::Target;
+
+    const REFUSED_COUNT: usize = 1000;
+
+    let (addr, _) = serve_session("refusing-listener", move |mut session| async move {
+        // Dynamic targets are refused until the acceptor starts creating them
+        let refusing = LinkAcceptor::new();
+        let accepting = LinkAcceptor::builder()
+            .on_dynamic_target(|target| {
+                Some(Target {
+                    address: Some("dynamic-q1".to_string()),
+                    ..target
+                })
+            })
+            .build();
+        let mut refused = 0;
+        while let Some(attach) = session.next_incoming_attach().await {
+            if refused < REFUSED_COUNT {
+                refused += 1;
+                let _ = refusing.accept_incoming_attach(attach, &mut session).await;
+            } else if let Ok(LinkEndpoint::Receiver(receiver)) =
+                accepting.accept_incoming_attach(attach, &mut session).await
+            {
+                tokio::spawn(receiver_main(receiver));
+            }
+        }
+    })
+    .await;
+
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("failed-attach-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::builder()
+        .handle_max(8u32)
+        .begin(&mut connection)
+        .await
+        .unwrap();
+
+    for _ in 0..REFUSED_COUNT {
+        let result = Sender::builder()
+            .name("retried-sender")
+            .target(Target::builder().dynamic(true).build())
+            .attach(&mut session)
+            .await;
+        assert!(matches!(
+            result,
+            Err(SenderAttachError::IncomingTargetIsNone)
+        ));
+    }
+
+    let mut sender = Sender::builder()
+        .name("retried-sender")
+        .target(Target::builder().dynamic(true).build())
+        .attach(&mut session)
+        .await
+        .unwrap();
+    assert!(sender.send("hello").await.unwrap().is_accepted());
+    sender.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
@@ -0,0 +1,78 @@
+//! Tests of the properties carried by flow frames
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{
+    types::{
+        definitions::Fields,
+        primitives::{Symbol, Value},
+    },
+    Connection, Sender, Session,
+};
+
+use common::{expect_receiver, spawn_single_link_listener};
+
+#[tokio::test]
+async fn flow_properties_set_mid_stream_are_seen_by_the_peer() {
+    use std::time::Duration;
+
+    const TRACKING_ID: &str = "com.microsoft:tracking-id";
+
+    fn tracking_id(value: &str) -> Fields {
+        let mut properties = Fields::new();
+        properties.insert(Symbol::from(TRACKING_ID), Value::from(value));
+        properties
+    }
+
+    let (addr, endpoint_rx) = spawn_single_link_listener("flow-properties-listener").await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("flow-properties-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::attach(&mut session, "flow-properties-sender", "q1")
+        .await
+        .unwrap();
+    let mut receiver = expect_receiver(endpoint_rx.await.unwrap());
+    assert_eq!(sender.remote_flow_properties(), None);
+
+    // The flow that issues the credit carries the properties set by the receiver
+    receiver.set_flow_properties(tracking_id("from-receiver"));
+    receiver.set_credit(1).await.unwrap();
+    let send = tokio::spawn(async move {
+        sender.send("hello").await.unwrap();
+        sender
+    });
+    let delivery = receiver.recv::<String>().await.unwrap();
+    receiver.accept(&delivery).await.unwrap();
+    let mut sender = send.await.unwrap();
+    assert_eq!(
+        sender.remote_flow_properties(),
+        Some(tracking_id("from-receiver"))
+    );
+
+    // The flow that the sender sends in reply to a drain carries the properties set by the
+    // sender after the link was attached
+    sender.set_flow_properties(tracking_id("from-sender"));
+    receiver.set_credit(1).await.unwrap();
+    receiver.drain().await.unwrap();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while receiver.remote_flow_properties().is_none() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(
+        receiver.remote_flow_properties(),
+        Some(tracking_id("from-sender"))
+    );
+
+    let (sender_closed, receiver_closed) = tokio::join!(sender.close(), receiver.close());
+    sender_closed.unwrap();
+    receiver_closed.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
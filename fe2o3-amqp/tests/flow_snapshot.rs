@@ -0,0 +1,54 @@
+//! Tests of the flow state snapshots of a link
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{types::primitives::Value, Connection, Receiver, Sender, Session};
+
+use common::spawn_listener;
+
+#[tokio::test]
+async fn flow_snapshot_tracks_credit_and_unsettled_deliveries() {
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("flow-snapshot-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+
+    let mut sender = Sender::attach(&mut session, "flow-snapshot-sender", "q1")
+        .await
+        .unwrap();
+    let receipt = sender.send("accept").await.unwrap();
+    assert!(receipt.is_accepted());
+    let snapshot = sender.flow_snapshot();
+    assert_eq!(snapshot.delivery_count, 1);
+    assert_eq!(snapshot.unsettled, 0);
+    assert!(format!("{:?}", sender).contains("delivery_count: 1"));
+    sender.close().await.unwrap();
+
+    let mut receiver = Receiver::builder()
+        .name("flow-snapshot-receiver")
+        .source("q1")
+        .credit_mode(fe2o3_amqp::link::receiver::CreditMode::Manual)
+        .attach(&mut session)
+        .await
+        .unwrap();
+    assert_eq!(receiver.flow_snapshot().link_credit, 0);
+    receiver.set_credit(1).await.unwrap();
+    assert_eq!(receiver.flow_snapshot().link_credit, 1);
+
+    let delivery = receiver.recv::<Value>().await.unwrap();
+    let snapshot = receiver.flow_snapshot();
+    assert_eq!(snapshot.link_credit, 0);
+    assert_eq!(snapshot.delivery_count, 1);
+    assert_eq!(snapshot.unsettled, 1);
+    receiver.accept(&delivery).await.unwrap();
+    assert_eq!(receiver.flow_snapshot().unsettled, 0);
+    assert!(format!("{:?}", receiver).contains("unsettled: 0"));
+
+    receiver.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
@@ -0,0 +1,53 @@
+//! Tests of validating the delivery count of incoming link flows
+
+#![cfg(all(feature = "acceptor", feature = "testing", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{types::definitions::AmqpError, Connection, Receiver, Session};
+
+use common::{expect_sender, spawn_single_link_listener};
+
+#[tokio::test]
+async fn link_flow_with_delivery_count_ahead_of_sender_ends_session() {
+    use fe2o3_amqp::{session::SessionFrameBody, types::performatives::Flow};
+
+    let (addr, endpoint_rx) = spawn_single_link_listener("delivery-count-listener").await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("delivery-count-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let _receiver = Receiver::attach(&mut session, "delivery-count-receiver", "q1")
+        .await
+        .unwrap();
+    let _sender = expect_sender(endpoint_rx.await.unwrap());
+
+    // The accepted sender has not sent any delivery, so the receiver cannot have counted five
+    let flow = Flow {
+        next_incoming_id: Some(0),
+        incoming_window: 2048,
+        next_outgoing_id: 0,
+        outgoing_window: 2048,
+        handle: Some(0.into()),
+        delivery_count: Some(5),
+        link_credit: Some(10),
+        available: None,
+        drain: false,
+        echo: false,
+        properties: None,
+    };
+    session
+        .send_raw(SessionFrameBody::Flow(flow))
+        .await
+        .unwrap();
+
+    match session.on_end().await {
+        Err(fe2o3_amqp::session::Error::RemoteEndedWithError(error)) => {
+            assert_eq!(error.condition, AmqpError::InvalidField.into());
+            assert!(error.description.unwrap().contains("delivery-count"));
+        }
+        result => panic!("Expecting RemoteEndedWithError, found {:?}", result),
+    }
+    connection.close().await.unwrap();
+}
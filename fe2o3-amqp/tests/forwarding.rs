@@ -0,0 +1,94 @@
+//! Tests of forwarding messages between links
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{
+    acceptor::LinkAcceptor, types::primitives::Value, Connection, Receiver, Sender, Session,
+};
+
+use common::{expect_receiver, expect_sender, serve_session};
+
+#[tokio::test]
+async fn forwarded_message_keeps_sections_except_delivery_annotations() {
+    use fe2o3_amqp::types::messaging::{
+        AmqpValue, DeliveryAnnotations, Footer, Header, Message, MessageAnnotations, Properties,
+    };
+    use tokio::sync::oneshot;
+
+    let delivery_annotations = DeliveryAnnotations::builder()
+        .insert("x-next-hop", "router")
+        .build();
+    let header = Header::builder().durable(true).build();
+    let message_annotations = MessageAnnotations::builder()
+        .insert("x-end-to-end", 1i32)
+        .build();
+    let properties = Properties::builder().message_id(7u64).build();
+    let footer = Footer::builder().insert("x-checksum", 2i32).build();
+
+    let (stripped_tx, stripped_rx) = oneshot::channel();
+    let (addr, _) = serve_session("router-listener", move |mut session| async move {
+        let link_acceptor = LinkAcceptor::new();
+        let mut incoming = expect_receiver(link_acceptor.accept(&mut session).await.unwrap());
+        let mut outgoing = expect_sender(link_acceptor.accept(&mut session).await.unwrap());
+
+        let mut delivery = incoming.recv_raw().await.unwrap();
+        let stripped = delivery.strip_delivery_annotations().unwrap();
+        let receipt = outgoing.forward(&delivery).await.unwrap();
+        assert!(receipt.is_accepted());
+        incoming.accept(&delivery).await.unwrap();
+        stripped_tx.send(stripped).unwrap();
+
+        let _ = incoming.close().await;
+        let _ = outgoing.close().await;
+        let _ = session.on_end().await;
+    })
+    .await;
+
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("router-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::attach(&mut session, "router-sender", "router-in")
+        .await
+        .unwrap();
+    let mut receiver = Receiver::attach(&mut session, "router-receiver", "router-out")
+        .await
+        .unwrap();
+
+    let message = Message::builder()
+        .header(header.clone())
+        .delivery_annotations(delivery_annotations.clone())
+        .message_annotations(message_annotations.clone())
+        .properties(properties.clone())
+        .value("hello router")
+        .footer(footer.clone())
+        .build();
+    let fut = sender.send_batchable(message).await.unwrap();
+
+    let delivery = receiver.recv_raw().await.unwrap();
+    let expected = [
+        serde_amqp::to_vec(&header).unwrap(),
+        serde_amqp::to_vec(&message_annotations).unwrap(),
+        serde_amqp::to_vec(&properties).unwrap(),
+        serde_amqp::to_vec(&AmqpValue("hello router")).unwrap(),
+        serde_amqp::to_vec(&footer).unwrap(),
+    ]
+    .concat();
+    assert_eq!(&delivery.payload()[..], &expected[..]);
+    assert!(delivery.delivery_annotations().unwrap().is_none());
+    let forwarded = delivery.decode::<Value>().unwrap();
+    assert_eq!(forwarded.message_annotations, Some(message_annotations));
+    assert_eq!(forwarded.body, Value::from("hello router"));
+    receiver.accept(&delivery).await.unwrap();
+
+    assert!(fut.await.unwrap().is_accepted());
+    assert_eq!(stripped_rx.await.unwrap(), Some(delivery_annotations));
+
+    sender.close().await.unwrap();
+    receiver.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
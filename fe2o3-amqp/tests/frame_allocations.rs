@@ -0,0 +1,158 @@
+//! Counts the allocations made by decoding a stream of small Transfer frames
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use fe2o3_amqp::{
+    frames::amqp::{FrameBody, FrameDecoder},
+    types::{
+        definitions::DeliveryTag,
+        performatives::{Performative, Transfer},
+    },
+};
+use serde::Deserialize;
+use serde_amqp::{de::Deserializer, read::IoReader};
+use tokio_util::codec::{Decoder, LengthDelimitedCodec};
+
+const FRAME_COUNT: usize = 10_000;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn count_allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+/// Encodes the frames the way they arrive on the wire, each prefixed with its size
+fn encode_transfer_frames() -> BytesMut {
+    let mut buf = BytesMut::new();
+    for i in 0..FRAME_COUNT {
+        let transfer = Transfer {
+            handle: 0.into(),
+            delivery_id: Some(i as u32),
+            delivery_tag: Some(DeliveryTag::from((i as u32).to_be_bytes().to_vec())),
+            message_format: Some(0),
+            settled: Some(false),
+            more: false,
+            rcv_settle_mode: None,
+            state: None,
+            resume: false,
+            aborted: false,
+            batchable: false,
+        };
+        let performative = serde_amqp::to_vec(&Performative::Transfer(transfer)).unwrap();
+        let payload = b"\x00\x53\x77\xa1\x05hello";
+        let size = 8 + performative.len() + payload.len();
+        buf.put_u32(size as u32);
+        buf.put_u8(2); // doff
+        buf.put_u8(0); // AMQP frame type
+        buf.put_u16(1); // channel
+        buf.put_slice(&performative);
+        buf.put_slice(payload);
+    }
+    buf
+}
+
+fn length_delimited_decoder() -> LengthDelimitedCodec {
+    LengthDelimitedCodec::builder()
+        .big_endian()
+        .length_field_length(4)
+        .max_frame_length(u32::MAX as usize)
+        .length_adjustment(-4)
+        .new_codec()
+}
+
+/// Decodes the performative through an IO reader and copies the payload out
+fn decode_with_io_reader(mut src: BytesMut) -> (Transfer, Bytes) {
+    src.advance(4);
+    let mut reader = src.reader();
+    let mut deserializer = Deserializer::new(IoReader::new(&mut reader));
+    let performative: Performative = Deserialize::deserialize(&mut deserializer).unwrap();
+    let payload = Bytes::copy_from_slice(reader.into_inner().chunk());
+    match performative {
+        Performative::Transfer(transfer) => (transfer, payload),
+        _ => panic!("Expecting a Transfer"),
+    }
+}
+
+#[test]
+fn decoding_small_transfer_frames_reuses_the_read_buffer() {
+    let frames: Vec<BytesMut> = {
+        let mut codec = length_delimited_decoder();
+        let mut src = encode_transfer_frames();
+        std::iter::from_fn(|| codec.decode(&mut src).unwrap()).collect()
+    };
+    assert_eq!(frames.len(), FRAME_COUNT);
+
+    let io_reader_frames = frames.clone();
+    let mut io_reader_decoded = Vec::with_capacity(FRAME_COUNT);
+    let io_reader_allocations = count_allocations(|| {
+        for frame in io_reader_frames {
+            io_reader_decoded.push(decode_with_io_reader(frame));
+        }
+    });
+
+    let mut decoded = Vec::with_capacity(FRAME_COUNT);
+    let allocations = count_allocations(|| {
+        let mut decoder = FrameDecoder {};
+        for mut frame in frames {
+            decoded.push(decoder.decode(&mut frame).unwrap().unwrap());
+        }
+    });
+
+    for (frame, (expected_transfer, expected_payload)) in decoded.iter().zip(&io_reader_decoded) {
+        assert_eq!(frame.channel, 1);
+        match &frame.body {
+            FrameBody::Transfer {
+                performative,
+                payload,
+            } => {
+                assert_eq!(performative, expected_transfer);
+                assert_eq!(payload, expected_payload);
+            }
+            _ => panic!("Expecting a Transfer"),
+        }
+    }
+
+    // Only the delivery tag of each Transfer is allocated, the payload is a slice of the read
+    // buffer
+    assert!(
+        allocations <= FRAME_COUNT,
+        "{} allocations for {} frames",
+        allocations,
+        FRAME_COUNT
+    );
+    assert!(
+        allocations * 2 < io_reader_allocations,
+        "{} allocations, {} with an IO reader",
+        allocations,
+        io_reader_allocations
+    );
+}
@@ -0,0 +1,136 @@
+//! Decodes byte streams captured from a client sending a message to the listener over SASL
+//! ANONYMOUS
+
+use fe2o3_amqp::{
+    frames::{
+        amqp::FrameBody,
+        decode_stream,
+        decoder::{DecodeErrorKind, Decoder, ParsedFrame},
+        sasl::Frame,
+    },
+    transport::protocol_header::ProtocolId,
+    types::{definitions::Role, messaging::DeliveryState},
+};
+
+const CLIENT_STREAM: &[u8] = include_bytes!("fixtures/sasl_anonymous_send_client.bin");
+const SERVER_STREAM: &[u8] = include_bytes!("fixtures/sasl_anonymous_send_server.bin");
+
+fn summary(frame: &ParsedFrame) -> String {
+    match frame {
+        ParsedFrame::Header { header, .. } => format!("{:?}", header.id),
+        ParsedFrame::Amqp { body, .. } => {
+            let body = format!("{:?}", body);
+            body[..body.find(['(', ' ']).unwrap_or(body.len())].to_string()
+        }
+        ParsedFrame::Sasl { frame, .. } => match frame {
+            Frame::Mechanisms(_) => "Mechanisms".to_string(),
+            Frame::Init(_) => "Init".to_string(),
+            Frame::Challenge(_) => "Challenge".to_string(),
+            Frame::Response(_) => "Response".to_string(),
+            Frame::Outcome(_) => "Outcome".to_string(),
+        },
+    }
+}
+
+#[test]
+fn client_stream_is_decoded() {
+    let frames = decode_stream(CLIENT_STREAM).unwrap();
+
+    let summaries: Vec<_> = frames.iter().map(summary).collect();
+    assert_eq!(
+        summaries,
+        ["Sasl", "Init", "Amqp", "Open", "Begin", "Attach", "Transfer", "Detach", "End", "Close"]
+    );
+    let offsets: Vec<_> = frames.iter().map(ParsedFrame::offset).collect();
+    assert_eq!(offsets, [0, 8, 45, 53, 102, 128, 179, 213, 229, 241]);
+
+    match &frames[6] {
+        ParsedFrame::Amqp {
+            channel: 0,
+            body: FrameBody::Transfer { payload, .. },
+            ..
+        } => assert!(payload.ends_with(b"hello")),
+        frame => panic!("Expecting a transfer, found {}", frame),
+    }
+}
+
+#[test]
+fn server_stream_is_decoded() {
+    let frames = decode_stream(SERVER_STREAM).unwrap();
+
+    let summaries: Vec<_> = frames.iter().map(summary).collect();
+    assert_eq!(
+        summaries,
+        [
+            "Sasl",
+            "Mechanisms",
+            "Outcome",
+            "Amqp",
+            "Open",
+            "Begin",
+            "Attach",
+            "Flow",
+            "Disposition",
+            "Detach",
+            "End",
+            "Close"
+        ]
+    );
+
+    match &frames[8] {
+        ParsedFrame::Amqp {
+            body: FrameBody::Disposition(disposition),
+            ..
+        } => {
+            assert_eq!(disposition.role, Role::Receiver);
+            assert!(matches!(
+                disposition.state,
+                Some(DeliveryState::Accepted(_))
+            ));
+        }
+        frame => panic!("Expecting a disposition, found {}", frame),
+    }
+}
+
+#[test]
+fn stream_fed_in_chunks_is_decoded() {
+    for chunk_size in [1, 3, 7, 64] {
+        let mut decoder = Decoder::new();
+        let mut frames = Vec::new();
+        for chunk in SERVER_STREAM.chunks(chunk_size) {
+            decoder.feed(chunk);
+            while let Some(frame) = decoder.next_frame().unwrap() {
+                frames.push(frame);
+            }
+        }
+        decoder.finish().unwrap();
+
+        assert_eq!(frames.len(), 12);
+        assert_eq!(decoder.offset(), SERVER_STREAM.len() as u64);
+        assert!(matches!(
+            &frames[3],
+            ParsedFrame::Header { offset: 55, header } if header.id == ProtocolId::Amqp
+        ));
+    }
+}
+
+#[test]
+fn frames_are_rendered_one_per_line() {
+    let dump: Vec<_> = decode_stream(SERVER_STREAM)
+        .unwrap()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+
+    assert_eq!(dump[0], "       0  HEADER  Sasl 1.0.0");
+    assert!(dump[2].starts_with("      39  SASL            Outcome("));
+    assert!(dump[4].starts_with("      63  AMQP    ch 0     Open("));
+    assert!(dump.iter().all(|line| !line.contains('\n')));
+}
+
+#[test]
+fn truncated_stream_reports_offset_of_incomplete_frame() {
+    let error = decode_stream(&CLIENT_STREAM[..CLIENT_STREAM.len() - 1]).unwrap_err();
+    assert_eq!(error.offset, 241);
+    assert!(matches!(error.kind, DecodeErrorKind::Incomplete(11)));
+}
@@ -0,0 +1,89 @@
+//! Tests of dropping connection and session handles
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use std::net::SocketAddr;
+
+use fe2o3_amqp::{
+    acceptor::{ConnectionAcceptor, SessionAcceptor},
+    Connection, Session,
+};
+
+use common::serve_connection;
+
+/// Spawns a listener that accepts a single connection and session, and reports how the session
+/// ended and how the connection was closed
+async fn spawn_end_recorder() -> (
+    SocketAddr,
+    tokio::sync::oneshot::Receiver<(
+        Result<(), fe2o3_amqp::session::Error>,
+        Result<(), fe2o3_amqp::connection::Error>,
+    )>,
+) {
+    let (result_tx, result_rx) = tokio::sync::oneshot::channel();
+    let (addr, _) = serve_connection(
+        ConnectionAcceptor::new("end-recorder"),
+        move |mut connection| async move {
+            let mut session = SessionAcceptor::new()
+                .accept(&mut connection)
+                .await
+                .unwrap();
+            let end = session.on_end().await;
+            let close = connection.on_close().await;
+            let _ = result_tx.send((end, close));
+        },
+    )
+    .await;
+
+    (addr, result_rx)
+}
+
+#[tokio::test]
+async fn dropped_connection_handle_closes_the_connection() {
+    use std::time::Duration;
+
+    let (addr, result) = spawn_end_recorder().await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("dropped-connection", &url[..])
+        .await
+        .unwrap();
+    let session = Session::begin(&mut connection).await.unwrap();
+
+    drop(connection);
+    let (end, close) = tokio::time::timeout(Duration::from_secs(5), result)
+        .await
+        .unwrap()
+        .unwrap();
+    // A Close frame is received rather than the connection being reset
+    assert!(matches!(
+        close,
+        Err(fe2o3_amqp::connection::Error::RemoteClosed)
+    ));
+    // The session still running on the closed connection does not send an End
+    assert!(end.is_err());
+    drop(session);
+}
+
+#[tokio::test]
+async fn dropped_session_handle_ends_the_session() {
+    use std::time::Duration;
+
+    let (addr, result) = spawn_end_recorder().await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("dropped-session", &url[..]).await.unwrap();
+    let session = Session::begin(&mut connection).await.unwrap();
+
+    drop(session);
+    drop(connection);
+    let (end, close) = tokio::time::timeout(Duration::from_secs(5), result)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(end, Err(fe2o3_amqp::session::Error::RemoteEnded)));
+    assert!(matches!(
+        close,
+        Err(fe2o3_amqp::connection::Error::RemoteClosed)
+    ));
+}
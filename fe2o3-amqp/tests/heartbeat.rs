@@ -0,0 +1,280 @@
+//! Tests that empty frames are sent at the interval chosen by the heartbeat policy from the idle
+//! time-out of a remote peer, and that a remote idle time-out that cannot be satisfied fails the
+//! open
+
+// The paused clock of tokio does not drive the timers of async-std
+#![cfg(not(feature = "rt-async-std"))]
+
+use std::time::Duration;
+
+use bytes::BytesMut;
+use fe2o3_amqp::{
+    connection::{ConnectionHandle, HeartbeatPolicy, OpenError},
+    frames::{
+        amqp::{Frame, FrameBody, FrameDecoder},
+        FRAME_TYPE_AMQP,
+    },
+    transport::protocol_header::ProtocolHeader,
+    types::{
+        definitions::{AmqpError, ErrorCondition},
+        performatives::{Close, Open},
+    },
+    Connection,
+};
+use serde::Serialize;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt, DuplexStream},
+    time::Instant,
+};
+use tokio_util::codec::Decoder;
+
+/// A peer that advertises an idle time-out and records when the empty frames arrive
+struct MockPeer {
+    stream: DuplexStream,
+}
+
+impl MockPeer {
+    async fn write_frame(&mut self, channel: u16, performative: impl Serialize) {
+        let body = serde_amqp::to_vec(&performative).unwrap();
+        let size = 8 + body.len();
+        let mut buf = Vec::with_capacity(size);
+        buf.extend_from_slice(&(size as u32).to_be_bytes());
+        buf.push(2);
+        buf.push(FRAME_TYPE_AMQP);
+        buf.extend_from_slice(&channel.to_be_bytes());
+        buf.extend_from_slice(&body);
+        self.stream.write_all(&buf).await.unwrap();
+    }
+
+    /// Reads the next frame, including empty frames
+    async fn read_any_frame(&mut self) -> Frame {
+        let size = self.stream.read_u32().await.unwrap() as usize;
+        let mut buf = BytesMut::zeroed(size - 4);
+        self.stream.read_exact(&mut buf).await.unwrap();
+        FrameDecoder {}.decode(&mut buf).unwrap().unwrap()
+    }
+
+    /// Reads the next frame that is not empty
+    async fn read_frame(&mut self) -> Frame {
+        loop {
+            let frame = self.read_any_frame().await;
+            if !matches!(frame.body, FrameBody::Empty) {
+                return frame;
+            }
+        }
+    }
+
+    async fn handshake(&mut self, idle_time_out: Option<u32>) {
+        let mut header = [0u8; 8];
+        self.stream.read_exact(&mut header).await.unwrap();
+        assert_eq!(header, <[u8; 8]>::from(ProtocolHeader::amqp()));
+        self.stream.write_all(&header).await.unwrap();
+        match self.read_frame().await.body {
+            FrameBody::Open(_) => {}
+            body => panic!("Expecting Open, found {:?}", body),
+        }
+        let open = Open {
+            container_id: "mock-peer".to_string(),
+            hostname: None,
+            max_frame_size: Default::default(),
+            channel_max: Default::default(),
+            idle_time_out,
+            outgoing_locales: None,
+            incoming_locales: None,
+            offered_capabilities: None,
+            desired_capabilities: None,
+            properties: None,
+        };
+        self.write_frame(0, open).await;
+    }
+
+    /// Returns the time between consecutive empty frames
+    async fn empty_frame_intervals(&mut self, count: usize) -> Vec<Duration> {
+        let mut last = None;
+        let mut intervals = Vec::with_capacity(count);
+        while intervals.len() < count {
+            let frame = self.read_any_frame().await;
+            assert!(matches!(frame.body, FrameBody::Empty));
+            let now = Instant::now();
+            if let Some(last) = last.replace(now) {
+                intervals.push(now - last);
+            }
+        }
+        intervals
+    }
+
+    /// Replies to the Close of the client and returns it
+    async fn close(&mut self) -> Close {
+        let close = match self.read_frame().await.body {
+            FrameBody::Close(close) => close,
+            body => panic!("Expecting Close, found {:?}", body),
+        };
+        self.write_frame(0, Close { error: None }).await;
+        close
+    }
+}
+
+async fn open_with_mock_peer(
+    remote_idle_timeout: Option<u32>,
+    policy: HeartbeatPolicy,
+) -> (ConnectionHandle<()>, MockPeer) {
+    let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+    let mut peer = MockPeer { stream: server_io };
+    let (connection, _) = tokio::join!(
+        Connection::builder()
+            .container_id("heartbeat-client")
+            .heartbeat_policy(policy)
+            .open_with_stream(client_io),
+        peer.handshake(remote_idle_timeout)
+    );
+    (connection.unwrap(), peer)
+}
+
+/// Returns the error of the open and the Close that the client sent to the peer
+async fn fail_open_with_mock_peer(
+    remote_idle_timeout: Option<u32>,
+    policy: HeartbeatPolicy,
+) -> (OpenError, Close) {
+    let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+    let mut peer = MockPeer { stream: server_io };
+    let (connection, close) = tokio::join!(
+        Connection::builder()
+            .container_id("heartbeat-client")
+            .heartbeat_policy(policy)
+            .open_with_stream(client_io),
+        async {
+            peer.handshake(remote_idle_timeout).await;
+            peer.close().await
+        }
+    );
+    (connection.unwrap_err(), close)
+}
+
+async fn close(mut connection: ConnectionHandle<()>, mut peer: MockPeer) {
+    let (result, _) = tokio::join!(connection.close(), peer.close());
+    result.unwrap();
+}
+
+fn assert_cadence(intervals: &[Duration], period: Duration) {
+    for interval in intervals {
+        assert!(
+            *interval >= period && *interval < period + Duration::from_millis(5),
+            "Expecting empty frames every {:?}, found {:?}",
+            period,
+            intervals
+        );
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn empty_frames_are_sent_every_half_of_a_small_remote_idle_timeout() {
+    let (connection, mut peer) = open_with_mock_peer(Some(100), HeartbeatPolicy::HalfRemote).await;
+    assert_eq!(
+        connection.remote_idle_timeout(),
+        Some(Duration::from_millis(100))
+    );
+    assert_eq!(
+        connection.heartbeat_interval(),
+        Some(Duration::from_millis(50))
+    );
+    assert_eq!(connection.local_idle_timeout(), None);
+
+    let intervals = peer.empty_frame_intervals(5).await;
+    assert_cadence(&intervals, Duration::from_millis(50));
+    close(connection, peer).await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn empty_frames_are_sent_every_half_of_a_large_remote_idle_timeout() {
+    let (connection, mut peer) =
+        open_with_mock_peer(Some(600_000), HeartbeatPolicy::HalfRemote).await;
+    assert_eq!(
+        connection.heartbeat_interval(),
+        Some(Duration::from_secs(300))
+    );
+
+    let intervals = peer.empty_frame_intervals(3).await;
+    assert_cadence(&intervals, Duration::from_secs(300));
+    close(connection, peer).await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn empty_frames_are_sent_at_a_fixed_interval() {
+    let policy = HeartbeatPolicy::Fixed(Duration::from_secs(1));
+    let (connection, mut peer) = open_with_mock_peer(Some(600_000), policy).await;
+    assert_eq!(
+        connection.heartbeat_interval(),
+        Some(Duration::from_secs(1))
+    );
+    let intervals = peer.empty_frame_intervals(5).await;
+    assert_cadence(&intervals, Duration::from_secs(1));
+    close(connection, peer).await;
+
+    // Empty frames are sent even if the remote peer has no idle time-out
+    let (connection, mut peer) = open_with_mock_peer(None, policy).await;
+    assert_eq!(connection.remote_idle_timeout(), None);
+    let intervals = peer.empty_frame_intervals(3).await;
+    assert_cadence(&intervals, Duration::from_secs(1));
+    close(connection, peer).await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn no_empty_frame_is_sent_without_remote_idle_timeout() {
+    for (remote_idle_timeout, policy) in [
+        (None, HeartbeatPolicy::HalfRemote),
+        (Some(0), HeartbeatPolicy::HalfRemote),
+        (None, HeartbeatPolicy::Disabled),
+    ] {
+        let (connection, mut peer) = open_with_mock_peer(remote_idle_timeout, policy).await;
+        assert_eq!(connection.heartbeat_interval(), None);
+        let read = tokio::time::timeout(Duration::from_secs(3600), peer.read_any_frame()).await;
+        assert!(read.is_err(), "Expecting no frame, found {:?}", read);
+        close(connection, peer).await;
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn unsatisfiable_remote_idle_timeout_fails_open() {
+    for (remote_idle_timeout, policy) in [
+        (10, HeartbeatPolicy::HalfRemote),
+        (100, HeartbeatPolicy::Fixed(Duration::from_millis(200))),
+        (100, HeartbeatPolicy::Disabled),
+    ] {
+        let (error, close) = fail_open_with_mock_peer(Some(remote_idle_timeout), policy).await;
+        match error {
+            OpenError::IdleTimeoutUnsatisfiable {
+                remote_idle_timeout: remote,
+                policy: p,
+            } => {
+                assert_eq!(remote, Duration::from_millis(remote_idle_timeout as u64));
+                assert_eq!(p, policy);
+            }
+            error => panic!("Expecting IdleTimeoutUnsatisfiable, found {:?}", error),
+        }
+        let error = close.error.unwrap();
+        assert_eq!(
+            error.condition,
+            ErrorCondition::AmqpError(AmqpError::NotAllowed)
+        );
+        assert!(error.description.unwrap().contains("idle time-out"));
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn local_idle_timeout_is_half_of_the_builder_value() {
+    let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+    let mut peer = MockPeer { stream: server_io };
+    let (connection, _) = tokio::join!(
+        Connection::builder()
+            .container_id("heartbeat-client")
+            .idle_time_out(2000u32)
+            .open_with_stream(client_io),
+        peer.handshake(None)
+    );
+    let connection = connection.unwrap();
+    assert_eq!(
+        connection.local_idle_timeout(),
+        Some(Duration::from_millis(1000))
+    );
+    close(connection, peer).await;
+}
@@ -0,0 +1,101 @@
+//! Tests of the incoming window advertised by a session
+
+#![cfg(all(feature = "acceptor", feature = "testing", not(target_arch = "wasm32")))]
+
+mod common;
+
+use std::net::SocketAddr;
+
+use fe2o3_amqp::{
+    acceptor::LinkAcceptor,
+    link::{LinkStateError, RecvError},
+    Connection, Receiver, Session,
+};
+
+use common::{expect_sender, sender_main, serve_session, FLOOD_COUNT};
+
+/// Accepts a single link from a receiver and floods it, forwarding the incoming-window of every
+/// Flow that the session receives until the session ends
+async fn spawn_incoming_window_observer() -> (SocketAddr, tokio::sync::mpsc::UnboundedReceiver<u32>)
+{
+    use fe2o3_amqp::session::SessionFrameBody;
+
+    let (window_tx, window_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (addr, _) = serve_session("incoming-window-observer", move |mut session| async move {
+        session.observe_raw_incoming().await.unwrap();
+        let sender = expect_sender(LinkAcceptor::new().accept(&mut session).await.unwrap());
+        tokio::spawn(sender_main(sender));
+        while let Some(body) = session.next_raw_incoming().await {
+            match body {
+                SessionFrameBody::Flow(flow) => {
+                    let _ = window_tx.send(flow.incoming_window);
+                }
+                SessionFrameBody::End(_) => break,
+                _ => {}
+            }
+        }
+        let _ = session.on_end().await;
+    })
+    .await;
+
+    (addr, window_rx)
+}
+
+/// Receives the flood slowly and returns the incoming-windows advertised by the session
+async fn advertised_windows_with_slow_consumer(
+    policy: fe2o3_amqp::session::IncomingWindowPolicy,
+) -> Vec<u32> {
+    use std::time::Duration;
+
+    let (addr, mut window_rx) = spawn_incoming_window_observer().await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("slow-consumer-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::builder()
+        .incoming_window(32)
+        .incoming_window_policy(policy)
+        .begin(&mut connection)
+        .await
+        .unwrap();
+    let mut receiver = Receiver::attach(&mut session, "slow-receiver", "flood")
+        .await
+        .unwrap();
+
+    for i in 0..FLOOD_COUNT {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let delivery = receiver.recv::<String>().await.unwrap();
+        assert!(delivery.body().starts_with(&format!("{:08}", i)));
+        receiver.accept(&delivery).await.unwrap();
+    }
+    match receiver.recv::<String>().await {
+        Err(RecvError::LinkStateError(LinkStateError::RemoteClosed)) => {}
+        other => panic!("Expecting RemoteClosed, found {:?}", other.map(|_| ())),
+    }
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+
+    let mut windows = Vec::new();
+    while let Some(window) = window_rx.recv().await {
+        windows.push(window);
+    }
+    windows
+}
+
+#[tokio::test]
+async fn incoming_window_follows_buffer_occupancy_of_slow_consumer() {
+    use fe2o3_amqp::session::IncomingWindowPolicy;
+
+    let windows = advertised_windows_with_slow_consumer(IncomingWindowPolicy::Static).await;
+    assert!(!windows.is_empty());
+    assert!(windows.iter().all(|window| *window == 32));
+
+    // The peer is slowed down to the pace of the receiver instead of filling its buffer
+    let windows = advertised_windows_with_slow_consumer(IncomingWindowPolicy::BufferBased {
+        low_watermark: 4,
+        high_watermark: 16,
+    })
+    .await;
+    assert!(windows.iter().all(|window| (1..=32).contains(window)));
+    assert!(windows.iter().any(|window| *window < 16));
+}
@@ -0,0 +1,63 @@
+//! Interoperability suite that runs a battery of scenarios against an external broker and
+//! reports which of them pass
+//!
+//! This needs the `interop` feature and is ignored by default. See `tests/interop/Readme.md` for
+//! the environment variables and the capability matrix of the broker.
+
+#![cfg(all(feature = "interop", not(target_arch = "wasm32")))]
+
+mod matrix;
+mod report;
+mod scenarios;
+
+use std::path::Path;
+
+use matrix::{Capabilities, InteropConfig};
+
+#[tokio::test]
+#[ignore = "needs an external broker, see tests/interop/Readme.md"]
+async fn interop() {
+    let config = InteropConfig::from_env().unwrap();
+    let report = scenarios::run(&config).await;
+    println!("{}", report);
+    if let Some(path) = &config.report_path {
+        report.write_json(path).unwrap();
+    }
+    assert_eq!(report.failed(), 0, "{}", report);
+}
+
+#[test]
+fn capability_matrices_of_the_brokers_are_valid() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/interop/capabilities");
+    let mut count = 0;
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let json = std::fs::read_to_string(&path).unwrap();
+        let capabilities = Capabilities::from_json(&json)
+            .unwrap_or_else(|error| panic!("{}: {}", path.display(), error));
+        assert_ne!(capabilities.broker, Capabilities::default().broker);
+        count += 1;
+    }
+    assert!(count > 0);
+}
+
+#[test]
+fn capability_matrix_rejects_unknown_fields() {
+    let capabilities = Capabilities::from_json(r#"{ "broker": "b", "drain": false }"#).unwrap();
+    assert!(!capabilities.drain);
+    assert_eq!(capabilities.address, Capabilities::default().address);
+
+    let error = Capabilities::from_json(r#"{ "drian": false }"#).unwrap_err();
+    assert!(error.contains("drian"));
+    assert!(Capabilities::from_json(r#"{ "max_frame_sizes": [-1] }"#).is_err());
+}
+
+#[test]
+fn skipped_names_cover_the_scenarios_under_them() {
+    let capabilities =
+        Capabilities::from_json(r#"{ "skip": ["sasl", "multi-frame/512"] }"#).unwrap();
+    assert!(capabilities.skips("sasl/PLAIN"));
+    assert!(capabilities.skips("multi-frame/512"));
+    assert!(!capabilities.skips("multi-frame/5120"));
+    assert!(!capabilities.skips("session-churn"));
+}
@@ -0,0 +1,284 @@
+//! Settings of the suite read from the environment and the capability matrix of the broker
+
+use std::{path::PathBuf, time::Duration};
+
+use serde_json::Value;
+use url::Url;
+
+/// Url of the broker, eg. `amqp://localhost:5672`
+pub const URL_VAR: &str = "AMQP_INTEROP_URL";
+
+/// Username for the SASL mechanisms that need credentials
+pub const USERNAME_VAR: &str = "AMQP_INTEROP_USERNAME";
+
+/// Password for the SASL mechanisms that need credentials
+pub const PASSWORD_VAR: &str = "AMQP_INTEROP_PASSWORD";
+
+/// Path to the capability matrix of the broker
+pub const CAPABILITIES_VAR: &str = "AMQP_INTEROP_CAPABILITIES";
+
+/// Comma separated names or name prefixes of the scenarios to run
+pub const SCENARIOS_VAR: &str = "AMQP_INTEROP_SCENARIOS";
+
+/// Path that the JSON report is written to
+pub const REPORT_VAR: &str = "AMQP_INTEROP_REPORT";
+
+/// Time-out of a single scenario in seconds
+pub const TIMEOUT_VAR: &str = "AMQP_INTEROP_TIMEOUT_SECS";
+
+/// Placeholder in the address that is replaced by a unique id for every scenario
+pub const ID_PLACEHOLDER: &str = "{id}";
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What the broker supports and how the scenarios address it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capabilities {
+    /// Name of the broker in the report
+    pub broker: String,
+
+    /// Address of the queue used by the scenarios, which may contain the `{id}` placeholder to
+    /// give every scenario its own queue
+    pub address: String,
+
+    /// Address of a node that does not exist, or `None` if the broker creates nodes on demand
+    pub nonexistent_address: Option<String>,
+
+    /// Error condition that the broker refuses a link to a nonexistent node with
+    pub not_found_condition: String,
+
+    /// SASL mechanisms to open connections with
+    pub sasl_mechanisms: Vec<String>,
+
+    /// Whether the broker stores messages in queues. A router like qpid-dispatch passes them on
+    /// to the receivers instead, together with the outcome of the receiver back to the sender
+    pub queues: bool,
+
+    /// Whether the broker supports the `second` receiver settle mode
+    pub rcv_settle_mode_second: bool,
+
+    /// Max frame sizes to send multi-frame messages with
+    pub max_frame_sizes: Vec<u32>,
+
+    /// Whether the broker answers a drain
+    pub drain: bool,
+
+    /// Outcomes after which the broker delivers the message again
+    pub redelivered_outcomes: Vec<String>,
+
+    /// Idle time-out in milliseconds that the broker is asked to keep the connection alive with
+    pub idle_time_out: u32,
+
+    /// Number of sessions begun and ended by the session churn scenario
+    pub session_churn: usize,
+
+    /// Names or name prefixes of the scenarios that are skipped for this broker
+    pub skip: Vec<String>,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self {
+            broker: "unknown".to_string(),
+            address: "fe2o3-interop-{id}".to_string(),
+            nonexistent_address: None,
+            not_found_condition: "amqp:not-found".to_string(),
+            sasl_mechanisms: vec!["ANONYMOUS".to_string()],
+            queues: true,
+            rcv_settle_mode_second: false,
+            max_frame_sizes: vec![512, 4096, 65536],
+            drain: true,
+            redelivered_outcomes: vec!["released".to_string(), "modified".to_string()],
+            idle_time_out: 2000,
+            session_churn: 32,
+            skip: Vec::new(),
+        }
+    }
+}
+
+impl Capabilities {
+    /// Parses a capability matrix. Fields that are missing keep their default value, and an
+    /// unknown field is an error so that a typo doesn't silently skip a check
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let value: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        let object = value
+            .as_object()
+            .ok_or_else(|| "The capability matrix must be a JSON object".to_string())?;
+        let mut capabilities = Self::default();
+        for (key, value) in object {
+            match key.as_str() {
+                "broker" => capabilities.broker = string(key, value)?,
+                "address" => capabilities.address = string(key, value)?,
+                "nonexistent_address" => {
+                    capabilities.nonexistent_address = match value {
+                        Value::Null => None,
+                        value => Some(string(key, value)?),
+                    }
+                }
+                "not_found_condition" => capabilities.not_found_condition = string(key, value)?,
+                "sasl_mechanisms" => capabilities.sasl_mechanisms = strings(key, value)?,
+                "queues" => capabilities.queues = boolean(key, value)?,
+                "rcv_settle_mode_second" => {
+                    capabilities.rcv_settle_mode_second = boolean(key, value)?
+                }
+                "max_frame_sizes" => {
+                    capabilities.max_frame_sizes = numbers(key, value)?
+                        .into_iter()
+                        .map(|n| u32::try_from(n).map_err(|e| format!("{}: {}", key, e)))
+                        .collect::<Result<_, _>>()?
+                }
+                "drain" => capabilities.drain = boolean(key, value)?,
+                "redelivered_outcomes" => capabilities.redelivered_outcomes = strings(key, value)?,
+                "idle_time_out" => {
+                    capabilities.idle_time_out =
+                        u32::try_from(number(key, value)?).map_err(|e| format!("{}: {}", key, e))?
+                }
+                "session_churn" => capabilities.session_churn = number(key, value)? as usize,
+                "skip" => capabilities.skip = strings(key, value)?,
+                // Free-form notes about the broker
+                "notes" => {}
+                _ => return Err(format!("Unknown field {:?} in the capability matrix", key)),
+            }
+        }
+        Ok(capabilities)
+    }
+
+    /// Whether the scenario is skipped by the matrix
+    pub fn skips(&self, scenario: &str) -> bool {
+        self.skip.iter().any(|name| matches_name(name, scenario))
+    }
+}
+
+/// Settings of a run of the suite
+#[derive(Debug, Clone)]
+pub struct InteropConfig {
+    /// Url of the broker without credentials
+    pub url: Url,
+
+    /// Credentials for PLAIN and SCRAM
+    pub credentials: Option<(String, String)>,
+
+    pub capabilities: Capabilities,
+
+    /// Only the scenarios matching one of these are run if this is not empty
+    pub scenarios: Vec<String>,
+
+    pub report_path: Option<PathBuf>,
+
+    pub timeout: Duration,
+}
+
+impl InteropConfig {
+    /// Reads the settings from the environment
+    pub fn from_env() -> Result<Self, String> {
+        let url = std::env::var(URL_VAR)
+            .map_err(|_| format!("{} must be set to the url of the broker", URL_VAR))?;
+        let mut url = Url::parse(&url).map_err(|e| format!("{}: {}", URL_VAR, e))?;
+
+        // Credentials in the url would override the SASL profile of every scenario
+        let url_credentials = match url.username() {
+            "" => None,
+            username => Some((
+                username.to_string(),
+                url.password().unwrap_or_default().to_string(),
+            )),
+        };
+        let _ = url.set_username("");
+        let _ = url.set_password(None);
+        let credentials = match (std::env::var(USERNAME_VAR), std::env::var(PASSWORD_VAR)) {
+            (Ok(username), Ok(password)) => Some((username, password)),
+            (Ok(username), Err(_)) => Some((username, String::new())),
+            _ => url_credentials,
+        };
+
+        let capabilities = match std::env::var(CAPABILITIES_VAR) {
+            Ok(path) => {
+                let json =
+                    std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path, e))?;
+                Capabilities::from_json(&json).map_err(|e| format!("{}: {}", path, e))?
+            }
+            Err(_) => Capabilities::default(),
+        };
+        let scenarios = std::env::var(SCENARIOS_VAR)
+            .map(|names| {
+                names
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let report_path = std::env::var(REPORT_VAR).ok().map(PathBuf::from);
+        let timeout = match std::env::var(TIMEOUT_VAR) {
+            Ok(secs) => Duration::from_secs(
+                secs.parse()
+                    .map_err(|e| format!("{}: {}", TIMEOUT_VAR, e))?,
+            ),
+            Err(_) => DEFAULT_TIMEOUT,
+        };
+
+        Ok(Self {
+            url,
+            credentials,
+            capabilities,
+            scenarios,
+            report_path,
+            timeout,
+        })
+    }
+
+    /// Whether the scenario is selected by [`SCENARIOS_VAR`]
+    pub fn selects(&self, scenario: &str) -> bool {
+        self.scenarios.is_empty()
+            || self
+                .scenarios
+                .iter()
+                .any(|name| matches_name(name, scenario))
+    }
+}
+
+/// A name matches the scenario itself and the scenarios under it, eg. `sasl` matches `sasl/PLAIN`
+fn matches_name(name: &str, scenario: &str) -> bool {
+    match scenario.strip_prefix(name) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+fn string(key: &str, value: &Value) -> Result<String, String> {
+    value
+        .as_str()
+        .map(String::from)
+        .ok_or_else(|| format!("{} must be a string", key))
+}
+
+fn strings(key: &str, value: &Value) -> Result<Vec<String>, String> {
+    value
+        .as_array()
+        .ok_or_else(|| format!("{} must be an array of strings", key))?
+        .iter()
+        .map(|value| string(key, value))
+        .collect()
+}
+
+fn boolean(key: &str, value: &Value) -> Result<bool, String> {
+    value
+        .as_bool()
+        .ok_or_else(|| format!("{} must be a boolean", key))
+}
+
+fn number(key: &str, value: &Value) -> Result<u64, String> {
+    value
+        .as_u64()
+        .ok_or_else(|| format!("{} must be a non-negative integer", key))
+}
+
+fn numbers(key: &str, value: &Value) -> Result<Vec<u64>, String> {
+    value
+        .as_array()
+        .ok_or_else(|| format!("{} must be an array of integers", key))?
+        .iter()
+        .map(|value| number(key, value))
+        .collect()
+}
@@ -0,0 +1,141 @@
+//! Structured pass/fail report of a run of the suite
+
+use std::{fmt, path::Path, time::Duration};
+
+use serde_json::{json, Value};
+
+/// How a scenario ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status {
+    Passed,
+
+    /// The scenario failed with the reason
+    Failed(String),
+
+    /// The scenario was not run for the reason
+    Skipped(String),
+}
+
+impl Status {
+    fn name(&self) -> &'static str {
+        match self {
+            Status::Passed => "passed",
+            Status::Failed(_) => "failed",
+            Status::Skipped(_) => "skipped",
+        }
+    }
+
+    fn detail(&self) -> Option<&str> {
+        match self {
+            Status::Passed => None,
+            Status::Failed(detail) | Status::Skipped(detail) => Some(detail),
+        }
+    }
+}
+
+/// The result of a single scenario
+#[derive(Debug, Clone)]
+pub struct ScenarioReport {
+    pub name: String,
+    pub status: Status,
+    pub duration: Duration,
+}
+
+/// The results of all the scenarios run against a broker
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub broker: String,
+    pub url: String,
+    pub scenarios: Vec<ScenarioReport>,
+}
+
+impl Report {
+    pub fn new(broker: impl Into<String>, url: impl Into<String>) -> Self {
+        Self {
+            broker: broker.into(),
+            url: url.into(),
+            scenarios: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, scenario: ScenarioReport) {
+        self.scenarios.push(scenario);
+    }
+
+    fn count(&self, status: fn(&Status) -> bool) -> usize {
+        self.scenarios.iter().filter(|s| status(&s.status)).count()
+    }
+
+    pub fn passed(&self) -> usize {
+        self.count(|status| matches!(status, Status::Passed))
+    }
+
+    pub fn failed(&self) -> usize {
+        self.count(|status| matches!(status, Status::Failed(_)))
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.count(|status| matches!(status, Status::Skipped(_)))
+    }
+
+    pub fn to_json(&self) -> Value {
+        let scenarios: Vec<Value> = self
+            .scenarios
+            .iter()
+            .map(|scenario| {
+                json!({
+                    "name": scenario.name,
+                    "status": scenario.status.name(),
+                    "detail": scenario.status.detail(),
+                    "duration_ms": scenario.duration.as_millis() as u64,
+                })
+            })
+            .collect();
+        json!({
+            "broker": self.broker,
+            "url": self.url,
+            "passed": self.passed(),
+            "failed": self.failed(),
+            "skipped": self.skipped(),
+            "scenarios": scenarios,
+        })
+    }
+
+    pub fn write_json(&self, path: &Path) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(&self.to_json())?;
+        std::fs::write(path, json)
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Interop report for {} at {}", self.broker, self.url)?;
+        let width = self
+            .scenarios
+            .iter()
+            .map(|scenario| scenario.name.len())
+            .max()
+            .unwrap_or_default();
+        for scenario in &self.scenarios {
+            write!(
+                f,
+                "  {:<width$}  {:<7}  {:>6} ms",
+                scenario.name,
+                scenario.status.name(),
+                scenario.duration.as_millis(),
+                width = width
+            )?;
+            match scenario.status.detail() {
+                Some(detail) => writeln!(f, "  {}", detail)?,
+                None => writeln!(f)?,
+            }
+        }
+        write!(
+            f,
+            "{} passed, {} failed, {} skipped",
+            self.passed(),
+            self.failed(),
+            self.skipped()
+        )
+    }
+}
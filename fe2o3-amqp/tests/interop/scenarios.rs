@@ -0,0 +1,874 @@
+//! The scenarios that are run against the broker
+//!
+//! Every scenario opens its own connection so that a failure doesn't leak into the next one.
+
+use std::{
+    collections::BTreeSet,
+    fmt,
+    future::Future,
+    pin::Pin,
+    time::{Duration, Instant},
+};
+
+use fe2o3_amqp::{
+    connection::{ConnectionHandle, OpenError},
+    link::{receiver::CreditMode, DetachError},
+    sasl_profile::SaslProfile,
+    session::SessionHandle,
+    types::{
+        definitions::{self, ReceiverSettleMode, SenderSettleMode},
+        messaging::Modified,
+        primitives::Binary,
+        sasl::SaslCode,
+    },
+    Connection, Receiver, Sender, Session,
+};
+
+use crate::{
+    matrix::{Capabilities, InteropConfig, ID_PLACEHOLDER},
+    report::{Report, ScenarioReport, Status},
+};
+
+/// How long the broker is given to deliver a message that is not expected to come
+const QUIET_PERIOD: Duration = Duration::from_millis(500);
+
+/// Why a scenario didn't pass
+#[derive(Debug)]
+enum Verdict {
+    Failed(String),
+    Skipped(String),
+}
+
+type Outcome = Result<(), Verdict>;
+
+type ScenarioFuture<'a> = Pin<Box<dyn Future<Output = Outcome> + 'a>>;
+
+struct Scenario {
+    name: String,
+    run: Box<dyn for<'a> Fn(&'a Context) -> ScenarioFuture<'a>>,
+
+    /// Added to the time-out of the config for a scenario that waits on purpose
+    extra_time: Duration,
+}
+
+impl Scenario {
+    fn with_extra_time(mut self, extra_time: Duration) -> Self {
+        self.extra_time = extra_time;
+        self
+    }
+}
+
+fn scenario<F>(name: impl Into<String>, run: F) -> Scenario
+where
+    F: for<'a> Fn(&'a Context) -> ScenarioFuture<'a> + 'static,
+{
+    Scenario {
+        name: name.into(),
+        run: Box::new(run),
+        extra_time: Duration::ZERO,
+    }
+}
+
+/// Turns an error into a failure that tells what was being done
+trait OrFail<T> {
+    fn or_fail(self, doing: &str) -> Result<T, Verdict>;
+}
+
+impl<T, E: fmt::Debug> OrFail<T> for Result<T, E> {
+    fn or_fail(self, doing: &str) -> Result<T, Verdict> {
+        self.map_err(|error| Verdict::Failed(format!("{} failed: {:?}", doing, error)))
+    }
+}
+
+macro_rules! ensure {
+    ($cond:expr, $($arg:tt)+) => {
+        if !$cond {
+            return Err(Verdict::Failed(format!($($arg)+)));
+        }
+    };
+}
+
+/// Connection settings that differ between the scenarios
+struct OpenOptions {
+    /// `None` opens the connection without a SASL layer
+    sasl_profile: Option<SaslProfile>,
+    max_frame_size: Option<u32>,
+    idle_time_out: Option<u32>,
+}
+
+/// What a scenario knows about the run
+struct Context {
+    config: InteropConfig,
+
+    /// Unique for every scenario, used in the names of the links and the address
+    id: String,
+
+    address: String,
+}
+
+impl Context {
+    fn new(config: &InteropConfig) -> Self {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let address = config.capabilities.address.replace(ID_PLACEHOLDER, &id);
+        Self {
+            config: config.clone(),
+            id,
+            address,
+        }
+    }
+
+    fn capabilities(&self) -> &Capabilities {
+        &self.config.capabilities
+    }
+
+    fn link_name(&self, role: &str) -> String {
+        format!("fe2o3-interop-{}-{}", role, self.id)
+    }
+
+    fn credentials(&self) -> Result<(String, String), Verdict> {
+        self.config.credentials.clone().ok_or_else(|| {
+            Verdict::Skipped(
+                "No credentials, set AMQP_INTEROP_USERNAME and AMQP_INTEROP_PASSWORD".into(),
+            )
+        })
+    }
+
+    /// PLAIN is used if there are credentials for it, and ANONYMOUS otherwise. The connection is
+    /// opened without SASL if the broker supports neither
+    fn open_options(&self) -> OpenOptions {
+        let mechanisms = &self.capabilities().sasl_mechanisms;
+        let supports = |name: &str| mechanisms.iter().any(|m| m == name);
+        let sasl_profile = match &self.config.credentials {
+            Some((username, password)) if supports("PLAIN") => Some(SaslProfile::Plain {
+                username: username.clone(),
+                password: password.clone(),
+            }),
+            _ if supports("ANONYMOUS") => Some(SaslProfile::Anonymous),
+            _ => None,
+        };
+        OpenOptions {
+            sasl_profile,
+            max_frame_size: None,
+            idle_time_out: None,
+        }
+    }
+
+    async fn open_with(&self, options: OpenOptions) -> Result<ConnectionHandle<()>, OpenError> {
+        let mut builder = Connection::builder().container_id(format!("fe2o3-interop-{}", self.id));
+        if let Some(profile) = options.sasl_profile {
+            builder = builder.sasl_profile(profile);
+        }
+        if let Some(max_frame_size) = options.max_frame_size {
+            builder = builder.max_frame_size(max_frame_size);
+        }
+        if let Some(idle_time_out) = options.idle_time_out {
+            builder = builder.idle_time_out(idle_time_out);
+        }
+        builder.open(self.config.url.clone()).await
+    }
+
+    async fn open(&self) -> Result<ConnectionHandle<()>, Verdict> {
+        self.open_with(self.open_options()).await.or_fail("Open")
+    }
+
+    async fn begin(
+        &self,
+        connection: &mut ConnectionHandle<()>,
+    ) -> Result<SessionHandle<()>, Verdict> {
+        let mut session = Session::begin(connection).await.or_fail("Begin")?;
+        self.purge(&mut session).await?;
+        Ok(session)
+    }
+
+    /// Removes what earlier runs left on a queue that is shared by the scenarios
+    async fn purge(&self, session: &mut SessionHandle<()>) -> Outcome {
+        if self.capabilities().address.contains(ID_PLACEHOLDER) {
+            return Ok(());
+        }
+        let mut receiver = Receiver::attach(session, self.link_name("purge"), &self.address[..])
+            .await
+            .or_fail("Attaching the receiver that purges the queue")?;
+        while let Ok(delivery) = tokio::time::timeout(QUIET_PERIOD, receiver.recv_raw()).await {
+            let delivery = delivery.or_fail("Purging the queue")?;
+            if !delivery.is_settled() {
+                receiver
+                    .accept(&delivery)
+                    .await
+                    .or_fail("Purging the queue")?;
+            }
+        }
+        receiver
+            .close()
+            .await
+            .or_fail("Closing the receiver that purges the queue")
+    }
+
+    async fn attach_sender(&self, session: &mut SessionHandle<()>) -> Result<Sender, Verdict> {
+        Sender::attach(session, self.link_name("sender"), &self.address[..])
+            .await
+            .or_fail("Attaching the sender")
+    }
+
+    async fn attach_receiver(
+        &self,
+        session: &mut SessionHandle<()>,
+        credit_mode: CreditMode,
+    ) -> Result<Receiver, Verdict> {
+        Receiver::builder()
+            .name(self.link_name("receiver"))
+            .source(&self.address[..])
+            .credit_mode(credit_mode)
+            .auto_accept(false)
+            .attach(session)
+            .await
+            .or_fail("Attaching the receiver")
+    }
+
+    /// Sends a message and receives it again, which shows that the session can still be used
+    async fn round_trip(&self, session: &mut SessionHandle<()>) -> Outcome {
+        let mut receiver = self.attach_receiver(session, CreditMode::default()).await?;
+        let mut sender = self.attach_sender(session).await?;
+        let receipt = sender.send_batchable("round-trip").await.or_fail("Send")?;
+        let delivery = receiver.recv::<String>().await.or_fail("Receive")?;
+        ensure!(
+            delivery.body() == "round-trip",
+            "Received {:?} instead of the message that was sent",
+            delivery.body()
+        );
+        receiver.accept(&delivery).await.or_fail("Accept")?;
+        let receipt = receipt.await.or_fail("Send")?;
+        ensure!(
+            receipt.is_accepted(),
+            "The message was not accepted: {}",
+            receipt
+        );
+        sender.close().await.or_fail("Closing the sender")?;
+        receiver.close().await.or_fail("Closing the receiver")
+    }
+}
+
+/// The scenarios in the order they are run
+fn scenarios(capabilities: &Capabilities) -> Vec<Scenario> {
+    let mut scenarios = vec![scenario("open-close", |ctx| Box::pin(open_close(ctx)))];
+
+    for mechanism in &capabilities.sasl_mechanisms {
+        let mechanism = mechanism.clone();
+        scenarios.push(scenario(format!("sasl/{}", mechanism), move |ctx| {
+            Box::pin(sasl(ctx, mechanism.clone()))
+        }));
+    }
+    if capabilities.sasl_mechanisms.iter().any(|m| m == "PLAIN") {
+        scenarios.push(scenario("sasl/invalid-credentials", |ctx| {
+            Box::pin(sasl_invalid_credentials(ctx))
+        }));
+    }
+
+    scenarios.push(scenario("session-churn", |ctx| {
+        Box::pin(session_churn(ctx))
+    }));
+
+    for snd_settle_mode in [
+        SenderSettleMode::Unsettled,
+        SenderSettleMode::Settled,
+        SenderSettleMode::Mixed,
+    ] {
+        for rcv_settle_mode in [ReceiverSettleMode::First, ReceiverSettleMode::Second] {
+            let name = format!(
+                "settle-modes/{}-{}",
+                format!("{:?}", snd_settle_mode).to_lowercase(),
+                format!("{:?}", rcv_settle_mode).to_lowercase()
+            );
+            let snd_settle_mode = snd_settle_mode.clone();
+            scenarios.push(scenario(name, move |ctx| {
+                Box::pin(settle_modes(
+                    ctx,
+                    snd_settle_mode.clone(),
+                    rcv_settle_mode.clone(),
+                ))
+            }));
+        }
+    }
+
+    for &max_frame_size in &capabilities.max_frame_sizes {
+        scenarios.push(scenario(
+            format!("multi-frame/{}", max_frame_size),
+            move |ctx| Box::pin(multi_frame(ctx, max_frame_size)),
+        ));
+    }
+
+    scenarios.push(scenario("drain", |ctx| Box::pin(drain(ctx))));
+    scenarios.push(scenario("outcomes", |ctx| Box::pin(outcomes(ctx))));
+    let idle_time = Duration::from_millis(capabilities.idle_time_out as u64 * 2);
+    scenarios.push(
+        scenario("idle-timeout", |ctx| Box::pin(idle_timeout(ctx))).with_extra_time(idle_time),
+    );
+    scenarios.push(scenario("nonexistent-node/sender", |ctx| {
+        Box::pin(nonexistent_node(ctx, true))
+    }));
+    scenarios.push(scenario("nonexistent-node/receiver", |ctx| {
+        Box::pin(nonexistent_node(ctx, false))
+    }));
+    scenarios
+}
+
+/// Runs the scenarios selected by the config, each within the time-out of the config
+pub async fn run(config: &InteropConfig) -> Report {
+    let mut report = Report::new(&config.capabilities.broker, config.url.as_str());
+    for scenario in scenarios(&config.capabilities) {
+        if !config.selects(&scenario.name) {
+            continue;
+        }
+        let started = Instant::now();
+        let status = if config.capabilities.skips(&scenario.name) {
+            Status::Skipped("Skipped by the capability matrix".to_string())
+        } else {
+            let context = Context::new(config);
+            let timeout = config.timeout + scenario.extra_time;
+            match tokio::time::timeout(timeout, (scenario.run)(&context)).await {
+                Ok(Ok(())) => Status::Passed,
+                Ok(Err(Verdict::Failed(reason))) => Status::Failed(reason),
+                Ok(Err(Verdict::Skipped(reason))) => Status::Skipped(reason),
+                Err(_) => Status::Failed(format!("Timed out after {:?}", timeout)),
+            }
+        };
+        report.push(ScenarioReport {
+            name: scenario.name,
+            status,
+            duration: started.elapsed(),
+        });
+    }
+    report
+}
+
+/// Opens and closes a connection
+async fn open_close(ctx: &Context) -> Outcome {
+    let mut connection = ctx.open().await?;
+    ensure!(
+        !connection.remote_open().container_id.is_empty(),
+        "The broker sent an empty container-id"
+    );
+    connection.close().await.or_fail("Close")
+}
+
+/// Opens a connection with the SASL mechanism and begins a session on it
+async fn sasl(ctx: &Context, mechanism: String) -> Outcome {
+    let sasl_profile = match mechanism.as_str() {
+        "NONE" => None,
+        "ANONYMOUS" => Some(SaslProfile::Anonymous),
+        "PLAIN" => {
+            let (username, password) = ctx.credentials()?;
+            Some(SaslProfile::Plain { username, password })
+        }
+        #[cfg(feature = "scram")]
+        "SCRAM-SHA-1" | "SCRAM-SHA-256" | "SCRAM-SHA-512" => {
+            use fe2o3_amqp::sasl_profile::{SaslScramSha1, SaslScramSha256, SaslScramSha512};
+
+            let (username, password) = ctx.credentials()?;
+            Some(match mechanism.as_str() {
+                "SCRAM-SHA-1" => SaslScramSha1::new(username, password).into(),
+                "SCRAM-SHA-256" => SaslScramSha256::new(username, password).into(),
+                _ => SaslScramSha512::new(username, password).into(),
+            })
+        }
+        #[cfg(not(feature = "scram"))]
+        "SCRAM-SHA-1" | "SCRAM-SHA-256" | "SCRAM-SHA-512" => {
+            return Err(Verdict::Skipped("Needs the `scram` feature".into()))
+        }
+        "EXTERNAL" => {
+            return Err(Verdict::Skipped(
+                "Needs a TLS client certificate, which the suite doesn't configure".into(),
+            ))
+        }
+        _ => {
+            return Err(Verdict::Failed(format!(
+                "Unknown SASL mechanism {:?} in the capability matrix",
+                mechanism
+            )))
+        }
+    };
+    let options = OpenOptions {
+        sasl_profile,
+        ..ctx.open_options()
+    };
+    let mut connection = ctx.open_with(options).await.or_fail("Open")?;
+    let mut session = Session::begin(&mut connection).await.or_fail("Begin")?;
+    session.end().await.or_fail("End")?;
+    connection.close().await.or_fail("Close")
+}
+
+/// A wrong password fails the open with the authentication error of the broker
+async fn sasl_invalid_credentials(ctx: &Context) -> Outcome {
+    let (username, password) = ctx.credentials()?;
+    let options = OpenOptions {
+        sasl_profile: Some(SaslProfile::Plain {
+            username,
+            password: format!("{}-invalid", password),
+        }),
+        ..ctx.open_options()
+    };
+    match ctx.open_with(options).await {
+        Err(OpenError::SaslOutcome {
+            code: SaslCode::Auth,
+            ..
+        }) => Ok(()),
+        Err(error) => Err(Verdict::Failed(format!(
+            "Expecting an authentication failure, found {:?}",
+            error
+        ))),
+        Ok(_) => Err(Verdict::Failed(
+            "The connection was opened with an invalid password".into(),
+        )),
+    }
+}
+
+/// Begins and ends sessions one after another and then many at once
+async fn session_churn(ctx: &Context) -> Outcome {
+    let count = ctx.capabilities().session_churn;
+    let mut connection = ctx.open().await?;
+    for _ in 0..count {
+        let mut session = Session::begin(&mut connection).await.or_fail("Begin")?;
+        session.end().await.or_fail("End")?;
+    }
+
+    let mut sessions = Vec::with_capacity(count);
+    for _ in 0..count {
+        sessions.push(Session::begin(&mut connection).await.or_fail("Begin")?);
+    }
+    ensure!(
+        connection.active_session_count() == count,
+        "Expecting {} active sessions, found {}",
+        count,
+        connection.active_session_count()
+    );
+    futures_util::future::try_join_all(sessions.iter_mut().map(|session| session.end()))
+        .await
+        .or_fail("Ending the sessions at once")?;
+    ensure!(
+        connection.active_session_count() == 0,
+        "{} sessions are still active after ending all of them",
+        connection.active_session_count()
+    );
+
+    let mut session = ctx.begin(&mut connection).await?;
+    ctx.round_trip(&mut session).await?;
+    session.end().await.or_fail("End")?;
+    connection.close().await.or_fail("Close")
+}
+
+/// Sends and receives messages on links with the settle modes
+async fn settle_modes(
+    ctx: &Context,
+    snd_settle_mode: SenderSettleMode,
+    rcv_settle_mode: ReceiverSettleMode,
+) -> Outcome {
+    if rcv_settle_mode == ReceiverSettleMode::Second && !ctx.capabilities().rcv_settle_mode_second {
+        return Err(Verdict::Skipped(
+            "The capability matrix says the broker doesn't support rcv-settle-mode second".into(),
+        ));
+    }
+    let mut connection = ctx.open().await?;
+    let mut session = ctx.begin(&mut connection).await?;
+    let mut receiver = Receiver::builder()
+        .name(ctx.link_name("receiver"))
+        .source(&ctx.address[..])
+        .sender_settle_mode(snd_settle_mode.clone())
+        .receiver_settle_mode(rcv_settle_mode.clone())
+        .auto_accept(false)
+        .attach(&mut session)
+        .await
+        .or_fail("Attaching the receiver")?;
+    ensure!(
+        *receiver.rcv_settle_mode() == rcv_settle_mode,
+        "The receiver settle mode in use is {:?}",
+        receiver.rcv_settle_mode()
+    );
+    let mut sender = Sender::builder()
+        .name(ctx.link_name("sender"))
+        .target(&ctx.address[..])
+        .sender_settle_mode(snd_settle_mode.clone())
+        .receiver_settle_mode(rcv_settle_mode.clone())
+        .attach(&mut session)
+        .await
+        .or_fail("Attaching the sender")?;
+    ensure!(
+        *sender.snd_settle_mode() == snd_settle_mode,
+        "The sender settle mode in use is {:?}",
+        sender.snd_settle_mode()
+    );
+
+    let mut receipts = Vec::new();
+    for i in 0..3 {
+        let receipt = sender
+            .send_batchable(format!("settle-mode-{}", i))
+            .await
+            .or_fail("Send")?;
+        receipts.push(receipt);
+    }
+    for i in 0..3 {
+        let delivery = receiver.recv::<String>().await.or_fail("Receive")?;
+        ensure!(
+            *delivery.body() == format!("settle-mode-{}", i),
+            "Received {:?} out of order",
+            delivery.body()
+        );
+        let settled = delivery.info().is_settled();
+        match snd_settle_mode {
+            SenderSettleMode::Settled => ensure!(settled, "The delivery was not sent settled"),
+            SenderSettleMode::Unsettled => ensure!(!settled, "The delivery was sent settled"),
+            SenderSettleMode::Mixed => {}
+        }
+        if !settled {
+            receiver.accept(&delivery).await.or_fail("Accept")?;
+        }
+    }
+    for receipt in receipts {
+        let receipt = receipt.await.or_fail("Send")?;
+        let expected = match snd_settle_mode {
+            SenderSettleMode::Settled => receipt.is_settled(),
+            SenderSettleMode::Unsettled => receipt.is_accepted(),
+            SenderSettleMode::Mixed => receipt.is_settled() || receipt.is_accepted(),
+        };
+        ensure!(
+            expected,
+            "Unexpected receipt {} with sender settle mode {:?}",
+            receipt,
+            snd_settle_mode
+        );
+    }
+
+    sender.close().await.or_fail("Closing the sender")?;
+    receiver.close().await.or_fail("Closing the receiver")?;
+    session.end().await.or_fail("End")?;
+    connection.close().await.or_fail("Close")
+}
+
+/// Sends a message several times larger than the negotiated max frame size
+async fn multi_frame(ctx: &Context, max_frame_size: u32) -> Outcome {
+    let options = OpenOptions {
+        max_frame_size: Some(max_frame_size),
+        ..ctx.open_options()
+    };
+    let mut connection = ctx.open_with(options).await.or_fail("Open")?;
+    let negotiated = connection.max_frame_size();
+    ensure!(
+        negotiated <= max_frame_size as usize,
+        "The negotiated max frame size {} is larger than the local max frame size",
+        negotiated
+    );
+    let mut session = ctx.begin(&mut connection).await?;
+    let mut receiver = ctx
+        .attach_receiver(&mut session, CreditMode::default())
+        .await?;
+    let mut sender = ctx.attach_sender(&mut session).await?;
+
+    let payload: Vec<u8> = (0..negotiated * 4 + 17).map(|i| (i % 251) as u8).collect();
+    if let Some(max_message_size) = sender.max_message_size() {
+        if payload.len() as u64 > max_message_size {
+            return Err(Verdict::Skipped(format!(
+                "The broker limits messages to {} bytes",
+                max_message_size
+            )));
+        }
+    }
+    let receipt = sender
+        .send_batchable(Binary::from(payload.clone()))
+        .await
+        .or_fail("Send")?;
+    let delivery = receiver.recv::<Binary>().await.or_fail("Receive")?;
+    receiver.accept(&delivery).await.or_fail("Accept")?;
+    ensure!(
+        delivery.body()[..] == payload[..],
+        "Received {} bytes that differ from the {} bytes sent",
+        delivery.body().len(),
+        payload.len()
+    );
+    let receipt = receipt.await.or_fail("Send")?;
+    ensure!(
+        receipt.is_accepted(),
+        "The message was not accepted: {}",
+        receipt
+    );
+
+    sender.close().await.or_fail("Closing the sender")?;
+    receiver.close().await.or_fail("Closing the receiver")?;
+    session.end().await.or_fail("End")?;
+    connection.close().await.or_fail("Close")
+}
+
+/// Drains a receiver that has more credit than there are messages
+async fn drain(ctx: &Context) -> Outcome {
+    if !ctx.capabilities().drain {
+        return Err(Verdict::Skipped(
+            "The capability matrix says the broker doesn't answer a drain".into(),
+        ));
+    }
+    let mut connection = ctx.open().await?;
+    let mut session = ctx.begin(&mut connection).await?;
+    let mut receiver = ctx
+        .attach_receiver(&mut session, CreditMode::Manual)
+        .await?;
+    let mut sender = ctx.attach_sender(&mut session).await?;
+
+    // Without queues nothing can be sent before the receiver issues credit, and the drain
+    // is answered right away
+    let count = match ctx.capabilities().queues {
+        true => 2,
+        false => 0,
+    };
+    for i in 0..count {
+        let receipt = sender.send(format!("drain-{}", i)).await.or_fail("Send")?;
+        ensure!(
+            receipt.is_accepted(),
+            "The message was not accepted: {}",
+            receipt
+        );
+    }
+    receiver.set_credit(10).await.or_fail("Issuing credit")?;
+    receiver.drain().await.or_fail("Drain")?;
+    receiver.drained().await;
+    let link_credit = receiver.flow_snapshot().link_credit;
+    ensure!(
+        link_credit <= count,
+        "{} credits are left after the drain with {} messages in the queue",
+        link_credit,
+        count
+    );
+
+    for i in 0..count {
+        let delivery = tokio::time::timeout(QUIET_PERIOD, receiver.recv::<String>())
+            .await
+            .map_err(|_| {
+                Verdict::Failed(format!(
+                    "Only {} of the {} messages were delivered before the drain was answered",
+                    i, count
+                ))
+            })?
+            .or_fail("Receive")?;
+        ensure!(
+            *delivery.body() == format!("drain-{}", i),
+            "Received {:?} out of order",
+            delivery.body()
+        );
+        receiver.accept(&delivery).await.or_fail("Accept")?;
+    }
+    ensure!(
+        receiver.flow_snapshot().link_credit == 0,
+        "The drained link still has credit"
+    );
+    let late = tokio::time::timeout(QUIET_PERIOD, receiver.recv::<String>()).await;
+    ensure!(
+        late.is_err(),
+        "A delivery arrived after the drain: {:?}",
+        late
+    );
+
+    sender.close().await.or_fail("Closing the sender")?;
+    receiver.close().await.or_fail("Closing the receiver")?;
+    session.end().await.or_fail("End")?;
+    connection.close().await.or_fail("Close")
+}
+
+/// Disposes a message with each outcome and checks which of them are delivered again
+async fn outcomes(ctx: &Context) -> Outcome {
+    const OUTCOMES: [&str; 4] = ["accepted", "rejected", "released", "modified"];
+
+    let mut connection = ctx.open().await?;
+    let mut session = ctx.begin(&mut connection).await?;
+    let mut receiver = ctx
+        .attach_receiver(&mut session, CreditMode::default())
+        .await?;
+    let mut sender = ctx.attach_sender(&mut session).await?;
+    let mut receipts = Vec::new();
+    for outcome in OUTCOMES {
+        let receipt = sender.send_batchable(outcome).await.or_fail("Send")?;
+        receipts.push((outcome, receipt));
+    }
+
+    // A message that is put back in the queue may come again before the others
+    let mut seen = BTreeSet::new();
+    let mut redelivered = BTreeSet::new();
+    loop {
+        let wait = match seen.len() == OUTCOMES.len() {
+            true => QUIET_PERIOD,
+            false => ctx.config.timeout,
+        };
+        let delivery = match tokio::time::timeout(wait, receiver.recv::<String>()).await {
+            Ok(delivery) => delivery.or_fail("Receive")?,
+            Err(_) => break,
+        };
+        let body = delivery.body().clone();
+        if !seen.insert(body.clone()) {
+            if body == "modified" {
+                let delivery_count = delivery
+                    .message()
+                    .header
+                    .as_ref()
+                    .map(|header| header.delivery_count)
+                    .unwrap_or_default();
+                ensure!(
+                    delivery_count > 0,
+                    "The delivery-count of the modified message was not incremented"
+                );
+            }
+            redelivered.insert(body);
+            receiver.accept(&delivery).await.or_fail("Accept")?;
+            continue;
+        }
+        match body.as_str() {
+            "accepted" => receiver.accept(&delivery).await.or_fail("Accept")?,
+            "rejected" => {
+                let error = definitions::Error::new(
+                    definitions::AmqpError::PreconditionFailed,
+                    "rejected by the interop suite".to_string(),
+                    None,
+                );
+                receiver.reject(&delivery, error).await.or_fail("Reject")?
+            }
+            "released" => receiver.release(&delivery).await.or_fail("Release")?,
+            "modified" => {
+                let modified = Modified {
+                    delivery_failed: Some(true),
+                    undeliverable_here: None,
+                    message_annotations: None,
+                };
+                receiver
+                    .modify(&delivery, modified)
+                    .await
+                    .or_fail("Modify")?
+            }
+            body => return Err(Verdict::Failed(format!("Received unexpected {:?}", body))),
+        }
+    }
+
+    ensure!(
+        seen.len() == OUTCOMES.len(),
+        "Only {:?} were delivered",
+        seen
+    );
+    let expected: BTreeSet<String> = ctx
+        .capabilities()
+        .redelivered_outcomes
+        .iter()
+        .cloned()
+        .collect();
+    ensure!(
+        redelivered == expected,
+        "Expecting {:?} to be delivered again, found {:?}",
+        expected,
+        redelivered
+    );
+
+    // A queue settles the message once it is stored, while a router passes the outcome of the
+    // receiver back to the sender
+    for (outcome, receipt) in receipts {
+        let receipt = receipt.await.or_fail("Send")?;
+        let expected = match (ctx.capabilities().queues, outcome) {
+            (true, _) | (false, "accepted") => receipt.is_accepted(),
+            (false, "rejected") => receipt.is_rejected(),
+            (false, "released") => receipt.is_released(),
+            (false, _) => receipt.is_modified(),
+        };
+        ensure!(
+            expected,
+            "Unexpected receipt {} for the message that was {}",
+            receipt,
+            outcome
+        );
+    }
+
+    sender.close().await.or_fail("Closing the sender")?;
+    receiver.close().await.or_fail("Closing the receiver")?;
+    session.end().await.or_fail("End")?;
+    connection.close().await.or_fail("Close")
+}
+
+/// Stays idle for twice the idle time-out and expects the broker to keep the connection alive
+async fn idle_timeout(ctx: &Context) -> Outcome {
+    let idle_time_out = ctx.capabilities().idle_time_out;
+    let options = OpenOptions {
+        idle_time_out: Some(idle_time_out),
+        ..ctx.open_options()
+    };
+    let mut connection = ctx.open_with(options).await.or_fail("Open")?;
+    if connection.remote_idle_timeout().is_some() {
+        ensure!(
+            connection.heartbeat_interval().is_some(),
+            "No empty frame is sent although the broker has an idle time-out of {:?}",
+            connection.remote_idle_timeout()
+        );
+    }
+
+    let idle_since = Instant::now();
+    tokio::time::sleep(Duration::from_millis(idle_time_out as u64 * 2)).await;
+    ensure!(
+        !connection.is_closed(),
+        "The connection was closed while idle"
+    );
+    ensure!(
+        connection
+            .last_received_at()
+            .map(|at| at > idle_since)
+            .unwrap_or(false),
+        "The broker sent no frame within an idle time-out of {} ms",
+        idle_time_out
+    );
+
+    let mut session = Session::begin(&mut connection).await.or_fail("Begin")?;
+    session.end().await.or_fail("End")?;
+    connection.close().await.or_fail("Close")
+}
+
+/// Attaches a link to a node that doesn't exist and expects the error of the broker
+async fn nonexistent_node(ctx: &Context, sender: bool) -> Outcome {
+    let address = ctx
+        .capabilities()
+        .nonexistent_address
+        .clone()
+        .ok_or_else(|| {
+            Verdict::Skipped("No nonexistent_address in the capability matrix".into())
+        })?;
+    let mut connection = ctx.open().await?;
+    let mut session = ctx.begin(&mut connection).await?;
+
+    // The broker may refuse the attach or detach the link right after attaching it
+    let error = if sender {
+        match Sender::attach(&mut session, ctx.link_name("missing"), &address[..]).await {
+            Err(error) => error.remote_error().cloned().ok_or_else(|| {
+                Verdict::Failed(format!("The attach failed without an error: {:?}", error))
+            })?,
+            Ok(sender) => remote_detach_error(sender.on_detach_by_remote()).await?,
+        }
+    } else {
+        match Receiver::attach(&mut session, ctx.link_name("missing"), &address[..]).await {
+            Err(error) => error.remote_error().cloned().ok_or_else(|| {
+                Verdict::Failed(format!("The attach failed without an error: {:?}", error))
+            })?,
+            Ok(receiver) => remote_detach_error(receiver.on_detach_by_remote()).await?,
+        }
+    };
+    let condition = error.condition.to_string();
+    ensure!(
+        condition == ctx.capabilities().not_found_condition,
+        "Expecting {}, found {:?}",
+        ctx.capabilities().not_found_condition,
+        error
+    );
+
+    // The error ends neither the session nor the connection
+    ctx.round_trip(&mut session).await?;
+    session.end().await.or_fail("End")?;
+    connection.close().await.or_fail("Close")
+}
+
+async fn remote_detach_error(
+    detached: impl Future<Output = Option<DetachError>>,
+) -> Result<definitions::Error, Verdict> {
+    match detached.await {
+        Some(DetachError::RemoteDetachedWithError(error))
+        | Some(DetachError::RemoteClosedWithError(error)) => Ok(error),
+        detached => Err(Verdict::Failed(format!(
+            "The link to the nonexistent node was attached and then {:?}",
+            detached
+        ))),
+    }
+}
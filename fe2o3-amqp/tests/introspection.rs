@@ -0,0 +1,77 @@
+//! Tests of the introspection queries of the handles
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{Connection, Receiver, Sender, Session};
+
+use common::spawn_listener;
+
+#[tokio::test]
+async fn handles_answer_introspection_queries() {
+    use fe2o3_amqp::{
+        introspect::LinkSummary,
+        link::{receiver::CreditMode, LinkState},
+        types::definitions::Role,
+    };
+
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("introspection-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::attach(&mut session, "introspection-sender", "q1")
+        .await
+        .unwrap();
+    // Without credit the remote sender never sends, so the link stays attached
+    let receiver = Receiver::builder()
+        .name("introspection-receiver")
+        .source("q1")
+        .credit_mode(CreditMode::Manual)
+        .attach(&mut session)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        connection.session_channels().await.answered(),
+        Some(vec![0])
+    );
+    let links = session.links().await.answered().unwrap();
+    assert_eq!(links.len(), 2);
+    assert_eq!(
+        links[0],
+        LinkSummary {
+            name: "introspection-sender".to_string(),
+            role: Role::Sender,
+            output_handle: 0,
+            input_handle: links[0].input_handle,
+        }
+    );
+    assert!(links[0].input_handle.is_some());
+    assert_eq!(links[1].name, "introspection-receiver");
+    assert_eq!(links[1].role, Role::Receiver);
+
+    assert!(matches!(sender.local_state(), LinkState::Attached));
+    let debug = format!("{:?}", sender);
+    assert!(debug.contains("state: Attached"));
+    assert!(debug.contains("target: Some(\"q1\")"));
+    let debug = format!("{:?}", receiver);
+    assert!(debug.contains("source: Some(\"q1\")"));
+    assert!(format!("{:?}", connection).contains("active_sessions: 1"));
+
+    sender.send("accept").await.unwrap();
+    sender.close().await.unwrap();
+    let links = session.links().await.answered().unwrap();
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0].name, "introspection-receiver");
+
+    drop(receiver);
+    session.end().await.unwrap();
+    let links = session.links().await;
+    assert!(links.is_unresponsive());
+    assert_eq!(format!("{:?}", links), "<unresponsive>");
+    connection.close().await.unwrap();
+    assert!(connection.session_channels().await.is_unresponsive());
+}
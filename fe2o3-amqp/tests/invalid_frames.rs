@@ -0,0 +1,280 @@
+//! Tests that a frame that cannot be decoded is skipped, ends only the session on its channel or
+//! closes the connection with a framing-error depending on what is wrong with it
+
+use bytes::BytesMut;
+use fe2o3_amqp::{
+    connection::{self, ConnectionEvent, ConnectionHandle},
+    frames::{
+        amqp::{Frame, FrameBody, FrameDecoder, InvalidFrameKind},
+        FRAME_TYPE_AMQP,
+    },
+    session::{self, SessionHandle},
+    transport::{self, protocol_header::ProtocolHeader},
+    types::{
+        definitions::{AmqpError, ConnectionError, ErrorCondition},
+        performatives::{Begin, Close, End, Open},
+    },
+    Connection, Session,
+};
+use serde::Serialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+use tokio_util::codec::Decoder;
+
+/// A malformed Flow that is missing the mandatory incoming-window
+const MALFORMED_FLOW: &[u8] = &[0x00, 0x53, 0x13, 0xc0, 0x02, 0x01, 0x40];
+
+/// A malformed Begin that is missing the mandatory next-outgoing-id
+const MALFORMED_BEGIN: &[u8] = &[0x00, 0x53, 0x11, 0xc0, 0x02, 0x01, 0x40];
+
+/// A described type that is not a performative
+const UNKNOWN_DESCRIBED: &[u8] = &[0x00, 0x53, 0x70, 0x45];
+
+/// A peer that writes frames byte by byte
+struct RawPeer {
+    stream: DuplexStream,
+}
+
+impl RawPeer {
+    async fn write_raw(&mut self, doff: u8, ftype: u8, channel: u16, body: &[u8]) {
+        // The frame header is written in full even if the data offset points inside of it
+        let header_size = usize::max(doff as usize * 4, 8);
+        let size = header_size + body.len();
+        let mut buf = Vec::with_capacity(size);
+        buf.extend_from_slice(&(size as u32).to_be_bytes());
+        buf.push(doff);
+        buf.push(ftype);
+        buf.extend_from_slice(&channel.to_be_bytes());
+        buf.resize(header_size, 0);
+        buf.extend_from_slice(body);
+        self.stream.write_all(&buf).await.unwrap();
+    }
+
+    async fn write_frame(&mut self, channel: u16, performative: impl Serialize) {
+        let body = serde_amqp::to_vec(&performative).unwrap();
+        self.write_raw(2, FRAME_TYPE_AMQP, channel, &body).await;
+    }
+
+    /// Reads the next frame that is not empty
+    async fn read_frame(&mut self) -> Frame {
+        loop {
+            let size = self.stream.read_u32().await.unwrap() as usize;
+            let mut buf = BytesMut::zeroed(size - 4);
+            self.stream.read_exact(&mut buf).await.unwrap();
+            let frame = FrameDecoder {}.decode(&mut buf).unwrap().unwrap();
+            if !matches!(frame.body, FrameBody::Empty) {
+                return frame;
+            }
+        }
+    }
+
+    async fn read_end(&mut self, channel: u16) -> End {
+        let frame = self.read_frame().await;
+        assert_eq!(frame.channel, channel);
+        match frame.body {
+            FrameBody::End(end) => end,
+            body => panic!("Expecting End, found {:?}", body),
+        }
+    }
+
+    /// Replies to the End of the client on the same channel
+    async fn end(&mut self, channel: u16) -> End {
+        let end = self.read_end(channel).await;
+        self.write_frame(channel, End { error: None }).await;
+        end
+    }
+}
+
+/// Opens a connection with two sessions to a raw peer, which maps the outgoing channel of each
+/// session to the same incoming channel
+async fn open_with_raw_peer() -> (
+    ConnectionHandle<()>,
+    SessionHandle<()>,
+    SessionHandle<()>,
+    RawPeer,
+) {
+    let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+    let mut peer = RawPeer { stream: server_io };
+
+    let peer_handshake = async {
+        let mut header = [0u8; 8];
+        peer.stream.read_exact(&mut header).await.unwrap();
+        assert_eq!(header, <[u8; 8]>::from(ProtocolHeader::amqp()));
+        peer.stream.write_all(&header).await.unwrap();
+        match peer.read_frame().await.body {
+            FrameBody::Open(_) => {}
+            body => panic!("Expecting Open, found {:?}", body),
+        }
+        let open = Open {
+            container_id: "raw-peer".to_string(),
+            hostname: None,
+            max_frame_size: Default::default(),
+            channel_max: Default::default(),
+            idle_time_out: None,
+            outgoing_locales: None,
+            incoming_locales: None,
+            offered_capabilities: None,
+            desired_capabilities: None,
+            properties: None,
+        };
+        peer.write_frame(0, open).await;
+
+        for _ in 0..2 {
+            let frame = peer.read_frame().await;
+            let begin = Begin {
+                remote_channel: Some(frame.channel),
+                next_outgoing_id: 0,
+                incoming_window: 2048,
+                outgoing_window: 2048,
+                handle_max: Default::default(),
+                offered_capabilities: None,
+                desired_capabilities: None,
+                properties: None,
+            };
+            peer.write_frame(frame.channel, begin).await;
+        }
+    };
+
+    let client = async {
+        let mut connection = Connection::builder()
+            .container_id("invalid-frames-client")
+            .open_with_stream(client_io)
+            .await
+            .unwrap();
+        let first = Session::begin(&mut connection).await.unwrap();
+        let second = Session::begin(&mut connection).await.unwrap();
+        (connection, first, second)
+    };
+
+    let ((connection, first, second), _) = tokio::join!(client, peer_handshake);
+    (connection, first, second, peer)
+}
+
+/// Closes the connection without errors
+async fn close_gracefully(mut connection: ConnectionHandle<()>, peer: &mut RawPeer) {
+    let peer_close = async {
+        match peer.read_frame().await.body {
+            FrameBody::Close(close) => assert!(close.error.is_none()),
+            body => panic!("Expecting Close, found {:?}", body),
+        }
+        peer.write_frame(0, Close { error: None }).await;
+    };
+    let (result, _) = tokio::join!(connection.close(), peer_close);
+    result.unwrap();
+}
+
+#[tokio::test]
+async fn unknown_frames_are_skipped() {
+    let (connection, mut first, mut second, mut peer) = open_with_raw_peer().await;
+    let mut events = connection.events();
+
+    peer.write_raw(2, 0x05, 0, &[0xde, 0xad]).await;
+    peer.write_raw(2, FRAME_TYPE_AMQP, 1, UNKNOWN_DESCRIBED)
+        .await;
+
+    match events.recv().await.unwrap() {
+        ConnectionEvent::FrameSkipped { channel, reason } => {
+            assert_eq!(channel, 0);
+            assert!(reason.contains("0x05"), "{}", reason);
+        }
+        event => panic!("Unexpected event {:?}", event),
+    }
+    match events.recv().await.unwrap() {
+        ConnectionEvent::FrameSkipped { channel, .. } => assert_eq!(channel, 1),
+        event => panic!("Unexpected event {:?}", event),
+    }
+
+    // The frames are skipped and the End with an extended header ends the session
+    let end = End { error: None };
+    let body = serde_amqp::to_vec(&end).unwrap();
+    peer.write_raw(3, FRAME_TYPE_AMQP, 0, &body).await;
+    assert!(peer.read_end(0).await.error.is_none());
+    assert!(matches!(
+        first.on_end().await,
+        Err(session::Error::RemoteEnded)
+    ));
+
+    let (result, _) = tokio::join!(second.end(), peer.end(1));
+    result.unwrap();
+    close_gracefully(connection, &mut peer).await;
+}
+
+#[tokio::test]
+async fn malformed_session_frame_ends_only_its_session() {
+    let (connection, mut first, mut second, mut peer) = open_with_raw_peer().await;
+    let mut events = connection.events();
+
+    peer.write_raw(2, FRAME_TYPE_AMQP, 0, MALFORMED_FLOW).await;
+
+    // Only the session on the channel of the frame is ended with a decode-error
+    let end = peer.end(0).await;
+    let error = end.error.unwrap();
+    assert_eq!(
+        error.condition,
+        ErrorCondition::AmqpError(AmqpError::DecodeError)
+    );
+    match first.on_end().await {
+        Err(session::Error::MalformedFrame(err)) => assert_eq!(err, error),
+        result => panic!("Unexpected result {:?}", result),
+    }
+    match events.recv().await.unwrap() {
+        ConnectionEvent::SessionEnded {
+            channel,
+            error: err,
+        } => {
+            assert_eq!(channel, 0);
+            assert_eq!(err, error);
+        }
+        event => panic!("Unexpected event {:?}", event),
+    }
+
+    // The other session and the connection are not affected
+    let (result, _) = tokio::join!(second.end(), peer.end(1));
+    result.unwrap();
+    close_gracefully(connection, &mut peer).await;
+}
+
+#[tokio::test]
+async fn invalid_frames_close_the_connection_with_framing_error() {
+    let cases: [(&str, u8, u16, &[u8]); 4] = [
+        ("malformed flow on unmapped channel", 2, 7, MALFORMED_FLOW),
+        ("malformed begin", 2, 0, MALFORMED_BEGIN),
+        ("undecodable descriptor", 2, 0, &[0x00, 0xff]),
+        ("data offset smaller than the header", 1, 0, &[]),
+    ];
+
+    for (case, doff, channel, body) in cases {
+        let (mut connection, mut first, mut second, mut peer) = open_with_raw_peer().await;
+        peer.write_raw(doff, FRAME_TYPE_AMQP, channel, body).await;
+
+        let frame = peer.read_frame().await;
+        let error = match frame.body {
+            FrameBody::Close(close) => close.error.unwrap(),
+            body => panic!("{}: expecting Close, found {:?}", case, body),
+        };
+        assert_eq!(
+            error.condition,
+            ErrorCondition::ConnectionError(ConnectionError::FramingError),
+            "{}",
+            case
+        );
+        peer.write_frame(0, Close { error: None }).await;
+
+        match connection.on_close().await {
+            Err(connection::Error::TransportError(transport::Error::InvalidFrame(invalid))) => {
+                assert_eq!(invalid.channel, channel, "{}", case);
+                assert!(!invalid.kind.is_skippable(), "{}", case);
+                if doff < 2 {
+                    assert!(matches!(
+                        invalid.kind,
+                        InvalidFrameKind::InvalidDataOffset(1)
+                    ));
+                }
+            }
+            result => panic!("{}: unexpected result {:?}", case, result),
+        }
+
+        // Both sessions are stopped with the connection
+        assert!(first.on_end().await.is_err(), "{}", case);
+        assert!(second.on_end().await.is_err(), "{}", case);
+    }
+}
@@ -0,0 +1,88 @@
+//! Tests of the last activity instants of the handles
+
+// The paused clock of tokio does not drive the timers of async-std
+#![cfg(all(
+    feature = "acceptor",
+    not(feature = "rt-async-std"),
+    not(target_arch = "wasm32")
+))]
+
+mod common;
+
+use fe2o3_amqp::{acceptor::ConnectionAcceptor, Connection, Receiver, Sender, Session};
+
+use common::connection_main;
+
+/// Asserts that the instant is between `gap` and a millisecond more before now. The sleeps of
+/// a paused clock are rounded up to the next millisecond
+fn assert_gap(instant: Option<std::time::Instant>, gap: std::time::Duration) {
+    let elapsed = tokio::time::Instant::now().into_std() - instant.unwrap();
+    assert!(
+        elapsed >= gap && elapsed <= gap + std::time::Duration::from_millis(1),
+        "expected a gap of {:?}, found {:?}",
+        gap,
+        elapsed
+    );
+}
+
+/// The paused clock only advances once every task is waiting, so each sleep starts after the
+/// frames of the previous step are exchanged
+#[tokio::test(start_paused = true)]
+async fn last_activity_instants_follow_the_paused_clock() {
+    use std::time::Duration;
+
+    let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        let connection_acceptor = ConnectionAcceptor::new("test-listener");
+        let connection = connection_acceptor.accept(server_io).await.unwrap();
+        connection_main(connection, false).await;
+    });
+
+    let mut connection = Connection::builder()
+        .container_id("activity-connection")
+        .open_with_stream(client_io)
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    assert_gap(connection.last_received_at(), Duration::from_secs(3));
+    assert_gap(connection.last_sent_at(), Duration::from_secs(3));
+
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::attach(&mut session, "activity-sender", "q1")
+        .await
+        .unwrap();
+    assert!(sender.last_disposition_at().is_none());
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    assert_gap(connection.last_received_at(), Duration::from_secs(2));
+
+    sender.send("hello").await.unwrap();
+    tokio::time::sleep(Duration::from_secs(4)).await;
+    assert_gap(sender.last_disposition_at(), Duration::from_secs(4));
+    assert_gap(connection.last_received_at(), Duration::from_secs(4));
+    assert_gap(connection.last_sent_at(), Duration::from_secs(4));
+
+    // The listener sends the next message once the previous one is accepted
+    let mut receiver = Receiver::attach(&mut session, "activity-receiver", "q2")
+        .await
+        .unwrap();
+    let delivery = receiver.recv::<String>().await.unwrap();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert_gap(receiver.last_delivery_at(), Duration::from_secs(1));
+
+    // The delivery is tracked when it arrives rather than when it is taken
+    receiver.accept(&delivery).await.unwrap();
+    tokio::time::sleep(Duration::from_secs(5)).await;
+    assert_gap(receiver.last_delivery_at(), Duration::from_secs(5));
+    assert_gap(connection.last_received_at(), Duration::from_secs(5));
+    let delivery = receiver.recv::<String>().await.unwrap();
+    assert_eq!(delivery.body(), "message-1");
+    assert_gap(receiver.last_delivery_at(), Duration::from_secs(5));
+
+    // Nothing is received on the other link
+    assert_gap(sender.last_disposition_at(), Duration::from_secs(10));
+
+    drop(receiver);
+    sender.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
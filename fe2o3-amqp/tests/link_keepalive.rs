@@ -0,0 +1,91 @@
+//! Tests of links that stay attached while nothing is exchanged
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{link::SendError, Connection, Receiver, Sender, Session};
+
+use common::{expect_receiver, expect_sender, spawn_single_link_listener};
+
+#[tokio::test]
+async fn send_fails_after_no_credit_and_link_stays_attached() {
+    use std::time::Duration;
+
+    let (addr, endpoint_rx) = spawn_single_link_listener("no-credit-listener").await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("no-credit-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::builder()
+        .name("no-credit-sender")
+        .target("q1")
+        .warn_after_no_credit(Duration::from_millis(50))
+        .fail_after_no_credit(Duration::from_millis(200))
+        .attach(&mut session)
+        .await
+        .unwrap();
+    let mut receiver = expect_receiver(endpoint_rx.await.unwrap());
+
+    // The listener withholds credit, so the send fails without consuming any
+    let result = sender.send("withheld").await;
+    assert!(matches!(result, Err(SendError::CreditTimeout)));
+    assert_eq!(sender.flow_snapshot().delivery_count, 0);
+
+    // The link is still attached and the send can be retried once credit is issued
+    receiver.set_credit(1).await.unwrap();
+    let receipt = tokio::spawn(async move {
+        let receipt = sender.send("hello").await.unwrap();
+        (sender, receipt)
+    });
+    let delivery = receiver.recv::<String>().await.unwrap();
+    assert_eq!(delivery.body(), "hello");
+    receiver.accept(&delivery).await.unwrap();
+    let (sender, receipt) = receipt.await.unwrap();
+    assert!(receipt.is_accepted());
+
+    let (sender_closed, receiver_closed) = tokio::join!(sender.close(), receiver.close());
+    sender_closed.unwrap();
+    receiver_closed.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn recv_keeps_waiting_after_no_delivery_warning() {
+    use std::time::Duration;
+
+    let (addr, endpoint_rx) = spawn_single_link_listener("no-delivery-listener").await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("no-delivery-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut receiver = Receiver::builder()
+        .name("no-delivery-receiver")
+        .source("q1")
+        .warn_after_no_delivery(Duration::from_millis(50))
+        .attach(&mut session)
+        .await
+        .unwrap();
+    let mut sender = expect_sender(endpoint_rx.await.unwrap());
+
+    // The delivery arrives after the warning is logged and is still received
+    let send = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let receipt = sender.send("late").await.unwrap();
+        (sender, receipt)
+    });
+    let delivery = receiver.recv::<String>().await.unwrap();
+    assert_eq!(delivery.body(), "late");
+    receiver.accept(&delivery).await.unwrap();
+    let (sender, receipt) = send.await.unwrap();
+    assert!(receipt.is_accepted());
+
+    let (sender_closed, receiver_closed) = tokio::join!(sender.close(), receiver.close());
+    sender_closed.unwrap();
+    receiver_closed.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
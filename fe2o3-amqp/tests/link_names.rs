@@ -0,0 +1,47 @@
+//! Tests of generating and checking link names
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{link::SenderAttachError, Connection, Sender, Session};
+
+use common::spawn_listener;
+
+#[tokio::test]
+async fn link_names_are_generated_and_checked_for_duplicates() {
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("link-name-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+
+    let sender = Sender::attach_auto_name(&mut session, "q1").await.unwrap();
+    let other = Sender::builder()
+        .auto_name("orders")
+        .target("q1")
+        .attach(&mut session)
+        .await
+        .unwrap();
+    assert!(sender.name().starts_with("fe2o3-sender-"));
+    assert!(other.name().starts_with("orders-"));
+    assert_ne!(sender.name(), other.name());
+
+    let name = sender.name().to_string();
+    let result = Sender::attach(&mut session, &name[..], "q1").await;
+    assert!(matches!(result, Err(SenderAttachError::DuplicatedLinkName)));
+
+    // The name of a detached link stays reserved because the link may be resumed
+    let detached = sender.detach().await.unwrap();
+    let result = Sender::attach(&mut session, &name[..], "q1").await;
+    assert!(matches!(result, Err(SenderAttachError::DuplicatedLinkName)));
+
+    drop(detached);
+    let sender = Sender::attach(&mut session, &name[..], "q1").await.unwrap();
+
+    sender.close().await.unwrap();
+    other.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
@@ -0,0 +1,102 @@
+//! Tests of parking and resuming remotely detached links
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use std::sync::Arc;
+
+use fe2o3_amqp::{
+    acceptor::{ConnectionAcceptor, LinkAcceptor, LinkEndpoint, SessionAcceptor},
+    link::{LinkStateError, RecvError},
+    Connection, Sender, Session,
+};
+
+use common::serve_session_with;
+
+#[tokio::test]
+async fn remotely_detached_link_is_parked_and_resumed() {
+    use std::{
+        sync::atomic::{AtomicBool, Ordering},
+        time::Duration,
+    };
+    use tokio::sync::mpsc;
+
+    let (received_tx, mut received_rx) = mpsc::unbounded_channel();
+    let acceptor = ConnectionAcceptor::new("test-listener");
+    let session_acceptor = SessionAcceptor::builder()
+        .parked_link_expiry(Duration::from_millis(500))
+        .build();
+    let (addr, _) = serve_session_with(acceptor, session_acceptor, move |mut session| async move {
+        let link_acceptor = LinkAcceptor::new();
+
+        // The first delivery of "held" is left unsettled
+        let hold = Arc::new(AtomicBool::new(true));
+        while let Ok(LinkEndpoint::Receiver(mut receiver)) =
+            link_acceptor.accept(&mut session).await
+        {
+            let parked_links = session.parked_links().clone();
+            let received_tx = received_tx.clone();
+            let hold = hold.clone();
+            tokio::spawn(async move {
+                loop {
+                    match receiver.recv::<String>().await {
+                        Ok(delivery) => {
+                            received_tx.send(delivery.body().clone()).unwrap();
+                            if delivery.body() != "held" || !hold.swap(false, Ordering::SeqCst) {
+                                receiver.accept(&delivery).await.unwrap();
+                            }
+                        }
+                        Err(RecvError::LinkStateError(LinkStateError::RemoteDetached)) => {
+                            parked_links.park(receiver).await.unwrap();
+                            received_tx.send("parked".to_string()).unwrap();
+                            return;
+                        }
+                        Err(_) => return,
+                    }
+                }
+            });
+        }
+        let _ = session.on_end().await;
+    })
+    .await;
+
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("parked-link-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+
+    let mut sender = Sender::attach(&mut session, "parked-sender", "q1")
+        .await
+        .unwrap();
+    let held = sender.send_batchable("held").await.unwrap();
+    assert_eq!(received_rx.recv().await.unwrap(), "held");
+    let detached = sender.detach().await.unwrap();
+    assert_eq!(received_rx.recv().await.unwrap(), "parked");
+
+    // The re-attach resumes the parked link. The unsettled delivery of "held" is resent to the
+    // parked receiver, which settles it, and the link is detached and parked once more before
+    // the resumption is complete
+    let mut sender = detached.resume().await.unwrap();
+    assert!(held.await.unwrap().is_accepted());
+    assert!(sender.send("after-resume").await.unwrap().is_accepted());
+    let mut received = Vec::new();
+    for _ in 0..3 {
+        received.push(received_rx.recv().await.unwrap());
+    }
+    assert_eq!(received, ["held", "parked", "after-resume"]);
+
+    // A parked link that is not resumed before it expires is discarded, and the re-attach
+    // attaches a new link instead
+    let detached = sender.detach().await.unwrap();
+    assert_eq!(received_rx.recv().await.unwrap(), "parked");
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    let mut sender = detached.resume().await.unwrap();
+    assert!(sender.send("after-expiry").await.unwrap().is_accepted());
+    assert_eq!(received_rx.recv().await.unwrap(), "after-expiry");
+
+    sender.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
@@ -0,0 +1,123 @@
+//! Tests of the credit issued by the accepted receivers
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{acceptor::LinkAcceptor, Connection, Sender, Session};
+
+use common::{expect_receiver, serve_session};
+
+#[tokio::test]
+async fn accepted_receiver_waits_for_manual_credit() {
+    use std::time::Duration;
+
+    use fe2o3_amqp::link::receiver::CreditMode;
+    use tokio::sync::oneshot;
+
+    let (receiver_tx, receiver_rx) = oneshot::channel();
+    let (addr, _) = serve_session("manual-credit-listener", move |mut session| async move {
+        let link_acceptor = LinkAcceptor::builder()
+            .credit_mode(CreditMode::Manual)
+            .build();
+        let receiver = expect_receiver(link_acceptor.accept(&mut session).await.unwrap());
+        receiver_tx.send(receiver).unwrap();
+        let _ = session.on_end().await;
+    })
+    .await;
+
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("manual-credit-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::attach(&mut session, "manual-credit-sender", "q1")
+        .await
+        .unwrap();
+    let send = tokio::spawn(async move {
+        let receipt = sender.send("hello").await.unwrap();
+        (sender, receipt)
+    });
+
+    // No credit is issued until the listener sets it
+    let mut receiver = receiver_rx.await.unwrap();
+    let pending = tokio::time::timeout(Duration::from_millis(200), receiver.recv::<String>()).await;
+    assert!(pending.is_err());
+
+    receiver.set_credit(1).await.unwrap();
+    let delivery = receiver.recv::<String>().await.unwrap();
+    assert_eq!(delivery.body(), "hello");
+    receiver.accept(&delivery).await.unwrap();
+
+    let (sender, receipt) = send.await.unwrap();
+    assert!(receipt.is_accepted());
+
+    // Both ends are in this task, so the links are closed concurrently
+    let (sender_closed, receiver_closed) = tokio::join!(sender.close(), receiver.close());
+    sender_closed.unwrap();
+    receiver_closed.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn client_sender_sends_to_accepted_receiver_in_every_credit_mode() {
+    use fe2o3_amqp::link::receiver::CreditMode;
+    use tokio::sync::oneshot;
+
+    const COUNT: u32 = 20;
+
+    for credit_mode in [
+        CreditMode::Auto(4),
+        CreditMode::Threshold {
+            credit: 4,
+            threshold: 1,
+        },
+        CreditMode::Manual,
+    ] {
+        let is_manual = matches!(credit_mode, CreditMode::Manual);
+        let (receiver_tx, receiver_rx) = oneshot::channel();
+        let (addr, _) = serve_session("credit-mode-listener", move |mut session| async move {
+            let link_acceptor = LinkAcceptor::builder().credit_mode(credit_mode).build();
+            let receiver = expect_receiver(link_acceptor.accept(&mut session).await.unwrap());
+            receiver_tx.send(receiver).unwrap();
+            let _ = session.on_end().await;
+        })
+        .await;
+
+        let url = format!("amqp://{}", addr);
+        let mut connection = Connection::open("credit-mode-connection", &url[..])
+            .await
+            .unwrap();
+        let mut session = Session::begin(&mut connection).await.unwrap();
+        let mut sender = Sender::attach(&mut session, "credit-mode-sender", "q1")
+            .await
+            .unwrap();
+        let send = tokio::spawn(async move {
+            for i in 0..COUNT {
+                let receipt = sender.send(format!("message-{}", i)).await.unwrap();
+                assert!(receipt.is_accepted());
+            }
+            sender
+        });
+
+        let mut receiver = receiver_rx.await.unwrap();
+        if is_manual {
+            // The credit is issued in two steps, which add up to all of the messages
+            receiver.set_credit(COUNT / 2).await.unwrap();
+            receiver.add_credit(COUNT - COUNT / 2).await.unwrap();
+        }
+        for i in 0..COUNT {
+            let delivery = receiver.recv::<String>().await.unwrap();
+            assert_eq!(delivery.body(), &format!("message-{}", i));
+            receiver.accept(&delivery).await.unwrap();
+        }
+
+        let sender = send.await.unwrap();
+        let (sender_closed, receiver_closed) = tokio::join!(sender.close(), receiver.close());
+        sender_closed.unwrap();
+        receiver_closed.unwrap();
+        session.end().await.unwrap();
+        connection.close().await.unwrap();
+    }
+}
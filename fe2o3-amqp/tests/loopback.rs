@@ -0,0 +1,189 @@
+//! Tests of the loopback node
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{
+    types::{
+        definitions::{self, AmqpError},
+        primitives::Value,
+    },
+    Connection, Receiver, Sender, Session,
+};
+
+use common::spawn_loopback_listener;
+
+#[tokio::test]
+async fn loopback_node_forwards_encoded_messages() {
+    use fe2o3_amqp::{
+        acceptor::LoopbackNode,
+        types::{
+            messaging::{ApplicationProperties, Message, MessageId, Properties},
+            primitives::SimpleValue,
+        },
+    };
+
+    let (addr, listener) = spawn_loopback_listener(LoopbackNode::new("loopback")).await;
+
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("loopback-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::attach(&mut session, "loopback-sender", "loopback")
+        .await
+        .unwrap();
+    let mut receiver = Receiver::attach(&mut session, "loopback-receiver", "loopback")
+        .await
+        .unwrap();
+
+    let message = Message::builder()
+        .properties(Properties::builder().message_id(1).build())
+        .application_properties(
+            ApplicationProperties::builder()
+                .insert("key", "value")
+                .build(),
+        )
+        .value("hello loopback")
+        .build();
+    let fut = sender.send_batchable(message).await.unwrap();
+    let delivery = receiver.recv::<Value>().await.unwrap();
+    assert_eq!(delivery.body(), &Value::from("hello loopback"));
+    let properties = delivery.message().properties.as_ref().unwrap();
+    assert_eq!(properties.message_id, Some(MessageId::from(1)));
+    let application_properties = delivery.message().application_properties.as_ref().unwrap();
+    assert_eq!(
+        application_properties.get("key"),
+        Some(&SimpleValue::from("value"))
+    );
+    receiver.accept(&delivery).await.unwrap();
+    assert!(fut.await.unwrap().is_accepted());
+
+    // The outcome of the receiver is returned to the sender
+    let fut = sender.send_batchable("reject me").await.unwrap();
+    let delivery = receiver.recv::<String>().await.unwrap();
+    let error = definitions::Error::new(AmqpError::NotAllowed, None, None);
+    receiver.reject(&delivery, error).await.unwrap();
+    assert!(fut.await.unwrap().is_rejected());
+
+    sender.close().await.unwrap();
+    receiver.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+    assert!(
+        listener.await.unwrap().is_none(),
+        "Expecting every link to be handled by the node"
+    );
+}
+
+#[tokio::test]
+async fn loopback_node_returns_outcomes_while_a_delivery_waits() {
+    use std::time::Duration;
+
+    use fe2o3_amqp::{acceptor::LoopbackNode, link::receiver::CreditMode};
+
+    let (addr, listener) = spawn_loopback_listener(LoopbackNode::new("loopback")).await;
+
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("waiting-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::attach(&mut session, "waiting-sender", "loopback")
+        .await
+        .unwrap();
+    let mut receiver = Receiver::builder()
+        .name("waiting-receiver")
+        .source("loopback")
+        .credit_mode(CreditMode::Manual)
+        .attach(&mut session)
+        .await
+        .unwrap();
+    receiver.set_credit(1).await.unwrap();
+
+    let first = sender.send_batchable("first").await.unwrap();
+    let delivery = receiver.recv::<String>().await.unwrap();
+
+    // The second message waits for link credit on the node
+    let second = sender.send_batchable("second").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    receiver.accept(&delivery).await.unwrap();
+    let receipt = tokio::time::timeout(Duration::from_secs(1), first)
+        .await
+        .expect("Expecting the outcome before the waiting message is taken");
+    assert!(receipt.unwrap().is_accepted());
+
+    receiver.set_credit(1).await.unwrap();
+    let delivery = receiver.recv::<String>().await.unwrap();
+    assert_eq!(delivery.body(), "second");
+    receiver.accept(&delivery).await.unwrap();
+    assert!(second.await.unwrap().is_accepted());
+
+    sender.close().await.unwrap();
+    receiver.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+    assert!(
+        listener.await.unwrap().is_none(),
+        "Expecting every link to be handled by the node"
+    );
+}
+
+#[tokio::test]
+async fn single_active_loopback_node_fails_over_to_the_next_receiver() {
+    use std::time::Duration;
+
+    use fe2o3_amqp::acceptor::{DeliveryPolicy, LoopbackNode};
+
+    let node = LoopbackNode::new("single-active")
+        .delivery_policy(DeliveryPolicy::SingleActive { failover: true });
+    let (addr, listener) = spawn_loopback_listener(node).await;
+
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("single-active-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::attach(&mut session, "single-active-sender", "single-active")
+        .await
+        .unwrap();
+    let mut active = Receiver::attach(&mut session, "active-receiver", "single-active")
+        .await
+        .unwrap();
+    let mut standby = Receiver::attach(&mut session, "standby-receiver", "single-active")
+        .await
+        .unwrap();
+
+    // Only the receiver attached first gets the messages
+    for i in 0..3 {
+        let fut = sender
+            .send_batchable(format!("message-{}", i))
+            .await
+            .unwrap();
+        let delivery = active.recv::<String>().await.unwrap();
+        assert_eq!(delivery.body(), &format!("message-{}", i));
+        active.accept(&delivery).await.unwrap();
+        assert!(fut.await.unwrap().is_accepted());
+    }
+    let standby_recv = tokio::time::timeout(Duration::from_millis(100), standby.recv::<String>());
+    assert!(standby_recv.await.is_err());
+
+    // The standby receiver takes over once the active receiver is detached
+    active.close().await.unwrap();
+    let fut = sender.send_batchable("after failover").await.unwrap();
+    let delivery = standby.recv::<String>().await.unwrap();
+    assert_eq!(delivery.body(), "after failover");
+    standby.accept(&delivery).await.unwrap();
+    assert!(fut.await.unwrap().is_accepted());
+
+    let (sender_closed, standby_closed) = tokio::join!(sender.close(), standby.close());
+    sender_closed.unwrap();
+    standby_closed.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+    assert!(
+        listener.await.unwrap().is_none(),
+        "Expecting every link to be handled by the node"
+    );
+}
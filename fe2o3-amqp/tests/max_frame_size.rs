@@ -0,0 +1,247 @@
+//! Tests of splitting frames against the negotiated max frame size
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use std::sync::Arc;
+
+use fe2o3_amqp::{
+    acceptor::{ConnectionAcceptor, LinkAcceptor, SessionAcceptor},
+    connection::OpenError,
+    Connection, Receiver, Sender, Session,
+};
+
+use common::{bind, expect_receiver, expect_sender};
+
+#[tokio::test]
+async fn large_message_is_split_against_remote_max_frame_size() {
+    use fe2o3_amqp::types::primitives::Binary;
+
+    let (tcp_listener, addr) = bind().await;
+    let payload: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+
+    let listener = tokio::spawn(async move {
+        let acceptor = ConnectionAcceptor::builder()
+            .container_id("test-listener")
+            .max_frame_size(512)
+            .build();
+        let (stream, _) = tcp_listener.accept().await.unwrap();
+        let mut connection = acceptor.accept(stream).await.unwrap();
+        let mut session = SessionAcceptor::new()
+            .accept(&mut connection)
+            .await
+            .unwrap();
+        let link = LinkAcceptor::new().accept(&mut session).await.unwrap();
+        let mut receiver = expect_receiver(link);
+        let delivery = receiver.recv::<Binary>().await.unwrap();
+        receiver.accept(&delivery).await.unwrap();
+        let body = delivery.into_body();
+        while receiver.recv::<Binary>().await.is_ok() {}
+        drop(receiver);
+        let _ = session.on_end().await;
+        let _ = connection.on_close().await;
+        body
+    });
+
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("test-client", &url[..]).await.unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::attach(&mut session, "sender", "q1").await.unwrap();
+    let outcome = sender.send(Binary::from(payload.clone())).await.unwrap();
+    assert!(outcome.is_accepted());
+    sender.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+
+    let received = listener.await.unwrap();
+    assert_eq!(received.len(), payload.len());
+    assert!(received[..] == payload[..]);
+}
+
+/// Keeps a copy of the bytes written to the stream
+#[derive(Debug)]
+struct RecordingStream {
+    stream: tokio::io::DuplexStream,
+    written: Arc<std::sync::Mutex<Vec<u8>>>,
+}
+
+impl RecordingStream {
+    fn new(stream: tokio::io::DuplexStream) -> (Self, Arc<std::sync::Mutex<Vec<u8>>>) {
+        let written = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let stream = Self {
+            stream,
+            written: written.clone(),
+        };
+        (stream, written)
+    }
+}
+
+impl tokio::io::AsyncRead for RecordingStream {
+    fn poll_read(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::pin::Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl tokio::io::AsyncWrite for RecordingStream {
+    fn poll_write(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> std::task::Poll<std::io::Result<usize>> {
+        let poll = std::pin::Pin::new(&mut self.stream).poll_write(cx, buf);
+        if let std::task::Poll::Ready(Ok(written)) = &poll {
+            self.written
+                .lock()
+                .unwrap()
+                .extend_from_slice(&buf[..*written]);
+        }
+        poll
+    }
+
+    fn poll_flush(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::pin::Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::pin::Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+/// The sizes of the frames that follow the protocol header in the recorded bytes
+fn recorded_frame_sizes(written: &[u8]) -> Vec<usize> {
+    let mut sizes = Vec::new();
+    let mut rest = &written[8..];
+    while rest.len() >= 4 {
+        let size = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        sizes.push(size);
+        rest = &rest[size..];
+    }
+    assert!(rest.is_empty());
+    sizes
+}
+
+/// Sends a large message each way between a client and a listener with the given max frame sizes
+/// and returns the frame sizes written by each of them
+async fn exchange_with_max_frame_sizes(client: u32, listener: u32) -> (Vec<usize>, Vec<usize>) {
+    use fe2o3_amqp::types::primitives::Binary;
+
+    let (client_io, listener_io) = tokio::io::duplex(64 * 1024);
+    let (client_io, client_written) = RecordingStream::new(client_io);
+    let (listener_io, listener_written) = RecordingStream::new(listener_io);
+    let negotiated = client.min(listener) as usize;
+    let payload: Vec<u8> = (0..16 * 1024).map(|i| (i % 251) as u8).collect();
+
+    let listener_payload = payload.clone();
+    let listener = tokio::spawn(async move {
+        let acceptor = ConnectionAcceptor::builder()
+            .container_id("test-listener")
+            .max_frame_size(listener)
+            .build();
+        let mut connection = acceptor.accept(listener_io).await.unwrap();
+        assert_eq!(connection.negotiated_max_frame_size(), negotiated);
+        let mut session = SessionAcceptor::new()
+            .accept(&mut connection)
+            .await
+            .unwrap();
+        let link_acceptor = LinkAcceptor::new();
+        let mut receiver = expect_receiver(link_acceptor.accept(&mut session).await.unwrap());
+        let delivery = receiver.recv::<Binary>().await.unwrap();
+        receiver.accept(&delivery).await.unwrap();
+        assert!(delivery.body()[..] == listener_payload[..]);
+        tokio::spawn(async move { while receiver.recv::<Binary>().await.is_ok() {} });
+
+        let mut sender = expect_sender(link_acceptor.accept(&mut session).await.unwrap());
+        let outcome = sender.send(Binary::from(listener_payload)).await.unwrap();
+        assert!(outcome.is_accepted());
+        let _ = sender.on_detach().await;
+        drop(sender);
+        let _ = session.on_end().await;
+        let _ = connection.on_close().await;
+    });
+
+    let mut connection = Connection::builder()
+        .container_id("test-client")
+        .max_frame_size(client)
+        .open_with_stream(client_io)
+        .await
+        .unwrap();
+    assert_eq!(connection.negotiated_max_frame_size(), negotiated);
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::attach(&mut session, "to-listener", "q1")
+        .await
+        .unwrap();
+    let outcome = sender.send(Binary::from(payload.clone())).await.unwrap();
+    assert!(outcome.is_accepted());
+    let mut receiver = Receiver::attach(&mut session, "from-listener", "q1")
+        .await
+        .unwrap();
+    let delivery = receiver.recv::<Binary>().await.unwrap();
+    receiver.accept(&delivery).await.unwrap();
+    assert!(delivery.body()[..] == payload[..]);
+
+    sender.close().await.unwrap();
+    receiver.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+    listener.await.unwrap();
+
+    let client_frames = recorded_frame_sizes(&client_written.lock().unwrap());
+    let listener_frames = recorded_frame_sizes(&listener_written.lock().unwrap());
+    (client_frames, listener_frames)
+}
+
+#[tokio::test]
+async fn frames_are_limited_to_negotiated_max_frame_size() {
+    for (client, listener) in [(1024, 4096), (4096, 1024)] {
+        let (client_frames, listener_frames) =
+            exchange_with_max_frame_sizes(client, listener).await;
+        for frames in [client_frames, listener_frames] {
+            assert!(frames.iter().all(|size| *size <= 1024), "{:?}", frames);
+            // The messages are split into several transfers
+            assert!(frames.len() > 16, "{:?}", frames);
+        }
+    }
+}
+
+#[tokio::test]
+async fn max_frame_size_below_minimum_is_rejected() {
+    let (client_io, _listener_io) = tokio::io::duplex(1024);
+    let result = Connection::builder()
+        .container_id("test-client")
+        .max_frame_size(511)
+        .open_with_stream(client_io)
+        .await;
+    assert!(matches!(result, Err(OpenError::MaxFrameSizeTooSmall(511))));
+
+    let result = Connection::builder()
+        .container_id("test-client")
+        .open("amqp://localhost:5672?max_frame_size=100")
+        .await;
+    assert!(matches!(result, Err(OpenError::MaxFrameSizeTooSmall(100))));
+}
+
+#[tokio::test]
+async fn acceptor_with_max_frame_size_below_minimum_rejects_connections() {
+    let (_client_io, listener_io) = tokio::io::duplex(1024);
+    let (listener_io, listener_written) = RecordingStream::new(listener_io);
+    let acceptor = ConnectionAcceptor::builder()
+        .container_id("test-listener")
+        .max_frame_size(511)
+        .build();
+
+    // The connection is rejected before the protocol header is exchanged
+    let result = acceptor.accept(listener_io).await;
+    assert!(matches!(result, Err(OpenError::MaxFrameSizeTooSmall(511))));
+    assert!(listener_written.lock().unwrap().is_empty());
+}
@@ -0,0 +1,96 @@
+//! Tests of forwarding messages with edited sections
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{Connection, Receiver, Sender, Session};
+
+use common::spawn_loopback_listener;
+
+#[tokio::test]
+async fn edited_message_is_forwarded_with_untouched_sections() {
+    use fe2o3_amqp::{
+        acceptor::LoopbackNode,
+        types::{
+            messaging::{
+                message::sections::SectionKind, ApplicationProperties, Body, Data, Message,
+                Properties,
+            },
+            primitives::{Binary, SimpleValue},
+        },
+    };
+
+    let (addr, listener) = spawn_loopback_listener(LoopbackNode::new("loopback")).await;
+
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("forwarding-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::attach(&mut session, "forwarding-sender", "loopback")
+        .await
+        .unwrap();
+    let mut receiver = Receiver::builder()
+        .name("forwarding-receiver")
+        .source("loopback")
+        .index_sections(true)
+        .attach(&mut session)
+        .await
+        .unwrap();
+
+    let message = Message::builder()
+        .properties(Properties::builder().message_id(1).build())
+        .application_properties(
+            ApplicationProperties::builder()
+                .insert("hops", 0u32)
+                .build(),
+        )
+        .data(Binary::from(vec![0xab; 64 * 1024]))
+        .build();
+    let fut = sender.send_batchable(message).await.unwrap();
+    let delivery = receiver.recv::<Body<Data>>().await.unwrap();
+    let original = delivery.message_mut().unwrap();
+
+    let mut message = original.clone();
+    let mut application_properties = message.decode::<ApplicationProperties>().unwrap().unwrap();
+    application_properties
+        .0
+        .insert(String::from("hops"), SimpleValue::from(1u32));
+    message.set(&application_properties).unwrap();
+    receiver.accept(&delivery).await.unwrap();
+    assert!(fut.await.unwrap().is_accepted());
+
+    // The node returns the outcome of the forwarded message once it is accepted
+    let forwarding = sender.forward_edited(message);
+    let receiving = async {
+        let forwarded = receiver.recv::<Body<Data>>().await.unwrap();
+        receiver.accept(&forwarded).await.unwrap();
+        forwarded
+    };
+    let (receipt, forwarded) = tokio::join!(forwarding, receiving);
+    assert!(receipt.unwrap().is_accepted());
+
+    assert_eq!(
+        forwarded.message().application_properties,
+        Some(application_properties)
+    );
+    assert_eq!(
+        forwarded.message().properties,
+        delivery.message().properties
+    );
+    assert_eq!(forwarded.body(), delivery.body());
+    let forwarded_sections = forwarded.message_mut().unwrap();
+    for kind in [SectionKind::Properties, SectionKind::Data] {
+        assert_eq!(forwarded_sections.get(kind), original.get(kind));
+    }
+
+    sender.close().await.unwrap();
+    receiver.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+    assert!(
+        listener.await.unwrap().is_none(),
+        "Expecting every link to be handled by the node"
+    );
+}
@@ -0,0 +1,129 @@
+//! Tests of redelivering modified messages
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use std::net::SocketAddr;
+
+use fe2o3_amqp::{
+    acceptor::{LinkAcceptor, LinkEndpoint},
+    types::{
+        definitions::Fields,
+        messaging::Modified,
+        primitives::{Symbol, Value},
+    },
+    Connection, Receiver, SendReceipt, Sender, Session,
+};
+
+use common::serve_session;
+
+/// A broker with a single queue that puts back the messages modified by a consumer, with the
+/// annotations of the outcome merged into them
+async fn spawn_redelivering_broker() -> SocketAddr {
+    use fe2o3_amqp::types::messaging::{Body, Message};
+
+    let (addr, _) = serve_session("test-listener", move |mut session| async move {
+        let (queue_tx, queue_rx) = tokio::sync::mpsc::unbounded_channel::<Message<Body<Value>>>();
+        let mut queue_rx = Some(queue_rx);
+        let link_acceptor = LinkAcceptor::new();
+        while let Ok(link) = link_acceptor.accept(&mut session).await {
+            let queue_tx = queue_tx.clone();
+            match link {
+                LinkEndpoint::Receiver(mut receiver) => {
+                    tokio::spawn(async move {
+                        while let Ok(delivery) = receiver.recv::<Body<Value>>().await {
+                            receiver.accept(&delivery).await.unwrap();
+                            queue_tx.send(delivery.into_message()).unwrap();
+                        }
+                    });
+                }
+                LinkEndpoint::Sender(mut sender) => {
+                    let mut queue_rx = queue_rx.take().unwrap();
+                    tokio::spawn(async move {
+                        loop {
+                            let mut message = tokio::select! {
+                                Some(message) = queue_rx.recv() => message,
+                                _ = sender.on_detach() => break,
+                            };
+                            match sender.send(message.clone()).await {
+                                Ok(SendReceipt::Modified(modified)) => {
+                                    message.apply_modified(&modified);
+                                    queue_tx.send(message).unwrap();
+                                }
+                                Ok(_) => {}
+                                Err(_) => break,
+                            }
+                        }
+                        let _ = sender.close().await;
+                    });
+                }
+            }
+        }
+    })
+    .await;
+    addr
+}
+
+#[tokio::test]
+async fn modified_annotations_are_carried_by_redelivery() {
+    use fe2o3_amqp::types::messaging::annotations::OwnedKey;
+
+    let addr = spawn_redelivering_broker().await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("redelivery-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::attach(&mut session, "redelivery-sender", "q1")
+        .await
+        .unwrap();
+    let mut receiver = Receiver::attach(&mut session, "redelivery-receiver", "q1")
+        .await
+        .unwrap();
+    assert!(sender.send("job").await.unwrap().is_accepted());
+
+    // Each attempt modifies the delivery with an incremented retry count
+    for attempt in 0..3u32 {
+        let delivery = receiver.recv::<String>().await.unwrap();
+        assert_eq!(delivery.body(), "job");
+        let retry_count = delivery
+            .message()
+            .message_annotations
+            .as_ref()
+            .and_then(|annotations| annotations.get(&OwnedKey::from("x-retry-count")));
+        let delivery_count = delivery
+            .message()
+            .header
+            .as_ref()
+            .map(|header| header.delivery_count);
+        match attempt {
+            0 => {
+                assert_eq!(retry_count, None);
+                assert_eq!(delivery_count, None);
+            }
+            _ => {
+                assert_eq!(retry_count, Some(&Value::Uint(attempt)));
+                assert_eq!(delivery_count, Some(attempt));
+            }
+        }
+
+        if attempt == 2 {
+            receiver.accept(&delivery).await.unwrap();
+            break;
+        }
+        let mut annotations = Fields::new();
+        annotations.insert(Symbol::from("x-retry-count"), Value::Uint(attempt + 1));
+        let modified = Modified {
+            delivery_failed: Some(true),
+            undeliverable_here: None,
+            message_annotations: Some(annotations),
+        };
+        receiver.modify(&delivery, modified).await.unwrap();
+    }
+
+    sender.close().await.unwrap();
+    receiver.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
@@ -0,0 +1,216 @@
+//! Tests of the validation of the remote open by the connection acceptor
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use std::net::SocketAddr;
+
+use fe2o3_amqp::{
+    acceptor::ConnectionAcceptor,
+    connection::OpenError,
+    types::{
+        definitions::{self, AmqpError, SenderSettleMode},
+        performatives::Open,
+        primitives::{Symbol, Value},
+    },
+    Connection, Sender, Session,
+};
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::TcpListener;
+
+use common::connection_main;
+
+/// Spawns a listener that only accepts connections from the container `allowed` and returns the
+/// outcome of each accept
+async fn spawn_validating_listener(
+    allowed: &'static str,
+) -> (
+    SocketAddr,
+    tokio::sync::mpsc::UnboundedReceiver<Result<(), OpenError>>,
+) {
+    let tcp_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = tcp_listener.local_addr().unwrap();
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let acceptor = ConnectionAcceptor::builder()
+        .container_id("validating-listener")
+        .on_open(
+            move |remote_open: &Open| match remote_open.container_id == allowed {
+                true => Ok(()),
+                false => Err(definitions::Error::new(
+                    AmqpError::UnauthorizedAccess,
+                    "Container is not allowed".to_string(),
+                    None,
+                )),
+            },
+        )
+        .build();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = tcp_listener.accept().await {
+            match acceptor.accept(stream).await {
+                Ok(connection) => {
+                    tokio::spawn(connection_main(connection, false));
+                    let _ = tx.send(Ok(()));
+                }
+                Err(error) => {
+                    let _ = tx.send(Err(error));
+                }
+            }
+        }
+    });
+    (addr, rx)
+}
+
+#[tokio::test]
+async fn validated_open_is_accepted() {
+    let (addr, mut outcomes) = spawn_validating_listener("allowed-client").await;
+    let url = format!("amqp://{}", addr);
+
+    let mut connection = Connection::open("allowed-client", &url[..]).await.unwrap();
+    assert!(outcomes.recv().await.unwrap().is_ok());
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let sender = Sender::attach(&mut session, "sender", "q1").await.unwrap();
+
+    sender.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn rejected_open_is_closed_with_the_error() {
+    let (addr, mut outcomes) = spawn_validating_listener("allowed-client").await;
+    let url = format!("amqp://{}", addr);
+
+    let error = Connection::open("other-client", &url[..])
+        .await
+        .unwrap_err();
+    match error {
+        OpenError::RemoteClosedWithError(error) => {
+            assert_eq!(
+                error.condition,
+                definitions::ErrorCondition::AmqpError(AmqpError::UnauthorizedAccess)
+            );
+        }
+        error => panic!("Unexpected error {:?}", error),
+    }
+    match outcomes.recv().await.unwrap() {
+        Err(OpenError::Rejected(error)) => {
+            assert_eq!(
+                error.description.as_deref(),
+                Some("Container is not allowed")
+            );
+        }
+        outcome => panic!("Unexpected outcome {:?}", outcome),
+    }
+}
+
+#[tokio::test]
+async fn rejected_open_discards_pipelined_frames() {
+    use fe2o3_amqp::types::{
+        definitions::{ReceiverSettleMode, Role},
+        messaging::Source,
+        performatives::{Attach, Begin, Close},
+    };
+    use fe2o3_amqp::{
+        frames::amqp::{Frame, FrameBody},
+        transport::Transport,
+    };
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let (addr, mut outcomes) = spawn_validating_listener("allowed-client").await;
+
+    // The client sends its Open, Begin and Attach without waiting for the remote Open
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    let header = *b"AMQP\x00\x01\x00\x00";
+    stream.write_all(&header).await.unwrap();
+    let mut remote_header = [0u8; 8];
+    stream.read_exact(&mut remote_header).await.unwrap();
+    assert_eq!(remote_header, header);
+
+    let mut transport = Transport::<_, Frame>::bind(stream, 64 * 1024, None);
+    let open = Open {
+        container_id: "pipelining-client".to_string(),
+        hostname: None,
+        max_frame_size: Default::default(),
+        channel_max: Default::default(),
+        idle_time_out: None,
+        outgoing_locales: None,
+        incoming_locales: None,
+        offered_capabilities: None,
+        desired_capabilities: None,
+        properties: None,
+    };
+    let begin = Begin {
+        remote_channel: None,
+        next_outgoing_id: 0,
+        incoming_window: 2048,
+        outgoing_window: 2048,
+        handle_max: Default::default(),
+        offered_capabilities: None,
+        desired_capabilities: None,
+        properties: None,
+    };
+    let attach = Attach {
+        name: "pipelined-link".to_string(),
+        handle: 0.into(),
+        role: Role::Receiver,
+        snd_settle_mode: SenderSettleMode::Mixed,
+        rcv_settle_mode: ReceiverSettleMode::First,
+        source: Some(Box::new(Source::builder().address("q1").build())),
+        target: None,
+        unsettled: None,
+        incomplete_unsettled: false,
+        initial_delivery_count: None,
+        max_message_size: None,
+        offered_capabilities: None,
+        desired_capabilities: None,
+        properties: None,
+    };
+    transport
+        .send(Frame::new(0u16, FrameBody::Open(open)))
+        .await
+        .unwrap();
+    transport
+        .send(Frame::new(0u16, FrameBody::Begin(begin)))
+        .await
+        .unwrap();
+    transport
+        .send(Frame::new(0u16, FrameBody::Attach(attach)))
+        .await
+        .unwrap();
+
+    // The Open that refuses the connection is immediately followed by the Close
+    let frame = transport.next().await.unwrap().unwrap();
+    match frame.body {
+        FrameBody::Open(open) => {
+            let properties = open.properties.unwrap();
+            assert_eq!(
+                properties.get(&Symbol::from("amqp:connection-establishment-failed")),
+                Some(&Value::Bool(true))
+            );
+        }
+        body => panic!("Expecting Open, found {:?}", body),
+    }
+    let frame = transport.next().await.unwrap().unwrap();
+    match frame.body {
+        FrameBody::Close(close) => {
+            let error = close.error.unwrap();
+            assert_eq!(
+                error.condition,
+                definitions::ErrorCondition::AmqpError(AmqpError::UnauthorizedAccess)
+            );
+        }
+        body => panic!("Expecting Close, found {:?}", body),
+    }
+
+    transport
+        .send(Frame::new(0u16, FrameBody::Close(Close { error: None })))
+        .await
+        .unwrap();
+    assert!(matches!(
+        outcomes.recv().await.unwrap(),
+        Err(OpenError::Rejected(_))
+    ));
+    // No Begin is answered for the refused connection
+    assert!(transport.next().await.is_none());
+}
@@ -0,0 +1,197 @@
+//! Tests of pausing and draining a receiver
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{acceptor::LinkAcceptor, Connection, Receiver, Sender, Session};
+
+use common::{expect_sender, serve_session};
+
+#[tokio::test]
+async fn paused_receiver_stops_the_listener_sender_until_resumed() {
+    use std::time::Duration;
+
+    use fe2o3_amqp::link::receiver::CreditMode;
+    use tokio::sync::oneshot;
+
+    async fn wait_for_credit(sender: &Sender, credit: u32) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while sender.flow_snapshot().link_credit != credit {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    let (sender_tx, sender_rx) = oneshot::channel();
+    let (addr, _) = serve_session("pause-listener", move |mut session| async move {
+        let sender = expect_sender(LinkAcceptor::new().accept(&mut session).await.unwrap());
+        sender_tx.send(sender).unwrap();
+        let _ = session.on_end().await;
+    })
+    .await;
+
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("pause-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut receiver = Receiver::builder()
+        .name("pause-receiver")
+        .source("q1")
+        .credit_mode(CreditMode::Auto(4))
+        .attach(&mut session)
+        .await
+        .unwrap();
+    let mut sender = sender_rx.await.unwrap();
+    wait_for_credit(&sender, 4).await;
+
+    // Three deliveries are on the wire when the credit is withdrawn
+    let mut in_flight = Vec::new();
+    for i in 0..3 {
+        let fut = sender
+            .send_batchable(format!("in-flight-{}", i))
+            .await
+            .unwrap();
+        in_flight.push(fut);
+    }
+    receiver.pause().await.unwrap();
+    assert!(receiver.is_paused());
+    wait_for_credit(&sender, 0).await;
+
+    for i in 0..3 {
+        let delivery = receiver.recv::<String>().await.unwrap();
+        assert_eq!(delivery.body(), &format!("in-flight-{}", i));
+        receiver.accept(&delivery).await.unwrap();
+    }
+    for fut in in_flight {
+        assert!(fut.await.unwrap().is_accepted());
+    }
+    // Accepting doesn't re-issue credit while paused
+    assert_eq!(receiver.flow_snapshot().link_credit, 0);
+    receiver
+        .update_credit_mode(CreditMode::Auto(8))
+        .await
+        .unwrap();
+    assert_eq!(sender.flow_snapshot().link_credit, 0);
+
+    // The sender waits for credit
+    let send = tokio::spawn(async move {
+        let receipt = sender.send("after-pause").await.unwrap();
+        (sender, receipt)
+    });
+    let pending = tokio::time::timeout(Duration::from_millis(200), receiver.recv::<String>()).await;
+    assert!(pending.is_err());
+
+    receiver.resume(8).await.unwrap();
+    assert!(!receiver.is_paused());
+    let delivery = receiver.recv::<String>().await.unwrap();
+    assert_eq!(delivery.body(), "after-pause");
+    receiver.accept(&delivery).await.unwrap();
+    let (mut sender, receipt) = send.await.unwrap();
+    assert!(receipt.is_accepted());
+
+    // The credit mode keeps refilling the credit after resuming
+    let send = tokio::spawn(async move {
+        for i in 0..20 {
+            let receipt = sender.send(format!("resumed-{}", i)).await.unwrap();
+            assert!(receipt.is_accepted());
+        }
+        sender
+    });
+    for i in 0..20 {
+        let delivery = receiver.recv::<String>().await.unwrap();
+        assert_eq!(delivery.body(), &format!("resumed-{}", i));
+        receiver.accept(&delivery).await.unwrap();
+    }
+    let sender = send.await.unwrap();
+
+    // Pausing again withdraws the refilled credit
+    receiver.pause().await.unwrap();
+    wait_for_credit(&sender, 0).await;
+    receiver.resume(2).await.unwrap();
+    wait_for_credit(&sender, 2).await;
+
+    let (sender_closed, receiver_closed) = tokio::join!(sender.close(), receiver.close());
+    sender_closed.unwrap();
+    receiver_closed.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn drained_resolves_once_the_listener_sender_answers_the_drain() {
+    use std::time::Duration;
+
+    use fe2o3_amqp::link::receiver::CreditMode;
+    use tokio::sync::oneshot;
+
+    let (sender_tx, sender_rx) = oneshot::channel();
+    let (addr, _) = serve_session("drain-listener", move |mut session| async move {
+        let sender = expect_sender(LinkAcceptor::new().accept(&mut session).await.unwrap());
+        sender_tx.send(sender).unwrap();
+        let _ = session.on_end().await;
+    })
+    .await;
+
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("drain-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut receiver = Receiver::builder()
+        .name("drain-receiver")
+        .source("q1")
+        .credit_mode(CreditMode::Manual)
+        .attach(&mut session)
+        .await
+        .unwrap();
+    let mut sender = sender_rx.await.unwrap();
+    receiver.set_credit(5).await.unwrap();
+
+    // Two deliveries are sent before the sender sees the drain
+    let mut outcomes = Vec::new();
+    for i in 0..2 {
+        let fut = sender.send_batchable(format!("m{}", i)).await.unwrap();
+        outcomes.push(fut);
+    }
+    receiver.drain().await.unwrap();
+    tokio::time::timeout(Duration::from_secs(5), receiver.drained())
+        .await
+        .unwrap();
+    assert_eq!(receiver.flow_snapshot().link_credit, 2);
+    assert_eq!(sender.flow_snapshot().link_credit, 0);
+
+    for i in 0..2 {
+        let delivery = receiver.recv::<String>().await.unwrap();
+        assert_eq!(delivery.body(), &format!("m{}", i));
+        receiver.accept(&delivery).await.unwrap();
+    }
+    for fut in outcomes {
+        assert!(fut.await.unwrap().is_accepted());
+    }
+    let snapshot = receiver.flow_snapshot();
+    assert_eq!(snapshot.link_credit, 0);
+    assert_eq!(
+        snapshot.delivery_count,
+        sender.flow_snapshot().delivery_count
+    );
+
+    // Setting the credit ends the drain
+    receiver.set_credit(1).await.unwrap();
+    let pending = tokio::time::timeout(Duration::from_millis(100), receiver.drained()).await;
+    assert!(pending.is_err());
+    let outcome = sender.send_batchable("after-drain").await.unwrap();
+    let delivery = receiver.recv::<String>().await.unwrap();
+    assert_eq!(delivery.body(), "after-drain");
+    receiver.accept(&delivery).await.unwrap();
+    assert!(outcome.await.unwrap().is_accepted());
+
+    let (sender_closed, receiver_closed) = tokio::join!(sender.close(), receiver.close());
+    sender_closed.unwrap();
+    receiver_closed.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
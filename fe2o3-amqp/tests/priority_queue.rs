@@ -0,0 +1,67 @@
+//! Tests of transferring messages by priority
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{Connection, Sender, Session};
+
+use common::{expect_receiver, spawn_single_link_listener};
+
+#[tokio::test]
+async fn priority_queue_transfers_high_priority_message_first_when_credit_arrives() {
+    use fe2o3_amqp::{
+        link::QueuePolicy,
+        types::messaging::{Header, Message},
+    };
+    use std::time::Duration;
+
+    let (addr, endpoint_rx) = spawn_single_link_listener("priority-listener").await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("priority-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let sender = Sender::builder()
+        .name("priority-sender")
+        .target("q1")
+        .queue_policy(QueuePolicy::Priority {
+            levels: 10,
+            aging: None,
+        })
+        .attach(&mut session)
+        .await
+        .unwrap();
+    let mut receiver = expect_receiver(endpoint_rx.await.unwrap());
+    let (owner, handle) = sender.split();
+
+    // The listener withholds credit while the messages are queued
+    let bulk_1 = handle.enqueue("bulk-1").await.unwrap();
+    let bulk_2 = handle.enqueue("bulk-2").await.unwrap();
+    let urgent = handle.enqueue_with_priority("urgent", 7).await.unwrap();
+    let control = Message::builder()
+        .header(Header::builder().priority(9).build())
+        .value("control")
+        .build();
+    let control = handle.enqueue(control).await.unwrap();
+    // Let the event loop take the queued messages before credit is issued
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    receiver.set_credit(4).await.unwrap();
+    let mut bodies = Vec::new();
+    for _ in 0..4 {
+        let delivery = receiver.recv::<String>().await.unwrap();
+        receiver.accept(&delivery).await.unwrap();
+        bodies.push(delivery.into_body());
+    }
+    assert_eq!(bodies, ["control", "urgent", "bulk-1", "bulk-2"]);
+    for queued in [bulk_1, bulk_2, urgent, control] {
+        assert!(queued.await.unwrap().is_accepted());
+    }
+
+    let (sender_closed, receiver_closed) = tokio::join!(owner.close(), receiver.close());
+    sender_closed.unwrap();
+    receiver_closed.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
@@ -0,0 +1,63 @@
+//! Tests of sending raw frames
+
+#![cfg(all(feature = "acceptor", feature = "testing", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{Connection, Sender, Session};
+
+use common::spawn_listener;
+
+#[tokio::test]
+async fn raw_flow_with_echo_is_answered_by_remote_receiver() {
+    use fe2o3_amqp::{session::SessionFrameBody, types::performatives::Flow};
+
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("raw-frame-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    session.observe_raw_incoming().await.unwrap();
+
+    let sender = Sender::attach(&mut session, "raw-frame-sender", "q1")
+        .await
+        .unwrap();
+    match session.next_raw_incoming().await.unwrap() {
+        SessionFrameBody::Attach(attach) => assert_eq!(attach.name, "raw-frame-sender"),
+        body => panic!("Expecting Attach, found {:?}", body),
+    }
+
+    // A flow with echo set is not sent by the sender on its own
+    let flow = Flow {
+        next_incoming_id: Some(0),
+        incoming_window: 2048,
+        next_outgoing_id: 0,
+        outgoing_window: 2048,
+        handle: Some(0.into()),
+        delivery_count: Some(0),
+        link_credit: Some(0),
+        available: Some(7),
+        drain: false,
+        echo: true,
+        properties: None,
+    };
+    session
+        .send_raw(SessionFrameBody::Flow(flow))
+        .await
+        .unwrap();
+
+    let echoed = loop {
+        match session.next_raw_incoming().await.unwrap() {
+            SessionFrameBody::Flow(flow) if flow.available == Some(7) => break flow,
+            SessionFrameBody::Flow(_) => continue,
+            body => panic!("Expecting Flow, found {:?}", body),
+        }
+    };
+    assert_eq!(echoed.handle, Some(0.into()));
+    assert!(!echoed.echo);
+
+    sender.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
@@ -0,0 +1,137 @@
+//! Tests of accepting with the second receiver settle mode
+
+#![cfg(all(feature = "acceptor", feature = "testing", not(target_arch = "wasm32")))]
+
+mod common;
+
+use std::net::SocketAddr;
+
+use fe2o3_amqp::{
+    acceptor::LinkAcceptor,
+    link::{LinkStateError, RecvError},
+    Connection, Receiver, SendReceipt, Session,
+};
+
+use common::{expect_sender, serve_session};
+
+/// Spawns a listener whose sender sends one message to a receiver in `ReceiverSettleMode::Second`
+/// and reports the outcome, and then detaches the link if the source address is "detach"
+async fn spawn_mode_second_listener() -> (
+    SocketAddr,
+    tokio::sync::oneshot::Receiver<SendReceipt>,
+    tokio::sync::mpsc::UnboundedReceiver<fe2o3_amqp::types::performatives::Disposition>,
+) {
+    use fe2o3_amqp::session::SessionFrameBody;
+
+    let (outcome_tx, outcome_rx) = tokio::sync::oneshot::channel();
+    let (disposition_tx, dispositions) = tokio::sync::mpsc::unbounded_channel();
+    let (addr, _) = serve_session("mode-second-listener", move |mut session| async move {
+        session.observe_raw_incoming().await.unwrap();
+        let mut sender = expect_sender(LinkAcceptor::new().accept(&mut session).await.unwrap());
+        tokio::spawn(async move {
+            let address = sender
+                .source()
+                .as_ref()
+                .and_then(|source| source.address.clone());
+            if address.as_deref() == Some("detach") {
+                let _outcome = sender.send_batchable("message-0").await.unwrap();
+                let _ = sender.detach().await;
+            } else {
+                let outcome = sender.send("message-0").await.unwrap();
+                outcome_tx.send(outcome).unwrap();
+                let _ = sender.close().await;
+            }
+        });
+        while let Some(body) = session.next_raw_incoming().await {
+            match body {
+                SessionFrameBody::Disposition(disposition) => {
+                    let _ = disposition_tx.send(disposition);
+                }
+                SessionFrameBody::End(_) => break,
+                _ => {}
+            }
+        }
+        let _ = session.on_end().await;
+    })
+    .await;
+    (addr, outcome_rx, dispositions)
+}
+
+#[tokio::test]
+async fn receiver_settle_mode_second_accept_waits_for_sender_settlement() {
+    use fe2o3_amqp::types::{definitions::ReceiverSettleMode, messaging::DeliveryState};
+
+    let (addr, outcome, mut dispositions) = spawn_mode_second_listener().await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("mode-second-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut receiver = Receiver::builder()
+        .name("mode-second-receiver")
+        .source("q1")
+        .receiver_settle_mode(ReceiverSettleMode::Second)
+        .attach(&mut session)
+        .await
+        .unwrap();
+
+    let delivery = receiver.recv::<String>().await.unwrap();
+    assert_eq!(delivery.body(), "message-0");
+    assert_eq!(receiver.flow_snapshot().unsettled, 1);
+
+    // The receiver sends its outcome unsettled and the delivery is only removed from the
+    // unsettled map once the sender has settled it
+    receiver.accept(&delivery).await.unwrap();
+    let disposition = dispositions.recv().await.unwrap();
+    assert!(!disposition.settled);
+    assert!(matches!(
+        disposition.state,
+        Some(DeliveryState::Accepted(_))
+    ));
+    assert_eq!(receiver.flow_snapshot().unsettled, 0);
+    assert!(outcome.await.unwrap().is_accepted());
+
+    assert!(matches!(
+        receiver.recv::<String>().await,
+        Err(RecvError::LinkStateError(LinkStateError::RemoteClosed))
+    ));
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn receiver_settle_mode_second_accept_fails_after_remote_detach() {
+    use fe2o3_amqp::{link::DispositionError, types::definitions::ReceiverSettleMode};
+
+    let (addr, _outcome, mut dispositions) = spawn_mode_second_listener().await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("mode-second-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut receiver = Receiver::builder()
+        .name("mode-second-receiver")
+        .source("detach")
+        .receiver_settle_mode(ReceiverSettleMode::Second)
+        .attach(&mut session)
+        .await
+        .unwrap();
+
+    let delivery = receiver.recv::<String>().await.unwrap();
+    assert!(matches!(
+        receiver.recv::<String>().await,
+        Err(RecvError::LinkStateError(LinkStateError::RemoteDetached))
+    ));
+
+    // The sender can no longer settle the delivery, so no outcome is sent
+    assert!(matches!(
+        receiver.accept(&delivery).await,
+        Err(DispositionError::Detached)
+    ));
+    assert_eq!(receiver.flow_snapshot().unsettled, 1);
+
+    drop(receiver);
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+    assert!(dispositions.recv().await.is_none());
+}
@@ -0,0 +1,83 @@
+//! Tests of receiving deliveries as a stream
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{
+    link::{LinkStateError, RecvError},
+    types::{definitions::AmqpError, primitives::Value},
+    Connection, Receiver, Session,
+};
+use futures_util::{stream::FusedStream, StreamExt};
+
+use common::spawn_listener;
+
+#[tokio::test]
+async fn receiver_stream_ends_on_remote_close() {
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("receiver-stream-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+
+    let receiver = Receiver::builder()
+        .name("receiver-stream")
+        .source("stream")
+        .auto_accept(true)
+        .attach(&mut session)
+        .await
+        .unwrap();
+    let bodies: Vec<Value> = receiver
+        .into_stream::<Value>()
+        .map(|delivery| delivery.unwrap().into_body())
+        .collect()
+        .await;
+    assert_eq!(
+        bodies,
+        vec![
+            Value::from("message-0"),
+            Value::from("message-1"),
+            Value::from("message-2")
+        ]
+    );
+
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn receiver_stream_yields_remote_detach_error() {
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("receiver-stream-error-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+
+    let mut receiver = Receiver::attach(&mut session, "receiver-stream-error", "stream-error")
+        .await
+        .unwrap();
+
+    // Taking one delivery with a borrowing stream leaves the receiver usable
+    let delivery = receiver.stream::<Value>().next().await.unwrap().unwrap();
+    receiver.accept(&delivery).await.unwrap();
+
+    let mut stream = receiver.into_stream::<Value>();
+    for _ in 0..2 {
+        let delivery = stream.next().await.unwrap().unwrap();
+        stream.get_ref().unwrap().accept(&delivery).await.unwrap();
+    }
+    match stream.next().await {
+        Some(Err(RecvError::LinkStateError(LinkStateError::RemoteClosedWithError(error)))) => {
+            assert_eq!(error.condition, AmqpError::ResourceDeleted.into())
+        }
+        other => panic!("Expecting RemoteClosedWithError, found {:?}", other),
+    }
+    assert!(stream.next().await.is_none());
+    assert!(stream.is_terminated());
+
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
@@ -0,0 +1,121 @@
+//! Tests of receiving deliveries in batches
+
+#![cfg(all(feature = "acceptor", feature = "testing", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{
+    acceptor::LinkAcceptor,
+    link::{LinkStateError, RecvError},
+    Connection, Receiver, Session,
+};
+
+use common::{expect_sender, serve_session};
+
+#[tokio::test]
+async fn recv_batch_settles_each_batch_with_one_disposition() {
+    use std::time::Duration;
+
+    use fe2o3_amqp::{link::receiver::CreditMode, session::SessionFrameBody};
+
+    let (send_more_tx, send_more_rx) = tokio::sync::oneshot::channel::<()>();
+    let (disposition_tx, mut dispositions) = tokio::sync::mpsc::unbounded_channel();
+    let (addr, _) = serve_session("batch-listener", move |mut session| async move {
+        session.observe_raw_incoming().await.unwrap();
+        let mut sender = expect_sender(LinkAcceptor::new().accept(&mut session).await.unwrap());
+        tokio::spawn(async move {
+            // Sends as credit is issued, and the last two messages only after the second batch
+            // has timed out
+            let mut outcomes = Vec::new();
+            for i in 0..5 {
+                let message = format!("message-{}", i);
+                outcomes.push(sender.send_batchable(message).await.unwrap());
+            }
+            send_more_rx.await.unwrap();
+            for i in 5..7 {
+                let message = format!("message-{}", i);
+                outcomes.push(sender.send_batchable(message).await.unwrap());
+            }
+            for outcome in outcomes {
+                assert!(outcome.await.unwrap().is_accepted());
+            }
+            sender.close().await.unwrap();
+        });
+        while let Some(body) = session.next_raw_incoming().await {
+            match body {
+                SessionFrameBody::Disposition(disposition) => {
+                    let _ = disposition_tx.send(disposition);
+                }
+                SessionFrameBody::End(_) => break,
+                _ => {}
+            }
+        }
+        let _ = session.on_end().await;
+    })
+    .await;
+
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("batch-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut receiver = Receiver::builder()
+        .name("batch-receiver")
+        .source("q1")
+        .credit_mode(CreditMode::Auto(2))
+        .attach(&mut session)
+        .await
+        .unwrap();
+
+    // The credit is raised to the size of the batch
+    let batch = receiver
+        .recv_batch::<String>(3, Duration::from_secs(5))
+        .await
+        .unwrap();
+    let bodies: Vec<_> = batch
+        .iter()
+        .map(|delivery| delivery.body().clone())
+        .collect();
+    assert_eq!(bodies, ["message-0", "message-1", "message-2"]);
+    receiver.accept_all(&batch).await.unwrap();
+
+    // Only two messages are available, so the deadline fires before the batch is full
+    let batch = receiver
+        .recv_batch::<String>(4, Duration::from_millis(200))
+        .await
+        .unwrap();
+    let bodies: Vec<_> = batch
+        .iter()
+        .map(|delivery| delivery.body().clone())
+        .collect();
+    assert_eq!(bodies, ["message-3", "message-4"]);
+    receiver.accept_all(&batch).await.unwrap();
+
+    // Messages sent after the deadline are received by the next batch
+    send_more_tx.send(()).unwrap();
+    let batch = receiver
+        .recv_batch::<String>(2, Duration::from_secs(5))
+        .await
+        .unwrap();
+    let bodies: Vec<_> = batch
+        .iter()
+        .map(|delivery| delivery.body().clone())
+        .collect();
+    assert_eq!(bodies, ["message-5", "message-6"]);
+    receiver.accept_all(&batch).await.unwrap();
+
+    // Each batch is settled with a single Disposition
+    for (first, last) in [(0, 2), (3, 4), (5, 6)] {
+        let disposition = dispositions.recv().await.unwrap();
+        assert_eq!(disposition.first, first);
+        assert_eq!(disposition.last, Some(last));
+    }
+
+    assert!(matches!(
+        receiver.recv::<String>().await,
+        Err(RecvError::LinkStateError(LinkStateError::RemoteClosed))
+    ));
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+    assert!(dispositions.recv().await.is_none());
+}
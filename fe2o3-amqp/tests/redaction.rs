@@ -0,0 +1,181 @@
+//! Tests that the credentials exchanged by a connection never show up in the log output
+
+#![cfg(all(feature = "log", feature = "acceptor"))]
+
+use std::{borrow::Cow, net::SocketAddr, sync::Mutex};
+
+use fe2o3_amqp::{
+    acceptor::{
+        ConnectionAcceptor, LinkAcceptor, LinkEndpoint, SaslPlainMechanism, SessionAcceptor,
+    },
+    frames::amqp::FrameBody,
+    sasl_profile::SaslProfile,
+    types::{
+        definitions::Fields,
+        messaging::{ApplicationProperties, Message},
+        primitives::{Symbol, Value},
+    },
+    Connection, Sender, Session,
+};
+use tokio::net::TcpListener;
+
+/// Appends every log record to a buffer
+struct CapturingLogger {
+    output: Mutex<String>,
+}
+
+impl log::Log for CapturingLogger {
+    fn enabled(&self, _: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        let mut output = self.output.lock().unwrap();
+        output.push_str(&record.args().to_string());
+        output.push('\n');
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: CapturingLogger = CapturingLogger {
+    output: Mutex::new(String::new()),
+};
+
+/// Installs the logger, which is shared by all the tests in this file
+fn captured_logs() -> &'static CapturingLogger {
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(log::LevelFilter::Trace);
+    &LOGGER
+}
+
+impl CapturingLogger {
+    fn output(&self) -> String {
+        self.output.lock().unwrap().clone()
+    }
+}
+
+const TOKEN_KEY: &str = "token";
+
+/// Masks the token in the properties of the Open frames
+fn mask_token(body: &FrameBody) -> Cow<'_, FrameBody> {
+    match body {
+        FrameBody::Open(open) => {
+            let mut open = open.clone();
+            if let Some(token) = open
+                .properties
+                .as_mut()
+                .and_then(|properties| properties.get_mut(TOKEN_KEY))
+            {
+                *token = Value::String(String::from("<masked>"));
+            }
+            Cow::Owned(FrameBody::Open(open))
+        }
+        _ => Cow::Borrowed(body),
+    }
+}
+
+/// Spawns a listener that authenticates with SASL PLAIN and receives one message on every
+/// incoming link
+async fn spawn_listener(username: &str, password: &str) -> SocketAddr {
+    let tcp_listener = TcpListener::bind("localhost:0").await.unwrap();
+    let addr = tcp_listener.local_addr().unwrap();
+    let connection_acceptor = ConnectionAcceptor::builder()
+        .container_id("test-redaction-listener")
+        .sasl_acceptor(SaslPlainMechanism::new(username, password))
+        .log_redactor(mask_token)
+        .build();
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = tcp_listener.accept().await {
+            let mut connection = connection_acceptor.accept(stream).await.unwrap();
+            tokio::spawn(async move {
+                let session_acceptor = SessionAcceptor::new();
+                while let Ok(mut session) = session_acceptor.accept(&mut connection).await {
+                    tokio::spawn(async move {
+                        let link_acceptor = LinkAcceptor::new();
+                        while let Ok(link) = link_acceptor.accept(&mut session).await {
+                            if let LinkEndpoint::Receiver(mut receiver) = link {
+                                let delivery = receiver.recv::<Value>().await.unwrap();
+                                receiver.accept(&delivery).await.unwrap();
+                                receiver.close().await.unwrap();
+                            }
+                        }
+                        let _ = session.on_end().await;
+                    });
+                }
+                let _ = connection.on_close().await;
+            });
+        }
+    });
+
+    addr
+}
+
+#[tokio::test]
+async fn sasl_plain_credentials_are_redacted() {
+    let logs = captured_logs();
+    let password = "plain-password-5f1e";
+    let addr = spawn_listener("test-user", password).await;
+
+    let profile = SaslProfile::Plain {
+        username: String::from("test-user"),
+        password: String::from(password),
+    };
+    assert!(!format!("{:?}", profile).contains(password));
+    let mut connection = Connection::builder()
+        .container_id("test-sasl-redaction")
+        .sasl_profile(profile)
+        .open(&format!("amqp://{}", addr)[..])
+        .await
+        .unwrap();
+    connection.close().await.unwrap();
+
+    let output = logs.output();
+    assert!(output.contains("initial_response: Some(<redacted, "));
+    assert!(!output.contains(password));
+}
+
+#[tokio::test]
+async fn masked_properties_are_redacted() {
+    let logs = captured_logs();
+    let token = "open-token-93ab";
+    let secret = "message-secret-c7d2";
+    let addr = spawn_listener("test-user", "test-password").await;
+
+    let mut properties = Fields::new();
+    properties.insert(Symbol::from(TOKEN_KEY), Value::String(String::from(token)));
+    let mut connection = Connection::builder()
+        .container_id("test-property-redaction")
+        .sasl_profile(SaslProfile::Plain {
+            username: String::from("test-user"),
+            password: String::from("test-password"),
+        })
+        .properties(properties)
+        .log_redactor(mask_token)
+        .open(&format!("amqp://{}", addr)[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::attach(&mut session, "test-sender", "q1")
+        .await
+        .unwrap();
+    let message = Message::builder()
+        .application_properties(
+            ApplicationProperties::builder()
+                .insert("password", secret)
+                .build(),
+        )
+        .value(secret)
+        .build();
+    sender.send(message).await.unwrap();
+    sender.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+
+    // Both sides trace the Open of the client
+    let output = logs.output();
+    assert!(output.matches("<masked>").count() >= 2);
+    assert!(!output.contains(token));
+    assert!(!output.contains(secret));
+}
@@ -0,0 +1,107 @@
+//! Tests of following connection redirects
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use fe2o3_amqp::{
+    acceptor::ConnectionAcceptor,
+    connection::OpenError,
+    types::{definitions, performatives::Open},
+    Connection,
+};
+use tokio::net::TcpListener;
+
+/// Spawns a listener that redirects every connection to the container returned by `target` for
+/// the port of the listener and counts the redirects
+async fn spawn_redirecting_listener(
+    target: impl Fn(u16) -> definitions::Redirect + Send + Sync + 'static,
+) -> (SocketAddr, Arc<AtomicUsize>) {
+    let tcp_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = tcp_listener.local_addr().unwrap();
+    let redirected = Arc::new(AtomicUsize::new(0));
+    let count = redirected.clone();
+    let acceptor = ConnectionAcceptor::builder()
+        .container_id("redirecting-listener")
+        .redirect_with(move |_: &Open| {
+            count.fetch_add(1, Ordering::Relaxed);
+            Some(target(addr.port()))
+        })
+        .build();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = tcp_listener.accept().await {
+            let error = acceptor.accept(stream).await.unwrap_err();
+            assert!(matches!(error, OpenError::Redirected(_)));
+        }
+    });
+    (addr, redirected)
+}
+
+#[tokio::test]
+async fn connection_redirect_is_followed_to_the_new_container() {
+    let tcp_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let target_port = tcp_listener.local_addr().unwrap().port();
+    let target = tokio::spawn(async move {
+        let (stream, _) = tcp_listener.accept().await.unwrap();
+        let mut connection = ConnectionAcceptor::new("target-listener")
+            .accept(stream)
+            .await
+            .unwrap();
+        let remote_open = connection.remote_open().clone();
+        let _ = connection.on_close().await;
+        remote_open
+    });
+
+    let redirect = definitions::Redirect::new("127.0.0.1", target_port).hostname("vhost-b");
+    let (addr, redirected) = {
+        let redirect = redirect.clone();
+        spawn_redirecting_listener(move |_| redirect.clone()).await
+    };
+    let url = format!("amqp://{}", addr);
+
+    // Without following, the redirect is returned
+    let error = Connection::open("test-client", &url[..]).await.unwrap_err();
+    assert_eq!(error.redirect(), Some(redirect));
+
+    let mut connection = Connection::builder()
+        .container_id("test-client")
+        .follow_redirects(1)
+        .open(&url[..])
+        .await
+        .unwrap();
+    assert_eq!(connection.remote_open().container_id, "target-listener");
+    connection.close().await.unwrap();
+
+    let remote_open = target.await.unwrap();
+    assert_eq!(remote_open.hostname.as_deref(), Some("vhost-b"));
+    assert_eq!(redirected.load(Ordering::Relaxed), 2);
+}
+
+#[tokio::test]
+async fn connection_redirects_stop_at_the_hop_limit() {
+    // Every connection is redirected back to the same listener
+    let (addr, redirected) =
+        spawn_redirecting_listener(|port| definitions::Redirect::new("127.0.0.1", port)).await;
+
+    let url = format!("amqp://{}", addr);
+    let error = Connection::builder()
+        .container_id("test-client")
+        .follow_redirects(3)
+        .open(&url[..])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.redirect(),
+        Some(definitions::Redirect::new("127.0.0.1", addr.port()))
+    );
+    // The first connection and three redirects
+    assert_eq!(redirected.load(Ordering::Relaxed), 4);
+}
@@ -0,0 +1,82 @@
+//! Tests of releasing unsettled deliveries when a receiver closes
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{link::DetachError, Connection, Receiver, Session};
+
+use common::{expect_sender, spawn_single_link_listener};
+
+#[tokio::test]
+async fn unsettled_deliveries_are_released_before_the_receiver_detaches() {
+    use fe2o3_amqp::link::receiver::OnCloseUnsettled;
+    use std::time::Duration;
+
+    const COUNT: usize = 50;
+
+    // The receiver is closed and then dropped without closing
+    for close in [true, false] {
+        let (addr, endpoint_rx) = spawn_single_link_listener("release-on-close-listener").await;
+        let url = format!("amqp://{}", addr);
+        let mut connection = Connection::open("release-on-close-connection", &url[..])
+            .await
+            .unwrap();
+        let mut session = Session::begin(&mut connection).await.unwrap();
+        let mut receiver = Receiver::builder()
+            .name("release-on-close-receiver")
+            .source("q1")
+            .on_close_unsettled(OnCloseUnsettled::Release)
+            .attach(&mut session)
+            .await
+            .unwrap();
+        let mut sender = expect_sender(endpoint_rx.await.unwrap());
+
+        // The sends are dropped once the deliveries are handed to the session, so that their
+        // outcomes are kept for `pending_outcomes`
+        let (_, deliveries) = tokio::join!(
+            async {
+                for i in 0..COUNT {
+                    tokio::select! {
+                        _ = sender.send(format!("m{}", i)) => panic!("The deliveries are never accepted"),
+                        _ = tokio::time::sleep(Duration::from_millis(10)) => {}
+                    }
+                }
+            },
+            async {
+                let mut deliveries = Vec::with_capacity(COUNT);
+                for _ in 0..COUNT {
+                    deliveries.push(receiver.recv::<String>().await.unwrap());
+                }
+                deliveries
+            }
+        );
+        assert_eq!(deliveries.len(), COUNT);
+        assert_eq!(receiver.flow_snapshot().unsettled, COUNT);
+
+        let detach_seen = async {
+            let error = sender.on_detach().await;
+            // The dispositions are handled before the Detach is relayed to the sender
+            let pending = sender.pending_outcomes();
+            let _ = sender.close().await;
+            (error, pending)
+        };
+        let ((error, pending), _) = tokio::join!(detach_seen, async {
+            match close {
+                true => receiver.close().await.unwrap(),
+                false => drop(receiver),
+            }
+        });
+        assert!(matches!(error, DetachError::ClosedByRemote));
+        assert_eq!(pending.len(), COUNT);
+        for pending in pending {
+            match pending.outcome {
+                Some(Ok(receipt)) => assert!(receipt.is_released()),
+                outcome => panic!("Expecting Released, found {:?}", outcome),
+            }
+        }
+
+        session.end().await.unwrap();
+        connection.close().await.unwrap();
+    }
+}
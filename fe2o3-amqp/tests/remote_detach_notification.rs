@@ -0,0 +1,161 @@
+//! Tests of notifying remote detaches
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{
+    link::{DetachError, LinkStateError, RecvError, SendError},
+    types::definitions::{self, AmqpError},
+    Connection, Receiver, Sender, Session,
+};
+
+use common::{expect_receiver, expect_sender, spawn_single_link_listener};
+
+#[tokio::test]
+async fn remote_detach_is_notified_while_a_send_is_waiting() {
+    use std::time::Duration;
+
+    for closed in [true, false] {
+        let (addr, endpoint_rx) = spawn_single_link_listener("remote-detach-listener").await;
+        let url = format!("amqp://{}", addr);
+        let mut connection = Connection::open("remote-detach-connection", &url[..])
+            .await
+            .unwrap();
+        let mut session = Session::begin(&mut connection).await.unwrap();
+        let mut sender = Sender::attach(&mut session, "remote-detach-sender", "q1")
+            .await
+            .unwrap();
+        let receiver = expect_receiver(endpoint_rx.await.unwrap());
+
+        // The listener withholds credit, so the send is waiting when the link is detached
+        let detached = sender.on_detach_by_remote();
+        let send = tokio::spawn(async move { sender.send("withheld").await });
+        let error = definitions::Error::new(
+            AmqpError::ResourceDeleted,
+            Some("Queue deleted".to_string()),
+            None,
+        );
+        let remote = {
+            let error = error.clone();
+            tokio::spawn(async move {
+                match closed {
+                    true => receiver.close_with_error(error).await,
+                    false => receiver
+                        .detach_with_error(error)
+                        .await
+                        .map(|_| ())
+                        .map_err(|e| e.1),
+                }
+            })
+        };
+
+        let notified = tokio::time::timeout(Duration::from_secs(1), detached)
+            .await
+            .unwrap();
+        let sent = tokio::time::timeout(Duration::from_secs(1), send)
+            .await
+            .unwrap()
+            .unwrap();
+        match closed {
+            true => {
+                assert!(
+                    matches!(notified, Some(DetachError::RemoteClosedWithError(e)) if e == error)
+                );
+                assert!(matches!(
+                    sent,
+                    Err(SendError::LinkStateError(LinkStateError::RemoteClosedWithError(e))) if e == error
+                ));
+            }
+            false => {
+                assert!(
+                    matches!(notified, Some(DetachError::RemoteDetachedWithError(e)) if e == error)
+                );
+                assert!(matches!(
+                    sent,
+                    Err(SendError::LinkStateError(LinkStateError::RemoteDetachedWithError(e))) if e == error
+                ));
+            }
+        }
+        remote.await.unwrap().unwrap();
+
+        session.end().await.unwrap();
+        connection.close().await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn remote_detach_is_notified_to_an_idle_receiver() {
+    use std::time::Duration;
+
+    for closed in [true, false] {
+        let (addr, endpoint_rx) = spawn_single_link_listener("idle-detach-listener").await;
+        let url = format!("amqp://{}", addr);
+        let mut connection = Connection::open("idle-detach-connection", &url[..])
+            .await
+            .unwrap();
+        let mut session = Session::begin(&mut connection).await.unwrap();
+        let mut receiver = Receiver::attach(&mut session, "idle-detach-receiver", "q1")
+            .await
+            .unwrap();
+        let sender = expect_sender(endpoint_rx.await.unwrap());
+
+        // Nothing reads the incoming frames of the receiver until the notification resolves
+        let detached = receiver.on_detach_by_remote();
+        let remote = tokio::spawn(async move {
+            match closed {
+                true => sender.close().await,
+                false => sender.detach().await.map(|_| ()).map_err(|e| e.1),
+            }
+        });
+        let notified = tokio::time::timeout(Duration::from_secs(1), detached)
+            .await
+            .unwrap();
+        match closed {
+            true => assert!(matches!(notified, Some(DetachError::ClosedByRemote))),
+            false => assert!(matches!(notified, Some(DetachError::DetachedByRemote))),
+        }
+
+        let result = receiver.recv::<String>().await;
+        match closed {
+            true => assert!(matches!(
+                result,
+                Err(RecvError::LinkStateError(LinkStateError::RemoteClosed))
+            )),
+            false => assert!(matches!(
+                result,
+                Err(RecvError::LinkStateError(LinkStateError::RemoteDetached))
+            )),
+        }
+        remote.await.unwrap().unwrap();
+
+        session.end().await.unwrap();
+        connection.close().await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn local_detach_is_not_notified_as_remote() {
+    let (addr, endpoint_rx) = spawn_single_link_listener("local-detach-listener").await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("local-detach-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let sender = Sender::attach(&mut session, "local-detach-sender", "q1")
+        .await
+        .unwrap();
+    let mut receiver = expect_receiver(endpoint_rx.await.unwrap());
+
+    let detached = sender.on_detach_by_remote();
+    let (sender_closed, result) = tokio::join!(sender.close(), receiver.recv::<String>());
+    sender_closed.unwrap();
+    assert!(matches!(
+        result,
+        Err(RecvError::LinkStateError(LinkStateError::RemoteClosed))
+    ));
+    assert!(detached.await.is_none());
+
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
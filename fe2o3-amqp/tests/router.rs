@@ -0,0 +1,263 @@
+//! Tests of routing deliveries to handlers
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use fe2o3_amqp::{
+    link::{DetachError, SenderAttachError},
+    types::{
+        definitions::{self, AmqpError},
+        messaging::{Modified, Rejected},
+    },
+    Connection, Receiver, SendReceipt, Sender, Session,
+};
+
+use common::serve_session;
+
+/// Serves the router on the first session of the first connection. Sending on the returned
+/// sender shuts the router down, and the returned receiver resolves once the router returns
+async fn spawn_router(
+    router: fe2o3_amqp::acceptor::Router,
+) -> (
+    SocketAddr,
+    tokio::sync::oneshot::Sender<()>,
+    tokio::sync::oneshot::Receiver<()>,
+) {
+    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+    let (served_tx, served_rx) = tokio::sync::oneshot::channel();
+    let (addr, _) = serve_session("test-listener", move |mut session| async move {
+        router
+            .serve_with_shutdown(&mut session, async {
+                let _ = shutdown_rx.await;
+            })
+            .await;
+        let _ = served_tx.send(());
+        let _ = session.on_end().await;
+    })
+    .await;
+    (addr, shutdown_tx, served_rx)
+}
+
+#[tokio::test]
+async fn router_settles_deliveries_with_the_outcome_of_the_matching_route() {
+    use fe2o3_amqp::{
+        acceptor::{AddressPattern, HandlerError, Route, Router},
+        link::{delivery::Delivery, ReceiverAttachError},
+    };
+
+    let (handled_tx, mut handled_rx) = tokio::sync::mpsc::unbounded_channel();
+    let router = Router::new()
+        .route(Route::new(
+            "orders/*/events",
+            move |delivery: Delivery<String>| {
+                let handled_tx = handled_tx.clone();
+                async move {
+                    match delivery.body().as_str() {
+                        "bad" => {
+                            Err(definitions::Error::new(AmqpError::DecodeError, None, None).into())
+                        }
+                        body => {
+                            handled_tx.send(body.to_string()).unwrap();
+                            Ok(())
+                        }
+                    }
+                }
+            },
+        ))
+        .route(Route::new("audit/#", |_: Delivery<String>| async {
+            Err(HandlerError::Release)
+        }))
+        .route(Route::new(
+            AddressPattern::prefix("orders/"),
+            |_: Delivery<String>| async {
+                Err(HandlerError::Modify(Modified {
+                    delivery_failed: Some(true),
+                    undeliverable_here: None,
+                    message_annotations: None,
+                }))
+            },
+        ));
+    let (addr, _shutdown, _served) = spawn_router(router).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("router-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+
+    // The first matching route handles the link even though the prefix route matches too
+    let mut sender = Sender::attach(&mut session, "orders-sender", "orders/1/events")
+        .await
+        .unwrap();
+    assert!(sender.send("good").await.unwrap().is_accepted());
+    assert_eq!(handled_rx.recv().await.unwrap(), "good");
+    match sender.send("bad").await.unwrap() {
+        SendReceipt::Rejected(Rejected { error: Some(error) }) => {
+            assert_eq!(error.condition, AmqpError::DecodeError.into())
+        }
+        receipt => panic!("Expecting Rejected, found {:?}", receipt),
+    }
+    sender.close().await.unwrap();
+
+    let mut sender = Sender::attach(&mut session, "audit-sender", "audit/a/b")
+        .await
+        .unwrap();
+    assert!(matches!(
+        sender.send("audit").await.unwrap(),
+        SendReceipt::Released(_)
+    ));
+    sender.close().await.unwrap();
+
+    let mut sender = Sender::attach(&mut session, "other-orders-sender", "orders/1")
+        .await
+        .unwrap();
+    match sender.send("other").await.unwrap() {
+        SendReceipt::Modified(modified) => assert_eq!(modified.delivery_failed, Some(true)),
+        receipt => panic!("Expecting Modified, found {:?}", receipt),
+    }
+    sender.close().await.unwrap();
+
+    // Unmatched addresses and remote receivers are refused
+    let error = Sender::attach(&mut session, "invoices-sender", "invoices/1")
+        .await
+        .unwrap_err();
+    assert!(matches!(error, SenderAttachError::RemoteDetached(_)));
+    assert_eq!(
+        error.remote_error().unwrap().condition,
+        AmqpError::NotFound.into()
+    );
+    let error = Receiver::attach(&mut session, "orders-receiver", "orders/1/events")
+        .await
+        .unwrap_err();
+    assert!(matches!(error, ReceiverAttachError::RemoteDetached(_)));
+    assert_eq!(
+        error.remote_error().unwrap().condition,
+        AmqpError::NotFound.into()
+    );
+
+    // The session is still usable after the refusals
+    let mut sender = Sender::attach(&mut session, "orders-sender-2", "orders/2/events")
+        .await
+        .unwrap();
+    assert!(sender.send("still good").await.unwrap().is_accepted());
+    sender.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn router_limits_concurrent_handlers_of_a_route() {
+    use fe2o3_amqp::{
+        acceptor::{Route, Router},
+        link::delivery::Delivery,
+    };
+
+    let running = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+    let (running_, peak_) = (running.clone(), peak.clone());
+    let router = Router::new().route(
+        Route::new("work", move |_: Delivery<String>| {
+            let (running, peak) = (running_.clone(), peak_.clone());
+            async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                // The handlers run on the runtime of the library
+                #[cfg(not(feature = "rt-async-std"))]
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                #[cfg(feature = "rt-async-std")]
+                async_std::task::sleep(std::time::Duration::from_millis(20)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                Ok(())
+            }
+        })
+        .max_concurrency(2),
+    );
+    let (addr, _shutdown, _served) = spawn_router(router).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("router-limit-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+
+    // The limit is shared by the links of the route
+    let mut outcomes = Vec::new();
+    let mut senders = Vec::new();
+    for i in 0..2 {
+        let mut sender = Sender::attach(&mut session, format!("work-sender-{}", i), "work")
+            .await
+            .unwrap();
+        for _ in 0..5 {
+            outcomes.push(sender.send_batchable("work").await.unwrap());
+        }
+        senders.push(sender);
+    }
+    for outcome in outcomes {
+        assert!(outcome.await.unwrap().is_accepted());
+    }
+    assert_eq!(peak.load(Ordering::SeqCst), 2);
+
+    for sender in senders {
+        sender.close().await.unwrap();
+    }
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn router_shutdown_waits_for_handlers_in_flight() {
+    use fe2o3_amqp::{
+        acceptor::{Route, Router},
+        link::delivery::Delivery,
+    };
+    use tokio::sync::{mpsc, Semaphore};
+
+    let (started_tx, mut started_rx) = mpsc::unbounded_channel();
+    let proceed = Arc::new(Semaphore::new(0));
+    let proceed_ = proceed.clone();
+    let router = Router::new().route(Route::new("jobs", move |_: Delivery<String>| {
+        let (started_tx, proceed) = (started_tx.clone(), proceed_.clone());
+        async move {
+            started_tx.send(()).unwrap();
+            proceed.acquire().await.unwrap().forget();
+            Ok(())
+        }
+    }));
+    let (addr, shutdown, mut served) = spawn_router(router).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("router-shutdown-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::attach(&mut session, "jobs-sender", "jobs")
+        .await
+        .unwrap();
+    let outcome = sender.send_batchable("job").await.unwrap();
+    started_rx.recv().await.unwrap();
+
+    // The router does not return while the handler is in flight
+    shutdown.send(()).unwrap();
+    let pending = tokio::time::timeout(std::time::Duration::from_millis(100), &mut served).await;
+    assert!(pending.is_err());
+
+    proceed.add_permits(1);
+    assert!(outcome.await.unwrap().is_accepted());
+
+    // The routed link is closed once its handlers are finished, and the router returns once the
+    // Detach is answered
+    assert!(matches!(
+        sender.on_detach_by_remote().await,
+        Some(DetachError::ClosedByRemote)
+    ));
+    let _ = sender.close().await;
+    served.await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
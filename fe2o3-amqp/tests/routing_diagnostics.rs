@@ -0,0 +1,143 @@
+//! Tests of the end conditions of frames routed to unknown handles
+
+#![cfg(all(feature = "acceptor", feature = "testing", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{types::definitions, Connection, Sender, Session};
+
+use common::spawn_listener;
+
+/// Waits for the End frame that the remote session sends and returns its error condition
+async fn next_raw_end_condition(
+    session: &mut fe2o3_amqp::session::SessionHandle<()>,
+) -> Option<definitions::ErrorCondition> {
+    use fe2o3_amqp::session::SessionFrameBody;
+
+    loop {
+        match session.next_raw_incoming().await? {
+            SessionFrameBody::End(end) => break end.error.map(|error| error.condition),
+            _ => continue,
+        }
+    }
+}
+
+#[tokio::test]
+async fn stale_handle_ends_only_the_session_it_is_sent_on() {
+    use fe2o3_amqp::{
+        introspect::{RoutingDecision, RoutingRecord},
+        session::SessionFrameBody,
+        types::{definitions::SessionError, performatives::Flow},
+    };
+
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("stale-handle-connection", &url[..])
+        .await
+        .unwrap();
+    let mut other_session = Session::begin(&mut connection).await.unwrap();
+    let mut other_sender = Sender::attach(&mut other_session, "other-sender", "q1")
+        .await
+        .unwrap();
+
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    session.observe_raw_incoming().await.unwrap();
+    let sender = Sender::attach(&mut session, "stale-handle-sender", "q1")
+        .await
+        .unwrap();
+    let input_handle = match session.next_raw_incoming().await.unwrap() {
+        SessionFrameBody::Attach(attach) => attach.handle.0,
+        body => panic!("Expecting Attach, found {:?}", body),
+    };
+    let history = session.routing_history().await.answered().unwrap();
+    assert!(history.contains(&RoutingRecord {
+        performative: "attach",
+        handle: input_handle,
+        decision: RoutingDecision::Routed { output_handle: 0 },
+    }));
+
+    // No link is attached with handle 42 on the remote session
+    let flow = Flow {
+        next_incoming_id: Some(0),
+        incoming_window: 2048,
+        next_outgoing_id: 0,
+        outgoing_window: 2048,
+        handle: Some(42.into()),
+        delivery_count: Some(0),
+        link_credit: Some(0),
+        available: None,
+        drain: false,
+        echo: false,
+        properties: None,
+    };
+    session
+        .send_raw(SessionFrameBody::Flow(flow))
+        .await
+        .unwrap();
+    assert_eq!(
+        next_raw_end_condition(&mut session).await,
+        Some(definitions::ErrorCondition::SessionError(
+            SessionError::UnattachedHandle
+        ))
+    );
+    drop(sender);
+    let _ = session.end().await;
+
+    // The other session on the same connection keeps working
+    let receipt = other_sender.send("accept").await.unwrap();
+    assert!(receipt.is_accepted());
+    other_sender.close().await.unwrap();
+    other_session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn attach_with_handle_in_use_ends_the_session() {
+    use fe2o3_amqp::{
+        session::SessionFrameBody,
+        types::definitions::{Role, SessionError},
+    };
+
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("handle-in-use-connection", &url[..])
+        .await
+        .unwrap();
+    let mut other_session = Session::begin(&mut connection).await.unwrap();
+
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    session.observe_raw_incoming().await.unwrap();
+    let sender = Sender::attach(&mut session, "attached-sender", "q1")
+        .await
+        .unwrap();
+    let mut attach = match session.next_raw_incoming().await.unwrap() {
+        SessionFrameBody::Attach(attach) => attach,
+        body => panic!("Expecting Attach, found {:?}", body),
+    };
+
+    // The handle 0 is still used by the attached sender on the remote session
+    attach.name = String::from("colliding-sender");
+    attach.handle = 0.into();
+    attach.role = Role::Sender;
+    session
+        .send_raw(SessionFrameBody::Attach(attach))
+        .await
+        .unwrap();
+    assert_eq!(
+        next_raw_end_condition(&mut session).await,
+        Some(definitions::ErrorCondition::SessionError(
+            SessionError::HandleInUse
+        ))
+    );
+    drop(sender);
+    let _ = session.end().await;
+
+    let mut other_sender = Sender::attach(&mut other_session, "other-sender", "q1")
+        .await
+        .unwrap();
+    let receipt = other_sender.send("accept").await.unwrap();
+    assert!(receipt.is_accepted());
+    other_sender.close().await.unwrap();
+    other_session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
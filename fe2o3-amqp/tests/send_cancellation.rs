@@ -0,0 +1,141 @@
+//! Tests of cancelling sends
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use std::collections::BTreeMap;
+
+use fe2o3_amqp::{
+    link::SendError, types::definitions::DeliveryTag, Connection, SendReceipt, Sender, Session,
+};
+
+use common::{expect_receiver, spawn_single_link_listener};
+
+#[tokio::test]
+async fn cancelled_send_waiting_for_credit_consumes_nothing() {
+    use std::time::Duration;
+
+    use tokio_util::sync::CancellationToken;
+
+    let (addr, endpoint_rx) = spawn_single_link_listener("cancel-credit-listener").await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("cancel-credit-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::attach(&mut session, "cancel-credit-sender", "q1")
+        .await
+        .unwrap();
+    let mut receiver = expect_receiver(endpoint_rx.await.unwrap());
+
+    // A token that is already cancelled sends nothing
+    let token = CancellationToken::new();
+    token.cancel();
+    let result = sender.send_cancellable(token, "never").await;
+    assert!(matches!(result, Err(SendError::Cancelled)));
+
+    // The listener withholds credit, so the cancelled send consumes none
+    let token = CancellationToken::new();
+    let cancel = token.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        cancel.cancel();
+    });
+    let result = sender.send_cancellable(token, "withheld").await;
+    assert!(matches!(result, Err(SendError::Cancelled)));
+    let snapshot = sender.flow_snapshot();
+    assert_eq!(snapshot.delivery_count, 0);
+    assert_eq!(snapshot.unsettled, 0);
+    assert!(sender.pending_outcomes().is_empty());
+
+    // The first delivery once credit is issued is the next message
+    receiver.set_credit(1).await.unwrap();
+    let receipt = tokio::spawn(async move {
+        let receipt = sender.send("hello").await.unwrap();
+        (sender, receipt)
+    });
+    let delivery = receiver.recv::<String>().await.unwrap();
+    assert_eq!(delivery.body(), "hello");
+    assert_eq!(delivery.delivery_tag(), &DeliveryTag::from([0u8; 4]));
+    receiver.accept(&delivery).await.unwrap();
+    let (sender, receipt) = receipt.await.unwrap();
+    assert!(receipt.is_accepted());
+
+    let (sender_closed, receiver_closed) = tokio::join!(sender.close(), receiver.close());
+    sender_closed.unwrap();
+    receiver_closed.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn cancelled_send_after_transfer_keeps_outcome_pending() {
+    use tokio_util::sync::CancellationToken;
+
+    let (addr, endpoint_rx) = spawn_single_link_listener("cancel-outcome-listener").await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("cancel-outcome-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::attach(&mut session, "cancel-outcome-sender", "q1")
+        .await
+        .unwrap();
+    let mut receiver = expect_receiver(endpoint_rx.await.unwrap());
+    receiver.set_credit(2).await.unwrap();
+
+    // The send is cancelled once the delivery has arrived but before it is accepted
+    let token = CancellationToken::new();
+    let cancel = token.clone();
+    let (result, delivery) = tokio::join!(sender.send_cancellable(token, "first"), async {
+        let delivery = receiver.recv::<String>().await.unwrap();
+        cancel.cancel();
+        delivery
+    });
+    assert!(matches!(result, Err(SendError::Cancelled)));
+    assert_eq!(sender.flow_snapshot().unsettled, 1);
+    let pending = sender.pending_outcomes();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(&pending[0].delivery_tag, delivery.delivery_tag());
+    assert!(pending[0].outcome.is_none());
+
+    // Dropping `send` after the transfer behaves the same
+    let (_, second) = tokio::join!(
+        async {
+            tokio::select! {
+                _ = sender.send("second") => panic!("The outcome is never sent"),
+                _ = tokio::time::sleep(std::time::Duration::from_millis(200)) => {}
+            }
+        },
+        receiver.recv::<String>()
+    );
+    let second = second.unwrap();
+    assert_eq!(sender.pending_outcomes().len(), 2);
+
+    // The outcomes are reported once they arrive
+    receiver.accept(&delivery).await.unwrap();
+    receiver.reject(&second, None).await.unwrap();
+    let mut outcomes = BTreeMap::new();
+    while outcomes.len() < 2 {
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        for pending in sender.pending_outcomes() {
+            if let Some(outcome) = pending.outcome {
+                outcomes.insert(pending.delivery_tag, outcome.unwrap());
+            }
+        }
+    }
+    assert!(outcomes[delivery.delivery_tag()].is_accepted());
+    assert!(matches!(
+        outcomes[second.delivery_tag()],
+        SendReceipt::Rejected(_)
+    ));
+    assert!(sender.pending_outcomes().is_empty());
+    assert_eq!(sender.flow_snapshot().unsettled, 0);
+
+    let (sender_closed, receiver_closed) = tokio::join!(sender.close(), receiver.close());
+    sender_closed.unwrap();
+    receiver_closed.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
@@ -0,0 +1,84 @@
+//! Tests of the order in which enqueued messages are transferred
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use std::{net::SocketAddr, sync::Arc};
+
+use fe2o3_amqp::{acceptor::LinkAcceptor, Connection, Sender, Session};
+
+use common::{expect_receiver, serve_session};
+
+/// Accepts a single receiver link and forwards the body of each delivery in the order they are
+/// received
+async fn spawn_order_recorder() -> (SocketAddr, tokio::sync::mpsc::UnboundedReceiver<u64>) {
+    let (body_tx, body_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (addr, _) = serve_session("order-recorder", move |mut session| async move {
+        let mut receiver = expect_receiver(LinkAcceptor::new().accept(&mut session).await.unwrap());
+        while let Ok(delivery) = receiver.recv::<u64>().await {
+            let _ = body_tx.send(*delivery.body());
+            receiver.accept(&delivery).await.unwrap();
+        }
+        let _ = receiver.close().await;
+        let _ = session.on_end().await;
+    })
+    .await;
+
+    (addr, body_rx)
+}
+
+#[tokio::test]
+async fn enqueued_messages_are_transferred_in_reservation_order() {
+    const TASKS: u64 = 8;
+    const MESSAGES: u64 = 10_000;
+
+    let (addr, mut bodies) = spawn_order_recorder().await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("enqueue-order-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let sender = Sender::attach(&mut session, "enqueue-order-sender", "q1")
+        .await
+        .unwrap();
+    let (owner, handle) = sender.split();
+
+    // The sequence number is taken and the message is queued under the same lock, so the
+    // sequence numbers are the order the messages are reserved in
+    let next_seq = Arc::new(tokio::sync::Mutex::new(0u64));
+    let tasks: Vec<_> = (0..TASKS)
+        .map(|_| {
+            let handle = handle.clone();
+            let next_seq = next_seq.clone();
+            tokio::spawn(async move {
+                let mut outcomes = Vec::new();
+                loop {
+                    let mut seq = next_seq.lock().await;
+                    if *seq == MESSAGES {
+                        break;
+                    }
+                    let queued = handle.enqueue(*seq).await.unwrap();
+                    *seq += 1;
+                    drop(seq);
+                    // The outcome is awaited on another task
+                    outcomes.push(tokio::spawn(queued));
+                }
+                for outcome in outcomes {
+                    assert!(outcome.await.unwrap().unwrap().is_accepted());
+                }
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    for expected in 0..MESSAGES {
+        assert_eq!(bodies.recv().await, Some(expected));
+    }
+
+    owner.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
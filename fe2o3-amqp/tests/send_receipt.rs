@@ -0,0 +1,164 @@
+//! Tests of the receipts of sent messages
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{
+    link::SendError,
+    types::{
+        definitions::{AmqpError, SenderSettleMode},
+        primitives::{Symbol, Value},
+    },
+    Connection, SendReceipt, Sendable, Sender, Session,
+};
+
+use common::spawn_listener;
+
+#[tokio::test]
+async fn send_receipt_for_each_outcome() {
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("send-receipt-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::attach(&mut session, "send-receipt-sender", "q1")
+        .await
+        .unwrap();
+
+    let receipt = sender.send("accept").await.unwrap();
+    assert!(matches!(receipt, SendReceipt::Accepted(_)));
+
+    let receipt = sender.send("reject").await.unwrap();
+    match receipt {
+        SendReceipt::Rejected(rejected) => {
+            let error = rejected.error.unwrap();
+            assert_eq!(error.condition, AmqpError::NotAllowed.into());
+        }
+        _ => panic!("Expecting Rejected, found {:?}", receipt),
+    }
+
+    let receipt = sender.send("release").await.unwrap();
+    assert!(matches!(receipt, SendReceipt::Released(_)));
+
+    let receipt = sender.send("modify").await.unwrap();
+    match receipt {
+        SendReceipt::Modified(modified) => assert_eq!(modified.delivery_failed, Some(true)),
+        _ => panic!("Expecting Modified, found {:?}", receipt),
+    }
+
+    let sendable = Sendable::builder().message("accept").settled(true).build();
+    let receipt = sender.send(sendable).await.unwrap();
+    assert!(receipt.is_settled());
+
+    sender.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn rejected_and_modified_outcomes_keep_error_info_and_annotations() {
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("outcome-info-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::attach(&mut session, "outcome-info-sender", "q1")
+        .await
+        .unwrap();
+
+    let receipt = sender.send("reject-with-info").await.unwrap();
+    match &receipt {
+        SendReceipt::Rejected(rejected) => {
+            let error = rejected.error.as_ref().unwrap();
+            assert_eq!(error.condition, AmqpError::ResourceLimitExceeded.into());
+            assert_eq!(error.description.as_deref(), Some("quota exceeded"));
+            let info = error.info.as_ref().unwrap();
+            assert_eq!(
+                info.get(&Symbol::from("reason")),
+                Some(&Value::from("QuotaExceeded"))
+            );
+        }
+        _ => panic!("Expecting Rejected, found {:?}", receipt),
+    }
+    assert_eq!(
+        receipt.to_string(),
+        r#"Rejected: amqp:resource-limit-exceeded: quota exceeded {reason: String("QuotaExceeded")}"#
+    );
+
+    let receipt = sender.send("modify-with-annotations").await.unwrap();
+    match &receipt {
+        SendReceipt::Modified(modified) => {
+            let annotations = modified.message_annotations.as_ref().unwrap();
+            assert_eq!(
+                annotations.get(&Symbol::from("x-opt-reason")),
+                Some(&Value::from("Busy"))
+            );
+        }
+        _ => panic!("Expecting Modified, found {:?}", receipt),
+    }
+    assert_eq!(
+        receipt.to_string(),
+        r#"Modified (delivery-failed: true, undeliverable-here: false, message-annotations: {x-opt-reason: String("Busy")})"#
+    );
+
+    sender.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn send_receipt_with_settled_sender_settle_mode() {
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("send-receipt-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::builder()
+        .name("settled-sender")
+        .target("q1")
+        .sender_settle_mode(SenderSettleMode::Settled)
+        .attach(&mut session)
+        .await
+        .unwrap();
+
+    let receipt = sender.send("reject").await.unwrap();
+    assert!(receipt.is_settled());
+
+    sender.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn send_receipt_rejected_as_error() {
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("send-receipt-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::builder()
+        .name("rejected-as-error-sender")
+        .target("q1")
+        .rejected_as_error(true)
+        .attach(&mut session)
+        .await
+        .unwrap();
+
+    let result = sender.send("reject").await;
+    assert!(matches!(result, Err(SendError::Rejected(_))));
+
+    let fut = sender.send_batchable("reject").await.unwrap();
+    assert!(matches!(fut.await, Err(SendError::Rejected(_))));
+
+    let receipt = sender.send("release").await.unwrap();
+    assert!(matches!(receipt, SendReceipt::Released(_)));
+
+    sender.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
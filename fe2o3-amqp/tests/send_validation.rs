@@ -0,0 +1,185 @@
+//! Tests of validating messages before they are sent
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{link::SendError, Connection, Sender, Session};
+
+use common::spawn_listener;
+
+/// A message with the `tenant` application property set if `tenant` is true
+fn tenant_message(
+    body: &str,
+    tenant: bool,
+) -> fe2o3_amqp::types::messaging::Message<fe2o3_amqp::types::messaging::AmqpValue<String>> {
+    use fe2o3_amqp::types::messaging::{ApplicationProperties, Message};
+
+    let builder = Message::builder();
+    let builder = match tenant {
+        true => builder.application_properties(
+            ApplicationProperties::builder()
+                .insert("tenant", "acme")
+                .build(),
+        ),
+        false => builder,
+    };
+    builder.value(body.to_string()).build()
+}
+
+/// Attaches a sender that requires the `tenant` application property and a maximum encoded size
+/// of 128 bytes
+async fn attach_validated_sender(
+    session: &mut fe2o3_amqp::session::SessionHandle<()>,
+    name: &str,
+    policy: fe2o3_amqp::link::BatchValidationPolicy,
+) -> Sender {
+    use fe2o3_amqp::link::validation::{MaxEncodedSize, RequiredProperties};
+
+    Sender::builder()
+        .name(name)
+        .target("q1")
+        .send_validator(MaxEncodedSize::new(128))
+        .send_validator(RequiredProperties::new(["tenant"]))
+        .batch_validation_policy(policy)
+        .attach(session)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn send_validator_fails_before_credit_is_consumed() {
+    use fe2o3_amqp::link::{BatchValidationPolicy, ValidationError};
+
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("send-validator-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = attach_validated_sender(
+        &mut session,
+        "send-validator-sender",
+        BatchValidationPolicy::FailBatch,
+    )
+    .await;
+    let link_credit = sender.flow_snapshot().link_credit;
+
+    match sender.send(tenant_message("accept", false)).await {
+        Err(SendError::Validation(ValidationError::MissingProperty(key))) => {
+            assert_eq!(key, "tenant")
+        }
+        result => panic!("Expecting MissingProperty, found {:?}", result),
+    }
+    match sender.send(tenant_message(&"a".repeat(256), true)).await {
+        Err(SendError::Validation(ValidationError::TooLarge { max, .. })) => assert_eq!(max, 128),
+        result => panic!("Expecting TooLarge, found {:?}", result),
+    }
+    let snapshot = sender.flow_snapshot();
+    assert_eq!(snapshot.delivery_count, 0);
+    assert_eq!(snapshot.link_credit, link_credit);
+    assert_eq!(snapshot.unsettled, 0);
+
+    // The link is untouched and valid messages are still sent
+    let receipt = sender.send(tenant_message("accept", true)).await.unwrap();
+    assert!(receipt.is_accepted());
+    assert_eq!(sender.flow_snapshot().delivery_count, 1);
+
+    sender.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn send_batch_fails_whole_batch_on_invalid_message() {
+    use fe2o3_amqp::link::{BatchValidationPolicy, ValidationError};
+
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("fail-batch-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = attach_validated_sender(
+        &mut session,
+        "fail-batch-sender",
+        BatchValidationPolicy::FailBatch,
+    )
+    .await;
+
+    let batch = [
+        tenant_message("accept", true),
+        tenant_message("accept", false),
+        tenant_message("accept", true),
+    ];
+    match sender.send_batch(batch).await {
+        Err(SendError::Validation(ValidationError::MissingProperty(_))) => {}
+        result => panic!(
+            "Expecting MissingProperty, found {:?}",
+            result.map(|r| r.len())
+        ),
+    }
+    assert_eq!(sender.flow_snapshot().delivery_count, 0);
+
+    let batch = [
+        tenant_message("accept", true),
+        tenant_message("reject", true),
+    ];
+    let results = sender.send_batch(batch).await.unwrap();
+    assert_eq!(results.len(), 2);
+    let mut receipts = Vec::new();
+    for result in results {
+        receipts.push(result.unwrap().await.unwrap());
+    }
+    assert!(receipts[0].is_accepted());
+    assert!(receipts[1].is_rejected());
+    assert_eq!(sender.flow_snapshot().delivery_count, 2);
+
+    sender.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn send_batch_skips_invalid_messages() {
+    use fe2o3_amqp::link::{BatchValidationPolicy, ValidationError};
+
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("skip-invalid-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = attach_validated_sender(
+        &mut session,
+        "skip-invalid-sender",
+        BatchValidationPolicy::SkipInvalid,
+    )
+    .await;
+
+    let batch = [
+        tenant_message("accept", true),
+        tenant_message("accept", false),
+        tenant_message(&"a".repeat(256), true),
+        tenant_message("release", true),
+    ];
+    let mut results = sender.send_batch(batch).await.unwrap().into_iter();
+    let receipt = results.next().unwrap().unwrap().await.unwrap();
+    assert!(receipt.is_accepted());
+    assert!(matches!(
+        results.next().unwrap(),
+        Err(ValidationError::MissingProperty(_))
+    ));
+    assert!(matches!(
+        results.next().unwrap(),
+        Err(ValidationError::TooLarge { .. })
+    ));
+    let receipt = results.next().unwrap().unwrap().await.unwrap();
+    assert!(receipt.is_released());
+    assert!(results.next().is_none());
+    assert_eq!(sender.flow_snapshot().delivery_count, 2);
+
+    sender.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
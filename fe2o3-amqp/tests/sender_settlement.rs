@@ -0,0 +1,144 @@
+//! Tests of the settled field of the transfers of a sender
+
+#![cfg(all(feature = "acceptor", feature = "testing", not(target_arch = "wasm32")))]
+
+mod common;
+
+use std::net::SocketAddr;
+
+use fe2o3_amqp::{
+    acceptor::{ConnectionAcceptor, LinkAcceptor, SessionAcceptor},
+    types::definitions::SenderSettleMode,
+    Connection, Sendable, Sender, Session,
+};
+
+use common::{bind, expect_receiver, receiver_main};
+
+/// Accepts a single link and forwards the performatives of the incoming transfers until the
+/// session ends
+async fn spawn_transfer_observer(
+    max_frame_size: u32,
+) -> (
+    SocketAddr,
+    tokio::sync::mpsc::UnboundedReceiver<fe2o3_amqp::types::performatives::Transfer>,
+) {
+    use fe2o3_amqp::session::SessionFrameBody;
+
+    let (tcp_listener, addr) = bind().await;
+    let (transfer_tx, transfer_rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let acceptor = ConnectionAcceptor::builder()
+            .container_id("transfer-observer")
+            .max_frame_size(max_frame_size)
+            .build();
+        let (stream, _) = tcp_listener.accept().await.unwrap();
+        let mut connection = acceptor.accept(stream).await.unwrap();
+        let mut session = SessionAcceptor::new()
+            .accept(&mut connection)
+            .await
+            .unwrap();
+        session.observe_raw_incoming().await.unwrap();
+        let receiver = expect_receiver(LinkAcceptor::new().accept(&mut session).await.unwrap());
+        tokio::spawn(receiver_main(receiver));
+        while let Some(body) = session.next_raw_incoming().await {
+            match body {
+                SessionFrameBody::Transfer { performative, .. } => {
+                    let _ = transfer_tx.send(performative);
+                }
+                SessionFrameBody::End(_) => break,
+                _ => {}
+            }
+        }
+        let _ = session.on_end().await;
+        let _ = connection.on_close().await;
+    });
+
+    (addr, transfer_rx)
+}
+
+#[tokio::test]
+async fn transfer_settled_field_follows_sender_settle_mode() {
+    let cases = [
+        // (snd-settle-mode, pre-settled by the application, expected settled field)
+        (SenderSettleMode::Settled, false, true),
+        (SenderSettleMode::Unsettled, false, false),
+        (SenderSettleMode::Unsettled, true, false),
+        (SenderSettleMode::Mixed, false, false),
+        (SenderSettleMode::Mixed, true, true),
+    ];
+
+    for (mode, pre_settled, expected) in cases {
+        let (addr, mut transfers) = spawn_transfer_observer(u16::MAX as u32).await;
+        let url = format!("amqp://{}", addr);
+        let mut connection = Connection::open("settle-mode-connection", &url[..])
+            .await
+            .unwrap();
+        let mut session = Session::begin(&mut connection).await.unwrap();
+        let mut sender = Sender::builder()
+            .name("settle-mode-sender")
+            .target("q1")
+            .sender_settle_mode(mode.clone())
+            .attach(&mut session)
+            .await
+            .unwrap();
+
+        for i in 0..2u32 {
+            let sendable = Sendable::builder()
+                .message("accept")
+                .settled(pre_settled)
+                .build();
+            let receipt = sender.send(sendable).await.unwrap();
+            assert_eq!(receipt.is_settled(), expected, "{:?}", mode);
+
+            let transfer = transfers.recv().await.unwrap();
+            assert_eq!(transfer.settled, Some(expected), "{:?}", mode);
+            assert_eq!(transfer.delivery_id, Some(i));
+            assert!(transfer.delivery_tag.is_some());
+            assert!(!transfer.more);
+        }
+
+        sender.close().await.unwrap();
+        session.end().await.unwrap();
+        connection.close().await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn multi_frame_transfer_carries_settled_on_first_frame_only() {
+    use fe2o3_amqp::types::primitives::Binary;
+
+    let (addr, mut transfers) = spawn_transfer_observer(512).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("multi-frame-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::builder()
+        .name("multi-frame-sender")
+        .target("q1")
+        .sender_settle_mode(SenderSettleMode::Settled)
+        .attach(&mut session)
+        .await
+        .unwrap();
+
+    let receipt = sender.send(Binary::from(vec![7u8; 4096])).await.unwrap();
+    assert!(receipt.is_settled());
+
+    let first = transfers.recv().await.unwrap();
+    assert_eq!(first.settled, Some(true));
+    assert_eq!(first.delivery_id, Some(0));
+    assert!(first.delivery_tag.is_some());
+    assert!(first.more);
+    loop {
+        let transfer = transfers.recv().await.unwrap();
+        assert_eq!(transfer.settled, None);
+        assert_eq!(transfer.delivery_tag, None);
+        if !transfer.more {
+            break;
+        }
+    }
+
+    sender.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
@@ -0,0 +1,71 @@
+//! Tests of the source and outcomes of a sender
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{
+    acceptor::LinkAcceptor, link::SendError, types::primitives::Symbol, Connection, Sender, Session,
+};
+
+use common::{expect_receiver, receiver_main, serve_session};
+
+#[tokio::test]
+async fn sender_source_arrives_intact_and_undeclared_outcome_is_an_error() {
+    use fe2o3_amqp::types::messaging::{
+        Accepted, DeliveryState, Outcome, Released, Source, TerminusDurability,
+    };
+    use tokio::sync::oneshot;
+
+    let (source_tx, source_rx) = oneshot::channel();
+    let (addr, _) = serve_session("test-listener", move |mut session| async move {
+        let receiver = expect_receiver(LinkAcceptor::new().accept(&mut session).await.unwrap());
+        source_tx.send(receiver.source().clone()).unwrap();
+        receiver_main(receiver).await;
+        let _ = session.on_end().await;
+    })
+    .await;
+
+    let source = Source::builder()
+        .address("q1")
+        .durable(TerminusDurability::UnsettledState)
+        .default_outcome(Outcome::Released(Released {}))
+        .outcomes(vec![
+            Symbol::from("amqp:released:list"),
+            Symbol::from("amqp:rejected:list"),
+        ])
+        .capabilities(vec![Symbol::from("example:capability")])
+        .build();
+
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("source-outcomes-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::builder()
+        .name("source-outcomes-sender")
+        .source(source.clone())
+        .target("q1")
+        .attach(&mut session)
+        .await
+        .unwrap();
+    assert_eq!(source_rx.await.unwrap(), Some(source));
+
+    let receipt = sender.send("release").await.unwrap();
+    assert!(receipt.is_released());
+    let receipt = sender.send("reject").await.unwrap();
+    assert!(receipt.is_rejected());
+
+    // The listener accepts any other message, which is not declared by the source
+    let result = sender.send("accept").await;
+    assert!(matches!(
+        result,
+        Err(SendError::UndeclaredOutcome(DeliveryState::Accepted(
+            Accepted {}
+        )))
+    ));
+
+    sender.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
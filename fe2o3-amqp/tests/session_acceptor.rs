@@ -0,0 +1,112 @@
+//! Tests of the per session negotiation of the session acceptor
+
+#![cfg(all(feature = "acceptor", feature = "testing", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{
+    acceptor::{ConnectionAcceptor, SessionAcceptor},
+    types::{
+        definitions::{self, AmqpError, Fields},
+        primitives::{Symbol, Value},
+    },
+    Connection, SendReceipt, Sender, Session,
+};
+
+use common::{serve_connection, session_main};
+
+#[tokio::test]
+async fn session_acceptor_on_begin_negotiates_per_session() {
+    use fe2o3_amqp::{
+        session::{BeginError, SessionFrameBody},
+        types::performatives::Begin,
+    };
+
+    let acceptor = ConnectionAcceptor::new("on-begin-listener");
+    let (addr, _) = serve_connection(acceptor, |mut connection| async move {
+        let session_acceptor = SessionAcceptor::builder()
+            .incoming_window(1024)
+            .on_begin(|remote: &Begin, local| {
+                let tier = remote
+                    .properties
+                    .as_ref()
+                    .and_then(|properties| properties.get(&Symbol::from("client-tier")));
+                match tier {
+                    Some(Value::String(tier)) if tier == "untrusted" => {
+                        Ok(local.incoming_window(remote.incoming_window.min(16)))
+                    }
+                    Some(Value::String(tier)) if tier == "banned" => Err(definitions::Error::new(
+                        AmqpError::UnauthorizedAccess,
+                        Some("banned client".to_string()),
+                        None,
+                    )),
+                    _ => Ok(local),
+                }
+            })
+            .build();
+        loop {
+            match session_acceptor.accept(&mut connection).await {
+                Ok(session) => {
+                    tokio::spawn(session_main(session, false));
+                }
+                Err(BeginError::Rejected(_)) => continue,
+                Err(_) => break,
+            }
+        }
+        let _ = connection.on_close().await;
+    })
+    .await;
+
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("on-begin-connection", &url[..])
+        .await
+        .unwrap();
+
+    let mut windows = Vec::new();
+    for (tier, name) in [
+        ("trusted", "trusted-sender"),
+        ("untrusted", "untrusted-sender"),
+    ] {
+        let mut properties = Fields::new();
+        properties.insert(Symbol::from("client-tier"), Value::from(tier));
+        let mut session = Session::builder()
+            .properties(properties)
+            .begin(&mut connection)
+            .await
+            .unwrap();
+        session.observe_raw_incoming().await.unwrap();
+
+        // The credit issued by the listener carries the incoming-window of its session
+        let mut sender = Sender::attach(&mut session, name, "q1").await.unwrap();
+        let flow = loop {
+            match session.next_raw_incoming().await.unwrap() {
+                SessionFrameBody::Flow(flow) => break flow,
+                _ => continue,
+            }
+        };
+        windows.push(flow.incoming_window);
+
+        let receipt = sender.send("hello").await.unwrap();
+        assert!(matches!(receipt, SendReceipt::Accepted(_)));
+        sender.close().await.unwrap();
+        session.end().await.unwrap();
+    }
+    assert_eq!(windows, vec![1024, 16]);
+
+    // A refused session is begun and then ended with the error
+    let mut properties = Fields::new();
+    properties.insert(Symbol::from("client-tier"), Value::from("banned"));
+    let mut session = Session::builder()
+        .properties(properties)
+        .begin(&mut connection)
+        .await
+        .unwrap();
+    match session.on_end().await {
+        Err(fe2o3_amqp::session::Error::RemoteEndedWithError(error)) => {
+            assert_eq!(error.condition, AmqpError::UnauthorizedAccess.into());
+        }
+        result => panic!("Expecting RemoteEndedWithError, found {:?}", result),
+    }
+
+    connection.close().await.unwrap();
+}
@@ -0,0 +1,126 @@
+//! Tests of the allocation of session channels
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{acceptor::ConnectionAcceptor, Connection, Sender, Session};
+
+use common::{bind, connection_main, spawn_listener};
+
+#[tokio::test]
+async fn session_channels_are_reused_after_end() {
+    let (tcp_listener, addr) = bind().await;
+    tokio::spawn(async move {
+        let acceptor = ConnectionAcceptor::builder()
+            .container_id("test-listener")
+            .channel_max(4)
+            .build();
+        let (stream, _) = tcp_listener.accept().await.unwrap();
+        let connection = acceptor.accept(stream).await.unwrap();
+        connection_main(connection, false).await;
+    });
+
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::builder()
+        .container_id("test-connection")
+        .channel_max(4)
+        .open(&url[..])
+        .await
+        .unwrap();
+
+    // Keeps channel 0 in use while the other channels are recycled
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    assert_eq!(connection.active_session_count(), 1);
+
+    for i in 0..10_000 {
+        let mut ephemeral = Session::begin(&mut connection)
+            .await
+            .unwrap_or_else(|error| panic!("begin {}: {:?}", i, error));
+        let sender = Sender::attach(&mut ephemeral, format!("sender-{}", i), "q1")
+            .await
+            .unwrap();
+        drop(sender);
+        ephemeral
+            .end()
+            .await
+            .unwrap_or_else(|error| panic!("end {}: {:?}", i, error));
+    }
+    // The connection releases the channel of the last session after `end()` returns
+    tokio::time::timeout(std::time::Duration::from_secs(1), async {
+        while connection.active_session_count() > 1 {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(connection.active_session_count(), 1);
+
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn max_sessions_limits_active_sessions() {
+    let (tcp_listener, addr) = bind().await;
+    tokio::spawn(async move {
+        let acceptor = ConnectionAcceptor::builder()
+            .container_id("test-listener")
+            .build();
+        let (stream, _) = tcp_listener.accept().await.unwrap();
+        let connection = acceptor.accept(stream).await.unwrap();
+        connection_main(connection, false).await;
+    });
+
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::builder()
+        .container_id("test-connection")
+        .max_sessions(2)
+        .open(&url[..])
+        .await
+        .unwrap();
+
+    let mut session0 = Session::begin(&mut connection).await.unwrap();
+    let mut session1 = Session::begin(&mut connection).await.unwrap();
+    assert_eq!(connection.active_session_count(), 2);
+    let result = Session::begin(&mut connection).await;
+    assert!(matches!(
+        result,
+        Err(fe2o3_amqp::session::BeginError::LocalMaxSessionsReached)
+    ));
+
+    session0.end().await.unwrap();
+    let mut session2 = Session::begin(&mut connection).await.unwrap();
+    assert_eq!(connection.active_session_count(), 2);
+
+    session1.end().await.unwrap();
+    session2.end().await.unwrap();
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn detach_reply_to_dropped_link_does_not_end_session() {
+    use std::time::Duration;
+
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("dropped-link-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let sender = Sender::attach(&mut session, "dropped-sender", "q1")
+        .await
+        .unwrap();
+
+    // Dropping the sender closes the link, and the reply arrives after the link is gone
+    drop(sender);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let mut sender = Sender::attach(&mut session, "dropped-sender", "q1")
+        .await
+        .unwrap();
+    assert!(sender.send("hello").await.unwrap().is_accepted());
+    sender.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
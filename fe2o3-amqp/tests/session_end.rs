@@ -0,0 +1,91 @@
+//! Tests of ending a session with an error
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{
+    acceptor::LinkAcceptor,
+    link::{LinkStateError, RecvError},
+    types::{
+        definitions::{self, AmqpError},
+        primitives::Value,
+    },
+    Connection, Receiver, Sender, Session,
+};
+
+use common::{expect_receiver, expect_sender, serve_session};
+
+#[tokio::test]
+async fn listener_observes_client_end_error_and_links_fail() {
+    use tokio::sync::oneshot;
+
+    let (result_tx, result_rx) = oneshot::channel();
+    let (addr, _) = serve_session("client-end-error-listener", move |mut session| async move {
+        let mut receiver = expect_receiver(LinkAcceptor::new().accept(&mut session).await.unwrap());
+        let recv = receiver.recv::<Value>().await.map(|_| ());
+        let end = session.on_end().await;
+        result_tx.send((recv, end)).unwrap();
+    })
+    .await;
+
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("client-end-error-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let _sender = Sender::attach(&mut session, "client-end-error-sender", "q1")
+        .await
+        .unwrap();
+
+    let error = definitions::Error::new(AmqpError::ResourceLimitExceeded, None, None);
+    session.end_with_error(error.clone()).await.unwrap();
+
+    let (recv, end) = result_rx.await.unwrap();
+    match recv {
+        Err(RecvError::LinkStateError(LinkStateError::SessionEnded(Some(e)))) => {
+            assert_eq!(e, error)
+        }
+        other => panic!("Expecting SessionEnded, found {:?}", other),
+    }
+    match end {
+        Err(fe2o3_amqp::session::Error::RemoteEndedWithError(e)) => assert_eq!(e, error),
+        other => panic!("Expecting RemoteEndedWithError, found {:?}", other),
+    }
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn client_observes_listener_end_error_and_links_fail() {
+    let error = definitions::Error::new(AmqpError::UnauthorizedAccess, None, None);
+    let listener_error = error.clone();
+    let (addr, _) = serve_session(
+        "listener-end-error-listener",
+        move |mut session| async move {
+            let _sender = expect_sender(LinkAcceptor::new().accept(&mut session).await.unwrap());
+            session.end_with_error(listener_error).await.unwrap();
+        },
+    )
+    .await;
+
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("listener-end-error-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut receiver = Receiver::attach(&mut session, "listener-end-error-receiver", "q1")
+        .await
+        .unwrap();
+
+    match receiver.recv::<Value>().await {
+        Err(RecvError::LinkStateError(LinkStateError::SessionEnded(Some(e)))) => {
+            assert_eq!(e, error)
+        }
+        other => panic!("Expecting SessionEnded, found {:?}", other.map(|_| ())),
+    }
+    match session.on_end().await {
+        Err(fe2o3_amqp::session::Error::RemoteEndedWithError(e)) => assert_eq!(e, error),
+        other => panic!("Expecting RemoteEndedWithError, found {:?}", other),
+    }
+    connection.close().await.unwrap();
+}
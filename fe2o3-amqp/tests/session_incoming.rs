@@ -0,0 +1,99 @@
+//! Tests of the incoming buffer of a session
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{
+    link::{LinkStateError, RecvError},
+    Connection, Receiver, Sender, Session,
+};
+
+use common::{spawn_listener, FLOOD_COUNT};
+
+#[tokio::test]
+async fn incoming_bytes_are_bounded_by_session_buffer_limit() {
+    use std::time::Duration;
+
+    let limit = 32 * 1024;
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("incoming-buffer-limit-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::builder()
+        .incoming_buffer_limit(limit)
+        .begin(&mut connection)
+        .await
+        .unwrap();
+    let mut receiver = Receiver::attach(&mut session, "flood-receiver", "flood")
+        .await
+        .unwrap();
+
+    // The receiver is not polled, so the deliveries pile up until the limit is reached
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let buffered = session.buffered_incoming_bytes();
+    assert!(buffered > 0);
+    assert!(buffered <= limit);
+
+    for i in 0..FLOOD_COUNT {
+        let delivery = receiver.recv::<String>().await.unwrap();
+        assert!(delivery.body().starts_with(&format!("{:08}", i)));
+        receiver.accept(&delivery).await.unwrap();
+        assert!(session.buffered_incoming_bytes() <= limit);
+    }
+    match receiver.recv::<String>().await {
+        Err(RecvError::LinkStateError(LinkStateError::RemoteClosed)) => {}
+        other => panic!("Expecting RemoteClosed, found {:?}", other.map(|_| ())),
+    }
+
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn stalled_receiver_does_not_block_other_links() {
+    use std::time::Duration;
+
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("stalled-receiver-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut builder = Receiver::builder()
+        .name("stalled-receiver")
+        .source("flood")
+        .target("stalled-receiver");
+    builder.buffer_size = 4;
+    let mut receiver = builder.attach(&mut session).await.unwrap();
+
+    // The receiver is not polled while its deliveries keep arriving, which must not hold up the
+    // outcomes of the sender on the same session
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let mut sender = Sender::attach(&mut session, "active-sender", "q")
+        .await
+        .unwrap();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        for i in 0..3 {
+            sender.send(format!("message-{}", i)).await.unwrap();
+        }
+    })
+    .await
+    .expect("The sender is blocked by the stalled receiver");
+    sender.close().await.unwrap();
+
+    // The deliveries that did not fit into the channel are relayed in order
+    for i in 0..FLOOD_COUNT {
+        let delivery = receiver.recv::<String>().await.unwrap();
+        assert!(delivery.body().starts_with(&format!("{:08}", i)));
+        receiver.accept(&delivery).await.unwrap();
+    }
+    match receiver.recv::<String>().await {
+        Err(RecvError::LinkStateError(LinkStateError::RemoteClosed)) => {}
+        other => panic!("Expecting RemoteClosed, found {:?}", other.map(|_| ())),
+    }
+
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
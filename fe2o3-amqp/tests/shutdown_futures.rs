@@ -0,0 +1,110 @@
+//! Tests of the futures that resolve when the handles shut down
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{Connection, Receiver, Sender, Session};
+
+use common::spawn_listener;
+
+#[tokio::test]
+async fn shutdown_futures_resolve_from_other_tasks() {
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("shutdown-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+
+    let detached_sender = Sender::attach(&mut session, "detached-sender", "q1")
+        .await
+        .unwrap();
+    let closed_sender = Sender::attach(&mut session, "closed-sender", "q1")
+        .await
+        .unwrap();
+
+    let detached = tokio::spawn(detached_sender.detached());
+    let closed = tokio::spawn(closed_sender.closed());
+    let ended = tokio::spawn(session.ended());
+
+    let _detached = detached_sender.detach().await.unwrap();
+    detached.await.unwrap();
+
+    closed_sender.close().await.unwrap();
+    closed.await.unwrap();
+
+    assert!(!ended.is_finished());
+    session.end().await.unwrap();
+    ended.await.unwrap();
+
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn shutdown_futures_resolve_when_the_session_ends() {
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("session-end-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+
+    let sender = Sender::attach(&mut session, "session-end-sender", "q1")
+        .await
+        .unwrap();
+    let receiver = Receiver::attach(&mut session, "session-end-receiver", "q1")
+        .await
+        .unwrap();
+    let sender_detached = tokio::spawn(sender.detached());
+    let sender_closed = tokio::spawn(sender.closed());
+    let receiver_detached = tokio::spawn(receiver.detached());
+    let receiver_closed = tokio::spawn(receiver.closed());
+
+    // The links are not reading their frames, so they only find out through the session
+    session.end().await.unwrap();
+    let timeout = std::time::Duration::from_secs(1);
+    for handle in [
+        sender_detached,
+        sender_closed,
+        receiver_detached,
+        receiver_closed,
+    ] {
+        tokio::time::timeout(timeout, handle)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    drop(sender);
+    drop(receiver);
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn shutdown_futures_resolve_when_the_connection_is_lost() {
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("connection-lost-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+
+    let sender = Sender::attach(&mut session, "connection-lost-sender", "q1")
+        .await
+        .unwrap();
+    let detached = tokio::spawn(sender.detached());
+    let ended = tokio::spawn(session.ended());
+
+    // Closing the connection stops the session event loop without ending the session, which the
+    // connection reports as an error
+    assert!(connection.close().await.is_err());
+    let timeout = std::time::Duration::from_secs(1);
+    tokio::time::timeout(timeout, ended).await.unwrap().unwrap();
+    tokio::time::timeout(timeout, detached)
+        .await
+        .unwrap()
+        .unwrap();
+
+    drop(sender);
+}
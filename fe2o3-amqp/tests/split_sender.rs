@@ -0,0 +1,70 @@
+//! Tests of sending from a sender split across tasks
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{
+    link::{LinkStateError, SendError},
+    Connection, Sender, Session,
+};
+
+use common::spawn_listener;
+
+#[tokio::test]
+async fn split_sender_sends_concurrently_from_many_tasks() {
+    const TASKS: usize = 16;
+    const MESSAGES_PER_TASK: usize = 24;
+
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("split-sender-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let sender = Sender::attach(&mut session, "split-sender", "q1")
+        .await
+        .unwrap();
+    let (owner, handle) = sender.split();
+
+    let tasks: Vec<_> = (0..TASKS)
+        .map(|task| {
+            let handle = handle.clone();
+            tokio::spawn(async move {
+                for i in 0..MESSAGES_PER_TASK {
+                    // The listener disposes each message according to its body, so a receipt
+                    // attributed to the wrong message is detected by its outcome
+                    let receipt = match (task + i) % 3 {
+                        0 => handle.send("release").await.unwrap(),
+                        1 => handle.send("reject").await.unwrap(),
+                        _ => {
+                            let fut = handle
+                                .send_batchable(format!("accept-{}-{}", task, i))
+                                .await
+                                .unwrap();
+                            fut.await.unwrap()
+                        }
+                    };
+                    match (task + i) % 3 {
+                        0 => assert!(receipt.is_released()),
+                        1 => assert!(receipt.is_rejected()),
+                        _ => assert!(receipt.is_accepted()),
+                    }
+                }
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    owner.close().await.unwrap();
+    assert!(handle.is_closed());
+    assert!(matches!(
+        handle.send("after-close").await,
+        Err(SendError::LinkStateError(LinkStateError::IllegalState))
+    ));
+
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
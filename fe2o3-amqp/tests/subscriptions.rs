@@ -0,0 +1,196 @@
+//! Tests of subscription links
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use std::{net::SocketAddr, sync::Arc};
+
+use fe2o3_amqp::{
+    acceptor::LinkAcceptor,
+    link::{
+        subscription::{GLOBAL, SHARED, SHARED_SUBS, TOPIC},
+        DetachError, Subscription,
+    },
+    types::{
+        messaging::{TerminusDurability, TerminusExpiryPolicy},
+        performatives::Attach,
+        primitives::Symbol,
+    },
+    Connection, Session,
+};
+
+use common::{expect_sender, serve_session};
+
+/// What the subscription recorder did with a link that attached to or detached from a topic
+#[derive(Debug)]
+enum SubscriptionEvent {
+    Created(Attach),
+    Resumed(Attach),
+    Kept(String),
+    Deleted(String),
+}
+
+/// Spawns a listener that keeps the subscriptions by link name like a broker would. A durable
+/// subscription is kept when the link is detached and is deleted by a closing detach
+async fn spawn_subscription_recorder() -> (
+    SocketAddr,
+    tokio::sync::mpsc::UnboundedReceiver<SubscriptionEvent>,
+) {
+    let (event_tx, event_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (addr, _) = serve_session("subscription-recorder", move |mut session| async move {
+        let link_acceptor = LinkAcceptor::new();
+        let subscriptions = Arc::new(std::sync::Mutex::new(std::collections::HashSet::new()));
+        while let Some(attach) = session.next_incoming_attach().await {
+            let name = attach.name.clone();
+            let event = match subscriptions.lock().unwrap().insert(name.clone()) {
+                true => SubscriptionEvent::Created(attach.clone()),
+                false => SubscriptionEvent::Resumed(attach.clone()),
+            };
+            let _ = event_tx.send(event);
+
+            let mut sender = expect_sender(
+                link_acceptor
+                    .accept_incoming_attach(attach, &mut session)
+                    .await
+                    .unwrap(),
+            );
+            let event_tx = event_tx.clone();
+            let subscriptions = subscriptions.clone();
+            tokio::spawn(async move {
+                match sender.on_detach().await {
+                    DetachError::DetachedByRemote => {
+                        let _ = sender.detach().await;
+                        let _ = event_tx.send(SubscriptionEvent::Kept(name));
+                    }
+                    _ => {
+                        let _ = sender.close().await;
+                        subscriptions.lock().unwrap().remove(&name);
+                        let _ = event_tx.send(SubscriptionEvent::Deleted(name));
+                    }
+                }
+            });
+        }
+        let _ = session.on_end().await;
+    })
+    .await;
+
+    (addr, event_rx)
+}
+
+#[tokio::test]
+async fn durable_shared_subscription_is_resumed_until_unsubscribed() {
+    let (addr, mut events) = spawn_subscription_recorder().await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("subscription-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+
+    let subscription = Subscription::builder()
+        .topic("prices")
+        .shared(true)
+        .durable(true)
+        .subscription_name("worker-group")
+        .build()
+        .unwrap();
+
+    let receiver = subscription.attach(&mut session).await.unwrap();
+    let attach = match events.recv().await.unwrap() {
+        SubscriptionEvent::Created(attach) => attach,
+        event => panic!(
+            "Expecting the subscription to be created, found {:?}",
+            event
+        ),
+    };
+    assert_eq!(attach.name, "worker-group");
+    let source = attach.source.unwrap();
+    assert_eq!(source.address.as_deref(), Some("prices"));
+    assert_eq!(
+        source.capabilities.unwrap().0,
+        vec![Symbol::from(TOPIC), Symbol::from(SHARED)]
+    );
+    assert_eq!(source.durable, TerminusDurability::UnsettledState);
+    assert_eq!(source.expiry_policy, TerminusExpiryPolicy::Never);
+    assert!(attach
+        .desired_capabilities
+        .unwrap()
+        .0
+        .contains(&Symbol::from(SHARED_SUBS)));
+
+    receiver.detach().await.unwrap();
+    assert!(matches!(
+        events.recv().await.unwrap(),
+        SubscriptionEvent::Kept(name) if name == "worker-group"
+    ));
+
+    let receiver = subscription.attach(&mut session).await.unwrap();
+    assert!(matches!(
+        events.recv().await.unwrap(),
+        SubscriptionEvent::Resumed(attach) if attach.name == "worker-group"
+    ));
+    receiver.detach().await.unwrap();
+    assert!(matches!(
+        events.recv().await.unwrap(),
+        SubscriptionEvent::Kept(_)
+    ));
+
+    subscription.unsubscribe(&mut session).await.unwrap();
+    assert!(matches!(
+        events.recv().await.unwrap(),
+        SubscriptionEvent::Resumed(_)
+    ));
+    assert!(matches!(
+        events.recv().await.unwrap(),
+        SubscriptionEvent::Deleted(name) if name == "worker-group"
+    ));
+
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn global_volatile_subscription_link_name_and_capabilities() {
+    let (addr, mut events) = spawn_subscription_recorder().await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("subscription-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+
+    let subscription = Subscription::builder()
+        .topic("prices")
+        .global(true)
+        .subscription_name("dashboards")
+        .build()
+        .unwrap();
+    let receiver = subscription.attach(&mut session).await.unwrap();
+    let attach = match events.recv().await.unwrap() {
+        SubscriptionEvent::Created(attach) => attach,
+        event => panic!(
+            "Expecting the subscription to be created, found {:?}",
+            event
+        ),
+    };
+    assert_eq!(attach.name, "dashboards|global|volatile");
+    let source = attach.source.unwrap();
+    assert_eq!(
+        source.capabilities.unwrap().0,
+        vec![
+            Symbol::from(TOPIC),
+            Symbol::from(SHARED),
+            Symbol::from(GLOBAL)
+        ]
+    );
+    assert_eq!(source.durable, TerminusDurability::None);
+    assert_eq!(source.expiry_policy, TerminusExpiryPolicy::LinkDetach);
+
+    receiver.close().await.unwrap();
+    assert!(matches!(
+        events.recv().await.unwrap(),
+        SubscriptionEvent::Deleted(_)
+    ));
+
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
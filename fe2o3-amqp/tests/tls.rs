@@ -0,0 +1,217 @@
+//! Tests of TLS connections to the listener
+
+#![cfg(all(feature = "acceptor", feature = "rustls", not(target_arch = "wasm32")))]
+
+mod common;
+
+use std::{net::SocketAddr, sync::Arc};
+
+use fe2o3_amqp::{
+    acceptor::ConnectionAcceptor, types::primitives::Symbol, Connection, Sender, Session,
+};
+
+use common::{bind, connection_main};
+
+struct TestCertificates {
+    ca: tokio_rustls::rustls::pki_types::CertificateDer<'static>,
+    server: tokio_rustls::rustls::pki_types::CertificateDer<'static>,
+    server_key: tokio_rustls::rustls::pki_types::PrivateKeyDer<'static>,
+    client: tokio_rustls::rustls::pki_types::CertificateDer<'static>,
+    client_key: tokio_rustls::rustls::pki_types::PrivateKeyDer<'static>,
+}
+
+/// A self-signed CA that issues a certificate for "localhost" and a client certificate
+fn generate_certificates() -> TestCertificates {
+    use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+    use tokio_rustls::rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+
+    let key_der =
+        |key: &KeyPair| PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key.serialize_der()));
+
+    let ca_key = KeyPair::generate().unwrap();
+    let mut ca_params = CertificateParams::new(Vec::new()).unwrap();
+    ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    let ca = ca_params.self_signed(&ca_key).unwrap();
+
+    let server_key = KeyPair::generate().unwrap();
+    let server = CertificateParams::new(vec!["localhost".to_string()])
+        .unwrap()
+        .signed_by(&server_key, &ca, &ca_key)
+        .unwrap();
+
+    let client_key = KeyPair::generate().unwrap();
+    let client = CertificateParams::new(vec!["test-client".to_string()])
+        .unwrap()
+        .signed_by(&client_key, &ca, &ca_key)
+        .unwrap();
+
+    TestCertificates {
+        ca: ca.der().clone(),
+        server: server.der().clone(),
+        server_key: key_der(&server_key),
+        client: client.der().clone(),
+        client_key: key_der(&client_key),
+    }
+}
+
+/// Offers SASL EXTERNAL and records the mechanisms chosen by the clients
+#[derive(Debug, Clone, Default)]
+struct SaslExternalMechanism {
+    chosen: Arc<std::sync::Mutex<Vec<Symbol>>>,
+}
+
+impl fe2o3_amqp::acceptor::SaslAcceptor for SaslExternalMechanism {
+    fn mechanisms(&self) -> fe2o3_amqp::types::primitives::Array<Symbol> {
+        vec![Symbol::from("EXTERNAL"), Symbol::from("ANONYMOUS")].into()
+    }
+
+    fn on_init(
+        &mut self,
+        init: fe2o3_amqp::types::sasl::SaslInit,
+    ) -> fe2o3_amqp::acceptor::sasl_acceptor::SaslServerFrame {
+        self.chosen.lock().unwrap().push(init.mechanism);
+        fe2o3_amqp::acceptor::sasl_acceptor::SaslServerFrame::Outcome(
+            fe2o3_amqp::types::sasl::SaslOutcome {
+                code: fe2o3_amqp::types::sasl::SaslCode::Ok,
+                additional_data: None,
+            },
+        )
+    }
+
+    fn on_response(
+        &mut self,
+        _response: fe2o3_amqp::types::sasl::SaslResponse,
+    ) -> fe2o3_amqp::acceptor::sasl_acceptor::SaslServerFrame {
+        fe2o3_amqp::acceptor::sasl_acceptor::SaslServerFrame::Outcome(
+            fe2o3_amqp::types::sasl::SaslOutcome {
+                code: fe2o3_amqp::types::sasl::SaslCode::Sys,
+                additional_data: None,
+            },
+        )
+    }
+}
+
+/// Spawns a listener that accepts TLS connections with the server certificate, and requires a
+/// client certificate issued by the CA if `require_client_auth` is true
+async fn spawn_tls_listener(
+    certs: &TestCertificates,
+    require_client_auth: bool,
+    sasl: SaslExternalMechanism,
+) -> SocketAddr {
+    use tokio_rustls::rustls::{server::WebPkiClientVerifier, RootCertStore, ServerConfig};
+
+    let builder = match require_client_auth {
+        true => {
+            let mut roots = RootCertStore::empty();
+            roots.add(certs.ca.clone()).unwrap();
+            let verifier = WebPkiClientVerifier::builder(Arc::new(roots))
+                .build()
+                .unwrap();
+            ServerConfig::builder().with_client_cert_verifier(verifier)
+        }
+        false => ServerConfig::builder().with_no_client_auth(),
+    };
+    let config = builder
+        .with_single_cert(vec![certs.server.clone()], certs.server_key.clone_key())
+        .unwrap();
+    let tls_acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config));
+
+    let (tcp_listener, addr) = bind().await;
+    let connection_acceptor = ConnectionAcceptor::builder()
+        .container_id("test-tls-listener")
+        .tls_acceptor(tls_acceptor)
+        .sasl_acceptor(sasl)
+        .build();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = tcp_listener.accept().await {
+            if let Ok(connection) = connection_acceptor.accept(stream).await {
+                tokio::spawn(connection_main(connection, false));
+            }
+        }
+    });
+
+    addr
+}
+
+#[tokio::test]
+async fn tls_connection_trusts_added_root_certificate() {
+    let certs = generate_certificates();
+    let addr = spawn_tls_listener(&certs, false, SaslExternalMechanism::default()).await;
+    let url = format!("amqps://localhost:{}", addr.port());
+
+    // The self-signed CA is not among the webpki roots
+    let result = Connection::builder()
+        .container_id("tls-untrusted")
+        .sasl_profile(fe2o3_amqp::sasl_profile::SaslProfile::Anonymous)
+        .open(&url[..])
+        .await;
+    assert!(result.is_err());
+
+    let mut connection = Connection::builder()
+        .container_id("tls-trusted")
+        .sasl_profile(fe2o3_amqp::sasl_profile::SaslProfile::Anonymous)
+        .tls_add_root_certificate(certs.ca.clone())
+        .open(&url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::attach(&mut session, "tls-sender", "q1")
+        .await
+        .unwrap();
+    sender
+        .send("hello")
+        .await
+        .unwrap()
+        .accepted_or_else(|o| o)
+        .unwrap();
+    sender.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
+
+#[cfg(feature = "dangerous-tls")]
+#[tokio::test]
+async fn tls_connection_accepts_invalid_certificate_when_overridden() {
+    let certs = generate_certificates();
+    let addr = spawn_tls_listener(&certs, false, SaslExternalMechanism::default()).await;
+
+    // The certificate is issued for "localhost" only
+    let url = format!("amqps://127.0.0.1:{}", addr.port());
+    let mut connection = Connection::builder()
+        .container_id("tls-danger")
+        .domain("example.com")
+        .sasl_profile(fe2o3_amqp::sasl_profile::SaslProfile::Anonymous)
+        .tls_danger_accept_invalid_certs(true)
+        .open(&url[..])
+        .await
+        .unwrap();
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn tls_client_certificate_defaults_to_sasl_external() {
+    let certs = generate_certificates();
+    let sasl = SaslExternalMechanism::default();
+    let addr = spawn_tls_listener(&certs, true, sasl.clone()).await;
+    let url = format!("amqps://localhost:{}", addr.port());
+
+    // The server rejects the handshake without a client certificate
+    let result = Connection::builder()
+        .container_id("mtls-no-cert")
+        .sasl_profile(fe2o3_amqp::sasl_profile::SaslProfile::Anonymous)
+        .tls_add_root_certificate(certs.ca.clone())
+        .open(&url[..])
+        .await;
+    assert!(result.is_err());
+    assert!(sasl.chosen.lock().unwrap().is_empty());
+
+    let mut connection = Connection::builder()
+        .container_id("mtls")
+        .tls_add_root_certificate(certs.ca.clone())
+        .tls_client_auth_cert(vec![certs.client.clone()], certs.client_key.clone_key())
+        .open(&url[..])
+        .await
+        .unwrap();
+    connection.close().await.unwrap();
+    assert_eq!(*sasl.chosen.lock().unwrap(), vec![Symbol::from("EXTERNAL")]);
+}
@@ -0,0 +1,163 @@
+//! Tests of the tracing spans of the client, captured by a subscriber installed with
+//! `tracing::subscriber::with_default`
+
+// The subscriber only sees the event loops spawned on its own thread, which async-std does not do
+#![cfg(all(
+    feature = "acceptor",
+    feature = "tracing",
+    not(feature = "rt-async-std"),
+    not(target_arch = "wasm32")
+))]
+
+mod common;
+
+use std::{
+    collections::BTreeMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use fe2o3_amqp::{Connection, Sender, Session};
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    Event, Metadata, Subscriber,
+};
+
+use common::spawn_listener;
+
+#[derive(Debug)]
+struct CapturedSpan {
+    name: &'static str,
+    fields: BTreeMap<&'static str, String>,
+}
+
+struct FieldVisitor<'a>(&'a mut BTreeMap<&'static str, String>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.insert(field.name(), format!("{:?}", value));
+    }
+}
+
+/// Records every span with its fields. The id of a span is its position in `spans` plus one
+#[derive(Default)]
+struct SpanCapture {
+    spans: Arc<Mutex<Vec<CapturedSpan>>>,
+}
+
+impl Subscriber for SpanCapture {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+        let mut fields = BTreeMap::new();
+        attrs.record(&mut FieldVisitor(&mut fields));
+
+        let mut spans = self.spans.lock().unwrap();
+        spans.push(CapturedSpan {
+            name: attrs.metadata().name(),
+            fields,
+        });
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut spans = self.spans.lock().unwrap();
+        let span = &mut spans[span.into_u64() as usize - 1];
+        values.record(&mut FieldVisitor(&mut span.fields));
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+/// Runs the listener on its own thread with a separate subscriber
+///
+/// While only one subscriber exists, whether a callsite is enabled is decided by the subscriber
+/// of the thread that reaches it first, so the listener thread must not run without one.
+fn spawn_listener_thread() -> SocketAddr {
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let _guard = tracing::subscriber::set_default(SpanCapture::default());
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async move {
+            tx.send(spawn_listener(false).await).unwrap();
+            std::future::pending::<()>().await
+        })
+    });
+    rx.recv().unwrap()
+}
+
+#[test]
+fn spans_carry_container_id_and_link_name() {
+    let addr = spawn_listener_thread();
+    let subscriber = SpanCapture::default();
+    let spans = subscriber.spans.clone();
+
+    // The event loops are spawned on the same thread, where the subscriber is installed
+    tracing::subscriber::with_default(subscriber, || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let url = format!("amqp://{}", addr);
+            let mut connection = Connection::open("span-connection", &url[..]).await.unwrap();
+            let mut session = Session::begin(&mut connection).await.unwrap();
+            let detached_sender = Sender::attach(&mut session, "span-detached", "q1")
+                .await
+                .unwrap();
+            let closed_sender = Sender::attach(&mut session, "span-closed", "q1")
+                .await
+                .unwrap();
+            let _detached = detached_sender.detach().await.unwrap();
+            closed_sender.close().await.unwrap();
+            session.end().await.unwrap();
+            connection.close().await.unwrap();
+        });
+    });
+
+    let spans = spans.lock().unwrap();
+    let find = |name: &str, fields: &[(&str, &str)]| {
+        let found = spans.iter().any(|span| {
+            span.name == name
+                && fields
+                    .iter()
+                    .all(|(key, value)| span.fields.get(key).map(String::as_str) == Some(value))
+        });
+        assert!(found, "No span {} with {:?} in {:#?}", name, fields, spans);
+    };
+    let container_id = ("container_id", "span-connection");
+
+    find("Connection::event_loop", &[container_id]);
+    find("Session::begin", &[container_id]);
+    find(
+        "Session::event_loop",
+        &[container_id, ("outgoing_channel", "0")],
+    );
+    find("attach", &[container_id, ("link_name", "span-detached")]);
+    find("attach", &[container_id, ("link_name", "span-closed")]);
+    find(
+        "send_detach",
+        &[("link_name", "span-detached"), ("closed", "false")],
+    );
+    find(
+        "send_detach",
+        &[("link_name", "span-closed"), ("closed", "true")],
+    );
+    find("Session::end", &[container_id]);
+}
@@ -0,0 +1,153 @@
+//! Tests of transactional posts
+
+#![cfg(all(
+    feature = "acceptor",
+    feature = "transaction",
+    not(target_arch = "wasm32")
+))]
+
+mod common;
+
+use std::net::SocketAddr;
+
+use fe2o3_amqp::{
+    acceptor::{ConnectionAcceptor, LinkAcceptor, SessionAcceptor},
+    Connection, Sendable, Sender, Session,
+};
+
+use common::{expect_receiver, serve_connection};
+
+/// Spawns a listener that accepts control links and records the bodies of the messages it
+/// receives. The connection is closed once a message with the body "close" is received
+async fn spawn_txn_recorder() -> (SocketAddr, tokio::sync::mpsc::UnboundedReceiver<String>) {
+    use fe2o3_amqp::transaction::coordinator::ControlLinkAcceptor;
+
+    let (body_tx, body_rx) = tokio::sync::mpsc::unbounded_channel();
+    let acceptor = ConnectionAcceptor::new("txn-recorder");
+    let (addr, _) = serve_connection(acceptor, |mut connection| async move {
+        let mut session = SessionAcceptor::builder()
+            .control_link_acceptor(ControlLinkAcceptor::default())
+            .build()
+            .accept(&mut connection)
+            .await
+            .unwrap();
+        let mut receiver = expect_receiver(LinkAcceptor::new().accept(&mut session).await.unwrap());
+        while let Ok(delivery) = receiver.recv::<String>().await {
+            let _ = receiver.accept(&delivery).await;
+            if delivery.body() == "close" {
+                let _ = connection.close().await;
+                return;
+            }
+            let _ = body_tx.send(delivery.into_body());
+        }
+        let _ = receiver.close().await;
+        let _ = session.on_end().await;
+        let _ = connection.on_close().await;
+    })
+    .await;
+
+    (addr, body_rx)
+}
+
+#[tokio::test]
+async fn transactional_posts_are_withheld_until_commit() {
+    use fe2o3_amqp::transaction::{Controller, Transaction, TransactionDischarge};
+    use std::time::Duration;
+
+    let (addr, mut bodies) = spawn_txn_recorder().await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("txn-commit-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let controller = Controller::attach(&mut session, "txn-controller")
+        .await
+        .unwrap();
+    let mut sender = Sender::attach(&mut session, "txn-sender", "q1")
+        .await
+        .unwrap();
+
+    let mut txn = Transaction::declare(&controller, None).await.unwrap();
+    let first = sender.send_txn(&mut txn, "first").await.unwrap();
+    let second = sender.send_txn(&mut txn, "second").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(bodies.try_recv().is_err());
+
+    txn.commit().await.unwrap();
+    assert!(first.await.unwrap().is_accepted());
+    assert!(second.await.unwrap().is_accepted());
+    assert_eq!(bodies.recv().await.unwrap(), "first");
+    assert_eq!(bodies.recv().await.unwrap(), "second");
+
+    controller.close().await.unwrap();
+    sender.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn transactional_posts_are_released_on_rollback() {
+    use fe2o3_amqp::transaction::{Controller, Transaction, TransactionDischarge};
+
+    let (addr, mut bodies) = spawn_txn_recorder().await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("txn-rollback-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let controller = Controller::attach(&mut session, "txn-controller")
+        .await
+        .unwrap();
+    let mut sender = Sender::attach(&mut session, "txn-sender", "q1")
+        .await
+        .unwrap();
+
+    let mut txn = Transaction::declare(&controller, None).await.unwrap();
+    let rolled_back = sender.send_txn(&mut txn, "rolled-back").await.unwrap();
+    txn.rollback().await.unwrap();
+    assert!(rolled_back.await.unwrap().is_released());
+
+    // Dropping the transaction also rolls it back
+    let mut txn = Transaction::declare(&controller, None).await.unwrap();
+    let dropped = sender.send_txn(&mut txn, "dropped").await.unwrap();
+    drop(txn);
+    assert!(dropped.await.unwrap().is_released());
+
+    sender.send("after").await.unwrap();
+    assert_eq!(bodies.recv().await.unwrap(), "after");
+
+    controller.close().await.unwrap();
+    sender.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn transactional_posts_fail_if_controller_detaches() {
+    use fe2o3_amqp::transaction::{Controller, PostError, Transaction, TransactionDischarge};
+
+    let (addr, _bodies) = spawn_txn_recorder().await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("txn-detach-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let controller = Controller::attach(&mut session, "txn-controller")
+        .await
+        .unwrap();
+    let mut sender = Sender::attach(&mut session, "txn-sender", "q1")
+        .await
+        .unwrap();
+
+    let mut txn = Transaction::declare(&controller, None).await.unwrap();
+    let posted = sender.send_txn(&mut txn, "posted").await.unwrap();
+
+    // The listener closes the connection, which detaches the control link. The message is sent
+    // settled because the close may reach the client before the disposition
+    let close = Sendable::builder().message("close").settled(true).build();
+    sender.send(close).await.unwrap();
+    let _ = connection.on_close().await;
+
+    assert!(txn.commit().await.is_err());
+    assert!(matches!(posted.await, Err(PostError::NotDischarged)));
+}
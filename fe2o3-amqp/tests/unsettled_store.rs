@@ -0,0 +1,53 @@
+//! Tests of loading and saving the unsettled map
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use fe2o3_amqp::{
+    link::unsettled_store::{InMemoryUnsettledStore, UnsettledStore},
+    types::definitions::DeliveryTag,
+    Connection, SendReceipt, Sender, Session,
+};
+
+use common::spawn_listener;
+
+#[tokio::test]
+async fn unsettled_store_is_loaded_at_attach_and_saved_on_change() {
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::open("unsettled-store-connection", &url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+
+    // Deliveries left unsettled by a previous run
+    let store = Arc::new(InMemoryUnsettledStore::new());
+    let mut restored = BTreeMap::new();
+    restored.insert(DeliveryTag::from(vec![9]), None);
+    store.save("unsettled-store-sender", &restored);
+
+    let mut sender = Sender::builder()
+        .name("unsettled-store-sender")
+        .target("q1")
+        .unsettled_store(store.clone())
+        .attach(&mut session)
+        .await
+        .unwrap();
+    // The remote peer doesn't know of the restored delivery, so the live map is empty
+    assert!(store.load("unsettled-store-sender").is_empty());
+
+    let fut = sender.send_batchable("accept").await.unwrap();
+    assert_eq!(store.load("unsettled-store-sender").len(), 1);
+    let receipt = fut.await.unwrap();
+    assert!(matches!(receipt, SendReceipt::Accepted(_)));
+
+    // The settlement by the remote peer is written back when the link is closed
+    sender.close().await.unwrap();
+    assert!(store.load("unsettled-store-sender").is_empty());
+
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
@@ -0,0 +1,48 @@
+//! Tests of coalescing outgoing transfers
+
+#![cfg(all(feature = "acceptor", not(target_arch = "wasm32")))]
+
+mod common;
+
+use fe2o3_amqp::{Connection, Sender, Session};
+
+use common::spawn_listener;
+
+#[tokio::test]
+async fn coalesced_transfers_are_written_on_delay_and_size() {
+    use std::time::Duration;
+
+    let addr = spawn_listener(false).await;
+    let url = format!("amqp://{}", addr);
+    let mut connection = Connection::builder()
+        .container_id("write-coalescing-connection")
+        .write_coalescing(Duration::from_millis(20), 4 * 1024)
+        .open(&url[..])
+        .await
+        .unwrap();
+    let mut session = Session::begin(&mut connection).await.unwrap();
+    let mut sender = Sender::attach(&mut session, "write-coalescing-sender", "q1")
+        .await
+        .unwrap();
+
+    // A lone Transfer is written once the delay expires
+    let receipt = sender.send("accept").await.unwrap();
+    assert!(receipt.is_accepted());
+
+    // A burst of Transfers is written whenever the buffer is full
+    let mut outcomes = Vec::new();
+    for i in 0..200 {
+        let outcome = sender
+            .send_batchable(format!("message-{}", i))
+            .await
+            .unwrap();
+        outcomes.push(outcome);
+    }
+    for outcome in outcomes {
+        assert!(outcome.await.unwrap().is_accepted());
+    }
+
+    sender.close().await.unwrap();
+    session.end().await.unwrap();
+    connection.close().await.unwrap();
+}
@@ -0,0 +1,9 @@
+#![no_main]
+
+use fe2o3_amqp_types::messaging::{message::DecodeIntoMessage, Message};
+use libfuzzer_sys::fuzz_target;
+use serde_amqp::Value;
+
+fuzz_target!(|data: &[u8]| {
+    let _: Result<Message<Value>, _> = Value::decode_into_message(data);
+});
@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use serde_amqp::{from_reader, from_slice, Value};
+
+fuzz_target!(|data: &[u8]| {
+    let _: Result<Value, _> = from_slice(data);
+    let _: Result<Value, _> = from_reader(data);
+});
@@ -1,10 +1,11 @@
 //! Deserializer implementation
 
+use alloc::{string::String, vec::Vec};
+use core::convert::TryInto;
 use serde::{
     de::{self},
     Deserialize,
 };
-use std::convert::TryInto;
 
 use crate::{
     __constants::{
@@ -12,31 +13,41 @@ use crate::{
         DESCRIPTOR, SYMBOL, SYMBOL_REF, TIMESTAMP, TRANSPARENT_VEC, UUID, VALUE,
     },
     descriptor::PeekDescriptor,
-    error::Error,
+    error::{Error, Segment},
     fixed_width::{DECIMAL128_WIDTH, DECIMAL32_WIDTH, DECIMAL64_WIDTH, UUID_WIDTH},
     format::{
         OFFSET_ARRAY32, OFFSET_ARRAY8, OFFSET_LIST32, OFFSET_LIST8, OFFSET_MAP32, OFFSET_MAP8,
     },
     format_code::EncodingCodes,
-    read::{IoReader, Read, SliceReader},
+    read::{Read, SliceReader},
     util::{EnumType, NewType, PeekTypeCode, StructEncoding},
 };
 
 /// Deserialize an instance of type T from an IO stream
+#[cfg(feature = "std")]
 pub fn from_reader<T: de::DeserializeOwned>(reader: impl std::io::Read) -> Result<T, Error> {
-    let reader = IoReader::new(reader);
+    let reader = crate::read::IoReader::new(reader);
     let mut de = Deserializer::new(reader);
-    T::deserialize(&mut de)
+    T::deserialize(&mut de).map_err(|err| err.within(None, 0))
 }
 
 /// Deserialize and instance of type T from a bytes slice
 pub fn from_slice<'de, T: de::Deserialize<'de>>(slice: &'de [u8]) -> Result<T, Error> {
     let reader = SliceReader::new(slice);
     let mut de = Deserializer::new(reader);
-    T::deserialize(&mut de)
+    T::deserialize(&mut de).map_err(|err| err.within(None, 0))
 }
 
+/// The default maximum nesting depth of compound and described values
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
 /// A structure that deserializes AMQP1.0 binary encoded values into rust types
+///
+/// Non-minimal encodings (eg. a value in the `smalluint` range encoded as `uint`, or a short
+/// string encoded as `str32`) are accepted as long as the format code belongs to the expected
+/// type, because the specification allows an encoder to choose any of the encodings of a type.
+/// Malformed input (unknown format codes, declared sizes or counts that exceed the remaining
+/// input, nesting deeper than the maximum depth) is rejected with an error.
 #[derive(Debug)]
 pub struct Deserializer<R> {
     reader: R,
@@ -44,20 +55,60 @@ pub struct Deserializer<R> {
     enum_type: EnumType,
     struct_encoding: StructEncoding,
     elem_format_code: Option<EncodingCodes>,
+    depth: usize,
+    max_depth: usize,
+}
+
+impl<R> Deserializer<R> {
+    fn enter_nested(&mut self) -> Result<(), Error> {
+        if self.depth >= self.max_depth {
+            return Err(Error::NestingTooDeep);
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn leave_nested(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+    }
 }
 
 impl<'de, R: Read<'de>> Deserializer<R> {
     /// Creates a new AMQP1.0 (crate)deserializer
     pub fn new(reader: R) -> Self {
+        Self::with_max_depth(reader, DEFAULT_MAX_DEPTH)
+    }
+
+    /// Creates a new AMQP1.0 deserializer that returns [`Error::NestingTooDeep`] if compound or
+    /// described values are nested deeper than `max_depth`
+    pub fn with_max_depth(reader: R, max_depth: usize) -> Self {
         Self {
             reader,
             new_type: Default::default(),
             enum_type: Default::default(),
             struct_encoding: StructEncoding::None,
             elem_format_code: None,
+            depth: 0,
+            max_depth,
         }
     }
 
+    /// Returns the underlying reader, which is positioned right after the deserialized value
+    pub fn into_reader(self) -> R {
+        self.reader
+    }
+
+    /// Deserializes an item of a compound or described value, and attaches the location of the
+    /// item to the error if it fails
+    fn deserialize_item<T>(&mut self, segment: Option<Segment>, seed: T) -> Result<T::Value, Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        let offset = self.reader.position();
+        seed.deserialize(&mut *self)
+            .map_err(|err| err.within(segment, offset))
+    }
+
     fn read_format_code(&mut self) -> Option<Result<EncodingCodes, Error>> {
         let code = self.reader.next();
         let code = code?;
@@ -479,7 +530,7 @@ impl<'de, R: Read<'de>> Deserializer<R> {
                     .reader
                     .peek_bytes(3 + size)
                     .ok_or_else(|| Error::unexpected_eof(""))?;
-                let slice = std::str::from_utf8(&_buf[3..])?;
+                let slice = core::str::from_utf8(&_buf[3..])?;
                 visitor.visit_str(slice)
             }
             EncodingCodes::Sym32 => {
@@ -497,7 +548,7 @@ impl<'de, R: Read<'de>> Deserializer<R> {
                     .reader
                     .peek_bytes(6 + size)
                     .ok_or_else(|| Error::unexpected_eof(""))?;
-                let slice = std::str::from_utf8(&_buf[6..])?;
+                let slice = core::str::from_utf8(&_buf[6..])?;
                 visitor.visit_str(slice)
             }
             EncodingCodes::Ulong0 => visitor.visit_u64(0),
@@ -577,8 +628,10 @@ where
                 self.deserialize_newtype_struct(SYMBOL, visitor)
             }
             EncodingCodes::DescribedType => {
-                // This will not handle DescribedBasic types
-                self.deserialize_struct("", &[""], visitor)
+                // The encoding of the described value is only known after it has been read, so
+                // it is buffered and surfaced the same way as a described `Value`
+                let value = crate::Value::deserialize(&mut *self)?;
+                crate::value::de::Deserializer::new(value).deserialize_described_any(visitor)
             }
             EncodingCodes::Array32 | EncodingCodes::Array8 => {
                 self.deserialize_newtype_struct(ARRAY, visitor)
@@ -887,10 +940,10 @@ where
                 self.elem_format_code = Some(format_code);
 
                 // Account for offset
-                let len = len - OFFSET_ARRAY8;
+                let len = len.checked_sub(OFFSET_ARRAY8).ok_or(Error::InvalidLength)?;
                 // let buf = self.reader.read_bytes(len)?;
 
-                visitor.visit_seq(ArrayAccess::new(self, len, count))
+                visitor.visit_seq(ArrayAccess::new(self, len, count)?)
             }
             EncodingCodes::Array32 => {
                 // Read "header" bytes
@@ -912,15 +965,17 @@ where
                 let count = u32::from_be_bytes(count_bytes) as usize;
 
                 // Account for offset
-                let len = len - OFFSET_ARRAY32;
+                let len = len
+                    .checked_sub(OFFSET_ARRAY32)
+                    .ok_or(Error::InvalidLength)?;
                 // let buf = self.reader.read_bytes(len)?;
 
-                visitor.visit_seq(ArrayAccess::new(self, len, count))
+                visitor.visit_seq(ArrayAccess::new(self, len, count)?)
             }
             EncodingCodes::List0 => {
                 let len = 0;
                 let count = 0;
-                visitor.visit_seq(ListAccess::new(self, len, count))
+                visitor.visit_seq(ListAccess::new(self, len, count)?)
             }
             EncodingCodes::List8 => {
                 let len = self
@@ -933,11 +988,11 @@ where
                     .ok_or_else(|| Error::unexpected_eof(""))? as usize;
 
                 // Account for offset
-                let len = len - OFFSET_LIST8;
+                let len = len.checked_sub(OFFSET_LIST8).ok_or(Error::InvalidLength)?;
 
                 // Make sure there is no other element format code
                 self.elem_format_code = None;
-                visitor.visit_seq(ListAccess::new(self, len, count))
+                visitor.visit_seq(ListAccess::new(self, len, count)?)
             }
             EncodingCodes::List32 => {
                 let len_bytes = self
@@ -952,11 +1007,11 @@ where
                 let count = u32::from_be_bytes(count_bytes) as usize;
 
                 // Account for offset
-                let len = len - OFFSET_LIST32;
+                let len = len.checked_sub(OFFSET_LIST32).ok_or(Error::InvalidLength)?;
 
                 // Make sure there is no other element format code
                 self.elem_format_code = None;
-                visitor.visit_seq(ListAccess::new(self, len, count))
+                visitor.visit_seq(ListAccess::new(self, len, count)?)
             }
             _ => Err(Error::InvalidFormatCode),
         }
@@ -988,7 +1043,7 @@ where
                     .ok_or_else(|| Error::unexpected_eof(""))? as usize;
 
                 // Account for offset
-                let size = size - OFFSET_LIST8;
+                let size = size.checked_sub(OFFSET_LIST8).ok_or(Error::InvalidLength)?;
 
                 // Make sure there is no other element format code
                 self.elem_format_code = None;
@@ -1007,7 +1062,9 @@ where
                 let count = u32::from_be_bytes(count_bytes) as usize;
 
                 // Account for offset
-                let size = size - OFFSET_LIST32;
+                let size = size
+                    .checked_sub(OFFSET_LIST32)
+                    .ok_or(Error::InvalidLength)?;
 
                 // Make sure there is no other element format code
                 self.elem_format_code = None;
@@ -1020,7 +1077,7 @@ where
             return Err(Error::SequenceLengthMismatch);
         }
 
-        visitor.visit_seq(ListAccess::new(self, size, count))
+        visitor.visit_seq(ListAccess::new(self, size, count)?)
     }
 
     fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -1042,7 +1099,7 @@ where
                     .ok_or_else(|| Error::unexpected_eof(""))? as usize;
 
                 // Account for offset
-                let size = size - OFFSET_MAP8;
+                let size = size.checked_sub(OFFSET_MAP8).ok_or(Error::InvalidLength)?;
 
                 (size, count)
             }
@@ -1060,7 +1117,7 @@ where
                 let count = u32::from_be_bytes(count_bytes) as usize;
 
                 // Account for offset
-                let size = size - OFFSET_MAP32;
+                let size = size.checked_sub(OFFSET_MAP32).ok_or(Error::InvalidLength)?;
 
                 (size, count)
             }
@@ -1069,7 +1126,7 @@ where
 
         // // AMQP map count includes both key and value, should be halfed
         // let count = count / 2;
-        visitor.visit_map(MapAccess::new(self, size, count))
+        visitor.visit_map(MapAccess::new(self, size, count)?)
     }
 
     fn deserialize_tuple_struct<V>(
@@ -1083,17 +1140,19 @@ where
     {
         if name == DESCRIBED_BASIC {
             self.struct_encoding = StructEncoding::DescribedBasic;
-            visitor.visit_seq(DescribedAccess::basic(self, len as u32))
+            visitor.visit_seq(DescribedAccess::basic(self, len as u32)?)
         } else if name == DESCRIBED_LIST {
             self.struct_encoding = StructEncoding::DescribedList;
-            visitor.visit_seq(DescribedAccess::list(self))
+            visitor.visit_seq(DescribedAccess::list(self, &[])?)
         } else {
             match self
                 .get_elem_code_or_peek_byte()
                 .ok_or_else(|| Error::unexpected_eof("Expecting format code"))??
                 .try_into()?
             {
-                EncodingCodes::DescribedType => visitor.visit_seq(DescribedAccess::list(self)),
+                EncodingCodes::DescribedType => {
+                    visitor.visit_seq(DescribedAccess::list(self, &[])?)
+                }
                 _ => self.deserialize_tuple(len, visitor),
             }
         }
@@ -1112,13 +1171,13 @@ where
         let cur_encoding = self.struct_encoding.clone();
         let result = if name == DESCRIBED_BASIC {
             self.struct_encoding = StructEncoding::DescribedBasic;
-            visitor.visit_seq(DescribedAccess::basic(self, fields.len() as u32))
+            visitor.visit_seq(DescribedAccess::basic(self, fields.len() as u32)?)
         } else if name == DESCRIBED_LIST {
             self.struct_encoding = StructEncoding::DescribedList;
-            visitor.visit_seq(DescribedAccess::list(self))
+            visitor.visit_seq(DescribedAccess::list(self, fields)?)
         } else if name == DESCRIBED_MAP {
             self.struct_encoding = StructEncoding::DescribedMap;
-            visitor.visit_map(DescribedAccess::map(self))
+            visitor.visit_map(DescribedAccess::map(self)?)
         } else {
             self.struct_encoding = StructEncoding::None;
             match self
@@ -1130,7 +1189,9 @@ where
                     self.deserialize_tuple(fields.len(), visitor)
                 }
                 EncodingCodes::Map32 | EncodingCodes::Map8 => self.deserialize_map(visitor),
-                EncodingCodes::DescribedType => visitor.visit_seq(DescribedAccess::list(self)),
+                EncodingCodes::DescribedType => {
+                    visitor.visit_seq(DescribedAccess::list(self, &[])?)
+                }
                 _ => Err(Error::InvalidFormatCode),
             }
         };
@@ -1195,7 +1256,7 @@ where
                     if count != 2 {
                         return Err(Error::InvalidLength);
                     }
-                    visitor.visit_enum(VariantAccess::new(self))
+                    visitor.visit_enum(VariantAccess::nested(self)?)
                 }
                 EncodingCodes::List32 | EncodingCodes::Map32 => {
                     let _code = self
@@ -1216,7 +1277,7 @@ where
                     if count != 2 {
                         return Err(Error::InvalidLength);
                     }
-                    visitor.visit_enum(VariantAccess::new(self))
+                    visitor.visit_enum(VariantAccess::nested(self)?)
                 }
                 // Symbols appears in the transport errors
                 EncodingCodes::Sym32 | EncodingCodes::Sym8 => {
@@ -1322,15 +1383,32 @@ pub struct ArrayAccess<'a, R> {
     de: &'a mut Deserializer<R>,
     _size: usize,
     count: usize,
+    index: usize,
 }
 
-impl<'a, R> ArrayAccess<'a, R> {
-    pub(crate) fn new(de: &'a mut Deserializer<R>, size: usize, count: usize) -> Self {
-        Self {
+impl<'a, 'de, R: Read<'de>> ArrayAccess<'a, R> {
+    pub(crate) fn new(
+        de: &'a mut Deserializer<R>,
+        size: usize,
+        count: usize,
+    ) -> Result<Self, Error> {
+        // Every element takes at least one byte
+        if count > size {
+            return Err(Error::InvalidLength);
+        }
+        de.enter_nested()?;
+        Ok(Self {
             de,
             _size: size,
             count,
-        }
+            index: 0,
+        })
+    }
+}
+
+impl<'a, R> Drop for ArrayAccess<'a, R> {
+    fn drop(&mut self) {
+        self.de.leave_nested();
     }
 }
 
@@ -1354,7 +1432,9 @@ impl<'a, 'de, R: Read<'de>> de::SeqAccess<'de> for ArrayAccess<'a, R> {
             }
             _ => {
                 self.count -= 1;
-                seed.deserialize(self.as_mut()).map(Some)
+                let segment = Segment::Element(self.index);
+                self.index += 1;
+                self.de.deserialize_item(Some(segment), seed).map(Some)
             }
         }
     }
@@ -1366,15 +1446,32 @@ pub struct ListAccess<'a, R> {
     de: &'a mut Deserializer<R>,
     _size: usize,
     count: usize,
+    index: usize,
 }
 
-impl<'a, R> ListAccess<'a, R> {
-    pub(crate) fn new(de: &'a mut Deserializer<R>, size: usize, count: usize) -> Self {
-        Self {
+impl<'a, 'de, R: Read<'de>> ListAccess<'a, R> {
+    pub(crate) fn new(
+        de: &'a mut Deserializer<R>,
+        size: usize,
+        count: usize,
+    ) -> Result<Self, Error> {
+        // Every element takes at least one byte
+        if count > size {
+            return Err(Error::InvalidLength);
+        }
+        de.enter_nested()?;
+        Ok(Self {
             de,
             _size: size,
             count,
-        }
+            index: 0,
+        })
+    }
+}
+
+impl<'a, R> Drop for ListAccess<'a, R> {
+    fn drop(&mut self) {
+        self.de.leave_nested();
     }
 }
 
@@ -1395,7 +1492,9 @@ impl<'a, 'de, R: Read<'de>> de::SeqAccess<'de> for ListAccess<'a, R> {
             0 => Ok(None),
             _ => {
                 self.count -= 1;
-                seed.deserialize(self.as_mut()).map(Some)
+                let segment = Segment::Element(self.index);
+                self.index += 1;
+                self.de.deserialize_item(Some(segment), seed).map(Some)
             }
         }
     }
@@ -1406,11 +1505,16 @@ impl<'a, 'de, R: Read<'de>> de::SeqAccess<'de> for ListAccess<'a, R> {
 pub struct TransparentVecAccess<'a, R> {
     de: &'a mut Deserializer<R>,
     cached: Option<PeekTypeCode>,
+    index: usize,
 }
 
 impl<'a, R> TransparentVecAccess<'a, R> {
     pub(crate) fn new(de: &'a mut Deserializer<R>) -> Self {
-        Self { de, cached: None }
+        Self {
+            de,
+            cached: None,
+            index: 0,
+        }
     }
 }
 
@@ -1454,7 +1558,9 @@ impl<'a, 'de, R: Read<'de>> de::SeqAccess<'de> for TransparentVecAccess<'a, R> {
             None => return Ok(None),
         }
 
-        seed.deserialize(self.as_mut()).map(Some)
+        let segment = Segment::Element(self.index);
+        self.index += 1;
+        self.de.deserialize_item(Some(segment), seed).map(Some)
     }
 }
 
@@ -1464,15 +1570,33 @@ pub struct MapAccess<'a, R> {
     de: &'a mut Deserializer<R>,
     _size: usize,
     count: usize,
+    /// Index of the current entry
+    index: usize,
 }
 
-impl<'a, R> MapAccess<'a, R> {
-    pub(crate) fn new(de: &'a mut Deserializer<R>, size: usize, count: usize) -> Self {
-        Self {
+impl<'a, 'de, R: Read<'de>> MapAccess<'a, R> {
+    pub(crate) fn new(
+        de: &'a mut Deserializer<R>,
+        size: usize,
+        count: usize,
+    ) -> Result<Self, Error> {
+        // AMQP map count includes both key and value, and every element takes at least one byte
+        if count % 2 == 1 || count > size {
+            return Err(Error::InvalidLength);
+        }
+        de.enter_nested()?;
+        Ok(Self {
             de,
             _size: size,
             count,
-        }
+            index: 0,
+        })
+    }
+}
+
+impl<'a, R> Drop for MapAccess<'a, R> {
+    fn drop(&mut self) {
+        self.de.leave_nested();
     }
 }
 
@@ -1493,7 +1617,8 @@ impl<'a, 'de, R: Read<'de>> de::MapAccess<'de> for MapAccess<'a, R> {
             0 => Ok(None),
             _ => {
                 self.count -= 1;
-                seed.deserialize(self.as_mut()).map(Some)
+                let segment = Segment::Key(self.index);
+                self.de.deserialize_item(Some(segment), seed).map(Some)
             }
         }
     }
@@ -1502,8 +1627,10 @@ impl<'a, 'de, R: Read<'de>> de::MapAccess<'de> for MapAccess<'a, R> {
     where
         V: de::DeserializeSeed<'de>,
     {
-        self.count -= 1;
-        seed.deserialize(self.as_mut())
+        self.count = self.count.checked_sub(1).ok_or(Error::InvalidLength)?;
+        let segment = Segment::Value(self.index);
+        self.index += 1;
+        self.de.deserialize_item(Some(segment), seed)
     }
 
     fn next_entry_seed<K, V>(
@@ -1520,8 +1647,12 @@ impl<'a, 'de, R: Read<'de>> de::MapAccess<'de> for MapAccess<'a, R> {
             _ => {
                 // AMQP map count includes both key and value
                 self.count -= 2;
-                let key = kseed.deserialize(self.as_mut())?;
-                let val = vseed.deserialize(self.as_mut())?;
+                let index = self.index;
+                self.index += 1;
+                let key = self.de.deserialize_item(Some(Segment::Key(index)), kseed)?;
+                let val = self
+                    .de
+                    .deserialize_item(Some(Segment::Value(index)), vseed)?;
                 Ok(Some((key, val)))
             }
         }
@@ -1532,11 +1663,26 @@ impl<'a, 'de, R: Read<'de>> de::MapAccess<'de> for MapAccess<'a, R> {
 #[derive(Debug)]
 pub struct VariantAccess<'a, R> {
     de: &'a mut Deserializer<R>,
+    nested: bool,
 }
 
-impl<'a, R> VariantAccess<'a, R> {
+impl<'a, 'de, R: Read<'de>> VariantAccess<'a, R> {
     pub(crate) fn new(de: &'a mut Deserializer<R>) -> Self {
-        Self { de }
+        Self { de, nested: false }
+    }
+
+    /// The variant is encoded inside a list or map, which counts towards the nesting depth
+    pub(crate) fn nested(de: &'a mut Deserializer<R>) -> Result<Self, Error> {
+        de.enter_nested()?;
+        Ok(Self { de, nested: true })
+    }
+}
+
+impl<'a, R> Drop for VariantAccess<'a, R> {
+    fn drop(&mut self) {
+        if self.nested {
+            self.de.leave_nested();
+        }
     }
 }
 
@@ -1570,14 +1716,14 @@ impl<'a, 'de, R: Read<'de>> de::VariantAccess<'de> for VariantAccess<'a, R> {
     where
         T: de::DeserializeSeed<'de>,
     {
-        seed.deserialize(self.de)
+        seed.deserialize(&mut *self.de)
     }
 
     fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        de::Deserializer::deserialize_tuple(self.de, len, visitor)
+        de::Deserializer::deserialize_tuple(&mut *self.de, len, visitor)
     }
 
     fn struct_variant<V>(
@@ -1588,7 +1734,7 @@ impl<'a, 'de, R: Read<'de>> de::VariantAccess<'de> for VariantAccess<'a, R> {
     where
         V: de::Visitor<'de>,
     {
-        de::Deserializer::deserialize_struct(self.de, "", fields, visitor)
+        de::Deserializer::deserialize_struct(&mut *self.de, "", fields, visitor)
     }
 }
 
@@ -1598,32 +1744,67 @@ pub struct DescribedAccess<'a, R> {
     de: &'a mut Deserializer<R>,
     counter: u32,
     field_count: u32,
+    /// Encoding of the described value, which determines the segments of the items
+    encoding: StructEncoding,
+    /// Names of the descriptor and the fields of a described list, if known
+    fields: &'static [&'static str],
 }
 
 impl<'a, 'de, R: Read<'de>> DescribedAccess<'a, R> {
     /// There will be at least one descriptor, and the length of the
     /// remaining items will be determined from the bytes
-    pub(crate) fn list(de: &'a mut Deserializer<R>) -> Self {
-        Self {
+    pub(crate) fn list(
+        de: &'a mut Deserializer<R>,
+        fields: &'static [&'static str],
+    ) -> Result<Self, Error> {
+        de.enter_nested()?;
+        Ok(Self {
             de,
             field_count: 1,
             counter: 0,
-        }
+            encoding: StructEncoding::DescribedList,
+            fields,
+        })
     }
 
-    pub(crate) fn basic(de: &'a mut Deserializer<R>, field_count: u32) -> Self {
-        Self {
+    pub(crate) fn basic(de: &'a mut Deserializer<R>, field_count: u32) -> Result<Self, Error> {
+        de.enter_nested()?;
+        Ok(Self {
             de,
             field_count,
             counter: 0,
-        }
+            encoding: StructEncoding::DescribedBasic,
+            fields: &[],
+        })
     }
 
-    pub(crate) fn map(de: &'a mut Deserializer<R>) -> Self {
-        Self {
+    pub(crate) fn map(de: &'a mut Deserializer<R>) -> Result<Self, Error> {
+        de.enter_nested()?;
+        Ok(Self {
             de,
             field_count: 1,
             counter: 0,
+            encoding: StructEncoding::DescribedMap,
+            fields: &[],
+        })
+    }
+
+    /// The segment leading to the current item
+    fn segment(&self) -> Option<Segment> {
+        let index = self.counter as usize;
+        match self.encoding {
+            // The value of a wrapper is not a field of its own
+            StructEncoding::DescribedBasic if self.field_count == 2 && index == 1 => None,
+            StructEncoding::DescribedBasic => Some(Segment::Element(index)),
+            StructEncoding::DescribedMap if index > 0 => match index % 2 {
+                1 => Some(Segment::Key(index / 2)),
+                _ => Some(Segment::Value(index / 2 - 1)),
+            },
+            _ if index == 0 => Some(Segment::Descriptor),
+            _ => match self.fields.get(index) {
+                Some(name) => Some(Segment::Field(name)),
+                None => Some(Segment::Element(index - 1)),
+            },
         }
     }
 
@@ -1707,6 +1888,12 @@ impl<'a, 'de, R: Read<'de>> DescribedAccess<'a, R> {
     }
 }
 
+impl<'a, R> Drop for DescribedAccess<'a, R> {
+    fn drop(&mut self) {
+        self.de.leave_nested();
+    }
+}
+
 impl<'a, R> AsMut<Deserializer<R>> for DescribedAccess<'a, R> {
     fn as_mut(&mut self) -> &mut Deserializer<R> {
         self.de
@@ -1728,20 +1915,25 @@ impl<'a, 'de, R: Read<'de>> de::SeqAccess<'de> for DescribedAccess<'a, R> {
             None => return Ok(None),
         };
         let code = byte.try_into()?;
+        let segment = self.segment();
         let result = match code {
             EncodingCodes::DescribedType => {
-                let result = seed.deserialize(self.as_mut()).map(Some);
+                let result = self.de.deserialize_item(segment, seed).map(Some);
                 // The list header should only be consume once for each list
                 // The sublist will create new DescribedAccess and thus take care of their own
                 // list headers
-                if self.counter == 0 {
+                if self.counter == 0 && result.is_ok() {
                     if let StructEncoding::DescribedList = self.de.struct_encoding {
-                        self.field_count += self.consume_list_header()?;
+                        let count = self.consume_list_header()?;
+                        self.field_count = self
+                            .field_count
+                            .checked_add(count)
+                            .ok_or(Error::InvalidLength)?;
                     }
                 }
                 result
             }
-            _ => seed.deserialize(self.as_mut()).map(Some),
+            _ => self.de.deserialize_item(segment, seed).map(Some),
         };
 
         self.counter += 1;
@@ -1766,6 +1958,7 @@ impl<'a, 'de, R: Read<'de>> de::MapAccess<'de> for DescribedAccess<'a, R> {
             None => return Ok(None),
         };
         let code = byte.try_into()?;
+        let segment = self.segment();
         let result = match code {
             EncodingCodes::Null => {
                 let _ = self.de.reader.next(); // consume the Null byte
@@ -1773,15 +1966,19 @@ impl<'a, 'de, R: Read<'de>> de::MapAccess<'de> for DescribedAccess<'a, R> {
             }
             EncodingCodes::DescribedType => {
                 self.de.enum_type = EnumType::Descriptor;
-                let result = seed.deserialize(self.as_mut()).map(Some);
-                if self.counter == 0 {
+                let result = self.de.deserialize_item(segment, seed).map(Some);
+                if self.counter == 0 && result.is_ok() {
                     if let StructEncoding::DescribedMap = self.de.struct_encoding {
-                        self.field_count += self.consume_map_header()?;
+                        let count = self.consume_map_header()?;
+                        self.field_count = self
+                            .field_count
+                            .checked_add(count)
+                            .ok_or(Error::InvalidLength)?;
                     }
                 }
                 result
             }
-            _ => seed.deserialize(self.as_mut()).map(Some),
+            _ => self.de.deserialize_item(segment, seed).map(Some),
         };
 
         self.counter += 1;
@@ -1796,8 +1993,9 @@ impl<'a, 'de, R: Read<'de>> de::MapAccess<'de> for DescribedAccess<'a, R> {
         if self.counter >= self.field_count {
             return Err(de::Error::custom("Invalid length. Expecting value"));
         }
+        let segment = self.segment();
         self.counter += 1;
-        seed.deserialize(self.as_mut())
+        self.de.deserialize_item(segment, seed)
     }
 
     fn next_entry_seed<K, V>(
@@ -1836,7 +2034,7 @@ impl<'a, 'de, R: Read<'de>> de::MapAccess<'de> for DescribedAccess<'a, R> {
 mod tests {
     use serde::{de::DeserializeOwned, Deserialize};
 
-    use crate::format_code::EncodingCodes;
+    use crate::{error::Error, format_code::EncodingCodes};
 
     use super::{from_reader, from_slice};
 
@@ -2505,4 +2703,251 @@ mod tests {
         let buf = to_vec(&expected).unwrap();
         assert_eq_from_reader_vs_expected(&buf, expected);
     }
+
+    #[test]
+    fn test_deserialize_non_minimal_encodings() {
+        // smalluint range encoded as uint
+        let buf = &[EncodingCodes::Uint as u8, 0, 0, 0, 5];
+        assert_eq_from_slice_vs_expected(buf, 5u32);
+        assert_eq_from_reader_vs_expected(buf, 5u32);
+
+        // zero encoded as smallulong
+        let buf = &[EncodingCodes::SmallUlong as u8, 0];
+        assert_eq_from_slice_vs_expected(buf, 0u64);
+
+        // smallint range encoded as int
+        let buf = &[EncodingCodes::Int as u8, 0xff, 0xff, 0xff, 0xff];
+        assert_eq_from_slice_vs_expected(buf, -1i32);
+
+        // short string encoded as str32
+        let buf = &[EncodingCodes::Str32 as u8, 0, 0, 0, 1, b'a'];
+        assert_eq_from_slice_vs_expected(buf, String::from("a"));
+
+        // empty list encoded as list8
+        let buf = &[EncodingCodes::List8 as u8, 1, 0];
+        assert_eq_from_slice_vs_expected(buf, Vec::<u32>::new());
+
+        // The format code must still belong to the expected type
+        let buf = &[EncodingCodes::Ulong as u8, 0, 0, 0, 0, 0, 0, 0, 5];
+        assert!(from_slice::<u32>(buf).is_err());
+    }
+
+    #[test]
+    fn test_deserialize_max_depth() {
+        use crate::{read::SliceReader, Value};
+
+        use super::{Deserializer, DEFAULT_MAX_DEPTH};
+
+        fn nested_lists(depth: usize) -> Vec<u8> {
+            let mut buf = vec![EncodingCodes::List0 as u8];
+            for _ in 0..depth {
+                let size = (buf.len() + 4) as u32;
+                let mut outer = vec![EncodingCodes::List32 as u8];
+                outer.extend(size.to_be_bytes());
+                outer.extend(1u32.to_be_bytes());
+                outer.append(&mut buf);
+                buf = outer;
+            }
+            buf
+        }
+
+        let buf = nested_lists(DEFAULT_MAX_DEPTH - 1);
+        assert!(from_slice::<Value>(&buf).is_ok());
+        assert!(from_reader::<Value>(&buf[..]).is_ok());
+
+        let buf = nested_lists(DEFAULT_MAX_DEPTH);
+        assert!(matches!(
+            from_slice::<Value>(&buf).map_err(Error::into_inner),
+            Err(Error::NestingTooDeep)
+        ));
+        assert!(matches!(
+            from_reader::<Value>(&buf[..]).map_err(Error::into_inner),
+            Err(Error::NestingTooDeep)
+        ));
+
+        let buf = nested_lists(4);
+        let mut de = Deserializer::with_max_depth(SliceReader::new(&buf), 4);
+        assert!(matches!(
+            Value::deserialize(&mut de).map_err(Error::into_inner),
+            Err(Error::NestingTooDeep)
+        ));
+
+        // Chained descriptors
+        let buf: Vec<u8> = [
+            EncodingCodes::DescribedType as u8,
+            EncodingCodes::SmallUlong as u8,
+            1,
+        ]
+        .repeat(100_000);
+        assert!(matches!(
+            from_slice::<Value>(&buf).map_err(Error::into_inner),
+            Err(Error::NestingTooDeep)
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_declared_size_exceeds_input() {
+        use crate::Value;
+
+        let buf = &[EncodingCodes::Vbin32 as u8, 0xff, 0xff, 0xff, 0xff, 1, 2];
+        assert!(from_slice::<Value>(buf).is_err());
+        assert!(from_reader::<Value>(&buf[..]).is_err());
+
+        let buf = &[EncodingCodes::Str32 as u8, 0xff, 0xff, 0xff, 0xff, b'a'];
+        assert!(from_slice::<Value>(buf).is_err());
+        assert!(from_reader::<Value>(&buf[..]).is_err());
+
+        // count larger than size
+        let buf = &[
+            EncodingCodes::List32 as u8,
+            0,
+            0,
+            0,
+            4,
+            0xff,
+            0xff,
+            0xff,
+            0xff,
+        ];
+        assert!(from_slice::<Value>(buf).is_err());
+        assert!(from_reader::<Value>(&buf[..]).is_err());
+
+        // size smaller than the count field
+        let buf = &[EncodingCodes::List8 as u8, 0, 0];
+        assert!(from_slice::<Value>(buf).is_err());
+
+        // odd number of map elements
+        let buf = &[EncodingCodes::Map8 as u8, 2, 1, EncodingCodes::Null as u8];
+        assert!(from_slice::<Value>(buf).is_err());
+
+        let buf = &[
+            EncodingCodes::Array32 as u8,
+            0,
+            0,
+            0,
+            5,
+            0xff,
+            0xff,
+            0xff,
+            0xff,
+            0x40,
+        ];
+        assert!(from_slice::<Value>(buf).is_err());
+        assert!(from_reader::<Value>(&buf[..]).is_err());
+    }
+
+    #[test]
+    fn test_deserialize_invalid_format_code() {
+        use crate::Value;
+
+        for code in [
+            0x01u8, 0x3f, 0x46, 0x57, 0x5f, 0x75, 0x85, 0x99, 0xa2, 0xc2, 0xf1, 0xff,
+        ] {
+            assert!(from_slice::<Value>(&[code, 0, 0, 0, 0]).is_err());
+            assert!(from_reader::<Value>(&[code, 0, 0, 0, 0][..]).is_err());
+        }
+    }
+
+    fn path_segments(err: &Error) -> Vec<crate::error::Segment> {
+        err.path().unwrap().segments().copied().collect()
+    }
+
+    #[test]
+    fn test_deserialize_error_location() {
+        use crate::error::Segment;
+        use crate::ser::to_vec;
+        use alloc::{collections::BTreeMap, string::ToString};
+
+        // A scalar at the top level
+        let err = from_slice::<u32>(&[0xff]).unwrap_err();
+        assert_eq!(err.offset(), Some(0));
+        assert!(err.path().unwrap().is_empty());
+        assert_eq!(
+            err.to_string(),
+            "Invalid format code at byte offset 0 (root)"
+        );
+
+        // The third element of a list
+        let buf = &[
+            EncodingCodes::List8 as u8,
+            6,
+            3,
+            EncodingCodes::SmallUint as u8,
+            1,
+            EncodingCodes::SmallUint as u8,
+            2,
+            0xff,
+        ];
+        for err in [
+            from_slice::<Vec<u32>>(buf).unwrap_err(),
+            from_reader::<Vec<u32>>(&buf[..]).unwrap_err(),
+        ] {
+            assert!(matches!(err.inner(), Error::InvalidFormatCode));
+            assert_eq!(err.offset(), Some(7));
+            assert_eq!(path_segments(&err), [Segment::Element(2)]);
+            assert_eq!(
+                err.to_string(),
+                "Invalid format code at byte offset 7 (root > [2])"
+            );
+        }
+
+        // The value of the second entry of a map, which is truncated
+        let map = BTreeMap::from([("a".to_string(), 1u32), ("b".to_string(), 1000)]);
+        let buf = to_vec(&map).unwrap();
+        let err = from_slice::<BTreeMap<String, u32>>(&buf[..buf.len() - 1]).unwrap_err();
+        assert!(matches!(err.inner(), Error::Io(_)));
+        assert_eq!(err.offset(), Some(buf.len() - 5));
+        assert_eq!(path_segments(&err), [Segment::Value(1)]);
+    }
+
+    #[cfg(feature = "serde_amqp_derive")]
+    #[test]
+    fn test_deserialize_error_location_in_described_list() {
+        use crate as serde_amqp;
+        use crate::error::Segment;
+        use crate::macros::{DeserializeComposite, SerializeComposite};
+        use crate::ser::to_vec;
+        use alloc::string::ToString;
+
+        #[derive(Debug, PartialEq, SerializeComposite, DeserializeComposite)]
+        #[amqp_contract(code = "00:13", encoding = "list", rename_all = "kebab-case")]
+        struct Foo {
+            is_fool: bool,
+            values: Vec<u32>,
+        }
+
+        // An element of a field of a described list
+        let foo = Foo {
+            is_fool: true,
+            values: vec![1, 2, 3],
+        };
+        let mut buf = to_vec(&foo).unwrap();
+        let offset = buf.len() - 2;
+        buf[offset] = EncodingCodes::Str8 as u8;
+        for err in [
+            from_slice::<Foo>(&buf).unwrap_err(),
+            from_reader::<Foo>(&buf[..]).unwrap_err(),
+        ] {
+            assert_eq!(err.offset(), Some(offset));
+            assert_eq!(
+                path_segments(&err),
+                [Segment::Field("values"), Segment::Element(2)]
+            );
+            assert_eq!(
+                err.to_string(),
+                alloc::format!(
+                    "{} at byte offset {} (root > values > [2])",
+                    err.inner(),
+                    offset
+                )
+            );
+        }
+
+        // A corrupted descriptor
+        let mut buf = to_vec(&foo).unwrap();
+        buf[1] = 0xff;
+        let err = from_slice::<Foo>(&buf).unwrap_err();
+        assert_eq!(err.offset(), Some(0));
+        assert_eq!(path_segments(&err), [Segment::Descriptor]);
+    }
 }
@@ -1,5 +1,7 @@
 //! Definition of `Descriptor` type.
 
+use alloc::string::String;
+
 use crate::__constants::DESCRIPTOR;
 use crate::primitives::Symbol;
 
@@ -27,7 +29,7 @@ pub enum Descriptor {
     Code(u64),
 }
 
-use std::convert::TryInto;
+use core::convert::TryInto;
 
 use serde::de::{self, VariantAccess};
 use serde::ser::Serialize;
@@ -63,7 +65,7 @@ struct FieldVisitor {}
 impl<'de> de::Visitor<'de> for FieldVisitor {
     type Value = Field;
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
         formatter.write_str("variant identifier")
     }
 
@@ -82,6 +84,18 @@ impl<'de> de::Visitor<'de> for FieldVisitor {
             _ => Err(de::Error::custom("Invalid format code")),
         }
     }
+
+    // The variant names are used when the descriptor is buffered by a self-describing visitor
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        match v {
+            "Name" => Ok(Field::Name),
+            "Code" => Ok(Field::Code),
+            _ => Err(de::Error::unknown_variant(v, &["Name", "Code"])),
+        }
+    }
 }
 
 impl<'de> de::Deserialize<'de> for Field {
@@ -98,7 +112,7 @@ struct DescriptorVisitor {}
 impl<'de> de::Visitor<'de> for DescriptorVisitor {
     type Value = Descriptor;
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
         formatter.write_str("enum Descriptor")
     }
 
@@ -143,7 +157,7 @@ struct PeekDescriptorVisitor {}
 impl<'de> de::Visitor<'de> for PeekDescriptorVisitor {
     type Value = PeekDescriptor;
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
         formatter.write_str("enum Descriptor")
     }
 
@@ -247,4 +261,85 @@ mod tests {
         let expected = PeekDescriptor::Name(Symbol::from("test:name"));
         assert_eq!(peek, expected);
     }
+
+    #[test]
+    fn test_descriptor_code_round_trip() {
+        use crate::ser::to_vec;
+
+        let codes: [(u64, &[u8]); 9] = [
+            (0, &[0x00, 0x44]),
+            (0x01, &[0x00, 0x53, 0x01]),
+            (0xff, &[0x00, 0x53, 0xff]),
+            (0x100, &[0x00, 0x80, 0, 0, 0, 0, 0, 0, 0x01, 0x00]),
+            (
+                0xffff_ffff,
+                &[0x00, 0x80, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff],
+            ),
+            (0x1_0000_0000, &[0x00, 0x80, 0, 0, 0, 0x01, 0, 0, 0, 0]),
+            // Service Bus and Event Hubs vendor codes, eg. com.microsoft:datetime-offset
+            (
+                0x0000_0137_0000_0001,
+                &[0x00, 0x80, 0, 0, 0x01, 0x37, 0, 0, 0, 0x01],
+            ),
+            (
+                0x0000_0137_0000_0004,
+                &[0x00, 0x80, 0, 0, 0x01, 0x37, 0, 0, 0, 0x04],
+            ),
+            (
+                u64::MAX,
+                &[0x00, 0x80, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+            ),
+        ];
+        for (code, expected) in codes {
+            let descriptor = Descriptor::Code(code);
+            let buf = to_vec(&descriptor).unwrap();
+            assert_eq!(buf, expected, "code {:#x}", code);
+            let deserialized: Descriptor = from_slice(&buf).unwrap();
+            assert_eq!(deserialized, descriptor);
+
+            let reader = SliceReader::new(&buf);
+            let mut deserializer = Deserializer::new(reader);
+            let peek = PeekDescriptor::deserialize(&mut deserializer).unwrap();
+            assert_eq!(peek, PeekDescriptor::Code(code));
+        }
+    }
+
+    #[test]
+    fn test_descriptor_code_in_any_ulong_encoding() {
+        let encodings: [(u64, &[u8]); 5] = [
+            (0, &[0x00, 0x44]),
+            (0, &[0x00, 0x53, 0x00]),
+            (0, &[0x00, 0x80, 0, 0, 0, 0, 0, 0, 0, 0]),
+            (0x71, &[0x00, 0x53, 0x71]),
+            (0x71, &[0x00, 0x80, 0, 0, 0, 0, 0, 0, 0, 0x71]),
+        ];
+        for (code, buf) in encodings {
+            let deserialized: Descriptor = from_slice(buf).unwrap();
+            assert_eq!(deserialized, Descriptor::Code(code), "{:x?}", buf);
+        }
+    }
+
+    #[test]
+    fn test_described_value_with_large_descriptor_code() {
+        use crate::{described::Described, ser::to_vec, Value};
+
+        let value = Value::Described(Box::new(Described {
+            descriptor: Descriptor::Code(0x0000_0137_0000_0004),
+            value: Value::Int(1),
+        }));
+        let buf = to_vec(&value).unwrap();
+        let expected = [0x00, 0x80, 0, 0, 0x01, 0x37, 0, 0, 0, 0x04, 0x54, 0x01];
+        assert_eq!(buf, expected);
+        let deserialized: Value = from_slice(&buf).unwrap();
+        assert_eq!(deserialized, value);
+
+        // A small code encoded as a full ulong
+        let buf = [0x00, 0x80, 0, 0, 0, 0, 0, 0, 0, 0x71, 0x54, 0x01];
+        let deserialized: Value = from_slice(&buf).unwrap();
+        let expected = Value::Described(Box::new(Described {
+            descriptor: Descriptor::Code(0x71),
+            value: Value::Int(1),
+        }));
+        assert_eq!(deserialized, expected);
+    }
 }
@@ -1,7 +1,14 @@
 //! Custom error
 
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::{self, Display};
 use serde::{de, ser};
-use std::fmt::Display;
+
+use crate::io;
 
 // pub type Result<T> = core::result::Result<T, Error>;
 
@@ -14,7 +21,7 @@ pub enum Error {
 
     /// IO error
     #[error("IO {0}")]
-    Io(std::io::Error),
+    Io(io::Error),
 
     /// Invalid format code
     #[error("Invalid format code")]
@@ -39,26 +46,163 @@ pub enum Error {
     /// Length is invalid
     #[error("Invalid length")]
     InvalidLength,
+
+    /// Compound or described values are nested deeper than the maximum depth allowed by the
+    /// deserializer
+    #[error("Maximum nesting depth exceeded")]
+    NestingTooDeep,
+
+    /// A decode error together with the location of the value that could not be decoded
+    #[error("{error} at byte offset {offset} ({path})")]
+    Located {
+        /// The error that occurred while decoding the value
+        error: Box<Error>,
+
+        /// Byte offset of the start of the value in the input
+        offset: usize,
+
+        /// Path from the top level value down to the value
+        path: Path,
+    },
 }
 
 impl Error {
     pub(crate) fn too_long() -> Self {
-        let io_err = std::io::Error::new(std::io::ErrorKind::Other, "Too long");
+        let io_err = io::Error::new(io::ErrorKind::Other, "Too long");
         Self::Io(io_err)
     }
 
-    pub(crate) fn unexpected_eof(
-        error: impl Into<Box<dyn std::error::Error + Send + Sync>>,
-    ) -> Self {
-        let io_err = std::io::Error::new(std::io::ErrorKind::UnexpectedEof, error);
+    pub(crate) fn unexpected_eof(message: &'static str) -> Self {
+        let io_err = io::Error::new(io::ErrorKind::UnexpectedEof, message);
         Self::Io(io_err)
     }
+
+    /// Attaches the location of the value being decoded if the error is not located yet, and
+    /// prepends the segment leading to that value otherwise
+    pub(crate) fn within(self, segment: Option<Segment>, offset: usize) -> Self {
+        match self {
+            Self::Located {
+                error,
+                offset,
+                mut path,
+            } => {
+                path.segments.extend(segment);
+                Self::Located {
+                    error,
+                    offset,
+                    path,
+                }
+            }
+            error => Self::Located {
+                error: Box::new(error),
+                offset,
+                path: Path {
+                    segments: segment.into_iter().collect(),
+                },
+            },
+        }
+    }
+
+    /// Byte offset of the start of the value that could not be decoded, if known
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Self::Located { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    /// Path to the value that could not be decoded, if known
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::Located { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Returns the error without the location
+    pub fn inner(&self) -> &Error {
+        match self {
+            Self::Located { error, .. } => error.inner(),
+            error => error,
+        }
+    }
+
+    /// Consumes the error and returns the error without the location
+    pub fn into_inner(self) -> Error {
+        match self {
+            Self::Located { error, .. } => error.into_inner(),
+            error => error,
+        }
+    }
+}
+
+/// Path from the top level value down to a nested value
+///
+/// The path is displayed starting with `root`, eg. `root > application_properties > value[3]`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Path {
+    /// Segments in reverse order, the innermost segment comes first
+    segments: Vec<Segment>,
+}
+
+impl Path {
+    /// Returns the segments starting from the top level value
+    pub fn segments(&self) -> impl Iterator<Item = &Segment> {
+        self.segments.iter().rev()
+    }
+
+    /// Whether the path points at the top level value
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+}
+
+impl Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("root")?;
+        for segment in self.segments() {
+            write!(f, " > {}", segment)?;
+        }
+        Ok(())
+    }
+}
+
+/// A step from a compound or described value into one of its items
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment {
+    /// The descriptor of a described value
+    Descriptor,
+
+    /// A named field of a described list
+    Field(&'static str),
+
+    /// The element at the index of an array, a list or the fields of a described value that
+    /// are not named
+    Element(usize),
+
+    /// The key of the entry at the index of a map
+    Key(usize),
+
+    /// The value of the entry at the index of a map
+    Value(usize),
+}
+
+impl Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Segment::Descriptor => f.write_str("descriptor"),
+            Segment::Field(name) => f.write_str(name),
+            Segment::Element(index) => write!(f, "[{}]", index),
+            Segment::Key(index) => write!(f, "key[{}]", index),
+            Segment::Value(index) => write!(f, "value[{}]", index),
+        }
+    }
 }
 
 impl ser::Error for Error {
     fn custom<T>(msg: T) -> Self
     where
-        T: std::fmt::Display,
+        T: Display,
     {
         Self::Message(msg.to_string())
     }
@@ -73,20 +217,20 @@ impl de::Error for Error {
     }
 }
 
-impl From<std::io::Error> for Error {
-    fn from(err: std::io::Error) -> Self {
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
         Self::Io(err)
     }
 }
 
-impl From<std::string::FromUtf8Error> for Error {
-    fn from(_: std::string::FromUtf8Error) -> Self {
+impl From<alloc::string::FromUtf8Error> for Error {
+    fn from(_: alloc::string::FromUtf8Error) -> Self {
         Error::InvalidUtf8Encoding
     }
 }
 
-impl From<std::str::Utf8Error> for Error {
-    fn from(_: std::str::Utf8Error) -> Self {
+impl From<core::str::Utf8Error> for Error {
+    fn from(_: core::str::Utf8Error) -> Self {
         Error::InvalidUtf8Encoding
     }
 }
@@ -0,0 +1,134 @@
+//! I/O traits and errors that the serializer and the deserializer are written against
+//!
+//! With the `"std"` feature, these are re-exports of their [`std::io`] counterparts. Without it, a
+//! minimal subset with the same signatures is provided so that the encoding can be used with only
+//! `alloc`.
+
+#[cfg(feature = "std")]
+pub use std::io::{Error, ErrorKind, Write};
+
+#[cfg(not(feature = "std"))]
+pub use self::no_std::{Error, ErrorKind, Write};
+
+#[cfg(not(feature = "std"))]
+mod no_std {
+    use alloc::vec::Vec;
+    use core::fmt;
+
+    /// A list specifying general categories of I/O error
+    ///
+    /// This is a subset of `std::io::ErrorKind`
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    #[non_exhaustive]
+    pub enum ErrorKind {
+        /// An operation could not be completed because an "end of file" was reached prematurely
+        UnexpectedEof,
+
+        /// An operation could not be completed because a call to `write` returned `Ok(0)`
+        WriteZero,
+
+        /// A custom error that does not fall under any other I/O error kind
+        Other,
+    }
+
+    impl ErrorKind {
+        fn as_str(&self) -> &'static str {
+            match self {
+                ErrorKind::UnexpectedEof => "unexpected end of file",
+                ErrorKind::WriteZero => "write zero",
+                ErrorKind::Other => "other error",
+            }
+        }
+    }
+
+    /// The error type for I/O operations, which carries its kind and a static message
+    #[derive(Debug)]
+    pub struct Error {
+        kind: ErrorKind,
+        message: &'static str,
+    }
+
+    impl Error {
+        /// Creates a new I/O error from a known kind of error and a message
+        pub fn new(kind: ErrorKind, message: &'static str) -> Self {
+            Self { kind, message }
+        }
+
+        /// Returns the corresponding [`ErrorKind`] for this error
+        pub fn kind(&self) -> ErrorKind {
+            self.kind
+        }
+    }
+
+    impl From<ErrorKind> for Error {
+        fn from(kind: ErrorKind) -> Self {
+            Self::new(kind, "")
+        }
+    }
+
+    impl fmt::Display for Error {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self.message {
+                "" => f.write_str(self.kind.as_str()),
+                message => f.write_str(message),
+            }
+        }
+    }
+
+    impl core::error::Error for Error {}
+
+    /// A trait for objects which are byte-oriented sinks
+    ///
+    /// This is a subset of `std::io::Write`
+    pub trait Write {
+        /// Write a buffer into this writer, returning how many bytes were written
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Error>;
+
+        /// Flush this output stream
+        fn flush(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+
+        /// Attempts to write an entire buffer into this writer
+        fn write_all(&mut self, mut buf: &[u8]) -> Result<(), Error> {
+            while !buf.is_empty() {
+                match self.write(buf)? {
+                    0 => {
+                        return Err(Error::new(
+                            ErrorKind::WriteZero,
+                            "failed to write whole buffer",
+                        ))
+                    }
+                    n => buf = &buf[n..],
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl<W: Write + ?Sized> Write for &mut W {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+            (**self).write(buf)
+        }
+
+        fn flush(&mut self) -> Result<(), Error> {
+            (**self).flush()
+        }
+
+        fn write_all(&mut self, buf: &[u8]) -> Result<(), Error> {
+            (**self).write_all(buf)
+        }
+    }
+
+    impl Write for Vec<u8> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+            self.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn write_all(&mut self, buf: &[u8]) -> Result<(), Error> {
+            self.extend_from_slice(buf);
+            Ok(())
+        }
+    }
+}
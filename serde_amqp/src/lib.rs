@@ -1,4 +1,5 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
+#![cfg_attr(not(feature = "std"), no_std)]
 #![deny(missing_docs, missing_debug_implementations)]
 
 //! A serde implementation of AMQP1.0 protocol and the primitive types.
@@ -14,12 +15,13 @@
 //! Serialization:
 //!
 //! - [`to_vec`]
+//! - [`to_writer`] and [`to_writer_with_options`]
 //! - [`serialized_size`]
 //!
 //! Deserialization:
 //!
 //! - [`from_slice`]
-//! - [`from_reader`]
+//! - [`from_reader`] (requires the `"std"` feature)
 //!
 //! # Primitive types
 //!
@@ -95,20 +97,81 @@
 //! - `StructVariant` is encoded/decoded as a map of one key-value pair with the variant index being
 //!   the key and a list of the fields being the value.
 //!
+//! # Untagged enums and `deserialize_any`
+//!
+//! Self-describing deserialization (eg. `#[serde(untagged)]` enums) is supported for described
+//! types. A described value is buffered and surfaced to the visitor as follows:
+//!
+//! - The descriptor is a map of one key-value pair in the externally tagged representation of
+//!   [`Descriptor`](descriptor::Descriptor), ie. `{"Name": symbol}` or `{"Code": u64}`.
+//! - A described list is a sequence of the descriptor followed by the elements of the list.
+//! - A described map is a map whose first key is the descriptor (with a `null` value) followed by the
+//!   entries of the map.
+//! - Any other described value is a sequence of the descriptor and the value.
+//!
+//! These are the shapes that the derived `DeserializeComposite` visitors expect, so types deriving
+//! `DeserializeComposite` can be used as variants of an untagged enum.
+//!
+//! ```rust
+//! use serde::Deserialize;
+//! use serde_amqp::{SerializeComposite, DeserializeComposite, to_vec, from_slice};
+//!
+//! #[derive(Debug, PartialEq, SerializeComposite, DeserializeComposite)]
+//! #[amqp_contract(code = "0x00:0x01", encoding = "list")]
+//! struct OrderV1 {
+//!     id: u64,
+//! }
+//!
+//! #[derive(Debug, PartialEq, SerializeComposite, DeserializeComposite)]
+//! #[amqp_contract(code = "0x00:0x02", encoding = "list")]
+//! struct OrderV2 {
+//!     id: u64,
+//!     item: String,
+//! }
+//!
+//! #[derive(Debug, PartialEq, Deserialize)]
+//! #[serde(untagged)]
+//! enum Order {
+//!     V1(OrderV1),
+//!     V2(OrderV2),
+//! }
+//!
+//! let buf = to_vec(&OrderV2 { id: 1, item: "apple".to_string() }).unwrap();
+//! let order: Order = from_slice(&buf).unwrap();
+//! assert_eq!(order, Order::V2(OrderV2 { id: 1, item: "apple".to_string() }));
+//! ```
+//!
 //! # Feature flag
 //!
 //! ```toml
-//! default = []
+//! default = ["std"]
 //! ```
 //!
 //! | Feature | Description |
 //! |---------|-------------|
+//! |`"std"`| uses the standard library, see [`no_std` support](#no_std-support) |
 //! |`"derive"`| enables [`SerializeComposite` and `DeserializeComposite`](#serializecomposite-and-deserializecomposite) |
 //! |`"extensions"`| enables `extensions` mod (see [Extensions](#extensions)), added since "0.4.5" |
 //! |`"time"`| enables conversion of `Timestamp` from/to `time::Duration` and `time::OffsetDateTime`, added since "0.5.1" |
 //! |`"chrono"`| enables conversion of `Timestamp` from/to `chrono::Duration` and `chrono::DateTime`, added since "0.5.1" |
 //! |`"chrono-preview"`| a temporary feature that removes the use of deprecated APIs in `chrono` crate |
 //! |`"uuid"`| enables conversion of `Uuid` from/to `uuid::Uuid`, added since "0.5.1" |
+//! |`"json"`| enables conversion of `Value` from/to `serde_json::Value` (see [`value::to_json`] and [`value::from_json`]) |
+//!
+//! `"json"`, `"time"`, `"chrono"` and `"uuid"` enable `"std"`.
+//!
+//! ## `no_std` support
+//!
+//! Without the `"std"` feature, the crate is `no_std` and only requires `alloc`. [`Value`], the
+//! primitive types, the serializer, the deserializer (with [`from_slice`]) and the derive macros
+//! are all available. The differences are
+//!
+//! - The serializer writes to an [`io::Write`] that is implemented for `Vec<u8>` instead of
+//!   [`std::io::Write`], and [`io::Error`] is a minimal error that only carries its kind and a
+//!   static message. With `"std"`, both are re-exports of their `std::io` counterparts.
+//! - [`from_reader`] and [`read::IoReader`] are not available.
+//! - [`OrderedMap`](primitives::OrderedMap) uses a fixed hasher
+//!   ([`MapHasher`](primitives::MapHasher)) instead of the randomly seeded hasher of `std`.
 //!
 //! ## `SerializeComposite` and `DeserializeComposite`
 //!
@@ -245,6 +308,8 @@
 //! 1. `TransparentVec` - a thin wrapper around `Vec` that is serialized/deserialized as a sequence
 //!    of elements `Vec` is treated as an AMQP `List` in the core spec
 
+extern crate alloc;
+
 // Public mods
 pub mod de;
 pub mod described;
@@ -252,6 +317,7 @@ pub mod descriptor;
 pub mod error;
 pub mod fixed_width;
 pub mod format_code;
+pub mod io;
 pub mod primitives;
 pub mod read;
 pub mod ser;
@@ -268,6 +334,13 @@ pub mod extensions;
 #[path = "constants.rs"]
 pub mod __constants;
 
+// Re-exports of `alloc` items that are used by derive macros, which cannot name `alloc` in a
+// crate that does not declare it
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::vec::Vec;
+}
+
 // Private mods
 mod util;
 
@@ -276,9 +349,11 @@ mod format;
 
 pub use serde;
 
-pub use de::{from_reader, from_slice};
+#[cfg(feature = "std")]
+pub use de::from_reader;
+pub use de::from_slice;
 pub use error::Error;
-pub use ser::to_vec;
+pub use ser::{to_vec, to_writer, to_writer_with_options, SerializerOptions};
 pub use size_ser::serialized_size;
 pub use value::{de::from_value, ser::to_value, Value};
 
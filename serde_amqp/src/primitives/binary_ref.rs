@@ -1,12 +1,22 @@
-use std::fmt::{LowerHex, UpperHex};
+use core::fmt::{Debug, Display, LowerHex, UpperHex};
 
 use serde::{de, Serialize};
 
 use super::Binary;
 
+/// The length in bytes above which binary values are formatted as their length followed by
+/// the hex of the leading bytes
+pub const BINARY_DISPLAY_LIMIT: usize = 32;
+
+/// The number of leading bytes shown when a binary value longer than [`BINARY_DISPLAY_LIMIT`] is
+/// formatted
+const TRUNCATED_DISPLAY_LEN: usize = 16;
+
 /// A wrapper over [`&[u8]`] that allows serialize as an AMQP Binary type and provide custom
 /// implementation for `LowerHex` and `UpperHex`
-#[derive(Debug)]
+///
+/// The `Debug` and `Display` output of a slice longer than [`BINARY_DISPLAY_LIMIT`] only
+/// contains its length and the hex of its leading bytes, eg. `<1024 bytes, 0x000102...>`.
 pub struct BinaryRef<'a>(pub &'a [u8]);
 
 impl<'a> From<&'a Binary> for BinaryRef<'a> {
@@ -33,8 +43,45 @@ impl<'de> de::Deserialize<'de> for BinaryRef<'de> {
     }
 }
 
+impl<'a> BinaryRef<'a> {
+    fn fmt_truncated(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "<{} bytes, 0x", self.0.len())?;
+        for byte in &self.0[..TRUNCATED_DISPLAY_LEN] {
+            write!(f, "{:02x}", byte)?;
+        }
+        write!(f, "...>")
+    }
+}
+
+/// Formats the bytes like a slice, unless there are more than [`BINARY_DISPLAY_LIMIT`] bytes
+impl<'a> Debug for BinaryRef<'a> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if self.0.len() > BINARY_DISPLAY_LIMIT {
+            self.fmt_truncated(f)
+        } else {
+            Debug::fmt(self.0, f)
+        }
+    }
+}
+
+/// Formats the bytes as hex, eg. `0x616d7170`, unless there are more than
+/// [`BINARY_DISPLAY_LIMIT`] bytes
+impl<'a> Display for BinaryRef<'a> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if self.0.len() > BINARY_DISPLAY_LIMIT {
+            self.fmt_truncated(f)
+        } else {
+            write!(f, "0x")?;
+            for byte in self.0 {
+                write!(f, "{:02x}", byte)?;
+            }
+            Ok(())
+        }
+    }
+}
+
 impl<'a> LowerHex for BinaryRef<'a> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         for byte in self.0 {
             write!(f, "{:x}", byte)?;
         }
@@ -43,7 +90,7 @@ impl<'a> LowerHex for BinaryRef<'a> {
 }
 
 impl<'a> UpperHex for BinaryRef<'a> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         for byte in self.0 {
             write!(f, "{:X}", byte)?;
         }
@@ -71,6 +118,19 @@ mod tests {
         assert_eq!("616D7170", s);
     }
 
+    #[test]
+    fn test_format_long_binary_is_truncated() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        let bref = BinaryRef(&bytes);
+        let expected = "<256 bytes, 0x000102030405060708090a0b0c0d0e0f...>";
+        assert_eq!(format!("{:?}", bref), expected);
+        assert_eq!(format!("{}", bref), expected);
+
+        let bref = BinaryRef(&bytes[..4]);
+        assert_eq!(format!("{:?}", bref), "[0, 1, 2, 3]");
+        assert_eq!(format!("{}", bref), "0x00010203");
+    }
+
     #[test]
     fn test_serialize_binary_ref() {
         let bref = BinaryRef(b"amqp");
@@ -1,6 +1,7 @@
 //! Custom structs that hold bytes for decimal types
 
-use std::convert::TryFrom;
+use alloc::string::ToString;
+use core::convert::TryFrom;
 
 use serde::de;
 use serde::ser;
@@ -70,7 +71,7 @@ mod dec32 {
     impl<'de> de::Visitor<'de> for Visitor {
         type Value = Dec32;
 
-        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
             formatter.write_str("struct Dec32")
         }
 
@@ -148,7 +149,7 @@ mod dec64 {
     impl<'de> de::Visitor<'de> for Visitor {
         type Value = Dec64;
 
-        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
             formatter.write_str("struct Dec64")
         }
 
@@ -226,7 +227,7 @@ mod dec128 {
     impl<'de> de::Visitor<'de> for Visitor {
         type Value = Dec128;
 
-        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
             formatter.write_str("struct Dec128")
         }
 
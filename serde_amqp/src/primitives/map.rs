@@ -1,19 +1,59 @@
-use std::{hash::Hash, marker::PhantomData, ops::RangeBounds};
+use core::{hash::Hash, marker::PhantomData, ops::RangeBounds};
 
 use indexmap::{Equivalent, IndexMap};
 use serde::{de, ser::SerializeMap, Deserialize, Serialize};
 
 pub use indexmap::map::{Drain, IntoKeys, IntoValues, Iter, IterMut, Keys, Values, ValuesMut};
 
+/// The hasher used by [`OrderedMap`]
+///
+/// This is the randomly seeded hasher of `std` when the `"std"` feature is enabled. There is no
+/// source of randomness without `std`, so a FNV-1a hasher is used instead.
+#[cfg(feature = "std")]
+pub type MapHasher = std::collections::hash_map::RandomState;
+
+/// The hasher used by [`OrderedMap`]
+///
+/// This is the randomly seeded hasher of `std` when the `"std"` feature is enabled. There is no
+/// source of randomness without `std`, so a FNV-1a hasher is used instead.
+#[cfg(not(feature = "std"))]
+pub type MapHasher = core::hash::BuildHasherDefault<FnvHasher>;
+
+/// A 64-bit FNV-1a hasher, which is used by [`OrderedMap`] without the `"std"` feature
+#[cfg(not(feature = "std"))]
+#[derive(Debug, Clone, Copy)]
+pub struct FnvHasher(u64);
+
+#[cfg(not(feature = "std"))]
+impl Default for FnvHasher {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl core::hash::Hasher for FnvHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
 /// A wrapper around [`IndexMap`] with custom implementation of [`PartialEq`], [`Eq`],
 /// [`PartialOrd`], [`Ord`], [`Hash`], [`Serialize`], and [`Deserialize`].
 ///
 /// Only a selected list of methods are re-exported for convenience.
 #[derive(Debug, Clone, Default)]
-pub struct OrderedMap<K, V>(IndexMap<K, V>);
+pub struct OrderedMap<K, V>(IndexMap<K, V, MapHasher>);
 
-impl<K, V> From<IndexMap<K, V>> for OrderedMap<K, V> {
-    fn from(map: IndexMap<K, V>) -> Self {
+impl<K, V> From<IndexMap<K, V, MapHasher>> for OrderedMap<K, V> {
+    fn from(map: IndexMap<K, V, MapHasher>) -> Self {
         Self(map)
     }
 }
@@ -21,7 +61,7 @@ impl<K, V> From<IndexMap<K, V>> for OrderedMap<K, V> {
 impl<K, V> OrderedMap<K, V> {
     /// Creates a new [`OrderedMap`]
     pub fn new() -> Self {
-        Self(IndexMap::new())
+        Self(IndexMap::default())
     }
 
     /// Return the number of key-value pairs in the map.
@@ -49,7 +89,7 @@ impl<K, V> OrderedMap<K, V> {
     ///
     /// It is intentional to NOT implement the `AsRef<IndexMap>` trait to avoid potential
     /// misuse
-    pub fn as_inner(&self) -> &IndexMap<K, V> {
+    pub fn as_inner(&self) -> &IndexMap<K, V, MapHasher> {
         &self.0
     }
 
@@ -57,12 +97,12 @@ impl<K, V> OrderedMap<K, V> {
     ///
     /// It is intentional to NOT implement the `AsMut<IndexMap>` trait to avoid potential
     /// misuse
-    pub fn as_inner_mut(&mut self) -> &mut IndexMap<K, V> {
+    pub fn as_inner_mut(&mut self) -> &mut IndexMap<K, V, MapHasher> {
         &mut self.0
     }
 
     /// Consumes the wrapper and returns the inner [`IndexMap`]
-    pub fn into_inner(self) -> IndexMap<K, V> {
+    pub fn into_inner(self) -> IndexMap<K, V, MapHasher> {
         self.0
     }
 
@@ -105,7 +145,7 @@ impl<K, V> OrderedMap<K, V> {
 
     ///Clears the IndexMap in the given index range, returning those key-value pairs as a drain iterator.
     ///
-    ///The range may be any type that implements `RangeBounds<usize>`, including all of the std::ops::Range* types, or even a tuple pair of Bound start and end values. To drain the map entirely, use RangeFull like map.drain(..).
+    ///The range may be any type that implements `RangeBounds<usize>`, including all of the core::ops::Range* types, or even a tuple pair of Bound start and end values. To drain the map entirely, use RangeFull like map.drain(..).
     ///
     ///This shifts down all entries following the drained range to fill the gap, and keeps the allocated memory for reuse.
     ///
@@ -191,7 +231,7 @@ where
     ///
     /// Calls [`IndexMap::with_capacity`] internally
     pub fn with_capacity(n: usize) -> Self {
-        Self(IndexMap::with_capacity(n))
+        Self(IndexMap::with_capacity_and_hasher(n, MapHasher::default()))
     }
 
     /// Shrink the capacity of the map as much as possible.
@@ -232,7 +272,7 @@ where
 {
     type Value = OrderedMap<K, V>;
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
         formatter.write_str("A sequence of map entries")
     }
 
@@ -240,7 +280,7 @@ where
     where
         A: de::MapAccess<'de>,
     {
-        let mut inner = IndexMap::new();
+        let mut inner = IndexMap::default();
         while let Some((key, value)) = map.next_entry()? {
             inner.insert(key, value);
         }
@@ -288,7 +328,7 @@ where
     V: PartialOrd,
 {
     #[inline]
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
         self.0.iter().partial_cmp(other.0.iter())
     }
 }
@@ -299,7 +339,7 @@ where
     V: Ord,
 {
     #[inline]
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
         self.0.iter().cmp(other.0.iter())
     }
 }
@@ -310,7 +350,7 @@ where
     V: Hash,
 {
     #[inline]
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
         state.write_usize(self.0.len());
         for entry in &self.0 {
             entry.hash(state)
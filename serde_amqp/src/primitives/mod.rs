@@ -18,6 +18,7 @@ pub use crate::primitives::timestamp::*;
 pub use crate::primitives::uuid::*;
 
 // Alias for the primitive types to match those in the spec
+use alloc::vec::Vec;
 use serde_bytes::ByteBuf;
 
 /// Represents a true or false value
@@ -1,5 +1,7 @@
-use std::{
+use alloc::string::String;
+use core::{
     borrow::Borrow,
+    fmt::{Display, Write},
     ops::{Deref, DerefMut},
 };
 
@@ -45,6 +47,27 @@ impl<'a> From<&'a str> for SymbolRef<'a> {
     }
 }
 
+/// Writes the printable ASCII characters of the symbol as they are and escapes all the other
+/// characters, which a well-formed symbol does not contain
+fn fmt_symbol(symbol: &str, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    for c in symbol.chars() {
+        if c.is_ascii_graphic() || c == ' ' {
+            f.write_char(c)?;
+        } else {
+            for escaped in c.escape_default() {
+                f.write_char(escaped)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+impl<'a> Display for SymbolRef<'a> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        fmt_symbol(self.0, f)
+    }
+}
+
 impl<'a> Deref for SymbolRef<'a> {
     type Target = &'a str;
 
@@ -80,7 +103,7 @@ struct SymbolRefVisitor {}
 impl<'de> Visitor<'de> for SymbolRefVisitor {
     type Value = SymbolRef<'de>;
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
         formatter.write_str("A borrowed symbol")
     }
 
@@ -88,7 +111,7 @@ impl<'de> Visitor<'de> for SymbolRefVisitor {
     where
         E: de::Error,
     {
-        std::str::from_utf8(v)
+        core::str::from_utf8(v)
             .map(SymbolRef)
             .map_err(|e| de::Error::custom(e))
     }
@@ -174,6 +197,12 @@ impl<'a> From<SymbolRef<'a>> for Symbol {
     }
 }
 
+impl Display for Symbol {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        fmt_symbol(&self.0, f)
+    }
+}
+
 impl Deref for Symbol {
     type Target = String;
 
@@ -209,7 +238,7 @@ struct SymbolVisitor {}
 impl<'de> Visitor<'de> for SymbolVisitor {
     type Value = Symbol;
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
         formatter.write_str("struct Symbol")
     }
 
@@ -251,6 +280,13 @@ mod tests {
 
     use super::{Symbol, SymbolRef};
 
+    #[test]
+    fn test_display_escapes_non_ascii() {
+        let symbol = Symbol::new("amqp:link\0\u{1b}[31m\u{e9}");
+        assert_eq!(symbol.to_string(), "amqp:link\\u{0}\\u{1b}[31m\\u{e9}");
+        assert_eq!(SymbolRef("com.example").to_string(), "com.example");
+    }
+
     #[test]
     fn test_serialize_symbol_ref() {
         let val = "hello AMQP";
@@ -1,5 +1,9 @@
+use core::time::Duration;
+
 use serde::de;
 use serde::ser;
+#[cfg(feature = "std")]
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::__constants::TIMESTAMP;
 
@@ -9,6 +13,10 @@ use crate::__constants::TIMESTAMP;
 /// category = fixed, width = 8
 /// label = "64-bit two’s-complement integer representing milliseconds since the unix epoch"
 /// 64-bit two’s-complement integer representing milliseconds since the unix epoch
+///
+/// The timestamp has a precision of one millisecond. With the `"std"` feature, it can be
+/// converted from and to [`std::time::SystemTime`], and the arithmetic methods return `None`
+/// instead of overflowing.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Timestamp(i64);
 
@@ -35,6 +43,90 @@ impl Timestamp {
     pub fn milliseconds(&self) -> i64 {
         self.0
     }
+
+    /// Adds the duration to the timestamp, returning `None` if the result overflows.
+    ///
+    /// The timestamp has a precision of one millisecond, and the part of the duration below one
+    /// millisecond is dropped.
+    pub fn checked_add(&self, duration: Duration) -> Option<Self> {
+        let millis = i64::try_from(duration.as_millis()).ok()?;
+        self.0.checked_add(millis).map(Self)
+    }
+
+    /// Subtracts the duration from the timestamp, returning `None` if the result overflows.
+    ///
+    /// The part of the duration below one millisecond is dropped, like in
+    /// [`checked_add`](Self::checked_add).
+    pub fn checked_sub(&self, duration: Duration) -> Option<Self> {
+        let millis = i64::try_from(duration.as_millis()).ok()?;
+        self.0.checked_sub(millis).map(Self)
+    }
+
+    /// Returns the duration from an earlier timestamp to this one, or `None` if `earlier` is
+    /// later than this timestamp
+    pub fn duration_since(&self, earlier: &Timestamp) -> Option<Duration> {
+        // The difference of two i64 always fits in an u64 if it is not negative
+        let millis = (self.0 as i128) - (earlier.0 as i128);
+        u64::try_from(millis).ok().map(Duration::from_millis)
+    }
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+#[cfg(feature = "std")]
+impl Timestamp {
+    /// Returns the current time, truncated to the millisecond
+    pub fn now() -> Self {
+        Self::from(SystemTime::now())
+    }
+
+    /// Returns the duration from this timestamp to now, or `None` if the timestamp is in the
+    /// future or cannot be compared with the system clock
+    pub fn elapsed(&self) -> Option<Duration> {
+        Self::now().duration_since(self)
+    }
+}
+
+/// The system time is truncated to the millisecond towards the past, so a time before the unix
+/// epoch with a fraction of a millisecond becomes the earlier millisecond. A system time outside
+/// of the range of a [`Timestamp`], which is about 292 million years around the epoch, saturates
+/// to [`i64::MIN`] or [`i64::MAX`] milliseconds.
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+#[cfg(feature = "std")]
+impl From<SystemTime> for Timestamp {
+    fn from(val: SystemTime) -> Self {
+        match val.duration_since(UNIX_EPOCH) {
+            Ok(after) => Self(i64::try_from(after.as_millis()).unwrap_or(i64::MAX)),
+            Err(err) => {
+                let before = err.duration();
+                // Round up so that the sub-millisecond part is truncated towards the past
+                let millis =
+                    before.as_millis() + u128::from(before.subsec_nanos() % 1_000_000 != 0);
+                Self(
+                    i64::try_from(millis)
+                        .map(|millis| -millis)
+                        .unwrap_or(i64::MIN),
+                )
+            }
+        }
+    }
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+#[cfg(feature = "std")]
+impl TryFrom<Timestamp> for SystemTime {
+    type Error = Timestamp;
+
+    /// Conversion from [`Timestamp`] to [`SystemTime`] is fallible. The timestamp is returned as
+    /// the error if it is out of the range of [`SystemTime`] on the platform, which is the case
+    /// for the timestamps before the unix epoch on some platforms.
+    fn try_from(value: Timestamp) -> Result<Self, Self::Error> {
+        let duration = Duration::from_millis(value.0.unsigned_abs());
+        let time = match value.0 >= 0 {
+            true => UNIX_EPOCH.checked_add(duration),
+            false => UNIX_EPOCH.checked_sub(duration),
+        };
+        time.ok_or(value)
+    }
 }
 
 impl ser::Serialize for Timestamp {
@@ -51,7 +143,7 @@ struct Visitor {}
 impl<'de> de::Visitor<'de> for Visitor {
     type Value = Timestamp;
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
         formatter.write_str("struct Timestamp")
     }
 
@@ -180,3 +272,99 @@ impl From<Timestamp> for Option<chrono::DateTime<chrono::Utc>> {
         ))
     }
 }
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::Timestamp;
+
+    const EDGES: [i64; 7] = [i64::MIN, i64::MIN + 1, -1_000, -1, 0, 1, i64::MAX];
+
+    #[test]
+    fn test_timestamp_round_trips_through_system_time() {
+        let mut rng = StdRng::seed_from_u64(0x83);
+        let random = (0..10_000).map(|_| rng.gen::<i64>());
+        for millis in EDGES.into_iter().chain(random) {
+            let timestamp = Timestamp::from_milliseconds(millis);
+            let time = SystemTime::try_from(timestamp.clone()).unwrap();
+            assert_eq!(Timestamp::from(time), timestamp);
+        }
+    }
+
+    #[test]
+    fn test_system_time_is_truncated_towards_the_past() {
+        let mut rng = StdRng::seed_from_u64(0x83);
+        let max_secs = i64::MAX as u64 / 1_000 - 1;
+        for _ in 0..10_000 {
+            let duration =
+                Duration::new(rng.gen_range(0..max_secs), rng.gen_range(0..1_000_000_000));
+            for time in [UNIX_EPOCH + duration, UNIX_EPOCH - duration] {
+                let timestamp = Timestamp::from(time);
+                let truncated = SystemTime::try_from(timestamp).unwrap();
+                let lost = time.duration_since(truncated).unwrap();
+                assert!(lost < Duration::from_millis(1));
+            }
+        }
+    }
+
+    #[test]
+    fn test_pre_epoch_system_time() {
+        let time = UNIX_EPOCH - Duration::from_micros(1_500);
+        assert_eq!(Timestamp::from(time).milliseconds(), -2);
+        let time = UNIX_EPOCH - Duration::from_millis(1);
+        assert_eq!(Timestamp::from(time).milliseconds(), -1);
+        let time = UNIX_EPOCH - Duration::from_secs(86_400);
+        assert_eq!(Timestamp::from(time).milliseconds(), -86_400_000);
+    }
+
+    #[test]
+    fn test_system_time_out_of_range_saturates() {
+        let far = Duration::from_secs(i64::MAX as u64 / 2);
+        if let Some(time) = UNIX_EPOCH.checked_add(far) {
+            assert_eq!(Timestamp::from(time).milliseconds(), i64::MAX);
+        }
+        if let Some(time) = UNIX_EPOCH.checked_sub(far) {
+            assert_eq!(Timestamp::from(time).milliseconds(), i64::MIN);
+        }
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        let timestamp = Timestamp::from_milliseconds(1_000);
+        let later = timestamp.checked_add(Duration::from_micros(2_500)).unwrap();
+        assert_eq!(later.milliseconds(), 1_002);
+        let earlier = timestamp.checked_sub(Duration::from_secs(2)).unwrap();
+        assert_eq!(earlier.milliseconds(), -1_000);
+        assert!(earlier < timestamp && timestamp < later);
+
+        assert_eq!(
+            later.duration_since(&earlier),
+            Some(Duration::from_millis(2_002))
+        );
+        assert_eq!(earlier.duration_since(&later), None);
+        let min = Timestamp::from_milliseconds(i64::MIN);
+        let max = Timestamp::from_milliseconds(i64::MAX);
+        assert_eq!(
+            max.duration_since(&min),
+            Some(Duration::from_millis(u64::MAX))
+        );
+
+        assert_eq!(max.checked_add(Duration::from_millis(1)), None);
+        assert_eq!(min.checked_sub(Duration::from_millis(1)), None);
+        assert_eq!(timestamp.checked_add(Duration::MAX), None);
+    }
+
+    #[test]
+    fn test_now_and_elapsed() {
+        let before = Timestamp::from(SystemTime::now());
+        let now = Timestamp::now();
+        assert!(now >= before);
+        assert!(before.elapsed().is_some());
+
+        let future = now.checked_add(Duration::from_secs(3_600)).unwrap();
+        assert_eq!(future.elapsed(), None);
+    }
+}
@@ -10,6 +10,8 @@ pub struct IoReader<R> {
     // an io reader
     reader: R,
     buf: Vec<u8>,
+    /// Number of bytes taken from the reader, including the bytes that are still buffered
+    taken: usize,
 }
 
 impl<R: io::Read> IoReader<R> {
@@ -18,6 +20,7 @@ impl<R: io::Read> IoReader<R> {
         Self {
             reader,
             buf: Vec::new(),
+            taken: 0,
         }
     }
 
@@ -30,11 +33,21 @@ impl<R: io::Read> IoReader<R> {
     }
 
     /// Fill the internal buffer with the given length
+    ///
+    /// The buffer only grows as bytes are actually read from the underlying reader, so a
+    /// declared length larger than the remaining input does not cause a large allocation
     pub fn fill_buffer(&mut self, len: usize) -> Result<(), Error> {
         let l = self.buf.len();
         if l < len {
-            self.buf.resize(len, 0);
-            self.reader.read_exact(&mut self.buf[l..])?;
+            let missing = (len - l) as u64;
+            let mut limited = io::Read::take(&mut self.reader, missing);
+            let read = io::Read::read_to_end(&mut limited, &mut self.buf);
+            // Bytes read before an error are kept in the buffer
+            self.taken += self.buf.len() - l;
+            let read = read?;
+            if (read as u64) < missing {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "").into());
+            }
             Ok(())
         } else {
             Ok(())
@@ -45,6 +58,10 @@ impl<R: io::Read> IoReader<R> {
 impl<R: io::Read> private::Sealed for IoReader<R> {}
 
 impl<'de, R: io::Read + 'de> Read<'de> for IoReader<R> {
+    fn position(&self) -> usize {
+        self.taken - self.buf.len()
+    }
+
     fn peek(&mut self) -> Option<u8> {
         match self.buf.first() {
             Some(b) => Some(*b),
@@ -52,6 +69,7 @@ impl<'de, R: io::Read + 'de> Read<'de> for IoReader<R> {
                 let mut buf = [0u8; 1];
                 match self.reader.read_exact(&mut buf) {
                     Ok(_) => {
+                        self.taken += 1;
                         self.buf.push(buf[0]);
                         Some(buf[0])
                     }
@@ -79,27 +97,32 @@ impl<'de, R: io::Read + 'de> Read<'de> for IoReader<R> {
             None => {
                 let mut buf = [0u8; 1];
                 match self.reader.read_exact(&mut buf) {
-                    Ok(_) => Some(buf[0]),
+                    Ok(_) => {
+                        self.taken += 1;
+                        Some(buf[0])
+                    }
                     Err(_) => None, // EOF
                 }
             }
         }
     }
 
+    fn read_bytes(&mut self, n: usize) -> Option<Vec<u8>> {
+        self.fill_buffer(n).ok()?;
+        Some(self.buf.drain(..n).collect())
+    }
+
     fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), io::Error> {
         let n = buf.len();
-        let l = self.buf.len();
 
-        if l < n {
-            (buf[..l]).copy_from_slice(&self.buf[..l]);
-            self.reader.read_exact(&mut buf[l..])?;
-            self.buf.drain(..l);
-            Ok(())
-        } else {
-            buf.copy_from_slice(&self.buf[..n]);
-            self.buf.drain(..n);
-            Ok(())
-        }
+        // Bytes from an incomplete read are kept in the internal buffer
+        self.fill_buffer(n).map_err(|err| match err {
+            Error::Io(err) => err,
+            _ => io::Error::new(io::ErrorKind::UnexpectedEof, ""),
+        })?;
+        buf.copy_from_slice(&self.buf[..n]);
+        self.buf.drain(..n);
+        Ok(())
     }
 
     fn forward_read_bytes<V>(&mut self, len: usize, visitor: V) -> Result<V::Value, Error>
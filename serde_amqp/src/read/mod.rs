@@ -1,10 +1,12 @@
 //! Custom `Read` trait
 
-use std::io;
+use alloc::{vec, vec::Vec};
 
-use crate::error::Error;
+use crate::{error::Error, io};
 
+#[cfg(feature = "std")]
 mod ioread;
+#[cfg(feature = "std")]
 pub use ioread::*;
 
 mod sliceread;
@@ -16,6 +18,9 @@ mod private {
 
 /// A custom Read trait for internal use
 pub trait Read<'de>: private::Sealed {
+    /// Number of bytes consumed so far
+    fn position(&self) -> usize;
+
     /// Peek the next byte without consuming
     fn peek(&mut self) -> Option<u8>;
 
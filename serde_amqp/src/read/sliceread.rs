@@ -1,6 +1,6 @@
-use std::io;
+use alloc::vec::Vec;
 
-use crate::error::Error;
+use crate::{error::Error, io};
 
 use super::{private, Read};
 
@@ -8,12 +8,21 @@ use super::{private, Read};
 #[derive(Debug)]
 pub struct SliceReader<'s> {
     slice: &'s [u8],
+    len: usize,
 }
 
 impl<'s> SliceReader<'s> {
     /// Creates a new slice reader
     pub fn new(slice: &'s [u8]) -> Self {
-        Self { slice }
+        Self {
+            slice,
+            len: slice.len(),
+        }
+    }
+
+    /// Returns the bytes that have not been read
+    pub fn remaining(&self) -> &'s [u8] {
+        self.slice
     }
 
     /// Return a slice of the given length. If the internal slice doesn't have
@@ -31,6 +40,10 @@ impl<'s> SliceReader<'s> {
 impl<'s> private::Sealed for SliceReader<'s> {}
 
 impl<'s> Read<'s> for SliceReader<'s> {
+    fn position(&self) -> usize {
+        self.len - self.slice.len()
+    }
+
     fn peek(&mut self) -> Option<u8> {
         self.slice.first().copied()
     }
@@ -49,6 +62,10 @@ impl<'s> Read<'s> for SliceReader<'s> {
         }
     }
 
+    fn read_bytes(&mut self, n: usize) -> Option<Vec<u8>> {
+        self.get_byte_slice(n).ok().map(|s| s.to_vec())
+    }
+
     fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), io::Error> {
         let n = buf.len();
 
@@ -72,7 +89,7 @@ impl<'s> Read<'s> for SliceReader<'s> {
     where
         V: serde::de::Visitor<'s>,
     {
-        let str_slice = std::str::from_utf8(self.get_byte_slice(len)?)?;
+        let str_slice = core::str::from_utf8(self.get_byte_slice(len)?)?;
         visitor.visit_borrowed_str(str_slice)
     }
 }
@@ -1,8 +1,6 @@
 //! Serializer implementation
 
-use std::io::Write;
-
-use bytes::BufMut;
+use alloc::{vec, vec::Vec};
 use serde::{
     ser::{self, SerializeMap},
     Serialize,
@@ -16,6 +14,7 @@ use crate::{
     error::Error,
     format::{OFFSET_LIST32, OFFSET_LIST8, OFFSET_MAP32, OFFSET_MAP8},
     format_code::EncodingCodes,
+    io::{self, Write},
     util::{FieldRole, IsArrayElement, NewType, StructEncoding},
 };
 
@@ -38,6 +37,152 @@ where
     Ok(writer)
 }
 
+/// Serializes the given value into the writer
+pub fn to_writer<W, T>(writer: W, value: &T) -> Result<(), Error>
+where
+    W: Write,
+    T: Serialize + ?Sized,
+{
+    to_writer_with_options(writer, value, SerializerOptions::default())
+}
+
+/// Serializes the given value into the writer with the given options
+///
+/// With [`SerializerOptions::prefer_streaming`], the value is serialized twice. The first pass
+/// only measures the compound values, so that the second pass can write the elements of a
+/// compound value directly to the writer after its size. The output is the same as without the
+/// option, and the memory used does not grow with the size of the value.
+pub fn to_writer_with_options<W, T>(
+    writer: W,
+    value: &T,
+    options: SerializerOptions,
+) -> Result<(), Error>
+where
+    W: Write,
+    T: Serialize + ?Sized,
+{
+    let mut serializer = Serializer::new(writer);
+    if options.prefer_streaming {
+        let mut measure = Serializer::new(Discard);
+        measure.streaming = Some(Streaming::measuring());
+        value.serialize(&mut measure)?;
+        serializer.streaming = measure.streaming.map(Streaming::into_writing);
+    }
+    value.serialize(&mut serializer)
+}
+
+/// Options of [`to_writer_with_options`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SerializerOptions {
+    /// Whether the elements of compound values are written directly to the writer instead of
+    /// being buffered until the size of the compound value is known. Defaults to `false`
+    ///
+    /// This serializes the value twice, and requires the value to serialize the same way both
+    /// times. It keeps the memory used flat when the value holds large binaries or strings.
+    pub prefer_streaming: bool,
+}
+
+impl SerializerOptions {
+    /// Sets [`prefer_streaming`](Self::prefer_streaming)
+    pub fn prefer_streaming(mut self, prefer_streaming: bool) -> Self {
+        self.prefer_streaming = prefer_streaming;
+        self
+    }
+}
+
+/// A writer that drops everything, which the measuring pass is written to
+#[derive(Debug)]
+struct Discard;
+
+impl Write for Discard {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        Ok(())
+    }
+}
+
+/// Number of bytes taken by the elements of a compound value, and the number of elements
+#[derive(Debug, Clone, Copy, Default)]
+struct CompoundSize {
+    len: usize,
+    num: usize,
+}
+
+/// State of a serializer that writes the elements of compound values directly to the writer
+#[derive(Debug)]
+struct Streaming {
+    /// Whether this is the first pass, which records the sizes of the compound values
+    measuring: bool,
+
+    /// Number of bytes written so far
+    position: usize,
+
+    /// Sizes of the compound values in the order they start
+    sizes: Vec<CompoundSize>,
+
+    /// The compound value that starts next in the second pass
+    next: usize,
+}
+
+impl Streaming {
+    fn measuring() -> Self {
+        Self {
+            measuring: true,
+            position: 0,
+            sizes: Vec::new(),
+            next: 0,
+        }
+    }
+
+    fn into_writing(self) -> Self {
+        Self {
+            measuring: false,
+            position: 0,
+            sizes: self.sizes,
+            next: 0,
+        }
+    }
+}
+
+/// A compound value that has started while streaming
+#[derive(Debug, Clone, Copy)]
+struct Slot {
+    index: usize,
+
+    /// Position of the first element
+    start: usize,
+}
+
+/// How the size of a compound value is written ahead of its elements
+#[derive(Debug, Clone)]
+enum Header {
+    /// The elements are written as they are
+    None,
+    List(IsArrayElement),
+    Map(IsArrayElement),
+    Array(IsArrayElement),
+
+    /// A map from the variant index to a list of the fields
+    Variant(u32, IsArrayElement),
+}
+
+/// Writes through the serializer so that the bytes are counted while streaming
+struct Tracked<'s, W>(&'s mut Serializer<W>);
+
+impl<'s, W: Write> Write for Tracked<'s, W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+        self.0.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        self.0.writer.flush()
+    }
+}
+
 /// A struct for serializing Rust structs/values into AMQP1.0 wire format
 #[derive(Debug)]
 pub struct Serializer<W> {
@@ -53,6 +198,9 @@ pub struct Serializer<W> {
     /// Whether we are serializing an array
     /// NOTE: This should only be changed by `SeqSerializer`
     pub is_array_elem: IsArrayElement,
+
+    /// Set if the elements of compound values are written directly to the writer
+    streaming: Option<Streaming>,
 }
 
 impl<W: Write> From<W> for Serializer<W> {
@@ -69,6 +217,7 @@ impl<W: Write> Serializer<W> {
             new_type: Default::default(),
             struct_encoding: Default::default(),
             is_array_elem: IsArrayElement::False,
+            streaming: None,
         }
     }
 
@@ -84,6 +233,7 @@ impl<W: Write> Serializer<W> {
             new_type: NewType::Symbol,
             struct_encoding: Default::default(),
             is_array_elem: IsArrayElement::False,
+            streaming: None,
         }
     }
 
@@ -94,6 +244,7 @@ impl<W: Write> Serializer<W> {
             new_type: Default::default(),
             struct_encoding: vec![StructEncoding::DescribedList],
             is_array_elem: IsArrayElement::False,
+            streaming: None,
         }
     }
 
@@ -104,6 +255,7 @@ impl<W: Write> Serializer<W> {
             new_type: Default::default(),
             struct_encoding: vec![StructEncoding::DescribedMap],
             is_array_elem: IsArrayElement::False,
+            streaming: None,
         }
     }
 
@@ -114,12 +266,144 @@ impl<W: Write> Serializer<W> {
             new_type: Default::default(),
             struct_encoding: vec![StructEncoding::DescribedBasic],
             is_array_elem: IsArrayElement::False,
+            streaming: None,
         }
     }
 
     fn struct_encoding(&self) -> &StructEncoding {
         self.struct_encoding.last().unwrap_or(&StructEncoding::None)
     }
+
+    fn is_streaming(&self) -> bool {
+        self.streaming.is_some()
+    }
+
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), io::Error> {
+        if let Some(streaming) = &mut self.streaming {
+            streaming.position += buf.len();
+        }
+        self.writer.write_all(buf)
+    }
+
+    /// Serializes an element of a compound value as if it was serialized on its own, which is
+    /// what a new serializer does for the buffered elements
+    fn serialize_element<T>(
+        &mut self,
+        value: &T,
+        struct_encoding: Option<StructEncoding>,
+        is_array_elem: IsArrayElement,
+    ) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        let new_type = core::mem::take(&mut self.new_type);
+        let outer_encoding = core::mem::replace(
+            &mut self.struct_encoding,
+            struct_encoding.into_iter().collect(),
+        );
+        let outer_is_array_elem = core::mem::replace(&mut self.is_array_elem, is_array_elem);
+        let result = value.serialize(&mut *self);
+        self.new_type = new_type;
+        self.struct_encoding = outer_encoding;
+        self.is_array_elem = outer_is_array_elem;
+        result
+    }
+
+    /// Starts a compound value while streaming. The second pass writes the size that is measured
+    /// by the first pass before any element
+    fn start_compound(&mut self, header: &Header) -> Result<Option<Slot>, Error> {
+        let streaming = match &mut self.streaming {
+            Some(streaming) => streaming,
+            None => return Ok(None),
+        };
+        let index = match streaming.measuring {
+            true => {
+                streaming.sizes.push(CompoundSize::default());
+                streaming.sizes.len() - 1
+            }
+            false => {
+                streaming.next += 1;
+                streaming.next - 1
+            }
+        };
+        if !streaming.measuring {
+            let size = streaming
+                .sizes
+                .get(index)
+                .copied()
+                .ok_or_else(not_serialized_the_same_way)?;
+            self.write_header(header, size)?;
+        }
+        let start = self.streaming.as_ref().map_or(0, |s| s.position);
+        Ok(Some(Slot { index, start }))
+    }
+
+    fn start_compound_once(
+        &mut self,
+        slot: &mut Option<Slot>,
+        header: &Header,
+    ) -> Result<(), Error> {
+        if slot.is_none() {
+            *slot = self.start_compound(header)?;
+        }
+        Ok(())
+    }
+
+    /// Ends a compound value while streaming. The first pass records its size and counts the
+    /// bytes of the size
+    fn end_compound(
+        &mut self,
+        slot: Option<Slot>,
+        header: &Header,
+        num: usize,
+    ) -> Result<(), Error> {
+        let (slot, streaming) = match (slot, &mut self.streaming) {
+            (Some(slot), Some(streaming)) => (slot, streaming),
+            _ => return Ok(()),
+        };
+        let len = streaming.position - slot.start;
+        match streaming.measuring {
+            true => {
+                let size = CompoundSize { len, num };
+                streaming.sizes[slot.index] = size;
+                self.write_header(header, size)
+            }
+            false => match streaming.sizes[slot.index] {
+                CompoundSize { len: measured, .. } if measured == len => Ok(()),
+                _ => Err(not_serialized_the_same_way()),
+            },
+        }
+    }
+
+    fn write_header(&mut self, header: &Header, size: CompoundSize) -> Result<(), Error> {
+        let CompoundSize { len, num } = size;
+        match header {
+            Header::None => Ok(()),
+            Header::List(is_array_elem) => {
+                write_list_header(Tracked(self), num, len, is_array_elem)
+            }
+            Header::Map(is_array_elem) => write_map_header(Tracked(self), num, len, is_array_elem),
+            Header::Array(is_array_elem) => {
+                write_array_header(Tracked(self), num, len, is_array_elem)
+            }
+            Header::Variant(variant_index, is_array_elem) => {
+                // The key and the size of the list of fields are small enough to be buffered
+                let mut head = Vec::new();
+                let mut key_se = Serializer::new(&mut head);
+                ser::Serialize::serialize(variant_index, &mut key_se)?;
+                write_list_header(&mut head, num, len, is_array_elem)?;
+
+                write_map_header(Tracked(self), 2, head.len() + len, is_array_elem)?;
+                self.write_all(&head)?;
+                Ok(())
+            }
+        }
+    }
+}
+
+fn not_serialized_the_same_way() -> Error {
+    Error::Message("The value is not serialized the same way in both passes".into())
 }
 
 impl<'a, W: Write + 'a> ser::Serializer for &'a mut Serializer<W> {
@@ -146,21 +430,21 @@ impl<'a, W: Write + 'a> ser::Serializer for &'a mut Serializer<W> {
                     false => [EncodingCodes::BooleanFalse as u8],
                 };
                 // This cannot be moved out of match because array have different length
-                self.writer.write_all(&buf)
+                self.write_all(&buf)
             }
             IsArrayElement::FirstElement => {
                 let buf = match v {
                     true => [EncodingCodes::Boolean as u8, 0x01],
                     false => [EncodingCodes::Boolean as u8, 0x00],
                 };
-                self.writer.write_all(&buf)
+                self.write_all(&buf)
             }
             IsArrayElement::OtherElement => {
                 let buf = match v {
                     true => [0x01u8],
                     false => [0x00u8],
                 };
-                self.writer.write_all(&buf)
+                self.write_all(&buf)
             }
         }
         .map_err(Into::into)
@@ -171,11 +455,11 @@ impl<'a, W: Write + 'a> ser::Serializer for &'a mut Serializer<W> {
         match self.is_array_elem {
             IsArrayElement::False | IsArrayElement::FirstElement => {
                 let buf = [EncodingCodes::Byte as u8, v as u8];
-                self.writer.write_all(&buf).map_err(Into::into)
+                self.write_all(&buf).map_err(Into::into)
             }
             IsArrayElement::OtherElement => {
                 let buf = [v as u8];
-                self.writer.write_all(&buf).map_err(Into::into)
+                self.write_all(&buf).map_err(Into::into)
             }
         }
     }
@@ -184,10 +468,10 @@ impl<'a, W: Write + 'a> ser::Serializer for &'a mut Serializer<W> {
     fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
         if let IsArrayElement::False | IsArrayElement::FirstElement = self.is_array_elem {
             let code = [EncodingCodes::Short as u8];
-            self.writer.write_all(&code)?;
+            self.write_all(&code)?;
         }
         let buf = v.to_be_bytes();
-        self.writer.write_all(&buf).map_err(Into::into)
+        self.write_all(&buf).map_err(Into::into)
     }
 
     #[inline]
@@ -196,24 +480,24 @@ impl<'a, W: Write + 'a> ser::Serializer for &'a mut Serializer<W> {
             IsArrayElement::False => match v {
                 val @ -128..=127 => {
                     let buf = [EncodingCodes::SmallInt as u8, val as u8];
-                    self.writer.write_all(&buf)?;
+                    self.write_all(&buf)?;
                 }
                 val => {
                     let code = [EncodingCodes::Int as u8];
-                    self.writer.write_all(&code)?;
+                    self.write_all(&code)?;
                     let buf: [u8; 4] = val.to_be_bytes();
-                    self.writer.write_all(&buf)?;
+                    self.write_all(&buf)?;
                 }
             },
             IsArrayElement::FirstElement => {
                 let code = [EncodingCodes::Int as u8];
-                self.writer.write_all(&code)?;
+                self.write_all(&code)?;
                 let buf: [u8; 4] = v.to_be_bytes();
-                self.writer.write_all(&buf)?;
+                self.write_all(&buf)?;
             }
             IsArrayElement::OtherElement => {
                 let buf: [u8; 4] = v.to_be_bytes();
-                self.writer.write_all(&buf)?;
+                self.write_all(&buf)?;
             }
         }
         Ok(())
@@ -226,33 +510,33 @@ impl<'a, W: Write + 'a> ser::Serializer for &'a mut Serializer<W> {
                 IsArrayElement::False => match v {
                     val @ -128..=127 => {
                         let buf = [EncodingCodes::SmallLong as u8, val as u8];
-                        self.writer.write_all(&buf)?;
+                        self.write_all(&buf)?;
                     }
                     val => {
                         let code = [EncodingCodes::Long as u8];
-                        self.writer.write_all(&code)?;
+                        self.write_all(&code)?;
                         let buf: [u8; 8] = val.to_be_bytes();
-                        self.writer.write_all(&buf)?;
+                        self.write_all(&buf)?;
                     }
                 },
                 IsArrayElement::FirstElement => {
                     let code = [EncodingCodes::Long as u8];
-                    self.writer.write_all(&code)?;
+                    self.write_all(&code)?;
                     let buf: [u8; 8] = v.to_be_bytes();
-                    self.writer.write_all(&buf)?;
+                    self.write_all(&buf)?;
                 }
                 IsArrayElement::OtherElement => {
                     let buf: [u8; 8] = v.to_be_bytes();
-                    self.writer.write_all(&buf)?;
+                    self.write_all(&buf)?;
                 }
             },
             NewType::Timestamp => {
                 if let IsArrayElement::False | IsArrayElement::FirstElement = self.is_array_elem {
                     let code = [EncodingCodes::Timestamp as u8];
-                    self.writer.write_all(&code)?;
+                    self.write_all(&code)?;
                 }
                 let buf = v.to_be_bytes();
-                self.writer.write_all(&buf)?;
+                self.write_all(&buf)?;
             }
             _ => unreachable!(),
         }
@@ -265,11 +549,11 @@ impl<'a, W: Write + 'a> ser::Serializer for &'a mut Serializer<W> {
         match self.is_array_elem {
             IsArrayElement::False | IsArrayElement::FirstElement => {
                 let buf = [EncodingCodes::Ubyte as u8, v];
-                self.writer.write_all(&buf)?;
+                self.write_all(&buf)?;
             }
             IsArrayElement::OtherElement => {
                 let buf = [v];
-                self.writer.write_all(&buf)?;
+                self.write_all(&buf)?;
             }
         }
         Ok(())
@@ -279,10 +563,10 @@ impl<'a, W: Write + 'a> ser::Serializer for &'a mut Serializer<W> {
     fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
         if let IsArrayElement::False | IsArrayElement::FirstElement = self.is_array_elem {
             let code = [EncodingCodes::Ushort as u8];
-            self.writer.write_all(&code)?;
+            self.write_all(&code)?;
         }
         let buf: [u8; 2] = v.to_be_bytes();
-        self.writer.write_all(&buf).map_err(Into::into)
+        self.write_all(&buf).map_err(Into::into)
     }
 
     #[inline]
@@ -293,31 +577,31 @@ impl<'a, W: Write + 'a> ser::Serializer for &'a mut Serializer<W> {
                     // uint0
                     0 => {
                         let buf = [EncodingCodes::Uint0 as u8];
-                        self.writer.write_all(&buf)?;
+                        self.write_all(&buf)?;
                     }
                     // smalluint
                     val @ 1..=255 => {
                         let buf = [EncodingCodes::SmallUint as u8, val as u8];
-                        self.writer.write_all(&buf)?;
+                        self.write_all(&buf)?;
                     }
                     // uint
                     val => {
                         let code = [EncodingCodes::Uint as u8];
-                        self.writer.write_all(&code)?;
+                        self.write_all(&code)?;
                         let buf: [u8; 4] = val.to_be_bytes();
-                        self.writer.write_all(&buf)?;
+                        self.write_all(&buf)?;
                     }
                 }
             }
             IsArrayElement::FirstElement => {
                 let code = [EncodingCodes::Uint as u8];
-                self.writer.write_all(&code)?;
+                self.write_all(&code)?;
                 let buf: [u8; 4] = v.to_be_bytes();
-                self.writer.write_all(&buf)?;
+                self.write_all(&buf)?;
             }
             IsArrayElement::OtherElement => {
                 let buf: [u8; 4] = v.to_be_bytes();
-                self.writer.write_all(&buf)?;
+                self.write_all(&buf)?;
             }
         }
         Ok(())
@@ -331,31 +615,31 @@ impl<'a, W: Write + 'a> ser::Serializer for &'a mut Serializer<W> {
                     // ulong0
                     0 => {
                         let buf = [EncodingCodes::Ulong0 as u8];
-                        self.writer.write_all(&buf)?;
+                        self.write_all(&buf)?;
                     }
                     // small ulong
                     val @ 1..=255 => {
                         let buf = [EncodingCodes::SmallUlong as u8, val as u8];
-                        self.writer.write_all(&buf)?;
+                        self.write_all(&buf)?;
                     }
                     // ulong
                     val => {
                         let code = [EncodingCodes::Ulong as u8];
-                        self.writer.write_all(&code)?;
+                        self.write_all(&code)?;
                         let buf: [u8; 8] = val.to_be_bytes();
-                        self.writer.write_all(&buf)?;
+                        self.write_all(&buf)?;
                     }
                 }
             }
             IsArrayElement::FirstElement => {
                 let code = [EncodingCodes::Ulong as u8];
-                self.writer.write_all(&code)?;
+                self.write_all(&code)?;
                 let buf: [u8; 8] = v.to_be_bytes();
-                self.writer.write_all(&buf)?;
+                self.write_all(&buf)?;
             }
             IsArrayElement::OtherElement => {
                 let buf: [u8; 8] = v.to_be_bytes();
-                self.writer.write_all(&buf)?;
+                self.write_all(&buf)?;
             }
         }
         Ok(())
@@ -365,20 +649,20 @@ impl<'a, W: Write + 'a> ser::Serializer for &'a mut Serializer<W> {
     fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
         if let IsArrayElement::False | IsArrayElement::FirstElement = self.is_array_elem {
             let code = [EncodingCodes::Float as u8];
-            self.writer.write_all(&code)?;
+            self.write_all(&code)?;
         }
         let buf = v.to_be_bytes();
-        self.writer.write_all(&buf).map_err(Into::into)
+        self.write_all(&buf).map_err(Into::into)
     }
 
     #[inline]
     fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
         if let IsArrayElement::False | IsArrayElement::FirstElement = self.is_array_elem {
             let code = [EncodingCodes::Double as u8];
-            self.writer.write_all(&code)?;
+            self.write_all(&code)?;
         }
         let buf = v.to_be_bytes();
-        self.writer.write_all(&buf).map_err(Into::into)
+        self.write_all(&buf).map_err(Into::into)
     }
 
     // `char` in rust is a subset of the unicode code points and
@@ -387,10 +671,10 @@ impl<'a, W: Write + 'a> ser::Serializer for &'a mut Serializer<W> {
     fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
         if let IsArrayElement::False | IsArrayElement::FirstElement = self.is_array_elem {
             let code = [EncodingCodes::Char as u8];
-            self.writer.write_all(&code)?;
+            self.write_all(&code)?;
         }
         let buf = (v as u32).to_be_bytes();
-        self.writer.write_all(&buf).map_err(Into::into)
+        self.write_all(&buf).map_err(Into::into)
     }
 
     // String slices are always valid utf-8
@@ -412,13 +696,13 @@ impl<'a, W: Write + 'a> ser::Serializer for &'a mut Serializer<W> {
                             // sym8
                             0..=U8_MAX_MINUS_1 => {
                                 let code = [EncodingCodes::Sym8 as u8, l as u8];
-                                self.writer.write_all(&code)?;
+                                self.write_all(&code)?;
                             }
                             U8_MAX..=U32_MAX_MINUS_4 => {
                                 let code = [EncodingCodes::Sym32 as u8];
                                 let width = (l as u32).to_be_bytes();
-                                self.writer.write_all(&code)?;
-                                self.writer.write_all(&width)?;
+                                self.write_all(&code)?;
+                                self.write_all(&width)?;
                             }
                             _ => return Err(Error::too_long()),
                         }
@@ -433,15 +717,15 @@ impl<'a, W: Write + 'a> ser::Serializer for &'a mut Serializer<W> {
                             0..=U8_MAX_MINUS_1 => {
                                 let code = [EncodingCodes::Str8 as u8, l as u8];
                                 // let width: [u8; 1] = (l as u8).to_be_bytes();
-                                self.writer.write_all(&code)?;
-                                // self.writer.write_all(&width)?;
+                                self.write_all(&code)?;
+                                // self.write_all(&width)?;
                             }
                             // str32-utf8
                             U8_MAX..=U32_MAX_MINUS_4 => {
                                 let code = [EncodingCodes::Str32 as u8];
                                 let width: [u8; 4] = (l as u32).to_be_bytes();
-                                self.writer.write_all(&code)?;
-                                self.writer.write_all(&width)?;
+                                self.write_all(&code)?;
+                                self.write_all(&width)?;
                             }
                             _ => return Err(Error::too_long()),
                         }
@@ -460,8 +744,8 @@ impl<'a, W: Write + 'a> ser::Serializer for &'a mut Serializer<W> {
 
                     let code = [EncodingCodes::Sym32 as u8];
                     let width = (l as u32).to_be_bytes();
-                    self.writer.write_all(&code)?;
-                    self.writer.write_all(&width)?;
+                    self.write_all(&code)?;
+                    self.write_all(&width)?;
                 }
                 NewType::None => {
                     // A string represents a sequence of Unicode characters
@@ -470,8 +754,8 @@ impl<'a, W: Write + 'a> ser::Serializer for &'a mut Serializer<W> {
 
                     let code = [EncodingCodes::Str32 as u8];
                     let width: [u8; 4] = (l as u32).to_be_bytes();
-                    self.writer.write_all(&code)?;
-                    self.writer.write_all(&width)?;
+                    self.write_all(&code)?;
+                    self.write_all(&width)?;
                 }
                 _ => unreachable!(),
             },
@@ -485,7 +769,7 @@ impl<'a, W: Write + 'a> ser::Serializer for &'a mut Serializer<W> {
                     let l = v.len();
 
                     let width = (l as u32).to_be_bytes();
-                    self.writer.write_all(&width)?;
+                    self.write_all(&width)?;
                 }
                 NewType::None => {
                     // A string represents a sequence of Unicode characters
@@ -493,13 +777,13 @@ impl<'a, W: Write + 'a> ser::Serializer for &'a mut Serializer<W> {
                     let l = v.chars().count();
 
                     let width: [u8; 4] = (l as u32).to_be_bytes();
-                    self.writer.write_all(&width)?;
+                    self.write_all(&width)?;
                 }
                 _ => unreachable!(),
             },
         }
 
-        self.writer.write_all(v.as_bytes()).map_err(Into::into)
+        self.write_all(v.as_bytes()).map_err(Into::into)
     }
 
     #[inline]
@@ -514,15 +798,15 @@ impl<'a, W: Write + 'a> ser::Serializer for &'a mut Serializer<W> {
                             0..=U8_MAX_MINUS_1 => {
                                 let code = [EncodingCodes::Vbin8 as u8];
                                 let width: [u8; 1] = (l as u8).to_be_bytes();
-                                self.writer.write_all(&code)?;
-                                self.writer.write_all(&width)?;
+                                self.write_all(&code)?;
+                                self.write_all(&width)?;
                             }
                             // vbin32
                             U8_MAX..=U32_MAX_MINUS_4 => {
                                 let code = [EncodingCodes::Vbin32 as u8];
                                 let width: [u8; 4] = (l as u32).to_be_bytes();
-                                self.writer.write_all(&code)?;
-                                self.writer.write_all(&width)?;
+                                self.write_all(&code)?;
+                                self.write_all(&width)?;
                             }
                             _ => return Err(Error::too_long()),
                         }
@@ -530,40 +814,40 @@ impl<'a, W: Write + 'a> ser::Serializer for &'a mut Serializer<W> {
                     IsArrayElement::FirstElement => {
                         let code = [EncodingCodes::Vbin32 as u8];
                         let width: [u8; 4] = (l as u32).to_be_bytes();
-                        self.writer.write_all(&code)?;
-                        self.writer.write_all(&width)?;
+                        self.write_all(&code)?;
+                        self.write_all(&width)?;
                     }
                     IsArrayElement::OtherElement => {
                         let width: [u8; 4] = (l as u32).to_be_bytes();
-                        self.writer.write_all(&width)?;
+                        self.write_all(&width)?;
                     }
                 }
             }
             NewType::Dec32 => {
                 if let IsArrayElement::False | IsArrayElement::FirstElement = self.is_array_elem {
                     let code = [EncodingCodes::Decimal32 as u8];
-                    self.writer.write_all(&code)?;
+                    self.write_all(&code)?;
                 }
                 self.new_type = NewType::None;
             }
             NewType::Dec64 => {
                 if let IsArrayElement::False | IsArrayElement::FirstElement = self.is_array_elem {
                     let code = [EncodingCodes::Decimal64 as u8];
-                    self.writer.write_all(&code)?;
+                    self.write_all(&code)?;
                 }
                 self.new_type = NewType::None;
             }
             NewType::Dec128 => {
                 if let IsArrayElement::False | IsArrayElement::FirstElement = self.is_array_elem {
                     let code = [EncodingCodes::Decimal128 as u8];
-                    self.writer.write_all(&code)?;
+                    self.write_all(&code)?;
                 }
                 self.new_type = NewType::None;
             }
             NewType::Uuid => {
                 if let IsArrayElement::False | IsArrayElement::FirstElement = self.is_array_elem {
                     let code = [EncodingCodes::Uuid as u8];
-                    self.writer.write_all(&code)?;
+                    self.write_all(&code)?;
                 }
                 self.new_type = NewType::None;
             }
@@ -575,14 +859,14 @@ impl<'a, W: Write + 'a> ser::Serializer for &'a mut Serializer<W> {
             | NewType::TransparentVec => unreachable!(),
         }
 
-        self.writer.write_all(v).map_err(Into::into)
+        self.write_all(v).map_err(Into::into)
     }
 
     // None is serialized as Bson::Null in BSON
     #[inline]
     fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
         let buf = [EncodingCodes::Null as u8];
-        self.writer.write_all(&buf).map_err(Into::into)
+        self.write_all(&buf).map_err(Into::into)
     }
 
     #[inline]
@@ -598,7 +882,7 @@ impl<'a, W: Write + 'a> ser::Serializer for &'a mut Serializer<W> {
     fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
         // unit is serialized as Bson::Null in BSON
         let buf = [EncodingCodes::Null as u8];
-        self.writer.write_all(&buf).map_err(Into::into)
+        self.write_all(&buf).map_err(Into::into)
     }
 
     // JSON, BSOM, AVRO all serialized to unit
@@ -671,7 +955,7 @@ impl<'a, W: Write + 'a> ser::Serializer for &'a mut Serializer<W> {
         // || name == VALUE || name == AMQP_ERROR || name == CONNECTION_ERROR || name == SESSION_ERROR || name == LINK_ERROR
         {
             let code = [EncodingCodes::DescribedType as u8];
-            self.writer.write_all(&code)?;
+            self.write_all(&code)?;
             value.serialize(self)
         } else {
             let mut state = self.serialize_map(Some(1))?;
@@ -716,12 +1000,12 @@ impl<'a, W: Write + 'a> ser::Serializer for &'a mut Serializer<W> {
         if name == DESCRIBED_BASIC {
             self.struct_encoding.push(StructEncoding::DescribedBasic);
             // let code = [EncodingCodes::DescribedType as u8];
-            // self.writer.write_all(&code)?;
+            // self.write_all(&code)?;
             Ok(TupleStructSerializer::descriptor(self))
         } else if name == DESCRIBED_LIST {
             self.struct_encoding.push(StructEncoding::DescribedList);
             // let code = [EncodingCodes::DescribedType as u8];
-            // self.writer.write_all(&code)?;
+            // self.write_all(&code)?;
             Ok(TupleStructSerializer::descriptor(self))
         } else {
             Ok(TupleStructSerializer::fields(self))
@@ -808,6 +1092,7 @@ pub struct SeqSerializer<'a, W: 'a> {
     se: &'a mut Serializer<W>,
     num: usize,
     buf: Vec<u8>,
+    slot: Option<Slot>,
 }
 
 impl<'a, W: 'a> SeqSerializer<'a, W> {
@@ -816,6 +1101,22 @@ impl<'a, W: 'a> SeqSerializer<'a, W> {
             se,
             num: 0,
             buf: Vec::new(),
+            slot: None,
+        }
+    }
+
+    fn header(&self) -> Header {
+        match self.se.new_type {
+            NewType::None => Header::List(self.se.is_array_elem.clone()),
+            NewType::Array => Header::Array(self.se.is_array_elem.clone()),
+            NewType::TransparentVec => Header::None,
+            NewType::Dec32
+            | NewType::Dec64
+            | NewType::Dec128
+            | NewType::Symbol
+            | NewType::SymbolRef
+            | NewType::Timestamp
+            | NewType::Uuid => unreachable!(),
         }
     }
 }
@@ -834,6 +1135,19 @@ impl<'a, W: Write + 'a> ser::SerializeSeq for SeqSerializer<'a, W> {
     where
         T: Serialize + ?Sized,
     {
+        if self.se.is_streaming() {
+            let header = self.header();
+            self.se.start_compound_once(&mut self.slot, &header)?;
+            let is_array_elem = match (&self.se.new_type, self.num) {
+                (NewType::Array, 0) => IsArrayElement::FirstElement,
+                (NewType::Array, _) => IsArrayElement::OtherElement,
+                _ => IsArrayElement::False,
+            };
+            self.se.serialize_element(value, None, is_array_elem)?;
+            self.num += 1;
+            return Ok(());
+        }
+
         match self.se.new_type {
             NewType::None => {
                 // Element in the list always has it own constructor
@@ -876,8 +1190,14 @@ impl<'a, W: Write + 'a> ser::SerializeSeq for SeqSerializer<'a, W> {
     }
 
     #[inline]
-    fn end(self) -> Result<Self::Ok, Self::Error> {
-        let Self { se, num, buf } = self;
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
+        if self.se.is_streaming() {
+            let header = self.header();
+            self.se.start_compound_once(&mut self.slot, &header)?;
+            return self.se.end_compound(self.slot, &header, self.num);
+        }
+
+        let Self { se, num, buf, .. } = self;
         match se.new_type {
             NewType::None => write_list(&mut se.writer, num, &buf, &se.is_array_elem),
             NewType::Array => write_array(&mut se.writer, num, &buf, &se.is_array_elem),
@@ -899,8 +1219,18 @@ fn write_array<'a, W: Write + 'a>(
     buf: &'a [u8],
     ext_is_array_elem: &IsArrayElement,
 ) -> Result<(), Error> {
-    let len = buf.len();
+    write_array_header(&mut writer, num, buf.len(), ext_is_array_elem)?;
+    writer.write_all(buf)?;
+    Ok(())
+}
 
+/// Writes the constructor, size and count of an array of `len` bytes of elements
+fn write_array_header<W: Write>(
+    mut writer: W,
+    num: usize,
+    len: usize,
+    ext_is_array_elem: &IsArrayElement,
+) -> Result<(), Error> {
     match len {
         0..=U8_MAX_MINUS_1 => {
             if let IsArrayElement::False | IsArrayElement::FirstElement = ext_is_array_elem {
@@ -926,7 +1256,6 @@ fn write_array<'a, W: Write + 'a>(
         }
         _ => return Err(Error::too_long()),
     }
-    writer.write_all(buf)?;
     Ok(())
 }
 
@@ -941,6 +1270,7 @@ pub struct TupleSerializer<'a, W: 'a> {
     se: &'a mut Serializer<W>,
     num: usize,
     buf: Vec<u8>,
+    slot: Option<Slot>,
 }
 
 impl<'a, W: 'a> TupleSerializer<'a, W> {
@@ -949,6 +1279,7 @@ impl<'a, W: 'a> TupleSerializer<'a, W> {
             se,
             num,
             buf: Vec::new(),
+            slot: None,
         }
     }
 }
@@ -964,13 +1295,27 @@ impl<'a, W: Write + 'a> ser::SerializeTuple for TupleSerializer<'a, W> {
     where
         T: Serialize + ?Sized,
     {
+        if self.se.is_streaming() {
+            let header = Header::List(self.se.is_array_elem.clone());
+            self.se.start_compound_once(&mut self.slot, &header)?;
+            return self
+                .se
+                .serialize_element(value, None, IsArrayElement::False);
+        }
+
         let mut serializer = Serializer::new(&mut self.buf);
         value.serialize(&mut serializer)
     }
 
     #[inline]
-    fn end(self) -> Result<Self::Ok, Self::Error> {
-        let Self { se, num, buf } = self;
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
+        if self.se.is_streaming() {
+            let header = Header::List(self.se.is_array_elem.clone());
+            self.se.start_compound_once(&mut self.slot, &header)?;
+            return self.se.end_compound(self.slot, &header, self.num);
+        }
+
+        let Self { se, num, buf, .. } = self;
         write_list(&mut se.writer, num, &buf, &se.is_array_elem)
     }
 }
@@ -981,8 +1326,18 @@ fn write_list<'a, W: Write + 'a>(
     buf: &'a [u8],
     ext_is_array_elem: &IsArrayElement,
 ) -> Result<(), Error> {
-    let len = buf.len();
+    write_list_header(&mut writer, num, buf.len(), ext_is_array_elem)?;
+    writer.write_all(buf)?;
+    Ok(())
+}
 
+/// Writes the constructor, size and count of a list of `len` bytes of elements
+fn write_list_header<W: Write>(
+    mut writer: W,
+    num: usize,
+    len: usize,
+    ext_is_array_elem: &IsArrayElement,
+) -> Result<(), Error> {
     // if `len` < 255, `num` must be smaller than 255
     match len {
         0 => {
@@ -1015,7 +1370,6 @@ fn write_list<'a, W: Write + 'a>(
         }
         _ => return Err(Error::too_long()),
     }
-    writer.write_all(buf)?;
     Ok(())
 }
 
@@ -1025,6 +1379,7 @@ pub struct MapSerializer<'a, W: 'a> {
     se: &'a mut Serializer<W>,
     num: usize,
     buf: Vec<u8>,
+    slot: Option<Slot>,
 }
 
 impl<'a, W: 'a> MapSerializer<'a, W> {
@@ -1033,10 +1388,25 @@ impl<'a, W: 'a> MapSerializer<'a, W> {
             se,
             num: 0,
             buf: Vec::new(),
+            slot: None,
         }
     }
 }
 
+impl<'a, W: Write + 'a> MapSerializer<'a, W> {
+    fn stream_element<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        let header = Header::Map(self.se.is_array_elem.clone());
+        self.se.start_compound_once(&mut self.slot, &header)?;
+        self.se
+            .serialize_element(value, None, IsArrayElement::False)?;
+        self.num += 1;
+        Ok(())
+    }
+}
+
 // Map is a compound type and thus requires knowing the size of the total number
 // of bytes
 impl<'a, W: Write + 'a> ser::SerializeMap for MapSerializer<'a, W> {
@@ -1049,6 +1419,11 @@ impl<'a, W: Write + 'a> ser::SerializeMap for MapSerializer<'a, W> {
         K: Serialize + ?Sized,
         V: Serialize + ?Sized,
     {
+        if self.se.is_streaming() {
+            self.stream_element(key)?;
+            return self.stream_element(value);
+        }
+
         let mut serializer = Serializer::new(&mut self.buf);
         key.serialize(&mut serializer)?;
         value.serialize(&mut serializer)?;
@@ -1061,6 +1436,10 @@ impl<'a, W: Write + 'a> ser::SerializeMap for MapSerializer<'a, W> {
     where
         T: Serialize + ?Sized,
     {
+        if self.se.is_streaming() {
+            return self.stream_element(key);
+        }
+
         let mut serializer = Serializer::new(&mut self.buf);
         key.serialize(&mut serializer)?;
         self.num += 1;
@@ -1072,6 +1451,10 @@ impl<'a, W: Write + 'a> ser::SerializeMap for MapSerializer<'a, W> {
     where
         T: Serialize + ?Sized,
     {
+        if self.se.is_streaming() {
+            return self.stream_element(value);
+        }
+
         let mut serializer = Serializer::new(&mut self.buf);
         value.serialize(&mut serializer)?;
         self.num += 1;
@@ -1079,8 +1462,14 @@ impl<'a, W: Write + 'a> ser::SerializeMap for MapSerializer<'a, W> {
     }
 
     #[inline]
-    fn end(self) -> Result<Self::Ok, Self::Error> {
-        let Self { se, num, buf } = self;
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
+        if self.se.is_streaming() {
+            let header = Header::Map(self.se.is_array_elem.clone());
+            self.se.start_compound_once(&mut self.slot, &header)?;
+            return self.se.end_compound(self.slot, &header, self.num);
+        }
+
+        let Self { se, num, buf, .. } = self;
         write_map(&mut se.writer, num, &buf, &se.is_array_elem)
     }
 }
@@ -1091,8 +1480,18 @@ fn write_map<'a, W: Write + 'a>(
     buf: &'a [u8],
     ext_is_array_elem: &IsArrayElement,
 ) -> Result<(), Error> {
-    let len = buf.len();
+    write_map_header(&mut writer, num, buf.len(), ext_is_array_elem)?;
+    writer.write_all(buf)?;
+    Ok(())
+}
 
+/// Writes the constructor, size and count of a map of `len` bytes of elements
+fn write_map_header<W: Write>(
+    mut writer: W,
+    num: usize,
+    len: usize,
+    ext_is_array_elem: &IsArrayElement,
+) -> Result<(), Error> {
     match len {
         // FIXME: Whether `len` should be 255 - 1
         0..=U8_MAX_MINUS_1 => {
@@ -1120,7 +1519,6 @@ fn write_map<'a, W: Write + 'a>(
         }
         _ => return Err(Error::too_long()),
     }
-    writer.write_all(buf)?;
     Ok(())
 }
 
@@ -1131,6 +1529,7 @@ pub struct TupleStructSerializer<'a, W: 'a> {
     field_role: FieldRole,
     count: usize,
     buf: Vec<u8>,
+    slot: Option<Slot>,
 }
 
 impl<'a, W: 'a> TupleStructSerializer<'a, W> {
@@ -1140,6 +1539,7 @@ impl<'a, W: 'a> TupleStructSerializer<'a, W> {
             field_role: FieldRole::Descriptor,
             count: 0,
             buf: Vec::new(),
+            slot: None,
         }
     }
 
@@ -1149,6 +1549,19 @@ impl<'a, W: 'a> TupleStructSerializer<'a, W> {
             field_role: FieldRole::Fields,
             count: 0,
             buf: Vec::new(),
+            slot: None,
+        }
+    }
+}
+
+impl<'a, W: Write + 'a> TupleStructSerializer<'a, W> {
+    fn header(&self) -> Header {
+        match self.se.struct_encoding() {
+            StructEncoding::None | StructEncoding::DescribedList => {
+                Header::List(IsArrayElement::False)
+            }
+            StructEncoding::DescribedBasic => Header::None,
+            StructEncoding::DescribedMap => unreachable!(),
         }
     }
 }
@@ -1174,6 +1587,24 @@ impl<'a, W: Write + 'a> ser::SerializeTupleStruct for TupleStructSerializer<'a,
             }
             FieldRole::Fields => {
                 self.count += 1;
+                if self.se.is_streaming() {
+                    // The list starts after the descriptor
+                    let header = self.header();
+                    self.se.start_compound_once(&mut self.slot, &header)?;
+                    return match self.se.struct_encoding() {
+                        StructEncoding::None | StructEncoding::DescribedBasic => {
+                            let is_array_elem = self.se.is_array_elem.clone();
+                            self.se.serialize_element(value, None, is_array_elem)
+                        }
+                        StructEncoding::DescribedList => self.se.serialize_element(
+                            value,
+                            Some(StructEncoding::DescribedList),
+                            IsArrayElement::False,
+                        ),
+                        StructEncoding::DescribedMap => unreachable!(),
+                    };
+                }
+
                 match self.se.struct_encoding() {
                     StructEncoding::None => {
                         // serialize regualr tuple struct as a list like in tuple
@@ -1198,7 +1629,20 @@ impl<'a, W: Write + 'a> ser::SerializeTupleStruct for TupleStructSerializer<'a,
         }
     }
 
-    fn end(self) -> Result<Self::Ok, Self::Error> {
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
+        if self.se.is_streaming() {
+            let header = self.header();
+            self.se.start_compound_once(&mut self.slot, &header)?;
+            self.se.end_compound(self.slot, &header, self.count)?;
+            match self.se.struct_encoding() {
+                StructEncoding::None => {}
+                _ => {
+                    self.se.struct_encoding.pop();
+                }
+            }
+            return Ok(());
+        }
+
         match self.se.struct_encoding() {
             StructEncoding::None => {
                 // serialize regualr tuple struct as a list like in tuple
@@ -1238,6 +1682,7 @@ pub struct StructSerializer<'a, W: 'a> {
     se: &'a mut Serializer<W>,
     count: usize,
     buf: Vec<u8>,
+    slot: Option<Slot>,
 }
 
 impl<'a, W: 'a> StructSerializer<'a, W> {
@@ -1246,6 +1691,26 @@ impl<'a, W: 'a> StructSerializer<'a, W> {
             se,
             count: 0,
             buf: vec![],
+            slot: None,
+        }
+    }
+}
+
+impl<'a, W: Write + 'a> StructSerializer<'a, W> {
+    fn header(&self) -> Header {
+        let is_array_elem = self.se.is_array_elem.clone();
+        match self.se.struct_encoding() {
+            StructEncoding::None | StructEncoding::DescribedList => Header::List(is_array_elem),
+            StructEncoding::DescribedMap => Header::Map(is_array_elem),
+            StructEncoding::DescribedBasic => Header::None,
+        }
+    }
+
+    /// Number of elements in the list or map
+    fn num(&self) -> usize {
+        match self.se.struct_encoding() {
+            StructEncoding::DescribedMap => self.count * 2,
+            _ => self.count,
         }
     }
 }
@@ -1268,6 +1733,33 @@ impl<'a, W: Write + 'a> ser::SerializeStruct for StructSerializer<'a, W> {
     {
         if key == DESCRIPTOR {
             value.serialize(self.as_mut())
+        } else if self.se.is_streaming() {
+            self.count += 1;
+            let encoding = match self.se.struct_encoding() {
+                StructEncoding::DescribedBasic => return value.serialize(self.as_mut()),
+                encoding => encoding.clone(),
+            };
+            // The list or map starts after the descriptor
+            let header = self.header();
+            self.se.start_compound_once(&mut self.slot, &header)?;
+            match encoding {
+                StructEncoding::None => {
+                    let is_array_elem = self.se.is_array_elem.clone();
+                    self.se.serialize_element(value, None, is_array_elem)
+                }
+                StructEncoding::DescribedMap => {
+                    self.se.serialize_element(
+                        key,
+                        Some(encoding.clone()),
+                        IsArrayElement::False,
+                    )?;
+                    self.se
+                        .serialize_element(value, Some(encoding), IsArrayElement::False)
+                }
+                _ => self
+                    .se
+                    .serialize_element(value, Some(encoding), IsArrayElement::False),
+            }
         } else {
             self.count += 1;
             match self.se.struct_encoding() {
@@ -1292,7 +1784,25 @@ impl<'a, W: Write + 'a> ser::SerializeStruct for StructSerializer<'a, W> {
     }
 
     #[inline]
-    fn end(self) -> Result<Self::Ok, Self::Error> {
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
+        if self.se.is_streaming() {
+            match self.se.struct_encoding() {
+                StructEncoding::DescribedBasic => {}
+                _ => {
+                    let header = self.header();
+                    self.se.start_compound_once(&mut self.slot, &header)?;
+                    self.se.end_compound(self.slot, &header, self.num())?;
+                }
+            }
+            match self.se.struct_encoding() {
+                StructEncoding::None => {}
+                _ => {
+                    self.se.struct_encoding.pop();
+                }
+            }
+            return Ok(());
+        }
+
         match self.se.struct_encoding() {
             StructEncoding::None => write_list(
                 &mut self.se.writer,
@@ -1337,6 +1847,7 @@ pub struct VariantSerializer<'a, W: 'a> {
     _variant: &'static str,
     num: usize,
     buf: Vec<u8>,
+    slot: Option<Slot>,
 }
 
 impl<'a, W: 'a> VariantSerializer<'a, W> {
@@ -1354,8 +1865,13 @@ impl<'a, W: 'a> VariantSerializer<'a, W> {
             _variant: variant,
             num,
             buf: Vec::new(),
+            slot: None,
         }
     }
+
+    fn header(&self) -> Header {
+        Header::Variant(self.variant_index, self.se.is_array_elem.clone())
+    }
 }
 
 impl<'a, W: Write + 'a> ser::SerializeTupleVariant for VariantSerializer<'a, W> {
@@ -1366,24 +1882,35 @@ impl<'a, W: Write + 'a> ser::SerializeTupleVariant for VariantSerializer<'a, W>
     where
         T: Serialize + ?Sized,
     {
+        if self.se.is_streaming() {
+            let header = self.header();
+            self.se.start_compound_once(&mut self.slot, &header)?;
+            return self
+                .se
+                .serialize_element(value, None, IsArrayElement::False);
+        }
+
         let mut se = Serializer::new(&mut self.buf);
         value.serialize(&mut se)
     }
 
-    fn end(self) -> Result<Self::Ok, Self::Error> {
-        use bytes::BytesMut;
-        let kv_buf = BytesMut::new();
-        let mut writer = kv_buf.writer();
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
+        if self.se.is_streaming() {
+            let header = self.header();
+            self.se.start_compound_once(&mut self.slot, &header)?;
+            return self.se.end_compound(self.slot, &header, self.num);
+        }
+
+        let mut buf = Vec::new();
 
         // Serialize key
-        let mut key_se = Serializer::new(&mut writer);
+        let mut key_se = Serializer::new(&mut buf);
         ser::Serialize::serialize(&self.variant_index, &mut key_se)?;
 
         // Write values
-        write_list(&mut writer, self.num, &self.buf, &self.se.is_array_elem)?;
+        write_list(&mut buf, self.num, &self.buf, &self.se.is_array_elem)?;
 
         // Write entire list
-        let buf = writer.into_inner().freeze();
         // write_list(&mut self.se.writer, 2, &buf, &self.se.is_array_elem)
         write_map(&mut self.se.writer, 2, &buf, &self.se.is_array_elem)
     }
@@ -2384,4 +2911,71 @@ mod test {
         let buf = to_vec(&data).unwrap();
         println!("{:#x?}", buf);
     }
+
+    fn assert_streamed_eq_to_vec<T: Serialize>(val: T) {
+        let expected = to_vec(&val).unwrap();
+        let mut streamed = Vec::new();
+        let options = SerializerOptions::default().prefer_streaming(true);
+        to_writer_with_options(&mut streamed, &val, options).unwrap();
+        assert_eq!(streamed, expected);
+    }
+
+    #[derive(Debug, Serialize)]
+    struct Streamed {
+        a: i32,
+        b: String,
+        c: Vec<Option<u8>>,
+    }
+
+    #[derive(Debug, Serialize)]
+    enum StreamedVariant {
+        Tuple(u32, String),
+        Struct { a: Streamed, b: bool },
+    }
+
+    #[test]
+    fn test_streamed_compounds_eq_to_vec() {
+        let long = "a".repeat(300);
+        assert_streamed_eq_to_vec(Vec::<i32>::new());
+        assert_streamed_eq_to_vec((1u8, "amqp", (2i64, ())));
+        assert_streamed_eq_to_vec(vec![(&NewType(NewType(1i32)), &false, "amqp")]);
+        assert_streamed_eq_to_vec(Streamed {
+            a: 1,
+            b: long.clone(),
+            c: vec![Some(1), None],
+        });
+        assert_streamed_eq_to_vec(StreamedVariant::Tuple(7, long.clone()));
+        assert_streamed_eq_to_vec(StreamedVariant::Struct {
+            a: Streamed {
+                a: 2,
+                b: String::from("b"),
+                c: vec![],
+            },
+            b: true,
+        });
+        assert_streamed_eq_to_vec(Array(vec![Array(vec![1u64, 2]), Array(vec![])]));
+        assert_streamed_eq_to_vec(Array(vec![Symbol::from("a"), Symbol::from(long)]));
+    }
+
+    #[test]
+    fn test_streamed_value_eq_to_vec() {
+        use crate::{described::Described, primitives::OrderedMap, Value};
+
+        let mut map = OrderedMap::new();
+        map.insert(Value::Symbol(Symbol::from("key")), Value::Uint(1));
+        map.insert(
+            Value::String(String::from("list")),
+            Value::List(vec![Value::Null, Value::Binary(vec![0; 300].into())]),
+        );
+        let described = Described {
+            descriptor: Descriptor::Code(0x75),
+            value: Value::List(vec![Value::Map(map.clone()), Value::Bool(true)]),
+        };
+        let value = Value::List(vec![
+            Value::Described(Box::new(described)),
+            Value::Array(Array(vec![Value::Map(map.clone()), Value::Map(map)])),
+            Value::Array(Array(vec![Value::Int(1), Value::Int(2)])),
+        ]);
+        assert_streamed_eq_to_vec(value);
+    }
 }
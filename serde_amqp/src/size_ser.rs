@@ -1,15 +1,16 @@
 //! Serializer that calculates the size of serialized data without actually allocating `Vec<u8>`
 
+use alloc::{vec, vec::Vec};
 use serde::ser::{self, SerializeMap};
 
 use crate::{
-    Error,
     __constants::{
         ARRAY, DECIMAL128, DECIMAL32, DECIMAL64, DESCRIBED_BASIC, DESCRIBED_LIST, DESCRIBED_MAP,
         DESCRIPTOR, SYMBOL, SYMBOL_REF, TIMESTAMP, TRANSPARENT_VEC, UUID,
     },
     ser::{U32_MAX_MINUS_4, U8_MAX, U8_MAX_MINUS_1},
     util::{FieldRole, IsArrayElement, NewType, StructEncoding},
+    Error,
 };
 
 /// Obtain the serialized size without allocating `Vec<u8>`
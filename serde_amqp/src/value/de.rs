@@ -1,14 +1,19 @@
 //! Value deserializer
 
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
 use ordered_float::OrderedFloat;
 use serde::de::{self};
 use serde_bytes::ByteBuf;
 
 use crate::{
     __constants::{
-        ARRAY, DECIMAL128, DECIMAL32, DECIMAL64, DESCRIBED_BASIC, DESCRIPTOR, SYMBOL, TIMESTAMP,
-        UUID, VALUE,
+        ARRAY, DECIMAL128, DECIMAL32, DECIMAL64, DESCRIPTOR, SYMBOL, TIMESTAMP, UUID, VALUE,
     },
+    descriptor::Descriptor,
     error::Error,
     format_code::EncodingCodes,
     primitives::OrderedMap,
@@ -117,7 +122,7 @@ struct FieldVisitor {}
 impl<'de> de::Visitor<'de> for FieldVisitor {
     type Value = ValueType;
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
         formatter.write_str("field of enum Value")
     }
 
@@ -192,7 +197,7 @@ struct ValueVisitor {
 impl<'de> de::Visitor<'de> for ValueVisitor {
     type Value = Value;
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
         formatter.write_str("enum Value")
     }
 
@@ -209,7 +214,7 @@ impl<'de> de::Visitor<'de> for ValueVisitor {
                 Ok(Value::Described(val))
             }
             ValueType::Null => {
-                de.newtype_variant()?;
+                de.newtype_variant::<()>()?;
                 Ok(Value::Null)
             }
             ValueType::Bool => {
@@ -554,6 +559,61 @@ impl Deserializer {
             value,
         }
     }
+
+    /// Surfaces a described value to a self-describing visitor, eg. when buffering for
+    /// `#[serde(untagged)]` enums.
+    ///
+    /// The descriptor is given as a single entry map in the externally tagged representation of
+    /// [`Descriptor`], ie. `{"Name": symbol}` or `{"Code": u64}`. A described list is visited as
+    /// a sequence of the descriptor followed by the elements of the list, and a described map is
+    /// visited as a map whose first key is the descriptor (with a null value) followed by the
+    /// entries of the map. Any other described value is visited as a sequence of the descriptor
+    /// and the value. These are the shapes that the derived `DeserializeComposite` visitors
+    /// expect.
+    pub(crate) fn deserialize_described_any<'de, V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let described = match self.value {
+            Value::Described(described) => *described,
+            _ => return Err(Error::InvalidValue),
+        };
+
+        let mut descriptor = OrderedMap::new();
+        match described.descriptor {
+            Descriptor::Name(name) => {
+                descriptor.insert(Value::String("Name".to_string()), Value::Symbol(name))
+            }
+            Descriptor::Code(code) => {
+                descriptor.insert(Value::String("Code".to_string()), Value::Ulong(code))
+            }
+        };
+        let descriptor = Value::Map(descriptor);
+
+        match described.value {
+            Value::List(list) => {
+                let mut elements = Vec::with_capacity(list.len() + 1);
+                elements.push(descriptor);
+                elements.extend(list);
+                visitor.visit_seq(SeqAccess {
+                    iter: elements.into_iter(),
+                    seq_type: SeqType::List,
+                })
+            }
+            Value::Map(map) => {
+                let mut entries = OrderedMap::new();
+                entries.insert(descriptor, Value::Null);
+                entries.as_inner_mut().extend(map.into_inner());
+                visitor.visit_map(MapAccess {
+                    iter: entries.into_iter(),
+                })
+            }
+            value => visitor.visit_seq(SeqAccess {
+                iter: vec![descriptor, value].into_iter(),
+                seq_type: SeqType::List,
+            }),
+        }
+    }
 }
 
 impl<'de> de::Deserializer<'de> for Deserializer {
@@ -569,7 +629,7 @@ impl<'de> de::Deserializer<'de> for Deserializer {
         V: de::Visitor<'de>,
     {
         match &self.value {
-            Value::Described(_) => self.deserialize_struct(DESCRIBED_BASIC, &[""], visitor),
+            Value::Described(_) => self.deserialize_described_any(visitor),
             Value::Null => self.deserialize_unit(visitor),
             Value::Bool(_) => self.deserialize_bool(visitor),
             Value::Ubyte(_) => self.deserialize_u8(visitor),
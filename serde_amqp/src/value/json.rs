@@ -0,0 +1,724 @@
+//! Conversion between [`Value`] and [`serde_json::Value`] for diagnostics and HTTP bridging
+//!
+//! JSON cannot represent every AMQP 1.0 type, so the mapping is lossy by default. With
+//! [`JsonOptions::annotations`] enabled, values that would otherwise lose their type are wrapped
+//! in a single-key object whose key is the AMQP type prefixed with `@`, and [`from_json`] restores
+//! the original [`Value`] exactly.
+//!
+//! | [`Value`] | JSON | JSON with annotations |
+//! |-----------|------|-----------------------|
+//! | `Null`, `Bool`, `Long`, `String` | `null`, boolean, number, string | same |
+//! | `Ubyte`, `Ushort`, `Uint`, `Ulong`, `Byte`, `Short`, `Int` | number | `{"@ubyte": 1}`, ... |
+//! | `Double` | number, `null` if not finite | number, `{"@double": "NaN"}` if not finite |
+//! | `Float` | number, `null` if not finite | `{"@float": 1.5}`, `{"@float": "Infinity"}` |
+//! | `Decimal32`, `Decimal64`, `Decimal128` | hex string of the encoded bytes | `{"@decimal32": "hex"}`, ... |
+//! | `Char` | string | `{"@char": "c"}` |
+//! | `Timestamp` | ISO-8601 string in UTC with milliseconds | `{"@timestamp": "1970-01-01T00:00:00.000Z"}` |
+//! | `Uuid` | canonical hyphenated lowercase string | `{"@uuid": "..."}` |
+//! | `Binary` | base64 (standard alphabet, padded) string | `{"@binary": "base64"}` |
+//! | `Symbol` | string | `{"@symbol": "name"}` |
+//! | `List` | array | array |
+//! | `Array` | array | `{"@array": [...]}` |
+//! | `Map` | object | object if the keys are strings in ascending order that don't start with `@`, `{"@map": [[key, value], ...]}` otherwise |
+//! | `Described` | `{"@descriptor": name or code, "value": ...}` | same |
+//!
+//! A timestamp whose year is outside `0000` to `9999` is written as the number of milliseconds
+//! since the unix epoch. Without annotations, map keys that are not strings, symbols or chars are
+//! written as the JSON text of the key.
+//!
+//! # Example
+//!
+//! ```rust
+//! use serde_amqp::{primitives::Symbol, value::{from_json, to_json, to_json_with, JsonOptions}, Value};
+//!
+//! let value = Value::Symbol(Symbol::from("amqp:accepted:list"));
+//! assert_eq!(to_json(&value), serde_json::json!("amqp:accepted:list"));
+//!
+//! let json = to_json_with(&value, &JsonOptions::annotated());
+//! assert_eq!(json, serde_json::json!({ "@symbol": "amqp:accepted:list" }));
+//! assert_eq!(from_json(&json), value);
+//! ```
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use indexmap::IndexMap;
+use ordered_float::OrderedFloat;
+use serde_bytes::ByteBuf;
+use serde_json::{Map, Number};
+
+use crate::{
+    described::Described,
+    descriptor::Descriptor,
+    primitives::{Array, Dec128, Dec32, Dec64, MapHasher, OrderedMap, Symbol, Timestamp, Uuid},
+};
+
+use super::Value;
+
+const DESCRIPTOR: &str = "@descriptor";
+const DESCRIBED_VALUE: &str = "value";
+
+/// Options of the mapping from [`Value`] to JSON
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JsonOptions {
+    /// Wrap values that would otherwise lose their AMQP type in a `{"@<type>": ...}` object so
+    /// that [`from_json`] restores them exactly
+    pub annotations: bool,
+}
+
+impl JsonOptions {
+    /// Options with annotations enabled, which makes the conversion lossless
+    pub fn annotated() -> Self {
+        Self { annotations: true }
+    }
+}
+
+/// Converts a [`Value`] to JSON with the default, lossy, mapping
+pub fn to_json(value: &Value) -> serde_json::Value {
+    to_json_with(value, &JsonOptions::default())
+}
+
+/// Converts a [`Value`] to JSON with the given options
+pub fn to_json_with(value: &Value, options: &JsonOptions) -> serde_json::Value {
+    use serde_json::Value as Json;
+
+    let annotate = options.annotations;
+    match value {
+        Value::Null => Json::Null,
+        Value::Bool(b) => Json::Bool(*b),
+        Value::Long(n) => Json::from(*n),
+        Value::String(s) => Json::String(s.clone()),
+        Value::List(list) => Json::Array(list.iter().map(|v| to_json_with(v, options)).collect()),
+
+        Value::Ubyte(n) => annotated(annotate, "@ubyte", Json::from(*n)),
+        Value::Ushort(n) => annotated(annotate, "@ushort", Json::from(*n)),
+        Value::Uint(n) => annotated(annotate, "@uint", Json::from(*n)),
+        Value::Ulong(n) => annotated(annotate, "@ulong", Json::from(*n)),
+        Value::Byte(n) => annotated(annotate, "@byte", Json::from(*n)),
+        Value::Short(n) => annotated(annotate, "@short", Json::from(*n)),
+        Value::Int(n) => annotated(annotate, "@int", Json::from(*n)),
+        Value::Float(OrderedFloat(f)) => {
+            // Going through the shortest decimal representation of the `f32` keeps `1.1f32` as
+            // `1.1` instead of `1.100000023841858`
+            let json = match f.to_string().parse::<f64>().ok().and_then(Number::from_f64) {
+                Some(n) => Json::Number(n),
+                None if annotate => Json::String(non_finite_to_str(f64::from(*f)).into()),
+                None => Json::Null,
+            };
+            annotated(annotate, "@float", json)
+        }
+        Value::Double(OrderedFloat(f)) => match Number::from_f64(*f) {
+            Some(n) => Json::Number(n),
+            None if annotate => annotated(annotate, "@double", non_finite_to_str(*f).into()),
+            None => Json::Null,
+        },
+        Value::Decimal32(d) => annotated(annotate, "@decimal32", to_hex(&d.clone().into_inner())),
+        Value::Decimal64(d) => annotated(annotate, "@decimal64", to_hex(&d.clone().into_inner())),
+        Value::Decimal128(d) => annotated(annotate, "@decimal128", to_hex(&d.clone().into_inner())),
+        Value::Char(c) => annotated(annotate, "@char", Json::String(c.to_string())),
+        Value::Timestamp(t) => {
+            let json = match format_timestamp(t.milliseconds()) {
+                Some(s) => Json::String(s),
+                None => Json::from(t.milliseconds()),
+            };
+            annotated(annotate, "@timestamp", json)
+        }
+        Value::Uuid(uuid) => annotated(annotate, "@uuid", Json::String(format_uuid(uuid))),
+        Value::Binary(buf) => annotated(annotate, "@binary", Json::String(BASE64.encode(buf))),
+        Value::Symbol(s) => annotated(annotate, "@symbol", Json::String(s.0.clone())),
+        Value::Array(array) => {
+            let json = Json::Array(array.0.iter().map(|v| to_json_with(v, options)).collect());
+            annotated(annotate, "@array", json)
+        }
+        Value::Map(map) => map_to_json(map, options),
+        Value::Described(described) => {
+            let descriptor = match &described.descriptor {
+                Descriptor::Name(name) => Json::String(name.0.clone()),
+                Descriptor::Code(code) => Json::from(*code),
+            };
+            let mut object = Map::new();
+            object.insert(DESCRIPTOR.into(), descriptor);
+            object.insert(
+                DESCRIBED_VALUE.into(),
+                to_json_with(&described.value, options),
+            );
+            Json::Object(object)
+        }
+    }
+}
+
+/// Converts JSON to a [`Value`]
+///
+/// Objects written by [`to_json_with`] with annotations are converted back to the annotated type.
+/// Any other JSON is converted like the `From<serde_json::Value>` implementation of [`Value`],
+/// which includes annotation objects whose content is not valid for the annotated type.
+pub fn from_json(json: &serde_json::Value) -> Value {
+    use serde_json::Value as Json;
+
+    match json {
+        Json::Null => Value::Null,
+        Json::Bool(b) => Value::Bool(*b),
+        Json::Number(n) => number_to_value(n),
+        Json::String(s) => Value::String(s.clone()),
+        Json::Array(array) => Value::List(array.iter().map(from_json).collect()),
+        Json::Object(object) => annotation_to_value(object).unwrap_or_else(|| {
+            let map: IndexMap<_, _, MapHasher> = object
+                .iter()
+                .map(|(key, value)| (Value::String(key.clone()), from_json(value)))
+                .collect();
+            Value::Map(OrderedMap::from(map))
+        }),
+    }
+}
+
+fn annotated(annotate: bool, key: &str, json: serde_json::Value) -> serde_json::Value {
+    match annotate {
+        true => {
+            let mut object = Map::new();
+            object.insert(key.into(), json);
+            serde_json::Value::Object(object)
+        }
+        false => json,
+    }
+}
+
+fn map_to_json(map: &OrderedMap<Value, Value>, options: &JsonOptions) -> serde_json::Value {
+    use serde_json::Value as Json;
+
+    if options.annotations {
+        // serde_json may sort the keys of an object, so only maps whose keys are already in
+        // ascending order are written as an object
+        let keys: Option<Vec<&str>> = map
+            .keys()
+            .map(|key| match key {
+                Value::String(s) if !s.starts_with('@') => Some(s.as_str()),
+                _ => None,
+            })
+            .collect();
+        let as_object = keys.is_some_and(|keys| keys.windows(2).all(|w| w[0] < w[1]));
+        if !as_object {
+            let entries = map
+                .iter()
+                .map(|(k, v)| Json::Array(vec![to_json_with(k, options), to_json_with(v, options)]))
+                .collect();
+            return annotated(true, "@map", Json::Array(entries));
+        }
+    }
+
+    let object = map
+        .iter()
+        .map(|(key, value)| {
+            let key = match key {
+                Value::String(s) => s.clone(),
+                Value::Symbol(s) => s.0.clone(),
+                Value::Char(c) => c.to_string(),
+                _ => to_json_with(key, options).to_string(),
+            };
+            (key, to_json_with(value, options))
+        })
+        .collect();
+    Json::Object(object)
+}
+
+fn number_to_value(n: &Number) -> Value {
+    if let Some(i) = n.as_i64() {
+        Value::Long(i)
+    } else if let Some(u) = n.as_u64() {
+        Value::Ulong(u)
+    } else {
+        Value::Double(OrderedFloat(n.as_f64().unwrap_or(f64::NAN)))
+    }
+}
+
+fn annotation_to_value(object: &Map<String, serde_json::Value>) -> Option<Value> {
+    use serde_json::Value as Json;
+
+    if object.len() == 2 {
+        let descriptor = match object.get(DESCRIPTOR)? {
+            Json::String(name) => Descriptor::Name(Symbol::from(name.as_str())),
+            Json::Number(code) => Descriptor::Code(code.as_u64()?),
+            _ => return None,
+        };
+        let value = from_json(object.get(DESCRIBED_VALUE)?);
+        return Some(Value::Described(Box::new(Described { descriptor, value })));
+    }
+
+    let mut entries = object.iter();
+    let (key, json) = match (entries.next(), entries.next()) {
+        (Some(entry), None) => entry,
+        _ => return None,
+    };
+    let value = match key.as_str() {
+        "@ubyte" => Value::Ubyte(u8::try_from(json.as_u64()?).ok()?),
+        "@ushort" => Value::Ushort(u16::try_from(json.as_u64()?).ok()?),
+        "@uint" => Value::Uint(u32::try_from(json.as_u64()?).ok()?),
+        "@ulong" => Value::Ulong(json.as_u64()?),
+        "@byte" => Value::Byte(i8::try_from(json.as_i64()?).ok()?),
+        "@short" => Value::Short(i16::try_from(json.as_i64()?).ok()?),
+        "@int" => Value::Int(i32::try_from(json.as_i64()?).ok()?),
+        "@float" => {
+            let f = match json {
+                Json::Number(n) => n.as_f64()? as f32,
+                Json::String(s) => non_finite_from_str(s)? as f32,
+                _ => return None,
+            };
+            Value::Float(OrderedFloat(f))
+        }
+        "@double" => Value::Double(OrderedFloat(non_finite_from_str(json.as_str()?)?)),
+        "@decimal32" => Value::Decimal32(Dec32::from(from_hex::<4>(json.as_str()?)?)),
+        "@decimal64" => Value::Decimal64(Dec64::from(from_hex::<8>(json.as_str()?)?)),
+        "@decimal128" => Value::Decimal128(Dec128::from(from_hex::<16>(json.as_str()?)?)),
+        "@char" => {
+            let mut chars = json.as_str()?.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Value::Char(c),
+                _ => return None,
+            }
+        }
+        "@timestamp" => {
+            let milliseconds = match json {
+                Json::String(s) => parse_timestamp(s)?,
+                Json::Number(n) => n.as_i64()?,
+                _ => return None,
+            };
+            Value::Timestamp(Timestamp::from_milliseconds(milliseconds))
+        }
+        "@uuid" => Value::Uuid(parse_uuid(json.as_str()?)?),
+        "@binary" => Value::Binary(ByteBuf::from(BASE64.decode(json.as_str()?).ok()?)),
+        "@symbol" => Value::Symbol(Symbol::from(json.as_str()?)),
+        "@array" => Value::Array(Array(json.as_array()?.iter().map(from_json).collect())),
+        "@map" => {
+            let map = json
+                .as_array()?
+                .iter()
+                .map(|entry| match entry.as_array()?.as_slice() {
+                    [key, value] => Some((from_json(key), from_json(value))),
+                    _ => None,
+                })
+                .collect::<Option<IndexMap<_, _, MapHasher>>>()?;
+            Value::Map(OrderedMap::from(map))
+        }
+        _ => return None,
+    };
+    Some(value)
+}
+
+fn non_finite_to_str(f: f64) -> &'static str {
+    if f.is_nan() {
+        "NaN"
+    } else if f.is_sign_positive() {
+        "Infinity"
+    } else {
+        "-Infinity"
+    }
+}
+
+fn non_finite_from_str(s: &str) -> Option<f64> {
+    match s {
+        "NaN" => Some(f64::NAN),
+        "Infinity" => Some(f64::INFINITY),
+        "-Infinity" => Some(f64::NEG_INFINITY),
+        _ => None,
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> serde_json::Value {
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    serde_json::Value::String(hex)
+}
+
+fn from_hex<const N: usize>(s: &str) -> Option<[u8; N]> {
+    if s.len() != N * 2 || !s.is_ascii() {
+        return None;
+    }
+    let mut bytes = [0u8; N];
+    for (byte, i) in bytes.iter_mut().zip((0..s.len()).step_by(2)) {
+        *byte = u8::from_str_radix(&s[i..i + 2], 16).ok()?;
+    }
+    Some(bytes)
+}
+
+fn format_uuid(uuid: &Uuid) -> String {
+    let mut s = String::with_capacity(36);
+    for (i, b) in uuid.as_inner().iter().enumerate() {
+        if matches!(i, 4 | 6 | 8 | 10) {
+            s.push('-');
+        }
+        s.push_str(&format!("{:02x}", b));
+    }
+    s
+}
+
+fn parse_uuid(s: &str) -> Option<Uuid> {
+    let bytes = s.as_bytes();
+    if bytes.len() != 36 || [8, 13, 18, 23].iter().any(|&i| bytes[i] != b'-') {
+        return None;
+    }
+    let hex: String = s.chars().filter(|c| *c != '-').collect();
+    from_hex::<16>(&hex).map(Uuid::from)
+}
+
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+/// Formats milliseconds since the unix epoch as `YYYY-MM-DDTHH:MM:SS.sssZ`, which is only
+/// possible for the years `0000` to `9999`
+fn format_timestamp(milliseconds: i64) -> Option<String> {
+    let (year, month, day) = civil_from_days(milliseconds.div_euclid(MILLIS_PER_DAY));
+    if !(0..=9999).contains(&year) {
+        return None;
+    }
+    let ms_of_day = milliseconds.rem_euclid(MILLIS_PER_DAY);
+    Some(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        ms_of_day / 3_600_000,
+        ms_of_day / 60_000 % 60,
+        ms_of_day / 1000 % 60,
+        ms_of_day % 1000
+    ))
+}
+
+/// Parses `YYYY-MM-DDTHH:MM:SS[.fraction](Z|+HH:MM|-HH:MM)` into milliseconds since the unix
+/// epoch. Digits of the fraction beyond milliseconds are truncated
+fn parse_timestamp(s: &str) -> Option<i64> {
+    fn number(s: &str) -> Option<i64> {
+        match s.bytes().all(|b| b.is_ascii_digit()) {
+            true => s.parse().ok(),
+            false => None,
+        }
+    }
+
+    if !s.is_ascii() || s.len() < 20 {
+        return None;
+    }
+    let (date_time, rest) = s.split_at(19);
+    let b = date_time.as_bytes();
+    if b[4] != b'-'
+        || b[7] != b'-'
+        || !matches!(b[10], b'T' | b't')
+        || b[13] != b':'
+        || b[16] != b':'
+    {
+        return None;
+    }
+    let year = number(&date_time[0..4])?;
+    let month = number(&date_time[5..7])?;
+    let day = number(&date_time[8..10])?;
+    let hour = number(&date_time[11..13])?;
+    let minute = number(&date_time[14..16])?;
+    let second = number(&date_time[17..19])?;
+    if !(1..=12).contains(&month) || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    if civil_from_days(days) != (year, month, day) {
+        return None;
+    }
+
+    let (fraction, offset) = match rest.strip_prefix('.') {
+        Some(rest) => {
+            let end = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            if end == 0 {
+                return None;
+            }
+            let digits = &rest[..end.min(3)];
+            let millis = number(digits)? * 10i64.pow(3 - digits.len() as u32);
+            (millis, &rest[end..])
+        }
+        None => (0, rest),
+    };
+    let offset_minutes = match offset {
+        "Z" | "z" => 0,
+        _ => {
+            let sign = match offset.as_bytes().first()? {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return None,
+            };
+            if offset.len() != 6 || offset.as_bytes()[3] != b':' {
+                return None;
+            }
+            sign * (number(&offset[1..3])? * 60 + number(&offset[4..6])?)
+        }
+    };
+
+    let seconds_of_day = hour * 3600 + minute * 60 + second - offset_minutes * 60;
+    Some(days * MILLIS_PER_DAY + seconds_of_day * 1000 + fraction)
+}
+
+/// Year, month and day of the number of days since the unix epoch in the proleptic Gregorian
+/// calendar
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Number of days since the unix epoch of a date in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+    use ordered_float::OrderedFloat;
+    use serde_bytes::ByteBuf;
+    use serde_json::json;
+
+    use crate::{
+        described::Described,
+        descriptor::Descriptor,
+        primitives::{Array, Dec128, Dec32, Dec64, MapHasher, OrderedMap, Symbol, Timestamp, Uuid},
+        Value,
+    };
+
+    use super::{from_json, to_json, to_json_with, JsonOptions};
+
+    fn map(entries: Vec<(Value, Value)>) -> Value {
+        let map: IndexMap<_, _, MapHasher> = entries.into_iter().collect();
+        Value::Map(OrderedMap::from(map))
+    }
+
+    fn described(descriptor: Descriptor, value: Value) -> Value {
+        Value::Described(Box::new(Described { descriptor, value }))
+    }
+
+    fn uuid() -> Uuid {
+        Uuid::from([
+            0x67, 0xe5, 0x50, 0x44, 0x10, 0xb1, 0x42, 0x6f, 0x92, 0x47, 0xbb, 0x68, 0x0e, 0x5f,
+            0xe0, 0xc8,
+        ])
+    }
+
+    fn every_variant() -> Vec<Value> {
+        vec![
+            Value::Null,
+            Value::Bool(true),
+            Value::Ubyte(u8::MAX),
+            Value::Ushort(u16::MAX),
+            Value::Uint(u32::MAX),
+            Value::Ulong(u64::MAX),
+            Value::Ulong(7),
+            Value::Byte(i8::MIN),
+            Value::Short(i16::MIN),
+            Value::Int(i32::MIN),
+            Value::Long(i64::MIN),
+            Value::Float(OrderedFloat(1.1)),
+            Value::Float(OrderedFloat(f32::NEG_INFINITY)),
+            Value::Double(OrderedFloat(-2.5)),
+            Value::Double(OrderedFloat(f64::INFINITY)),
+            Value::Decimal32(Dec32::from([1, 2, 3, 4])),
+            Value::Decimal64(Dec64::from([1, 2, 3, 4, 5, 6, 7, 8])),
+            Value::Decimal128(Dec128::from([0xff; 16])),
+            Value::Char('🦀'),
+            Value::Timestamp(Timestamp::from_milliseconds(1_700_000_000_123)),
+            Value::Timestamp(Timestamp::from_milliseconds(-1)),
+            Value::Timestamp(Timestamp::from_milliseconds(i64::MAX)),
+            Value::Uuid(uuid()),
+            Value::Binary(ByteBuf::from(vec![0, 1, 2, 0xfe, 0xff])),
+            Value::String(String::from("hello")),
+            Value::Symbol(Symbol::from("amqp:accepted:list")),
+            Value::List(vec![Value::Int(1), Value::String(String::from("two"))]),
+            Value::Array(Array(vec![Value::Uint(1), Value::Uint(2)])),
+            map(vec![
+                (Value::String(String::from("a")), Value::Int(1)),
+                (Value::String(String::from("b")), Value::Null),
+            ]),
+            map(vec![
+                (Value::String(String::from("b")), Value::Int(1)),
+                (Value::String(String::from("a")), Value::Int(2)),
+            ]),
+            map(vec![(
+                Value::String(String::from("@symbol")),
+                Value::Int(1),
+            )]),
+            map(vec![(Value::Uint(1), Value::Symbol(Symbol::from("one")))]),
+            described(Descriptor::Code(0x24), Value::List(vec![])),
+            described(
+                Descriptor::Name(Symbol::from("example:nested")),
+                map(vec![
+                    (
+                        Value::Symbol(Symbol::from("x-opt-key")),
+                        described(
+                            Descriptor::Code(0x77),
+                            map(vec![(
+                                Value::String(String::from("inner")),
+                                Value::Binary(ByteBuf::from(vec![1, 2, 3])),
+                            )]),
+                        ),
+                    ),
+                    (
+                        Value::Long(-1),
+                        Value::Timestamp(Timestamp::from_milliseconds(0)),
+                    ),
+                ]),
+            ),
+        ]
+    }
+
+    #[test]
+    fn annotated_round_trip_is_lossless() {
+        for value in every_variant() {
+            let json = to_json_with(&value, &JsonOptions::annotated());
+            let text = serde_json::to_string(&json).unwrap();
+            let parsed: serde_json::Value = serde_json::from_str(&text).unwrap();
+            assert_eq!(from_json(&parsed), value, "{}", text);
+        }
+    }
+
+    #[test]
+    fn default_mapping() {
+        let expected = [
+            json!(null),
+            json!(true),
+            json!(255),
+            json!(65535),
+            json!(4294967295u32),
+            json!(u64::MAX),
+            json!(7),
+            json!(-128),
+            json!(-32768),
+            json!(i32::MIN),
+            json!(i64::MIN),
+            json!(1.1),
+            json!(null),
+            json!(-2.5),
+            json!(null),
+            json!("01020304"),
+            json!("0102030405060708"),
+            json!("ffffffffffffffffffffffffffffffff"),
+            json!("🦀"),
+            json!("2023-11-14T22:13:20.123Z"),
+            json!("1969-12-31T23:59:59.999Z"),
+            json!(i64::MAX),
+            json!("67e55044-10b1-426f-9247-bb680e5fe0c8"),
+            json!("AAEC/v8="),
+            json!("hello"),
+            json!("amqp:accepted:list"),
+            json!([1, "two"]),
+            json!([1, 2]),
+            json!({ "a": 1, "b": null }),
+            json!({ "b": 1, "a": 2 }),
+            json!({ "@symbol": 1 }),
+            json!({ "1": "one" }),
+            json!({ "@descriptor": 0x24, "value": [] }),
+            json!({
+                "@descriptor": "example:nested",
+                "value": {
+                    "x-opt-key": {
+                        "@descriptor": 0x77,
+                        "value": { "inner": "AQID" }
+                    },
+                    "-1": "1970-01-01T00:00:00.000Z"
+                }
+            }),
+        ];
+
+        let values = every_variant();
+        assert_eq!(values.len(), expected.len());
+        for (value, expected) in values.iter().zip(expected) {
+            assert_eq!(to_json(value), expected, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn annotated_mapping() {
+        let options = JsonOptions::annotated();
+        assert_eq!(
+            to_json_with(&Value::Symbol(Symbol::from("s")), &options),
+            json!({ "@symbol": "s" })
+        );
+        assert_eq!(
+            to_json_with(&Value::Float(OrderedFloat(f32::NAN)), &options),
+            json!({ "@float": "NaN" })
+        );
+        assert_eq!(
+            to_json_with(&Value::Timestamp(Timestamp::from_milliseconds(0)), &options),
+            json!({ "@timestamp": "1970-01-01T00:00:00.000Z" })
+        );
+        assert_eq!(
+            to_json_with(
+                &map(vec![
+                    (Value::String(String::from("b")), Value::Int(1)),
+                    (Value::String(String::from("a")), Value::Long(2)),
+                ]),
+                &options
+            ),
+            json!({ "@map": [["b", { "@int": 1 }], ["a", 2]] })
+        );
+    }
+
+    #[test]
+    fn from_plain_json() {
+        let json = json!({ "n": -1, "u": u64::MAX, "f": 0.5, "list": ["s", null, false] });
+        let expected = map(vec![
+            (
+                Value::String(String::from("f")),
+                Value::Double(OrderedFloat(0.5)),
+            ),
+            (
+                Value::String(String::from("list")),
+                Value::List(vec![
+                    Value::String(String::from("s")),
+                    Value::Null,
+                    Value::Bool(false),
+                ]),
+            ),
+            (Value::String(String::from("n")), Value::Long(-1)),
+            (Value::String(String::from("u")), Value::Ulong(u64::MAX)),
+        ]);
+        assert_eq!(from_json(&json), expected);
+    }
+
+    #[test]
+    fn invalid_annotations_are_plain_objects() {
+        for json in [
+            json!({ "@ubyte": 256 }),
+            json!({ "@uuid": "not-a-uuid" }),
+            json!({ "@timestamp": "2023-02-30T00:00:00Z" }),
+            json!({ "@binary": "%%%" }),
+            json!({ "@unknown": 1 }),
+        ] {
+            let (key, value) = json.as_object().unwrap().iter().next().unwrap();
+            let expected = map(vec![(Value::String(key.clone()), from_json(value))]);
+            assert_eq!(from_json(&json), expected);
+        }
+    }
+
+    #[test]
+    fn parse_timestamps() {
+        let cases = [
+            ("1970-01-01T00:00:00Z", 0),
+            ("1970-01-01T00:00:00.5Z", 500),
+            ("1970-01-01T00:00:00.123456Z", 123),
+            ("1970-01-01T01:00:00+01:00", 0),
+            ("1969-12-31T23:00:00-01:00", 0),
+            ("2000-02-29T12:34:56.789Z", 951_827_696_789),
+            ("0000-01-01T00:00:00.000Z", -62_167_219_200_000),
+        ];
+        for (s, expected) in cases {
+            let json = json!({ "@timestamp": s });
+            assert_eq!(
+                from_json(&json),
+                Value::Timestamp(Timestamp::from_milliseconds(expected)),
+                "{}",
+                s
+            );
+        }
+    }
+}
@@ -1,26 +1,38 @@
 //! Value type for untyped AMQP1.0 data structures.
 
+use alloc::{
+    boxed::Box,
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::hash::Hash;
 use indexmap::IndexMap;
 use ordered_float::OrderedFloat;
 use serde::Serialize;
 use serde_bytes::ByteBuf;
-use std::{
-    collections::{BTreeMap, HashMap},
-    hash::Hash,
-};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
 
 use crate::{
     described::Described,
     format_code::EncodingCodes,
-    primitives::{Array, Dec128, Dec32, Dec64, OrderedMap, Symbol, Timestamp, Uuid},
+    primitives::{
+        Array, BinaryRef, Dec128, Dec32, Dec64, MapHasher, OrderedMap, Symbol, Timestamp, Uuid,
+    },
     Error,
 };
 
 pub(crate) mod de;
 pub(crate) mod ser;
 
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "json")]
+pub use json::{from_json, to_json, to_json_with, JsonOptions};
+
 /// Primitive type definitions
-#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[derive(Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Default)]
 pub enum Value {
     /// Described type
     ///
@@ -274,6 +286,40 @@ pub enum Value {
     Array(Array<Value>),
 }
 
+/// Binary values longer than [`BINARY_DISPLAY_LIMIT`](crate::primitives::BINARY_DISPLAY_LIMIT)
+/// are formatted as their length and the hex of their leading bytes
+impl core::fmt::Debug for Value {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Described(arg0) => f.debug_tuple("Described").field(arg0).finish(),
+            Self::Null => write!(f, "Null"),
+            Self::Bool(arg0) => f.debug_tuple("Bool").field(arg0).finish(),
+            Self::Ubyte(arg0) => f.debug_tuple("Ubyte").field(arg0).finish(),
+            Self::Ushort(arg0) => f.debug_tuple("Ushort").field(arg0).finish(),
+            Self::Uint(arg0) => f.debug_tuple("Uint").field(arg0).finish(),
+            Self::Ulong(arg0) => f.debug_tuple("Ulong").field(arg0).finish(),
+            Self::Byte(arg0) => f.debug_tuple("Byte").field(arg0).finish(),
+            Self::Short(arg0) => f.debug_tuple("Short").field(arg0).finish(),
+            Self::Int(arg0) => f.debug_tuple("Int").field(arg0).finish(),
+            Self::Long(arg0) => f.debug_tuple("Long").field(arg0).finish(),
+            Self::Float(arg0) => f.debug_tuple("Float").field(arg0).finish(),
+            Self::Double(arg0) => f.debug_tuple("Double").field(arg0).finish(),
+            Self::Decimal32(arg0) => f.debug_tuple("Decimal32").field(arg0).finish(),
+            Self::Decimal64(arg0) => f.debug_tuple("Decimal64").field(arg0).finish(),
+            Self::Decimal128(arg0) => f.debug_tuple("Decimal128").field(arg0).finish(),
+            Self::Char(arg0) => f.debug_tuple("Char").field(arg0).finish(),
+            Self::Timestamp(arg0) => f.debug_tuple("Timestamp").field(arg0).finish(),
+            Self::Uuid(arg0) => f.debug_tuple("Uuid").field(arg0).finish(),
+            Self::Binary(arg0) => f.debug_tuple("Binary").field(&BinaryRef(arg0)).finish(),
+            Self::String(arg0) => f.debug_tuple("String").field(arg0).finish(),
+            Self::Symbol(arg0) => f.debug_tuple("Symbol").field(arg0).finish(),
+            Self::List(arg0) => f.debug_tuple("List").field(arg0).finish(),
+            Self::Map(arg0) => f.debug_tuple("Map").field(arg0).finish(),
+            Self::Array(arg0) => f.debug_tuple("Array").field(arg0).finish(),
+        }
+    }
+}
+
 impl Value {
     /// Get the format code of the value type
     pub fn format_code(&self) -> u8 {
@@ -407,7 +453,8 @@ where
     V: Into<Value>,
 {
     fn from(map: BTreeMap<K, V>) -> Self {
-        let map: IndexMap<_, _> = map.into_iter().map(|(k, v)| (k.into(), v.into())).collect();
+        let map: IndexMap<_, _, MapHasher> =
+            map.into_iter().map(|(k, v)| (k.into(), v.into())).collect();
         Value::Map(OrderedMap::from(map))
     }
 }
@@ -559,9 +606,10 @@ where
     }
 }
 
+#[cfg(feature = "std")]
 impl<K, V> TryFrom<Value> for HashMap<K, V>
 where
-    K: TryFrom<Value, Error = Value> + std::hash::Hash + Eq,
+    K: TryFrom<Value, Error = Value> + core::hash::Hash + Eq,
     V: TryFrom<Value, Error = Value>,
 {
     type Error = Value;
@@ -581,9 +629,9 @@ where
     }
 }
 
-impl<K, V> TryFrom<Value> for IndexMap<K, V>
+impl<K, V> TryFrom<Value> for IndexMap<K, V, MapHasher>
 where
-    K: TryFrom<Value, Error = Value> + std::hash::Hash + Eq,
+    K: TryFrom<Value, Error = Value> + core::hash::Hash + Eq,
     V: TryFrom<Value, Error = Value>,
 {
     type Error = Value;
@@ -626,7 +674,7 @@ impl From<serde_json::Value> for Value {
                 Value::List(v)
             }
             serde_json::Value::Object(o) => {
-                let map: IndexMap<_, _> = o
+                let map: IndexMap<_, _, MapHasher> = o
                     .into_iter()
                     .map(|(key, value)| (Value::String(key), Value::from(value)))
                     .collect();
@@ -850,6 +898,19 @@ mod tests {
         assert_eq_from_reader_vs_expected(buf, expected);
     }
 
+    #[test]
+    fn test_debug_long_value_binary() {
+        use serde_bytes::ByteBuf;
+        let value = Value::Binary(ByteBuf::from(vec![0xab; 1024]));
+        assert_eq!(
+            format!("{:?}", value),
+            "Binary(<1024 bytes, 0xabababababababababababababababab...>)"
+        );
+
+        let value = Value::List(vec![Value::Binary(ByteBuf::from(vec![1, 2]))]);
+        assert_eq!(format!("{:?}", value), "List([Binary([1, 2])])");
+    }
+
     #[test]
     fn test_value_string() {
         let expected = Value::String(String::from("amqp"));
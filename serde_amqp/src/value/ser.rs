@@ -1,6 +1,7 @@
 //! Value serializer
 
-use std::convert::TryFrom;
+use alloc::{boxed::Box, string::String, vec, vec::Vec};
+use core::convert::TryFrom;
 
 use ordered_float::OrderedFloat;
 use serde::ser::{self};
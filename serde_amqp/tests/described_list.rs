@@ -480,3 +480,119 @@ fn single_custom_struct() {
     let decoded: Single<CustomStruct> = from_slice(&buf).unwrap();
     assert_eq!(decoded, value);
 }
+
+#[cfg(feature = "derive")]
+#[derive(Debug, SerializeComposite, DeserializeComposite, PartialEq)]
+#[amqp_contract(
+    name = "test:example:trailing",
+    code = "0x0000_0001:0000_0002",
+    encoding = "list"
+)]
+struct Trailing {
+    a: u32,
+    b: Option<u32>,
+    c: std::option::Option<u32>,
+    #[amqp_contract(default)]
+    d: bool,
+    e: core::option::Option<String>,
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn trailing_nulls_are_omitted() {
+    let value = Trailing {
+        a: 1,
+        b: None,
+        c: Some(2),
+        d: false,
+        e: None,
+    };
+    let buf = to_vec(&value).unwrap();
+    // The interior null is kept and the list count only includes the first three fields
+    let expected = [
+        0x0, 0x80, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x2, 0xc0, 0x6, 0x3, 0x52, 0x1, 0x40, 0x52,
+        0x2,
+    ];
+    assert_eq!(buf, expected);
+
+    let decoded: Trailing = from_slice(&buf).unwrap();
+    assert_eq!(decoded, value);
+
+    let value = Trailing {
+        a: 1,
+        b: None,
+        c: None,
+        d: false,
+        e: None,
+    };
+    let buf = to_vec(&value).unwrap();
+    let expected = [
+        0x0, 0x80, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x2, 0xc0, 0x3, 0x1, 0x52, 0x1,
+    ];
+    assert_eq!(buf, expected);
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn explicit_trailing_nulls_are_accepted() {
+    let buf = [
+        0x0, 0x80, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x2, 0xc0, 0x6, 0x5, 0x52, 0x1, 0x40, 0x40,
+        0x40, 0x40,
+    ];
+    let decoded: Trailing = from_slice(&buf).unwrap();
+    let expected = Trailing {
+        a: 1,
+        b: None,
+        c: None,
+        d: false,
+        e: None,
+    };
+    assert_eq!(decoded, expected);
+}
+
+#[cfg(feature = "derive")]
+#[derive(Debug, SerializeComposite, DeserializeComposite, PartialEq)]
+#[amqp_contract(code = "0x0000_0000:0x0000_0071", encoding = "list")]
+struct SmallCode {
+    a: i32,
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn small_descriptor_code_in_any_ulong_encoding() {
+    let value = SmallCode { a: 1 };
+    let buf = to_vec(&value).unwrap();
+    let expected = [0x0, 0x53, 0x71, 0xc0, 0x3, 0x1, 0x54, 0x1];
+    assert_eq!(buf, expected);
+
+    let ulong = [
+        0x0, 0x80, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x71, 0xc0, 0x3, 0x1, 0x54, 0x1,
+    ];
+    let decoded: SmallCode = from_slice(&ulong).unwrap();
+    assert_eq!(decoded, value);
+}
+
+#[cfg(feature = "derive")]
+#[derive(Debug, SerializeComposite, DeserializeComposite, PartialEq)]
+#[amqp_contract(
+    name = "com.microsoft:test",
+    code = "0x0000_0137:0x0000_0004",
+    encoding = "list"
+)]
+struct VendorCode {
+    a: i32,
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn vendor_descriptor_code() {
+    let value = VendorCode { a: 1 };
+    let buf = to_vec(&value).unwrap();
+    let expected = [
+        0x0, 0x80, 0x0, 0x0, 0x1, 0x37, 0x0, 0x0, 0x0, 0x4, 0xc0, 0x3, 0x1, 0x54, 0x1,
+    ];
+    assert_eq!(buf, expected);
+
+    let decoded: VendorCode = from_slice(&buf).unwrap();
+    assert_eq!(decoded, value);
+}
@@ -0,0 +1,148 @@
+//! Tests of serializing with `SerializerOptions::prefer_streaming`
+//!
+//! The allocations are counted by the global allocator, so this is kept in its own test binary
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    io,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use serde_amqp::{
+    described::Described, descriptor::Descriptor, from_slice, primitives::OrderedMap, to_vec,
+    to_writer_with_options, SerializerOptions, Value,
+};
+
+/// Keeps track of the number of bytes allocated and of the peak since the last reset
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            PEAK.fetch_max(allocated, Ordering::SeqCst);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Returns the value and the number of bytes allocated at the peak of `f` on top of what was
+/// allocated before
+fn peak_allocation<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATED.load(Ordering::SeqCst);
+    PEAK.store(before, Ordering::SeqCst);
+    let value = f();
+    (value, PEAK.load(Ordering::SeqCst) - before)
+}
+
+const PAYLOAD_SIZE: usize = 64 * 1024 * 1024;
+const MB: usize = 1024 * 1024;
+
+fn section(code: u64, value: Value) -> Value {
+    Value::Described(Box::new(Described {
+        descriptor: Descriptor::Code(code),
+        value,
+    }))
+}
+
+/// A value that is laid out like a message with a header, application properties and a data
+/// section
+fn message(payload: Vec<u8>) -> Value {
+    let mut properties = OrderedMap::new();
+    properties.insert(
+        Value::String(String::from("content-type")),
+        Value::String(String::from("application/octet-stream")),
+    );
+    Value::List(vec![
+        section(0x70, Value::List(vec![Value::Bool(true), Value::Ubyte(4)])),
+        section(0x74, Value::Map(properties)),
+        section(0x75, Value::Binary(payload.into())),
+    ])
+}
+
+#[test]
+fn streaming_large_binary_has_flat_memory_usage() {
+    let value = message(vec![0xab; PAYLOAD_SIZE]);
+    let options = SerializerOptions::default().prefer_streaming(true);
+
+    // Nothing that grows with the payload is allocated besides the output
+    let (result, peak) = peak_allocation(|| to_writer_with_options(io::sink(), &value, options));
+    result.unwrap();
+    assert!(peak < MB, "peak allocation of {peak} bytes");
+
+    let mut buf = Vec::with_capacity(PAYLOAD_SIZE + 1024);
+    let (result, peak) = peak_allocation(|| to_writer_with_options(&mut buf, &value, options));
+    result.unwrap();
+    assert!(peak < MB, "peak allocation of {peak} bytes");
+    assert!(buf.len() > PAYLOAD_SIZE);
+
+    // The buffered serializer copies the payload into a buffer for each compound level
+    let (expected, peak) = peak_allocation(|| to_vec(&value).unwrap());
+    assert!(peak > 2 * PAYLOAD_SIZE, "peak allocation of {peak} bytes");
+    assert_eq!(buf, expected);
+    drop(expected);
+
+    let decoded: Value = from_slice(&buf).unwrap();
+    assert_eq!(decoded, value);
+}
+
+#[cfg(feature = "derive")]
+mod composite {
+    use serde_amqp::{
+        primitives::Array, to_vec, to_writer_with_options, DeserializeComposite,
+        SerializeComposite, SerializerOptions,
+    };
+
+    #[derive(Debug, SerializeComposite, DeserializeComposite)]
+    #[amqp_contract(code = "0x00:0x01", encoding = "list")]
+    struct Inner {
+        a: Option<u32>,
+        b: Array<String>,
+    }
+
+    #[derive(Debug, SerializeComposite, DeserializeComposite)]
+    #[amqp_contract(name = "test:outer", encoding = "map")]
+    struct Outer {
+        inner: Inner,
+        list: Vec<Inner>,
+    }
+
+    #[derive(Debug, SerializeComposite, DeserializeComposite)]
+    #[amqp_contract(code = "0x00:0x02", encoding = "basic")]
+    struct Wrapper(Outer);
+
+    #[test]
+    fn streamed_composite_eq_to_vec() {
+        let inner = || Inner {
+            a: Some(1),
+            b: Array(vec![String::from("amqp"), "a".repeat(300)]),
+        };
+        let value = Wrapper(Outer {
+            inner: inner(),
+            list: vec![
+                inner(),
+                Inner {
+                    a: None,
+                    b: Array(vec![]),
+                },
+            ],
+        });
+
+        let mut buf = Vec::new();
+        let options = SerializerOptions::default().prefer_streaming(true);
+        to_writer_with_options(&mut buf, &value, options).unwrap();
+        assert_eq!(buf, to_vec(&value).unwrap());
+    }
+}
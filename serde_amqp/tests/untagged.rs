@@ -0,0 +1,114 @@
+//! Tests `#[serde(untagged)]` enums over described types, which are deserialized through
+//! `deserialize_any`
+
+#[cfg(feature = "derive")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "derive")]
+use serde_amqp::{from_slice, to_vec, DeserializeComposite, SerializeComposite};
+
+#[cfg(feature = "derive")]
+#[derive(Debug, Clone, PartialEq, SerializeComposite, DeserializeComposite)]
+#[amqp_contract(code = "0x0000_0000:0x0000_0001", encoding = "list")]
+struct OrderV1 {
+    id: u64,
+    item: String,
+}
+
+#[cfg(feature = "derive")]
+#[derive(Debug, Clone, PartialEq, SerializeComposite, DeserializeComposite)]
+#[amqp_contract(name = "example:order:v2", encoding = "map")]
+struct OrderV2 {
+    id: u64,
+    items: Vec<String>,
+    note: Option<String>,
+}
+
+#[cfg(feature = "derive")]
+#[derive(Debug, Clone, PartialEq, SerializeComposite, DeserializeComposite)]
+#[amqp_contract(code = "0x0000_0000:0x0000_0003", encoding = "list")]
+struct Batch {
+    first: OrderV1,
+    count: u32,
+}
+
+#[cfg(feature = "derive")]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum Order {
+    V1(OrderV1),
+    V2(OrderV2),
+    Batch(Batch),
+    Plain(String),
+}
+
+#[cfg(feature = "derive")]
+fn round_trip<T: Serialize>(value: &T) -> Order {
+    let buf = to_vec(value).unwrap();
+    from_slice(&buf).unwrap()
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn untagged_enum_over_described_list() {
+    let order = OrderV1 {
+        id: 1,
+        item: "apple".to_string(),
+    };
+    assert_eq!(round_trip(&order), Order::V1(order));
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn untagged_enum_over_described_map() {
+    let order = OrderV2 {
+        id: 2,
+        items: vec!["apple".to_string(), "pear".to_string()],
+        note: None,
+    };
+    assert_eq!(round_trip(&order), Order::V2(order));
+
+    let order = OrderV2 {
+        id: 3,
+        items: vec![],
+        note: Some("fragile".to_string()),
+    };
+    assert_eq!(round_trip(&order), Order::V2(order));
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn untagged_enum_over_nested_described_types() {
+    let batch = Batch {
+        first: OrderV1 {
+            id: 4,
+            item: "plum".to_string(),
+        },
+        count: 10,
+    };
+    assert_eq!(round_trip(&batch), Order::Batch(batch));
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn untagged_enum_over_primitive() {
+    let plain = String::from("order-5");
+    assert_eq!(round_trip(&plain), Order::Plain(plain));
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn untagged_enum_rejects_unknown_descriptor() {
+    #[derive(Debug, SerializeComposite, DeserializeComposite)]
+    #[amqp_contract(code = "0x0000_0000:0x0000_0009", encoding = "list")]
+    struct Unknown {
+        id: u64,
+        item: String,
+    }
+
+    let unknown = Unknown {
+        id: 6,
+        item: "fig".to_string(),
+    };
+    let buf = to_vec(&unknown).unwrap();
+    assert!(from_slice::<Order>(&buf).is_err());
+}
@@ -4,8 +4,8 @@ use syn::{spanned::Spanned, DeriveInput, Fields};
 use crate::{
     util::{
         convert_to_case, generic_visitor, get_span_of, macro_rules_unwrap_or_default,
-        macro_rules_unwrap_or_none, parse_described_struct_attr, parse_named_field_attrs,
-        where_deserialize,
+        macro_rules_unwrap_or_none, normalize_option_type, parse_described_struct_attr,
+        parse_named_field_attrs, where_deserialize,
     },
     DescribedStructAttr, EncodingType, FieldAttr,
 };
@@ -147,7 +147,7 @@ fn expand_deserialize_unit_struct(
                 impl<'de> serde_amqp::serde::de::Visitor<'de> for Visitor {
                     type Value = #ident;
 
-                    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
                         formatter.write_str(#expecting)
                     }
 
@@ -169,7 +169,7 @@ fn expand_deserialize_unit_struct(
 fn impl_visit_seq_for_tuple_struct(
     ident: &syn::Ident,
     field_idents: &Vec<syn::Ident>,
-    field_types: &Vec<&syn::Type>,
+    field_types: &Vec<syn::Type>,
     evaluate_descriptor: &proc_macro2::TokenStream,
 ) -> proc_macro2::TokenStream {
     let unwrap_or_none = match field_idents.len() {
@@ -235,7 +235,11 @@ fn expand_deserialize_tuple_struct(
         .map(|(id, span)| syn::Ident::new(&id, span))
         .collect();
 
-    let field_types: Vec<&syn::Type> = fields.unnamed.iter().map(|f| &f.ty).collect();
+    let field_types: Vec<syn::Type> = fields
+        .unnamed
+        .iter()
+        .map(|f| normalize_option_type(&f.ty))
+        .collect();
     let visit_seq =
         impl_visit_seq_for_tuple_struct(ident, &field_idents, &field_types, evaluate_descriptor);
     let len = field_idents.len();
@@ -256,7 +260,7 @@ fn expand_deserialize_tuple_struct(
                 impl<'de, #gen_params> serde_amqp::serde::de::Visitor<'de> for Visitor<#gen_params> #where_clause {
                     type Value = #ident<#gen_params>;
 
-                    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
                         formatter.write_str(#expecting)
                     }
 
@@ -319,7 +323,11 @@ fn expand_deserialize_struct(
         .iter()
         .map(|i| convert_to_case(rename_all, i.to_string(), ctx).unwrap())
         .collect();
-    let field_types: Vec<&syn::Type> = fields.named.iter().map(|f| &f.ty).collect();
+    let field_types: Vec<syn::Type> = fields
+        .named
+        .iter()
+        .map(|f| normalize_option_type(&f.ty))
+        .collect();
     let field_attrs = parse_named_field_attrs(fields.named.iter());
 
     let deserialize_field = impl_deserialize_for_field(&field_idents, &field_names);
@@ -382,7 +390,7 @@ fn expand_deserialize_struct(
                 impl<'de, #gen_params> serde_amqp::serde::de::Visitor<'de> for Visitor<#gen_params> #where_clause {
                     type Value = #ident<#gen_params>;
 
-                    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
                         formatter.write_str(#expecting)
                     }
 
@@ -418,7 +426,7 @@ fn impl_deserialize_for_field(
         impl<'de> serde_amqp::serde::de::Visitor<'de> for FieldVisitor {
             type Value = Field;
 
-            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
                 formatter.write_str("field identifier")
             }
 
@@ -459,7 +467,7 @@ fn impl_deserialize_for_field(
 fn impl_visit_seq_for_struct(
     ident: &syn::Ident,
     field_idents: &[syn::Ident],
-    field_types: &[&syn::Type],
+    field_types: &[syn::Type],
     field_attrs: &[FieldAttr],
     evaluate_descriptor: &proc_macro2::TokenStream,
 ) -> proc_macro2::TokenStream {
@@ -500,7 +508,7 @@ fn impl_visit_map(
     ident: &syn::Ident,
     field_idents: &Vec<syn::Ident>,
     field_names: &Vec<String>,
-    field_types: &Vec<&syn::Type>,
+    field_types: &Vec<syn::Type>,
     field_attrs: &Vec<FieldAttr>,
     evaluate_descriptor: &proc_macro2::TokenStream,
 ) -> proc_macro2::TokenStream {
@@ -5,8 +5,8 @@ use crate::{
     util::{
         convert_to_case, macro_rules_buffer_if_eq_default, macro_rules_buffer_if_none,
         macro_rules_buffer_if_none_for_tuple_struct, macro_rules_serialize_if_neq_default,
-        macro_rules_serialize_if_some, parse_described_struct_attr, parse_named_field_attrs,
-        where_serialize,
+        macro_rules_serialize_if_some, normalize_option_type, parse_described_struct_attr,
+        parse_named_field_attrs, where_serialize,
     },
     DescribedStructAttr, EncodingType, FieldAttr,
 };
@@ -130,7 +130,11 @@ fn expand_serialize_tuple_struct(
         .enumerate()
         .map(|(i, _)| syn::Index::from(i))
         .collect();
-    let field_types: Vec<&syn::Type> = fields.unnamed.iter().map(|f| &f.ty).collect();
+    let field_types: Vec<syn::Type> = fields
+        .unnamed
+        .iter()
+        .map(|f| normalize_option_type(&f.ty))
+        .collect();
     let len = field_indices.len();
     let buffer_if_none = macro_rules_buffer_if_none_for_tuple_struct();
     let where_clause = match generics.params.len() {
@@ -195,7 +199,11 @@ fn expand_serialize_struct(
         .iter()
         .map(|i| convert_to_case(rename_all, i.to_string(), ctx).unwrap())
         .collect();
-    let field_types: Vec<&syn::Type> = fields.named.iter().map(|f| &f.ty).collect();
+    let field_types: Vec<syn::Type> = fields
+        .named
+        .iter()
+        .map(|f| normalize_option_type(&f.ty))
+        .collect();
     let field_attrs = parse_named_field_attrs(fields.named.iter());
     let declarative_macro = match encoding {
         EncodingType::Basic | EncodingType::List => {
@@ -278,7 +286,7 @@ fn expand_serialize_struct(
             {
                 use serde_amqp::serde::ser::SerializeStruct;
                 // let mut null_count = 0u32;
-                let mut nulls: Vec<&str> = Vec::new();
+                let mut nulls: serde_amqp::__private::Vec<&str> = serde_amqp::__private::Vec::new();
                 // len + 1 for compatibility with other serializer
                 let mut state = serializer.serialize_struct(#struct_name, #len + 1)?;
                 // serialize descriptor
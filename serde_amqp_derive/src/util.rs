@@ -137,6 +137,37 @@ pub(crate) fn get_span_of(ident_str: &str, ctx: &DeriveInput) -> Option<Span> {
         })
 }
 
+/// Rewrites a path qualified option (ie. `std::option::Option<T>` or `core::option::Option<T>`)
+/// as `Option<T>` so that the generated declarative macros can tell optional fields apart.
+///
+/// Trailing `None` fields are only omitted from (and tolerated missing in) the list encoding if
+/// the field is recognized as optional.
+pub(crate) fn normalize_option_type(ty: &syn::Type) -> syn::Type {
+    if let syn::Type::Path(type_path) = ty {
+        if type_path.qself.is_none() {
+            let segments = &type_path.path.segments;
+            let is_option_path = match segments.len() {
+                1 => true,
+                3 => {
+                    (segments[0].ident == "std" || segments[0].ident == "core")
+                        && segments[1].ident == "option"
+                }
+                _ => false,
+            };
+            if let Some(last) = segments.last() {
+                if is_option_path && last.ident == "Option" {
+                    if let syn::PathArguments::AngleBracketed(args) = &last.arguments {
+                        if let Some(syn::GenericArgument::Type(inner)) = args.args.first() {
+                            return syn::parse_quote!(Option<#inner>);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    ty.clone()
+}
+
 pub(crate) fn where_serialize(generics: &syn::Generics) -> proc_macro2::TokenStream {
     let mut wheres = Vec::new();
     generics
@@ -195,7 +226,7 @@ pub(crate) fn generic_visitor(generics: &syn::Generics) -> proc_macro2::TokenStr
         impl<#(#generic_types),*> Visitor<#(#generic_types),*> {
             fn new() -> Self {
                 Self {
-                    #(#field_ids: std::marker::PhantomData),*
+                    #(#field_ids: core::marker::PhantomData),*
                 }
             }
         }
@@ -221,7 +252,7 @@ pub(crate) fn generic_visitor_fields(
         .for_each(|(i, ty)| {
             types.push(ty);
             let field_id = syn::Ident::new(&format!("_field{}", i), ty.span());
-            let token = quote!(#field_id: std::marker::PhantomData<#ty>);
+            let token = quote!(#field_id: core::marker::PhantomData<#ty>);
             let field = syn::Field::parse_named.parse2(token);
             fields.push(field.unwrap());
         });